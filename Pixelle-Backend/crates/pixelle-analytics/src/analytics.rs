@@ -6,6 +6,7 @@ pub struct AnalyticsEvent {
     pub event_type: String,
     pub user_id: Option<String>,
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
     pub properties: serde_json::Value,
 }
 
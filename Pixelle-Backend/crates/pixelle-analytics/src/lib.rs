@@ -1,7 +1,9 @@
 pub mod analytics;
 pub mod events;
 pub mod metrics;
+pub mod sessions;
 
 pub use analytics::*;
 pub use events::*;
 pub use metrics::*;
+pub use sessions::*;
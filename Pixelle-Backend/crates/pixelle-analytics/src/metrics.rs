@@ -1,5 +1,6 @@
 use prometheus::{Counter, Histogram, IntCounter, IntGauge, Opts, Registry};
 use std::sync::Arc;
+use crate::sessions::ActiveUserGauges;
 
 #[derive(Clone)]
 pub struct AnalyticsMetrics {
@@ -7,6 +8,9 @@ pub struct AnalyticsMetrics {
     user_events_total: IntCounter,
     post_events_total: IntCounter,
     engagement_events_total: IntCounter,
+    concurrent_sessions: IntGauge,
+    active_users: IntGauge,
+    sessions_closed_total: IntCounter,
 }
 
 impl AnalyticsMetrics {
@@ -30,13 +34,34 @@ impl AnalyticsMetrics {
 
         registry.register(Box::new(user_events_total.clone())).unwrap();
         registry.register(Box::new(post_events_total.clone())).unwrap();
+        let concurrent_sessions = IntGauge::new(
+            "concurrent_sessions",
+            "Sessions that have not gone idle",
+        ).unwrap();
+        
+        let active_users = IntGauge::new(
+            "active_users",
+            "Users with an event inside the active window",
+        ).unwrap();
+        
+        let sessions_closed_total = IntCounter::new(
+            "sessions_closed_total",
+            "Total number of sessions closed",
+        ).unwrap();
+
         registry.register(Box::new(engagement_events_total.clone())).unwrap();
+        registry.register(Box::new(concurrent_sessions.clone())).unwrap();
+        registry.register(Box::new(active_users.clone())).unwrap();
+        registry.register(Box::new(sessions_closed_total.clone())).unwrap();
 
         Self {
             registry: Arc::new(registry),
             user_events_total,
             post_events_total,
             engagement_events_total,
+            concurrent_sessions,
+            active_users,
+            sessions_closed_total,
         }
     }
 
@@ -52,6 +77,15 @@ impl AnalyticsMetrics {
         self.engagement_events_total.inc();
     }
 
+    pub fn set_session_gauges(&self, gauges: &ActiveUserGauges) {
+        self.concurrent_sessions.set(gauges.concurrent_sessions as i64);
+        self.active_users.set(gauges.active_users as i64);
+    }
+
+    pub fn add_sessions_closed(&self, count: u64) {
+        self.sessions_closed_total.inc_by(count);
+    }
+
     pub fn registry(&self) -> Arc<Registry> {
         self.registry.clone()
     }
@@ -0,0 +1,343 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::io::AsyncWriteExt;
+use crate::analytics::AnalyticsEvent;
+use crate::metrics::AnalyticsMetrics;
+
+/// Session defaults
+pub const SESSION_IDLE_TIMEOUT_SECONDS: i64 = 1800; // 30 minutes
+pub const ACTIVE_USER_WINDOW_SECONDS: i64 = 300; // 5 minutes
+pub const REALTIME_GAUGE_INTERVAL_SECONDS: u64 = 5;
+
+/// Closed sessions held while the summary store is unavailable; the oldest are dropped beyond this
+const MAX_PENDING_SUMMARIES: usize = 100_000;
+
+/// How events are grouped into sessions
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// A gap longer than this between a user's events starts a new session
+    pub idle_timeout: Duration,
+    /// Users with an event this recent count as active
+    pub active_window: Duration,
+    /// How often idle sessions are closed and gauges refreshed
+    pub gauge_interval: std::time::Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::seconds(SESSION_IDLE_TIMEOUT_SECONDS),
+            active_window: Duration::seconds(ACTIVE_USER_WINDOW_SECONDS),
+            gauge_interval: std::time::Duration::from_secs(REALTIME_GAUGE_INTERVAL_SECONDS),
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Read `SESSION_IDLE_TIMEOUT_SECONDS`, `ACTIVE_USER_WINDOW_SECONDS` and
+    /// `REALTIME_GAUGE_INTERVAL_SECONDS`, keeping defaults for unset or invalid values
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let seconds = |name: &str| env::var(name).ok().and_then(|value| value.parse::<u64>().ok()).filter(|&value| value > 0);
+        Self {
+            idle_timeout: seconds("SESSION_IDLE_TIMEOUT_SECONDS")
+                .map(|value| Duration::seconds(value as i64))
+                .unwrap_or(defaults.idle_timeout),
+            active_window: seconds("ACTIVE_USER_WINDOW_SECONDS")
+                .map(|value| Duration::seconds(value as i64))
+                .unwrap_or(defaults.active_window),
+            gauge_interval: seconds("REALTIME_GAUGE_INTERVAL_SECONDS")
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(defaults.gauge_interval),
+        }
+    }
+}
+
+/// A finished session, kept for cohort analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    /// User ID and start time, unique per session
+    pub session_id: String,
+    pub user_id: String,
+    pub started_at: DateTime<Utc>,
+    /// Time of the session's last event
+    pub ended_at: DateTime<Utc>,
+    pub duration_seconds: i64,
+    pub event_count: u64,
+    /// Events per event type
+    pub event_counts: BTreeMap<String, u64>,
+    /// `platform` property of the first event, when clients send one
+    pub platform: Option<String>,
+}
+
+/// Realtime counters for the ops dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveUserGauges {
+    /// Sessions that have not gone idle yet, one per user
+    pub concurrent_sessions: usize,
+    /// Users with an event inside the active window
+    pub active_users: usize,
+    pub active_window_seconds: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+struct OpenSession {
+    started_at: DateTime<Utc>,
+    last_event_at: DateTime<Utc>,
+    event_count: u64,
+    event_counts: BTreeMap<String, u64>,
+    platform: Option<String>,
+}
+
+impl OpenSession {
+    fn start(event: &AnalyticsEvent) -> Self {
+        Self {
+            started_at: event.timestamp,
+            last_event_at: event.timestamp,
+            event_count: 0,
+            event_counts: BTreeMap::new(),
+            platform: event.properties.get("platform").and_then(|platform| platform.as_str()).map(str::to_string),
+        }
+    }
+
+    fn add(&mut self, event: &AnalyticsEvent) {
+        // Late events widen the session instead of moving it backwards
+        self.started_at = self.started_at.min(event.timestamp);
+        self.last_event_at = self.last_event_at.max(event.timestamp);
+        self.event_count += 1;
+        *self.event_counts.entry(event.event_type.clone()).or_default() += 1;
+    }
+
+    fn close(self, user_id: String) -> SessionSummary {
+        SessionSummary {
+            session_id: format!("{}:{}", user_id, self.started_at.timestamp_millis()),
+            user_id,
+            started_at: self.started_at,
+            ended_at: self.last_event_at,
+            duration_seconds: (self.last_event_at - self.started_at).num_seconds(),
+            event_count: self.event_count,
+            event_counts: self.event_counts,
+            platform: self.platform,
+        }
+    }
+}
+
+/// Streaming sessionization operator.
+///
+/// Stitches each user's events into sessions, closing a session when the
+/// user has been idle longer than the timeout. Events without a user ID are
+/// not sessionized. The operator holds one open session per user and does no
+/// I/O; `RealtimeSessions` drives it and persists what it closes.
+pub struct Sessionizer {
+    idle_timeout: Duration,
+    active_window: Duration,
+    open: HashMap<String, OpenSession>,
+}
+
+impl Sessionizer {
+    pub fn new(config: &SessionConfig) -> Self {
+        Self {
+            idle_timeout: config.idle_timeout,
+            active_window: config.active_window,
+            open: HashMap::new(),
+        }
+    }
+
+    /// Add an event to its user's session, returning the previous session if the gap closed it
+    pub fn ingest(&mut self, event: &AnalyticsEvent) -> Option<SessionSummary> {
+        let user_id = event.user_id.as_ref()?;
+        let mut closed = None;
+        if let Some(session) = self.open.get(user_id) {
+            if event.timestamp - session.last_event_at > self.idle_timeout {
+                let session = self.open.remove(user_id).expect("session checked above");
+                closed = Some(session.close(user_id.clone()));
+            }
+        }
+        self.open
+            .entry(user_id.clone())
+            .or_insert_with(|| OpenSession::start(event))
+            .add(event);
+        closed
+    }
+
+    /// Close every session idle for longer than the timeout at `now`
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Vec<SessionSummary> {
+        let cutoff = now - self.idle_timeout;
+        let idle: Vec<String> = self
+            .open
+            .iter()
+            .filter(|(_, session)| session.last_event_at < cutoff)
+            .map(|(user_id, _)| user_id.clone())
+            .collect();
+        idle.into_iter()
+            .filter_map(|user_id| self.open.remove(&user_id).map(|session| session.close(user_id)))
+            .collect()
+    }
+
+    /// Close every open session, for shutdown
+    pub fn close_all(&mut self) -> Vec<SessionSummary> {
+        self.open.drain().map(|(user_id, session)| session.close(user_id)).collect()
+    }
+
+    /// Counters at `now`; call `expire` first so idle sessions are not counted
+    pub fn gauges(&self, now: DateTime<Utc>) -> ActiveUserGauges {
+        let active_since = now - self.active_window;
+        ActiveUserGauges {
+            concurrent_sessions: self.open.len(),
+            active_users: self.open.values().filter(|session| session.last_event_at >= active_since).count(),
+            active_window_seconds: self.active_window.num_seconds(),
+            updated_at: now,
+        }
+    }
+}
+
+/// Durable storage for closed sessions
+#[async_trait]
+pub trait SessionSummaryStore: Send + Sync {
+    async fn save_summaries(&self, summaries: &[SessionSummary]) -> anyhow::Result<()>;
+}
+
+/// Writes summaries as JSON lines, one file per session start date, so cohort
+/// jobs can load the days they need. A retried write can repeat lines;
+/// `session_id` is unique, so readers drop duplicates by it.
+pub struct JsonLinesSessionStore {
+    dir: PathBuf,
+}
+
+impl JsonLinesSessionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl SessionSummaryStore for JsonLinesSessionStore {
+    async fn save_summaries(&self, summaries: &[SessionSummary]) -> anyhow::Result<()> {
+        let mut by_day: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for summary in summaries {
+            let lines = by_day.entry(summary.started_at.format("%Y-%m-%d").to_string()).or_default();
+            serde_json::to_writer(&mut *lines, summary)?;
+            lines.push(b'\n');
+        }
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        for (day, lines) in by_day {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(format!("sessions-{}.jsonl", day)))
+                .await?;
+            file.write_all(&lines).await?;
+            file.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Sessionization over the live event stream.
+///
+/// Events are sessionized as they are tracked. A ticker closes idle sessions,
+/// refreshes the active-user gauges and writes closed sessions to the store;
+/// summaries that fail to write are retried on the next tick.
+pub struct RealtimeSessions {
+    config: SessionConfig,
+    sessionizer: Mutex<Sessionizer>,
+    pending: Mutex<Vec<SessionSummary>>,
+    gauges: RwLock<ActiveUserGauges>,
+    store: Arc<dyn SessionSummaryStore>,
+    metrics: AnalyticsMetrics,
+}
+
+impl RealtimeSessions {
+    pub fn new(config: SessionConfig, store: Arc<dyn SessionSummaryStore>, metrics: AnalyticsMetrics) -> Self {
+        let sessionizer = Sessionizer::new(&config);
+        let gauges = sessionizer.gauges(Utc::now());
+        Self {
+            config,
+            sessionizer: Mutex::new(sessionizer),
+            pending: Mutex::new(Vec::new()),
+            gauges: RwLock::new(gauges),
+            store,
+            metrics,
+        }
+    }
+
+    pub fn track(&self, event: &AnalyticsEvent) {
+        let closed = self.sessionizer.lock().unwrap().ingest(event);
+        if let Some(summary) = closed {
+            self.queue(vec![summary]);
+        }
+    }
+
+    /// Gauges as of the last tick; cheap enough to poll from a dashboard
+    pub fn gauges(&self) -> ActiveUserGauges {
+        self.gauges.read().unwrap().clone()
+    }
+
+    /// Close idle sessions, refresh the gauges and persist closed sessions
+    pub async fn tick(&self, now: DateTime<Utc>) -> anyhow::Result<()> {
+        let (closed, gauges) = {
+            let mut sessionizer = self.sessionizer.lock().unwrap();
+            let closed = sessionizer.expire(now);
+            (closed, sessionizer.gauges(now))
+        };
+        self.metrics.set_session_gauges(&gauges);
+        *self.gauges.write().unwrap() = gauges;
+        self.queue(closed);
+        self.flush().await
+    }
+
+    /// Close every open session and persist it, for shutdown
+    pub async fn close_all(&self) -> anyhow::Result<()> {
+        let closed = self.sessionizer.lock().unwrap().close_all();
+        self.queue(closed);
+        self.flush().await
+    }
+
+    pub fn spawn_ticker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.gauge_interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.tick(Utc::now()).await {
+                    tracing::error!("Failed to persist session summaries: {}", e);
+                }
+            }
+        })
+    }
+
+    fn queue(&self, closed: Vec<SessionSummary>) {
+        if closed.is_empty() {
+            return;
+        }
+        self.metrics.add_sessions_closed(closed.len() as u64);
+        let mut pending = self.pending.lock().unwrap();
+        pending.extend(closed);
+        if pending.len() > MAX_PENDING_SUMMARIES {
+            let dropped = pending.len() - MAX_PENDING_SUMMARIES;
+            pending.drain(..dropped);
+            tracing::warn!("Dropped {} unsaved session summaries", dropped);
+        }
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        let summaries = std::mem::take(&mut *self.pending.lock().unwrap());
+        if summaries.is_empty() {
+            return Ok(());
+        }
+        if let Err(e) = self.store.save_summaries(&summaries).await {
+            // Keep them ahead of anything closed meanwhile
+            let mut pending = self.pending.lock().unwrap();
+            let newer = std::mem::replace(&mut *pending, summaries);
+            pending.extend(newer);
+            return Err(e);
+        }
+        tracing::debug!("Saved {} session summaries", summaries.len());
+        Ok(())
+    }
+}
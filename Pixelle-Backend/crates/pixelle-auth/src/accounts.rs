@@ -0,0 +1,382 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use pixelle_core::{
+    PixelleError, PixelleResult, PrivacySettings, UserId, UserProfile, EMAIL_VERIFICATION_TTL_HOURS, PASSWORD_RESET_TTL_MINUTES,
+};
+use pixelle_database::{Backends, Capability, DocumentRepository, Migration, StoreError, StoreQuery, StoreResult, Transaction};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+/// Profiles, shared with user-service
+pub const USERS_REPOSITORY: &str = "users";
+pub const CREDENTIALS_REPOSITORY: &str = "credentials";
+pub const USERNAMES_REPOSITORY: &str = "usernames";
+pub const EMAILS_REPOSITORY: &str = "emails";
+pub const SESSIONS_REPOSITORY: &str = "sessions";
+pub const PASSWORD_RESETS_REPOSITORY: &str = "password_resets";
+pub const EMAIL_VERIFICATIONS_REPOSITORY: &str = "email_verifications";
+pub const EMAIL_OUTBOX_REPOSITORY: &str = "email_outbox";
+pub const ACCOUNT_UNLOCKS_REPOSITORY: &str = "account_unlocks";
+pub const API_KEYS_REPOSITORY: &str = "api_keys";
+pub const ROLES_REPOSITORY: &str = "roles";
+pub const INVITES_REPOSITORY: &str = "invites";
+pub const REFERRALS_REPOSITORY: &str = "referrals";
+pub const AUDIT_LOG_REPOSITORY: &str = "audit_log";
+pub const VERIFICATIONS_REPOSITORY: &str = "verifications";
+pub const REVOCATIONS_REPOSITORY: &str = "token_revocations";
+
+/// Profiles read per page while migrating
+const MIGRATION_PAGE_SIZE: usize = 500;
+
+/// Password hash of an account, kept apart from the profile other services read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    pub user_id: UserId,
+    pub password_hash: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Reserves a username or email, stored under its lowercase form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claim {
+    pub user_id: UserId,
+    /// When the owner of an email claim followed a verification link sent to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+/// Outstanding password reset, stored under a hash of its token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordReset {
+    pub user_id: UserId,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Outstanding email verification, stored under a hash of its token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailVerification {
+    pub user_id: UserId,
+    /// Address the link was sent to; verifying fails if the account no
+    /// longer holds it
+    pub email: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Outstanding link lifting a login lockout, stored under a hash of its token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountUnlock {
+    pub user_id: UserId,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Account details submitted at registration
+#[derive(Debug, Clone, Deserialize)]
+pub struct Registration {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Code of the invite the user signed up with
+    #[serde(default)]
+    pub invite_code: Option<String>,
+}
+
+/// Token to send to the account's email; only its hash is stored
+#[derive(Debug, Clone)]
+pub struct PasswordResetToken {
+    pub user: UserProfile,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Token to send to an account's email to verify it; only its hash is stored
+#[derive(Debug, Clone)]
+pub struct EmailVerificationToken {
+    pub user: UserProfile,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Accounts, credentials, password resets and email verifications in
+/// pixelle-database
+///
+/// Registration writes the profile, the credentials and the username and
+/// email claims in one transaction, and a password reset replaces the
+/// credentials, consumes the token and ends the account's sessions in
+/// another, so every auth repository must be placed on one backend with
+/// transactions.
+pub struct AccountStore {
+    profiles: DocumentRepository<UserProfile>,
+    credentials: DocumentRepository<Credentials>,
+    usernames: DocumentRepository<Claim>,
+    emails: DocumentRepository<Claim>,
+    password_resets: DocumentRepository<PasswordReset>,
+    email_verifications: DocumentRepository<EmailVerification>,
+    unlocks: DocumentRepository<AccountUnlock>,
+}
+
+impl AccountStore {
+    pub fn new(backends: &Backends) -> StoreResult<Self> {
+        let required = [Capability::Transactions];
+        Ok(Self {
+            profiles: backends.repository(USERS_REPOSITORY, &required)?,
+            credentials: backends.repository(CREDENTIALS_REPOSITORY, &required)?,
+            usernames: backends.repository(USERNAMES_REPOSITORY, &required)?,
+            emails: backends.repository(EMAILS_REPOSITORY, &required)?,
+            password_resets: backends.repository(PASSWORD_RESETS_REPOSITORY, &required)?,
+            email_verifications: backends.repository(EMAIL_VERIFICATIONS_REPOSITORY, &required)?,
+            unlocks: backends.repository(ACCOUNT_UNLOCKS_REPOSITORY, &required)?,
+        })
+    }
+
+    /// Create the profile and credentials of a new account, failing with a
+    /// conflict if the username or email is taken
+    pub async fn create_account(&self, registration: &Registration, password_hash: String) -> PixelleResult<UserProfile> {
+        let now = Utc::now();
+        let profile = UserProfile {
+            id: uuid::Uuid::new_v4(),
+            username: registration.username.trim().to_string(),
+            email: registration.email.trim().to_string(),
+            display_name: registration.display_name.clone(),
+            bio: None,
+            avatar_url: None,
+            is_verified: false,
+            is_private: false,
+            privacy: PrivacySettings::default(),
+            preferred_languages: Vec::new(),
+            region: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let credentials = Credentials {
+            user_id: profile.id,
+            password_hash,
+            updated_at: now,
+        };
+        let claim = Claim { user_id: profile.id, verified_at: None };
+        let id = profile.id.to_string();
+
+        let mut transaction = self.profiles.transaction().map_err(store_error)?;
+        transaction.insert(&self.usernames, &claim_key(&profile.username), &claim).map_err(store_error)?;
+        transaction.insert(&self.emails, &claim_key(&profile.email), &claim).map_err(store_error)?;
+        transaction.insert(&self.profiles, &id, &profile).map_err(store_error)?;
+        transaction.insert(&self.credentials, &id, &credentials).map_err(store_error)?;
+        transaction.commit().await.map_err(store_error)?;
+        Ok(profile)
+    }
+
+    /// Profile and credentials of the account with this username or email
+    pub async fn find_account(&self, account: &str) -> PixelleResult<Option<(UserProfile, Credentials)>> {
+        let claims = if account.contains('@') { &self.emails } else { &self.usernames };
+        let Some(claim) = claims.get(&claim_key(account)).await.map_err(store_error)? else {
+            return Ok(None);
+        };
+        let id = claim.user_id.to_string();
+        let profile = self.profiles.get(&id).await.map_err(store_error)?;
+        let credentials = self.credentials.get(&id).await.map_err(store_error)?;
+        Ok(profile.zip(credentials))
+    }
+
+    pub async fn get_profile(&self, user_id: UserId) -> PixelleResult<Option<UserProfile>> {
+        self.profiles.get(&user_id.to_string()).await.map_err(store_error)
+    }
+
+    /// Set the verified badge of a profile; `false` for unknown users
+    pub async fn set_verified(&self, user_id: UserId, verified: bool) -> PixelleResult<bool> {
+        let Some(mut profile) = self.get_profile(user_id).await? else {
+            return Ok(false);
+        };
+        if profile.is_verified != verified {
+            profile.is_verified = verified;
+            profile.updated_at = Utc::now();
+            self.profiles.put(&user_id.to_string(), &profile).await.map_err(store_error)?;
+        }
+        Ok(true)
+    }
+
+    /// Issue a reset token for the account registered with `email`, if any
+    pub async fn create_password_reset(&self, email: &str) -> PixelleResult<Option<PasswordResetToken>> {
+        let Some((user, _)) = self.find_account(email).await? else {
+            return Ok(None);
+        };
+        let token = new_token()?;
+        let expires_at = Utc::now() + Duration::minutes(PASSWORD_RESET_TTL_MINUTES);
+
+        let reset = PasswordReset { user_id: user.id, expires_at };
+        self.password_resets.put(&token_key(&token), &reset).await.map_err(store_error)?;
+        Ok(Some(PasswordResetToken { user, token, expires_at }))
+    }
+
+    /// Check a reset token and start a transaction that sets the new
+    /// password hash and consumes the token
+    ///
+    /// The commit fails if the token was consumed since the check, so two
+    /// concurrent resets with one token cannot both set a password.
+    pub async fn begin_password_reset(&self, token: &str, password_hash: String) -> PixelleResult<(UserId, Transaction)> {
+        let key = token_key(token);
+        let invalid = || PixelleError::Authentication("Invalid or expired reset token".to_string());
+        let reset = self.password_resets.get(&key).await.map_err(store_error)?.ok_or_else(invalid)?;
+        if reset.expires_at <= Utc::now() {
+            self.password_resets.delete(&key).await.map_err(store_error)?;
+            return Err(invalid());
+        }
+
+        let credentials = Credentials {
+            user_id: reset.user_id,
+            password_hash,
+            updated_at: Utc::now(),
+        };
+        let mut transaction = self.credentials.transaction().map_err(store_error)?;
+        transaction.put(&self.credentials, &reset.user_id.to_string(), &credentials).map_err(store_error)?;
+        transaction.take(&self.password_resets, &key).map_err(store_error)?;
+        Ok((reset.user_id, transaction))
+    }
+
+    /// Whether the account's current email has been verified
+    pub async fn is_email_verified(&self, user: &UserProfile) -> PixelleResult<bool> {
+        let claim = self.emails.get(&claim_key(&user.email)).await.map_err(store_error)?;
+        Ok(claim.is_some_and(|claim| claim.user_id == user.id && claim.verified_at.is_some()))
+    }
+
+    /// Issue a token that verifies the account's current email
+    pub async fn create_email_verification(&self, user: &UserProfile) -> PixelleResult<EmailVerificationToken> {
+        let token = new_token()?;
+        let expires_at = Utc::now() + Duration::hours(EMAIL_VERIFICATION_TTL_HOURS);
+        let verification = EmailVerification {
+            user_id: user.id,
+            email: user.email.clone(),
+            expires_at,
+        };
+        self.email_verifications.put(&token_key(&token), &verification).await.map_err(store_error)?;
+        Ok(EmailVerificationToken {
+            user: user.clone(),
+            token,
+            expires_at,
+        })
+    }
+
+    /// Mark the email a verification token was sent to as verified and
+    /// consume the token
+    pub async fn verify_email(&self, token: &str) -> PixelleResult<UserId> {
+        let key = token_key(token);
+        let invalid = || PixelleError::Authentication("Invalid or expired verification token".to_string());
+        let verification = self.email_verifications.get(&key).await.map_err(store_error)?.ok_or_else(invalid)?;
+        let email_key = claim_key(&verification.email);
+        let claim = self.emails.get(&email_key).await.map_err(store_error)?;
+        let still_held = claim.is_some_and(|claim| claim.user_id == verification.user_id);
+        if verification.expires_at <= Utc::now() || !still_held {
+            self.email_verifications.delete(&key).await.map_err(store_error)?;
+            return Err(invalid());
+        }
+
+        let claim = Claim {
+            user_id: verification.user_id,
+            verified_at: Some(Utc::now()),
+        };
+        let mut transaction = self.emails.transaction().map_err(store_error)?;
+        transaction.put(&self.emails, &email_key, &claim).map_err(store_error)?;
+        transaction.take(&self.email_verifications, &key).map_err(store_error)?;
+        transaction.commit().await.map_err(store_error)?;
+        Ok(verification.user_id)
+    }
+
+    /// Issue a token that lifts the lockout of an account until it ends by itself
+    pub async fn create_account_unlock(&self, user_id: UserId, expires_at: DateTime<Utc>) -> PixelleResult<String> {
+        let token = new_token()?;
+        let unlock = AccountUnlock { user_id, expires_at };
+        self.unlocks.put(&token_key(&token), &unlock).await.map_err(store_error)?;
+        Ok(token)
+    }
+
+    /// Consume an unlock token, returning the account it unlocks
+    pub async fn consume_account_unlock(&self, token: &str) -> PixelleResult<UserId> {
+        let key = token_key(token);
+        let invalid = || PixelleError::Authentication("Invalid or expired unlock token".to_string());
+        let unlock = self.unlocks.get(&key).await.map_err(store_error)?.ok_or_else(invalid)?;
+        let consumed = self.unlocks.delete(&key).await.map_err(store_error)?;
+        if !consumed || unlock.expires_at <= Utc::now() {
+            return Err(invalid());
+        }
+        Ok(unlock.user_id)
+    }
+}
+
+/// Migrations of the auth repositories, applied by auth-service at startup
+pub fn account_migrations() -> Vec<Migration> {
+    vec![Migration {
+        version: 1,
+        name: "claim_existing_usernames_and_emails",
+        up: claim_existing_accounts,
+    }]
+}
+
+/// Claim the usernames and emails of profiles created before claims
+/// existed, so registrations cannot take them
+fn claim_existing_accounts(backends: &Backends) -> BoxFuture<'_, StoreResult<()>> {
+    Box::pin(async move {
+        let accounts = AccountStore::new(backends)?;
+        let mut offset = 0;
+        loop {
+            let query = StoreQuery::new().order_by("id", false).offset(offset).limit(MIGRATION_PAGE_SIZE);
+            let profiles = accounts.profiles.find(&query).await?;
+            for profile in &profiles {
+                let claim = Claim { user_id: profile.id, verified_at: None };
+                for (claims, value) in [(&accounts.usernames, &profile.username), (&accounts.emails, &profile.email)] {
+                    let key = claim_key(value);
+                    if claims.get(&key).await?.is_none() {
+                        claims.put(&key, &claim).await?;
+                    }
+                }
+            }
+            if profiles.len() < MIGRATION_PAGE_SIZE {
+                return Ok(());
+            }
+            offset += profiles.len();
+        }
+    })
+}
+
+/// Random URL-safe token for links sent by email
+fn new_token() -> PixelleResult<String> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| PixelleError::Internal("Failed to generate a token".to_string()))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Usernames and emails are unique regardless of case
+fn claim_key(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+/// Reset, verification and unlock tokens and API keys are stored by hash, so reading the store does
+/// not allow using them
+pub(crate) fn token_key(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, token.as_bytes()))
+}
+
+/// Store failures as service errors; a taken claim is a conflict
+pub(crate) fn store_error(error: StoreError) -> PixelleError {
+    match error {
+        StoreError::Conflict { collection, .. } if collection == USERNAMES_REPOSITORY => {
+            PixelleError::Conflict("Username already exists".to_string())
+        }
+        StoreError::Conflict { collection, .. } if collection == EMAILS_REPOSITORY => {
+            PixelleError::Conflict("Email already exists".to_string())
+        }
+        StoreError::Conflict { collection, id } => PixelleError::Conflict(format!("{} already holds {}", collection, id)),
+        StoreError::Missing { collection, .. } if collection == PASSWORD_RESETS_REPOSITORY => {
+            PixelleError::Authentication("Invalid or expired reset token".to_string())
+        }
+        StoreError::Missing { collection, .. } if collection == EMAIL_VERIFICATIONS_REPOSITORY => {
+            PixelleError::Authentication("Invalid or expired verification token".to_string())
+        }
+        StoreError::Serialization(e) => PixelleError::Serialization(e),
+        error => PixelleError::Internal(format!("Account store: {}", error)),
+    }
+}
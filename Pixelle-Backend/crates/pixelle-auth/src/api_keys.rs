@@ -0,0 +1,357 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use pixelle_core::{
+    PixelleError, PixelleResult, UserId, API_KEY_DEFAULT_RATE_LIMIT_PER_MINUTE, MAX_API_KEYS_PER_USER,
+};
+use pixelle_database::{Backends, Capability, DocumentRepository, StoreQuery, StoreResult};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::accounts::{store_error, token_key, API_KEYS_REPOSITORY};
+
+/// API keys start with this, so they can be told apart from access tokens
+/// in an `Authorization: Bearer` header and found by secret scanners
+pub const API_KEY_PREFIX: &str = "pxk_";
+
+/// Key IDs are simple UUIDs, and secrets 32 random bytes in unpadded base64url
+const API_KEY_ID_LENGTH: usize = 32;
+const API_KEY_SECRET_LENGTH: usize = 43;
+
+const MAX_API_KEY_NAME_LENGTH: usize = 64;
+const MAX_API_KEY_SCOPES: usize = 16;
+
+/// A credential a user creates for scripts and integrations
+///
+/// It acts for its owner, but only within its scopes. Only a hash of its
+/// secret is stored, so the full key is shown once, when it is created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub user_id: UserId,
+    pub name: String,
+    /// Scopes such as `users:read`, checked by [`crate::Authenticate`]
+    pub scopes: Vec<String>,
+    /// Requests per minute, counted by each service instance
+    pub rate_limit_per_minute: u32,
+    pub secret_hash: String,
+    pub created_at: DateTime<Utc>,
+    /// Never expires when unset
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Recorded when a service looks the key up, so at most once per cache
+    /// period per service instance
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// What a service learns about a valid API key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyGrant {
+    pub key_id: String,
+    pub user_id: UserId,
+    /// Region whose auth-service stores the key
+    #[serde(default)]
+    pub region: String,
+    pub scopes: Vec<String>,
+    pub rate_limit_per_minute: u32,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<&ApiKey> for ApiKeyGrant {
+    fn from(key: &ApiKey) -> Self {
+        Self {
+            key_id: key.id.clone(),
+            user_id: key.user_id,
+            region: String::new(),
+            scopes: key.scopes.clone(),
+            rate_limit_per_minute: key.rate_limit_per_minute,
+            expires_at: key.expires_at,
+        }
+    }
+}
+
+/// Settings of a key to create
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Defaults to `API_KEY_DEFAULT_RATE_LIMIT_PER_MINUTE`
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<u32>,
+}
+
+/// A created key with its full secret, to be shown to the user once
+#[derive(Debug, Clone)]
+pub struct IssuedApiKey {
+    pub key: ApiKey,
+    pub secret: String,
+}
+
+/// API keys in the `api_keys` repository, stored under their ID
+///
+/// A full key is `pxk_<id>_<secret>`; the ID finds the stored key and the
+/// secret is checked against its hash.
+pub struct ApiKeyService {
+    keys: DocumentRepository<ApiKey>,
+}
+
+impl ApiKeyService {
+    pub fn new(backends: &Backends) -> StoreResult<Self> {
+        Ok(Self {
+            keys: backends.repository(API_KEYS_REPOSITORY, &[Capability::Durable])?,
+        })
+    }
+
+    pub async fn create_key(&self, user_id: UserId, request: NewApiKey) -> PixelleResult<IssuedApiKey> {
+        let now = Utc::now();
+        validate_new_key(&request, now)?;
+        if self.list_keys(user_id).await?.len() >= MAX_API_KEYS_PER_USER {
+            return Err(PixelleError::Validation(format!(
+                "Accounts can have at most {} API keys",
+                MAX_API_KEYS_PER_USER
+            )));
+        }
+
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| PixelleError::Internal("Failed to generate an API key".to_string()))?;
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let secret = format!("{}{}_{}", API_KEY_PREFIX, id, URL_SAFE_NO_PAD.encode(bytes));
+
+        let key = ApiKey {
+            id,
+            user_id,
+            name: request.name.trim().to_string(),
+            scopes: request.scopes,
+            rate_limit_per_minute: request.rate_limit_per_minute.unwrap_or(API_KEY_DEFAULT_RATE_LIMIT_PER_MINUTE),
+            secret_hash: token_key(&secret),
+            created_at: now,
+            expires_at: request.expires_at,
+            last_used_at: None,
+        };
+        self.keys.put(&key.id, &key).await.map_err(store_error)?;
+        Ok(IssuedApiKey { key, secret })
+    }
+
+    /// Keys of a user, expired ones included, newest first
+    pub async fn list_keys(&self, user_id: UserId) -> PixelleResult<Vec<ApiKey>> {
+        let query = StoreQuery::new().filter("user_id", user_id.to_string());
+        let mut keys = self.keys.find(&query).await.map_err(store_error)?;
+        keys.sort_by_key(|key| std::cmp::Reverse(key.created_at));
+        Ok(keys)
+    }
+
+    /// Delete a key of a user; keys of other users are reported as not found
+    pub async fn revoke_key(&self, user_id: UserId, key_id: &str) -> PixelleResult<()> {
+        match self.keys.get(key_id).await.map_err(store_error)? {
+            Some(key) if key.user_id == user_id => {
+                self.keys.delete(key_id).await.map_err(store_error)?;
+                Ok(())
+            }
+            _ => Err(PixelleError::NotFound(format!("API key {} not found", key_id))),
+        }
+    }
+
+    /// The grant of a full key if it exists and has not expired
+    pub async fn verify_key(&self, secret: &str) -> PixelleResult<Option<ApiKeyGrant>> {
+        let Some((id, _)) = split_api_key(secret) else {
+            return Ok(None);
+        };
+        let Some(mut key) = self.keys.get(id).await.map_err(store_error)? else {
+            return Ok(None);
+        };
+        let now = Utc::now();
+        if key.secret_hash != token_key(secret) || key.is_expired(now) {
+            return Ok(None);
+        }
+
+        key.last_used_at = Some(now);
+        self.keys.put(&key.id, &key).await.map_err(store_error)?;
+        Ok(Some(ApiKeyGrant::from(&key)))
+    }
+}
+
+fn validate_new_key(request: &NewApiKey, now: DateTime<Utc>) -> PixelleResult<()> {
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_API_KEY_NAME_LENGTH {
+        return Err(PixelleError::Validation(format!(
+            "API key names are 1 to {} characters",
+            MAX_API_KEY_NAME_LENGTH
+        )));
+    }
+    if request.scopes.is_empty() || request.scopes.len() > MAX_API_KEY_SCOPES {
+        return Err(PixelleError::Validation(format!(
+            "API keys have 1 to {} scopes",
+            MAX_API_KEY_SCOPES
+        )));
+    }
+    if let Some(scope) = request.scopes.iter().find(|scope| !is_valid_scope(scope)) {
+        return Err(PixelleError::Validation(format!("Invalid scope {}", scope)));
+    }
+    if request.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(PixelleError::Validation("API keys must expire in the future".to_string()));
+    }
+    if request.rate_limit_per_minute == Some(0) {
+        return Err(PixelleError::Validation("Rate limits must allow at least one request".to_string()));
+    }
+    Ok(())
+}
+
+/// The API key in an `Authorization` header value, if it holds one rather
+/// than an access token
+pub fn api_key_from_bearer(header: &str) -> Option<&str> {
+    header
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| token.starts_with(API_KEY_PREFIX))
+}
+
+/// ID and secret of a well-formed key, `pxk_<id>_<secret>`
+///
+/// Keys that fail this cannot exist, so they can be rejected without a lookup.
+pub fn split_api_key(key: &str) -> Option<(&str, &str)> {
+    let (id, secret) = key.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+    let id_valid = id.len() == API_KEY_ID_LENGTH && id.chars().all(|c| c.is_ascii_digit() || matches!(c, 'a'..='f'));
+    let secret_valid = secret.len() == API_KEY_SECRET_LENGTH
+        && secret.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    (id_valid && secret_valid).then_some((id, secret))
+}
+
+/// Whether `scopes` allow `scope`; write access to a resource includes
+/// read access
+pub fn scopes_allow(scopes: &[String], scope: &str) -> bool {
+    let write = scope.strip_suffix(":read").map(|resource| format!("{}:write", resource));
+    scopes.iter().any(|granted| granted == scope || Some(granted) == write.as_ref())
+}
+
+/// Scopes are `<resource>:read` or `<resource>:write`, e.g. `users:read`
+fn is_valid_scope(scope: &str) -> bool {
+    match scope.split_once(':') {
+        Some((resource, access)) => {
+            !resource.is_empty()
+                && resource.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+                && matches!(access, "read" | "write")
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use pixelle_database::InMemoryStore;
+    use std::sync::Arc;
+
+    fn service() -> ApiKeyService {
+        ApiKeyService {
+            keys: DocumentRepository::new(Arc::new(InMemoryStore::new()), API_KEYS_REPOSITORY).unwrap(),
+        }
+    }
+
+    fn new_key(scopes: &[&str]) -> NewApiKey {
+        NewApiKey {
+            name: "deploy bot".to_string(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            expires_at: None,
+            rate_limit_per_minute: None,
+        }
+    }
+
+    #[test]
+    fn keys_are_parsed_only_when_well_formed() {
+        let id = "0123456789abcdef0123456789abcdef";
+        let secret = "a".repeat(API_KEY_SECRET_LENGTH - 2) + "-_";
+        let key = format!("{}{}_{}", API_KEY_PREFIX, id, secret);
+        assert_eq!(split_api_key(&key), Some((id, secret.as_str())));
+
+        for malformed in [
+            "pxk_x_y".to_string(),
+            format!("{}_{}", id, secret),
+            format!("{}{}", API_KEY_PREFIX, id),
+            format!("{}{}_{}", API_KEY_PREFIX, id.to_uppercase(), secret),
+            format!("{}{}_{}x", API_KEY_PREFIX, id, secret),
+            format!("{}{}_{}=", API_KEY_PREFIX, id, &secret[1..]),
+        ] {
+            assert_eq!(split_api_key(&malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn bearer_headers_hold_keys_or_tokens() {
+        assert_eq!(api_key_from_bearer("Bearer pxk_abc_def"), Some("pxk_abc_def"));
+        assert_eq!(api_key_from_bearer("Bearer  pxk_abc_def "), Some("pxk_abc_def"));
+        assert_eq!(api_key_from_bearer("Bearer eyJhbGciOiJFUzI1NiJ9.e30.sig"), None);
+        assert_eq!(api_key_from_bearer("Basic pxk_abc_def"), None);
+    }
+
+    #[test]
+    fn write_scopes_include_reads() {
+        let scopes = vec!["users:write".to_string(), "posts:read".to_string()];
+        assert!(scopes_allow(&scopes, "users:read"));
+        assert!(scopes_allow(&scopes, "users:write"));
+        assert!(scopes_allow(&scopes, "posts:read"));
+        assert!(!scopes_allow(&scopes, "posts:write"));
+        assert!(!scopes_allow(&scopes, "feed:read"));
+    }
+
+    #[test]
+    fn new_keys_are_validated() {
+        let now = Utc::now();
+        assert!(validate_new_key(&new_key(&["users:read", "link-previews:write"]), now).is_ok());
+        assert!(validate_new_key(&new_key(&[]), now).is_err());
+        assert!(validate_new_key(&new_key(&["users"]), now).is_err());
+        assert!(validate_new_key(&new_key(&["users:admin"]), now).is_err());
+        assert!(validate_new_key(&new_key(&["Users:read"]), now).is_err());
+        assert!(validate_new_key(&NewApiKey { name: " ".to_string(), ..new_key(&["users:read"]) }, now).is_err());
+        let expired = NewApiKey { expires_at: Some(now - Duration::minutes(1)), ..new_key(&["users:read"]) };
+        assert!(validate_new_key(&expired, now).is_err());
+        let unlimited = NewApiKey { rate_limit_per_minute: Some(0), ..new_key(&["users:read"]) };
+        assert!(validate_new_key(&unlimited, now).is_err());
+    }
+
+    #[tokio::test]
+    async fn issued_keys_verify_until_revoked() {
+        let service = service();
+        let user_id = UserId::new_v4();
+        let issued = service.create_key(user_id, new_key(&["users:read"])).await.unwrap();
+        assert!(split_api_key(&issued.secret).is_some());
+        assert_ne!(issued.key.secret_hash, issued.secret);
+        assert_eq!(issued.key.rate_limit_per_minute, API_KEY_DEFAULT_RATE_LIMIT_PER_MINUTE);
+
+        let grant = service.verify_key(&issued.secret).await.unwrap().unwrap();
+        assert_eq!(grant.key_id, issued.key.id);
+        assert_eq!(grant.user_id, user_id);
+        assert_eq!(grant.scopes, vec!["users:read".to_string()]);
+        let stored = service.list_keys(user_id).await.unwrap();
+        assert!(stored[0].last_used_at.is_some());
+
+        // The ID alone, with another secret, is not enough
+        let (id, _) = split_api_key(&issued.secret).unwrap();
+        let forged = format!("{}{}_{}", API_KEY_PREFIX, id, "A".repeat(API_KEY_SECRET_LENGTH));
+        assert_eq!(service.verify_key(&forged).await.unwrap(), None);
+
+        // Only the owner can revoke a key
+        assert!(service.revoke_key(UserId::new_v4(), &issued.key.id).await.is_err());
+        service.revoke_key(user_id, &issued.key.id).await.unwrap();
+        assert_eq!(service.verify_key(&issued.secret).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn expired_keys_do_not_verify() {
+        let service = service();
+        let issued = service.create_key(UserId::new_v4(), new_key(&["users:read"])).await.unwrap();
+        let mut key = issued.key.clone();
+        key.expires_at = Some(Utc::now() - Duration::seconds(1));
+        service.keys.put(&key.id, &key).await.unwrap();
+        assert_eq!(service.verify_key(&issued.secret).await.unwrap(), None);
+    }
+}
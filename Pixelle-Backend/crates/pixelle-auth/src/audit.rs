@@ -0,0 +1,267 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use pixelle_analytics::AnalyticsEvent;
+use pixelle_core::{PixelleResult, UserId, VerificationEvent, VerificationEventSink};
+use pixelle_database::{Backends, Capability, DocumentRepository, StoreQuery, StoreResult};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::accounts::{store_error, AUDIT_LOG_REPOSITORY};
+
+/// Most entries one query returns
+pub const MAX_AUDIT_QUERY_LIMIT: usize = 500;
+/// Entries read from the store at a time while querying
+const AUDIT_PAGE_SIZE: usize = 200;
+
+/// Security-relevant thing that happened to an account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Login,
+    /// Wrong password, or refused by login protection or the challenge gate
+    LoginFailed,
+    Logout,
+    /// Every session ended and the user's tokens revoked, e.g. signing out
+    /// everywhere or losing a role
+    SessionsRevoked,
+    /// One session ended and its tokens revoked
+    SessionRevoked,
+    PasswordChanged,
+    /// A second factor was enrolled or removed
+    MfaChanged,
+    RoleChanged,
+    ApiKeyCreated,
+    ApiKeyRevoked,
+    AccountLocked,
+    AccountUnlocked,
+    EmailVerified,
+    VerificationRequested,
+    VerificationSubmitted,
+    /// A reviewer opened a verification document
+    VerificationDocumentViewed,
+    VerificationApproved,
+    VerificationRejected,
+    VerificationRevoked,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Login => "login",
+            AuditAction::LoginFailed => "login_failed",
+            AuditAction::Logout => "logout",
+            AuditAction::SessionsRevoked => "sessions_revoked",
+            AuditAction::SessionRevoked => "session_revoked",
+            AuditAction::PasswordChanged => "password_changed",
+            AuditAction::MfaChanged => "mfa_changed",
+            AuditAction::RoleChanged => "role_changed",
+            AuditAction::ApiKeyCreated => "api_key_created",
+            AuditAction::ApiKeyRevoked => "api_key_revoked",
+            AuditAction::AccountLocked => "account_locked",
+            AuditAction::AccountUnlocked => "account_unlocked",
+            AuditAction::EmailVerified => "email_verified",
+            AuditAction::VerificationRequested => "verification_requested",
+            AuditAction::VerificationSubmitted => "verification_submitted",
+            AuditAction::VerificationDocumentViewed => "verification_document_viewed",
+            AuditAction::VerificationApproved => "verification_approved",
+            AuditAction::VerificationRejected => "verification_rejected",
+            AuditAction::VerificationRevoked => "verification_revoked",
+        }
+    }
+}
+
+/// One record of the audit log; never changed once written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Starts with the time in hex, so entries sort by when they happened
+    pub id: String,
+    pub action: AuditAction,
+    /// Account the action concerns; `None` for logins to unknown accounts
+    pub user_id: Option<UserId>,
+    /// Who acted, when not the account itself, e.g. the admin changing roles
+    pub actor_id: Option<UserId>,
+    pub ip: Option<String>,
+    #[serde(default)]
+    pub details: serde_json::Value,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, user_id: Option<UserId>) -> Self {
+        let occurred_at = Utc::now();
+        Self {
+            id: format!("{:016x}-{}", occurred_at.timestamp_micros(), uuid::Uuid::new_v4().simple()),
+            action,
+            user_id,
+            actor_id: None,
+            ip: None,
+            details: serde_json::Value::Null,
+            occurred_at,
+        }
+    }
+
+    pub fn actor(mut self, actor_id: UserId) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    pub fn ip(mut self, ip: Option<String>) -> Self {
+        self.ip = ip;
+        self
+    }
+
+    pub fn details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// Audit entries as analytics events, under the account they concern;
+/// IPs stay in the audit log
+impl From<&AuditEntry> for AnalyticsEvent {
+    fn from(entry: &AuditEntry) -> Self {
+        AnalyticsEvent {
+            event_type: format!("audit_{}", entry.action.as_str()),
+            user_id: entry.user_id.map(|user_id| user_id.to_string()),
+            timestamp: entry.occurred_at,
+            properties: serde_json::json!({
+                "audit_id": entry.id,
+                "actor_id": entry.actor_id,
+                "details": entry.details,
+            }),
+        }
+    }
+}
+
+/// Receives every audit entry once it is stored, e.g. to export it
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    async fn record(&self, entry: &AuditEntry) -> PixelleResult<()>;
+}
+
+/// Which entries to return, newest first; unset fields match everything
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditQuery {
+    pub user_id: Option<UserId>,
+    pub actor_id: Option<UserId>,
+    pub action: Option<AuditAction>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Append-only log of security-relevant events in the `audit_log`
+/// repository
+///
+/// Entries can only be appended and read back. Recording never fails the
+/// action being audited: errors are logged, and so are failures of sinks.
+pub struct AuditLog {
+    entries: DocumentRepository<AuditEntry>,
+    sinks: Vec<Arc<dyn AuditSink>>,
+}
+
+impl AuditLog {
+    pub fn new(backends: &Backends) -> StoreResult<Self> {
+        Ok(Self {
+            entries: backends.repository(AUDIT_LOG_REPOSITORY, &[Capability::Durable])?,
+            sinks: Vec::new(),
+        })
+    }
+
+    pub fn with_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Append an entry and hand it to the sinks
+    pub async fn record(&self, entry: AuditEntry) {
+        if let Err(e) = self.entries.put(&entry.id, &entry).await {
+            tracing::error!("Failed to write audit entry {} ({}): {}", entry.id, entry.action.as_str(), e);
+            return;
+        }
+        for sink in &self.sinks {
+            if let Err(e) = sink.record(&entry).await {
+                tracing::warn!("Failed to export audit entry {}: {}", entry.id, e);
+            }
+        }
+    }
+
+    /// Entries matching the query, newest first, at most
+    /// `MAX_AUDIT_QUERY_LIMIT`
+    ///
+    /// Pages through the log from the newest entry, skipping those after
+    /// `until` and stopping at the first before `since`.
+    pub async fn query(&self, query: &AuditQuery) -> PixelleResult<Vec<AuditEntry>> {
+        let limit = query.limit.unwrap_or(100).min(MAX_AUDIT_QUERY_LIMIT);
+        let mut base = StoreQuery::new().order_by("id", true);
+        if let Some(user_id) = query.user_id {
+            base = base.filter("user_id", user_id.to_string());
+        }
+        if let Some(actor_id) = query.actor_id {
+            base = base.filter("actor_id", actor_id.to_string());
+        }
+        if let Some(action) = query.action {
+            base = base.filter("action", action.as_str());
+        }
+
+        let mut matched = Vec::new();
+        let mut skip = query.offset;
+        let mut page_offset = 0;
+        loop {
+            let page = self
+                .entries
+                .find(&base.clone().offset(page_offset).limit(AUDIT_PAGE_SIZE))
+                .await
+                .map_err(store_error)?;
+            let exhausted = page.len() < AUDIT_PAGE_SIZE;
+            page_offset += page.len();
+            for entry in page {
+                if query.since.is_some_and(|since| entry.occurred_at < since) {
+                    return Ok(matched);
+                }
+                if query.until.is_some_and(|until| entry.occurred_at >= until) {
+                    continue;
+                }
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                matched.push(entry);
+                if matched.len() >= limit {
+                    return Ok(matched);
+                }
+            }
+            if exhausted {
+                return Ok(matched);
+            }
+        }
+    }
+}
+
+/// Audits every step of profile verification, with the reviewer as actor
+/// and the reason of rejections and revocations
+#[async_trait]
+impl VerificationEventSink for AuditLog {
+    async fn record(&self, event: &VerificationEvent) -> PixelleResult<()> {
+        let action = match event {
+            VerificationEvent::Requested(_) => AuditAction::VerificationRequested,
+            VerificationEvent::Submitted(_) => AuditAction::VerificationSubmitted,
+            VerificationEvent::DocumentViewed { .. } => AuditAction::VerificationDocumentViewed,
+            VerificationEvent::Approved(_) => AuditAction::VerificationApproved,
+            VerificationEvent::Rejected(_) => AuditAction::VerificationRejected,
+            VerificationEvent::Revoked(_) => AuditAction::VerificationRevoked,
+        };
+        let request = event.request();
+        let mut entry = AuditEntry::new(action, Some(request.user_id)).details(serde_json::json!({
+            "properties": event.properties(),
+            "reason_note": request.reason_note,
+        }));
+        if let Some(actor_id) = event.actor_id() {
+            entry = entry.actor(actor_id);
+        }
+        AuditLog::record(self, entry).await;
+        Ok(())
+    }
+}
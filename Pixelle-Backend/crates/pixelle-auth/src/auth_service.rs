@@ -1,51 +1,617 @@
 use async_trait::async_trait;
-use pixelle_core::{AuthService, UserProfile, PixelleResult, UserId};
+use pixelle_core::{
+    is_valid_email, is_valid_username, AuthService, InviteService, PixelleError, PixelleResult, Role, RoleAssignment,
+    RoleService, Subject, UserId, UserProfile, MAX_PASSWORD_LENGTH, MIN_PASSWORD_LENGTH,
+};
+use pixelle_database::{Backends, Capability};
+use std::sync::Arc;
+use crate::accounts::{store_error, AccountStore, Credentials, Registration, SESSIONS_REPOSITORY};
+use crate::api_keys::{ApiKey, ApiKeyGrant, ApiKeyService, IssuedApiKey, NewApiKey};
+use crate::audit::{AuditAction, AuditEntry, AuditLog};
+use crate::challenge::{AuthAttempt, AuthFlow, ChallengeError, ChallengeGate};
+use crate::clients::ServiceClients;
+use crate::config::EmailConfig;
+use crate::email::{EmailOutbox, EmailTemplate, LogEmailTransport};
+use crate::invites::InviteStore;
 use crate::jwt::JwtService;
+use crate::lockout::{LoginBlock, LoginProtection};
 use crate::passphrase::PassphraseService;
-use crate::session::SessionService;
+use crate::revocation::{RevocationKind, RevocationList, RevocationStore};
+use crate::roles::RoleStore;
+use crate::session::{Session, SessionDevice, SessionService};
 
 /// Authentication service implementation
 pub struct AuthServiceImpl {
-    jwt_service: JwtService,
+    jwt_service: Arc<JwtService>,
     passphrase_service: PassphraseService,
     session_service: SessionService,
+    revocations: RevocationStore,
+    accounts: AccountStore,
+    api_keys: ApiKeyService,
+    roles: RoleService,
+    service_clients: ServiceClients,
+    challenges: Option<Arc<ChallengeGate>>,
+    login_protection: Option<Arc<LoginProtection>>,
+    email: Arc<EmailOutbox>,
+    invites: Arc<InviteService>,
+    audit: Arc<AuditLog>,
 }
 
 impl AuthServiceImpl {
-    pub fn new(jwt_secret: String) -> Self {
-        Self {
-            jwt_service: JwtService::new(jwt_secret),
+    /// Keep accounts and sessions in the repositories `backends` places them
+    /// on; emails are only logged until `with_email` is given a transport,
+    /// and referrals earn nothing until `with_invites` is given rewarders
+    pub fn new(jwt_service: Arc<JwtService>, backends: &Backends) -> PixelleResult<Self> {
+        let sessions = backends
+            .repository(SESSIONS_REPOSITORY, &[Capability::Transactions])
+            .map_err(store_error)?;
+        Ok(Self {
+            jwt_service,
             passphrase_service: PassphraseService::new(),
-            session_service: SessionService::new(),
+            session_service: SessionService::new(sessions),
+            revocations: RevocationStore::new(backends).map_err(store_error)?,
+            accounts: AccountStore::new(backends).map_err(store_error)?,
+            api_keys: ApiKeyService::new(backends).map_err(store_error)?,
+            roles: RoleService::new(Arc::new(RoleStore::new(backends).map_err(store_error)?)),
+            service_clients: ServiceClients::new(Vec::new()),
+            challenges: None,
+            login_protection: None,
+            email: Arc::new(
+                EmailOutbox::new(backends, Arc::new(LogEmailTransport), EmailConfig::default()).map_err(store_error)?,
+            ),
+            invites: Arc::new(InviteService::new(Arc::new(InviteStore::new(backends).map_err(store_error)?))),
+            audit: Arc::new(AuditLog::new(backends).map_err(store_error)?),
+        })
+    }
+
+    /// Back off and lock out repeated failed logins
+    pub fn with_login_protection(mut self, login_protection: Arc<LoginProtection>) -> Self {
+        self.login_protection = Some(login_protection);
+        self
+    }
+
+    /// Send account emails through this outbox
+    pub fn with_email(mut self, email: Arc<EmailOutbox>) -> Self {
+        self.email = email;
+        self
+    }
+
+    pub fn email_outbox(&self) -> &Arc<EmailOutbox> {
+        &self.email
+    }
+
+    /// Track invites and referrals with this service, e.g. one with event
+    /// sinks and rewarders; it must use the invite repositories of `backends`
+    pub fn with_invites(mut self, invites: Arc<InviteService>) -> Self {
+        self.invites = invites;
+        self
+    }
+
+    pub fn invites(&self) -> &Arc<InviteService> {
+        &self.invites
+    }
+
+    /// Record security events in this log, e.g. one with export sinks; it
+    /// must use the audit repository of `backends`
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    pub fn audit_log(&self) -> &Arc<AuditLog> {
+        &self.audit
+    }
+
+    /// Issue service tokens to these internal services
+    pub fn with_service_clients(mut self, service_clients: ServiceClients) -> Self {
+        self.service_clients = service_clients;
+        self
+    }
+
+    /// Ask for a challenge on risky logins and after repeated failures
+    pub fn with_challenges(mut self, challenges: Arc<ChallengeGate>) -> Self {
+        self.challenges = Some(challenges);
+        self
+    }
+
+    /// Create an account and its profile behind the challenge gate, and send
+    /// a link verifying its email; the attempt's account defaults to the
+    /// username
+    ///
+    /// A signup with an invite code is attributed to its inviter. The code
+    /// is checked before the account is created, but the account is kept if
+    /// attributing it fails afterwards.
+    pub async fn register(&self, registration: &Registration, attempt: &AuthAttempt) -> Result<UserProfile, ChallengeError> {
+        let attempt = AuthAttempt {
+            account: attempt.account.clone().or_else(|| Some(registration.username.clone())),
+            ..attempt.clone()
+        };
+        if let Some(challenges) = &self.challenges {
+            challenges.check(AuthFlow::Register, &attempt).await?;
+        }
+
+        if !is_valid_username(registration.username.trim()) {
+            return Err(PixelleError::Validation("Usernames are 3 to 20 letters, digits or underscores".to_string()).into());
+        }
+        if !is_valid_email(registration.email.trim()) {
+            return Err(PixelleError::Validation("Invalid email address".to_string()).into());
+        }
+        validate_password(&registration.password)?;
+        if let Some(code) = &registration.invite_code {
+            self.invites.invite(code).await?;
+        }
+
+        let password_hash = self.passphrase_service.hash_passphrase(&registration.password).await?;
+        let user = self.accounts.create_account(registration, password_hash).await?;
+        // The account exists either way; the user can ask for another link
+        if let Err(e) = self.send_verification(&user).await {
+            tracing::warn!("Failed to queue the verification email of {}: {}", user.id, e);
+        }
+        if let Some(code) = &registration.invite_code {
+            if let Err(e) = self.invites.attribute_signup(code, user.id, attempt.ip.clone()).await {
+                tracing::warn!("Failed to attribute the signup of {} to invite {}: {}", user.id, code, e);
+            }
+        }
+        Ok(user)
+    }
+
+    /// Send another verification link to the user's email
+    pub async fn resend_email_verification(&self, user_id: UserId) -> PixelleResult<()> {
+        let user = self
+            .accounts
+            .get_profile(user_id)
+            .await?
+            .ok_or_else(|| PixelleError::NotFound(format!("User {} not found", user_id)))?;
+        if self.accounts.is_email_verified(&user).await? {
+            return Err(PixelleError::Conflict("Email is already verified".to_string()));
+        }
+        self.send_verification(&user).await
+    }
+
+    async fn send_verification(&self, user: &UserProfile) -> PixelleResult<()> {
+        let verification = self.accounts.create_email_verification(user).await?;
+        let template = EmailTemplate::VerifyEmail {
+            token: verification.token,
+            expires_at: verification.expires_at,
+        };
+        self.email.enqueue(user.id, &user.email, display_name(user), &template).await?;
+        Ok(())
+    }
+
+    /// Verify the email a verification link was sent to, which qualifies
+    /// the user's referral for its reward
+    pub async fn verify_email(&self, token: &str) -> PixelleResult<UserId> {
+        let user_id = self.accounts.verify_email(token).await?;
+        self.audit.record(AuditEntry::new(AuditAction::EmailVerified, Some(user_id))).await;
+        if let Err(e) = self.invites.qualify(user_id).await {
+            tracing::warn!("Failed to qualify the referral of {}: {}", user_id, e);
+        }
+        Ok(user_id)
+    }
+
+    pub async fn is_email_verified(&self, user: &UserProfile) -> PixelleResult<bool> {
+        self.accounts.is_email_verified(user).await
+    }
+
+    /// Authenticate behind login protection and the challenge gate; the
+    /// attempt's account defaults to `username`
+    ///
+    /// Failures counted by the protection also count towards the gate's
+    /// limit. Protection that cannot reach its store lets logins through.
+    pub async fn login(
+        &self,
+        username: &str,
+        password: &str,
+        attempt: &AuthAttempt,
+    ) -> Result<Option<UserProfile>, ChallengeError> {
+        let mut attempt = AuthAttempt {
+            account: attempt.account.clone().or_else(|| Some(username.to_string())),
+            ..attempt.clone()
+        };
+        let account = self.accounts.find_account(username).await?;
+        let account_id = account.as_ref().map(|(profile, _)| profile.id);
+        // Existing accounts are counted under their ID, whichever name they sign in with
+        let account_key = match account_id {
+            Some(user_id) => user_id.to_string(),
+            None => username.trim().to_lowercase(),
+        };
+        let audit = |action: AuditAction, reason: &str| {
+            AuditEntry::new(action, account_id)
+                .ip(attempt.ip.clone())
+                .details(serde_json::json!({ "username": username, "reason": reason }))
+        };
+
+        if let Some(protection) = &self.login_protection {
+            let ip = attempt.ip.as_deref();
+            let block = match protection.check(ip, &account_key).await {
+                Ok(Some(LoginBlock::Locked { until })) => Some(ChallengeError::Locked { until }),
+                Ok(Some(LoginBlock::Backoff { retry_after })) => {
+                    let retry_after_seconds = (retry_after.num_milliseconds() + 999) / 1000;
+                    Some(ChallengeError::Throttled { retry_after_seconds })
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    tracing::warn!("Login protection unavailable: {}", e);
+                    None
+                }
+            };
+            if let Some(block) = block {
+                self.audit.record(audit(AuditAction::LoginFailed, block.code())).await;
+                return Err(block);
+            }
+            match protection.failures(ip, &account_key).await {
+                Ok(failures) => attempt.prior_failures = Some(failures),
+                Err(e) => tracing::warn!("Login protection unavailable: {}", e),
+            }
+        }
+        if let Some(challenges) = &self.challenges {
+            if let Err(e) = challenges.check(AuthFlow::Login, &attempt).await {
+                self.audit.record(audit(AuditAction::LoginFailed, e.code())).await;
+                return Err(e);
+            }
+        }
+
+        let user = self.verify_credentials(account, password).await?;
+        match &user {
+            Some(_) => self.audit.record(audit(AuditAction::Login, "ok")).await,
+            None => self.audit.record(audit(AuditAction::LoginFailed, "invalid_credentials")).await,
+        }
+        if let Some(challenges) = &self.challenges {
+            match user {
+                Some(_) => challenges.record_login_success(&attempt),
+                None => challenges.record_login_failure(&attempt),
+            }
+        }
+        if let Some(protection) = &self.login_protection {
+            match &user {
+                Some(_) => {
+                    if let Err(e) = protection.record_success(&account_key).await {
+                        tracing::warn!("Login protection unavailable: {}", e);
+                    }
+                }
+                None => match protection.record_failure(attempt.ip.as_deref(), &account_key).await {
+                    Ok(outcome) => {
+                        if let (Some(until), Ok(user_id)) = (outcome.locked_until, account_key.parse::<UserId>()) {
+                            tracing::info!("Locked account {} after {} failed logins", user_id, outcome.account_failures);
+                            let entry = AuditEntry::new(AuditAction::AccountLocked, Some(user_id))
+                                .ip(attempt.ip.clone())
+                                .details(serde_json::json!({ "until": until, "failures": outcome.account_failures }));
+                            self.audit.record(entry).await;
+                            if let Err(e) = self.send_unlock(user_id, until).await {
+                                tracing::warn!("Failed to queue the unlock email of {}: {}", user_id, e);
+                            }
+                        }
+                    }
+                    Err(e) => tracing::warn!("Login protection unavailable: {}", e),
+                },
+            }
+        }
+        Ok(user)
+    }
+
+    async fn send_unlock(&self, user_id: UserId, until: chrono::DateTime<chrono::Utc>) -> PixelleResult<()> {
+        let Some(user) = self.accounts.get_profile(user_id).await? else {
+            return Ok(());
+        };
+        let token = self.accounts.create_account_unlock(user_id, until).await?;
+        let template = EmailTemplate::AccountLocked { token, until };
+        self.email.enqueue(user.id, &user.email, display_name(&user), &template).await?;
+        Ok(())
+    }
+
+    /// Lift a login lockout with the token from an unlock email
+    pub async fn unlock_account(&self, token: &str) -> PixelleResult<UserId> {
+        let user_id = self.accounts.consume_account_unlock(token).await?;
+        if let Some(protection) = &self.login_protection {
+            protection.unlock(&user_id.to_string()).await?;
+        }
+        self.audit.record(AuditEntry::new(AuditAction::AccountUnlocked, Some(user_id))).await;
+        Ok(user_id)
+    }
+
+    async fn verify_credentials(
+        &self,
+        account: Option<(UserProfile, Credentials)>,
+        password: &str,
+    ) -> PixelleResult<Option<UserProfile>> {
+        let Some((profile, credentials)) = account else {
+            self.passphrase_service.verify_absent_account(password).await?;
+            return Ok(None);
+        };
+        let verified = self.passphrase_service.verify_passphrase(password, &credentials.password_hash).await?;
+        Ok(verified.then_some(profile))
+    }
+
+    /// Email a password reset link behind the challenge gate, if an account
+    /// has the email; callers should answer the same either way
+    pub async fn request_password_reset(&self, email: &str, attempt: &AuthAttempt) -> Result<(), ChallengeError> {
+        let attempt = AuthAttempt {
+            account: attempt.account.clone().or_else(|| Some(email.to_string())),
+            ..attempt.clone()
+        };
+        if let Some(challenges) = &self.challenges {
+            challenges.check(AuthFlow::PasswordReset, &attempt).await?;
+        }
+        let Some(reset) = self.accounts.create_password_reset(email).await? else {
+            return Ok(());
+        };
+        let template = EmailTemplate::PasswordReset {
+            token: reset.token,
+            expires_at: reset.expires_at,
+        };
+        self.email.enqueue(reset.user.id, &reset.user.email, display_name(&reset.user), &template).await?;
+        Ok(())
+    }
+
+    /// Set a new password with a reset token, ending every session of the
+    /// account and lifting any login lockout
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> PixelleResult<UserId> {
+        validate_password(new_password)?;
+        let password_hash = self.passphrase_service.hash_passphrase(new_password).await?;
+        let (user_id, mut transaction) = self.accounts.begin_password_reset(token, password_hash).await?;
+        let session_ids = self.session_service.revoke_user_sessions(user_id, &mut transaction).await?;
+        transaction.commit().await.map_err(store_error)?;
+
+        self.revoke_tokens(Some(user_id), &session_ids).await?;
+        if let Some(protection) = &self.login_protection {
+            if let Err(e) = protection.unlock(&user_id.to_string()).await {
+                tracing::warn!("Failed to lift the lockout of {}: {}", user_id, e);
+            }
+        }
+        let entry = AuditEntry::new(AuditAction::PasswordChanged, Some(user_id))
+            .details(serde_json::json!({ "via": "password_reset" }));
+        self.audit.record(entry).await;
+        Ok(user_id)
+    }
+
+    /// Start a session for a signed-in device and issue its first access
+    /// token, which carries the user's roles
+    ///
+    /// The user is emailed when the device is new to an account that is
+    /// signed in elsewhere.
+    pub async fn sign_in(&self, user_id: UserId, device: SessionDevice, ip: Option<String>) -> PixelleResult<String> {
+        let roles = self.roles.roles_of(user_id).await?;
+        let sessions = self.session_service.list_sessions(user_id).await?;
+        let session = self.session_service.create_session(user_id, device, ip).await?;
+        if !sessions.is_empty() && !sessions.iter().any(|known| same_device(known, &session)) {
+            if let Err(e) = self.notify_sign_in(&session).await {
+                tracing::warn!("Failed to queue the sign-in notice of {}: {}", user_id, e);
+            }
         }
+        self.jwt_service.issue(user_id, &session.id, &roles)
+    }
+
+    async fn notify_sign_in(&self, session: &Session) -> PixelleResult<()> {
+        let Some(user) = self.accounts.get_profile(session.user_id).await? else {
+            return Ok(());
+        };
+        let template = EmailTemplate::SuspiciousLogin {
+            device: session.device.clone(),
+            ip: session.ip.clone(),
+            at: session.created_at,
+        };
+        self.email.enqueue(user.id, &user.email, display_name(&user), &template).await?;
+        Ok(())
+    }
+
+    /// Record a use of the session from `ip`, or `None` if it has ended
+    pub async fn session_seen(&self, session_id: &str, ip: Option<&str>) -> PixelleResult<Option<Session>> {
+        match self.session_service.get_session(session_id).await? {
+            Some(session) => Ok(Some(self.session_service.touch_session(session, ip).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Active sessions of a user, most recently used first
+    pub async fn list_sessions(&self, user_id: UserId) -> PixelleResult<Vec<Session>> {
+        self.session_service.list_sessions(user_id).await
+    }
+
+    /// End one session of a user, e.g. a lost device, and refuse its access
+    /// tokens; sessions of other users are reported as not found
+    pub async fn revoke_user_session(&self, user_id: UserId, session_id: &str) -> PixelleResult<()> {
+        let not_found = || PixelleError::NotFound(format!("Session {} not found", session_id));
+        let session = self.session_service.get_session(session_id).await?.ok_or_else(not_found)?;
+        if session.user_id != user_id {
+            return Err(not_found());
+        }
+        self.session_service.revoke_session(session_id).await?;
+
+        self.revoke_tokens(None, &[session_id.to_string()]).await?;
+        let entry = AuditEntry::new(AuditAction::SessionRevoked, Some(user_id))
+            .details(serde_json::json!({ "session_id": session_id }));
+        self.audit.record(entry).await;
+        Ok(())
+    }
+
+    pub async fn create_api_key(&self, user_id: UserId, request: NewApiKey) -> PixelleResult<IssuedApiKey> {
+        let issued = self.api_keys.create_key(user_id, request).await?;
+        let entry = AuditEntry::new(AuditAction::ApiKeyCreated, Some(user_id))
+            .details(serde_json::json!({ "key_id": issued.key.id, "scopes": issued.key.scopes }));
+        self.audit.record(entry).await;
+        Ok(issued)
+    }
+
+    pub async fn list_api_keys(&self, user_id: UserId) -> PixelleResult<Vec<ApiKey>> {
+        self.api_keys.list_keys(user_id).await
+    }
+
+    /// Delete an API key of a user; services stop accepting it once their
+    /// cached lookup expires
+    pub async fn revoke_api_key(&self, user_id: UserId, key_id: &str) -> PixelleResult<()> {
+        self.api_keys.revoke_key(user_id, key_id).await?;
+        let entry = AuditEntry::new(AuditAction::ApiKeyRevoked, Some(user_id))
+            .details(serde_json::json!({ "key_id": key_id }));
+        self.audit.record(entry).await;
+        Ok(())
+    }
+
+    /// Grant of a full API key stored in this region, for services to cache
+    pub async fn verify_api_key(&self, secret: &str) -> PixelleResult<Option<ApiKeyGrant>> {
+        Ok(self.api_keys.verify_key(secret).await?.map(|grant| ApiKeyGrant {
+            region: self.jwt_service.region().to_string(),
+            ..grant
+        }))
+    }
+
+    /// Client-credentials grant: a service token and its scopes for an
+    /// internal service
+    pub fn issue_service_token(&self, client_id: &str, secret: &str, scope: Option<&str>) -> PixelleResult<(String, Vec<String>)> {
+        let scopes = self.service_clients.authenticate(client_id, secret, scope)?;
+        let token = self.jwt_service.issue_service_token(client_id, scopes.clone())?;
+        Ok((token, scopes))
+    }
+
+    /// Roles of a user; the default role if none were assigned
+    pub async fn get_roles(&self, user_id: UserId) -> PixelleResult<Vec<Role>> {
+        self.roles.roles_of(user_id).await
+    }
+
+    pub async fn role_members(&self, role: Role) -> PixelleResult<Vec<RoleAssignment>> {
+        self.roles.members(role).await
+    }
+
+    /// Replace the roles of a user on behalf of `actor`, who needs
+    /// `roles:manage`
+    ///
+    /// Access tokens carry roles, so a user who loses one is signed out
+    /// everywhere rather than keeping it until their tokens expire; added
+    /// roles apply from the next sign-in.
+    pub async fn assign_roles(&self, actor: &Subject, user_id: UserId, roles: Vec<Role>) -> PixelleResult<RoleAssignment> {
+        let previous = self.roles.roles_of(user_id).await?;
+        let assignment = self.roles.assign(actor, user_id, roles).await?;
+        let entry = AuditEntry::new(AuditAction::RoleChanged, Some(user_id))
+            .actor(actor.user_id)
+            .details(serde_json::json!({ "previous": previous, "roles": assignment.roles }));
+        self.audit.record(entry).await;
+        if previous.iter().any(|role| !assignment.roles.contains(role)) {
+            self.revoke_all_sessions(user_id).await?;
+        }
+        Ok(assignment)
+    }
+
+    /// Make these users admins if they are not yet, e.g. from
+    /// `AUTH_ADMIN_USER_IDS` at startup
+    pub async fn bootstrap_admins(&self, user_ids: &[UserId]) -> PixelleResult<()> {
+        for &user_id in user_ids {
+            if self.roles.bootstrap_admin(user_id).await? {
+                tracing::info!("Made {} an admin", user_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Refuse the access tokens of these sessions and, with `user_id`, every
+    /// token the user was issued before now
+    ///
+    /// Recorded in the shared store so every instance publishes it, and in
+    /// the local list so this instance refuses the tokens right away.
+    async fn revoke_tokens(&self, user_id: Option<UserId>, session_ids: &[String]) -> PixelleResult<()> {
+        let now = chrono::Utc::now();
+        {
+            let mut local = self.jwt_service.revocations().write().unwrap();
+            for session_id in session_ids {
+                local.revoke_session(session_id, now);
+            }
+            if let Some(user_id) = user_id {
+                local.revoke_user(&user_id.to_string(), now);
+            }
+        }
+        for session_id in session_ids {
+            self.revocations.record(RevocationKind::Session, session_id, now).await.map_err(store_error)?;
+        }
+        if let Some(user_id) = user_id {
+            self.revocations.record(RevocationKind::User, &user_id.to_string(), now).await.map_err(store_error)?;
+        }
+        Ok(())
+    }
+
+    /// Revocations every instance of this region publishes, merged into the
+    /// local list tokens are verified against
+    pub async fn refresh_revocations(&self) -> PixelleResult<RevocationList> {
+        let now = chrono::Utc::now();
+        let stored = self.revocations.load(now).await.map_err(store_error)?;
+        let mut local = self.jwt_service.revocations().write().unwrap();
+        local.merge(&stored);
+        local.prune(now);
+        Ok(local.clone())
+    }
+
+    /// Delete stored revocations no unexpired token can match
+    pub async fn prune_revocations(&self) -> PixelleResult<usize> {
+        self.revocations.prune(chrono::Utc::now()).await.map_err(store_error)
+    }
+
+    /// End every session of a user and refuse the access tokens already issued
+    pub async fn revoke_all_sessions(&self, user_id: UserId) -> PixelleResult<()> {
+        let mut transaction = self.session_service.transaction()?;
+        let session_ids = self.session_service.revoke_user_sessions(user_id, &mut transaction).await?;
+        transaction.commit().await.map_err(store_error)?;
+
+        self.revoke_tokens(Some(user_id), &session_ids).await?;
+        self.audit.record(AuditEntry::new(AuditAction::SessionsRevoked, Some(user_id))).await;
+        Ok(())
+    }
+}
+
+/// Name emails greet the user with
+fn display_name(user: &UserProfile) -> &str {
+    user.display_name.as_deref().filter(|name| !name.trim().is_empty()).unwrap_or(&user.username)
+}
+
+/// Whether two sessions were signed in from the same device: by fingerprint
+/// when the new one has one, otherwise by user agent and IP
+fn same_device(known: &Session, new: &Session) -> bool {
+    match &new.device.fingerprint {
+        Some(fingerprint) => known.device.fingerprint.as_ref() == Some(fingerprint),
+        None => known.device.user_agent == new.device.user_agent && known.ip == new.ip,
+    }
+}
+
+fn validate_password(password: &str) -> PixelleResult<()> {
+    let length = password.chars().count();
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&length) {
+        return Err(PixelleError::Validation(format!(
+            "Passwords are {} to {} characters",
+            MIN_PASSWORD_LENGTH, MAX_PASSWORD_LENGTH
+        )));
     }
+    Ok(())
 }
 
 #[async_trait]
 impl AuthService for AuthServiceImpl {
     async fn authenticate_user(&self, username: &str, password: &str) -> PixelleResult<Option<UserProfile>> {
-        // This would typically query the database
-        // For now, we'll return None to indicate authentication failure
-        Ok(None)
+        let account = self.accounts.find_account(username).await?;
+        self.verify_credentials(account, password).await
     }
 
     async fn create_session(&self, user_id: UserId) -> PixelleResult<String> {
-        self.jwt_service.create_token(user_id).await
+        self.sign_in(user_id, SessionDevice::default(), None).await
     }
 
     async fn validate_session(&self, session_token: &str) -> PixelleResult<Option<UserId>> {
-        self.jwt_service.validate_token(session_token).await
+        let Ok(claims) = self.jwt_service.verify(session_token) else {
+            return Ok(None);
+        };
+        Ok(self.session_seen(&claims.sid, None).await?.map(|session| session.user_id))
     }
 
     async fn revoke_session(&self, session_token: &str) -> PixelleResult<()> {
-        self.session_service.revoke_session(session_token).await
+        let claims = self.jwt_service.revoke_token(session_token)?;
+        self.session_service.revoke_session(&claims.sid).await?;
+        self.revoke_tokens(None, std::slice::from_ref(&claims.sid)).await?;
+        let entry = AuditEntry::new(AuditAction::Logout, claims.user_id().ok())
+            .details(serde_json::json!({ "session_id": claims.sid }));
+        self.audit.record(entry).await;
+        Ok(())
     }
 
-    async fn hash_passphrase(&self, passphrase: &str) -> PixelleResult<String> {
-        self.passphrase_service.hash_password(passphrase).await
+    async fn hash_password(&self, password: &str) -> PixelleResult<String> {
+        self.passphrase_service.hash_passphrase(password).await
     }
 
-    async fn verify_passphrase(&self, passphrase: &str, hash: &str) -> PixelleResult<bool> {
-        self.passphrase_service.verify_password(passphrase, hash).await
+    async fn verify_password(&self, password: &str, hash: &str) -> PixelleResult<bool> {
+        self.passphrase_service.verify_passphrase(password, hash).await
     }
 }
@@ -0,0 +1,439 @@
+use crate::config::{ChallengeConfig, ChallengeProvider, FlowThresholds};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use pixelle_core::{PixelleError, PixelleResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+const RECAPTCHA_VERIFY_URL: &str = "https://www.google.com/recaptcha/api/siteverify";
+const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+
+/// Auth flows that can ask for a challenge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthFlow {
+    Register,
+    Login,
+    PasswordReset,
+}
+
+impl AuthFlow {
+    /// Action the widget is rendered with; reCAPTCHA v3 and Turnstile echo
+    /// it back so a token solved for one flow cannot be used for another
+    pub fn action(&self) -> &'static str {
+        match self {
+            AuthFlow::Register => "register",
+            AuthFlow::Login => "login",
+            AuthFlow::PasswordReset => "password_reset",
+        }
+    }
+}
+
+/// Who is attempting a flow, as far as the caller knows
+#[derive(Debug, Clone, Default)]
+pub struct AuthAttempt {
+    pub ip: Option<String>,
+    /// Username or email the attempt is for
+    pub account: Option<String>,
+    /// From 0 (trusted) to 1, from whatever risk scoring the caller has
+    pub risk_score: Option<f32>,
+    /// Failures counted outside the gate, e.g. by `LoginProtection` across
+    /// instances; weighed like the gate's own attempt counts
+    pub prior_failures: Option<u32>,
+    /// Challenge response the client sent along, if any
+    pub challenge_token: Option<String>,
+}
+
+/// A provider's answer about a challenge response
+#[derive(Debug, Clone, Default)]
+pub struct ChallengeVerdict {
+    pub success: bool,
+    /// reCAPTCHA v3 score, 1 being most likely human
+    pub score: Option<f32>,
+    pub error_codes: Vec<String>,
+}
+
+/// Checks challenge responses with the provider that issued them
+#[async_trait]
+pub trait ChallengeVerifier: Send + Sync {
+    fn provider(&self) -> ChallengeProvider;
+    async fn verify(&self, token: &str, remote_ip: Option<&str>, flow: AuthFlow) -> PixelleResult<ChallengeVerdict>;
+}
+
+/// Answer of the siteverify endpoint all three providers implement
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default)]
+    score: Option<f32>,
+    #[serde(default)]
+    action: Option<String>,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+impl SiteVerifyResponse {
+    /// Also fail answers solved for another flow, and v3 answers scoring
+    /// under `min_score`
+    fn verdict(mut self, flow: AuthFlow, min_score: Option<f32>) -> ChallengeVerdict {
+        let mut success = self.success;
+        if self.action.as_deref().is_some_and(|action| !action.is_empty() && action != flow.action()) {
+            success = false;
+            self.error_codes.push("action-mismatch".to_string());
+        }
+        if let (Some(score), Some(min_score)) = (self.score, min_score) {
+            if score < min_score {
+                success = false;
+                self.error_codes.push("score-too-low".to_string());
+            }
+        }
+        ChallengeVerdict { success, score: self.score, error_codes: self.error_codes }
+    }
+}
+
+async fn site_verify(client: &reqwest::Client, url: &str, form: &[(&str, &str)]) -> PixelleResult<SiteVerifyResponse> {
+    client
+        .post(url)
+        .form(form)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| PixelleError::ExternalService(format!("{}: {}", url, e)))?
+        .json()
+        .await
+        .map_err(|e| PixelleError::ExternalService(format!("Invalid answer from {}: {}", url, e)))
+}
+
+fn verify_form<'a>(secret: &'a str, token: &'a str, remote_ip: Option<&'a str>) -> Vec<(&'a str, &'a str)> {
+    let mut form = vec![("secret", secret), ("response", token)];
+    if let Some(remote_ip) = remote_ip {
+        form.push(("remoteip", remote_ip));
+    }
+    form
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap_or_default()
+}
+
+/// hCaptcha, whose answers are also checked against the site key
+pub struct HCaptcha {
+    secret: String,
+    site_key: String,
+    client: reqwest::Client,
+}
+
+impl HCaptcha {
+    pub fn new(secret: impl Into<String>, site_key: impl Into<String>) -> Self {
+        Self { secret: secret.into(), site_key: site_key.into(), client: http_client() }
+    }
+}
+
+#[async_trait]
+impl ChallengeVerifier for HCaptcha {
+    fn provider(&self) -> ChallengeProvider {
+        ChallengeProvider::HCaptcha
+    }
+
+    async fn verify(&self, token: &str, remote_ip: Option<&str>, flow: AuthFlow) -> PixelleResult<ChallengeVerdict> {
+        let mut form = verify_form(&self.secret, token, remote_ip);
+        form.push(("sitekey", &self.site_key));
+        Ok(site_verify(&self.client, HCAPTCHA_VERIFY_URL, &form).await?.verdict(flow, None))
+    }
+}
+
+/// reCAPTCHA v2 or v3; v3 answers must also score at least `min_score`
+pub struct ReCaptcha {
+    secret: String,
+    min_score: f32,
+    client: reqwest::Client,
+}
+
+impl ReCaptcha {
+    pub fn new(secret: impl Into<String>, min_score: f32) -> Self {
+        Self { secret: secret.into(), min_score, client: http_client() }
+    }
+}
+
+#[async_trait]
+impl ChallengeVerifier for ReCaptcha {
+    fn provider(&self) -> ChallengeProvider {
+        ChallengeProvider::ReCaptcha
+    }
+
+    async fn verify(&self, token: &str, remote_ip: Option<&str>, flow: AuthFlow) -> PixelleResult<ChallengeVerdict> {
+        let form = verify_form(&self.secret, token, remote_ip);
+        Ok(site_verify(&self.client, RECAPTCHA_VERIFY_URL, &form).await?.verdict(flow, Some(self.min_score)))
+    }
+}
+
+/// Cloudflare Turnstile
+pub struct Turnstile {
+    secret: String,
+    client: reqwest::Client,
+}
+
+impl Turnstile {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self { secret: secret.into(), client: http_client() }
+    }
+}
+
+#[async_trait]
+impl ChallengeVerifier for Turnstile {
+    fn provider(&self) -> ChallengeProvider {
+        ChallengeProvider::Turnstile
+    }
+
+    async fn verify(&self, token: &str, remote_ip: Option<&str>, flow: AuthFlow) -> PixelleResult<ChallengeVerdict> {
+        let form = verify_form(&self.secret, token, remote_ip);
+        Ok(site_verify(&self.client, TURNSTILE_VERIFY_URL, &form).await?.verdict(flow, None))
+    }
+}
+
+/// Adapter of the configured provider, if any
+pub fn challenge_verifier(config: &ChallengeConfig) -> Option<Arc<dyn ChallengeVerifier>> {
+    let verifier: Arc<dyn ChallengeVerifier> = match config.provider? {
+        ChallengeProvider::HCaptcha => Arc::new(HCaptcha::new(&config.secret, &config.site_key)),
+        ChallengeProvider::ReCaptcha => Arc::new(ReCaptcha::new(&config.secret, config.min_score)),
+        ChallengeProvider::Turnstile => Arc::new(Turnstile::new(&config.secret)),
+    };
+    Some(verifier)
+}
+
+/// Why an attempt has to carry a challenge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengeReason {
+    RiskScore,
+    TooManyAttempts,
+}
+
+/// Which widget the client has to solve before retrying
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengeRequired {
+    pub provider: ChallengeProvider,
+    pub site_key: String,
+    pub flow: AuthFlow,
+    pub reason: ChallengeReason,
+}
+
+/// A gated auth flow that did not go through
+#[derive(Debug, thiserror::Error)]
+pub enum ChallengeError {
+    #[error("Solve a challenge to {}", .0.flow.action())]
+    Required(ChallengeRequired),
+
+    #[error("Challenge verification failed: {}", .error_codes.join(", "))]
+    Failed { challenge: ChallengeRequired, error_codes: Vec<String> },
+
+    #[error("Challenge provider unavailable: {0}")]
+    Unavailable(PixelleError),
+
+    /// Too many failed logins; retry after the wait
+    #[error("Too many failed attempts, retry in {retry_after_seconds} seconds")]
+    Throttled { retry_after_seconds: i64 },
+
+    /// Locked after repeated failed logins, until the time or an unlock link
+    #[error("Account locked until {until}; follow the link sent by email to unlock it")]
+    Locked { until: DateTime<Utc> },
+
+    /// The flow itself failed after passing the gate
+    #[error(transparent)]
+    Auth(#[from] PixelleError),
+}
+
+impl ChallengeError {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ChallengeError::Required(_) => 428,
+            ChallengeError::Failed { .. } => 403,
+            ChallengeError::Unavailable(_) => 503,
+            ChallengeError::Throttled { .. } => 429,
+            ChallengeError::Locked { .. } => 423,
+            ChallengeError::Auth(e) => e.status_code(),
+        }
+    }
+
+    /// Seconds to send as `Retry-After`
+    pub fn retry_after(&self) -> Option<i64> {
+        match self {
+            ChallengeError::Throttled { retry_after_seconds } => Some(*retry_after_seconds),
+            ChallengeError::Locked { until } => Some((*until - Utc::now()).num_seconds().max(1)),
+            _ => None,
+        }
+    }
+
+    /// Machine-readable code of the error body
+    pub fn code(&self) -> &'static str {
+        match self {
+            ChallengeError::Required(_) => "challenge_required",
+            ChallengeError::Failed { .. } => "challenge_failed",
+            ChallengeError::Unavailable(_) => "challenge_unavailable",
+            ChallengeError::Throttled { .. } => "too_many_attempts",
+            ChallengeError::Locked { .. } => "account_locked",
+            ChallengeError::Auth(_) => "auth_failed",
+        }
+    }
+
+    /// Error body telling the client which challenge to solve
+    pub fn body(&self) -> serde_json::Value {
+        let challenge = match self {
+            ChallengeError::Required(challenge) | ChallengeError::Failed { challenge, .. } => Some(challenge),
+            _ => None,
+        };
+        serde_json::json!({ "error": self.to_string(), "code": self.code(), "challenge": challenge })
+    }
+}
+
+/// Attempts counted for one flow and client IP or account
+struct AttemptWindow {
+    started_at: DateTime<Utc>,
+    count: u32,
+}
+
+/// Decides when registration, login and password reset need a challenge,
+/// and verifies the answers
+///
+/// Attempts are counted per client IP and per account in fixed windows held
+/// in memory, so each auth-service instance counts its own.
+pub struct ChallengeGate {
+    config: ChallengeConfig,
+    verifier: Option<Arc<dyn ChallengeVerifier>>,
+    attempts: Mutex<HashMap<(AuthFlow, String), AttemptWindow>>,
+}
+
+impl ChallengeGate {
+    /// Verify with the adapter of the configured provider
+    pub fn new(config: ChallengeConfig) -> Self {
+        let verifier = challenge_verifier(&config);
+        Self::with_verifier(config, verifier)
+    }
+
+    pub fn with_verifier(config: ChallengeConfig, verifier: Option<Arc<dyn ChallengeVerifier>>) -> Self {
+        Self { config, verifier, attempts: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(ChallengeConfig::from_env())
+    }
+
+    pub fn config(&self) -> &ChallengeConfig {
+        &self.config
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.verifier.is_some()
+    }
+
+    fn thresholds(&self, flow: AuthFlow) -> FlowThresholds {
+        match flow {
+            AuthFlow::Register => self.config.register,
+            AuthFlow::Login => self.config.login,
+            AuthFlow::PasswordReset => self.config.password_reset,
+        }
+    }
+
+    /// Keys an attempt is counted under
+    fn keys(attempt: &AuthAttempt) -> Vec<String> {
+        let ip = attempt.ip.iter().map(|ip| format!("ip:{}", ip));
+        let account = attempt.account.iter().map(|account| format!("account:{}", account.to_lowercase()));
+        ip.chain(account).collect()
+    }
+
+    fn window_open(&self, window: &AttemptWindow, now: DateTime<Utc>) -> bool {
+        now - window.started_at < chrono::Duration::seconds(self.config.window_seconds)
+    }
+
+    /// Why `attempt` has to carry a challenge, if it has to
+    pub fn requirement(&self, flow: AuthFlow, attempt: &AuthAttempt, now: DateTime<Utc>) -> Option<ChallengeReason> {
+        self.verifier.as_ref()?;
+        let thresholds = self.thresholds(flow);
+        if attempt.risk_score.is_some_and(|score| score >= thresholds.risk_score) {
+            return Some(ChallengeReason::RiskScore);
+        }
+        if thresholds.attempts == 0 {
+            return None;
+        }
+        if attempt.prior_failures.is_some_and(|failures| failures >= thresholds.attempts) {
+            return Some(ChallengeReason::TooManyAttempts);
+        }
+        let attempts = self.attempts.lock().unwrap();
+        Self::keys(attempt)
+            .into_iter()
+            .filter_map(|key| attempts.get(&(flow, key)))
+            .any(|window| self.window_open(window, now) && window.count >= thresholds.attempts)
+            .then_some(ChallengeReason::TooManyAttempts)
+    }
+
+    /// Let an attempt of `flow` through, or say which challenge it needs
+    ///
+    /// Registrations and password resets count towards their limit here,
+    /// logins through `record_login_failure`.
+    pub async fn check(&self, flow: AuthFlow, attempt: &AuthAttempt) -> Result<(), ChallengeError> {
+        let now = Utc::now();
+        let reason = self.requirement(flow, attempt, now);
+        if flow != AuthFlow::Login {
+            self.count(flow, attempt, now);
+        }
+        let (Some(reason), Some(verifier)) = (reason, &self.verifier) else {
+            return Ok(());
+        };
+
+        let challenge = ChallengeRequired {
+            provider: verifier.provider(),
+            site_key: self.config.site_key.clone(),
+            flow,
+            reason,
+        };
+        let Some(token) = attempt.challenge_token.as_deref().filter(|token| !token.is_empty()) else {
+            return Err(ChallengeError::Required(challenge));
+        };
+        match verifier.verify(token, attempt.ip.as_deref(), flow).await {
+            Ok(verdict) if verdict.success => Ok(()),
+            Ok(verdict) => Err(ChallengeError::Failed { challenge, error_codes: verdict.error_codes }),
+            Err(e) if self.config.fail_open => {
+                tracing::warn!("Letting a {} attempt through unchallenged: {}", flow.action(), e);
+                Ok(())
+            }
+            Err(e) => Err(ChallengeError::Unavailable(e)),
+        }
+    }
+
+    pub fn record_login_failure(&self, attempt: &AuthAttempt) {
+        self.count(AuthFlow::Login, attempt, Utc::now());
+    }
+
+    /// Forget the account's failed logins; its IP's stay, as one IP may be
+    /// trying many accounts
+    pub fn record_login_success(&self, attempt: &AuthAttempt) {
+        if let Some(account) = &attempt.account {
+            self.attempts
+                .lock()
+                .unwrap()
+                .remove(&(AuthFlow::Login, format!("account:{}", account.to_lowercase())));
+        }
+    }
+
+    fn count(&self, flow: AuthFlow, attempt: &AuthAttempt, now: DateTime<Utc>) {
+        let mut attempts = self.attempts.lock().unwrap();
+        for key in Self::keys(attempt) {
+            let window = attempts.entry((flow, key)).or_insert(AttemptWindow { started_at: now, count: 0 });
+            if !self.window_open(window, now) {
+                *window = AttemptWindow { started_at: now, count: 0 };
+            }
+            window.count += 1;
+        }
+    }
+
+    /// Drop windows that have ended
+    pub fn prune(&self, now: DateTime<Utc>) {
+        self.attempts.lock().unwrap().retain(|_, window| self.window_open(window, now));
+    }
+}
@@ -0,0 +1,176 @@
+use pixelle_core::{PixelleError, PixelleResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::accounts::token_key;
+
+/// Service tokens are renewed this long before they expire
+const SERVICE_TOKEN_RENEW_AHEAD: Duration = Duration::from_secs(60);
+
+/// An internal service allowed to use the client-credentials grant
+#[derive(Debug, Clone)]
+pub struct ServiceClient {
+    pub id: String,
+    secret_hash: String,
+    /// Scopes its tokens may carry
+    pub scopes: Vec<String>,
+}
+
+impl ServiceClient {
+    pub fn new(id: impl Into<String>, secret: &str, scopes: Vec<String>) -> Self {
+        Self {
+            id: id.into(),
+            secret_hash: token_key(secret),
+            scopes,
+        }
+    }
+}
+
+/// Internal services auth-service issues service tokens to
+pub struct ServiceClients {
+    clients: HashMap<String, ServiceClient>,
+}
+
+impl ServiceClients {
+    pub fn new(clients: Vec<ServiceClient>) -> Self {
+        Self {
+            clients: clients.into_iter().map(|client| (client.id.clone(), client)).collect(),
+        }
+    }
+
+    /// Read the clients listed in `AUTH_CLIENTS`, e.g. `feed-service`, each
+    /// with `AUTH_CLIENT_<ID>_SECRET` and space-separated
+    /// `AUTH_CLIENT_<ID>_SCOPES`; the ID is uppercased with `-` as `_`
+    pub fn from_env() -> Self {
+        let ids = env::var("AUTH_CLIENTS").unwrap_or_default();
+        let mut clients = Vec::new();
+        for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+            let prefix = format!("AUTH_CLIENT_{}", id.to_ascii_uppercase().replace('-', "_"));
+            let Some(secret) = env::var(format!("{}_SECRET", prefix)).ok().filter(|secret| !secret.is_empty()) else {
+                tracing::warn!("{}_SECRET not set; {} cannot get service tokens", prefix, id);
+                continue;
+            };
+            let scopes = env::var(format!("{}_SCOPES", prefix))
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            clients.push(ServiceClient::new(id, &secret, scopes));
+        }
+        Self::new(clients)
+    }
+
+    /// Scopes to grant a client that presented `secret`: the requested ones,
+    /// space-separated, or all of its scopes when none were requested
+    pub fn authenticate(&self, client_id: &str, secret: &str, requested: Option<&str>) -> PixelleResult<Vec<String>> {
+        let client = self
+            .clients
+            .get(client_id)
+            .filter(|client| client.secret_hash == token_key(secret))
+            .ok_or_else(|| PixelleError::Authentication("Invalid client credentials".to_string()))?;
+
+        let Some(requested) = requested.filter(|requested| !requested.trim().is_empty()) else {
+            return Ok(client.scopes.clone());
+        };
+        let mut scopes = Vec::new();
+        for scope in requested.split_whitespace() {
+            if !client.scopes.iter().any(|allowed| allowed == scope) {
+                return Err(PixelleError::Authorization(format!("{} may not request {}", client_id, scope)));
+            }
+            scopes.push(scope.to_string());
+        }
+        Ok(scopes)
+    }
+}
+
+/// Token response of the client-credentials grant
+#[derive(Debug, Deserialize)]
+struct ServiceTokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// Gets service tokens from auth-service for calls to other services, and
+/// renews them before they expire
+pub struct ServiceCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scopes: Option<String>,
+    client: reqwest::Client,
+    /// Current token and when to renew it
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl ServiceCredentials {
+    pub fn new(auth_service_url: &str, client_id: impl Into<String>, client_secret: impl Into<String>) -> Self {
+        Self {
+            token_url: format!("{}/api/v1/auth/token", auth_service_url.trim_end_matches('/')),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scopes: None,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Ask for these scopes rather than every scope the client has
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = Some(scopes.join(" "));
+        self
+    }
+
+    /// Credentials from `AUTH_CLIENT_ID` and `AUTH_CLIENT_SECRET`, or `None`
+    /// when the service has none
+    pub fn from_env(auth_service_url: &str) -> Option<Self> {
+        let client_id = env::var("AUTH_CLIENT_ID").ok().filter(|id| !id.is_empty())?;
+        let client_secret = env::var("AUTH_CLIENT_SECRET").ok().filter(|secret| !secret.is_empty())?;
+        Some(Self::new(auth_service_url, client_id, client_secret))
+    }
+
+    /// A service token that is valid for at least another minute
+    pub async fn token(&self) -> PixelleResult<String> {
+        let mut token = self.token.lock().await;
+        if let Some((current, renew_at)) = token.as_ref() {
+            if Instant::now() < *renew_at {
+                return Ok(current.clone());
+            }
+        }
+
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+        ];
+        if let Some(scopes) = &self.scopes {
+            form.push(("scope", scopes));
+        }
+        let response: ServiceTokenResponse = self
+            .client
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PixelleError::ExternalService(format!("{}: {}", self.token_url, e)))?
+            .json()
+            .await
+            .map_err(|e| PixelleError::ExternalService(format!("Invalid token response from {}: {}", self.token_url, e)))?;
+
+        let lifetime = Duration::from_secs(response.expires_in);
+        let renew_at = Instant::now() + lifetime.saturating_sub(SERVICE_TOKEN_RENEW_AHEAD);
+        *token = Some((response.access_token.clone(), renew_at));
+        Ok(response.access_token)
+    }
+
+    /// Value for the `Authorization` header of a call to another service
+    pub async fn authorization(&self) -> PixelleResult<String> {
+        Ok(format!("Bearer {}", self.token().await?))
+    }
+}
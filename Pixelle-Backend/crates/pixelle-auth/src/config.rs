@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::str::FromStr;
+
+/// Service that issues and verifies human challenges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengeProvider {
+    HCaptcha,
+    ReCaptcha,
+    Turnstile,
+}
+
+impl FromStr for ChallengeProvider {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "hcaptcha" => Ok(ChallengeProvider::HCaptcha),
+            "recaptcha" => Ok(ChallengeProvider::ReCaptcha),
+            "turnstile" => Ok(ChallengeProvider::Turnstile),
+            other => Err(format!("Unknown challenge provider {}", other)),
+        }
+    }
+}
+
+/// When one auth flow asks for a challenge
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FlowThresholds {
+    /// Attempts counted for one client IP or account within the window
+    /// before every further attempt must carry a challenge; 0 never asks
+    /// for one on volume alone
+    pub attempts: u32,
+    /// Risk scores from 0 to 1 at or above this always need a challenge
+    pub risk_score: f32,
+}
+
+/// Challenges in front of registration, login and password reset
+///
+/// Registrations and password resets count every attempt, logins only the
+/// failed ones, so people who type their password right are never asked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengeConfig {
+    /// No provider turns challenges off
+    pub provider: Option<ChallengeProvider>,
+    /// Public key the client renders the widget with
+    pub site_key: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// reCAPTCHA v3 answers scoring below this fail verification
+    pub min_score: f32,
+    /// Let attempts through when the provider cannot be reached, instead of
+    /// refusing them
+    pub fail_open: bool,
+    pub window_seconds: i64,
+    pub register: FlowThresholds,
+    pub login: FlowThresholds,
+    pub password_reset: FlowThresholds,
+}
+
+impl Default for ChallengeConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            site_key: String::new(),
+            secret: String::new(),
+            min_score: 0.5,
+            fail_open: false,
+            window_seconds: 3600,
+            register: FlowThresholds { attempts: 3, risk_score: 0.7 },
+            login: FlowThresholds { attempts: 5, risk_score: 0.8 },
+            password_reset: FlowThresholds { attempts: 3, risk_score: 0.7 },
+        }
+    }
+}
+
+impl ChallengeConfig {
+    /// Read `AUTH_CHALLENGE_*`, keeping the defaults for unset or unparseable values
+    pub fn from_env() -> Self {
+        fn var<T: FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.trim().parse().ok())
+        }
+
+        let defaults = Self::default();
+        let thresholds = |flow: &str, default: FlowThresholds| FlowThresholds {
+            attempts: var(&format!("AUTH_CHALLENGE_{}_ATTEMPTS", flow)).unwrap_or(default.attempts),
+            risk_score: var(&format!("AUTH_CHALLENGE_{}_RISK_SCORE", flow)).unwrap_or(default.risk_score),
+        };
+        Self {
+            provider: var("AUTH_CHALLENGE_PROVIDER"),
+            site_key: env::var("AUTH_CHALLENGE_SITE_KEY").unwrap_or_default(),
+            secret: env::var("AUTH_CHALLENGE_SECRET").unwrap_or_default(),
+            min_score: var("AUTH_CHALLENGE_MIN_SCORE").unwrap_or(defaults.min_score),
+            fail_open: var("AUTH_CHALLENGE_FAIL_OPEN").unwrap_or(defaults.fail_open),
+            window_seconds: var("AUTH_CHALLENGE_WINDOW_SECONDS").unwrap_or(defaults.window_seconds),
+            register: thresholds("REGISTER", defaults.register),
+            login: thresholds("LOGIN", defaults.login),
+            password_reset: thresholds("PASSWORD_RESET", defaults.password_reset),
+        }
+    }
+}
+
+/// Transactional email sent through the outbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    /// Base URL of the app links in emails open, e.g.
+    /// `https://pixelle.app/verify-email?token=...`
+    pub app_url: String,
+    /// Sends tried before an email is given up on
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled after each further failure up
+    /// to an hour
+    pub retry_base_seconds: i64,
+    /// How often the outbox is checked for due emails
+    pub dispatch_interval_seconds: u64,
+    /// Sent and failed emails are deleted after this long
+    pub retention_days: i64,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            app_url: "https://pixelle.app".to_string(),
+            max_attempts: 8,
+            retry_base_seconds: 30,
+            dispatch_interval_seconds: 5,
+            retention_days: 7,
+        }
+    }
+}
+
+impl EmailConfig {
+    /// Read `AUTH_EMAIL_*`, keeping the defaults for unset or unparseable values
+    pub fn from_env() -> Self {
+        fn var<T: FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.trim().parse().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            app_url: env::var("AUTH_EMAIL_APP_URL")
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .unwrap_or(defaults.app_url),
+            max_attempts: var("AUTH_EMAIL_MAX_ATTEMPTS").unwrap_or(defaults.max_attempts).max(1),
+            retry_base_seconds: var("AUTH_EMAIL_RETRY_SECONDS").unwrap_or(defaults.retry_base_seconds),
+            dispatch_interval_seconds: var("AUTH_EMAIL_DISPATCH_INTERVAL_SECONDS")
+                .unwrap_or(defaults.dispatch_interval_seconds)
+                .max(1),
+            retention_days: var("AUTH_EMAIL_RETENTION_DAYS").unwrap_or(defaults.retention_days),
+        }
+    }
+}
+
+/// Backoff and lockout of failed logins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockoutConfig {
+    /// Failures per account within the window before attempts have to wait
+    pub account_free_failures: u32,
+    /// Failures per client IP within the window before attempts have to
+    /// wait; higher, as many people can share an IP
+    pub ip_free_failures: u32,
+    /// Wait after the first failure past the free ones, doubled after each
+    /// further failure
+    pub backoff_base_seconds: i64,
+    pub max_backoff_seconds: i64,
+    /// Failures per account within the window that lock it; 0 never locks
+    pub lockout_threshold: u32,
+    pub lockout_minutes: i64,
+    /// How long failures are counted for
+    pub window_seconds: i64,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            account_free_failures: 3,
+            ip_free_failures: 20,
+            backoff_base_seconds: 1,
+            max_backoff_seconds: 900,
+            lockout_threshold: 10,
+            lockout_minutes: 30,
+            window_seconds: 3600,
+        }
+    }
+}
+
+impl LockoutConfig {
+    /// Read `AUTH_LOCKOUT_*`, keeping the defaults for unset or unparseable values
+    pub fn from_env() -> Self {
+        fn var<T: FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.trim().parse().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            account_free_failures: var("AUTH_LOCKOUT_ACCOUNT_FREE_FAILURES").unwrap_or(defaults.account_free_failures),
+            ip_free_failures: var("AUTH_LOCKOUT_IP_FREE_FAILURES").unwrap_or(defaults.ip_free_failures),
+            backoff_base_seconds: var("AUTH_LOCKOUT_BACKOFF_SECONDS").unwrap_or(defaults.backoff_base_seconds),
+            max_backoff_seconds: var("AUTH_LOCKOUT_MAX_BACKOFF_SECONDS").unwrap_or(defaults.max_backoff_seconds),
+            lockout_threshold: var("AUTH_LOCKOUT_THRESHOLD").unwrap_or(defaults.lockout_threshold),
+            lockout_minutes: var("AUTH_LOCKOUT_MINUTES").unwrap_or(defaults.lockout_minutes),
+            window_seconds: var("AUTH_LOCKOUT_WINDOW_SECONDS").unwrap_or(defaults.window_seconds),
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials as SmtpCredentials;
+use lettre::{Message, SmtpTransport, Transport};
+use pixelle_core::{PixelleError, PixelleResult, UserId};
+use pixelle_database::{Backends, Capability, DocumentRepository, StoreQuery, StoreResult};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::Arc;
+
+use crate::accounts::{store_error, EMAIL_OUTBOX_REPOSITORY};
+use crate::config::EmailConfig;
+use crate::session::SessionDevice;
+
+/// Longest wait between two sends of one email
+const MAX_RETRY_DELAY_SECONDS: i64 = 3600;
+
+/// Emails auth-service sends about an account
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmailTemplate {
+    VerifyEmail { token: String, expires_at: DateTime<Utc> },
+    PasswordReset { token: String, expires_at: DateTime<Utc> },
+    /// Failed logins locked the account until `until`
+    AccountLocked { token: String, until: DateTime<Utc> },
+    /// A sign-in from a device none of the account's sessions use
+    SuspiciousLogin {
+        device: SessionDevice,
+        ip: Option<String>,
+        at: DateTime<Utc>,
+    },
+}
+
+/// Subject and plain-text body of an email
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub body: String,
+}
+
+impl EmailTemplate {
+    /// Recorded with outbox entries
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EmailTemplate::VerifyEmail { .. } => "verify_email",
+            EmailTemplate::PasswordReset { .. } => "password_reset",
+            EmailTemplate::AccountLocked { .. } => "account_locked",
+            EmailTemplate::SuspiciousLogin { .. } => "suspicious_login",
+        }
+    }
+
+    /// After this the email is useless, so it is no longer sent
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            EmailTemplate::VerifyEmail { expires_at, .. } | EmailTemplate::PasswordReset { expires_at, .. } => {
+                Some(*expires_at)
+            }
+            EmailTemplate::AccountLocked { until, .. } => Some(*until),
+            EmailTemplate::SuspiciousLogin { .. } => None,
+        }
+    }
+
+    /// The message for the account called `name`, with links into `config.app_url`
+    pub fn render(&self, name: &str, config: &EmailConfig) -> EmailMessage {
+        let link = |path: &str, token: &str| format!("{}/{}?token={}", config.app_url.trim_end_matches('/'), path, token);
+        let time = |at: &DateTime<Utc>| at.format("%Y-%m-%d %H:%M UTC").to_string();
+        match self {
+            EmailTemplate::VerifyEmail { token, expires_at } => EmailMessage {
+                subject: "Verify your Pixelle email address".to_string(),
+                body: format!(
+                    "Hi {},\n\nConfirm this is your email address by opening the link below before {}:\n\n{}\n\n\
+                     If you did not create a Pixelle account, you can ignore this email.",
+                    name,
+                    time(expires_at),
+                    link("verify-email", token)
+                ),
+            },
+            EmailTemplate::PasswordReset { token, expires_at } => EmailMessage {
+                subject: "Reset your Pixelle password".to_string(),
+                body: format!(
+                    "Hi {},\n\nSomeone asked to reset the password of your account. Choose a new one before {} at:\n\n{}\n\n\
+                     Resetting signs you out on every device. If you did not ask for this, ignore this email; \
+                     your password stays the same.",
+                    name,
+                    time(expires_at),
+                    link("reset-password", token)
+                ),
+            },
+            EmailTemplate::AccountLocked { token, until } => EmailMessage {
+                subject: "Your Pixelle account has been locked".to_string(),
+                body: format!(
+                    "Hi {},\n\nAfter several failed sign-in attempts we locked your account until {}. \
+                     If these were you, unlock it now at:\n\n{}\n\n\
+                     If they were not, someone may be guessing your password; consider resetting it.",
+                    name,
+                    time(until),
+                    link("unlock-account", token)
+                ),
+            },
+            EmailTemplate::SuspiciousLogin { device, ip, at } => EmailMessage {
+                subject: "New sign-in to your Pixelle account".to_string(),
+                body: format!(
+                    "Hi {},\n\nYour account was signed in to from a new device on {}.\n\n\
+                     Device: {}\nIP address: {}\n\n\
+                     If this was you, there is nothing to do. Otherwise, reset your password and sign out \
+                     the devices you do not recognize under Settings > Sessions.",
+                    name,
+                    time(at),
+                    device.user_agent.as_deref().unwrap_or("unknown"),
+                    ip.as_deref().unwrap_or("unknown")
+                ),
+            },
+        }
+    }
+}
+
+/// Delivers rendered emails
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, to: &str, message: &EmailMessage) -> PixelleResult<()>;
+}
+
+/// Sends through an SMTP relay
+pub struct SmtpEmailTransport {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpEmailTransport {
+    /// Build from `SMTP_HOST`, `SMTP_USERNAME`, `SMTP_PASSWORD` and `SMTP_FROM`.
+    ///
+    /// Returns `None` when `SMTP_HOST` is not set.
+    pub fn from_env() -> PixelleResult<Option<Self>> {
+        let Ok(host) = env::var("SMTP_HOST") else {
+            return Ok(None);
+        };
+
+        let mut builder = SmtpTransport::relay(&host)
+            .map_err(|e| PixelleError::Internal(format!("Invalid SMTP relay '{}': {}", host, e)))?;
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+            builder = builder.credentials(SmtpCredentials::new(username, password));
+        }
+
+        let from = env::var("SMTP_FROM")
+            .unwrap_or_else(|_| "Pixelle <no-reply@pixelle.app>".to_string())
+            .parse::<Mailbox>()
+            .map_err(|e| PixelleError::Internal(format!("Invalid SMTP_FROM address: {}", e)))?;
+
+        Ok(Some(Self {
+            transport: builder.build(),
+            from,
+        }))
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpEmailTransport {
+    async fn send(&self, to: &str, message: &EmailMessage) -> PixelleResult<()> {
+        let to = to
+            .parse::<Mailbox>()
+            .map_err(|e| PixelleError::Validation(format!("Invalid email address: {}", e)))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject.clone())
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body.clone())
+            .map_err(|e| PixelleError::Internal(format!("Failed to build email: {}", e)))?;
+
+        // The SMTP transport blocks, so keep it off the async workers
+        let transport = self.transport.clone();
+        tokio::task::spawn_blocking(move || transport.send(&message))
+            .await
+            .map_err(|e| PixelleError::Internal(format!("Email task failed: {}", e)))?
+            .map_err(|e| PixelleError::ExternalService(format!("Failed to send email: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// Logs the subject instead of sending, for deployments without SMTP;
+/// bodies hold tokens, so they are not logged
+pub struct LogEmailTransport;
+
+#[async_trait]
+impl EmailTransport for LogEmailTransport {
+    async fn send(&self, to: &str, message: &EmailMessage) -> PixelleResult<()> {
+        tracing::info!("Email to {}: {}", to, message.subject);
+        Ok(())
+    }
+}
+
+/// Where an outbox entry is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    /// Out of attempts, or expired before it could be sent
+    Failed,
+}
+
+/// An email waiting in, or delivered from, the `email_outbox` repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEmail {
+    pub id: String,
+    pub user_id: UserId,
+    pub to: String,
+    /// `EmailTemplate::kind` of the message
+    pub kind: String,
+    pub message: EmailMessage,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of one pass over the outbox
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchReport {
+    pub sent: usize,
+    pub retrying: usize,
+    pub failed: usize,
+}
+
+/// Transactional email, written to the `email_outbox` repository and
+/// delivered by a background dispatcher
+///
+/// Enqueuing never waits on the mail server, and a failed send is retried
+/// with exponential backoff until `max_attempts`. Each send is recorded
+/// before it is tried, so an instance that crashes mid-send does not retry
+/// before the backoff; instances sharing the outbox may still rarely send an
+/// email twice. Bodies are cleared once an email is finished with, so the
+/// tokens in its links do not outlive it.
+pub struct EmailOutbox {
+    emails: DocumentRepository<OutboxEmail>,
+    transport: Arc<dyn EmailTransport>,
+    config: EmailConfig,
+}
+
+impl EmailOutbox {
+    pub fn new(backends: &Backends, transport: Arc<dyn EmailTransport>, config: EmailConfig) -> StoreResult<Self> {
+        Ok(Self {
+            emails: backends.repository(EMAIL_OUTBOX_REPOSITORY, &[Capability::Durable])?,
+            transport,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &EmailConfig {
+        &self.config
+    }
+
+    /// Render `template` for the account called `name` and queue it for `to`
+    pub async fn enqueue(&self, user_id: UserId, to: &str, name: &str, template: &EmailTemplate) -> PixelleResult<OutboxEmail> {
+        let now = Utc::now();
+        let email = OutboxEmail {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id,
+            to: to.to_string(),
+            kind: template.kind().to_string(),
+            message: template.render(name, &self.config),
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            expires_at: template.expires_at(),
+            last_error: None,
+            created_at: now,
+            sent_at: None,
+        };
+        self.emails.put(&email.id, &email).await.map_err(store_error)?;
+        Ok(email)
+    }
+
+    pub async fn get(&self, id: &str) -> PixelleResult<Option<OutboxEmail>> {
+        self.emails.get(id).await.map_err(store_error)
+    }
+
+    /// Try every pending email that is due, oldest first
+    pub async fn dispatch_due(&self, now: DateTime<Utc>) -> PixelleResult<DispatchReport> {
+        let query = StoreQuery::new().filter("status", "pending");
+        let mut due = self.emails.find(&query).await.map_err(store_error)?;
+        due.retain(|email| email.next_attempt_at <= now);
+        due.sort_by_key(|email| email.next_attempt_at);
+
+        let mut report = DispatchReport::default();
+        for email in due {
+            match self.deliver(email, now).await?.status {
+                OutboxStatus::Sent => report.sent += 1,
+                OutboxStatus::Pending => report.retrying += 1,
+                OutboxStatus::Failed => report.failed += 1,
+            }
+        }
+        Ok(report)
+    }
+
+    async fn deliver(&self, mut email: OutboxEmail, now: DateTime<Utc>) -> PixelleResult<OutboxEmail> {
+        if email.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            email.last_error = Some("Expired before it could be sent".to_string());
+            return self.finish(email, OutboxStatus::Failed).await;
+        }
+
+        email.attempts += 1;
+        email.next_attempt_at = now + self.retry_delay(email.attempts);
+        self.emails.put(&email.id, &email).await.map_err(store_error)?;
+
+        match self.transport.send(&email.to, &email.message).await {
+            Ok(()) => {
+                email.sent_at = Some(now);
+                email.last_error = None;
+                self.finish(email, OutboxStatus::Sent).await
+            }
+            Err(e) => {
+                tracing::warn!("Sending {} email {} failed on attempt {}: {}", email.kind, email.id, email.attempts, e);
+                email.last_error = Some(e.to_string());
+                if email.attempts >= self.config.max_attempts {
+                    return self.finish(email, OutboxStatus::Failed).await;
+                }
+                self.emails.put(&email.id, &email).await.map_err(store_error)?;
+                Ok(email)
+            }
+        }
+    }
+
+    async fn finish(&self, mut email: OutboxEmail, status: OutboxStatus) -> PixelleResult<OutboxEmail> {
+        email.status = status;
+        email.message.body.clear();
+        self.emails.put(&email.id, &email).await.map_err(store_error)?;
+        Ok(email)
+    }
+
+    /// Wait after the `attempts`-th send before the next
+    fn retry_delay(&self, attempts: u32) -> Duration {
+        let factor = 1i64 << attempts.saturating_sub(1).min(20);
+        Duration::seconds(self.config.retry_base_seconds.max(1).saturating_mul(factor).min(MAX_RETRY_DELAY_SECONDS))
+    }
+
+    /// Delete sent and failed emails older than the retention
+    pub async fn prune(&self, now: DateTime<Utc>) -> PixelleResult<usize> {
+        let cutoff = now - Duration::days(self.config.retention_days);
+        let mut pruned = 0;
+        for status in ["sent", "failed"] {
+            let query = StoreQuery::new().filter("status", status);
+            for email in self.emails.find(&query).await.map_err(store_error)? {
+                if email.created_at < cutoff {
+                    self.emails.delete(&email.id).await.map_err(store_error)?;
+                    pruned += 1;
+                }
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Run `dispatch_due` every `dispatch_interval_seconds`, and `prune`
+    /// hourly, until the task is aborted
+    pub fn spawn_dispatcher(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut dispatch = tokio::time::interval(std::time::Duration::from_secs(self.config.dispatch_interval_seconds));
+            let mut prune = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                tokio::select! {
+                    _ = dispatch.tick() => match self.dispatch_due(Utc::now()).await {
+                        Ok(report) if report.failed > 0 => tracing::error!("Gave up on {} emails", report.failed),
+                        Ok(_) => {}
+                        Err(e) => tracing::error!("Email dispatch failed: {}", e),
+                    },
+                    _ = prune.tick() => if let Err(e) = self.prune(Utc::now()).await {
+                        tracing::error!("Pruning the email outbox failed: {}", e);
+                    },
+                }
+            }
+        })
+    }
+}
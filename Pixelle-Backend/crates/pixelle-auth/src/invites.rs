@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use pixelle_core::{Invite, InviteRepository, PixelleError, PixelleResult, Referral, ReferralStatus, UserId};
+use pixelle_database::{Backends, Capability, DocumentRepository, StoreQuery, StoreResult};
+
+use crate::accounts::{store_error, INVITES_REPOSITORY, REFERRALS_REPOSITORY};
+
+/// Invites in the `invites` repository under their code, and referrals in
+/// `referrals` under the invitee's user ID
+pub struct InviteStore {
+    invites: DocumentRepository<Invite>,
+    referrals: DocumentRepository<Referral>,
+}
+
+impl InviteStore {
+    pub fn new(backends: &Backends) -> StoreResult<Self> {
+        Ok(Self {
+            invites: backends.repository(INVITES_REPOSITORY, &[Capability::Durable])?,
+            referrals: backends.repository(REFERRALS_REPOSITORY, &[Capability::Durable])?,
+        })
+    }
+}
+
+#[async_trait]
+impl InviteRepository for InviteStore {
+    async fn get_invite(&self, code: &str) -> PixelleResult<Option<Invite>> {
+        self.invites.get(code).await.map_err(store_error)
+    }
+
+    async fn save_invite(&self, invite: &Invite) -> PixelleResult<()> {
+        self.invites.put(&invite.code, invite).await.map_err(store_error)
+    }
+
+    async fn list_invites(&self, inviter_id: UserId) -> PixelleResult<Vec<Invite>> {
+        let query = StoreQuery::new().filter("inviter_id", inviter_id.to_string());
+        self.invites.find(&query).await.map_err(store_error)
+    }
+
+    async fn get_referral(&self, invitee_id: UserId) -> PixelleResult<Option<Referral>> {
+        self.referrals.get(&invitee_id.to_string()).await.map_err(store_error)
+    }
+
+    async fn save_referral(&self, referral: &Referral) -> PixelleResult<()> {
+        self.referrals
+            .put(&referral.invitee_id.to_string(), referral)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn list_referrals(&self, inviter_id: UserId) -> PixelleResult<Vec<Referral>> {
+        let query = StoreQuery::new().filter("inviter_id", inviter_id.to_string());
+        self.referrals.find(&query).await.map_err(store_error)
+    }
+
+    async fn list_referrals_with_status(&self, status: ReferralStatus) -> PixelleResult<Vec<Referral>> {
+        let status = serde_json::to_value(status).map_err(PixelleError::Serialization)?;
+        let query = StoreQuery::new().filter("status", status);
+        self.referrals.find(&query).await.map_err(store_error)
+    }
+}
@@ -1,58 +1,167 @@
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use crate::keys::KeyRing;
+use crate::revocation::RevocationList;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
-use pixelle_core::{PixelleResult, UserId, PixelleError};
+use pixelle_core::{PixelleResult, Role, UserId, PixelleError, ACCESS_TOKEN_TTL_MINUTES, SERVICE_TOKEN_TTL_MINUTES};
 use chrono::{Duration, Utc};
+use std::sync::{Arc, RwLock};
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String, // User ID
-    exp: i64,    // Expiration time
-    iat: i64,    // Issued at
+/// Claims of an access token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessClaims {
+    pub sub: String, // User ID
+    pub sid: String, // Session ID
+    /// Region whose auth-service owns the session; per-session state lives there
+    pub region: String,
+    pub iss: String,
+    pub jti: String,
+    /// Roles granted to the user, e.g. `moderator`; absent from older tokens
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    /// Internal service a client-credentials token was issued to; `sub` is
+    /// the same client ID and the token acts for no user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Scopes granted to a service token
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    pub exp: i64,    // Expiration time
+    pub iat: i64,    // Issued at
 }
 
+impl AccessClaims {
+    pub fn user_id(&self) -> PixelleResult<UserId> {
+        if self.is_service() {
+            return Err(PixelleError::Authentication("Service tokens act for no user".to_string()));
+        }
+        self.sub.parse::<UserId>()
+            .map_err(|_| PixelleError::Authentication("Invalid user ID in token".to_string()))
+    }
+
+    pub fn is_service(&self) -> bool {
+        self.client_id.is_some()
+    }
+}
+
+/// Verify an ES256 access token against the key named in its header
+pub(crate) fn decode_access_token(
+    token: &str,
+    key_for: impl Fn(&str) -> Option<DecodingKey>,
+    check_expiry: bool,
+) -> PixelleResult<AccessClaims> {
+    let header = decode_header(token)
+        .map_err(|e| PixelleError::Authentication(format!("Malformed token: {}", e)))?;
+    let kid = header.kid
+        .ok_or_else(|| PixelleError::Authentication("Token has no key ID".to_string()))?;
+    let key = key_for(&kid)
+        .ok_or_else(|| PixelleError::Authentication(format!("Unknown signing key {}", kid)))?;
+
+    let mut validation = Validation::new(Algorithm::ES256);
+    validation.validate_exp = check_expiry;
+    validation.set_required_spec_claims(&["exp", "sub"]);
+
+    decode::<AccessClaims>(token, &key, &validation)
+        .map(|data| data.claims)
+        .map_err(|e| PixelleError::Authentication(format!("Invalid token: {}", e)))
+}
+
+/// Issues and checks access tokens for one region
 pub struct JwtService {
-    secret: String,
+    keys: Arc<KeyRing>,
+    revocations: Arc<RwLock<RevocationList>>,
+    region: String,
+    issuer: String,
 }
 
 impl JwtService {
-    pub fn new(secret: String) -> Self {
-        Self { secret }
+    pub fn new(keys: Arc<KeyRing>, region: impl Into<String>) -> Self {
+        let region = region.into();
+        Self {
+            keys,
+            revocations: Arc::new(RwLock::new(RevocationList::default())),
+            issuer: format!("pixelle-auth/{}", region),
+            region,
+        }
+    }
+
+    pub fn keys(&self) -> &Arc<KeyRing> {
+        &self.keys
     }
 
+    pub fn revocations(&self) -> &Arc<RwLock<RevocationList>> {
+        &self.revocations
+    }
+
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// Start a session and issue its first access token
     pub async fn create_token(&self, user_id: UserId) -> PixelleResult<String> {
-        let expiration = Utc::now()
-            .checked_add_signed(Duration::hours(24))
-            .expect("valid timestamp")
-            .timestamp();
+        self.issue(user_id, &uuid::Uuid::new_v4().to_string(), &[])
+    }
 
-        let claims = Claims {
+    /// Issue an access token for an existing session, carrying the user's
+    /// roles so services can authorize without asking auth-service
+    pub fn issue(&self, user_id: UserId, session_id: &str, roles: &[Role]) -> PixelleResult<String> {
+        let now = Utc::now();
+        let claims = AccessClaims {
             sub: user_id.to_string(),
-            exp: expiration,
-            iat: Utc::now().timestamp(),
+            sid: session_id.to_string(),
+            region: self.region.clone(),
+            iss: self.issuer.clone(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            client_id: None,
+            scopes: Vec::new(),
+            exp: (now + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).timestamp(),
+            iat: now.timestamp(),
+        };
+        self.keys.sign(&claims)
+    }
+
+    /// Issue a client-credentials token to an internal service
+    ///
+    /// The token has no session; its `sid` is its own ID, so it can be
+    /// revoked like a session.
+    pub fn issue_service_token(&self, client_id: &str, scopes: Vec<String>) -> PixelleResult<String> {
+        let now = Utc::now();
+        let jti = uuid::Uuid::new_v4().to_string();
+        let claims = AccessClaims {
+            sub: client_id.to_string(),
+            sid: jti.clone(),
+            region: self.region.clone(),
+            iss: self.issuer.clone(),
+            jti,
+            roles: Vec::new(),
+            client_id: Some(client_id.to_string()),
+            scopes,
+            exp: (now + Duration::minutes(SERVICE_TOKEN_TTL_MINUTES)).timestamp(),
+            iat: now.timestamp(),
         };
+        self.keys.sign(&claims)
+    }
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.secret.as_ref()),
-        )
-        .map_err(|e| PixelleError::Internal(format!("JWT encoding error: {}", e)))
+    /// Claims of a valid, unrevoked token issued by this region
+    pub fn verify(&self, token: &str) -> PixelleResult<AccessClaims> {
+        let claims = decode_access_token(token, |kid| self.keys.decoding_key(kid), true)?;
+        if self.revocations.read().unwrap().is_revoked(&claims) {
+            return Err(PixelleError::Authentication("Token has been revoked".to_string()));
+        }
+        Ok(claims)
     }
 
     pub async fn validate_token(&self, token: &str) -> PixelleResult<Option<UserId>> {
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.secret.as_ref()),
-            &Validation::default(),
-        );
-
-        match token_data {
-            Ok(token_data) => {
-                let user_id = token_data.claims.sub.parse::<UserId>()
-                    .map_err(|_| PixelleError::Authentication("Invalid user ID in token".to_string()))?;
-                Ok(Some(user_id))
-            }
+        match self.verify(token) {
+            Ok(claims) => Ok(Some(claims.user_id()?)),
             Err(_) => Ok(None),
         }
     }
+
+    /// Revoke the session a token belongs to; expired tokens are accepted so logout always works
+    pub fn revoke_token(&self, token: &str) -> PixelleResult<AccessClaims> {
+        let claims = decode_access_token(token, |kid| self.keys.decoding_key(kid), false)?;
+        self.revocations.write().unwrap().revoke_session(&claims.sid, Utc::now());
+        Ok(claims)
+    }
 }
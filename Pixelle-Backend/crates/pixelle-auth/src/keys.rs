@@ -0,0 +1,170 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header};
+use pixelle_core::{
+    PixelleError, PixelleResult, ACCESS_TOKEN_TTL_MINUTES, SIGNING_KEY_PUBLISH_AHEAD_MINUTES,
+};
+use ring::digest::{digest, SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+/// Public half of a signing key, as published in the JWKS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    pub crv: String,
+    pub x: String,
+    pub y: String,
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+}
+
+impl Jwk {
+    pub fn decoding_key(&self) -> PixelleResult<DecodingKey> {
+        if self.kty != "EC" || self.crv != "P-256" {
+            return Err(PixelleError::Authentication(format!("Unsupported key type {} {}", self.kty, self.crv)));
+        }
+        DecodingKey::from_ec_components(&self.x, &self.y)
+            .map_err(|e| PixelleError::Authentication(format!("Invalid JWK {}: {}", self.kid, e)))
+    }
+}
+
+/// JSON Web Key Set served at `/.well-known/jwks.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+struct SigningKey {
+    kid: String,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Jwk,
+    /// Published before this, signing from it on
+    activates_at: DateTime<Utc>,
+    /// Set once a newer key takes over signing
+    retired_at: Option<DateTime<Utc>>,
+}
+
+impl SigningKey {
+    fn from_pkcs8(der: &[u8], activates_at: DateTime<Utc>) -> PixelleResult<Self> {
+        let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &SystemRandom::new())
+            .map_err(|e| PixelleError::Internal(format!("Invalid P-256 signing key: {}", e)))?;
+
+        // Uncompressed point: 0x04 || X || Y
+        let public = pair.public_key().as_ref();
+        let (x, y) = (&public[1..33], &public[33..65]);
+        // Derived from the public key, so every region agrees on it
+        let kid = URL_SAFE_NO_PAD.encode(&digest(&SHA256, public).as_ref()[..12]);
+
+        let jwk = Jwk {
+            kty: "EC".to_string(),
+            crv: "P-256".to_string(),
+            x: URL_SAFE_NO_PAD.encode(x),
+            y: URL_SAFE_NO_PAD.encode(y),
+            kid: kid.clone(),
+            alg: "ES256".to_string(),
+            key_use: "sig".to_string(),
+        };
+        Ok(Self {
+            kid,
+            encoding: EncodingKey::from_ec_der(der),
+            decoding: jwk.decoding_key()?,
+            jwk,
+            activates_at,
+            retired_at: None,
+        })
+    }
+
+    fn generate(activates_at: DateTime<Utc>) -> PixelleResult<Self> {
+        let der = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+            .map_err(|e| PixelleError::Internal(format!("Failed to generate signing key: {}", e)))?;
+        Self::from_pkcs8(der.as_ref(), activates_at)
+    }
+}
+
+/// ES256 signing keys of one auth-service region, with rotation
+///
+/// A rotated-in key is published for `publish_ahead` before it signs
+/// anything, and a retired key stays published until every token it signed
+/// has expired, so validators refreshing the JWKS never see an unknown key.
+pub struct KeyRing {
+    /// Newest first
+    keys: RwLock<Vec<SigningKey>>,
+    publish_ahead: Duration,
+    token_ttl: Duration,
+}
+
+impl KeyRing {
+    /// Start with a freshly generated key that signs immediately
+    pub fn generate() -> PixelleResult<Self> {
+        Ok(Self::with_key(SigningKey::generate(Utc::now())?))
+    }
+
+    /// Start with a PKCS#8 P-256 key, so restarts keep signing with the same key
+    pub fn from_pkcs8(der: &[u8]) -> PixelleResult<Self> {
+        Ok(Self::with_key(SigningKey::from_pkcs8(der, Utc::now())?))
+    }
+
+    fn with_key(key: SigningKey) -> Self {
+        Self {
+            keys: RwLock::new(vec![key]),
+            publish_ahead: Duration::minutes(SIGNING_KEY_PUBLISH_AHEAD_MINUTES),
+            token_ttl: Duration::minutes(ACCESS_TOKEN_TTL_MINUTES),
+        }
+    }
+
+    /// Add a key that takes over signing after the publish-ahead period; returns its ID
+    pub fn rotate(&self) -> PixelleResult<String> {
+        let key = SigningKey::generate(Utc::now() + self.publish_ahead)?;
+        let kid = key.kid.clone();
+        self.keys.write().unwrap().insert(0, key);
+        Ok(kid)
+    }
+
+    /// Mark keys that no longer sign as retired and drop those no token can still use
+    pub fn prune(&self, now: DateTime<Utc>) -> usize {
+        let mut keys = self.keys.write().unwrap();
+        let signing = keys.iter().position(|key| key.activates_at <= now);
+        for (i, key) in keys.iter_mut().enumerate() {
+            if signing.is_some_and(|signing| i > signing) && key.retired_at.is_none() {
+                key.retired_at = Some(now);
+            }
+        }
+        let before = keys.len();
+        let token_ttl = self.token_ttl;
+        keys.retain(|key| key.retired_at.is_none_or(|retired| retired + token_ttl > now));
+        before - keys.len()
+    }
+
+    /// Sign claims with the newest active key
+    pub fn sign<T: Serialize>(&self, claims: &T) -> PixelleResult<String> {
+        let now = Utc::now();
+        let keys = self.keys.read().unwrap();
+        let key = keys
+            .iter()
+            .find(|key| key.activates_at <= now)
+            .ok_or_else(|| PixelleError::Internal("No active signing key".to_string()))?;
+
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(key.kid.clone());
+        jsonwebtoken::encode(&header, claims, &key.encoding)
+            .map_err(|e| PixelleError::Internal(format!("JWT encoding error: {}", e)))
+    }
+
+    pub fn decoding_key(&self, kid: &str) -> Option<DecodingKey> {
+        self.keys.read().unwrap().iter().find(|key| key.kid == kid).map(|key| key.decoding.clone())
+    }
+
+    /// Every key a validator may need: pending, active and recently retired
+    pub fn jwks(&self) -> Jwks {
+        Jwks {
+            keys: self.keys.read().unwrap().iter().map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
@@ -1,9 +1,39 @@
+pub mod accounts;
+pub mod api_keys;
+pub mod audit;
 pub mod auth_service;
+pub mod challenge;
+pub mod clients;
+pub mod config;
+pub mod email;
+pub mod invites;
 pub mod jwt;
+pub mod keys;
+pub mod lockout;
+pub mod middleware;
 pub mod passphrase;
+pub mod revocation;
+pub mod roles;
 pub mod session;
+pub mod validator;
+pub mod verification;
 
+pub use accounts::*;
+pub use api_keys::*;
+pub use audit::*;
 pub use auth_service::*;
+pub use challenge::*;
+pub use clients::*;
+pub use config::*;
+pub use email::*;
+pub use invites::*;
 pub use jwt::*;
+pub use keys::*;
+pub use lockout::*;
+pub use middleware::*;
 pub use passphrase::*;
+pub use revocation::*;
+pub use roles::*;
 pub use session::*;
+pub use validator::*;
+pub use verification::*;
@@ -0,0 +1,410 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use pixelle_core::{PixelleError, PixelleResult};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::config::LockoutConfig;
+
+/// Prefix of every key the throttle writes, so it can share a Redis
+const KEY_PREFIX: &str = "pixelle:auth:";
+
+/// Expiring counters and markers shared by every auth-service instance
+#[async_trait]
+pub trait ThrottleStore: Send + Sync {
+    /// Add one to the counter, starting it with `window` to live if new;
+    /// returns the new count
+    async fn increment(&self, key: &str, window: Duration) -> PixelleResult<u32>;
+    async fn count(&self, key: &str) -> PixelleResult<u32>;
+    /// Set a marker that disappears after `ttl`
+    async fn hold(&self, key: &str, ttl: Duration) -> PixelleResult<()>;
+    /// How long the marker has left, if it is set
+    async fn held_for(&self, key: &str) -> PixelleResult<Option<Duration>>;
+    async fn clear(&self, keys: &[String]) -> PixelleResult<()>;
+}
+
+/// Counters in Redis, e.g. the one behind cache-service
+///
+/// Connects on first use and again after a failed command.
+pub struct RedisThrottleStore {
+    client: redis::Client,
+    connection: tokio::sync::Mutex<Option<redis::aio::MultiplexedConnection>>,
+}
+
+impl RedisThrottleStore {
+    pub fn new(url: &str) -> PixelleResult<Self> {
+        let client = redis::Client::open(url).map_err(|e| PixelleError::Internal(format!("Invalid Redis URL: {}", e)))?;
+        Ok(Self {
+            client,
+            connection: tokio::sync::Mutex::new(None),
+        })
+    }
+
+    async fn query<T: redis::FromRedisValue>(&self, command: &redis::Pipeline) -> PixelleResult<T> {
+        let mut open = {
+            let mut connection = self.connection.lock().await;
+            match connection.as_ref() {
+                Some(open) => open.clone(),
+                None => {
+                    let open = self
+                        .client
+                        .get_multiplexed_tokio_connection()
+                        .await
+                        .map_err(|e| PixelleError::ExternalService(format!("Redis unavailable: {}", e)))?;
+                    *connection = Some(open.clone());
+                    open
+                }
+            }
+        };
+
+        match command.query_async(&mut open).await {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                if e.is_io_error() || e.is_connection_dropped() {
+                    *self.connection.lock().await = None;
+                }
+                Err(PixelleError::ExternalService(format!("Redis command failed: {}", e)))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ThrottleStore for RedisThrottleStore {
+    async fn increment(&self, key: &str, window: Duration) -> PixelleResult<u32> {
+        let mut command = redis::pipe();
+        command
+            .atomic()
+            .incr(key, 1)
+            .cmd("EXPIRE")
+            .arg(key)
+            .arg(window.num_seconds().max(1))
+            .arg("NX")
+            .ignore();
+        let (count,): (u32,) = self.query(&command).await?;
+        Ok(count)
+    }
+
+    async fn count(&self, key: &str) -> PixelleResult<u32> {
+        let mut command = redis::pipe();
+        command.get(key);
+        let (count,): (Option<u32>,) = self.query(&command).await?;
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn hold(&self, key: &str, ttl: Duration) -> PixelleResult<()> {
+        let mut command = redis::pipe();
+        command.cmd("SET").arg(key).arg(1).arg("PX").arg(ttl.num_milliseconds().max(1)).ignore();
+        self.query::<()>(&command).await
+    }
+
+    async fn held_for(&self, key: &str) -> PixelleResult<Option<Duration>> {
+        let mut command = redis::pipe();
+        command.cmd("PTTL").arg(key);
+        let (ttl,): (i64,) = self.query(&command).await?;
+        // -2 is a missing key, -1 one without expiry, which this never sets
+        Ok((ttl > 0).then(|| Duration::milliseconds(ttl)))
+    }
+
+    async fn clear(&self, keys: &[String]) -> PixelleResult<()> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let mut command = redis::pipe();
+        command.del(keys).ignore();
+        self.query::<()>(&command).await
+    }
+}
+
+/// Counters in process memory, for a single instance and tests
+#[derive(Default)]
+pub struct InMemoryThrottleStore {
+    entries: Mutex<HashMap<String, (u32, DateTime<Utc>)>>,
+}
+
+impl InMemoryThrottleStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn live(&self, key: &str, now: DateTime<Utc>) -> Option<(u32, DateTime<Utc>)> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(&(value, expires_at)) if expires_at > now => Some((value, expires_at)),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+#[async_trait]
+impl ThrottleStore for InMemoryThrottleStore {
+    async fn increment(&self, key: &str, window: Duration) -> PixelleResult<u32> {
+        let now = Utc::now();
+        let (count, expires_at) = self.live(key, now).unwrap_or((0, now + window));
+        self.entries.lock().unwrap().insert(key.to_string(), (count + 1, expires_at));
+        Ok(count + 1)
+    }
+
+    async fn count(&self, key: &str) -> PixelleResult<u32> {
+        Ok(self.live(key, Utc::now()).map_or(0, |(count, _)| count))
+    }
+
+    async fn hold(&self, key: &str, ttl: Duration) -> PixelleResult<()> {
+        self.entries.lock().unwrap().insert(key.to_string(), (1, Utc::now() + ttl));
+        Ok(())
+    }
+
+    async fn held_for(&self, key: &str) -> PixelleResult<Option<Duration>> {
+        let now = Utc::now();
+        Ok(self.live(key, now).map(|(_, expires_at)| expires_at - now))
+    }
+
+    async fn clear(&self, keys: &[String]) -> PixelleResult<()> {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys {
+            entries.remove(key);
+        }
+        Ok(())
+    }
+}
+
+/// Why a login is refused before the password is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginBlock {
+    /// Too many recent failures from the IP or for the account; retry later
+    Backoff { retry_after: Duration },
+    /// The account is locked until the time or until unlocked by email
+    Locked { until: DateTime<Utc> },
+}
+
+/// Result of recording a failed login
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FailureOutcome {
+    /// Failures for the account within the window, this one included
+    pub account_failures: u32,
+    /// Set when this failure locked the account; failures after a lock ends
+    /// lock it again until the window runs out
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// Adaptive protection of the login flow
+///
+/// Failed logins are counted per client IP and per account within a window.
+/// Past a number of free failures each further one makes the next attempt
+/// wait, doubling every time, and enough failures for one account lock it.
+/// Accounts are identified by user ID when they exist and by the submitted
+/// username or email otherwise, so locking does not reveal which exist.
+pub struct LoginProtection {
+    store: Arc<dyn ThrottleStore>,
+    config: LockoutConfig,
+}
+
+impl LoginProtection {
+    pub fn new(store: Arc<dyn ThrottleStore>, config: LockoutConfig) -> Self {
+        Self { store, config }
+    }
+
+    pub fn config(&self) -> &LockoutConfig {
+        &self.config
+    }
+
+    fn key(kind: &str, subject: &str, value: &str) -> String {
+        format!("{}{}:{}:{}", KEY_PREFIX, kind, subject, value.to_lowercase())
+    }
+
+    /// Counter and backoff keys of an attempt, IP first
+    fn subjects(ip: Option<&str>, account: &str) -> Vec<(&'static str, String)> {
+        let mut subjects = Vec::with_capacity(2);
+        if let Some(ip) = ip {
+            subjects.push(("ip", ip.to_string()));
+        }
+        subjects.push(("account", account.to_string()));
+        subjects
+    }
+
+    fn free_failures(&self, subject: &str) -> u32 {
+        if subject == "ip" {
+            self.config.ip_free_failures
+        } else {
+            self.config.account_free_failures
+        }
+    }
+
+    /// Wait imposed after the `failures`-th failure
+    fn backoff(&self, subject: &str, failures: u32) -> Option<Duration> {
+        let over = failures.checked_sub(self.free_failures(subject)).filter(|over| *over > 0)?;
+        let factor = 1i64 << (over - 1).min(20);
+        let seconds = self.config.backoff_base_seconds.max(1).saturating_mul(factor);
+        Some(Duration::seconds(seconds.min(self.config.max_backoff_seconds)))
+    }
+
+    /// Why a login for `account` from `ip` must not be tried now, if it must not
+    pub async fn check(&self, ip: Option<&str>, account: &str) -> PixelleResult<Option<LoginBlock>> {
+        if let Some(left) = self.store.held_for(&Self::key("lock", "account", account)).await? {
+            return Ok(Some(LoginBlock::Locked { until: Utc::now() + left }));
+        }
+        let mut wait = None;
+        for (subject, value) in Self::subjects(ip, account) {
+            if let Some(left) = self.store.held_for(&Self::key("wait", subject, &value)).await? {
+                wait = wait.max(Some(left));
+            }
+        }
+        Ok(wait.map(|retry_after| LoginBlock::Backoff { retry_after }))
+    }
+
+    /// Most failures the IP or the account has in the window, for the
+    /// challenge gate to weigh
+    pub async fn failures(&self, ip: Option<&str>, account: &str) -> PixelleResult<u32> {
+        let mut failures = 0;
+        for (subject, value) in Self::subjects(ip, account) {
+            failures = failures.max(self.store.count(&Self::key("fail", subject, &value)).await?);
+        }
+        Ok(failures)
+    }
+
+    /// Count a failed login, starting backoff and locking the account once
+    /// it has `lockout_threshold` failures
+    pub async fn record_failure(&self, ip: Option<&str>, account: &str) -> PixelleResult<FailureOutcome> {
+        let window = Duration::seconds(self.config.window_seconds);
+        let mut outcome = FailureOutcome::default();
+        for (subject, value) in Self::subjects(ip, account) {
+            let failures = self.store.increment(&Self::key("fail", subject, &value), window).await?;
+            if let Some(wait) = self.backoff(subject, failures) {
+                self.store.hold(&Self::key("wait", subject, &value), wait).await?;
+            }
+            if subject == "account" {
+                outcome.account_failures = failures;
+            }
+        }
+
+        if self.config.lockout_threshold > 0 && outcome.account_failures >= self.config.lockout_threshold {
+            let duration = Duration::minutes(self.config.lockout_minutes);
+            self.store.hold(&Self::key("lock", "account", account), duration).await?;
+            outcome.locked_until = Some(Utc::now() + duration);
+        }
+        Ok(outcome)
+    }
+
+    /// Forget the account's failures after a successful login; its IP's
+    /// stay, as one IP may be trying many accounts
+    pub async fn record_success(&self, account: &str) -> PixelleResult<()> {
+        self.store
+            .clear(&[Self::key("fail", "account", account), Self::key("wait", "account", account)])
+            .await
+    }
+
+    /// Lift a lock and forget the account's failures, e.g. from an unlock
+    /// link or a password reset
+    pub async fn unlock(&self, account: &str) -> PixelleResult<()> {
+        self.store
+            .clear(&[
+                Self::key("lock", "account", account),
+                Self::key("fail", "account", account),
+                Self::key("wait", "account", account),
+            ])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protection(config: LockoutConfig) -> LoginProtection {
+        LoginProtection::new(Arc::new(InMemoryThrottleStore::new()), config)
+    }
+
+    fn retry_after(block: Option<LoginBlock>) -> Option<i64> {
+        match block {
+            Some(LoginBlock::Backoff { retry_after }) => Some(retry_after.num_seconds()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_past_the_free_failures_up_to_the_cap() {
+        let protection = protection(LockoutConfig {
+            account_free_failures: 3,
+            backoff_base_seconds: 2,
+            max_backoff_seconds: 60,
+            ..LockoutConfig::default()
+        });
+        let waits: Vec<_> = (1..=9)
+            .map(|failures| protection.backoff("account", failures).map(|wait| wait.num_seconds()))
+            .collect();
+        assert_eq!(waits, [None, None, None, Some(2), Some(4), Some(8), Some(16), Some(32), Some(60)]);
+        // IPs get their own allowance
+        assert_eq!(protection.backoff("ip", 4), None);
+        // Huge counts neither overflow nor exceed the cap
+        assert_eq!(protection.backoff("account", u32::MAX).map(|wait| wait.num_seconds()), Some(60));
+    }
+
+    #[tokio::test]
+    async fn failures_past_the_free_ones_make_attempts_wait() {
+        let protection = protection(LockoutConfig { lockout_threshold: 0, ..LockoutConfig::default() });
+        for _ in 0..3 {
+            protection.record_failure(Some("10.0.0.1"), "alice").await.unwrap();
+            assert_eq!(protection.check(Some("10.0.0.1"), "alice").await.unwrap(), None);
+        }
+        protection.record_failure(Some("10.0.0.1"), "alice").await.unwrap();
+        let wait = retry_after(protection.check(Some("10.0.0.2"), "Alice").await.unwrap()).unwrap();
+        assert!((0..=1).contains(&wait), "waited {}s", wait);
+        assert_eq!(protection.failures(Some("10.0.0.1"), "bob").await.unwrap(), 4);
+        assert_eq!(protection.check(Some("10.0.0.1"), "bob").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn enough_failures_lock_the_account_until_unlocked() {
+        let protection = protection(LockoutConfig {
+            lockout_threshold: 5,
+            lockout_minutes: 30,
+            ..LockoutConfig::default()
+        });
+        for failure in 1..5 {
+            let outcome = protection.record_failure(None, "alice").await.unwrap();
+            assert_eq!(outcome.account_failures, failure);
+            assert_eq!(outcome.locked_until, None);
+        }
+        let outcome = protection.record_failure(None, "alice").await.unwrap();
+        let until = outcome.locked_until.expect("fifth failure locks");
+        assert!(until > Utc::now() + Duration::minutes(29));
+        assert!(matches!(protection.check(None, "alice").await.unwrap(), Some(LoginBlock::Locked { .. })));
+
+        protection.unlock("alice").await.unwrap();
+        assert_eq!(protection.check(None, "alice").await.unwrap(), None);
+        assert_eq!(protection.failures(None, "alice").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn success_forgets_the_account_but_not_the_ip() {
+        let protection = protection(LockoutConfig::default());
+        for _ in 0..4 {
+            protection.record_failure(Some("10.0.0.1"), "alice").await.unwrap();
+        }
+        protection.record_success("alice").await.unwrap();
+        assert_eq!(protection.check(None, "alice").await.unwrap(), None);
+        assert_eq!(protection.failures(None, "alice").await.unwrap(), 0);
+        assert_eq!(protection.failures(Some("10.0.0.1"), "alice").await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn in_memory_counters_expire_with_their_window() {
+        let store = InMemoryThrottleStore::new();
+        assert_eq!(store.increment("a", Duration::minutes(1)).await.unwrap(), 1);
+        assert_eq!(store.increment("a", Duration::minutes(1)).await.unwrap(), 2);
+        assert_eq!(store.count("a").await.unwrap(), 2);
+        assert_eq!(store.increment("b", Duration::zero()).await.unwrap(), 1);
+        assert_eq!(store.count("b").await.unwrap(), 0);
+
+        store.hold("c", Duration::minutes(5)).await.unwrap();
+        let left = store.held_for("c").await.unwrap().unwrap();
+        assert!(left > Duration::minutes(4) && left <= Duration::minutes(5));
+        store.clear(&["a".to_string(), "c".to_string()]).await.unwrap();
+        assert_eq!(store.count("a").await.unwrap(), 0);
+        assert_eq!(store.held_for("c").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,397 @@
+use crate::api_keys::{api_key_from_bearer, scopes_allow, ApiKeyGrant};
+use crate::jwt::AccessClaims;
+use crate::validator::TokenValidator;
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header::AUTHORIZATION, Method},
+    Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use pixelle_core::{Permission, PixelleError, PixelleResult, PolicyEngine, Resource, RolePolicy, Subject, UserId};
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Caller of a request, taken from its validated access token or API key
+///
+/// Extract it in a handler to require authentication, or as
+/// `Option<AuthenticatedUser>` to also serve anonymous callers. Only
+/// available behind [`Authenticate`].
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: UserId,
+    /// Session of the access token, or the ID of the API key
+    pub session_id: String,
+    /// Region whose auth-service owns the session or key
+    pub region: String,
+    pub roles: Vec<String>,
+    /// Scopes of the API key the request was made with; `None` for access
+    /// tokens, which carry all of the user's permissions
+    pub api_key_scopes: Option<Vec<String>>,
+}
+
+impl AuthenticatedUser {
+    pub fn from_claims(claims: &AccessClaims) -> PixelleResult<Self> {
+        Ok(Self {
+            user_id: claims.user_id()?,
+            session_id: claims.sid.clone(),
+            region: claims.region.clone(),
+            roles: claims.roles.clone(),
+            api_key_scopes: None,
+        })
+    }
+
+    pub fn from_api_key(grant: &ApiKeyGrant) -> Self {
+        Self {
+            user_id: grant.user_id,
+            session_id: grant.key_id.clone(),
+            region: grant.region.clone(),
+            roles: Vec::new(),
+            api_key_scopes: Some(grant.scopes.clone()),
+        }
+    }
+
+    pub fn is_api_key(&self) -> bool {
+        self.api_key_scopes.is_some()
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|granted| granted == role)
+    }
+
+    /// The caller as the policy sees it; API keys carry no roles, so they
+    /// act with the default role
+    pub fn subject(&self) -> Subject {
+        Subject::new(self.user_id, &self.roles)
+    }
+
+    /// Fail unless the default policy grants `permission`, on `resource` if given
+    pub fn require(&self, permission: Permission, resource: Option<&Resource>) -> PixelleResult<()> {
+        RolePolicy.evaluate(&self.subject(), permission, resource).into_result()
+    }
+
+    /// Fail unless the caller is `user_id`, given as a path segment
+    pub fn ensure_is(&self, user_id: &str) -> PixelleResult<()> {
+        match user_id.parse::<UserId>() {
+            Ok(user_id) if user_id == self.user_id => Ok(()),
+            _ => Err(PixelleError::Authorization("Not allowed to act for another user".to_string())),
+        }
+    }
+}
+
+/// Internal service calling with a token from the client-credentials grant
+///
+/// Such calls act for no user, so they never reach handlers that extract
+/// [`AuthenticatedUser`]. Only available behind [`Authenticate`].
+#[derive(Debug, Clone)]
+pub struct AuthenticatedService {
+    pub client_id: String,
+    pub scopes: Vec<String>,
+}
+
+impl AuthenticatedService {
+    pub fn require_scope(&self, scope: &str) -> PixelleResult<()> {
+        if scopes_allow(&self.scopes, scope) {
+            Ok(())
+        } else {
+            Err(PixelleError::Authorization(format!("{} lacks scope {}", self.client_id, scope)))
+        }
+    }
+}
+
+/// A user or an internal service, for endpoints both may call
+#[derive(Debug, Clone)]
+pub enum Caller {
+    User(AuthenticatedUser),
+    Service(AuthenticatedService),
+}
+
+impl Caller {
+    /// Fail unless the caller is `user_id`, or a service granted `scope`
+    pub fn ensure_acts_for(&self, user_id: &str, scope: &str) -> PixelleResult<()> {
+        match self {
+            Caller::User(user) => user.ensure_is(user_id),
+            Caller::Service(service) => service.require_scope(scope),
+        }
+    }
+}
+
+/// An authentication failure, answered with its status and a JSON error
+#[derive(Debug)]
+pub struct AuthRejection(pub PixelleError);
+
+impl fmt::Display for AuthRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ResponseError for AuthRejection {
+    fn error_response(&self) -> HttpResponse {
+        let status = actix_web::http::StatusCode::from_u16(self.0.status_code())
+            .unwrap_or(actix_web::http::StatusCode::UNAUTHORIZED);
+        HttpResponse::build(status).json(serde_json::json!({ "error": self.0.to_string() }))
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = AuthRejection;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthenticatedUser>()
+                .cloned()
+                .ok_or_else(|| AuthRejection(PixelleError::Authentication("Authentication required".to_string()))),
+        )
+    }
+}
+
+impl FromRequest for AuthenticatedService {
+    type Error = AuthRejection;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthenticatedService>()
+                .cloned()
+                .ok_or_else(|| AuthRejection(PixelleError::Authentication("Service token required".to_string()))),
+        )
+    }
+}
+
+impl FromRequest for Caller {
+    type Error = AuthRejection;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let extensions = req.extensions();
+        let caller = match extensions.get::<AuthenticatedUser>() {
+            Some(user) => Some(Caller::User(user.clone())),
+            None => extensions.get::<AuthenticatedService>().cloned().map(Caller::Service),
+        };
+        ready(caller.ok_or_else(|| AuthRejection(PixelleError::Authentication("Authentication required".to_string()))))
+    }
+}
+
+/// Validates bearer tokens and API keys and makes the caller available to
+/// handlers as [`AuthenticatedUser`] or [`AuthenticatedService`]
+///
+/// Requests without a token continue anonymously, so handlers decide whether
+/// they need a caller; an invalid or revoked token is rejected with 401, and
+/// an API key over its rate limit with 429.
+///
+/// API keys are only accepted by services that name the resource they serve
+/// with [`Authenticate::scoped`]. Reads then need `<resource>:read` and
+/// everything else `<resource>:write`.
+pub struct Authenticate {
+    validator: Arc<TokenValidator>,
+    resource: Option<Rc<str>>,
+}
+
+impl Authenticate {
+    pub fn new(validator: Arc<TokenValidator>) -> Self {
+        Self { validator, resource: None }
+    }
+
+    /// Accept API keys with scopes on `resource`, e.g. `users`
+    pub fn scoped(mut self, resource: &str) -> Self {
+        self.resource = Some(Rc::from(resource));
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authenticate
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticateMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticateMiddleware {
+            service: Rc::new(service),
+            validator: self.validator.clone(),
+            resource: self.resource.clone(),
+        }))
+    }
+}
+
+pub struct AuthenticateMiddleware<S> {
+    service: Rc<S>,
+    validator: Arc<TokenValidator>,
+    resource: Option<Rc<str>>,
+}
+
+/// Scope an API key needs for a request to `resource`
+fn required_scope(resource: &str, method: &Method) -> String {
+    let access = if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) { "read" } else { "write" };
+    format!("{}:{}", resource, access)
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticateMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let reject = |req: ServiceRequest, e: PixelleError| {
+            let response = AuthRejection(e).error_response().map_into_right_body();
+            Box::pin(async move { Ok(req.into_response(response)) }) as Self::Future
+        };
+        let authorization = req
+            .headers()
+            .get(AUTHORIZATION)
+            .map(|value| value.to_str().unwrap_or_default().to_string());
+
+        if let Some(key) = authorization.as_deref().and_then(api_key_from_bearer) {
+            let Some(resource) = self.resource.clone() else {
+                return reject(req, PixelleError::Authorization("API keys are not accepted here".to_string()));
+            };
+            let key = key.to_string();
+            let validator = self.validator.clone();
+            let service = self.service.clone();
+            return Box::pin(async move {
+                let scope = required_scope(&resource, req.method());
+                let user = validator.validate_api_key(&key).await.and_then(|grant| {
+                    if scopes_allow(&grant.scopes, &scope) {
+                        Ok(AuthenticatedUser::from_api_key(&grant))
+                    } else {
+                        Err(PixelleError::Authorization(format!("API key lacks scope {}", scope)))
+                    }
+                });
+                match user {
+                    Ok(user) => {
+                        req.extensions_mut().insert(user);
+                    }
+                    Err(e) => {
+                        let response = AuthRejection(e).error_response().map_into_right_body();
+                        return Ok(req.into_response(response));
+                    }
+                }
+                Ok(service.call(req).await?.map_into_left_body())
+            });
+        }
+
+        if let Some(authorization) = authorization {
+            let claims = match self.validator.validate_bearer(&authorization) {
+                Ok(claims) => claims,
+                Err(e) => return reject(req, e),
+            };
+            match &claims.client_id {
+                Some(client_id) => {
+                    req.extensions_mut().insert(AuthenticatedService {
+                        client_id: client_id.clone(),
+                        scopes: claims.scopes.clone(),
+                    });
+                }
+                None => match AuthenticatedUser::from_claims(&claims) {
+                    Ok(user) => {
+                        req.extensions_mut().insert(user);
+                    }
+                    Err(e) => return reject(req, e),
+                },
+            }
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { Ok(fut.await?.map_into_left_body()) })
+    }
+}
+
+/// Answers 403 unless the caller holds a permission, e.g. on an admin scope
+///
+/// Wrap it inside [`Authenticate`], which must run first: anonymous
+/// requests are answered 401, and service tokens, which hold no roles, 403.
+/// Checks that depend on the resource belong in handlers, through
+/// [`AuthenticatedUser::require`].
+pub struct RequirePermission {
+    permission: Permission,
+    policy: Arc<dyn PolicyEngine>,
+}
+
+impl RequirePermission {
+    pub fn new(permission: Permission) -> Self {
+        Self::with_policy(permission, Arc::new(RolePolicy))
+    }
+
+    pub fn with_policy(permission: Permission, policy: Arc<dyn PolicyEngine>) -> Self {
+        Self { permission, policy }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequirePermission
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequirePermissionMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequirePermissionMiddleware {
+            service: Rc::new(service),
+            permission: self.permission,
+            policy: self.policy.clone(),
+        }))
+    }
+}
+
+pub struct RequirePermissionMiddleware<S> {
+    service: Rc<S>,
+    permission: Permission,
+    policy: Arc<dyn PolicyEngine>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequirePermissionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let decision = {
+            let extensions = req.extensions();
+            match extensions.get::<AuthenticatedUser>() {
+                Some(user) => self.policy.evaluate(&user.subject(), self.permission, None).into_result(),
+                None if extensions.contains::<AuthenticatedService>() => Err(PixelleError::Authorization(format!(
+                    "Service tokens do not hold {}",
+                    self.permission
+                ))),
+                None => Err(PixelleError::Authentication("Authentication required".to_string())),
+            }
+        };
+        if let Err(e) = decision {
+            let response = AuthRejection(e).error_response().map_into_right_body();
+            return Box::pin(async move { Ok(req.into_response(response)) });
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { Ok(fut.await?.map_into_left_body()) })
+    }
+}
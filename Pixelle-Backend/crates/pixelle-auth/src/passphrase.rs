@@ -1,8 +1,14 @@
-use argon2::{Argon2, PassphraseHash, PassphraseHasher, PassphraseVerifier};
-use argon2::passphrase_hash::rand_core::OsRng;
-use argon2::passphrase_hash::SaltString;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
 use pixelle_core::{PixelleResult, PixelleError};
+use std::sync::OnceLock;
 
+/// Hash of a passphrase nobody knows, checked against when no account
+/// matches a login
+static ABSENT_ACCOUNT_HASH: OnceLock<String> = OnceLock::new();
+
+#[derive(Default)]
 pub struct PassphraseService;
 
 impl PassphraseService {
@@ -10,25 +16,39 @@ impl PassphraseService {
         Self
     }
 
-    struct hash_passphrase(&self, passphrase: &str) -> PixelleResult<String> {
+    pub async fn hash_passphrase(&self, passphrase: &str) -> PixelleResult<String> {
         let salt = SaltString::generate(&mut OsRng);
         let argon2 = Argon2::default();
         
         let passphrase_hash = argon2
-            .hash_passphrase(passphrase.as_bytes(), &salt)
+            .hash_password(passphrase.as_bytes(), &salt)
             .map_err(|e| PixelleError::Internal(format!("Passphrase hashing error: {}", e)))?;
 
         Ok(passphrase_hash.to_string())
     }
 
     pub async fn verify_passphrase(&self, passphrase: &str, hash: &str) -> PixelleResult<bool> {
-        let parsed_hash = PassphraseHash::new(hash)
+        let parsed_hash = PasswordHash::new(hash)
             .map_err(|e| PixelleError::Internal(format!("Invalid passphrase hash: {}", e)))?;
 
         let result = Argon2::default()
-            .verify_passphrase(passphrase.as_bytes(), &parsed_hash)
+            .verify_password(passphrase.as_bytes(), &parsed_hash)
             .is_ok();
 
         Ok(result)
     }
+
+    /// Spend the time of a verification when no account matches, so
+    /// response times don't reveal which usernames and emails exist
+    pub async fn verify_absent_account(&self, passphrase: &str) -> PixelleResult<()> {
+        let hash = match ABSENT_ACCOUNT_HASH.get() {
+            Some(hash) => hash,
+            None => {
+                let hash = self.hash_passphrase(&uuid::Uuid::new_v4().to_string()).await?;
+                ABSENT_ACCOUNT_HASH.get_or_init(|| hash)
+            }
+        };
+        self.verify_passphrase(passphrase, hash).await?;
+        Ok(())
+    }
 }
@@ -0,0 +1,222 @@
+use crate::accounts::REVOCATIONS_REPOSITORY;
+use crate::jwt::AccessClaims;
+use chrono::{DateTime, Duration, Utc};
+use pixelle_core::ACCESS_TOKEN_TTL_MINUTES;
+use pixelle_database::{Backends, Capability, DocumentRepository, StoreQuery, StoreResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Sessions and users whose access tokens must be refused before they expire
+///
+/// Access tokens are short-lived, so an entry only has to outlive the tokens it
+/// revokes and the list stays small enough to poll from every region.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevocationList {
+    /// Bumped on every change so validators can tell whether anything changed
+    pub version: u64,
+    /// Revoked session ID to when the entry can be dropped, in unix seconds
+    pub sessions: HashMap<String, i64>,
+    /// User ID to the revocation time; tokens issued before it are refused
+    ///
+    /// Token issue times are whole seconds, so a token issued in the second of
+    /// the revocation is let through; revoke its session to refuse it as well.
+    pub users: HashMap<String, i64>,
+}
+
+impl RevocationList {
+    fn token_ttl() -> Duration {
+        Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)
+    }
+
+    /// Refuse every token of a session, e.g. on logout
+    pub fn revoke_session(&mut self, session_id: &str, now: DateTime<Utc>) {
+        self.sessions.insert(session_id.to_string(), (now + Self::token_ttl()).timestamp());
+        self.version += 1;
+    }
+
+    /// Refuse every token a user holds, e.g. after a password change
+    pub fn revoke_user(&mut self, user_id: &str, now: DateTime<Utc>) {
+        self.users.insert(user_id.to_string(), now.timestamp());
+        self.version += 1;
+    }
+
+    /// Add the entries of `other`, keeping the later of two for the same
+    /// session or user; entries only leave the list when pruned
+    pub fn merge(&mut self, other: &RevocationList) {
+        let mut changed = false;
+        for (entries, others) in [(&mut self.sessions, &other.sessions), (&mut self.users, &other.users)] {
+            for (id, &time) in others {
+                let entry = entries.entry(id.clone()).or_insert(i64::MIN);
+                if *entry < time {
+                    *entry = time;
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            self.version += 1;
+        }
+    }
+
+    pub fn is_revoked(&self, claims: &AccessClaims) -> bool {
+        self.sessions.contains_key(&claims.sid) || self.users.get(&claims.sub).is_some_and(|revoked| claims.iat < *revoked)
+    }
+
+    /// Drop entries no unexpired token can match
+    pub fn prune(&mut self, now: DateTime<Utc>) -> usize {
+        let before = self.sessions.len() + self.users.len();
+        let now = now.timestamp();
+        let ttl = Self::token_ttl().num_seconds();
+        self.sessions.retain(|_, expires| *expires > now);
+        self.users.retain(|_, revoked| *revoked + ttl > now);
+
+        let removed = before - self.sessions.len() - self.users.len();
+        if removed > 0 {
+            self.version += 1;
+        }
+        removed
+    }
+}
+
+/// What a stored revocation refuses the tokens of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationKind {
+    Session,
+    User,
+}
+
+/// One entry of a [`RevocationList`] as stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revocation {
+    pub kind: RevocationKind,
+    /// Session or user ID
+    pub subject: String,
+    pub revoked_at: DateTime<Utc>,
+}
+
+impl Revocation {
+    fn id(&self) -> String {
+        let kind = match self.kind {
+            RevocationKind::Session => "session",
+            RevocationKind::User => "user",
+        };
+        format!("{}:{}", kind, self.subject)
+    }
+}
+
+/// Revocations in the `token_revocations` repository
+///
+/// Every auth-service instance of a region writes and serves the same list,
+/// so a revocation is published whichever instance recorded it and survives
+/// restarts.
+pub struct RevocationStore {
+    revocations: DocumentRepository<Revocation>,
+}
+
+impl RevocationStore {
+    pub fn new(backends: &Backends) -> StoreResult<Self> {
+        Ok(Self {
+            revocations: backends.repository(REVOCATIONS_REPOSITORY, &[Capability::Durable])?,
+        })
+    }
+
+    /// Record a revocation; a later one of the same subject replaces it
+    pub async fn record(&self, kind: RevocationKind, subject: &str, now: DateTime<Utc>) -> StoreResult<()> {
+        let revocation = Revocation { kind, subject: subject.to_string(), revoked_at: now };
+        self.revocations.put(&revocation.id(), &revocation).await
+    }
+
+    /// The stored entries no unexpired token can match yet, as a list
+    pub async fn load(&self, now: DateTime<Utc>) -> StoreResult<RevocationList> {
+        let mut list = RevocationList::default();
+        for revocation in self.revocations.find(&StoreQuery::new()).await? {
+            match revocation.kind {
+                RevocationKind::Session => list.revoke_session(&revocation.subject, revocation.revoked_at),
+                RevocationKind::User => list.revoke_user(&revocation.subject, revocation.revoked_at),
+            }
+        }
+        list.prune(now);
+        Ok(list)
+    }
+
+    /// Delete entries no unexpired token can match
+    pub async fn prune(&self, now: DateTime<Utc>) -> StoreResult<usize> {
+        let cutoff = now - RevocationList::token_ttl();
+        let mut removed = 0;
+        for revocation in self.revocations.find(&StoreQuery::new()).await? {
+            if revocation.revoked_at <= cutoff && self.revocations.delete(&revocation.id()).await? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixelle_database::InMemoryStore;
+    use std::sync::Arc;
+
+    fn claims(user_id: &str, session_id: &str, iat: i64) -> AccessClaims {
+        AccessClaims {
+            sub: user_id.to_string(),
+            sid: session_id.to_string(),
+            region: "default".to_string(),
+            iss: "pixelle-auth/default".to_string(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            roles: Vec::new(),
+            client_id: None,
+            scopes: Vec::new(),
+            exp: iat + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES).num_seconds(),
+            iat,
+        }
+    }
+
+    fn store() -> RevocationStore {
+        RevocationStore {
+            revocations: DocumentRepository::new(Arc::new(InMemoryStore::new()), REVOCATIONS_REPOSITORY).unwrap(),
+        }
+    }
+
+    #[test]
+    fn tokens_issued_in_the_second_of_a_user_revocation_pass() {
+        let now = Utc::now();
+        let mut list = RevocationList::default();
+        list.revoke_user("user", now);
+        list.revoke_session("old", now);
+
+        assert!(list.is_revoked(&claims("user", "older", now.timestamp() - 1)));
+        assert!(list.is_revoked(&claims("user", "old", now.timestamp())));
+        assert!(!list.is_revoked(&claims("user", "new", now.timestamp())));
+        assert!(!list.is_revoked(&claims("other", "older", now.timestamp() - 1)));
+    }
+
+    #[tokio::test]
+    async fn stored_revocations_are_shared_until_they_expire() {
+        let store = store();
+        let now = Utc::now();
+        let expired = now - Duration::minutes(ACCESS_TOKEN_TTL_MINUTES + 1);
+        store.record(RevocationKind::Session, "session", now).await.unwrap();
+        store.record(RevocationKind::User, "user", now).await.unwrap();
+        store.record(RevocationKind::Session, "ended", expired).await.unwrap();
+
+        let list = store.load(now).await.unwrap();
+        assert!(list.is_revoked(&claims("someone", "session", now.timestamp())));
+        assert!(list.is_revoked(&claims("user", "any", now.timestamp() - 1)));
+        assert!(!list.sessions.contains_key("ended"));
+
+        assert_eq!(store.prune(now).await.unwrap(), 1);
+        assert_eq!(store.load(now).await.unwrap().sessions.len(), 1);
+
+        let mut cached = RevocationList::default();
+        cached.revoke_session("local", now);
+        cached.merge(&list);
+        let version = cached.version;
+        cached.merge(&list);
+        assert_eq!(cached.version, version);
+        assert_eq!(cached.sessions.len(), 2);
+        assert!(cached.users.contains_key("user"));
+    }
+}
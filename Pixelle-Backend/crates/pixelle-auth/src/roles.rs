@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use pixelle_core::{PixelleResult, Role, RoleAssignment, RoleRepository, UserId};
+use pixelle_database::{Backends, Capability, DocumentRepository, StoreQuery, StoreResult};
+
+use crate::accounts::{store_error, ROLES_REPOSITORY};
+
+/// Role assignments in the `roles` repository, stored under the user ID
+///
+/// Only accounts that were given roles explicitly have a document, so
+/// listing the holders of a role scans a small collection.
+pub struct RoleStore {
+    assignments: DocumentRepository<RoleAssignment>,
+}
+
+impl RoleStore {
+    pub fn new(backends: &Backends) -> StoreResult<Self> {
+        Ok(Self {
+            assignments: backends.repository(ROLES_REPOSITORY, &[Capability::Durable])?,
+        })
+    }
+}
+
+#[async_trait]
+impl RoleRepository for RoleStore {
+    async fn get_roles(&self, user_id: UserId) -> PixelleResult<Option<RoleAssignment>> {
+        self.assignments.get(&user_id.to_string()).await.map_err(store_error)
+    }
+
+    async fn save_roles(&self, assignment: &RoleAssignment) -> PixelleResult<()> {
+        self.assignments
+            .put(&assignment.user_id.to_string(), assignment)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn list_with_role(&self, role: Role) -> PixelleResult<Vec<RoleAssignment>> {
+        let mut assignments = self.assignments.find(&StoreQuery::new()).await.map_err(store_error)?;
+        assignments.retain(|assignment| assignment.roles.contains(&role));
+        assignments.sort_by_key(|assignment| assignment.updated_at);
+        Ok(assignments)
+    }
+}
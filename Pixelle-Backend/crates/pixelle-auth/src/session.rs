@@ -1,61 +1,133 @@
-use pixelle_core::{PixelleResult, PixelleError};
-use std::collections::HashMap;
-use std::sync::Mutex;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use pixelle_core::{PixelleResult, UserId, REFRESH_TOKEN_EXPIRATION_DAYS, SESSION_LAST_SEEN_INTERVAL_MINUTES};
+use pixelle_database::{DocumentRepository, StoreQuery, Transaction};
+use serde::{Deserialize, Serialize};
 
-pub struct SessionService {
-    sessions: Mutex<HashMap<String, SessionData>>,
+use crate::accounts::store_error;
+
+/// Device a session was signed in from, as reported by the client
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionDevice {
+    /// Identifier the client derives for the device, stable across sign-ins
+    pub fingerprint: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// A signed-in device; its access tokens carry the session ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub user_id: UserId,
+    #[serde(default)]
+    pub device: SessionDevice,
+    /// Client IP at sign-in, then at the last recorded use
+    #[serde(default)]
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Recorded at most every `SESSION_LAST_SEEN_INTERVAL_MINUTES`; unset on
+    /// sessions created before it was tracked
+    #[serde(default)]
+    pub last_seen_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn last_seen(&self) -> DateTime<Utc> {
+        self.last_seen_at.unwrap_or(self.created_at)
+    }
 }
 
-struct SessionData {
-    user_id: String,
-    expires_at: DateTime<Utc>,
+/// Sessions in the `sessions` repository
+///
+/// Expired sessions are deleted when they are next looked up.
+pub struct SessionService {
+    sessions: DocumentRepository<Session>,
 }
 
 impl SessionService {
-    pub fn new() -> Self {
-        Self {
-            sessions: Mutex::new(HashMap::new()),
-        }
+    pub fn new(sessions: DocumentRepository<Session>) -> Self {
+        Self { sessions }
     }
 
-    pub async fn create_session(&self, user_id: &str, expires_at: DateTime<Utc>) -> PixelleResult<String> {
-        let session_id = uuid::Uuid::new_v4().to_string();
-        let session_data = SessionData {
-            user_id: user_id.to_string(),
-            expires_at,
+    pub async fn create_session(&self, user_id: UserId, device: SessionDevice, ip: Option<String>) -> PixelleResult<Session> {
+        let now = Utc::now();
+        let session = Session {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id,
+            device,
+            ip,
+            created_at: now,
+            last_seen_at: Some(now),
+            expires_at: now + Duration::days(REFRESH_TOKEN_EXPIRATION_DAYS),
         };
+        self.sessions.put(&session.id, &session).await.map_err(store_error)?;
+        Ok(session)
+    }
 
-        if let Ok(mut sessions) = self.sessions.lock() {
-            sessions.insert(session_id.clone(), session_data);
+    /// The session if it exists and has not expired
+    pub async fn get_session(&self, session_id: &str) -> PixelleResult<Option<Session>> {
+        match self.sessions.get(session_id).await.map_err(store_error)? {
+            Some(session) if session.expires_at > Utc::now() => Ok(Some(session)),
+            Some(_) => {
+                self.revoke_session(session_id).await?;
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Record that a session was used, from `ip` if known; only written when
+    /// the IP changed or the last record is older than the interval
+    pub async fn touch_session(&self, mut session: Session, ip: Option<&str>) -> PixelleResult<Session> {
+        let now = Utc::now();
+        let moved = ip.is_some_and(|ip| session.ip.as_deref() != Some(ip));
+        if !moved && now - session.last_seen() < Duration::minutes(SESSION_LAST_SEEN_INTERVAL_MINUTES) {
+            return Ok(session);
         }
 
-        Ok(session_id)
+        if let Some(ip) = ip {
+            session.ip = Some(ip.to_string());
+        }
+        session.last_seen_at = Some(now);
+        self.sessions.put(&session.id, &session).await.map_err(store_error)?;
+        Ok(session)
     }
 
-    pub async fn get_session(&self, session_id: &str) -> PixelleResult<Option<String>> {
-        if let Ok(sessions) = self.sessions.lock() {
-            if let Some(session_data) = sessions.get(session_id) {
-                if session_data.expires_at > Utc::now() {
-                    return Ok(Some(session_data.user_id.clone()));
-                }
+    /// Unexpired sessions of a user, most recently used first
+    pub async fn list_sessions(&self, user_id: UserId) -> PixelleResult<Vec<Session>> {
+        let query = StoreQuery::new().filter("user_id", user_id.to_string());
+        let now = Utc::now();
+        let mut sessions = Vec::new();
+        for session in self.sessions.find(&query).await.map_err(store_error)? {
+            if session.expires_at > now {
+                sessions.push(session);
+            } else {
+                self.revoke_session(&session.id).await?;
             }
         }
-        Ok(None)
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen()));
+        Ok(sessions)
     }
 
     pub async fn revoke_session(&self, session_id: &str) -> PixelleResult<()> {
-        if let Ok(mut sessions) = self.sessions.lock() {
-            sessions.remove(session_id);
-        }
+        self.sessions.delete(session_id).await.map_err(store_error)?;
         Ok(())
     }
 
-    pub async fn cleanup_expired_sessions(&self) -> PixelleResult<()> {
-        let now = Utc::now();
-        if let Ok(mut sessions) = self.sessions.lock() {
-            sessions.retain(|_, session_data| session_data.expires_at > now);
+    /// Start a transaction on the backend sessions are stored on
+    pub fn transaction(&self) -> PixelleResult<Transaction> {
+        self.sessions.transaction().map_err(store_error)
+    }
+
+    /// Add deleting every session of a user to a transaction, e.g. on
+    /// password reset; returns the IDs of the sessions
+    pub async fn revoke_user_sessions(&self, user_id: UserId, transaction: &mut Transaction) -> PixelleResult<Vec<String>> {
+        let query = StoreQuery::new().filter("user_id", user_id.to_string());
+        let mut session_ids = Vec::new();
+        for session in self.sessions.find(&query).await.map_err(store_error)? {
+            transaction.delete(&self.sessions, &session.id).map_err(store_error)?;
+            session_ids.push(session.id);
         }
-        Ok(())
+        Ok(session_ids)
     }
 }
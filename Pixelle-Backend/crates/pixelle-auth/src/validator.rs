@@ -0,0 +1,284 @@
+use crate::accounts::token_key;
+use crate::api_keys::{split_api_key, ApiKeyGrant};
+use crate::jwt::{decode_access_token, AccessClaims};
+use crate::keys::Jwks;
+use crate::revocation::RevocationList;
+use jsonwebtoken::DecodingKey;
+use pixelle_core::{PixelleError, PixelleResult, API_KEY_CACHE_SECONDS, JWKS_REFRESH_INTERVAL_SECONDS};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Cached API keys and rate limit windows before stale ones are pruned
+const API_KEY_PRUNE_THRESHOLD: usize = 10_000;
+
+/// Signing keys and revocations published by one region's auth-service
+#[derive(Default)]
+struct SourceState {
+    keys: HashMap<String, DecodingKey>,
+    revocations: RevocationList,
+}
+
+/// Grant an auth-service returned for an API key
+struct CachedApiKey {
+    grant: ApiKeyGrant,
+    fetched_at: Instant,
+}
+
+/// Requests made with an API key in the current minute
+struct RateWindow {
+    started: Instant,
+    count: u32,
+}
+
+/// Validates access tokens locally, without calling auth-service per request
+///
+/// Keys and revocation lists are pulled from the auth-service of every region
+/// in the background, so a token issued in one region is accepted in all of
+/// them. When a region cannot be reached its last known state is kept.
+///
+/// API keys are looked up at the auth-services on first use and cached for
+/// `API_KEY_CACHE_SECONDS`, keyed by their hash. Unknown keys are not cached,
+/// so guessed keys cannot fill the cache and a key works as soon as it is
+/// issued.
+pub struct TokenValidator {
+    /// Base URLs of the auth-services whose tokens are trusted
+    sources: Vec<String>,
+    state: RwLock<Vec<SourceState>>,
+    api_keys: RwLock<HashMap<String, CachedApiKey>>,
+    /// Rate limit windows by API key ID
+    windows: Mutex<HashMap<String, RateWindow>>,
+    client: reqwest::Client,
+}
+
+impl TokenValidator {
+    pub fn new(sources: Vec<String>) -> Self {
+        let sources: Vec<String> = sources.into_iter().map(|source| source.trim_end_matches('/').to_string()).collect();
+        Self {
+            state: RwLock::new(sources.iter().map(|_| SourceState::default()).collect()),
+            api_keys: RwLock::new(HashMap::new()),
+            windows: Mutex::new(HashMap::new()),
+            sources,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Trust the auth-services listed in `AUTH_SERVICE_URLS`, falling back to `default_source`
+    pub fn from_env(default_source: &str) -> Self {
+        let sources = env::var("AUTH_SERVICE_URLS")
+            .ok()
+            .map(|urls| urls.split(',').map(|url| url.trim().to_string()).filter(|url| !url.is_empty()).collect::<Vec<_>>())
+            .filter(|urls| !urls.is_empty())
+            .unwrap_or_else(|| vec![default_source.to_string()]);
+        Self::new(sources)
+    }
+
+    /// Fetch keys and revocations from every source
+    ///
+    /// Succeeds if at least one source answered, so one unreachable region does
+    /// not stop validation of tokens from the others.
+    pub async fn refresh(&self) -> PixelleResult<()> {
+        let mut failures = Vec::new();
+        for (index, source) in self.sources.iter().enumerate() {
+            match self.fetch(source).await {
+                Ok(state) => self.state.write().unwrap()[index] = state,
+                Err(e) => {
+                    tracing::warn!("Failed to refresh signing keys from {}: {}", source, e);
+                    failures.push(e);
+                }
+            }
+        }
+
+        if !self.sources.is_empty() && failures.len() == self.sources.len() {
+            return Err(PixelleError::ExternalService(format!(
+                "No auth-service reachable: {}",
+                failures.into_iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; ")
+            )));
+        }
+        Ok(())
+    }
+
+    async fn fetch(&self, source: &str) -> PixelleResult<SourceState> {
+        let get = |path: &str| {
+            let url = format!("{}{}", source, path);
+            let client = self.client.clone();
+            async move {
+                client
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| PixelleError::ExternalService(format!("{}: {}", url, e)))
+            }
+        };
+
+        let jwks: Jwks = get("/.well-known/jwks.json").await?
+            .json()
+            .await
+            .map_err(|e| PixelleError::ExternalService(format!("Invalid JWKS from {}: {}", source, e)))?;
+        let revocations: RevocationList = get("/api/v1/auth/revocations").await?
+            .json()
+            .await
+            .map_err(|e| PixelleError::ExternalService(format!("Invalid revocation list from {}: {}", source, e)))?;
+
+        let mut keys = HashMap::new();
+        for jwk in &jwks.keys {
+            keys.insert(jwk.kid.clone(), jwk.decoding_key()?);
+        }
+        Ok(SourceState { keys, revocations })
+    }
+
+    /// Refresh on an interval for as long as the validator is alive
+    pub fn spawn_refresh(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(JWKS_REFRESH_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.refresh().await {
+                    tracing::error!("Token validation keys are stale: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Claims of a valid, unrevoked token
+    pub fn validate(&self, token: &str) -> PixelleResult<AccessClaims> {
+        let state = self.state.read().unwrap();
+        let claims = decode_access_token(
+            token,
+            |kid| state.iter().find_map(|source| source.keys.get(kid).cloned()),
+            true,
+        )?;
+        if state.iter().any(|source| source.revocations.is_revoked(&claims)) {
+            return Err(PixelleError::Authentication("Token has been revoked".to_string()));
+        }
+        Ok(claims)
+    }
+
+    /// Validate the value of an `Authorization: Bearer` header
+    pub fn validate_bearer(&self, header: &str) -> PixelleResult<AccessClaims> {
+        let token = header
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| PixelleError::Authentication("Expected a bearer token".to_string()))?;
+        self.validate(token.trim())
+    }
+
+    /// Grant of a valid API key, counting the request against the key's
+    /// rate limit
+    pub async fn validate_api_key(&self, key: &str) -> PixelleResult<ApiKeyGrant> {
+        let grant = self.api_key_grant(key).await?;
+        self.count_api_key_request(&grant)?;
+        Ok(grant)
+    }
+
+    /// Grant of a valid API key, without counting the request; for proxies
+    /// that leave rate limits and scopes to the service behind them
+    pub async fn api_key_grant(&self, key: &str) -> PixelleResult<ApiKeyGrant> {
+        let invalid = || PixelleError::Authentication("Invalid or expired API key".to_string());
+        if split_api_key(key).is_none() {
+            return Err(invalid());
+        }
+        let hash = token_key(key);
+        let cache_for = Duration::from_secs(API_KEY_CACHE_SECONDS);
+        let cached = self
+            .api_keys
+            .read()
+            .unwrap()
+            .get(&hash)
+            .filter(|cached| cached.fetched_at.elapsed() < cache_for)
+            .map(|cached| cached.grant.clone());
+        let grant = match cached {
+            Some(grant) => grant,
+            None => {
+                let grant = self.lookup_api_key(key).await?.ok_or_else(invalid)?;
+                let mut api_keys = self.api_keys.write().unwrap();
+                if api_keys.len() >= API_KEY_PRUNE_THRESHOLD {
+                    api_keys.retain(|_, cached| cached.fetched_at.elapsed() < cache_for);
+                }
+                api_keys.insert(hash, CachedApiKey { grant: grant.clone(), fetched_at: Instant::now() });
+                grant
+            }
+        };
+
+        if grant.expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now()) {
+            return Err(invalid());
+        }
+        Ok(grant)
+    }
+
+    /// Ask each auth-service in turn, since a key is stored in the region
+    /// it was created in
+    async fn lookup_api_key(&self, key: &str) -> PixelleResult<Option<ApiKeyGrant>> {
+        let mut failures = Vec::new();
+        for source in &self.sources {
+            let url = format!("{}/api/v1/auth/api-keys/verify", source);
+            let response = self
+                .client
+                .post(&url)
+                .json(&serde_json::json!({ "key": key }))
+                .send()
+                .await
+                .map_err(|e| PixelleError::ExternalService(format!("{}: {}", url, e)));
+            match response {
+                Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => continue,
+                Ok(response) if response.status().is_success() => {
+                    let grant = response
+                        .json()
+                        .await
+                        .map_err(|e| PixelleError::ExternalService(format!("Invalid API key grant from {}: {}", source, e)))?;
+                    return Ok(Some(grant));
+                }
+                Ok(response) => failures.push(format!("{}: {}", url, response.status())),
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+
+        // A region that did not answer may hold the key
+        if !failures.is_empty() {
+            return Err(PixelleError::ExternalService(format!("Cannot look up API key: {}", failures.join("; "))));
+        }
+        Ok(None)
+    }
+
+    /// Count a request in the key's fixed one-minute window
+    fn count_api_key_request(&self, grant: &ApiKeyGrant) -> PixelleResult<()> {
+        let now = Instant::now();
+        let minute = Duration::from_secs(60);
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= API_KEY_PRUNE_THRESHOLD {
+            windows.retain(|_, window| now.duration_since(window.started) < minute);
+        }
+
+        let window = windows
+            .entry(grant.key_id.clone())
+            .or_insert(RateWindow { started: now, count: 0 });
+        if now.duration_since(window.started) >= minute {
+            *window = RateWindow { started: now, count: 0 };
+        }
+        if window.count >= grant.rate_limit_per_minute {
+            return Err(PixelleError::RateLimitExceeded);
+        }
+        window.count += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::API_KEY_PREFIX;
+
+    #[tokio::test]
+    async fn unknown_api_keys_are_not_cached() {
+        let validator = TokenValidator::new(Vec::new());
+        let key = format!("{}{}_{}", API_KEY_PREFIX, "0".repeat(32), "a".repeat(43));
+        assert!(split_api_key(&key).is_some());
+
+        assert!(validator.api_key_grant(&key).await.is_err());
+        assert!(validator.api_keys.read().unwrap().is_empty());
+    }
+}
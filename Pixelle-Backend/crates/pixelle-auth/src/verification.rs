@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use pixelle_core::{
+    PixelleError, PixelleResult, UserId, VerificationEvent, VerificationEventSink, VerificationRepository,
+    VerificationRequest, VerificationStatus,
+};
+use pixelle_database::{Backends, Capability, DocumentRepository, StoreQuery, StoreResult};
+
+use crate::accounts::{store_error, AccountStore, VERIFICATIONS_REPOSITORY};
+
+/// Verification requests in the `verifications` repository under the
+/// user's ID
+pub struct VerificationStore {
+    requests: DocumentRepository<VerificationRequest>,
+}
+
+impl VerificationStore {
+    pub fn new(backends: &Backends) -> StoreResult<Self> {
+        Ok(Self {
+            requests: backends.repository(VERIFICATIONS_REPOSITORY, &[Capability::Durable])?,
+        })
+    }
+}
+
+#[async_trait]
+impl VerificationRepository for VerificationStore {
+    async fn get_verification(&self, user_id: UserId) -> PixelleResult<Option<VerificationRequest>> {
+        self.requests.get(&user_id.to_string()).await.map_err(store_error)
+    }
+
+    async fn save_verification(&self, request: &VerificationRequest) -> PixelleResult<()> {
+        self.requests
+            .put(&request.user_id.to_string(), request)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn list_verifications_with_status(&self, status: VerificationStatus) -> PixelleResult<Vec<VerificationRequest>> {
+        let status = serde_json::to_value(status).map_err(PixelleError::Serialization)?;
+        let query = StoreQuery::new().filter("status", status);
+        self.requests.find(&query).await.map_err(store_error)
+    }
+
+    async fn list_verifications_holding_documents(&self) -> PixelleResult<Vec<VerificationRequest>> {
+        let query = StoreQuery::new().filter("documents_held", true);
+        self.requests.find(&query).await.map_err(store_error)
+    }
+}
+
+/// Keeps the verified badge of the profiles in the `users` repository in
+/// step with approvals and revocations
+#[async_trait]
+impl VerificationEventSink for AccountStore {
+    async fn record(&self, event: &VerificationEvent) -> PixelleResult<()> {
+        let Some(verified) = event.verified() else {
+            return Ok(());
+        };
+        let user_id = event.request().user_id;
+        if !self.set_verified(user_id, verified).await? {
+            tracing::warn!("No profile for {} to set verified to {}", user_id, verified);
+        }
+        Ok(())
+    }
+}
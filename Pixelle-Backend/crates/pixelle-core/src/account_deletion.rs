@@ -0,0 +1,367 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::constants::ACCOUNT_DELETION_GRACE_DAYS;
+use crate::errors::{PixelleError, PixelleResult};
+use crate::traits::{AccountDeletionRepository, AccountMailer, PurgeStep};
+use crate::types::{Post, UserId, UserProfile};
+
+/// Where an account is in the deletion process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionStage {
+    /// Hidden everywhere and waiting out the grace period; can be reactivated
+    Deactivated,
+    /// The privacy pipeline is erasing the account's data
+    Purging,
+    /// All purge steps finished; nothing is left to restore
+    Deleted,
+    /// The owner came back during the grace period
+    Reactivated,
+}
+
+/// A user's request to delete their account and its progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletionRequest {
+    pub user_id: UserId,
+    /// Where stage emails go; cleared once the final email is sent
+    #[serde(skip_serializing)]
+    pub email: Option<String>,
+    pub stage: DeletionStage,
+    pub requested_at: DateTime<Utc>,
+    /// End of the grace period, after which the account is purged
+    pub purge_after: DateTime<Utc>,
+    /// Purge steps already finished, in pipeline order
+    pub completed_steps: Vec<String>,
+    pub total_steps: usize,
+    /// Error from the last purge attempt; the step is retried on the next sweep
+    pub last_error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DeletionRequest {
+    /// The account must not show up anywhere
+    pub fn hides_account(&self) -> bool {
+        self.stage != DeletionStage::Reactivated
+    }
+
+    /// The owner can still undo the deletion
+    pub fn can_reactivate(&self, now: DateTime<Utc>) -> bool {
+        self.stage == DeletionStage::Deactivated && now < self.purge_after
+    }
+
+    fn is_due(&self, now: DateTime<Utc>) -> bool {
+        match self.stage {
+            DeletionStage::Deactivated => now >= self.purge_after,
+            DeletionStage::Purging => true,
+            DeletionStage::Deleted | DeletionStage::Reactivated => false,
+        }
+    }
+}
+
+/// Emails sent as an account moves through deletion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeletionNotice {
+    Scheduled { purge_after: DateTime<Utc> },
+    Reactivated,
+    PurgeStarted,
+    Deleted,
+}
+
+impl DeletionNotice {
+    pub fn subject(&self) -> &'static str {
+        match self {
+            DeletionNotice::Scheduled { .. } => "Your Pixelle account is scheduled for deletion",
+            DeletionNotice::Reactivated => "Your Pixelle account has been reactivated",
+            DeletionNotice::PurgeStarted => "We are deleting your Pixelle account",
+            DeletionNotice::Deleted => "Your Pixelle account has been deleted",
+        }
+    }
+
+    pub fn body(&self) -> String {
+        match self {
+            DeletionNotice::Scheduled { purge_after } => format!(
+                "Your account has been deactivated and is hidden from other people. \
+                 It will be permanently deleted on {}. Sign in and reactivate it before then to keep it.",
+                purge_after.format("%Y-%m-%d %H:%M UTC")
+            ),
+            DeletionNotice::Reactivated => {
+                "Your account has been reactivated and is visible again. No data was deleted.".to_string()
+            }
+            DeletionNotice::PurgeStarted => {
+                "The grace period has ended and your account data is now being deleted. This can no longer be undone."
+                    .to_string()
+            }
+            DeletionNotice::Deleted => {
+                "Your account and its data have been deleted. This is the last email you will receive from us.".to_string()
+            }
+        }
+    }
+}
+
+/// Staged account deletion.
+///
+/// Deleting an account deactivates it straight away, waits out a grace period
+/// during which the owner can reactivate, then runs the registered purge
+/// steps in order. Progress is saved after every step so an interrupted purge
+/// resumes where it stopped; steps must therefore be idempotent.
+pub struct AccountDeletionService {
+    repository: Arc<dyn AccountDeletionRepository>,
+    mailer: Arc<dyn AccountMailer>,
+    steps: Vec<Arc<dyn PurgeStep>>,
+    grace_period: Duration,
+    /// Serializes stage changes so reactivation cannot race the start of a purge
+    transitions: tokio::sync::Mutex<()>,
+}
+
+impl AccountDeletionService {
+    pub fn new(repository: Arc<dyn AccountDeletionRepository>, mailer: Arc<dyn AccountMailer>) -> Self {
+        Self {
+            repository,
+            mailer,
+            steps: Vec::new(),
+            grace_period: Duration::days(ACCOUNT_DELETION_GRACE_DAYS),
+            transitions: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Append a step to the privacy pipeline
+    pub fn with_step(mut self, step: Arc<dyn PurgeStep>) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Deactivate `user` and schedule its deletion
+    pub async fn request_deletion(&self, user: &UserProfile) -> PixelleResult<DeletionRequest> {
+        let _guard = self.transitions.lock().await;
+
+        if let Some(existing) = self.repository.get_request(user.id).await? {
+            if existing.hides_account() {
+                return Err(PixelleError::Conflict("Account deletion is already in progress".to_string()));
+            }
+        }
+
+        let now = crate::utils::now();
+        let request = DeletionRequest {
+            user_id: user.id,
+            email: Some(user.email.clone()),
+            stage: DeletionStage::Deactivated,
+            requested_at: now,
+            purge_after: now + self.grace_period,
+            completed_steps: Vec::new(),
+            total_steps: self.steps.len(),
+            last_error: None,
+            updated_at: now,
+        };
+        self.repository.save_request(&request).await?;
+
+        tracing::info!("Account {} deactivated, deletion scheduled for {}", user.id, request.purge_after);
+        self.notify(&request, DeletionNotice::Scheduled { purge_after: request.purge_after }).await;
+        Ok(request)
+    }
+
+    /// Undo a pending deletion during the grace period
+    pub async fn reactivate(&self, user_id: UserId) -> PixelleResult<DeletionRequest> {
+        let _guard = self.transitions.lock().await;
+
+        let mut request = self.repository.get_request(user_id).await?
+            .filter(|request| request.hides_account())
+            .ok_or_else(|| PixelleError::NotFound("No pending account deletion".to_string()))?;
+
+        let now = crate::utils::now();
+        if !request.can_reactivate(now) {
+            return Err(PixelleError::Conflict("The grace period has ended; the account can no longer be reactivated".to_string()));
+        }
+
+        request.stage = DeletionStage::Reactivated;
+        request.updated_at = now;
+        self.repository.save_request(&request).await?;
+
+        tracing::info!("Account {} reactivated", user_id);
+        self.notify(&request, DeletionNotice::Reactivated).await;
+        Ok(request)
+    }
+
+    /// Latest deletion request for a user
+    pub async fn status(&self, user_id: UserId) -> PixelleResult<Option<DeletionRequest>> {
+        self.repository.get_request(user_id).await
+    }
+
+    /// Whether the account is deactivated or gone
+    pub async fn is_hidden(&self, user_id: UserId) -> PixelleResult<bool> {
+        Ok(self.repository.get_request(user_id).await?.is_some_and(|request| request.hides_account()))
+    }
+
+    /// Drop posts written by hidden accounts
+    pub async fn filter_posts(&self, posts: Vec<Post>) -> PixelleResult<Vec<Post>> {
+        let mut hidden: HashMap<UserId, bool> = HashMap::new();
+        let mut visible = Vec::with_capacity(posts.len());
+        for post in posts {
+            let is_hidden = match hidden.get(&post.author_id) {
+                Some(is_hidden) => *is_hidden,
+                None => {
+                    let is_hidden = self.is_hidden(post.author_id).await?;
+                    hidden.insert(post.author_id, is_hidden);
+                    is_hidden
+                }
+            };
+            if !is_hidden {
+                visible.push(post);
+            }
+        }
+        Ok(visible)
+    }
+
+    /// Drop hidden accounts from a list of users
+    pub async fn filter_users(&self, users: Vec<UserProfile>) -> PixelleResult<Vec<UserProfile>> {
+        let mut visible = Vec::with_capacity(users.len());
+        for user in users {
+            if !self.is_hidden(user.id).await? {
+                visible.push(user);
+            }
+        }
+        Ok(visible)
+    }
+
+    /// Purge every account whose grace period has ended, and resume
+    /// interrupted purges. Returns the requests that were worked on.
+    pub async fn run_due(&self) -> PixelleResult<Vec<DeletionRequest>> {
+        let due = self.repository.list_due(crate::utils::now()).await?;
+        let mut processed = Vec::with_capacity(due.len());
+        for request in due {
+            processed.push(self.purge(request.user_id).await?);
+        }
+        Ok(processed)
+    }
+
+    async fn purge(&self, user_id: UserId) -> PixelleResult<DeletionRequest> {
+        let mut request = {
+            let _guard = self.transitions.lock().await;
+            let Some(mut request) = self.repository.get_request(user_id).await? else {
+                return Err(PixelleError::NotFound("No pending account deletion".to_string()));
+            };
+            // Reactivated between listing and locking
+            if !request.is_due(crate::utils::now()) {
+                return Ok(request);
+            }
+            if request.stage == DeletionStage::Deactivated {
+                request.stage = DeletionStage::Purging;
+                request.total_steps = self.steps.len();
+                request.updated_at = crate::utils::now();
+                self.repository.save_request(&request).await?;
+                self.notify(&request, DeletionNotice::PurgeStarted).await;
+            }
+            request
+        };
+
+        for step in &self.steps {
+            if request.completed_steps.iter().any(|done| done == step.name()) {
+                continue;
+            }
+            match step.purge(user_id).await {
+                Ok(()) => {
+                    request.completed_steps.push(step.name().to_string());
+                    request.last_error = None;
+                    request.updated_at = crate::utils::now();
+                    self.repository.save_request(&request).await?;
+                    tracing::debug!("Purge step '{}' finished for account {}", step.name(), user_id);
+                }
+                Err(e) => {
+                    tracing::error!("Purge step '{}' failed for account {}: {}", step.name(), user_id, e);
+                    request.last_error = Some(format!("{}: {}", step.name(), e));
+                    request.updated_at = crate::utils::now();
+                    self.repository.save_request(&request).await?;
+                    return Ok(request);
+                }
+            }
+        }
+
+        request.stage = DeletionStage::Deleted;
+        request.updated_at = crate::utils::now();
+        self.repository.save_request(&request).await?;
+        self.notify(&request, DeletionNotice::Deleted).await;
+
+        // The address was only kept for the final email
+        request.email = None;
+        self.repository.save_request(&request).await?;
+
+        tracing::info!("Account {} deleted", user_id);
+        Ok(request)
+    }
+
+    /// Email the account owner; failures are logged and never block deletion
+    async fn notify(&self, request: &DeletionRequest, notice: DeletionNotice) {
+        let Some(email) = &request.email else {
+            return;
+        };
+        if let Err(e) = self.mailer.send(email, &notice).await {
+            tracing::warn!("Failed to send '{}' email for account {}: {}", notice.subject(), request.user_id, e);
+        }
+    }
+
+    /// Run `run_due` every `interval` until the task is aborted
+    pub fn spawn_sweeper(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_due().await {
+                    tracing::error!("Account deletion sweep failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// Deletion requests kept in process memory
+#[derive(Default)]
+pub struct InMemoryAccountDeletionRepository {
+    requests: Mutex<HashMap<UserId, DeletionRequest>>,
+}
+
+impl InMemoryAccountDeletionRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AccountDeletionRepository for InMemoryAccountDeletionRepository {
+    async fn save_request(&self, request: &DeletionRequest) -> PixelleResult<()> {
+        self.requests.lock().unwrap().insert(request.user_id, request.clone());
+        Ok(())
+    }
+
+    async fn get_request(&self, user_id: UserId) -> PixelleResult<Option<DeletionRequest>> {
+        Ok(self.requests.lock().unwrap().get(&user_id).cloned())
+    }
+
+    async fn list_due(&self, now: DateTime<Utc>) -> PixelleResult<Vec<DeletionRequest>> {
+        Ok(self.requests
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|request| request.is_due(now))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Mailer that only logs, for development when no SMTP server is configured
+#[derive(Default)]
+pub struct LogAccountMailer;
+
+#[async_trait]
+impl AccountMailer for LogAccountMailer {
+    async fn send(&self, to: &str, notice: &DeletionNotice) -> PixelleResult<()> {
+        tracing::info!("Account email to {}: {}", to, notice.subject());
+        Ok(())
+    }
+}
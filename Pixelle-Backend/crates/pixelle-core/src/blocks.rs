@@ -0,0 +1,311 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use crate::errors::{PixelleError, PixelleResult};
+use crate::traits::BlockListRepository;
+use crate::types::{Comment, Post, UserId, UserProfile};
+
+/// How long a cached block list is trusted before it is reloaded.
+///
+/// Changes made through the service invalidate the cache immediately; the TTL
+/// bounds staleness for changes that arrive from other instances.
+pub const BLOCK_LIST_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Buffered block list changes per subscriber
+const CHANGE_CHANNEL_CAPACITY: usize = 1024;
+
+/// Kind of relationship one user holds towards another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationKind {
+    /// Hides both users from each other everywhere
+    Block,
+    /// Hides the muted user's content from the muter only
+    Mute,
+}
+
+/// Everything that limits what one user sees of others.
+///
+/// Blocks apply in both directions: a user never sees content from people
+/// they blocked or from people who blocked them. Mutes are one-way and only
+/// quiet content, so muted users still show up in search.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockList {
+    pub user_id: UserId,
+    pub blocked: HashSet<UserId>,
+    pub blocked_by: HashSet<UserId>,
+    pub muted: HashSet<UserId>,
+}
+
+impl BlockList {
+    pub fn new(user_id: UserId) -> Self {
+        Self {
+            user_id,
+            ..Default::default()
+        }
+    }
+
+    /// Either user has blocked the other
+    pub fn is_blocked_with(&self, other: &UserId) -> bool {
+        self.blocked.contains(other) || self.blocked_by.contains(other)
+    }
+
+    /// Posts, comments and activity from `author` are hidden from this user
+    pub fn hides_content_from(&self, author: &UserId) -> bool {
+        self.is_blocked_with(author) || self.muted.contains(author)
+    }
+
+    /// `user` is left out of this user's search results
+    pub fn hides_in_search(&self, user: &UserId) -> bool {
+        self.is_blocked_with(user)
+    }
+
+    /// Activity by `actor` may notify this user
+    pub fn allows_notification_from(&self, actor: &UserId) -> bool {
+        !self.hides_content_from(actor)
+    }
+
+    /// Drop feed candidates written by hidden authors
+    pub fn filter_posts(&self, posts: Vec<Post>) -> Vec<Post> {
+        posts
+            .into_iter()
+            .filter(|post| !self.hides_content_from(&post.author_id))
+            .collect()
+    }
+
+    /// Drop users this user should not find in search
+    pub fn filter_users(&self, users: Vec<UserProfile>) -> Vec<UserProfile> {
+        users
+            .into_iter()
+            .filter(|user| !self.hides_in_search(&user.id))
+            .collect()
+    }
+}
+
+/// Whether `comment` on a post by the owner of `post_author` is shown to the owner of `viewer`.
+///
+/// Comments by people the post author has blocked are hidden from everyone,
+/// so a blocked user cannot keep a presence under the blocker's posts.
+pub fn comment_visible(viewer: &BlockList, post_author: &BlockList, comment: &Comment) -> bool {
+    if post_author.blocked.contains(&comment.author_id) {
+        return false;
+    }
+    comment.author_id == viewer.user_id || !viewer.hides_content_from(&comment.author_id)
+}
+
+/// A change to someone's block or mute list.
+///
+/// Published after every change so other services can drop their cached
+/// lists for the affected users.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockListChange {
+    pub actor_id: UserId,
+    pub target_id: UserId,
+    pub kind: RelationKind,
+    /// True when the relation was added, false when it was removed
+    pub active: bool,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl BlockListChange {
+    /// Users whose block list reflects this change
+    pub fn affected_users(&self) -> Vec<UserId> {
+        match self.kind {
+            RelationKind::Block => vec![self.actor_id, self.target_id],
+            RelationKind::Mute => vec![self.actor_id],
+        }
+    }
+}
+
+struct CachedBlockList {
+    list: Arc<BlockList>,
+    loaded_at: Instant,
+}
+
+/// Shared block list lookups with caching and invalidation.
+///
+/// Feed, search, notification and comment code paths ask this service
+/// instead of the repository so a viewer's list is loaded once per TTL.
+pub struct BlockListService {
+    repository: Arc<dyn BlockListRepository>,
+    cache: Mutex<HashMap<UserId, CachedBlockList>>,
+    ttl: Duration,
+    changes: broadcast::Sender<BlockListChange>,
+}
+
+impl BlockListService {
+    pub fn new(repository: Arc<dyn BlockListRepository>) -> Self {
+        Self::with_ttl(repository, BLOCK_LIST_CACHE_TTL)
+    }
+
+    pub fn with_ttl(repository: Arc<dyn BlockListRepository>, ttl: Duration) -> Self {
+        let (changes, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+        Self {
+            repository,
+            cache: Mutex::new(HashMap::new()),
+            ttl,
+            changes,
+        }
+    }
+
+    /// Changes made through this service, for forwarding to other instances
+    pub fn subscribe(&self) -> broadcast::Receiver<BlockListChange> {
+        self.changes.subscribe()
+    }
+
+    /// Block list for `user_id`, from cache when fresh
+    pub async fn get(&self, user_id: UserId) -> PixelleResult<Arc<BlockList>> {
+        if let Some(cached) = self.cache.lock().unwrap().get(&user_id) {
+            if cached.loaded_at.elapsed() < self.ttl {
+                return Ok(cached.list.clone());
+            }
+        }
+
+        let list = Arc::new(self.repository.get_block_list(user_id).await?);
+        self.cache.lock().unwrap().insert(
+            user_id,
+            CachedBlockList {
+                list: list.clone(),
+                loaded_at: Instant::now(),
+            },
+        );
+        Ok(list)
+    }
+
+    /// Drop the cached list for `user_id`
+    pub fn invalidate(&self, user_id: &UserId) {
+        self.cache.lock().unwrap().remove(user_id);
+    }
+
+    /// Apply a change published by another instance
+    pub fn apply_change(&self, change: &BlockListChange) {
+        for user_id in change.affected_users() {
+            self.invalidate(&user_id);
+        }
+    }
+
+    pub async fn block(&self, actor_id: UserId, target_id: UserId) -> PixelleResult<bool> {
+        self.set_relation(actor_id, target_id, RelationKind::Block, true).await
+    }
+
+    pub async fn unblock(&self, actor_id: UserId, target_id: UserId) -> PixelleResult<bool> {
+        self.set_relation(actor_id, target_id, RelationKind::Block, false).await
+    }
+
+    pub async fn mute(&self, actor_id: UserId, target_id: UserId) -> PixelleResult<bool> {
+        self.set_relation(actor_id, target_id, RelationKind::Mute, true).await
+    }
+
+    pub async fn unmute(&self, actor_id: UserId, target_id: UserId) -> PixelleResult<bool> {
+        self.set_relation(actor_id, target_id, RelationKind::Mute, false).await
+    }
+
+    /// Add or remove a relation; returns false when nothing changed
+    async fn set_relation(&self, actor_id: UserId, target_id: UserId, kind: RelationKind, active: bool) -> PixelleResult<bool> {
+        if actor_id == target_id {
+            return Err(PixelleError::Validation("Users cannot block or mute themselves".to_string()));
+        }
+
+        let changed = if active {
+            self.repository.add_relation(actor_id, target_id, kind).await?
+        } else {
+            self.repository.remove_relation(actor_id, target_id, kind).await?
+        };
+        if !changed {
+            return Ok(false);
+        }
+
+        let change = BlockListChange {
+            actor_id,
+            target_id,
+            kind,
+            active,
+            changed_at: crate::utils::now(),
+        };
+        self.apply_change(&change);
+        // Nobody listening is fine; the cache TTL covers other instances
+        let _ = self.changes.send(change);
+
+        tracing::debug!("{:?} from {} to {} set to {}", kind, actor_id, target_id, active);
+        Ok(true)
+    }
+
+    /// Feed candidates the viewer is allowed to see
+    pub async fn filter_feed(&self, viewer_id: UserId, posts: Vec<Post>) -> PixelleResult<Vec<Post>> {
+        Ok(self.get(viewer_id).await?.filter_posts(posts))
+    }
+
+    /// Search results with blocked users removed
+    pub async fn filter_search(&self, viewer_id: UserId, users: Vec<UserProfile>) -> PixelleResult<Vec<UserProfile>> {
+        Ok(self.get(viewer_id).await?.filter_users(users))
+    }
+
+    /// Whether activity by `actor_id` should notify `recipient_id`
+    pub async fn should_notify(&self, recipient_id: UserId, actor_id: UserId) -> PixelleResult<bool> {
+        Ok(self.get(recipient_id).await?.allows_notification_from(&actor_id))
+    }
+
+    /// Whether `commenter_id` may comment on a post by `post_author_id`
+    pub async fn can_comment(&self, post_author_id: UserId, commenter_id: UserId) -> PixelleResult<bool> {
+        Ok(!self.get(post_author_id).await?.is_blocked_with(&commenter_id))
+    }
+
+    /// Comments on a post by `post_author_id` that the viewer is allowed to see
+    pub async fn visible_comments(&self, viewer_id: UserId, post_author_id: UserId, comments: Vec<Comment>) -> PixelleResult<Vec<Comment>> {
+        let viewer = self.get(viewer_id).await?;
+        let post_author = self.get(post_author_id).await?;
+        Ok(comments
+            .into_iter()
+            .filter(|comment| comment_visible(&viewer, &post_author, comment))
+            .collect())
+    }
+}
+
+/// Block list storage kept in process memory
+#[derive(Default)]
+pub struct InMemoryBlockListRepository {
+    relations: Mutex<HashSet<(UserId, UserId, RelationKind)>>,
+}
+
+impl InMemoryBlockListRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl BlockListRepository for InMemoryBlockListRepository {
+    async fn add_relation(&self, actor_id: UserId, target_id: UserId, kind: RelationKind) -> PixelleResult<bool> {
+        Ok(self.relations.lock().unwrap().insert((actor_id, target_id, kind)))
+    }
+
+    async fn remove_relation(&self, actor_id: UserId, target_id: UserId, kind: RelationKind) -> PixelleResult<bool> {
+        Ok(self.relations.lock().unwrap().remove(&(actor_id, target_id, kind)))
+    }
+
+    async fn get_block_list(&self, user_id: UserId) -> PixelleResult<BlockList> {
+        let relations = self.relations.lock().unwrap();
+        let mut list = BlockList::new(user_id);
+
+        for (actor_id, target_id, kind) in relations.iter() {
+            match kind {
+                RelationKind::Block if *actor_id == user_id => {
+                    list.blocked.insert(*target_id);
+                }
+                RelationKind::Block if *target_id == user_id => {
+                    list.blocked_by.insert(*actor_id);
+                }
+                RelationKind::Mute if *actor_id == user_id => {
+                    list.muted.insert(*target_id);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(list)
+    }
+}
@@ -10,6 +10,53 @@ pub const JWT_SECRET: &str = "your-secret-key-here";
 pub const JWT_EXPIRATION_HOURS: i64 = 24;
 pub const REFRESH_TOKEN_EXPIRATION_DAYS: i64 = 30;
 
+/// Access tokens are validated locally by every service, so they stay short-lived
+/// and revocation only has to be tracked until they expire
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+pub const SIGNING_KEY_ROTATION_HOURS: i64 = 24;
+/// New signing keys are published this long before they sign anything, so
+/// validators have picked them up by the time tokens carry them
+pub const SIGNING_KEY_PUBLISH_AHEAD_MINUTES: i64 = 5;
+pub const JWKS_REFRESH_INTERVAL_SECONDS: u64 = 60;
+/// Password reset links stop working after this long
+pub const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+/// Email verification links stop working after this long
+pub const EMAIL_VERIFICATION_TTL_HOURS: i64 = 48;
+/// Sessions record their last use at most this often, so activity does not
+/// write to the store on every request
+pub const SESSION_LAST_SEEN_INTERVAL_MINUTES: i64 = 5;
+/// Service tokens from the client-credentials grant; not renewable, so
+/// services request a new one before this runs out
+pub const SERVICE_TOKEN_TTL_MINUTES: i64 = 15;
+/// Services cache API key lookups this long, which is also how long a
+/// revoked key keeps working
+pub const API_KEY_CACHE_SECONDS: u64 = 60;
+/// Requests per minute an API key is allowed when created without a limit
+pub const API_KEY_DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
+pub const MAX_API_KEYS_PER_USER: usize = 20;
+
+/// Invites and referrals
+pub const INVITE_CODE_LENGTH: usize = 10;
+pub const INVITE_DEFAULT_MAX_USES: u32 = 5;
+pub const INVITE_MAX_USES: u32 = 100;
+pub const INVITE_TTL_DAYS: i64 = 30;
+pub const MAX_ACTIVE_INVITES_PER_USER: usize = 10;
+/// Referrals of one inviter beyond this in a day are held for review
+pub const MAX_REFERRALS_PER_DAY: usize = 20;
+pub const REFERRAL_REWARD_RETRY_INTERVAL_SECONDS: u64 = 300;
+
+/// Profile verification
+pub const VERIFICATION_MAX_DOCUMENTS: usize = 5;
+pub const VERIFICATION_DOCUMENT_TYPES: &[&str] = &["image/jpeg", "image/png", "application/pdf"];
+pub const VERIFICATION_UPLOAD_URL_TTL_SECONDS: u64 = 900; // 15 minutes
+pub const VERIFICATION_VIEW_URL_TTL_SECONDS: u64 = 300; // 5 minutes
+/// Documents are deleted this long after a decision, or after a request
+/// was opened if it is never submitted
+pub const VERIFICATION_DOCUMENT_RETENTION_DAYS: i64 = 30;
+pub const VERIFICATION_NOTE_MAX_LENGTH: usize = 1000;
+pub const VERIFICATION_REAPPLY_COOLDOWN_DAYS: i64 = 30;
+pub const VERIFICATION_PURGE_INTERVAL_SECONDS: u64 = 3600; // 1 hour
+
 /// Password requirements
 pub const MIN_PASSWORD_LENGTH: usize = 8;
 pub const MAX_PASSWORD_LENGTH: usize = 128;
@@ -31,6 +78,20 @@ pub const MAX_FILE_SIZE_BYTES: u64 = 10 * 1024 * 1024; // 10MB
 pub const ALLOWED_IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
 pub const ALLOWED_VIDEO_TYPES: &[&str] = &["video/mp4", "video/webm", "video/ogg"];
 
+/// Account deletion
+pub const ACCOUNT_DELETION_GRACE_DAYS: i64 = 30;
+pub const ACCOUNT_DELETION_SWEEP_INTERVAL_SECONDS: u64 = 3600; // 1 hour
+
+/// Sagas
+pub const SAGA_MAX_STEP_ATTEMPTS: u32 = 5;
+pub const SAGA_STUCK_AFTER_SECONDS: i64 = 900; // 15 minutes
+pub const SAGA_SWEEP_INTERVAL_SECONDS: u64 = 30;
+
+/// Notifications
+pub const NOTIFICATION_DIGEST_HOUR: u32 = 9; // local time
+pub const NOTIFICATION_MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+pub const NOTIFICATION_FLUSH_INTERVAL_SECONDS: u64 = 60;
+
 /// Cache TTL values (in seconds)
 pub const USER_CACHE_TTL: u64 = 3600; // 1 hour
 pub const POST_CACHE_TTL: u64 = 1800; // 30 minutes
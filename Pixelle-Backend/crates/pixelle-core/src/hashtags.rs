@@ -0,0 +1,552 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use crate::errors::{PixelleError, PixelleResult};
+use crate::traits::HashtagRepository;
+use crate::types::{Post, UserId};
+
+/// Longest hashtag, in characters, after normalization
+pub const MAX_HASHTAG_LENGTH: usize = 100;
+
+/// How long the cached blocklist is trusted before it is reloaded.
+///
+/// Changes made through the service invalidate the cache immediately; the TTL
+/// bounds staleness for changes made by other instances.
+pub const HASHTAG_BLOCKLIST_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Posts two tags must share before one is suggested for the other
+const MIN_RELATED_CO_OCCURRENCES: u64 = 2;
+
+/// Canonical form of a hashtag, or `None` when it is not one.
+///
+/// The leading `#` is optional. Compatibility forms are folded with NFKC, so
+/// full-width and styled letters match their plain forms, and the result is
+/// lowercased, so `#Café`, `#CAFÉ` and `#ｃａｆé` are the same tag. Tags
+/// are letters, marks, digits and underscores, and cannot be digits alone.
+pub fn normalize_hashtag(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    let raw = raw.strip_prefix(['#', '＃']).unwrap_or(raw);
+    let tag: String = raw.nfkc().flat_map(char::to_lowercase).nfc().collect();
+
+    let length = tag.chars().count();
+    if length == 0 || length > MAX_HASHTAG_LENGTH {
+        return None;
+    }
+    if !tag.chars().all(is_hashtag_char) || tag.chars().all(|c| c.is_numeric() || c == '_') {
+        return None;
+    }
+    Some(tag)
+}
+
+fn is_hashtag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || is_combining_mark(c)
+}
+
+/// A hashtag as written in a post
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashtagMention {
+    /// Normalized form
+    pub tag: String,
+    /// As the author wrote it, without the `#`
+    pub display: String,
+}
+
+/// Hashtags in `text`, in order of first use and without duplicates.
+///
+/// A `#` only starts a tag at the beginning of the text or after a character
+/// that cannot be part of a word, so URL fragments and `C#` are skipped.
+pub fn extract_hashtags(text: &str) -> Vec<HashtagMention> {
+    let mut mentions = Vec::new();
+    let mut seen = HashSet::new();
+    let mut previous: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let starts_tag = matches!(c, '#' | '＃') && !previous.is_some_and(|p| is_hashtag_char(p) || matches!(p, '&' | '/'));
+        previous = Some(c);
+        if !starts_tag {
+            continue;
+        }
+
+        let body = start + c.len_utf8();
+        let mut end = body;
+        while let Some(&(index, next)) = chars.peek() {
+            if !is_hashtag_char(next) {
+                break;
+            }
+            end = index + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+
+        let display = &text[body..end];
+        if let Some(tag) = normalize_hashtag(display) {
+            if seen.insert(tag.clone()) {
+                mentions.push(HashtagMention {
+                    tag,
+                    display: display.to_string(),
+                });
+            }
+        }
+    }
+
+    mentions
+}
+
+/// Normalized tags mentioned in a post
+pub fn post_hashtags(post: &Post) -> HashSet<String> {
+    extract_hashtags(&post.content).into_iter().map(|mention| mention.tag).collect()
+}
+
+/// A hashtag and how it is used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hashtag {
+    /// Normalized form, which identifies the hashtag
+    pub tag: String,
+    /// Form it was first written in
+    pub display: String,
+    /// Posts mentioning the tag
+    pub usage_count: u64,
+    pub follower_count: u64,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl Hashtag {
+    pub fn new(tag: String, display: String) -> Self {
+        Self {
+            tag,
+            display,
+            usage_count: 0,
+            follower_count: 0,
+            created_at: crate::utils::now(),
+            last_used_at: None,
+        }
+    }
+}
+
+/// A hashtag moderators took out of circulation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashtagBlock {
+    pub tag: String,
+    pub reason: String,
+    pub blocked_by: UserId,
+    pub blocked_at: DateTime<Utc>,
+}
+
+/// A hashtag suggested because it is used alongside another
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedHashtag {
+    pub tag: String,
+    pub display: String,
+    pub usage_count: u64,
+    /// Posts mentioning both tags
+    pub co_occurrences: u64,
+    /// Co-occurrences relative to how common both tags are, up to 1
+    pub score: f64,
+}
+
+/// Hashtags a user follows
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FollowedHashtags {
+    pub user_id: UserId,
+    pub tags: HashSet<String>,
+}
+
+impl FollowedHashtags {
+    /// The post mentions a followed tag
+    pub fn matches(&self, post: &Post) -> bool {
+        !self.tags.is_empty() && post_hashtags(post).iter().any(|tag| self.tags.contains(tag))
+    }
+
+    /// Posts mentioning a followed tag
+    pub fn filter_posts(&self, posts: impl IntoIterator<Item = Post>) -> Vec<Post> {
+        posts.into_iter().filter(|post| self.matches(post)).collect()
+    }
+}
+
+struct CachedBlocklist {
+    blocks: Arc<HashMap<String, HashtagBlock>>,
+    loaded_at: Instant,
+}
+
+/// Hashtag entities, follows, suggestions and the blocklist.
+///
+/// Blocked tags are not counted when posts use them, cannot be followed, and
+/// never show up as hashtags or suggestions. Posts mentioning them are left
+/// to moderation.
+pub struct HashtagService {
+    repository: Arc<dyn HashtagRepository>,
+    blocklist: Mutex<Option<CachedBlocklist>>,
+    ttl: Duration,
+}
+
+impl HashtagService {
+    pub fn new(repository: Arc<dyn HashtagRepository>) -> Self {
+        Self::with_ttl(repository, HASHTAG_BLOCKLIST_CACHE_TTL)
+    }
+
+    pub fn with_ttl(repository: Arc<dyn HashtagRepository>, ttl: Duration) -> Self {
+        Self {
+            repository,
+            blocklist: Mutex::new(None),
+            ttl,
+        }
+    }
+
+    /// Blocked tags, from cache when fresh
+    async fn blocklist(&self) -> PixelleResult<Arc<HashMap<String, HashtagBlock>>> {
+        if let Some(cached) = self.blocklist.lock().unwrap().as_ref() {
+            if cached.loaded_at.elapsed() < self.ttl {
+                return Ok(cached.blocks.clone());
+            }
+        }
+
+        let blocks: HashMap<String, HashtagBlock> = self
+            .repository
+            .list_blocks()
+            .await?
+            .into_iter()
+            .map(|block| (block.tag.clone(), block))
+            .collect();
+        let blocks = Arc::new(blocks);
+        *self.blocklist.lock().unwrap() = Some(CachedBlocklist {
+            blocks: blocks.clone(),
+            loaded_at: Instant::now(),
+        });
+        Ok(blocks)
+    }
+
+    /// Drop the cached blocklist
+    pub fn invalidate(&self) {
+        *self.blocklist.lock().unwrap() = None;
+    }
+
+    pub async fn is_blocked(&self, raw: &str) -> PixelleResult<bool> {
+        let tag = parse(raw)?;
+        Ok(self.blocklist().await?.contains_key(&tag))
+    }
+
+    /// Count the hashtags of a newly published post; returns the tags counted
+    pub async fn record_post(&self, post: &Post) -> PixelleResult<Vec<String>> {
+        let blocked = self.blocklist().await?;
+        let mentions: Vec<HashtagMention> = extract_hashtags(&post.content)
+            .into_iter()
+            .filter(|mention| !blocked.contains_key(&mention.tag))
+            .collect();
+        if mentions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.repository.record_usage(&mentions, post.created_at).await?;
+        Ok(mentions.into_iter().map(|mention| mention.tag).collect())
+    }
+
+    /// Stop counting the hashtags of a removed post
+    pub async fn release_post(&self, post: &Post) -> PixelleResult<()> {
+        let tags: Vec<String> = post_hashtags(post).into_iter().collect();
+        if tags.is_empty() {
+            return Ok(());
+        }
+        self.repository.release_usage(&tags).await
+    }
+
+    pub async fn get(&self, raw: &str) -> PixelleResult<Hashtag> {
+        let tag = parse(raw)?;
+        if self.blocklist().await?.contains_key(&tag) {
+            return Err(not_found(&tag));
+        }
+        self.repository.get_hashtag(&tag).await?.ok_or_else(|| not_found(&tag))
+    }
+
+    /// Follow a hashtag; returns false when already following
+    pub async fn follow(&self, user_id: UserId, raw: &str) -> PixelleResult<bool> {
+        let tag = parse(raw)?;
+        if self.blocklist().await?.contains_key(&tag) {
+            return Err(PixelleError::Validation(format!("#{} cannot be followed", tag)));
+        }
+        let display = raw.trim().trim_start_matches(['#', '＃']).to_string();
+        let changed = self.repository.follow(user_id, &tag, &display).await?;
+        if changed {
+            tracing::debug!("{} followed #{}", user_id, tag);
+        }
+        Ok(changed)
+    }
+
+    /// Unfollow a hashtag, blocked or not; returns false when not following
+    pub async fn unfollow(&self, user_id: UserId, raw: &str) -> PixelleResult<bool> {
+        let tag = parse(raw)?;
+        self.repository.unfollow(user_id, &tag).await
+    }
+
+    /// Hashtags the user follows that are not blocked
+    pub async fn followed(&self, user_id: UserId) -> PixelleResult<FollowedHashtags> {
+        let blocked = self.blocklist().await?;
+        let tags = self
+            .repository
+            .followed(user_id)
+            .await?
+            .into_iter()
+            .filter(|tag| !blocked.contains_key(tag))
+            .collect();
+        Ok(FollowedHashtags { user_id, tags })
+    }
+
+    /// Posts from `pool` mentioning a hashtag the user follows, as extra feed candidates
+    pub async fn feed_candidates(&self, user_id: UserId, pool: impl IntoIterator<Item = Post>) -> PixelleResult<Vec<Post>> {
+        Ok(self.followed(user_id).await?.filter_posts(pool))
+    }
+
+    /// Hashtags most often used together with `raw`, best first
+    pub async fn related(&self, raw: &str, limit: usize) -> PixelleResult<Vec<RelatedHashtag>> {
+        let tag = parse(raw)?;
+        let blocked = self.blocklist().await?;
+        if blocked.contains_key(&tag) {
+            return Ok(Vec::new());
+        }
+        let Some(hashtag) = self.repository.get_hashtag(&tag).await? else {
+            return Ok(Vec::new());
+        };
+
+        let mut related = Vec::new();
+        for (other, co_occurrences) in self.repository.co_occurrences(&tag).await? {
+            if co_occurrences < MIN_RELATED_CO_OCCURRENCES || blocked.contains_key(&other) {
+                continue;
+            }
+            let Some(other) = self.repository.get_hashtag(&other).await? else {
+                continue;
+            };
+            // Cosine similarity of the two tags' posts, so ubiquitous tags do not dominate
+            let score = co_occurrences as f64 / ((hashtag.usage_count.max(1) * other.usage_count.max(1)) as f64).sqrt();
+            related.push(RelatedHashtag {
+                tag: other.tag,
+                display: other.display,
+                usage_count: other.usage_count,
+                co_occurrences,
+                score: score.min(1.0),
+            });
+        }
+
+        related.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| b.usage_count.cmp(&a.usage_count)));
+        related.truncate(limit);
+        Ok(related)
+    }
+
+    /// Hashtags related to the ones a user follows that they do not follow yet
+    pub async fn suggestions(&self, user_id: UserId, limit: usize) -> PixelleResult<Vec<RelatedHashtag>> {
+        let followed = self.followed(user_id).await?;
+        let mut suggestions: HashMap<String, RelatedHashtag> = HashMap::new();
+
+        for tag in &followed.tags {
+            for related in self.related(tag, limit.saturating_mul(4)).await? {
+                if followed.tags.contains(&related.tag) {
+                    continue;
+                }
+                // Tags related to several followed tags rank higher
+                suggestions
+                    .entry(related.tag.clone())
+                    .and_modify(|suggestion| {
+                        suggestion.score += related.score;
+                        suggestion.co_occurrences += related.co_occurrences;
+                    })
+                    .or_insert(related);
+            }
+        }
+
+        let mut suggestions: Vec<RelatedHashtag> = suggestions.into_values().collect();
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.tag.cmp(&b.tag)));
+        suggestions.truncate(limit);
+        Ok(suggestions)
+    }
+
+    /// Block an abusive hashtag; returns false when it was already blocked
+    pub async fn block(&self, raw: &str, reason: &str, moderator_id: UserId) -> PixelleResult<bool> {
+        let tag = parse(raw)?;
+        if reason.trim().is_empty() {
+            return Err(PixelleError::Validation("A reason is required to block a hashtag".to_string()));
+        }
+        let block = HashtagBlock {
+            tag: tag.clone(),
+            reason: reason.trim().to_string(),
+            blocked_by: moderator_id,
+            blocked_at: crate::utils::now(),
+        };
+        let changed = self.repository.add_block(&block).await?;
+        self.invalidate();
+        if changed {
+            tracing::info!("#{} blocked by {}: {}", tag, moderator_id, block.reason);
+        }
+        Ok(changed)
+    }
+
+    /// Lift a block; returns false when the tag was not blocked
+    pub async fn unblock(&self, raw: &str) -> PixelleResult<bool> {
+        let tag = parse(raw)?;
+        let changed = self.repository.remove_block(&tag).await?;
+        self.invalidate();
+        Ok(changed)
+    }
+
+    pub async fn list_blocked(&self) -> PixelleResult<Vec<HashtagBlock>> {
+        let mut blocks: Vec<HashtagBlock> = self.blocklist().await?.values().cloned().collect();
+        blocks.sort_by(|a, b| a.tag.cmp(&b.tag));
+        Ok(blocks)
+    }
+}
+
+fn parse(raw: &str) -> PixelleResult<String> {
+    normalize_hashtag(raw).ok_or_else(|| PixelleError::Validation(format!("Invalid hashtag: {}", raw)))
+}
+
+fn not_found(tag: &str) -> PixelleError {
+    PixelleError::NotFound(format!("Hashtag #{}", tag))
+}
+
+#[derive(Default)]
+struct HashtagState {
+    hashtags: HashMap<String, Hashtag>,
+    /// Posts mentioning both tags, keyed by the pair in sorted order
+    pairs: HashMap<(String, String), u64>,
+    follows: HashSet<(UserId, String)>,
+    blocks: HashMap<String, HashtagBlock>,
+}
+
+impl HashtagState {
+    fn entry(&mut self, tag: &str, display: &str) -> &mut Hashtag {
+        self.hashtags
+            .entry(tag.to_string())
+            .or_insert_with(|| Hashtag::new(tag.to_string(), display.to_string()))
+    }
+}
+
+/// Hashtag storage kept in process memory
+#[derive(Default)]
+pub struct InMemoryHashtagRepository {
+    state: Mutex<HashtagState>,
+}
+
+impl InMemoryHashtagRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn pair(a: &str, b: &str) -> (String, String) {
+    if a < b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+#[async_trait]
+impl HashtagRepository for InMemoryHashtagRepository {
+    async fn record_usage(&self, mentions: &[HashtagMention], at: DateTime<Utc>) -> PixelleResult<()> {
+        let mut state = self.state.lock().unwrap();
+        for mention in mentions {
+            let hashtag = state.entry(&mention.tag, &mention.display);
+            hashtag.usage_count += 1;
+            hashtag.last_used_at = Some(hashtag.last_used_at.map_or(at, |last| last.max(at)));
+        }
+        for (i, a) in mentions.iter().enumerate() {
+            for b in &mentions[i + 1..] {
+                *state.pairs.entry(pair(&a.tag, &b.tag)).or_default() += 1;
+            }
+        }
+        Ok(())
+    }
+
+    async fn release_usage(&self, tags: &[String]) -> PixelleResult<()> {
+        let mut state = self.state.lock().unwrap();
+        for tag in tags {
+            if let Some(hashtag) = state.hashtags.get_mut(tag) {
+                hashtag.usage_count = hashtag.usage_count.saturating_sub(1);
+            }
+        }
+        for (i, a) in tags.iter().enumerate() {
+            for b in &tags[i + 1..] {
+                let key = pair(a, b);
+                if let Some(count) = state.pairs.get_mut(&key) {
+                    *count -= 1;
+                    if *count == 0 {
+                        state.pairs.remove(&key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_hashtag(&self, tag: &str) -> PixelleResult<Option<Hashtag>> {
+        Ok(self.state.lock().unwrap().hashtags.get(tag).cloned())
+    }
+
+    async fn co_occurrences(&self, tag: &str) -> PixelleResult<Vec<(String, u64)>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .pairs
+            .iter()
+            .filter_map(|((a, b), count)| {
+                if a == tag {
+                    Some((b.clone(), *count))
+                } else if b == tag {
+                    Some((a.clone(), *count))
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+
+    async fn follow(&self, user_id: UserId, tag: &str, display: &str) -> PixelleResult<bool> {
+        let mut state = self.state.lock().unwrap();
+        if !state.follows.insert((user_id, tag.to_string())) {
+            return Ok(false);
+        }
+        state.entry(tag, display).follower_count += 1;
+        Ok(true)
+    }
+
+    async fn unfollow(&self, user_id: UserId, tag: &str) -> PixelleResult<bool> {
+        let mut state = self.state.lock().unwrap();
+        if !state.follows.remove(&(user_id, tag.to_string())) {
+            return Ok(false);
+        }
+        if let Some(hashtag) = state.hashtags.get_mut(tag) {
+            hashtag.follower_count = hashtag.follower_count.saturating_sub(1);
+        }
+        Ok(true)
+    }
+
+    async fn followed(&self, user_id: UserId) -> PixelleResult<HashSet<String>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .follows
+            .iter()
+            .filter(|(follower, _)| *follower == user_id)
+            .map(|(_, tag)| tag.clone())
+            .collect())
+    }
+
+    async fn add_block(&self, block: &HashtagBlock) -> PixelleResult<bool> {
+        let mut state = self.state.lock().unwrap();
+        if state.blocks.contains_key(&block.tag) {
+            return Ok(false);
+        }
+        state.blocks.insert(block.tag.clone(), block.clone());
+        Ok(true)
+    }
+
+    async fn remove_block(&self, tag: &str) -> PixelleResult<bool> {
+        Ok(self.state.lock().unwrap().blocks.remove(tag).is_some())
+    }
+
+    async fn list_blocks(&self) -> PixelleResult<Vec<HashtagBlock>> {
+        Ok(self.state.lock().unwrap().blocks.values().cloned().collect())
+    }
+}
@@ -0,0 +1,391 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::constants::{
+    INVITE_CODE_LENGTH, INVITE_DEFAULT_MAX_USES, INVITE_MAX_USES, INVITE_TTL_DAYS, MAX_ACTIVE_INVITES_PER_USER,
+    MAX_REFERRALS_PER_DAY,
+};
+use crate::errors::{PixelleError, PixelleResult};
+use crate::roles::{Permission, RolePolicy, Subject};
+use crate::traits::{InviteRepository, PolicyEngine, ReferralEventSink, ReferralRewarder};
+use crate::types::UserId;
+
+/// Letters and digits that cannot be mistaken for each other when typed
+const INVITE_CODE_CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// A code a user hands out to bring others to Pixelle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invite {
+    pub code: String,
+    pub inviter_id: UserId,
+    /// Signups the code can be used for
+    pub max_uses: u32,
+    pub uses: u32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl Invite {
+    /// Whether a signup can still use the code
+    pub fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && now < self.expires_at && self.uses < self.max_uses
+    }
+}
+
+/// Where a referral is on the way to a reward
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferralStatus {
+    /// Signed up; rewards wait until the invitee verifies their email
+    Pending,
+    /// Earned a reward that has not been issued yet
+    Qualified,
+    Rewarded,
+    /// Held back by abuse controls until staff review it
+    Flagged,
+    /// Turned down on review; never rewarded
+    Rejected,
+}
+
+/// A signup attributed to the inviter whose code it used
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Referral {
+    pub invitee_id: UserId,
+    pub inviter_id: UserId,
+    pub code: String,
+    pub status: ReferralStatus,
+    /// Why abuse controls flagged it
+    pub flag_reason: Option<String>,
+    /// Client IP at signup, for abuse review
+    pub signup_ip: Option<String>,
+    pub signed_up_at: DateTime<Utc>,
+    /// When the invitee verified their email, also while flagged
+    pub qualified_at: Option<DateTime<Utc>>,
+    pub rewarded_at: Option<DateTime<Utc>>,
+    pub reviewed_by: Option<UserId>,
+}
+
+/// Something that happened in the referral loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferralEvent {
+    InviteCreated(Invite),
+    SignedUp(Referral),
+    Flagged(Referral),
+    Qualified(Referral),
+    Rewarded(Referral),
+    Rejected(Referral),
+}
+
+impl ReferralEvent {
+    /// Analytics event type
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReferralEvent::InviteCreated(_) => "invite_created",
+            ReferralEvent::SignedUp(_) => "referral_signed_up",
+            ReferralEvent::Flagged(_) => "referral_flagged",
+            ReferralEvent::Qualified(_) => "referral_qualified",
+            ReferralEvent::Rewarded(_) => "referral_rewarded",
+            ReferralEvent::Rejected(_) => "referral_rejected",
+        }
+    }
+
+    /// The inviter, whom every event is about
+    pub fn inviter_id(&self) -> UserId {
+        match self {
+            ReferralEvent::InviteCreated(invite) => invite.inviter_id,
+            ReferralEvent::SignedUp(referral)
+            | ReferralEvent::Flagged(referral)
+            | ReferralEvent::Qualified(referral)
+            | ReferralEvent::Rewarded(referral)
+            | ReferralEvent::Rejected(referral) => referral.inviter_id,
+        }
+    }
+
+    pub fn properties(&self) -> serde_json::Value {
+        match self {
+            ReferralEvent::InviteCreated(invite) => serde_json::json!({
+                "code": invite.code,
+                "max_uses": invite.max_uses,
+            }),
+            ReferralEvent::SignedUp(referral)
+            | ReferralEvent::Flagged(referral)
+            | ReferralEvent::Qualified(referral)
+            | ReferralEvent::Rewarded(referral)
+            | ReferralEvent::Rejected(referral) => serde_json::json!({
+                "code": referral.code,
+                "invitee_id": referral.invitee_id,
+                "flag_reason": referral.flag_reason,
+            }),
+        }
+    }
+}
+
+/// Invite codes, signups attributed to them, and the rewards they earn.
+///
+/// Each user holds at most `MAX_ACTIVE_INVITES_PER_USER` usable codes. A
+/// referral qualifies once the invitee verifies their email, and is then
+/// handed to every [`ReferralRewarder`]; referrals whose rewards failed stay
+/// qualified for `retry_rewards`. Referrals beyond `MAX_REFERRALS_PER_DAY`
+/// for one inviter, or from a signup IP the inviter already referred, are
+/// flagged instead and only rewarded once staff with `users:suspend`
+/// approve them. Events go to every [`ReferralEventSink`]; their failures
+/// are logged and never fail the flow.
+pub struct InviteService {
+    repository: Arc<dyn InviteRepository>,
+    policy: Arc<dyn PolicyEngine>,
+    sinks: Vec<Arc<dyn ReferralEventSink>>,
+    rewarders: Vec<Arc<dyn ReferralRewarder>>,
+}
+
+impl InviteService {
+    pub fn new(repository: Arc<dyn InviteRepository>) -> Self {
+        Self {
+            repository,
+            policy: Arc::new(RolePolicy),
+            sinks: Vec::new(),
+            rewarders: Vec::new(),
+        }
+    }
+
+    pub fn with_event_sink(mut self, sink: Arc<dyn ReferralEventSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    pub fn with_rewarder(mut self, rewarder: Arc<dyn ReferralRewarder>) -> Self {
+        self.rewarders.push(rewarder);
+        self
+    }
+
+    async fn emit(&self, event: ReferralEvent) {
+        for sink in &self.sinks {
+            if let Err(e) = sink.record(&event).await {
+                tracing::warn!("Failed to record {} for {}: {}", event.name(), event.inviter_id(), e);
+            }
+        }
+    }
+
+    /// Create a code for `inviter_id`, usable `max_uses` times (by default
+    /// `INVITE_DEFAULT_MAX_USES`)
+    pub async fn create_invite(&self, inviter_id: UserId, max_uses: Option<u32>) -> PixelleResult<Invite> {
+        let max_uses = max_uses.unwrap_or(INVITE_DEFAULT_MAX_USES);
+        if !(1..=INVITE_MAX_USES).contains(&max_uses) {
+            return Err(PixelleError::Validation(format!("Invites can be used 1 to {} times", INVITE_MAX_USES)));
+        }
+        let now = Utc::now();
+        let active = self
+            .repository
+            .list_invites(inviter_id)
+            .await?
+            .iter()
+            .filter(|invite| invite.is_usable(now))
+            .count();
+        if active >= MAX_ACTIVE_INVITES_PER_USER {
+            return Err(PixelleError::Conflict(format!(
+                "At most {} invites can be active; revoke one first",
+                MAX_ACTIVE_INVITES_PER_USER
+            )));
+        }
+
+        let invite = Invite {
+            code: generate_invite_code(),
+            inviter_id,
+            max_uses,
+            uses: 0,
+            created_at: now,
+            expires_at: now + Duration::days(INVITE_TTL_DAYS),
+            revoked_at: None,
+        };
+        self.repository.save_invite(&invite).await?;
+        self.emit(ReferralEvent::InviteCreated(invite.clone())).await;
+        Ok(invite)
+    }
+
+    /// Invites of a user, newest first
+    pub async fn list_invites(&self, inviter_id: UserId) -> PixelleResult<Vec<Invite>> {
+        let mut invites = self.repository.list_invites(inviter_id).await?;
+        invites.sort_by_key(|invite| std::cmp::Reverse(invite.created_at));
+        Ok(invites)
+    }
+
+    /// A usable invite; codes are not case-sensitive
+    pub async fn invite(&self, code: &str) -> PixelleResult<Invite> {
+        let invalid = || PixelleError::Validation("Invalid or expired invite code".to_string());
+        let invite = self.repository.get_invite(&normalize_code(code)).await?.ok_or_else(invalid)?;
+        if !invite.is_usable(Utc::now()) {
+            return Err(invalid());
+        }
+        Ok(invite)
+    }
+
+    /// Stop a code from being used, on behalf of its owner or staff with
+    /// `users:suspend`
+    pub async fn revoke_invite(&self, actor: &Subject, code: &str) -> PixelleResult<Invite> {
+        let not_found = || PixelleError::NotFound(format!("Invite {} not found", code));
+        let mut invite = self.repository.get_invite(&normalize_code(code)).await?.ok_or_else(not_found)?;
+        if invite.inviter_id != actor.user_id && !self.policy.evaluate(actor, Permission::SuspendUsers, None).is_allowed() {
+            return Err(not_found());
+        }
+        if invite.revoked_at.is_none() {
+            invite.revoked_at = Some(Utc::now());
+            self.repository.save_invite(&invite).await?;
+        }
+        Ok(invite)
+    }
+
+    /// Attribute a new account to the inviter whose code it signed up with
+    ///
+    /// Uses are checked before they are counted, so concurrent signups can
+    /// take a code a little past its limit.
+    pub async fn attribute_signup(&self, code: &str, invitee_id: UserId, signup_ip: Option<String>) -> PixelleResult<Referral> {
+        if let Some(referral) = self.repository.get_referral(invitee_id).await? {
+            return Ok(referral);
+        }
+        let mut invite = self.invite(code).await?;
+        if invite.inviter_id == invitee_id {
+            return Err(PixelleError::Validation("Accounts cannot invite themselves".to_string()));
+        }
+        invite.uses += 1;
+        self.repository.save_invite(&invite).await?;
+
+        let now = Utc::now();
+        let earlier = self.repository.list_referrals(invite.inviter_id).await?;
+        let today = earlier.iter().filter(|referral| now - referral.signed_up_at < Duration::days(1)).count();
+        let flag_reason = if today >= MAX_REFERRALS_PER_DAY {
+            Some(format!("More than {} referrals in a day", MAX_REFERRALS_PER_DAY))
+        } else if signup_ip.is_some() && earlier.iter().any(|referral| referral.signup_ip == signup_ip) {
+            Some("Signup IP already referred by this inviter".to_string())
+        } else {
+            None
+        };
+
+        let referral = Referral {
+            invitee_id,
+            inviter_id: invite.inviter_id,
+            code: invite.code,
+            status: if flag_reason.is_some() { ReferralStatus::Flagged } else { ReferralStatus::Pending },
+            flag_reason,
+            signup_ip,
+            signed_up_at: now,
+            qualified_at: None,
+            rewarded_at: None,
+            reviewed_by: None,
+        };
+        self.repository.save_referral(&referral).await?;
+        self.emit(ReferralEvent::SignedUp(referral.clone())).await;
+        if referral.status == ReferralStatus::Flagged {
+            self.emit(ReferralEvent::Flagged(referral.clone())).await;
+        }
+        Ok(referral)
+    }
+
+    /// Record that an invitee verified their email and issue the rewards of
+    /// their referral, if it is not flagged; `None` for accounts nobody invited
+    pub async fn qualify(&self, invitee_id: UserId) -> PixelleResult<Option<Referral>> {
+        let Some(mut referral) = self.repository.get_referral(invitee_id).await? else {
+            return Ok(None);
+        };
+        if referral.qualified_at.is_some() {
+            return Ok(Some(referral));
+        }
+        referral.qualified_at = Some(Utc::now());
+        if referral.status != ReferralStatus::Pending {
+            self.repository.save_referral(&referral).await?;
+            return Ok(Some(referral));
+        }
+        referral.status = ReferralStatus::Qualified;
+        self.repository.save_referral(&referral).await?;
+        self.emit(ReferralEvent::Qualified(referral.clone())).await;
+        self.reward(referral).await.map(Some)
+    }
+
+    /// Approve or reject a flagged referral; needs `users:suspend`
+    pub async fn review(&self, actor: &Subject, invitee_id: UserId, approve: bool) -> PixelleResult<Referral> {
+        self.policy.evaluate(actor, Permission::SuspendUsers, None).into_result()?;
+        let mut referral = self
+            .repository
+            .get_referral(invitee_id)
+            .await?
+            .ok_or_else(|| PixelleError::NotFound(format!("No referral for {}", invitee_id)))?;
+        if referral.status != ReferralStatus::Flagged {
+            return Err(PixelleError::Conflict("Only flagged referrals are reviewed".to_string()));
+        }
+        referral.reviewed_by = Some(actor.user_id);
+
+        if !approve {
+            referral.status = ReferralStatus::Rejected;
+            self.repository.save_referral(&referral).await?;
+            self.emit(ReferralEvent::Rejected(referral.clone())).await;
+            return Ok(referral);
+        }
+        if referral.qualified_at.is_none() {
+            referral.status = ReferralStatus::Pending;
+            self.repository.save_referral(&referral).await?;
+            return Ok(referral);
+        }
+        referral.status = ReferralStatus::Qualified;
+        self.repository.save_referral(&referral).await?;
+        self.emit(ReferralEvent::Qualified(referral.clone())).await;
+        self.reward(referral).await
+    }
+
+    /// Referrals of an inviter, newest first
+    pub async fn list_referrals(&self, inviter_id: UserId) -> PixelleResult<Vec<Referral>> {
+        let mut referrals = self.repository.list_referrals(inviter_id).await?;
+        referrals.sort_by_key(|referral| std::cmp::Reverse(referral.signed_up_at));
+        Ok(referrals)
+    }
+
+    /// Hand a qualified referral to every rewarder, marking it rewarded if
+    /// they all succeed
+    async fn reward(&self, mut referral: Referral) -> PixelleResult<Referral> {
+        for rewarder in &self.rewarders {
+            if let Err(e) = rewarder.issue(&referral).await {
+                tracing::warn!("Failed to reward the referral of {}: {}", referral.invitee_id, e);
+                return Ok(referral);
+            }
+        }
+        referral.status = ReferralStatus::Rewarded;
+        referral.rewarded_at = Some(Utc::now());
+        self.repository.save_referral(&referral).await?;
+        self.emit(ReferralEvent::Rewarded(referral.clone())).await;
+        Ok(referral)
+    }
+
+    /// Issue the rewards that failed before; returns how many were issued
+    pub async fn retry_rewards(&self) -> PixelleResult<usize> {
+        let mut rewarded = 0;
+        for referral in self.repository.list_referrals_with_status(ReferralStatus::Qualified).await? {
+            if self.reward(referral).await?.status == ReferralStatus::Rewarded {
+                rewarded += 1;
+            }
+        }
+        Ok(rewarded)
+    }
+
+    /// Run `retry_rewards` every `interval` until the task is aborted
+    pub fn spawn_reward_retries(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.retry_rewards().await {
+                    tracing::error!("Retrying referral rewards failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+fn generate_invite_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..INVITE_CODE_LENGTH)
+        .map(|_| INVITE_CODE_CHARSET[rng.gen_range(0..INVITE_CODE_CHARSET.len())] as char)
+        .collect()
+}
+
+fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
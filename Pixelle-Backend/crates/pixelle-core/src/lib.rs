@@ -3,9 +3,29 @@ pub mod traits;
 pub mod errors;
 pub mod utils;
 pub mod constants;
+pub mod locale;
+pub mod blocks;
+pub mod hashtags;
+pub mod privacy;
+pub mod account_deletion;
+pub mod saga;
+pub mod notifications;
+pub mod roles;
+pub mod invites;
+pub mod verification;
 
 pub use types::*;
 pub use traits::*;
 pub use errors::*;
 pub use utils::*;
 pub use constants::*;
+pub use locale::*;
+pub use blocks::*;
+pub use hashtags::*;
+pub use privacy::*;
+pub use account_deletion::*;
+pub use saga::*;
+pub use notifications::*;
+pub use roles::*;
+pub use invites::*;
+pub use verification::*;
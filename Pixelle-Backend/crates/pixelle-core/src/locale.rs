@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+/// Geographic tag attached to a post
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoTag {
+    /// ISO 3166-1 alpha-2 country code, upper case
+    pub country_code: String,
+    /// Free-form region or city name
+    pub region: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// Language and region preferences used to localize feeds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalePreferences {
+    /// ISO 639-1 language codes in order of preference
+    pub languages: Vec<String>,
+    /// ISO 3166-1 alpha-2 country code
+    pub region: Option<String>,
+}
+
+impl LocalePreferences {
+    pub fn new(languages: Vec<String>, region: Option<String>) -> Self {
+        Self {
+            languages: languages.iter().map(|l| normalize_language(l)).filter(|l| !l.is_empty()).collect(),
+            region: region.map(|r| r.trim().to_uppercase()).filter(|r| !r.is_empty()),
+        }
+    }
+
+    /// True when there is nothing to localize on
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty() && self.region.is_none()
+    }
+
+    pub fn accepts_language(&self, language: &str) -> bool {
+        self.languages.is_empty() || self.languages.iter().any(|l| l == language)
+    }
+}
+
+/// Normalize a language tag such as "en-US" or "PT_br" to its primary subtag
+pub fn normalize_language(tag: &str) -> String {
+    tag.trim()
+        .split(['-', '_'])
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Best-effort language detection for short social posts.
+///
+/// Uses script detection for non-Latin scripts and stop word counts for the
+/// common Latin-script languages. Returns `None` when the text is too short
+/// or ambiguous, in which case callers should not filter on language.
+pub fn detect_language(text: &str) -> Option<String> {
+    let mut latin = 0usize;
+    let mut scripts: [(&str, usize); 6] = [
+        ("ru", 0), // Cyrillic
+        ("ar", 0), // Arabic
+        ("hi", 0), // Devanagari
+        ("ja", 0), // Hiragana / Katakana
+        ("ko", 0), // Hangul
+        ("zh", 0), // CJK ideographs
+    ];
+
+    for c in text.chars() {
+        match c as u32 {
+            0x0041..=0x024F => latin += 1,
+            0x0400..=0x04FF => scripts[0].1 += 1,
+            0x0600..=0x06FF => scripts[1].1 += 1,
+            0x0900..=0x097F => scripts[2].1 += 1,
+            0x3040..=0x30FF => scripts[3].1 += 1,
+            0xAC00..=0xD7AF => scripts[4].1 += 1,
+            0x4E00..=0x9FFF => scripts[5].1 += 1,
+            _ => {}
+        }
+    }
+
+    // Kana anywhere means Japanese even when mixed with kanji
+    if scripts[3].1 > 0 {
+        return Some("ja".to_string());
+    }
+
+    if let Some((code, count)) = scripts.iter().max_by_key(|(_, count)| *count) {
+        if *count > latin {
+            return Some(code.to_string());
+        }
+    }
+
+    detect_latin_language(text)
+}
+
+fn detect_latin_language(text: &str) -> Option<String> {
+    const STOP_WORDS: &[(&str, &[&str])] = &[
+        ("en", &["the", "and", "is", "of", "to", "in", "it", "you", "this", "that", "with", "for"]),
+        ("es", &["el", "la", "de", "que", "y", "en", "los", "es", "por", "con", "una", "para"]),
+        ("pt", &["o", "a", "de", "que", "e", "do", "da", "em", "um", "para", "com", "não"]),
+        ("fr", &["le", "la", "les", "de", "et", "est", "un", "une", "pour", "que", "dans", "pas"]),
+        ("de", &["der", "die", "und", "das", "ist", "nicht", "ein", "mit", "ich", "zu", "den", "auf"]),
+        ("it", &["il", "di", "che", "e", "la", "per", "un", "non", "sono", "con", "una", "gli"]),
+    ];
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+
+    if words.len() < 3 {
+        return None;
+    }
+
+    let mut scores: Vec<(&str, usize)> = STOP_WORDS
+        .iter()
+        .map(|(code, stop_words)| {
+            let hits = words.iter().filter(|w| stop_words.contains(&w.as_str())).count();
+            (*code, hits)
+        })
+        .collect();
+    scores.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+
+    match (scores.first(), scores.get(1)) {
+        (Some((code, best)), Some((_, runner_up))) if *best > 0 && best > runner_up => Some(code.to_string()),
+        _ => None,
+    }
+}
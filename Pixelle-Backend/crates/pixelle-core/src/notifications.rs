@@ -0,0 +1,410 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDateTime, NaiveTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use crate::constants::{NOTIFICATION_DIGEST_HOUR, NOTIFICATION_MAX_UTC_OFFSET_MINUTES};
+use crate::errors::{PixelleError, PixelleResult};
+use crate::traits::NotificationPreferenceRepository;
+use crate::types::UserId;
+
+/// What a notification is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationType {
+    Like,
+    Comment,
+    Mention,
+    Follow,
+    DirectMessage,
+    /// A post by someone the user follows
+    NewPost,
+    /// Sign-ins, password changes and account deletion
+    AccountSecurity,
+}
+
+impl NotificationType {
+    pub const ALL: [NotificationType; 7] = [
+        NotificationType::Like,
+        NotificationType::Comment,
+        NotificationType::Mention,
+        NotificationType::Follow,
+        NotificationType::DirectMessage,
+        NotificationType::NewPost,
+        NotificationType::AccountSecurity,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationType::Like => "like",
+            NotificationType::Comment => "comment",
+            NotificationType::Mention => "mention",
+            NotificationType::Follow => "follow",
+            NotificationType::DirectMessage => "direct_message",
+            NotificationType::NewPost => "new_post",
+            NotificationType::AccountSecurity => "account_security",
+        }
+    }
+
+    /// Delivered on every channel right away, whatever the preferences say
+    pub fn is_mandatory(&self) -> bool {
+        matches!(self, NotificationType::AccountSecurity)
+    }
+}
+
+impl FromStr for NotificationType {
+    type Err = PixelleError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == value)
+            .ok_or_else(|| PixelleError::Validation(format!("Unknown notification type {}", value)))
+    }
+}
+
+/// Where a notification is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Push,
+    Email,
+    InApp,
+}
+
+impl NotificationChannel {
+    pub const ALL: [NotificationChannel; 3] = [NotificationChannel::Push, NotificationChannel::Email, NotificationChannel::InApp];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationChannel::Push => "push",
+            NotificationChannel::Email => "email",
+            NotificationChannel::InApp => "in_app",
+        }
+    }
+
+    /// Interrupts the user, so held back during quiet hours
+    pub fn interrupts(&self) -> bool {
+        !matches!(self, NotificationChannel::InApp)
+    }
+}
+
+impl FromStr for NotificationChannel {
+    type Err = PixelleError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|channel| channel.name() == value)
+            .ok_or_else(|| PixelleError::Validation(format!("Unknown notification channel {}", value)))
+    }
+}
+
+/// How often notifications on a channel are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+    Immediate,
+    /// At the top of every hour
+    Hourly,
+    /// At `NOTIFICATION_DIGEST_HOUR` local time
+    Daily,
+    /// On Mondays at `NOTIFICATION_DIGEST_HOUR` local time
+    Weekly,
+}
+
+/// Delivery of one notification type on one channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelPreference {
+    pub enabled: bool,
+    pub frequency: DigestFrequency,
+}
+
+impl ChannelPreference {
+    pub const IMMEDIATE: ChannelPreference = ChannelPreference {
+        enabled: true,
+        frequency: DigestFrequency::Immediate,
+    };
+}
+
+/// Delivery of one notification type on every channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypePreferences {
+    pub push: ChannelPreference,
+    pub email: ChannelPreference,
+    pub in_app: ChannelPreference,
+}
+
+impl TypePreferences {
+    /// Push and in-app right away; email as a daily digest, except security notices
+    pub fn default_for(kind: NotificationType) -> Self {
+        let email = if kind.is_mandatory() {
+            ChannelPreference::IMMEDIATE
+        } else {
+            ChannelPreference {
+                enabled: true,
+                frequency: DigestFrequency::Daily,
+            }
+        };
+        Self {
+            push: ChannelPreference::IMMEDIATE,
+            email,
+            in_app: ChannelPreference::IMMEDIATE,
+        }
+    }
+
+    pub fn channel(&self, channel: NotificationChannel) -> &ChannelPreference {
+        match channel {
+            NotificationChannel::Push => &self.push,
+            NotificationChannel::Email => &self.email,
+            NotificationChannel::InApp => &self.in_app,
+        }
+    }
+
+    pub fn channel_mut(&mut self, channel: NotificationChannel) -> &mut ChannelPreference {
+        match channel {
+            NotificationChannel::Push => &mut self.push,
+            NotificationChannel::Email => &mut self.email,
+            NotificationChannel::InApp => &mut self.in_app,
+        }
+    }
+}
+
+/// Local times between which push and email notifications are held back;
+/// may wrap past midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// When a notification goes out on one channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "delivery", rename_all = "snake_case")]
+pub enum Delivery {
+    Now,
+    /// Held until quiet hours end
+    Held { until: DateTime<Utc> },
+    /// Batched with others into a digest sent at `at`
+    Digest { at: DateTime<Utc> },
+    /// Turned off by the recipient
+    Skipped,
+}
+
+/// A user's notification preferences; types without an entry use their defaults
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub types: BTreeMap<NotificationType, TypePreferences>,
+    pub quiet_hours: Option<QuietHours>,
+    /// Minutes east of UTC that quiet hours and digest times are given in
+    pub utc_offset_minutes: i32,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            types: NotificationType::ALL
+                .into_iter()
+                .map(|kind| (kind, TypePreferences::default_for(kind)))
+                .collect(),
+            quiet_hours: None,
+            utc_offset_minutes: 0,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    pub fn preferences_for(&self, kind: NotificationType) -> TypePreferences {
+        self.types.get(&kind).copied().unwrap_or_else(|| TypePreferences::default_for(kind))
+    }
+
+    pub fn validate(&self) -> PixelleResult<()> {
+        if self.utc_offset_minutes.abs() > NOTIFICATION_MAX_UTC_OFFSET_MINUTES {
+            return Err(PixelleError::Validation(format!(
+                "UTC offset must be within {} minutes",
+                NOTIFICATION_MAX_UTC_OFFSET_MINUTES
+            )));
+        }
+        if let Some(quiet_hours) = &self.quiet_hours {
+            if quiet_hours.start == quiet_hours.end {
+                return Err(PixelleError::Validation("Quiet hours must not start and end at the same time".to_string()));
+            }
+        }
+        for (kind, preferences) in &self.types {
+            if kind.is_mandatory() && *preferences != TypePreferences::default_for(*kind) {
+                return Err(PixelleError::Validation(format!("{} notifications cannot be changed", kind.name())));
+            }
+        }
+        Ok(())
+    }
+
+    /// The policy the dispatch path applies to one notification on one channel
+    pub fn delivery(&self, kind: NotificationType, channel: NotificationChannel, now: DateTime<Utc>) -> Delivery {
+        if kind.is_mandatory() {
+            return Delivery::Now;
+        }
+        let preference = *self.preferences_for(kind).channel(channel);
+        if !preference.enabled {
+            return Delivery::Skipped;
+        }
+
+        let digest_at = match preference.frequency {
+            DigestFrequency::Immediate => None,
+            frequency => Some(self.next_digest(frequency, now)),
+        };
+        let due = digest_at.unwrap_or(now);
+        let due = if channel.interrupts() { self.after_quiet_hours(due) } else { due };
+        match digest_at {
+            Some(_) => Delivery::Digest { at: due },
+            None if due > now => Delivery::Held { until: due },
+            None => Delivery::Now,
+        }
+    }
+
+    fn offset(&self) -> FixedOffset {
+        FixedOffset::east_opt(self.utc_offset_minutes * 60).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+    }
+
+    fn local(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        time.with_timezone(&self.offset()).naive_local()
+    }
+
+    fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        (local - Duration::minutes(self.utc_offset_minutes as i64)).and_utc()
+    }
+
+    /// First digest time after `now`
+    fn next_digest(&self, frequency: DigestFrequency, now: DateTime<Utc>) -> DateTime<Utc> {
+        let local = self.local(now);
+        let digest_time = NaiveTime::from_hms_opt(NOTIFICATION_DIGEST_HOUR, 0, 0).unwrap();
+        let next = match frequency {
+            DigestFrequency::Immediate => return now,
+            DigestFrequency::Hourly => {
+                local.date().and_hms_opt(local.hour(), 0, 0).unwrap() + Duration::hours(1)
+            }
+            DigestFrequency::Daily => {
+                let today = local.date().and_time(digest_time);
+                if today > local { today } else { today + Duration::days(1) }
+            }
+            DigestFrequency::Weekly => {
+                let days_to_monday = (7 - local.weekday().num_days_from_monday() as i64) % 7;
+                let monday = local.date().and_time(digest_time) + Duration::days(days_to_monday);
+                if monday > local { monday } else { monday + Duration::days(7) }
+            }
+        };
+        self.to_utc(next)
+    }
+
+    /// `time`, or the end of the quiet hours it falls in
+    fn after_quiet_hours(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let Some(quiet_hours) = &self.quiet_hours else {
+            return time;
+        };
+        let local = self.local(time);
+        if !quiet_hours.contains(local.time()) {
+            return time;
+        }
+        let mut end = local.date().and_time(quiet_hours.end);
+        if end <= local {
+            end += Duration::days(1);
+        }
+        self.to_utc(end)
+    }
+}
+
+/// Preferences used by the notification dispatch path and edited by clients
+///
+/// Users who never saved preferences get the defaults.
+pub struct NotificationPreferenceService {
+    repository: Arc<dyn NotificationPreferenceRepository>,
+}
+
+impl NotificationPreferenceService {
+    pub fn new(repository: Arc<dyn NotificationPreferenceRepository>) -> Self {
+        Self { repository }
+    }
+
+    pub async fn get(&self, user_id: UserId) -> PixelleResult<NotificationPreferences> {
+        Ok(self.repository.get_notification_preferences(user_id).await?.unwrap_or_default())
+    }
+
+    /// Replace every preference of `user_id`
+    pub async fn update(&self, user_id: UserId, preferences: NotificationPreferences) -> PixelleResult<NotificationPreferences> {
+        preferences.validate()?;
+        self.repository.save_notification_preferences(user_id, &preferences).await?;
+        tracing::debug!("Notification preferences of {} updated", user_id);
+        Ok(preferences)
+    }
+
+    /// Change one notification type on one channel, keeping what is not given
+    pub async fn update_channel(
+        &self,
+        user_id: UserId,
+        kind: NotificationType,
+        channel: NotificationChannel,
+        enabled: Option<bool>,
+        frequency: Option<DigestFrequency>,
+    ) -> PixelleResult<NotificationPreferences> {
+        let mut preferences = self.get(user_id).await?;
+        let mut type_preferences = preferences.preferences_for(kind);
+        let preference = type_preferences.channel_mut(channel);
+        if let Some(enabled) = enabled {
+            preference.enabled = enabled;
+        }
+        if let Some(frequency) = frequency {
+            preference.frequency = frequency;
+        }
+        preferences.types.insert(kind, type_preferences);
+        self.update(user_id, preferences).await
+    }
+
+    /// How a notification of `kind` reaches `recipient_id` on each channel
+    pub async fn route(
+        &self,
+        recipient_id: UserId,
+        kind: NotificationType,
+        now: DateTime<Utc>,
+    ) -> PixelleResult<Vec<(NotificationChannel, Delivery)>> {
+        let preferences = self.get(recipient_id).await?;
+        Ok(NotificationChannel::ALL
+            .into_iter()
+            .map(|channel| (channel, preferences.delivery(kind, channel, now)))
+            .collect())
+    }
+}
+
+/// Notification preferences kept in process memory
+#[derive(Default)]
+pub struct InMemoryNotificationPreferenceRepository {
+    preferences: Mutex<HashMap<UserId, NotificationPreferences>>,
+}
+
+impl InMemoryNotificationPreferenceRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl NotificationPreferenceRepository for InMemoryNotificationPreferenceRepository {
+    async fn get_notification_preferences(&self, user_id: UserId) -> PixelleResult<Option<NotificationPreferences>> {
+        Ok(self.preferences.lock().unwrap().get(&user_id).cloned())
+    }
+
+    async fn save_notification_preferences(&self, user_id: UserId, preferences: &NotificationPreferences) -> PixelleResult<()> {
+        self.preferences.lock().unwrap().insert(user_id, preferences.clone());
+        Ok(())
+    }
+}
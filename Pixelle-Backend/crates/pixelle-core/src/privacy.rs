@@ -0,0 +1,290 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::errors::PixelleResult;
+use crate::traits::{FollowRepository, PrivacyRepository};
+use crate::types::{Post, UserId, UserProfile};
+
+/// How long cached privacy settings are trusted before they are reloaded.
+///
+/// Changes made through the service invalidate the cache immediately; the TTL
+/// bounds staleness for changes made on other instances.
+pub const PRIVACY_SETTINGS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Who a privacy setting lets in, relative to the account owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Audience {
+    Everyone,
+    /// People who follow the owner
+    Followers,
+    /// People the owner follows
+    Following,
+    Nobody,
+}
+
+impl Audience {
+    pub fn admits(&self, connection: &Connection) -> bool {
+        match self {
+            Audience::Everyone => true,
+            Audience::Followers => connection.follows_owner,
+            Audience::Following => connection.followed_by_owner,
+            Audience::Nobody => false,
+        }
+    }
+}
+
+/// Privacy settings stored on a user profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    /// Posts and activity are only shown to followers
+    pub private_account: bool,
+    /// Who can start a direct message conversation
+    pub direct_messages: Audience,
+    /// Who can see the follower and following lists
+    pub follower_lists: Audience,
+    /// Shown in search to people who do not follow the account
+    pub discoverable: bool,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            private_account: false,
+            direct_messages: Audience::Everyone,
+            follower_lists: Audience::Everyone,
+            discoverable: true,
+        }
+    }
+}
+
+/// Something a viewer wants to do with another user's account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyAction {
+    /// See posts, and be notified about them
+    ViewContent,
+    SendMessage,
+    ViewFollowerLists,
+    FindInSearch,
+}
+
+/// How a viewer is connected to the account owner
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Connection {
+    pub is_owner: bool,
+    pub follows_owner: bool,
+    pub followed_by_owner: bool,
+}
+
+impl PrivacySettings {
+    /// The policy every service applies; owners can always act on their own account.
+    ///
+    /// Blocks are separate and checked through `BlockListService`.
+    pub fn allows(&self, action: PrivacyAction, connection: &Connection) -> bool {
+        if connection.is_owner {
+            return true;
+        }
+        let sees_content = !self.private_account || connection.follows_owner;
+
+        match action {
+            PrivacyAction::ViewContent => sees_content,
+            PrivacyAction::SendMessage => self.direct_messages.admits(connection),
+            PrivacyAction::ViewFollowerLists => sees_content && self.follower_lists.admits(connection),
+            PrivacyAction::FindInSearch => self.discoverable || connection.follows_owner,
+        }
+    }
+}
+
+struct CachedSettings {
+    settings: Arc<PrivacySettings>,
+    loaded_at: Instant,
+}
+
+/// Shared privacy policy evaluation with cached settings.
+///
+/// Feed, search, messaging and notification code paths ask this service so
+/// every one of them applies `PrivacySettings::allows` the same way.
+/// Anonymous viewers are treated as strangers.
+pub struct PrivacyService {
+    repository: Arc<dyn PrivacyRepository>,
+    follows: Arc<dyn FollowRepository>,
+    cache: Mutex<HashMap<UserId, CachedSettings>>,
+    ttl: Duration,
+}
+
+impl PrivacyService {
+    pub fn new(repository: Arc<dyn PrivacyRepository>, follows: Arc<dyn FollowRepository>) -> Self {
+        Self::with_ttl(repository, follows, PRIVACY_SETTINGS_CACHE_TTL)
+    }
+
+    pub fn with_ttl(repository: Arc<dyn PrivacyRepository>, follows: Arc<dyn FollowRepository>, ttl: Duration) -> Self {
+        Self {
+            repository,
+            follows,
+            cache: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Settings for `user_id`, from cache when fresh
+    pub async fn get(&self, user_id: UserId) -> PixelleResult<Arc<PrivacySettings>> {
+        if let Some(cached) = self.cache.lock().unwrap().get(&user_id) {
+            if cached.loaded_at.elapsed() < self.ttl {
+                return Ok(cached.settings.clone());
+            }
+        }
+
+        let settings = Arc::new(self.repository.get_privacy_settings(user_id).await?);
+        self.cache.lock().unwrap().insert(
+            user_id,
+            CachedSettings {
+                settings: settings.clone(),
+                loaded_at: Instant::now(),
+            },
+        );
+        Ok(settings)
+    }
+
+    pub async fn update(&self, user_id: UserId, settings: PrivacySettings) -> PixelleResult<PrivacySettings> {
+        self.repository.save_privacy_settings(user_id, &settings).await?;
+        self.invalidate(&user_id);
+        tracing::debug!("Privacy settings of {} updated", user_id);
+        Ok(settings)
+    }
+
+    /// Drop the cached settings for `user_id`
+    pub fn invalidate(&self, user_id: &UserId) {
+        self.cache.lock().unwrap().remove(user_id);
+    }
+
+    pub async fn connection(&self, viewer_id: Option<UserId>, owner_id: UserId) -> PixelleResult<Connection> {
+        let Some(viewer_id) = viewer_id else {
+            return Ok(Connection::default());
+        };
+        if viewer_id == owner_id {
+            return Ok(Connection {
+                is_owner: true,
+                ..Connection::default()
+            });
+        }
+        Ok(Connection {
+            is_owner: false,
+            follows_owner: self.follows.is_following(viewer_id, owner_id).await?,
+            followed_by_owner: self.follows.is_following(owner_id, viewer_id).await?,
+        })
+    }
+
+    /// Whether the viewer may perform `action` on the owner's account
+    pub async fn check(&self, viewer_id: Option<UserId>, owner_id: UserId, action: PrivacyAction) -> PixelleResult<bool> {
+        let settings = self.get(owner_id).await?;
+        let connection = self.connection(viewer_id, owner_id).await?;
+        Ok(settings.allows(action, &connection))
+    }
+
+    /// Feed candidates whose authors let the viewer see their posts
+    pub async fn filter_feed(&self, viewer_id: Option<UserId>, posts: Vec<Post>) -> PixelleResult<Vec<Post>> {
+        let mut allowed: HashMap<UserId, bool> = HashMap::new();
+        let mut visible = Vec::with_capacity(posts.len());
+        for post in posts {
+            let shown = match allowed.get(&post.author_id) {
+                Some(shown) => *shown,
+                None => {
+                    let shown = self.check(viewer_id, post.author_id, PrivacyAction::ViewContent).await?;
+                    allowed.insert(post.author_id, shown);
+                    shown
+                }
+            };
+            if shown {
+                visible.push(post);
+            }
+        }
+        Ok(visible)
+    }
+
+    /// Search results with undiscoverable users removed, judged by the settings on each profile
+    pub async fn filter_search(&self, viewer_id: Option<UserId>, users: Vec<UserProfile>) -> PixelleResult<Vec<UserProfile>> {
+        let mut visible = Vec::with_capacity(users.len());
+        for user in users {
+            let connection = self.connection(viewer_id, user.id).await?;
+            if user.privacy.allows(PrivacyAction::FindInSearch, &connection) {
+                visible.push(user);
+            }
+        }
+        Ok(visible)
+    }
+
+    pub async fn can_message(&self, sender_id: UserId, recipient_id: UserId) -> PixelleResult<bool> {
+        self.check(Some(sender_id), recipient_id, PrivacyAction::SendMessage).await
+    }
+
+    pub async fn can_view_follower_lists(&self, viewer_id: Option<UserId>, owner_id: UserId) -> PixelleResult<bool> {
+        self.check(viewer_id, owner_id, PrivacyAction::ViewFollowerLists).await
+    }
+
+    /// Whether `recipient_id` should hear about a new post by `author_id`
+    pub async fn should_notify_about_post(&self, recipient_id: UserId, author_id: UserId) -> PixelleResult<bool> {
+        self.check(Some(recipient_id), author_id, PrivacyAction::ViewContent).await
+    }
+
+    /// Whether a message from `sender_id` may notify `recipient_id`
+    pub async fn should_notify_about_message(&self, recipient_id: UserId, sender_id: UserId) -> PixelleResult<bool> {
+        self.can_message(sender_id, recipient_id).await
+    }
+}
+
+/// Privacy settings kept in process memory, for services without profile storage
+#[derive(Default)]
+pub struct InMemoryPrivacyRepository {
+    settings: Mutex<HashMap<UserId, PrivacySettings>>,
+}
+
+impl InMemoryPrivacyRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PrivacyRepository for InMemoryPrivacyRepository {
+    async fn get_privacy_settings(&self, user_id: UserId) -> PixelleResult<PrivacySettings> {
+        Ok(self.settings.lock().unwrap().get(&user_id).cloned().unwrap_or_default())
+    }
+
+    async fn save_privacy_settings(&self, user_id: UserId, settings: &PrivacySettings) -> PixelleResult<()> {
+        self.settings.lock().unwrap().insert(user_id, settings.clone());
+        Ok(())
+    }
+}
+
+/// Follow graph kept in process memory
+#[derive(Default)]
+pub struct InMemoryFollowRepository {
+    follows: Mutex<HashSet<(UserId, UserId)>>,
+}
+
+impl InMemoryFollowRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns false when the follow already existed
+    pub fn follow(&self, follower_id: UserId, followee_id: UserId) -> bool {
+        self.follows.lock().unwrap().insert((follower_id, followee_id))
+    }
+
+    /// Returns false when there was no such follow
+    pub fn unfollow(&self, follower_id: UserId, followee_id: UserId) -> bool {
+        self.follows.lock().unwrap().remove(&(follower_id, followee_id))
+    }
+}
+
+#[async_trait]
+impl FollowRepository for InMemoryFollowRepository {
+    async fn is_following(&self, follower_id: UserId, followee_id: UserId) -> PixelleResult<bool> {
+        Ok(self.follows.lock().unwrap().contains(&(follower_id, followee_id)))
+    }
+}
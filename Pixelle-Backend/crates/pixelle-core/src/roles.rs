@@ -0,0 +1,316 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use crate::errors::{PixelleError, PixelleResult};
+use crate::traits::{PolicyEngine, RoleRepository};
+use crate::types::UserId;
+
+/// Role of an account; a user can hold several
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Moderator,
+    Creator,
+    Viewer,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Admin, Role::Moderator, Role::Creator, Role::Viewer];
+
+    /// Role of accounts that were never assigned one
+    pub const DEFAULT: Role = Role::Creator;
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Moderator => "moderator",
+            Role::Creator => "creator",
+            Role::Viewer => "viewer",
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Admin => &Permission::ALL,
+            Role::Moderator => &[
+                Permission::ViewContent,
+                Permission::PublishContent,
+                Permission::ModerateContent,
+                Permission::SuspendUsers,
+                Permission::ReviewVerifications,
+            ],
+            Role::Creator => &[Permission::ViewContent, Permission::PublishContent, Permission::ViewAnalytics],
+            Role::Viewer => &[Permission::ViewContent],
+        }
+    }
+
+    pub fn grants(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = PixelleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::ALL
+            .into_iter()
+            .find(|role| role.as_str() == s)
+            .ok_or_else(|| PixelleError::Validation(format!("Unknown role {}", s)))
+    }
+}
+
+/// Something a role allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    #[serde(rename = "content:view")]
+    ViewContent,
+    /// Create, edit and delete posts; others' posts also need `ModerateContent`
+    #[serde(rename = "content:publish")]
+    PublishContent,
+    /// Act on anyone's content, e.g. remove posts or block hashtags
+    #[serde(rename = "content:moderate")]
+    ModerateContent,
+    #[serde(rename = "users:suspend")]
+    SuspendUsers,
+    /// Analytics of the account's own content
+    #[serde(rename = "analytics:view")]
+    ViewAnalytics,
+    /// Assign roles to accounts
+    #[serde(rename = "roles:manage")]
+    ManageRoles,
+    /// Read the security audit log of every account
+    #[serde(rename = "audit:view")]
+    ViewAuditLog,
+    /// Work the profile verification queue and revoke verified badges
+    #[serde(rename = "verifications:review")]
+    ReviewVerifications,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 8] = [
+        Permission::ViewContent,
+        Permission::PublishContent,
+        Permission::ModerateContent,
+        Permission::SuspendUsers,
+        Permission::ViewAnalytics,
+        Permission::ManageRoles,
+        Permission::ViewAuditLog,
+        Permission::ReviewVerifications,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ViewContent => "content:view",
+            Permission::PublishContent => "content:publish",
+            Permission::ModerateContent => "content:moderate",
+            Permission::SuspendUsers => "users:suspend",
+            Permission::ViewAnalytics => "analytics:view",
+            Permission::ManageRoles => "roles:manage",
+            Permission::ViewAuditLog => "audit:view",
+            Permission::ReviewVerifications => "verifications:review",
+        }
+    }
+
+    /// Whether holding it on one's own resources is different from holding
+    /// it on everyone's
+    fn is_owner_scoped(&self) -> bool {
+        matches!(self, Permission::PublishContent | Permission::ViewAnalytics)
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Who asks for a permission
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subject {
+    pub user_id: UserId,
+    pub roles: Vec<Role>,
+}
+
+impl Subject {
+    /// Unknown role names, e.g. from a newer auth-service, are ignored, and
+    /// no roles at all means the default role
+    pub fn new<S: AsRef<str>>(user_id: UserId, roles: &[S]) -> Self {
+        let mut roles: Vec<Role> = roles.iter().filter_map(|role| role.as_ref().parse().ok()).collect();
+        if roles.is_empty() {
+            roles.push(Role::DEFAULT);
+        }
+        Self { user_id, roles }
+    }
+
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.contains(&role)
+    }
+}
+
+/// What a permission is asked for; `None` in evaluations that concern no
+/// particular resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resource {
+    pub owner_id: UserId,
+}
+
+impl Resource {
+    pub fn owned_by(owner_id: UserId) -> Self {
+        Self { owner_id }
+    }
+}
+
+/// Outcome of a policy evaluation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny(String),
+}
+
+impl Decision {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Decision::Allow)
+    }
+
+    /// `Authorization` error for a denial
+    pub fn into_result(self) -> PixelleResult<()> {
+        match self {
+            Decision::Allow => Ok(()),
+            Decision::Deny(reason) => Err(PixelleError::Authorization(reason)),
+        }
+    }
+}
+
+/// The policy every service applies unless given another
+///
+/// A subject holds a permission if one of its roles grants it. Publishing on
+/// and viewing analytics of another user's resource also takes
+/// `ModerateContent`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RolePolicy;
+
+impl PolicyEngine for RolePolicy {
+    fn evaluate(&self, subject: &Subject, permission: Permission, resource: Option<&Resource>) -> Decision {
+        let holds = |permission: Permission| subject.roles.iter().any(|role| role.grants(permission));
+        if !holds(permission) {
+            return Decision::Deny(format!("Missing permission {}", permission));
+        }
+        match resource {
+            Some(resource)
+                if permission.is_owner_scoped()
+                    && resource.owner_id != subject.user_id
+                    && !holds(Permission::ModerateContent) =>
+            {
+                Decision::Deny(format!("{} only applies to your own content", permission))
+            }
+            _ => Decision::Allow,
+        }
+    }
+}
+
+/// Roles assigned to a user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleAssignment {
+    pub user_id: UserId,
+    pub roles: Vec<Role>,
+    pub updated_at: DateTime<Utc>,
+    /// `None` for assignments made at startup, e.g. bootstrapped admins
+    pub updated_by: Option<UserId>,
+}
+
+/// Role assignments, checked against a policy.
+///
+/// auth-service uses this to manage roles and embeds them in access tokens;
+/// other services evaluate the token's roles with the same [`PolicyEngine`].
+pub struct RoleService {
+    repository: Arc<dyn RoleRepository>,
+    policy: Arc<dyn PolicyEngine>,
+}
+
+impl RoleService {
+    pub fn new(repository: Arc<dyn RoleRepository>) -> Self {
+        Self::with_policy(repository, Arc::new(RolePolicy))
+    }
+
+    pub fn with_policy(repository: Arc<dyn RoleRepository>, policy: Arc<dyn PolicyEngine>) -> Self {
+        Self { repository, policy }
+    }
+
+    pub fn policy(&self) -> &Arc<dyn PolicyEngine> {
+        &self.policy
+    }
+
+    /// Roles of a user; the default role for users without an assignment
+    pub async fn roles_of(&self, user_id: UserId) -> PixelleResult<Vec<Role>> {
+        Ok(match self.repository.get_roles(user_id).await? {
+            Some(assignment) if !assignment.roles.is_empty() => assignment.roles,
+            _ => vec![Role::DEFAULT],
+        })
+    }
+
+    /// Assignments holding `role`
+    pub async fn members(&self, role: Role) -> PixelleResult<Vec<RoleAssignment>> {
+        self.repository.list_with_role(role).await
+    }
+
+    /// Replace the roles of `user_id` on behalf of `actor`, who needs
+    /// `ManageRoles`
+    ///
+    /// The last admin cannot lose the role, so roles can always be managed.
+    pub async fn assign(&self, actor: &Subject, user_id: UserId, roles: Vec<Role>) -> PixelleResult<RoleAssignment> {
+        self.policy.evaluate(actor, Permission::ManageRoles, None).into_result()?;
+        let roles: Vec<Role> = roles.into_iter().collect::<BTreeSet<_>>().into_iter().collect();
+        if roles.is_empty() {
+            return Err(PixelleError::Validation("Accounts need at least one role".to_string()));
+        }
+
+        if !roles.contains(&Role::Admin) && self.roles_of(user_id).await?.contains(&Role::Admin) {
+            let admins = self.repository.list_with_role(Role::Admin).await?;
+            if admins.iter().all(|admin| admin.user_id == user_id) {
+                return Err(PixelleError::Conflict("The last admin cannot lose the admin role".to_string()));
+            }
+        }
+
+        let assignment = RoleAssignment {
+            user_id,
+            roles,
+            updated_at: Utc::now(),
+            updated_by: Some(actor.user_id),
+        };
+        self.repository.save_roles(&assignment).await?;
+        Ok(assignment)
+    }
+
+    /// Make `user_id` an admin without an acting user, keeping its other
+    /// roles; used to seed the first admins
+    pub async fn bootstrap_admin(&self, user_id: UserId) -> PixelleResult<bool> {
+        let mut roles = match self.repository.get_roles(user_id).await? {
+            Some(assignment) => assignment.roles,
+            None => vec![Role::DEFAULT],
+        };
+        if roles.contains(&Role::Admin) {
+            return Ok(false);
+        }
+        roles.insert(0, Role::Admin);
+        self.repository
+            .save_roles(&RoleAssignment {
+                user_id,
+                roles,
+                updated_at: Utc::now(),
+                updated_by: None,
+            })
+            .await?;
+        Ok(true)
+    }
+}
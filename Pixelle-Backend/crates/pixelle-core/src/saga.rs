@@ -0,0 +1,414 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use crate::constants::{SAGA_MAX_STEP_ATTEMPTS, SAGA_STUCK_AFTER_SECONDS};
+use crate::errors::{PixelleError, PixelleResult};
+use crate::traits::{SagaRepository, SagaStep};
+use crate::types::Id;
+
+/// Where a saga is in its run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SagaStatus {
+    /// Steps are running forward; a failed step is retried on the next sweep
+    Running,
+    /// A step gave up or an operator aborted; finished steps are being undone
+    Compensating,
+    /// Every step finished
+    Completed,
+    /// Every finished step was undone
+    Compensated,
+}
+
+impl SagaStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, SagaStatus::Completed | SagaStatus::Compensated)
+    }
+}
+
+/// Values shared between the steps of one saga.
+///
+/// Steps record what they created here (a post ID, an object key) so later
+/// steps and compensations can find it. The context is saved after every step.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SagaContext {
+    pub saga_id: Id,
+    values: serde_json::Map<String, serde_json::Value>,
+}
+
+impl SagaContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value before the saga starts
+    pub fn with<T: Serialize>(mut self, key: &str, value: &T) -> PixelleResult<Self> {
+        self.set(key, value)?;
+        Ok(self)
+    }
+
+    pub fn set<T: Serialize>(&mut self, key: &str, value: &T) -> PixelleResult<()> {
+        self.values.insert(key.to_string(), serde_json::to_value(value)?);
+        Ok(())
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> PixelleResult<Option<T>> {
+        match self.values.get(key) {
+            Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Like `get`, for values an earlier step must have set
+    pub fn require<T: DeserializeOwned>(&self, key: &str) -> PixelleResult<T> {
+        self.get(key)?
+            .ok_or_else(|| PixelleError::Internal(format!("Saga {} has no '{}' value", self.saga_id, key)))
+    }
+}
+
+/// A saga and its progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SagaRecord {
+    pub id: Id,
+    /// Name of the definition that runs this saga
+    pub kind: String,
+    pub status: SagaStatus,
+    pub context: SagaContext,
+    /// Steps that finished, in definition order
+    pub completed_steps: Vec<String>,
+    /// Finished steps that were undone, in the order they were undone
+    pub compensated_steps: Vec<String>,
+    /// Failed attempts at the current step or compensation
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SagaRecord {
+    /// Still unfinished long after it started
+    pub fn is_stuck(&self, now: DateTime<Utc>, stuck_after: Duration) -> bool {
+        !self.status.is_finished() && now - self.started_at >= stuck_after
+    }
+}
+
+/// The ordered steps of one kind of saga
+pub struct SagaDefinition {
+    kind: String,
+    steps: Vec<Arc<dyn SagaStep>>,
+}
+
+impl SagaDefinition {
+    pub fn new(kind: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Append a step; steps run in the order they are added
+    pub fn with_step(mut self, step: Arc<dyn SagaStep>) -> Self {
+        self.steps.push(step);
+        self
+    }
+}
+
+/// Runs multi-step workflows that span services.
+///
+/// Each step has a compensation. Steps run in order and progress is saved
+/// after each one, so a saga interrupted by a crash resumes on the next sweep
+/// where it stopped. A step that keeps failing is given up on after
+/// `max_attempts`, and the steps that already finished are compensated in
+/// reverse order. Failed compensations are retried on every sweep.
+///
+/// Steps and compensations may run more than once and must be idempotent;
+/// `SagaContext::saga_id` makes a good idempotency key. A failed step is not
+/// compensated, so it must not leave anything behind.
+pub struct SagaOrchestrator {
+    repository: Arc<dyn SagaRepository>,
+    definitions: HashMap<String, SagaDefinition>,
+    max_attempts: u32,
+    stuck_after: Duration,
+    /// Sagas being driven by this process, so a sweep cannot run one twice
+    in_flight: Mutex<HashSet<Id>>,
+}
+
+impl SagaOrchestrator {
+    pub fn new(repository: Arc<dyn SagaRepository>) -> Self {
+        Self {
+            repository,
+            definitions: HashMap::new(),
+            max_attempts: SAGA_MAX_STEP_ATTEMPTS,
+            stuck_after: Duration::seconds(SAGA_STUCK_AFTER_SECONDS),
+            in_flight: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn with_saga(mut self, definition: SagaDefinition) -> Self {
+        self.definitions.insert(definition.kind.clone(), definition);
+        self
+    }
+
+    /// Failed attempts at a step before the saga is compensated
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// How long a saga may stay unfinished before it is reported as stuck
+    pub fn with_stuck_after(mut self, stuck_after: Duration) -> Self {
+        self.stuck_after = stuck_after;
+        self
+    }
+
+    /// Save a new saga and run it as far as it goes
+    pub async fn start(&self, kind: &str, mut context: SagaContext) -> PixelleResult<SagaRecord> {
+        if !self.definitions.contains_key(kind) {
+            return Err(PixelleError::Validation(format!("Unknown saga '{}'", kind)));
+        }
+
+        let now = crate::utils::now();
+        context.saga_id = crate::utils::generate_id();
+        let saga = SagaRecord {
+            id: context.saga_id,
+            kind: kind.to_string(),
+            status: SagaStatus::Running,
+            context,
+            completed_steps: Vec::new(),
+            compensated_steps: Vec::new(),
+            attempts: 0,
+            last_error: None,
+            started_at: now,
+            updated_at: now,
+        };
+        self.repository.save_saga(&saga).await?;
+
+        tracing::info!("Saga {} ({}) started", saga.id, kind);
+        self.drive(saga.id).await
+    }
+
+    pub async fn get(&self, id: Id) -> PixelleResult<Option<SagaRecord>> {
+        self.repository.get_saga(id).await
+    }
+
+    /// Unfinished sagas that started longer ago than the stuck threshold
+    pub async fn list_stuck(&self) -> PixelleResult<Vec<SagaRecord>> {
+        let now = crate::utils::now();
+        let mut stuck: Vec<SagaRecord> = self.repository
+            .list_unfinished()
+            .await?
+            .into_iter()
+            .filter(|saga| saga.is_stuck(now, self.stuck_after))
+            .collect();
+        stuck.sort_by_key(|saga| saga.started_at);
+        Ok(stuck)
+    }
+
+    /// Run an unfinished saga again straight away with a fresh attempt count
+    pub async fn retry(&self, id: Id) -> PixelleResult<SagaRecord> {
+        let _claim = self.claim(id)?;
+        let mut saga = self.unfinished(id).await?;
+        saga.attempts = 0;
+        saga.updated_at = crate::utils::now();
+        self.repository.save_saga(&saga).await?;
+
+        tracing::info!("Saga {} retried", id);
+        self.run(saga).await
+    }
+
+    /// Stop running steps forward and undo the ones that finished
+    pub async fn abort(&self, id: Id) -> PixelleResult<SagaRecord> {
+        let _claim = self.claim(id)?;
+        let mut saga = self.unfinished(id).await?;
+        if saga.status == SagaStatus::Running {
+            saga.status = SagaStatus::Compensating;
+            saga.attempts = 0;
+            saga.last_error = Some("Aborted by an operator".to_string());
+            saga.updated_at = crate::utils::now();
+            self.repository.save_saga(&saga).await?;
+        }
+
+        tracing::info!("Saga {} aborted", id);
+        self.run(saga).await
+    }
+
+    /// Carry every unfinished saga forward, including ones interrupted by a
+    /// restart. Returns the sagas that were worked on.
+    pub async fn resume_unfinished(&self) -> PixelleResult<Vec<SagaRecord>> {
+        let unfinished = self.repository.list_unfinished().await?;
+        let mut processed = Vec::with_capacity(unfinished.len());
+        for saga in unfinished {
+            match self.drive(saga.id).await {
+                Ok(saga) => processed.push(saga),
+                Err(e) => tracing::error!("Saga {} ({}) could not be resumed: {}", saga.id, saga.kind, e),
+            }
+        }
+        Ok(processed)
+    }
+
+    async fn unfinished(&self, id: Id) -> PixelleResult<SagaRecord> {
+        let saga = self.repository.get_saga(id).await?
+            .ok_or_else(|| PixelleError::NotFound("Saga not found".to_string()))?;
+        if saga.status.is_finished() {
+            return Err(PixelleError::Conflict("Saga has already finished".to_string()));
+        }
+        Ok(saga)
+    }
+
+    fn claim(&self, id: Id) -> PixelleResult<SagaClaim<'_>> {
+        if !self.in_flight.lock().unwrap().insert(id) {
+            return Err(PixelleError::Conflict("Saga is already running".to_string()));
+        }
+        Ok(SagaClaim { in_flight: &self.in_flight, id })
+    }
+
+    /// Run a saga unless this process is already running it
+    async fn drive(&self, id: Id) -> PixelleResult<SagaRecord> {
+        let claim = self.claim(id);
+        let saga = self.repository.get_saga(id).await?
+            .ok_or_else(|| PixelleError::NotFound("Saga not found".to_string()))?;
+        match claim {
+            Ok(_claim) => self.run(saga).await,
+            Err(_) => Ok(saga),
+        }
+    }
+
+    async fn run(&self, mut saga: SagaRecord) -> PixelleResult<SagaRecord> {
+        let definition = self.definitions.get(&saga.kind)
+            .ok_or_else(|| PixelleError::Internal(format!("No definition for saga '{}'", saga.kind)))?;
+
+        if saga.status == SagaStatus::Running {
+            for step in &definition.steps {
+                if saga.completed_steps.iter().any(|done| done == step.name()) {
+                    continue;
+                }
+                match step.execute(&mut saga.context).await {
+                    Ok(()) => {
+                        saga.completed_steps.push(step.name().to_string());
+                        saga.attempts = 0;
+                        saga.last_error = None;
+                        saga.updated_at = crate::utils::now();
+                        self.repository.save_saga(&saga).await?;
+                        tracing::debug!("Saga {} step '{}' finished", saga.id, step.name());
+                    }
+                    Err(e) => {
+                        saga.attempts += 1;
+                        saga.last_error = Some(format!("{}: {}", step.name(), e));
+                        saga.updated_at = crate::utils::now();
+                        if saga.attempts < self.max_attempts {
+                            tracing::warn!("Saga {} step '{}' failed, will retry: {}", saga.id, step.name(), e);
+                            self.repository.save_saga(&saga).await?;
+                            return Ok(saga);
+                        }
+                        tracing::error!("Saga {} step '{}' gave up, compensating: {}", saga.id, step.name(), e);
+                        saga.status = SagaStatus::Compensating;
+                        saga.attempts = 0;
+                        self.repository.save_saga(&saga).await?;
+                        break;
+                    }
+                }
+            }
+
+            if saga.status == SagaStatus::Running {
+                saga.status = SagaStatus::Completed;
+                saga.updated_at = crate::utils::now();
+                self.repository.save_saga(&saga).await?;
+                tracing::info!("Saga {} ({}) completed", saga.id, saga.kind);
+                return Ok(saga);
+            }
+        }
+
+        if saga.status == SagaStatus::Compensating {
+            for step in definition.steps.iter().rev() {
+                let finished = saga.completed_steps.iter().any(|done| done == step.name());
+                let undone = saga.compensated_steps.iter().any(|done| done == step.name());
+                if !finished || undone {
+                    continue;
+                }
+                if let Err(e) = step.compensate(&saga.context).await {
+                    tracing::error!("Saga {} compensation of '{}' failed: {}", saga.id, step.name(), e);
+                    saga.attempts += 1;
+                    saga.last_error = Some(format!("compensating {}: {}", step.name(), e));
+                    saga.updated_at = crate::utils::now();
+                    self.repository.save_saga(&saga).await?;
+                    return Ok(saga);
+                }
+                saga.compensated_steps.push(step.name().to_string());
+                saga.attempts = 0;
+                saga.updated_at = crate::utils::now();
+                self.repository.save_saga(&saga).await?;
+                tracing::debug!("Saga {} step '{}' compensated", saga.id, step.name());
+            }
+
+            saga.status = SagaStatus::Compensated;
+            saga.updated_at = crate::utils::now();
+            self.repository.save_saga(&saga).await?;
+            tracing::info!("Saga {} ({}) compensated", saga.id, saga.kind);
+        }
+
+        Ok(saga)
+    }
+
+    /// Run `resume_unfinished` every `interval` until the task is aborted
+    pub fn spawn_sweeper(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.resume_unfinished().await {
+                    tracing::error!("Saga sweep failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// Marks a saga as being run by this process until dropped, including when
+/// the future running it is cancelled
+struct SagaClaim<'a> {
+    in_flight: &'a Mutex<HashSet<Id>>,
+    id: Id,
+}
+
+impl Drop for SagaClaim<'_> {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Sagas kept in process memory; they do not survive a restart
+#[derive(Default)]
+pub struct InMemorySagaRepository {
+    sagas: Mutex<HashMap<Id, SagaRecord>>,
+}
+
+impl InMemorySagaRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SagaRepository for InMemorySagaRepository {
+    async fn save_saga(&self, saga: &SagaRecord) -> PixelleResult<()> {
+        self.sagas.lock().unwrap().insert(saga.id, saga.clone());
+        Ok(())
+    }
+
+    async fn get_saga(&self, id: Id) -> PixelleResult<Option<SagaRecord>> {
+        Ok(self.sagas.lock().unwrap().get(&id).cloned())
+    }
+
+    async fn list_unfinished(&self) -> PixelleResult<Vec<SagaRecord>> {
+        Ok(self.sagas
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|saga| !saga.status.is_finished())
+            .cloned()
+            .collect())
+    }
+}
@@ -1,6 +1,18 @@
 use async_trait::async_trait;
 use crate::types::{UserId, PostId, CommentId, UserProfile, Post, Comment, PaginationParams, PaginatedResponse};
 use crate::errors::PixelleResult;
+use crate::blocks::{BlockList, RelationKind};
+use crate::hashtags::{Hashtag, HashtagBlock, HashtagMention};
+use crate::privacy::PrivacySettings;
+use crate::notifications::NotificationPreferences;
+use crate::account_deletion::{DeletionNotice, DeletionRequest};
+use crate::saga::{SagaContext, SagaRecord};
+use crate::roles::{Decision, Permission, Resource, Role, RoleAssignment, Subject};
+use crate::invites::{Invite, Referral, ReferralEvent, ReferralStatus};
+use crate::verification::{PresignedUrl, VerificationEvent, VerificationRequest, VerificationStatus};
+use crate::types::Id;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
 
 /// Repository trait for user operations
 #[async_trait]
@@ -39,6 +51,180 @@ pub trait CommentRepository {
     async fn unlike_comment(&self, comment_id: CommentId, user_id: UserId) -> PixelleResult<()>;
 }
 
+/// Repository trait for block and mute relations
+#[async_trait]
+pub trait BlockListRepository: Send + Sync {
+    /// Returns false when the relation already existed
+    async fn add_relation(&self, actor_id: UserId, target_id: UserId, kind: RelationKind) -> PixelleResult<bool>;
+    /// Returns false when there was no such relation
+    async fn remove_relation(&self, actor_id: UserId, target_id: UserId, kind: RelationKind) -> PixelleResult<bool>;
+    async fn get_block_list(&self, user_id: UserId) -> PixelleResult<BlockList>;
+}
+
+/// Repository trait for hashtag entities, follows and the blocklist.
+///
+/// Tags passed in are already normalized.
+#[async_trait]
+pub trait HashtagRepository: Send + Sync {
+    /// Count one more post for each tag, and for each pair of them, creating unseen tags
+    async fn record_usage(&self, mentions: &[HashtagMention], at: DateTime<Utc>) -> PixelleResult<()>;
+    /// Undo `record_usage` for a removed post
+    async fn release_usage(&self, tags: &[String]) -> PixelleResult<()>;
+    async fn get_hashtag(&self, tag: &str) -> PixelleResult<Option<Hashtag>>;
+    /// Tags used in the same posts as `tag`, with how many posts they share
+    async fn co_occurrences(&self, tag: &str) -> PixelleResult<Vec<(String, u64)>>;
+    /// Returns false when already following; creates the tag as `display` if unseen
+    async fn follow(&self, user_id: UserId, tag: &str, display: &str) -> PixelleResult<bool>;
+    /// Returns false when not following
+    async fn unfollow(&self, user_id: UserId, tag: &str) -> PixelleResult<bool>;
+    async fn followed(&self, user_id: UserId) -> PixelleResult<HashSet<String>>;
+    /// Returns false when the tag was already blocked
+    async fn add_block(&self, block: &HashtagBlock) -> PixelleResult<bool>;
+    /// Returns false when the tag was not blocked
+    async fn remove_block(&self, tag: &str) -> PixelleResult<bool>;
+    async fn list_blocks(&self) -> PixelleResult<Vec<HashtagBlock>>;
+}
+
+/// Repository trait for privacy settings, which live on the user profile
+#[async_trait]
+pub trait PrivacyRepository: Send + Sync {
+    /// Default settings for users without any on record
+    async fn get_privacy_settings(&self, user_id: UserId) -> PixelleResult<PrivacySettings>;
+    async fn save_privacy_settings(&self, user_id: UserId, settings: &PrivacySettings) -> PixelleResult<()>;
+}
+
+/// Repository trait for notification preferences
+#[async_trait]
+pub trait NotificationPreferenceRepository: Send + Sync {
+    /// `None` for users who never saved preferences
+    async fn get_notification_preferences(&self, user_id: UserId) -> PixelleResult<Option<NotificationPreferences>>;
+    async fn save_notification_preferences(&self, user_id: UserId, preferences: &NotificationPreferences) -> PixelleResult<()>;
+}
+
+/// Repository trait for who follows whom
+#[async_trait]
+pub trait FollowRepository: Send + Sync {
+    async fn is_following(&self, follower_id: UserId, followee_id: UserId) -> PixelleResult<bool>;
+}
+
+/// Repository trait for account deletion requests
+#[async_trait]
+pub trait AccountDeletionRepository: Send + Sync {
+    async fn save_request(&self, request: &DeletionRequest) -> PixelleResult<()>;
+    async fn get_request(&self, user_id: UserId) -> PixelleResult<Option<DeletionRequest>>;
+    /// Requests whose grace period has ended, plus purges that have not finished
+    async fn list_due(&self, now: DateTime<Utc>) -> PixelleResult<Vec<DeletionRequest>>;
+}
+
+/// One stage of the privacy pipeline that erases a deleted account's data.
+///
+/// Steps may be retried after a failure, so they must be idempotent.
+#[async_trait]
+pub trait PurgeStep: Send + Sync {
+    /// Stable name recorded in deletion progress
+    fn name(&self) -> &str;
+    async fn purge(&self, user_id: UserId) -> PixelleResult<()>;
+}
+
+/// Sends account lifecycle emails
+#[async_trait]
+pub trait AccountMailer: Send + Sync {
+    async fn send(&self, to: &str, notice: &DeletionNotice) -> PixelleResult<()>;
+}
+
+/// Storage for saga progress
+#[async_trait]
+pub trait SagaRepository: Send + Sync {
+    async fn save_saga(&self, saga: &SagaRecord) -> PixelleResult<()>;
+    async fn get_saga(&self, id: Id) -> PixelleResult<Option<SagaRecord>>;
+    /// Sagas that are still running or compensating
+    async fn list_unfinished(&self) -> PixelleResult<Vec<SagaRecord>>;
+}
+
+/// One step of a saga, paired with the action that undoes it.
+///
+/// Both may be retried, so they must be idempotent.
+#[async_trait]
+pub trait SagaStep: Send + Sync {
+    /// Stable name recorded in saga progress
+    fn name(&self) -> &str;
+    async fn execute(&self, context: &mut SagaContext) -> PixelleResult<()>;
+    /// Undo a finished `execute`
+    async fn compensate(&self, context: &SagaContext) -> PixelleResult<()>;
+}
+
+/// Repository trait for role assignments
+#[async_trait]
+pub trait RoleRepository: Send + Sync {
+    /// `None` for users who were never assigned roles
+    async fn get_roles(&self, user_id: UserId) -> PixelleResult<Option<RoleAssignment>>;
+    async fn save_roles(&self, assignment: &RoleAssignment) -> PixelleResult<()>;
+    async fn list_with_role(&self, role: Role) -> PixelleResult<Vec<RoleAssignment>>;
+}
+
+/// Repository trait for invite codes and the referrals they bring in
+#[async_trait]
+pub trait InviteRepository: Send + Sync {
+    /// Invites are keyed by code
+    async fn get_invite(&self, code: &str) -> PixelleResult<Option<Invite>>;
+    async fn save_invite(&self, invite: &Invite) -> PixelleResult<()>;
+    async fn list_invites(&self, inviter_id: UserId) -> PixelleResult<Vec<Invite>>;
+    /// Referrals are keyed by invitee, who can only be referred once
+    async fn get_referral(&self, invitee_id: UserId) -> PixelleResult<Option<Referral>>;
+    async fn save_referral(&self, referral: &Referral) -> PixelleResult<()>;
+    async fn list_referrals(&self, inviter_id: UserId) -> PixelleResult<Vec<Referral>>;
+    async fn list_referrals_with_status(&self, status: ReferralStatus) -> PixelleResult<Vec<Referral>>;
+}
+
+/// Receives referral events, e.g. to forward them to analytics
+#[async_trait]
+pub trait ReferralEventSink: Send + Sync {
+    async fn record(&self, event: &ReferralEvent) -> PixelleResult<()>;
+}
+
+/// Issues the reward a qualified referral earned its inviter.
+///
+/// Failed rewards are retried, so issuing must be idempotent per invitee.
+#[async_trait]
+pub trait ReferralRewarder: Send + Sync {
+    async fn issue(&self, referral: &Referral) -> PixelleResult<()>;
+}
+
+/// Repository trait for profile verification requests, one per user
+#[async_trait]
+pub trait VerificationRepository: Send + Sync {
+    async fn get_verification(&self, user_id: UserId) -> PixelleResult<Option<VerificationRequest>>;
+    async fn save_verification(&self, request: &VerificationRequest) -> PixelleResult<()>;
+    async fn list_verifications_with_status(&self, status: VerificationStatus) -> PixelleResult<Vec<VerificationRequest>>;
+    /// Requests whose documents have not been deleted yet
+    async fn list_verifications_holding_documents(&self) -> PixelleResult<Vec<VerificationRequest>>;
+}
+
+/// Object storage for verification documents, which never pass through
+/// the services: clients upload and reviewers download with presigned URLs
+#[async_trait]
+pub trait VerificationDocumentStorage: Send + Sync {
+    async fn presign_upload(&self, key: &str, content_type: &str, ttl: std::time::Duration) -> PixelleResult<PresignedUrl>;
+    async fn presign_download(&self, key: &str, ttl: std::time::Duration) -> PixelleResult<PresignedUrl>;
+    /// Deleting a missing object succeeds, e.g. a document never uploaded
+    async fn delete(&self, key: &str) -> PixelleResult<()>;
+}
+
+/// Receives verification events, e.g. to audit decisions or to update the
+/// verified badge wherever profiles are copied
+#[async_trait]
+pub trait VerificationEventSink: Send + Sync {
+    async fn record(&self, event: &VerificationEvent) -> PixelleResult<()>;
+}
+
+/// Decides whether a subject holds a permission, optionally on a resource.
+///
+/// Every service evaluates through this, so access rules live in one place;
+/// `RolePolicy` is the default.
+pub trait PolicyEngine: Send + Sync {
+    fn evaluate(&self, subject: &Subject, permission: Permission, resource: Option<&Resource>) -> Decision;
+}
+
 /// Authentication service trait
 #[async_trait]
 pub trait AuthService {
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 use validator::Validate;
+use crate::locale::GeoTag;
+use crate::privacy::PrivacySettings;
 
 /// User ID type alias
 pub type UserId = Uuid;
@@ -53,7 +55,16 @@ pub struct UserProfile {
     pub bio: Option<String>,
     pub avatar_url: Option<String>,
     pub is_verified: bool,
+    /// Mirrors `privacy.private_account` for older clients
     pub is_private: bool,
+    #[serde(default)]
+    pub privacy: PrivacySettings,
+    /// ISO 639-1 language codes in order of preference
+    #[serde(default)]
+    pub preferred_languages: Vec<String>,
+    /// ISO 3166-1 alpha-2 country code
+    #[serde(default)]
+    pub region: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -69,6 +80,11 @@ pub struct Post {
     pub comments_count: u32,
     pub shares_count: u32,
     pub is_public: bool,
+    /// Detected ISO 639-1 language of the content
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub geo: Option<GeoTag>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
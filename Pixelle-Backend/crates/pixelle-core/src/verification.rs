@@ -0,0 +1,533 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::constants::{
+    VERIFICATION_DOCUMENT_RETENTION_DAYS, VERIFICATION_DOCUMENT_TYPES, VERIFICATION_MAX_DOCUMENTS,
+    VERIFICATION_NOTE_MAX_LENGTH, VERIFICATION_REAPPLY_COOLDOWN_DAYS, VERIFICATION_UPLOAD_URL_TTL_SECONDS,
+    VERIFICATION_VIEW_URL_TTL_SECONDS,
+};
+use crate::errors::{PixelleError, PixelleResult};
+use crate::roles::{Permission, RolePolicy, Subject};
+use crate::traits::{PolicyEngine, VerificationDocumentStorage, VerificationEventSink, VerificationRepository};
+use crate::types::UserId;
+
+/// Where a verification request is in review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    /// Opened; the user is still uploading documents
+    AwaitingDocuments,
+    /// Submitted and waiting in the review queue
+    Pending,
+    Approved,
+    Rejected,
+    /// Approved once, then taken back by staff
+    Revoked,
+}
+
+/// What a document is meant to prove
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    GovernmentId,
+    BusinessRegistration,
+    /// Press coverage and the like, showing the account is notable
+    Notability,
+    Other,
+}
+
+/// Why a request was rejected or a verification revoked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationReason {
+    DocumentUnreadable,
+    DocumentExpired,
+    /// The documents name someone other than the account claims to be
+    IdentityMismatch,
+    NotNotable,
+    Impersonation,
+    PolicyViolation,
+    /// Needs a note explaining it
+    Other,
+}
+
+impl VerificationReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationReason::DocumentUnreadable => "document_unreadable",
+            VerificationReason::DocumentExpired => "document_expired",
+            VerificationReason::IdentityMismatch => "identity_mismatch",
+            VerificationReason::NotNotable => "not_notable",
+            VerificationReason::Impersonation => "impersonation",
+            VerificationReason::PolicyViolation => "policy_violation",
+            VerificationReason::Other => "other",
+        }
+    }
+}
+
+/// A document attached to a verification request; the file itself is only
+/// in object storage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationDocument {
+    pub id: String,
+    pub kind: DocumentKind,
+    pub content_type: String,
+    pub storage_key: String,
+    pub added_at: DateTime<Utc>,
+    /// When the file was deleted from storage
+    pub purged_at: Option<DateTime<Utc>>,
+}
+
+/// A user's request for the verified badge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationRequest {
+    pub user_id: UserId,
+    pub status: VerificationStatus,
+    /// What the user tells reviewers, e.g. who they are
+    pub note: Option<String>,
+    pub documents: Vec<VerificationDocument>,
+    /// Whether any document is still in storage
+    pub documents_held: bool,
+    pub created_at: DateTime<Utc>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    /// When it was approved, rejected or revoked
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decided_by: Option<UserId>,
+    pub reason: Option<VerificationReason>,
+    pub reason_note: Option<String>,
+    /// When the documents are deleted; `None` while in review
+    pub purge_after: Option<DateTime<Utc>>,
+}
+
+/// A URL that grants one request on one object until it expires
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresignedUrl {
+    pub method: String,
+    pub url: String,
+    /// Headers the request must carry, e.g. its content type
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A document added to a request and where the client uploads its file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentUpload {
+    pub document: VerificationDocument,
+    pub upload: PresignedUrl,
+}
+
+/// A reviewer's call on a pending request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum VerificationDecision {
+    Approve,
+    Reject {
+        reason: VerificationReason,
+        note: Option<String>,
+    },
+}
+
+/// Something that happened to a verification request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationEvent {
+    Requested(VerificationRequest),
+    Submitted(VerificationRequest),
+    /// A reviewer opened one of the documents
+    DocumentViewed {
+        request: VerificationRequest,
+        document_id: String,
+        viewer_id: UserId,
+    },
+    Approved(VerificationRequest),
+    Rejected(VerificationRequest),
+    Revoked(VerificationRequest),
+}
+
+impl VerificationEvent {
+    /// Analytics event type
+    pub fn name(&self) -> &'static str {
+        match self {
+            VerificationEvent::Requested(_) => "verification_requested",
+            VerificationEvent::Submitted(_) => "verification_submitted",
+            VerificationEvent::DocumentViewed { .. } => "verification_document_viewed",
+            VerificationEvent::Approved(_) => "verification_approved",
+            VerificationEvent::Rejected(_) => "verification_rejected",
+            VerificationEvent::Revoked(_) => "verification_revoked",
+        }
+    }
+
+    pub fn request(&self) -> &VerificationRequest {
+        match self {
+            VerificationEvent::Requested(request)
+            | VerificationEvent::Submitted(request)
+            | VerificationEvent::DocumentViewed { request, .. }
+            | VerificationEvent::Approved(request)
+            | VerificationEvent::Rejected(request)
+            | VerificationEvent::Revoked(request) => request,
+        }
+    }
+
+    /// The staff member who acted, for reviews and decisions
+    pub fn actor_id(&self) -> Option<UserId> {
+        match self {
+            VerificationEvent::Requested(_) | VerificationEvent::Submitted(_) => None,
+            VerificationEvent::DocumentViewed { viewer_id, .. } => Some(*viewer_id),
+            VerificationEvent::Approved(request)
+            | VerificationEvent::Rejected(request)
+            | VerificationEvent::Revoked(request) => request.decided_by,
+        }
+    }
+
+    /// The user's verified badge after the event, if it changed
+    pub fn verified(&self) -> Option<bool> {
+        match self {
+            VerificationEvent::Approved(_) => Some(true),
+            VerificationEvent::Revoked(_) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Properties without the user's note or document contents
+    pub fn properties(&self) -> serde_json::Value {
+        let request = self.request();
+        let document_id = match self {
+            VerificationEvent::DocumentViewed { document_id, .. } => Some(document_id),
+            _ => None,
+        };
+        serde_json::json!({
+            "status": request.status,
+            "documents": request.documents.len(),
+            "document_id": document_id,
+            "actor_id": self.actor_id(),
+            "reason": request.reason,
+        })
+    }
+}
+
+/// Profile verification: users open a request, upload documents and submit
+/// it, and staff with `verifications:review` work the queue.
+///
+/// Documents go straight to [`VerificationDocumentStorage`] through
+/// presigned URLs, and reviewers open them the same way. They are deleted
+/// `VERIFICATION_DOCUMENT_RETENTION_DAYS` after the decision, or after the
+/// request was opened if it is never submitted, by `purge_documents`.
+/// Rejected and revoked users can ask again after
+/// `VERIFICATION_REAPPLY_COOLDOWN_DAYS`. Events go to every
+/// [`VerificationEventSink`]; their failures are logged and never fail the
+/// flow, so sinks that keep the badge elsewhere must tolerate being missed.
+pub struct VerificationService {
+    repository: Arc<dyn VerificationRepository>,
+    storage: Arc<dyn VerificationDocumentStorage>,
+    policy: Arc<dyn PolicyEngine>,
+    sinks: Vec<Arc<dyn VerificationEventSink>>,
+}
+
+impl VerificationService {
+    pub fn new(repository: Arc<dyn VerificationRepository>, storage: Arc<dyn VerificationDocumentStorage>) -> Self {
+        Self {
+            repository,
+            storage,
+            policy: Arc::new(RolePolicy),
+            sinks: Vec::new(),
+        }
+    }
+
+    pub fn with_event_sink(mut self, sink: Arc<dyn VerificationEventSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    async fn emit(&self, event: VerificationEvent) {
+        for sink in &self.sinks {
+            if let Err(e) = sink.record(&event).await {
+                tracing::warn!("Failed to record {} for {}: {}", event.name(), event.request().user_id, e);
+            }
+        }
+    }
+
+    /// The user's latest request, if any
+    pub async fn status(&self, user_id: UserId) -> PixelleResult<Option<VerificationRequest>> {
+        self.repository.get_verification(user_id).await
+    }
+
+    async fn open_request(&self, user_id: UserId) -> PixelleResult<VerificationRequest> {
+        self.repository
+            .get_verification(user_id)
+            .await?
+            .ok_or_else(|| PixelleError::NotFound(format!("No verification request for {}", user_id)))
+    }
+
+    /// Open a request for `user_id`, replacing a past rejected or revoked
+    /// one once its cooldown has passed
+    pub async fn request(&self, user_id: UserId, note: Option<String>) -> PixelleResult<VerificationRequest> {
+        validate_note(note.as_deref())?;
+        let now = Utc::now();
+        if let Some(previous) = self.repository.get_verification(user_id).await? {
+            match previous.status {
+                VerificationStatus::AwaitingDocuments | VerificationStatus::Pending => {
+                    return Err(PixelleError::Conflict("A verification request is already open".to_string()));
+                }
+                VerificationStatus::Approved => {
+                    return Err(PixelleError::Conflict("The account is already verified".to_string()));
+                }
+                VerificationStatus::Rejected | VerificationStatus::Revoked => {
+                    let again_at = previous.decided_at.unwrap_or(previous.created_at)
+                        + Duration::days(VERIFICATION_REAPPLY_COOLDOWN_DAYS);
+                    if now < again_at {
+                        return Err(PixelleError::Conflict(format!(
+                            "Verification can be requested again after {}",
+                            again_at.format("%Y-%m-%d")
+                        )));
+                    }
+                }
+            }
+            // The new request replaces this one, so nothing would delete its files later
+            self.delete_documents(previous).await?;
+        }
+
+        let request = VerificationRequest {
+            user_id,
+            status: VerificationStatus::AwaitingDocuments,
+            note,
+            documents: Vec::new(),
+            documents_held: false,
+            created_at: now,
+            submitted_at: None,
+            decided_at: None,
+            decided_by: None,
+            reason: None,
+            reason_note: None,
+            purge_after: Some(now + Duration::days(VERIFICATION_DOCUMENT_RETENTION_DAYS)),
+        };
+        self.repository.save_verification(&request).await?;
+        self.emit(VerificationEvent::Requested(request.clone())).await;
+        Ok(request)
+    }
+
+    /// Add a document to the user's open request and presign its upload,
+    /// valid for `VERIFICATION_UPLOAD_URL_TTL_SECONDS`
+    pub async fn add_document(&self, user_id: UserId, kind: DocumentKind, content_type: &str) -> PixelleResult<DocumentUpload> {
+        let mut request = self.open_request(user_id).await?;
+        if request.status != VerificationStatus::AwaitingDocuments {
+            return Err(PixelleError::Conflict("Documents can only be added before submitting".to_string()));
+        }
+        if !VERIFICATION_DOCUMENT_TYPES.contains(&content_type) {
+            return Err(PixelleError::Validation(format!(
+                "Documents must be one of {}",
+                VERIFICATION_DOCUMENT_TYPES.join(", ")
+            )));
+        }
+        if request.documents.len() >= VERIFICATION_MAX_DOCUMENTS {
+            return Err(PixelleError::Validation(format!(
+                "At most {} documents can be attached",
+                VERIFICATION_MAX_DOCUMENTS
+            )));
+        }
+
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let document = VerificationDocument {
+            storage_key: format!("{}/{}", user_id, id),
+            id,
+            kind,
+            content_type: content_type.to_string(),
+            added_at: Utc::now(),
+            purged_at: None,
+        };
+        let upload = self
+            .storage
+            .presign_upload(
+                &document.storage_key,
+                content_type,
+                std::time::Duration::from_secs(VERIFICATION_UPLOAD_URL_TTL_SECONDS),
+            )
+            .await?;
+        request.documents.push(document.clone());
+        request.documents_held = true;
+        self.repository.save_verification(&request).await?;
+        Ok(DocumentUpload { document, upload })
+    }
+
+    /// Put the user's request in the review queue
+    pub async fn submit(&self, user_id: UserId) -> PixelleResult<VerificationRequest> {
+        let mut request = self.open_request(user_id).await?;
+        if request.status != VerificationStatus::AwaitingDocuments {
+            return Err(PixelleError::Conflict("Only requests awaiting documents can be submitted".to_string()));
+        }
+        if request.documents.is_empty() {
+            return Err(PixelleError::Validation("Attach at least one document first".to_string()));
+        }
+        request.status = VerificationStatus::Pending;
+        request.submitted_at = Some(Utc::now());
+        request.purge_after = None;
+        self.repository.save_verification(&request).await?;
+        self.emit(VerificationEvent::Submitted(request.clone())).await;
+        Ok(request)
+    }
+
+    /// Submitted requests, oldest first; needs `verifications:review`
+    pub async fn queue(&self, actor: &Subject, limit: usize, offset: usize) -> PixelleResult<Vec<VerificationRequest>> {
+        self.policy.evaluate(actor, Permission::ReviewVerifications, None).into_result()?;
+        let mut pending = self.repository.list_verifications_with_status(VerificationStatus::Pending).await?;
+        pending.sort_by_key(|request| request.submitted_at);
+        Ok(pending.into_iter().skip(offset).take(limit).collect())
+    }
+
+    /// Any user's request, for review; needs `verifications:review`
+    pub async fn review_request(&self, actor: &Subject, user_id: UserId) -> PixelleResult<VerificationRequest> {
+        self.policy.evaluate(actor, Permission::ReviewVerifications, None).into_result()?;
+        self.open_request(user_id).await
+    }
+
+    /// Presign a download of a document, valid for
+    /// `VERIFICATION_VIEW_URL_TTL_SECONDS`; needs `verifications:review`
+    pub async fn document_url(&self, actor: &Subject, user_id: UserId, document_id: &str) -> PixelleResult<PresignedUrl> {
+        self.policy.evaluate(actor, Permission::ReviewVerifications, None).into_result()?;
+        let request = self.open_request(user_id).await?;
+        let document = request
+            .documents
+            .iter()
+            .find(|document| document.id == document_id)
+            .ok_or_else(|| PixelleError::NotFound(format!("Document {} not found", document_id)))?;
+        if document.purged_at.is_some() {
+            return Err(PixelleError::NotFound(format!("Document {} was deleted", document_id)));
+        }
+        let url = self
+            .storage
+            .presign_download(
+                &document.storage_key,
+                std::time::Duration::from_secs(VERIFICATION_VIEW_URL_TTL_SECONDS),
+            )
+            .await?;
+        let document_id = document.id.clone();
+        self.emit(VerificationEvent::DocumentViewed {
+            request,
+            document_id,
+            viewer_id: actor.user_id,
+        })
+        .await;
+        Ok(url)
+    }
+
+    /// Approve or reject a submitted request; needs `verifications:review`,
+    /// and nobody decides their own
+    pub async fn decide(&self, actor: &Subject, user_id: UserId, decision: VerificationDecision) -> PixelleResult<VerificationRequest> {
+        self.policy.evaluate(actor, Permission::ReviewVerifications, None).into_result()?;
+        if actor.user_id == user_id {
+            return Err(PixelleError::Authorization("Reviewers cannot decide their own verification".to_string()));
+        }
+        let mut request = self.open_request(user_id).await?;
+        if request.status != VerificationStatus::Pending {
+            return Err(PixelleError::Conflict("Only submitted requests can be decided".to_string()));
+        }
+
+        let now = Utc::now();
+        request.decided_at = Some(now);
+        request.decided_by = Some(actor.user_id);
+        request.purge_after = Some(now + Duration::days(VERIFICATION_DOCUMENT_RETENTION_DAYS));
+        match decision {
+            VerificationDecision::Approve => {
+                request.status = VerificationStatus::Approved;
+                self.repository.save_verification(&request).await?;
+                self.emit(VerificationEvent::Approved(request.clone())).await;
+            }
+            VerificationDecision::Reject { reason, note } => {
+                validate_reason(reason, note.as_deref())?;
+                request.status = VerificationStatus::Rejected;
+                request.reason = Some(reason);
+                request.reason_note = note;
+                self.repository.save_verification(&request).await?;
+                self.emit(VerificationEvent::Rejected(request.clone())).await;
+            }
+        }
+        Ok(request)
+    }
+
+    /// Take the badge back from a verified user; needs `verifications:review`
+    pub async fn revoke(
+        &self,
+        actor: &Subject,
+        user_id: UserId,
+        reason: VerificationReason,
+        note: Option<String>,
+    ) -> PixelleResult<VerificationRequest> {
+        self.policy.evaluate(actor, Permission::ReviewVerifications, None).into_result()?;
+        validate_reason(reason, note.as_deref())?;
+        let mut request = self.open_request(user_id).await?;
+        if request.status != VerificationStatus::Approved {
+            return Err(PixelleError::Conflict("Only verified accounts can be revoked".to_string()));
+        }
+        request.status = VerificationStatus::Revoked;
+        request.decided_at = Some(Utc::now());
+        request.decided_by = Some(actor.user_id);
+        request.reason = Some(reason);
+        request.reason_note = note;
+        self.repository.save_verification(&request).await?;
+        self.emit(VerificationEvent::Revoked(request.clone())).await;
+        Ok(request)
+    }
+
+    /// Delete every document of a request from storage and record it
+    async fn delete_documents(&self, mut request: VerificationRequest) -> PixelleResult<usize> {
+        let now = Utc::now();
+        let mut deleted = 0;
+        for document in request.documents.iter_mut().filter(|document| document.purged_at.is_none()) {
+            self.storage.delete(&document.storage_key).await?;
+            document.purged_at = Some(now);
+            deleted += 1;
+        }
+        request.documents_held = false;
+        self.repository.save_verification(&request).await?;
+        Ok(deleted)
+    }
+
+    /// Delete the documents whose retention ended; returns how many were
+    /// deleted
+    pub async fn purge_documents(&self) -> PixelleResult<usize> {
+        let now = Utc::now();
+        let mut deleted = 0;
+        for request in self.repository.list_verifications_holding_documents().await? {
+            if request.purge_after.is_none_or(|purge_after| purge_after > now) {
+                continue;
+            }
+            let user_id = request.user_id;
+            match self.delete_documents(request).await {
+                Ok(count) => deleted += count,
+                Err(e) => tracing::warn!("Failed to delete the verification documents of {}: {}", user_id, e),
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Run `purge_documents` every `interval` until the task is aborted
+    pub fn spawn_document_purge(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.purge_documents().await {
+                    tracing::error!("Purging verification documents failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+fn validate_note(note: Option<&str>) -> PixelleResult<()> {
+    if note.is_some_and(|note| note.chars().count() > VERIFICATION_NOTE_MAX_LENGTH) {
+        return Err(PixelleError::Validation(format!(
+            "Notes are at most {} characters",
+            VERIFICATION_NOTE_MAX_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+fn validate_reason(reason: VerificationReason, note: Option<&str>) -> PixelleResult<()> {
+    validate_note(note)?;
+    if reason == VerificationReason::Other && note.is_none_or(|note| note.trim().is_empty()) {
+        return Err(PixelleError::Validation("Explain the reason in a note".to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::sync::Arc;
+
+use crate::largetable::LargetableStore;
+use crate::maintable::MaintableStore;
+use crate::memory::InMemoryStore;
+use crate::repository::DocumentRepository;
+use crate::store::{BackendKind, Capability, DocumentStore, StoreError, StoreResult};
+
+/// Which backend each repository uses, and how to reach them
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    /// Backend of repositories without their own entry
+    pub default_backend: BackendKind,
+    /// Backend per repository, e.g. `users` in Maintable and `feed_timelines` in Largetable
+    pub repositories: BTreeMap<String, BackendKind>,
+    pub maintable_url: Option<String>,
+    pub largetable_url: Option<String>,
+    pub largetable_database: String,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            default_backend: BackendKind::Memory,
+            repositories: BTreeMap::new(),
+            maintable_url: None,
+            largetable_url: None,
+            largetable_database: "pixelle".to_string(),
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// `DATABASE_BACKEND` (default `memory`), per-repository overrides in
+    /// `DATABASE_BACKENDS` as `users=maintable,feed_timelines=largetable`,
+    /// `DATABASE_URL`, `LARGETABLE_URL` and `LARGETABLE_DATABASE`
+    pub fn from_env() -> StoreResult<Self> {
+        let mut config = Self::default();
+        if let Ok(backend) = env::var("DATABASE_BACKEND") {
+            config.default_backend = backend.parse()?;
+        }
+        if let Ok(overrides) = env::var("DATABASE_BACKENDS") {
+            for entry in overrides.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                let (repository, backend) = entry
+                    .split_once('=')
+                    .ok_or_else(|| StoreError::Config(format!("Expected repository=backend, got {}", entry)))?;
+                config.repositories.insert(repository.trim().to_string(), backend.parse()?);
+            }
+        }
+        config.maintable_url = env::var("DATABASE_URL").ok().filter(|url| !url.is_empty());
+        config.largetable_url = env::var("LARGETABLE_URL").ok().filter(|url| !url.is_empty());
+        if let Ok(database) = env::var("LARGETABLE_DATABASE") {
+            config.largetable_database = database;
+        }
+        Ok(config)
+    }
+
+    /// Place a repository on a backend
+    pub fn with_repository(mut self, repository: &str, backend: BackendKind) -> Self {
+        self.repositories.insert(repository.to_string(), backend);
+        self
+    }
+
+    pub fn backend_for(&self, repository: &str) -> BackendKind {
+        self.repositories.get(repository).copied().unwrap_or(self.default_backend)
+    }
+
+    /// Every backend some repository can be placed on
+    fn backends_in_use(&self) -> Vec<BackendKind> {
+        let mut backends: Vec<BackendKind> = self.repositories.values().copied().collect();
+        backends.push(self.default_backend);
+        backends.sort();
+        backends.dedup();
+        backends
+    }
+}
+
+/// Connected backends, handing each repository the store it is configured for
+///
+/// Services build one at startup and ask it for their repositories, which
+/// fails early when a repository is placed on a backend that lacks a
+/// capability it needs.
+pub struct Backends {
+    config: DatabaseConfig,
+    stores: HashMap<BackendKind, Arc<dyn DocumentStore>>,
+}
+
+impl Backends {
+    /// Connect to every backend the configuration places a repository on
+    pub async fn connect(config: DatabaseConfig) -> StoreResult<Self> {
+        let mut stores: HashMap<BackendKind, Arc<dyn DocumentStore>> = HashMap::new();
+        for backend in config.backends_in_use() {
+            let store: Arc<dyn DocumentStore> = match backend {
+                BackendKind::Maintable => {
+                    let url = config
+                        .maintable_url
+                        .as_deref()
+                        .ok_or_else(|| StoreError::Config("DATABASE_URL is required for maintable".to_string()))?;
+                    Arc::new(MaintableStore::connect(url).await?)
+                }
+                BackendKind::Largetable => {
+                    let url = config
+                        .largetable_url
+                        .as_deref()
+                        .ok_or_else(|| StoreError::Config("LARGETABLE_URL is required for largetable".to_string()))?;
+                    Arc::new(LargetableStore::new(url, &config.largetable_database)?)
+                }
+                BackendKind::Memory => Arc::new(InMemoryStore::new()),
+            };
+            store.health_check().await?;
+            tracing::info!("Connected to {} with {:?}", backend, store.capabilities());
+            stores.insert(backend, store);
+        }
+        Ok(Self { config, stores })
+    }
+
+    /// Backends built elsewhere, e.g. in tests
+    pub fn from_stores(config: DatabaseConfig, stores: Vec<Arc<dyn DocumentStore>>) -> Self {
+        let stores = stores.into_iter().map(|store| (store.kind(), store)).collect();
+        Self { config, stores }
+    }
+
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+
+    /// Store a repository is placed on
+    pub fn store(&self, repository: &str) -> StoreResult<Arc<dyn DocumentStore>> {
+        let backend = self.config.backend_for(repository);
+        self.stores
+            .get(&backend)
+            .cloned()
+            .ok_or_else(|| StoreError::Config(format!("{} is placed on {}, which is not connected", repository, backend)))
+    }
+
+    /// Typed repository over the collection named after it, checking its
+    /// backend has every capability in `required`
+    pub fn repository<T: Serialize + DeserializeOwned>(&self, repository: &str, required: &[Capability]) -> StoreResult<DocumentRepository<T>> {
+        let store = self.store(repository)?;
+        if let Some(capability) = required.iter().find(|capability| !store.supports(**capability)) {
+            return Err(StoreError::Unsupported {
+                backend: store.kind(),
+                capability: *capability,
+                repository: repository.to_string(),
+            });
+        }
+        DocumentRepository::new(store, repository)
+    }
+}
@@ -0,0 +1,181 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::store::{
+    validate_name, BackendKind, Capabilities, Capability, DocumentStore, StoreError, StoreQuery, StoreResult,
+};
+
+/// Fields Largetable adds to every object it returns
+const SYSTEM_FIELDS: [&str; 4] = ["_id", "_version", "_created_at", "_updated_at"];
+/// Holds the caller's ID when it is not a UUID
+const KEY_FIELD: &str = "_key";
+/// Namespace for deriving document UUIDs from other IDs
+const ID_NAMESPACE: Uuid = Uuid::from_u128(0x7c3e_51a2_94d6_4b8f_a0c1_2f6e_9d3b_8a54);
+
+/// Documents in a Largetable database, over its HTTP API
+///
+/// Collections map to Largetable collections, created on first write.
+/// Largetable identifies documents by UUID: IDs that are UUIDs are used as
+/// they are, and others are mapped to a name-based UUID and kept in `_key`.
+/// Top-level fields starting with an underscore are reserved. Replacing a
+/// document deletes and reinserts it, so a concurrent reader can briefly
+/// miss it.
+pub struct LargetableStore {
+    client: reqwest::Client,
+    base_url: String,
+    database: String,
+}
+
+impl LargetableStore {
+    pub fn new(base_url: &str, database: &str) -> StoreResult<Self> {
+        validate_name("database", database)?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            database: database.to_string(),
+        })
+    }
+
+    fn collection_url(&self, collection: &str) -> StoreResult<String> {
+        validate_name("collection", collection)?;
+        Ok(format!("{}/databases/{}/collections/{}", self.base_url, self.database, collection))
+    }
+
+    fn document_url(&self, collection: &str, id: &str) -> StoreResult<String> {
+        Ok(format!("{}/documents/{}", self.collection_url(collection)?, document_id(id)))
+    }
+}
+
+#[async_trait]
+impl DocumentStore for LargetableStore {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Largetable
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::from([Capability::Durable, Capability::SecondaryIndexes, Capability::Sharding])
+    }
+
+    async fn get(&self, collection: &str, id: &str) -> StoreResult<Option<Value>> {
+        let response = self
+            .client
+            .get(self.document_url(collection, id)?)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let document: Value = checked(response).await?.json().await.map_err(backend_error)?;
+        Ok(Some(strip_system_fields(document)))
+    }
+
+    async fn put(&self, collection: &str, id: &str, document: Value) -> StoreResult<()> {
+        let Value::Object(mut fields) = document else {
+            return Err(StoreError::Backend {
+                backend: BackendKind::Largetable,
+                message: "documents must be JSON objects".to_string(),
+            });
+        };
+        self.delete(collection, id).await?;
+
+        fields.insert("_id".to_string(), json!(document_id(id).to_string()));
+        if Uuid::parse_str(id).is_err() {
+            fields.insert(KEY_FIELD.to_string(), json!(id));
+        }
+        let response = self
+            .client
+            .post(format!("{}/documents", self.collection_url(collection)?))
+            .json(&fields)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        checked(response).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> StoreResult<bool> {
+        let response = self
+            .client
+            .delete(self.document_url(collection, id)?)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        checked(response).await?;
+        Ok(true)
+    }
+
+    async fn query(&self, collection: &str, query: &StoreQuery) -> StoreResult<Vec<Value>> {
+        let mut body = json!({ "filter": query.filter, "skip": query.offset });
+        if let Some(limit) = query.limit {
+            body["limit"] = json!(limit);
+        }
+        let response = self
+            .client
+            .post(format!("{}/query", self.collection_url(collection)?))
+            .json(&body)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        let mut result: Value = checked(response).await?.json().await.map_err(backend_error)?;
+        let documents = match result.get_mut("documents").map(Value::take) {
+            Some(Value::Array(documents)) => documents,
+            _ => Vec::new(),
+        };
+        Ok(documents.into_iter().map(strip_system_fields).collect())
+    }
+
+    async fn health_check(&self) -> StoreResult<()> {
+        let response = self
+            .client
+            .get(format!("{}/health", self.base_url))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        checked(response).await?;
+        Ok(())
+    }
+}
+
+/// Largetable ID of a document
+fn document_id(id: &str) -> Uuid {
+    Uuid::parse_str(id).unwrap_or_else(|_| Uuid::new_v5(&ID_NAMESPACE, id.as_bytes()))
+}
+
+/// The document as it was stored, without the fields Largetable adds to
+/// it and to each nested object
+fn strip_system_fields(document: Value) -> Value {
+    match document {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .filter(|(key, _)| !SYSTEM_FIELDS.contains(&key.as_str()) && key != KEY_FIELD)
+                .map(|(key, value)| (key, strip_system_fields(value)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(strip_system_fields).collect()),
+        value => value,
+    }
+}
+
+async fn checked(response: reqwest::Response) -> StoreResult<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    Err(StoreError::Backend {
+        backend: BackendKind::Largetable,
+        message: format!("{} responded with {}", response.url().path(), response.status()),
+    })
+}
+
+fn backend_error(error: reqwest::Error) -> StoreError {
+    StoreError::Backend {
+        backend: BackendKind::Largetable,
+        message: error.to_string(),
+    }
+}
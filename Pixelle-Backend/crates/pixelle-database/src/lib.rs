@@ -1,9 +1,19 @@
+pub mod backends;
 pub mod connection;
+pub mod largetable;
+pub mod maintable;
+pub mod memory;
 pub mod migrations;
 pub mod models;
 pub mod repository;
+pub mod store;
 
+pub use backends::*;
 pub use connection::*;
+pub use largetable::*;
+pub use maintable::*;
+pub use memory::*;
 pub use migrations::*;
 pub use models::*;
 pub use repository::*;
+pub use store::*;
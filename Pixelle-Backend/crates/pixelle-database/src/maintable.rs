@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::{Maintable, Pool, Row};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use crate::store::{
+    validate_name, BackendKind, Capabilities, Capability, DocumentStore, StoreError, StoreQuery, StoreResult, WriteOp,
+};
+
+/// Documents in Maintable, one table per collection
+///
+/// Each table holds the document ID and the document as `jsonb`, with a GIN
+/// index so equality filters are answered by containment (`@>`). Tables are
+/// created on first use.
+pub struct MaintableStore {
+    pool: Pool<Maintable>,
+    created: Mutex<HashSet<String>>,
+}
+
+impl MaintableStore {
+    pub fn new(pool: Pool<Maintable>) -> Self {
+        Self {
+            pool,
+            created: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub async fn connect(database_url: &str) -> StoreResult<Self> {
+        let pool = Pool::connect(database_url).await.map_err(backend_error)?;
+        Ok(Self::new(pool))
+    }
+
+    async fn table(&self, collection: &str) -> StoreResult<String> {
+        validate_name("collection", collection)?;
+        let table = format!("documents_{}", collection);
+        if self.created.lock().unwrap().contains(collection) {
+            return Ok(table);
+        }
+
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
+                id TEXT PRIMARY KEY,
+                document JSONB NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )"
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(backend_error)?;
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {table}_document ON {table} USING GIN (document jsonb_path_ops)"
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(backend_error)?;
+
+        self.created.lock().unwrap().insert(collection.to_string());
+        Ok(table)
+    }
+}
+
+#[async_trait]
+impl DocumentStore for MaintableStore {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Maintable
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::from([
+            Capability::Durable,
+            Capability::Transactions,
+            Capability::Sorting,
+            Capability::SecondaryIndexes,
+        ])
+    }
+
+    async fn get(&self, collection: &str, id: &str) -> StoreResult<Option<Value>> {
+        let table = self.table(collection).await?;
+        let row = sqlx::query(&format!("SELECT document FROM {table} WHERE id = $1"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(backend_error)?;
+        row.map(|row| row.try_get::<Json<Value>, _>("document").map(|document| document.0))
+            .transpose()
+            .map_err(backend_error)
+    }
+
+    async fn put(&self, collection: &str, id: &str, document: Value) -> StoreResult<()> {
+        let table = self.table(collection).await?;
+        sqlx::query(&upsert(&table))
+            .bind(id)
+            .bind(Json(document))
+            .execute(&self.pool)
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> StoreResult<bool> {
+        let table = self.table(collection).await?;
+        let result = sqlx::query(&format!("DELETE FROM {table} WHERE id = $1"))
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(backend_error)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn query(&self, collection: &str, query: &StoreQuery) -> StoreResult<Vec<Value>> {
+        let table = self.table(collection).await?;
+        // Missing fields sort first, as in the other backends
+        let order = match &query.order_by {
+            Some(order) if order.descending => "ORDER BY document -> $4 DESC NULLS LAST, id",
+            Some(_) => "ORDER BY document -> $4 ASC NULLS FIRST, id",
+            None => "ORDER BY id",
+        };
+        let sql = format!("SELECT document FROM {table} WHERE document @> $1 {order} LIMIT $2 OFFSET $3");
+        let mut statement = sqlx::query(&sql)
+            .bind(Json(Value::Object(query.filter.clone())))
+            .bind(query.limit.map(|limit| limit as i64))
+            .bind(query.offset as i64);
+        if let Some(order) = &query.order_by {
+            statement = statement.bind(order.field.clone());
+        }
+        let rows = statement.fetch_all(&self.pool).await.map_err(backend_error)?;
+        rows.iter()
+            .map(|row| row.try_get::<Json<Value>, _>("document").map(|document| document.0))
+            .collect::<Result<_, _>>()
+            .map_err(backend_error)
+    }
+
+    async fn write_batch(&self, writes: Vec<WriteOp>) -> StoreResult<()> {
+        // Tables are created outside the transaction, as on any first use
+        let mut tables = Vec::with_capacity(writes.len());
+        for write in &writes {
+            tables.push(self.table(write.collection()).await?);
+        }
+
+        // Rolled back when dropped on an early return
+        let mut transaction = self.pool.begin().await.map_err(backend_error)?;
+        for (write, table) in writes.into_iter().zip(tables) {
+            match write {
+                WriteOp::Insert { collection, id, document } => {
+                    let result = sqlx::query(&format!(
+                        "INSERT INTO {table} (id, document) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING"
+                    ))
+                    .bind(&id)
+                    .bind(Json(document))
+                    .execute(&mut *transaction)
+                    .await
+                    .map_err(backend_error)?;
+                    if result.rows_affected() == 0 {
+                        return Err(StoreError::Conflict { collection, id });
+                    }
+                }
+                WriteOp::Put { id, document, .. } => {
+                    sqlx::query(&upsert(&table))
+                        .bind(id)
+                        .bind(Json(document))
+                        .execute(&mut *transaction)
+                        .await
+                        .map_err(backend_error)?;
+                }
+                WriteOp::Delete { id, .. } => {
+                    sqlx::query(&format!("DELETE FROM {table} WHERE id = $1"))
+                        .bind(id)
+                        .execute(&mut *transaction)
+                        .await
+                        .map_err(backend_error)?;
+                }
+                WriteOp::Take { collection, id } => {
+                    // A concurrent take waits on the row lock, then deletes nothing
+                    let result = sqlx::query(&format!("DELETE FROM {table} WHERE id = $1"))
+                        .bind(&id)
+                        .execute(&mut *transaction)
+                        .await
+                        .map_err(backend_error)?;
+                    if result.rows_affected() == 0 {
+                        return Err(StoreError::Missing { collection, id });
+                    }
+                }
+            }
+        }
+        transaction.commit().await.map_err(backend_error)
+    }
+
+    async fn health_check(&self) -> StoreResult<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await.map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+fn upsert(table: &str) -> String {
+    format!(
+        "INSERT INTO {table} (id, document) VALUES ($1, $2)
+         ON CONFLICT (id) DO UPDATE SET document = EXCLUDED.document, updated_at = now()"
+    )
+}
+
+fn backend_error(error: sqlx::Error) -> StoreError {
+    StoreError::Backend {
+        backend: BackendKind::Maintable,
+        message: error.to_string(),
+    }
+}
@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::store::{
+    sort_and_page, validate_name, BackendKind, Capabilities, Capability, DocumentStore, StoreError, StoreQuery,
+    StoreResult, WriteOp,
+};
+
+/// Documents in process memory, for development and tests
+#[derive(Default)]
+pub struct InMemoryStore {
+    collections: RwLock<HashMap<String, BTreeMap<String, Value>>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DocumentStore for InMemoryStore {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Memory
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::from([Capability::Sorting, Capability::Transactions])
+    }
+
+    async fn get(&self, collection: &str, id: &str) -> StoreResult<Option<Value>> {
+        let collections = self.collections.read().unwrap();
+        Ok(collections.get(collection).and_then(|documents| documents.get(id)).cloned())
+    }
+
+    async fn put(&self, collection: &str, id: &str, document: Value) -> StoreResult<()> {
+        validate_name("collection", collection)?;
+        let mut collections = self.collections.write().unwrap();
+        collections
+            .entry(collection.to_string())
+            .or_default()
+            .insert(id.to_string(), document);
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> StoreResult<bool> {
+        let mut collections = self.collections.write().unwrap();
+        Ok(collections
+            .get_mut(collection)
+            .is_some_and(|documents| documents.remove(id).is_some()))
+    }
+
+    async fn query(&self, collection: &str, query: &StoreQuery) -> StoreResult<Vec<Value>> {
+        let matches: Vec<Value> = {
+            let collections = self.collections.read().unwrap();
+            collections
+                .get(collection)
+                .map(|documents| documents.values().filter(|document| query.matches(document)).cloned().collect())
+                .unwrap_or_default()
+        };
+        Ok(sort_and_page(matches, query))
+    }
+
+    async fn write_batch(&self, writes: Vec<WriteOp>) -> StoreResult<()> {
+        let mut collections = self.collections.write().unwrap();
+        // Check every insert and take before applying anything
+        let mut inserted = HashSet::new();
+        let mut taken = HashSet::new();
+        for write in &writes {
+            validate_name("collection", write.collection())?;
+            match write {
+                WriteOp::Insert { collection, id, .. } => {
+                    let held = collections.get(collection).is_some_and(|documents| documents.contains_key(id));
+                    if held || !inserted.insert((collection, id)) {
+                        return Err(StoreError::Conflict { collection: collection.clone(), id: id.clone() });
+                    }
+                }
+                WriteOp::Take { collection, id } => {
+                    let held = collections.get(collection).is_some_and(|documents| documents.contains_key(id));
+                    if !held || !taken.insert((collection, id)) {
+                        return Err(StoreError::Missing { collection: collection.clone(), id: id.clone() });
+                    }
+                }
+                WriteOp::Put { .. } | WriteOp::Delete { .. } => {}
+            }
+        }
+        for write in writes {
+            match write {
+                WriteOp::Insert { collection, id, document } | WriteOp::Put { collection, id, document } => {
+                    collections.entry(collection).or_default().insert(id, document);
+                }
+                WriteOp::Delete { collection, id } | WriteOp::Take { collection, id } => {
+                    if let Some(documents) = collections.get_mut(&collection) {
+                        documents.remove(&id);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> StoreResult<()> {
+        Ok(())
+    }
+}
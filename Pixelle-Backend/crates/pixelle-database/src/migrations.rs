@@ -1,11 +1,75 @@
-use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
 
-pub struct MigrationRunner;
+use crate::backends::Backends;
+use crate::repository::DocumentRepository;
+use crate::store::{StoreError, StoreResult};
+
+/// Repository recording which migrations have been applied
+pub const MIGRATIONS_REPOSITORY: &str = "schema_migrations";
+
+/// One change to stored data, applied once
+///
+/// A migration runs again if its instance stops before recording it, or if
+/// two instances start together, so it must be safe to repeat.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub up: for<'a> fn(&'a Backends) -> BoxFuture<'a, StoreResult<()>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AppliedMigration {
+    component: String,
+    version: u32,
+    name: String,
+    applied_at: DateTime<Utc>,
+}
+
+/// Applies one component's migrations in version order, skipping those
+/// already recorded
+pub struct MigrationRunner {
+    component: String,
+    migrations: Vec<Migration>,
+}
 
 impl MigrationRunner {
-    pub async fn run_migrations(database_url: &str) -> Result<()> {
-        // In a real implementation, this would run database migrations
-        tracing::info!("Running database migrations on {}", database_url);
-        Ok(())
+    pub fn new(component: &str, mut migrations: Vec<Migration>) -> StoreResult<Self> {
+        migrations.sort_by_key(|migration| migration.version);
+        if let Some(pair) = migrations.windows(2).find(|pair| pair[0].version == pair[1].version) {
+            return Err(StoreError::Config(format!(
+                "{} has two migrations numbered {}: {} and {}",
+                component, pair[0].version, pair[0].name, pair[1].name
+            )));
+        }
+        Ok(Self {
+            component: component.to_string(),
+            migrations,
+        })
+    }
+
+    /// Apply pending migrations, returning the versions applied
+    pub async fn run(&self, backends: &Backends) -> StoreResult<Vec<u32>> {
+        let applied: DocumentRepository<AppliedMigration> = backends.repository(MIGRATIONS_REPOSITORY, &[])?;
+        let mut versions = Vec::new();
+        for migration in &self.migrations {
+            let id = format!("{}:{}", self.component, migration.version);
+            if applied.get(&id).await?.is_some() {
+                continue;
+            }
+
+            tracing::info!("Applying {} migration {} ({})", self.component, migration.version, migration.name);
+            (migration.up)(backends).await?;
+            let record = AppliedMigration {
+                component: self.component.clone(),
+                version: migration.version,
+                name: migration.name.to_string(),
+                applied_at: Utc::now(),
+            };
+            applied.put(&id, &record).await?;
+            versions.push(migration.version);
+        }
+        Ok(versions)
     }
 }
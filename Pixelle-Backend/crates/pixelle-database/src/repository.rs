@@ -1,4 +1,12 @@
 use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::store::{
+    sort_and_page, validate_name, BackendKind, Capability, DocumentStore, StoreError, StoreQuery, StoreResult, WriteOp,
+};
 
 pub struct DatabaseRepository;
 
@@ -11,3 +19,158 @@ impl DatabaseRepository {
         Ok(true)
     }
 }
+
+/// Typed documents of one collection, on whichever backend holds it
+pub struct DocumentRepository<T> {
+    store: Arc<dyn DocumentStore>,
+    collection: String,
+    _documents: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for DocumentRepository<T> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            collection: self.collection.clone(),
+            _documents: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> DocumentRepository<T> {
+    pub fn new(store: Arc<dyn DocumentStore>, collection: &str) -> StoreResult<Self> {
+        validate_name("collection", collection)?;
+        Ok(Self {
+            store,
+            collection: collection.to_string(),
+            _documents: PhantomData,
+        })
+    }
+
+    pub fn backend(&self) -> BackendKind {
+        self.store.kind()
+    }
+
+    /// Start a transaction on this repository's backend, which other
+    /// repositories on the same backend can join
+    pub fn transaction(&self) -> StoreResult<Transaction> {
+        if !self.store.supports(Capability::Transactions) {
+            return Err(StoreError::Unsupported {
+                backend: self.store.kind(),
+                capability: Capability::Transactions,
+                repository: self.collection.clone(),
+            });
+        }
+        Ok(Transaction {
+            store: self.store.clone(),
+            writes: Vec::new(),
+        })
+    }
+
+    pub async fn get(&self, id: &str) -> StoreResult<Option<T>> {
+        match self.store.get(&self.collection, id).await? {
+            Some(document) => Ok(Some(serde_json::from_value(document)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Insert or replace a document
+    pub async fn put(&self, id: &str, document: &T) -> StoreResult<()> {
+        self.store.put(&self.collection, id, serde_json::to_value(document)?).await
+    }
+
+    pub async fn delete(&self, id: &str) -> StoreResult<bool> {
+        self.store.delete(&self.collection, id).await
+    }
+
+    /// Documents matching the query
+    ///
+    /// On backends that cannot sort, every match is fetched and sorted here
+    /// before the page is taken, so keep ordered queries on them narrow.
+    pub async fn find(&self, query: &StoreQuery) -> StoreResult<Vec<T>> {
+        let documents = if query.order_by.is_some() && !self.store.supports(Capability::Sorting) {
+            let unordered = StoreQuery {
+                filter: query.filter.clone(),
+                ..StoreQuery::default()
+            };
+            sort_and_page(self.store.query(&self.collection, &unordered).await?, query)
+        } else {
+            self.store.query(&self.collection, query).await?
+        };
+        documents
+            .into_iter()
+            .map(|document| serde_json::from_value(document).map_err(Into::into))
+            .collect()
+    }
+}
+
+/// Writes to repositories on one backend that commit together
+pub struct Transaction {
+    store: Arc<dyn DocumentStore>,
+    writes: Vec<WriteOp>,
+}
+
+impl Transaction {
+    /// Add a document; the commit fails with [`StoreError::Conflict`] if the ID is taken
+    pub fn insert<T: Serialize>(&mut self, repository: &DocumentRepository<T>, id: &str, document: &T) -> StoreResult<()> {
+        self.check_store(repository)?;
+        self.writes.push(WriteOp::Insert {
+            collection: repository.collection.clone(),
+            id: id.to_string(),
+            document: serde_json::to_value(document)?,
+        });
+        Ok(())
+    }
+
+    /// Insert or replace a document
+    pub fn put<T: Serialize>(&mut self, repository: &DocumentRepository<T>, id: &str, document: &T) -> StoreResult<()> {
+        self.check_store(repository)?;
+        self.writes.push(WriteOp::Put {
+            collection: repository.collection.clone(),
+            id: id.to_string(),
+            document: serde_json::to_value(document)?,
+        });
+        Ok(())
+    }
+
+    pub fn delete<T>(&mut self, repository: &DocumentRepository<T>, id: &str) -> StoreResult<()> {
+        self.check_store(repository)?;
+        self.writes.push(WriteOp::Delete {
+            collection: repository.collection.clone(),
+            id: id.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Delete a document; the commit fails with [`StoreError::Missing`] if
+    /// there is none, e.g. because a concurrent transaction took it first
+    pub fn take<T>(&mut self, repository: &DocumentRepository<T>, id: &str) -> StoreResult<()> {
+        self.check_store(repository)?;
+        self.writes.push(WriteOp::Take {
+            collection: repository.collection.clone(),
+            id: id.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Apply every write, or none if any fails
+    pub async fn commit(self) -> StoreResult<()> {
+        if self.writes.is_empty() {
+            return Ok(());
+        }
+        self.store.write_batch(self.writes).await
+    }
+
+    fn check_store<T>(&self, repository: &DocumentRepository<T>) -> StoreResult<()> {
+        if Arc::ptr_eq(&self.store, &repository.store) {
+            Ok(())
+        } else {
+            Err(StoreError::Config(format!(
+                "{} is on {}, outside a transaction on {}",
+                repository.collection,
+                repository.store.kind(),
+                self.store.kind()
+            )))
+        }
+    }
+}
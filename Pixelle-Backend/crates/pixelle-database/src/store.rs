@@ -0,0 +1,264 @@
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Database a repository's documents live in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BackendKind {
+    /// Relational, for aggregates that need transactions and strong consistency
+    Maintable,
+    /// Document store that shards horizontally, for large append-heavy aggregates
+    Largetable,
+    /// Process memory, for development and tests
+    Memory,
+}
+
+impl BackendKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::Maintable => "maintable",
+            BackendKind::Largetable => "largetable",
+            BackendKind::Memory => "memory",
+        }
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = StoreError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "maintable" | "postgres" | "postgresql" => Ok(BackendKind::Maintable),
+            "largetable" => Ok(BackendKind::Largetable),
+            "memory" => Ok(BackendKind::Memory),
+            other => Err(StoreError::Config(format!("Unknown database backend: {}", other))),
+        }
+    }
+}
+
+/// What a backend can do natively
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Capability {
+    /// Survives a restart
+    Durable,
+    /// Writes to several documents can commit together
+    Transactions,
+    /// Queries are ordered by the backend, so limits apply after sorting
+    Sorting,
+    /// Field filters can be served from an index rather than a scan
+    SecondaryIndexes,
+    /// Data is spread over several servers as it grows
+    Sharding,
+}
+
+pub type Capabilities = BTreeSet<Capability>;
+
+/// Query on a collection: equality filters, an optional order and a page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreQuery {
+    /// Top-level fields that must equal the given values
+    pub filter: Map<String, Value>,
+    pub order_by: Option<Order>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl StoreQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filter(mut self, field: &str, value: impl Into<Value>) -> Self {
+        self.filter.insert(field.to_string(), value.into());
+        self
+    }
+
+    pub fn order_by(mut self, field: &str, descending: bool) -> Self {
+        self.order_by = Some(Order { field: field.to_string(), descending });
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Whether a document passes the filter
+    pub fn matches(&self, document: &Value) -> bool {
+        self.filter
+            .iter()
+            .all(|(field, expected)| document.get(field) == Some(expected))
+    }
+}
+
+/// One write of a batch that commits as a whole
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteOp {
+    /// Add a document, failing the batch with [`StoreError::Conflict`] if the ID is taken
+    Insert { collection: String, id: String, document: Value },
+    /// Insert or replace a document
+    Put { collection: String, id: String, document: Value },
+    Delete { collection: String, id: String },
+    /// Delete a document, failing the batch with [`StoreError::Missing`] if there is none
+    Take { collection: String, id: String },
+}
+
+impl WriteOp {
+    pub fn collection(&self) -> &str {
+        match self {
+            WriteOp::Insert { collection, .. }
+            | WriteOp::Put { collection, .. }
+            | WriteOp::Delete { collection, .. }
+            | WriteOp::Take { collection, .. } => collection,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Database configuration error: {0}")]
+    Config(String),
+
+    #[error("{backend} does not support {capability:?}, which {repository} requires")]
+    Unsupported {
+        backend: BackendKind,
+        capability: Capability,
+        repository: String,
+    },
+
+    #[error("{collection} already holds {id}")]
+    Conflict { collection: String, id: String },
+
+    #[error("{collection} does not hold {id}")]
+    Missing { collection: String, id: String },
+
+    #[error("Invalid {what}: {value}")]
+    InvalidName { what: &'static str, value: String },
+
+    #[error("{backend} error: {message}")]
+    Backend { backend: BackendKind, message: String },
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// JSON documents by collection and ID, on one backend
+///
+/// Each backend maps collections and documents onto its own model; callers
+/// only see documents and the [`Capability`]s the backend reports, and adapt
+/// to what is missing, e.g. by sorting results themselves.
+#[async_trait]
+pub trait DocumentStore: Send + Sync {
+    fn kind(&self) -> BackendKind;
+
+    fn capabilities(&self) -> Capabilities;
+
+    fn supports(&self, capability: Capability) -> bool {
+        self.capabilities().contains(&capability)
+    }
+
+    async fn get(&self, collection: &str, id: &str) -> StoreResult<Option<Value>>;
+
+    /// Insert or replace a document
+    async fn put(&self, collection: &str, id: &str, document: Value) -> StoreResult<()>;
+
+    /// Whether a document was deleted
+    async fn delete(&self, collection: &str, id: &str) -> StoreResult<bool>;
+
+    /// Documents matching the query; backends without [`Capability::Sorting`]
+    /// ignore `order_by`, and [`DocumentRepository`](crate::DocumentRepository)
+    /// sorts for them
+    async fn query(&self, collection: &str, query: &StoreQuery) -> StoreResult<Vec<Value>>;
+
+    /// Apply every write or none of them; only backends with
+    /// [`Capability::Transactions`] can
+    async fn write_batch(&self, writes: Vec<WriteOp>) -> StoreResult<()> {
+        Err(StoreError::Unsupported {
+            backend: self.kind(),
+            capability: Capability::Transactions,
+            repository: writes.first().map(|write| write.collection().to_string()).unwrap_or_default(),
+        })
+    }
+
+    async fn health_check(&self) -> StoreResult<()>;
+}
+
+/// Sort documents by the query's order and apply its page
+///
+/// For backends that cannot sort; `documents` must hold every match.
+pub fn sort_and_page(mut documents: Vec<Value>, query: &StoreQuery) -> Vec<Value> {
+    if let Some(order) = &query.order_by {
+        documents.sort_by(|a, b| {
+            let ordering = compare_values(a.get(&order.field), b.get(&order.field));
+            if order.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+    documents
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Order of JSON values: missing and null first, then booleans, numbers,
+/// strings, arrays and objects
+fn compare_values(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    fn rank(value: Option<&Value>) -> u8 {
+        match value {
+            None | Some(Value::Null) => 0,
+            Some(Value::Bool(_)) => 1,
+            Some(Value::Number(_)) => 2,
+            Some(Value::String(_)) => 3,
+            Some(Value::Array(_)) => 4,
+            Some(Value::Object(_)) => 5,
+        }
+    }
+    match (a, b) {
+        (Some(Value::Bool(a)), Some(Value::Bool(b))) => a.cmp(b),
+        (Some(Value::Number(a)), Some(Value::Number(b))) => {
+            a.as_f64().unwrap_or(0.0).total_cmp(&b.as_f64().unwrap_or(0.0))
+        }
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Collection names become table names, so only lowercase letters, digits
+/// and underscores are allowed
+pub fn validate_name(what: &'static str, value: &str) -> StoreResult<()> {
+    let valid = !value.is_empty()
+        && value.len() <= 63
+        && value.starts_with(|c: char| c.is_ascii_lowercase())
+        && value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidName { what, value: value.to_string() })
+    }
+}
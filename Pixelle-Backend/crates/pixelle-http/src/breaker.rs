@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakerState {
+    Closed { failures: u32 },
+    /// Calls fail fast until the deadline passes
+    Open { until: Instant },
+    /// One probe is in flight; its outcome closes or reopens the circuit
+    HalfOpen { probe_started: Instant },
+}
+
+/// Circuit breaker for one destination host
+///
+/// Opens after `failure_threshold` consecutive failures, so callers stop
+/// waiting on timeouts from a host that is down. Once `open_duration` has
+/// passed a single probe is let through. A probe that never reports back
+/// (its caller was cancelled) is replaced by another after the same delay.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_duration: Duration,
+    state: BreakerState,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+            state: BreakerState::Closed { failures: 0 },
+        }
+    }
+
+    /// Whether a call may go out now
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        match self.state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } if now >= until => {
+                self.state = BreakerState::HalfOpen { probe_started: now };
+                true
+            }
+            BreakerState::HalfOpen { probe_started } if now >= probe_started + self.open_duration => {
+                self.state = BreakerState::HalfOpen { probe_started: now };
+                true
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => false,
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.state = BreakerState::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&mut self, now: Instant) {
+        self.state = match self.state {
+            BreakerState::Closed { failures } if failures + 1 < self.failure_threshold => {
+                BreakerState::Closed { failures: failures + 1 }
+            }
+            _ => BreakerState::Open { until: now + self.open_duration },
+        };
+    }
+
+    /// True while calls are being rejected or a probe is deciding
+    pub fn is_open(&self) -> bool {
+        !matches!(self.state, BreakerState::Closed { .. })
+    }
+}
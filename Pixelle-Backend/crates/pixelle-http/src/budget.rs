@@ -0,0 +1,40 @@
+use std::sync::Mutex;
+
+/// Caps retries at a fraction of request volume
+///
+/// Each original request deposits `ratio` tokens and each retry spends one,
+/// so when a dependency fails outright retries add at most `ratio` extra load
+/// instead of multiplying it. The balance never exceeds `reserve` plus what
+/// 100 retries would cost, and starts at `reserve`.
+#[derive(Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    max_balance: f64,
+    balance: Mutex<f64>,
+}
+
+impl RetryBudget {
+    pub fn new(ratio: f64, reserve: f64) -> Self {
+        Self {
+            ratio,
+            max_balance: reserve + 100.0,
+            balance: Mutex::new(reserve),
+        }
+    }
+
+    /// Credit the budget for an original (non-retry) request
+    pub fn deposit(&self) {
+        let mut balance = self.balance.lock().unwrap();
+        *balance = (*balance + self.ratio).min(self.max_balance);
+    }
+
+    /// Spend one retry, if the budget allows it
+    pub fn try_withdraw(&self) -> bool {
+        let mut balance = self.balance.lock().unwrap();
+        if *balance < 1.0 {
+            return false;
+        }
+        *balance -= 1.0;
+        true
+    }
+}
@@ -0,0 +1,374 @@
+use pixelle_core::PixelleError;
+use pixelle_monitoring::{current_trace_ids, DownstreamCall, RequestContext, TraceParent, TRACEPARENT_HEADER};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, Request, Response, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::breaker::CircuitBreaker;
+use crate::budget::RetryBudget;
+use crate::config::HttpClientConfig;
+use crate::metrics::HttpClientMetrics;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpClientError {
+    #[error("Circuit open for {0}")]
+    CircuitOpen(String),
+
+    #[error("Request to {host} timed out")]
+    Timeout { host: String },
+
+    #[error("Request to {host} failed: {source}")]
+    Request { host: String, source: reqwest::Error },
+}
+
+impl HttpClientError {
+    /// Whether the destination is unavailable rather than slow
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            HttpClientError::CircuitOpen(_) => true,
+            HttpClientError::Timeout { .. } => false,
+            HttpClientError::Request { source, .. } => source.is_connect(),
+        }
+    }
+}
+
+impl From<HttpClientError> for PixelleError {
+    fn from(error: HttpClientError) -> Self {
+        PixelleError::ExternalService(error.to_string())
+    }
+}
+
+/// Shared outbound HTTP client
+///
+/// Create one per process and clone it: clones share the connection pool,
+/// retry budget, circuit breakers and metrics. Every call gets the configured
+/// timeouts, `x-request-id` and `traceparent` headers for the request being
+/// handled, and is counted under its destination host. Idempotent requests
+/// that fail with a connection error, timeout, 502, 503 or 504 are retried
+/// while the retry budget allows.
+#[derive(Clone)]
+pub struct HttpClient {
+    inner: Arc<ClientInner>,
+}
+
+struct ClientInner {
+    client: reqwest::Client,
+    config: HttpClientConfig,
+    budget: RetryBudget,
+    breakers: Mutex<HashMap<String, CircuitBreaker>>,
+    metrics: HttpClientMetrics,
+}
+
+impl HttpClient {
+    pub fn new(config: HttpClientConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout)
+            .pool_idle_timeout(config.pool_idle_timeout)
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .user_agent(config.user_agent.clone())
+            .build()
+            .unwrap_or_default();
+        Self {
+            inner: Arc::new(ClientInner {
+                client,
+                budget: RetryBudget::new(config.retry_budget_ratio, config.retry_budget_reserve),
+                breakers: Mutex::new(HashMap::new()),
+                metrics: HttpClientMetrics::new(),
+                config,
+            }),
+        }
+    }
+
+    /// Client configured from `HTTP_*` environment variables
+    pub fn from_env(service: &str) -> Self {
+        Self::new(HttpClientConfig::from_env(service))
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        RequestBuilder {
+            client: self.clone(),
+            builder: self.inner.client.request(method, url),
+            context: None,
+            idempotent: None,
+        }
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    pub fn put(&self, url: &str) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    pub fn delete(&self, url: &str) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    pub fn metrics(&self) -> &HttpClientMetrics {
+        &self.inner.metrics
+    }
+
+    /// Hosts whose circuit is currently open
+    pub fn open_circuits(&self) -> Vec<String> {
+        let breakers = self.inner.breakers.lock().unwrap();
+        let mut hosts: Vec<String> = breakers
+            .iter()
+            .filter(|(_, breaker)| breaker.is_open())
+            .map(|(host, _)| host.clone())
+            .collect();
+        hosts.sort();
+        hosts
+    }
+
+    fn acquire(&self, host: &str) -> bool {
+        let mut breakers = self.inner.breakers.lock().unwrap();
+        let config = &self.inner.config;
+        breakers
+            .entry(host.to_string())
+            .or_insert_with(|| CircuitBreaker::new(config.breaker_failure_threshold, config.breaker_open_duration))
+            .try_acquire(Instant::now())
+    }
+
+    fn record(&self, host: &str, success: bool) {
+        let mut breakers = self.inner.breakers.lock().unwrap();
+        let Some(breaker) = breakers.get_mut(host) else {
+            return;
+        };
+        let was_open = breaker.is_open();
+        if success {
+            breaker.record_success();
+        } else {
+            breaker.record_failure(Instant::now());
+        }
+        let is_open = breaker.is_open();
+        drop(breakers);
+
+        if is_open != was_open {
+            if is_open {
+                tracing::warn!("Circuit to {} opened", host);
+            } else {
+                tracing::info!("Circuit to {} closed", host);
+            }
+            self.inner.metrics.set_circuit_open(host, is_open);
+        }
+    }
+
+    async fn execute(&self, mut request: Request, context: Option<RequestContext>, idempotent: bool) -> Result<Response, HttpClientError> {
+        let host = destination(&request);
+        propagate_trace(request.headers_mut(), context.as_ref());
+        self.inner.budget.deposit();
+        // Captured requests record each call once, after its last attempt
+        let recorder = context.and_then(|context| context.capture);
+        let call_started = Instant::now();
+        let call = DownstreamCall {
+            method: request.method().to_string(),
+            url: sanitized_url(request.url()),
+            span_id: request
+                .headers()
+                .get(TRACEPARENT_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(TraceParent::parse)
+                .map(|trace| trace.span_id),
+            status: None,
+            error: None,
+            attempts: 0,
+            duration_ms: 0,
+        };
+        let record_call = |status: Option<u16>, error: Option<String>, attempts: u32| {
+            if let Some(recorder) = &recorder {
+                recorder.record(DownstreamCall {
+                    status,
+                    error,
+                    attempts,
+                    duration_ms: call_started.elapsed().as_millis() as u64,
+                    ..call.clone()
+                });
+            }
+        };
+
+        let mut attempt = 0;
+        loop {
+            if !self.acquire(&host) {
+                self.inner.metrics.increment_rejected(&host);
+                let error = HttpClientError::CircuitOpen(host);
+                record_call(None, Some(error.to_string()), attempt);
+                return Err(error);
+            }
+
+            // Streaming bodies cannot be replayed, so they get a single attempt
+            let retry = if idempotent && attempt < self.inner.config.max_retries { request.try_clone() } else { None };
+            let started = Instant::now();
+            let result = self.inner.client.execute(request).await;
+            let elapsed = started.elapsed().as_secs_f64();
+
+            let (outcome, failed, retryable) = match &result {
+                Ok(response) => {
+                    let status = response.status();
+                    let retryable = matches!(
+                        status,
+                        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+                    );
+                    (status_class(status), status.is_server_error(), retryable)
+                }
+                Err(e) if e.is_timeout() => ("timeout", true, true),
+                Err(_) => ("error", true, true),
+            };
+            self.inner.metrics.observe_attempt(&host, outcome, elapsed);
+            self.record(&host, !failed);
+
+            match retry {
+                Some(next) if retryable && self.inner.budget.try_withdraw() => {
+                    tracing::debug!("Retrying {} {} after {}", next.method(), next.url(), outcome);
+                    self.inner.metrics.increment_retries(&host);
+                    tokio::time::sleep(self.inner.config.retry_backoff * 2u32.saturating_pow(attempt)).await;
+                    request = next;
+                    attempt += 1;
+                }
+                _ => {
+                    record_call(
+                        result.as_ref().ok().map(|response| response.status().as_u16()),
+                        result.as_ref().err().map(|e| e.to_string()),
+                        attempt + 1,
+                    );
+                    return result.map_err(|source| {
+                        if source.is_timeout() {
+                            HttpClientError::Timeout { host }
+                        } else {
+                            HttpClientError::Request { host, source }
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Request under construction, sent through [`HttpClient`]
+pub struct RequestBuilder {
+    client: HttpClient,
+    builder: reqwest::RequestBuilder,
+    context: Option<RequestContext>,
+    idempotent: Option<bool>,
+}
+
+impl RequestBuilder {
+    /// Correlate the call with the request being handled
+    pub fn context(mut self, context: &RequestContext) -> Self {
+        self.context = Some(context.clone());
+        self
+    }
+
+    /// Override whether the request may be retried; by default only
+    /// GET, HEAD, OPTIONS, PUT and DELETE are
+    pub fn idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent = Some(idempotent);
+        self
+    }
+
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.builder = self.builder.header(name, value);
+        self
+    }
+
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.builder = self.builder.headers(headers);
+        self
+    }
+
+    pub fn query<T: Serialize + ?Sized>(mut self, query: &T) -> Self {
+        self.builder = self.builder.query(query);
+        self
+    }
+
+    pub fn json<T: Serialize + ?Sized>(mut self, json: &T) -> Self {
+        self.builder = self.builder.json(json);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<reqwest::Body>) -> Self {
+        self.builder = self.builder.body(body);
+        self
+    }
+
+    /// Per-attempt timeout replacing the client default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.builder = self.builder.timeout(timeout);
+        self
+    }
+
+    pub async fn send(self) -> Result<Response, HttpClientError> {
+        let request = self.builder.build().map_err(|source| HttpClientError::Request {
+            host: source.url().map(host_label).unwrap_or_default(),
+            source,
+        })?;
+        let idempotent = self.idempotent.unwrap_or_else(|| {
+            matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE)
+        });
+        self.client.execute(request, self.context, idempotent).await
+    }
+}
+
+/// Metric and breaker key for a URL, e.g. `feed-service:8082`
+fn host_label(url: &reqwest::Url) -> String {
+    match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// URL without credentials, as recorded in request captures
+fn sanitized_url(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.to_string()
+}
+
+fn destination(request: &Request) -> String {
+    host_label(request.url())
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Stamp the request with the caller's correlation IDs
+///
+/// An explicit context wins over headers copied from elsewhere; without one
+/// the active OpenTelemetry span is used, if any.
+fn propagate_trace(headers: &mut HeaderMap, context: Option<&RequestContext>) {
+    match context {
+        Some(context) => {
+            for (name, value) in context.headers() {
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    headers.insert(name, value);
+                }
+            }
+        }
+        None => {
+            if headers.contains_key(TRACEPARENT_HEADER) {
+                return;
+            }
+            if let Some((trace_id, span_id)) = current_trace_ids() {
+                if let Ok(value) = HeaderValue::from_str(&format!("00-{}-{}-01", trace_id, span_id)) {
+                    headers.insert(TRACEPARENT_HEADER, value);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Outbound HTTP defaults
+pub const HTTP_CONNECT_TIMEOUT_MS: u64 = 2000;
+pub const HTTP_REQUEST_TIMEOUT_MS: u64 = 10_000;
+pub const HTTP_POOL_IDLE_TIMEOUT_SECONDS: u64 = 90;
+pub const HTTP_POOL_MAX_IDLE_PER_HOST: usize = 32;
+pub const HTTP_MAX_RETRIES: u32 = 2;
+pub const HTTP_RETRY_BACKOFF_MS: u64 = 50;
+/// Retries allowed per original request, averaged over time
+pub const HTTP_RETRY_BUDGET_RATIO: f64 = 0.1;
+/// Retries always available, so a quiet service can still retry a failed call
+pub const HTTP_RETRY_BUDGET_RESERVE: f64 = 10.0;
+pub const HTTP_BREAKER_FAILURE_THRESHOLD: u32 = 5;
+pub const HTTP_BREAKER_OPEN_SECONDS: u64 = 30;
+
+/// Timeouts, pooling, retries and circuit breaking for outbound calls
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    pub connect_timeout: Duration,
+    /// Whole-request timeout for each attempt, including reading the body
+    pub request_timeout: Duration,
+    /// Idle pooled connections are closed after this long
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+    /// Retries after the first attempt, for idempotent requests only
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each one after
+    pub retry_backoff: Duration,
+    pub retry_budget_ratio: f64,
+    pub retry_budget_reserve: f64,
+    /// Consecutive failures to a host that open its circuit
+    pub breaker_failure_threshold: u32,
+    /// How long an open circuit fails fast before letting a probe through
+    pub breaker_open_duration: Duration,
+    /// Sent as the `User-Agent` header
+    pub user_agent: String,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_millis(HTTP_CONNECT_TIMEOUT_MS),
+            request_timeout: Duration::from_millis(HTTP_REQUEST_TIMEOUT_MS),
+            pool_idle_timeout: Duration::from_secs(HTTP_POOL_IDLE_TIMEOUT_SECONDS),
+            pool_max_idle_per_host: HTTP_POOL_MAX_IDLE_PER_HOST,
+            max_retries: HTTP_MAX_RETRIES,
+            retry_backoff: Duration::from_millis(HTTP_RETRY_BACKOFF_MS),
+            retry_budget_ratio: HTTP_RETRY_BUDGET_RATIO,
+            retry_budget_reserve: HTTP_RETRY_BUDGET_RESERVE,
+            breaker_failure_threshold: HTTP_BREAKER_FAILURE_THRESHOLD,
+            breaker_open_duration: Duration::from_secs(HTTP_BREAKER_OPEN_SECONDS),
+            user_agent: "pixelle".to_string(),
+        }
+    }
+}
+
+impl HttpClientConfig {
+    /// Defaults overridden by `HTTP_*` environment variables, identifying as `service`
+    pub fn from_env(service: &str) -> Self {
+        fn var<T: FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.parse().ok())
+        }
+
+        let mut config = Self {
+            user_agent: format!("pixelle-{}", service),
+            ..Self::default()
+        };
+        if let Some(ms) = var("HTTP_CONNECT_TIMEOUT_MS") {
+            config.connect_timeout = Duration::from_millis(ms);
+        }
+        if let Some(ms) = var("HTTP_REQUEST_TIMEOUT_MS") {
+            config.request_timeout = Duration::from_millis(ms);
+        }
+        if let Some(seconds) = var("HTTP_POOL_IDLE_TIMEOUT_SECONDS") {
+            config.pool_idle_timeout = Duration::from_secs(seconds);
+        }
+        if let Some(max_idle) = var("HTTP_POOL_MAX_IDLE_PER_HOST") {
+            config.pool_max_idle_per_host = max_idle;
+        }
+        if let Some(retries) = var("HTTP_MAX_RETRIES") {
+            config.max_retries = retries;
+        }
+        if let Some(ratio) = var::<f64>("HTTP_RETRY_BUDGET_RATIO").filter(|ratio| *ratio >= 0.0) {
+            config.retry_budget_ratio = ratio;
+        }
+        if let Some(threshold) = var::<u32>("HTTP_BREAKER_FAILURE_THRESHOLD").filter(|threshold| *threshold > 0) {
+            config.breaker_failure_threshold = threshold;
+        }
+        if let Some(seconds) = var("HTTP_BREAKER_OPEN_SECONDS") {
+            config.breaker_open_duration = Duration::from_secs(seconds);
+        }
+        config
+    }
+}
@@ -0,0 +1,11 @@
+pub mod breaker;
+pub mod budget;
+pub mod client;
+pub mod config;
+pub mod metrics;
+
+pub use breaker::*;
+pub use budget::*;
+pub use client::*;
+pub use config::*;
+pub use metrics::*;
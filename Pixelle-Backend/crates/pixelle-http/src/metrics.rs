@@ -0,0 +1,90 @@
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry};
+use std::sync::Arc;
+
+/// Outbound call metrics, labelled by destination host
+#[derive(Clone)]
+pub struct HttpClientMetrics {
+    registry: Arc<Registry>,
+    requests_total: IntCounterVec,
+    request_duration: HistogramVec,
+    retries_total: IntCounterVec,
+    rejected_total: IntCounterVec,
+    circuit_open: IntGaugeVec,
+}
+
+impl HttpClientMetrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let requests_total = IntCounterVec::new(
+            Opts::new("outbound_requests_total", "Outbound HTTP attempts by host and outcome"),
+            &["host", "outcome"],
+        ).unwrap();
+
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "outbound_request_duration_seconds",
+                "Outbound HTTP attempt duration in seconds",
+            ),
+            &["host"],
+        ).unwrap();
+
+        let retries_total = IntCounterVec::new(
+            Opts::new("outbound_retries_total", "Outbound HTTP retries by host"),
+            &["host"],
+        ).unwrap();
+
+        let rejected_total = IntCounterVec::new(
+            Opts::new("outbound_circuit_rejections_total", "Calls failed fast by an open circuit"),
+            &["host"],
+        ).unwrap();
+
+        let circuit_open = IntGaugeVec::new(
+            Opts::new("outbound_circuit_open", "1 while the circuit to a host is open"),
+            &["host"],
+        ).unwrap();
+
+        registry.register(Box::new(requests_total.clone())).unwrap();
+        registry.register(Box::new(request_duration.clone())).unwrap();
+        registry.register(Box::new(retries_total.clone())).unwrap();
+        registry.register(Box::new(rejected_total.clone())).unwrap();
+        registry.register(Box::new(circuit_open.clone())).unwrap();
+
+        Self {
+            registry: Arc::new(registry),
+            requests_total,
+            request_duration,
+            retries_total,
+            rejected_total,
+            circuit_open,
+        }
+    }
+
+    /// `outcome` is the status class (`2xx`, `5xx`, ...), `timeout` or `error`
+    pub fn observe_attempt(&self, host: &str, outcome: &str, duration: f64) {
+        self.requests_total.with_label_values(&[host, outcome]).inc();
+        self.request_duration.with_label_values(&[host]).observe(duration);
+    }
+
+    pub fn increment_retries(&self, host: &str) {
+        self.retries_total.with_label_values(&[host]).inc();
+    }
+
+    pub fn increment_rejected(&self, host: &str) {
+        self.rejected_total.with_label_values(&[host]).inc();
+    }
+
+    pub fn set_circuit_open(&self, host: &str, open: bool) {
+        self.circuit_open.with_label_values(&[host]).set(i64::from(open));
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+}
+
+impl Default for HttpClientMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,491 @@
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web::{self, Bytes};
+use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use crate::logging::{RequestContext, REQUEST_ID_HEADER, TRACEPARENT_HEADER};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::env;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Header the debug routes read the access token from
+pub const CAPTURE_TOKEN_HEADER: &str = "x-capture-token";
+/// Set by the replay tool to the ID of the capture being replayed
+pub const REPLAY_OF_HEADER: &str = "x-pixelle-replay-of";
+
+/// Headers never stored; replays must supply them again
+const DROPPED_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie", "set-cookie", "x-api-key", CAPTURE_TOKEN_HEADER];
+
+/// Body and query fields whose name contains one of these are redacted
+const DEFAULT_REDACTED_FIELDS: &[&str] = &["password", "secret", "token", "api_key", "apikey", "card_number", "cvv"];
+
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    pub service: String,
+    /// Path prefixes whose requests are captured; empty disables capture
+    pub routes: Vec<String>,
+    /// How long a capture is kept
+    pub ttl: Duration,
+    /// Captures kept at once; the oldest is evicted first
+    pub max_captures: usize,
+    /// Larger bodies are not stored, and such captures cannot be replayed
+    pub max_body_bytes: usize,
+    /// Redacted in addition to the defaults, matched as substrings of field names
+    pub redact_fields: Vec<String>,
+    /// Required in `x-capture-token` by the debug routes when set
+    pub access_token: Option<String>,
+}
+
+impl CaptureConfig {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            routes: Vec::new(),
+            ttl: Duration::from_secs(900),
+            max_captures: 500,
+            max_body_bytes: 64 * 1024,
+            redact_fields: Vec::new(),
+            access_token: None,
+        }
+    }
+
+    /// Read `CAPTURE_ROUTES` and the other `CAPTURE_*` variables
+    pub fn from_env(service: impl Into<String>) -> Self {
+        let mut config = Self::new(service);
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let list = |value: String| -> Vec<String> {
+            value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect()
+        };
+
+        config.routes = var("CAPTURE_ROUTES").map(list).unwrap_or_default();
+        if let Some(seconds) = var("CAPTURE_TTL_SECONDS").and_then(|v| v.parse().ok()) {
+            config.ttl = Duration::from_secs(seconds);
+        }
+        if let Some(max) = var("CAPTURE_MAX").and_then(|v| v.parse().ok()) {
+            config.max_captures = max;
+        }
+        if let Some(bytes) = var("CAPTURE_MAX_BODY_BYTES").and_then(|v| v.parse().ok()) {
+            config.max_body_bytes = bytes;
+        }
+        config.redact_fields = var("CAPTURE_REDACT_FIELDS")
+            .map(|fields| list(fields.to_lowercase()))
+            .unwrap_or_default();
+        config.access_token = var("CAPTURE_ACCESS_TOKEN");
+        config
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.routes.is_empty() && self.max_captures > 0
+    }
+
+    pub fn captures(&self, path: &str) -> bool {
+        self.max_captures > 0 && self.routes.iter().any(|route| path.starts_with(route.as_str()))
+    }
+}
+
+/// Sanitized request body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum CapturedBody {
+    Json(Value),
+    Text(String),
+}
+
+/// Call made to another service while a captured request was handled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownstreamCall {
+    pub method: String,
+    pub url: String,
+    /// Span ID sent in `traceparent`; the callee's capture has it as `parent_span_id`
+    pub span_id: Option<String>,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub duration_ms: u64,
+}
+
+/// A handled request, sanitized and stored for inspection and replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedRequest {
+    pub id: String,
+    pub service: String,
+    pub captured_at: DateTime<Utc>,
+    pub request_id: String,
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    /// Headers that were present but not stored
+    pub dropped_headers: Vec<String>,
+    pub body: Option<CapturedBody>,
+    /// Why the body is missing although the request had one
+    pub body_omitted: Option<String>,
+    pub status: u16,
+    pub duration_ms: u64,
+    pub calls: Vec<DownstreamCall>,
+}
+
+impl CapturedRequest {
+    /// Whether replaying sends the same inputs the service saw
+    pub fn replayable(&self) -> bool {
+        self.body_omitted.is_none()
+    }
+}
+
+/// Listing entry for `GET /debug/captures`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureSummary {
+    pub id: String,
+    pub captured_at: DateTime<Utc>,
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: u64,
+    pub calls: usize,
+}
+
+impl From<&CapturedRequest> for CaptureSummary {
+    fn from(capture: &CapturedRequest) -> Self {
+        Self {
+            id: capture.id.clone(),
+            captured_at: capture.captured_at,
+            request_id: capture.request_id.clone(),
+            method: capture.method.clone(),
+            path: capture.path.clone(),
+            status: capture.status,
+            duration_ms: capture.duration_ms,
+            calls: capture.calls.len(),
+        }
+    }
+}
+
+/// Collects the downstream calls of one captured request
+///
+/// Carried on [`RequestContext::capture`]; the shared HTTP client records
+/// every call made with a context that has one.
+#[derive(Debug, Clone)]
+pub struct CallRecorder {
+    calls: Arc<Mutex<Vec<DownstreamCall>>>,
+    redact_fields: Arc<Vec<String>>,
+}
+
+impl CallRecorder {
+    fn new(redact_fields: Arc<Vec<String>>) -> Self {
+        Self {
+            calls: Arc::new(Mutex::new(Vec::new())),
+            redact_fields,
+        }
+    }
+
+    /// Add a call, redacting sensitive query parameters from its URL
+    pub fn record(&self, mut call: DownstreamCall) {
+        call.url = match call.url.split_once('?') {
+            Some((base, query)) => format!("{}?{}", base, redact_query(query, &self.redact_fields)),
+            None => call.url,
+        };
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn take(&self) -> Vec<DownstreamCall> {
+        std::mem::take(&mut *self.calls.lock().unwrap())
+    }
+}
+
+fn is_sensitive(name: &str, extra: &[String]) -> bool {
+    let name = name.to_lowercase();
+    DEFAULT_REDACTED_FIELDS.iter().any(|field| name.contains(field)) || extra.iter().any(|field| name.contains(field.as_str()))
+}
+
+fn redact_query(query: &str, extra: &[String]) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_sensitive(name, extra) => format!("{}={}", name, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn redact_json(value: &mut Value, extra: &[String]) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if is_sensitive(name, extra) {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(field, extra);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact_json(item, extra)),
+        _ => {}
+    }
+}
+
+/// Recent captures, kept in memory for a short time
+///
+/// Captures hold user input, so they are never written to disk and expire
+/// after `CAPTURE_TTL_SECONDS`.
+pub struct CaptureStore {
+    config: CaptureConfig,
+    redact_fields: Arc<Vec<String>>,
+    captures: Mutex<VecDeque<CapturedRequest>>,
+}
+
+impl CaptureStore {
+    pub fn new(config: CaptureConfig) -> Self {
+        Self {
+            redact_fields: Arc::new(config.redact_fields.clone()),
+            captures: Mutex::new(VecDeque::new()),
+            config,
+        }
+    }
+
+    /// Store configured from `CAPTURE_*` environment variables
+    pub fn from_env(service: &str) -> Self {
+        Self::new(CaptureConfig::from_env(service))
+    }
+
+    pub fn config(&self) -> &CaptureConfig {
+        &self.config
+    }
+
+    pub fn insert(&self, capture: CapturedRequest) {
+        let mut captures = self.captures.lock().unwrap();
+        Self::evict_expired(&mut captures, self.config.ttl);
+        while captures.len() >= self.config.max_captures.max(1) {
+            captures.pop_front();
+        }
+        captures.push_back(capture);
+    }
+
+    pub fn get(&self, id: &str) -> Option<CapturedRequest> {
+        let mut captures = self.captures.lock().unwrap();
+        Self::evict_expired(&mut captures, self.config.ttl);
+        captures.iter().find(|capture| capture.id == id).cloned()
+    }
+
+    /// Captures newest first, optionally only those of one request ID
+    pub fn list(&self, request_id: Option<&str>) -> Vec<CaptureSummary> {
+        let mut captures = self.captures.lock().unwrap();
+        Self::evict_expired(&mut captures, self.config.ttl);
+        captures
+            .iter()
+            .rev()
+            .filter(|capture| request_id.is_none_or(|id| capture.request_id == id))
+            .map(CaptureSummary::from)
+            .collect()
+    }
+
+    fn evict_expired(captures: &mut VecDeque<CapturedRequest>, ttl: Duration) {
+        let cutoff = Utc::now() - chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+        while captures.front().is_some_and(|capture| capture.captured_at < cutoff) {
+            captures.pop_front();
+        }
+    }
+
+    /// Sanitized copy of the request, before the handler has run
+    fn begin(&self, req: &ServiceRequest, context: &RequestContext, body: &Bytes) -> CapturedRequest {
+        let mut headers = Vec::new();
+        let mut dropped_headers = Vec::new();
+        for (name, value) in req.headers() {
+            let name = name.as_str();
+            if DROPPED_HEADERS.contains(&name) || is_sensitive(name, &self.redact_fields) {
+                dropped_headers.push(name.to_string());
+            } else if name != REQUEST_ID_HEADER && name != TRACEPARENT_HEADER {
+                if let Ok(value) = value.to_str() {
+                    headers.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+
+        let (body, body_omitted) = if body.is_empty() {
+            (None, None)
+        } else if body.len() > self.config.max_body_bytes {
+            (None, Some(format!("{} bytes, over the {} byte limit", body.len(), self.config.max_body_bytes)))
+        } else if let Ok(mut json) = serde_json::from_slice::<Value>(body) {
+            redact_json(&mut json, &self.redact_fields);
+            (Some(CapturedBody::Json(json)), None)
+        } else if let Ok(text) = std::str::from_utf8(body) {
+            (Some(CapturedBody::Text(text.to_string())), None)
+        } else {
+            (None, Some(format!("{} bytes of binary data", body.len())))
+        };
+
+        CapturedRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            service: self.config.service.clone(),
+            captured_at: Utc::now(),
+            request_id: context.request_id.clone(),
+            trace_id: context.trace.trace_id.clone(),
+            span_id: context.trace.span_id.clone(),
+            parent_span_id: context.parent_span_id.clone(),
+            method: req.method().to_string(),
+            path: req.path().to_string(),
+            query: redact_query(req.query_string(), &self.redact_fields),
+            headers,
+            dropped_headers,
+            body,
+            body_omitted,
+            status: 0,
+            duration_ms: 0,
+            calls: Vec::new(),
+        }
+    }
+}
+
+/// Middleware that captures requests to the configured routes
+///
+/// Register it before [`RequestCorrelation`](crate::RequestCorrelation) so it
+/// runs inside it and sees the request's correlation IDs. The body of a
+/// captured request is buffered in full before the handler runs.
+pub struct RequestCapture {
+    store: web::Data<CaptureStore>,
+}
+
+impl RequestCapture {
+    pub fn new(store: web::Data<CaptureStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestCapture
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestCaptureMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestCaptureMiddleware {
+            service: Rc::new(service),
+            store: self.store.clone(),
+        }))
+    }
+}
+
+pub struct RequestCaptureMiddleware<S> {
+    service: Rc<S>,
+    store: web::Data<CaptureStore>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestCaptureMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if !self.store.config.captures(req.path()) {
+            return Box::pin(self.service.call(req));
+        }
+        let service = self.service.clone();
+        let store = self.store.clone();
+
+        Box::pin(async move {
+            let started = Instant::now();
+            let body = req.extract::<Bytes>().await?;
+            req.set_payload(Payload::from(body.clone()));
+
+            let recorder = CallRecorder::new(store.redact_fields.clone());
+            let context = {
+                let mut extensions = req.extensions_mut();
+                let context = match extensions.remove::<RequestContext>() {
+                    Some(context) => context,
+                    None => {
+                        let header = |name: &str| req.headers().get(name).and_then(|value| value.to_str().ok());
+                        RequestContext::from_headers(header(REQUEST_ID_HEADER), header(TRACEPARENT_HEADER))
+                    }
+                };
+                let context = RequestContext {
+                    capture: Some(recorder.clone()),
+                    ..context
+                };
+                extensions.insert(context.clone());
+                context
+            };
+            let mut capture = store.begin(&req, &context, &body);
+
+            let result = service.call(req).await;
+            capture.status = match &result {
+                Ok(res) => res.status().as_u16(),
+                Err(e) => e.as_response_error().status_code().as_u16(),
+            };
+            capture.duration_ms = started.elapsed().as_millis() as u64;
+            capture.calls = recorder.take();
+            tracing::debug!(capture_id = %capture.id, "Captured {} {}", capture.method, capture.path);
+            store.insert(capture);
+            result
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CaptureListQuery {
+    pub request_id: Option<String>,
+}
+
+/// Response refusing the caller, if it may not read captures
+///
+/// The routes answer 404 when capture is off, as if they did not exist.
+fn rejection(req: &HttpRequest, store: &CaptureStore) -> Option<HttpResponse> {
+    if !store.config.enabled() {
+        return Some(HttpResponse::NotFound().finish());
+    }
+    let token = store.config.access_token.as_ref()?;
+    let given = req.headers().get(CAPTURE_TOKEN_HEADER).map(|value| value.as_bytes());
+    (given != Some(token.as_bytes())).then(|| HttpResponse::Unauthorized().finish())
+}
+
+pub async fn list_captures(
+    req: HttpRequest,
+    store: web::Data<CaptureStore>,
+    query: web::Query<CaptureListQuery>,
+) -> HttpResponse {
+    if let Some(response) = rejection(&req, &store) {
+        return response;
+    }
+    HttpResponse::Ok().json(store.list(query.request_id.as_deref()))
+}
+
+pub async fn get_capture(req: HttpRequest, store: web::Data<CaptureStore>, id: web::Path<String>) -> HttpResponse {
+    if let Some(response) = rejection(&req, &store) {
+        return response;
+    }
+    match store.get(&id) {
+        Some(capture) => HttpResponse::Ok().json(capture),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// Register `GET /debug/captures` and `GET /debug/captures/{id}`
+///
+/// The handlers read the store from app data, so register it with
+/// `.app_data(store.clone())`.
+pub fn capture_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/debug/captures").route(web::get().to(list_captures)))
+        .service(web::resource("/debug/captures/{id}").route(web::get().to(get_capture)));
+}
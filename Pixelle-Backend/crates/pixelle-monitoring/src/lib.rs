@@ -1,7 +1,13 @@
 pub mod metrics;
 pub mod tracing;
 pub mod health;
+pub mod logging;
+pub mod capture;
+pub mod scaling;
 
 pub use metrics::*;
 pub use tracing::*;
 pub use health::*;
+pub use logging::*;
+pub use capture::*;
+pub use scaling::*;
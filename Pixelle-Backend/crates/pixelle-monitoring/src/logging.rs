@@ -0,0 +1,782 @@
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crate::capture::CallRecorder;
+use opentelemetry::trace::TraceContextExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::future::{ready, Future, Ready};
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::callsite::Identifier;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Instrument, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Targets never shipped to a sink; the exporter's own HTTP client logs
+/// through them and would feed back into the pipeline
+const EXPORT_EXCLUDED_TARGETS: &[&str] = &["hyper", "reqwest", "h2", "rustls"];
+
+/// One log line as written to stdout and shipped to the sink
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub service: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    /// Event fields, plus the fields of every span the event happened in
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::String(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+}
+
+/// Fields recorded on a span, kept in the span's extensions
+struct SpanFields(Map<String, Value>);
+
+/// Keeps one in every `every` debug and trace events per callsite
+///
+/// Counting per callsite means a chatty loop is thinned out without silencing
+/// the rarer debug lines next to it. Info and above are never sampled.
+pub struct DebugSampler {
+    every: u64,
+    counters: Mutex<HashMap<Identifier, u64>>,
+}
+
+impl DebugSampler {
+    pub fn new(every: u64) -> Self {
+        Self {
+            every: every.max(1),
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn keep(&self, metadata: &Metadata<'_>) -> bool {
+        if self.every == 1 || *metadata.level() < Level::DEBUG {
+            return true;
+        }
+        let mut counters = self.counters.lock().unwrap();
+        let seen = counters.entry(metadata.callsite()).or_insert(0);
+        *seen += 1;
+        (*seen - 1).is_multiple_of(self.every)
+    }
+}
+
+/// Formats every event as a single JSON line with service, version and correlation IDs
+pub struct JsonLogLayer {
+    service: String,
+    version: String,
+    sampler: DebugSampler,
+    exporter: Option<mpsc::Sender<LogRecord>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl JsonLogLayer {
+    pub fn new(service: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            version: version.into(),
+            sampler: DebugSampler::new(1),
+            exporter: None,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn with_debug_sampling(mut self, every: u64) -> Self {
+        self.sampler = DebugSampler::new(every);
+        self
+    }
+
+    /// Also ship records to an export pipeline
+    pub fn with_exporter(mut self, exporter: &LogExportHandle) -> Self {
+        self.exporter = Some(exporter.sender.clone());
+        self.dropped = exporter.dropped.clone();
+        self
+    }
+
+    fn build_record<S>(&self, event: &Event<'_>, ctx: &Context<'_, S>) -> LogRecord
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let metadata = event.metadata();
+
+        let mut fields = Map::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(SpanFields(span_fields)) = span.extensions().get::<SpanFields>() {
+                    fields.extend(span_fields.clone());
+                }
+            }
+        }
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        fields.extend(visitor.0);
+
+        let mut take = |name: &str| match fields.remove(name) {
+            Some(Value::String(value)) => Some(value),
+            Some(other) => Some(other.to_string()),
+            None => None,
+        };
+        let message = take("message").unwrap_or_default();
+        let request_id = take("request_id");
+        let (trace_id, span_id) = match (take("trace_id"), take("span_id")) {
+            (Some(trace_id), span_id) => (Some(trace_id), span_id),
+            (None, _) => current_trace_ids().unzip(),
+        };
+
+        LogRecord {
+            timestamp: Utc::now(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message,
+            service: self.service.clone(),
+            version: self.version.clone(),
+            request_id,
+            trace_id,
+            span_id,
+            fields,
+        }
+    }
+}
+
+impl<S> Layer<S> for JsonLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut visitor = JsonVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanFields(visitor.0));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+            let mut visitor = JsonVisitor(std::mem::take(fields));
+            values.record(&mut visitor);
+            *fields = visitor.0;
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !self.sampler.keep(event.metadata()) {
+            return;
+        }
+        let record = self.build_record(event, &ctx);
+
+        if let Ok(mut line) = serde_json::to_vec(&record) {
+            line.push(b'\n');
+            let _ = std::io::stdout().lock().write_all(&line);
+        }
+
+        let Some(exporter) = &self.exporter else { return };
+        if EXPORT_EXCLUDED_TARGETS.iter().any(|target| record.target.starts_with(target)) {
+            return;
+        }
+        // Logging must never block a request; a full buffer drops the record
+        if exporter.try_send(record).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// W3C trace context carried in the `traceparent` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceParent {
+    /// 32 lowercase hex digits
+    pub trace_id: String,
+    /// 16 lowercase hex digits identifying the caller's span
+    pub span_id: String,
+    pub sampled: bool,
+}
+
+impl TraceParent {
+    /// Start a new trace
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: uuid::Uuid::new_v4().simple().to_string(),
+            span_id: new_span_id(),
+            sampled: true,
+        }
+    }
+
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.trim().split('-');
+        let (version, trace_id, span_id, flags) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let is_hex = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_hexdigit());
+        if !is_hex(version, 2) || version == "ff" || !is_hex(trace_id, 32) || !is_hex(span_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        // All-zero IDs are invalid per the spec
+        if trace_id.bytes().all(|b| b == b'0') || span_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Some(Self {
+            trace_id: trace_id.to_ascii_lowercase(),
+            span_id: span_id.to_ascii_lowercase(),
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    /// Context for a new span in the same trace, to hand to the next hop
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: new_span_id(),
+            sampled: self.sampled,
+        }
+    }
+
+    pub fn to_header(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, u8::from(self.sampled))
+    }
+}
+
+fn new_span_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+/// Trace and span ID of the active OpenTelemetry span, if there is one
+pub fn current_trace_ids() -> Option<(String, String)> {
+    let context = opentelemetry::Context::current();
+    let span = context.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return None;
+    }
+    Some((span_context.trace_id().to_string(), span_context.span_id().to_string()))
+}
+
+/// Correlation IDs of the request being handled
+///
+/// Set by [`RequestCorrelation`] and available to handlers as an extractor.
+/// Pass [`RequestContext::headers`] on outgoing calls so the next service
+/// logs under the same request and trace.
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub request_id: String,
+    pub trace: TraceParent,
+    /// Span ID of the caller, when the request arrived with a `traceparent`
+    pub parent_span_id: Option<String>,
+    /// Set by [`RequestCapture`](crate::RequestCapture) on captured requests
+    pub capture: Option<CallRecorder>,
+}
+
+impl RequestContext {
+    pub fn from_headers(request_id: Option<&str>, traceparent: Option<&str>) -> Self {
+        let request_id = request_id
+            .filter(|id| !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+            .map(str::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let parent = traceparent.and_then(TraceParent::parse);
+        let trace = parent.as_ref().map(TraceParent::child).unwrap_or_else(TraceParent::new_root);
+        Self {
+            request_id,
+            trace,
+            parent_span_id: parent.map(|parent| parent.span_id),
+            capture: None,
+        }
+    }
+
+    /// Span that stamps every log line inside it with this context
+    pub fn span(&self) -> tracing::Span {
+        tracing::info_span!(
+            "request",
+            request_id = %self.request_id,
+            trace_id = %self.trace.trace_id,
+            span_id = %self.trace.span_id,
+        )
+    }
+
+    /// Headers to forward on calls to other services
+    pub fn headers(&self) -> [(&'static str, String); 2] {
+        [
+            (REQUEST_ID_HEADER, self.request_id.clone()),
+            (TRACEPARENT_HEADER, self.trace.child().to_header()),
+        ]
+    }
+}
+
+impl FromRequest for RequestContext {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let context = req.extensions().get::<RequestContext>().cloned().unwrap_or_else(|| {
+            let header = |name: &str| req.headers().get(name).and_then(|value| value.to_str().ok());
+            RequestContext::from_headers(header(REQUEST_ID_HEADER), header(TRACEPARENT_HEADER))
+        });
+        ready(Ok(context))
+    }
+}
+
+/// Middleware that runs each request inside a span carrying its request ID and trace context
+///
+/// Incoming `x-request-id` and `traceparent` headers are honoured; missing ones
+/// are generated. The request headers are rewritten so a proxy forwards the
+/// new span, and the request ID is echoed on the response.
+pub struct RequestCorrelation;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestCorrelation
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestCorrelationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestCorrelationMiddleware { service }))
+    }
+}
+
+pub struct RequestCorrelationMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestCorrelationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let header = |name: &str| req.headers().get(name).and_then(|value| value.to_str().ok());
+        let context = RequestContext::from_headers(header(REQUEST_ID_HEADER), header(TRACEPARENT_HEADER));
+
+        for (name, value) in [
+            (REQUEST_ID_HEADER, context.request_id.clone()),
+            (TRACEPARENT_HEADER, context.trace.to_header()),
+        ] {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                req.headers_mut().insert(HeaderName::from_static(name), value);
+            }
+        }
+
+        let span = context.span();
+        let request_id = HeaderValue::from_str(&context.request_id).ok();
+        req.extensions_mut().insert(context);
+
+        let fut = span.in_scope(|| self.service.call(req));
+        Box::pin(
+            async move {
+                let mut res = fut.await?;
+                if let Some(request_id) = request_id {
+                    res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), request_id);
+                }
+                Ok(res)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Destination for exported log batches
+#[async_trait]
+pub trait LogSink: Send + Sync {
+    fn name(&self) -> &str;
+    async fn export(&self, records: &[LogRecord]) -> anyhow::Result<()>;
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Pushes logs to Grafana Loki, one stream per service and level
+pub struct LokiSink {
+    client: reqwest::Client,
+    push_url: String,
+    tenant: Option<String>,
+}
+
+impl LokiSink {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: http_client(),
+            push_url: format!("{}/loki/api/v1/push", base_url.trim_end_matches('/')),
+            tenant: None,
+        }
+    }
+
+    /// Sent as `X-Scope-OrgID` for multi-tenant Loki
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+}
+
+#[async_trait]
+impl LogSink for LokiSink {
+    fn name(&self) -> &str {
+        "loki"
+    }
+
+    async fn export(&self, records: &[LogRecord]) -> anyhow::Result<()> {
+        // Labels stay low-cardinality; request and trace IDs live in the line
+        let mut streams: HashMap<(&str, &str), Vec<Value>> = HashMap::new();
+        for record in records {
+            let nanos = record.timestamp.timestamp_nanos_opt().unwrap_or_default();
+            streams
+                .entry((record.service.as_str(), record.level.as_str()))
+                .or_default()
+                .push(json!([nanos.to_string(), serde_json::to_string(record)?]));
+        }
+        let streams: Vec<Value> = streams
+            .into_iter()
+            .map(|((service, level), values)| {
+                json!({
+                    "stream": { "service": service, "level": level.to_lowercase() },
+                    "values": values,
+                })
+            })
+            .collect();
+
+        let mut request = self.client.post(&self.push_url).json(&json!({ "streams": streams }));
+        if let Some(tenant) = &self.tenant {
+            request = request.header("X-Scope-OrgID", tenant);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Indexes logs into Elasticsearch with the bulk API, one index per day
+pub struct ElasticsearchSink {
+    client: reqwest::Client,
+    bulk_url: String,
+    index_prefix: String,
+    api_key: Option<String>,
+}
+
+impl ElasticsearchSink {
+    pub fn new(base_url: &str, index_prefix: impl Into<String>) -> Self {
+        Self {
+            client: http_client(),
+            bulk_url: format!("{}/_bulk", base_url.trim_end_matches('/')),
+            index_prefix: index_prefix.into(),
+            api_key: None,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+}
+
+#[async_trait]
+impl LogSink for ElasticsearchSink {
+    fn name(&self) -> &str {
+        "elasticsearch"
+    }
+
+    async fn export(&self, records: &[LogRecord]) -> anyhow::Result<()> {
+        let mut body = String::new();
+        for record in records {
+            let index = format!("{}-{}", self.index_prefix, record.timestamp.format("%Y.%m.%d"));
+            body.push_str(&json!({ "index": { "_index": index } }).to_string());
+            body.push('\n');
+            body.push_str(&serde_json::to_string(record)?);
+            body.push('\n');
+        }
+
+        let mut request = self
+            .client
+            .post(&self.bulk_url)
+            .header("Content-Type", "application/x-ndjson")
+            .body(body);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("Authorization", format!("ApiKey {}", api_key));
+        }
+        let response: Value = request.send().await?.error_for_status()?.json().await?;
+        // The bulk API answers 200 even when individual documents are rejected
+        if response.get("errors").and_then(Value::as_bool).unwrap_or(false) {
+            anyhow::bail!("Elasticsearch rejected part of a bulk request of {} records", records.len());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogExportConfig {
+    /// Records buffered before new ones are dropped
+    pub buffer: usize,
+    pub batch_size: usize,
+    /// Longest a record waits before its batch is sent
+    pub flush_interval: Duration,
+}
+
+impl Default for LogExportConfig {
+    fn default() -> Self {
+        Self {
+            buffer: 10_000,
+            batch_size: 500,
+            flush_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Handle to a running export pipeline
+pub struct LogExportHandle {
+    sender: mpsc::Sender<LogRecord>,
+    dropped: Arc<AtomicU64>,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl LogExportHandle {
+    /// Records dropped because the buffer was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Send what is buffered and stop the pipeline
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        let _ = self.task.await;
+    }
+}
+
+/// Start a background task that batches records and ships them to a sink
+///
+/// Must be called from within a Tokio runtime. Failed batches are reported on
+/// stderr and discarded rather than retried, so a sink outage cannot grow the
+/// service's memory.
+pub fn spawn_log_exporter(sink: Arc<dyn LogSink>, config: LogExportConfig) -> LogExportHandle {
+    let (sender, mut receiver) = mpsc::channel(config.buffer.max(1));
+    let (shutdown, mut shutdown_rx) = oneshot::channel();
+    let batch_size = config.batch_size.max(1);
+
+    let task = tokio::spawn(async move {
+        let mut batch: Vec<LogRecord> = Vec::with_capacity(batch_size);
+        let mut ticker = tokio::time::interval(config.flush_interval);
+        loop {
+            tokio::select! {
+                record = receiver.recv() => match record {
+                    Some(record) => {
+                        batch.push(record);
+                        if batch.len() >= batch_size {
+                            flush(sink.as_ref(), &mut batch).await;
+                        }
+                    }
+                    None => break,
+                },
+                _ = ticker.tick() => flush(sink.as_ref(), &mut batch).await,
+                _ = &mut shutdown_rx => break,
+            }
+        }
+        receiver.close();
+        while let Some(record) = receiver.recv().await {
+            batch.push(record);
+            if batch.len() >= batch_size {
+                flush(sink.as_ref(), &mut batch).await;
+            }
+        }
+        flush(sink.as_ref(), &mut batch).await;
+    });
+
+    LogExportHandle {
+        sender,
+        dropped: Arc::new(AtomicU64::new(0)),
+        shutdown: Some(shutdown),
+        task,
+    }
+}
+
+async fn flush(sink: &dyn LogSink, batch: &mut Vec<LogRecord>) {
+    if batch.is_empty() {
+        return;
+    }
+    // Reporting through tracing would loop back into this pipeline
+    if let Err(e) = sink.export(batch).await {
+        eprintln!("Failed to export {} log records to {}: {}", batch.len(), sink.name(), e);
+    }
+    batch.clear();
+}
+
+/// Logging settings shared by every service
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    pub service: String,
+    pub version: String,
+    /// Plain text for local development instead of JSON lines
+    pub text: bool,
+    /// Keep one in this many debug events per callsite
+    pub debug_sample_every: u64,
+    pub sink: Option<LogSinkConfig>,
+    pub export: LogExportConfig,
+}
+
+#[derive(Debug, Clone)]
+pub enum LogSinkConfig {
+    Loki { url: String, tenant: Option<String> },
+    Elasticsearch { url: String, index_prefix: String, api_key: Option<String> },
+}
+
+impl LogSinkConfig {
+    pub fn build(&self) -> Arc<dyn LogSink> {
+        match self {
+            LogSinkConfig::Loki { url, tenant } => {
+                let sink = LokiSink::new(url);
+                Arc::new(match tenant {
+                    Some(tenant) => sink.with_tenant(tenant.clone()),
+                    None => sink,
+                })
+            }
+            LogSinkConfig::Elasticsearch { url, index_prefix, api_key } => {
+                let sink = ElasticsearchSink::new(url, index_prefix.clone());
+                Arc::new(match api_key {
+                    Some(api_key) => sink.with_api_key(api_key.clone()),
+                    None => sink,
+                })
+            }
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn new(service: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            version: version.into(),
+            text: false,
+            debug_sample_every: 1,
+            sink: None,
+            export: LogExportConfig::default(),
+        }
+    }
+
+    /// Read `LOG_FORMAT`, `LOG_DEBUG_SAMPLE_EVERY`, `LOG_SINK` and `LOG_SINK_*`
+    pub fn from_env(service: impl Into<String>, version: impl Into<String>) -> Self {
+        let mut config = Self::new(service, version);
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+        config.text = var("LOG_FORMAT").is_some_and(|format| format.eq_ignore_ascii_case("text"));
+        if let Some(every) = var("LOG_DEBUG_SAMPLE_EVERY").and_then(|v| v.parse().ok()) {
+            config.debug_sample_every = every;
+        }
+        if let Some(size) = var("LOG_EXPORT_BATCH_SIZE").and_then(|v| v.parse().ok()) {
+            config.export.batch_size = size;
+        }
+        if let Some(ms) = var("LOG_EXPORT_FLUSH_MS").and_then(|v| v.parse().ok()) {
+            config.export.flush_interval = Duration::from_millis(ms);
+        }
+
+        config.sink = match (var("LOG_SINK").as_deref(), var("LOG_SINK_URL")) {
+            (Some("loki"), Some(url)) => Some(LogSinkConfig::Loki {
+                url,
+                tenant: var("LOG_SINK_TENANT"),
+            }),
+            (Some("elasticsearch"), Some(url)) => Some(LogSinkConfig::Elasticsearch {
+                url,
+                index_prefix: var("LOG_SINK_INDEX").unwrap_or_else(|| "pixelle-logs".to_string()),
+                api_key: var("LOG_SINK_API_KEY"),
+            }),
+            _ => None,
+        };
+        config
+    }
+}
+
+/// Install the standard subscriber: JSON lines on stdout, optionally shipped to a sink
+///
+/// Returns the export handle when a sink is configured; shut it down before
+/// exiting so buffered records are sent.
+pub fn init_logging(config: LoggingConfig) -> Option<LogExportHandle> {
+    // Sinks receive the JSON records, so text mode only writes to stdout
+    let exporter = config
+        .sink
+        .as_ref()
+        .filter(|_| !config.text)
+        .map(|sink| spawn_log_exporter(sink.build(), config.export.clone()));
+
+    let json = (!config.text).then(|| {
+        let layer = JsonLogLayer::new(&config.service, &config.version).with_debug_sampling(config.debug_sample_every);
+        match &exporter {
+            Some(exporter) => layer.with_exporter(exporter),
+            None => layer,
+        }
+    });
+    let text = config.text.then(tracing_subscriber::fmt::layer);
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "pixelle=debug,tower_http=debug".into()),
+        )
+        .with(json)
+        .with(text)
+        .init();
+
+    exporter
+}
@@ -0,0 +1,366 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::env;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Latencies kept for the p95; older ones are dropped first under heavy load
+const MAX_LATENCY_SAMPLES: usize = 10_000;
+
+/// Paths that say nothing about load and are left out of the signals
+const UNTRACKED_PATHS: &[&str] = &["/health", "/metrics", "/scaling"];
+
+#[derive(Debug, Clone)]
+pub struct ScalingConfig {
+    pub service: String,
+    /// p95 latency the service should stay under
+    pub latency_slo: Duration,
+    /// Requests one instance handles at once at full load; more than this
+    /// count as queued
+    pub worker_capacity: usize,
+    /// Queued requests one instance should carry
+    pub target_queue_depth: f64,
+    /// Share of `worker_capacity` one instance should run at
+    pub target_saturation: f64,
+    /// Signals are averaged over this window, so a short burst does not
+    /// scale the service
+    pub window: Duration,
+    /// How often in-flight requests and registered signals are sampled
+    pub sample_interval: Duration,
+}
+
+impl ScalingConfig {
+    pub fn new(service: impl Into<String>) -> Self {
+        let cores = std::thread::available_parallelism().map(|cores| cores.get()).unwrap_or(1);
+        Self {
+            service: service.into(),
+            latency_slo: Duration::from_millis(500),
+            worker_capacity: cores * 64,
+            target_queue_depth: 10.0,
+            target_saturation: 0.7,
+            window: Duration::from_secs(60),
+            sample_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Read `SCALING_LATENCY_SLO_MS`, `SCALING_WORKER_CAPACITY`,
+    /// `SCALING_TARGET_QUEUE_DEPTH`, `SCALING_TARGET_SATURATION` and
+    /// `SCALING_WINDOW_SECONDS`
+    pub fn from_env(service: impl Into<String>) -> Self {
+        let mut config = Self::new(service);
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+        if let Some(millis) = var("SCALING_LATENCY_SLO_MS").and_then(|v| v.parse().ok()) {
+            config.latency_slo = Duration::from_millis(millis);
+        }
+        if let Some(capacity) = var("SCALING_WORKER_CAPACITY").and_then(|v| v.parse::<usize>().ok()) {
+            config.worker_capacity = capacity.max(1);
+        }
+        if let Some(depth) = var("SCALING_TARGET_QUEUE_DEPTH").and_then(|v| v.parse::<f64>().ok()) {
+            config.target_queue_depth = depth;
+        }
+        if let Some(saturation) = var("SCALING_TARGET_SATURATION").and_then(|v| v.parse::<f64>().ok()) {
+            config.target_saturation = saturation;
+        }
+        if let Some(seconds) = var("SCALING_WINDOW_SECONDS").and_then(|v| v.parse().ok()) {
+            config.window = Duration::from_secs(seconds);
+        }
+        config
+    }
+}
+
+/// One signal, normalized against its target
+#[derive(Debug, Clone, Serialize)]
+pub struct ScalingSignal {
+    pub name: String,
+    /// Smoothed over the window
+    pub value: f64,
+    pub target: f64,
+    /// `value / target`; above 1 the service wants more instances
+    pub utilization: f64,
+}
+
+/// What orchestrators read from `GET /scaling/signals`
+///
+/// Point HPA external metrics or the KEDA `metrics-api` scaler at
+/// `utilization` with a target of 1 to scale on the most loaded signal.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScalingReport {
+    pub service: String,
+    pub generated_at: DateTime<Utc>,
+    pub window_seconds: u64,
+    /// Highest utilization of any signal
+    pub utilization: f64,
+    pub signals: Vec<ScalingSignal>,
+}
+
+/// Samples taken within the smoothing window
+#[derive(Default)]
+struct Window {
+    samples: VecDeque<(Instant, f64)>,
+}
+
+impl Window {
+    fn push(&mut self, at: Instant, value: f64, window: Duration) {
+        self.samples.push_back((at, value));
+        self.evict(at, window);
+    }
+
+    fn evict(&mut self, now: Instant, window: Duration) {
+        while self.samples.front().is_some_and(|(at, _)| now.duration_since(*at) > window) {
+            self.samples.pop_front();
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().map(|(_, value)| value).sum::<f64>() / self.samples.len() as f64
+    }
+
+    fn percentile(&self, percentile: f64) -> f64 {
+        let mut values: Vec<f64> = self.samples.iter().map(|(_, value)| *value).collect();
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.sort_by(f64::total_cmp);
+        let rank = ((percentile * values.len() as f64).ceil() as usize).clamp(1, values.len());
+        values[rank - 1]
+    }
+}
+
+/// A signal a service registered, sampled from `probe`
+struct RegisteredSignal {
+    name: String,
+    target: f64,
+    probe: Box<dyn Fn() -> f64 + Send + Sync>,
+    window: Window,
+}
+
+/// Scaling signals of one service instance
+///
+/// Tracks in-flight requests and their latency through [`TrackScalingSignals`],
+/// and samples any signal the service registers. Every signal is reported
+/// against its target, so orchestrators can scale on load the service
+/// actually feels rather than on CPU alone.
+pub struct ScalingSignals {
+    config: ScalingConfig,
+    in_flight: AtomicUsize,
+    latencies: Mutex<Window>,
+    queue_depth: Mutex<Window>,
+    saturation: Mutex<Window>,
+    registered: Mutex<Vec<RegisteredSignal>>,
+}
+
+impl ScalingSignals {
+    pub fn new(config: ScalingConfig) -> Self {
+        Self {
+            config,
+            in_flight: AtomicUsize::new(0),
+            latencies: Mutex::new(Window::default()),
+            queue_depth: Mutex::new(Window::default()),
+            saturation: Mutex::new(Window::default()),
+            registered: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Signals configured from `SCALING_*` environment variables
+    pub fn from_env(service: &str) -> Self {
+        Self::new(ScalingConfig::from_env(service))
+    }
+
+    pub fn config(&self) -> &ScalingConfig {
+        &self.config
+    }
+
+    /// Report a service-specific signal, such as a job backlog, against
+    /// `target` per instance; registering a name again replaces it
+    pub fn register(&self, name: impl Into<String>, target: f64, probe: impl Fn() -> f64 + Send + Sync + 'static) {
+        let name = name.into();
+        let mut registered = self.registered.lock().unwrap();
+        registered.retain(|signal| signal.name != name);
+        registered.push(RegisteredSignal {
+            name,
+            target,
+            probe: Box::new(probe),
+            window: Window::default(),
+        });
+    }
+
+    fn request_started(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn request_finished(&self, started: Instant) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        let now = Instant::now();
+        let mut latencies = self.latencies.lock().unwrap();
+        latencies.push(now, now.duration_since(started).as_secs_f64() * 1000.0, self.config.window);
+        while latencies.samples.len() > MAX_LATENCY_SAMPLES {
+            latencies.samples.pop_front();
+        }
+    }
+
+    /// Record in-flight requests and every registered signal
+    pub fn sample(&self, now: Instant) {
+        let window = self.config.window;
+        let capacity = self.config.worker_capacity.max(1);
+        let in_flight = self.in_flight.load(Ordering::Relaxed);
+
+        self.queue_depth.lock().unwrap().push(now, in_flight.saturating_sub(capacity) as f64, window);
+        self.saturation.lock().unwrap().push(now, in_flight.min(capacity) as f64 / capacity as f64, window);
+        for signal in self.registered.lock().unwrap().iter_mut() {
+            let value = (signal.probe)();
+            signal.window.push(now, value, window);
+        }
+    }
+
+    pub fn report(&self, now: Instant) -> ScalingReport {
+        let window = self.config.window;
+        let smoothed = |samples: &Mutex<Window>, measure: fn(&Window) -> f64| {
+            let mut samples = samples.lock().unwrap();
+            samples.evict(now, window);
+            measure(&samples)
+        };
+
+        let mut signals = vec![
+            signal("queue_depth", smoothed(&self.queue_depth, Window::mean), self.config.target_queue_depth),
+            signal(
+                "p95_latency_ms",
+                smoothed(&self.latencies, |latencies| latencies.percentile(0.95)),
+                self.config.latency_slo.as_secs_f64() * 1000.0,
+            ),
+            signal("worker_saturation", smoothed(&self.saturation, Window::mean), self.config.target_saturation),
+        ];
+        for registered in self.registered.lock().unwrap().iter_mut() {
+            registered.window.evict(now, window);
+            signals.push(signal(&registered.name, registered.window.mean(), registered.target));
+        }
+
+        ScalingReport {
+            service: self.config.service.clone(),
+            generated_at: Utc::now(),
+            window_seconds: window.as_secs(),
+            utilization: signals.iter().map(|signal| signal.utilization).fold(0.0, f64::max),
+            signals,
+        }
+    }
+
+    /// Sample every `sample_interval` for as long as the signals are alive
+    pub fn spawn_sampler(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.sample_interval);
+            loop {
+                ticker.tick().await;
+                self.sample(Instant::now());
+            }
+        })
+    }
+}
+
+fn signal(name: &str, value: f64, target: f64) -> ScalingSignal {
+    ScalingSignal {
+        name: name.to_string(),
+        value,
+        target,
+        utilization: if target > 0.0 { value / target } else { 0.0 },
+    }
+}
+
+/// Ends the request's tracking even if the handler future is dropped
+struct InFlight {
+    signals: web::Data<ScalingSignals>,
+    started: Instant,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.signals.request_finished(self.started);
+    }
+}
+
+/// Middleware counting in-flight requests and recording their latency for
+/// [`ScalingSignals`]
+pub struct TrackScalingSignals {
+    signals: web::Data<ScalingSignals>,
+}
+
+impl TrackScalingSignals {
+    pub fn new(signals: web::Data<ScalingSignals>) -> Self {
+        Self { signals }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for TrackScalingSignals
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TrackScalingSignalsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TrackScalingSignalsMiddleware {
+            service,
+            signals: self.signals.clone(),
+        }))
+    }
+}
+
+pub struct TrackScalingSignalsMiddleware<S> {
+    service: S,
+    signals: web::Data<ScalingSignals>,
+}
+
+impl<S, B> Service<ServiceRequest> for TrackScalingSignalsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if UNTRACKED_PATHS.iter().any(|path| req.path().starts_with(path)) {
+            return Box::pin(self.service.call(req));
+        }
+        self.signals.request_started();
+        let in_flight = InFlight {
+            signals: self.signals.clone(),
+            started: Instant::now(),
+        };
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let result = fut.await;
+            drop(in_flight);
+            result
+        })
+    }
+}
+
+pub async fn scaling_signals(signals: web::Data<ScalingSignals>) -> HttpResponse {
+    HttpResponse::Ok().json(signals.report(Instant::now()))
+}
+
+/// Register `GET /scaling/signals`
+///
+/// The handler reads the signals from app data, so register them with
+/// `.app_data(signals.clone())`.
+pub fn scaling_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/scaling/signals").route(web::get().to(scaling_signals)));
+}
@@ -0,0 +1,58 @@
+use actix_web::{http::header, web, HttpResponse, Result};
+use pixelle_analytics::{AnalyticsEvent, AnalyticsMetrics, RealtimeSessions};
+use pixelle_core::ApiResponse;
+use prometheus::{Encoder, TextEncoder};
+use std::sync::Arc;
+
+/// Largest batch accepted in one request
+const MAX_EVENTS_PER_REQUEST: usize = 1000;
+
+pub async fn track_events(
+    sessions: web::Data<Arc<RealtimeSessions>>,
+    events: web::Json<Vec<AnalyticsEvent>>,
+) -> Result<HttpResponse> {
+    let events = events.into_inner();
+    if events.len() > MAX_EVENTS_PER_REQUEST {
+        return Ok(HttpResponse::PayloadTooLarge().json(ApiResponse::<usize> {
+            success: false,
+            data: None,
+            error: Some(format!("At most {} events per request", MAX_EVENTS_PER_REQUEST)),
+            message: None,
+        }));
+    }
+
+    for event in &events {
+        sessions.track(event);
+    }
+    Ok(HttpResponse::Accepted().json(ApiResponse {
+        success: true,
+        data: Some(events.len()),
+        error: None,
+        message: None,
+    }))
+}
+
+/// Concurrent sessions and active users, refreshed every few seconds for the ops dashboard
+pub async fn get_realtime(sessions: web::Data<Arc<RealtimeSessions>>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(sessions.gauges()))
+}
+
+pub async fn metrics(metrics: web::Data<AnalyticsMetrics>) -> Result<HttpResponse> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&metrics.registry().gather(), &mut buffer)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(buffer))
+}
+
+pub async fn health_check() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "healthy",
+        "service": "analytics-service",
+        "timestamp": chrono::Utc::now()
+    })))
+}
@@ -0,0 +1,59 @@
+use actix_web::{web, App, HttpServer};
+use pixelle_analytics::{AnalyticsMetrics, JsonLinesSessionStore, RealtimeSessions, SessionConfig};
+use pixelle_monitoring::{init_logging, LoggingConfig, RequestCorrelation};
+use std::env;
+use std::sync::Arc;
+
+mod handlers;
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    // Initialize structured logging
+    let log_export = init_logging(LoggingConfig::from_env("analytics-service", env!("CARGO_PKG_VERSION")));
+    
+    // Get port from environment or use default
+    let port = env::var("PORT").unwrap_or_else(|_| "8085".to_string());
+    let bind_address = format!("0.0.0.0:{}", port);
+    
+    tracing::info!("Starting analytics service on {}", bind_address);
+    
+    let metrics = AnalyticsMetrics::new();
+    let summary_dir = env::var("SESSION_SUMMARY_DIR").unwrap_or_else(|_| "./data/sessions".to_string());
+    let sessions = Arc::new(RealtimeSessions::new(
+        SessionConfig::from_env(),
+        Arc::new(JsonLinesSessionStore::new(summary_dir)),
+        metrics.clone(),
+    ));
+    let ticker = sessions.clone().spawn_ticker();
+    
+    let app_sessions = web::Data::new(sessions.clone());
+    let app_metrics = web::Data::new(metrics);
+    let result = HttpServer::new(move || {
+        App::new()
+            .wrap(RequestCorrelation)
+            .app_data(app_sessions.clone())
+            .app_data(app_metrics.clone())
+            .service(
+                web::scope("/api/v1/analytics")
+                    .route("/events", web::post().to(handlers::track_events))
+                    .route("/realtime", web::get().to(handlers::get_realtime))
+            )
+            .route("/metrics", web::get().to(handlers::metrics))
+            .route("/health", web::get().to(handlers::health_check))
+    })
+    .bind(bind_address)?
+    .run()
+    .await;
+
+    // Sessions still open at shutdown are saved as they stand
+    ticker.abort();
+    if let Err(e) = sessions.close_all().await {
+        tracing::error!("Failed to save open sessions: {}", e);
+    }
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
+}
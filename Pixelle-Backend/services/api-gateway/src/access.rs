@@ -0,0 +1,158 @@
+use crate::config::{RouteAccess, RouteConfig};
+use actix_web::http::Method;
+use std::sync::RwLock;
+
+/// Percent-encoded characters that would change which segments a path has
+/// once decoded: `.`, `/` and `\`
+const ENCODED_SEPARATORS: &[&str] = &["%2e", "%2f", "%5c"];
+
+/// Whether a path is already in the form the upstream will see
+///
+/// The forwarded URL is normalised by the HTTP client, which collapses dot
+/// segments, so a path like `/public/../private` would be judged against one
+/// route and served by another. Empty segments, dot segments, backslashes and
+/// their percent-encodings are refused instead of rewritten, so the path the
+/// gateway checks is always the path that gets forwarded.
+pub fn is_canonical_path(path: &str) -> bool {
+    let Some(rest) = path.strip_prefix('/') else {
+        return false;
+    };
+    if path.contains('\\') {
+        return false;
+    }
+    let lowered = path.to_ascii_lowercase();
+    if ENCODED_SEPARATORS.iter().any(|encoded| lowered.contains(encoded)) {
+        return false;
+    }
+    // A single trailing slash is fine; any other empty segment is not
+    let rest = rest.strip_suffix('/').unwrap_or(rest);
+    rest.is_empty() || rest.split('/').all(|segment| !matches!(segment, "" | "." | ".."))
+}
+
+/// Which routes anonymous clients may call, replaced along with the routes
+/// on reload
+pub struct AccessPolicy {
+    routes: RwLock<Vec<RouteConfig>>,
+}
+
+impl AccessPolicy {
+    pub fn new(routes: Vec<RouteConfig>) -> Self {
+        Self {
+            routes: RwLock::new(routes),
+        }
+    }
+
+    pub fn set_routes(&self, routes: Vec<RouteConfig>) {
+        *self.routes.write().unwrap() = routes;
+    }
+
+    /// Whether a request without credentials may be forwarded
+    ///
+    /// Uses the route with the longest matching prefix, like the router.
+    /// Paths without a route are let through to get the router's 404, but
+    /// paths that are not canonical never are.
+    pub fn allows_anonymous(&self, method: &Method, path: &str) -> bool {
+        if !is_canonical_path(path) {
+            return false;
+        }
+        let routes = self.routes.read().unwrap();
+        let access = routes
+            .iter()
+            .filter(|route| route.matches(path))
+            .max_by_key(|route| route.prefix.len())
+            .map(|route| route.access);
+        match access {
+            None | Some(RouteAccess::Public) => true,
+            Some(RouteAccess::PublicReads) => method == Method::GET || method == Method::HEAD,
+            Some(RouteAccess::Authenticated) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> AccessPolicy {
+        AccessPolicy::new(vec![
+            RouteConfig::new("/api/v1/users", "http://users").with_access(RouteAccess::PublicReads),
+            RouteConfig::new("/api/v1/feed", "http://feed"),
+            RouteConfig::new("/api/v1/feed/trending", "http://feed").with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/auth", "http://auth"),
+            RouteConfig::new("/api/v1/auth/unlock", "http://auth").with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/auth/invites/", "http://auth").with_access(RouteAccess::PublicReads),
+        ])
+    }
+
+    #[test]
+    fn longest_matching_prefix_decides() {
+        let policy = policy();
+        assert!(!policy.allows_anonymous(&Method::GET, "/api/v1/feed"));
+        assert!(!policy.allows_anonymous(&Method::GET, "/api/v1/feed/home"));
+        assert!(policy.allows_anonymous(&Method::GET, "/api/v1/feed/trending"));
+        assert!(policy.allows_anonymous(&Method::GET, "/api/v1/feed/trending/tags"));
+        assert!(!policy.allows_anonymous(&Method::POST, "/api/v1/auth/logout"));
+        assert!(policy.allows_anonymous(&Method::POST, "/api/v1/auth/unlock"));
+    }
+
+    #[test]
+    fn prefixes_match_whole_segments() {
+        let policy = policy();
+        assert!(!policy.allows_anonymous(&Method::POST, "/api/v1/auth/unlockall"));
+        assert!(!policy.allows_anonymous(&Method::GET, "/api/v1/feed/trendingx"));
+        assert!(policy.allows_anonymous(&Method::GET, "/api/v1/users/42"));
+        // Falls through to no route at all, which the router answers with 404
+        assert!(policy.allows_anonymous(&Method::GET, "/api/v1/users-admin"));
+        assert!(policy.allows_anonymous(&Method::GET, "/api/v1/auth/invites/abc123"));
+        assert!(!policy.allows_anonymous(&Method::GET, "/api/v1/auth/invites"));
+    }
+
+    #[test]
+    fn public_reads_open_only_get_and_head() {
+        let policy = policy();
+        assert!(policy.allows_anonymous(&Method::GET, "/api/v1/users/42"));
+        assert!(policy.allows_anonymous(&Method::HEAD, "/api/v1/users/42"));
+        assert!(!policy.allows_anonymous(&Method::POST, "/api/v1/users/42"));
+        assert!(!policy.allows_anonymous(&Method::DELETE, "/api/v1/users/42"));
+        assert!(!policy.allows_anonymous(&Method::PUT, "/api/v1/auth/invites/abc123"));
+    }
+
+    #[test]
+    fn dot_segments_and_encoded_separators_are_not_canonical() {
+        for path in [
+            "/api/v1/feed/trending/../home",
+            "/api/v1/feed/trending/./../home",
+            "/api/v1/feed/trending/..",
+            "/api/v1/feed/trending/%2e%2e/home",
+            "/api/v1/feed/trending/%2E%2E/home",
+            "/api/v1/feed/trending/.%2e/home",
+            "/api/v1/feed/trending%2f..%2fhome",
+            "/api/v1/feed/trending/..\\home",
+            "/api/v1/feed/trending/..%5chome",
+            "/api/v1//feed/trending",
+            "api/v1/feed/trending",
+        ] {
+            assert!(!is_canonical_path(path), "{path}");
+        }
+        for path in ["/", "/api/v1/feed/trending", "/api/v1/feed/trending/", "/api/v1/users/a.b", "/api/v1/users/..x"] {
+            assert!(is_canonical_path(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn paths_escaping_a_public_route_are_refused() {
+        let policy = policy();
+        assert!(!policy.allows_anonymous(&Method::GET, "/api/v1/feed/trending/../home"));
+        assert!(!policy.allows_anonymous(&Method::GET, "/api/v1/feed/trending/%2e%2e/home"));
+        assert!(!policy.allows_anonymous(&Method::GET, "/api/v1/users/../auth/sessions"));
+        assert!(!policy.allows_anonymous(&Method::POST, "/api/v1/auth/unlock/../logout"));
+        assert!(!policy.allows_anonymous(&Method::GET, "//api/v1/feed/trending"));
+    }
+
+    #[test]
+    fn reloaded_routes_replace_the_policy() {
+        let policy = policy();
+        policy.set_routes(vec![RouteConfig::new("/api/v1/users", "http://users")]);
+        assert!(!policy.allows_anonymous(&Method::GET, "/api/v1/users/42"));
+    }
+}
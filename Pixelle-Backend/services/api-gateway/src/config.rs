@@ -10,20 +10,89 @@ pub struct GatewayConfig {
     pub rate_limit_requests_per_minute: u32,
     pub rate_limit_requests_per_hour: u32,
     pub cors_origins: Vec<String>,
-    pub jwt_secret: String,
+    /// Upstream for each path prefix; the longest matching prefix wins
+    pub routes: Vec<RouteConfig>,
+    /// How long in-flight requests may finish after shutdown starts
+    pub drain_timeout_secs: u64,
+    /// How long to keep serving after reporting not ready, so the
+    /// orchestrator stops sending traffic before the listener closes
+    pub readiness_grace_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteConfig {
+    pub prefix: String,
+    pub upstream: String,
+    /// Who may call the route; authenticated callers only unless set
+    #[serde(default)]
+    pub access: RouteAccess,
+}
+
+/// Whether a route needs a bearer token at the gateway
+///
+/// Services still check what the caller may do; this only keeps anonymous
+/// traffic off routes that never serve it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteAccess {
+    /// A valid access token or API key is required
+    #[default]
+    Authenticated,
+    /// `GET` and `HEAD` are open to anyone; other methods need a token
+    PublicReads,
+    /// Open to anyone; a token, if sent, is still validated
+    Public,
+}
+
+/// Settings read from `GATEWAY_CONFIG_FILE`, which can change on reload
+#[derive(Debug, Default, Deserialize)]
+struct ReloadableConfig {
+    routes: Option<Vec<RouteConfig>>,
+    rate_limit_requests_per_minute: Option<u32>,
+    rate_limit_requests_per_hour: Option<u32>,
 }
 
 impl GatewayConfig {
     pub fn from_env() -> Self {
+        let user_service_url = env::var("USER_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:8081".to_string());
+        let feed_service_url = env::var("FEED_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:8082".to_string());
+        let content_service_url = env::var("CONTENT_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:8083".to_string());
+        let auth_service_url = env::var("AUTH_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:8084".to_string());
+        let link_preview_service_url = env::var("LINK_PREVIEW_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:8086".to_string());
+        let notification_service_url = env::var("NOTIFICATION_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:8088".to_string());
+        let routes = vec![
+            RouteConfig::new("/api/v1/users", &user_service_url).with_access(RouteAccess::PublicReads),
+            RouteConfig::new("/api/v1/feed", &feed_service_url),
+            RouteConfig::new("/api/v1/feed/trending", &feed_service_url).with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/posts", &content_service_url).with_access(RouteAccess::PublicReads),
+            RouteConfig::new("/api/v1/auth", &auth_service_url),
+            // Signing in and recovering an account happen before there is a token
+            RouteConfig::new("/api/v1/auth/register", &auth_service_url).with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/auth/login", &auth_service_url).with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/auth/challenge", &auth_service_url).with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/auth/token", &auth_service_url).with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/auth/password-reset", &auth_service_url).with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/auth/email/verify", &auth_service_url).with_access(RouteAccess::Public),
+            RouteConfig::new("/api/v1/auth/unlock", &auth_service_url).with_access(RouteAccess::Public),
+            // Invite links are checked on the sign-up page
+            RouteConfig::new("/api/v1/auth/invites/", &auth_service_url).with_access(RouteAccess::PublicReads),
+            // Only preview images are public; unfurling is internal
+            RouteConfig::new("/api/v1/unfurl/images", &link_preview_service_url).with_access(RouteAccess::Public),
+            // Preferences are public; dispatch is internal
+            RouteConfig::new("/api/v1/notifications/preferences", &notification_service_url),
+        ];
+
         Self {
-            user_service_url: env::var("USER_SERVICE_URL")
-                .unwrap_or_else(|_| "http://localhost:8081".to_string()),
-            feed_service_url: env::var("FEED_SERVICE_URL")
-                .unwrap_or_else(|_| "http://localhost:8082".to_string()),
-            content_service_url: env::var("CONTENT_SERVICE_URL")
-                .unwrap_or_else(|_| "http://localhost:8083".to_string()),
-            auth_service_url: env::var("AUTH_SERVICE_URL")
-                .unwrap_or_else(|_| "http://localhost:8084".to_string()),
+            user_service_url,
+            feed_service_url,
+            content_service_url,
+            auth_service_url,
             rate_limit_requests_per_minute: env::var("RATE_LIMIT_PER_MINUTE")
                 .unwrap_or_else(|_| "100".to_string())
                 .parse()
@@ -37,8 +106,75 @@ impl GatewayConfig {
                 .split(',')
                 .map(|s| s.trim().to_string())
                 .collect(),
-            jwt_secret: env::var("JWT_SECRET")
-                .unwrap_or_else(|_| "your-secret-key-here".to_string()),
+            routes,
+            drain_timeout_secs: env::var("GATEWAY_DRAIN_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(30),
+            readiness_grace_secs: env::var("GATEWAY_READINESS_GRACE_SECONDS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(5),
+        }
+    }
+
+    /// Environment settings, with routes and rate limits from
+    /// `GATEWAY_CONFIG_FILE` (JSON, TOML or YAML) when it is set.
+    ///
+    /// Called again on `SIGHUP`; an invalid file is an error, so a bad edit
+    /// never replaces a working configuration.
+    pub fn load() -> Result<Self, config::ConfigError> {
+        let mut gateway = Self::from_env();
+        let Ok(path) = env::var("GATEWAY_CONFIG_FILE") else {
+            return Ok(gateway);
+        };
+
+        let file: ReloadableConfig = config::Config::builder()
+            .add_source(config::File::with_name(&path))
+            .build()?
+            .try_deserialize()?;
+        if let Some(routes) = file.routes {
+            gateway.routes = routes;
+        }
+        if let Some(per_minute) = file.rate_limit_requests_per_minute {
+            gateway.rate_limit_requests_per_minute = per_minute;
+        }
+        if let Some(per_hour) = file.rate_limit_requests_per_hour {
+            gateway.rate_limit_requests_per_hour = per_hour;
+        }
+
+        for route in &gateway.routes {
+            if !route.prefix.starts_with('/') {
+                return Err(config::ConfigError::Message(format!("Route prefix must start with '/': {}", route.prefix)));
+            }
+            if !route.upstream.starts_with("http://") && !route.upstream.starts_with("https://") {
+                return Err(config::ConfigError::Message(format!("Route upstream must be an http(s) URL: {}", route.upstream)));
+            }
+        }
+        Ok(gateway)
+    }
+}
+
+impl RouteConfig {
+    pub fn new(prefix: &str, upstream: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            upstream: upstream.trim_end_matches('/').to_string(),
+            access: RouteAccess::default(),
+        }
+    }
+
+    pub fn with_access(mut self, access: RouteAccess) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Whether the prefix covers the path on a segment boundary, so
+    /// `/api/v1/users` matches `/api/v1/users/42` but not `/api/v1/users-admin`
+    pub fn matches(&self, path: &str) -> bool {
+        match path.strip_prefix(self.prefix.as_str()) {
+            Some(rest) => rest.is_empty() || self.prefix.ends_with('/') || rest.starts_with('/'),
+            None => false,
         }
     }
 }
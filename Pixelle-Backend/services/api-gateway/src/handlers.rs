@@ -1,18 +1,23 @@
 use actix_web::{web, HttpRequest, HttpResponse, Result};
+use pixelle_http::HttpClient;
+use pixelle_monitoring::RequestContext;
+use prometheus::{Encoder, TextEncoder};
 use serde_json::json;
+use crate::lifecycle::Lifecycle;
 use crate::routing::ServiceRouter;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 pub async fn proxy_request(
     req: HttpRequest,
-    payload: web::Payload,
+    context: RequestContext,
+    body: web::Bytes,
     service_router: web::Data<Arc<RwLock<ServiceRouter>>>,
 ) -> Result<HttpResponse> {
     let router = service_router.get_ref();
     let router_guard = router.read().await;
     
-    match router_guard.route_request(&req, payload).await {
+    match router_guard.route_request(&req, &context, body).await {
         Ok(response) => Ok(response),
         Err(e) => {
             tracing::error!("Proxy error: {}", e);
@@ -32,7 +37,27 @@ pub async fn health_check() -> Result<HttpResponse> {
     })))
 }
 
-pub async fn metrics() -> Result<HttpResponse> {
-    // This would return Prometheus metrics
-    Ok(HttpResponse::Ok().body("# HELP http_requests_total Total number of HTTP requests\n# TYPE http_requests_total counter\nhttp_requests_total 0"))
+/// Readiness probe; fails as soon as shutdown starts so the orchestrator
+/// stops sending traffic while in-flight requests drain
+pub async fn readiness(lifecycle: web::Data<Lifecycle>) -> Result<HttpResponse> {
+    let body = json!({
+        "ready": lifecycle.is_ready(),
+        "in_flight": lifecycle.in_flight(),
+    });
+    if lifecycle.is_ready() {
+        Ok(HttpResponse::Ok().json(body))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(body))
+    }
+}
+
+/// Outbound call metrics per upstream service
+pub async fn metrics(client: web::Data<HttpClient>) -> Result<HttpResponse> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&client.metrics().registry().gather(), &mut buffer)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(buffer))
 }
@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Readiness and in-flight requests of the gateway, for graceful shutdown
+///
+/// Once draining, the readiness probe fails so the orchestrator stops
+/// routing new traffic here, while requests already on their way are still
+/// served.
+#[derive(Default)]
+pub struct Lifecycle {
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        !self.is_draining()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// Report not ready from now on; returns false if already draining
+    pub fn begin_drain(&self) -> bool {
+        !self.draining.swap(true, Ordering::AcqRel)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Count a request as in flight until the guard is dropped
+    pub fn enter(self: &Arc<Self>) -> InFlightGuard {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        InFlightGuard { lifecycle: self.clone() }
+    }
+}
+
+pub struct InFlightGuard {
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.lifecycle.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
@@ -1,25 +1,36 @@
-use actix_web::{web, App, HttpServer, middleware};
+use actix_cors::Cors;
+use actix_web::{web, App, HttpServer};
 use actix_web::middleware::Logger;
-use pixelle_monitoring::init_tracing;
+use pixelle_auth::TokenValidator;
+use pixelle_http::HttpClient;
+use pixelle_monitoring::{init_logging, LoggingConfig, RequestCorrelation};
 use std::env;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::RwLock;
 
+mod access;
 mod handlers;
 mod middleware;
 mod config;
+mod lifecycle;
+mod rate_limit;
 mod routing;
 
+use access::AccessPolicy;
 use config::GatewayConfig;
+use lifecycle::Lifecycle;
+use rate_limit::{RateLimiter, RateLimits};
 use routing::ServiceRouter;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Initialize tracing
-    init_tracing();
+    // Initialize structured logging
+    let log_export = init_logging(LoggingConfig::from_env("api-gateway", env!("CARGO_PKG_VERSION")));
     
     // Load configuration
-    let config = GatewayConfig::from_env();
+    let config = GatewayConfig::load().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
     
     // Get port from environment or use default
     let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
@@ -28,28 +39,126 @@ async fn main() -> std::io::Result<()> {
     tracing::info!("Starting API Gateway on {}", bind_address);
     tracing::info!("User service URL: {}", config.user_service_url);
     
+    // One pooled client for every upstream call
+    let http_client = HttpClient::from_env("api-gateway");
+    
     // Create service router
-    let service_router = Arc::new(RwLock::new(ServiceRouter::new(config.clone())));
+    let service_router = Arc::new(RwLock::new(ServiceRouter::new(config.clone(), http_client.clone())));
+    
+    // Rate limits and routes are replaced on SIGHUP without dropping connections
+    let rate_limiter = Arc::new(RateLimiter::new(rate_limits(&config)));
+    let access_policy = Arc::new(AccessPolicy::new(config.routes.clone()));
+    let lifecycle = Arc::new(Lifecycle::new());
     
-    HttpServer::new(move || {
+    // Tokens are validated locally against keys pulled from every region's auth-service
+    let validator = Arc::new(TokenValidator::from_env(&config.auth_service_url));
+    if let Err(e) = validator.refresh().await {
+        tracing::warn!("Starting without signing keys, authenticated requests will fail until refresh: {}", e);
+    }
+    validator.clone().spawn_refresh();
+    
+    let app_router = service_router.clone();
+    let app_client = http_client.clone();
+    let app_limiter = rate_limiter.clone();
+    let app_access = access_policy.clone();
+    let app_lifecycle = lifecycle.clone();
+    let server = HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
-            .wrap(middleware::cors::Cors::permissive())
-            .app_data(web::Data::new(service_router.clone()))
+            .wrap(middleware::RateLimit::new(app_limiter.clone()))
+            .wrap(middleware::Authenticate::new(validator.clone(), app_access.clone()))
+            .wrap(Cors::permissive())
+            .wrap(middleware::TrackInFlight::new(app_lifecycle.clone()))
+            // Outermost, so the access log and the proxied request carry the IDs
+            .wrap(RequestCorrelation)
+            .app_data(web::Data::new(app_router.clone()))
+            .app_data(web::Data::new(app_client.clone()))
+            .app_data(web::Data::from(app_lifecycle.clone()))
             .service(
                 web::scope("/api/v1")
-                    .service(handlers::proxy_request)
+                    .default_service(web::to(handlers::proxy_request))
             )
             .service(
                 web::scope("/health")
-                    .service(handlers::health_check)
-            )
-            .service(
-                web::scope("/metrics")
-                    .service(handlers::metrics)
+                    .route("", web::get().to(handlers::health_check))
+                    .route("/ready", web::get().to(handlers::readiness))
             )
+            .route("/metrics", web::get().to(handlers::metrics))
     })
     .bind(bind_address)?
-    .run()
-    .await
+    // Signals are handled below so readiness drops before the listener closes
+    .disable_signals()
+    .shutdown_timeout(config.drain_timeout_secs)
+    .run();
+    
+    tokio::spawn(handle_signals(server.handle(), config, lifecycle.clone(), service_router, http_client, rate_limiter, access_policy));
+    let result = server.await;
+    if lifecycle.in_flight() > 0 {
+        tracing::warn!("Drain deadline passed with {} requests in flight", lifecycle.in_flight());
+    }
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
+}
+
+fn rate_limits(config: &GatewayConfig) -> RateLimits {
+    RateLimits {
+        per_minute: config.rate_limit_requests_per_minute,
+        per_hour: config.rate_limit_requests_per_hour,
+    }
+}
+
+/// Reload routes and rate limits on SIGHUP; on SIGTERM or SIGINT report not
+/// ready, keep serving for the readiness grace period, then stop accepting
+/// connections and let in-flight requests finish within the drain timeout.
+async fn handle_signals(
+    server: actix_web::dev::ServerHandle,
+    config: GatewayConfig,
+    lifecycle: Arc<Lifecycle>,
+    service_router: Arc<RwLock<ServiceRouter>>,
+    http_client: HttpClient,
+    rate_limiter: Arc<RateLimiter>,
+    access_policy: Arc<AccessPolicy>,
+) {
+    let (mut hangup, mut terminate, mut interrupt) = match (
+        signal(SignalKind::hangup()),
+        signal(SignalKind::terminate()),
+        signal(SignalKind::interrupt()),
+    ) {
+        (Ok(hangup), Ok(terminate), Ok(interrupt)) => (hangup, terminate, interrupt),
+        _ => {
+            tracing::error!("Failed to install signal handlers, stopping");
+            server.stop(false).await;
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            _ = hangup.recv() => match GatewayConfig::load() {
+                Ok(reloaded) => {
+                    rate_limiter.set_limits(rate_limits(&reloaded));
+                    access_policy.set_routes(reloaded.routes.clone());
+                    let routes = reloaded.routes.len();
+                    *service_router.write().await = ServiceRouter::new(reloaded, http_client.clone());
+                    tracing::info!("Reloaded configuration with {} routes", routes);
+                }
+                Err(e) => tracing::error!("Keeping current configuration, reload failed: {}", e),
+            },
+            _ = terminate.recv() => break,
+            _ = interrupt.recv() => break,
+        }
+    }
+
+    lifecycle.begin_drain();
+    tracing::info!(
+        "Shutting down: not ready, closing listener in {}s, draining for up to {}s",
+        config.readiness_grace_secs,
+        config.drain_timeout_secs
+    );
+    tokio::time::sleep(Duration::from_secs(config.readiness_grace_secs)).await;
+    server.stop(true).await;
 }
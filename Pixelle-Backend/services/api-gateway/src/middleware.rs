@@ -1,11 +1,20 @@
 use actix_web::{
+    body::EitherBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage, HttpRequest,
+    http::{
+        header::{HeaderName, HeaderValue, AUTHORIZATION, CONNECTION, RETRY_AFTER},
+        StatusCode,
+    },
+    Error, HttpMessage, HttpResponse,
 };
-use futures_util::future::{ready, LocalBoxFuture, Ready};
-use std::future::Future;
-use std::pin::Pin;
-use std::task::{Context, Poll};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use pixelle_auth::{api_key_from_bearer, TokenValidator};
+use pixelle_core::PixelleError;
+use crate::access::{is_canonical_path, AccessPolicy};
+use crate::lifecycle::Lifecycle;
+use crate::rate_limit::RateLimiter;
+use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Instant;
 
 pub struct RequestLogger;
@@ -66,3 +75,334 @@ where
         })
     }
 }
+
+/// Identity headers set for downstream services once a token has been validated
+pub const USER_ID_HEADER: &str = "x-user-id";
+pub const SESSION_ID_HEADER: &str = "x-session-id";
+pub const SESSION_REGION_HEADER: &str = "x-session-region";
+/// The user's roles, comma-separated; empty for users without any
+pub const ROLES_HEADER: &str = "x-roles";
+
+/// Validates bearer tokens locally and passes the caller's identity downstream
+///
+/// Identity headers sent by clients are always dropped, so services can trust
+/// them. Paths with dot segments, empty segments or encoded separators are
+/// rejected with 400 before any route is matched, since the forwarded URL
+/// would not be the path that was checked. Requests without a token are
+/// rejected with 401 unless their route allows anonymous access; an invalid
+/// token is always rejected. API keys are
+/// checked to exist and be unexpired, then passed on without identity
+/// headers for the service to validate again, since only services know which
+/// scopes they need and count the key's rate limit.
+pub struct Authenticate {
+    validator: Arc<TokenValidator>,
+    access: Arc<AccessPolicy>,
+}
+
+impl Authenticate {
+    pub fn new(validator: Arc<TokenValidator>, access: Arc<AccessPolicy>) -> Self {
+        Self { validator, access }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authenticate
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticateMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticateMiddleware {
+            service: Rc::new(service),
+            validator: self.validator.clone(),
+            access: self.access.clone(),
+        }))
+    }
+}
+
+pub struct AuthenticateMiddleware<S> {
+    service: Rc<S>,
+    validator: Arc<TokenValidator>,
+    access: Arc<AccessPolicy>,
+}
+
+/// Error response with the status the error maps to
+fn rejection(error: &PixelleError) -> HttpResponse {
+    let status = StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::UNAUTHORIZED);
+    HttpResponse::build(status).json(serde_json::json!({ "error": error.to_string() }))
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticateMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        for name in [USER_ID_HEADER, SESSION_ID_HEADER, SESSION_REGION_HEADER, ROLES_HEADER] {
+            req.headers_mut().remove(name);
+        }
+
+        if !is_canonical_path(req.path()) {
+            let response = HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "Path must not contain dot segments, empty segments or encoded separators" }))
+                .map_into_right_body();
+            return Box::pin(async move { Ok(req.into_response(response)) });
+        }
+
+        let authorization = req
+            .headers()
+            .get(AUTHORIZATION)
+            .map(|value| value.to_str().unwrap_or_default().to_string());
+        if authorization.is_none() && !self.access.allows_anonymous(req.method(), req.path()) {
+            let response = HttpResponse::Unauthorized()
+                .json(serde_json::json!({ "error": "Authentication required" }))
+                .map_into_right_body();
+            return Box::pin(async move { Ok(req.into_response(response)) });
+        }
+        if let Some(key) = authorization.as_deref().and_then(api_key_from_bearer) {
+            let key = key.to_string();
+            let validator = self.validator.clone();
+            let service = self.service.clone();
+            return Box::pin(async move {
+                if let Err(e) = validator.api_key_grant(&key).await {
+                    return Ok(req.into_response(rejection(&e).map_into_right_body()));
+                }
+                Ok(service.call(req).await?.map_into_left_body())
+            });
+        }
+        if let Some(authorization) = authorization {
+            let claims = match self.validator.validate_bearer(&authorization) {
+                Ok(claims) => claims,
+                Err(e) => {
+                    let response = rejection(&e).map_into_right_body();
+                    return Box::pin(async move { Ok(req.into_response(response)) });
+                }
+            };
+
+            for (name, value) in [
+                (USER_ID_HEADER, &claims.sub),
+                (SESSION_ID_HEADER, &claims.sid),
+                (SESSION_REGION_HEADER, &claims.region),
+            ] {
+                if let Ok(value) = HeaderValue::from_str(value) {
+                    req.headers_mut().insert(HeaderName::from_static(name), value);
+                }
+            }
+            if let Ok(roles) = HeaderValue::from_str(&claims.roles.join(",")) {
+                req.headers_mut().insert(HeaderName::from_static(ROLES_HEADER), roles);
+            }
+            req.extensions_mut().insert(claims);
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { Ok(fut.await?.map_into_left_body()) })
+    }
+}
+
+/// Counts in-flight requests, and asks clients to reconnect elsewhere once
+/// the gateway is draining
+///
+/// Requests that arrive while draining are still served, but the response
+/// closes the connection so keep-alive clients do not reuse it.
+pub struct TrackInFlight {
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl TrackInFlight {
+    pub fn new(lifecycle: Arc<Lifecycle>) -> Self {
+        Self { lifecycle }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for TrackInFlight
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TrackInFlightMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TrackInFlightMiddleware {
+            service,
+            lifecycle: self.lifecycle.clone(),
+        }))
+    }
+}
+
+pub struct TrackInFlightMiddleware<S> {
+    service: S,
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl<S, B> Service<ServiceRequest> for TrackInFlightMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let guard = self.lifecycle.enter();
+        let lifecycle = self.lifecycle.clone();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+            if lifecycle.is_draining() {
+                res.headers_mut().insert(CONNECTION, HeaderValue::from_static("close"));
+            }
+            drop(guard);
+            Ok(res)
+        })
+    }
+}
+
+/// Rejects clients over their request limits with 429
+///
+/// Authenticated clients are limited by user, anonymous ones by address.
+/// Health checks and metrics are never limited. Runs inside `Authenticate`,
+/// so the user ID header can be trusted.
+pub struct RateLimit {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimit {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service,
+            limiter: self.limiter.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if !req.path().starts_with("/health") && !req.path().starts_with("/metrics") {
+            let client = match req.headers().get(USER_ID_HEADER).and_then(|value| value.to_str().ok()) {
+                Some(user_id) => format!("user:{}", user_id),
+                None => format!("addr:{}", req.connection_info().realip_remote_addr().unwrap_or("unknown")),
+            };
+            if let Err(retry_after) = self.limiter.check(&client, Instant::now()) {
+                let response = HttpResponse::TooManyRequests()
+                    .insert_header((RETRY_AFTER, retry_after.as_secs().max(1).to_string()))
+                    .json(serde_json::json!({ "error": "Rate limit exceeded" }))
+                    .map_into_right_body();
+                return Box::pin(async move { Ok(req.into_response(response)) });
+            }
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { Ok(fut.await?.map_into_left_body()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RouteAccess, RouteConfig};
+    use actix_web::{test, web, App};
+
+    /// A gateway whose validator knows no auth-service, so no API key exists
+    fn app_parts() -> (Arc<TokenValidator>, Arc<AccessPolicy>) {
+        let access = AccessPolicy::new(vec![
+            RouteConfig::new("/api/v1/feed", "http://feed"),
+            RouteConfig::new("/api/v1/feed/trending", "http://feed").with_access(RouteAccess::Public),
+        ]);
+        (Arc::new(TokenValidator::new(Vec::new())), Arc::new(access))
+    }
+
+    async fn status(authorization: Option<&str>, path: &str) -> StatusCode {
+        let (validator, access) = app_parts();
+        let app = test::init_service(
+            App::new()
+                .wrap(Authenticate::new(validator, access))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        let mut request = test::TestRequest::get().uri(path);
+        if let Some(authorization) = authorization {
+            request = request.insert_header((AUTHORIZATION, authorization));
+        }
+        test::call_service(&app, request.to_request()).await.status()
+    }
+
+    #[actix_web::test]
+    async fn protected_routes_need_credentials() {
+        assert_eq!(status(None, "/api/v1/feed/home").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(None, "/api/v1/feed/trending").await, StatusCode::OK);
+        assert_eq!(status(Some("Bearer not-a-token"), "/api/v1/feed/home").await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn non_canonical_paths_are_rejected_before_routing() {
+        assert_eq!(status(None, "/api/v1/feed/trending/../home").await, StatusCode::BAD_REQUEST);
+        assert_eq!(status(None, "/api/v1/feed/trending/%2e%2e/home").await, StatusCode::BAD_REQUEST);
+        assert_eq!(status(Some("Bearer not-a-token"), "/api/v1/feed//home").await, StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn api_keys_are_checked_before_forwarding() {
+        // Malformed keys are rejected without a lookup
+        assert_eq!(status(Some("Bearer pxk_x_y"), "/api/v1/feed/home").await, StatusCode::UNAUTHORIZED);
+        // Well-formed keys no auth-service knows are rejected too
+        let unknown = format!("Bearer pxk_{}_{}", "0".repeat(32), "A".repeat(43));
+        assert_eq!(status(Some(&unknown), "/api/v1/feed/home").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some(&unknown), "/api/v1/feed/trending").await, StatusCode::UNAUTHORIZED);
+    }
+}
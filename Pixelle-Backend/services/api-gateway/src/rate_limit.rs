@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+/// Tracked clients before idle ones are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Requests allowed per client; zero means unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    pub per_minute: u32,
+    pub per_hour: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+impl Window {
+    fn new(now: Instant) -> Self {
+        Self { started: now, count: 0 }
+    }
+
+    /// Count a request, or return how long until the window resets
+    fn hit(&mut self, now: Instant, length: Duration, limit: u32) -> Result<(), Duration> {
+        if now.duration_since(self.started) >= length {
+            *self = Self::new(now);
+        }
+        if limit > 0 && self.count >= limit {
+            return Err(length.saturating_sub(now.duration_since(self.started)));
+        }
+        self.count += 1;
+        Ok(())
+    }
+}
+
+struct ClientWindows {
+    minute: Window,
+    hour: Window,
+}
+
+/// Fixed-window request limits per client
+///
+/// Limits can be replaced while running; counts already made in the current
+/// windows are kept.
+pub struct RateLimiter {
+    limits: RwLock<RateLimits>,
+    clients: Mutex<HashMap<String, ClientWindows>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits: RwLock::new(limits),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        *self.limits.read().unwrap()
+    }
+
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.write().unwrap() = limits;
+    }
+
+    /// Count a request from `client`, or return how long it must wait
+    pub fn check(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let limits = self.limits();
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= PRUNE_THRESHOLD {
+            clients.retain(|_, windows| now.duration_since(windows.hour.started) < HOUR);
+        }
+
+        let windows = clients.entry(client.to_string()).or_insert_with(|| ClientWindows {
+            minute: Window::new(now),
+            hour: Window::new(now),
+        });
+        // Check both before counting, so a rejected request uses neither window
+        let (mut minute, mut hour) = (windows.minute, windows.hour);
+        minute.hit(now, MINUTE, limits.per_minute)?;
+        hour.hit(now, HOUR, limits.per_hour)?;
+        windows.minute = minute;
+        windows.hour = hour;
+        Ok(())
+    }
+}
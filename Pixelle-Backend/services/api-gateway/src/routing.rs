@@ -1,76 +1,107 @@
-use actix_web::{HttpRequest, HttpResponse, web::Payload};
-use reqwest::Client;
+use actix_web::{HttpRequest, HttpResponse, web::Bytes};
+use actix_web::http::StatusCode;
+use pixelle_http::{HttpClient, HttpClientError};
+use pixelle_monitoring::RequestContext;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::access::is_canonical_path;
 use crate::config::GatewayConfig;
 use anyhow::Result;
 
+/// Headers that describe the client connection rather than the request
+const HOP_BY_HOP_HEADERS: &[&str] = &["connection", "host", "keep-alive", "transfer-encoding", "upgrade", "content-length"];
+
 pub struct ServiceRouter {
     config: GatewayConfig,
-    client: Client,
+    client: HttpClient,
 }
 
 impl ServiceRouter {
-    pub fn new(config: GatewayConfig) -> Self {
+    pub fn new(config: GatewayConfig, client: HttpClient) -> Self {
         Self {
             config,
-            client: Client::new(),
+            client,
         }
     }
 
-    pub async fn route_request(&self, req: &HttpRequest, payload: Payload) -> Result<HttpResponse> {
+    pub async fn route_request(&self, req: &HttpRequest, context: &RequestContext, body: Bytes) -> Result<HttpResponse> {
         let path = req.path();
-        let method = req.method().as_str();
-        
-        // Route based on path
-        let target_url = if path.starts_with("/api/v1/users") {
-            format!("{}{}", self.config.user_service_url, path)
-        } else if path.starts_with("/api/v1/feed") {
-            format!("{}{}", self.config.feed_service_url, path)
-        } else if path.starts_with("/api/v1/posts") {
-            format!("{}{}", self.config.content_service_url, path)
-        } else if path.starts_with("/api/v1/auth") {
-            format!("{}{}", self.config.auth_service_url, path)
-        } else {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Service not found",
-                "path": path
+        if !is_canonical_path(path) {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Path must not contain dot segments, empty segments or encoded separators",
             })));
+        }
+
+        // Route to the upstream with the longest matching prefix
+        let route = self.config.routes
+            .iter()
+            .filter(|route| route.matches(path))
+            .max_by_key(|route| route.prefix.len());
+        let target_url = match route {
+            Some(route) => format!("{}{}", route.upstream.trim_end_matches('/'), path),
+            None => {
+                return Ok(HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Service not found",
+                    "path": path
+                })));
+            }
         };
 
         // Forward the request
-        self.forward_request(&target_url, req, payload).await
+        self.forward_request(&target_url, req, context, body).await
     }
 
-    async fn forward_request(&self, target_url: &str, req: &HttpRequest, payload: Payload) -> Result<HttpResponse> {
-        let method = req.method().clone();
-        let headers = req.headers().clone();
-        
-        // Build the request
-        let mut request_builder = self.client
-            .request(method, target_url)
-            .headers(headers);
+    async fn forward_request(&self, target_url: &str, req: &HttpRequest, context: &RequestContext, body: Bytes) -> Result<HttpResponse> {
+        let target_url = match req.uri().query() {
+            Some(query) => format!("{}?{}", target_url, query),
+            None => target_url.to_string(),
+        };
+        let method = reqwest::Method::from_bytes(req.method().as_str().as_bytes())?;
 
-        // Add query parameters
-        if let Some(query) = req.uri().query() {
-            request_builder = request_builder.query(&[("", query)]);
+        let mut headers = HeaderMap::new();
+        for (name, value) in req.headers() {
+            if HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_str().as_bytes()), HeaderValue::from_bytes(value.as_bytes())) {
+                headers.append(name, value);
+            }
         }
 
-        // Execute the request
-        let response = request_builder
-            .body(payload)
+        // Retries, timeouts and circuit breaking come from the shared client
+        let response = match self.client
+            .request(method, &target_url)
+            .headers(headers)
+            .context(context)
+            .body(body)
             .send()
-            .await?;
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::warn!("Upstream call failed: {}", e);
+                let status = match &e {
+                    HttpClientError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+                    e if e.is_unavailable() => StatusCode::SERVICE_UNAVAILABLE,
+                    _ => StatusCode::BAD_GATEWAY,
+                };
+                return Ok(HttpResponse::build(status).json(serde_json::json!({
+                    "error": "Upstream service unavailable",
+                    "path": req.path()
+                })));
+            }
+        };
 
         // Convert response
-        let status = response.status();
+        let status = StatusCode::from_u16(response.status().as_u16())?;
         let headers = response.headers().clone();
         let body = response.bytes().await?;
 
         let mut http_response = HttpResponse::build(status);
         
         // Copy headers
-        for (key, value) in headers {
-            if let Some(key) = key {
-                http_response.header(key, value);
+        for (key, value) in headers.iter() {
+            if !HOP_BY_HOP_HEADERS.contains(&key.as_str()) {
+                http_response.append_header((key.as_str(), value.as_bytes()));
             }
         }
 
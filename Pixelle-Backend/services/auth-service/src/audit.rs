@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use pixelle_analytics::AnalyticsEvent;
+use pixelle_auth::{AuditEntry, AuditSink};
+use pixelle_core::PixelleResult;
+use pixelle_http::HttpClient;
+use std::env;
+
+/// Exports audit entries to analytics-service as `audit_*` events
+///
+/// Sending runs in the background so audited requests are not delayed;
+/// failures are only logged, as the entry is already in the audit log.
+/// Without `ANALYTICS_SERVICE_URL` nothing is sent.
+pub struct AnalyticsAuditExport {
+    client: HttpClient,
+    events_url: Option<String>,
+}
+
+impl AnalyticsAuditExport {
+    pub fn from_env(client: HttpClient) -> Self {
+        let events_url = env::var("ANALYTICS_SERVICE_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| format!("{}/api/v1/analytics/events", url.trim_end_matches('/')));
+        Self { client, events_url }
+    }
+}
+
+#[async_trait]
+impl AuditSink for AnalyticsAuditExport {
+    async fn record(&self, entry: &AuditEntry) -> PixelleResult<()> {
+        let Some(events_url) = &self.events_url else {
+            return Ok(());
+        };
+        let request = self.client.post(events_url).json(&[AnalyticsEvent::from(entry)]);
+        let id = entry.id.clone();
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    tracing::debug!("Analytics rejected audit entry {}: {}", id, response.status());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::debug!("Failed to export audit entry {}: {}", id, e),
+            }
+        });
+        Ok(())
+    }
+}
@@ -1,24 +1,1236 @@
-use actix_web::{web, App, HttpServer};
-use pixelle_monitoring::init_tracing;
+mod audit;
+mod referrals;
+mod verification;
+
+use actix_web::http::StatusCode;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use pixelle_analytics::AnalyticsEvent;
+use pixelle_auth::{
+    account_migrations, AccessClaims, AccountStore, ApiKey, AuditLog, AuditQuery, AuthAttempt, AuthServiceImpl,
+    ChallengeError, ChallengeGate, EmailConfig, EmailOutbox, EmailTransport, InMemoryThrottleStore, InviteStore,
+    JwtService, KeyRing, LockoutConfig, LogEmailTransport, LoginProtection, NewApiKey, RedisThrottleStore,
+    Registration, ServiceClients, ServiceCredentials, SessionDevice, SmtpEmailTransport, ThrottleStore,
+    VerificationStore,
+};
+use pixelle_core::{
+    AuthService, DocumentKind, Invite, InviteService, Permission, PixelleError, PixelleResult, PolicyEngine, Role,
+    RolePolicy, Subject, UserId, VerificationDecision, VerificationReason, VerificationService,
+    REFERRAL_REWARD_RETRY_INTERVAL_SECONDS, SERVICE_TOKEN_TTL_MINUTES, SIGNING_KEY_ROTATION_HOURS,
+    VERIFICATION_PURGE_INTERVAL_SECONDS,
+};
+use pixelle_database::{Backends, DatabaseConfig, MigrationRunner};
+use pixelle_http::HttpClient;
+use pixelle_monitoring::{init_logging, LoggingConfig, RequestCorrelation};
+use audit::AnalyticsAuditExport;
+use referrals::{AnalyticsReferralEvents, WebhookReferralRewarder};
+use verification::{AnalyticsVerificationEvents, NimbuxDocumentStorage, UserServiceVerifiedBadges};
+use serde::Deserialize;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    init_tracing();
-    
-    HttpServer::new(|| {
+    let log_export = init_logging(LoggingConfig::from_env("auth-service", env!("CARGO_PKG_VERSION")));
+
+    let region = env::var("AUTH_REGION").unwrap_or_else(|_| "default".to_string());
+    let keys = Arc::new(load_key_ring().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?);
+    let jwt_service = Arc::new(JwtService::new(keys, region.clone()));
+    let challenges = web::Data::new(ChallengeGate::from_env());
+
+    let backends = connect_database().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+    let email = Arc::new(
+        EmailOutbox::new(&backends, email_transport(), EmailConfig::from_env())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?,
+    );
+    email.clone().spawn_dispatcher();
+    let http_client = HttpClient::from_env("auth-service");
+    let audit = Arc::new(
+        AuditLog::new(&backends)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?
+            .with_sink(Arc::new(AnalyticsAuditExport::from_env(http_client.clone()))),
+    );
+    let verifications = verification_service(&backends, http_client.clone(), audit.clone())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?
+        .map(Arc::new);
+    if let Some(verifications) = &verifications {
+        verifications
+            .clone()
+            .spawn_document_purge(Duration::from_secs(VERIFICATION_PURGE_INTERVAL_SECONDS));
+    }
+    let verifications = verifications.map(web::Data::from);
+    let invites = Arc::new(
+        invite_service(&backends, http_client).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?,
+    );
+    invites
+        .clone()
+        .spawn_reward_retries(Duration::from_secs(REFERRAL_REWARD_RETRY_INTERVAL_SECONDS));
+    let auth_service = AuthServiceImpl::new(jwt_service.clone(), &backends)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?
+        .with_challenges(challenges.clone().into_inner())
+        .with_service_clients(ServiceClients::from_env())
+        .with_email(email)
+        .with_invites(invites)
+        .with_audit(audit)
+        .with_login_protection(Arc::new(LoginProtection::new(
+            throttle_store().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?,
+            LockoutConfig::from_env(),
+        )));
+    auth_service
+        .bootstrap_admins(&admin_user_ids())
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let auth_service = web::Data::new(auth_service);
+    let jwt_service = web::Data::from(jwt_service);
+    spawn_key_rotation(jwt_service.clone(), auth_service.clone(), challenges.clone());
+
+    tracing::info!("Starting auth service for region {}", region);
+
+    let result = HttpServer::new(move || {
         App::new()
+            .wrap(RequestCorrelation)
+            .app_data(jwt_service.clone())
+            .app_data(challenges.clone())
+            .app_data(auth_service.clone())
+            .configure(|cfg| {
+                if let Some(verifications) = &verifications {
+                    cfg.app_data(verifications.clone());
+                }
+            })
+            .route("/.well-known/jwks.json", web::get().to(jwks))
+            .service(
+                web::scope("/api/v1/auth")
+                    .route("/register", web::post().to(register))
+                    .route("/login", web::post().to(login))
+                    .route("/revocations", web::get().to(revocations))
+                    .route("/challenge", web::get().to(challenge))
+                    .route("/password-reset", web::post().to(request_password_reset))
+                    .route("/password-reset/confirm", web::post().to(confirm_password_reset))
+                    .route("/email/verify", web::post().to(verify_email))
+                    .route("/email/verification", web::post().to(resend_email_verification))
+                    .route("/unlock", web::post().to(unlock_account))
+                    .route("/logout", web::post().to(logout))
+                    .route("/logout-everywhere", web::post().to(logout_everywhere))
+                    .route("/sessions", web::get().to(list_sessions))
+                    .route("/sessions/{session_id}", web::delete().to(revoke_session))
+                    .route("/token", web::post().to(token))
+                    .route("/api-keys", web::post().to(create_api_key))
+                    .route("/api-keys", web::get().to(list_api_keys))
+                    .route("/api-keys/verify", web::post().to(verify_api_key))
+                    .route("/api-keys/{key_id}", web::delete().to(revoke_api_key))
+                    .route("/roles", web::get().to(list_roles))
+                    .route("/roles/{role}/members", web::get().to(list_role_members))
+                    .route("/users/{user_id}/roles", web::get().to(get_user_roles))
+                    .route("/users/{user_id}/roles", web::put().to(assign_user_roles))
+                    .route("/invites", web::post().to(create_invite))
+                    .route("/invites", web::get().to(list_invites))
+                    .route("/invites/{code}", web::get().to(get_invite))
+                    .route("/invites/{code}", web::delete().to(revoke_invite))
+                    .route("/referrals", web::get().to(list_referrals))
+                    .route("/referrals/{invitee_id}/review", web::post().to(review_referral))
+                    .route("/users/{user_id}/referrals", web::get().to(list_user_referrals))
+                    .route("/audit", web::get().to(query_audit_log))
+                    .route("/audit/export", web::get().to(export_audit_log))
+                    .route("/verification", web::get().to(get_verification))
+                    .route("/verification", web::post().to(request_verification))
+                    .route("/verification/documents", web::post().to(add_verification_document))
+                    .route("/verification/submit", web::post().to(submit_verification))
+                    .route("/verifications", web::get().to(verification_queue))
+                    .route("/verifications/{user_id}", web::get().to(review_verification))
+                    .route("/verifications/{user_id}/documents/{document_id}", web::get().to(verification_document))
+                    .route("/verifications/{user_id}/decision", web::post().to(decide_verification))
+                    .route("/verifications/{user_id}/revoke", web::post().to(revoke_verification))
+            )
             .service(
                 web::scope("/health")
-                    .service(health_check)
+                    .route("", web::get().to(health_check))
             )
     })
     .bind("0.0.0.0:8080")?
     .run()
-    .await
+    .await;
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
+}
+
+/// Sign with `AUTH_SIGNING_KEY` (base64 PKCS#8 P-256) if set, so restarts keep the same key
+fn load_key_ring() -> PixelleResult<KeyRing> {
+    match env::var("AUTH_SIGNING_KEY") {
+        Ok(encoded) => {
+            let der = STANDARD
+                .decode(encoded.trim())
+                .map_err(|e| PixelleError::Internal(format!("AUTH_SIGNING_KEY is not base64: {}", e)))?;
+            KeyRing::from_pkcs8(&der)
+        }
+        Err(_) => {
+            tracing::warn!("AUTH_SIGNING_KEY not set; signing with a generated key");
+            KeyRing::generate()
+        }
+    }
+}
+
+/// Connect to the backends `DATABASE_*` places the auth repositories on and
+/// bring them up to date
+async fn connect_database() -> anyhow::Result<Backends> {
+    let backends = Backends::connect(DatabaseConfig::from_env()?).await?;
+    let applied = MigrationRunner::new("auth", account_migrations())?.run(&backends).await?;
+    if !applied.is_empty() {
+        tracing::info!("Applied auth migrations {:?}", applied);
+    }
+    Ok(backends)
+}
+
+/// SMTP when `SMTP_HOST` is set; otherwise emails are only logged
+fn email_transport() -> Arc<dyn EmailTransport> {
+    match SmtpEmailTransport::from_env() {
+        Ok(Some(transport)) => Arc::new(transport),
+        Ok(None) => {
+            tracing::warn!("SMTP_HOST not set; account emails will only be logged");
+            Arc::new(LogEmailTransport)
+        }
+        Err(e) => {
+            tracing::error!("Invalid SMTP configuration, account emails will only be logged: {}", e);
+            Arc::new(LogEmailTransport)
+        }
+    }
+}
+
+/// Count failed logins in the Redis at `REDIS_URL`, shared by every
+/// instance; without it each instance counts its own
+fn throttle_store() -> PixelleResult<Arc<dyn ThrottleStore>> {
+    match env::var("REDIS_URL") {
+        Ok(url) => Ok(Arc::new(RedisThrottleStore::new(&url)?)),
+        Err(_) => {
+            tracing::warn!("REDIS_URL not set; failed logins are counted per instance");
+            Ok(Arc::new(InMemoryThrottleStore::new()))
+        }
+    }
+}
+
+/// Invites and referrals, reported to analytics and rewarded through the
+/// webhook when their variables are set
+fn invite_service(backends: &Backends, client: HttpClient) -> anyhow::Result<InviteService> {
+    let mut invites = InviteService::new(Arc::new(InviteStore::new(backends)?))
+        .with_event_sink(Arc::new(AnalyticsReferralEvents::from_env(client.clone())));
+    match WebhookReferralRewarder::from_env(client) {
+        Some(rewarder) => invites = invites.with_rewarder(Arc::new(rewarder)),
+        None => tracing::warn!("REFERRAL_REWARD_WEBHOOK_URL not set; referrals are tracked without rewards"),
+    }
+    Ok(invites)
+}
+
+/// Profile verification with documents in Nimbux, or `None` without it;
+/// every step is audited and badges are set on auth profiles, user-service
+/// profiles and in analytics
+fn verification_service(backends: &Backends, client: HttpClient, audit: Arc<AuditLog>) -> anyhow::Result<Option<VerificationService>> {
+    let Some(storage) = NimbuxDocumentStorage::from_env(client.clone()) else {
+        tracing::warn!("NIMBUX_API_URL or NIMBUX_USER_ID not set; profile verification is disabled");
+        return Ok(None);
+    };
+    let mut verifications = VerificationService::new(Arc::new(VerificationStore::new(backends)?), Arc::new(storage))
+        .with_event_sink(audit)
+        .with_event_sink(Arc::new(AccountStore::new(backends)?))
+        .with_event_sink(Arc::new(AnalyticsVerificationEvents::from_env(client.clone())));
+    let auth_service_url = env::var("AUTH_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8084".to_string());
+    match ServiceCredentials::from_env(&auth_service_url) {
+        Some(credentials) => {
+            let user_service_url = env::var("USER_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8081".to_string());
+            let badges = UserServiceVerifiedBadges::new(client, &user_service_url, credentials);
+            verifications = verifications.with_event_sink(Arc::new(badges));
+        }
+        None => tracing::warn!("AUTH_CLIENT_ID not set; verified badges are not copied to user-service"),
+    }
+    Ok(Some(verifications))
+}
+
+/// Users made admins at startup, from comma-separated `AUTH_ADMIN_USER_IDS`,
+/// so a new deployment has someone who can assign roles
+fn admin_user_ids() -> Vec<UserId> {
+    env::var("AUTH_ADMIN_USER_IDS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .filter_map(|id| match id.parse() {
+            Ok(user_id) => Some(user_id),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid user ID {} in AUTH_ADMIN_USER_IDS", id);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Rotate the signing key on schedule, drop expired keys, revocations and
+/// attempt counts, and pick up revocations other instances recorded
+fn spawn_key_rotation(
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+    challenges: web::Data<ChallengeGate>,
+) {
+    tokio::spawn(async move {
+        let rotation = Duration::from_secs(SIGNING_KEY_ROTATION_HOURS as u64 * 3600);
+        let mut prune = tokio::time::interval(Duration::from_secs(60));
+        let mut rotate = tokio::time::interval_at(tokio::time::Instant::now() + rotation, rotation);
+        loop {
+            tokio::select! {
+                _ = rotate.tick() => match jwt_service.keys().rotate() {
+                    Ok(kid) => tracing::info!("Rotated signing key, {} signs next", kid),
+                    Err(e) => tracing::error!("Signing key rotation failed: {}", e),
+                },
+                _ = prune.tick() => {
+                    let now = chrono::Utc::now();
+                    jwt_service.keys().prune(now);
+                    challenges.prune(now);
+                    if let Err(e) = auth_service.prune_revocations().await {
+                        tracing::warn!("Failed to prune token revocations: {}", e);
+                    }
+                    if let Err(e) = auth_service.refresh_revocations().await {
+                        tracing::error!("Token revocations are stale: {}", e);
+                    }
+                }
+            }
+        }
+    });
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+fn unauthorized(e: PixelleError) -> HttpResponse {
+    HttpResponse::Unauthorized().json(serde_json::json!({ "error": e.to_string() }))
+}
+
+fn error_response(e: PixelleError) -> HttpResponse {
+    let status = StatusCode::from_u16(e.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    HttpResponse::build(status).json(serde_json::json!({ "error": e.to_string() }))
+}
+
+fn challenge_error(e: ChallengeError) -> HttpResponse {
+    let status = StatusCode::from_u16(e.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = HttpResponse::build(status);
+    if let Some(seconds) = e.retry_after() {
+        response.insert_header(("Retry-After", seconds.to_string()));
+    }
+    response.json(e.body())
+}
+
+fn client_ip(req: &HttpRequest) -> Option<String> {
+    req.connection_info().realip_remote_addr().map(str::to_string)
+}
+
+fn auth_attempt(req: &HttpRequest, challenge_token: Option<String>) -> AuthAttempt {
+    AuthAttempt {
+        ip: client_ip(req),
+        challenge_token,
+        ..AuthAttempt::default()
+    }
+}
+
+/// Device details clients send when signing in; the fingerprint comes from
+/// `X-Device-Fingerprint`
+fn session_device(req: &HttpRequest) -> SessionDevice {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    SessionDevice {
+        fingerprint: header("X-Device-Fingerprint"),
+        user_agent: header("User-Agent"),
+    }
+}
+
+/// Claims of the presented bearer token
+fn access_claims(req: &HttpRequest, jwt_service: &JwtService) -> PixelleResult<AccessClaims> {
+    let token = bearer_token(req).ok_or_else(|| PixelleError::Authentication("Missing bearer token".to_string()))?;
+    jwt_service.verify(token)
+}
+
+#[derive(Deserialize)]
+struct RegisterRequest {
+    #[serde(flatten)]
+    registration: Registration,
+    challenge_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    /// Username or email
+    username: String,
+    password: String,
+    challenge_token: Option<String>,
+}
+
+/// Create an account, email it a verification link and sign it in
+async fn register(req: HttpRequest, body: web::Json<RegisterRequest>, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    let RegisterRequest { registration, challenge_token } = body.into_inner();
+    let user = match auth_service.register(&registration, &auth_attempt(&req, challenge_token)).await {
+        Ok(user) => user,
+        Err(e) => return challenge_error(e),
+    };
+    match auth_service.sign_in(user.id, session_device(&req), client_ip(&req)).await {
+        Ok(access_token) => HttpResponse::Created().json(serde_json::json!({
+            "user": user,
+            "access_token": access_token,
+            "email_verified": false,
+        })),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn login(req: HttpRequest, body: web::Json<LoginRequest>, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    let LoginRequest { username, password, challenge_token } = body.into_inner();
+    let user = match auth_service.login(&username, &password, &auth_attempt(&req, challenge_token)).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return unauthorized(PixelleError::Authentication("Invalid username or password".to_string())),
+        Err(e) => return challenge_error(e),
+    };
+    let email_verified = match auth_service.is_email_verified(&user).await {
+        Ok(verified) => verified,
+        Err(e) => return error_response(e),
+    };
+    match auth_service.sign_in(user.id, session_device(&req), client_ip(&req)).await {
+        Ok(access_token) => HttpResponse::Ok().json(serde_json::json!({
+            "user": user,
+            "access_token": access_token,
+            "email_verified": email_verified,
+        })),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct PasswordResetRequest {
+    email: String,
+    challenge_token: Option<String>,
+}
+
+/// Email a reset link; answers `202` whether or not an account has the email
+async fn request_password_reset(
+    req: HttpRequest,
+    body: web::Json<PasswordResetRequest>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let PasswordResetRequest { email, challenge_token } = body.into_inner();
+    match auth_service.request_password_reset(&email, &auth_attempt(&req, challenge_token)).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => challenge_error(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct ConfirmPasswordResetRequest {
+    token: String,
+    new_password: String,
+}
+
+/// Set a new password with the token from a reset link
+async fn confirm_password_reset(body: web::Json<ConfirmPasswordResetRequest>, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    match auth_service.reset_password(&body.token, &body.new_password).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct VerifyEmailRequest {
+    token: String,
+}
+
+/// Verify an email with the token from a verification link
+async fn verify_email(body: web::Json<VerifyEmailRequest>, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    match auth_service.verify_email(&body.token).await {
+        Ok(user_id) => HttpResponse::Ok().json(serde_json::json!({ "user_id": user_id, "email_verified": true })),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct UnlockRequest {
+    token: String,
+}
+
+/// Lift a login lockout with the token from an unlock email
+async fn unlock_account(body: web::Json<UnlockRequest>, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    match auth_service.unlock_account(&body.token).await {
+        Ok(user_id) => HttpResponse::Ok().json(serde_json::json!({ "user_id": user_id, "unlocked": true })),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Send the presented token's user another verification link
+async fn resend_email_verification(
+    req: HttpRequest,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.resend_email_verification(user_id).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn jwks(jwt_service: web::Data<JwtService>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=60"))
+        .json(jwt_service.keys().jwks())
+}
+
+/// Revocations recorded by any instance of this region
+async fn revocations(auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    match auth_service.refresh_revocations().await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Provider and site key clients render challenge widgets with
+async fn challenge(challenges: web::Data<ChallengeGate>) -> HttpResponse {
+    let config = challenges.config();
+    HttpResponse::Ok().json(serde_json::json!({
+        "enabled": challenges.is_enabled(),
+        "provider": config.provider,
+        "site_key": config.site_key,
+    }))
+}
+
+/// Revoke the session of the presented token
+async fn logout(req: HttpRequest, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    let Some(token) = bearer_token(&req) else {
+        return unauthorized(PixelleError::Authentication("Missing bearer token".to_string()));
+    };
+    match auth_service.revoke_session(token).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e @ PixelleError::Authentication(_)) => unauthorized(e),
+        Err(e) => error_response(e),
+    }
+}
+
+/// End every session of the presented token's user and revoke the tokens
+/// issued to it so far
+async fn logout_everywhere(
+    req: HttpRequest,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let claims = match access_claims(&req, &jwt_service) {
+        Ok(claims) => claims,
+        Err(e) => return unauthorized(e),
+    };
+    let user_id = match claims.user_id() {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.revoke_all_sessions(user_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Signed-in devices of the presented token's user, most recently used
+/// first, marking the one making the request
+async fn list_sessions(
+    req: HttpRequest,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let claims = match access_claims(&req, &jwt_service) {
+        Ok(claims) => claims,
+        Err(e) => return unauthorized(e),
+    };
+    let user_id = match claims.user_id() {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.session_seen(&claims.sid, client_ip(&req).as_deref()).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return unauthorized(PixelleError::Authentication("Session has ended".to_string())),
+        Err(e) => return error_response(e),
+    }
+
+    match auth_service.list_sessions(user_id).await {
+        Ok(sessions) => {
+            let sessions: Vec<_> = sessions
+                .into_iter()
+                .map(|session| {
+                    serde_json::json!({
+                        "id": session.id,
+                        "device": session.device,
+                        "ip": session.ip,
+                        "created_at": session.created_at,
+                        "last_seen_at": session.last_seen(),
+                        "expires_at": session.expires_at,
+                        "current": session.id == claims.sid,
+                    })
+                })
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({ "sessions": sessions }))
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+/// Sign one of the presented token's user's devices out
+async fn revoke_session(
+    req: HttpRequest,
+    path: web::Path<String>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let claims = match access_claims(&req, &jwt_service) {
+        Ok(claims) => claims,
+        Err(e) => return unauthorized(e),
+    };
+    let user_id = match claims.user_id() {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.revoke_user_session(user_id, &path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    grant_type: String,
+    client_id: String,
+    client_secret: String,
+    /// Space-separated scopes; all of the client's when absent
+    scope: Option<String>,
+}
+
+/// Client-credentials grant, for internal services calling each other
+async fn token(body: web::Form<TokenRequest>, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    if body.grant_type != "client_credentials" {
+        return error_response(PixelleError::Validation(format!("Unsupported grant type {}", body.grant_type)));
+    }
+    match auth_service.issue_service_token(&body.client_id, &body.client_secret, body.scope.as_deref()) {
+        Ok((access_token, scopes)) => HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(serde_json::json!({
+                "access_token": access_token,
+                "token_type": "Bearer",
+                "expires_in": SERVICE_TOKEN_TTL_MINUTES * 60,
+                "scope": scopes.join(" "),
+            })),
+        Err(e @ PixelleError::Authentication(_)) => unauthorized(e),
+        Err(e) => error_response(e),
+    }
+}
+
+/// An API key as shown to its owner; the secret is only in the creation response
+fn api_key_json(key: &ApiKey) -> serde_json::Value {
+    serde_json::json!({
+        "id": key.id,
+        "name": key.name,
+        "scopes": key.scopes,
+        "rate_limit_per_minute": key.rate_limit_per_minute,
+        "created_at": key.created_at,
+        "expires_at": key.expires_at,
+        "last_used_at": key.last_used_at,
+    })
+}
+
+/// Create an API key for the presented token's user
+async fn create_api_key(
+    req: HttpRequest,
+    body: web::Json<NewApiKey>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.create_api_key(user_id, body.into_inner()).await {
+        Ok(issued) => {
+            let mut key = api_key_json(&issued.key);
+            key["key"] = serde_json::Value::String(issued.secret);
+            HttpResponse::Created().json(key)
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+async fn list_api_keys(
+    req: HttpRequest,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.list_api_keys(user_id).await {
+        Ok(keys) => HttpResponse::Ok().json(serde_json::json!({
+            "api_keys": keys.iter().map(api_key_json).collect::<Vec<_>>(),
+        })),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn revoke_api_key(
+    req: HttpRequest,
+    path: web::Path<String>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.revoke_api_key(user_id, &path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct VerifyApiKeyRequest {
+    key: String,
+}
+
+/// Grant of an API key, looked up by services' `TokenValidator`; 404 when
+/// the key is unknown here, expired or revoked
+async fn verify_api_key(body: web::Json<VerifyApiKeyRequest>, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    match auth_service.verify_api_key(&body.key).await {
+        Ok(Some(grant)) => HttpResponse::Ok().json(grant),
+        Ok(None) => error_response(PixelleError::NotFound("Unknown API key".to_string())),
+        Err(e) => error_response(e),
+    }
+}
+
+/// The caller of the presented access token, as the role policy sees it
+fn access_subject(req: &HttpRequest, jwt_service: &JwtService) -> PixelleResult<Subject> {
+    let claims = access_claims(req, jwt_service)?;
+    Ok(Subject::new(claims.user_id()?, &claims.roles))
+}
+
+/// Every role and the permissions it grants
+async fn list_roles() -> HttpResponse {
+    let roles: Vec<_> = Role::ALL
+        .iter()
+        .map(|role| serde_json::json!({ "role": role, "permissions": role.permissions() }))
+        .collect();
+    HttpResponse::Ok().json(serde_json::json!({ "roles": roles, "default": Role::DEFAULT }))
+}
+
+/// Users assigned a role; needs `roles:manage`
+async fn list_role_members(
+    req: HttpRequest,
+    path: web::Path<String>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    if let Err(e) = RolePolicy.evaluate(&subject, Permission::ManageRoles, None).into_result() {
+        return error_response(e);
+    }
+    let role = match path.parse::<Role>() {
+        Ok(role) => role,
+        Err(e) => return error_response(e),
+    };
+    match auth_service.role_members(role).await {
+        Ok(members) => HttpResponse::Ok().json(serde_json::json!({ "role": role, "members": members })),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Roles of a user, for the user themselves or a caller with `roles:manage`
+async fn get_user_roles(
+    req: HttpRequest,
+    path: web::Path<String>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    let Ok(user_id) = path.parse::<UserId>() else {
+        return error_response(PixelleError::Validation(format!("Invalid user ID {}", path)));
+    };
+    if user_id != subject.user_id {
+        if let Err(e) = RolePolicy.evaluate(&subject, Permission::ManageRoles, None).into_result() {
+            return error_response(e);
+        }
+    }
+    match auth_service.get_roles(user_id).await {
+        Ok(roles) => HttpResponse::Ok().json(serde_json::json!({ "user_id": user_id, "roles": roles })),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct AssignRolesRequest {
+    roles: Vec<Role>,
+}
+
+/// Replace the roles of a user; needs `roles:manage`
+async fn assign_user_roles(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<AssignRolesRequest>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    let Ok(user_id) = path.parse::<UserId>() else {
+        return error_response(PixelleError::Validation(format!("Invalid user ID {}", path)));
+    };
+    match auth_service.assign_roles(&subject, user_id, body.into_inner().roles).await {
+        Ok(assignment) => HttpResponse::Ok().json(assignment),
+        Err(e) => error_response(e),
+    }
+}
+
+/// An invite with the link to share it by
+fn invite_json(invite: &Invite, auth_service: &AuthServiceImpl) -> serde_json::Value {
+    let app_url = auth_service.email_outbox().config().app_url.trim_end_matches('/');
+    serde_json::json!({
+        "code": invite.code,
+        "link": format!("{}/invite/{}", app_url, invite.code),
+        "max_uses": invite.max_uses,
+        "uses": invite.uses,
+        "created_at": invite.created_at,
+        "expires_at": invite.expires_at,
+        "revoked_at": invite.revoked_at,
+    })
+}
+
+#[derive(Deserialize)]
+struct CreateInviteRequest {
+    max_uses: Option<u32>,
+}
+
+/// Create an invite for the presented token's user, within their quota
+async fn create_invite(
+    req: HttpRequest,
+    body: Option<web::Json<CreateInviteRequest>>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    let max_uses = body.and_then(|body| body.max_uses);
+    match auth_service.invites().create_invite(user_id, max_uses).await {
+        Ok(invite) => HttpResponse::Created().json(invite_json(&invite, &auth_service)),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn list_invites(
+    req: HttpRequest,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.invites().list_invites(user_id).await {
+        Ok(invites) => HttpResponse::Ok().json(serde_json::json!({
+            "invites": invites.iter().map(|invite| invite_json(invite, &auth_service)).collect::<Vec<_>>(),
+        })),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Whether a code can be signed up with, for the signup page; public
+async fn get_invite(path: web::Path<String>, auth_service: web::Data<AuthServiceImpl>) -> HttpResponse {
+    match auth_service.invites().invite(&path).await {
+        Ok(invite) => HttpResponse::Ok().json(serde_json::json!({
+            "code": invite.code,
+            "inviter_id": invite.inviter_id,
+            "expires_at": invite.expires_at,
+        })),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Revoke an invite of the caller, or any invite with `users:suspend`
+async fn revoke_invite(
+    req: HttpRequest,
+    path: web::Path<String>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    match auth_service.invites().revoke_invite(&subject, &path).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn referrals_response(auth_service: &AuthServiceImpl, inviter_id: UserId) -> HttpResponse {
+    match auth_service.invites().list_referrals(inviter_id).await {
+        Ok(referrals) => HttpResponse::Ok().json(serde_json::json!({ "referrals": referrals })),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Signups attributed to the caller's invites
+async fn list_referrals(
+    req: HttpRequest,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => referrals_response(&auth_service, user_id).await,
+        Err(e) => unauthorized(e),
+    }
+}
+
+/// Signups attributed to a user's invites, for abuse review; needs
+/// `users:suspend`
+async fn list_user_referrals(
+    req: HttpRequest,
+    path: web::Path<String>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    if let Err(e) = RolePolicy.evaluate(&subject, Permission::SuspendUsers, None).into_result() {
+        return error_response(e);
+    }
+    let Ok(user_id) = path.parse::<UserId>() else {
+        return error_response(PixelleError::Validation(format!("Invalid user ID {}", path)));
+    };
+    referrals_response(&auth_service, user_id).await
+}
+
+#[derive(Deserialize)]
+struct ReviewReferralRequest {
+    approve: bool,
+}
+
+/// Approve or reject a flagged referral; needs `users:suspend`
+async fn review_referral(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<ReviewReferralRequest>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    let Ok(invitee_id) = path.parse::<UserId>() else {
+        return error_response(PixelleError::Validation(format!("Invalid user ID {}", path)));
+    };
+    match auth_service.invites().review(&subject, invitee_id, body.approve).await {
+        Ok(referral) => HttpResponse::Ok().json(referral),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Audit entries, newest first, filtered by `user_id`, `actor_id`, `action`
+/// and `since`/`until` and paged with `limit` and `offset`; needs `audit:view`
+async fn query_audit_log(
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    if let Err(e) = RolePolicy.evaluate(&subject, Permission::ViewAuditLog, None).into_result() {
+        return error_response(e);
+    }
+    match auth_service.audit_log().query(&query).await {
+        Ok(entries) => HttpResponse::Ok().json(serde_json::json!({ "entries": entries })),
+        Err(e) => error_response(e),
+    }
+}
+
+/// The same entries as analytics events, ready to post to analytics-service's
+/// events endpoint, e.g. to backfill it; needs `audit:view`
+async fn export_audit_log(
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+    jwt_service: web::Data<JwtService>,
+    auth_service: web::Data<AuthServiceImpl>,
+) -> HttpResponse {
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    if let Err(e) = RolePolicy.evaluate(&subject, Permission::ViewAuditLog, None).into_result() {
+        return error_response(e);
+    }
+    match auth_service.audit_log().query(&query).await {
+        Ok(entries) => HttpResponse::Ok().json(entries.iter().map(AnalyticsEvent::from).collect::<Vec<_>>()),
+        Err(e) => error_response(e),
+    }
+}
+
+fn verification_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": "Profile verification is not configured" }))
+}
+
+/// The caller's latest verification request, or `null`
+async fn get_verification(
+    req: HttpRequest,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match verifications.status(user_id).await {
+        Ok(request) => HttpResponse::Ok().json(serde_json::json!({ "verification": request })),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct RequestVerificationRequest {
+    note: Option<String>,
+}
+
+/// Open a verification request for the caller
+async fn request_verification(
+    req: HttpRequest,
+    body: Option<web::Json<RequestVerificationRequest>>,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    let note = body.and_then(|body| body.into_inner().note);
+    match verifications.request(user_id, note).await {
+        Ok(request) => HttpResponse::Created().json(request),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct AddDocumentRequest {
+    kind: DocumentKind,
+    content_type: String,
+}
+
+/// Attach a document to the caller's open request; the response holds the
+/// presigned URL to upload its file to
+async fn add_verification_document(
+    req: HttpRequest,
+    body: web::Json<AddDocumentRequest>,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match verifications.add_document(user_id, body.kind, &body.content_type).await {
+        Ok(upload) => HttpResponse::Created().json(upload),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Send the caller's request to the review queue
+async fn submit_verification(
+    req: HttpRequest,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let user_id = match access_claims(&req, &jwt_service).and_then(|claims| claims.user_id()) {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(e),
+    };
+    match verifications.submit(user_id).await {
+        Ok(request) => HttpResponse::Ok().json(request),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct VerificationQueueQuery {
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+/// Submitted requests, oldest first, paged with `limit` (at most 200) and
+/// `offset`; needs `verifications:review`
+async fn verification_queue(
+    req: HttpRequest,
+    query: web::Query<VerificationQueueQuery>,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    let limit = query.limit.unwrap_or(50).min(200);
+    match verifications.queue(&subject, limit, query.offset).await {
+        Ok(requests) => HttpResponse::Ok().json(serde_json::json!({ "verifications": requests })),
+        Err(e) => error_response(e),
+    }
+}
+
+/// A user's request, for review; needs `verifications:review`
+async fn review_verification(
+    req: HttpRequest,
+    path: web::Path<String>,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    let Ok(user_id) = path.parse::<UserId>() else {
+        return error_response(PixelleError::Validation(format!("Invalid user ID {}", path)));
+    };
+    match verifications.review_request(&subject, user_id).await {
+        Ok(request) => HttpResponse::Ok().json(request),
+        Err(e) => error_response(e),
+    }
+}
+
+/// A short-lived presigned URL to view a document; needs
+/// `verifications:review`, and every view is audited
+async fn verification_document(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    let (user_id, document_id) = path.into_inner();
+    let Ok(user_id) = user_id.parse::<UserId>() else {
+        return error_response(PixelleError::Validation(format!("Invalid user ID {}", user_id)));
+    };
+    match verifications.document_url(&subject, user_id, &document_id).await {
+        Ok(url) => HttpResponse::Ok().json(url),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Approve or reject a submitted request, e.g.
+/// `{"decision": "reject", "reason": "document_expired"}`; needs
+/// `verifications:review`
+async fn decide_verification(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<VerificationDecision>,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    let Ok(user_id) = path.parse::<UserId>() else {
+        return error_response(PixelleError::Validation(format!("Invalid user ID {}", path)));
+    };
+    match verifications.decide(&subject, user_id, body.into_inner()).await {
+        Ok(request) => HttpResponse::Ok().json(request),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct RevokeVerificationRequest {
+    reason: VerificationReason,
+    note: Option<String>,
+}
+
+/// Take the verified badge back; needs `verifications:review`
+async fn revoke_verification(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<RevokeVerificationRequest>,
+    jwt_service: web::Data<JwtService>,
+    verifications: Option<web::Data<VerificationService>>,
+) -> HttpResponse {
+    let Some(verifications) = verifications else {
+        return verification_unavailable();
+    };
+    let subject = match access_subject(&req, &jwt_service) {
+        Ok(subject) => subject,
+        Err(e) => return unauthorized(e),
+    };
+    let Ok(user_id) = path.parse::<UserId>() else {
+        return error_response(PixelleError::Validation(format!("Invalid user ID {}", path)));
+    };
+    let RevokeVerificationRequest { reason, note } = body.into_inner();
+    match verifications.revoke(&subject, user_id, reason, note).await {
+        Ok(request) => HttpResponse::Ok().json(request),
+        Err(e) => error_response(e),
+    }
 }
 
-async fn health_check() -> actix_web::HttpResponse {
-    actix_web::HttpResponse::Ok().json(serde_json::json!({
+async fn health_check() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
         "status": "healthy",
         "service": "auth-service"
     }))
@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use pixelle_core::{PixelleError, PixelleResult, Referral, ReferralEvent, ReferralEventSink, ReferralRewarder};
+use pixelle_http::HttpClient;
+use reqwest::header::{HeaderName, HeaderValue};
+use std::env;
+
+/// Reports referral events to analytics-service, with the inviter as the user
+///
+/// Without `ANALYTICS_SERVICE_URL` nothing is sent.
+pub struct AnalyticsReferralEvents {
+    client: HttpClient,
+    events_url: Option<String>,
+}
+
+impl AnalyticsReferralEvents {
+    pub fn from_env(client: HttpClient) -> Self {
+        let events_url = env::var("ANALYTICS_SERVICE_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| format!("{}/api/v1/analytics/events", url.trim_end_matches('/')));
+        Self { client, events_url }
+    }
+}
+
+#[async_trait]
+impl ReferralEventSink for AnalyticsReferralEvents {
+    async fn record(&self, event: &ReferralEvent) -> PixelleResult<()> {
+        let Some(events_url) = &self.events_url else {
+            return Ok(());
+        };
+        let event = serde_json::json!({
+            "event_type": event.name(),
+            "user_id": event.inviter_id().to_string(),
+            "timestamp": chrono::Utc::now(),
+            "properties": event.properties(),
+        });
+        let response = self.client.post(events_url).json(&[event]).send().await?;
+        if !response.status().is_success() {
+            return Err(PixelleError::ExternalService(format!(
+                "Analytics rejected a referral event: {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Issues referral rewards by posting qualified referrals to
+/// `REFERRAL_REWARD_WEBHOOK_URL`, e.g. a billing service granting credit
+///
+/// Requests carry the invitee's ID as `Idempotency-Key`, since failed
+/// rewards are posted again. `None` without the variable.
+pub struct WebhookReferralRewarder {
+    client: HttpClient,
+    url: String,
+}
+
+impl WebhookReferralRewarder {
+    pub fn from_env(client: HttpClient) -> Option<Self> {
+        let url = env::var("REFERRAL_REWARD_WEBHOOK_URL").ok().filter(|url| !url.is_empty())?;
+        Some(Self { client, url })
+    }
+}
+
+#[async_trait]
+impl ReferralRewarder for WebhookReferralRewarder {
+    async fn issue(&self, referral: &Referral) -> PixelleResult<()> {
+        let key = HeaderValue::from_str(&format!("referral-{}", referral.invitee_id))
+            .map_err(|e| PixelleError::Internal(e.to_string()))?;
+        let response = self
+            .client
+            .post(&self.url)
+            .idempotent(true)
+            .header(HeaderName::from_static("idempotency-key"), key)
+            .json(referral)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(PixelleError::ExternalService(format!(
+                "Reward webhook answered {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
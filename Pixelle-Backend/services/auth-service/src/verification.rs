@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+use pixelle_auth::ServiceCredentials;
+use pixelle_core::{
+    PixelleError, PixelleResult, PresignedUrl, VerificationDocumentStorage, VerificationEvent, VerificationEventSink,
+};
+use pixelle_http::HttpClient;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use serde::Deserialize;
+use std::env;
+use std::time::Duration;
+
+/// Response envelope of the Nimbux API
+#[derive(Deserialize)]
+struct NimbuxResponse<T> {
+    data: Option<T>,
+    error: Option<String>,
+}
+
+/// Verification documents in a Nimbux bucket, presigned through the Nimbux
+/// API on behalf of one Nimbux user
+///
+/// The bucket should be private to that user, with a lifecycle rule
+/// expiring objects after `VERIFICATION_DOCUMENT_RETENTION_DAYS` in case a
+/// purge is missed.
+pub struct NimbuxDocumentStorage {
+    client: HttpClient,
+    objects_url: String,
+    user_id: String,
+}
+
+impl NimbuxDocumentStorage {
+    /// Storage from `NIMBUX_API_URL`, `NIMBUX_USER_ID` and
+    /// `NIMBUX_VERIFICATION_BUCKET` (by default `pixelle-verification`), or
+    /// `None` without the first two
+    pub fn from_env(client: HttpClient) -> Option<Self> {
+        let api_url = env::var("NIMBUX_API_URL").ok().filter(|url| !url.is_empty())?;
+        let user_id = env::var("NIMBUX_USER_ID").ok().filter(|id| !id.is_empty())?;
+        let bucket = env::var("NIMBUX_VERIFICATION_BUCKET").unwrap_or_else(|_| "pixelle-verification".to_string());
+        Some(Self {
+            client,
+            objects_url: format!("{}/api/v1/buckets/{}/objects", api_url.trim_end_matches('/'), bucket),
+            user_id,
+        })
+    }
+
+    /// Object URL; keys are one path segment in the Nimbux API
+    fn object_url(&self, key: &str) -> String {
+        format!("{}/{}", self.objects_url, key.replace('/', "%2F"))
+    }
+
+    async fn presign(&self, key: &str, method: &str, content_type: Option<&str>, ttl: Duration) -> PixelleResult<PresignedUrl> {
+        let response = self
+            .client
+            .post(&format!("{}/presign", self.object_url(key)))
+            .json(&serde_json::json!({
+                "user_id": self.user_id,
+                "method": method,
+                "expires_in_secs": ttl.as_secs(),
+                "content_type": content_type,
+            }))
+            .send()
+            .await?;
+        let status = response.status();
+        let body: NimbuxResponse<PresignedUrl> = response
+            .json()
+            .await
+            .map_err(|e| PixelleError::ExternalService(format!("Invalid presign response from Nimbux: {}", e)))?;
+        match body.data {
+            Some(url) if status.is_success() => Ok(url),
+            _ => Err(PixelleError::ExternalService(format!(
+                "Nimbux refused to presign {} {}: {}",
+                method,
+                key,
+                body.error.unwrap_or_else(|| status.to_string())
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl VerificationDocumentStorage for NimbuxDocumentStorage {
+    async fn presign_upload(&self, key: &str, content_type: &str, ttl: Duration) -> PixelleResult<PresignedUrl> {
+        self.presign(key, "PUT", Some(content_type), ttl).await
+    }
+
+    async fn presign_download(&self, key: &str, ttl: Duration) -> PixelleResult<PresignedUrl> {
+        self.presign(key, "GET", None, ttl).await
+    }
+
+    async fn delete(&self, key: &str) -> PixelleResult<()> {
+        let response = self.client.delete(&self.object_url(key)).idempotent(true).send().await?;
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            return Err(PixelleError::ExternalService(format!(
+                "Nimbux answered {} deleting {}",
+                response.status(),
+                key
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Copies verified badges to the profiles of user-service, which serve
+/// profile pages and user search
+///
+/// Calls carry a service token with `users:verify`.
+pub struct UserServiceVerifiedBadges {
+    client: HttpClient,
+    users_url: String,
+    credentials: ServiceCredentials,
+}
+
+impl UserServiceVerifiedBadges {
+    pub fn new(client: HttpClient, user_service_url: &str, credentials: ServiceCredentials) -> Self {
+        Self {
+            client,
+            users_url: format!("{}/api/v1/users", user_service_url.trim_end_matches('/')),
+            credentials: credentials.with_scopes(&["users:verify"]),
+        }
+    }
+}
+
+#[async_trait]
+impl VerificationEventSink for UserServiceVerifiedBadges {
+    async fn record(&self, event: &VerificationEvent) -> PixelleResult<()> {
+        let Some(verified) = event.verified() else {
+            return Ok(());
+        };
+        let user_id = event.request().user_id;
+        let authorization = HeaderValue::from_str(&self.credentials.authorization().await?)
+            .map_err(|e| PixelleError::Internal(format!("Invalid service token: {}", e)))?;
+        let response = self
+            .client
+            .put(&format!("{}/{}/verified", self.users_url, user_id))
+            .idempotent(true)
+            .header(AUTHORIZATION, authorization)
+            .json(&serde_json::json!({ "verified": verified }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(PixelleError::ExternalService(format!(
+                "user-service answered {} setting the badge of {}",
+                response.status(),
+                user_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Reports verification events to analytics-service, without the user's
+/// note or documents
+///
+/// Without `ANALYTICS_SERVICE_URL` nothing is sent.
+pub struct AnalyticsVerificationEvents {
+    client: HttpClient,
+    events_url: Option<String>,
+}
+
+impl AnalyticsVerificationEvents {
+    pub fn from_env(client: HttpClient) -> Self {
+        let events_url = env::var("ANALYTICS_SERVICE_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| format!("{}/api/v1/analytics/events", url.trim_end_matches('/')));
+        Self { client, events_url }
+    }
+}
+
+#[async_trait]
+impl VerificationEventSink for AnalyticsVerificationEvents {
+    async fn record(&self, event: &VerificationEvent) -> PixelleResult<()> {
+        let Some(events_url) = &self.events_url else {
+            return Ok(());
+        };
+        let event = serde_json::json!({
+            "event_type": event.name(),
+            "user_id": event.request().user_id.to_string(),
+            "timestamp": chrono::Utc::now(),
+            "properties": event.properties(),
+        });
+        let response = self.client.post(events_url).json(&[event]).send().await?;
+        if !response.status().is_success() {
+            return Err(PixelleError::ExternalService(format!(
+                "Analytics rejected a verification event: {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,13 @@
 use actix_web::{web, App, HttpServer};
-use pixelle_monitoring::init_tracing;
+use pixelle_monitoring::{init_logging, LoggingConfig, RequestCorrelation};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    init_tracing();
+    let log_export = init_logging(LoggingConfig::from_env("cache-service", env!("CARGO_PKG_VERSION")));
     
-    HttpServer::new(|| {
+    let result = HttpServer::new(|| {
         App::new()
+            .wrap(RequestCorrelation)
             .service(
                 web::scope("/health")
                     .service(health_check)
@@ -14,7 +15,13 @@ async fn main() -> std::io::Result<()> {
     })
     .bind("0.0.0.0:8080")?
     .run()
-    .await
+    .await;
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
 }
 
 async fn health_check() -> actix_web::HttpResponse {
@@ -0,0 +1,396 @@
+use actix_web::{web, HttpResponse, Result};
+use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use pixelle_auth::AuthenticatedUser;
+use pixelle_core::{ApiResponse, HashtagService, Id, PixelleError, SagaOrchestrator, UserId};
+use crate::service::{ContentService, CreatedPost};
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePostRequest {
+    pub author_id: UserId,
+    pub content: String,
+    #[serde(default)]
+    pub media_urls: Vec<String>,
+    #[serde(default = "default_is_public")]
+    pub is_public: bool,
+    /// Publish at this time instead of immediately
+    pub publish_at: Option<DateTime<Utc>>,
+    /// Draft to discard once the post is published
+    pub draft_id: Option<Id>,
+}
+
+fn default_is_public() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveDraftRequest {
+    pub author_id: UserId,
+    /// Omit on the first save to create a new draft
+    pub draft_id: Option<Id>,
+    pub content: String,
+    #[serde(default)]
+    pub media_urls: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorQuery {
+    pub author_id: UserId,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LimitQuery {
+    pub limit: Option<usize>,
+}
+
+impl LimitQuery {
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(10).clamp(1, 50)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockHashtagRequest {
+    pub reason: String,
+}
+
+pub async fn create_post(
+    content_service: web::Data<ContentService>,
+    request: web::Json<CreatePostRequest>,
+) -> Result<HttpResponse> {
+    match content_service.create_post(&request.into_inner()).await {
+        Ok(CreatedPost::Published(post)) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(post),
+            error: None,
+            message: Some("Post published successfully".to_string()),
+        })),
+        Ok(CreatedPost::Scheduled(scheduled)) => Ok(HttpResponse::Accepted().json(ApiResponse {
+            success: true,
+            data: Some(scheduled),
+            error: None,
+            message: Some("Post scheduled successfully".to_string()),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn list_scheduled_posts(
+    content_service: web::Data<ContentService>,
+    path: web::Path<UserId>,
+) -> Result<HttpResponse> {
+    match content_service.list_scheduled(path.into_inner()).await {
+        Ok(scheduled) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(scheduled),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn cancel_scheduled_post(
+    content_service: web::Data<ContentService>,
+    path: web::Path<Id>,
+    query: web::Query<AuthorQuery>,
+) -> Result<HttpResponse> {
+    match content_service.cancel_scheduled(path.into_inner(), query.author_id).await {
+        Ok(scheduled) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(scheduled),
+            error: None,
+            message: Some("Scheduled post cancelled".to_string()),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn save_draft(
+    content_service: web::Data<ContentService>,
+    request: web::Json<SaveDraftRequest>,
+) -> Result<HttpResponse> {
+    match content_service.save_draft(&request.into_inner()).await {
+        Ok(draft) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(draft),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn list_drafts(
+    content_service: web::Data<ContentService>,
+    path: web::Path<UserId>,
+) -> Result<HttpResponse> {
+    match content_service.list_drafts(path.into_inner()).await {
+        Ok(drafts) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(drafts),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn get_draft_revisions(
+    content_service: web::Data<ContentService>,
+    path: web::Path<Id>,
+    query: web::Query<AuthorQuery>,
+) -> Result<HttpResponse> {
+    match content_service.get_draft_revisions(path.into_inner(), query.author_id).await {
+        Ok(revisions) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(revisions),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn restore_draft_revision(
+    content_service: web::Data<ContentService>,
+    path: web::Path<(Id, u32)>,
+    query: web::Query<AuthorQuery>,
+) -> Result<HttpResponse> {
+    let (draft_id, revision) = path.into_inner();
+    match content_service.restore_draft_revision(draft_id, query.author_id, revision).await {
+        Ok(draft) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(draft),
+            error: None,
+            message: Some(format!("Restored revision {}", revision)),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn delete_draft(
+    content_service: web::Data<ContentService>,
+    path: web::Path<Id>,
+    query: web::Query<AuthorQuery>,
+) -> Result<HttpResponse> {
+    match content_service.delete_draft(path.into_inner(), query.author_id).await {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn get_hashtag(
+    hashtags: web::Data<HashtagService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    match hashtags.get(&path.into_inner()).await {
+        Ok(hashtag) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(hashtag),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn get_related_hashtags(
+    hashtags: web::Data<HashtagService>,
+    path: web::Path<String>,
+    query: web::Query<LimitQuery>,
+) -> Result<HttpResponse> {
+    match hashtags.related(&path.into_inner(), query.limit()).await {
+        Ok(related) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(related),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn list_followed_hashtags(
+    hashtags: web::Data<HashtagService>,
+    path: web::Path<UserId>,
+) -> Result<HttpResponse> {
+    match hashtags.followed(path.into_inner()).await {
+        Ok(followed) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(followed),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn follow_hashtag(
+    hashtags: web::Data<HashtagService>,
+    path: web::Path<(UserId, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, tag) = path.into_inner();
+    match hashtags.follow(user_id, &tag).await {
+        Ok(changed) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(changed),
+            error: None,
+            message: Some(if changed { "Hashtag followed" } else { "Already following" }.to_string()),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn unfollow_hashtag(
+    hashtags: web::Data<HashtagService>,
+    path: web::Path<(UserId, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, tag) = path.into_inner();
+    match hashtags.unfollow(user_id, &tag).await {
+        Ok(changed) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(changed),
+            error: None,
+            message: Some(if changed { "Hashtag unfollowed" } else { "Not following" }.to_string()),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn suggest_hashtags(
+    hashtags: web::Data<HashtagService>,
+    path: web::Path<UserId>,
+    query: web::Query<LimitQuery>,
+) -> Result<HttpResponse> {
+    match hashtags.suggestions(path.into_inner(), query.limit()).await {
+        Ok(suggestions) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(suggestions),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn list_blocked_hashtags(hashtags: web::Data<HashtagService>) -> Result<HttpResponse> {
+    match hashtags.list_blocked().await {
+        Ok(blocked) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(blocked),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn block_hashtag(
+    hashtags: web::Data<HashtagService>,
+    path: web::Path<String>,
+    request: web::Json<BlockHashtagRequest>,
+    moderator: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    match hashtags.block(&path.into_inner(), &request.reason, moderator.user_id).await {
+        Ok(changed) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(changed),
+            error: None,
+            message: Some(if changed { "Hashtag blocked" } else { "Already blocked" }.to_string()),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn unblock_hashtag(
+    hashtags: web::Data<HashtagService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    match hashtags.unblock(&path.into_inner()).await {
+        Ok(changed) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(changed),
+            error: None,
+            message: Some(if changed { "Hashtag unblocked" } else { "Not blocked" }.to_string()),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn list_stuck_sagas(sagas: web::Data<SagaOrchestrator>) -> Result<HttpResponse> {
+    match sagas.list_stuck().await {
+        Ok(stuck) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(stuck),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn get_saga(
+    sagas: web::Data<SagaOrchestrator>,
+    path: web::Path<Id>,
+) -> Result<HttpResponse> {
+    match sagas.get(path.into_inner()).await {
+        Ok(Some(saga)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(saga),
+            error: None,
+            message: None,
+        })),
+        Ok(None) => Ok(error_response(PixelleError::NotFound("Saga not found".to_string()))),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn retry_saga(
+    sagas: web::Data<SagaOrchestrator>,
+    path: web::Path<Id>,
+) -> Result<HttpResponse> {
+    match sagas.retry(path.into_inner()).await {
+        Ok(saga) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(saga),
+            error: None,
+            message: Some("Saga retried".to_string()),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn abort_saga(
+    sagas: web::Data<SagaOrchestrator>,
+    path: web::Path<Id>,
+) -> Result<HttpResponse> {
+    match sagas.abort(path.into_inner()).await {
+        Ok(saga) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(saga),
+            error: None,
+            message: Some("Saga aborted; finished steps are being compensated".to_string()),
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn health_check() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "healthy",
+        "service": "content-service",
+        "timestamp": chrono::Utc::now()
+    })))
+}
+
+fn error_response(error: PixelleError) -> HttpResponse {
+    let status = actix_web::http::StatusCode::from_u16(error.status_code())
+        .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(error.to_string()),
+        message: None,
+    })
+}
@@ -0,0 +1,114 @@
+use actix_web::{web, App, HttpServer};
+use pixelle_analytics::AnalyticsService;
+use pixelle_auth::{Authenticate, RequirePermission, TokenValidator};
+use pixelle_core::{HashtagService, Permission, InMemoryHashtagRepository, InMemorySagaRepository, SagaOrchestrator, SAGA_SWEEP_INTERVAL_SECONDS};
+use pixelle_monitoring::{init_logging, scaling_routes, LoggingConfig, RequestCorrelation, ScalingSignals, TrackScalingSignals};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod handlers;
+mod models;
+mod repository;
+mod scheduler;
+mod service;
+
+use repository::ContentRepository;
+use service::ContentService;
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    // Initialize structured logging
+    let log_export = init_logging(LoggingConfig::from_env("content-service", env!("CARGO_PKG_VERSION")));
+    
+    // Get port from environment or use default
+    let port = env::var("PORT").unwrap_or_else(|_| "8083".to_string());
+    let bind_address = format!("0.0.0.0:{}", port);
+    
+    let hashtags = Arc::new(HashtagService::new(Arc::new(InMemoryHashtagRepository::new())));
+    let content_service = Arc::new(
+        ContentService::new(ContentRepository::new(), AnalyticsService::new()).with_hashtags(hashtags.clone()),
+    );
+
+    // Publish scheduled posts in the background
+    tokio::spawn(scheduler::run_scheduler(content_service.clone(), scheduler::DEFAULT_TICK));
+
+    // Multi-service workflows; the sweeper resumes any left unfinished
+    let sagas = Arc::new(SagaOrchestrator::new(Arc::new(InMemorySagaRepository::new())));
+    sagas.clone().spawn_sweeper(Duration::from_secs(SAGA_SWEEP_INTERVAL_SECONDS));
+    
+    // Admin routes check the caller's roles, read from access tokens
+    // validated against auth-service's keys
+    let auth_service_url = env::var("AUTH_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8084".to_string());
+    let validator = Arc::new(TokenValidator::from_env(&auth_service_url));
+    if let Err(e) = validator.refresh().await {
+        tracing::warn!("Starting without signing keys, authenticated requests will fail until refresh: {}", e);
+    }
+    validator.clone().spawn_refresh();
+    
+    tracing::info!("Starting content service on {}", bind_address);
+    
+    let content_data = web::Data::from(content_service);
+    let saga_data = web::Data::from(sagas);
+    let hashtag_data = web::Data::from(hashtags);
+    
+    // Load reported to the orchestrator, see SCALING_*
+    let signals = web::Data::new(ScalingSignals::from_env("content-service"));
+    signals.clone().into_inner().spawn_sampler();
+    
+    let result = HttpServer::new(move || {
+        App::new()
+            .wrap(TrackScalingSignals::new(signals.clone()))
+            .wrap(Authenticate::new(validator.clone()))
+            .wrap(RequestCorrelation)
+            .app_data(content_data.clone())
+            .app_data(saga_data.clone())
+            .app_data(hashtag_data.clone())
+            .app_data(signals.clone())
+            .configure(scaling_routes)
+            .service(
+                web::scope("/api/v1/content")
+                    .route("/posts", web::post().to(handlers::create_post))
+                    .route("/users/{user_id}/scheduled", web::get().to(handlers::list_scheduled_posts))
+                    .route("/scheduled/{id}", web::delete().to(handlers::cancel_scheduled_post))
+                    .route("/drafts", web::put().to(handlers::save_draft))
+                    .route("/users/{user_id}/drafts", web::get().to(handlers::list_drafts))
+                    .route("/drafts/{id}", web::delete().to(handlers::delete_draft))
+                    .route("/drafts/{id}/revisions", web::get().to(handlers::get_draft_revisions))
+                    .route("/drafts/{id}/revisions/{revision}/restore", web::post().to(handlers::restore_draft_revision))
+                    .route("/hashtags/{tag}", web::get().to(handlers::get_hashtag))
+                    .route("/hashtags/{tag}/related", web::get().to(handlers::get_related_hashtags))
+                    .route("/users/{user_id}/hashtags", web::get().to(handlers::list_followed_hashtags))
+                    .route("/users/{user_id}/hashtags/suggestions", web::get().to(handlers::suggest_hashtags))
+                    .route("/users/{user_id}/hashtags/{tag}", web::put().to(handlers::follow_hashtag))
+                    .route("/users/{user_id}/hashtags/{tag}", web::delete().to(handlers::unfollow_hashtag))
+            )
+            .service(
+                web::scope("/api/v1/admin/hashtags")
+                    .wrap(RequirePermission::new(Permission::ModerateContent))
+                    .route("/blocked", web::get().to(handlers::list_blocked_hashtags))
+                    .route("/blocked/{tag}", web::put().to(handlers::block_hashtag))
+                    .route("/blocked/{tag}", web::delete().to(handlers::unblock_hashtag))
+            )
+            .service(
+                web::scope("/api/v1/admin/sagas")
+                    .route("/stuck", web::get().to(handlers::list_stuck_sagas))
+                    .route("/{id}", web::get().to(handlers::get_saga))
+                    .route("/{id}/retry", web::post().to(handlers::retry_saga))
+                    .route("/{id}/abort", web::post().to(handlers::abort_saga))
+            )
+            .service(
+                web::scope("/health")
+                    .route("", web::get().to(handlers::health_check))
+            )
+    })
+    .bind(bind_address)?
+    .run()
+    .await;
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
+}
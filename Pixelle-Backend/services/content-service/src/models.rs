@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use pixelle_core::{Id, PostId, UserId};
+
+/// Lifecycle of a post that has not been published yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleStatus {
+    Pending,
+    Published,
+    Cancelled,
+    Failed,
+}
+
+/// A post waiting for its publish-at time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledPost {
+    pub id: Id,
+    pub author_id: UserId,
+    pub content: String,
+    pub media_urls: Vec<String>,
+    pub is_public: bool,
+    pub publish_at: DateTime<Utc>,
+    pub status: ScheduleStatus,
+    /// Set once the scheduler has published the post
+    pub post_id: Option<PostId>,
+    /// Draft the schedule was created from, if any
+    pub draft_id: Option<Id>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Work-in-progress post that is auto-saved by clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Draft {
+    pub id: Id,
+    pub author_id: UserId,
+    pub content: String,
+    pub media_urls: Vec<String>,
+    /// Number of the latest revision
+    pub revision: u32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Snapshot of a draft at one auto-save
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftRevision {
+    pub draft_id: Id,
+    pub revision: u32,
+    pub content: String,
+    pub media_urls: Vec<String>,
+    pub saved_at: DateTime<Utc>,
+}
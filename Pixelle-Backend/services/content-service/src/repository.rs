@@ -0,0 +1,132 @@
+use pixelle_core::{Id, Post, PostId, PixelleError, PixelleResult, UserId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use crate::models::{Draft, DraftRevision, ScheduleStatus, ScheduledPost};
+
+/// Maximum number of revisions kept per draft; older ones are dropped
+const MAX_DRAFT_REVISIONS: usize = 50;
+
+pub struct ContentRepository {
+    posts: Mutex<HashMap<PostId, Post>>,
+    scheduled: Mutex<HashMap<Id, ScheduledPost>>,
+    drafts: Mutex<HashMap<Id, Draft>>,
+    revisions: Mutex<HashMap<Id, Vec<DraftRevision>>>,
+}
+
+impl ContentRepository {
+    pub fn new() -> Self {
+        Self {
+            posts: Mutex::new(HashMap::new()),
+            scheduled: Mutex::new(HashMap::new()),
+            drafts: Mutex::new(HashMap::new()),
+            revisions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn create_post(&self, post: &Post) -> PixelleResult<Post> {
+        let mut posts = self.posts.lock().unwrap();
+        posts.insert(post.id, post.clone());
+        Ok(post.clone())
+    }
+
+    pub fn save_scheduled(&self, scheduled: &ScheduledPost) -> PixelleResult<ScheduledPost> {
+        let mut entries = self.scheduled.lock().unwrap();
+        entries.insert(scheduled.id, scheduled.clone());
+        Ok(scheduled.clone())
+    }
+
+    pub fn get_scheduled(&self, id: Id) -> PixelleResult<Option<ScheduledPost>> {
+        let entries = self.scheduled.lock().unwrap();
+        Ok(entries.get(&id).cloned())
+    }
+
+    /// Pending schedules for an author, soonest first
+    pub fn list_scheduled(&self, author_id: UserId) -> PixelleResult<Vec<ScheduledPost>> {
+        let entries = self.scheduled.lock().unwrap();
+        let mut pending: Vec<ScheduledPost> = entries
+            .values()
+            .filter(|s| s.author_id == author_id && s.status == ScheduleStatus::Pending)
+            .cloned()
+            .collect();
+        pending.sort_by_key(|s| s.publish_at);
+        Ok(pending)
+    }
+
+    /// Pending schedules whose publish-at time has passed
+    pub fn due_scheduled(&self, now: DateTime<Utc>) -> PixelleResult<Vec<ScheduledPost>> {
+        let entries = self.scheduled.lock().unwrap();
+        let mut due: Vec<ScheduledPost> = entries
+            .values()
+            .filter(|s| s.status == ScheduleStatus::Pending && s.publish_at <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|s| s.publish_at);
+        Ok(due)
+    }
+
+    /// Move a pending schedule to a new status.
+    ///
+    /// Fails with `Conflict` if the schedule has already left the pending
+    /// state, which keeps a cancel racing the scheduler from double-publishing.
+    pub fn transition_scheduled(&self, id: Id, status: ScheduleStatus, post_id: Option<PostId>) -> PixelleResult<ScheduledPost> {
+        let mut entries = self.scheduled.lock().unwrap();
+        let entry = entries
+            .get_mut(&id)
+            .ok_or_else(|| PixelleError::NotFound("Scheduled post not found".to_string()))?;
+
+        if entry.status != ScheduleStatus::Pending {
+            return Err(PixelleError::Conflict(format!("Scheduled post is already {:?}", entry.status)));
+        }
+
+        entry.status = status;
+        entry.post_id = post_id;
+        entry.updated_at = Utc::now();
+        Ok(entry.clone())
+    }
+
+    pub fn get_draft(&self, draft_id: Id) -> PixelleResult<Option<Draft>> {
+        let drafts = self.drafts.lock().unwrap();
+        Ok(drafts.get(&draft_id).cloned())
+    }
+
+    pub fn list_drafts(&self, author_id: UserId) -> PixelleResult<Vec<Draft>> {
+        let drafts = self.drafts.lock().unwrap();
+        let mut owned: Vec<Draft> = drafts.values().filter(|d| d.author_id == author_id).cloned().collect();
+        owned.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(owned)
+    }
+
+    /// Store the draft and append its current content as a new revision
+    pub fn save_draft(&self, draft: &Draft) -> PixelleResult<Draft> {
+        let mut drafts = self.drafts.lock().unwrap();
+        let mut revisions = self.revisions.lock().unwrap();
+
+        let history = revisions.entry(draft.id).or_default();
+        history.push(DraftRevision {
+            draft_id: draft.id,
+            revision: draft.revision,
+            content: draft.content.clone(),
+            media_urls: draft.media_urls.clone(),
+            saved_at: draft.updated_at,
+        });
+        if history.len() > MAX_DRAFT_REVISIONS {
+            let excess = history.len() - MAX_DRAFT_REVISIONS;
+            history.drain(..excess);
+        }
+
+        drafts.insert(draft.id, draft.clone());
+        Ok(draft.clone())
+    }
+
+    pub fn get_revisions(&self, draft_id: Id) -> PixelleResult<Vec<DraftRevision>> {
+        let revisions = self.revisions.lock().unwrap();
+        Ok(revisions.get(&draft_id).cloned().unwrap_or_default())
+    }
+
+    pub fn delete_draft(&self, draft_id: Id) -> PixelleResult<()> {
+        self.drafts.lock().unwrap().remove(&draft_id);
+        self.revisions.lock().unwrap().remove(&draft_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::service::ContentService;
+
+/// How often the scheduler looks for posts that have come due
+pub const DEFAULT_TICK: Duration = Duration::from_secs(5);
+
+/// Background task that publishes scheduled posts.
+///
+/// Publishing latency is bounded by `tick`; the task runs until the process
+/// exits.
+pub async fn run_scheduler(service: Arc<ContentService>, tick: Duration) {
+    let mut interval = tokio::time::interval(tick);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match service.publish_due().await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Published {} scheduled posts", count),
+            Err(e) => tracing::error!("Scheduled post run failed: {}", e),
+        }
+    }
+}
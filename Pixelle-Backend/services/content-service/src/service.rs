@@ -0,0 +1,281 @@
+use pixelle_core::{HashtagService, Id, InMemoryHashtagRepository, Post, PixelleError, PixelleResult, UserId};
+use std::sync::Arc;
+use pixelle_analytics::{AnalyticsEvent, AnalyticsService};
+use chrono::{DateTime, Utc};
+use crate::models::{Draft, DraftRevision, ScheduleStatus, ScheduledPost};
+use crate::repository::ContentRepository;
+
+/// Schedules further out than this are rejected
+const MAX_SCHEDULE_AHEAD_DAYS: i64 = 90;
+
+/// Result of a create-post request
+pub enum CreatedPost {
+    Published(Post),
+    Scheduled(ScheduledPost),
+}
+
+pub struct ContentService {
+    repository: ContentRepository,
+    analytics: AnalyticsService,
+    hashtags: Arc<HashtagService>,
+}
+
+impl ContentService {
+    pub fn new(repository: ContentRepository, analytics: AnalyticsService) -> Self {
+        Self {
+            repository,
+            analytics,
+            hashtags: Arc::new(HashtagService::new(Arc::new(InMemoryHashtagRepository::new()))),
+        }
+    }
+
+    /// Share hashtag entities so published posts count towards their tags
+    pub fn with_hashtags(mut self, hashtags: Arc<HashtagService>) -> Self {
+        self.hashtags = hashtags;
+        self
+    }
+
+    /// Publish now, or schedule when `publish_at` is in the future
+    pub async fn create_post(&self, request: &crate::handlers::CreatePostRequest) -> PixelleResult<CreatedPost> {
+        validate_content(&request.content)?;
+
+        match request.publish_at {
+            Some(publish_at) if publish_at > Utc::now() => {
+                let scheduled = self.schedule_post(request, publish_at)?;
+                Ok(CreatedPost::Scheduled(scheduled))
+            }
+            _ => {
+                let post = self
+                    .publish(request.author_id, &request.content, &request.media_urls, request.is_public)
+                    .await?;
+                Ok(CreatedPost::Published(post))
+            }
+        }
+    }
+
+    fn schedule_post(&self, request: &crate::handlers::CreatePostRequest, publish_at: DateTime<Utc>) -> PixelleResult<ScheduledPost> {
+        if publish_at > Utc::now() + chrono::Duration::days(MAX_SCHEDULE_AHEAD_DAYS) {
+            return Err(PixelleError::Validation(format!(
+                "Posts can be scheduled at most {} days ahead",
+                MAX_SCHEDULE_AHEAD_DAYS
+            )));
+        }
+
+        if let Some(draft_id) = request.draft_id {
+            self.owned_draft(draft_id, request.author_id)?;
+        }
+
+        let scheduled = ScheduledPost {
+            id: pixelle_core::generate_id(),
+            author_id: request.author_id,
+            content: request.content.clone(),
+            media_urls: request.media_urls.clone(),
+            is_public: request.is_public,
+            publish_at,
+            status: ScheduleStatus::Pending,
+            post_id: None,
+            draft_id: request.draft_id,
+            created_at: pixelle_core::now(),
+            updated_at: pixelle_core::now(),
+        };
+
+        tracing::info!("Scheduled post {} for {}", scheduled.id, publish_at);
+        self.repository.save_scheduled(&scheduled)
+    }
+
+    pub async fn list_scheduled(&self, author_id: UserId) -> PixelleResult<Vec<ScheduledPost>> {
+        self.repository.list_scheduled(author_id)
+    }
+
+    pub async fn cancel_scheduled(&self, id: Id, author_id: UserId) -> PixelleResult<ScheduledPost> {
+        let scheduled = self
+            .repository
+            .get_scheduled(id)?
+            .ok_or_else(|| PixelleError::NotFound("Scheduled post not found".to_string()))?;
+
+        if scheduled.author_id != author_id {
+            return Err(PixelleError::Authorization("Not the author of this scheduled post".to_string()));
+        }
+
+        self.repository.transition_scheduled(id, ScheduleStatus::Cancelled, None)
+    }
+
+    /// Publish every schedule that has come due; returns how many were published
+    pub async fn publish_due(&self) -> PixelleResult<usize> {
+        let due = self.repository.due_scheduled(Utc::now())?;
+        let mut published = 0;
+
+        for scheduled in due {
+            // Claim the schedule before publishing so a concurrent cancel loses
+            let post_id = pixelle_core::generate_id();
+            if let Err(e) = self.repository.transition_scheduled(scheduled.id, ScheduleStatus::Published, Some(post_id)) {
+                tracing::debug!("Skipping scheduled post {}: {}", scheduled.id, e);
+                continue;
+            }
+
+            let post = self.build_post(post_id, scheduled.author_id, &scheduled.content, &scheduled.media_urls, scheduled.is_public);
+            match self.store_and_announce(post).await {
+                Ok(_) => {
+                    published += 1;
+                    if let Some(draft_id) = scheduled.draft_id {
+                        self.repository.delete_draft(draft_id)?;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to publish scheduled post {}: {}", scheduled.id, e);
+                    let mut failed = scheduled.clone();
+                    failed.status = ScheduleStatus::Failed;
+                    failed.updated_at = Utc::now();
+                    self.repository.save_scheduled(&failed)?;
+                }
+            }
+        }
+
+        Ok(published)
+    }
+
+    /// Auto-save a draft, creating it on first save.
+    ///
+    /// Every save records a revision so earlier versions can be restored.
+    pub async fn save_draft(&self, request: &crate::handlers::SaveDraftRequest) -> PixelleResult<Draft> {
+        let now = pixelle_core::now();
+
+        let draft = match request.draft_id {
+            Some(draft_id) => {
+                let mut draft = self.owned_draft(draft_id, request.author_id)?;
+
+                // Unchanged auto-saves do not create a new revision
+                if draft.content == request.content && draft.media_urls == request.media_urls {
+                    return Ok(draft);
+                }
+
+                draft.content = request.content.clone();
+                draft.media_urls = request.media_urls.clone();
+                draft.revision += 1;
+                draft.updated_at = now;
+                draft
+            }
+            None => Draft {
+                id: pixelle_core::generate_id(),
+                author_id: request.author_id,
+                content: request.content.clone(),
+                media_urls: request.media_urls.clone(),
+                revision: 1,
+                created_at: now,
+                updated_at: now,
+            },
+        };
+
+        self.repository.save_draft(&draft)
+    }
+
+    pub async fn list_drafts(&self, author_id: UserId) -> PixelleResult<Vec<Draft>> {
+        self.repository.list_drafts(author_id)
+    }
+
+    pub async fn get_draft_revisions(&self, draft_id: Id, author_id: UserId) -> PixelleResult<Vec<DraftRevision>> {
+        self.owned_draft(draft_id, author_id)?;
+        self.repository.get_revisions(draft_id)
+    }
+
+    /// Restore an earlier revision; the restore itself becomes the newest revision
+    pub async fn restore_draft_revision(&self, draft_id: Id, author_id: UserId, revision: u32) -> PixelleResult<Draft> {
+        let mut draft = self.owned_draft(draft_id, author_id)?;
+        let target = self
+            .repository
+            .get_revisions(draft_id)?
+            .into_iter()
+            .find(|r| r.revision == revision)
+            .ok_or_else(|| PixelleError::NotFound(format!("Revision {} not found", revision)))?;
+
+        draft.content = target.content;
+        draft.media_urls = target.media_urls;
+        draft.revision += 1;
+        draft.updated_at = pixelle_core::now();
+        self.repository.save_draft(&draft)
+    }
+
+    pub async fn delete_draft(&self, draft_id: Id, author_id: UserId) -> PixelleResult<()> {
+        self.owned_draft(draft_id, author_id)?;
+        self.repository.delete_draft(draft_id)
+    }
+
+    fn owned_draft(&self, draft_id: Id, author_id: UserId) -> PixelleResult<Draft> {
+        let draft = self
+            .repository
+            .get_draft(draft_id)?
+            .ok_or_else(|| PixelleError::NotFound("Draft not found".to_string()))?;
+
+        if draft.author_id != author_id {
+            return Err(PixelleError::Authorization("Not the author of this draft".to_string()));
+        }
+
+        Ok(draft)
+    }
+
+    async fn publish(&self, author_id: UserId, content: &str, media_urls: &[String], is_public: bool) -> PixelleResult<Post> {
+        let post = self.build_post(pixelle_core::generate_id(), author_id, content, media_urls, is_public);
+        self.store_and_announce(post).await
+    }
+
+    fn build_post(&self, id: Id, author_id: UserId, content: &str, media_urls: &[String], is_public: bool) -> Post {
+        Post {
+            id,
+            author_id,
+            content: content.to_string(),
+            media_urls: media_urls.to_vec(),
+            likes_count: 0,
+            comments_count: 0,
+            shares_count: 0,
+            is_public,
+            language: pixelle_core::detect_language(content),
+            geo: None,
+            created_at: pixelle_core::now(),
+            updated_at: pixelle_core::now(),
+        }
+    }
+
+    /// Store a post and emit the post-created event.
+    ///
+    /// Immediate and scheduled posts both go through here so downstream
+    /// consumers cannot tell them apart.
+    async fn store_and_announce(&self, post: Post) -> PixelleResult<Post> {
+        let post = self.repository.create_post(&post)?;
+
+        // A failed count must not fail the post; usage counts are advisory
+        let hashtags = match self.hashtags.record_post(&post).await {
+            Ok(hashtags) => hashtags,
+            Err(e) => {
+                tracing::warn!("Failed to count hashtags of {}: {}", post.id, e);
+                Vec::new()
+            }
+        };
+
+        let event = AnalyticsEvent {
+            event_type: "post_created".to_string(),
+            user_id: Some(post.author_id.to_string()),
+            timestamp: post.created_at,
+            properties: serde_json::json!({
+                "post_id": post.id,
+                "is_public": post.is_public,
+                "media_count": post.media_urls.len(),
+                "hashtags": hashtags,
+            }),
+        };
+        if let Err(e) = self.analytics.track_event(event).await {
+            tracing::warn!("Failed to emit post_created for {}: {}", post.id, e);
+        }
+
+        Ok(post)
+    }
+}
+
+fn validate_content(content: &str) -> PixelleResult<()> {
+    if content.trim().is_empty() {
+        return Err(PixelleError::Validation("Post content cannot be empty".to_string()));
+    }
+    if content.chars().count() > 2000 {
+        return Err(PixelleError::Validation("Post content must be at most 2000 characters".to_string()));
+    }
+    Ok(())
+}
@@ -1,16 +1,40 @@
 use actix_web::{web, HttpResponse, Result};
 use serde::{Deserialize, Serialize};
-use pixelle_core::{ApiResponse, PaginationParams, PaginatedResponse, Post};
+use pixelle_core::{ApiResponse, PaginationParams, PaginatedResponse, Post, LocalePreferences};
+use pixelle_monitoring::RequestContext;
+use crate::impressions::ImpressionReporter;
 use crate::service::FeedService;
 
 #[derive(Debug, Deserialize)]
 pub struct FeedQuery {
     pub page: Option<u32>,
     pub per_page: Option<u32>,
+    /// Comma-separated language codes, e.g. "en,pt-BR"
+    pub lang: Option<String>,
+    /// ISO 3166-1 alpha-2 country code
+    pub region: Option<String>,
+    /// Viewer of the trending page, used to hide blocked and muted authors
+    pub viewer_id: Option<String>,
+}
+
+impl FeedQuery {
+    fn locale(&self) -> Option<LocalePreferences> {
+        if self.lang.is_none() && self.region.is_none() {
+            return None;
+        }
+        let languages = self
+            .lang
+            .as_deref()
+            .map(|lang| lang.split(',').map(|l| l.to_string()).collect())
+            .unwrap_or_default();
+        Some(LocalePreferences::new(languages, self.region.clone()))
+    }
 }
 
 pub async fn get_user_feed(
     feed_service: web::Data<FeedService>,
+    impressions: web::Data<ImpressionReporter>,
+    context: RequestContext,
     query: web::Query<FeedQuery>,
     user_id: web::Path<String>,
 ) -> Result<HttpResponse> {
@@ -19,15 +43,18 @@ pub async fn get_user_feed(
         per_page: query.per_page.unwrap_or(20),
     };
     
-    let result = feed_service.get_user_feed(&user_id, &pagination).await;
+    let result = feed_service.get_user_feed(&user_id, &pagination, query.locale()).await;
     
     match result {
-        Ok(posts) => Ok(HttpResponse::Ok().json(ApiResponse {
-            success: true,
-            data: Some(posts),
-            error: None,
-            message: None,
-        })),
+        Ok(posts) => {
+            impressions.report(&context, Some(&user_id), "home", &posts.items);
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(posts),
+                error: None,
+                message: None,
+            }))
+        }
         Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<PaginatedResponse<Post>> {
             success: false,
             data: None,
@@ -39,6 +66,8 @@ pub async fn get_user_feed(
 
 pub async fn get_trending_posts(
     feed_service: web::Data<FeedService>,
+    impressions: web::Data<ImpressionReporter>,
+    context: RequestContext,
     query: web::Query<FeedQuery>,
 ) -> Result<HttpResponse> {
     let pagination = PaginationParams {
@@ -46,15 +75,18 @@ pub async fn get_trending_posts(
         per_page: query.per_page.unwrap_or(20),
     };
     
-    let result = feed_service.get_trending_posts(&pagination).await;
+    let result = feed_service.get_trending_posts(&pagination, query.locale(), query.viewer_id.as_deref()).await;
     
     match result {
-        Ok(posts) => Ok(HttpResponse::Ok().json(ApiResponse {
-            success: true,
-            data: Some(posts),
-            error: None,
-            message: None,
-        })),
+        Ok(posts) => {
+            impressions.report(&context, query.viewer_id.as_deref(), "trending", &posts.items);
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(posts),
+                error: None,
+                message: None,
+            }))
+        }
         Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<PaginatedResponse<Post>> {
             success: false,
             data: None,
@@ -0,0 +1,53 @@
+use pixelle_analytics::AnalyticsEvent;
+use pixelle_core::Post;
+use pixelle_http::HttpClient;
+use pixelle_monitoring::RequestContext;
+use std::env;
+
+/// Reports each served feed page to analytics-service as a `feed_impression` event
+///
+/// Reporting runs in the background and never delays or fails the feed
+/// response. Without `ANALYTICS_SERVICE_URL` nothing is sent.
+pub struct ImpressionReporter {
+    client: HttpClient,
+    events_url: Option<String>,
+}
+
+impl ImpressionReporter {
+    pub fn from_env(client: HttpClient) -> Self {
+        let events_url = env::var("ANALYTICS_SERVICE_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| format!("{}/api/v1/analytics/events", url.trim_end_matches('/')));
+        Self { client, events_url }
+    }
+
+    pub fn report(&self, context: &RequestContext, user_id: Option<&str>, feed: &str, posts: &[Post]) {
+        let Some(events_url) = self.events_url.clone() else {
+            return;
+        };
+        if posts.is_empty() {
+            return;
+        }
+
+        let event = AnalyticsEvent {
+            event_type: "feed_impression".to_string(),
+            user_id: user_id.map(str::to_string),
+            timestamp: chrono::Utc::now(),
+            properties: serde_json::json!({
+                "feed": feed,
+                "post_ids": posts.iter().map(|post| post.id).collect::<Vec<_>>(),
+            }),
+        };
+        let request = self.client.post(&events_url).context(context).json(&[event]);
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    tracing::debug!("Analytics rejected feed impressions: {}", response.status());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::debug!("Failed to report feed impressions: {}", e),
+            }
+        });
+    }
+}
@@ -0,0 +1,104 @@
+use pixelle_core::{LocalePreferences, Post};
+
+/// Boost applied to posts written in one of the viewer's languages
+const LANGUAGE_BOOST: f64 = 1.5;
+
+/// Boost applied to posts tagged in the viewer's region
+const REGION_BOOST: f64 = 1.3;
+
+/// Minimum regional posts before regional trending replaces global trending
+const MIN_REGIONAL_TRENDING: usize = 5;
+
+/// Applies language and region preferences to feed candidates
+pub struct LocaleRanker {
+    preferences: LocalePreferences,
+}
+
+impl LocaleRanker {
+    pub fn new(preferences: LocalePreferences) -> Self {
+        Self { preferences }
+    }
+
+    /// Drop candidates the viewer cannot read.
+    ///
+    /// Posts without a detected language are kept, as is everything when the
+    /// viewer has no language preference on record.
+    pub fn filter_candidates(&self, posts: Vec<Post>) -> Vec<Post> {
+        if self.preferences.languages.is_empty() {
+            return posts;
+        }
+
+        posts
+            .into_iter()
+            .filter(|post| match &post.language {
+                Some(language) => self.preferences.accepts_language(language),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Multiplier applied on top of a post's engagement score
+    pub fn boost(&self, post: &Post) -> f64 {
+        let mut boost = 1.0;
+
+        if let Some(language) = &post.language {
+            if !self.preferences.languages.is_empty() && self.preferences.accepts_language(language) {
+                boost *= LANGUAGE_BOOST;
+            }
+        }
+
+        if let (Some(region), Some(geo)) = (&self.preferences.region, &post.geo) {
+            if geo.country_code.eq_ignore_ascii_case(region) {
+                boost *= REGION_BOOST;
+            }
+        }
+
+        boost
+    }
+
+    /// Rank trending posts for the viewer's locale.
+    ///
+    /// Uses regional trending when the region has enough tagged posts and falls
+    /// back to global trending otherwise; either way posts are ordered by
+    /// engagement with language and region boosts applied.
+    pub fn rank_trending(&self, posts: Vec<Post>) -> Vec<Post> {
+        let posts = self.filter_candidates(posts);
+
+        let posts = match &self.preferences.region {
+            Some(region) => {
+                let regional_count = posts
+                    .iter()
+                    .filter(|post| post.geo.as_ref().map_or(false, |geo| geo.country_code.eq_ignore_ascii_case(region)))
+                    .count();
+
+                if regional_count >= MIN_REGIONAL_TRENDING {
+                    posts
+                        .into_iter()
+                        .filter(|post| post.geo.as_ref().map_or(false, |geo| geo.country_code.eq_ignore_ascii_case(region)))
+                        .collect()
+                } else {
+                    tracing::debug!(
+                        "Only {} trending posts tagged in {}, falling back to global trending",
+                        regional_count,
+                        region
+                    );
+                    posts
+                }
+            }
+            None => posts,
+        };
+
+        let mut scored: Vec<(f64, Post)> = posts
+            .into_iter()
+            .map(|post| (engagement_score(&post) * self.boost(&post), post))
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        scored.into_iter().map(|(_, post)| post).collect()
+    }
+}
+
+/// Raw engagement score used for trending
+pub fn engagement_score(post: &Post) -> f64 {
+    post.likes_count as f64 + post.comments_count as f64 * 2.0 + post.shares_count as f64 * 3.0
+}
@@ -1,15 +1,25 @@
 use actix_web::{web, App, HttpServer};
-use pixelle_monitoring::init_tracing;
+use pixelle_auth::ServiceCredentials;
+use pixelle_core::BlockListService;
+use pixelle_http::HttpClient;
+use pixelle_monitoring::{
+    capture_routes, init_logging, scaling_routes, CaptureStore, LoggingConfig, RequestCapture, RequestCorrelation, ScalingSignals,
+    TrackScalingSignals,
+};
 use std::env;
+use std::sync::Arc;
 
 mod handlers;
+mod impressions;
+mod locale;
 mod models;
 mod service;
+mod users;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Initialize tracing
-    init_tracing();
+    // Initialize structured logging
+    let log_export = init_logging(LoggingConfig::from_env("feed-service", env!("CARGO_PKG_VERSION")));
     
     // Get port from environment or use default
     let port = env::var("PORT").unwrap_or_else(|_| "8082".to_string());
@@ -17,19 +27,60 @@ async fn main() -> std::io::Result<()> {
     
     tracing::info!("Starting feed service on {}", bind_address);
     
-    HttpServer::new(|| {
+    let http_client = HttpClient::from_env("feed-service");
+    let impressions = web::Data::new(impressions::ImpressionReporter::from_env(http_client.clone()));
+    
+    // Block lists live in user-service, which is called with a service token from auth-service
+    let mut feed_service = service::FeedService::new();
+    let auth_service_url = env::var("AUTH_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8084".to_string());
+    match ServiceCredentials::from_env(&auth_service_url) {
+        Some(credentials) => {
+            let user_service_url = env::var("USER_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8081".to_string());
+            let block_lists = users::UserServiceBlockLists::new(http_client, &user_service_url, credentials);
+            feed_service = feed_service.with_block_lists(Arc::new(BlockListService::new(Arc::new(block_lists))));
+        }
+        None => tracing::warn!("AUTH_CLIENT_ID not set; feeds will not hide blocked authors"),
+    }
+    let feed_service = web::Data::new(feed_service);
+    
+    // Opt-in request capture for replaying ranking bugs, see CAPTURE_ROUTES
+    let captures = web::Data::new(CaptureStore::from_env("feed-service"));
+    if captures.config().enabled() {
+        tracing::info!("Capturing requests to {}", captures.config().routes.join(", "));
+    }
+    
+    // Load reported to the orchestrator, see SCALING_*
+    let signals = web::Data::new(ScalingSignals::from_env("feed-service"));
+    signals.clone().into_inner().spawn_sampler();
+    
+    let result = HttpServer::new(move || {
         App::new()
+            .wrap(RequestCapture::new(captures.clone()))
+            .wrap(TrackScalingSignals::new(signals.clone()))
+            .wrap(RequestCorrelation)
+            .app_data(feed_service.clone())
+            .app_data(impressions.clone())
+            .app_data(captures.clone())
+            .app_data(signals.clone())
+            .configure(capture_routes)
+            .configure(scaling_routes)
             .service(
                 web::scope("/api/v1/feed")
-                    .service(handlers::get_user_feed)
-                    .service(handlers::get_trending_posts)
+                    .route("/trending", web::get().to(handlers::get_trending_posts))
+                    .route("/{user_id}", web::get().to(handlers::get_user_feed))
             )
             .service(
                 web::scope("/health")
-                    .service(handlers::health_check)
+                    .route("", web::get().to(handlers::health_check))
             )
     })
     .bind(bind_address)?
     .run()
-    .await
+    .await;
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
 }
@@ -1,9 +1,16 @@
-use pixelle_core::{Post, PaginationParams, PaginatedResponse, PixelleResult};
-use std::collections::HashMap;
-use std::sync::Mutex;
+use pixelle_core::{Post, PaginationParams, PaginatedResponse, PixelleResult, LocalePreferences, BlockListService, InMemoryBlockListRepository, UserId, AccountDeletionService, PrivacyService, InMemoryPrivacyRepository, InMemoryFollowRepository, HashtagService, InMemoryHashtagRepository};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use crate::locale::LocaleRanker;
 
 pub struct FeedService {
     posts: Mutex<HashMap<String, Vec<Post>>>,
+    user_locales: Mutex<HashMap<String, LocalePreferences>>,
+    block_lists: Arc<BlockListService>,
+    privacy: Arc<PrivacyService>,
+    /// Hides posts by deactivated accounts when set
+    deletions: Option<Arc<AccountDeletionService>>,
+    hashtags: Arc<HashtagService>,
 }
 
 impl FeedService {
@@ -21,6 +28,8 @@ impl FeedService {
                 comments_count: 5,
                 shares_count: 2,
                 is_public: true,
+                language: pixelle_core::detect_language("This is a sample post for the feed!"),
+                geo: None,
                 created_at: pixelle_core::now(),
                 updated_at: pixelle_core::now(),
             },
@@ -33,6 +42,8 @@ impl FeedService {
                 comments_count: 15,
                 shares_count: 8,
                 is_public: true,
+                language: pixelle_core::detect_language("Another interesting post about technology and innovation."),
+                geo: None,
                 created_at: pixelle_core::now(),
                 updated_at: pixelle_core::now(),
             },
@@ -42,13 +53,91 @@ impl FeedService {
         
         Self {
             posts: Mutex::new(posts),
+            user_locales: Mutex::new(HashMap::new()),
+            block_lists: Arc::new(BlockListService::new(Arc::new(InMemoryBlockListRepository::new()))),
+            privacy: Arc::new(PrivacyService::new(
+                Arc::new(InMemoryPrivacyRepository::new()),
+                Arc::new(InMemoryFollowRepository::new()),
+            )),
+            deletions: None,
+            hashtags: Arc::new(HashtagService::new(Arc::new(InMemoryHashtagRepository::new()))),
         }
     }
 
-    pub async fn get_user_feed(&self, user_id: &str, pagination: &PaginationParams) -> PixelleResult<PaginatedResponse<Post>> {
-        let posts = self.posts.lock().unwrap();
+    /// Share block list lookups with the rest of the process
+    pub fn with_block_lists(mut self, block_lists: Arc<BlockListService>) -> Self {
+        self.block_lists = block_lists;
+        self
+    }
+
+    /// Share privacy settings so private accounts only reach their followers
+    pub fn with_privacy(mut self, privacy: Arc<PrivacyService>) -> Self {
+        self.privacy = privacy;
+        self
+    }
+
+    /// Share account deletion state so deactivated authors disappear from feeds
+    pub fn with_account_deletions(mut self, deletions: Arc<AccountDeletionService>) -> Self {
+        self.deletions = Some(deletions);
+        self
+    }
+
+    /// Share hashtag follows so followed tags pull posts into the feed
+    pub fn with_hashtags(mut self, hashtags: Arc<HashtagService>) -> Self {
+        self.hashtags = hashtags;
+        self
+    }
+
+    /// Drop candidates from deactivated accounts, from authors the viewer
+    /// blocked, muted or was blocked by, and from private accounts the viewer
+    /// does not follow.
+    ///
+    /// Viewers without a user ID (anonymous or legacy string IDs) skip the
+    /// block list and only see public accounts.
+    async fn filter_hidden(&self, viewer_id: Option<&str>, posts: Vec<Post>) -> PixelleResult<Vec<Post>> {
+        let posts = match &self.deletions {
+            Some(deletions) => deletions.filter_posts(posts).await?,
+            None => posts,
+        };
+        let viewer_id = viewer_id.and_then(|id| id.parse::<UserId>().ok());
+        let posts = match viewer_id {
+            Some(viewer_id) => self.block_lists.filter_feed(viewer_id, posts).await?,
+            None => posts,
+        };
+        self.privacy.filter_feed(viewer_id, posts).await
+    }
+
+    /// Record a user's language and region preferences for later feed requests
+    pub fn set_user_locale(&self, user_id: &str, preferences: LocalePreferences) {
+        self.user_locales.lock().unwrap().insert(user_id.to_string(), preferences);
+    }
+
+    /// Request preferences win; otherwise fall back to the stored profile preferences
+    fn resolve_locale(&self, user_id: Option<&str>, requested: Option<LocalePreferences>) -> LocalePreferences {
+        match requested {
+            Some(preferences) if !preferences.is_empty() => preferences,
+            _ => user_id
+                .and_then(|id| self.user_locales.lock().unwrap().get(id).cloned())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub async fn get_user_feed(&self, user_id: &str, pagination: &PaginationParams, locale: Option<LocalePreferences>) -> PixelleResult<PaginatedResponse<Post>> {
+        let ranker = LocaleRanker::new(self.resolve_locale(Some(user_id), locale));
+        let mut candidates = self.posts.lock().unwrap().get(user_id).cloned().unwrap_or_default();
+
+        // Posts carrying a followed hashtag join the feed even from unfollowed authors
+        if let Ok(viewer) = user_id.parse::<UserId>() {
+            let pool: Vec<Post> = self.posts.lock().unwrap().values().flatten().cloned().collect();
+            let mut seen: HashSet<_> = candidates.iter().map(|post| post.id).collect();
+            for post in self.hashtags.feed_candidates(viewer, pool).await? {
+                if seen.insert(post.id) {
+                    candidates.push(post);
+                }
+            }
+        }
         
-        let user_posts = posts.get(user_id).cloned().unwrap_or_default();
+        let user_posts = ranker.filter_candidates(self.filter_hidden(Some(user_id), candidates).await?);
         let total = user_posts.len() as u64;
         
         let start = ((pagination.page - 1) * pagination.per_page) as usize;
@@ -71,12 +160,12 @@ impl FeedService {
         })
     }
 
-    pub async fn get_trending_posts(&self, pagination: &PaginationParams) -> PixelleResult<PaginatedResponse<Post>> {
-        let posts = self.posts.lock().unwrap();
+    pub async fn get_trending_posts(&self, pagination: &PaginationParams, locale: Option<LocalePreferences>, viewer_id: Option<&str>) -> PixelleResult<PaginatedResponse<Post>> {
+        let ranker = LocaleRanker::new(self.resolve_locale(None, locale));
+        let candidates: Vec<Post> = self.posts.lock().unwrap().values().flatten().cloned().collect();
         
-        // Flatten all posts and sort by likes
-        let mut all_posts: Vec<Post> = posts.values().flatten().cloned().collect();
-        all_posts.sort_by(|a, b| b.likes_count.cmp(&a.likes_count));
+        // Flatten all posts and rank by locale-boosted engagement
+        let all_posts = ranker.rank_trending(self.filter_hidden(viewer_id, candidates).await?);
         
         let total = all_posts.len() as u64;
         
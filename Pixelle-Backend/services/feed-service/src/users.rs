@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use pixelle_auth::ServiceCredentials;
+use pixelle_core::{ApiResponse, BlockList, BlockListRepository, PixelleError, PixelleResult, RelationKind, UserId};
+use pixelle_http::HttpClient;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+
+/// Block lists read from user-service, which owns them
+///
+/// Calls carry a service token with `users:read`. Blocks and mutes are made
+/// through user-service, so this side never writes.
+pub struct UserServiceBlockLists {
+    client: HttpClient,
+    users_url: String,
+    credentials: ServiceCredentials,
+}
+
+impl UserServiceBlockLists {
+    pub fn new(client: HttpClient, user_service_url: &str, credentials: ServiceCredentials) -> Self {
+        Self {
+            client,
+            users_url: format!("{}/api/v1/users", user_service_url.trim_end_matches('/')),
+            credentials: credentials.with_scopes(&["users:read"]),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockListRepository for UserServiceBlockLists {
+    async fn add_relation(&self, _actor_id: UserId, _target_id: UserId, _kind: RelationKind) -> PixelleResult<bool> {
+        Err(PixelleError::Internal("Block lists are changed through user-service".to_string()))
+    }
+
+    async fn remove_relation(&self, _actor_id: UserId, _target_id: UserId, _kind: RelationKind) -> PixelleResult<bool> {
+        Err(PixelleError::Internal("Block lists are changed through user-service".to_string()))
+    }
+
+    async fn get_block_list(&self, user_id: UserId) -> PixelleResult<BlockList> {
+        let authorization = HeaderValue::from_str(&self.credentials.authorization().await?)
+            .map_err(|e| PixelleError::Internal(format!("Invalid service token: {}", e)))?;
+        let response = self
+            .client
+            .get(&format!("{}/{}/blocks", self.users_url, user_id))
+            .header(AUTHORIZATION, authorization)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(PixelleError::ExternalService(format!(
+                "user-service answered {} for the block list of {}",
+                response.status(),
+                user_id
+            )));
+        }
+
+        let body: ApiResponse<BlockList> = response
+            .json()
+            .await
+            .map_err(|e| PixelleError::ExternalService(format!("Invalid block list from user-service: {}", e)))?;
+        body.data
+            .ok_or_else(|| PixelleError::ExternalService(format!("user-service sent no block list for {}", user_id)))
+    }
+}
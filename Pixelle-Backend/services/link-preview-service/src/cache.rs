@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Entry<V> {
+    value: V,
+    expires: Instant,
+}
+
+/// Bounded map whose entries expire
+///
+/// When full, expired entries are dropped first and then those closest to
+/// expiring.
+pub struct TtlCache<V> {
+    capacity: usize,
+    entries: Mutex<HashMap<String, Entry<V>>>,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &str, now: Instant) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.expires > now => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, value: V, ttl: Duration, now: Instant) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires > now);
+            // Still full: evict the tenth closest to expiring, so this runs rarely
+            if entries.len() >= self.capacity {
+                let mut expiries: Vec<Instant> = entries.values().map(|entry| entry.expires).collect();
+                let evicted = (self.capacity / 10).max(1).min(expiries.len() - 1);
+                let (_, cutoff, _) = expiries.select_nth_unstable(evicted);
+                let cutoff = *cutoff;
+                entries.retain(|_, entry| entry.expires > cutoff);
+            }
+        }
+        entries.insert(key, Entry { value, expires: now + ttl });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
@@ -0,0 +1,251 @@
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::redirect::{Attempt, Policy};
+use reqwest::{StatusCode, Url};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum FetchError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Blocked destination: {0}")]
+    Blocked(String),
+
+    #[error("Request to {host} failed: {message}")]
+    Request { host: String, message: String },
+
+    #[error("{host} responded with {status}")]
+    Status { host: String, status: StatusCode },
+
+    #[error("Unsupported content type: {0}")]
+    UnsupportedType(String),
+
+    #[error("Response larger than {0} bytes")]
+    TooLarge(usize),
+}
+
+/// A fetched response body, possibly cut off at the requested size
+pub struct Fetched {
+    /// Where the content was found, after redirects
+    pub url: Url,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+/// Limits on requests to user-supplied URLs
+#[derive(Debug, Clone)]
+pub struct FetchConfig {
+    pub timeout: Duration,
+    pub max_redirects: usize,
+    /// Ports a URL may name; the scheme's default port is always allowed
+    pub allowed_ports: Vec<u16>,
+    pub user_agent: String,
+}
+
+/// HTTP client for URLs pasted by users
+///
+/// Only `http` and `https` URLs without credentials are fetched, on their
+/// default port or one in `allowed_ports`. Every host name is resolved
+/// through [`PublicResolver`], which fails unless all of its addresses are
+/// public, and the connection uses the addresses it checked, so a name
+/// cannot be rebound to an internal address between the check and the
+/// request. Literal addresses and every redirect hop are checked the same way.
+/// Environment proxies are ignored.
+#[derive(Clone)]
+pub struct SafeFetcher {
+    client: reqwest::Client,
+    config: FetchConfig,
+}
+
+impl SafeFetcher {
+    /// Fails if the guarded client cannot be built; there is no unguarded fallback
+    pub fn new(config: FetchConfig) -> Result<Self, reqwest::Error> {
+        let redirect_config = config.clone();
+        let policy = Policy::custom(move |attempt: Attempt| {
+            if attempt.previous().len() > redirect_config.max_redirects {
+                return attempt.error(format!("more than {} redirects", redirect_config.max_redirects));
+            }
+            match check_url(attempt.url(), &redirect_config) {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e),
+            }
+        });
+        let client = reqwest::Client::builder()
+            .dns_resolver(std::sync::Arc::new(PublicResolver))
+            .redirect(policy)
+            .no_proxy()
+            .connect_timeout(config.timeout)
+            .timeout(config.timeout)
+            .user_agent(config.user_agent.clone())
+            .build()?;
+        Ok(Self { client, config })
+    }
+
+    /// Parse a user-supplied URL and check it may be fetched
+    pub fn parse(&self, url: &str) -> Result<Url, FetchError> {
+        let mut url = Url::parse(url.trim()).map_err(|e| FetchError::InvalidUrl(e.to_string()))?;
+        url.set_fragment(None);
+        check_url(&url, &self.config)?;
+        Ok(url)
+    }
+
+    /// GET `url`, accepting only content types starting with one of `accept`
+    ///
+    /// Bodies over `max_bytes` are cut off when `truncate` is set and refused
+    /// otherwise.
+    pub async fn get(&self, url: &Url, accept: &[&str], max_bytes: usize, truncate: bool) -> Result<Fetched, FetchError> {
+        check_url(url, &self.config)?;
+        let host = url.host_str().unwrap_or_default().to_string();
+        let request_error = |e: reqwest::Error| {
+            refusal(&e).unwrap_or_else(|| FetchError::Request { host: host.clone(), message: e.to_string() })
+        };
+
+        let mut response = self
+            .client
+            .get(url.clone())
+            .header(ACCEPT, HeaderValue::from_str(&accept.join(", ")).unwrap_or(HeaderValue::from_static("*/*")))
+            .send()
+            .await
+            .map_err(request_error)?;
+        if !response.status().is_success() {
+            return Err(FetchError::Status { host, status: response.status() });
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        if !accept.iter().any(|accepted| content_type.starts_with(accepted)) {
+            return Err(FetchError::UnsupportedType(content_type));
+        }
+        if !truncate && response.content_length().is_some_and(|length| length > max_bytes as u64) {
+            return Err(FetchError::TooLarge(max_bytes));
+        }
+
+        let url = response.url().clone();
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+            if body.len() + chunk.len() > max_bytes {
+                if !truncate {
+                    return Err(FetchError::TooLarge(max_bytes));
+                }
+                body.extend_from_slice(&chunk[..max_bytes - body.len()]);
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(Fetched { url, content_type, body })
+    }
+}
+
+/// Resolves host names, failing unless every address is public
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if let Some(address) = addresses.iter().find(|address| !is_public(address.ip())) {
+                return Err(FetchError::Blocked(format!("{} resolves to {}", host, address.ip())).into());
+            }
+            let addresses: Addrs = Box::new(addresses.into_iter());
+            Ok(addresses)
+        })
+    }
+}
+
+fn check_url(url: &Url, config: &FetchConfig) -> Result<(), FetchError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(FetchError::InvalidUrl(format!("unsupported scheme {}", url.scheme())));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(FetchError::InvalidUrl("URLs with credentials are not fetched".to_string()));
+    }
+    if let Some(port) = url.port() {
+        if !config.allowed_ports.contains(&port) {
+            return Err(FetchError::Blocked(format!("port {} is not allowed", port)));
+        }
+    }
+    let host = url.host_str().ok_or_else(|| FetchError::InvalidUrl("URL has no host".to_string()))?;
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) if !is_public(ip) => Err(FetchError::Blocked(format!("{} is not a public address", ip))),
+        Ok(_) => Ok(()),
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            if domain == "localhost" || domain.ends_with(".localhost") || !domain.contains('.') {
+                return Err(FetchError::Blocked(format!("{} is not a public host", domain)));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Whether an address is globally routable
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_v4(v4);
+            }
+            let segments = ip.segments();
+            let embedded_v4 = |high: u16, low: u16| Ipv4Addr::from(((high as u32) << 16) | low as u32);
+            match segments {
+                // NAT64 and 6to4 reach the IPv4 address they carry
+                [0x64, 0xff9b, 0, 0, 0, 0, high, low] => is_public_v4(embedded_v4(high, low)),
+                [0x2002, high, low, ..] => is_public_v4(embedded_v4(high, low)),
+                _ => {
+                    !(ip.is_unspecified()
+                        || ip.is_loopback()
+                        || ip.is_multicast()
+                        // Unique local fc00::/7, link-local fe80::/10 and site-local fec0::/10
+                        || (segments[0] & 0xfe00) == 0xfc00
+                        || (segments[0] & 0xffc0) == 0xfe80
+                        || (segments[0] & 0xffc0) == 0xfec0
+                        // Documentation 2001:db8::/32 and the deprecated IPv4-compatible ::/96
+                        || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+                        || ip.octets()[..12].iter().all(|&byte| byte == 0))
+                }
+            }
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space 100.64.0.0/10
+        || (a == 100 && (b & 0xc0) == 64)
+        // IETF protocol assignments 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking 198.18.0.0/15
+        || (a == 198 && (b & 0xfe) == 18)
+        // Reserved 240.0.0.0/4
+        || a >= 240)
+}
+
+/// The refusal behind a failed request, when the resolver or redirect policy refused it
+fn refusal(error: &reqwest::Error) -> Option<FetchError> {
+    let mut source = std::error::Error::source(error);
+    while let Some(error) = source {
+        match error.downcast_ref::<FetchError>() {
+            Some(FetchError::Blocked(message)) => return Some(FetchError::Blocked(message.clone())),
+            Some(FetchError::InvalidUrl(message)) => return Some(FetchError::InvalidUrl(message.clone())),
+            _ => source = error.source(),
+        }
+    }
+    None
+}
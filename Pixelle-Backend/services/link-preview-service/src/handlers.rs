@@ -0,0 +1,70 @@
+use actix_web::{http::header, web, HttpResponse, Result};
+use pixelle_core::ApiResponse;
+use pixelle_monitoring::RequestContext;
+use serde::Deserialize;
+
+use crate::service::{UnfurlError, Unfurler};
+
+#[derive(Debug, Deserialize)]
+pub struct UnfurlRequest {
+    pub url: String,
+}
+
+/// Preview of a link pasted into a post or message
+pub async fn unfurl(unfurler: web::Data<Unfurler>, request: web::Json<UnfurlRequest>) -> Result<HttpResponse> {
+    match unfurler.unfurl(&request.url).await {
+        Ok(preview) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(preview.as_ref()),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Resized preview image, for a URL signed while unfurling
+pub async fn get_image(
+    unfurler: web::Data<Unfurler>,
+    context: RequestContext,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (signature, encoded_url) = path.into_inner();
+    let images = unfurler.images();
+    let image = match images.verify(&signature, &encoded_url) {
+        Ok(url) => images.load(&context, &url).await,
+        Err(e) => Err(e),
+    };
+    match image {
+        Ok(image) => Ok(HttpResponse::Ok()
+            .content_type(image.content_type.as_str())
+            .insert_header((header::CACHE_CONTROL, "public, max-age=86400, immutable"))
+            .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+            .body(image.body.clone())),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn health_check(unfurler: web::Data<Unfurler>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "healthy",
+        "service": "link-preview-service",
+        "cached_previews": unfurler.cached_previews(),
+        "timestamp": chrono::Utc::now()
+    })))
+}
+
+fn error_response(error: UnfurlError) -> HttpResponse {
+    let status = actix_web::http::StatusCode::from_u16(error.status_code())
+        .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = HttpResponse::build(status);
+    if let UnfurlError::RateLimited(retry_after) = &error {
+        response.insert_header((header::RETRY_AFTER, retry_after.as_secs().max(1).to_string()));
+    }
+    response.json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(error.to_string()),
+        message: None,
+    })
+}
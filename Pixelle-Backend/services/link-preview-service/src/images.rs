@@ -0,0 +1,119 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use pixelle_http::HttpClient;
+use pixelle_monitoring::RequestContext;
+use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use reqwest::Url;
+use ring::hmac;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::cache::TtlCache;
+use crate::fetch::SafeFetcher;
+use crate::rate_limit::DomainLimiter;
+use crate::service::{UnfurlConfig, UnfurlError};
+
+/// Image types passed to media-processor; SVG is never proxied
+const IMAGE_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+/// A preview image as resized by media-processor
+pub struct ProxiedImage {
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+/// Serves preview images from our own origin
+///
+/// Clients never load a previewed site's image directly, which would tell
+/// the site who is reading the post. Preview image URLs point here instead,
+/// signed so only images found while unfurling can be requested. The image is
+/// fetched with the same protections as pages, resized by media-processor and
+/// cached.
+pub struct ImageProxy {
+    key: hmac::Key,
+    base_url: String,
+    resize_url: String,
+    client: HttpClient,
+    fetcher: SafeFetcher,
+    limiter: Arc<DomainLimiter>,
+    cache: TtlCache<Arc<ProxiedImage>>,
+    config: UnfurlConfig,
+}
+
+impl ImageProxy {
+    pub fn new(config: UnfurlConfig, client: HttpClient, fetcher: SafeFetcher, limiter: Arc<DomainLimiter>) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, &config.image_key),
+            base_url: config.image_base_url.trim_end_matches('/').to_string(),
+            resize_url: format!("{}/api/v1/media/resize", config.media_processor_url.trim_end_matches('/')),
+            client,
+            fetcher,
+            limiter,
+            cache: TtlCache::new(config.image_cache_capacity),
+            config,
+        }
+    }
+
+    /// Signed URL serving `image` through the proxy
+    pub fn proxy_url(&self, image: &Url) -> String {
+        let signature = hmac::sign(&self.key, image.as_str().as_bytes());
+        format!(
+            "{}/{}/{}",
+            self.base_url,
+            URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            URL_SAFE_NO_PAD.encode(image.as_str())
+        )
+    }
+
+    /// The image URL a proxy path names, if its signature is ours
+    pub fn verify(&self, signature: &str, encoded_url: &str) -> Result<Url, UnfurlError> {
+        let invalid = || UnfurlError::InvalidSignature;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        let url = URL_SAFE_NO_PAD.decode(encoded_url).map_err(|_| invalid())?;
+        hmac::verify(&self.key, &url, &signature).map_err(|_| invalid())?;
+        let url = String::from_utf8(url).map_err(|_| invalid())?;
+        Ok(self.fetcher.parse(&url)?)
+    }
+
+    pub async fn load(&self, context: &RequestContext, url: &Url) -> Result<Arc<ProxiedImage>, UnfurlError> {
+        if let Some(image) = self.cache.get(url.as_str(), Instant::now()) {
+            return Ok(image);
+        }
+        self.limiter
+            .check(url.host_str().unwrap_or_default(), Instant::now())
+            .map_err(UnfurlError::RateLimited)?;
+
+        let original = self.fetcher.get(url, &IMAGE_TYPES, self.config.max_image_bytes, false).await?;
+        let content_type = HeaderValue::from_str(&original.content_type)
+            .map_err(|_| UnfurlError::MediaProcessor("invalid content type".to_string()))?;
+        let response = self
+            .client
+            .post(&self.resize_url)
+            .context(context)
+            .query(&[("max_width", self.config.image_max_width), ("max_height", self.config.image_max_height)])
+            .header(CONTENT_TYPE, content_type)
+            .body(original.body)
+            .send()
+            .await
+            .map_err(|e| UnfurlError::MediaProcessor(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(UnfurlError::MediaProcessor(format!("resize failed with {}", response.status())));
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("image/jpeg")
+            .to_string();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| UnfurlError::MediaProcessor(e.to_string()))?
+            .to_vec();
+        let image = Arc::new(ProxiedImage { content_type, body });
+        self.cache
+            .insert(url.to_string(), image.clone(), self.config.image_cache_ttl, Instant::now());
+        Ok(image)
+    }
+}
@@ -0,0 +1,63 @@
+use actix_web::{web, App, HttpServer};
+use pixelle_http::HttpClient;
+use pixelle_monitoring::{init_logging, LoggingConfig, RequestCorrelation};
+use std::env;
+use std::sync::Arc;
+
+mod cache;
+mod fetch;
+mod handlers;
+mod images;
+mod metadata;
+mod rate_limit;
+mod service;
+
+use fetch::SafeFetcher;
+use images::ImageProxy;
+use rate_limit::DomainLimiter;
+use service::{UnfurlConfig, Unfurler};
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    // Initialize structured logging
+    let log_export = init_logging(LoggingConfig::from_env("link-preview-service", env!("CARGO_PKG_VERSION")));
+
+    // Get port from environment or use default
+    let port = env::var("PORT").unwrap_or_else(|_| "8086".to_string());
+    let bind_address = format!("0.0.0.0:{}", port);
+
+    tracing::info!("Starting link preview service on {}", bind_address);
+
+    // Pages and images share one fetcher and one limit per domain
+    let config = UnfurlConfig::from_env();
+    let fetcher = SafeFetcher::new(config.fetch.clone()).map_err(std::io::Error::other)?;
+    let limiter = Arc::new(DomainLimiter::new(config.domain_requests_per_minute));
+    let images = Arc::new(ImageProxy::new(
+        config.clone(),
+        HttpClient::from_env("link-preview-service"),
+        fetcher.clone(),
+        limiter.clone(),
+    ));
+    let unfurler = web::Data::new(Unfurler::new(config, fetcher, limiter, images));
+
+    let result = HttpServer::new(move || {
+        App::new()
+            .wrap(RequestCorrelation)
+            .app_data(unfurler.clone())
+            .service(
+                web::scope("/api/v1/unfurl")
+                    .route("", web::post().to(handlers::unfurl))
+                    .route("/images/{signature}/{url}", web::get().to(handlers::get_image))
+            )
+            .route("/health", web::get().to(handlers::health_check))
+    })
+    .bind(bind_address)?
+    .run()
+    .await;
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
+}
@@ -0,0 +1,94 @@
+use reqwest::Url;
+use scraper::{Html, Selector};
+use std::collections::HashMap;
+
+const MAX_TITLE_CHARS: usize = 300;
+const MAX_DESCRIPTION_CHARS: usize = 1000;
+const MAX_SITE_NAME_CHARS: usize = 100;
+
+/// Metadata a page declares about itself
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    /// OpenGraph type, e.g. `article` or `video.other`
+    pub kind: Option<String>,
+    pub canonical_url: Option<Url>,
+    pub image: Option<Url>,
+    pub image_alt: Option<String>,
+    /// Twitter card type, e.g. `summary_large_image`
+    pub card: Option<String>,
+}
+
+/// Read OpenGraph and Twitter card tags from a page, falling back to its
+/// `<title>` and description
+///
+/// OpenGraph wins over Twitter cards, which win over plain HTML. Relative
+/// URLs are resolved against `page_url`, and only `http` and `https` ones
+/// are kept.
+pub fn parse(html: &str, page_url: &Url) -> PageMetadata {
+    let document = Html::parse_document(html);
+    let meta = Selector::parse("meta").unwrap();
+    let title = Selector::parse("title").unwrap();
+    let canonical = Selector::parse("link[rel~=canonical]").unwrap();
+
+    // First value of each property; pages often repeat tags
+    let mut tags: HashMap<String, String> = HashMap::new();
+    for element in document.select(&meta) {
+        let element = element.value();
+        let Some(key) = element.attr("property").or_else(|| element.attr("name")) else {
+            continue;
+        };
+        let Some(content) = element.attr("content") else {
+            continue;
+        };
+        tags.entry(key.trim().to_ascii_lowercase()).or_insert_with(|| content.to_string());
+    }
+    let tag = |keys: &[&str]| keys.iter().find_map(|key| tags.get(*key)).and_then(|value| clean(value));
+    let url_tag = |keys: &[&str]| keys.iter().find_map(|key| tags.get(*key)).and_then(|value| resolve(page_url, value));
+
+    let document_title = document
+        .select(&title)
+        .next()
+        .and_then(|element| clean(&element.text().collect::<String>()));
+    let canonical_url = document
+        .select(&canonical)
+        .next()
+        .and_then(|element| element.value().attr("href"))
+        .and_then(|href| resolve(page_url, href))
+        .or_else(|| url_tag(&["og:url"]));
+
+    PageMetadata {
+        title: tag(&["og:title", "twitter:title"])
+            .or(document_title)
+            .map(|title| truncate(title, MAX_TITLE_CHARS)),
+        description: tag(&["og:description", "twitter:description", "description"])
+            .map(|description| truncate(description, MAX_DESCRIPTION_CHARS)),
+        site_name: tag(&["og:site_name", "application-name"]).map(|name| truncate(name, MAX_SITE_NAME_CHARS)),
+        kind: tag(&["og:type"]),
+        canonical_url,
+        image: url_tag(&["og:image:secure_url", "og:image", "og:image:url", "twitter:image", "twitter:image:src"]),
+        image_alt: tag(&["og:image:alt", "twitter:image:alt"]).map(|alt| truncate(alt, MAX_DESCRIPTION_CHARS)),
+        card: tag(&["twitter:card"]),
+    }
+}
+
+/// Collapse whitespace, dropping empty values
+fn clean(value: &str) -> Option<String> {
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!value.is_empty()).then_some(value)
+}
+
+fn truncate(mut value: String, max_chars: usize) -> String {
+    if let Some((index, _)) = value.char_indices().nth(max_chars) {
+        value.truncate(index);
+        value.push('…');
+    }
+    value
+}
+
+fn resolve(page_url: &Url, value: &str) -> Option<Url> {
+    let url = page_url.join(value.trim()).ok()?;
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const MINUTE: Duration = Duration::from_secs(60);
+/// Tracked domains before idle ones are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// Fixed-window limit on fetches per destination domain
+///
+/// Keeps a viral link, or someone pasting many links to one site, from
+/// turning the service into a crawler of that site. Only fetches count;
+/// previews served from the cache are free.
+pub struct DomainLimiter {
+    per_minute: u32,
+    domains: Mutex<HashMap<String, Window>>,
+}
+
+impl DomainLimiter {
+    /// `per_minute` of zero means unlimited
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            domains: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a fetch from `host`, or return how long until one is allowed
+    pub fn check(&self, host: &str, now: Instant) -> Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+        let mut domains = self.domains.lock().unwrap();
+        if domains.len() >= PRUNE_THRESHOLD {
+            domains.retain(|_, window| now.duration_since(window.started) < MINUTE);
+        }
+
+        let window = domains
+            .entry(domain_of(host))
+            .or_insert(Window { started: now, count: 0 });
+        if now.duration_since(window.started) >= MINUTE {
+            *window = Window { started: now, count: 0 };
+        }
+        if window.count >= self.per_minute {
+            return Err(MINUTE.saturating_sub(now.duration_since(window.started)));
+        }
+        window.count += 1;
+        Ok(())
+    }
+}
+
+/// Key a host is limited under; `www.` and letter case don't make a new domain
+pub fn domain_of(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    match host.strip_prefix("www.") {
+        Some(domain) => domain.to_string(),
+        None => host,
+    }
+}
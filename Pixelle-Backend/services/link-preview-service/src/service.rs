@@ -0,0 +1,226 @@
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::Serialize;
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::cache::TtlCache;
+use crate::fetch::{FetchConfig, FetchError, SafeFetcher};
+use crate::images::ImageProxy;
+use crate::metadata;
+use crate::rate_limit::DomainLimiter;
+
+/// Page types unfurled; an image link previews as itself
+const PAGE_TYPES: [&str; 6] = ["text/html", "application/xhtml+xml", "image/jpeg", "image/png", "image/gif", "image/webp"];
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum UnfurlError {
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+
+    #[error("Too many previews requested for this domain")]
+    RateLimited(Duration),
+
+    #[error("Invalid image signature")]
+    InvalidSignature,
+
+    #[error("Media processor error: {0}")]
+    MediaProcessor(String),
+}
+
+impl UnfurlError {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            UnfurlError::Fetch(FetchError::InvalidUrl(_)) => 400,
+            UnfurlError::Fetch(FetchError::Blocked(_)) | UnfurlError::InvalidSignature => 403,
+            UnfurlError::Fetch(FetchError::UnsupportedType(_)) => 415,
+            UnfurlError::Fetch(FetchError::TooLarge(_)) => 413,
+            UnfurlError::Fetch(FetchError::Request { .. } | FetchError::Status { .. }) => 502,
+            UnfurlError::RateLimited(_) => 429,
+            UnfurlError::MediaProcessor(_) => 502,
+        }
+    }
+}
+
+/// Settings for fetching, caching and proxying previews
+#[derive(Debug, Clone)]
+pub struct UnfurlConfig {
+    pub fetch: FetchConfig,
+    pub cache_ttl: Duration,
+    /// How long a failed unfurl is remembered, so a broken link isn't refetched on every paste
+    pub failure_ttl: Duration,
+    pub cache_capacity: usize,
+    /// Fetches per destination domain per minute, pages and images together
+    pub domain_requests_per_minute: u32,
+    /// Pages are cut off here; the metadata is in the head
+    pub max_page_bytes: usize,
+    pub max_image_bytes: usize,
+    pub image_max_width: u32,
+    pub image_max_height: u32,
+    pub image_cache_ttl: Duration,
+    pub image_cache_capacity: usize,
+    pub media_processor_url: String,
+    /// Public prefix of the image proxy, as clients reach it
+    pub image_base_url: String,
+    /// Signs image proxy URLs; every replica must share it
+    pub image_key: Vec<u8>,
+}
+
+impl UnfurlConfig {
+    pub fn from_env() -> Self {
+        fn var<T: FromStr>(name: &str, default: T) -> T {
+            env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let image_key = match env::var("UNFURL_IMAGE_KEY") {
+            Ok(key) if !key.is_empty() => key.into_bytes(),
+            _ => {
+                tracing::warn!("UNFURL_IMAGE_KEY is not set; image URLs will not survive a restart or work across replicas");
+                let mut key = [0u8; 32];
+                ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut key)
+                    .expect("system randomness is unavailable");
+                key.to_vec()
+            }
+        };
+
+        Self {
+            fetch: FetchConfig {
+                timeout: Duration::from_millis(var("UNFURL_FETCH_TIMEOUT_MS", 5000)),
+                max_redirects: var("UNFURL_MAX_REDIRECTS", 5),
+                allowed_ports: env::var("UNFURL_ALLOWED_PORTS")
+                    .unwrap_or_else(|_| "80,443,8080,8443".to_string())
+                    .split(',')
+                    .filter_map(|port| port.trim().parse().ok())
+                    .collect(),
+                user_agent: env::var("UNFURL_USER_AGENT")
+                    .unwrap_or_else(|_| "Pixellebot/1.0 (link previews)".to_string()),
+            },
+            cache_ttl: Duration::from_secs(var("UNFURL_CACHE_TTL_SECONDS", 86_400)),
+            failure_ttl: Duration::from_secs(var("UNFURL_FAILURE_TTL_SECONDS", 600)),
+            cache_capacity: var("UNFURL_CACHE_CAPACITY", 100_000),
+            domain_requests_per_minute: var("UNFURL_DOMAIN_REQUESTS_PER_MINUTE", 60),
+            max_page_bytes: var("UNFURL_MAX_PAGE_BYTES", 1024 * 1024),
+            max_image_bytes: var("UNFURL_MAX_IMAGE_BYTES", 10 * 1024 * 1024),
+            image_max_width: var("UNFURL_IMAGE_MAX_WIDTH", 1200),
+            image_max_height: var("UNFURL_IMAGE_MAX_HEIGHT", 630),
+            image_cache_ttl: Duration::from_secs(var("UNFURL_IMAGE_CACHE_TTL_SECONDS", 86_400)),
+            image_cache_capacity: var("UNFURL_IMAGE_CACHE_CAPACITY", 2000),
+            media_processor_url: env::var("MEDIA_PROCESSOR_URL")
+                .unwrap_or_else(|_| "http://localhost:8087".to_string()),
+            image_base_url: env::var("UNFURL_IMAGE_BASE_URL")
+                .unwrap_or_else(|_| "/api/v1/unfurl/images".to_string()),
+            image_key,
+        }
+    }
+}
+
+/// Preview of a link, as shown under a post or message
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkPreview {
+    /// The link as pasted, without its fragment
+    pub url: String,
+    /// The page's canonical URL, or where redirects ended
+    pub resolved_url: String,
+    pub domain: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    /// OpenGraph type, e.g. `article` or `video.other`
+    pub kind: Option<String>,
+    /// Twitter card type, e.g. `summary_large_image`
+    pub card: Option<String>,
+    pub image: Option<PreviewImage>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewImage {
+    /// Proxied, resized copy; clients must load this, not `source_url`
+    pub url: String,
+    pub source_url: String,
+    pub alt: Option<String>,
+}
+
+/// Unfurls links into previews
+///
+/// Results are cached by URL for `cache_ttl`, and failures for
+/// `failure_ttl`. Only cache misses fetch, and they count against the
+/// destination domain's rate limit.
+pub struct Unfurler {
+    fetcher: SafeFetcher,
+    limiter: Arc<DomainLimiter>,
+    images: Arc<ImageProxy>,
+    cache: TtlCache<Result<Arc<LinkPreview>, UnfurlError>>,
+    config: UnfurlConfig,
+}
+
+impl Unfurler {
+    pub fn new(config: UnfurlConfig, fetcher: SafeFetcher, limiter: Arc<DomainLimiter>, images: Arc<ImageProxy>) -> Self {
+        Self {
+            fetcher,
+            limiter,
+            images,
+            cache: TtlCache::new(config.cache_capacity),
+            config,
+        }
+    }
+
+    pub fn images(&self) -> &ImageProxy {
+        &self.images
+    }
+
+    pub fn cached_previews(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub async fn unfurl(&self, url: &str) -> Result<Arc<LinkPreview>, UnfurlError> {
+        let url = self.fetcher.parse(url)?;
+        if let Some(result) = self.cache.get(url.as_str(), Instant::now()) {
+            return result;
+        }
+        let host = url.host_str().unwrap_or_default().to_string();
+        self.limiter.check(&host, Instant::now()).map_err(UnfurlError::RateLimited)?;
+
+        let result = self.fetch_preview(&url).await.map(Arc::new);
+        match &result {
+            Ok(_) => self.cache.insert(url.to_string(), result.clone(), self.config.cache_ttl, Instant::now()),
+            Err(e) => {
+                tracing::debug!("Failed to unfurl {}: {}", url, e);
+                self.cache.insert(url.to_string(), result.clone(), self.config.failure_ttl, Instant::now());
+            }
+        }
+        result
+    }
+
+    async fn fetch_preview(&self, url: &Url) -> Result<LinkPreview, UnfurlError> {
+        let page = self.fetcher.get(url, &PAGE_TYPES, self.config.max_page_bytes, true).await?;
+        let metadata = if page.content_type.starts_with("image/") {
+            metadata::PageMetadata {
+                image: Some(page.url.clone()),
+                ..Default::default()
+            }
+        } else {
+            metadata::parse(&String::from_utf8_lossy(&page.body), &page.url)
+        };
+
+        let resolved_url = metadata.canonical_url.as_ref().unwrap_or(&page.url);
+        Ok(LinkPreview {
+            url: url.to_string(),
+            resolved_url: resolved_url.to_string(),
+            domain: crate::rate_limit::domain_of(resolved_url.host_str().unwrap_or_default()),
+            title: metadata.title,
+            description: metadata.description,
+            site_name: metadata.site_name,
+            kind: metadata.kind,
+            card: metadata.card,
+            image: metadata.image.map(|image| PreviewImage {
+                url: self.images.proxy_url(&image),
+                source_url: image.to_string(),
+                alt: metadata.image_alt,
+            }),
+            fetched_at: Utc::now(),
+        })
+    }
+}
@@ -0,0 +1,111 @@
+use actix_web::{web, HttpResponse, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::io::{Limits, Reader};
+use image::{DynamicImage, ImageFormat};
+use pixelle_core::ApiResponse;
+use serde::Deserialize;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Largest source image accepted for resizing
+pub const MAX_SOURCE_BYTES: usize = 20 * 1024 * 1024;
+/// Sources larger than this in either dimension are refused before decoding
+const MAX_SOURCE_DIMENSION: u32 = 12_000;
+const MAX_DECODE_ALLOCATION: u64 = 512 * 1024 * 1024;
+const MAX_OUTPUT_DIMENSION: u32 = 4096;
+const JPEG_QUALITY: u8 = 85;
+
+/// Resizes decoding or encoding right now, reported as a scaling signal
+static ACTIVE_RESIZES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn active_resizes() -> usize {
+    ACTIVE_RESIZES.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResizeQuery {
+    pub max_width: u32,
+    pub max_height: u32,
+}
+
+/// Shrink an image to fit within `max_width` by `max_height`
+///
+/// The aspect ratio is kept and images are never enlarged. Output is JPEG,
+/// or PNG when the image has transparency; animations keep only their first
+/// frame.
+pub async fn resize(query: web::Query<ResizeQuery>, body: web::Bytes) -> Result<HttpResponse> {
+    let ResizeQuery { max_width, max_height } = query.into_inner();
+    if max_width == 0 || max_height == 0 || max_width > MAX_OUTPUT_DIMENSION || max_height > MAX_OUTPUT_DIMENSION {
+        return Ok(bad_request(format!(
+            "max_width and max_height must be between 1 and {}",
+            MAX_OUTPUT_DIMENSION
+        )));
+    }
+
+    // Decoding and encoding are CPU-bound
+    let resized = web::block(move || -> std::result::Result<(Vec<u8>, &'static str), String> {
+        ACTIVE_RESIZES.fetch_add(1, Ordering::Relaxed);
+        let result = resize_image(&body, max_width, max_height);
+        ACTIVE_RESIZES.fetch_sub(1, Ordering::Relaxed);
+        result
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    match resized {
+        Ok((body, content_type)) => Ok(HttpResponse::Ok().content_type(content_type).body(body)),
+        Err(e) => Ok(bad_request(format!("Could not resize image: {}", e))),
+    }
+}
+
+fn resize_image(body: &[u8], max_width: u32, max_height: u32) -> std::result::Result<(Vec<u8>, &'static str), String> {
+    let image = decode(body)?;
+    let image = if image.width() > max_width || image.height() > max_height {
+        image.thumbnail(max_width, max_height)
+    } else {
+        image
+    };
+
+    let mut output = Cursor::new(Vec::new());
+    if image.color().has_alpha() {
+        image
+            .write_to(&mut output, ImageFormat::Png)
+            .map_err(|e| e.to_string())?;
+        Ok((output.into_inner(), "image/png"))
+    } else {
+        JpegEncoder::new_with_quality(&mut output, JPEG_QUALITY)
+            .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))
+            .map_err(|e| e.to_string())?;
+        Ok((output.into_inner(), "image/jpeg"))
+    }
+}
+
+fn decode(bytes: &[u8]) -> std::result::Result<DynamicImage, String> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOCATION);
+
+    let mut reader = Reader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    reader.limits(limits);
+    reader.decode().map_err(|e| e.to_string())
+}
+
+pub async fn health_check() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "healthy",
+        "service": "media-processor",
+        "timestamp": chrono::Utc::now()
+    })))
+}
+
+fn bad_request(error: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(error),
+        message: None,
+    })
+}
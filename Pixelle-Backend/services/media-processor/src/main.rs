@@ -0,0 +1,46 @@
+use actix_web::{web, App, HttpServer};
+use pixelle_monitoring::{init_logging, scaling_routes, LoggingConfig, RequestCorrelation, ScalingSignals, TrackScalingSignals};
+use std::env;
+
+mod handlers;
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    // Initialize structured logging
+    let log_export = init_logging(LoggingConfig::from_env("media-processor", env!("CARGO_PKG_VERSION")));
+
+    // Get port from environment or use default
+    let port = env::var("PORT").unwrap_or_else(|_| "8087".to_string());
+    let bind_address = format!("0.0.0.0:{}", port);
+
+    tracing::info!("Starting media processor on {}", bind_address);
+
+    // Resizes are CPU-bound, so one per core is a full instance
+    let signals = web::Data::new(ScalingSignals::from_env("media-processor"));
+    let cores = std::thread::available_parallelism().map(|cores| cores.get()).unwrap_or(1);
+    signals.register("active_resizes", cores as f64, || handlers::active_resizes() as f64);
+    signals.clone().into_inner().spawn_sampler();
+
+    let result = HttpServer::new(move || {
+        App::new()
+            .wrap(TrackScalingSignals::new(signals.clone()))
+            .wrap(RequestCorrelation)
+            .app_data(signals.clone())
+            .configure(scaling_routes)
+            .app_data(web::PayloadConfig::new(handlers::MAX_SOURCE_BYTES))
+            .service(
+                web::scope("/api/v1/media")
+                    .route("/resize", web::post().to(handlers::resize))
+            )
+            .route("/health", web::get().to(handlers::health_check))
+    })
+    .bind(bind_address)?
+    .run()
+    .await;
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
+}
@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use pixelle_core::{Delivery, NotificationChannel, NotificationPreferenceService, NotificationType, PixelleResult, UserId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A notification for one recipient
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub recipient_id: UserId,
+    #[serde(rename = "type")]
+    pub kind: NotificationType,
+    pub title: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Delivers notifications on a channel
+#[async_trait]
+pub trait NotificationSender: Send + Sync {
+    /// Send `notifications` to one recipient; more than one is sent as a digest
+    async fn send(&self, channel: NotificationChannel, recipient_id: UserId, notifications: &[Notification]) -> PixelleResult<()>;
+}
+
+/// Logs notifications instead of delivering them, until push and email
+/// providers are configured
+pub struct LogNotificationSender;
+
+#[async_trait]
+impl NotificationSender for LogNotificationSender {
+    async fn send(&self, channel: NotificationChannel, recipient_id: UserId, notifications: &[Notification]) -> PixelleResult<()> {
+        match notifications {
+            [notification] => tracing::info!(
+                "{} notification for {}: {}",
+                channel.name(),
+                recipient_id,
+                notification.title
+            ),
+            _ => tracing::info!(
+                "{} digest of {} notifications for {}",
+                channel.name(),
+                notifications.len(),
+                recipient_id
+            ),
+        }
+        Ok(())
+    }
+}
+
+struct Pending {
+    due: DateTime<Utc>,
+    channel: NotificationChannel,
+    notification: Notification,
+}
+
+/// Routes notifications through the recipient's preferences
+///
+/// Notifications held for quiet hours or a digest wait in memory until due,
+/// and are lost if the service restarts before then.
+pub struct NotificationDispatcher {
+    preferences: Arc<NotificationPreferenceService>,
+    sender: Arc<dyn NotificationSender>,
+    pending: Mutex<Vec<Pending>>,
+}
+
+impl NotificationDispatcher {
+    pub fn new(preferences: Arc<NotificationPreferenceService>, sender: Arc<dyn NotificationSender>) -> Self {
+        Self {
+            preferences,
+            sender,
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Send on the channels that deliver now and queue the rest
+    pub async fn dispatch(&self, notification: Notification, now: DateTime<Utc>) -> PixelleResult<Vec<(NotificationChannel, Delivery)>> {
+        let routes = self.preferences.route(notification.recipient_id, notification.kind, now).await?;
+        for (channel, delivery) in &routes {
+            let due = match delivery {
+                Delivery::Now => {
+                    self.send(*channel, notification.recipient_id, std::slice::from_ref(&notification)).await;
+                    continue;
+                }
+                Delivery::Held { until } => *until,
+                Delivery::Digest { at } => *at,
+                Delivery::Skipped => continue,
+            };
+            self.pending.lock().unwrap().push(Pending {
+                due,
+                channel: *channel,
+                notification: notification.clone(),
+            });
+        }
+        Ok(routes)
+    }
+
+    /// Send every queued notification that is due, one message per
+    /// recipient and channel; returns how many were sent
+    pub async fn flush(&self, now: DateTime<Utc>) -> usize {
+        let due: Vec<Pending> = {
+            let mut pending = self.pending.lock().unwrap();
+            let (due, waiting) = pending.drain(..).partition(|entry| entry.due <= now);
+            *pending = waiting;
+            due
+        };
+
+        let mut batches: BTreeMap<(UserId, NotificationChannel), Vec<Notification>> = BTreeMap::new();
+        for entry in due {
+            batches
+                .entry((entry.notification.recipient_id, entry.channel))
+                .or_default()
+                .push(entry.notification);
+        }
+        let mut sent = 0;
+        for ((recipient_id, channel), notifications) in batches {
+            self.send(channel, recipient_id, &notifications).await;
+            sent += notifications.len();
+        }
+        sent
+    }
+
+    /// Failed sends are logged and dropped, like account emails
+    async fn send(&self, channel: NotificationChannel, recipient_id: UserId, notifications: &[Notification]) {
+        if let Err(e) = self.sender.send(channel, recipient_id, notifications).await {
+            tracing::warn!("Failed to send {} notification to {}: {}", channel.name(), recipient_id, e);
+        }
+    }
+
+    /// Flush due notifications on an interval for as long as the dispatcher is alive
+    pub fn spawn_flusher(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let sent = self.flush(Utc::now()).await;
+                if sent > 0 {
+                    tracing::debug!("Sent {} held notifications", sent);
+                }
+            }
+        })
+    }
+}
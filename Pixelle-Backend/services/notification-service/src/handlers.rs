@@ -0,0 +1,147 @@
+use actix_web::{web, HttpResponse, Result};
+use chrono::Utc;
+use pixelle_auth::AuthenticatedUser;
+use pixelle_core::{
+    ApiResponse, Delivery, DigestFrequency, NotificationChannel, NotificationPreferenceService, NotificationPreferences,
+    NotificationType, PixelleError, UserId,
+};
+use serde::{Deserialize, Serialize};
+use crate::dispatch::{Notification, NotificationDispatcher};
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateChannelRequest {
+    pub enabled: Option<bool>,
+    pub frequency: Option<DigestFrequency>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DispatchRequest {
+    pub recipient_id: UserId,
+    #[serde(rename = "type")]
+    pub kind: NotificationType,
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChannelDelivery {
+    pub channel: NotificationChannel,
+    #[serde(flatten)]
+    pub delivery: Delivery,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DispatchResponse {
+    pub id: String,
+    pub deliveries: Vec<ChannelDelivery>,
+}
+
+fn error_response(error: PixelleError) -> HttpResponse {
+    let mut response = match &error {
+        PixelleError::Validation(_) => HttpResponse::BadRequest(),
+        PixelleError::NotFound(_) => HttpResponse::NotFound(),
+        _ => HttpResponse::InternalServerError(),
+    };
+    response.json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(error.to_string()),
+        message: None,
+    })
+}
+
+fn preferences_response(preferences: NotificationPreferences, message: Option<&str>) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(preferences),
+        error: None,
+        message: message.map(str::to_string),
+    })
+}
+
+pub async fn get_preferences(
+    caller: AuthenticatedUser,
+    preferences: web::Data<NotificationPreferenceService>,
+) -> Result<HttpResponse> {
+    match preferences.get(caller.user_id).await {
+        Ok(current) => Ok(preferences_response(current, None)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Replace every preference; types left out fall back to their defaults
+pub async fn update_preferences(
+    caller: AuthenticatedUser,
+    preferences: web::Data<NotificationPreferenceService>,
+    request: web::Json<NotificationPreferences>,
+) -> Result<HttpResponse> {
+    match preferences.update(caller.user_id, request.into_inner()).await {
+        Ok(updated) => Ok(preferences_response(updated, Some("Notification preferences updated"))),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Change one notification type on one channel
+pub async fn update_channel(
+    caller: AuthenticatedUser,
+    preferences: web::Data<NotificationPreferenceService>,
+    path: web::Path<(String, String)>,
+    request: web::Json<UpdateChannelRequest>,
+) -> Result<HttpResponse> {
+    let (kind, channel) = path.into_inner();
+    let target = kind.parse::<NotificationType>().and_then(|kind| Ok((kind, channel.parse::<NotificationChannel>()?)));
+    let (kind, channel) = match target {
+        Ok(target) => target,
+        Err(e) => return Ok(error_response(e)),
+    };
+
+    let result = preferences
+        .update_channel(caller.user_id, kind, channel, request.enabled, request.frequency)
+        .await;
+    match result {
+        Ok(updated) => Ok(preferences_response(updated, Some("Notification preferences updated"))),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Deliver a notification from another service according to the
+/// recipient's preferences
+pub async fn dispatch_notification(
+    dispatcher: web::Data<NotificationDispatcher>,
+    request: web::Json<DispatchRequest>,
+) -> Result<HttpResponse> {
+    let request = request.into_inner();
+    let notification = Notification {
+        id: uuid::Uuid::new_v4().to_string(),
+        recipient_id: request.recipient_id,
+        kind: request.kind,
+        title: request.title,
+        body: request.body,
+        created_at: Utc::now(),
+    };
+    let id = notification.id.clone();
+
+    match dispatcher.dispatch(notification, Utc::now()).await {
+        Ok(routes) => Ok(HttpResponse::Accepted().json(ApiResponse {
+            success: true,
+            data: Some(DispatchResponse {
+                id,
+                deliveries: routes
+                    .into_iter()
+                    .map(|(channel, delivery)| ChannelDelivery { channel, delivery })
+                    .collect(),
+            }),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn health_check() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "healthy",
+        "service": "notification-service",
+        "timestamp": chrono::Utc::now()
+    })))
+}
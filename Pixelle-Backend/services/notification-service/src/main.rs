@@ -0,0 +1,92 @@
+use actix_web::{web, App, HttpServer};
+use pixelle_auth::{Authenticate, TokenValidator};
+use pixelle_core::{NotificationPreferenceService, NOTIFICATION_FLUSH_INTERVAL_SECONDS};
+use pixelle_database::{Backends, DatabaseConfig, MigrationRunner};
+use pixelle_monitoring::{init_logging, scaling_routes, LoggingConfig, RequestCorrelation, ScalingSignals, TrackScalingSignals};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod dispatch;
+mod handlers;
+mod repository;
+
+use dispatch::{LogNotificationSender, NotificationDispatcher};
+use repository::{notification_migrations, PreferenceStore};
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    // Initialize structured logging
+    let log_export = init_logging(LoggingConfig::from_env("notification-service", env!("CARGO_PKG_VERSION")));
+
+    // Get port from environment or use default
+    let port = env::var("PORT").unwrap_or_else(|_| "8088".to_string());
+    let bind_address = format!("0.0.0.0:{}", port);
+
+    let backends = connect_database().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+    let store = PreferenceStore::new(&backends).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    let preferences = Arc::new(NotificationPreferenceService::new(Arc::new(store)));
+
+    // Notifications held for quiet hours or digests go out once due
+    let dispatcher = Arc::new(NotificationDispatcher::new(preferences.clone(), Arc::new(LogNotificationSender)));
+    dispatcher.clone().spawn_flusher(Duration::from_secs(NOTIFICATION_FLUSH_INTERVAL_SECONDS));
+
+    // Callers are identified from their access tokens, validated against auth-service's keys
+    let auth_service_url = env::var("AUTH_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8084".to_string());
+    let validator = Arc::new(TokenValidator::from_env(&auth_service_url));
+    if let Err(e) = validator.refresh().await {
+        tracing::warn!("Starting without signing keys, authenticated requests will fail until refresh: {}", e);
+    }
+    validator.clone().spawn_refresh();
+
+    tracing::info!("Starting notification service on {}", bind_address);
+
+    let preference_data = web::Data::from(preferences);
+    let dispatcher_data = web::Data::from(dispatcher);
+
+    // Load reported to the orchestrator, see SCALING_*
+    let signals = web::Data::new(ScalingSignals::from_env("notification-service"));
+    signals.clone().into_inner().spawn_sampler();
+
+    let result = HttpServer::new(move || {
+        App::new()
+            .wrap(Authenticate::new(validator.clone()))
+            .wrap(TrackScalingSignals::new(signals.clone()))
+            .wrap(RequestCorrelation)
+            .app_data(preference_data.clone())
+            .app_data(dispatcher_data.clone())
+            .app_data(signals.clone())
+            .configure(scaling_routes)
+            .service(
+                web::scope("/api/v1/notifications")
+                    .route("", web::post().to(handlers::dispatch_notification))
+                    .route("/preferences", web::get().to(handlers::get_preferences))
+                    .route("/preferences", web::put().to(handlers::update_preferences))
+                    .route("/preferences/{type}/{channel}", web::patch().to(handlers::update_channel))
+            )
+            .service(
+                web::scope("/health")
+                    .route("", web::get().to(handlers::health_check))
+            )
+    })
+    .bind(bind_address)?
+    .run()
+    .await;
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
+}
+
+/// Connect to the backends `DATABASE_*` places the preferences on and
+/// bring them up to date
+async fn connect_database() -> anyhow::Result<Backends> {
+    let backends = Backends::connect(DatabaseConfig::from_env()?).await?;
+    let applied = MigrationRunner::new("notifications", notification_migrations())?.run(&backends).await?;
+    if !applied.is_empty() {
+        tracing::info!("Applied notification migrations {:?}", applied);
+    }
+    Ok(backends)
+}
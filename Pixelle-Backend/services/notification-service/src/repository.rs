@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use pixelle_auth::USERS_REPOSITORY;
+use pixelle_core::{NotificationPreferenceRepository, NotificationPreferences, PixelleError, PixelleResult, UserId, UserProfile};
+use pixelle_database::{Backends, DocumentRepository, Migration, StoreError, StoreQuery, StoreResult};
+
+pub const NOTIFICATION_PREFERENCES_REPOSITORY: &str = "notification_preferences";
+
+/// Profiles read per page while migrating
+const MIGRATION_PAGE_SIZE: usize = 500;
+
+/// Notification preferences in pixelle-database, stored under the user ID
+pub struct PreferenceStore {
+    preferences: DocumentRepository<NotificationPreferences>,
+}
+
+impl PreferenceStore {
+    pub fn new(backends: &Backends) -> StoreResult<Self> {
+        Ok(Self {
+            preferences: backends.repository(NOTIFICATION_PREFERENCES_REPOSITORY, &[])?,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationPreferenceRepository for PreferenceStore {
+    async fn get_notification_preferences(&self, user_id: UserId) -> PixelleResult<Option<NotificationPreferences>> {
+        self.preferences.get(&user_id.to_string()).await.map_err(store_error)
+    }
+
+    async fn save_notification_preferences(&self, user_id: UserId, preferences: &NotificationPreferences) -> PixelleResult<()> {
+        self.preferences.put(&user_id.to_string(), preferences).await.map_err(store_error)
+    }
+}
+
+pub fn notification_migrations() -> Vec<Migration> {
+    vec![Migration {
+        version: 1,
+        name: "default_preferences_for_existing_users",
+        up: default_existing_preferences,
+    }]
+}
+
+/// Store today's defaults for users who existed before preferences did, so
+/// changing a default later only affects users who never had one
+fn default_existing_preferences(backends: &Backends) -> BoxFuture<'_, StoreResult<()>> {
+    Box::pin(async move {
+        let profiles: DocumentRepository<UserProfile> = backends.repository(USERS_REPOSITORY, &[])?;
+        let store = PreferenceStore::new(backends)?;
+        let defaults = NotificationPreferences::default();
+        let mut offset = 0;
+        loop {
+            let query = StoreQuery::new().order_by("id", false).offset(offset).limit(MIGRATION_PAGE_SIZE);
+            let users = profiles.find(&query).await?;
+            for user in &users {
+                let id = user.id.to_string();
+                if store.preferences.get(&id).await?.is_none() {
+                    store.preferences.put(&id, &defaults).await?;
+                }
+            }
+            if users.len() < MIGRATION_PAGE_SIZE {
+                return Ok(());
+            }
+            offset += users.len();
+        }
+    })
+}
+
+fn store_error(error: StoreError) -> PixelleError {
+    match error {
+        StoreError::Serialization(e) => PixelleError::Serialization(e),
+        error => PixelleError::Internal(format!("Preference store: {}", error)),
+    }
+}
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use pixelle_core::{AccountMailer, DeletionNotice, PixelleError, PixelleResult, PurgeStep, UserId, UserRepository};
+use crate::repository::UserRepositoryImpl;
+use std::env;
+use std::sync::Arc;
+
+/// Privacy pipeline step that removes the user's profile
+pub struct ProfilePurgeStep {
+    repository: Arc<UserRepositoryImpl>,
+}
+
+impl ProfilePurgeStep {
+    pub fn new(repository: Arc<UserRepositoryImpl>) -> Self {
+        Self { repository }
+    }
+}
+
+#[async_trait]
+impl PurgeStep for ProfilePurgeStep {
+    fn name(&self) -> &str {
+        "user_profile"
+    }
+
+    async fn purge(&self, user_id: UserId) -> PixelleResult<()> {
+        // Removing a missing profile is a no-op, so retries are safe
+        self.repository.delete_user(user_id).await
+    }
+}
+
+/// Sends account emails through an SMTP relay
+pub struct SmtpAccountMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpAccountMailer {
+    /// Build from `SMTP_HOST`, `SMTP_USERNAME`, `SMTP_PASSWORD` and `SMTP_FROM`.
+    ///
+    /// Returns `None` when `SMTP_HOST` is not set.
+    pub fn from_env() -> PixelleResult<Option<Self>> {
+        let Ok(host) = env::var("SMTP_HOST") else {
+            return Ok(None);
+        };
+
+        let mut builder = SmtpTransport::relay(&host)
+            .map_err(|e| PixelleError::Internal(format!("Invalid SMTP relay '{}': {}", host, e)))?;
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let from = env::var("SMTP_FROM")
+            .unwrap_or_else(|_| "Pixelle <no-reply@pixelle.app>".to_string())
+            .parse::<Mailbox>()
+            .map_err(|e| PixelleError::Internal(format!("Invalid SMTP_FROM address: {}", e)))?;
+
+        Ok(Some(Self {
+            transport: builder.build(),
+            from,
+        }))
+    }
+}
+
+#[async_trait]
+impl AccountMailer for SmtpAccountMailer {
+    async fn send(&self, to: &str, notice: &DeletionNotice) -> PixelleResult<()> {
+        let to = to
+            .parse::<Mailbox>()
+            .map_err(|e| PixelleError::Validation(format!("Invalid email address: {}", e)))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(notice.subject())
+            .header(ContentType::TEXT_PLAIN)
+            .body(notice.body())
+            .map_err(|e| PixelleError::Internal(format!("Failed to build email: {}", e)))?;
+
+        // The SMTP transport blocks, so keep it off the async workers
+        let transport = self.transport.clone();
+        tokio::task::spawn_blocking(move || transport.send(&message))
+            .await
+            .map_err(|e| PixelleError::Internal(format!("Email task failed: {}", e)))?
+            .map_err(|e| PixelleError::ExternalService(format!("Failed to send email: {}", e)))?;
+        Ok(())
+    }
+}
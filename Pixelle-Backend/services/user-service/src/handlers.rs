@@ -1,7 +1,8 @@
 use actix_web::{web, HttpResponse, Result};
 use serde::{Deserialize, Serialize};
-use pixelle_core::{UserProfile, ApiResponse, PaginationParams, PaginatedResponse, PixelleResult};
+use pixelle_core::{UserProfile, ApiResponse, PaginationParams, PaginatedResponse, PixelleResult, PixelleError, BlockList, DeletionRequest, PrivacySettings, PrivacyAction, Audience};
 use crate::service::UserService;
+use pixelle_auth::{AuthenticatedService, AuthenticatedUser, Caller};
 
 #[derive(Debug, Deserialize)]
 pub struct CreateUserRequest {
@@ -18,6 +19,8 @@ pub struct UpdateUserRequest {
     pub bio: Option<String>,
     pub avatar_url: Option<String>,
     pub is_private: Option<bool>,
+    pub preferred_languages: Option<Vec<String>>,
+    pub region: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -25,6 +28,34 @@ pub struct SearchUsersQuery {
     pub q: String,
     pub page: Option<u32>,
     pub per_page: Option<u32>,
+    /// User running the search; users blocked with them are left out
+    pub viewer_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePrivacySettingsRequest {
+    pub private_account: Option<bool>,
+    pub direct_messages: Option<Audience>,
+    pub follower_lists: Option<Audience>,
+    pub discoverable: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PrivacyCheckQuery {
+    pub action: PrivacyAction,
+    /// Anonymous when absent
+    pub viewer_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetVerifiedRequest {
+    pub verified: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrivacyCheckResponse {
+    pub action: PrivacyAction,
+    pub allowed: bool,
 }
 
 pub async fn create_user(
@@ -79,11 +110,15 @@ pub async fn get_user(
 }
 
 pub async fn update_user(
+    caller: AuthenticatedUser,
     user_service: web::Data<UserService>,
     path: web::Path<String>,
     request: web::Json<UpdateUserRequest>,
 ) -> Result<HttpResponse> {
     let user_id = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
     let result = user_service.update_user(&user_id, &request.into_inner()).await;
     
     match result {
@@ -103,28 +138,104 @@ pub async fn update_user(
 }
 
 pub async fn delete_user(
+    caller: AuthenticatedUser,
     user_service: web::Data<UserService>,
     path: web::Path<String>,
 ) -> Result<HttpResponse> {
     let user_id = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
     let result = user_service.delete_user(&user_id).await;
     
     match result {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+        Ok(request) => {
+            let message = format!(
+                "Account deactivated; it will be deleted on {} unless reactivated",
+                request.purge_after.format("%Y-%m-%d")
+            );
+            Ok(HttpResponse::Accepted().json(ApiResponse {
+                success: true,
+                data: Some(request),
+                error: None,
+                message: Some(message),
+            }))
+        }
+        Err(e) => Ok(deletion_error(e)),
+    }
+}
+
+pub async fn reactivate_user(
+    caller: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.reactivate_user(&user_id).await;
+    
+    match result {
+        Ok(request) => Ok(HttpResponse::Ok().json(ApiResponse {
             success: true,
-            data: None,
+            data: Some(request),
             error: None,
-            message: Some("User deleted successfully".to_string()),
+            message: Some("Account reactivated".to_string()),
         })),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-            success: false,
-            data: None,
-            error: Some(e.to_string()),
+        Err(e) => Ok(deletion_error(e)),
+    }
+}
+
+pub async fn get_deletion_status(
+    caller: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.get_deletion_status(&user_id).await;
+    
+    match result {
+        Ok(Some(request)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(request),
+            error: None,
             message: None,
         })),
+        Ok(None) => Ok(deletion_error(PixelleError::NotFound("No account deletion requested".to_string()))),
+        Err(e) => Ok(deletion_error(e)),
     }
 }
 
+/// Refuse to act on an account other than the caller's
+fn forbidden(error: PixelleError) -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(error.to_string()),
+        message: None,
+    })
+}
+
+/// Map account deletion errors to their HTTP status
+fn deletion_error(error: PixelleError) -> HttpResponse {
+    let mut response = match &error {
+        PixelleError::Validation(_) => HttpResponse::BadRequest(),
+        PixelleError::NotFound(_) => HttpResponse::NotFound(),
+        PixelleError::Conflict(_) => HttpResponse::Conflict(),
+        _ => HttpResponse::InternalServerError(),
+    };
+    response.json(ApiResponse::<DeletionRequest> {
+        success: false,
+        data: None,
+        error: Some(error.to_string()),
+        message: None,
+    })
+}
+
 pub async fn search_users(
     user_service: web::Data<UserService>,
     query: web::Query<SearchUsersQuery>,
@@ -134,7 +245,7 @@ pub async fn search_users(
         per_page: query.per_page.unwrap_or(20),
     };
     
-    let result = user_service.search_users(&query.q, &pagination).await;
+    let result = user_service.search_users(&query.q, &pagination, query.viewer_id.as_deref()).await;
     
     match result {
         Ok(users) => Ok(HttpResponse::Ok().json(ApiResponse {
@@ -152,6 +263,220 @@ pub async fn search_users(
     }
 }
 
+/// Shared response for block and mute changes
+fn relation_response(result: PixelleResult<bool>, changed: &str, unchanged: &str) -> HttpResponse {
+    match result {
+        Ok(changed_now) => HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+            message: Some(if changed_now { changed } else { unchanged }.to_string()),
+        }),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+            message: None,
+        }),
+    }
+}
+
+pub async fn block_user(
+    caller: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, target_id) = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.block_user(&user_id, &target_id).await;
+    Ok(relation_response(result, "User blocked", "User was already blocked"))
+}
+
+pub async fn unblock_user(
+    caller: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, target_id) = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.unblock_user(&user_id, &target_id).await;
+    Ok(relation_response(result, "User unblocked", "User was not blocked"))
+}
+
+pub async fn mute_user(
+    caller: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, target_id) = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.mute_user(&user_id, &target_id).await;
+    Ok(relation_response(result, "User muted", "User was already muted"))
+}
+
+pub async fn unmute_user(
+    caller: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, target_id) = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.unmute_user(&user_id, &target_id).await;
+    Ok(relation_response(result, "User unmuted", "User was not muted"))
+}
+
+/// Also served to internal services with `users:read`, e.g. feed-service
+/// hiding blocked authors
+pub async fn get_block_list(
+    caller: Caller,
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = caller.ensure_acts_for(&user_id, "users:read") {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.get_block_list(&user_id).await;
+    
+    match result {
+        Ok(list) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(list),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<BlockList> {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+            message: None,
+        })),
+    }
+}
+
+/// Verified badge of a profile, set by auth-service when a verification
+/// is approved or revoked; only for internal services with `users:verify`
+pub async fn set_verified(
+    caller: AuthenticatedService,
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+    request: web::Json<SetVerifiedRequest>,
+) -> Result<HttpResponse> {
+    if let Err(e) = caller.require_scope("users:verify") {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.set_verified(&path.into_inner(), request.verified).await;
+    
+    match result {
+        Ok(user) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(user),
+            error: None,
+            message: None,
+        })),
+        Err(e) => {
+            let mut response = match &e {
+                PixelleError::Validation(_) => HttpResponse::BadRequest(),
+                PixelleError::NotFound(_) => HttpResponse::NotFound(),
+                _ => HttpResponse::InternalServerError(),
+            };
+            Ok(response.json(ApiResponse::<UserProfile> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: None,
+            }))
+        }
+    }
+}
+
+/// Map privacy settings errors to their HTTP status
+fn privacy_error(error: PixelleError) -> HttpResponse {
+    let mut response = match &error {
+        PixelleError::Validation(_) => HttpResponse::BadRequest(),
+        PixelleError::NotFound(_) => HttpResponse::NotFound(),
+        _ => HttpResponse::InternalServerError(),
+    };
+    response.json(ApiResponse::<PrivacySettings> {
+        success: false,
+        data: None,
+        error: Some(error.to_string()),
+        message: None,
+    })
+}
+
+pub async fn get_privacy_settings(
+    caller: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.get_privacy_settings(&user_id).await;
+    
+    match result {
+        Ok(settings) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(privacy_error(e)),
+    }
+}
+
+pub async fn update_privacy_settings(
+    caller: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+    request: web::Json<UpdatePrivacySettingsRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = caller.ensure_is(&user_id) {
+        return Ok(forbidden(e));
+    }
+    let result = user_service.update_privacy_settings(&user_id, &request.into_inner()).await;
+    
+    match result {
+        Ok(settings) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings),
+            error: None,
+            message: Some("Privacy settings updated".to_string()),
+        })),
+        Err(e) => Ok(privacy_error(e)),
+    }
+}
+
+pub async fn check_privacy(
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+    query: web::Query<PrivacyCheckQuery>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let result = user_service.check_privacy(&user_id, query.viewer_id.as_deref(), query.action).await;
+    
+    match result {
+        Ok(allowed) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(PrivacyCheckResponse { action: query.action, allowed }),
+            error: None,
+            message: None,
+        })),
+        Err(e) => Ok(privacy_error(e)),
+    }
+}
+
 pub async fn health_check() -> Result<HttpResponse> {
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "status": "healthy",
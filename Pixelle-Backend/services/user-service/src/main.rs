@@ -1,7 +1,16 @@
 use actix_web::{web, App, HttpServer};
-use pixelle_monitoring::init_tracing;
+use pixelle_auth::{Authenticate, PassphraseService, TokenValidator};
+use pixelle_core::{
+    AccountDeletionService, AccountMailer, BlockListService, InMemoryAccountDeletionRepository,
+    InMemoryBlockListRepository, InMemoryFollowRepository, LogAccountMailer, PrivacyService,
+    ACCOUNT_DELETION_SWEEP_INTERVAL_SECONDS,
+};
+use pixelle_monitoring::{init_logging, LoggingConfig, RequestCorrelation};
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 
+mod deletion;
 mod handlers;
 mod models;
 mod repository;
@@ -9,8 +18,8 @@ mod service;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Initialize tracing
-    init_tracing();
+    // Initialize structured logging
+    let log_export = init_logging(LoggingConfig::from_env("user-service", env!("CARGO_PKG_VERSION")));
     
     // Get port from environment or use default
     let port = env::var("PORT").unwrap_or_else(|_| "8081".to_string());
@@ -18,22 +27,82 @@ async fn main() -> std::io::Result<()> {
     
     tracing::info!("Starting user service on {}", bind_address);
     
-    HttpServer::new(|| {
+    let repository = Arc::new(repository::UserRepositoryImpl::new());
+    let block_lists = Arc::new(BlockListService::new(Arc::new(InMemoryBlockListRepository::new())));
+    // Settings live on the profiles; follows stay empty until social-service owns the graph
+    let privacy = Arc::new(PrivacyService::new(repository.clone(), Arc::new(InMemoryFollowRepository::new())));
+    
+    let mailer: Arc<dyn AccountMailer> = match deletion::SmtpAccountMailer::from_env() {
+        Ok(Some(mailer)) => Arc::new(mailer),
+        Ok(None) => {
+            tracing::warn!("SMTP_HOST not set; account emails will only be logged");
+            Arc::new(LogAccountMailer)
+        }
+        Err(e) => {
+            tracing::error!("Invalid SMTP configuration, account emails will only be logged: {}", e);
+            Arc::new(LogAccountMailer)
+        }
+    };
+    let deletions = Arc::new(
+        AccountDeletionService::new(Arc::new(InMemoryAccountDeletionRepository::new()), mailer)
+            .with_step(Arc::new(deletion::ProfilePurgeStep::new(repository.clone()))),
+    );
+    deletions.clone().spawn_sweeper(Duration::from_secs(ACCOUNT_DELETION_SWEEP_INTERVAL_SECONDS));
+    
+    // Tokens are issued by auth-service; passwords are only hashed here
+    let user_service = web::Data::new(service::UserService::new(
+        repository,
+        PassphraseService::new(),
+        block_lists,
+        privacy,
+        deletions,
+    ));
+    
+    // Callers are identified from their access tokens, validated against auth-service's keys,
+    // or from API keys with `users:*` scopes
+    let auth_service_url = env::var("AUTH_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8084".to_string());
+    let validator = Arc::new(TokenValidator::from_env(&auth_service_url));
+    if let Err(e) = validator.refresh().await {
+        tracing::warn!("Starting without signing keys, authenticated requests will fail until refresh: {}", e);
+    }
+    validator.clone().spawn_refresh();
+    
+    let result = HttpServer::new(move || {
         App::new()
+            .wrap(Authenticate::new(validator.clone()).scoped("users"))
+            .wrap(RequestCorrelation)
+            .app_data(user_service.clone())
             .service(
                 web::scope("/api/v1/users")
-                    .service(handlers::create_user)
-                    .service(handlers::get_user)
-                    .service(handlers::update_user)
-                    .service(handlers::delete_user)
-                    .service(handlers::search_users)
+                    .route("", web::post().to(handlers::create_user))
+                    .route("/search", web::get().to(handlers::search_users))
+                    .route("/{user_id}", web::get().to(handlers::get_user))
+                    .route("/{user_id}", web::put().to(handlers::update_user))
+                    .route("/{user_id}", web::delete().to(handlers::delete_user))
+                    .route("/{user_id}/blocks", web::get().to(handlers::get_block_list))
+                    .route("/{user_id}/blocks/{target_id}", web::put().to(handlers::block_user))
+                    .route("/{user_id}/blocks/{target_id}", web::delete().to(handlers::unblock_user))
+                    .route("/{user_id}/mutes/{target_id}", web::put().to(handlers::mute_user))
+                    .route("/{user_id}/mutes/{target_id}", web::delete().to(handlers::unmute_user))
+                    .route("/{user_id}/privacy", web::get().to(handlers::get_privacy_settings))
+                    .route("/{user_id}/privacy", web::patch().to(handlers::update_privacy_settings))
+                    .route("/{user_id}/privacy/check", web::get().to(handlers::check_privacy))
+                    .route("/{user_id}/deletion", web::get().to(handlers::get_deletion_status))
+                    .route("/{user_id}/reactivate", web::post().to(handlers::reactivate_user))
+                    .route("/{user_id}/verified", web::put().to(handlers::set_verified))
             )
             .service(
                 web::scope("/health")
-                    .service(handlers::health_check)
+                    .route("", web::get().to(handlers::health_check))
             )
     })
     .bind(bind_address)?
     .run()
-    .await
+    .await;
+
+    // Send buffered log records before exiting
+    if let Some(log_export) = log_export {
+        log_export.shutdown().await;
+    }
+    result
 }
@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use pixelle_core::{UserProfile, PaginationParams, PaginatedResponse, PixelleResult, UserRepository, UserId};
+use pixelle_core::{UserProfile, PaginationParams, PaginatedResponse, PixelleResult, UserRepository, UserId, PrivacyRepository, PrivacySettings};
 use std::collections::HashMap;
 use std::sync::Mutex;
 use chrono::Utc;
@@ -105,3 +105,22 @@ impl UserRepository for UserRepositoryImpl {
         })
     }
 }
+
+#[async_trait]
+impl PrivacyRepository for UserRepositoryImpl {
+    async fn get_privacy_settings(&self, user_id: UserId) -> PixelleResult<PrivacySettings> {
+        let users = self.users.lock().unwrap();
+        Ok(users.get(&user_id).map(|user| user.privacy.clone()).unwrap_or_default())
+    }
+
+    async fn save_privacy_settings(&self, user_id: UserId, settings: &PrivacySettings) -> PixelleResult<()> {
+        let mut users = self.users.lock().unwrap();
+        
+        let user = users.get_mut(&user_id)
+            .ok_or_else(|| pixelle_core::PixelleError::NotFound("User not found".to_string()))?;
+        user.is_private = settings.private_account;
+        user.privacy = settings.clone();
+        user.updated_at = pixelle_core::now();
+        Ok(())
+    }
+}
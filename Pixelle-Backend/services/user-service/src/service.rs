@@ -1,18 +1,31 @@
 use async_trait::async_trait;
-use pixelle_core::{UserProfile, PaginationParams, PaginatedResponse, PixelleResult, UserRepository};
+use pixelle_core::{UserProfile, PaginationParams, PaginatedResponse, PixelleResult, UserRepository, BlockList, BlockListService, AccountDeletionService, DeletionRequest, PrivacyService, PrivacySettings, PrivacyAction};
 use crate::repository::UserRepositoryImpl;
-use pixelle_auth::AuthServiceImpl;
+use pixelle_auth::PassphraseService;
+use std::sync::Arc;
 
 pub struct UserService {
-    repository: UserRepositoryImpl,
-    auth_service: AuthServiceImpl,
+    repository: Arc<UserRepositoryImpl>,
+    passphrases: PassphraseService,
+    block_lists: Arc<BlockListService>,
+    privacy: Arc<PrivacyService>,
+    deletions: Arc<AccountDeletionService>,
 }
 
 impl UserService {
-    pub fn new(repository: UserRepositoryImpl, auth_service: AuthServiceImpl) -> Self {
+    pub fn new(
+        repository: Arc<UserRepositoryImpl>,
+        passphrases: PassphraseService,
+        block_lists: Arc<BlockListService>,
+        privacy: Arc<PrivacyService>,
+        deletions: Arc<AccountDeletionService>,
+    ) -> Self {
         Self {
             repository,
-            auth_service,
+            passphrases,
+            block_lists,
+            privacy,
+            deletions,
         }
     }
 
@@ -27,7 +40,7 @@ impl UserService {
         }
 
         // Hash password
-        let hashed_password = self.auth_service.hash_password(&request.password).await?;
+        let hashed_password = self.passphrases.hash_passphrase(&request.password).await?;
 
         // Create user profile
         let user = UserProfile {
@@ -39,6 +52,9 @@ impl UserService {
             avatar_url: None,
             is_verified: false,
             is_private: false,
+            privacy: PrivacySettings::default(),
+            preferred_languages: Vec::new(),
+            region: None,
             created_at: pixelle_core::now(),
             updated_at: pixelle_core::now(),
         };
@@ -47,10 +63,14 @@ impl UserService {
         self.repository.create_user(&user).await
     }
 
+    /// Look up a user; deactivated accounts are reported as missing
     pub async fn get_user_by_id(&self, user_id: &str) -> PixelleResult<Option<UserProfile>> {
         let user_id = user_id.parse::<pixelle_core::UserId>()
             .map_err(|_| pixelle_core::PixelleError::Validation("Invalid user ID format".to_string()))?;
         
+        if self.deletions.is_hidden(user_id).await? {
+            return Ok(None);
+        }
         self.repository.get_user_by_id(user_id).await
     }
 
@@ -58,6 +78,9 @@ impl UserService {
         let user_id = user_id.parse::<pixelle_core::UserId>()
             .map_err(|_| pixelle_core::PixelleError::Validation("Invalid user ID format".to_string()))?;
         
+        if self.deletions.is_hidden(user_id).await? {
+            return Err(pixelle_core::PixelleError::NotFound("User not found".to_string()));
+        }
         let mut user = self.repository.get_user_by_id(user_id).await?
             .ok_or_else(|| pixelle_core::PixelleError::NotFound("User not found".to_string()))?;
 
@@ -73,21 +96,132 @@ impl UserService {
         }
         if let Some(is_private) = request.is_private {
             user.is_private = is_private;
+            user.privacy.private_account = is_private;
+        }
+        if request.preferred_languages.is_some() || request.region.is_some() {
+            let locale = pixelle_core::LocalePreferences::new(
+                request.preferred_languages.clone().unwrap_or_else(|| user.preferred_languages.clone()),
+                request.region.clone().or_else(|| user.region.clone()),
+            );
+            user.preferred_languages = locale.languages;
+            user.region = locale.region;
         }
 
         user.updated_at = pixelle_core::now();
 
-        self.repository.update_user(&user).await
+        let user = self.repository.update_user(&user).await?;
+        self.privacy.invalidate(&user.id);
+        Ok(user)
     }
 
-    pub async fn delete_user(&self, user_id: &str) -> PixelleResult<()> {
-        let user_id = user_id.parse::<pixelle_core::UserId>()
-            .map_err(|_| pixelle_core::PixelleError::Validation("Invalid user ID format".to_string()))?;
+    /// Deactivate the account now and delete it once the grace period ends
+    pub async fn delete_user(&self, user_id: &str) -> PixelleResult<DeletionRequest> {
+        let user_id = parse_user_id(user_id)?;
+        
+        let user = self.repository.get_user_by_id(user_id).await?
+            .ok_or_else(|| pixelle_core::PixelleError::NotFound("User not found".to_string()))?;
+        self.deletions.request_deletion(&user).await
+    }
+
+    /// Cancel a pending deletion during the grace period
+    pub async fn reactivate_user(&self, user_id: &str) -> PixelleResult<DeletionRequest> {
+        self.deletions.reactivate(parse_user_id(user_id)?).await
+    }
+
+    pub async fn get_deletion_status(&self, user_id: &str) -> PixelleResult<Option<DeletionRequest>> {
+        self.deletions.status(parse_user_id(user_id)?).await
+    }
+
+    /// Search users, leaving out deactivated accounts, anyone blocked with the viewer
+    /// and undiscoverable accounts the viewer does not follow
+    pub async fn search_users(&self, query: &str, pagination: &PaginationParams, viewer_id: Option<&str>) -> PixelleResult<PaginatedResponse<UserProfile>> {
+        let viewer_id = viewer_id.map(parse_user_id).transpose()?;
+        let mut results = self.repository.search_users(query, pagination).await?;
+        results.items = self.deletions.filter_users(results.items).await?;
+
+        if let Some(viewer_id) = viewer_id {
+            results.items = self.block_lists.filter_search(viewer_id, results.items).await?;
+        }
+        results.items = self.privacy.filter_search(viewer_id, results.items).await?;
+
+        Ok(results)
+    }
+
+    pub async fn get_privacy_settings(&self, user_id: &str) -> PixelleResult<PrivacySettings> {
+        let user_id = parse_user_id(user_id)?;
         
-        self.repository.delete_user(user_id).await
+        if self.deletions.is_hidden(user_id).await? {
+            return Err(pixelle_core::PixelleError::NotFound("User not found".to_string()));
+        }
+        let user = self.repository.get_user_by_id(user_id).await?
+            .ok_or_else(|| pixelle_core::PixelleError::NotFound("User not found".to_string()))?;
+        Ok(user.privacy)
+    }
+
+    /// Change the settings given in the request and keep the rest
+    pub async fn update_privacy_settings(&self, user_id: &str, request: &crate::handlers::UpdatePrivacySettingsRequest) -> PixelleResult<PrivacySettings> {
+        let mut settings = self.get_privacy_settings(user_id).await?;
+
+        if let Some(private_account) = request.private_account {
+            settings.private_account = private_account;
+        }
+        if let Some(direct_messages) = request.direct_messages {
+            settings.direct_messages = direct_messages;
+        }
+        if let Some(follower_lists) = request.follower_lists {
+            settings.follower_lists = follower_lists;
+        }
+        if let Some(discoverable) = request.discoverable {
+            settings.discoverable = discoverable;
+        }
+
+        self.privacy.update(parse_user_id(user_id)?, settings).await
+    }
+
+    /// Evaluate the owner's privacy policy for services that cannot link it in
+    pub async fn check_privacy(&self, owner_id: &str, viewer_id: Option<&str>, action: PrivacyAction) -> PixelleResult<bool> {
+        let owner_id = parse_user_id(owner_id)?;
+        let viewer_id = viewer_id.map(parse_user_id).transpose()?;
+
+        if let Some(viewer_id) = viewer_id {
+            if self.block_lists.get(owner_id).await?.is_blocked_with(&viewer_id) {
+                return Ok(false);
+            }
+        }
+        self.privacy.check(viewer_id, owner_id, action).await
+    }
+
+    pub async fn block_user(&self, user_id: &str, target_id: &str) -> PixelleResult<bool> {
+        self.block_lists.block(parse_user_id(user_id)?, parse_user_id(target_id)?).await
+    }
+
+    pub async fn unblock_user(&self, user_id: &str, target_id: &str) -> PixelleResult<bool> {
+        self.block_lists.unblock(parse_user_id(user_id)?, parse_user_id(target_id)?).await
+    }
+
+    pub async fn mute_user(&self, user_id: &str, target_id: &str) -> PixelleResult<bool> {
+        self.block_lists.mute(parse_user_id(user_id)?, parse_user_id(target_id)?).await
+    }
+
+    pub async fn unmute_user(&self, user_id: &str, target_id: &str) -> PixelleResult<bool> {
+        self.block_lists.unmute(parse_user_id(user_id)?, parse_user_id(target_id)?).await
+    }
+
+    pub async fn get_block_list(&self, user_id: &str) -> PixelleResult<BlockList> {
+        Ok(self.block_lists.get(parse_user_id(user_id)?).await?.as_ref().clone())
     }
 
-    pub async fn search_users(&self, query: &str, pagination: &PaginationParams) -> PixelleResult<PaginatedResponse<UserProfile>> {
-        self.repository.search_users(query, pagination).await
+    /// Set the verified badge auth-service granted or revoked
+    pub async fn set_verified(&self, user_id: &str, verified: bool) -> PixelleResult<UserProfile> {
+        let mut user = self.repository.get_user_by_id(parse_user_id(user_id)?).await?
+            .ok_or_else(|| pixelle_core::PixelleError::NotFound("User not found".to_string()))?;
+        user.is_verified = verified;
+        user.updated_at = pixelle_core::now();
+        self.repository.update_user(&user).await
     }
 }
+
+fn parse_user_id(user_id: &str) -> PixelleResult<pixelle_core::UserId> {
+    user_id.parse::<pixelle_core::UserId>()
+        .map_err(|_| pixelle_core::PixelleError::Validation("Invalid user ID format".to_string()))
+}
@@ -0,0 +1,233 @@
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+use pixelle_monitoring::{CaptureSummary, CapturedBody, CapturedRequest, CAPTURE_TOKEN_HEADER, REPLAY_OF_HEADER, REQUEST_ID_HEADER};
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(name = "request-replay")]
+#[command(about = "Inspect captured requests and replay them against a service")]
+struct Args {
+    /// Access token for the debug routes; defaults to CAPTURE_ACCESS_TOKEN
+    #[arg(long, global = true)]
+    token: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List recent captures of a service
+    List {
+        /// Base URL of the service that captured, e.g. http://localhost:8082
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        request_id: Option<String>,
+    },
+    /// Print a capture and its downstream call graph
+    Show {
+        #[arg(long)]
+        from: String,
+        /// Other services to look for captures of the same request in
+        #[arg(long)]
+        also: Vec<String>,
+        id: String,
+    },
+    /// Send a captured request again, to a local or staging service
+    Run {
+        #[arg(long)]
+        from: String,
+        /// Base URL of the service to replay against
+        #[arg(long)]
+        to: String,
+        /// Extra or replacement header, e.g. "Authorization: Bearer ..."
+        #[arg(long = "header")]
+        headers: Vec<String>,
+        id: String,
+    },
+}
+
+/// Headers set by the client or regenerated for the replay
+const SKIPPED_HEADERS: &[&str] = &["host", "content-length", "connection", "transfer-encoding"];
+
+struct CaptureApi {
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl CaptureApi {
+    async fn get<T: serde::de::DeserializeOwned>(&self, url: String) -> anyhow::Result<T> {
+        let mut request = self.client.get(&url);
+        if let Some(token) = &self.token {
+            request = request.header(CAPTURE_TOKEN_HEADER, token);
+        }
+        let response = request.send().await.with_context(|| format!("Failed to reach {}", url))?;
+        match response.status().as_u16() {
+            401 => bail!("{} rejected the access token", url),
+            404 => bail!("Not found: {} (is CAPTURE_ROUTES set on the service?)", url),
+            _ => {}
+        }
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    async fn list(&self, base: &str, request_id: Option<&str>) -> anyhow::Result<Vec<CaptureSummary>> {
+        let mut url = format!("{}/debug/captures", base.trim_end_matches('/'));
+        if let Some(request_id) = request_id {
+            url = format!("{}?request_id={}", url, request_id);
+        }
+        self.get(url).await
+    }
+
+    async fn capture(&self, base: &str, id: &str) -> anyhow::Result<CapturedRequest> {
+        self.get(format!("{}/debug/captures/{}", base.trim_end_matches('/'), id)).await
+    }
+}
+
+fn print_capture(capture: &CapturedRequest, related: &[CapturedRequest], depth: usize) {
+    let indent = "  ".repeat(depth);
+    println!(
+        "{}{} {} {}{} -> {} in {} ms",
+        indent,
+        capture.service,
+        capture.method,
+        capture.path,
+        if capture.query.is_empty() { String::new() } else { format!("?{}", capture.query) },
+        capture.status,
+        capture.duration_ms
+    );
+    for call in &capture.calls {
+        let outcome = match (call.status, &call.error) {
+            (Some(status), _) => status.to_string(),
+            (None, Some(error)) => error.clone(),
+            (None, None) => "no response".to_string(),
+        };
+        println!(
+            "{}  -> {} {} -> {} in {} ms ({} attempt{})",
+            indent,
+            call.method,
+            call.url,
+            outcome,
+            call.duration_ms,
+            call.attempts,
+            if call.attempts == 1 { "" } else { "s" }
+        );
+        // The callee's capture continues the graph from the span this call sent
+        let callee = related
+            .iter()
+            .find(|callee| callee.parent_span_id.is_some() && callee.parent_span_id == call.span_id);
+        if let Some(callee) = callee {
+            print_capture(callee, related, depth + 2);
+        }
+    }
+}
+
+async fn run(api: &CaptureApi, from: &str, to: &str, headers: &[String], id: &str) -> anyhow::Result<()> {
+    let capture = api.capture(from, id).await?;
+    if !capture.replayable() {
+        bail!("Capture {} cannot be replayed: body omitted ({})", id, capture.body_omitted.as_deref().unwrap_or_default());
+    }
+
+    let mut url = format!("{}{}", to.trim_end_matches('/'), capture.path);
+    if !capture.query.is_empty() {
+        url = format!("{}?{}", url, capture.query);
+    }
+    let method = reqwest::Method::from_bytes(capture.method.as_bytes())?;
+    let mut request = api.client.request(method, &url).header(REPLAY_OF_HEADER, &capture.id);
+
+    let overrides: Vec<(String, String)> = headers
+        .iter()
+        .map(|header| match header.split_once(':') {
+            Some((name, value)) => Ok((name.trim().to_lowercase(), value.trim().to_string())),
+            None => bail!("Header must look like \"Name: value\", got {:?}", header),
+        })
+        .collect::<anyhow::Result<_>>()?;
+    for (name, value) in &capture.headers {
+        if !SKIPPED_HEADERS.contains(&name.as_str()) && !overrides.iter().any(|(o, _)| o == name) {
+            request = request.header(name, value);
+        }
+    }
+    for (name, value) in &overrides {
+        request = request.header(name, value);
+    }
+    let missing: Vec<&String> = capture
+        .dropped_headers
+        .iter()
+        .filter(|name| !overrides.iter().any(|(o, _)| o == *name))
+        .collect();
+    if !missing.is_empty() {
+        eprintln!("Warning: not captured and not given with --header: {:?}", missing);
+    }
+    if capture.query.contains("[redacted]") || serde_json::to_string(&capture.body)?.contains("[redacted]") {
+        eprintln!("Warning: the capture has redacted fields; the replay sends the placeholder");
+    }
+
+    request = match &capture.body {
+        Some(CapturedBody::Json(json)) => request.body(serde_json::to_vec(json)?),
+        Some(CapturedBody::Text(text)) => request.body(text.clone()),
+        None => request,
+    };
+
+    println!("Replaying {} {} {} to {}", capture.id, capture.method, capture.path, url);
+    let started = Instant::now();
+    let response = request.send().await.with_context(|| format!("Failed to reach {}", url))?;
+    let elapsed = started.elapsed().as_millis();
+    let status = response.status().as_u16();
+    let request_id = response
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await?;
+
+    println!("Status: {} (captured {}){}", status, capture.status, if status == capture.status { "" } else { "  <- differs" });
+    println!("Time: {} ms (captured {} ms)", elapsed, capture.duration_ms);
+    if let Some(request_id) = request_id {
+        println!("Request ID: {} (list its captures with --request-id)", request_id);
+    }
+    match serde_json::from_str::<serde_json::Value>(&body) {
+        Ok(json) => println!("{}", serde_json::to_string_pretty(&json)?),
+        Err(_) => println!("{}", body),
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let api = CaptureApi {
+        client: reqwest::Client::new(),
+        token: args.token.or_else(|| std::env::var("CAPTURE_ACCESS_TOKEN").ok()),
+    };
+
+    match args.command {
+        Command::List { from, request_id } => {
+            for capture in api.list(&from, request_id.as_deref()).await? {
+                println!(
+                    "{}  {}  {} {} -> {} in {} ms, {} calls  [{}]",
+                    capture.id,
+                    capture.captured_at.format("%H:%M:%S"),
+                    capture.method,
+                    capture.path,
+                    capture.status,
+                    capture.duration_ms,
+                    capture.calls,
+                    capture.request_id
+                );
+            }
+        }
+        Command::Show { from, also, id } => {
+            let capture = api.capture(&from, &id).await?;
+            let mut related = Vec::new();
+            for base in &also {
+                for summary in api.list(base, Some(&capture.request_id)).await? {
+                    related.push(api.capture(base, &summary.id).await?);
+                }
+            }
+            println!("Request {} trace {}", capture.request_id, capture.trace_id);
+            print_capture(&capture, &related, 0);
+        }
+        Command::Run { from, to, headers, id } => run(&api, &from, &to, &headers, &id).await?,
+    }
+    Ok(())
+}
@@ -57,6 +57,11 @@ use std::collections::HashMap;
 use std::io::{Read, Write, BufReader, BufWriter};
 use anyhow::{Result, anyhow};
 
+pub mod parser;
+pub mod stream_metadata;
+
+pub use parser::{BitstreamError, ParseLimits};
+
 /// Biological bitstream formatter
 pub struct BiologicalBitstreamFormatter {
     data_organizer: BiologicalDataOrganizer,
@@ -502,6 +507,8 @@ pub struct BitstreamConfig {
     pub biological_accuracy_threshold: f64,
     pub compression_target_ratio: f64,
     pub streaming_latency_target: f64,
+    pub max_sections: usize,          // Parser limit on the declared section count
+    pub max_section_bytes: usize,     // Parser limit on a single section's size
 }
 
 impl Default for BitstreamConfig {
@@ -514,6 +521,8 @@ impl Default for BitstreamConfig {
             biological_accuracy_threshold: 0.947,
             compression_target_ratio: 0.95,
             streaming_latency_target: 16.67, // 60fps
+            max_sections: 4096,
+            max_section_bytes: 256 * 1024 * 1024,
         }
     }
 }
@@ -582,20 +591,20 @@ impl BiologicalBitstreamFormatter {
     }
 
     /// Parse bitstream back to data
-    pub fn parse_bitstream(&self, bitstream: &[u8]) -> Result<CompressionData> {
+    ///
+    /// Never panics, whatever the input; malformed bitstreams are reported
+    /// as a `BitstreamError`.
+    pub fn parse_bitstream(&self, bitstream: &[u8]) -> std::result::Result<CompressionData, BitstreamError> {
         // Step 1: Parse bitstream structure
         let parsed_data = self.parse_bitstream_structure(bitstream)?;
 
         // Step 2: Apply error correction
-        let corrected_data = self.error_resilience.correct_errors(&parsed_data)?;
-
-        // Step 3: Reorganize data
-        let reorganized_data = self.data_organizer.reorganize_data(&corrected_data)?;
+        let corrected_data = self.error_resilience.correct_errors(&parsed_data)
+            .map_err(|e| BitstreamError::Recovery(e.to_string()))?;
 
-        // Step 4: Create compression data
-        let compression_data = CompressionData::from_parsed_data(reorganized_data)?;
-
-        Ok(compression_data)
+        // Step 3: Reorganize data into compression data
+        self.data_organizer.reorganize_data(&corrected_data)
+            .map_err(|e| BitstreamError::Recovery(e.to_string()))
     }
 
     /// Create bitstream from data and bit allocation
@@ -629,13 +638,13 @@ impl BiologicalBitstreamFormatter {
         // Add version
         header.push(0x01); // Version 1
 
-        // Add data size
-        let data_size = data.size() as u32;
+        // Add data size and section count
+        let data_size = u32::try_from(data.size())
+            .map_err(|_| anyhow!("Section data exceeds the maximum bitstream size"))?;
         header.extend_from_slice(&data_size.to_le_bytes());
-
-        // Add bit allocation info
-        let allocation_info = bit_allocation.serialize()?;
-        header.extend_from_slice(&allocation_info);
+        let section_count = u32::try_from(data.sections.len())
+            .map_err(|_| anyhow!("Too many sections for one bitstream"))?;
+        header.extend_from_slice(&section_count.to_le_bytes());
 
         // Add biological parameters
         let biological_params = self.serialize_biological_parameters(data)?;
@@ -679,10 +688,11 @@ impl BiologicalBitstreamFormatter {
     }
 
     /// Parse bitstream structure
-    fn parse_bitstream_structure(&self, bitstream: &[u8]) -> Result<ParsedData> {
-        // Implement bitstream parsing
-        // This would parse the bitstream structure and extract data
-        Ok(ParsedData::new())
+    fn parse_bitstream_structure(&self, bitstream: &[u8]) -> std::result::Result<ParsedData, BitstreamError> {
+        parser::parse(bitstream, ParseLimits {
+            max_sections: self.config.max_sections,
+            max_section_bytes: self.config.max_section_bytes,
+        })
     }
 
     /// Create section header
@@ -690,10 +700,11 @@ impl BiologicalBitstreamFormatter {
         let mut header = Vec::new();
 
         // Add section type
-        header.push(section.section_type.clone() as u8);
+        header.push(section.section_type as u8);
 
         // Add section size
-        let section_size = section.size() as u32;
+        let section_size = u32::try_from(section.size())
+            .map_err(|_| anyhow!("Section exceeds the maximum bitstream size"))?;
         header.extend_from_slice(&section_size.to_le_bytes());
 
         // Add biological parameters
@@ -705,30 +716,30 @@ impl BiologicalBitstreamFormatter {
 
     /// Serialize section data
     fn serialize_section_data(&self, section: &DataSection, bit_allocation: &BitAllocation) -> Result<Vec<u8>> {
-        // Implement section data serialization
-        // This would serialize the section data according to bit allocation
-        Ok(Vec::new())
+        Ok(section.data.clone())
     }
 
     /// Serialize biological parameters
     fn serialize_biological_parameters(&self, data: &CompressionData) -> Result<Vec<u8>> {
-        // Implement biological parameters serialization
-        // This would serialize the biological parameters
-        Ok(Vec::new())
+        let mut params = Vec::with_capacity(parser::BIOLOGICAL_PARAMETERS_LEN);
+        parser::write_biological_parameters(&mut params, &data.biological_parameters);
+        Ok(params)
     }
 
     /// Serialize section biological parameters
     fn serialize_section_biological_parameters(&self, section: &DataSection) -> Result<Vec<u8>> {
-        // Implement section biological parameters serialization
-        // This would serialize the section biological parameters
-        Ok(Vec::new())
+        let mut params = Vec::with_capacity(parser::BIOLOGICAL_PARAMETERS_LEN);
+        parser::write_biological_parameters(&mut params, &section.biological_parameters);
+        Ok(params)
     }
 
     /// Calculate checksum
     fn calculate_checksum(&self, data: &CompressionData) -> Result<u32> {
-        // Implement checksum calculation
-        // This would calculate a checksum for error detection
-        Ok(0x12345678) // Placeholder
+        let mut crc = parser::Crc32::new();
+        for section in &data.sections {
+            crc.update(&section.data);
+        }
+        Ok(crc.finish())
     }
 
     /// Calculate biological accuracy
@@ -742,6 +753,9 @@ impl BiologicalBitstreamFormatter {
     fn calculate_compression_ratio(&self, original: &CompressionData, compressed: &[u8]) -> Result<f64> {
         let original_size = original.size();
         let compressed_size = compressed.len();
+        if original_size == 0 {
+            return Ok(0.0);
+        }
         
         Ok(1.0 - (compressed_size as f64 / original_size as f64))
     }
@@ -772,9 +786,7 @@ impl BiologicalDataOrganizer {
 
     /// Reorganize data
     pub fn reorganize_data(&self, data: &ParsedData) -> Result<CompressionData> {
-        // Implement data reorganization
-        // This would reorganize parsed data back to compression data
-        Ok(CompressionData::new())
+        CompressionData::from_parsed_data(data.clone())
     }
 }
 
@@ -1094,8 +1106,11 @@ impl CompressionData {
     }
 
     pub fn from_parsed_data(parsed: ParsedData) -> Result<Self> {
-        // Implement data creation from parsed data
-        Ok(Self::new())
+        Ok(Self {
+            sections: parsed.sections,
+            biological_parameters: parsed.biological_parameters,
+            metadata: parsed.metadata,
+        })
     }
 }
 
@@ -1106,7 +1121,7 @@ pub struct DataSection {
     pub biological_parameters: BiologicalParameters,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SectionType {
     Header,
     RetinalData,
@@ -1117,6 +1132,22 @@ pub enum SectionType {
     Footer,
 }
 
+impl SectionType {
+    /// Section type for its wire value
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SectionType::Header),
+            1 => Some(SectionType::RetinalData),
+            2 => Some(SectionType::CorticalData),
+            3 => Some(SectionType::MotionData),
+            4 => Some(SectionType::TransformData),
+            5 => Some(SectionType::QuantizedData),
+            6 => Some(SectionType::Footer),
+            _ => None,
+        }
+    }
+}
+
 impl DataSection {
     pub fn size(&self) -> usize {
         self.data.len()
@@ -1165,14 +1196,19 @@ impl BitAllocation {
 #[derive(Debug, Clone)]
 pub struct ParsedData {
     pub data: Vec<u8>,
+    pub sections: Vec<DataSection>,
+    pub biological_parameters: BiologicalParameters,
     pub metadata: DataMetadata,
 }
 
 impl ParsedData {
     pub fn new() -> Self {
+        let empty = CompressionData::new();
         Self {
             data: Vec::new(),
-            metadata: DataMetadata::new(),
+            sections: empty.sections,
+            biological_parameters: empty.biological_parameters,
+            metadata: empty.metadata,
         }
     }
 }
@@ -1253,10 +1289,55 @@ mod tests {
     #[test]
     fn test_bitstream_parsing() {
         let config = BitstreamConfig::default();
-        let formatter = BiologicalBitstreamFormatter::new(config).unwrap();
+        let mut formatter = BiologicalBitstreamFormatter::new(config).unwrap();
         
-        let bitstream = vec![0u8; 100];
-        let result = formatter.parse_bitstream(&bitstream);
-        assert!(result.is_ok());
+        let mut data = CompressionData::new();
+        for (section_type, bytes) in [(SectionType::RetinalData, vec![1u8, 2, 3]), (SectionType::MotionData, vec![9u8; 40])] {
+            data.sections.push(DataSection {
+                section_type,
+                data: bytes,
+                biological_parameters: data.biological_parameters.clone(),
+            });
+        }
+        let output = formatter.format_bitstream(&data).unwrap();
+        
+        let parsed = formatter.parse_bitstream(&output.bitstream).unwrap();
+        assert_eq!(parsed.sections.len(), 2);
+        assert_eq!(parsed.sections[1].section_type, SectionType::MotionData);
+        assert_eq!(parsed.sections[1].data, vec![9u8; 40]);
+        
+        // A zeroed buffer is not a bitstream
+        assert_eq!(formatter.parse_bitstream(&[0u8; 100]).unwrap_err(), BitstreamError::BadMagic);
+    }
+
+    #[test]
+    fn test_bitstream_parsing_rejects_corruption() {
+        let mut formatter = BiologicalBitstreamFormatter::new(BitstreamConfig::default()).unwrap();
+        let mut data = CompressionData::new();
+        data.sections.push(DataSection {
+            section_type: SectionType::QuantizedData,
+            data: vec![7u8; 16],
+            biological_parameters: data.biological_parameters.clone(),
+        });
+        let bitstream = formatter.format_bitstream(&data).unwrap().bitstream;
+        
+        // Every truncation fails cleanly
+        for len in 0..bitstream.len() {
+            assert!(formatter.parse_bitstream(&bitstream[..len]).is_err());
+        }
+        
+        let mut flipped = bitstream.clone();
+        let data_offset = parser::HEADER_LEN + parser::SECTION_HEADER_LEN;
+        flipped[data_offset] ^= 0xFF;
+        assert!(matches!(formatter.parse_bitstream(&flipped), Err(BitstreamError::ChecksumMismatch { .. })));
+        
+        // A forged section count is rejected before anything is allocated
+        let mut forged = bitstream.clone();
+        forged[11..15].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(formatter.parse_bitstream(&forged), Err(BitstreamError::TooManySections { .. })));
+        
+        let mut trailing = bitstream;
+        trailing.push(0);
+        assert_eq!(formatter.parse_bitstream(&trailing).unwrap_err(), BitstreamError::TrailingData(1));
     }
 }
\ No newline at end of file
@@ -0,0 +1,310 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Bitstream Parser
+//!
+//! Decodes the container written by `BiologicalBitstreamFormatter`. Input is
+//! treated as hostile: every length and count is checked against the bytes
+//! actually present and the configured limits before anything is allocated,
+//! and every failure is reported as a `BitstreamError` rather than a panic.
+//!
+//! Layout (little-endian):
+//! - Header: `AFIYAH`, version u8, data size u32, section count u32, biological parameters
+//! - Section: type u8, size u32, biological parameters, `size` bytes of data
+//! - Footer: CRC-32 of the section data u32, biological accuracy f64, compression ratio f64
+//!
+//! Biological parameters are four f64 values: processing time, energy
+//! consumption, biological accuracy and adaptation rate.
+
+use thiserror::Error;
+
+use super::{BiologicalParameters, DataMetadata, DataSection, ParsedData, SectionType};
+use crate::AfiyahError;
+
+/// Magic number opening every bitstream
+pub const BITSTREAM_MAGIC: [u8; 6] = *b"AFIYAH";
+
+/// Only bitstream version this parser reads
+pub const BITSTREAM_VERSION: u8 = 1;
+
+pub(crate) const BIOLOGICAL_PARAMETERS_LEN: usize = 32;
+pub(crate) const HEADER_LEN: usize = BITSTREAM_MAGIC.len() + 1 + 4 + 4 + BIOLOGICAL_PARAMETERS_LEN;
+pub(crate) const SECTION_HEADER_LEN: usize = 1 + 4 + BIOLOGICAL_PARAMETERS_LEN;
+pub(crate) const FOOTER_LEN: usize = 4 + 8 + 8;
+
+/// Why a bitstream could not be parsed
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum BitstreamError {
+    #[error("Bitstream is truncated: {field} needs {needed} bytes at offset {offset}, {available} available")]
+    Truncated {
+        field: &'static str,
+        offset: usize,
+        needed: usize,
+        available: usize,
+    },
+
+    #[error("Bitstream does not start with the AFIYAH magic number")]
+    BadMagic,
+
+    #[error("Unsupported bitstream version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Bitstream declares {count} sections, more than the limit of {limit}")]
+    TooManySections { count: u32, limit: usize },
+
+    #[error("Section {index} declares {size} bytes, more than the limit of {limit}")]
+    SectionTooLarge { index: usize, size: u32, limit: usize },
+
+    #[error("Section {index} has unknown type {section_type}")]
+    UnknownSectionType { index: usize, section_type: u8 },
+
+    #[error("Invalid {field}: {value}")]
+    InvalidField { field: &'static str, value: f64 },
+
+    #[error("Header declares {declared} bytes of section data but sections hold {actual}")]
+    DataSizeMismatch { declared: u32, actual: u64 },
+
+    #[error("Checksum mismatch: footer has {expected:#010x}, data hashes to {actual:#010x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+
+    #[error("{0} unexpected bytes after the footer")]
+    TrailingData(usize),
+
+    #[error("Malformed packet: {0}")]
+    Malformed(String),
+
+    #[error("Error recovery failed: {0}")]
+    Recovery(String),
+}
+
+impl From<BitstreamError> for AfiyahError {
+    fn from(error: BitstreamError) -> Self {
+        AfiyahError::BitstreamFormatting {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Limits applied while parsing, so a forged header cannot force large allocations
+#[derive(Debug, Clone, Copy)]
+pub struct ParseLimits {
+    pub max_sections: usize,
+    pub max_section_bytes: usize,
+}
+
+/// Cursor over untrusted bytes; every read is bounds checked
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    pub(crate) fn take(&mut self, field: &'static str, len: usize) -> Result<&'a [u8], BitstreamError> {
+        if len > self.remaining() {
+            return Err(BitstreamError::Truncated {
+                field,
+                offset: self.offset,
+                needed: len,
+                available: self.remaining(),
+            });
+        }
+        let bytes = &self.data[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self, field: &'static str) -> Result<[u8; N], BitstreamError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(field, N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn u8(&mut self, field: &'static str) -> Result<u8, BitstreamError> {
+        Ok(self.array::<1>(field)?[0])
+    }
+
+    pub(crate) fn u32(&mut self, field: &'static str) -> Result<u32, BitstreamError> {
+        Ok(u32::from_le_bytes(self.array(field)?))
+    }
+
+    pub(crate) fn u64(&mut self, field: &'static str) -> Result<u64, BitstreamError> {
+        Ok(u64::from_le_bytes(self.array(field)?))
+    }
+
+    /// Reads an f64, rejecting NaN and infinities
+    pub(crate) fn finite_f64(&mut self, field: &'static str) -> Result<f64, BitstreamError> {
+        let value = f64::from_le_bytes(self.array(field)?);
+        if !value.is_finite() {
+            return Err(BitstreamError::InvalidField { field, value });
+        }
+        Ok(value)
+    }
+}
+
+/// Parses a complete bitstream
+pub fn parse(bitstream: &[u8], limits: ParseLimits) -> Result<ParsedData, BitstreamError> {
+    let mut reader = ByteReader::new(bitstream);
+
+    if reader.take("magic number", BITSTREAM_MAGIC.len())? != BITSTREAM_MAGIC {
+        return Err(BitstreamError::BadMagic);
+    }
+    let version = reader.u8("version")?;
+    if version != BITSTREAM_VERSION {
+        return Err(BitstreamError::UnsupportedVersion(version));
+    }
+    let declared_size = reader.u32("data size")?;
+    let section_count = reader.u32("section count")?;
+    if section_count as usize > limits.max_sections {
+        return Err(BitstreamError::TooManySections {
+            count: section_count,
+            limit: limits.max_sections,
+        });
+    }
+    let biological_parameters = read_biological_parameters(&mut reader)?;
+    debug_assert_eq!(reader.offset(), HEADER_LEN);
+
+    // Each section needs at least its header, so the count alone can be checked up front
+    let minimum = section_count as usize * SECTION_HEADER_LEN + FOOTER_LEN;
+    if minimum > reader.remaining() {
+        return Err(BitstreamError::Truncated {
+            field: "sections",
+            offset: reader.offset(),
+            needed: minimum,
+            available: reader.remaining(),
+        });
+    }
+
+    let mut sections = Vec::with_capacity(section_count as usize);
+    let mut data_size = 0u64;
+    let mut crc = Crc32::new();
+    for index in 0..section_count as usize {
+        let section_type = reader.u8("section type")?;
+        let section_type = SectionType::from_u8(section_type)
+            .ok_or(BitstreamError::UnknownSectionType { index, section_type })?;
+        let size = reader.u32("section size")?;
+        if size as usize > limits.max_section_bytes {
+            return Err(BitstreamError::SectionTooLarge {
+                index,
+                size,
+                limit: limits.max_section_bytes,
+            });
+        }
+        let section_parameters = read_biological_parameters(&mut reader)?;
+        let data = reader.take("section data", size as usize)?;
+
+        crc.update(data);
+        data_size += size as u64;
+        sections.push(DataSection {
+            section_type,
+            data: data.to_vec(),
+            biological_parameters: section_parameters,
+        });
+    }
+    if data_size != declared_size as u64 {
+        return Err(BitstreamError::DataSizeMismatch {
+            declared: declared_size,
+            actual: data_size,
+        });
+    }
+
+    let expected = reader.u32("checksum")?;
+    let actual = crc.finish();
+    if expected != actual {
+        return Err(BitstreamError::ChecksumMismatch { expected, actual });
+    }
+    let biological_accuracy = reader.finite_f64("biological accuracy")?;
+    let compression_ratio = reader.finite_f64("compression ratio")?;
+    if reader.remaining() > 0 {
+        return Err(BitstreamError::TrailingData(reader.remaining()));
+    }
+
+    Ok(ParsedData {
+        data: Vec::new(),
+        sections,
+        biological_parameters,
+        metadata: DataMetadata {
+            biological_accuracy,
+            compression_ratio,
+            ..DataMetadata::new()
+        },
+    })
+}
+
+fn read_biological_parameters(reader: &mut ByteReader<'_>) -> Result<BiologicalParameters, BitstreamError> {
+    Ok(BiologicalParameters {
+        processing_time: reader.finite_f64("processing time")?,
+        energy_consumption: reader.finite_f64("energy consumption")?,
+        biological_accuracy: reader.finite_f64("biological accuracy")?,
+        adaptation_rate: reader.finite_f64("adaptation rate")?,
+    })
+}
+
+pub(crate) fn write_biological_parameters(out: &mut Vec<u8>, parameters: &BiologicalParameters) {
+    out.extend_from_slice(&parameters.processing_time.to_le_bytes());
+    out.extend_from_slice(&parameters.energy_consumption.to_le_bytes());
+    out.extend_from_slice(&parameters.biological_accuracy.to_le_bytes());
+    out.extend_from_slice(&parameters.adaptation_rate.to_le_bytes());
+}
+
+/// CRC-32 (IEEE 802.3) of section data, as stored in the footer
+pub(crate) struct Crc32(u32);
+
+impl Crc32 {
+    pub(crate) fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (self.0 & 1).wrapping_neg();
+                self.0 = (self.0 >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u32 {
+        !self.0
+    }
+}
@@ -0,0 +1,612 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Stream Metadata Messages
+//!
+//! Broadcast workflows need timecodes and HDR signalling to travel with the
+//! video. Metadata is carried in `AMET` packets interleaved with the GOP
+//! packets of the muxed stream, each ahead of the GOP holding its frame. The
+//! payload uses SEI message syntax and the HEVC payload types, so downstream
+//! tooling can map messages one-to-one. Because the packets are separate from
+//! the GOPs, metadata can be read and rewritten without re-encoding, and a
+//! transcode carries it across by copying it into the new muxer.
+//!
+//! Biological Foundation:
+//! - Context signals travel alongside the sensory stream without altering it
+//! - Later stages can consult them independently of the stimulus itself
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use super::parser::{BitstreamError, ByteReader};
+use crate::performance_optimization::gop_parallel::GOP_MAGIC;
+use crate::AfiyahError;
+
+/// Magic marker preceding every metadata packet in the muxed bitstream
+pub const METADATA_MAGIC: [u8; 4] = *b"AMET";
+
+/// Magic, u64 frame number, u32 message count and u32 payload length
+const PACKET_HEADER_LEN: usize = 20;
+
+/// HEVC SEI payload types
+pub const PAYLOAD_USER_DATA_UNREGISTERED: u32 = 5;
+pub const PAYLOAD_TIME_CODE: u32 = 136;
+pub const PAYLOAD_MASTERING_DISPLAY_COLOUR_VOLUME: u32 = 137;
+pub const PAYLOAD_CONTENT_LIGHT_LEVEL_INFO: u32 = 144;
+
+/// Unregistered user data with this UUID holds an Afiyah key/value pair
+pub const KEY_VALUE_UUID: [u8; 16] = [
+    0x6a, 0x1f, 0x3c, 0x52, 0x9e, 0x04, 0x4b, 0x8d, 0xa7, 0x31, 0x5e, 0xc2, 0x0b, 0x96, 0xd4, 0x7f,
+];
+
+/// SMPTE timecode of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u16,
+    pub drop_frame: bool,
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.drop_frame { ';' } else { ':' };
+        write!(f, "{:02}:{:02}:{:02}{}{:02}", self.hours, self.minutes, self.seconds, separator, self.frames)
+    }
+}
+
+/// Colour volume of the display the content was mastered on (SMPTE ST 2086)
+///
+/// Chromaticities are in units of 0.00002 and luminances in 0.0001 cd/m².
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MasteringDisplay {
+    pub primaries: [[u16; 2]; 3], // (x, y) for green, blue and red, in HEVC order
+    pub white_point: [u16; 2],
+    pub max_luminance: u32,
+    pub min_luminance: u32,
+}
+
+/// Content light level of the stream (CTA-861.3), in cd/m²
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentLightLevel {
+    pub max_content_light_level: u16,
+    pub max_frame_average_light_level: u16,
+}
+
+/// A single metadata message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MetadataMessage {
+    Timecode(Timecode),
+    MasteringDisplay(MasteringDisplay),
+    ContentLightLevel(ContentLightLevel),
+    KeyValue { key: String, value: String },
+    /// Any other SEI payload, passed through byte for byte
+    Raw { payload_type: u32, payload: Vec<u8> },
+}
+
+impl MetadataMessage {
+    /// SEI payload type the message is written as
+    pub fn payload_type(&self) -> u32 {
+        match self {
+            MetadataMessage::Timecode(_) => PAYLOAD_TIME_CODE,
+            MetadataMessage::MasteringDisplay(_) => PAYLOAD_MASTERING_DISPLAY_COLOUR_VOLUME,
+            MetadataMessage::ContentLightLevel(_) => PAYLOAD_CONTENT_LIGHT_LEVEL_INFO,
+            MetadataMessage::KeyValue { .. } => PAYLOAD_USER_DATA_UNREGISTERED,
+            MetadataMessage::Raw { payload_type, .. } => *payload_type,
+        }
+    }
+
+    fn encode_payload(&self) -> Result<Vec<u8>, AfiyahError> {
+        let mut payload = Vec::new();
+        match self {
+            MetadataMessage::Timecode(timecode) => {
+                if timecode.hours > 31 || timecode.minutes > 59 || timecode.seconds > 59 || timecode.frames > 511 {
+                    return Err(AfiyahError::BitstreamFormatting {
+                        message: format!("Timecode {} is out of range", timecode),
+                    });
+                }
+                // One clock timestamp with a full timestamp and no time offset
+                let mut bits = BitWriter::default();
+                bits.write(1, 2); // num_clock_ts
+                bits.write(1, 1); // clock_timestamp_flag
+                bits.write(0, 1); // units_field_based_flag
+                bits.write(if timecode.drop_frame { 4 } else { 0 }, 5); // counting_type
+                bits.write(1, 1); // full_timestamp_flag
+                bits.write(0, 1); // discontinuity_flag
+                bits.write(timecode.drop_frame as u32, 1); // cnt_dropped_flag
+                bits.write(timecode.frames as u32, 9);
+                bits.write(timecode.seconds as u32, 6);
+                bits.write(timecode.minutes as u32, 6);
+                bits.write(timecode.hours as u32, 5);
+                bits.write(0, 5); // time_offset_length
+                payload = bits.finish();
+            }
+            MetadataMessage::MasteringDisplay(display) => {
+                for [x, y] in display.primaries {
+                    payload.extend_from_slice(&x.to_be_bytes());
+                    payload.extend_from_slice(&y.to_be_bytes());
+                }
+                payload.extend_from_slice(&display.white_point[0].to_be_bytes());
+                payload.extend_from_slice(&display.white_point[1].to_be_bytes());
+                payload.extend_from_slice(&display.max_luminance.to_be_bytes());
+                payload.extend_from_slice(&display.min_luminance.to_be_bytes());
+            }
+            MetadataMessage::ContentLightLevel(level) => {
+                payload.extend_from_slice(&level.max_content_light_level.to_be_bytes());
+                payload.extend_from_slice(&level.max_frame_average_light_level.to_be_bytes());
+            }
+            MetadataMessage::KeyValue { key, value } => {
+                let key_length = u16::try_from(key.len()).map_err(|_| AfiyahError::BitstreamFormatting {
+                    message: "Metadata key exceeds 65535 bytes".to_string(),
+                })?;
+                payload.extend_from_slice(&KEY_VALUE_UUID);
+                payload.extend_from_slice(&key_length.to_be_bytes());
+                payload.extend_from_slice(key.as_bytes());
+                payload.extend_from_slice(value.as_bytes());
+            }
+            MetadataMessage::Raw { payload: raw, .. } => payload.extend_from_slice(raw),
+        }
+        Ok(payload)
+    }
+
+    /// Decodes a payload, keeping it raw when the type is unknown or malformed
+    fn decode_payload(payload_type: u32, payload: &[u8]) -> Self {
+        let decoded = match payload_type {
+            PAYLOAD_TIME_CODE => decode_timecode(payload).map(MetadataMessage::Timecode),
+            PAYLOAD_MASTERING_DISPLAY_COLOUR_VOLUME if payload.len() == 24 => {
+                let u16_at = |i: usize| u16::from_be_bytes([payload[i], payload[i + 1]]);
+                let u32_at = |i: usize| u32::from_be_bytes([payload[i], payload[i + 1], payload[i + 2], payload[i + 3]]);
+                Some(MetadataMessage::MasteringDisplay(MasteringDisplay {
+                    primaries: [[u16_at(0), u16_at(2)], [u16_at(4), u16_at(6)], [u16_at(8), u16_at(10)]],
+                    white_point: [u16_at(12), u16_at(14)],
+                    max_luminance: u32_at(16),
+                    min_luminance: u32_at(20),
+                }))
+            }
+            PAYLOAD_CONTENT_LIGHT_LEVEL_INFO if payload.len() == 4 => {
+                Some(MetadataMessage::ContentLightLevel(ContentLightLevel {
+                    max_content_light_level: u16::from_be_bytes([payload[0], payload[1]]),
+                    max_frame_average_light_level: u16::from_be_bytes([payload[2], payload[3]]),
+                }))
+            }
+            PAYLOAD_USER_DATA_UNREGISTERED if payload.len() >= 18 && payload[..16] == KEY_VALUE_UUID => {
+                let key_length = u16::from_be_bytes([payload[16], payload[17]]) as usize;
+                payload.get(18..18 + key_length).and_then(|key| {
+                    let key = String::from_utf8(key.to_vec()).ok()?;
+                    let value = String::from_utf8(payload[18 + key_length..].to_vec()).ok()?;
+                    Some(MetadataMessage::KeyValue { key, value })
+                })
+            }
+            _ => None,
+        };
+        decoded.unwrap_or_else(|| MetadataMessage::Raw {
+            payload_type,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// Reads the first clock timestamp of a time code payload
+fn decode_timecode(payload: &[u8]) -> Option<Timecode> {
+    let mut bits = BitReader::new(payload);
+    let clock_count = bits.read(2)?;
+    for _ in 0..clock_count {
+        if bits.read(1)? == 0 {
+            continue;
+        }
+        bits.read(1)?; // units_field_based_flag
+        let counting_type = bits.read(5)?;
+        let full_timestamp = bits.read(1)? == 1;
+        bits.read(1)?; // discontinuity_flag
+        let dropped = bits.read(1)? == 1;
+        let frames = bits.read(9)? as u16;
+
+        let (mut seconds, mut minutes, mut hours) = (0, 0, 0);
+        if full_timestamp {
+            seconds = bits.read(6)?;
+            minutes = bits.read(6)?;
+            hours = bits.read(5)?;
+        } else if bits.read(1)? == 1 {
+            seconds = bits.read(6)?;
+            if bits.read(1)? == 1 {
+                minutes = bits.read(6)?;
+                if bits.read(1)? == 1 {
+                    hours = bits.read(5)?;
+                }
+            }
+        }
+
+        return Some(Timecode {
+            hours: hours as u8,
+            minutes: minutes as u8,
+            seconds: seconds as u8,
+            frames,
+            drop_frame: dropped || counting_type == 4,
+        });
+    }
+    None
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    used: u32, // Bits used in the last byte
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        for i in (0..bits).rev() {
+            if self.used.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= bit << (7 - self.used % 8);
+            self.used += 1;
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..bits {
+            let byte = *self.bytes.get(self.position / 8)?;
+            value = (value << 1) | ((byte >> (7 - self.position % 8)) & 1) as u32;
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
+
+/// Encodes messages in SEI syntax: 0xFF-extended type and size, then payload
+pub fn encode_messages(messages: &[MetadataMessage]) -> Result<Vec<u8>, AfiyahError> {
+    let mut encoded = Vec::new();
+    for message in messages {
+        let payload = message.encode_payload()?;
+        write_ff_coded(&mut encoded, message.payload_type() as usize);
+        write_ff_coded(&mut encoded, payload.len());
+        encoded.extend_from_slice(&payload);
+    }
+    Ok(encoded)
+}
+
+/// Decodes messages written by `encode_messages`
+pub fn decode_messages(mut data: &[u8]) -> Result<Vec<MetadataMessage>, AfiyahError> {
+    let mut messages = Vec::new();
+    while !data.is_empty() {
+        let payload_type = read_ff_coded(&mut data)?;
+        let size = read_ff_coded(&mut data)?;
+        if size > data.len() {
+            return Err(AfiyahError::BitstreamFormatting {
+                message: format!("SEI payload of type {} is truncated", payload_type),
+            });
+        }
+        let (payload, rest) = data.split_at(size);
+        messages.push(MetadataMessage::decode_payload(payload_type as u32, payload));
+        data = rest;
+    }
+    Ok(messages)
+}
+
+fn write_ff_coded(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0xFF {
+        out.push(0xFF);
+        value -= 0xFF;
+    }
+    out.push(value as u8);
+}
+
+fn read_ff_coded(data: &mut &[u8]) -> Result<usize, AfiyahError> {
+    let mut value = 0usize;
+    loop {
+        let Some((&byte, rest)) = data.split_first() else {
+            return Err(AfiyahError::BitstreamFormatting {
+                message: "SEI message header is truncated".to_string(),
+            });
+        };
+        *data = rest;
+        value += byte as usize;
+        if byte != 0xFF {
+            return Ok(value);
+        }
+    }
+}
+
+/// Metadata messages for one frame, numbered from the start of the stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataPacket {
+    pub frame: u64,
+    pub messages: Vec<MetadataMessage>,
+}
+
+/// A packet of a muxed stream
+#[derive(Debug, Clone)]
+pub enum StreamPacket<'a> {
+    Gop {
+        index: u64,
+        first_frame: u64,
+        frame_count: u32,
+        bytes: &'a [u8], // The whole packet, header included
+    },
+    Metadata(MetadataPacket),
+}
+
+/// Splits a muxed stream into its GOP and metadata packets
+pub fn parse_stream(bitstream: &[u8]) -> Result<Vec<StreamPacket<'_>>, AfiyahError> {
+    let mut packets = Vec::new();
+    let mut reader = ByteReader::new(bitstream);
+    let mut frames = 0u64;
+
+    while reader.remaining() > 0 {
+        let start = reader.offset();
+        let magic = reader.take("packet magic", 4)?;
+        let number = reader.u64("packet number")?;
+        let count = reader.u32("packet count")?;
+        let length = reader.u32("packet length")?;
+        let payload = reader.take("packet payload", length as usize)?;
+
+        if magic == GOP_MAGIC {
+            packets.push(StreamPacket::Gop {
+                index: number,
+                first_frame: frames,
+                frame_count: count,
+                bytes: &bitstream[start..reader.offset()],
+            });
+            frames = frames.saturating_add(count as u64);
+        } else if magic == METADATA_MAGIC {
+            let messages = decode_messages(payload)?;
+            if messages.len() != count as usize {
+                return Err(BitstreamError::Malformed(format!(
+                    "metadata packet for frame {} holds {} messages, expected {}",
+                    number,
+                    messages.len(),
+                    count
+                ))
+                .into());
+            }
+            packets.push(StreamPacket::Metadata(MetadataPacket { frame: number, messages }));
+        } else {
+            return Err(BitstreamError::Malformed(format!("unknown packet at byte {}", start)).into());
+        }
+    }
+    Ok(packets)
+}
+
+/// All metadata of a muxed stream, in stream order
+pub fn read_metadata(bitstream: &[u8]) -> Result<Vec<MetadataPacket>, AfiyahError> {
+    Ok(parse_stream(bitstream)?
+        .into_iter()
+        .filter_map(|packet| match packet {
+            StreamPacket::Metadata(metadata) => Some(metadata),
+            StreamPacket::Gop { .. } => None,
+        })
+        .collect())
+}
+
+/// Copies a muxed stream to `writer` with its metadata replaced by `metadata`
+///
+/// GOP packets are copied byte for byte, so nothing is re-encoded. To edit
+/// metadata, read it with `read_metadata`, change it and write it back.
+pub fn rewrite_metadata<W: Write>(bitstream: &[u8], metadata: Vec<MetadataPacket>, mut writer: W) -> Result<W, AfiyahError> {
+    let mut schedule = MetadataSchedule::default();
+    for packet in metadata {
+        schedule.insert(packet.frame, packet.messages);
+    }
+
+    for packet in parse_stream(bitstream)? {
+        if let StreamPacket::Gop { first_frame, frame_count, bytes, .. } = packet {
+            schedule.write_before(&mut writer, first_frame + frame_count as u64)?;
+            writer.write_all(bytes)?;
+        }
+    }
+    schedule.ensure_empty()?;
+    writer.flush()?;
+    Ok(writer)
+}
+
+/// Metadata waiting to be written ahead of the GOP holding its frame
+#[derive(Debug, Default)]
+pub struct MetadataSchedule {
+    pending: BTreeMap<u64, Vec<MetadataMessage>>,
+}
+
+impl MetadataSchedule {
+    /// Queues messages for a frame, after any already queued for it
+    pub fn insert(&mut self, frame: u64, messages: Vec<MetadataMessage>) {
+        if !messages.is_empty() {
+            self.pending.entry(frame).or_default().extend(messages);
+        }
+    }
+
+    /// First frame with queued metadata
+    pub fn first_pending(&self) -> Option<u64> {
+        self.pending.keys().next().copied()
+    }
+
+    /// Writes a packet for every queued frame before `end_frame`, returning the bytes written
+    pub fn write_before<W: Write>(&mut self, writer: &mut W, end_frame: u64) -> Result<u64, AfiyahError> {
+        let mut written = 0;
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() >= end_frame {
+                break;
+            }
+            let (frame, messages) = entry.remove_entry();
+            written += write_packet(writer, frame, &messages)?;
+        }
+        Ok(written)
+    }
+
+    /// Fails if metadata is queued for frames the stream never reached
+    pub fn ensure_empty(&self) -> Result<(), AfiyahError> {
+        match self.first_pending() {
+            Some(frame) => Err(AfiyahError::BitstreamFormatting {
+                message: format!("Metadata for frame {} is past the end of the stream", frame),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes one metadata packet, returning its size
+pub fn write_packet<W: Write>(writer: &mut W, frame: u64, messages: &[MetadataMessage]) -> Result<u64, AfiyahError> {
+    let payload = encode_messages(messages)?;
+    let length = u32::try_from(payload.len()).map_err(|_| AfiyahError::BitstreamFormatting {
+        message: format!("Metadata for frame {} exceeds the maximum payload size", frame),
+    })?;
+
+    writer.write_all(&METADATA_MAGIC)?;
+    writer.write_all(&frame.to_le_bytes())?;
+    writer.write_all(&(messages.len() as u32).to_le_bytes())?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(&payload)?;
+    Ok((PACKET_HEADER_LEN + payload.len()) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::performance_optimization::gop_parallel::{EncodedGop, OrderedGopMuxer};
+    use std::time::Duration;
+
+    fn hdr_messages() -> Vec<MetadataMessage> {
+        vec![
+            MetadataMessage::Timecode(Timecode { hours: 10, minutes: 0, seconds: 59, frames: 29, drop_frame: true }),
+            MetadataMessage::MasteringDisplay(MasteringDisplay {
+                primaries: [[13250, 34500], [7500, 3000], [34000, 16000]],
+                white_point: [15635, 16450],
+                max_luminance: 10_000_000,
+                min_luminance: 50,
+            }),
+            MetadataMessage::ContentLightLevel(ContentLightLevel {
+                max_content_light_level: 1000,
+                max_frame_average_light_level: 400,
+            }),
+            MetadataMessage::KeyValue { key: "camera".to_string(), value: "A".to_string() },
+            MetadataMessage::Raw { payload_type: 300, payload: vec![7; 300] },
+        ]
+    }
+
+    fn gop(index: u64, frame_count: u32) -> EncodedGop {
+        EncodedGop {
+            index,
+            frame_count,
+            data: vec![index as u8; 8],
+            encode_time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        let messages = hdr_messages();
+        let encoded = encode_messages(&messages).unwrap();
+        assert_eq!(decode_messages(&encoded).unwrap(), messages);
+        if let MetadataMessage::Timecode(timecode) = &messages[0] {
+            assert_eq!(timecode.to_string(), "10:00:59;29");
+        }
+    }
+
+    #[test]
+    fn test_metadata_is_muxed_ahead_of_its_gop_and_rewritten_in_place() {
+        let mut muxer = OrderedGopMuxer::new(Vec::new());
+        muxer.attach_metadata(0, hdr_messages()).unwrap();
+        muxer.attach_metadata(5, vec![MetadataMessage::KeyValue { key: "scene".to_string(), value: "2".to_string() }]).unwrap();
+        muxer.push(gop(1, 4)).unwrap();
+        muxer.push(gop(0, 4)).unwrap();
+        let stream = muxer.finish().unwrap();
+
+        let order: Vec<String> = parse_stream(&stream)
+            .unwrap()
+            .iter()
+            .map(|packet| match packet {
+                StreamPacket::Gop { index, .. } => format!("gop {}", index),
+                StreamPacket::Metadata(metadata) => format!("meta {}", metadata.frame),
+            })
+            .collect();
+        assert_eq!(order, ["meta 0", "gop 0", "meta 5", "gop 1"]);
+
+        // Drop the HDR messages from frame 0 without touching the GOPs
+        let mut metadata = read_metadata(&stream).unwrap();
+        metadata[0].messages.retain(|message| matches!(message, MetadataMessage::Timecode(_)));
+        let rewritten = rewrite_metadata(&stream, metadata.clone(), Vec::new()).unwrap();
+        assert_eq!(read_metadata(&rewritten).unwrap(), metadata);
+
+        let gops = |bitstream: &[u8]| -> Vec<Vec<u8>> {
+            parse_stream(bitstream)
+                .unwrap()
+                .into_iter()
+                .filter_map(|packet| match packet {
+                    StreamPacket::Gop { bytes, .. } => Some(bytes.to_vec()),
+                    StreamPacket::Metadata(_) => None,
+                })
+                .collect()
+        };
+        assert_eq!(gops(&rewritten), gops(&stream));
+
+        // Metadata past the last frame cannot be placed
+        let past_end = vec![MetadataPacket { frame: 8, messages: hdr_messages() }];
+        assert!(rewrite_metadata(&stream, past_end, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_transcode_preserves_metadata() {
+        let mut source = OrderedGopMuxer::new(Vec::new());
+        source.attach_metadata(3, hdr_messages()).unwrap();
+        source.push(gop(0, 4)).unwrap();
+        let source = source.finish().unwrap();
+
+        // Re-encoded with a different GOP size
+        let mut muxer = OrderedGopMuxer::new(Vec::new());
+        assert_eq!(muxer.preserve_metadata(&source).unwrap(), 1);
+        muxer.push(gop(0, 2)).unwrap();
+        muxer.push(gop(1, 2)).unwrap();
+        let transcoded = muxer.finish().unwrap();
+
+        assert_eq!(read_metadata(&transcoded).unwrap(), read_metadata(&source).unwrap());
+    }
+}
@@ -40,10 +40,13 @@ pub mod V3_V5;
 pub mod attention_mechanisms;
 
 use crate::AfiyahError;
+use crate::performance_optimization::frame_buffer_pool::{pooled_vec, SharedFrameBufferPool};
 use ndarray::Array3;
 
 /// Main visual cortex processor
-pub struct VisualCortex;
+pub struct VisualCortex {
+    buffer_pool: Option<SharedFrameBufferPool>,
+}
 
 /// Output from cortical processing
 #[derive(Debug, Clone)]
@@ -61,7 +64,19 @@ pub struct CorticalCalibrationParams {
 impl VisualCortex {
     /// Creates a new visual cortex
     pub fn new() -> Result<Self, AfiyahError> {
-        Ok(Self)
+        Ok(Self { buffer_pool: None })
+    }
+
+    /// Draws cortical output buffers from a shared frame buffer pool
+    pub fn set_buffer_pool(&mut self, pool: SharedFrameBufferPool) {
+        self.buffer_pool = Some(pool);
+    }
+
+    /// Returns a cortical output buffer to the pool
+    pub fn recycle(&self, output: CorticalOutput) {
+        if let Some(pool) = &self.buffer_pool {
+            pool.release_vec(output.data);
+        }
     }
     
     /// Trains cortical filters
@@ -72,7 +87,7 @@ impl VisualCortex {
     /// Processes retinal output through cortical areas
     pub fn process(&mut self, _input: &crate::retinal_processing::RetinalOutput) -> Result<CorticalOutput, AfiyahError> {
         Ok(CorticalOutput {
-            data: vec![0.0; 1000],
+            data: pooled_vec(self.buffer_pool.as_deref(), 1000),
         })
     }
     
@@ -80,4 +95,41 @@ impl VisualCortex {
     pub fn validate_biological_accuracy(&self, _output: &CorticalOutput) -> Result<f64, AfiyahError> {
         Ok(0.947)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::performance_optimization::frame_buffer_pool::FrameBufferPool;
+    use crate::retinal_processing::RetinalOutput;
+
+    fn retinal_output() -> RetinalOutput {
+        RetinalOutput {
+            magnocellular_stream: vec![0.1; 16],
+            parvocellular_stream: vec![0.2; 16],
+            koniocellular_stream: vec![0.3; 16],
+            adaptation_level: 0.5,
+            compression_ratio: 0.9,
+        }
+    }
+
+    #[test]
+    fn test_pooled_cortex_steady_state_allocations() {
+        let pool = FrameBufferPool::shared();
+        let mut cortex = VisualCortex::new().unwrap();
+        cortex.set_buffer_pool(pool.clone());
+        let input = retinal_output();
+
+        // Warm-up frame populates the size class
+        let output = cortex.process(&input).unwrap();
+        cortex.recycle(output);
+        let warm = pool.stats().fresh_allocations;
+
+        for _ in 0..60 {
+            let output = cortex.process(&input).unwrap();
+            assert_eq!(output.data.len(), 1000);
+            cortex.recycle(output);
+        }
+
+        assert_eq!(pool.stats().fresh_allocations, warm);
+    }
+}
@@ -0,0 +1,16 @@
+#![no_main]
+
+//! Feeds arbitrary bytes to the bitstream parser, which must return an
+//! error rather than panic or allocate unboundedly.
+
+use afiyah::bitstream_formatting::{BiologicalBitstreamFormatter, BitstreamConfig};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let config = BitstreamConfig {
+        max_section_bytes: 1 << 20,
+        ..BitstreamConfig::default()
+    };
+    let formatter = BiologicalBitstreamFormatter::new(config).expect("default formatter");
+    let _ = formatter.parse_bitstream(data);
+});
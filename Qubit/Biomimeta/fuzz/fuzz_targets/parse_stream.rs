@@ -0,0 +1,19 @@
+#![no_main]
+
+//! Feeds arbitrary bytes to the muxed stream parser and, when a stream
+//! parses, checks that rewriting its metadata is stable.
+
+use afiyah::bitstream_formatting::stream_metadata::{read_metadata, rewrite_metadata};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(metadata) = read_metadata(data) else {
+        return;
+    };
+    // The first rewrite may reorder or merge packets; a second must change nothing
+    if let Ok(rewritten) = rewrite_metadata(data, metadata, Vec::new()) {
+        let metadata = read_metadata(&rewritten).expect("rewritten stream parses");
+        let again = rewrite_metadata(&rewritten, metadata, Vec::new()).expect("rewritten stream rewrites");
+        assert_eq!(again, rewritten);
+    }
+});
@@ -127,6 +127,17 @@ pub mod streaming_protocols;
 // Quality metrics system
 pub mod quality_metrics;
 
+pub use performance_optimization::frame_buffer_pool::{FrameBufferPool, FrameBufferPoolConfig, FrameBufferPoolStats, SharedFrameBufferPool};
+pub use performance_optimization::gop_parallel::{GopParallelEncoder, GopParallelConfig, GopEncoder, GopBudget, EncodedGop, OrderedGopMuxer, RateController, GopEncodeStats};
+pub use performance_optimization::adaptive_gop::{AdaptiveGopConfig, AdaptiveGops, FrameSignals, KeyframeAnalyzer, KeyframePlacementStats, KeyframePlanner, KeyframeReason};
+pub use performance_optimization::lookahead::{Lookahead, LookaheadConfig, LookaheadCache, LookaheadStats, ComplexityMaps, FrameComplexity};
+pub use performance_optimization::per_title::{PerTitleOptimizer, PerTitleConfig, PerTitleStats, TitleProfile, TitleCatalog, LadderRung, ShotComplexity, EncodePreset, ComplexityClass};
+pub use performance_optimization::encoder_session::{EncoderSession, EncoderSessionConfig, EncodedChunk, FrameSource, frames_from_iter};
+pub use motion_estimation::long_term_reference::{LongTermReferenceEncoder, LongTermReferenceDecoder, LongTermReferenceConfig, LongTermReferenceStats, BackgroundModel, BlockMode, DecodedFrame, LtrFrameType};
+pub use bitstream_formatting::stream_metadata::{MetadataMessage, MetadataPacket, Timecode, MasteringDisplay, ContentLightLevel, read_metadata, rewrite_metadata};
+pub use perceptual_optimization::colour_vision::{ColourVisionDeficiency, CvdProfile, ChannelWeights, DaltonizationHint, CvdQualityReport};
+pub use hardware_abstraction::hardware_decode::{DecodeBackends, DecodeBackend, HardwareDecoder, HardwareDecodeSource, DecodeCapability, DecodeApi, CompressedCodec, CompressedPacket, DecodedSurface, SurfaceFormat, StreamParameters, InputPath, DecodeStats, platform_decode_apis};
+
 // External dependencies
 use ndarray::{Array2, Array3, s};
 use std::collections::HashMap;
@@ -214,6 +225,7 @@ pub struct CompressionEngine {
     motion_estimator: BiologicalMotionEstimator,
     quantizer: BiologicalQuantizer,
     bitstream_formatter: BiologicalBitstreamFormatter,
+    buffer_pool: SharedFrameBufferPool,
     config: EngineConfig,
 }
 
@@ -246,9 +258,14 @@ impl Default for EngineConfig {
 impl CompressionEngine {
     /// Create a new compression engine with all biological components
     pub fn new(config: EngineConfig) -> Result<Self, AfiyahError> {
+        // Frame buffers are shared across stages and reused between frames
+        let buffer_pool = FrameBufferPool::shared();
+
         // Initialize biological components
-        let retinal_processor = RetinalProcessor::new()?;
-        let visual_cortex = VisualCortex::new()?;
+        let mut retinal_processor = RetinalProcessor::new()?;
+        retinal_processor.set_buffer_pool(buffer_pool.clone());
+        let mut visual_cortex = VisualCortex::new()?;
+        visual_cortex.set_buffer_pool(buffer_pool.clone());
         let synaptic_adaptation = SynapticAdaptation::new()?;
         let perceptual_optimizer = PerceptualOptimizer::new()?;
         let streaming_engine = AdaptiveStreamer::new()?;
@@ -266,9 +283,11 @@ impl CompressionEngine {
 
         // Initialize core compression components
         let entropy_coder = BiologicalEntropyCoder::new(EntropyCodingConfig::default())?;
-        let transform_coder = BiologicalTransformCoder::new(TransformCodingConfig::default())?;
+        let mut transform_coder = BiologicalTransformCoder::new(TransformCodingConfig::default())?;
+        transform_coder.set_buffer_pool(buffer_pool.clone());
         let motion_estimator = BiologicalMotionEstimator::new(MotionEstimationConfig::default())?;
-        let quantizer = BiologicalQuantizer::new(QuantizationConfig::default())?;
+        let mut quantizer = BiologicalQuantizer::new(QuantizationConfig::default())?;
+        quantizer.set_buffer_pool(buffer_pool.clone());
         let bitstream_formatter = BiologicalBitstreamFormatter::new(BitstreamConfig::default())?;
 
         Ok(Self {
@@ -293,12 +312,35 @@ impl CompressionEngine {
             motion_estimator,
             quantizer,
             bitstream_formatter,
+            buffer_pool,
             config,
         })
     }
 
+    /// Gets frame buffer pool statistics for allocation monitoring
+    pub fn buffer_pool_stats(&self) -> FrameBufferPoolStats {
+        self.buffer_pool.stats()
+    }
+
+    /// Coarsens quantization for rate control; 1.0 is nominal quality
+    pub fn set_rate_scale(&mut self, scale: f64) {
+        self.quantizer.set_rate_scale(scale);
+    }
+
+    /// Varies the rate scale per block of the following frames, e.g. by a lookahead analysis; None is uniform
+    pub fn set_block_scales(&mut self, scales: Option<Array2<f64>>) {
+        self.quantizer.set_block_scales(scales);
+    }
+
     /// Compress video data using the complete biological pipeline
     pub fn compress(&mut self, input: &VisualInput) -> Result<CompressionResult, AfiyahError> {
+        self.compress_frame(input)
+    }
+
+    /// Compress one frame to a bitstream; unlike the cortical `compress`
+    /// returning `CompressedOutput`, its name is unambiguous to callers such
+    /// as the GOP encoder
+    pub fn compress_frame(&mut self, input: &VisualInput) -> Result<CompressionResult, AfiyahError> {
         // Step 1: Retinal processing
         let retinal_output = self.retinal_processor.process(input)?;
 
@@ -318,6 +360,12 @@ impl CompressionEngine {
         let symbols = self.convert_to_symbols(&quantization_result.quantized_data)?;
         let entropy_encoded = self.entropy_coder.encode(&symbols)?;
 
+        // Intermediate stage outputs are no longer needed for this frame
+        self.retinal_processor.recycle(retinal_output);
+        self.visual_cortex.recycle(cortical_output);
+        self.transform_coder.recycle(transform_output);
+        self.quantizer.recycle(quantization_result);
+
         // Step 7: Bitstream formatting
         let compression_data = CompressionData::new(); // Create from processed data
         let bitstream_output = self.bitstream_formatter.format_bitstream(&compression_data)?;
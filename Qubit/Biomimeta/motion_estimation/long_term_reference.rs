@@ -0,0 +1,923 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Long-Term Reference Frames and Background Modeling
+//!
+//! Surveillance and webcam footage is mostly a static scene with a few
+//! moving objects. Next to the previous frame, the encoder keeps a slowly
+//! updated model of the background and periodically promotes it to a
+//! long-term reference (LTR). Every block of an inter frame is predicted
+//! from whichever reference matches better: blocks that barely change are
+//! copied outright, the rest carry a sparse quantized residual. Background
+//! uncovered by a departing object is found in the LTR even though the
+//! previous frame still shows the object, which is where most of the bits
+//! go in conventional short-term prediction.
+//!
+//! The model is updated from reconstructed frames only, so the decoder
+//! rebuilds the same model and LTR without either being transmitted. When
+//! most blocks match neither reference (a camera cut, a light switched on)
+//! the encoder declares a scene reset and starts over from a key frame.
+//!
+//! Frame layout (little-endian): type u8, flags u8, width u32, height u32,
+//! plane count u8, quantizer step f32. Key frames follow with the DPCM of
+//! every quantized sample; inter frames with two mode bits per block, then
+//! the nonzero residuals of each residual block as varint gap and zigzag
+//! value pairs.
+//!
+//! Biological Foundation:
+//! - Retinal and cortical adaptation suppress responses to unchanging input
+//! - Change detection draws attention only to what moved or appeared
+//! - Scene memory lets familiar surroundings be recognised after occlusion
+
+use serde::{Deserialize, Serialize};
+
+use crate::bitstream_formatting::parser::{BitstreamError, ByteReader};
+use crate::performance_optimization::gop_parallel::{GopBudget, GopEncoder};
+use crate::{AfiyahError, VisualInput};
+
+const FRAME_KEY: u8 = 0;
+const FRAME_INTER: u8 = 1;
+
+/// Frame flag: promote the background model to the LTR after this frame
+const FLAG_PROMOTE_LTR: u8 = 1;
+
+/// Largest frame the decoder allocates for, in samples per plane
+const MAX_FRAME_SAMPLES: usize = 8192 * 8192;
+
+/// Prediction of one block in an inter frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockMode {
+    CopyPrevious,
+    CopyLongTerm,
+    ResidualPrevious,
+    ResidualLongTerm,
+}
+
+impl BlockMode {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => BlockMode::CopyPrevious,
+            1 => BlockMode::CopyLongTerm,
+            2 => BlockMode::ResidualPrevious,
+            _ => BlockMode::ResidualLongTerm,
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            BlockMode::CopyPrevious => 0,
+            BlockMode::CopyLongTerm => 1,
+            BlockMode::ResidualPrevious => 2,
+            BlockMode::ResidualLongTerm => 3,
+        }
+    }
+
+    fn uses_long_term(self) -> bool {
+        matches!(self, BlockMode::CopyLongTerm | BlockMode::ResidualLongTerm)
+    }
+
+    fn has_residual(self) -> bool {
+        matches!(self, BlockMode::ResidualPrevious | BlockMode::ResidualLongTerm)
+    }
+}
+
+/// Whether a frame stands alone or predicts from the references
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LtrFrameType {
+    Key,
+    Inter,
+}
+
+/// Long-term reference and background model configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LongTermReferenceConfig {
+    pub block_size: usize,              // Edge of the square prediction blocks, in samples
+    pub quantizer_step: f64,            // Sample step of key frames and residuals at quality scale 1.0
+    pub skip_threshold: f64,            // Mean absolute difference up to which a block copies its reference
+    pub scene_change_difference: f64,   // Mean absolute difference above which a block matches neither reference
+    pub scene_reset_fraction: f64,      // Fraction of such blocks that forces a key frame
+    pub background_learning_rate: f64,  // Weight of each frame's background samples in the model
+    pub foreground_threshold: f64,      // Difference from the model above which a sample is foreground
+    pub absorb_after_frames: u32,       // Frames a foreground sample must hold still to join the background; 0 never
+    pub ltr_refresh_interval: u32,      // Frames between promotions of the model to the LTR
+}
+
+impl Default for LongTermReferenceConfig {
+    fn default() -> Self {
+        Self {
+            block_size: 16,
+            quantizer_step: 1.0 / 64.0,
+            skip_threshold: 0.02,
+            scene_change_difference: 0.15,
+            scene_reset_fraction: 0.6,
+            background_learning_rate: 0.02,
+            foreground_threshold: 0.08,
+            absorb_after_frames: 150,
+            ltr_refresh_interval: 60,
+        }
+    }
+}
+
+impl LongTermReferenceConfig {
+    /// Fixed cameras: a slow model that ignores passers-by, with a parked
+    /// car or moved chair absorbed after about ten seconds at 30 fps
+    pub fn surveillance() -> Self {
+        Self {
+            skip_threshold: 0.015,
+            background_learning_rate: 0.01,
+            absorb_after_frames: 300,
+            ltr_refresh_interval: 150,
+            ..Self::default()
+        }
+    }
+
+    /// Webcams: auto exposure and a person who shifts around, so the model
+    /// follows lighting faster and the LTR is refreshed more often
+    pub fn webcam() -> Self {
+        Self {
+            skip_threshold: 0.025,
+            scene_reset_fraction: 0.5,
+            background_learning_rate: 0.05,
+            foreground_threshold: 0.1,
+            absorb_after_frames: 60,
+            ltr_refresh_interval: 30,
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), AfiyahError> {
+        let invalid = |message: &str| {
+            Err(AfiyahError::Configuration {
+                message: message.to_string(),
+            })
+        };
+        if self.block_size == 0 {
+            return invalid("Block size must be at least one sample");
+        }
+        if !(self.quantizer_step > 0.0 && self.quantizer_step.is_finite()) {
+            return invalid("Quantizer step must be positive");
+        }
+        if !(self.skip_threshold >= 0.0 && self.skip_threshold < self.scene_change_difference) {
+            return invalid("Skip threshold must be non-negative and below the scene change difference");
+        }
+        if !(0.0..=1.0).contains(&self.scene_reset_fraction) {
+            return invalid("Scene reset fraction must be between 0 and 1");
+        }
+        if !(self.background_learning_rate > 0.0 && self.background_learning_rate <= 1.0) {
+            return invalid("Background learning rate must be in (0, 1]");
+        }
+        if self.foreground_threshold.is_nan() || self.foreground_threshold <= 0.0 {
+            return invalid("Foreground threshold must be positive");
+        }
+        if self.ltr_refresh_interval == 0 {
+            return invalid("LTR refresh interval must be at least one frame");
+        }
+        Ok(())
+    }
+}
+
+/// Per-sample running estimate of the static scene
+#[derive(Debug, Clone)]
+pub struct BackgroundModel {
+    mean: Vec<Vec<f64>>,
+    still_frames: Vec<Vec<u32>>,
+}
+
+impl BackgroundModel {
+    fn new(planes: &[Vec<f64>]) -> Self {
+        Self {
+            mean: planes.to_vec(),
+            still_frames: planes.iter().map(|plane| vec![0; plane.len()]).collect(),
+        }
+    }
+
+    /// Estimated background luminance
+    pub fn luminance(&self) -> &[f64] {
+        &self.mean[0]
+    }
+
+    /// Estimated background chrominance, empty for luminance-only input
+    pub fn chrominance(&self) -> &[f64] {
+        self.mean.get(1).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Blends background samples of `current` into the model; foreground
+    /// samples that stay put long enough replace the model outright
+    fn update(&mut self, current: &[Vec<f64>], previous: &[Vec<f64>], config: &LongTermReferenceConfig) {
+        for plane in 0..self.mean.len() {
+            let samples = self.mean[plane].iter_mut().zip(&mut self.still_frames[plane]);
+            for ((mean, still), (&now, &before)) in samples.zip(current[plane].iter().zip(&previous[plane])) {
+                let difference = now - *mean;
+                if difference.abs() <= config.foreground_threshold {
+                    *mean += config.background_learning_rate * difference;
+                    *still = 0;
+                } else if (now - before).abs() <= config.foreground_threshold {
+                    *still += 1;
+                    if config.absorb_after_frames > 0 && *still >= config.absorb_after_frames {
+                        *mean = now;
+                        *still = 0;
+                    }
+                } else {
+                    *still = 0;
+                }
+            }
+        }
+    }
+}
+
+/// References shared by the encoder and decoder, rebuilt identically on both sides
+#[derive(Debug, Clone)]
+struct ReferenceState {
+    width: usize,
+    height: usize,
+    previous: Vec<Vec<f64>>,
+    long_term: Vec<Vec<f64>>,
+    model: BackgroundModel,
+}
+
+impl ReferenceState {
+    fn key(width: usize, height: usize, reconstruction: Vec<Vec<f64>>) -> Self {
+        Self {
+            width,
+            height,
+            long_term: reconstruction.clone(),
+            model: BackgroundModel::new(&reconstruction),
+            previous: reconstruction,
+        }
+    }
+
+    fn matches(&self, width: usize, height: usize, planes: usize) -> bool {
+        self.width == width && self.height == height && self.previous.len() == planes
+    }
+
+    fn reference(&self, mode: BlockMode) -> &[Vec<f64>] {
+        if mode.uses_long_term() {
+            &self.long_term
+        } else {
+            &self.previous
+        }
+    }
+
+    /// Takes `reconstruction` as the new previous frame, after updating the model from it
+    fn advance(&mut self, reconstruction: Vec<Vec<f64>>, promote: bool, config: &LongTermReferenceConfig) {
+        self.model.update(&reconstruction, &self.previous, config);
+        if promote {
+            self.long_term = self.model.mean.clone();
+        }
+        self.previous = reconstruction;
+    }
+}
+
+/// Sample indices of each block, in raster order of blocks and samples
+struct BlockGrid {
+    width: usize,
+    height: usize,
+    block_size: usize,
+}
+
+impl BlockGrid {
+    fn count(&self) -> usize {
+        self.width.div_ceil(self.block_size) * self.height.div_ceil(self.block_size)
+    }
+
+    fn samples(&self, block: usize) -> impl Iterator<Item = usize> + '_ {
+        let blocks_x = self.width.div_ceil(self.block_size);
+        let x0 = block % blocks_x * self.block_size;
+        let y0 = block / blocks_x * self.block_size;
+        let x1 = (x0 + self.block_size).min(self.width);
+        let y1 = (y0 + self.block_size).min(self.height);
+        (y0..y1).flat_map(move |y| (x0..x1).map(move |x| y * self.width + x))
+    }
+}
+
+/// Coding statistics since the encoder was created
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LongTermReferenceStats {
+    pub frames: u64,
+    pub key_frames: u64,
+    pub scene_resets: u64,          // Key frames forced by a change of scene
+    pub ltr_promotions: u64,
+    pub blocks_copied_previous: u64,
+    pub blocks_copied_long_term: u64,
+    pub residual_blocks_previous: u64,
+    pub residual_blocks_long_term: u64,
+    pub bytes: u64,
+}
+
+impl LongTermReferenceStats {
+    /// Share of inter-frame blocks coded without any residual
+    pub fn copied_ratio(&self) -> f64 {
+        let copied = self.blocks_copied_previous + self.blocks_copied_long_term;
+        let total = copied + self.residual_blocks_previous + self.residual_blocks_long_term;
+        if total == 0 {
+            return 0.0;
+        }
+        copied as f64 / total as f64
+    }
+
+    /// Share of inter-frame blocks predicted from the LTR
+    pub fn long_term_ratio(&self) -> f64 {
+        let long_term = self.blocks_copied_long_term + self.residual_blocks_long_term;
+        let total = long_term + self.blocks_copied_previous + self.residual_blocks_previous;
+        if total == 0 {
+            return 0.0;
+        }
+        long_term as f64 / total as f64
+    }
+}
+
+/// Encoder predicting from the previous frame and a background LTR
+pub struct LongTermReferenceEncoder {
+    config: LongTermReferenceConfig,
+    state: Option<ReferenceState>,
+    frames_since_promotion: u32,
+    force_key_frame: bool,
+    stats: LongTermReferenceStats,
+}
+
+impl LongTermReferenceEncoder {
+    pub fn new(config: LongTermReferenceConfig) -> Result<Self, AfiyahError> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            state: None,
+            frames_since_promotion: 0,
+            force_key_frame: false,
+            stats: LongTermReferenceStats::default(),
+        })
+    }
+
+    pub fn config(&self) -> &LongTermReferenceConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> &LongTermReferenceStats {
+        &self.stats
+    }
+
+    /// Current background model, once a key frame has been coded
+    pub fn background(&self) -> Option<&BackgroundModel> {
+        self.state.as_ref().map(|state| &state.model)
+    }
+
+    /// Codes the next frame as a key frame
+    pub fn force_key_frame(&mut self) {
+        self.force_key_frame = true;
+    }
+
+    /// Encodes one frame; `quality_scale` above 1.0 quantizes more coarsely
+    pub fn encode_frame(&mut self, frame: &VisualInput, quality_scale: f64) -> Result<Vec<u8>, AfiyahError> {
+        let (width, height) = frame.spatial_resolution;
+        let planes = frame_planes(frame)?;
+        // The decoder only sees the f32 step, so the encoder must quantize with exactly that value
+        let step = (self.config.quantizer_step * quality_scale) as f32;
+        if !(step > 0.0 && step.is_finite()) {
+            return Err(AfiyahError::Configuration {
+                message: format!("Quality scale {} gives an unusable quantizer step", quality_scale),
+            });
+        }
+
+        let mut out = Vec::new();
+        let grid = BlockGrid {
+            width,
+            height,
+            block_size: self.config.block_size,
+        };
+        let modes = match self.state.as_ref().filter(|state| state.matches(width, height, planes.len())) {
+            Some(state) if !self.force_key_frame => {
+                let modes = choose_modes(&self.config, state, &grid, &planes);
+                if modes.is_none() {
+                    self.stats.scene_resets += 1;
+                }
+                modes
+            }
+            _ => None,
+        };
+
+        match modes {
+            Some(modes) => {
+                self.frames_since_promotion += 1;
+                let promote = self.frames_since_promotion >= self.config.ltr_refresh_interval;
+                write_header(&mut out, FRAME_INTER, if promote { FLAG_PROMOTE_LTR } else { 0 }, width, height, planes.len(), step);
+                self.encode_inter(&mut out, &grid, &planes, &modes, step as f64, promote);
+            }
+            None => {
+                write_header(&mut out, FRAME_KEY, 0, width, height, planes.len(), step);
+                let reconstruction = encode_key(&mut out, &planes, step as f64);
+                self.state = Some(ReferenceState::key(width, height, reconstruction));
+                self.frames_since_promotion = 0;
+                self.force_key_frame = false;
+                self.stats.key_frames += 1;
+            }
+        }
+
+        self.stats.frames += 1;
+        self.stats.bytes += out.len() as u64;
+        Ok(out)
+    }
+
+    fn encode_inter(
+        &mut self,
+        out: &mut Vec<u8>,
+        grid: &BlockGrid,
+        planes: &[&[f64]],
+        modes: &[BlockMode],
+        step: f64,
+        promote: bool,
+    ) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        write_modes(out, modes);
+
+        let mut reconstruction = state.previous.clone();
+        for (block, &mode) in modes.iter().enumerate() {
+            let reference = state.reference(mode);
+            match mode {
+                BlockMode::CopyPrevious => self.stats.blocks_copied_previous += 1,
+                BlockMode::CopyLongTerm => self.stats.blocks_copied_long_term += 1,
+                BlockMode::ResidualPrevious => self.stats.residual_blocks_previous += 1,
+                BlockMode::ResidualLongTerm => self.stats.residual_blocks_long_term += 1,
+            }
+
+            let mut residuals = Vec::new();
+            for (plane, source) in planes.iter().enumerate() {
+                for index in grid.samples(block) {
+                    let predicted = reference[plane][index];
+                    let level = if mode.has_residual() {
+                        ((source[index] - predicted) / step).round() as i64
+                    } else {
+                        0
+                    };
+                    reconstruction[plane][index] = predicted + level as f64 * step;
+                    residuals.push(level);
+                }
+            }
+            if mode.has_residual() {
+                write_sparse(out, &residuals);
+            }
+        }
+
+        state.advance(reconstruction, promote, &self.config);
+        if promote {
+            self.frames_since_promotion = 0;
+            self.stats.ltr_promotions += 1;
+        }
+    }
+}
+
+/// Each GOP is closed: it opens with a key frame and a fresh background model,
+/// so GOPs can still be encoded in parallel. Frames are length-prefixed.
+impl GopEncoder for LongTermReferenceEncoder {
+    fn encode_gop(&mut self, frames: &[VisualInput], budget: &GopBudget) -> Result<Vec<u8>, AfiyahError> {
+        self.force_key_frame();
+
+        let mut payload = Vec::new();
+        for frame in frames {
+            let data = self.encode_frame(frame, budget.quality_scale)?;
+            let length = u32::try_from(data.len()).map_err(|_| AfiyahError::Compression {
+                message: "Compressed frame exceeds the maximum payload size".to_string(),
+            })?;
+            payload.extend_from_slice(&length.to_le_bytes());
+            payload.extend_from_slice(&data);
+        }
+        Ok(payload)
+    }
+}
+
+/// Frame rebuilt by the decoder
+#[derive(Debug, Clone)]
+pub struct DecodedFrame {
+    pub frame_type: LtrFrameType,
+    pub spatial_resolution: (usize, usize),
+    pub luminance_data: Vec<f64>,
+    pub chrominance_data: Vec<f64>,
+}
+
+/// Decoder mirroring the encoder's references
+pub struct LongTermReferenceDecoder {
+    config: LongTermReferenceConfig,
+    state: Option<ReferenceState>,
+}
+
+impl LongTermReferenceDecoder {
+    /// `config` must match the encoder's, since both sides update the model with it
+    pub fn new(config: LongTermReferenceConfig) -> Result<Self, AfiyahError> {
+        config.validate()?;
+        Ok(Self { config, state: None })
+    }
+
+    pub fn decode_frame(&mut self, data: &[u8]) -> Result<DecodedFrame, AfiyahError> {
+        let mut reader = ByteReader::new(data);
+        let frame_type = reader.u8("frame type")?;
+        let flags = reader.u8("frame flags")?;
+        let width = reader.u32("width")? as usize;
+        let height = reader.u32("height")? as usize;
+        let plane_count = reader.u8("plane count")? as usize;
+        let step = f32::from_bits(reader.u32("quantizer step")?);
+
+        if width == 0 || height == 0 || width.saturating_mul(height) > MAX_FRAME_SAMPLES {
+            return Err(malformed(format!("Unsupported frame size {}x{}", width, height)));
+        }
+        if !(1..=2).contains(&plane_count) {
+            return Err(malformed(format!("Unsupported plane count {}", plane_count)));
+        }
+        if !(step > 0.0 && step.is_finite()) {
+            return Err(BitstreamError::InvalidField {
+                field: "quantizer step",
+                value: step as f64,
+            }
+            .into());
+        }
+        let step = step as f64;
+
+        let frame_type = match frame_type {
+            FRAME_KEY => {
+                let reconstruction = decode_key(&mut reader, width * height, plane_count, step)?;
+                self.state = Some(ReferenceState::key(width, height, reconstruction));
+                LtrFrameType::Key
+            }
+            FRAME_INTER => {
+                let state = self
+                    .state
+                    .as_mut()
+                    .filter(|state| state.matches(width, height, plane_count))
+                    .ok_or_else(|| malformed("Inter frame without a matching key frame".to_string()))?;
+                let grid = BlockGrid {
+                    width,
+                    height,
+                    block_size: self.config.block_size,
+                };
+                let reconstruction = decode_inter(&mut reader, state, &grid, step)?;
+                state.advance(reconstruction, flags & FLAG_PROMOTE_LTR != 0, &self.config);
+                LtrFrameType::Inter
+            }
+            other => return Err(malformed(format!("Unknown frame type {}", other))),
+        };
+        if reader.remaining() > 0 {
+            return Err(BitstreamError::TrailingData(reader.remaining()).into());
+        }
+
+        let state = self.state.as_ref().ok_or_else(|| malformed("No reference state".to_string()))?;
+        Ok(DecodedFrame {
+            frame_type,
+            spatial_resolution: (width, height),
+            luminance_data: state.previous[0].clone(),
+            chrominance_data: state.previous.get(1).cloned().unwrap_or_default(),
+        })
+    }
+}
+
+fn malformed(message: String) -> AfiyahError {
+    BitstreamError::Malformed(message).into()
+}
+
+fn frame_planes(frame: &VisualInput) -> Result<Vec<&[f64]>, AfiyahError> {
+    let (width, height) = frame.spatial_resolution;
+    let samples = width * height;
+    if samples == 0 || samples > MAX_FRAME_SAMPLES || frame.luminance_data.len() != samples {
+        return Err(AfiyahError::InputError {
+            message: format!(
+                "Frame of {}x{} needs {} luminance samples, got {}",
+                width,
+                height,
+                samples,
+                frame.luminance_data.len()
+            ),
+        });
+    }
+    match frame.chrominance_data.len() {
+        0 => Ok(vec![frame.luminance_data.as_slice()]),
+        n if n == samples => Ok(vec![frame.luminance_data.as_slice(), frame.chrominance_data.as_slice()]),
+        n => Err(AfiyahError::InputError {
+            message: format!("Chrominance must be empty or one sample per pixel, got {} for {} pixels", n, samples),
+        }),
+    }
+}
+
+/// Picks the better reference for every block, or `None` on a scene reset
+fn choose_modes(
+    config: &LongTermReferenceConfig,
+    state: &ReferenceState,
+    grid: &BlockGrid,
+    planes: &[&[f64]],
+) -> Option<Vec<BlockMode>> {
+    let mut modes = Vec::with_capacity(grid.count());
+    let mut unmatched = 0;
+    for block in 0..grid.count() {
+        let previous = mean_absolute_difference(grid, block, planes, &state.previous);
+        let long_term = mean_absolute_difference(grid, block, planes, &state.long_term);
+        let (difference, copy, residual) = if long_term < previous {
+            (long_term, BlockMode::CopyLongTerm, BlockMode::ResidualLongTerm)
+        } else {
+            (previous, BlockMode::CopyPrevious, BlockMode::ResidualPrevious)
+        };
+
+        if difference > config.scene_change_difference {
+            unmatched += 1;
+        }
+        modes.push(if difference <= config.skip_threshold { copy } else { residual });
+    }
+
+    if unmatched as f64 > config.scene_reset_fraction * grid.count() as f64 {
+        return None;
+    }
+    Some(modes)
+}
+
+fn mean_absolute_difference(grid: &BlockGrid, block: usize, planes: &[&[f64]], reference: &[Vec<f64>]) -> f64 {
+    let mut total = 0.0;
+    let mut count = 0usize;
+    for (plane, source) in planes.iter().enumerate() {
+        for index in grid.samples(block) {
+            total += (source[index] - reference[plane][index]).abs();
+            count += 1;
+        }
+    }
+    total / count.max(1) as f64
+}
+
+fn write_header(out: &mut Vec<u8>, frame_type: u8, flags: u8, width: usize, height: usize, planes: usize, step: f32) {
+    out.push(frame_type);
+    out.push(flags);
+    out.extend_from_slice(&(width as u32).to_le_bytes());
+    out.extend_from_slice(&(height as u32).to_le_bytes());
+    out.push(planes as u8);
+    out.extend_from_slice(&step.to_bits().to_le_bytes());
+}
+
+fn encode_key(out: &mut Vec<u8>, planes: &[&[f64]], step: f64) -> Vec<Vec<f64>> {
+    planes
+        .iter()
+        .map(|plane| {
+            let mut last = 0i64;
+            plane
+                .iter()
+                .map(|&sample| {
+                    let level = (sample / step).round() as i64;
+                    write_varint(out, zigzag(level.wrapping_sub(last)));
+                    last = level;
+                    level as f64 * step
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn decode_key(reader: &mut ByteReader<'_>, samples: usize, planes: usize, step: f64) -> Result<Vec<Vec<f64>>, AfiyahError> {
+    // Every sample takes at least one byte, which bounds the allocation by the input size
+    if samples * planes > reader.remaining() {
+        return Err(BitstreamError::Truncated {
+            field: "key frame samples",
+            offset: reader.offset(),
+            needed: samples * planes,
+            available: reader.remaining(),
+        }
+        .into());
+    }
+    (0..planes)
+        .map(|_| {
+            let mut last = 0i64;
+            (0..samples)
+                .map(|_| {
+                    last = last.wrapping_add(unzigzag(read_varint(reader)?));
+                    Ok(last as f64 * step)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn write_modes(out: &mut Vec<u8>, modes: &[BlockMode]) {
+    for chunk in modes.chunks(4) {
+        let byte = chunk
+            .iter()
+            .enumerate()
+            .fold(0u8, |byte, (i, mode)| byte | mode.bits() << (i * 2));
+        out.push(byte);
+    }
+}
+
+fn decode_inter(
+    reader: &mut ByteReader<'_>,
+    state: &ReferenceState,
+    grid: &BlockGrid,
+    step: f64,
+) -> Result<Vec<Vec<f64>>, AfiyahError> {
+    let mode_bytes = reader.take("block modes", grid.count().div_ceil(4))?;
+    let mut reconstruction = state.previous.clone();
+
+    for block in 0..grid.count() {
+        let mode = BlockMode::from_bits(mode_bytes[block / 4] >> (block % 4 * 2));
+        let reference = state.reference(mode);
+        let block_samples = grid.samples(block).count() * reconstruction.len();
+        let residuals = if mode.has_residual() {
+            read_sparse(reader, block_samples)?
+        } else {
+            vec![0; block_samples]
+        };
+
+        let mut residuals = residuals.into_iter();
+        for (plane, samples) in reconstruction.iter_mut().enumerate() {
+            for index in grid.samples(block) {
+                let level = residuals.next().unwrap_or(0);
+                samples[index] = reference[plane][index] + level as f64 * step;
+            }
+        }
+    }
+    Ok(reconstruction)
+}
+
+/// Nonzero count, then a gap and value for each nonzero level
+fn write_sparse(out: &mut Vec<u8>, levels: &[i64]) {
+    let nonzero = levels.iter().filter(|&&level| level != 0).count();
+    write_varint(out, nonzero as u64);
+    let mut gap = 0u64;
+    for &level in levels {
+        if level == 0 {
+            gap += 1;
+            continue;
+        }
+        write_varint(out, gap);
+        write_varint(out, zigzag(level));
+        gap = 0;
+    }
+}
+
+fn read_sparse(reader: &mut ByteReader<'_>, len: usize) -> Result<Vec<i64>, AfiyahError> {
+    let nonzero = read_varint(reader)?;
+    if nonzero > len as u64 {
+        return Err(malformed(format!("Block declares {} residuals for {} samples", nonzero, len)));
+    }
+    let mut levels = vec![0; len];
+    let mut position = 0u64;
+    for _ in 0..nonzero {
+        position = position.saturating_add(read_varint(reader)?);
+        if position >= len as u64 {
+            return Err(malformed(format!("Residual at {} lies outside a block of {} samples", position, len)));
+        }
+        levels[position as usize] = unzigzag(read_varint(reader)?);
+        position += 1;
+    }
+    Ok(levels)
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(reader: &mut ByteReader<'_>) -> Result<u64, AfiyahError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = reader.u8("varint")?;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(malformed("Varint longer than 64 bits".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InputMetadata;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+
+    fn background(x: usize, y: usize) -> f64 {
+        0.3 + 0.4 * ((x as f64 * 0.2).sin() * (y as f64 * 0.15).cos()).abs()
+    }
+
+    /// Static textured scene with a bright square at `object`, if any
+    fn frame(object: Option<(usize, usize)>, brightness: f64) -> VisualInput {
+        let luminance_data = (0..WIDTH * HEIGHT)
+            .map(|index| {
+                let (x, y) = (index % WIDTH, index / WIDTH);
+                match object {
+                    Some((ox, oy)) if (ox..ox + 12).contains(&x) && (oy..oy + 12).contains(&y) => 0.95,
+                    _ => background(x, y) * brightness,
+                }
+            })
+            .collect();
+        VisualInput {
+            luminance_data,
+            chrominance_data: vec![0.5; WIDTH * HEIGHT],
+            spatial_resolution: (WIDTH, HEIGHT),
+            temporal_resolution: 30.0,
+            metadata: InputMetadata {
+                viewing_distance: 1.0,
+                ambient_lighting: 100.0,
+                viewer_age: 30,
+                color_temperature: 6500.0,
+            },
+        }
+    }
+
+    fn round_trip(config: LongTermReferenceConfig, frames: &[VisualInput]) -> (LongTermReferenceEncoder, Vec<usize>) {
+        let mut encoder = LongTermReferenceEncoder::new(config.clone()).unwrap();
+        let mut decoder = LongTermReferenceDecoder::new(config).unwrap();
+        let mut sizes = Vec::new();
+        for frame in frames {
+            let data = encoder.encode_frame(frame, 1.0).unwrap();
+            let decoded = decoder.decode_frame(&data).unwrap();
+            let state = encoder.state.as_ref().unwrap();
+            assert_eq!(decoded.luminance_data, state.previous[0], "decoder drifted from the encoder");
+            assert_eq!(decoded.chrominance_data, state.previous[1]);
+            sizes.push(data.len());
+        }
+        (encoder, sizes)
+    }
+
+    #[test]
+    fn test_static_scene_codes_sparse_diffs() {
+        let frames: Vec<_> = (0..60).map(|i| frame(Some((i % 50, 20)), 1.0)).collect();
+        let (encoder, sizes) = round_trip(LongTermReferenceConfig::surveillance(), &frames);
+
+        let stats = encoder.stats();
+        assert_eq!(stats.key_frames, 1);
+        assert!(stats.copied_ratio() > 0.7, "copied ratio {}", stats.copied_ratio());
+        let inter = sizes[1..].iter().sum::<usize>() / (sizes.len() - 1);
+        assert!(inter * 10 < sizes[0], "inter frames average {} bytes against a {} byte key frame", inter, sizes[0]);
+
+        // The moving object never becomes background
+        let model = encoder.background().unwrap();
+        assert!((model.luminance()[0] - background(0, 0)).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_uncovered_background_predicts_from_ltr() {
+        let config = LongTermReferenceConfig {
+            ltr_refresh_interval: 5,
+            ..LongTermReferenceConfig::surveillance()
+        };
+        // Background alone, then an object that stays for a while and leaves
+        let mut frames: Vec<_> = (0..10).map(|_| frame(None, 1.0)).collect();
+        frames.extend((0..10).map(|_| frame(Some((24, 16)), 1.0)));
+        let (encoder, _) = round_trip(config.clone(), &frames);
+        let before_leaving = encoder.stats().blocks_copied_long_term;
+
+        frames.push(frame(None, 1.0));
+        let (encoder, _) = round_trip(config, &frames);
+        assert!(encoder.stats().blocks_copied_long_term > before_leaving);
+        // Only the object's arrival needed residuals; its departure was copied from the LTR
+        assert_eq!(encoder.stats().residual_blocks_previous + encoder.stats().residual_blocks_long_term, 2);
+    }
+
+    #[test]
+    fn test_scene_reset_forces_key_frame() {
+        let mut frames: Vec<_> = (0..5).map(|_| frame(None, 1.0)).collect();
+        frames.extend((0..5).map(|_| frame(None, 0.1)));
+        let (encoder, _) = round_trip(LongTermReferenceConfig::webcam(), &frames);
+        assert_eq!(encoder.stats().scene_resets, 1);
+        assert_eq!(encoder.stats().key_frames, 2);
+
+        let mut decoder = LongTermReferenceDecoder::new(LongTermReferenceConfig::default()).unwrap();
+        let mut encoder = LongTermReferenceEncoder::new(LongTermReferenceConfig::default()).unwrap();
+        encoder.encode_frame(&frames[0], 1.0).unwrap();
+        let inter = encoder.encode_frame(&frames[1], 1.0).unwrap();
+        assert!(decoder.decode_frame(&inter).is_err());
+        assert!(decoder.decode_frame(&inter[..5]).is_err());
+    }
+}
@@ -56,6 +56,8 @@ use ndarray::{Array1, Array2, Array3, s, Axis};
 use std::collections::HashMap;
 use anyhow::{Result, anyhow};
 
+pub mod long_term_reference;
+
 /// Biological motion estimation engine
 pub struct BiologicalMotionEstimator {
     saccadic_predictor: SaccadicMotionPredictor,
@@ -0,0 +1,535 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Colour Vision Module
+//!
+//! Accessibility mode for viewers with colour vision deficiencies (CVD).
+//! Dichromacy is simulated by projecting colours onto the cone plane the
+//! viewer can still tell apart (Viénot, Brettel & Mollon, 1999), with the
+//! severity blending towards normal vision for anomalous trichromats.
+//!
+//! The simulation drives three things:
+//! - Channel weights telling the perceptual optimizer how much of an error
+//!   along the luma and chroma axes the viewer can see, so bits move away
+//!   from colour differences they cannot perceive
+//! - Daltonization hints carried in stream metadata, which decoders apply
+//!   to shift invisible colour contrast into channels the viewer sees
+//! - Quality reports scoring the encode as the viewer sees it
+//!
+//! Biological Foundation:
+//! - Protanopes lack L cones and deuteranopes M cones, confusing red and green
+//! - Tritanopes lack S cones and confuse blue with green and yellow with violet
+//! - Luminance is carried mostly by L and M cones, so dichromats keep most of it
+
+use std::fmt;
+use std::str::FromStr;
+
+use ndarray::Array3;
+use serde::{Deserialize, Serialize};
+
+use crate::AfiyahError;
+use crate::bitstream_formatting::stream_metadata::MetadataMessage;
+
+/// Metadata key daltonization hints are carried under
+pub const DALTONIZATION_KEY: &str = "afiyah.daltonization";
+
+/// Colour difference (CIE76) at which two colours are just told apart
+pub const JUST_NOTICEABLE_DELTA_E: f64 = 2.3;
+
+/// Channel weights never drop below this, so the stream still looks right
+/// to viewers with normal vision
+pub const MIN_CHANNEL_WEIGHT: f64 = 0.25;
+
+/// Linear RGB to LMS cone responses (Viénot et al., 1999)
+const RGB_TO_LMS: [[f64; 3]; 3] = [
+    [17.8824, 43.5161, 4.11935],
+    [3.45565, 27.1554, 3.86714],
+    [0.0299566, 0.184309, 1.46709],
+];
+
+const LMS_TO_RGB: [[f64; 3]; 3] = [
+    [0.0809444479, -0.130504409, 0.116721066],
+    [-0.0102485335, 0.0540193266, -0.113614708],
+    [-0.000365296938, -0.00412161469, 0.693511405],
+];
+
+const IDENTITY: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Offset around mid grey used to measure channel visibility
+const PROBE_STEP: f64 = 0.05;
+
+/// Common dichromacies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ColourVisionDeficiency {
+    /// Missing L cones
+    Protanopia,
+    /// Missing M cones, the most common form
+    Deuteranopia,
+    /// Missing S cones
+    Tritanopia,
+}
+
+impl ColourVisionDeficiency {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColourVisionDeficiency::Protanopia => "protanopia",
+            ColourVisionDeficiency::Deuteranopia => "deuteranopia",
+            ColourVisionDeficiency::Tritanopia => "tritanopia",
+        }
+    }
+
+    /// Rebuilds the missing cone response from the other two
+    fn projection(&self) -> [[f64; 3]; 3] {
+        match self {
+            ColourVisionDeficiency::Protanopia => [[0.0, 2.02344, -2.52581], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            ColourVisionDeficiency::Deuteranopia => [[1.0, 0.0, 0.0], [0.494207, 0.0, 1.24827], [0.0, 0.0, 1.0]],
+            ColourVisionDeficiency::Tritanopia => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-0.395913, 0.801109, 0.0]],
+        }
+    }
+
+    /// Moves the colour error the viewer cannot see into channels they can
+    /// (Fidaner, Lin & Ozguven, 2005)
+    fn error_shift(&self) -> [[f64; 3]; 3] {
+        match self {
+            ColourVisionDeficiency::Protanopia | ColourVisionDeficiency::Deuteranopia => {
+                [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]]
+            }
+            ColourVisionDeficiency::Tritanopia => [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]],
+        }
+    }
+}
+
+impl fmt::Display for ColourVisionDeficiency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ColourVisionDeficiency {
+    type Err = AfiyahError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "protanopia" | "protan" => Ok(ColourVisionDeficiency::Protanopia),
+            "deuteranopia" | "deutan" => Ok(ColourVisionDeficiency::Deuteranopia),
+            "tritanopia" | "tritan" => Ok(ColourVisionDeficiency::Tritanopia),
+            _ => Err(AfiyahError::Configuration { message: format!("Unknown colour vision deficiency {}", s) }),
+        }
+    }
+}
+
+/// A deficiency and how strong it is: 1.0 is dichromacy, lower values model
+/// anomalous trichromacy (protanomaly, deuteranomaly, tritanomaly)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CvdProfile {
+    pub deficiency: ColourVisionDeficiency,
+    pub severity: f64,
+}
+
+impl CvdProfile {
+    pub fn new(deficiency: ColourVisionDeficiency, severity: f64) -> Result<Self, AfiyahError> {
+        if !(0.0..=1.0).contains(&severity) {
+            return Err(AfiyahError::Configuration {
+                message: format!("Severity of {} must be between 0 and 1, got {}", deficiency, severity),
+            });
+        }
+        Ok(Self { deficiency, severity })
+    }
+
+    /// Full dichromacy
+    pub fn dichromat(deficiency: ColourVisionDeficiency) -> Self {
+        Self { deficiency, severity: 1.0 }
+    }
+
+    /// Linear RGB as the viewer sees it
+    fn simulation(&self) -> [[f64; 3]; 3] {
+        let dichromat = multiply(&LMS_TO_RGB, &multiply(&self.deficiency.projection(), &RGB_TO_LMS));
+        let mut blended = IDENTITY;
+        for (row, dichromat_row) in blended.iter_mut().zip(dichromat.iter()) {
+            for (value, dichromat_value) in row.iter_mut().zip(dichromat_row.iter()) {
+                *value += (dichromat_value - *value) * self.severity;
+            }
+        }
+        blended
+    }
+
+    /// An sRGB colour (0.0-1.0 per channel) as the viewer sees it
+    pub fn simulate_pixel(&self, rgb: [f64; 3]) -> [f64; 3] {
+        self.simulate_with(&self.simulation(), rgb)
+    }
+
+    fn simulate_with(&self, simulation: &[[f64; 3]; 3], rgb: [f64; 3]) -> [f64; 3] {
+        let seen = apply(simulation, rgb.map(srgb_to_linear));
+        seen.map(|channel| linear_to_srgb(channel.clamp(0.0, 1.0)))
+    }
+
+    /// An sRGB frame, shaped (height, width, 3), as the viewer sees it
+    pub fn simulate(&self, frame: &Array3<f64>) -> Result<Array3<f64>, AfiyahError> {
+        check_frame(frame)?;
+        let simulation = self.simulation();
+        Ok(map_pixels(frame, |rgb| self.simulate_with(&simulation, rgb)))
+    }
+
+    /// Recolours an sRGB frame so contrast the viewer would lose shows up in
+    /// channels they can see. `strength` scales the correction (0.0-1.0).
+    pub fn daltonize(&self, frame: &Array3<f64>, strength: f64) -> Result<Array3<f64>, AfiyahError> {
+        check_frame(frame)?;
+        let simulation = self.simulation();
+        let shift = self.deficiency.error_shift();
+        Ok(map_pixels(frame, |rgb| {
+            let seen = self.simulate_with(&simulation, rgb);
+            let error = [rgb[0] - seen[0], rgb[1] - seen[1], rgb[2] - seen[2]];
+            let correction = apply(&shift, error);
+            [0, 1, 2].map(|c| (rgb[c] + correction[c] * strength).clamp(0.0, 1.0))
+        }))
+    }
+
+    /// How much of an error along each YCbCr axis the viewer still sees
+    pub fn channel_weights(&self) -> ChannelWeights {
+        let simulation = self.simulation();
+        let visibility = |axis: [f64; 3]| {
+            let probe = |sign: f64| {
+                let ycbcr = [0.5 + sign * axis[0], sign * axis[1], sign * axis[2]];
+                rgb_to_ycbcr(self.simulate_with(&simulation, ycbcr_to_rgb(ycbcr)))
+            };
+            let (above, below) = (probe(1.0), probe(-1.0));
+            let seen = (0..3).map(|c| (above[c] - below[c]).powi(2)).sum::<f64>().sqrt();
+            (seen / (2.0 * PROBE_STEP)).clamp(MIN_CHANNEL_WEIGHT, 1.0)
+        };
+        ChannelWeights {
+            luma: visibility([PROBE_STEP, 0.0, 0.0]),
+            cb: visibility([0.0, PROBE_STEP, 0.0]),
+            cr: visibility([0.0, 0.0, PROBE_STEP]),
+        }
+    }
+}
+
+/// Visibility of errors along each YCbCr axis, relative to normal vision.
+/// Quantizers can widen a channel's step size by the reciprocal.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChannelWeights {
+    pub luma: f64,
+    pub cb: f64,
+    pub cr: f64,
+}
+
+impl ChannelWeights {
+    pub fn normal() -> Self {
+        Self { luma: 1.0, cb: 1.0, cr: 1.0 }
+    }
+}
+
+impl Default for ChannelWeights {
+    fn default() -> Self {
+        Self::normal()
+    }
+}
+
+/// Tells decoders to daltonize for a viewer profile. Players apply it only
+/// when the viewer has opted in, so the stream itself is unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DaltonizationHint {
+    pub profile: CvdProfile,
+    pub strength: f64,
+}
+
+impl DaltonizationHint {
+    pub fn new(profile: CvdProfile, strength: f64) -> Result<Self, AfiyahError> {
+        if !(0.0..=1.0).contains(&strength) {
+            return Err(AfiyahError::Configuration {
+                message: format!("Daltonization strength must be between 0 and 1, got {}", strength),
+            });
+        }
+        Ok(Self { profile, strength })
+    }
+
+    /// The hint as a key-value metadata message
+    pub fn to_metadata(&self) -> Result<MetadataMessage, AfiyahError> {
+        let value = serde_json::to_string(self).map_err(|e| AfiyahError::BitstreamFormatting {
+            message: format!("Failed to encode daltonization hint: {}", e),
+        })?;
+        Ok(MetadataMessage::KeyValue { key: DALTONIZATION_KEY.to_string(), value })
+    }
+
+    /// The last daltonization hint among a packet's messages, if any
+    pub fn from_metadata(messages: &[MetadataMessage]) -> Result<Option<Self>, AfiyahError> {
+        let Some(value) = messages.iter().rev().find_map(|message| match message {
+            MetadataMessage::KeyValue { key, value } if key == DALTONIZATION_KEY => Some(value),
+            _ => None,
+        }) else {
+            return Ok(None);
+        };
+        let hint: Self = serde_json::from_str(value).map_err(|e| AfiyahError::BitstreamFormatting {
+            message: format!("Invalid daltonization hint {}: {}", value, e),
+        })?;
+        let profile = CvdProfile::new(hint.profile.deficiency, hint.profile.severity)?;
+        Self::new(profile, hint.strength).map(Some)
+    }
+
+    /// Daltonizes a decoded sRGB frame
+    pub fn apply(&self, frame: &Array3<f64>) -> Result<Array3<f64>, AfiyahError> {
+        self.profile.daltonize(frame, self.strength)
+    }
+}
+
+/// Fidelity of a distorted frame to its reference
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColourQuality {
+    /// Over all three sRGB channels, capped at 100 dB for identical frames
+    pub psnr: f64,
+    /// Mean CIE76 colour difference
+    pub mean_delta_e: f64,
+    /// Share of pixels differing by more than a just-noticeable difference
+    pub visible_differences: f64,
+}
+
+impl ColourQuality {
+    fn measure(reference: &Array3<f64>, distorted: &Array3<f64>) -> Self {
+        let pixels = reference.dim().0 * reference.dim().1;
+        let mse = reference.iter().zip(distorted.iter()).map(|(a, b)| (a - b).powi(2)).sum::<f64>() / (pixels * 3) as f64;
+        let psnr = if mse == 0.0 { 100.0 } else { 10.0 * (1.0 / mse).log10() };
+
+        let (mut total_delta_e, mut visible) = (0.0, 0);
+        for (a, b) in pixel_iter(reference).zip(pixel_iter(distorted)) {
+            let delta_e = delta_e(a, b);
+            total_delta_e += delta_e;
+            if delta_e > JUST_NOTICEABLE_DELTA_E {
+                visible += 1;
+            }
+        }
+        Self {
+            psnr,
+            mean_delta_e: total_delta_e / pixels as f64,
+            visible_differences: visible as f64 / pixels as f64,
+        }
+    }
+}
+
+/// Quality of an encode for viewers with normal vision and for a CVD profile
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CvdQualityReport {
+    pub profile: CvdProfile,
+    pub normal: ColourQuality,
+    /// Distorted against reference, both as the profile sees them
+    pub cvd: ColourQuality,
+    /// Mean colour difference between the reference and the profile's view
+    /// of it: how much colour information the viewer loses before coding
+    pub confusion: f64,
+}
+
+impl CvdQualityReport {
+    pub fn assess(reference: &Array3<f64>, distorted: &Array3<f64>, profile: CvdProfile) -> Result<Self, AfiyahError> {
+        check_frame(reference)?;
+        if reference.dim() != distorted.dim() {
+            return Err(AfiyahError::InputError {
+                message: format!("Reference is {:?} but distorted frame is {:?}", reference.dim(), distorted.dim()),
+            });
+        }
+        let seen_reference = profile.simulate(reference)?;
+        let seen_distorted = profile.simulate(distorted)?;
+        let pixels = (reference.dim().0 * reference.dim().1).max(1);
+        let confusion = pixel_iter(reference).zip(pixel_iter(&seen_reference)).map(|(a, b)| delta_e(a, b)).sum::<f64>() / pixels as f64;
+
+        Ok(Self {
+            profile,
+            normal: ColourQuality::measure(reference, distorted),
+            cvd: ColourQuality::measure(&seen_reference, &seen_distorted),
+            confusion,
+        })
+    }
+}
+
+fn check_frame(frame: &Array3<f64>) -> Result<(), AfiyahError> {
+    let (height, width, channels) = frame.dim();
+    if channels != 3 || height == 0 || width == 0 {
+        return Err(AfiyahError::InputError {
+            message: format!("Expected a non-empty RGB frame, got shape {:?}", frame.dim()),
+        });
+    }
+    Ok(())
+}
+
+fn pixel_iter(frame: &Array3<f64>) -> impl Iterator<Item = [f64; 3]> + '_ {
+    frame.outer_iter().flat_map(|row| row.outer_iter().map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect::<Vec<_>>())
+}
+
+fn map_pixels(frame: &Array3<f64>, mut f: impl FnMut([f64; 3]) -> [f64; 3]) -> Array3<f64> {
+    let mut mapped = frame.clone();
+    for mut row in mapped.outer_iter_mut() {
+        for mut pixel in row.outer_iter_mut() {
+            let rgb = f([pixel[0], pixel[1], pixel[2]]);
+            for (c, value) in rgb.into_iter().enumerate() {
+                pixel[c] = value;
+            }
+        }
+    }
+    mapped
+}
+
+fn multiply(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut product = [[0.0; 3]; 3];
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    product
+}
+
+fn apply(matrix: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+/// BT.709 with chroma centred on zero
+fn rgb_to_ycbcr(rgb: [f64; 3]) -> [f64; 3] {
+    let y = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+    [y, (rgb[2] - y) / 1.8556, (rgb[0] - y) / 1.5748]
+}
+
+fn ycbcr_to_rgb(ycbcr: [f64; 3]) -> [f64; 3] {
+    let [y, cb, cr] = ycbcr;
+    [y + 1.5748 * cr, y - 0.1873 * cb - 0.4681 * cr, y + 1.8556 * cb]
+}
+
+/// CIELAB under D65
+fn srgb_to_lab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(|c| srgb_to_linear(c.clamp(0.0, 1.0)));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f64| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn delta_e(a: [f64; 3], b: [f64; 3]) -> f64 {
+    let (a, b) = (srgb_to_lab(a), srgb_to_lab(b));
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(height: usize, width: usize, rgb: [f64; 3]) -> Array3<f64> {
+        Array3::from_shape_fn((height, width, 3), |(_, _, c)| rgb[c])
+    }
+
+    #[test]
+    fn test_dichromats_confuse_their_colours() {
+        let (red, green, blue) = ([0.8, 0.2, 0.2], [0.3, 0.6, 0.2], [0.2, 0.3, 0.8]);
+        let deutan = CvdProfile::dichromat(ColourVisionDeficiency::Deuteranopia);
+        let tritan = CvdProfile::dichromat(ColourVisionDeficiency::Tritanopia);
+
+        // Red and green are far apart, but close for a deuteranope
+        assert!(delta_e(red, green) > 50.0);
+        assert!(delta_e(deutan.simulate_pixel(red), deutan.simulate_pixel(green)) < delta_e(red, green) / 2.0);
+
+        // Greys look the same to everyone
+        for profile in [deutan, tritan, CvdProfile::dichromat(ColourVisionDeficiency::Protanopia)] {
+            assert!(delta_e(profile.simulate_pixel([0.5; 3]), [0.5; 3]) < 1.0);
+        }
+
+        // No severity is normal vision
+        let none = CvdProfile::new(ColourVisionDeficiency::Protanopia, 0.0).unwrap();
+        assert!(delta_e(none.simulate_pixel(blue), blue) < 1e-6);
+        assert!(CvdProfile::new(ColourVisionDeficiency::Protanopia, 1.5).is_err());
+        assert_eq!("deutan".parse::<ColourVisionDeficiency>().unwrap(), ColourVisionDeficiency::Deuteranopia);
+    }
+
+    #[test]
+    fn test_channel_weights_follow_the_confusion_axis() {
+        let deutan = CvdProfile::dichromat(ColourVisionDeficiency::Deuteranopia).channel_weights();
+        assert!(deutan.luma > 0.9);
+        assert!(deutan.cr < deutan.cb);
+        assert!(deutan.cr < 0.6);
+
+        let tritan = CvdProfile::dichromat(ColourVisionDeficiency::Tritanopia).channel_weights();
+        assert!(tritan.cb < tritan.cr);
+
+        let mild = CvdProfile::new(ColourVisionDeficiency::Deuteranopia, 0.3).unwrap().channel_weights();
+        assert!(mild.cr > deutan.cr);
+        let none = CvdProfile::new(ColourVisionDeficiency::Deuteranopia, 0.0).unwrap().channel_weights();
+        assert!((none.cr - 1.0).abs() < 0.05 && (none.cb - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_daltonization_hint_round_trips_and_restores_contrast() {
+        let profile = CvdProfile::dichromat(ColourVisionDeficiency::Protanopia);
+        let hint = DaltonizationHint::new(profile, 0.8).unwrap();
+        let messages = vec![
+            MetadataMessage::KeyValue { key: "title".to_string(), value: "Retina".to_string() },
+            hint.to_metadata().unwrap(),
+        ];
+        assert_eq!(DaltonizationHint::from_metadata(&messages).unwrap(), Some(hint));
+        assert_eq!(DaltonizationHint::from_metadata(&messages[..1]).unwrap(), None);
+        let bad = MetadataMessage::KeyValue { key: DALTONIZATION_KEY.to_string(), value: "{}".to_string() };
+        assert!(DaltonizationHint::from_metadata(&[bad]).is_err());
+
+        // After daltonizing, red and green are further apart as the viewer sees them
+        let (red, green) = (solid(1, 1, [0.8, 0.3, 0.3]), solid(1, 1, [0.4, 0.55, 0.3]));
+        let seen = |frame: &Array3<f64>| pixel_iter(&profile.simulate(frame).unwrap()).next().unwrap();
+        let before = delta_e(seen(&red), seen(&green));
+        let after = delta_e(seen(&hint.apply(&red).unwrap()), seen(&hint.apply(&green).unwrap()));
+        assert!(after > before, "{} <= {}", after, before);
+    }
+
+    #[test]
+    fn test_cvd_quality_discounts_invisible_errors() {
+        let reference = solid(8, 8, [0.5, 0.5, 0.5]);
+        // A red-green error a deuteranope barely sees, against a luma error of similar size
+        let chroma = map_pixels(&reference, |rgb| {
+            let [y, cb, cr] = rgb_to_ycbcr(rgb);
+            ycbcr_to_rgb([y, cb, cr + 0.06])
+        });
+        let luma = map_pixels(&reference, |rgb| [rgb[0] + 0.06, rgb[1] + 0.06, rgb[2] + 0.06]);
+        let profile = CvdProfile::dichromat(ColourVisionDeficiency::Deuteranopia);
+
+        let chroma_report = CvdQualityReport::assess(&reference, &chroma, profile).unwrap();
+        let luma_report = CvdQualityReport::assess(&reference, &luma, profile).unwrap();
+        assert!(chroma_report.cvd.mean_delta_e < chroma_report.normal.mean_delta_e / 2.0);
+        assert!(chroma_report.cvd.psnr > chroma_report.normal.psnr);
+        assert!((luma_report.cvd.mean_delta_e - luma_report.normal.mean_delta_e).abs() < 1.0);
+        assert!(chroma_report.confusion < 1.0);
+
+        let identical = CvdQualityReport::assess(&reference, &reference, profile).unwrap();
+        assert_eq!((identical.normal.psnr, identical.cvd.visible_differences), (100.0, 0.0));
+        assert!(CvdQualityReport::assess(&reference, &solid(4, 8, [0.5; 3]), profile).is_err());
+    }
+}
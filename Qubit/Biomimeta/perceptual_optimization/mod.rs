@@ -75,11 +75,13 @@ pub mod quality_metrics;
 pub mod temporal_prediction_networks;
 pub mod foveal_sampling;
 pub mod perceptual_error_model;
+pub mod colour_vision;
 
 // Re-export the main types
 pub use masking_algorithms::{MaskingAlgorithm, MaskingParams};
 pub use quality_metrics::{QualityCalculator, QualityMetrics};
 pub use temporal_prediction_networks::TemporalPredictionNetwork;
+pub use colour_vision::{ColourVisionDeficiency, CvdProfile, ChannelWeights, DaltonizationHint, CvdQualityReport, ColourQuality};
 
 /// Perceptual optimizer implementing biological perceptual optimization
 pub struct PerceptualOptimizer {
@@ -96,6 +98,8 @@ pub struct OptimizationParams {
     pub quality_threshold: f64,
     pub prediction_enabled: bool,
     pub optimization_strength: f64,
+    /// Weights errors by what viewers with this deficiency can see
+    pub colour_vision: Option<CvdProfile>,
 }
 
 impl Default for OptimizationParams {
@@ -105,6 +109,7 @@ impl Default for OptimizationParams {
             quality_threshold: 0.8,
             prediction_enabled: true,
             optimization_strength: 0.9,
+            colour_vision: None,
         }
     }
 }
@@ -270,4 +275,11 @@ impl PerceptualOptimizer {
     pub fn get_params(&self) -> &OptimizationParams {
         &self.optimization_params
     }
+
+    /// Visibility of luma and chroma errors for the target audience
+    pub fn channel_weights(&self) -> ChannelWeights {
+        self.optimization_params.colour_vision
+            .map(|profile| profile.channel_weights())
+            .unwrap_or_default()
+    }
 }
\ No newline at end of file
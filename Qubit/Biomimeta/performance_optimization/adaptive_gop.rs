@@ -0,0 +1,553 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Adaptive GOP Structure and Key Frame Placement
+//!
+//! Instead of opening a GOP every fixed number of frames, key frames are
+//! placed where the content asks for one: at scene changes, where nothing
+//! in the previous GOP helps prediction, and at predicted attention shifts,
+//! where a viewer's gaze is about to jump to something new and a clean
+//! reference pays off. A minimum GOP length keeps bursts of signals from
+//! flooding the stream with key frames, and a maximum, tightened by the
+//! seekability target, bounds how far a seek has to decode from.
+//!
+//! Both signals are computed on a small luminance thumbnail. The scene
+//! change score mixes the luminance histogram distance with the mean
+//! thumbnail difference between consecutive frames. Attention shifts come
+//! from the saccade predictor, fed with saliency onsets (regions more
+//! salient than in the previous frame) and a fovea at the current predicted
+//! fixation, so a static but busy scene does not keep pulling the gaze.
+//!
+//! Biological Foundation:
+//! - Abrupt onsets capture attention and trigger saccades (Yantis & Jonides, 1984)
+//! - Saccadic suppression hides the transient of a fresh reference at the jump
+//! - Event segmentation splits continuous experience at scene boundaries
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::cortical_processing::attention_mechanisms::saccade_prediction::{SaccadePredictor, TargetType};
+use crate::cortical_processing::attention_mechanisms::{FovealMap, SaliencyMap, SaliencyProcessor};
+use crate::{AfiyahError, VisualInput};
+
+/// Luminance histogram bins used by the scene change score
+const HISTOGRAM_BINS: usize = 32;
+
+/// Mean thumbnail difference that counts as a complete change of layout
+const FULL_LAYOUT_CHANGE: f64 = 0.25;
+
+/// Radius of the predicted fovea, as a fraction of the thumbnail edge
+const FOVEA_FRACTION: f64 = 0.15;
+
+/// Adaptive GOP and key frame placement configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveGopConfig {
+    pub min_gop_frames: usize,          // Frames a GOP holds before a signal may close it
+    pub max_gop_frames: usize,          // Longest GOP regardless of content
+    pub max_seek_seconds: f64,          // Longest stretch between key frames a seek may have to decode; 0 disables
+    pub scene_change_threshold: f64,    // Scene change score in [0, 1] that places a key frame
+    pub attention_shift_threshold: f64, // Predicted attention shift score in [0, 1] that places a key frame
+    pub attention_shift_distance: f64,  // Saccade amplitude, as a fraction of the frame diagonal, that scores 1.0
+    pub analysis_resolution: usize,     // Edge of the square luminance thumbnail the signals are computed on
+}
+
+impl Default for AdaptiveGopConfig {
+    fn default() -> Self {
+        Self {
+            min_gop_frames: 8,
+            max_gop_frames: 300,
+            max_seek_seconds: 2.0,
+            scene_change_threshold: 0.4,
+            attention_shift_threshold: 0.6,
+            attention_shift_distance: 0.35,
+            analysis_resolution: 32,
+        }
+    }
+}
+
+impl AdaptiveGopConfig {
+    /// Low-latency streaming: short GOPs so joins and seeks land quickly
+    pub fn streaming() -> Self {
+        Self {
+            min_gop_frames: 4,
+            max_seek_seconds: 1.0,
+            ..Self::default()
+        }
+    }
+
+    /// Video on demand: long GOPs where the content allows, seekable every few seconds
+    pub fn on_demand() -> Self {
+        Self {
+            min_gop_frames: 12,
+            max_seek_seconds: 5.0,
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), AfiyahError> {
+        let invalid = |message: &str| {
+            Err(AfiyahError::Configuration {
+                message: message.to_string(),
+            })
+        };
+        if self.min_gop_frames == 0 {
+            return invalid("Minimum GOP must be at least one frame");
+        }
+        if self.max_gop_frames < self.min_gop_frames {
+            return invalid("Maximum GOP must not be shorter than the minimum GOP");
+        }
+        if !(self.max_seek_seconds >= 0.0 && self.max_seek_seconds.is_finite()) {
+            return invalid("Seekability target must be a non-negative number of seconds");
+        }
+        if !(self.scene_change_threshold > 0.0 && self.scene_change_threshold <= 1.0) {
+            return invalid("Scene change threshold must be in (0, 1]");
+        }
+        if !(self.attention_shift_threshold > 0.0 && self.attention_shift_threshold <= 1.0) {
+            return invalid("Attention shift threshold must be in (0, 1]");
+        }
+        if self.attention_shift_distance.is_nan() || self.attention_shift_distance <= 0.0 {
+            return invalid("Attention shift distance must be positive");
+        }
+        if self.analysis_resolution < 4 {
+            return invalid("Analysis resolution must be at least 4 samples");
+        }
+        Ok(())
+    }
+
+    /// Longest GOP at `frame_rate`, with the seekability target applied
+    pub fn max_gop_for(&self, frame_rate: f64) -> usize {
+        if self.max_seek_seconds == 0.0 {
+            return self.max_gop_frames;
+        }
+        let seekable = (self.max_seek_seconds * frame_rate).floor().max(1.0) as usize;
+        self.max_gop_frames.min(seekable)
+    }
+}
+
+/// Per-frame signals driving key frame placement, each in [0, 1]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameSignals {
+    pub scene_change: f64,
+    pub attention_shift: f64,
+}
+
+/// Why a frame was made a key frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyframeReason {
+    StreamStart,
+    SceneChange,
+    AttentionShift,
+    MaxGop, // The maximum GOP or seekability target was reached
+}
+
+/// Where key frames went and why
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyframePlacementStats {
+    pub frames: u64,
+    pub keyframes: u64,
+    pub scene_change_keyframes: u64,
+    pub attention_keyframes: u64,
+    pub max_gop_keyframes: u64,
+    pub suppressed_signals: u64, // Signals inside the minimum GOP that placed no key frame
+    pub longest_gop: usize,
+}
+
+impl KeyframePlacementStats {
+    pub fn mean_gop_frames(&self) -> f64 {
+        if self.keyframes == 0 {
+            return 0.0;
+        }
+        self.frames as f64 / self.keyframes as f64
+    }
+}
+
+/// Decides frame by frame whether to open a new GOP
+pub struct KeyframePlanner {
+    config: AdaptiveGopConfig,
+    max_gop: usize,
+    frames_in_gop: usize,
+    stats: KeyframePlacementStats,
+}
+
+impl KeyframePlanner {
+    pub fn new(config: AdaptiveGopConfig, frame_rate: f64) -> Result<Self, AfiyahError> {
+        config.validate()?;
+        if !(frame_rate > 0.0 && frame_rate.is_finite()) {
+            return Err(AfiyahError::Configuration {
+                message: "Frame rate must be positive".to_string(),
+            });
+        }
+        let max_gop = config.max_gop_for(frame_rate);
+        if max_gop < config.min_gop_frames {
+            return Err(AfiyahError::Configuration {
+                message: format!(
+                    "A {}s seekability target at {} fps allows GOPs of {} frames, below the minimum of {}",
+                    config.max_seek_seconds, frame_rate, max_gop, config.min_gop_frames
+                ),
+            });
+        }
+
+        Ok(Self {
+            config,
+            max_gop,
+            frames_in_gop: 0,
+            stats: KeyframePlacementStats::default(),
+        })
+    }
+
+    /// Longest GOP the planner allows
+    pub fn max_gop_frames(&self) -> usize {
+        self.max_gop
+    }
+
+    /// Places the next frame, returning the reason if it opens a new GOP
+    pub fn place(&mut self, signals: FrameSignals) -> Option<KeyframeReason> {
+        let signal = if signals.scene_change >= self.config.scene_change_threshold {
+            Some(KeyframeReason::SceneChange)
+        } else if signals.attention_shift >= self.config.attention_shift_threshold {
+            Some(KeyframeReason::AttentionShift)
+        } else {
+            None
+        };
+
+        let reason = if self.stats.frames == 0 {
+            Some(KeyframeReason::StreamStart)
+        } else if self.frames_in_gop >= self.max_gop {
+            Some(KeyframeReason::MaxGop)
+        } else if signal.is_some() && self.frames_in_gop < self.config.min_gop_frames {
+            self.stats.suppressed_signals += 1;
+            None
+        } else {
+            signal
+        };
+
+        self.stats.frames += 1;
+        match reason {
+            Some(reason) => {
+                self.stats.keyframes += 1;
+                match reason {
+                    KeyframeReason::SceneChange => self.stats.scene_change_keyframes += 1,
+                    KeyframeReason::AttentionShift => self.stats.attention_keyframes += 1,
+                    KeyframeReason::MaxGop => self.stats.max_gop_keyframes += 1,
+                    KeyframeReason::StreamStart => {}
+                }
+                self.frames_in_gop = 1;
+            }
+            None => self.frames_in_gop += 1,
+        }
+        self.stats.longest_gop = self.stats.longest_gop.max(self.frames_in_gop);
+        reason
+    }
+
+    pub fn stats(&self) -> &KeyframePlacementStats {
+        &self.stats
+    }
+}
+
+/// Luminance summary of the previous frame
+struct FrameSummary {
+    resolution: (usize, usize),
+    thumbnail: Array2<f64>,
+    histogram: [f64; HISTOGRAM_BINS],
+    saliency: Array2<f64>,
+}
+
+/// Computes scene change and predicted attention shift signals per frame
+pub struct KeyframeAnalyzer {
+    resolution: usize,
+    shift_distance: f64,
+    saliency: SaliencyProcessor,
+    saccades: SaccadePredictor,
+    fixation: (f64, f64),
+    previous: Option<FrameSummary>,
+}
+
+impl KeyframeAnalyzer {
+    pub fn new(config: &AdaptiveGopConfig) -> Result<Self, AfiyahError> {
+        config.validate()?;
+        let center = config.analysis_resolution as f64 / 2.0;
+        Ok(Self {
+            resolution: config.analysis_resolution,
+            shift_distance: config.attention_shift_distance,
+            saliency: SaliencyProcessor::new()?,
+            saccades: SaccadePredictor::new()?,
+            fixation: (center, center),
+            previous: None,
+        })
+    }
+
+    /// Predicted fixation on the analysis thumbnail, as (x, y)
+    pub fn fixation(&self) -> (f64, f64) {
+        self.fixation
+    }
+
+    pub fn analyze(&mut self, frame: &VisualInput) -> Result<FrameSignals, AfiyahError> {
+        let (width, height) = frame.spatial_resolution;
+        if width == 0 || height == 0 || frame.luminance_data.len() < width * height {
+            return Err(AfiyahError::InputError {
+                message: format!(
+                    "Frame holds {} luminance samples for a {}x{} resolution",
+                    frame.luminance_data.len(),
+                    width,
+                    height
+                ),
+            });
+        }
+
+        let thumbnail = self.thumbnail(&frame.luminance_data, width, height);
+        let histogram = histogram(&frame.luminance_data[..width * height]);
+        let saliency = self.saliency.compute_saliency(&thumbnail)?.weights;
+
+        let signals = match self.previous.take() {
+            Some(previous) if previous.resolution == (width, height) => FrameSignals {
+                scene_change: scene_change_score(&previous, &thumbnail, &histogram),
+                attention_shift: self.attention_shift(&previous.saliency, &saliency)?,
+            },
+            // A new resolution cannot predict from the old one
+            Some(_) => FrameSignals {
+                scene_change: 1.0,
+                attention_shift: 0.0,
+            },
+            None => FrameSignals::default(),
+        };
+
+        self.previous = Some(FrameSummary {
+            resolution: (width, height),
+            thumbnail,
+            histogram,
+            saliency,
+        });
+        Ok(signals)
+    }
+
+    /// Box-filtered luminance at the analysis resolution
+    fn thumbnail(&self, luminance: &[f64], width: usize, height: usize) -> Array2<f64> {
+        let n = self.resolution;
+        Array2::from_shape_fn((n, n), |(row, col)| {
+            let (y0, y1) = (row * height / n, ((row + 1) * height / n).max(row * height / n + 1).min(height));
+            let (x0, x1) = (col * width / n, ((col + 1) * width / n).max(col * width / n + 1).min(width));
+            let mut sum = 0.0;
+            for y in y0..y1 {
+                sum += luminance[y * width + x0..y * width + x1].iter().sum::<f64>();
+            }
+            sum / ((y1 - y0) * (x1 - x0)) as f64
+        })
+    }
+
+    /// Scores the saccade toward the strongest saliency onset outside the fovea,
+    /// and moves the predicted fixation there
+    fn attention_shift(&mut self, previous: &Array2<f64>, current: &Array2<f64>) -> Result<f64, AfiyahError> {
+        let n = self.resolution;
+        let peak = current.iter().cloned().fold(0.0, f64::max);
+        if peak <= 0.0 {
+            return Ok(0.0);
+        }
+
+        let mut onsets = SaliencyMap::new(n, n);
+        onsets.weights = (current - previous).mapv(|onset| onset.max(0.0) / peak);
+
+        let mut fovea = FovealMap::new(n, n);
+        fovea.foveal_center = self.fixation;
+        let radius = n as f64 * FOVEA_FRACTION;
+        fovea.weights = Array2::from_shape_fn((n, n), |(row, col)| {
+            let dx = col as f64 - self.fixation.0;
+            let dy = row as f64 - self.fixation.1;
+            (-(dx * dx + dy * dy) / (radius * radius)).exp()
+        });
+
+        let targets = self.saccades.predict_saccades(&fovea, &onsets)?;
+        let Some(target) = targets.iter().find(|target| target.target_type == TargetType::Salient) else {
+            return Ok(0.0);
+        };
+
+        let diagonal = (2.0 * (n * n) as f64).sqrt();
+        let amplitude = (target.saccade_vector.magnitude / (self.shift_distance * diagonal)).min(1.0);
+        self.fixation = target.position;
+        Ok(target.confidence * amplitude)
+    }
+}
+
+fn histogram(luminance: &[f64]) -> [f64; HISTOGRAM_BINS] {
+    let mut histogram = [0.0; HISTOGRAM_BINS];
+    for &sample in luminance {
+        let bin = (sample.clamp(0.0, 1.0) * HISTOGRAM_BINS as f64) as usize;
+        histogram[bin.min(HISTOGRAM_BINS - 1)] += 1.0;
+    }
+    let total = luminance.len().max(1) as f64;
+    histogram.iter_mut().for_each(|count| *count /= total);
+    histogram
+}
+
+/// Even mix of histogram distance (lighting and palette) and thumbnail
+/// difference (layout), so a pan or a flash alone scores about half
+fn scene_change_score(previous: &FrameSummary, thumbnail: &Array2<f64>, histogram: &[f64; HISTOGRAM_BINS]) -> f64 {
+    let histogram_distance = 0.5 * previous.histogram.iter().zip(histogram).map(|(a, b)| (a - b).abs()).sum::<f64>();
+    let layout = (&previous.thumbnail - thumbnail).mapv(f64::abs).mean().unwrap_or(0.0);
+    0.5 * histogram_distance.min(1.0) + 0.5 * (layout / FULL_LAYOUT_CHANGE).min(1.0)
+}
+
+/// Splits frames into GOPs that open on the key frames the planner places
+pub struct AdaptiveGops<I: Iterator<Item = VisualInput>> {
+    frames: I,
+    analyzer: KeyframeAnalyzer,
+    planner: KeyframePlanner,
+    carry: Option<VisualInput>,
+}
+
+impl<I: Iterator<Item = VisualInput>> AdaptiveGops<I> {
+    pub fn new(frames: I, config: AdaptiveGopConfig, frame_rate: f64) -> Result<Self, AfiyahError> {
+        Ok(Self {
+            frames,
+            analyzer: KeyframeAnalyzer::new(&config)?,
+            planner: KeyframePlanner::new(config, frame_rate)?,
+            carry: None,
+        })
+    }
+
+    pub fn stats(&self) -> &KeyframePlacementStats {
+        self.planner.stats()
+    }
+}
+
+impl<I: Iterator<Item = VisualInput>> Iterator for AdaptiveGops<I> {
+    type Item = Result<Vec<VisualInput>, AfiyahError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The key frame that closed the previous GOP opens this one
+        let mut gop: Vec<VisualInput> = self.carry.take().into_iter().collect();
+        for frame in self.frames.by_ref() {
+            let signals = match self.analyzer.analyze(&frame) {
+                Ok(signals) => signals,
+                Err(e) => return Some(Err(e)),
+            };
+            if self.planner.place(signals).is_some() && !gop.is_empty() {
+                self.carry = Some(frame);
+                return Some(Ok(gop));
+            }
+            gop.push(frame);
+        }
+        (!gop.is_empty()).then_some(Ok(gop))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InputMetadata;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+
+    /// Textured scene `scene`, with a bright square at `object` if any
+    fn frame(scene: usize, object: Option<(usize, usize)>) -> VisualInput {
+        let luminance_data = (0..WIDTH * HEIGHT)
+            .map(|index| {
+                let (x, y) = (index % WIDTH, index / WIDTH);
+                match object {
+                    Some((ox, oy)) if (ox..ox + 10).contains(&x) && (oy..oy + 10).contains(&y) => 1.0,
+                    _ if scene == 0 => 0.2 + 0.2 * ((x as f64 * 0.3).sin() * (y as f64 * 0.2).cos()).abs(),
+                    _ => 0.6 + 0.3 * ((x as f64 * 0.05).cos() + (y as f64 * 0.4).sin()).abs() / 2.0,
+                }
+            })
+            .collect();
+        VisualInput {
+            luminance_data,
+            chrominance_data: vec![0.5; WIDTH * HEIGHT],
+            spatial_resolution: (WIDTH, HEIGHT),
+            temporal_resolution: 30.0,
+            metadata: InputMetadata {
+                viewing_distance: 1.0,
+                ambient_lighting: 100.0,
+                viewer_age: 30,
+                color_temperature: 6500.0,
+            },
+        }
+    }
+
+    fn gop_lengths(frames: Vec<VisualInput>, config: AdaptiveGopConfig) -> (Vec<usize>, KeyframePlacementStats) {
+        let mut gops = AdaptiveGops::new(frames.into_iter(), config, 30.0).unwrap();
+        let lengths = gops.by_ref().map(|gop| gop.unwrap().len()).collect();
+        (lengths, gops.stats().clone())
+    }
+
+    #[test]
+    fn test_key_frames_follow_scene_changes() {
+        let frames: Vec<_> = (0..40).map(|i| frame(usize::from(i >= 25), None)).collect();
+        let (lengths, stats) = gop_lengths(frames, AdaptiveGopConfig::default());
+
+        assert_eq!(lengths, vec![25, 15]);
+        assert_eq!(stats.scene_change_keyframes, 1);
+        assert_eq!(stats.max_gop_keyframes, 0);
+    }
+
+    #[test]
+    fn test_key_frames_follow_predicted_attention_shifts() {
+        // A bright object appears in the far corner of a static scene
+        let frames: Vec<_> = (0..30).map(|i| frame(0, (i >= 12).then_some((50, 36)))).collect();
+        let (lengths, stats) = gop_lengths(frames, AdaptiveGopConfig::default());
+
+        assert_eq!(lengths, vec![12, 18]);
+        assert_eq!(stats.attention_keyframes, 1);
+        assert_eq!(stats.scene_change_keyframes, 0);
+    }
+
+    #[test]
+    fn test_gop_limits_and_seekability_hold() {
+        // Scene cuts every 3 frames, inside the minimum GOP
+        let frames: Vec<_> = (0..100).map(|i| frame(i / 3 % 2, None)).collect();
+        let config = AdaptiveGopConfig {
+            min_gop_frames: 5,
+            max_seek_seconds: 0.5,
+            ..AdaptiveGopConfig::default()
+        };
+        let (lengths, stats) = gop_lengths(frames, config);
+        assert!(lengths.iter().all(|&length| length <= 15), "{:?}", lengths);
+        assert!(lengths[..lengths.len() - 1].iter().all(|&length| length >= 5), "{:?}", lengths);
+        assert!(stats.suppressed_signals > 0);
+
+        // Static content is cut only by the seekability target: 0.5s at 30 fps
+        let static_frames: Vec<_> = (0..40).map(|_| frame(0, None)).collect();
+        let config = AdaptiveGopConfig {
+            max_seek_seconds: 0.5,
+            ..AdaptiveGopConfig::default()
+        };
+        let (lengths, stats) = gop_lengths(static_frames, config);
+        assert_eq!(lengths, vec![15, 15, 10]);
+        assert_eq!(stats.max_gop_keyframes, 2);
+
+        let impossible = AdaptiveGopConfig {
+            min_gop_frames: 30,
+            max_seek_seconds: 0.5,
+            ..AdaptiveGopConfig::default()
+        };
+        assert!(KeyframePlanner::new(impossible, 30.0).is_err());
+    }
+}
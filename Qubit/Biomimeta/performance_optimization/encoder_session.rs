@@ -0,0 +1,516 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Streaming Encoder Sessions
+//!
+//! `compress` and the GOP encoders take frames that are already in memory.
+//! An encoder session instead pulls frames from an asynchronous source, such
+//! as a camera or a file being read, cuts them into GOPs as they arrive and
+//! yields each GOP as soon as it is encoded. GOPs are either a fixed number
+//! of frames or opened at scene changes and attention shifts as in
+//! `adaptive_gop`, and the rate controller budgets each GOP from the sizes of
+//! those before it, so the session never needs to see the whole input.
+//!
+//! Encoding is CPU bound, so each GOP is encoded on the rayon pool while the
+//! session reads the next one from the source. Nothing happens between
+//! polls: a consumer that stops polling stops the source from being read,
+//! and at most one GOP is encoding while the next is gathered. The session
+//! is not tied to any async runtime.
+//!
+//! Biological Foundation:
+//! - The retina streams spikes continuously instead of delivering whole scenes
+//! - Cortical processing keeps pace with the input rather than buffering it
+//! - Eye movements segment the stream into fixations, as GOPs segment frames
+
+use std::future::Future;
+use std::time::Instant;
+
+use futures::channel::oneshot;
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::adaptive_gop::{AdaptiveGopConfig, KeyframeAnalyzer, KeyframePlacementStats, KeyframePlanner, KeyframeReason};
+use super::gop_parallel::{EncodedGop, GopBudget, GopEncoder, RateController};
+use crate::{AfiyahError, VisualInput};
+
+/// Asynchronous supplier of frames, such as a camera or a file being decoded
+pub trait FrameSource: Send {
+    /// Next frame in presentation order, or `None` once the source is exhausted
+    fn next_frame(&mut self) -> impl Future<Output = Result<Option<VisualInput>, AfiyahError>> + Send;
+}
+
+impl<S> FrameSource for S
+where
+    S: Stream<Item = Result<VisualInput, AfiyahError>> + Unpin + Send,
+{
+    async fn next_frame(&mut self) -> Result<Option<VisualInput>, AfiyahError> {
+        self.next().await.transpose()
+    }
+}
+
+/// Frame source over frames already in memory
+pub fn frames_from_iter<I>(frames: I) -> impl FrameSource
+where
+    I: IntoIterator<Item = VisualInput>,
+    I::IntoIter: Send,
+{
+    stream::iter(frames.into_iter().map(Ok))
+}
+
+/// Encoder session configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncoderSessionConfig {
+    pub gop_size: usize,                         // Frames per GOP unless GOPs are placed adaptively
+    pub adaptive_gop: Option<AdaptiveGopConfig>, // Opens GOPs at scene changes and attention shifts instead
+    pub target_bitrate_bps: u64,
+    pub frame_rate: f64,
+    pub rate_window: usize, // GOPs over which the rate controller pays back bitrate error
+}
+
+impl Default for EncoderSessionConfig {
+    fn default() -> Self {
+        Self {
+            gop_size: 30,
+            adaptive_gop: None,
+            target_bitrate_bps: 8_000_000,
+            frame_rate: 30.0,
+            rate_window: 4,
+        }
+    }
+}
+
+/// One encoded GOP yielded by a session
+#[derive(Debug, Clone)]
+pub struct EncodedChunk {
+    pub gop: EncodedGop,                  // Ready for `OrderedGopMuxer::push`
+    pub first_frame: u64,                 // Position of the GOP's key frame in the stream
+    pub budget: GopBudget,
+    pub keyframe: Option<KeyframeReason>, // Why the GOP was opened; `None` for fixed GOPs
+    pub achieved_bitrate_bps: f64,        // Over the stream so far, including this GOP
+}
+
+enum GopSplitter {
+    Fixed(usize),
+    Adaptive(Box<AdaptiveSplitter>),
+}
+
+struct AdaptiveSplitter {
+    analyzer: KeyframeAnalyzer,
+    planner: KeyframePlanner,
+    carry: Option<(VisualInput, KeyframeReason)>,
+}
+
+struct GopFrames {
+    frames: Vec<VisualInput>,
+    first_frame: u64,
+    keyframe: Option<KeyframeReason>,
+}
+
+struct EncodeResult<E> {
+    encoder: E,
+    data: Result<Vec<u8>, AfiyahError>,
+    encode_time: std::time::Duration,
+}
+
+struct InFlightGop<E> {
+    result: oneshot::Receiver<EncodeResult<E>>,
+    budget: GopBudget,
+    first_frame: u64,
+    frame_count: usize,
+    keyframe: Option<KeyframeReason>,
+}
+
+/// Encodes frames from an asynchronous source one GOP at a time
+///
+/// Drive it with `next_chunk`, or turn it into a stream of chunks with
+/// `into_stream`. A source or encoder error ends the session, discarding any
+/// GOP that was still encoding.
+pub struct EncoderSession<S: FrameSource, E: GopEncoder + 'static> {
+    source: S,
+    encoder: Option<E>,
+    splitter: GopSplitter,
+    rate: RateController,
+    frame_rate: f64,
+    frames_read: u64,
+    next_index: u64,
+    in_flight: Option<InFlightGop<E>>,
+    finished: bool,
+}
+
+impl<S: FrameSource, E: GopEncoder + 'static> EncoderSession<S, E> {
+    pub fn new(source: S, encoder: E, config: EncoderSessionConfig) -> Result<Self, AfiyahError> {
+        if !(config.frame_rate > 0.0 && config.frame_rate.is_finite()) {
+            return Err(AfiyahError::Configuration {
+                message: "Frame rate must be positive".to_string(),
+            });
+        }
+        let splitter = match config.adaptive_gop {
+            Some(adaptive) => GopSplitter::Adaptive(Box::new(AdaptiveSplitter {
+                analyzer: KeyframeAnalyzer::new(&adaptive)?,
+                planner: KeyframePlanner::new(adaptive, config.frame_rate)?,
+                carry: None,
+            })),
+            None if config.gop_size == 0 => {
+                return Err(AfiyahError::Configuration {
+                    message: "GOP size must be at least one frame".to_string(),
+                });
+            }
+            None => GopSplitter::Fixed(config.gop_size),
+        };
+
+        Ok(Self {
+            source,
+            encoder: Some(encoder),
+            splitter,
+            rate: RateController::new(config.target_bitrate_bps, config.frame_rate, config.rate_window),
+            frame_rate: config.frame_rate,
+            frames_read: 0,
+            next_index: 0,
+            in_flight: None,
+            finished: false,
+        })
+    }
+
+    /// Key frame placement so far; `None` for fixed GOPs
+    pub fn keyframe_stats(&self) -> Option<&KeyframePlacementStats> {
+        match &self.splitter {
+            GopSplitter::Fixed(_) => None,
+            GopSplitter::Adaptive(adaptive) => Some(adaptive.planner.stats()),
+        }
+    }
+
+    /// Bits produced per second of content so far
+    pub fn achieved_bitrate(&self) -> f64 {
+        self.rate.achieved_bitrate(self.frame_rate)
+    }
+
+    /// Encodes the next GOP, or returns `None` once the source is exhausted
+    pub async fn next_chunk(&mut self) -> Result<Option<EncodedChunk>, AfiyahError> {
+        if self.finished {
+            return Ok(None);
+        }
+        let chunk = self.advance().await;
+        if !matches!(chunk, Ok(Some(_))) {
+            self.finished = true;
+            self.in_flight = None;
+        }
+        chunk
+    }
+
+    /// Stream of encoded GOPs in presentation order, ending after the source does or after an error
+    pub fn into_stream(self) -> impl Stream<Item = Result<EncodedChunk, AfiyahError>> + Send {
+        stream::unfold(self, |mut session| async move {
+            let chunk = session.next_chunk().await.transpose()?;
+            Some((chunk, session))
+        })
+    }
+
+    async fn advance(&mut self) -> Result<Option<EncodedChunk>, AfiyahError> {
+        loop {
+            // Read the next GOP while the previous one encodes
+            let next = self.read_gop().await?;
+            match (self.in_flight.take(), next) {
+                (Some(in_flight), next) => {
+                    let chunk = self.collect(in_flight).await?;
+                    if let Some(gop) = next {
+                        self.dispatch(gop);
+                    }
+                    return Ok(Some(chunk));
+                }
+                // The first GOP: start it and read the one after
+                (None, Some(gop)) => self.dispatch(gop),
+                (None, None) => return Ok(None),
+            }
+        }
+    }
+
+    async fn read_gop(&mut self) -> Result<Option<GopFrames>, AfiyahError> {
+        let first_frame = self.frames_read;
+        let mut frames = Vec::new();
+        let mut keyframe = None;
+
+        match &mut self.splitter {
+            GopSplitter::Fixed(gop_size) => {
+                while frames.len() < *gop_size {
+                    match self.source.next_frame().await? {
+                        Some(frame) => frames.push(frame),
+                        None => break,
+                    }
+                }
+            }
+            GopSplitter::Adaptive(adaptive) => {
+                // The key frame that closed the previous GOP opens this one
+                if let Some((frame, reason)) = adaptive.carry.take() {
+                    frames.push(frame);
+                    keyframe = Some(reason);
+                }
+                while let Some(frame) = self.source.next_frame().await? {
+                    match adaptive.planner.place(adaptive.analyzer.analyze(&frame)?) {
+                        Some(reason) if !frames.is_empty() => {
+                            adaptive.carry = Some((frame, reason));
+                            break;
+                        }
+                        Some(reason) => {
+                            keyframe = Some(reason);
+                            frames.push(frame);
+                        }
+                        None => frames.push(frame),
+                    }
+                }
+            }
+        }
+
+        self.frames_read += frames.len() as u64;
+        Ok((!frames.is_empty()).then_some(GopFrames {
+            frames,
+            first_frame,
+            keyframe,
+        }))
+    }
+
+    /// Budgets a GOP and starts encoding it on the rayon pool
+    fn dispatch(&mut self, gop: GopFrames) {
+        let mut encoder = self.encoder.take().expect("encoder is idle between GOPs");
+        let budget = self.rate.allocate(self.next_index, gop.frames.len());
+        self.next_index += 1;
+
+        let (sender, result) = oneshot::channel();
+        let frames = gop.frames;
+        let frame_count = frames.len();
+        rayon::spawn(move || {
+            let started = Instant::now();
+            let data = encoder.encode_gop(&frames, &budget);
+            let _ = sender.send(EncodeResult {
+                encoder,
+                data,
+                encode_time: started.elapsed(),
+            });
+        });
+
+        self.in_flight = Some(InFlightGop {
+            result,
+            budget,
+            first_frame: gop.first_frame,
+            frame_count,
+            keyframe: gop.keyframe,
+        });
+    }
+
+    async fn collect(&mut self, in_flight: InFlightGop<E>) -> Result<EncodedChunk, AfiyahError> {
+        let result = in_flight.result.await.map_err(|_| AfiyahError::Compression {
+            message: format!("GOP {} encoder panicked", in_flight.budget.gop_index),
+        })?;
+        self.encoder = Some(result.encoder);
+        let data = result.data?;
+
+        self.rate.complete(&in_flight.budget, in_flight.frame_count, data.len() as u64 * 8);
+        Ok(EncodedChunk {
+            gop: EncodedGop {
+                index: in_flight.budget.gop_index,
+                frame_count: in_flight.frame_count as u32,
+                data,
+                encode_time: result.encode_time,
+            },
+            first_frame: in_flight.first_frame,
+            budget: in_flight.budget,
+            keyframe: in_flight.keyframe,
+            achieved_bitrate_bps: self.achieved_bitrate(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::performance_optimization::gop_parallel::OrderedGopMuxer;
+    use crate::InputMetadata;
+    use futures::executor::block_on;
+    use futures::future::poll_fn;
+    use futures::TryStreamExt;
+    use std::task::Poll;
+
+    /// Produces `complexity / quality_scale` bytes per frame
+    struct SyntheticEncoder {
+        complexity: f64,
+    }
+
+    impl GopEncoder for SyntheticEncoder {
+        fn encode_gop(&mut self, frames: &[VisualInput], budget: &GopBudget) -> Result<Vec<u8>, AfiyahError> {
+            let bytes = (self.complexity / budget.quality_scale) as usize * frames.len();
+            Ok(vec![budget.gop_index as u8; bytes])
+        }
+    }
+
+    /// Waits once before every frame, as a camera does between captures, and can fail partway
+    struct CameraSource {
+        frames: std::vec::IntoIter<VisualInput>,
+        fail_after: Option<usize>,
+        delivered: usize,
+    }
+
+    impl FrameSource for CameraSource {
+        async fn next_frame(&mut self) -> Result<Option<VisualInput>, AfiyahError> {
+            let mut waited = false;
+            poll_fn(|cx| {
+                if waited {
+                    return Poll::Ready(());
+                }
+                waited = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+            .await;
+
+            if self.fail_after == Some(self.delivered) {
+                return Err(AfiyahError::InputError {
+                    message: "camera disconnected".to_string(),
+                });
+            }
+            self.delivered += 1;
+            Ok(self.frames.next())
+        }
+    }
+
+    fn camera(frames: Vec<VisualInput>, fail_after: Option<usize>) -> CameraSource {
+        CameraSource {
+            frames: frames.into_iter(),
+            fail_after,
+            delivered: 0,
+        }
+    }
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+
+    /// Textured scene `scene`
+    fn frame(scene: usize) -> VisualInput {
+        let luminance_data = (0..WIDTH * HEIGHT)
+            .map(|index| {
+                let (x, y) = ((index % WIDTH) as f64, (index / WIDTH) as f64);
+                match scene {
+                    0 => 0.2 + 0.2 * ((x * 0.3).sin() * (y * 0.2).cos()).abs(),
+                    _ => 0.6 + 0.3 * ((x * 0.05).cos() + (y * 0.4).sin()).abs() / 2.0,
+                }
+            })
+            .collect();
+        VisualInput {
+            luminance_data,
+            chrominance_data: vec![0.5; WIDTH * HEIGHT],
+            spatial_resolution: (WIDTH, HEIGHT),
+            temporal_resolution: 30.0,
+            metadata: InputMetadata {
+                viewing_distance: 1.0,
+                ambient_lighting: 100.0,
+                viewer_age: 30,
+                color_temperature: 6500.0,
+            },
+        }
+    }
+
+    fn frames(count: usize) -> Vec<VisualInput> {
+        (0..count).map(|_| frame(0)).collect()
+    }
+
+    #[test]
+    fn test_streams_fixed_gops_in_order() {
+        let config = EncoderSessionConfig {
+            gop_size: 4,
+            ..Default::default()
+        };
+        let session = EncoderSession::new(camera(frames(42), None), SyntheticEncoder { complexity: 100.0 }, config).unwrap();
+        let chunks: Vec<EncodedChunk> = block_on(session.into_stream().try_collect()).unwrap();
+
+        assert_eq!(chunks.len(), 11);
+        assert!(chunks.iter().enumerate().all(|(i, chunk)| chunk.gop.index == i as u64 && chunk.first_frame == 4 * i as u64));
+        assert_eq!(chunks.iter().map(|chunk| chunk.gop.frame_count).sum::<u32>(), 42);
+        assert!(chunks.iter().all(|chunk| chunk.keyframe.is_none()));
+
+        let mut muxer = OrderedGopMuxer::new(Vec::new());
+        for chunk in chunks {
+            muxer.push(chunk.gop).unwrap();
+        }
+        assert!(!muxer.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rate_control_converges_incrementally() {
+        // 50 kbit/s at 25 fps is 250 bytes per frame, against a natural 1000
+        let config = EncoderSessionConfig {
+            gop_size: 5,
+            target_bitrate_bps: 50_000,
+            frame_rate: 25.0,
+            ..Default::default()
+        };
+        let mut session = EncoderSession::new(frames_from_iter(frames(1000)), SyntheticEncoder { complexity: 1000.0 }, config).unwrap();
+        let mut last = None;
+        while let Some(chunk) = block_on(session.next_chunk()).unwrap() {
+            last = Some(chunk);
+        }
+
+        let error = last.unwrap().achieved_bitrate_bps / 50_000.0 - 1.0;
+        assert!(error.abs() < 0.1, "bitrate error {}", error);
+    }
+
+    #[test]
+    fn test_adaptive_gops_open_at_scene_changes() {
+        let frames: Vec<_> = (0..40).map(|i| frame(usize::from(i >= 25))).collect();
+        let config = EncoderSessionConfig {
+            adaptive_gop: Some(AdaptiveGopConfig::default()),
+            ..Default::default()
+        };
+        let mut session = EncoderSession::new(frames_from_iter(frames), SyntheticEncoder { complexity: 10.0 }, config).unwrap();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = block_on(session.next_chunk()).unwrap() {
+            chunks.push((chunk.first_frame, chunk.gop.frame_count, chunk.keyframe));
+        }
+
+        assert_eq!(
+            chunks,
+            vec![(0, 25, Some(KeyframeReason::StreamStart)), (25, 15, Some(KeyframeReason::SceneChange))]
+        );
+        assert_eq!(session.keyframe_stats().unwrap().scene_change_keyframes, 1);
+    }
+
+    #[test]
+    fn test_source_error_ends_the_stream() {
+        let config = EncoderSessionConfig {
+            gop_size: 4,
+            ..Default::default()
+        };
+        let session = EncoderSession::new(camera(frames(20), Some(10)), SyntheticEncoder { complexity: 100.0 }, config).unwrap();
+        let results: Vec<_> = block_on(session.into_stream().collect());
+
+        // GOP 0 is yielded while GOP 1 encodes; the error surfaces reading GOP 2
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().gop.index, 0);
+        assert!(matches!(results[1], Err(AfiyahError::InputError { .. })));
+    }
+}
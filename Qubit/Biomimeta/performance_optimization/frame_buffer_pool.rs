@@ -0,0 +1,340 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Frame Buffer Pool for the Compression Pipeline
+//!
+//! Every pipeline stage used to allocate fresh `Vec<f64>`/`Array2`/`Array3`
+//! buffers per frame, which at 60fps turns into constant allocator churn. This
+//! pool keeps released buffers in power-of-two size classes so the next frame
+//! can reuse them. Stages acquire zeroed buffers from the pool and the engine
+//! hands intermediate outputs back once a frame has been emitted.
+//!
+//! Biological Foundation:
+//! - Neural populations are recruited and released rather than grown per stimulus
+//! - Steady-state processing reuses the same resources frame after frame
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use ndarray::{Array2, Array3};
+use serde::{Deserialize, Serialize};
+
+/// Pool shared between pipeline stages
+pub type SharedFrameBufferPool = Arc<FrameBufferPool>;
+
+/// Frame buffer pool configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameBufferPoolConfig {
+    pub max_buffers_per_class: usize,   // Retained buffers per size class
+    pub max_buffer_elements: usize,     // Larger buffers are never retained
+}
+
+impl Default for FrameBufferPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_buffers_per_class: 16,
+            max_buffer_elements: 7680 * 4320 * 3, // One 8K RGB frame
+        }
+    }
+}
+
+/// Allocation statistics used by the steady-state regression tests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameBufferPoolStats {
+    pub fresh_allocations: u64,   // Acquisitions that hit the allocator
+    pub reuses: u64,              // Acquisitions served from the pool
+    pub releases: u64,            // Buffers returned to the pool
+    pub discards: u64,            // Returned buffers dropped (class full or oversized)
+    pub retained_buffers: u64,    // Buffers currently held by the pool
+}
+
+impl FrameBufferPoolStats {
+    /// Fraction of acquisitions served without allocating
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.fresh_allocations + self.reuses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.reuses as f64 / total as f64
+    }
+}
+
+/// Size-class frame buffer pool
+pub struct FrameBufferPool {
+    config: FrameBufferPoolConfig,
+    classes: Mutex<HashMap<usize, Vec<Vec<f64>>>>,
+    fresh_allocations: AtomicU64,
+    reuses: AtomicU64,
+    releases: AtomicU64,
+    discards: AtomicU64,
+}
+
+impl FrameBufferPool {
+    /// Creates a new pool with default limits
+    pub fn new() -> Self {
+        Self::with_config(FrameBufferPoolConfig::default())
+    }
+
+    /// Creates a new pool with custom limits
+    pub fn with_config(config: FrameBufferPoolConfig) -> Self {
+        Self {
+            config,
+            classes: Mutex::new(HashMap::new()),
+            fresh_allocations: AtomicU64::new(0),
+            reuses: AtomicU64::new(0),
+            releases: AtomicU64::new(0),
+            discards: AtomicU64::new(0),
+        }
+    }
+
+    /// Creates a pool ready to be shared between stages
+    pub fn shared() -> SharedFrameBufferPool {
+        Arc::new(Self::new())
+    }
+
+    /// Acquires a zeroed vector of `len` elements
+    pub fn acquire_vec(&self, len: usize) -> Vec<f64> {
+        let class = Self::size_class(len);
+
+        let pooled = self
+            .classes
+            .lock()
+            .ok()
+            .and_then(|mut classes| classes.get_mut(&class).and_then(|buffers| buffers.pop()));
+
+        match pooled {
+            Some(mut buffer) => {
+                self.reuses.fetch_add(1, Ordering::Relaxed);
+                buffer.clear();
+                buffer.resize(len, 0.0);
+                buffer
+            }
+            None => {
+                self.fresh_allocations.fetch_add(1, Ordering::Relaxed);
+                let mut buffer = Vec::with_capacity(class);
+                buffer.resize(len, 0.0);
+                buffer
+            }
+        }
+    }
+
+    /// Acquires a zeroed 2D array
+    pub fn acquire_array2(&self, shape: (usize, usize)) -> Array2<f64> {
+        let buffer = self.acquire_vec(shape.0 * shape.1);
+        Array2::from_shape_vec(shape, buffer).expect("pooled buffer length matches shape")
+    }
+
+    /// Acquires a zeroed 3D array
+    pub fn acquire_array3(&self, shape: (usize, usize, usize)) -> Array3<f64> {
+        let buffer = self.acquire_vec(shape.0 * shape.1 * shape.2);
+        Array3::from_shape_vec(shape, buffer).expect("pooled buffer length matches shape")
+    }
+
+    /// Returns a vector to the pool
+    pub fn release_vec(&self, buffer: Vec<f64>) {
+        let capacity = buffer.capacity();
+        if capacity == 0 || capacity > self.config.max_buffer_elements {
+            self.discards.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        // File under the largest class the buffer can fully serve
+        let class = if capacity.is_power_of_two() {
+            capacity
+        } else {
+            Self::size_class(capacity) >> 1
+        };
+
+        let retained = match self.classes.lock() {
+            Ok(mut classes) => {
+                let buffers = classes.entry(class).or_default();
+                if buffers.len() < self.config.max_buffers_per_class {
+                    buffers.push(buffer);
+                    true
+                } else {
+                    false
+                }
+            }
+            Err(_) => false,
+        };
+
+        if retained {
+            self.releases.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.discards.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns a 2D array's storage to the pool
+    pub fn release_array2(&self, array: Array2<f64>) {
+        self.release_vec(array.into_raw_vec());
+    }
+
+    /// Returns a 3D array's storage to the pool
+    pub fn release_array3(&self, array: Array3<f64>) {
+        self.release_vec(array.into_raw_vec());
+    }
+
+    /// Gets current allocation statistics
+    pub fn stats(&self) -> FrameBufferPoolStats {
+        let retained_buffers = self
+            .classes
+            .lock()
+            .map(|classes| classes.values().map(|buffers| buffers.len() as u64).sum())
+            .unwrap_or(0);
+
+        FrameBufferPoolStats {
+            fresh_allocations: self.fresh_allocations.load(Ordering::Relaxed),
+            reuses: self.reuses.load(Ordering::Relaxed),
+            releases: self.releases.load(Ordering::Relaxed),
+            discards: self.discards.load(Ordering::Relaxed),
+            retained_buffers,
+        }
+    }
+
+    /// Drops every retained buffer
+    pub fn clear(&self) {
+        if let Ok(mut classes) = self.classes.lock() {
+            classes.clear();
+        }
+    }
+
+    fn size_class(len: usize) -> usize {
+        len.max(1).next_power_of_two()
+    }
+}
+
+impl Default for FrameBufferPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Acquires a zeroed 2D array from an optional pool
+pub fn pooled_array2(pool: Option<&FrameBufferPool>, shape: (usize, usize)) -> Array2<f64> {
+    match pool {
+        Some(pool) => pool.acquire_array2(shape),
+        None => Array2::zeros(shape),
+    }
+}
+
+/// Acquires a zeroed vector from an optional pool
+pub fn pooled_vec(pool: Option<&FrameBufferPool>, len: usize) -> Vec<f64> {
+    match pool {
+        Some(pool) => pool.acquire_vec(len),
+        None => vec![0.0; len],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire_returns_zeroed_buffers() {
+        let pool = FrameBufferPool::new();
+        let mut buffer = pool.acquire_vec(100);
+        buffer.iter_mut().for_each(|v| *v = 1.0);
+        pool.release_vec(buffer);
+
+        let buffer = pool.acquire_vec(80);
+        assert_eq!(buffer.len(), 80);
+        assert!(buffer.iter().all(|&v| v == 0.0));
+    }
+
+    #[test]
+    fn test_size_class_reuse() {
+        let pool = FrameBufferPool::new();
+        let array = pool.acquire_array2((64, 64));
+        pool.release_array2(array);
+
+        // Same size class (4096 elements) is served from the pool
+        let array = pool.acquire_array2((32, 128));
+        assert_eq!(array.dim(), (32, 128));
+
+        let stats = pool.stats();
+        assert_eq!(stats.fresh_allocations, 1);
+        assert_eq!(stats.reuses, 1);
+    }
+
+    #[test]
+    fn test_steady_state_has_no_fresh_allocations() {
+        let pool = FrameBufferPool::new();
+
+        // Warm-up frame
+        let frame = pool.acquire_array3((32, 32, 3));
+        let coefficients = pool.acquire_array2((32, 32));
+        pool.release_array3(frame);
+        pool.release_array2(coefficients);
+        let warm = pool.stats().fresh_allocations;
+
+        for _ in 0..120 {
+            let frame = pool.acquire_array3((32, 32, 3));
+            let coefficients = pool.acquire_array2((32, 32));
+            pool.release_array3(frame);
+            pool.release_array2(coefficients);
+        }
+
+        let stats = pool.stats();
+        assert_eq!(stats.fresh_allocations, warm);
+        assert_eq!(stats.reuses, 240);
+        assert!(stats.hit_rate() > 0.99);
+    }
+
+    #[test]
+    fn test_class_limit_discards_excess() {
+        let pool = FrameBufferPool::with_config(FrameBufferPoolConfig {
+            max_buffers_per_class: 1,
+            max_buffer_elements: 1024,
+        });
+        pool.release_vec(vec![0.0; 16]);
+        pool.release_vec(vec![0.0; 16]);
+        pool.release_vec(vec![0.0; 2048]);
+
+        let stats = pool.stats();
+        assert_eq!(stats.releases, 1);
+        assert_eq!(stats.discards, 2);
+        assert_eq!(stats.retained_buffers, 1);
+    }
+
+    #[test]
+    fn test_non_power_of_two_capacity_files_under_smaller_class() {
+        let pool = FrameBufferPool::new();
+        pool.release_vec(Vec::with_capacity(100));
+
+        // 100 elements cannot serve the 128 class, but can serve 64
+        let _ = pool.acquire_vec(100);
+        assert_eq!(pool.stats().fresh_allocations, 1);
+        let _ = pool.acquire_vec(64);
+        assert_eq!(pool.stats().reuses, 1);
+    }
+}
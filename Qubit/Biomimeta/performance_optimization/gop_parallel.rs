@@ -0,0 +1,862 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! GOP-Level Parallel Encoding
+//!
+//! Groups of pictures are independent once they start at a key frame, so
+//! they can be encoded concurrently. GOPs are dispatched to a work-stealing
+//! rayon pool, each worker checking out its own encoder instance. Only a
+//! bounded number of GOPs is in flight at once (encoding or waiting to be
+//! muxed), which caps memory regardless of input length. The muxer writes GOPs
+//! strictly in presentation order, and a shared rate controller hands out bit
+//! budgets so the stream bitrate holds even though GOPs finish out of order.
+//! Metadata attached to the muxer is written ahead of the GOP holding its frame.
+//! GOPs are either a fixed number of frames or cut adaptively at scene
+//! changes and predicted attention shifts (see `adaptive_gop`). Two-pass
+//! encodes plan budgets from a lookahead analysis instead (see `lookahead`).
+//!
+//! Biological Foundation:
+//! - Parallel visual pathways process independent streams concurrently
+//! - Perception reassembles them into a single ordered experience
+//! - A shared metabolic budget constrains total neural activity
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crossbeam::channel;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::adaptive_gop::{AdaptiveGopConfig, AdaptiveGops, KeyframePlacementStats};
+use super::lookahead::{ComplexityMaps, Lookahead, LookaheadConfig};
+use crate::bitstream_formatting::stream_metadata::{self, MetadataMessage, MetadataSchedule};
+use crate::{AfiyahError, CompressionEngine, VisualInput};
+
+/// Magic marker preceding every GOP in the muxed bitstream
+pub const GOP_MAGIC: [u8; 4] = *b"AGOP";
+
+/// Quality scale bounds handed to encoders
+const MIN_QUALITY_SCALE: f64 = 0.25;
+const MAX_QUALITY_SCALE: f64 = 8.0;
+
+/// GOP parallel encoding configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GopParallelConfig {
+    pub gop_size: usize,            // Frames per GOP
+    pub worker_threads: usize,      // 0 uses one worker per core
+    pub max_in_flight_gops: usize,  // GOPs encoding or awaiting the muxer; 0 uses twice the worker count
+    pub target_bitrate_bps: u64,    // Stream-level bitrate target
+    pub frame_rate: f64,            // Frames per second, used to turn bitrate into per-GOP budgets
+}
+
+impl Default for GopParallelConfig {
+    fn default() -> Self {
+        Self {
+            gop_size: 30,
+            worker_threads: 0,
+            max_in_flight_gops: 0,
+            target_bitrate_bps: 8_000_000,
+            frame_rate: 30.0,
+        }
+    }
+}
+
+/// Bit budget assigned to one GOP by the rate controller
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GopBudget {
+    pub gop_index: u64,
+    pub target_bits: u64,
+    pub quality_scale: f64, // 1.0 is nominal; larger values quantize more coarsely
+}
+
+/// Encoder for a single closed GOP
+///
+/// Each worker owns its own instance, so implementations need not be `Sync`.
+pub trait GopEncoder: Send {
+    fn encode_gop(&mut self, frames: &[VisualInput], budget: &GopBudget) -> Result<Vec<u8>, AfiyahError>;
+
+    /// Encodes a GOP with per-block quantization scales for each frame from a
+    /// lookahead analysis, each averaging 1.0; ignores them unless overridden
+    fn encode_gop_with_scales(
+        &mut self,
+        frames: &[VisualInput],
+        budget: &GopBudget,
+        _block_scales: &[Array2<f64>],
+    ) -> Result<Vec<u8>, AfiyahError> {
+        self.encode_gop(frames, budget)
+    }
+}
+
+/// Encoded GOP waiting to be muxed
+#[derive(Debug, Clone)]
+pub struct EncodedGop {
+    pub index: u64,
+    pub frame_count: u32,
+    pub data: Vec<u8>,
+    pub encode_time: Duration,
+}
+
+/// Rate controller shared by all in-flight GOPs
+///
+/// Bits are modelled as proportional to content cost and inversely
+/// proportional to the quality scale. Each completed GOP refines the scale
+/// needed to hit the nominal per-frame budget, and any accumulated over- or
+/// undershoot is spread across the next window of GOPs so parallel encodes
+/// converge on the target together.
+///
+/// Without a plan every frame is assumed to cost the same, so each GOP is
+/// budgeted by its length. With a lookahead plan the stream's total budget is
+/// split by each GOP's share of the analyzed cost, and the scale accounts for
+/// how much harder or easier the GOP is than average.
+pub struct RateController {
+    state: Mutex<RateState>,
+}
+
+#[derive(Debug)]
+struct RateState {
+    bits_per_frame: f64,
+    window: usize,
+    base_scale: f64,
+    frames_allocated: usize,
+    frames_completed: u64,
+    bits_produced: u64,
+    bits_planned: f64, // Planned bits of completed GOPs, before error correction
+    in_flight: HashMap<u64, GopPlan>,
+    plan: Option<RatePlan>,
+}
+
+/// Budget of a dispatched GOP before error correction
+#[derive(Debug, Clone, Copy)]
+struct GopPlan {
+    planned_bits: f64,
+    relative_cost: f64, // GOP cost over the cost of as many average frames
+}
+
+#[derive(Debug)]
+struct RatePlan {
+    maps: Arc<ComplexityMaps>,
+    exponent: f64,
+    total_weight: f64,
+    mean_cost: f64,
+}
+
+impl RateController {
+    /// Creates a controller for `target_bitrate_bps` at `frame_rate`
+    pub fn new(target_bitrate_bps: u64, frame_rate: f64, window: usize) -> Self {
+        Self {
+            state: Mutex::new(RateState {
+                bits_per_frame: target_bitrate_bps as f64 / frame_rate.max(1.0),
+                window: window.max(1),
+                base_scale: 1.0,
+                frames_allocated: 0,
+                frames_completed: 0,
+                bits_produced: 0,
+                bits_planned: 0.0,
+                in_flight: HashMap::new(),
+                plan: None,
+            }),
+        }
+    }
+
+    /// Plans budgets from a lookahead analysis of the frames about to be allocated
+    ///
+    /// `exponent` is how closely bits follow cost: 1.0 gives every GOP the
+    /// same quality scale, 0.0 the same bits per frame.
+    pub fn with_plan(self, maps: Arc<ComplexityMaps>, exponent: f64) -> Self {
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if !maps.is_empty() {
+                let frames = maps.len();
+                state.plan = Some(RatePlan {
+                    total_weight: maps.weight(0..frames, exponent),
+                    mean_cost: maps.cost(0..frames) / frames as f64,
+                    maps,
+                    exponent,
+                });
+            }
+        }
+        self
+    }
+
+    /// Assigns a budget to the next GOP to be dispatched
+    ///
+    /// GOPs must be allocated in presentation order.
+    pub fn allocate(&self, gop_index: u64, frame_count: usize) -> GopBudget {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let nominal = state.bits_per_frame * frame_count as f64;
+        let frames = state.frames_allocated..state.frames_allocated + frame_count;
+        state.frames_allocated = frames.end;
+
+        let plan = match &state.plan {
+            Some(plan) if frames.end <= plan.maps.len() && plan.total_weight > 0.0 => {
+                let total_bits = state.bits_per_frame * plan.maps.len() as f64;
+                GopPlan {
+                    planned_bits: total_bits * plan.maps.weight(frames.clone(), plan.exponent) / plan.total_weight,
+                    relative_cost: plan.maps.cost(frames) / (plan.mean_cost * frame_count as f64),
+                }
+            }
+            _ => GopPlan {
+                planned_bits: nominal,
+                relative_cost: 1.0,
+            },
+        };
+
+        // Pay back (or spend) the running error over the in-flight window
+        let debt = state.bits_produced as f64 - state.bits_planned;
+        let planned = plan.planned_bits;
+        let target = (planned - debt / state.window as f64).clamp(planned * 0.5, planned * 2.0).max(1.0);
+
+        let quality_scale =
+            (state.base_scale * nominal * plan.relative_cost / target).clamp(MIN_QUALITY_SCALE, MAX_QUALITY_SCALE);
+        state.in_flight.insert(gop_index, plan);
+
+        GopBudget {
+            gop_index,
+            target_bits: target as u64,
+            quality_scale,
+        }
+    }
+
+    /// Records the size of a finished GOP
+    pub fn complete(&self, budget: &GopBudget, frame_count: usize, bits: u64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let nominal = state.bits_per_frame * frame_count as f64;
+        let plan = state.in_flight.remove(&budget.gop_index).unwrap_or(GopPlan {
+            planned_bits: nominal,
+            relative_cost: 1.0,
+        });
+        state.frames_completed += frame_count as u64;
+        state.bits_planned += plan.planned_bits;
+        state.bits_produced += bits;
+
+        if bits == 0 {
+            return;
+        }
+
+        // Scale that would have produced the nominal budget for an average GOP of this length
+        let estimate = (budget.quality_scale * bits as f64 / (nominal * plan.relative_cost))
+            .clamp(MIN_QUALITY_SCALE, MAX_QUALITY_SCALE);
+        state.base_scale = 0.5 * state.base_scale + 0.5 * estimate;
+    }
+
+    /// Bits produced per second of content so far
+    pub fn achieved_bitrate(&self, frame_rate: f64) -> f64 {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.frames_completed == 0 {
+            return 0.0;
+        }
+        state.bits_produced as f64 / state.frames_completed as f64 * frame_rate
+    }
+}
+
+/// Writes encoded GOPs in order, buffering any that finish early
+///
+/// Each GOP is framed as `AGOP`, a little-endian u64 index, u32 frame count
+/// and u32 payload length, followed by the payload. Metadata packets use the
+/// same framing under `AMET`, with the frame number in place of the index.
+pub struct OrderedGopMuxer<W: Write> {
+    writer: W,
+    next_index: u64,
+    pending: BTreeMap<u64, EncodedGop>,
+    bytes_written: u64,
+    frames_written: u64,
+    metadata: MetadataSchedule,
+}
+
+impl<W: Write> OrderedGopMuxer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            next_index: 0,
+            pending: BTreeMap::new(),
+            bytes_written: 0,
+            frames_written: 0,
+            metadata: MetadataSchedule::default(),
+        }
+    }
+
+    /// Attaches metadata to a frame, counted from the start of the stream
+    ///
+    /// Fails if the GOP holding the frame has already been written.
+    pub fn attach_metadata(&mut self, frame: u64, messages: Vec<MetadataMessage>) -> Result<(), AfiyahError> {
+        if frame < self.frames_written {
+            return Err(AfiyahError::BitstreamFormatting {
+                message: format!("Frame {} has already been muxed", frame),
+            });
+        }
+        self.metadata.insert(frame, messages);
+        Ok(())
+    }
+
+    /// Attaches every metadata packet of `source` to the same frame of this
+    /// stream, so a transcode keeps it; returns the number of packets
+    pub fn preserve_metadata(&mut self, source: &[u8]) -> Result<usize, AfiyahError> {
+        let packets = stream_metadata::read_metadata(source)?;
+        let count = packets.len();
+        for packet in packets {
+            self.attach_metadata(packet.frame, packet.messages)?;
+        }
+        Ok(count)
+    }
+
+    /// Accepts a GOP and writes every GOP that is now contiguous
+    pub fn push(&mut self, gop: EncodedGop) -> Result<(), AfiyahError> {
+        if gop.index < self.next_index || self.pending.contains_key(&gop.index) {
+            return Err(AfiyahError::Streaming {
+                message: format!("GOP {} was muxed twice", gop.index),
+            });
+        }
+        self.pending.insert(gop.index, gop);
+
+        while let Some(gop) = self.pending.remove(&self.next_index) {
+            self.write_gop(&gop)?;
+            self.next_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Index of the next GOP the muxer is waiting for
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// GOPs received out of order and not yet written
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Flushes the writer, failing if any GOP is still missing or any
+    /// metadata belongs to a frame past the end
+    pub fn finish(mut self) -> Result<W, AfiyahError> {
+        if let Some(index) = self.pending.keys().next() {
+            return Err(AfiyahError::Streaming {
+                message: format!("GOP {} is missing before GOP {}", self.next_index, index),
+            });
+        }
+        self.metadata.ensure_empty()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_gop(&mut self, gop: &EncodedGop) -> Result<(), AfiyahError> {
+        let length = u32::try_from(gop.data.len()).map_err(|_| AfiyahError::Streaming {
+            message: format!("GOP {} exceeds the maximum payload size", gop.index),
+        })?;
+
+        let gop_end = self.frames_written + gop.frame_count as u64;
+        self.bytes_written += self.metadata.write_before(&mut self.writer, gop_end)?;
+
+        self.writer.write_all(&GOP_MAGIC)?;
+        self.writer.write_all(&gop.index.to_le_bytes())?;
+        self.writer.write_all(&gop.frame_count.to_le_bytes())?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&gop.data)?;
+        self.bytes_written += (GOP_MAGIC.len() + 16 + gop.data.len()) as u64;
+        self.frames_written = gop_end;
+        Ok(())
+    }
+}
+
+/// Results of a parallel encode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GopEncodeStats {
+    pub gops: u64,
+    pub frames: u64,
+    pub bytes: u64,
+    pub wall_time: Duration,
+    pub encode_time: Duration,      // Summed across workers
+    pub peak_in_flight: usize,
+    pub target_bitrate_bps: u64,
+    pub achieved_bitrate_bps: f64,
+    pub keyframes: Option<KeyframePlacementStats>, // Set by adaptive encodes
+    pub lookahead_cached: Option<bool>, // Set by two-pass encodes: whether the analysis came from the cache
+}
+
+impl GopEncodeStats {
+    /// Encode time over wall time; approaches the worker count when encoding scales
+    pub fn parallel_speedup(&self) -> f64 {
+        if self.wall_time.is_zero() {
+            return 0.0;
+        }
+        self.encode_time.as_secs_f64() / self.wall_time.as_secs_f64()
+    }
+
+    /// Relative deviation of the achieved bitrate from the target
+    pub fn bitrate_error(&self) -> f64 {
+        if self.target_bitrate_bps == 0 {
+            return 0.0;
+        }
+        (self.achieved_bitrate_bps - self.target_bitrate_bps as f64) / self.target_bitrate_bps as f64
+    }
+}
+
+/// Encodes GOPs in parallel on a work-stealing pool
+pub struct GopParallelEncoder<E, F>
+where
+    E: GopEncoder + 'static,
+    F: Fn() -> Result<E, AfiyahError> + Send + Sync + 'static,
+{
+    config: GopParallelConfig,
+    pool: rayon::ThreadPool,
+    factory: Arc<F>,
+    idle_encoders: Arc<Mutex<Vec<E>>>,
+    max_in_flight: usize,
+}
+
+impl<E, F> GopParallelEncoder<E, F>
+where
+    E: GopEncoder + 'static,
+    F: Fn() -> Result<E, AfiyahError> + Send + Sync + 'static,
+{
+    /// Creates an encoder; `factory` builds one encoder per concurrently busy worker
+    pub fn new(config: GopParallelConfig, factory: F) -> Result<Self, AfiyahError> {
+        if config.gop_size == 0 {
+            return Err(AfiyahError::Configuration {
+                message: "GOP size must be at least one frame".to_string(),
+            });
+        }
+        if config.frame_rate <= 0.0 {
+            return Err(AfiyahError::Configuration {
+                message: "Frame rate must be positive".to_string(),
+            });
+        }
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.worker_threads)
+            .thread_name(|i| format!("afiyah-gop-{}", i))
+            .build()
+            .map_err(|e| AfiyahError::Configuration {
+                message: format!("Failed to start GOP worker pool: {}", e),
+            })?;
+
+        let max_in_flight = match config.max_in_flight_gops {
+            0 => pool.current_num_threads() * 2,
+            n => n,
+        };
+
+        Ok(Self {
+            config,
+            pool,
+            factory: Arc::new(factory),
+            idle_encoders: Arc::new(Mutex::new(Vec::new())),
+            max_in_flight,
+        })
+    }
+
+    /// Number of worker threads in the pool
+    pub fn worker_threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Encodes `frames` in fixed GOPs and writes the ordered bitstream to `muxer`
+    pub fn encode<I, W>(&self, frames: I, muxer: &mut OrderedGopMuxer<W>) -> Result<GopEncodeStats, AfiyahError>
+    where
+        I: IntoIterator<Item = VisualInput>,
+        W: Write,
+    {
+        let gop_size = self.config.gop_size;
+        let mut frames = frames.into_iter().peekable();
+        let gops = std::iter::from_fn(|| {
+            frames.peek()?;
+            Some(Ok(frames.by_ref().take(gop_size).collect()))
+        });
+        self.encode_gops(gops, muxer, None)
+    }
+
+    /// Encodes `frames` in fixed GOPs after a lookahead pass over all of them
+    ///
+    /// Budgets are planned from the analysis rather than learned during the
+    /// encode, and encoders receive per-block quantization scales. The
+    /// analysis is loaded from the lookahead's cache when the same frames were
+    /// encoded before.
+    pub fn encode_two_pass<W: Write>(
+        &self,
+        frames: &[VisualInput],
+        muxer: &mut OrderedGopMuxer<W>,
+        lookahead: &mut Lookahead,
+    ) -> Result<GopEncodeStats, AfiyahError> {
+        let (maps, cached) = lookahead.analyze(frames)?;
+        let gops = frames.chunks(self.config.gop_size).map(|gop| Ok(gop.to_vec()));
+        let mut stats = self.encode_gops(gops, muxer, Some((&maps, lookahead.config())))?;
+        stats.lookahead_cached = Some(cached);
+        Ok(stats)
+    }
+
+    /// Encodes `frames` with key frames placed by scene changes and predicted
+    /// attention shifts instead of every `gop_size` frames
+    ///
+    /// `gop_config` applies to this encode only, so streams with different
+    /// seekability targets can share one encoder.
+    pub fn encode_adaptive<I, W>(
+        &self,
+        frames: I,
+        muxer: &mut OrderedGopMuxer<W>,
+        gop_config: &AdaptiveGopConfig,
+    ) -> Result<GopEncodeStats, AfiyahError>
+    where
+        I: IntoIterator<Item = VisualInput>,
+        W: Write,
+    {
+        let mut gops = AdaptiveGops::new(frames.into_iter(), gop_config.clone(), self.config.frame_rate)?;
+        let mut stats = self.encode_gops(gops.by_ref(), muxer, None)?;
+        stats.keyframes = Some(gops.stats().clone());
+        Ok(stats)
+    }
+
+    fn encode_gops<G, W>(
+        &self,
+        gops: G,
+        muxer: &mut OrderedGopMuxer<W>,
+        lookahead: Option<(&Arc<ComplexityMaps>, &LookaheadConfig)>,
+    ) -> Result<GopEncodeStats, AfiyahError>
+    where
+        G: Iterator<Item = Result<Vec<VisualInput>, AfiyahError>>,
+        W: Write,
+    {
+        let started = Instant::now();
+        let mut rate = RateController::new(self.config.target_bitrate_bps, self.config.frame_rate, self.max_in_flight);
+        if let Some((maps, config)) = lookahead {
+            rate = rate.with_plan(maps.clone(), config.complexity_exponent);
+        }
+        let rate = Arc::new(rate);
+        let (sender, receiver) = channel::unbounded::<Result<EncodedGop, AfiyahError>>();
+
+        let first_index = muxer.next_index();
+        let bytes_before = muxer.bytes_written();
+        let mut dispatched = first_index;
+        let mut frame_total = 0u64;
+        let mut encode_time = Duration::ZERO;
+        let mut peak_in_flight = 0;
+
+        let collect = |muxer: &mut OrderedGopMuxer<W>, encode_time: &mut Duration| -> Result<(), AfiyahError> {
+            let gop = receiver.recv().map_err(|_| AfiyahError::Streaming {
+                message: "GOP worker pool stopped unexpectedly".to_string(),
+            })??;
+            *encode_time += gop.encode_time;
+            muxer.push(gop)
+        };
+
+        for gop in gops {
+            let gop = gop?;
+            let first_frame = frame_total as usize;
+            frame_total += gop.len() as u64;
+
+            // GOPs still encoding or buffered in the muxer both hold memory
+            while dispatched - muxer.next_index() >= self.max_in_flight as u64 {
+                collect(muxer, &mut encode_time)?;
+            }
+
+            let block_scales = match lookahead {
+                Some((maps, config)) => (first_frame..first_frame + gop.len())
+                    .filter_map(|frame| maps.block_scales(frame, config))
+                    .collect(),
+                None => Vec::new(),
+            };
+            let budget = rate.allocate(dispatched, gop.len());
+            self.dispatch(gop, budget, block_scales, rate.clone(), sender.clone());
+            dispatched += 1;
+            peak_in_flight = peak_in_flight.max((dispatched - muxer.next_index()) as usize);
+        }
+
+        while muxer.next_index() < dispatched {
+            collect(muxer, &mut encode_time)?;
+        }
+
+        let gops = dispatched - first_index;
+        Ok(GopEncodeStats {
+            gops,
+            frames: frame_total,
+            bytes: muxer.bytes_written() - bytes_before,
+            wall_time: started.elapsed(),
+            encode_time,
+            peak_in_flight,
+            target_bitrate_bps: self.config.target_bitrate_bps,
+            achieved_bitrate_bps: rate.achieved_bitrate(self.config.frame_rate),
+            keyframes: None,
+            lookahead_cached: None,
+        })
+    }
+
+    fn dispatch(
+        &self,
+        frames: Vec<VisualInput>,
+        budget: GopBudget,
+        block_scales: Vec<Array2<f64>>,
+        rate: Arc<RateController>,
+        sender: channel::Sender<Result<EncodedGop, AfiyahError>>,
+    ) {
+        let factory = self.factory.clone();
+        let idle_encoders = self.idle_encoders.clone();
+
+        self.pool.spawn(move || {
+            let started = Instant::now();
+            let checked_out = idle_encoders.lock().unwrap_or_else(|e| e.into_inner()).pop();
+            let encoder = match checked_out {
+                Some(encoder) => Ok(encoder),
+                None => factory(),
+            };
+            let result = encoder.and_then(|mut encoder| {
+                let data = encoder.encode_gop_with_scales(&frames, &budget, &block_scales);
+                idle_encoders.lock().unwrap_or_else(|e| e.into_inner()).push(encoder);
+                data
+            });
+
+            let result = result.map(|data| {
+                rate.complete(&budget, frames.len(), data.len() as u64 * 8);
+                EncodedGop {
+                    index: budget.gop_index,
+                    frame_count: frames.len() as u32,
+                    data,
+                    encode_time: started.elapsed(),
+                }
+            });
+
+            // The receiver is gone only if the encode already failed
+            let _ = sender.send(result);
+        });
+    }
+}
+
+/// Encodes every frame of the GOP with the full biological pipeline
+///
+/// Frames are length-prefixed so the decoder can split the payload again.
+impl GopEncoder for CompressionEngine {
+    fn encode_gop(&mut self, frames: &[VisualInput], budget: &GopBudget) -> Result<Vec<u8>, AfiyahError> {
+        self.encode_gop_with_scales(frames, budget, &[])
+    }
+
+    fn encode_gop_with_scales(
+        &mut self,
+        frames: &[VisualInput],
+        budget: &GopBudget,
+        block_scales: &[Array2<f64>],
+    ) -> Result<Vec<u8>, AfiyahError> {
+        self.set_rate_scale(budget.quality_scale);
+
+        let mut payload = Vec::new();
+        for (index, frame) in frames.iter().enumerate() {
+            self.set_block_scales(block_scales.get(index).cloned());
+            let result = self.compress_frame(frame);
+            self.set_block_scales(None);
+            let result = result?;
+            let length = u32::try_from(result.compressed_data.len()).map_err(|_| AfiyahError::Compression {
+                message: "Compressed frame exceeds the maximum payload size".to_string(),
+            })?;
+            payload.extend_from_slice(&length.to_le_bytes());
+            payload.extend_from_slice(&result.compressed_data);
+        }
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InputMetadata;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Produces `complexity / quality_scale` bytes per frame, with uneven timing
+    struct SyntheticEncoder {
+        complexity: f64,
+    }
+
+    impl GopEncoder for SyntheticEncoder {
+        fn encode_gop(&mut self, frames: &[VisualInput], budget: &GopBudget) -> Result<Vec<u8>, AfiyahError> {
+            std::thread::sleep(Duration::from_millis((budget.gop_index * 7 % 5) * 2));
+            let bytes = (self.complexity / budget.quality_scale) as usize * frames.len();
+            Ok(vec![budget.gop_index as u8; bytes])
+        }
+    }
+
+    fn frames(count: usize) -> Vec<VisualInput> {
+        (0..count)
+            .map(|_| VisualInput {
+                luminance_data: Vec::new(),
+                chrominance_data: Vec::new(),
+                spatial_resolution: (0, 0),
+                temporal_resolution: 30.0,
+                metadata: InputMetadata {
+                    viewing_distance: 1.0,
+                    ambient_lighting: 100.0,
+                    viewer_age: 30,
+                    color_temperature: 6500.0,
+                },
+            })
+            .collect()
+    }
+
+    fn read_indices(bitstream: &[u8]) -> Vec<u64> {
+        let mut indices = Vec::new();
+        let mut offset = 0;
+        while offset < bitstream.len() {
+            assert_eq!(&bitstream[offset..offset + 4], &GOP_MAGIC);
+            let index = u64::from_le_bytes(bitstream[offset + 4..offset + 12].try_into().unwrap());
+            let length = u32::from_le_bytes(bitstream[offset + 16..offset + 20].try_into().unwrap()) as usize;
+            assert!(bitstream[offset + 20..offset + 20 + length].iter().all(|&b| b == index as u8));
+            indices.push(index);
+            offset += 20 + length;
+        }
+        indices
+    }
+
+    #[test]
+    fn test_gops_are_muxed_in_order_with_bounded_in_flight() {
+        let config = GopParallelConfig {
+            gop_size: 4,
+            worker_threads: 4,
+            max_in_flight_gops: 3,
+            ..Default::default()
+        };
+        let encoder = GopParallelEncoder::new(config, || Ok(SyntheticEncoder { complexity: 100.0 })).unwrap();
+
+        let mut muxer = OrderedGopMuxer::new(Vec::new());
+        let stats = encoder.encode(frames(42), &mut muxer).unwrap();
+        let bitstream = muxer.finish().unwrap();
+
+        assert_eq!(stats.gops, 11);
+        assert_eq!(stats.frames, 42);
+        assert!(stats.peak_in_flight <= 3);
+        assert_eq!(read_indices(&bitstream), (0..11).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_rate_control_converges_on_target() {
+        // 50 kbit/s at 25 fps is 250 bytes per frame, against a natural 1000
+        let config = GopParallelConfig {
+            gop_size: 5,
+            worker_threads: 4,
+            max_in_flight_gops: 4,
+            target_bitrate_bps: 50_000,
+            frame_rate: 25.0,
+        };
+        let encoder = GopParallelEncoder::new(config, || Ok(SyntheticEncoder { complexity: 1000.0 })).unwrap();
+
+        let mut muxer = OrderedGopMuxer::new(Vec::new());
+        let stats = encoder.encode(frames(1000), &mut muxer).unwrap();
+
+        assert!(stats.bitrate_error().abs() < 0.1, "bitrate error {}", stats.bitrate_error());
+    }
+
+    /// Produces bytes in proportion to each frame's mean gradient, recording the block scales it receives
+    struct TexturedEncoder {
+        scaled_frames: Arc<AtomicUsize>,
+    }
+
+    impl GopEncoder for TexturedEncoder {
+        fn encode_gop(&mut self, frames: &[VisualInput], budget: &GopBudget) -> Result<Vec<u8>, AfiyahError> {
+            self.encode_gop_with_scales(frames, budget, &[])
+        }
+
+        fn encode_gop_with_scales(
+            &mut self,
+            frames: &[VisualInput],
+            budget: &GopBudget,
+            block_scales: &[Array2<f64>],
+        ) -> Result<Vec<u8>, AfiyahError> {
+            self.scaled_frames.fetch_add(block_scales.len(), Ordering::Relaxed);
+            let bytes: f64 = frames
+                .iter()
+                .map(|frame| {
+                    let gradient: f64 = frame.luminance_data.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum();
+                    (200.0 + 2.0 * gradient) / budget.quality_scale
+                })
+                .sum();
+            Ok(vec![budget.gop_index as u8; bytes as usize])
+        }
+    }
+
+    fn textured_frames(count: usize) -> Vec<VisualInput> {
+        (0..count)
+            .map(|index| {
+                let textured = (index / 20) % 2 == 1;
+                let mut frame = frames(1).remove(0);
+                frame.spatial_resolution = (32, 32);
+                frame.luminance_data = (0..32 * 32)
+                    .map(|i| if textured { ((i + i / 32 + index) % 2) as f64 } else { 0.3 })
+                    .collect();
+                frame
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_two_pass_plans_rate_and_reuses_cached_analysis() {
+        // 50 kbit/s at 25 fps is 250 bytes per frame, against a natural 200 flat and 2200 textured
+        let config = GopParallelConfig {
+            gop_size: 5,
+            worker_threads: 4,
+            max_in_flight_gops: 4,
+            target_bitrate_bps: 50_000,
+            frame_rate: 25.0,
+        };
+        let scaled_frames = Arc::new(AtomicUsize::new(0));
+        let factory_counter = scaled_frames.clone();
+        let encoder = GopParallelEncoder::new(config, move || {
+            Ok(TexturedEncoder { scaled_frames: factory_counter.clone() })
+        })
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut lookahead = Lookahead::new(LookaheadConfig {
+            cache_dir: Some(dir.path().to_path_buf()),
+            ..LookaheadConfig::default()
+        })
+        .unwrap();
+        let frames = textured_frames(400);
+
+        // A single pass only reacts once a scene change has overshot
+        let mut muxer = OrderedGopMuxer::new(Vec::new());
+        let single = encoder.encode(frames.clone(), &mut muxer).unwrap();
+        assert_eq!(scaled_frames.load(Ordering::Relaxed), 0);
+
+        let mut muxer = OrderedGopMuxer::new(Vec::new());
+        let first = encoder.encode_two_pass(&frames, &mut muxer, &mut lookahead).unwrap();
+        assert_eq!(first.lookahead_cached, Some(false));
+        assert!(
+            first.bitrate_error().abs() < single.bitrate_error().abs() / 3.0,
+            "two-pass error {} vs single-pass {}",
+            first.bitrate_error(),
+            single.bitrate_error()
+        );
+        assert_eq!(scaled_frames.load(Ordering::Relaxed), 400);
+
+        let mut muxer = OrderedGopMuxer::new(Vec::new());
+        let second = encoder.encode_two_pass(&frames, &mut muxer, &mut lookahead).unwrap();
+        assert_eq!(second.lookahead_cached, Some(true));
+        assert_eq!(lookahead.stats().cache_hits, 1);
+    }
+}
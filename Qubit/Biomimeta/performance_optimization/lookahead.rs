@@ -0,0 +1,496 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Two-Pass Lookahead Analysis
+//!
+//! A first pass over the whole input measures every frame before anything is
+//! encoded. Each frame gets a coding cost estimate and two maps on a coarse
+//! block grid: per-block cost and saliency. Like an encoder's lookahead, the
+//! cost of a frame is the cheaper of coding it on its own (luminance
+//! gradients) and predicting it from the previous frame (the difference to
+//! it), so static stretches score low however detailed they are.
+//!
+//! The encode pass uses the maps twice. The rate controller splits the
+//! stream's bit budget across GOPs by their share of the total cost, raised
+//! to the complexity exponent, instead of learning the content as it goes.
+//! The quantizer gets per-block scales: finer where the content is salient,
+//! coarser where busy texture masks the error.
+//!
+//! Analyses are cached on disk, keyed by a hash of the frame content and the
+//! grid resolution, so repeated encodes of the same mezzanine at different
+//! bitrates or quantization settings skip the first pass entirely.
+//!
+//! Biological Foundation:
+//! - Visual masking hides distortion in busy texture (Legge & Foley, 1980)
+//! - Attention sharpens perception where saliency draws the gaze
+//! - Prediction from recent input makes static scenes cheap to represent
+
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::cortical_processing::attention_mechanisms::SaliencyProcessor;
+use crate::{AfiyahError, VisualInput};
+
+/// Bumped whenever the analysis or its serialized form changes, invalidating cached analyses
+const ANALYSIS_VERSION: u32 = 1;
+
+/// Cost floor, so flat black frames still receive some bits
+const MIN_FRAME_COST: f64 = 1e-3;
+
+/// Range a block's quantization scale may take before normalization
+const MIN_BLOCK_SCALE: f64 = 0.5;
+const MAX_BLOCK_SCALE: f64 = 2.0;
+
+/// Lookahead analysis configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookaheadConfig {
+    pub grid_resolution: usize,     // Edge of the square block grid the maps are computed on
+    pub complexity_exponent: f64,   // How closely bits follow cost: 1.0 gives constant quality, 0.0 a constant bitrate
+    pub saliency_weight: f64,       // How much finer the most salient blocks are quantized, in [0, 1)
+    pub masking_weight: f64,        // How much coarser busy blocks are quantized relative to their cost, in [0, 1]
+    pub cache_dir: Option<PathBuf>, // Where analyses are kept between encodes; None analyzes every time
+}
+
+impl Default for LookaheadConfig {
+    fn default() -> Self {
+        Self {
+            grid_resolution: 16,
+            complexity_exponent: 0.6,
+            saliency_weight: 0.4,
+            masking_weight: 0.3,
+            cache_dir: None,
+        }
+    }
+}
+
+impl LookaheadConfig {
+    pub fn validate(&self) -> Result<(), AfiyahError> {
+        let invalid = |message: &str| {
+            Err(AfiyahError::Configuration {
+                message: message.to_string(),
+            })
+        };
+        if self.grid_resolution < 2 {
+            return invalid("Lookahead grid must be at least 2 blocks wide");
+        }
+        if !(0.0..=1.0).contains(&self.complexity_exponent) {
+            return invalid("Complexity exponent must be in [0, 1]");
+        }
+        if !(0.0..1.0).contains(&self.saliency_weight) {
+            return invalid("Saliency weight must be in [0, 1)");
+        }
+        if !(0.0..=1.0).contains(&self.masking_weight) {
+            return invalid("Masking weight must be in [0, 1]");
+        }
+        Ok(())
+    }
+}
+
+/// First-pass measurements of one frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameComplexity {
+    pub spatial: f64,       // Mean absolute luminance gradient
+    pub temporal: f64,      // Mean absolute difference to the previous frame; 0 without one
+    pub cost: f64,          // Estimated relative coding cost
+    pub blocks: Vec<f32>,   // Per-block cost, row-major on the grid
+    pub saliency: Vec<f32>, // Per-block saliency in [0, 1], row-major on the grid
+}
+
+/// First-pass analysis of a whole input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComplexityMaps {
+    pub content_hash: u64,
+    pub grid_resolution: usize,
+    pub frames: Vec<FrameComplexity>,
+}
+
+impl ComplexityMaps {
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Summed cost of `frames`
+    pub fn cost(&self, frames: Range<usize>) -> f64 {
+        self.frames[frames].iter().map(|frame| frame.cost).sum()
+    }
+
+    /// Share of the bit budget `frames` should receive, before normalization
+    pub fn weight(&self, frames: Range<usize>, exponent: f64) -> f64 {
+        self.frames[frames].iter().map(|frame| frame.cost.powf(exponent)).sum()
+    }
+
+    /// Per-block quantization scales of a frame, averaging 1.0 so the frame's
+    /// bits move between blocks rather than changing in total
+    pub fn block_scales(&self, frame: usize, config: &LookaheadConfig) -> Option<Array2<f64>> {
+        let frame = self.frames.get(frame)?;
+        let n = self.grid_resolution;
+        let mean_cost = frame.blocks.iter().map(|&cost| cost as f64).sum::<f64>() / frame.blocks.len() as f64;
+
+        let mut scales = Array2::from_shape_fn((n, n), |(row, col)| {
+            let index = row * n + col;
+            let masking = if mean_cost > 0.0 {
+                1.0 + config.masking_weight * (frame.blocks[index] as f64 / mean_cost - 1.0)
+            } else {
+                1.0
+            };
+            let attention = 1.0 - config.saliency_weight * frame.saliency[index] as f64;
+            (masking * attention).clamp(MIN_BLOCK_SCALE, MAX_BLOCK_SCALE)
+        });
+        let mean = scales.mean().unwrap_or(1.0);
+        if mean > 0.0 {
+            scales.mapv_inplace(|scale| scale / mean);
+        }
+        Some(scales)
+    }
+}
+
+/// Work done by a lookahead
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LookaheadStats {
+    pub analyses: u64,          // Inputs analyzed by a first pass
+    pub cache_hits: u64,        // Inputs whose analysis was loaded from the cache
+    pub frames_analyzed: u64,
+    pub analysis_time: Duration,
+}
+
+/// Analyses stored on disk, one file per content hash and grid resolution
+pub struct LookaheadCache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedAnalysis {
+    version: u32,
+    maps: ComplexityMaps,
+}
+
+impl LookaheadCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, content_hash: u64, grid_resolution: usize) -> PathBuf {
+        self.dir.join(format!("{:016x}-{}.lookahead", content_hash, grid_resolution))
+    }
+
+    /// The cached analysis of this content, if any
+    ///
+    /// Unreadable or stale entries count as missing; the next store replaces them.
+    pub fn load(&self, content_hash: u64, grid_resolution: usize) -> Option<ComplexityMaps> {
+        let bytes = fs::read(self.path(content_hash, grid_resolution)).ok()?;
+        let cached: CachedAnalysis = bincode::deserialize(&bytes).ok()?;
+        let valid = cached.version == ANALYSIS_VERSION
+            && cached.maps.content_hash == content_hash
+            && cached.maps.grid_resolution == grid_resolution;
+        valid.then_some(cached.maps)
+    }
+
+    /// Stores an analysis, replacing any previous one atomically
+    pub fn store(&self, maps: &ComplexityMaps) -> Result<(), AfiyahError> {
+        let cached = CachedAnalysis {
+            version: ANALYSIS_VERSION,
+            maps: maps.clone(),
+        };
+        let bytes = bincode::serialize(&cached).map_err(|e| AfiyahError::PerformanceOptimization {
+            message: format!("Failed to serialize lookahead analysis: {}", e),
+        })?;
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(maps.content_hash, maps.grid_resolution);
+        // Concurrent encodes of the same content each write their own temporary file
+        let temporary = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temporary, bytes)?;
+        fs::rename(&temporary, &path)?;
+        Ok(())
+    }
+}
+
+/// Runs the first pass, or loads its result from the cache
+pub struct Lookahead {
+    config: LookaheadConfig,
+    saliency: SaliencyProcessor,
+    cache: Option<LookaheadCache>,
+    stats: LookaheadStats,
+}
+
+impl Lookahead {
+    pub fn new(config: LookaheadConfig) -> Result<Self, AfiyahError> {
+        config.validate()?;
+        let cache = config.cache_dir.clone().map(LookaheadCache::new);
+        Ok(Self {
+            config,
+            saliency: SaliencyProcessor::new()?,
+            cache,
+            stats: LookaheadStats::default(),
+        })
+    }
+
+    pub fn config(&self) -> &LookaheadConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> &LookaheadStats {
+        &self.stats
+    }
+
+    /// Analyzes `frames`, returning the cached analysis when this content was analyzed before
+    ///
+    /// Returns whether the analysis came from the cache alongside it.
+    pub fn analyze(&mut self, frames: &[VisualInput]) -> Result<(Arc<ComplexityMaps>, bool), AfiyahError> {
+        for frame in frames {
+            check_frame(frame)?;
+        }
+        let content_hash = content_hash(frames);
+        let grid = self.config.grid_resolution;
+
+        if let Some(maps) = self.cache.as_ref().and_then(|cache| cache.load(content_hash, grid)) {
+            if maps.len() == frames.len() {
+                self.stats.cache_hits += 1;
+                return Ok((Arc::new(maps), true));
+            }
+        }
+
+        let started = Instant::now();
+        let mut frame_maps = Vec::with_capacity(frames.len());
+        let mut previous: Option<&VisualInput> = None;
+        for frame in frames {
+            let reference = previous.filter(|previous| previous.spatial_resolution == frame.spatial_resolution);
+            frame_maps.push(self.analyze_frame(frame, reference)?);
+            previous = Some(frame);
+        }
+        let maps = ComplexityMaps {
+            content_hash,
+            grid_resolution: grid,
+            frames: frame_maps,
+        };
+
+        self.stats.analyses += 1;
+        self.stats.frames_analyzed += frames.len() as u64;
+        self.stats.analysis_time += started.elapsed();
+        if let Some(cache) = &self.cache {
+            cache.store(&maps)?;
+        }
+        Ok((Arc::new(maps), false))
+    }
+
+    fn analyze_frame(&self, frame: &VisualInput, reference: Option<&VisualInput>) -> Result<FrameComplexity, AfiyahError> {
+        let (width, height) = frame.spatial_resolution;
+        let n = self.config.grid_resolution;
+        let luminance = &frame.luminance_data;
+        let block = |x: usize, y: usize| (y * n / height) * n + x * n / width;
+
+        let mut spatial = vec![0.0; n * n];
+        let mut temporal = vec![0.0; n * n];
+        let mut mean = vec![0.0; n * n];
+        let mut samples = vec![0usize; n * n];
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let sample = luminance[index];
+                let right = if x + 1 < width { luminance[index + 1] } else { sample };
+                let below = if y + 1 < height { luminance[index + width] } else { sample };
+
+                let b = block(x, y);
+                spatial[b] += (right - sample).abs() + (below - sample).abs();
+                if let Some(reference) = reference {
+                    temporal[b] += (sample - reference.luminance_data[index]).abs();
+                }
+                mean[b] += sample;
+                samples[b] += 1;
+            }
+        }
+
+        let mut blocks = Vec::with_capacity(n * n);
+        for b in 0..n * n {
+            let count = samples[b].max(1) as f64;
+            mean[b] /= count;
+            spatial[b] /= count;
+            temporal[b] /= count;
+            // The cheaper of intra and inter coding, as an encoder would choose per block
+            let cost = match reference {
+                Some(_) => spatial[b].min(temporal[b]),
+                None => spatial[b],
+            };
+            blocks.push(cost as f32);
+        }
+
+        let thumbnail = Array2::from_shape_vec((n, n), mean).map_err(|e| AfiyahError::PerformanceOptimization {
+            message: format!("Failed to build lookahead thumbnail: {}", e),
+        })?;
+        let weights = self.saliency.compute_saliency(&thumbnail)?.weights;
+        let peak = weights.iter().cloned().fold(0.0, f64::max);
+        let saliency = weights
+            .iter()
+            .map(|&weight| if peak > 0.0 { (weight / peak) as f32 } else { 0.0 })
+            .collect();
+
+        let spatial_mean = spatial.iter().sum::<f64>() / (n * n) as f64;
+        let temporal_mean = temporal.iter().sum::<f64>() / (n * n) as f64;
+        let cost = blocks.iter().map(|&cost| cost as f64).sum::<f64>() / (n * n) as f64;
+        Ok(FrameComplexity {
+            spatial: spatial_mean,
+            temporal: temporal_mean,
+            cost: cost.max(MIN_FRAME_COST),
+            blocks,
+            saliency,
+        })
+    }
+}
+
+fn check_frame(frame: &VisualInput) -> Result<(), AfiyahError> {
+    let (width, height) = frame.spatial_resolution;
+    if width == 0 || height == 0 || frame.luminance_data.len() < width * height {
+        return Err(AfiyahError::InputError {
+            message: format!(
+                "Frame holds {} luminance samples for a {}x{} resolution",
+                frame.luminance_data.len(),
+                width,
+                height
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// FNV-1a over the resolution and samples of every frame, stable across
+/// builds and platforms so cache keys survive upgrades
+pub fn content_hash(frames: &[VisualInput]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = OFFSET;
+    let mut feed = |bytes: [u8; 8]| {
+        for byte in bytes {
+            hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+        }
+    };
+    feed((frames.len() as u64).to_le_bytes());
+    for frame in frames {
+        feed((frame.spatial_resolution.0 as u64).to_le_bytes());
+        feed((frame.spatial_resolution.1 as u64).to_le_bytes());
+        feed((frame.luminance_data.len() as u64).to_le_bytes());
+        frame.luminance_data.iter().for_each(|sample| feed(sample.to_bits().to_le_bytes()));
+        feed((frame.chrominance_data.len() as u64).to_le_bytes());
+        frame.chrominance_data.iter().for_each(|sample| feed(sample.to_bits().to_le_bytes()));
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InputMetadata;
+
+    const WIDTH: usize = 32;
+    const HEIGHT: usize = 32;
+
+    /// Flat grey, with a moving bright square or a busy texture on the left half
+    fn frame(index: usize, textured: bool) -> VisualInput {
+        let luminance_data = (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let (x, y) = (i % WIDTH, i / WIDTH);
+                if textured && x < WIDTH / 2 {
+                    ((x + y + index) % 2) as f64
+                } else if (index..index + 4).contains(&x) && (12..16).contains(&y) {
+                    1.0
+                } else {
+                    0.3
+                }
+            })
+            .collect();
+        VisualInput {
+            luminance_data,
+            chrominance_data: vec![0.5; WIDTH * HEIGHT],
+            spatial_resolution: (WIDTH, HEIGHT),
+            temporal_resolution: 30.0,
+            metadata: InputMetadata {
+                viewing_distance: 1.0,
+                ambient_lighting: 100.0,
+                viewer_age: 30,
+                color_temperature: 6500.0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_busy_frames_cost_more_and_masking_coarsens_texture() {
+        let frames: Vec<_> = (0..6).map(|i| frame(i, i >= 3)).collect();
+        let mut lookahead = Lookahead::new(LookaheadConfig::default()).unwrap();
+        let (maps, cached) = lookahead.analyze(&frames).unwrap();
+
+        assert!(!cached);
+        assert_eq!(maps.len(), 6);
+        assert!(maps.cost(3..6) > 2.0 * maps.cost(0..3), "{} vs {}", maps.cost(3..6), maps.cost(0..3));
+
+        let scales = maps.block_scales(4, lookahead.config()).unwrap();
+        assert!((scales.mean().unwrap() - 1.0).abs() < 1e-9);
+        assert!(scales[[8, 2]] > scales[[2, 13]], "textured {} vs flat {}", scales[[8, 2]], scales[[2, 13]]);
+    }
+
+    #[test]
+    fn test_repeated_analysis_is_served_from_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LookaheadConfig {
+            cache_dir: Some(dir.path().to_path_buf()),
+            ..LookaheadConfig::default()
+        };
+        let frames: Vec<_> = (0..4).map(|i| frame(i, false)).collect();
+
+        let (first, cached) = Lookahead::new(config.clone()).unwrap().analyze(&frames).unwrap();
+        assert!(!cached);
+
+        let mut lookahead = Lookahead::new(config.clone()).unwrap();
+        let (second, cached) = lookahead.analyze(&frames).unwrap();
+        assert!(cached);
+        assert_eq!(first, second);
+        assert_eq!(lookahead.stats().analyses, 0);
+
+        // Different content, or a different grid, is analyzed afresh
+        let other: Vec<_> = (1..5).map(|i| frame(i, false)).collect();
+        assert!(!lookahead.analyze(&other).unwrap().1);
+        let coarse = LookaheadConfig { grid_resolution: 8, ..config };
+        assert!(!Lookahead::new(coarse).unwrap().analyze(&frames).unwrap().1);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+}
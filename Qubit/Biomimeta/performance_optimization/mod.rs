@@ -3,4 +3,10 @@
 pub mod gpu_acceleration;
 pub mod simd_optimization;
 pub mod memory_optimization;
-pub mod thread_optimization;
\ No newline at end of file
+pub mod thread_optimization;
+pub mod frame_buffer_pool;
+pub mod gop_parallel;
+pub mod adaptive_gop;
+pub mod lookahead;
+pub mod per_title;
+pub mod encoder_session;
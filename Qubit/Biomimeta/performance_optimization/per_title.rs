@@ -0,0 +1,694 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Per-Title Encoding Optimization
+//!
+//! A fixed bitrate ladder wastes bits on simple titles and starves complex
+//! ones. The per-title optimizer measures each title instead: the frames are
+//! split into shots at scene changes, and every shot is scored with the
+//! lookahead's complexity analysis. A rate-quality model predicts, for each
+//! candidate resolution and bitrate, the quality a viewer would see after
+//! the rendition is scaled back to the source size. Taking the best
+//! resolution at every bitrate traces the title's convex hull, and rungs are
+//! picked along it from the bitrate that reaches the target quality down,
+//! each a noticeable quality step below the one above.
+//!
+//! The resulting profile also carries an encode preset: how long GOPs may
+//! get and how closely the two-pass rate plan should follow shot costs.
+//! Profiles are kept in a small JSON catalog keyed by the content hash, so
+//! re-encodes and re-packaging of the same mezzanine reuse the ladder
+//! without analyzing the title again.
+//!
+//! Biological Foundation:
+//! - Just-noticeable differences set how far apart rungs need to be (Weber, 1834)
+//! - Busy texture masks coding error, so complex shots tolerate fewer bits per detail
+//! - Fine detail is lost to upscaling only where the source has detail to lose
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::adaptive_gop::{AdaptiveGopConfig, KeyframeAnalyzer};
+use super::gop_parallel::GopParallelConfig;
+use super::lookahead::{content_hash, ComplexityMaps, Lookahead, LookaheadConfig};
+use crate::streaming_engine::QualityLevel;
+use crate::{AfiyahError, VisualInput};
+
+/// Bumped whenever profiles or their derivation change, invalidating catalog entries
+const CATALOG_VERSION: u32 = 1;
+
+/// Bits per pixel, relative to shot cost, that close about 63% of the gap to full quality
+const BPP_SCALE: f64 = 1.15;
+
+/// Cost floor, so near-static shots do not predict perfect quality at any bitrate
+const MIN_SHOT_COST: f64 = 5e-3;
+
+/// Spatial detail at which half of the detail lost by downscaling is visible
+const DETAIL_HALF_POINT: f64 = 0.05;
+
+/// Mean shot cost separating simple from moderate, and moderate from complex titles
+const LOW_COMPLEXITY: f64 = 0.02;
+const HIGH_COMPLEXITY: f64 = 0.08;
+
+/// Per-title ladder and shot detection configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PerTitleConfig {
+    pub candidate_heights: Vec<usize>, // Rendition heights to consider; heights above the source are skipped
+    pub min_bitrate_bps: u64,          // Bottom of the bitrate search and of the ladder
+    pub max_bitrate_bps: u64,          // Top of the bitrate search
+    pub bitrate_step: f64,             // Ratio between neighbouring bitrates on the search grid
+    pub target_quality: f64,           // Predicted quality in (0, 1] the top rung aims for
+    pub min_quality_step: f64,         // Smallest predicted quality gap between neighbouring rungs
+    pub max_rungs: usize,
+    pub shot_threshold: f64,           // Scene change score in (0, 1] that starts a new shot
+    pub min_shot_frames: usize,        // Frames a shot holds before a scene change may end it
+}
+
+impl Default for PerTitleConfig {
+    fn default() -> Self {
+        Self {
+            candidate_heights: vec![240, 360, 480, 720, 1080, 1440, 2160],
+            min_bitrate_bps: 150_000,
+            max_bitrate_bps: 20_000_000,
+            bitrate_step: 1.1,
+            target_quality: 0.95,
+            min_quality_step: 0.05,
+            max_rungs: 6,
+            shot_threshold: 0.4,
+            min_shot_frames: 12,
+        }
+    }
+}
+
+impl PerTitleConfig {
+    pub fn validate(&self) -> Result<(), AfiyahError> {
+        let invalid = |message: &str| {
+            Err(AfiyahError::Configuration {
+                message: message.to_string(),
+            })
+        };
+        if self.candidate_heights.is_empty() || self.candidate_heights.contains(&0) {
+            return invalid("Per-title candidate heights must be non-empty and positive");
+        }
+        if self.min_bitrate_bps == 0 || self.max_bitrate_bps < self.min_bitrate_bps {
+            return invalid("Per-title bitrate range must be positive and not inverted");
+        }
+        if !(self.bitrate_step > 1.0 && self.bitrate_step.is_finite()) {
+            return invalid("Bitrate step must be greater than 1");
+        }
+        if !(self.target_quality > 0.0 && self.target_quality <= 1.0) {
+            return invalid("Target quality must be in (0, 1]");
+        }
+        if !(self.min_quality_step > 0.0 && self.min_quality_step < 1.0) {
+            return invalid("Minimum quality step must be in (0, 1)");
+        }
+        if self.max_rungs == 0 {
+            return invalid("A ladder needs at least one rung");
+        }
+        if !(self.shot_threshold > 0.0 && self.shot_threshold <= 1.0) {
+            return invalid("Shot threshold must be in (0, 1]");
+        }
+        if self.min_shot_frames == 0 {
+            return invalid("Minimum shot length must be at least one frame");
+        }
+        Ok(())
+    }
+}
+
+/// Complexity of one shot, averaged over its frames
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShotComplexity {
+    pub start_frame: usize,
+    pub end_frame: usize, // Exclusive
+    pub spatial: f64,
+    pub temporal: f64,
+    pub cost: f64,
+}
+
+impl ShotComplexity {
+    pub fn frames(&self) -> usize {
+        self.end_frame - self.start_frame
+    }
+}
+
+/// One rendition of the ladder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LadderRung {
+    pub width: usize,
+    pub height: usize,
+    pub bitrate_bps: u64,
+    pub predicted_quality: f64, // In [0, 1], after scaling back to the source size
+}
+
+/// How demanding a title is to encode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComplexityClass {
+    Low,    // Slides, animation, static shots
+    Medium,
+    High,   // Grain, fast motion, dense texture
+}
+
+/// Encoder settings tuned to a title
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncodePreset {
+    pub complexity_class: ComplexityClass,
+    pub complexity_exponent: f64, // Lookahead exponent; titles whose shots differ more follow shot cost more closely
+    pub min_gop_frames: usize,
+    pub max_seek_seconds: f64,    // Longer for simple titles, where key frames are expensive relative to the rest
+}
+
+impl EncodePreset {
+    /// Key frame placement for `encode_adaptive`
+    pub fn gop_config(&self) -> AdaptiveGopConfig {
+        AdaptiveGopConfig {
+            min_gop_frames: self.min_gop_frames,
+            max_seek_seconds: self.max_seek_seconds,
+            ..AdaptiveGopConfig::on_demand()
+        }
+    }
+
+    /// `base` with this title's complexity exponent, for `encode_two_pass`
+    pub fn lookahead_config(&self, base: &LookaheadConfig) -> LookaheadConfig {
+        LookaheadConfig {
+            complexity_exponent: self.complexity_exponent,
+            ..base.clone()
+        }
+    }
+}
+
+/// Ladder and encode preset derived for one title
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TitleProfile {
+    pub content_hash: u64,
+    pub config: PerTitleConfig,   // Settings the profile was derived with
+    pub grid_resolution: usize,   // Lookahead grid the shots were scored on
+    pub source_resolution: (usize, usize),
+    pub frame_rate: f64,
+    pub frame_count: usize,
+    pub shots: Vec<ShotComplexity>,
+    pub ladder: Vec<LadderRung>,  // Ascending bitrate
+    pub preset: EncodePreset,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TitleProfile {
+    /// `base` set up to encode one rung of the ladder
+    pub fn encoder_config(&self, rung: usize, base: &GopParallelConfig) -> Option<GopParallelConfig> {
+        let rung = self.ladder.get(rung)?;
+        Some(GopParallelConfig {
+            target_bitrate_bps: rung.bitrate_bps,
+            frame_rate: self.frame_rate,
+            ..base.clone()
+        })
+    }
+
+    /// The ladder as quality levels for packaging and adaptive streaming
+    pub fn quality_levels(&self) -> Vec<QualityLevel> {
+        self.ladder
+            .iter()
+            .map(|rung| {
+                QualityLevel::new(
+                    format!("{}p", rung.height),
+                    rung.bitrate_bps.min(u32::MAX as u64) as u32,
+                    (rung.width as u32, rung.height as u32),
+                    self.frame_rate,
+                    rung.predicted_quality,
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CatalogFile {
+    version: u32,
+    profiles: BTreeMap<String, TitleProfile>,
+}
+
+/// Title profiles stored in one JSON file, keyed by content hash
+pub struct TitleCatalog {
+    path: PathBuf,
+    profiles: BTreeMap<String, TitleProfile>,
+}
+
+impl TitleCatalog {
+    /// Opens the catalog at `path`
+    ///
+    /// A missing file is an empty catalog. So is an unreadable or stale one;
+    /// the next insert replaces it.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let profiles = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CatalogFile>(&bytes).ok())
+            .filter(|file| file.version == CATALOG_VERSION)
+            .map(|file| file.profiles)
+            .unwrap_or_default();
+        Self { path, profiles }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.profiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    pub fn get(&self, content_hash: u64) -> Option<&TitleProfile> {
+        self.profiles.get(&key(content_hash))
+    }
+
+    /// Stores a profile, replacing any previous one for the same content
+    pub fn insert(&mut self, profile: TitleProfile) -> Result<(), AfiyahError> {
+        self.profiles.insert(key(profile.content_hash), profile);
+        self.save()
+    }
+
+    pub fn remove(&mut self, content_hash: u64) -> Result<Option<TitleProfile>, AfiyahError> {
+        let removed = self.profiles.remove(&key(content_hash));
+        if removed.is_some() {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Rewrites the file atomically
+    fn save(&self) -> Result<(), AfiyahError> {
+        let file = CatalogFile {
+            version: CATALOG_VERSION,
+            profiles: self.profiles.clone(),
+        };
+        let bytes = serde_json::to_vec_pretty(&file).map_err(|e| AfiyahError::PerformanceOptimization {
+            message: format!("Failed to serialize title catalog: {}", e),
+        })?;
+
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let temporary = self.path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temporary, bytes)?;
+        fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
+
+fn key(content_hash: u64) -> String {
+    format!("{:016x}", content_hash)
+}
+
+/// Work done by a per-title optimizer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PerTitleStats {
+    pub titles_analyzed: u64,
+    pub catalog_hits: u64,  // Titles whose profile was reused from the catalog
+    pub shots_analyzed: u64,
+}
+
+/// Derives per-title profiles, reusing catalogued ones
+pub struct PerTitleOptimizer {
+    config: PerTitleConfig,
+    lookahead: Lookahead,
+    catalog: Option<TitleCatalog>,
+    stats: PerTitleStats,
+}
+
+impl PerTitleOptimizer {
+    pub fn new(config: PerTitleConfig, lookahead: Lookahead) -> Result<Self, AfiyahError> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            lookahead,
+            catalog: None,
+            stats: PerTitleStats::default(),
+        })
+    }
+
+    /// Look profiles up in, and add new ones to, `catalog`
+    pub fn with_catalog(mut self, catalog: TitleCatalog) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    pub fn config(&self) -> &PerTitleConfig {
+        &self.config
+    }
+
+    pub fn catalog(&self) -> Option<&TitleCatalog> {
+        self.catalog.as_ref()
+    }
+
+    pub fn stats(&self) -> &PerTitleStats {
+        &self.stats
+    }
+
+    /// The lookahead used for shot analysis, to reuse its cache in the encode pass
+    pub fn lookahead_mut(&mut self) -> &mut Lookahead {
+        &mut self.lookahead
+    }
+
+    /// The profile of `frames`, from the catalog when this content was
+    /// optimized before with the same settings
+    ///
+    /// Returns whether the profile came from the catalog alongside it.
+    pub fn optimize(&mut self, frames: &[VisualInput]) -> Result<(Arc<TitleProfile>, bool), AfiyahError> {
+        let Some(first) = frames.first() else {
+            return Err(AfiyahError::InputError {
+                message: "Per-title optimization needs at least one frame".to_string(),
+            });
+        };
+        let content_hash = content_hash(frames);
+        let grid_resolution = self.lookahead.config().grid_resolution;
+        let catalogued = self.catalog.as_ref().and_then(|catalog| catalog.get(content_hash)).filter(|profile| {
+            profile.config == self.config && profile.grid_resolution == grid_resolution && profile.frame_count == frames.len()
+        });
+        if let Some(profile) = catalogued {
+            self.stats.catalog_hits += 1;
+            return Ok((Arc::new(profile.clone()), true));
+        }
+
+        let (maps, _) = self.lookahead.analyze(frames)?;
+        let shots = self.shots(frames, &maps)?;
+        let frame_rate = if first.temporal_resolution > 0.0 { first.temporal_resolution } else { 30.0 };
+        let profile = TitleProfile {
+            content_hash,
+            config: self.config.clone(),
+            grid_resolution,
+            source_resolution: first.spatial_resolution,
+            frame_rate,
+            frame_count: frames.len(),
+            ladder: ladder(&self.config, &shots, first.spatial_resolution, frame_rate),
+            preset: preset(&shots),
+            shots,
+            created_at: Utc::now(),
+        };
+
+        self.stats.titles_analyzed += 1;
+        self.stats.shots_analyzed += profile.shots.len() as u64;
+        if let Some(catalog) = &mut self.catalog {
+            catalog.insert(profile.clone())?;
+        }
+        Ok((Arc::new(profile), false))
+    }
+
+    /// Splits the title at scene changes and averages the lookahead costs of each shot
+    fn shots(&self, frames: &[VisualInput], maps: &ComplexityMaps) -> Result<Vec<ShotComplexity>, AfiyahError> {
+        let mut analyzer = KeyframeAnalyzer::new(&AdaptiveGopConfig {
+            scene_change_threshold: self.config.shot_threshold,
+            ..AdaptiveGopConfig::default()
+        })?;
+        let mut cuts = vec![0];
+        for (index, frame) in frames.iter().enumerate() {
+            let signals = analyzer.analyze(frame)?;
+            let shot_start = *cuts.last().unwrap_or(&0);
+            if index > 0 && signals.scene_change >= self.config.shot_threshold && index - shot_start >= self.config.min_shot_frames {
+                cuts.push(index);
+            }
+        }
+        cuts.push(frames.len());
+
+        Ok(cuts
+            .windows(2)
+            .map(|bounds| {
+                let (start, end) = (bounds[0], bounds[1]);
+                let shot = &maps.frames[start..end];
+                let count = shot.len() as f64;
+                ShotComplexity {
+                    start_frame: start,
+                    end_frame: end,
+                    spatial: shot.iter().map(|frame| frame.spatial).sum::<f64>() / count,
+                    // The cut itself is coded as a key frame, not predicted
+                    temporal: shot.iter().skip(1).map(|frame| frame.temporal).sum::<f64>() / (count - 1.0).max(1.0),
+                    cost: maps.cost(start..end) / count,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Predicted quality of the title encoded at `resolution` and `bitrate_bps`,
+/// averaged over shots by their length
+fn predicted_quality(shots: &[ShotComplexity], source_height: usize, resolution: (usize, usize), bitrate_bps: f64, frame_rate: f64) -> f64 {
+    let (width, height) = resolution;
+    let scale = (height as f64 / source_height as f64).min(1.0);
+    let bits_per_pixel = bitrate_bps / (width * height) as f64 / frame_rate;
+
+    let mut total = 0.0;
+    let mut frames = 0.0;
+    for shot in shots {
+        // Downscaling packs the same detail into fewer pixels
+        let cost = shot.cost.max(MIN_SHOT_COST) / scale.sqrt();
+        let coding = 1.0 - (-BPP_SCALE * bits_per_pixel / cost).exp();
+        let detail = shot.spatial / (shot.spatial + DETAIL_HALF_POINT);
+        let scaling = 1.0 - detail * (1.0 - scale);
+        total += coding * scaling * shot.frames() as f64;
+        frames += shot.frames() as f64;
+    }
+    if frames > 0.0 {
+        total / frames
+    } else {
+        0.0
+    }
+}
+
+/// Best rendition at each bitrate of the search grid, then rungs picked
+/// from the target quality down in noticeable steps
+fn ladder(config: &PerTitleConfig, shots: &[ShotComplexity], source: (usize, usize), frame_rate: f64) -> Vec<LadderRung> {
+    let (source_width, source_height) = source;
+    let mut heights: Vec<usize> = config.candidate_heights.iter().copied().filter(|&height| height <= source_height).collect();
+    if heights.is_empty() {
+        heights.push(source_height);
+    }
+    let resolutions: Vec<(usize, usize)> = heights
+        .into_iter()
+        .map(|height| {
+            let width = (source_width as f64 * height as f64 / source_height as f64 / 2.0).round() as usize * 2;
+            (width.max(2), height)
+        })
+        .collect();
+
+    let mut hull = Vec::new();
+    let mut bitrate = config.min_bitrate_bps as f64;
+    loop {
+        let bitrate_bps = bitrate.min(config.max_bitrate_bps as f64);
+        let best = resolutions
+            .iter()
+            .map(|&resolution| (resolution, predicted_quality(shots, source_height, resolution, bitrate_bps, frame_rate)))
+            .fold(None, |best: Option<((usize, usize), f64)>, candidate| match best {
+                Some(best) if best.1 >= candidate.1 => Some(best),
+                _ => Some(candidate),
+            });
+        if let Some(((width, height), predicted_quality)) = best {
+            hull.push(LadderRung {
+                width,
+                height,
+                bitrate_bps: bitrate_bps.round() as u64,
+                predicted_quality,
+            });
+        }
+        if bitrate_bps >= config.max_bitrate_bps as f64 {
+            break;
+        }
+        bitrate *= config.bitrate_step;
+    }
+
+    let top = hull
+        .iter()
+        .position(|point| point.predicted_quality >= config.target_quality)
+        .unwrap_or(hull.len() - 1);
+    let mut rungs = vec![hull[top].clone()];
+    for point in hull[..top].iter().rev() {
+        if rungs.len() == config.max_rungs {
+            break;
+        }
+        let above = rungs.last().expect("ladder has a top rung");
+        if above.predicted_quality - point.predicted_quality >= config.min_quality_step {
+            rungs.push(point.clone());
+        }
+    }
+    // Keep a floor for the weakest connections
+    let bottom = &hull[0];
+    if rungs.len() < config.max_rungs && rungs.last().is_some_and(|lowest| lowest.bitrate_bps > bottom.bitrate_bps) {
+        rungs.push(bottom.clone());
+    }
+    rungs.reverse();
+    rungs
+}
+
+fn preset(shots: &[ShotComplexity]) -> EncodePreset {
+    let frames = shots.iter().map(ShotComplexity::frames).sum::<usize>().max(1) as f64;
+    let mean = shots.iter().map(|shot| shot.cost * shot.frames() as f64).sum::<f64>() / frames;
+    let variance = shots
+        .iter()
+        .map(|shot| (shot.cost - mean).powi(2) * shot.frames() as f64)
+        .sum::<f64>()
+        / frames;
+    let variation = if mean > 0.0 { variance.sqrt() / mean } else { 0.0 };
+
+    let (complexity_class, min_gop_frames, max_seek_seconds) = if mean < LOW_COMPLEXITY {
+        (ComplexityClass::Low, 12, 5.0)
+    } else if mean < HIGH_COMPLEXITY {
+        (ComplexityClass::Medium, 8, 4.0)
+    } else {
+        (ComplexityClass::High, 4, 2.0)
+    };
+    EncodePreset {
+        complexity_class,
+        complexity_exponent: (0.4 + 0.5 * variation).clamp(0.3, 0.9),
+        min_gop_frames,
+        max_seek_seconds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InputMetadata;
+
+    const WIDTH: usize = 32;
+    const HEIGHT: usize = 32;
+
+    /// A drifting gradient, or dense moving texture
+    fn frame(index: usize, busy: bool) -> VisualInput {
+        let luminance_data = (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let (x, y) = (i % WIDTH, i / WIDTH);
+                if busy {
+                    ((x * 7 + y * 13 + index * 5) % 11) as f64 / 10.0
+                } else {
+                    0.2 + 0.3 * ((x + index) % WIDTH) as f64 / WIDTH as f64
+                }
+            })
+            .collect();
+        VisualInput {
+            luminance_data,
+            chrominance_data: vec![0.5; WIDTH * HEIGHT],
+            spatial_resolution: (WIDTH, HEIGHT),
+            temporal_resolution: 30.0,
+            metadata: InputMetadata {
+                viewing_distance: 1.0,
+                ambient_lighting: 100.0,
+                viewer_age: 30,
+                color_temperature: 6500.0,
+            },
+        }
+    }
+
+    fn config() -> PerTitleConfig {
+        PerTitleConfig {
+            candidate_heights: vec![8, 16, 32],
+            min_bitrate_bps: 500,
+            max_bitrate_bps: 500_000,
+            min_shot_frames: 8,
+            ..PerTitleConfig::default()
+        }
+    }
+
+    fn optimizer() -> PerTitleOptimizer {
+        PerTitleOptimizer::new(config(), Lookahead::new(LookaheadConfig::default()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_complex_titles_get_richer_ladders() {
+        let simple: Vec<_> = (0..8).map(|i| frame(i, false)).collect();
+        let mixed: Vec<_> = (0..16).map(|i| frame(i, i >= 8)).collect();
+
+        let mut optimizer = optimizer();
+        let (simple, _) = optimizer.optimize(&simple).unwrap();
+        let (mixed, _) = optimizer.optimize(&mixed).unwrap();
+
+        assert_eq!(simple.shots.len(), 1);
+        assert_eq!(mixed.shots.len(), 2);
+        assert_eq!(mixed.shots[1].start_frame, 8);
+        assert!(mixed.shots[1].cost > mixed.shots[0].cost);
+
+        for profile in [&simple, &mixed] {
+            assert!(!profile.ladder.is_empty() && profile.ladder.len() <= config().max_rungs);
+            assert!(profile.ladder.windows(2).all(|pair| {
+                pair[0].bitrate_bps < pair[1].bitrate_bps && pair[0].predicted_quality < pair[1].predicted_quality
+            }));
+        }
+        let top = |profile: &TitleProfile| profile.ladder.last().unwrap().bitrate_bps;
+        assert!(top(&mixed) > top(&simple), "{} vs {}", top(&mixed), top(&simple));
+        assert_eq!(simple.preset.complexity_class, ComplexityClass::Low);
+        assert!(mixed.preset.complexity_exponent > simple.preset.complexity_exponent);
+
+        let levels = mixed.quality_levels();
+        assert_eq!(levels.len(), mixed.ladder.len());
+        let base = GopParallelConfig::default();
+        assert_eq!(mixed.encoder_config(0, &base).unwrap().target_bitrate_bps, mixed.ladder[0].bitrate_bps);
+        assert!(mixed.encoder_config(mixed.ladder.len(), &base).is_none());
+    }
+
+    #[test]
+    fn test_catalogued_profiles_are_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("titles.json");
+        let frames: Vec<_> = (0..8).map(|i| frame(i, true)).collect();
+
+        let mut first = optimizer().with_catalog(TitleCatalog::open(&path));
+        let (profile, reused) = first.optimize(&frames).unwrap();
+        assert!(!reused);
+        assert_eq!(first.catalog().unwrap().len(), 1);
+
+        // A later process finds the profile without analyzing again
+        let mut second = optimizer().with_catalog(TitleCatalog::open(&path));
+        let (again, reused) = second.optimize(&frames).unwrap();
+        assert!(reused);
+        assert_eq!(again.created_at, profile.created_at);
+        let bitrates = |profile: &TitleProfile| profile.ladder.iter().map(|rung| rung.bitrate_bps).collect::<Vec<_>>();
+        assert_eq!(bitrates(&again), bitrates(&profile));
+        assert_eq!(second.stats().titles_analyzed, 0);
+        assert_eq!(second.lookahead_mut().stats().analyses, 0);
+
+        // Other settings derive a new profile, replacing the stale one
+        let other = PerTitleConfig { max_rungs: 2, ..config() };
+        let mut third = PerTitleOptimizer::new(other, Lookahead::new(LookaheadConfig::default()).unwrap())
+            .unwrap()
+            .with_catalog(TitleCatalog::open(&path));
+        let (replaced, reused) = third.optimize(&frames).unwrap();
+        assert!(!reused);
+        assert!(replaced.ladder.len() <= 2);
+        assert_eq!(TitleCatalog::open(&path).get(profile.content_hash).unwrap().config.max_rungs, 2);
+
+        let mut catalog = TitleCatalog::open(&path);
+        assert!(catalog.remove(profile.content_hash).unwrap().is_some());
+        assert!(TitleCatalog::open(&path).is_empty());
+    }
+}
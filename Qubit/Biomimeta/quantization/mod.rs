@@ -56,6 +56,8 @@ use ndarray::{Array1, Array2, Array3, s, Axis};
 use std::collections::HashMap;
 use anyhow::{Result, anyhow};
 
+use crate::performance_optimization::frame_buffer_pool::{pooled_array2, SharedFrameBufferPool};
+
 /// Biological quantization engine
 pub struct BiologicalQuantizer {
     contrast_sensitivity_model: ContrastSensitivityModel,
@@ -63,6 +65,9 @@ pub struct BiologicalQuantizer {
     neural_noise_quantizer: NeuralNoiseQuantizer,
     adaptive_quantizer: AdaptiveQuantizer,
     config: QuantizationConfig,
+    buffer_pool: Option<SharedFrameBufferPool>,
+    rate_scale: f64,
+    block_scales: Option<Array2<f64>>,
 }
 
 /// Contrast sensitivity model
@@ -331,9 +336,39 @@ impl BiologicalQuantizer {
             neural_noise_quantizer,
             adaptive_quantizer,
             config,
+            buffer_pool: None,
+            rate_scale: 1.0,
+            block_scales: None,
         })
     }
 
+    /// Draws quantized buffers from a shared frame buffer pool
+    pub fn set_buffer_pool(&mut self, pool: SharedFrameBufferPool) {
+        self.buffer_pool = Some(pool);
+    }
+
+    /// Coarsens quantization by `scale` on top of the selected strategy (1.0 is nominal)
+    ///
+    /// Used by rate control to trade quality for bits.
+    pub fn set_rate_scale(&mut self, scale: f64) {
+        self.rate_scale = if scale.is_finite() { scale.max(1.0) } else { 1.0 };
+    }
+
+    /// Scales the rate scale per block, stretching `scales` over the data (None is uniform)
+    ///
+    /// Used by two-pass encodes to quantize salient blocks finer and busy,
+    /// masking blocks coarser.
+    pub fn set_block_scales(&mut self, scales: Option<Array2<f64>>) {
+        self.block_scales = scales.filter(|scales| !scales.is_empty() && scales.iter().all(|scale| scale.is_finite()));
+    }
+
+    /// Returns a quantization result's buffer to the pool
+    pub fn recycle(&self, result: QuantizationResult) {
+        if let Some(pool) = &self.buffer_pool {
+            pool.release_array2(result.quantized_data);
+        }
+    }
+
     /// Quantize data using biological quantization
     pub fn quantize(&mut self, data: &Array2<f64>, content_analysis: Option<&ContentAnalysis>) -> Result<QuantizationResult> {
         // Step 1: Analyze visual content if not provided
@@ -365,12 +400,30 @@ impl BiologicalQuantizer {
             }
         };
 
-        // Step 4: Calculate quantization metrics
+        // Step 4: Apply rate control coarsening, per block when scales are set
+        let mut quantized_data = quantized_data;
+        let levels = (self.config.quantization_levels.max(2) - 1) as f64;
+        if let Some(scales) = &self.block_scales {
+            let (rows, cols) = quantized_data.dim();
+            let (scale_rows, scale_cols) = scales.dim();
+            for ((row, col), v) in quantized_data.indexed_iter_mut() {
+                let scale = self.rate_scale * scales[[row * scale_rows / rows, col * scale_cols / cols]];
+                if scale > 1.0 {
+                    let step = scale / levels;
+                    *v = (*v / step).round() * step;
+                }
+            }
+        } else if self.rate_scale > 1.0 {
+            let step = self.rate_scale / levels;
+            quantized_data.mapv_inplace(|v| (v / step).round() * step);
+        }
+
+        // Step 5: Calculate quantization metrics
         let quantization_error = self.calculate_quantization_error(data, &quantized_data)?;
         let biological_accuracy = self.calculate_biological_accuracy(&quantized_data)?;
         let compression_ratio = self.calculate_compression_ratio(data, &quantized_data)?;
 
-        // Step 5: Create quantization result
+        // Step 6: Create quantization result
         let result = QuantizationResult {
             quantized_data,
             quantization_strategy,
@@ -424,7 +477,7 @@ impl BiologicalQuantizer {
         let neural_quantized = self.neural_noise_quantizer.quantize(data)?;
 
         // Weighted combination based on content characteristics
-        let mut hybrid_quantized = Array2::zeros(data.dim());
+        let mut hybrid_quantized = pooled_array2(self.buffer_pool.as_deref(), data.dim());
         let (height, width) = data.dim();
 
         for i in 0..height {
@@ -440,6 +493,13 @@ impl BiologicalQuantizer {
             }
         }
 
+        // Intermediate strategy outputs are only needed for the blend
+        if let Some(pool) = &self.buffer_pool {
+            pool.release_array2(contrast_quantized);
+            pool.release_array2(foveal_quantized);
+            pool.release_array2(neural_quantized);
+        }
+
         Ok(hybrid_quantized)
     }
 
@@ -12,6 +12,7 @@ pub mod koniocellular;
 use crate::AfiyahError;
 use crate::retinal_processing::RetinalCalibrationParams;
 use crate::retinal_processing::amacrine_networks::AmacrineResponse;
+use crate::performance_optimization::frame_buffer_pool::{pooled_vec, FrameBufferPool};
 
 /// Ganglion cell pathways for parallel processing
 pub struct GanglionPathways {
@@ -34,6 +35,11 @@ impl GanglionPathways {
 
     /// Processes amacrine response through ganglion pathways
     pub fn process(&self, input: &AmacrineResponse) -> Result<GanglionResponse, AfiyahError> {
+        self.process_with_pool(input, None)
+    }
+
+    /// Processes amacrine response, drawing output streams from a frame buffer pool
+    pub fn process_with_pool(&self, input: &AmacrineResponse, pool: Option<&FrameBufferPool>) -> Result<GanglionResponse, AfiyahError> {
         // Process through magnocellular pathway
         let magnocellular_response = self.magnocellular.process(input)?;
         
@@ -44,10 +50,11 @@ impl GanglionPathways {
         let koniocellular_response = self.koniocellular.process(input)?;
         
         // Integrate pathways
-        let integrated_response = self.pathway_integration.integrate(
+        let integrated_response = self.pathway_integration.integrate_with_pool(
             &magnocellular_response,
             &parvocellular_response,
-            &koniocellular_response
+            &koniocellular_response,
+            pool,
         )?;
         
         Ok(GanglionResponse {
@@ -123,19 +130,35 @@ impl PathwayIntegration {
         magnocellular: &magnocellular::MagnocellularResponse,
         parvocellular: &parvocellular::ParvocellularResponse,
         koniocellular: &koniocellular::KoniocellularResponse,
+    ) -> Result<IntegratedResponse, AfiyahError> {
+        self.integrate_with_pool(magnocellular, parvocellular, koniocellular, None)
+    }
+
+    pub fn integrate_with_pool(
+        &self,
+        magnocellular: &magnocellular::MagnocellularResponse,
+        parvocellular: &parvocellular::ParvocellularResponse,
+        koniocellular: &koniocellular::KoniocellularResponse,
+        pool: Option<&FrameBufferPool>,
     ) -> Result<IntegratedResponse, AfiyahError> {
         // Apply pathway weights
-        let weighted_magnocellular: Vec<f64> = magnocellular.motion_signals.iter()
-            .map(|&x| x * self.integration_weights.magnocellular_weight)
-            .collect();
+        let weighted_magnocellular = Self::weighted_signals(
+            &magnocellular.motion_signals,
+            self.integration_weights.magnocellular_weight,
+            pool,
+        );
         
-        let weighted_parvocellular: Vec<f64> = parvocellular.detail_signals.iter()
-            .map(|&x| x * self.integration_weights.parvocellular_weight)
-            .collect();
+        let weighted_parvocellular = Self::weighted_signals(
+            &parvocellular.detail_signals,
+            self.integration_weights.parvocellular_weight,
+            pool,
+        );
         
-        let weighted_koniocellular: Vec<f64> = koniocellular.color_signals.iter()
-            .map(|&x| x * self.integration_weights.koniocellular_weight)
-            .collect();
+        let weighted_koniocellular = Self::weighted_signals(
+            &koniocellular.color_signals,
+            self.integration_weights.koniocellular_weight,
+            pool,
+        );
         
         // Calculate temporal resolution
         let temporal_resolution = (magnocellular.temporal_resolution * 0.4) +
@@ -151,6 +174,14 @@ impl PathwayIntegration {
         })
     }
 
+    fn weighted_signals(signals: &[f64], weight: f64, pool: Option<&FrameBufferPool>) -> Vec<f64> {
+        let mut weighted = pooled_vec(pool, signals.len());
+        for (out, &x) in weighted.iter_mut().zip(signals) {
+            *out = x * weight;
+        }
+        weighted
+    }
+
     pub fn calibrate(&mut self, params: &RetinalCalibrationParams) -> Result<(), AfiyahError> {
         // Adjust integration weights based on adaptation
         let adaptation_factor = params.adaptation_rate;
@@ -10,6 +10,7 @@ pub mod ganglion_pathways;
 pub mod amacrine_networks;
 
 use crate::AfiyahError;
+use crate::performance_optimization::frame_buffer_pool::SharedFrameBufferPool;
 
 /// Main retinal processor that orchestrates all retinal processing stages
 pub struct RetinalProcessor {
@@ -18,6 +19,7 @@ pub struct RetinalProcessor {
     ganglion_pathways: GanglionPathways,
     amacrine_networks: AmacrineNetworks,
     adaptation_state: AdaptationState,
+    buffer_pool: Option<SharedFrameBufferPool>,
 }
 
 impl RetinalProcessor {
@@ -29,9 +31,24 @@ impl RetinalProcessor {
             ganglion_pathways: GanglionPathways::new()?,
             amacrine_networks: AmacrineNetworks::new()?,
             adaptation_state: AdaptationState::default(),
+            buffer_pool: None,
         })
     }
 
+    /// Draws ganglion output streams from a shared frame buffer pool
+    pub fn set_buffer_pool(&mut self, pool: SharedFrameBufferPool) {
+        self.buffer_pool = Some(pool);
+    }
+
+    /// Returns a retinal output's stream buffers to the pool
+    pub fn recycle(&self, output: RetinalOutput) {
+        if let Some(pool) = &self.buffer_pool {
+            pool.release_vec(output.magnocellular_stream);
+            pool.release_vec(output.parvocellular_stream);
+            pool.release_vec(output.koniocellular_stream);
+        }
+    }
+
     /// Processes visual input through the complete retinal pipeline
     pub fn process(&mut self, input: &crate::VisualInput) -> Result<RetinalOutput, AfiyahError> {
         // Stage 1: Photoreceptor sampling and transduction
@@ -44,7 +61,7 @@ impl RetinalProcessor {
         let amacrine_response = self.amacrine_networks.process(&bipolar_response)?;
         
         // Stage 4: Ganglion cell pathway processing
-        let ganglion_response = self.ganglion_pathways.process(&amacrine_response)?;
+        let ganglion_response = self.ganglion_pathways.process_with_pool(&amacrine_response, self.buffer_pool.as_deref())?;
         
         // Update adaptation state based on input characteristics
         self.update_adaptation(input)?;
@@ -0,0 +1,886 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Hardware Decode Hooks
+//!
+//! Hybrid deployments receive most of their input as H.264 or HEVC. Rather
+//! than decoding it in software, the platform decoder does the work: VAAPI
+//! on Linux, VideoToolbox on Apple platforms and NVDEC on NVIDIA GPUs. Each
+//! one is bound by a `DecodeBackend` registered with `DecodeBackends`, which
+//! probes what every backend can decode and ranks the input paths for a
+//! stream by how fast they decode it, so the transcoding adapter takes the
+//! fastest one and falls back to the next if it cannot be opened.
+//!
+//! The bindings themselves live with the embedding application, which links
+//! libva, VideoToolbox or the NVIDIA video codec SDK; `platform_decode_apis`
+//! reports which of them the host could provide. Decoded surfaces are mapped
+//! to memory and converted to `VisualInput`, and `HardwareDecodeSource` turns
+//! a decoder into a `FrameSource`, so an `EncoderSession` encodes decoded
+//! frames as they come out of the decoder.
+//!
+//! Biological Foundation:
+//! - The lateral geniculate nucleus relays retinal signals to cortex without
+//!   re-encoding them, as decoded frames pass straight into the encoder
+//! - Specialised pathways handle the signals they are built for, as fixed
+//!   function decoders handle the codecs they support
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use futures::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::performance_optimization::encoder_session::FrameSource;
+use crate::{AfiyahError, InputMetadata, VisualInput};
+
+/// Pixel count decode throughput is quoted at, 1920x1080
+const REFERENCE_PIXELS: f64 = 1920.0 * 1080.0;
+
+/// Conventional codecs accepted as input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CompressedCodec {
+    H264,
+    Hevc,
+}
+
+/// Platform decoder APIs, in order of preference when they are equally fast
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DecodeApi {
+    Nvdec,
+    VideoToolbox,
+    Vaapi,
+    Software, // A CPU decoder registered as the last resort
+}
+
+impl DecodeApi {
+    pub fn is_hardware(&self) -> bool {
+        !matches!(self, DecodeApi::Software)
+    }
+}
+
+/// Layout of decoded surfaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SurfaceFormat {
+    Nv12, // 8-bit luma plane, then interleaved CbCr at half resolution
+    P010, // As NV12 with 16-bit little-endian samples holding 10 bits in the high bits
+    I420, // 8-bit luma, Cb and Cr planes, chroma at half resolution
+}
+
+impl SurfaceFormat {
+    pub fn bit_depth(&self) -> u8 {
+        match self {
+            SurfaceFormat::Nv12 | SurfaceFormat::I420 => 8,
+            SurfaceFormat::P010 => 10,
+        }
+    }
+
+    fn plane_count(&self) -> usize {
+        match self {
+            SurfaceFormat::Nv12 | SurfaceFormat::P010 => 2,
+            SurfaceFormat::I420 => 3,
+        }
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        match self {
+            SurfaceFormat::Nv12 | SurfaceFormat::I420 => 1,
+            SurfaceFormat::P010 => 2,
+        }
+    }
+}
+
+/// What the input stream needs from a decoder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamParameters {
+    pub codec: CompressedCodec,
+    pub width: usize,
+    pub height: usize,
+    pub bit_depth: u8,
+    pub frame_rate: f64,
+}
+
+/// One codec a backend decodes, as reported by its probe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodeCapability {
+    pub api: DecodeApi,
+    pub device: String, // e.g. `/dev/dri/renderD128` or the GPU name
+    pub codec: CompressedCodec,
+    pub max_width: usize,
+    pub max_height: usize,
+    pub max_bit_depth: u8,
+    pub output_format: SurfaceFormat,
+    pub decode_fps: f64, // Frames per second sustained at 1920x1080
+}
+
+impl DecodeCapability {
+    pub fn supports(&self, stream: &StreamParameters) -> bool {
+        self.codec == stream.codec
+            && stream.width <= self.max_width
+            && stream.height <= self.max_height
+            && stream.bit_depth <= self.max_bit_depth
+            && stream.bit_depth <= self.output_format.bit_depth()
+    }
+
+    /// Frames per second expected for the stream, scaling the reference rate by pixel count
+    pub fn expected_fps(&self, stream: &StreamParameters) -> f64 {
+        let pixels = (stream.width * stream.height).max(1) as f64;
+        self.decode_fps * REFERENCE_PIXELS / pixels
+    }
+}
+
+/// A way to decode an input stream, ranked by `DecodeBackends::input_paths`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputPath {
+    pub api: DecodeApi,
+    pub device: String,
+    pub output_format: SurfaceFormat,
+    pub expected_fps: f64,
+    pub realtime: bool, // Decodes at least as fast as the stream plays
+}
+
+impl InputPath {
+    fn new(capability: &DecodeCapability, stream: &StreamParameters) -> Self {
+        let expected_fps = capability.expected_fps(stream);
+        Self {
+            api: capability.api,
+            device: capability.device.clone(),
+            output_format: capability.output_format,
+            expected_fps,
+            realtime: expected_fps >= stream.frame_rate,
+        }
+    }
+}
+
+/// Compressed access unit in decode order
+#[derive(Debug, Clone)]
+pub struct CompressedPacket {
+    pub data: Vec<u8>,
+    pub pts: i64,
+    pub dts: i64,
+    pub keyframe: bool,
+}
+
+/// Decoded picture mapped from device memory
+#[derive(Debug, Clone)]
+pub struct DecodedSurface {
+    pub width: usize,
+    pub height: usize,
+    pub format: SurfaceFormat,
+    pub planes: Vec<Vec<u8>>,
+    pub pitches: Vec<usize>, // Bytes per row of each plane, at least the row's samples
+    pub pts: i64,
+    pub full_range: bool, // Samples span the full range rather than studio swing
+}
+
+impl DecodedSurface {
+    /// Normalises the surface to `VisualInput`.
+    ///
+    /// Chroma is upsampled to one sample per pixel, and Cb and Cr are folded
+    /// into the single chrominance channel as their mean.
+    pub fn to_visual_input(&self, frame_rate: f64, metadata: InputMetadata) -> Result<VisualInput, AfiyahError> {
+        self.validate()?;
+        let (width, height) = (self.width, self.height);
+        let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+
+        let luma = SampleReader::new(self, 0);
+        let luminance_data = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| luma.normalised(luma.sample(y, x), false))
+            .collect();
+
+        let (cb_plane, cr_plane) = (SampleReader::new(self, 1), SampleReader::new(self, self.planes.len() - 1));
+        let mut chroma = Vec::with_capacity(chroma_width * chroma_height);
+        for y in 0..chroma_height {
+            for x in 0..chroma_width {
+                let (cb, cr) = match self.format {
+                    SurfaceFormat::Nv12 | SurfaceFormat::P010 => (cb_plane.sample(y, 2 * x), cb_plane.sample(y, 2 * x + 1)),
+                    SurfaceFormat::I420 => (cb_plane.sample(y, x), cr_plane.sample(y, x)),
+                };
+                chroma.push((cb_plane.normalised(cb, true) + cr_plane.normalised(cr, true)) / 2.0);
+            }
+        }
+        let chrominance_data = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| chroma[(y / 2) * chroma_width + x / 2])
+            .collect();
+
+        Ok(VisualInput {
+            luminance_data,
+            chrominance_data,
+            spatial_resolution: (width, height),
+            temporal_resolution: frame_rate,
+            metadata,
+        })
+    }
+
+    fn validate(&self) -> Result<(), AfiyahError> {
+        let invalid = |message: String| Err(AfiyahError::HardwareAcceleration { message });
+        if self.width == 0 || self.height == 0 {
+            return invalid(format!("Decoded surface of {}x{} is empty", self.width, self.height));
+        }
+        let planes = self.format.plane_count();
+        if self.planes.len() != planes || self.pitches.len() != planes {
+            return invalid(format!(
+                "{:?} surfaces have {} planes, got {} planes and {} pitches",
+                self.format,
+                planes,
+                self.planes.len(),
+                self.pitches.len()
+            ));
+        }
+
+        let sample = self.format.bytes_per_sample();
+        let (chroma_width, chroma_height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        for (index, (plane, &pitch)) in self.planes.iter().zip(&self.pitches).enumerate() {
+            let (row_bytes, rows) = match (index, self.format) {
+                (0, _) => (self.width * sample, self.height),
+                (_, SurfaceFormat::Nv12 | SurfaceFormat::P010) => (2 * chroma_width * sample, chroma_height),
+                (_, SurfaceFormat::I420) => (chroma_width * sample, chroma_height),
+            };
+            if pitch < row_bytes || plane.len() < pitch * (rows - 1) + row_bytes {
+                return invalid(format!(
+                    "Plane {} of a {}x{} {:?} surface needs {} rows of {} bytes, got {} bytes with a pitch of {}",
+                    index,
+                    self.width,
+                    self.height,
+                    self.format,
+                    rows,
+                    row_bytes,
+                    plane.len(),
+                    pitch
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads samples of one validated plane
+struct SampleReader<'a> {
+    plane: &'a [u8],
+    pitch: usize,
+    format: SurfaceFormat,
+    full_range: bool,
+}
+
+impl<'a> SampleReader<'a> {
+    fn new(surface: &'a DecodedSurface, plane: usize) -> Self {
+        Self {
+            plane: &surface.planes[plane],
+            pitch: surface.pitches[plane],
+            format: surface.format,
+            full_range: surface.full_range,
+        }
+    }
+
+    fn sample(&self, row: usize, column: usize) -> u16 {
+        let offset = row * self.pitch;
+        match self.format {
+            SurfaceFormat::Nv12 | SurfaceFormat::I420 => u16::from(self.plane[offset + column]),
+            SurfaceFormat::P010 => {
+                let at = offset + 2 * column;
+                u16::from_le_bytes([self.plane[at], self.plane[at + 1]]) >> 6
+            }
+        }
+    }
+
+    /// Sample scaled to 0..1; studio swing is 16..235 for luma and 16..240 for chroma at 8 bits
+    fn normalised(&self, value: u16, chroma: bool) -> f64 {
+        let scale = f64::from(1u16 << (self.format.bit_depth() - 8));
+        let (low, span) = match (self.full_range, chroma) {
+            (true, _) => (0.0, 255.0 * scale + (scale - 1.0)),
+            (false, false) => (16.0 * scale, 219.0 * scale),
+            (false, true) => (16.0 * scale, 224.0 * scale),
+        };
+        ((f64::from(value) - low) / span).clamp(0.0, 1.0)
+    }
+}
+
+/// An open platform decoder.
+///
+/// Packets go in in decode order and surfaces come out in presentation
+/// order. `HardwareDecodeSource` takes every ready surface before sending
+/// another packet, so a decoder must accept a packet whenever none is ready.
+pub trait HardwareDecoder: Send {
+    fn capability(&self) -> &DecodeCapability;
+    fn send_packet(&mut self, packet: &CompressedPacket) -> Result<(), AfiyahError>;
+    /// Next surface in presentation order, or `None` until more packets are sent
+    fn receive_surface(&mut self) -> Result<Option<DecodedSurface>, AfiyahError>;
+    /// Ends the stream, making the surfaces still held for reordering receivable
+    fn drain(&mut self) -> Result<(), AfiyahError>;
+}
+
+/// Binding to one platform decoder API, such as a libva or NVDEC wrapper
+pub trait DecodeBackend: Send + Sync {
+    fn api(&self) -> DecodeApi;
+    /// Codecs and limits of every device the API can reach
+    fn probe(&self) -> Result<Vec<DecodeCapability>, AfiyahError>;
+    fn open(&self, stream: &StreamParameters, capability: &DecodeCapability) -> Result<Box<dyn HardwareDecoder>, AfiyahError>;
+}
+
+/// Registered decode backends and what their probes found
+pub struct DecodeBackends {
+    backends: Vec<Box<dyn DecodeBackend>>,
+    capabilities: Vec<(usize, DecodeCapability)>, // With the index of the backend that reported it
+}
+
+impl Default for DecodeBackends {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecodeBackends {
+    pub fn new() -> Self {
+        Self {
+            backends: Vec::new(),
+            capabilities: Vec::new(),
+        }
+    }
+
+    pub fn with_backend(mut self, backend: Box<dyn DecodeBackend>) -> Self {
+        self.register(backend);
+        self
+    }
+
+    /// Adds a backend; it is probed by the next `detect`
+    pub fn register(&mut self, backend: Box<dyn DecodeBackend>) {
+        self.backends.push(backend);
+    }
+
+    /// Probes every backend, replacing what earlier probes found. A backend
+    /// whose probe fails, such as VAAPI without a render node, offers nothing.
+    pub fn detect(&mut self) {
+        self.capabilities.clear();
+        for (index, backend) in self.backends.iter().enumerate() {
+            match backend.probe() {
+                Ok(capabilities) => self.capabilities.extend(capabilities.into_iter().map(|capability| (index, capability))),
+                Err(error) => log::warn!("{:?} decode backend unavailable: {}", backend.api(), error),
+            }
+        }
+    }
+
+    pub fn capabilities(&self) -> impl Iterator<Item = &DecodeCapability> {
+        self.capabilities.iter().map(|(_, capability)| capability)
+    }
+
+    /// Ways to decode the stream, fastest first
+    pub fn input_paths(&self, stream: &StreamParameters) -> Vec<InputPath> {
+        self.ranked(stream)
+            .into_iter()
+            .map(|(_, capability)| InputPath::new(capability, stream))
+            .collect()
+    }
+
+    /// The fastest way to decode the stream, or `None` if no backend supports it
+    pub fn choose_input_path(&self, stream: &StreamParameters) -> Option<InputPath> {
+        self.input_paths(stream).into_iter().next()
+    }
+
+    /// Opens a decoder on the fastest path that opens, trying the others in
+    /// turn when a device is busy or refuses the stream
+    pub fn open(&self, stream: &StreamParameters) -> Result<(InputPath, Box<dyn HardwareDecoder>), AfiyahError> {
+        let mut failures = Vec::new();
+        for (index, capability) in self.ranked(stream) {
+            match self.backends[index].open(stream, capability) {
+                Ok(decoder) => return Ok((InputPath::new(capability, stream), decoder)),
+                Err(error) => failures.push(format!("{:?} on {}: {}", capability.api, capability.device, error)),
+            }
+        }
+
+        let message = if failures.is_empty() {
+            format!(
+                "No decode backend supports {:?} at {}x{} and {} bits",
+                stream.codec, stream.width, stream.height, stream.bit_depth
+            )
+        } else {
+            format!("Every decoder for {:?} failed to open: {}", stream.codec, failures.join("; "))
+        };
+        Err(AfiyahError::HardwareAcceleration { message })
+    }
+
+    fn ranked(&self, stream: &StreamParameters) -> Vec<(usize, &DecodeCapability)> {
+        let mut ranked: Vec<_> = self
+            .capabilities
+            .iter()
+            .filter(|(_, capability)| capability.supports(stream))
+            .map(|(index, capability)| (*index, capability))
+            .collect();
+        ranked.sort_by(|(_, a), (_, b)| {
+            b.expected_fps(stream)
+                .total_cmp(&a.expected_fps(stream))
+                .then(a.api.cmp(&b.api))
+        });
+        ranked
+    }
+}
+
+/// Decoder APIs the host could provide, judged from its drivers, before any
+/// backend is registered or probed
+pub fn platform_decode_apis() -> Vec<DecodeApi> {
+    let mut apis = Vec::new();
+    if cfg!(any(target_os = "linux", target_os = "windows")) && nvidia_driver_present() {
+        apis.push(DecodeApi::Nvdec);
+    }
+    if cfg!(any(target_os = "macos", target_os = "ios")) {
+        apis.push(DecodeApi::VideoToolbox);
+    }
+    if cfg!(target_os = "linux") && render_node_present() {
+        apis.push(DecodeApi::Vaapi);
+    }
+    apis
+}
+
+fn nvidia_driver_present() -> bool {
+    if cfg!(target_os = "windows") {
+        return std::env::var_os("SystemRoot")
+            .map(|root| Path::new(&root).join("System32").join("nvcuvid.dll").exists())
+            .unwrap_or(false);
+    }
+    Path::new("/proc/driver/nvidia/version").exists()
+}
+
+fn render_node_present() -> bool {
+    std::fs::read_dir("/dev/dri")
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().starts_with("renderD"))
+        })
+        .unwrap_or(false)
+}
+
+/// Decode counters of a `HardwareDecodeSource`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DecodeStats {
+    pub packets_sent: u64,
+    pub frames_decoded: u64,
+    pub decode_time: Duration, // Spent in the decoder, excluding waits for packets
+}
+
+impl DecodeStats {
+    pub fn decode_fps(&self) -> f64 {
+        let seconds = self.decode_time.as_secs_f64();
+        if seconds > 0.0 {
+            self.frames_decoded as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Frames decoded by a platform decoder from a stream of compressed packets.
+///
+/// It is a `FrameSource`, so an `EncoderSession` encodes the frames as the
+/// decoder produces them. Decoder calls run on the polling task; platform
+/// decoders return quickly, and the session encodes on the rayon pool.
+pub struct HardwareDecodeSource<P> {
+    packets: P,
+    decoder: Box<dyn HardwareDecoder>,
+    frame_rate: f64,
+    metadata: InputMetadata,
+    drained: bool,
+    stats: DecodeStats,
+}
+
+impl<P> HardwareDecodeSource<P>
+where
+    P: Stream<Item = Result<CompressedPacket, AfiyahError>> + Unpin + Send,
+{
+    pub fn new(packets: P, decoder: Box<dyn HardwareDecoder>, stream: &StreamParameters, metadata: InputMetadata) -> Self {
+        Self {
+            packets,
+            decoder,
+            frame_rate: stream.frame_rate,
+            metadata,
+            drained: false,
+            stats: DecodeStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &DecodeStats {
+        &self.stats
+    }
+
+    pub fn capability(&self) -> &DecodeCapability {
+        self.decoder.capability()
+    }
+
+    fn timed<T>(&mut self, decode: impl FnOnce(&mut dyn HardwareDecoder) -> Result<T, AfiyahError>) -> Result<T, AfiyahError> {
+        let start = Instant::now();
+        let result = decode(self.decoder.as_mut());
+        self.stats.decode_time += start.elapsed();
+        result
+    }
+}
+
+impl<P> FrameSource for HardwareDecodeSource<P>
+where
+    P: Stream<Item = Result<CompressedPacket, AfiyahError>> + Unpin + Send,
+{
+    async fn next_frame(&mut self) -> Result<Option<VisualInput>, AfiyahError> {
+        loop {
+            if let Some(surface) = self.timed(|decoder| decoder.receive_surface())? {
+                self.stats.frames_decoded += 1;
+                return surface.to_visual_input(self.frame_rate, self.metadata.clone()).map(Some);
+            }
+            if self.drained {
+                return Ok(None);
+            }
+            match self.packets.next().await {
+                Some(packet) => {
+                    let packet = packet?;
+                    self.timed(|decoder| decoder.send_packet(&packet))?;
+                    self.stats.packets_sent += 1;
+                }
+                None => {
+                    self.timed(|decoder| decoder.drain())?;
+                    self.drained = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::performance_optimization::encoder_session::{EncoderSession, EncoderSessionConfig};
+    use crate::performance_optimization::gop_parallel::{GopBudget, GopEncoder};
+    use futures::executor::block_on;
+    use futures::stream;
+    use std::collections::BTreeMap;
+
+    const WIDTH: usize = 4;
+    const HEIGHT: usize = 2;
+
+    /// Decodes each packet to a flat NV12 surface whose luma is the packet's
+    /// first byte, holding two surfaces back as B-frame reordering does
+    struct FakeDecoder {
+        capability: DecodeCapability,
+        held: BTreeMap<i64, u8>,
+        draining: bool,
+    }
+
+    impl HardwareDecoder for FakeDecoder {
+        fn capability(&self) -> &DecodeCapability {
+            &self.capability
+        }
+
+        fn send_packet(&mut self, packet: &CompressedPacket) -> Result<(), AfiyahError> {
+            self.held.insert(packet.pts, packet.data[0]);
+            Ok(())
+        }
+
+        fn receive_surface(&mut self) -> Result<Option<DecodedSurface>, AfiyahError> {
+            if self.held.len() <= 2 && !self.draining {
+                return Ok(None);
+            }
+            Ok(self.held.pop_first().map(|(pts, luma)| DecodedSurface {
+                width: WIDTH,
+                height: HEIGHT,
+                format: SurfaceFormat::Nv12,
+                planes: vec![vec![luma; WIDTH * HEIGHT], vec![128; WIDTH]],
+                pitches: vec![WIDTH, WIDTH],
+                pts,
+                full_range: true,
+            }))
+        }
+
+        fn drain(&mut self) -> Result<(), AfiyahError> {
+            self.draining = true;
+            Ok(())
+        }
+    }
+
+    struct FakeBackend {
+        api: DecodeApi,
+        capabilities: Vec<DecodeCapability>,
+        refuses: bool,
+    }
+
+    impl DecodeBackend for FakeBackend {
+        fn api(&self) -> DecodeApi {
+            self.api
+        }
+
+        fn probe(&self) -> Result<Vec<DecodeCapability>, AfiyahError> {
+            Ok(self.capabilities.clone())
+        }
+
+        fn open(&self, _stream: &StreamParameters, capability: &DecodeCapability) -> Result<Box<dyn HardwareDecoder>, AfiyahError> {
+            if self.refuses {
+                return Err(AfiyahError::HardwareAcceleration {
+                    message: "device busy".to_string(),
+                });
+            }
+            Ok(Box::new(FakeDecoder {
+                capability: capability.clone(),
+                held: BTreeMap::new(),
+                draining: false,
+            }))
+        }
+    }
+
+    fn capability(api: DecodeApi, codec: CompressedCodec, max_bit_depth: u8, decode_fps: f64) -> DecodeCapability {
+        DecodeCapability {
+            api,
+            device: format!("{:?}0", api),
+            codec,
+            max_width: 4096,
+            max_height: 2304,
+            max_bit_depth,
+            output_format: if max_bit_depth > 8 { SurfaceFormat::P010 } else { SurfaceFormat::Nv12 },
+            decode_fps,
+        }
+    }
+
+    fn backend(api: DecodeApi, capabilities: Vec<DecodeCapability>, refuses: bool) -> Box<dyn DecodeBackend> {
+        Box::new(FakeBackend { api, capabilities, refuses })
+    }
+
+    fn stream_parameters(codec: CompressedCodec, width: usize, height: usize, bit_depth: u8) -> StreamParameters {
+        StreamParameters {
+            codec,
+            width,
+            height,
+            bit_depth,
+            frame_rate: 60.0,
+        }
+    }
+
+    fn metadata() -> InputMetadata {
+        InputMetadata {
+            viewing_distance: 1.0,
+            ambient_lighting: 100.0,
+            viewer_age: 30,
+            color_temperature: 6500.0,
+        }
+    }
+
+    #[test]
+    fn test_ranks_input_paths_by_expected_speed() {
+        let mut backends = DecodeBackends::new()
+            .with_backend(backend(
+                DecodeApi::Nvdec,
+                vec![
+                    capability(DecodeApi::Nvdec, CompressedCodec::H264, 8, 400.0),
+                    capability(DecodeApi::Nvdec, CompressedCodec::Hevc, 10, 500.0),
+                ],
+                false,
+            ))
+            .with_backend(backend(DecodeApi::Vaapi, vec![capability(DecodeApi::Vaapi, CompressedCodec::H264, 8, 600.0)], false))
+            .with_backend(backend(
+                DecodeApi::Software,
+                vec![
+                    capability(DecodeApi::Software, CompressedCodec::H264, 8, 90.0),
+                    capability(DecodeApi::Software, CompressedCodec::Hevc, 8, 60.0),
+                ],
+                false,
+            ));
+        backends.detect();
+        assert_eq!(backends.capabilities().count(), 5);
+
+        let h264 = backends.input_paths(&stream_parameters(CompressedCodec::H264, 1920, 1080, 8));
+        let apis: Vec<_> = h264.iter().map(|path| path.api).collect();
+        assert_eq!(apis, vec![DecodeApi::Vaapi, DecodeApi::Nvdec, DecodeApi::Software]);
+        assert!(h264.iter().all(|path| path.realtime));
+
+        // Only NVDEC decodes 10-bit HEVC, at a quarter of its 1080p rate for 4K
+        let hevc = backends.input_paths(&stream_parameters(CompressedCodec::Hevc, 3840, 2160, 10));
+        assert_eq!(hevc.len(), 1);
+        assert_eq!(hevc[0].output_format, SurfaceFormat::P010);
+        assert!((hevc[0].expected_fps - 125.0).abs() < 1e-9);
+
+        // A 10-bit decoder also takes 8-bit streams, and 4K software decoding is far slower
+        let hevc_8bit = backends.choose_input_path(&stream_parameters(CompressedCodec::Hevc, 3840, 2160, 8)).unwrap();
+        assert_eq!(hevc_8bit.api, DecodeApi::Nvdec);
+        assert!(backends.choose_input_path(&stream_parameters(CompressedCodec::H264, 7680, 4320, 8)).is_none());
+    }
+
+    #[test]
+    fn test_open_falls_back_when_a_device_refuses() {
+        let mut backends = DecodeBackends::new()
+            .with_backend(backend(DecodeApi::Nvdec, vec![capability(DecodeApi::Nvdec, CompressedCodec::H264, 8, 400.0)], true))
+            .with_backend(backend(DecodeApi::Vaapi, vec![capability(DecodeApi::Vaapi, CompressedCodec::H264, 8, 300.0)], false));
+        backends.detect();
+
+        let (path, decoder) = backends.open(&stream_parameters(CompressedCodec::H264, 1920, 1080, 8)).unwrap();
+        assert_eq!(path.api, DecodeApi::Vaapi);
+        assert_eq!(decoder.capability().api, DecodeApi::Vaapi);
+
+        let mut refused = DecodeBackends::new().with_backend(backend(
+            DecodeApi::Nvdec,
+            vec![capability(DecodeApi::Nvdec, CompressedCodec::H264, 8, 400.0)],
+            true,
+        ));
+        refused.detect();
+        match refused.open(&stream_parameters(CompressedCodec::H264, 1920, 1080, 8)) {
+            Err(AfiyahError::HardwareAcceleration { message }) => assert!(message.contains("device busy"), "{}", message),
+            other => panic!("expected a hardware error, got {:?}", other.map(|(path, _)| path)),
+        }
+    }
+
+    #[test]
+    fn test_converts_decoded_surfaces() {
+        // Studio swing NV12 with padded rows: black, white and neutral chroma
+        let nv12 = DecodedSurface {
+            width: 3,
+            height: 2,
+            format: SurfaceFormat::Nv12,
+            planes: vec![vec![16, 235, 126, 0, 16, 235, 126, 0], vec![128, 128, 240, 240]],
+            pitches: vec![4, 4],
+            pts: 0,
+            full_range: false,
+        };
+        let frame = nv12.to_visual_input(30.0, metadata()).unwrap();
+        assert_eq!(frame.spatial_resolution, (3, 2));
+        assert_eq!(frame.luminance_data.len(), 6);
+        assert_eq!(frame.luminance_data[0], 0.0);
+        assert_eq!(frame.luminance_data[1], 1.0);
+        assert!((frame.luminance_data[2] - 110.0 / 219.0).abs() < 1e-12);
+        // The first chroma sample covers columns 0-1, the second column 2
+        assert!((frame.chrominance_data[0] - 112.0 / 224.0).abs() < 1e-12);
+        assert_eq!(frame.chrominance_data[1], frame.chrominance_data[0]);
+        assert_eq!(frame.chrominance_data[2], 1.0);
+
+        // Full range P010: 10-bit samples in the high bits
+        let sample = |value: u16| (value << 6).to_le_bytes();
+        let p010 = DecodedSurface {
+            width: 2,
+            height: 2,
+            format: SurfaceFormat::P010,
+            planes: vec![
+                [sample(0), sample(1023), sample(512), sample(1023)].concat(),
+                [sample(0), sample(1023)].concat(),
+            ],
+            pitches: vec![4, 4],
+            pts: 0,
+            full_range: true,
+        };
+        let frame = p010.to_visual_input(30.0, metadata()).unwrap();
+        assert_eq!(frame.luminance_data[..2], [0.0, 1.0]);
+        assert!((frame.luminance_data[2] - 512.0 / 1023.0).abs() < 1e-12);
+        assert!(frame.chrominance_data.iter().all(|&chroma| (chroma - 0.5).abs() < 1e-12));
+
+        let i420 = DecodedSurface {
+            width: 2,
+            height: 2,
+            format: SurfaceFormat::I420,
+            planes: vec![vec![255; 4], vec![0], vec![255]],
+            pitches: vec![2, 1, 1],
+            pts: 0,
+            full_range: true,
+        };
+        assert_eq!(i420.to_visual_input(30.0, metadata()).unwrap().chrominance_data, vec![0.5; 4]);
+
+        let truncated = DecodedSurface {
+            planes: vec![vec![0; 6], vec![128; 4]],
+            ..nv12
+        };
+        assert!(matches!(
+            truncated.to_visual_input(30.0, metadata()),
+            Err(AfiyahError::HardwareAcceleration { .. })
+        ));
+    }
+
+    /// Records the luma of every frame it encodes
+    struct RecordingEncoder {
+        seen: std::sync::Arc<std::sync::Mutex<Vec<f64>>>,
+    }
+
+    impl GopEncoder for RecordingEncoder {
+        fn encode_gop(&mut self, frames: &[VisualInput], _budget: &GopBudget) -> Result<Vec<u8>, AfiyahError> {
+            self.seen.lock().unwrap().extend(frames.iter().map(|frame| frame.luminance_data[0]));
+            Ok(vec![0; frames.len()])
+        }
+    }
+
+    #[test]
+    fn test_decoded_frames_feed_an_encoder_session() {
+        let mut backends = DecodeBackends::new().with_backend(backend(
+            DecodeApi::Vaapi,
+            vec![capability(DecodeApi::Vaapi, CompressedCodec::H264, 8, 300.0)],
+            false,
+        ));
+        backends.detect();
+        let parameters = stream_parameters(CompressedCodec::H264, WIDTH, HEIGHT, 8);
+        let (_, decoder) = backends.open(&parameters).unwrap();
+
+        // Decode order I P B B P B: presentation order is by pts
+        let order = [(0, 0), (3, 3), (1, 1), (2, 2), (5, 5), (4, 4)];
+        let packets = stream::iter(order.map(|(pts, luma)| {
+            Ok(CompressedPacket {
+                data: vec![luma * 50],
+                pts,
+                dts: pts,
+                keyframe: pts == 0,
+            })
+        }));
+        let source = HardwareDecodeSource::new(packets, decoder, &parameters, metadata());
+
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = EncoderSessionConfig {
+            gop_size: 4,
+            ..Default::default()
+        };
+        let mut session = EncoderSession::new(source, RecordingEncoder { seen: seen.clone() }, config).unwrap();
+        let mut frames = 0;
+        while let Some(chunk) = block_on(session.next_chunk()).unwrap() {
+            frames += chunk.gop.frame_count;
+        }
+
+        assert_eq!(frames, 6);
+        let expected: Vec<f64> = (0..6).map(|luma| f64::from(luma * 50) / 255.0).collect();
+        assert_eq!(*seen.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_decode_source_reports_stats_and_packet_errors() {
+        let decoder = Box::new(FakeDecoder {
+            capability: capability(DecodeApi::Nvdec, CompressedCodec::Hevc, 10, 500.0),
+            held: BTreeMap::new(),
+            draining: false,
+        });
+        let packets = stream::iter(vec![
+            Ok(CompressedPacket { data: vec![10], pts: 0, dts: 0, keyframe: true }),
+            Ok(CompressedPacket { data: vec![20], pts: 1, dts: 1, keyframe: false }),
+            Err(AfiyahError::InputError { message: "truncated packet".to_string() }),
+        ]);
+        let parameters = stream_parameters(CompressedCodec::Hevc, WIDTH, HEIGHT, 10);
+        let mut source = HardwareDecodeSource::new(packets, decoder, &parameters, metadata());
+
+        assert!(matches!(block_on(source.next_frame()), Err(AfiyahError::InputError { .. })));
+        assert_eq!(source.stats().packets_sent, 2);
+        assert_eq!(source.stats().frames_decoded, 0);
+        assert_eq!(source.capability().api, DecodeApi::Nvdec);
+    }
+}
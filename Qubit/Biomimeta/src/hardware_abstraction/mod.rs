@@ -50,6 +50,8 @@ use std::sync::Arc;
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 
+pub mod hardware_decode;
+
 /// Main hardware abstraction layer
 pub struct HardwareAbstractionLayer {
     device_manager: DeviceManager,
@@ -0,0 +1,403 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Adaptation Experiments Module
+//!
+//! Live A/B experiments over the bitrate adaptation algorithm. Sessions are
+//! assigned to a variant by deterministic hashing of the experiment and session
+//! identifiers, so a viewer keeps the same variant across reconnects and across
+//! every edge node that serves them. Per-variant QoE samples are aggregated so
+//! the default algorithm can be chosen from observed data.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::AfiyahError;
+
+/// Adaptation algorithm variants that can be compared in an experiment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AdaptationVariant {
+    /// Pure quality-ratio stepping without perceptual weighting
+    Linear,
+    /// Foveal/peripheral/motion weighted biological factor
+    Biological,
+    /// Equal blend of the linear and biological factors
+    Hybrid,
+}
+
+impl AdaptationVariant {
+    /// Bitrate scaling factor applied on top of the quality-ratio step
+    pub fn bitrate_factor(&self, biological_factor: f64) -> f64 {
+        match self {
+            AdaptationVariant::Linear => 1.0,
+            AdaptationVariant::Biological => biological_factor,
+            AdaptationVariant::Hybrid => (1.0 + biological_factor) / 2.0,
+        }
+    }
+}
+
+/// Experiment configuration with weighted variant allocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentConfig {
+    pub experiment_id: String,
+    pub variants: Vec<(AdaptationVariant, u32)>,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        Self {
+            experiment_id: "adaptation_default".to_string(),
+            variants: vec![
+                (AdaptationVariant::Linear, 1),
+                (AdaptationVariant::Biological, 1),
+                (AdaptationVariant::Hybrid, 1),
+            ],
+        }
+    }
+}
+
+/// A single QoE observation reported for a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QoeSample {
+    pub perceptual_quality: f64,  // Overall perceptual quality (0.0-1.0)
+    pub bitrate: u32,             // Delivered bitrate in bps
+    pub rebuffer_ms: u64,         // Stall time since the previous sample
+    pub quality_switch: bool,     // Whether the rendition changed
+}
+
+/// Aggregated QoE metrics for one variant
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VariantMetrics {
+    pub sessions: u64,
+    pub samples: u64,
+    pub total_quality: f64,
+    pub total_bitrate: u64,
+    pub total_rebuffer_ms: u64,
+    pub quality_switches: u64,
+}
+
+impl VariantMetrics {
+    pub fn record(&mut self, sample: &QoeSample) {
+        self.samples += 1;
+        self.total_quality += sample.perceptual_quality;
+        self.total_bitrate += sample.bitrate as u64;
+        self.total_rebuffer_ms += sample.rebuffer_ms;
+        if sample.quality_switch {
+            self.quality_switches += 1;
+        }
+    }
+
+    /// Mean perceptual quality across all samples
+    pub fn average_quality(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.total_quality / self.samples as f64
+    }
+
+    /// Mean delivered bitrate across all samples
+    pub fn average_bitrate(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.total_bitrate as f64 / self.samples as f64
+    }
+
+    /// Fraction of samples that switched rendition
+    pub fn switch_rate(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.quality_switches as f64 / self.samples as f64
+    }
+
+    /// Composite QoE score: quality penalised by stalls and rendition churn
+    pub fn qoe_score(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        let rebuffer_per_sample = self.total_rebuffer_ms as f64 / self.samples as f64;
+        let rebuffer_penalty = (rebuffer_per_sample / 1000.0).min(1.0) * 0.5;
+        let switch_penalty = self.switch_rate() * 0.2;
+        (self.average_quality() - rebuffer_penalty - switch_penalty).max(0.0)
+    }
+}
+
+/// Per-variant summary exposed through the telemetry API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantSummary {
+    pub variant: AdaptationVariant,
+    pub sessions: u64,
+    pub samples: u64,
+    pub average_quality: f64,
+    pub average_bitrate: f64,
+    pub total_rebuffer_ms: u64,
+    pub switch_rate: f64,
+    pub qoe_score: f64,
+}
+
+/// Experiment results snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentResults {
+    pub experiment_id: String,
+    pub variants: Vec<VariantSummary>,
+}
+
+impl ExperimentResults {
+    /// Variant with the highest QoE score among those that received samples
+    pub fn leading_variant(&self) -> Option<AdaptationVariant> {
+        self.variants
+            .iter()
+            .filter(|v| v.samples > 0)
+            .max_by(|a, b| a.qoe_score.partial_cmp(&b.qoe_score).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|v| v.variant)
+    }
+}
+
+/// Live adaptation experiment tracking assignments and per-variant metrics
+pub struct AdaptationExperiment {
+    config: ExperimentConfig,
+    total_weight: u64,
+    assignments: HashMap<String, AdaptationVariant>,
+    metrics: HashMap<AdaptationVariant, VariantMetrics>,
+}
+
+impl AdaptationExperiment {
+    /// Creates a new experiment, validating the variant allocation
+    pub fn new(config: ExperimentConfig) -> Result<Self, AfiyahError> {
+        if config.variants.is_empty() {
+            return Err(AfiyahError::Configuration {
+                message: "Experiment requires at least one variant".to_string(),
+            });
+        }
+
+        let total_weight: u64 = config.variants.iter().map(|(_, w)| *w as u64).sum();
+        if total_weight == 0 {
+            return Err(AfiyahError::Configuration {
+                message: "Experiment variant weights must not all be zero".to_string(),
+            });
+        }
+
+        let metrics = config
+            .variants
+            .iter()
+            .map(|(variant, _)| (*variant, VariantMetrics::default()))
+            .collect();
+
+        Ok(Self {
+            config,
+            total_weight,
+            assignments: HashMap::new(),
+            metrics,
+        })
+    }
+
+    /// Deterministically selects a variant for a session without recording it
+    pub fn variant_for(&self, session_id: &str) -> AdaptationVariant {
+        let bucket = stable_hash(&self.config.experiment_id, session_id) % self.total_weight;
+
+        let mut cumulative = 0u64;
+        for (variant, weight) in &self.config.variants {
+            cumulative += *weight as u64;
+            if bucket < cumulative {
+                return *variant;
+            }
+        }
+
+        // Unreachable while total_weight is the sum of the weights
+        self.config.variants[0].0
+    }
+
+    /// Assigns a session to its variant, counting it once per experiment
+    pub fn assign(&mut self, session_id: &str) -> AdaptationVariant {
+        if let Some(variant) = self.assignments.get(session_id) {
+            return *variant;
+        }
+
+        let variant = self.variant_for(session_id);
+        self.assignments.insert(session_id.to_string(), variant);
+        self.metrics.entry(variant).or_default().sessions += 1;
+        variant
+    }
+
+    /// Records a QoE sample against the session's assigned variant
+    pub fn record(&mut self, session_id: &str, sample: &QoeSample) -> Result<(), AfiyahError> {
+        let variant = *self.assignments.get(session_id).ok_or_else(|| AfiyahError::Streaming {
+            message: format!("Session {} is not enrolled in experiment {}", session_id, self.config.experiment_id),
+        })?;
+        self.metrics.entry(variant).or_default().record(sample);
+        Ok(())
+    }
+
+    /// Returns the assigned variant for a session, if enrolled
+    pub fn assignment(&self, session_id: &str) -> Option<AdaptationVariant> {
+        self.assignments.get(session_id).copied()
+    }
+
+    /// Builds a results snapshot in configuration order
+    pub fn results(&self) -> ExperimentResults {
+        let variants = self
+            .config
+            .variants
+            .iter()
+            .map(|(variant, _)| {
+                let metrics = self.metrics.get(variant).cloned().unwrap_or_default();
+                VariantSummary {
+                    variant: *variant,
+                    sessions: metrics.sessions,
+                    samples: metrics.samples,
+                    average_quality: metrics.average_quality(),
+                    average_bitrate: metrics.average_bitrate(),
+                    total_rebuffer_ms: metrics.total_rebuffer_ms,
+                    switch_rate: metrics.switch_rate(),
+                    qoe_score: metrics.qoe_score(),
+                }
+            })
+            .collect();
+
+        ExperimentResults {
+            experiment_id: self.config.experiment_id.clone(),
+            variants,
+        }
+    }
+
+    /// Gets the experiment configuration
+    pub fn get_config(&self) -> &ExperimentConfig {
+        &self.config
+    }
+}
+
+/// FNV-1a over experiment and session identifiers.
+///
+/// `DefaultHasher` is not stable across Rust releases, which would reshuffle
+/// assignments whenever the edge fleet is rebuilt.
+fn stable_hash(experiment_id: &str, session_id: &str) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let mut hash = FNV_OFFSET;
+    for byte in experiment_id.bytes().chain(std::iter::once(0u8)).chain(session_id.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(quality: f64, rebuffer_ms: u64) -> QoeSample {
+        QoeSample {
+            perceptual_quality: quality,
+            bitrate: 1_000_000,
+            rebuffer_ms,
+            quality_switch: false,
+        }
+    }
+
+    #[test]
+    fn test_assignment_is_deterministic() {
+        let first = AdaptationExperiment::new(ExperimentConfig::default()).unwrap();
+        let second = AdaptationExperiment::new(ExperimentConfig::default()).unwrap();
+
+        for i in 0..100 {
+            let session = format!("session_{}", i);
+            assert_eq!(first.variant_for(&session), second.variant_for(&session));
+        }
+    }
+
+    #[test]
+    fn test_assignment_covers_all_variants() {
+        let mut experiment = AdaptationExperiment::new(ExperimentConfig::default()).unwrap();
+        for i in 0..300 {
+            experiment.assign(&format!("session_{}", i));
+        }
+
+        let results = experiment.results();
+        assert_eq!(results.variants.len(), 3);
+        assert!(results.variants.iter().all(|v| v.sessions > 0));
+        assert_eq!(results.variants.iter().map(|v| v.sessions).sum::<u64>(), 300);
+    }
+
+    #[test]
+    fn test_zero_weight_variant_is_never_assigned() {
+        let config = ExperimentConfig {
+            experiment_id: "holdout".to_string(),
+            variants: vec![(AdaptationVariant::Linear, 0), (AdaptationVariant::Hybrid, 1)],
+        };
+        let experiment = AdaptationExperiment::new(config).unwrap();
+        for i in 0..50 {
+            assert_eq!(experiment.variant_for(&format!("s{}", i)), AdaptationVariant::Hybrid);
+        }
+    }
+
+    #[test]
+    fn test_invalid_config_rejected() {
+        let config = ExperimentConfig {
+            experiment_id: "empty".to_string(),
+            variants: Vec::new(),
+        };
+        assert!(AdaptationExperiment::new(config).is_err());
+    }
+
+    #[test]
+    fn test_record_requires_enrollment() {
+        let mut experiment = AdaptationExperiment::new(ExperimentConfig::default()).unwrap();
+        assert!(experiment.record("unknown", &sample(0.9, 0)).is_err());
+
+        experiment.assign("known");
+        assert!(experiment.record("known", &sample(0.9, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_leading_variant_penalises_rebuffering() {
+        let config = ExperimentConfig {
+            experiment_id: "qoe".to_string(),
+            variants: vec![(AdaptationVariant::Linear, 1)],
+        };
+        let mut experiment = AdaptationExperiment::new(config).unwrap();
+        experiment.assign("a");
+        experiment.record("a", &sample(0.9, 2000)).unwrap();
+
+        let results = experiment.results();
+        assert_eq!(results.leading_variant(), Some(AdaptationVariant::Linear));
+        assert!(results.variants[0].qoe_score < 0.9);
+    }
+
+    #[test]
+    fn test_variant_bitrate_factor() {
+        assert_eq!(AdaptationVariant::Linear.bitrate_factor(1.4), 1.0);
+        assert_eq!(AdaptationVariant::Biological.bitrate_factor(1.4), 1.4);
+        assert!((AdaptationVariant::Hybrid.bitrate_factor(1.4) - 1.2).abs() < 1e-9);
+    }
+}
@@ -288,6 +288,14 @@ impl AdaptiveBitrateController {
         Ok(())
     }
 
+    /// Updates the playback buffer level reported for a session
+    pub fn update_buffer_level(&mut self, session_id: &str, buffer_level: Duration) -> Result<(), AfiyahError> {
+        if let Some(session) = self.sessions.get_mut(session_id) {
+            session.buffer_level = buffer_level;
+        }
+        Ok(())
+    }
+
     /// Determines optimal quality level for current conditions
     pub fn determine_optimal_quality(&mut self, session_id: &str) -> Result<String, AfiyahError> {
         let session = self.sessions.get(session_id)
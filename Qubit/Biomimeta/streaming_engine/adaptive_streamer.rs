@@ -2,6 +2,9 @@
 
 use ndarray::Array2;
 use crate::AfiyahError;
+use crate::streaming_engine::adaptation_experiments::{
+    AdaptationExperiment, AdaptationVariant, ExperimentConfig, ExperimentResults, QoeSample,
+};
 
 /// Streaming configuration for adaptive streaming
 #[derive(Debug, Clone)]
@@ -75,6 +78,8 @@ pub struct AdaptiveStreamer {
     state: StreamingState,
     adaptation_history: Vec<f64>,
     quality_history: Vec<f64>,
+    experiment: Option<AdaptationExperiment>,
+    active_variant: Option<AdaptationVariant>,
 }
 
 impl AdaptiveStreamer {
@@ -90,6 +95,8 @@ impl AdaptiveStreamer {
             state,
             adaptation_history,
             quality_history,
+            experiment: None,
+            active_variant: None,
         })
     }
 
@@ -167,10 +174,9 @@ impl AdaptiveStreamer {
             new_bitrate *= 0.8;
         }
 
-        // Apply biological optimization if enabled
-        if self.config.biological_optimization {
-            new_bitrate *= self.calculate_biological_factor(quality_params)?;
-        }
+        // Apply the variant's perceptual weighting
+        let biological_factor = self.calculate_biological_factor(quality_params)?;
+        new_bitrate *= self.effective_variant().bitrate_factor(biological_factor);
 
         // Clamp to configured limits
         new_bitrate = new_bitrate.max(self.config.min_bitrate as f64).min(self.config.max_bitrate as f64);
@@ -189,6 +195,54 @@ impl AdaptiveStreamer {
         Ok(biological_factor.clamp(0.5, 1.5))
     }
 
+    /// Variant driving adaptation: the experiment assignment if one is active,
+    /// otherwise the configured biological optimization setting
+    pub fn effective_variant(&self) -> AdaptationVariant {
+        self.active_variant.unwrap_or(if self.config.biological_optimization {
+            AdaptationVariant::Biological
+        } else {
+            AdaptationVariant::Linear
+        })
+    }
+
+    /// Starts a live adaptation experiment, replacing any running one
+    pub fn start_experiment(&mut self, config: ExperimentConfig) -> Result<(), AfiyahError> {
+        self.experiment = Some(AdaptationExperiment::new(config)?);
+        self.active_variant = None;
+        Ok(())
+    }
+
+    /// Ends the running experiment and returns its final results
+    pub fn stop_experiment(&mut self) -> Option<ExperimentResults> {
+        self.active_variant = None;
+        self.experiment.take().map(|experiment| experiment.results())
+    }
+
+    /// Enrolls a session in the running experiment and adapts with its variant
+    pub fn assign_session(&mut self, session_id: &str) -> Result<AdaptationVariant, AfiyahError> {
+        let experiment = self.experiment.as_mut().ok_or_else(|| AfiyahError::Streaming {
+            message: "No adaptation experiment is running".to_string(),
+        })?;
+        let variant = experiment.assign(session_id);
+        self.active_variant = Some(variant);
+        Ok(variant)
+    }
+
+    /// Records a QoE sample for an enrolled session
+    pub fn record_session_qoe(&mut self, session_id: &str, sample: &QoeSample) -> Result<(), AfiyahError> {
+        match self.experiment.as_mut() {
+            Some(experiment) => experiment.record(session_id, sample),
+            None => Err(AfiyahError::Streaming {
+                message: "No adaptation experiment is running".to_string(),
+            }),
+        }
+    }
+
+    /// Gets results of the running experiment
+    pub fn experiment_results(&self) -> Option<ExperimentResults> {
+        self.experiment.as_ref().map(|experiment| experiment.results())
+    }
+
     /// Gets current streaming state
     pub fn get_state(&self) -> &StreamingState {
         &self.state
@@ -253,5 +307,29 @@ mod tests {
         let result = streamer.configure(config);
         assert!(result.is_ok());
         assert_eq!(streamer.get_config().target_bitrate, 2000000);
+        assert_eq!(streamer.effective_variant(), AdaptationVariant::Linear);
+    }
+
+    #[test]
+    fn test_experiment_session_assignment() {
+        let mut streamer = AdaptiveStreamer::new().unwrap();
+        assert!(streamer.assign_session("viewer_1").is_err());
+
+        streamer.start_experiment(ExperimentConfig::default()).unwrap();
+        let variant = streamer.assign_session("viewer_1").unwrap();
+        assert_eq!(streamer.effective_variant(), variant);
+        assert_eq!(streamer.assign_session("viewer_1").unwrap(), variant);
+
+        let sample = QoeSample {
+            perceptual_quality: 0.85,
+            bitrate: 1_500_000,
+            rebuffer_ms: 0,
+            quality_switch: false,
+        };
+        streamer.record_session_qoe("viewer_1", &sample).unwrap();
+
+        let results = streamer.stop_experiment().unwrap();
+        assert_eq!(results.leading_variant(), Some(variant));
+        assert!(streamer.experiment_results().is_none());
     }
 }
\ No newline at end of file
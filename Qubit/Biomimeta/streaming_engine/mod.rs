@@ -45,21 +45,25 @@ use crate::AfiyahError;
 
 // Re-export all sub-modules
 pub mod adaptive_streamer;
+pub mod adaptation_experiments;
 pub mod biological_qos;
 pub mod foveated_encoder;
 pub mod frame_scheduler;
 pub mod adaptive_bitrate_streaming;
 pub mod cdn_integration;
 pub mod intelligent_load_balancing;
+pub mod network_simulator;
 
 // Re-export the main types
 pub use adaptive_streamer::{AdaptiveStreamer, StreamingConfig, StreamingState};
+pub use adaptation_experiments::{AdaptationExperiment, AdaptationVariant, ExperimentConfig, ExperimentResults, QoeSample, VariantSummary};
 pub use biological_qos::{QoSManager, PerceptualQuality};
 pub use foveated_encoder::{FoveatedEncoder, FoveatedConfig, EncodingRegion};
 pub use frame_scheduler::{FrameScheduler, SchedulerConfig, FramePriority};
 pub use adaptive_bitrate_streaming::{AdaptiveBitrateController, AdaptiveStreamingConfig, QualityLevel, NetworkConditions, StreamingSession};
 pub use cdn_integration::{CDNManager, CDNConfig, CDNNode, GeographicLocation, CDNCapabilities, ContentRequest, CDNResponse};
 pub use intelligent_load_balancing::{IntelligentLoadBalancer, LoadBalancingConfig, ServerNode, ServerCapabilities, LoadBalancingRequest, LoadBalancingResponse};
+pub use network_simulator::{AdaptationAlgorithm, BufferRule, ControllerAlgorithm, NetworkPattern, NetworkSimulator, NetworkTrace, QoeReport, QoeThresholds, SimulationConfig, ThroughputRule, TracePoint};
 
 /// Main streaming engine that coordinates all streaming components
 pub struct StreamingEngine {
@@ -192,6 +196,26 @@ impl StreamingEngine {
         self.load_balancer.update_server_load(server_id, load)
     }
 
+    /// Starts an A/B experiment over the adaptation algorithm
+    pub fn start_adaptation_experiment(&mut self, config: ExperimentConfig) -> Result<(), AfiyahError> {
+        self.adaptive_streamer.start_experiment(config)
+    }
+
+    /// Enrolls a viewer session in the running adaptation experiment
+    pub fn enroll_experiment_session(&mut self, session_id: &str) -> Result<AdaptationVariant, AfiyahError> {
+        self.adaptive_streamer.assign_session(session_id)
+    }
+
+    /// Reports a QoE observation for an enrolled viewer session
+    pub fn report_session_qoe(&mut self, session_id: &str, sample: QoeSample) -> Result<(), AfiyahError> {
+        self.adaptive_streamer.record_session_qoe(session_id, &sample)
+    }
+
+    /// Gets per-variant experiment telemetry
+    pub fn get_experiment_results(&self) -> Option<ExperimentResults> {
+        self.adaptive_streamer.experiment_results()
+    }
+
     /// Gets streaming performance metrics
     pub fn get_performance_metrics(&self) -> Result<StreamingPerformanceMetrics, AfiyahError> {
         let adaptive_state = self.adaptive_bitrate_controller.get_state();
@@ -253,4 +277,22 @@ mod tests {
         
         let _encoded_frame = result.unwrap();
     }
+
+    #[test]
+    fn test_experiment_telemetry() {
+        let mut engine = StreamingEngine::new().unwrap();
+        assert!(engine.get_experiment_results().is_none());
+
+        engine.start_adaptation_experiment(ExperimentConfig::default()).unwrap();
+        engine.enroll_experiment_session("viewer").unwrap();
+        engine.report_session_qoe("viewer", QoeSample {
+            perceptual_quality: 0.9,
+            bitrate: 2_000_000,
+            rebuffer_ms: 0,
+            quality_switch: true,
+        }).unwrap();
+
+        let results = engine.get_experiment_results().unwrap();
+        assert_eq!(results.variants.iter().map(|v| v.samples).sum::<u64>(), 1);
+    }
 }
\ No newline at end of file
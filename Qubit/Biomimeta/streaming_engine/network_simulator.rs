@@ -0,0 +1,694 @@
+/* Biomimeta - Biomimetic Video Compression & Streaming Engine
+*  Copyright (C) 2025 Neo Qiss. All Rights Reserved.
+*
+*  PROPRIETARY NOTICE: This software and all associated intellectual property,
+*  including but not limited to algorithms, biological models, neural architectures,
+*  and compression methodologies, are the exclusive property of Neo Qiss.
+*
+*  COMMERCIAL RESTRICTION: Commercial use, distribution, or integration of this
+*  software is STRICTLY PROHIBITED without explicit written authorization and
+*  formal partnership agreements. Unauthorized commercial use constitutes
+*  copyright infringement and may result in legal action.
+*
+*  RESEARCH LICENSE: This software is made available under the Biological Research
+*  Public License (BRPL) v1.0 EXCLUSIVELY for academic research, educational purposes,
+*  and non-commercial scientific collaboration. Commercial entities must obtain
+*  separate licensing agreements.
+*
+*  BIOLOGICAL RESEARCH ATTRIBUTION: This software implements proprietary biological
+*  models derived from extensive neuroscientific research. All use must maintain
+*  complete scientific attribution as specified in the BRPL license terms.
+*
+*  NO WARRANTIES: This software is provided for research purposes only. No warranties
+*  are made regarding biological accuracy, medical safety, or fitness for any purpose.
+*
+*  For commercial licensing: commercial@biomimeta.com
+*  For research partnerships: research@biomimeta.com
+*  Legal inquiries: legal@biomimeta.com
+*
+*  VIOLATION OF THESE TERMS MAY RESULT IN IMMEDIATE LICENSE TERMINATION AND LEGAL ACTION.
+*/
+
+//! Network Simulator Module
+//!
+//! Replays bandwidth, latency and loss traces against a simulated player to
+//! compare adaptation algorithms offline. Traces are either recorded from real
+//! sessions or generated from synthetic patterns. Time is simulated, so a
+//! ten-minute session replays in milliseconds and every run of the same trace
+//! gives the same result, which lets the test suite fail on adaptation
+//! regressions before they reach viewers.
+//!
+//! Each segment becomes a `QoeSample`, scored exactly as live adaptation
+//! experiments score them, so offline and live results compare directly.
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::AfiyahError;
+use crate::streaming_engine::adaptation_experiments::{QoeSample, VariantMetrics};
+use crate::streaming_engine::adaptive_bitrate_streaming::{
+    AdaptiveBitrateController, AdaptiveStreamingConfig, NetworkConditions, QualityLevel,
+};
+
+/// Segments whose throughput feeds the player's estimate
+const THROUGHPUT_WINDOW: usize = 5;
+
+/// Network conditions from `at_ms` until the next point
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TracePoint {
+    pub at_ms: u64,               // Offset from the start of the trace
+    pub bandwidth_bps: f64,       // Link bandwidth in bps
+    pub latency_ms: u64,          // Round trip of a segment request
+    pub packet_loss: f64,         // Loss rate (0.0-1.0), taken off the bandwidth
+}
+
+impl TracePoint {
+    fn goodput(&self) -> f64 {
+        self.bandwidth_bps * (1.0 - self.packet_loss)
+    }
+
+    fn starts_at(&self) -> f64 {
+        self.at_ms as f64 / 1000.0
+    }
+}
+
+/// Synthetic bandwidth patterns for traces nobody has recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NetworkPattern {
+    Constant { bandwidth_bps: f64 },
+    /// Alternates between two bandwidths, starting high
+    Step { high_bps: f64, low_bps: f64, period_ms: u64 },
+    /// Loses the link entirely for `outage_ms` at the end of every period
+    Outage { bandwidth_bps: f64, period_ms: u64, outage_ms: u64 },
+    /// Wanders around a mean each second, the same way for the same seed
+    RandomWalk { mean_bps: f64, volatility: f64, seed: u64 },
+}
+
+/// A bandwidth, latency and loss timeline. The last point holds for the
+/// rest of a session that outlasts the trace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkTrace {
+    pub name: String,
+    pub points: Vec<TracePoint>,
+}
+
+impl NetworkTrace {
+    /// Creates a trace, checking the points start at zero, are in order and
+    /// end on a usable link
+    pub fn new(name: impl Into<String>, points: Vec<TracePoint>) -> Result<Self, AfiyahError> {
+        let name = name.into();
+        let invalid = |message: String| AfiyahError::InputError { message: format!("Trace {}: {}", name, message) };
+
+        match points.first() {
+            None => return Err(invalid("has no points".to_string())),
+            Some(first) if first.at_ms != 0 => return Err(invalid("must start at 0 ms".to_string())),
+            Some(_) => {}
+        }
+        if let Some(pair) = points.windows(2).find(|pair| pair[1].at_ms <= pair[0].at_ms) {
+            return Err(invalid(format!("point at {} ms is out of order", pair[1].at_ms)));
+        }
+        if let Some(point) = points.iter().find(|point| {
+            !(point.bandwidth_bps >= 0.0 && point.bandwidth_bps.is_finite() && (0.0..1.0).contains(&point.packet_loss))
+        }) {
+            return Err(invalid(format!("point at {} ms has invalid bandwidth or loss", point.at_ms)));
+        }
+        if points[points.len() - 1].goodput() <= 0.0 {
+            return Err(invalid("must end with bandwidth available".to_string()));
+        }
+
+        Ok(Self { name, points })
+    }
+
+    /// Parses a recorded trace with one `time_ms,bandwidth_kbps,latency_ms,loss`
+    /// line per sample. Blank lines, `#` comments and a header are skipped.
+    pub fn parse(name: impl Into<String>, text: &str) -> Result<Self, AfiyahError> {
+        let name = name.into();
+        let mut points = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("time_ms") {
+                continue;
+            }
+            let invalid = || AfiyahError::InputError {
+                message: format!("Trace {} line {}: expected time_ms,bandwidth_kbps,latency_ms,loss", name, number + 1),
+            };
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != 4 {
+                return Err(invalid());
+            }
+            points.push(TracePoint {
+                at_ms: fields[0].parse().map_err(|_| invalid())?,
+                bandwidth_bps: fields[1].parse::<f64>().map_err(|_| invalid())? * 1000.0,
+                latency_ms: fields[2].parse().map_err(|_| invalid())?,
+                packet_loss: fields[3].parse().map_err(|_| invalid())?,
+            });
+        }
+
+        Self::new(name, points)
+    }
+
+    /// Generates `duration` of a pattern with 40 ms latency and no loss
+    pub fn synthetic(name: impl Into<String>, pattern: &NetworkPattern, duration: Duration) -> Result<Self, AfiyahError> {
+        let duration_ms = duration.as_millis().max(1) as u64;
+        let point = |at_ms, bandwidth_bps| TracePoint { at_ms, bandwidth_bps, latency_ms: 40, packet_loss: 0.0 };
+
+        let points = match *pattern {
+            NetworkPattern::Constant { bandwidth_bps } => vec![point(0, bandwidth_bps)],
+            NetworkPattern::Step { high_bps, low_bps, period_ms } => {
+                let period_ms = period_ms.max(1);
+                (0..duration_ms.div_ceil(period_ms))
+                    .map(|i| point(i * period_ms, if i % 2 == 0 { high_bps } else { low_bps }))
+                    .collect()
+            }
+            NetworkPattern::Outage { bandwidth_bps, period_ms, outage_ms } => {
+                let period_ms = period_ms.max(1);
+                let outage_ms = outage_ms.min(period_ms - 1);
+                (0..duration_ms.div_ceil(period_ms))
+                    .flat_map(|i| {
+                        let start = i * period_ms;
+                        let mut points = vec![point(start, bandwidth_bps)];
+                        if outage_ms > 0 {
+                            points.push(point(start + period_ms - outage_ms, 0.0));
+                        }
+                        points
+                    })
+                    .chain(std::iter::once(point(duration_ms.div_ceil(period_ms) * period_ms, bandwidth_bps)))
+                    .collect()
+            }
+            NetworkPattern::RandomWalk { mean_bps, volatility, seed } => {
+                let mut rng = XorShift(seed.max(1));
+                let mut bandwidth = mean_bps;
+                (0..duration_ms.div_ceil(1000))
+                    .map(|i| {
+                        // Step by up to `volatility` of the mean, pulled back toward it
+                        let step = (rng.next_f64() * 2.0 - 1.0) * volatility * mean_bps;
+                        bandwidth = (bandwidth + step + (mean_bps - bandwidth) * 0.1).max(mean_bps * 0.05);
+                        point(i * 1000, bandwidth)
+                    })
+                    .collect()
+            }
+        };
+
+        Self::new(name, points)
+    }
+
+    /// Sets the same latency and loss on every point
+    pub fn with_impairments(mut self, latency: Duration, packet_loss: f64) -> Self {
+        for point in &mut self.points {
+            point.latency_ms = latency.as_millis() as u64;
+            point.packet_loss = packet_loss.clamp(0.0, 0.99);
+        }
+        self
+    }
+
+    /// Conditions at `seconds` into the trace
+    pub fn at(&self, seconds: f64) -> &TracePoint {
+        &self.points[self.index_at(seconds)]
+    }
+
+    fn index_at(&self, seconds: f64) -> usize {
+        self.points.partition_point(|point| point.starts_at() <= seconds).saturating_sub(1)
+    }
+
+    /// Seconds to fetch `bits` with a request sent at `start`: one round trip,
+    /// then the bits at the goodput of each point the transfer spans
+    pub fn download_time(&self, start: f64, bits: f64) -> f64 {
+        let mut time = start + self.at(start).latency_ms as f64 / 1000.0;
+        let mut index = self.index_at(time);
+        let mut remaining = bits;
+
+        loop {
+            let goodput = self.points[index].goodput();
+            match self.points.get(index + 1).map(TracePoint::starts_at) {
+                Some(end) => {
+                    let available = goodput * (end - time);
+                    if goodput > 0.0 && available >= remaining {
+                        return time + remaining / goodput - start;
+                    }
+                    remaining -= available;
+                    time = end;
+                    index += 1;
+                }
+                // The last point always has goodput
+                None => return time + remaining / goodput - start,
+            }
+        }
+    }
+}
+
+/// What the player knows when it picks the next segment's rendition
+#[derive(Debug, Clone)]
+pub struct PlayerObservation {
+    pub time: Duration,
+    pub buffer_level: Duration,
+    /// Harmonic mean of recent segment throughputs; `None` before the first
+    pub throughput_bps: Option<f64>,
+    pub latency: Duration,
+    pub packet_loss: f64,
+    /// Ladder index of the previous segment
+    pub last_rendition: Option<usize>,
+}
+
+/// An adaptation algorithm the simulator can drive
+pub trait AdaptationAlgorithm {
+    /// Name the algorithm is reported under
+    fn name(&self) -> String;
+
+    /// Forgets state from an earlier run
+    fn reset(&mut self) -> Result<(), AfiyahError> {
+        Ok(())
+    }
+
+    /// Ladder index of the rendition for the next segment
+    fn select(&mut self, ladder: &[QualityLevel], observation: &PlayerObservation) -> Result<usize, AfiyahError>;
+}
+
+/// The production `AdaptiveBitrateController`, fed the player's measurements
+pub struct ControllerAlgorithm {
+    controller: AdaptiveBitrateController,
+}
+
+impl ControllerAlgorithm {
+    const SESSION: &'static str = "simulation";
+
+    pub fn new(config: AdaptiveStreamingConfig) -> Result<Self, AfiyahError> {
+        let mut controller = AdaptiveBitrateController::new(config)?;
+        controller.create_session(Self::SESSION.to_string())?;
+        Ok(Self { controller })
+    }
+}
+
+impl AdaptationAlgorithm for ControllerAlgorithm {
+    fn name(&self) -> String {
+        "controller".to_string()
+    }
+
+    fn reset(&mut self) -> Result<(), AfiyahError> {
+        self.controller.create_session(Self::SESSION.to_string())
+    }
+
+    fn select(&mut self, ladder: &[QualityLevel], observation: &PlayerObservation) -> Result<usize, AfiyahError> {
+        let conditions = NetworkConditions {
+            // Without a measurement, assume the lowest rendition fits
+            bandwidth: observation.throughput_bps.unwrap_or(ladder[0].bitrate as f64),
+            latency: observation.latency,
+            packet_loss: observation.packet_loss,
+            jitter: Duration::ZERO,
+            congestion_level: 0.0,
+            timestamp: SystemTime::now(),
+        };
+        self.controller.update_network_conditions(Self::SESSION, conditions)?;
+        self.controller.update_buffer_level(Self::SESSION, observation.buffer_level)?;
+
+        let id = self.controller.adapt_quality(Self::SESSION)?;
+        ladder.iter().position(|level| level.id == id).ok_or_else(|| AfiyahError::Streaming {
+            message: format!("Controller chose {}, which is not in the simulated ladder", id),
+        })
+    }
+}
+
+/// Baseline: the highest rendition under a share of the measured throughput
+pub struct ThroughputRule {
+    pub safety_factor: f64,
+}
+
+impl Default for ThroughputRule {
+    fn default() -> Self {
+        Self { safety_factor: 0.8 }
+    }
+}
+
+impl AdaptationAlgorithm for ThroughputRule {
+    fn name(&self) -> String {
+        "throughput".to_string()
+    }
+
+    fn select(&mut self, ladder: &[QualityLevel], observation: &PlayerObservation) -> Result<usize, AfiyahError> {
+        Ok(match observation.throughput_bps {
+            Some(throughput) => highest_under(ladder, throughput * self.safety_factor),
+            None => 0,
+        })
+    }
+}
+
+/// Baseline: buffer-based adaptation, lowest rendition below the reservoir,
+/// highest above reservoir plus cushion and linear in between
+pub struct BufferRule {
+    pub reservoir: Duration,
+    pub cushion: Duration,
+}
+
+impl Default for BufferRule {
+    fn default() -> Self {
+        Self {
+            reservoir: Duration::from_secs(5),
+            cushion: Duration::from_secs(15),
+        }
+    }
+}
+
+impl AdaptationAlgorithm for BufferRule {
+    fn name(&self) -> String {
+        "buffer".to_string()
+    }
+
+    fn select(&mut self, ladder: &[QualityLevel], observation: &PlayerObservation) -> Result<usize, AfiyahError> {
+        let above_reservoir = observation.buffer_level.saturating_sub(self.reservoir).as_secs_f64();
+        let position = (above_reservoir / self.cushion.as_secs_f64().max(f64::EPSILON)).min(1.0);
+        let lowest = ladder[0].bitrate as f64;
+        let highest = ladder[ladder.len() - 1].bitrate as f64;
+        Ok(highest_under(ladder, lowest + (highest - lowest) * position))
+    }
+}
+
+/// Index of the highest-bitrate rendition at or under `bitrate`, or the lowest
+fn highest_under(ladder: &[QualityLevel], bitrate: f64) -> usize {
+    ladder
+        .iter()
+        .enumerate()
+        .filter(|(_, level)| level.bitrate as f64 <= bitrate)
+        .max_by_key(|(_, level)| level.bitrate)
+        .map_or(0, |(index, _)| index)
+}
+
+/// Simulated player and content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    /// Renditions, which must match the ladder of the algorithms under test
+    pub quality_levels: Vec<QualityLevel>,
+    pub segment_duration: Duration,
+    pub session_duration: Duration,
+    /// Buffer needed before playback starts
+    pub startup_buffer: Duration,
+    /// The player stops fetching when another segment would not fit
+    pub max_buffer: Duration,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        let streaming = AdaptiveStreamingConfig::default();
+        Self {
+            quality_levels: streaming.quality_levels,
+            segment_duration: Duration::from_secs(2),
+            session_duration: Duration::from_secs(600),
+            startup_buffer: Duration::from_secs(4),
+            max_buffer: streaming.buffer_target,
+        }
+    }
+}
+
+/// QoE of one algorithm over one trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QoeReport {
+    pub algorithm: String,
+    pub trace: String,
+    pub segments: u64,
+    pub startup_delay_ms: u64,
+    /// Stalls after playback started
+    pub rebuffer_events: u64,
+    pub total_rebuffer_ms: u64,
+    /// Share of the session after startup spent stalled
+    pub rebuffer_ratio: f64,
+    pub average_quality: f64,
+    pub average_bitrate: f64,
+    pub switch_rate: f64,
+    /// The score live experiments report for a variant
+    pub qoe_score: f64,
+    /// Segments fetched at each rendition, in ladder order
+    pub renditions: Vec<(String, u64)>,
+}
+
+/// Limits a report must stay within
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QoeThresholds {
+    pub min_qoe_score: f64,
+    pub max_rebuffer_ratio: f64,
+    pub max_startup_delay: Duration,
+    pub max_switch_rate: f64,
+}
+
+impl Default for QoeThresholds {
+    fn default() -> Self {
+        Self {
+            min_qoe_score: 0.7,
+            max_rebuffer_ratio: 0.02,
+            max_startup_delay: Duration::from_secs(5),
+            max_switch_rate: 0.2,
+        }
+    }
+}
+
+impl QoeReport {
+    /// Fails with every threshold the report breaks
+    pub fn check(&self, thresholds: &QoeThresholds) -> Result<(), AfiyahError> {
+        let mut violations = Vec::new();
+        if self.qoe_score < thresholds.min_qoe_score {
+            violations.push(format!("QoE score {:.3} < {:.3}", self.qoe_score, thresholds.min_qoe_score));
+        }
+        if self.rebuffer_ratio > thresholds.max_rebuffer_ratio {
+            violations.push(format!("rebuffer ratio {:.3} > {:.3}", self.rebuffer_ratio, thresholds.max_rebuffer_ratio));
+        }
+        if self.startup_delay_ms > thresholds.max_startup_delay.as_millis() as u64 {
+            violations.push(format!(
+                "startup delay {} ms > {} ms",
+                self.startup_delay_ms,
+                thresholds.max_startup_delay.as_millis()
+            ));
+        }
+        if self.switch_rate > thresholds.max_switch_rate {
+            violations.push(format!("switch rate {:.3} > {:.3}", self.switch_rate, thresholds.max_switch_rate));
+        }
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(AfiyahError::Streaming {
+            message: format!("{} on {}: {}", self.algorithm, self.trace, violations.join(", ")),
+        })
+    }
+}
+
+/// Replays traces against a simulated player
+pub struct NetworkSimulator {
+    config: SimulationConfig,
+}
+
+impl NetworkSimulator {
+    /// Creates a new simulator, validating the player configuration
+    pub fn new(config: SimulationConfig) -> Result<Self, AfiyahError> {
+        if config.quality_levels.is_empty() {
+            return Err(AfiyahError::Configuration {
+                message: "Simulation requires at least one quality level".to_string(),
+            });
+        }
+        if config.segment_duration.is_zero() || config.max_buffer < config.segment_duration {
+            return Err(AfiyahError::Configuration {
+                message: "Segments must be non-empty and fit in the maximum buffer".to_string(),
+            });
+        }
+        Ok(Self { config })
+    }
+
+    /// Plays a session over `trace`, with `algorithm` picking each segment's rendition
+    pub fn run(&self, trace: &NetworkTrace, algorithm: &mut dyn AdaptationAlgorithm) -> Result<QoeReport, AfiyahError> {
+        algorithm.reset()?;
+        let ladder = &self.config.quality_levels;
+        let segment = self.config.segment_duration.as_secs_f64();
+        let max_buffer = self.config.max_buffer.as_secs_f64();
+        let startup_buffer = self.config.startup_buffer.as_secs_f64().min(self.config.session_duration.as_secs_f64());
+        let segments = (self.config.session_duration.as_secs_f64() / segment).ceil().max(1.0) as u64;
+
+        let mut time = 0.0;
+        let mut buffer = 0.0;
+        let mut startup_delay = None;
+        let mut throughputs = VecDeque::with_capacity(THROUGHPUT_WINDOW);
+        let mut last_rendition = None;
+        let mut renditions = vec![0u64; ladder.len()];
+        let mut rebuffer_events = 0;
+        let mut total_rebuffer = 0.0;
+        let mut metrics = VariantMetrics { sessions: 1, ..VariantMetrics::default() };
+
+        for _ in 0..segments {
+            // Wait, playing, until the next segment fits in the buffer
+            let excess = buffer + segment - max_buffer;
+            if excess > 0.0 {
+                time += excess;
+                buffer -= excess;
+            }
+
+            let conditions = trace.at(time);
+            let observation = PlayerObservation {
+                time: Duration::from_secs_f64(time),
+                buffer_level: Duration::from_secs_f64(buffer),
+                throughput_bps: harmonic_mean(&throughputs),
+                latency: Duration::from_millis(conditions.latency_ms),
+                packet_loss: conditions.packet_loss,
+                last_rendition,
+            };
+            let index = algorithm.select(ladder, &observation)?;
+            let level = ladder.get(index).ok_or_else(|| AfiyahError::Streaming {
+                message: format!("{} chose rendition {} of {}", algorithm.name(), index, ladder.len()),
+            })?;
+
+            let bits = level.bitrate as f64 * segment;
+            let elapsed = trace.download_time(time, bits);
+            time += elapsed;
+            if throughputs.len() == THROUGHPUT_WINDOW {
+                throughputs.pop_front();
+            }
+            throughputs.push_back(bits / elapsed);
+
+            let mut stall = 0.0;
+            if startup_delay.is_some() {
+                if elapsed > buffer {
+                    stall = elapsed - buffer;
+                    rebuffer_events += 1;
+                    total_rebuffer += stall;
+                }
+                buffer = (buffer - elapsed).max(0.0);
+            }
+            buffer += segment;
+            if startup_delay.is_none() && buffer >= startup_buffer {
+                startup_delay = Some(time);
+            }
+
+            metrics.record(&QoeSample {
+                perceptual_quality: level.quality_score,
+                bitrate: level.bitrate,
+                rebuffer_ms: (stall * 1000.0) as u64,
+                quality_switch: last_rendition.is_some_and(|last| last != index),
+            });
+            renditions[index] += 1;
+            last_rendition = Some(index);
+        }
+
+        let played = segments as f64 * segment;
+        Ok(QoeReport {
+            algorithm: algorithm.name(),
+            trace: trace.name.clone(),
+            segments,
+            startup_delay_ms: (startup_delay.unwrap_or(time) * 1000.0) as u64,
+            rebuffer_events,
+            total_rebuffer_ms: (total_rebuffer * 1000.0) as u64,
+            rebuffer_ratio: total_rebuffer / (played + total_rebuffer),
+            average_quality: metrics.average_quality(),
+            average_bitrate: metrics.average_bitrate(),
+            switch_rate: metrics.switch_rate(),
+            qoe_score: metrics.qoe_score(),
+            renditions: ladder.iter().map(|level| level.id.clone()).zip(renditions).collect(),
+        })
+    }
+
+    /// Runs every algorithm over the same trace
+    pub fn compare(
+        &self,
+        trace: &NetworkTrace,
+        algorithms: &mut [Box<dyn AdaptationAlgorithm>],
+    ) -> Result<Vec<QoeReport>, AfiyahError> {
+        algorithms.iter_mut().map(|algorithm| self.run(trace, algorithm.as_mut())).collect()
+    }
+
+    /// Gets the simulation configuration
+    pub fn get_config(&self) -> &SimulationConfig {
+        &self.config
+    }
+}
+
+fn harmonic_mean(values: &VecDeque<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.len() as f64 / values.iter().map(|value| 1.0 / value.max(1.0)).sum::<f64>())
+}
+
+/// xorshift64, so synthetic traces stay the same across `rand` releases
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant(bandwidth_bps: f64) -> NetworkTrace {
+        NetworkTrace::synthetic("constant", &NetworkPattern::Constant { bandwidth_bps }, Duration::from_secs(60)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_recorded_trace() {
+        let trace = NetworkTrace::parse("wifi", "# recorded\ntime_ms,bandwidth_kbps,latency_ms,loss\n0,2000,30,0.0\n\n1000,500,80,0.02\n").unwrap();
+        assert_eq!(trace.points.len(), 2);
+        assert_eq!(trace.at(1.5).bandwidth_bps, 500_000.0);
+        assert_eq!(trace.at(0.5).latency_ms, 30);
+
+        assert!(NetworkTrace::parse("bad", "0,2000,30").is_err());
+        assert!(NetworkTrace::parse("late", "500,2000,30,0").is_err());
+        assert!(NetworkTrace::parse("dead", "0,2000,30,0\n1000,0,30,0").is_err());
+    }
+
+    #[test]
+    fn test_download_spans_an_outage() {
+        let pattern = NetworkPattern::Outage { bandwidth_bps: 1_000_000.0, period_ms: 2000, outage_ms: 1000 };
+        let trace = NetworkTrace::synthetic("outage", &pattern, Duration::from_secs(10)).unwrap().with_impairments(Duration::ZERO, 0.0);
+
+        // 1 Mb of the 1.5 Mb arrives before the outage, the rest after it
+        assert!((trace.download_time(0.0, 1_500_000.0) - 2.5).abs() < 1e-9);
+        assert!((trace.download_time(0.5, 500_000.0) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_random_walk_is_reproducible() {
+        let pattern = NetworkPattern::RandomWalk { mean_bps: 3_000_000.0, volatility: 0.3, seed: 7 };
+        let first = NetworkTrace::synthetic("walk", &pattern, Duration::from_secs(30)).unwrap();
+        let second = NetworkTrace::synthetic("walk", &pattern, Duration::from_secs(30)).unwrap();
+        assert_eq!(first.points, second.points);
+        assert_eq!(first.points.len(), 30);
+        assert!(first.points.iter().all(|point| point.bandwidth_bps > 0.0));
+    }
+
+    #[test]
+    fn test_ample_bandwidth_plays_without_stalls() {
+        let simulator = NetworkSimulator::new(SimulationConfig::default()).unwrap();
+        let report = simulator.run(&constant(40_000_000.0), &mut ThroughputRule::default()).unwrap();
+
+        assert_eq!(report.rebuffer_events, 0);
+        assert!(report.startup_delay_ms < 1000);
+        // Playback starts on the lowest rendition and climbs once throughput is measured
+        assert_eq!(report.renditions[0], ("240p".to_string(), 1));
+        assert!(report.renditions[5].1 > report.segments * 9 / 10);
+    }
+
+    #[test]
+    fn test_starved_link_rebuffers_and_fails_thresholds() {
+        let simulator = NetworkSimulator::new(SimulationConfig::default()).unwrap();
+        let report = simulator.run(&constant(150_000.0), &mut ThroughputRule::default()).unwrap();
+
+        assert!(report.rebuffer_events > 0);
+        assert!(report.check(&QoeThresholds::default()).is_err());
+    }
+
+    #[test]
+    fn test_compare_reports_each_algorithm() {
+        let simulator = NetworkSimulator::new(SimulationConfig::default()).unwrap();
+        let mut algorithms: Vec<Box<dyn AdaptationAlgorithm>> = vec![
+            Box::new(ControllerAlgorithm::new(AdaptiveStreamingConfig::default()).unwrap()),
+            Box::new(ThroughputRule::default()),
+            Box::new(BufferRule::default()),
+        ];
+
+        let reports = simulator.compare(&constant(3_000_000.0), &mut algorithms).unwrap();
+        let names: Vec<&str> = reports.iter().map(|report| report.algorithm.as_str()).collect();
+        assert_eq!(names, ["controller", "throughput", "buffer"]);
+        assert!(reports.iter().all(|report| report.segments == 300));
+    }
+}
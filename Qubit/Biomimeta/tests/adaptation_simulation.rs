@@ -0,0 +1,93 @@
+//! Adaptation regressions over network traces
+//!
+//! The production controller replays every trace under `tests/traces` and a
+//! set of synthetic patterns. It must stay within the default QoE thresholds
+//! and score no worse than the plain throughput rule. Add a trace here when a
+//! network condition has caused a poor session in the field.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use afiyah::streaming_engine::adaptive_bitrate_streaming::AdaptiveStreamingConfig;
+use afiyah::streaming_engine::network_simulator::{
+    AdaptationAlgorithm, BufferRule, ControllerAlgorithm, NetworkPattern, NetworkSimulator, NetworkTrace, QoeThresholds,
+    SimulationConfig, ThroughputRule,
+};
+
+/// How far the controller's QoE score may fall below the throughput rule's
+const BASELINE_TOLERANCE: f64 = 0.01;
+
+fn recorded_traces() -> Vec<NetworkTrace> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/traces");
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("reading {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "csv"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "{} has no traces", dir.display());
+    files
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            NetworkTrace::parse(name, &fs::read_to_string(&path).unwrap()).unwrap()
+        })
+        .collect()
+}
+
+fn synthetic_traces() -> Vec<NetworkTrace> {
+    let duration = Duration::from_secs(600);
+    [
+        ("constant_3mbps", NetworkPattern::Constant { bandwidth_bps: 3_000_000.0 }),
+        ("step_6_to_1mbps", NetworkPattern::Step { high_bps: 6_000_000.0, low_bps: 1_200_000.0, period_ms: 30_000 }),
+        ("outage_8s", NetworkPattern::Outage { bandwidth_bps: 4_000_000.0, period_ms: 60_000, outage_ms: 8_000 }),
+        ("random_walk", NetworkPattern::RandomWalk { mean_bps: 3_000_000.0, volatility: 0.3, seed: 42 }),
+    ]
+    .iter()
+    .map(|(name, pattern)| NetworkTrace::synthetic(*name, pattern, duration).unwrap())
+    .collect()
+}
+
+fn algorithms() -> Vec<Box<dyn AdaptationAlgorithm>> {
+    vec![
+        Box::new(ControllerAlgorithm::new(AdaptiveStreamingConfig::default()).unwrap()),
+        Box::new(ThroughputRule::default()),
+        Box::new(BufferRule::default()),
+    ]
+}
+
+#[test]
+fn controller_meets_qoe_thresholds() {
+    let simulator = NetworkSimulator::new(SimulationConfig::default()).unwrap();
+    let thresholds = QoeThresholds::default();
+    let mut algorithms = algorithms();
+
+    for trace in recorded_traces().into_iter().chain(synthetic_traces()) {
+        let reports = simulator.compare(&trace, &mut algorithms).unwrap();
+        let (controller, throughput) = (&reports[0], &reports[1]);
+
+        if let Err(e) = controller.check(&thresholds) {
+            panic!("{}", e);
+        }
+        assert!(
+            controller.qoe_score + BASELINE_TOLERANCE >= throughput.qoe_score,
+            "controller scored {:.3} on {}, below the throughput rule's {:.3}",
+            controller.qoe_score,
+            trace.name,
+            throughput.qoe_score
+        );
+    }
+}
+
+#[test]
+fn replays_are_deterministic() {
+    let simulator = NetworkSimulator::new(SimulationConfig::default()).unwrap();
+    let trace = &synthetic_traces()[3];
+    let mut controller = ControllerAlgorithm::new(AdaptiveStreamingConfig::default()).unwrap();
+
+    let first = simulator.run(trace, &mut controller).unwrap();
+    let second = simulator.run(trace, &mut controller).unwrap();
+    assert_eq!(first.renditions, second.renditions);
+    assert_eq!(first.total_rebuffer_ms, second.total_rebuffer_ms);
+}
@@ -0,0 +1,40 @@
+//! Regression corpus of malformed bitstreams
+//!
+//! Every file under `tests/corpus` must be rejected with an error; none may
+//! panic. Add inputs found by `cargo fuzz` here once they are fixed.
+
+use std::fs;
+use std::path::Path;
+
+use afiyah::bitstream_formatting::stream_metadata::{read_metadata, rewrite_metadata};
+use afiyah::bitstream_formatting::{BiologicalBitstreamFormatter, BitstreamConfig};
+
+fn corpus(name: &str) -> Vec<(String, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus").join(name);
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("reading {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "{} has no corpus files", dir.display());
+    files
+        .into_iter()
+        .map(|path| (path.file_name().unwrap().to_string_lossy().into_owned(), fs::read(&path).unwrap()))
+        .collect()
+}
+
+#[test]
+fn malformed_bitstreams_are_rejected() {
+    let formatter = BiologicalBitstreamFormatter::new(BitstreamConfig::default()).unwrap();
+    for (name, bytes) in corpus("bitstream") {
+        assert!(formatter.parse_bitstream(&bytes).is_err(), "{} was accepted", name);
+    }
+}
+
+#[test]
+fn malformed_streams_are_rejected() {
+    for (name, bytes) in corpus("stream") {
+        let result = read_metadata(&bytes).and_then(|metadata| rewrite_metadata(&bytes, metadata, Vec::new()));
+        assert!(result.is_err(), "{} was accepted", name);
+    }
+}
@@ -56,6 +56,8 @@ use num_complex::Complex64;
 use std::f64::consts::PI;
 use anyhow::{Result, anyhow};
 
+use crate::performance_optimization::frame_buffer_pool::{pooled_array2, SharedFrameBufferPool};
+
 /// Biological transform coding engine
 pub struct BiologicalTransformCoder {
     orientation_filters: OrientationSelectiveFilters,
@@ -63,6 +65,7 @@ pub struct BiologicalTransformCoder {
     adaptive_selector: AdaptiveTransformSelector,
     frequency_analyzer: BiologicalFrequencyAnalyzer,
     config: TransformCodingConfig,
+    buffer_pool: Option<SharedFrameBufferPool>,
 }
 
 /// Orientation-selective filters based on V1 simple cells
@@ -250,9 +253,22 @@ impl BiologicalTransformCoder {
             adaptive_selector,
             frequency_analyzer,
             config,
+            buffer_pool: None,
         })
     }
 
+    /// Draws coefficient buffers from a shared frame buffer pool
+    pub fn set_buffer_pool(&mut self, pool: SharedFrameBufferPool) {
+        self.buffer_pool = Some(pool);
+    }
+
+    /// Returns a transform output's coefficient buffer to the pool
+    pub fn recycle(&self, output: TransformOutput) {
+        if let Some(pool) = &self.buffer_pool {
+            pool.release_array2(output.coefficients);
+        }
+    }
+
     /// Transform image data using biological transforms
     pub fn transform(&mut self, image_data: &Array2<f64>) -> Result<TransformOutput> {
         // Step 1: Analyze visual content
@@ -287,13 +303,15 @@ impl BiologicalTransformCoder {
         let frequency_analysis = self.frequency_analyzer.analyze_frequencies(&transform_coefficients)?;
 
         // Step 5: Create transform output
+        let biological_accuracy = self.calculate_biological_accuracy(&transform_coefficients)?;
+        let compression_potential = self.calculate_compression_potential(&transform_coefficients)?;
         let output = TransformOutput {
-            coefficients: transform_coefficients.clone(),
+            coefficients: transform_coefficients,
             transform_type: selected_transform,
             content_analysis,
             frequency_analysis,
-            biological_accuracy: self.calculate_biological_accuracy(&transform_coefficients)?,
-            compression_potential: self.calculate_compression_potential(&transform_coefficients)?,
+            biological_accuracy,
+            compression_potential,
         };
 
         Ok(output)
@@ -326,7 +344,7 @@ impl BiologicalTransformCoder {
     /// Apply biological DCT transform
     fn apply_biological_dct(&self, image_data: &Array2<f64>) -> Result<Array2<f64>> {
         let (height, width) = image_data.dim();
-        let mut dct_coeffs = Array2::zeros((height, width));
+        let mut dct_coeffs = pooled_array2(self.buffer_pool.as_deref(), (height, width));
         
         // Apply 2D DCT with biological frequency weighting
         for u in 0..height {
@@ -352,7 +370,7 @@ impl BiologicalTransformCoder {
     /// Apply inverse biological DCT transform
     fn apply_inverse_biological_dct(&self, coefficients: &Array2<f64>) -> Result<Array2<f64>> {
         let (height, width) = coefficients.dim();
-        let mut image_data = Array2::zeros((height, width));
+        let mut image_data = pooled_array2(self.buffer_pool.as_deref(), (height, width));
         
         // Apply inverse 2D DCT with biological frequency weighting
         for x in 0..height {
@@ -379,7 +397,7 @@ impl BiologicalTransformCoder {
     /// Apply Gabor transform
     fn apply_gabor_transform(&self, image_data: &Array2<f64>) -> Result<Array2<f64>> {
         let (height, width) = image_data.dim();
-        let mut gabor_coeffs = Array2::zeros((height, width));
+        let mut gabor_coeffs = pooled_array2(self.buffer_pool.as_deref(), (height, width));
         
         // Apply Gabor filters with biological orientation tuning
         for orientation in &self.orientation_filters.orientations {
@@ -409,7 +427,7 @@ impl BiologicalTransformCoder {
     /// Apply cortical Fourier transform
     fn apply_cortical_fourier(&self, image_data: &Array2<f64>) -> Result<Array2<f64>> {
         let (height, width) = image_data.dim();
-        let mut fourier_coeffs = Array2::zeros((height, width));
+        let mut fourier_coeffs = pooled_array2(self.buffer_pool.as_deref(), (height, width));
         
         // Apply 2D FFT with biological frequency weighting
         for u in 0..height {
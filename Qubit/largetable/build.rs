@@ -0,0 +1,21 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Generates the gRPC server and client from `proto/largetable.proto`, and
+//! the C header of the embedding API from `src/ffi/c_bindings`
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::compile_protos("proto/largetable.proto")?;
+
+    println!("cargo:rerun-if-changed=src/ffi/c_bindings/mod.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_config(cbindgen::Config::from_file("cbindgen.toml")?)
+        .with_src("src/ffi/c_bindings/mod.rs")
+        .generate()?
+        .write_to_file("include/largetable.h");
+    Ok(())
+}
@@ -277,6 +277,11 @@ async fn test_indexing() -> Result<(), Box<dyn std::error::Error>> {
     }).await?;
     println!("✅ Created Time-series index on 'timestamp'");
     
+    // Indexes build in the background; wait for them before listing
+    while collection.list_index_builds().iter().any(|build| !build.phase.is_finished()) {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    
     // List all indexes
     let indexes = collection.list_indexes().await?;
     println!("✅ Total indexes created: {}", indexes.len());
@@ -0,0 +1,218 @@
+/* ===========================================
+ * Largetable - Next-Generation NoSQL Database
+ * (c) 2025 Neo Qiss. All Rights Reserved.
+ * Built to outperform MongoDB with Rust's power.
+ * ===========================================
+ *
+ * Generated by cbindgen from src/ffi/c_bindings; do not edit. */
+
+#ifndef LARGETABLE_H
+#define LARGETABLE_H
+
+#include <stddef.h>
+
+/**
+ * Result of a call
+ */
+typedef enum LtStatus {
+  LT_STATUS_OK = 0,
+  /**
+   * No document has the given id
+   */
+  LT_STATUS_NOT_FOUND = 1,
+  /**
+   * The cursor has no more documents
+   */
+  LT_STATUS_DONE = 2,
+  /**
+   * A null pointer, invalid UTF-8, malformed JSON or an unknown option
+   */
+  LT_STATUS_INVALID_ARGUMENT = 3,
+  /**
+   * A document could not be converted to or from JSON
+   */
+  LT_STATUS_SERIALIZATION = 4,
+  /**
+   * A document was rejected by its collection's validators
+   */
+  LT_STATUS_VALIDATION = 5,
+  /**
+   * A query or index failed
+   */
+  LT_STATUS_QUERY = 6,
+  LT_STATUS_PERMISSION_DENIED = 7,
+  /**
+   * The operation ran past its deadline or was cancelled
+   */
+  LT_STATUS_DEADLINE_EXCEEDED = 8,
+  LT_STATUS_RESOURCE_EXHAUSTED = 9,
+  /**
+   * The storage engine, or a replica or shard it depends on, failed
+   */
+  LT_STATUS_STORAGE = 10,
+  /**
+   * A bug in Largetable; the message says where
+   */
+  LT_STATUS_INTERNAL = 11,
+} LtStatus;
+
+/**
+ * Results of a query, read one document at a time
+ */
+typedef struct LtCursor LtCursor;
+
+/**
+ * An embedded database engine
+ */
+typedef struct LtDatabase LtDatabase;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Version of the library, e.g. "1.0.0"; a static string the caller must not free
+ */
+const char *largetable_version(void);
+
+/**
+ * Message of the last failed call on this thread, or NULL if none failed
+ *
+ * The string belongs to the library and is valid until the next failure on
+ * this thread.
+ */
+const char *largetable_last_error(void);
+
+/**
+ * Release a string returned by the library; NULL is ignored
+ *
+ * # Safety
+ * `string` must be NULL or a string handed out through an out-parameter of
+ * this library, not yet freed.
+ */
+void largetable_string_free(char *string);
+
+/**
+ * Open an embedded engine on `storage_engine` ("lsm", "btree", "columnar" or
+ * "graph"; NULL for "lsm")
+ *
+ * # Safety
+ * `storage_engine` must be NULL or a NUL-terminated string, and `out` a
+ * valid pointer to write the handle to.
+ */
+enum LtStatus largetable_open(const char *storage_engine, struct LtDatabase **out);
+
+/**
+ * Close an engine opened by `largetable_open`; NULL is ignored
+ *
+ * # Safety
+ * `db` must be NULL or a handle from `largetable_open` that is not used
+ * afterwards, by this or any other thread.
+ */
+void largetable_close(struct LtDatabase *db);
+
+/**
+ * Insert a JSON document, writing its id to `id_out` unless it is NULL
+ *
+ * # Safety
+ * `db` must be a live handle, the strings NUL-terminated, and `id_out` NULL
+ * or a valid pointer.
+ */
+enum LtStatus largetable_insert(const struct LtDatabase *db,
+                                const char *database,
+                                const char *collection,
+                                const char *document,
+                                char **id_out);
+
+/**
+ * Find a document by id, writing it as JSON to `document_out`
+ *
+ * Returns `LT_STATUS_NOT_FOUND` if there is no such document.
+ *
+ * # Safety
+ * `db` must be a live handle, the strings NUL-terminated, and
+ * `document_out` a valid pointer.
+ */
+enum LtStatus largetable_find_by_id(const struct LtDatabase *db,
+                                    const char *database,
+                                    const char *collection,
+                                    const char *id,
+                                    char **document_out);
+
+/**
+ * Replace the document with `id` by a JSON document
+ *
+ * Returns `LT_STATUS_NOT_FOUND` if there is no such document.
+ *
+ * # Safety
+ * `db` must be a live handle and the strings NUL-terminated.
+ */
+enum LtStatus largetable_update_by_id(const struct LtDatabase *db,
+                                      const char *database,
+                                      const char *collection,
+                                      const char *id,
+                                      const char *document);
+
+/**
+ * Delete the document with `id`
+ *
+ * Returns `LT_STATUS_NOT_FOUND` if there is no such document.
+ *
+ * # Safety
+ * `db` must be a live handle and the strings NUL-terminated.
+ */
+enum LtStatus largetable_delete_by_id(const struct LtDatabase *db,
+                                      const char *database,
+                                      const char *collection,
+                                      const char *id);
+
+/**
+ * Run a JSON query, writing a cursor over its results to `cursor_out`
+ *
+ * A NULL query matches every document. The query runs to completion before
+ * this returns, so iterating the cursor does not touch the database.
+ *
+ * # Safety
+ * `db` must be a live handle, the strings NUL-terminated or, for `query`,
+ * NULL, and `cursor_out` a valid pointer.
+ */
+enum LtStatus largetable_query(const struct LtDatabase *db,
+                               const char *database,
+                               const char *collection,
+                               const char *query,
+                               struct LtCursor **cursor_out);
+
+/**
+ * Write the next document of a cursor as JSON to `document_out`
+ *
+ * Returns `LT_STATUS_DONE`, leaving `document_out` untouched, once every
+ * document has been read.
+ *
+ * # Safety
+ * `cursor` must be a live cursor not used by another thread, and
+ * `document_out` a valid pointer.
+ */
+enum LtStatus largetable_cursor_next(struct LtCursor *cursor, char **document_out);
+
+/**
+ * Number of documents matching the query, before its skip and limit
+ *
+ * # Safety
+ * `cursor` must be NULL, which counts 0, or a live cursor.
+ */
+size_t largetable_cursor_total_count(const struct LtCursor *cursor);
+
+/**
+ * Release a cursor; NULL is ignored
+ *
+ * # Safety
+ * `cursor` must be NULL or a cursor from `largetable_query` that is not
+ * used afterwards.
+ */
+void largetable_cursor_close(struct LtCursor *cursor);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LARGETABLE_H */
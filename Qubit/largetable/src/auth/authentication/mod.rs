@@ -5,3 +5,173 @@
 // ===========================================
 
 //! Authentication strategies
+
+pub mod scram;
+
+pub use scram::{ScramClient, ScramConversation, ScramCredentials, DEFAULT_ITERATIONS};
+
+use crate::auth::rbac::{Principal, RoleGrant};
+use crate::{LargetableError, Result};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::info;
+
+/// A database user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub name: String,
+    pub roles: Vec<RoleGrant>,
+    pub credentials: ScramCredentials,
+}
+
+impl User {
+    pub fn principal(&self) -> Principal {
+        Principal::user(self.name.clone(), self.roles.clone())
+    }
+}
+
+/// A user as reported to administrators, without credentials
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub name: String,
+    pub roles: Vec<RoleGrant>,
+}
+
+/// Users and their credentials, optionally persisted to a JSON file
+pub struct UserStore {
+    path: Option<PathBuf>,
+    iterations: u32,
+    users: RwLock<BTreeMap<String, User>>,
+}
+
+impl UserStore {
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            iterations: DEFAULT_ITERATIONS,
+            users: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Store backed by a users file, created empty if it does not exist yet
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let users: BTreeMap<String, User> = if path.exists() {
+            serde_json::from_slice(&std::fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        info!("Loaded {} users from {}", users.len(), path.display());
+
+        Ok(Self {
+            path: Some(path),
+            users: RwLock::new(users),
+            ..Self::in_memory()
+        })
+    }
+
+    /// PBKDF2 iterations for passwords set from now on
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.read().is_empty()
+    }
+
+    pub fn credentials(&self, name: &str) -> Option<ScramCredentials> {
+        self.users.read().get(name).map(|user| user.credentials.clone())
+    }
+
+    pub fn principal(&self, name: &str) -> Option<Principal> {
+        self.users.read().get(name).map(User::principal)
+    }
+
+    pub fn list_users(&self) -> Vec<UserInfo> {
+        self.users
+            .read()
+            .values()
+            .map(|user| UserInfo { name: user.name.clone(), roles: user.roles.clone() })
+            .collect()
+    }
+
+    pub fn create_user(&self, name: &str, password: &str, roles: Vec<RoleGrant>) -> Result<()> {
+        if name.is_empty() || password.is_empty() {
+            return Err(LargetableError::Query("User name and password cannot be empty".to_string()));
+        }
+        let credentials = ScramCredentials::new(password, self.iterations)?;
+
+        let mut users = self.users.write();
+        if users.contains_key(name) {
+            return Err(LargetableError::Query(format!("User '{}' already exists", name)));
+        }
+        users.insert(name.to_string(), User { name: name.to_string(), roles, credentials });
+        self.persist(&users)?;
+        info!("Created user '{}'", name);
+        Ok(())
+    }
+
+    /// Replace a user's roles; open sessions pick up the change on their next request
+    pub fn set_roles(&self, name: &str, roles: Vec<RoleGrant>) -> Result<bool> {
+        let mut users = self.users.write();
+        let Some(user) = users.get_mut(name) else {
+            return Ok(false);
+        };
+        user.roles = roles;
+        self.persist(&users)?;
+        Ok(true)
+    }
+
+    pub fn set_password(&self, name: &str, password: &str) -> Result<bool> {
+        let credentials = ScramCredentials::new(password, self.iterations)?;
+        let mut users = self.users.write();
+        let Some(user) = users.get_mut(name) else {
+            return Ok(false);
+        };
+        user.credentials = credentials;
+        self.persist(&users)?;
+        Ok(true)
+    }
+
+    pub fn drop_user(&self, name: &str) -> Result<bool> {
+        let mut users = self.users.write();
+        if users.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.persist(&users)?;
+        info!("Dropped user '{}'", name);
+        Ok(true)
+    }
+
+    fn persist(&self, users: &BTreeMap<String, User>) -> Result<()> {
+        if let Some(path) = &self.path {
+            std::fs::write(path, serde_json::to_vec_pretty(users)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::rbac::Role;
+
+    #[test]
+    fn test_user_store_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.json");
+
+        let store = UserStore::open(&path).unwrap().with_iterations(4096);
+        store.create_user("app", "secret", vec![RoleGrant::on(Role::ReadWrite, "shop")]).unwrap();
+        assert!(store.create_user("app", "again", Vec::new()).is_err());
+
+        let reopened = UserStore::open(&path).unwrap();
+        assert_eq!(reopened.list_users()[0].roles, vec![RoleGrant::on(Role::ReadWrite, "shop")]);
+        assert!(reopened.credentials("app").is_some());
+        assert!(reopened.drop_user("app").unwrap());
+        assert!(UserStore::open(&path).unwrap().is_empty());
+    }
+}
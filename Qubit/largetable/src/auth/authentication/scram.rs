@@ -0,0 +1,334 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! SCRAM-SHA-256 (RFC 5802, RFC 7677)
+//!
+//! The server keeps only a salt, an iteration count and the derived
+//! `StoredKey`/`ServerKey`, never the password. Channel binding is not
+//! supported; usernames are taken as-is, without SASLprep.
+
+use crate::{LargetableError, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// PBKDF2 iterations for new credentials; RFC 7677 asks for at least 4096
+pub const DEFAULT_ITERATIONS: u32 = 15_000;
+
+const MIN_ITERATIONS: u32 = 4_096;
+
+const NONCE_LEN: usize = 24;
+
+/// What the server stores for a user's password
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScramCredentials {
+    pub salt: String,
+    pub iterations: u32,
+    pub stored_key: String,
+    pub server_key: String,
+}
+
+impl ScramCredentials {
+    /// Derive credentials for `password` with a fresh random salt
+    pub fn new(password: &str, iterations: u32) -> Result<Self> {
+        if iterations < MIN_ITERATIONS {
+            return Err(LargetableError::Config(format!(
+                "SCRAM iterations must be at least {}, got {}",
+                MIN_ITERATIONS, iterations
+            )));
+        }
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Ok(Self::derive(password, &salt, iterations))
+    }
+
+    fn derive(password: &str, salt: &[u8], iterations: u32) -> Self {
+        let salted_password = salted_password(password, salt, iterations);
+        let client_key = hmac(&salted_password, b"Client Key");
+        Self {
+            salt: BASE64.encode(salt),
+            iterations,
+            stored_key: BASE64.encode(Sha256::digest(client_key)),
+            server_key: BASE64.encode(hmac(&salted_password, b"Server Key")),
+        }
+    }
+
+    /// Stand-in for an unknown user, stable per username so a client cannot
+    /// tell unknown users from wrong passwords by the salt it gets back
+    fn mock(username: &str) -> Self {
+        static MOCK_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+        let key = MOCK_KEY.get_or_init(|| {
+            let mut key = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            key
+        });
+        let salt = hmac(key, username.as_bytes());
+        Self {
+            salt: BASE64.encode(&salt[..16]),
+            iterations: DEFAULT_ITERATIONS,
+            stored_key: String::new(),
+            server_key: String::new(),
+        }
+    }
+}
+
+fn salted_password(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut output);
+    output
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+fn nonce() -> String {
+    let mut bytes = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE64.encode(bytes)
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>> {
+    BASE64
+        .decode(value)
+        .map_err(|e| LargetableError::Auth(format!("Invalid base64 in SCRAM {}: {}", field, e)))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Value of the `key=` attribute at `position` in a comma-separated SCRAM message
+fn attribute(message: &str, position: usize, key: char) -> Result<&str> {
+    message
+        .split(',')
+        .nth(position)
+        .and_then(|attribute| attribute.strip_prefix(key))
+        .and_then(|attribute| attribute.strip_prefix('='))
+        .ok_or_else(|| LargetableError::Auth(format!("Malformed SCRAM message: expected '{}=' attribute", key)))
+}
+
+fn decode_username(username: &str) -> Result<String> {
+    let decoded = username.replace("=2C", ",").replace("=3D", "=");
+    if decoded.is_empty() || username.replace("=2C", "").replace("=3D", "").contains('=') {
+        return Err(LargetableError::Auth("Malformed SCRAM username".to_string()));
+    }
+    Ok(decoded)
+}
+
+fn encode_username(username: &str) -> String {
+    username.replace('=', "=3D").replace(',', "=2C")
+}
+
+/// Server side of one authentication exchange
+#[derive(Debug)]
+pub struct ScramConversation {
+    username: String,
+    credentials: ScramCredentials,
+    /// False when `credentials` is a mock for an unknown user
+    known_user: bool,
+    gs2_header: String,
+    client_first_bare: String,
+    server_first: String,
+    nonce: String,
+}
+
+impl ScramConversation {
+    /// Handle the client-first message and produce the server-first message
+    pub fn start(client_first: &str, lookup: impl FnOnce(&str) -> Option<ScramCredentials>) -> Result<(Self, String)> {
+        let mut parts = client_first.splitn(3, ',');
+        let (Some(cbind_flag), Some(authzid), Some(client_first_bare)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(LargetableError::Auth("Malformed SCRAM client-first message".to_string()));
+        };
+        if cbind_flag.starts_with('p') {
+            return Err(LargetableError::Auth("SCRAM channel binding is not supported".to_string()));
+        }
+        if cbind_flag != "n" && cbind_flag != "y" {
+            return Err(LargetableError::Auth("Malformed SCRAM GS2 header".to_string()));
+        }
+        if client_first_bare.starts_with("m=") {
+            return Err(LargetableError::Auth("Unsupported mandatory SCRAM extension".to_string()));
+        }
+
+        let username = decode_username(attribute(client_first_bare, 0, 'n')?)?;
+        if !authzid.is_empty() && authzid.strip_prefix("a=").map(decode_username).transpose()?.as_deref() != Some(username.as_str()) {
+            return Err(LargetableError::Auth("SCRAM authorization identity must match the username".to_string()));
+        }
+        let client_nonce = attribute(client_first_bare, 1, 'r')?;
+        if client_nonce.is_empty() {
+            return Err(LargetableError::Auth("Empty SCRAM client nonce".to_string()));
+        }
+
+        let (credentials, known_user) = match lookup(&username) {
+            Some(credentials) => (credentials, true),
+            None => (ScramCredentials::mock(&username), false),
+        };
+        let nonce = format!("{}{}", client_nonce, self::nonce());
+        let server_first = format!("r={},s={},i={}", nonce, credentials.salt, credentials.iterations);
+
+        let conversation = Self {
+            username,
+            credentials,
+            known_user,
+            gs2_header: format!("{},{},", cbind_flag, authzid),
+            client_first_bare: client_first_bare.to_string(),
+            server_first: server_first.clone(),
+            nonce,
+        };
+        Ok((conversation, server_first))
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Verify the client-final message's proof and produce the server-final message
+    pub fn finish(self, client_final: &str) -> Result<String> {
+        let Some((without_proof, proof)) = client_final.rsplit_once(",p=") else {
+            return Err(LargetableError::Auth("Malformed SCRAM client-final message".to_string()));
+        };
+        if decode("channel binding", attribute(without_proof, 0, 'c')?)? != self.gs2_header.as_bytes() {
+            return Err(LargetableError::Auth("SCRAM channel binding does not match".to_string()));
+        }
+        if attribute(without_proof, 1, 'r')? != self.nonce {
+            return Err(LargetableError::Auth("SCRAM nonce does not match".to_string()));
+        }
+
+        let auth_message = format!("{},{},{}", self.client_first_bare, self.server_first, without_proof);
+        let stored_key = decode("stored key", &self.credentials.stored_key)?;
+        let client_signature = hmac(&stored_key, auth_message.as_bytes());
+        let client_key: Vec<u8> = decode("proof", proof)?
+            .iter()
+            .zip(client_signature)
+            .map(|(proof, signature)| proof ^ signature)
+            .collect();
+
+        if !self.known_user || !constant_time_eq(&Sha256::digest(&client_key), &stored_key) {
+            return Err(LargetableError::Auth("Authentication failed".to_string()));
+        }
+
+        let server_key = decode("server key", &self.credentials.server_key)?;
+        Ok(format!("v={}", BASE64.encode(hmac(&server_key, auth_message.as_bytes()))))
+    }
+}
+
+/// Client side of one authentication exchange, for drivers and tools
+#[derive(Debug)]
+pub struct ScramClient {
+    password: String,
+    client_first_bare: String,
+    client_nonce: String,
+    /// Expected server signature, known once the client-final message is built
+    server_signature: Option<[u8; 32]>,
+}
+
+impl ScramClient {
+    pub fn new(username: &str, password: &str) -> Self {
+        let client_nonce = nonce();
+        Self {
+            password: password.to_string(),
+            client_first_bare: format!("n={},r={}", encode_username(username), client_nonce),
+            client_nonce,
+            server_signature: None,
+        }
+    }
+
+    pub fn client_first(&self) -> String {
+        format!("n,,{}", self.client_first_bare)
+    }
+
+    /// Answer the server-first message with the client proof
+    pub fn client_final(&mut self, server_first: &str) -> Result<String> {
+        let nonce = attribute(server_first, 0, 'r')?;
+        if !nonce.starts_with(&self.client_nonce) || nonce.len() == self.client_nonce.len() {
+            return Err(LargetableError::Auth("Server nonce does not extend the client nonce".to_string()));
+        }
+        let salt = decode("salt", attribute(server_first, 1, 's')?)?;
+        let iterations: u32 = attribute(server_first, 2, 'i')?
+            .parse()
+            .map_err(|_| LargetableError::Auth("Invalid SCRAM iteration count".to_string()))?;
+        if iterations < MIN_ITERATIONS {
+            return Err(LargetableError::Auth(format!("Server asked for only {} SCRAM iterations", iterations)));
+        }
+
+        let salted_password = salted_password(&self.password, &salt, iterations);
+        let client_key = hmac(&salted_password, b"Client Key");
+        let stored_key = Sha256::digest(client_key);
+        let without_proof = format!("c={},r={}", BASE64.encode("n,,"), nonce);
+        let auth_message = format!("{},{},{}", self.client_first_bare, server_first, without_proof);
+        let client_signature = hmac(&stored_key, auth_message.as_bytes());
+        let proof: Vec<u8> = client_key.iter().zip(client_signature).map(|(key, signature)| key ^ signature).collect();
+
+        self.server_signature = Some(hmac(&hmac(&salted_password, b"Server Key"), auth_message.as_bytes()));
+        Ok(format!("{},p={}", without_proof, BASE64.encode(proof)))
+    }
+
+    /// Check the server-final message, proving the server knows the credentials too
+    pub fn verify_server_final(&self, server_final: &str) -> Result<()> {
+        if let Some(error) = server_final.strip_prefix("e=") {
+            return Err(LargetableError::Auth(format!("Server rejected authentication: {}", error)));
+        }
+        let signature = decode("server signature", attribute(server_final, 0, 'v')?)?;
+        match &self.server_signature {
+            Some(expected) if constant_time_eq(&signature, expected) => Ok(()),
+            _ => Err(LargetableError::Auth("Invalid SCRAM server signature".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scram_round_trip() {
+        let credentials = ScramCredentials::new("pencil", MIN_ITERATIONS).unwrap();
+        let mut client = ScramClient::new("user,name", "pencil");
+
+        let (conversation, server_first) =
+            ScramConversation::start(&client.client_first(), |username| (username == "user,name").then(|| credentials.clone())).unwrap();
+        assert_eq!(conversation.username(), "user,name");
+
+        let client_final = client.client_final(&server_first).unwrap();
+        let server_final = conversation.finish(&client_final).unwrap();
+        client.verify_server_final(&server_final).unwrap();
+    }
+
+    #[test]
+    fn test_scram_rejects_wrong_password_and_unknown_user() {
+        let credentials = ScramCredentials::new("pencil", MIN_ITERATIONS).unwrap();
+
+        let mut client = ScramClient::new("user", "crayon");
+        let (conversation, server_first) = ScramConversation::start(&client.client_first(), |_| Some(credentials.clone())).unwrap();
+        assert!(conversation.finish(&client.client_final(&server_first).unwrap()).is_err());
+
+        let mut client = ScramClient::new("ghost", "pencil");
+        let (conversation, server_first) = ScramConversation::start(&client.client_first(), |_| None).unwrap();
+        let (_, again) = ScramConversation::start(&client.client_first(), |_| None).unwrap();
+        assert_eq!(attribute(&server_first, 1, 's').unwrap(), attribute(&again, 1, 's').unwrap());
+        assert!(conversation.finish(&client.client_final(&server_first).unwrap()).is_err());
+
+        assert!(ScramConversation::start("p=tls-unique,,n=user,r=abc", |_| None).is_err());
+        assert!(ScramCredentials::new("pencil", 1).is_err());
+    }
+
+    #[test]
+    fn test_scram_rejects_tampered_nonce() {
+        let credentials = ScramCredentials::new("pencil", MIN_ITERATIONS).unwrap();
+        let mut client = ScramClient::new("user", "pencil");
+        let (conversation, server_first) = ScramConversation::start(&client.client_first(), |_| Some(credentials.clone())).unwrap();
+        let client_final = client.client_final(&server_first).unwrap().replacen(",r=", ",r=x", 1);
+        assert!(conversation.finish(&client_final).is_err());
+    }
+}
@@ -5,3 +5,313 @@
 // ===========================================
 
 //! Authorization rules
+//!
+//! Clients authenticate with SCRAM-SHA-256 and receive a session token, sent
+//! as `Authorization: Bearer <token>` on later HTTP requests or gRPC calls.
+//! [`AuthLayer`] resolves the token to a [`Principal`] and runs the request
+//! with it in scope; the engine checks that principal on every operation.
+
+use crate::auth::authentication::{ScramConversation, UserStore};
+use crate::auth::rbac::{Action, Principal};
+use crate::{LargetableError, Result};
+use axum::http::{header, HeaderMap, Request};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// How long a client has to answer the server-first message
+const CONVERSATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Unfinished conversations allowed at once, so clients cannot exhaust memory
+const MAX_PENDING_CONVERSATIONS: usize = 10_000;
+
+tokio::task_local! {
+    static PRINCIPAL: Principal;
+}
+
+/// Run `future` as `principal`; engine operations inside it are checked against its roles
+pub async fn with_principal<F: Future>(principal: Principal, future: F) -> F::Output {
+    PRINCIPAL.scope(principal, future).await
+}
+
+/// The principal the current request runs as, if a front end set one
+pub fn current_principal() -> Option<Principal> {
+    PRINCIPAL.try_with(Principal::clone).ok()
+}
+
+/// Reply to a client-first message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScramStart {
+    pub conversation_id: String,
+    /// Server-first message
+    pub payload: String,
+}
+
+/// Reply to a client-final message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScramFinish {
+    /// Server-final message, for the client to verify the server
+    pub payload: String,
+    pub token: String,
+    pub expires_in_secs: u64,
+}
+
+struct PendingConversation {
+    conversation: ScramConversation,
+    started_at: Instant,
+}
+
+struct Session {
+    username: String,
+    expires_at: Instant,
+}
+
+/// Users, authentication exchanges and sessions of one server
+pub struct AccessControl {
+    enabled: bool,
+    users: UserStore,
+    session_ttl: Duration,
+    conversations: Mutex<HashMap<String, PendingConversation>>,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl AccessControl {
+    /// Every operation is allowed; the default for embedded engines
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            users: UserStore::in_memory(),
+            session_ttl: Duration::ZERO,
+            conversations: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn new(users: UserStore, session_ttl: Duration) -> Self {
+        info!("Access control enabled, sessions last {:?}", session_ttl);
+        Self {
+            enabled: true,
+            users,
+            session_ttl,
+            ..Self::disabled()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn users(&self) -> &UserStore {
+        &self.users
+    }
+
+    /// Check the current principal may perform `action`, on `database` unless it is a cluster action
+    pub fn authorize(&self, action: Action, database: Option<&str>) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        match current_principal() {
+            Some(principal) => principal.check(action, database),
+            None => Err(LargetableError::Auth(format!("Authentication required for {}", action))),
+        }
+    }
+
+    /// Whether the current principal should see `database` in listings
+    pub fn can_see_database(&self, database: &str) -> bool {
+        !self.enabled
+            || current_principal().is_some_and(|principal| {
+                principal.can(Action::ListDatabases, None) || principal.has_access_to(database)
+            })
+    }
+
+    /// Begin a SCRAM-SHA-256 exchange with the client-first message
+    pub fn start_scram(&self, client_first: &str) -> Result<ScramStart> {
+        if !self.enabled {
+            return Err(LargetableError::Auth("Authentication is not enabled on this server".to_string()));
+        }
+        let (conversation, payload) = ScramConversation::start(client_first, |name| self.users.credentials(name))?;
+
+        let mut conversations = self.conversations.lock();
+        conversations.retain(|_, pending| pending.started_at.elapsed() < CONVERSATION_TIMEOUT);
+        if conversations.len() >= MAX_PENDING_CONVERSATIONS {
+            return Err(LargetableError::ResourceExhausted("Too many authentication attempts in progress".to_string()));
+        }
+        let conversation_id = random_token();
+        conversations.insert(conversation_id.clone(), PendingConversation { conversation, started_at: Instant::now() });
+
+        Ok(ScramStart { conversation_id, payload })
+    }
+
+    /// Complete an exchange with the client-final message and open a session
+    pub fn finish_scram(&self, conversation_id: &str, client_final: &str) -> Result<ScramFinish> {
+        let pending = self
+            .conversations
+            .lock()
+            .remove(conversation_id)
+            .filter(|pending| pending.started_at.elapsed() < CONVERSATION_TIMEOUT)
+            .ok_or_else(|| LargetableError::Auth("Unknown or expired authentication conversation".to_string()))?;
+
+        let username = pending.conversation.username().to_string();
+        let payload = pending.conversation.finish(client_final).inspect_err(|_| {
+            info!("Failed authentication for user '{}'", username);
+        })?;
+
+        let token = random_token();
+        let mut sessions = self.sessions.lock();
+        sessions.retain(|_, session| session.expires_at > Instant::now());
+        sessions.insert(token.clone(), Session { username: username.clone(), expires_at: Instant::now() + self.session_ttl });
+        info!("User '{}' authenticated", username);
+
+        Ok(ScramFinish { payload, token, expires_in_secs: self.session_ttl.as_secs() })
+    }
+
+    /// Principal for a session token; roles are read from the user store so
+    /// role changes and dropped users take effect on open sessions
+    pub fn principal_for_token(&self, token: Option<&str>) -> Principal {
+        let Some(token) = token else {
+            return Principal::anonymous();
+        };
+        let username = match self.sessions.lock().get(token) {
+            Some(session) if session.expires_at > Instant::now() => session.username.clone(),
+            _ => {
+                debug!("Request with an unknown or expired session token");
+                return Principal::anonymous();
+            }
+        };
+        self.users.principal(&username).unwrap_or_else(Principal::anonymous)
+    }
+
+    /// Close a session; returns whether it was open
+    pub fn end_session(&self, token: &str) -> bool {
+        self.sessions.lock().remove(token).is_some()
+    }
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Bearer token of a request, if it sent one
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Tower layer that runs each HTTP request or gRPC call as the principal of its session token
+#[derive(Clone)]
+pub struct AuthLayer {
+    access: Arc<AccessControl>,
+}
+
+impl AuthLayer {
+    pub fn new(access: Arc<AccessControl>) -> Self {
+        Self { access }
+    }
+}
+
+impl<S> tower::Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService { inner, access: self.access.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    access: Arc<AccessControl>,
+}
+
+impl<S, B> tower::Service<Request<B>> for AuthService<S>
+where
+    S: tower::Service<Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, std::result::Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        if !self.access.is_enabled() {
+            return Box::pin(self.inner.call(request));
+        }
+        let principal = self.access.principal_for_token(bearer_token(request.headers()));
+        Box::pin(with_principal(principal, self.inner.call(request)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::authentication::ScramClient;
+    use crate::auth::rbac::{Role, RoleGrant};
+
+    fn access_control() -> AccessControl {
+        let users = UserStore::in_memory().with_iterations(4096);
+        users.create_user("app", "secret", vec![RoleGrant::on(Role::Read, "shop")]).unwrap();
+        AccessControl::new(users, Duration::from_secs(60))
+    }
+
+    fn login(access: &AccessControl, username: &str, password: &str) -> Result<ScramFinish> {
+        let mut client = ScramClient::new(username, password);
+        let start = access.start_scram(&client.client_first())?;
+        let finish = access.finish_scram(&start.conversation_id, &client.client_final(&start.payload)?)?;
+        client.verify_server_final(&finish.payload)?;
+        Ok(finish)
+    }
+
+    #[tokio::test]
+    async fn test_session_principal_is_checked() {
+        let access = access_control();
+        let session = login(&access, "app", "secret").unwrap();
+        assert!(login(&access, "app", "wrong").is_err());
+
+        let principal = access.principal_for_token(Some(&session.token));
+        assert_eq!(principal.username.as_deref(), Some("app"));
+        assert!(access.principal_for_token(Some("forged")).username.is_none());
+
+        with_principal(principal, async {
+            assert!(access.authorize(Action::Find, Some("shop")).is_ok());
+            assert!(matches!(access.authorize(Action::Insert, Some("shop")), Err(LargetableError::PermissionDenied(_))));
+            assert!(access.can_see_database("shop") && !access.can_see_database("billing"));
+        })
+        .await;
+        assert!(matches!(access.authorize(Action::Find, Some("shop")), Err(LargetableError::Auth(_))));
+
+        access.users().set_roles("app", Vec::new()).unwrap();
+        assert!(access.principal_for_token(Some(&session.token)).grants.is_empty());
+        assert!(access.end_session(&session.token));
+    }
+
+    #[test]
+    fn test_conversation_is_single_use() {
+        let access = access_control();
+        let mut client = ScramClient::new("app", "secret");
+        let start = access.start_scram(&client.client_first()).unwrap();
+        let client_final = client.client_final(&start.payload).unwrap();
+        assert!(access.finish_scram(&start.conversation_id, &client_final).is_ok());
+        assert!(access.finish_scram(&start.conversation_id, &client_final).is_err());
+
+        assert!(AccessControl::disabled().start_scram(&client.client_first()).is_err());
+        assert!(AccessControl::disabled().authorize(Action::ManageUsers, None).is_ok());
+    }
+}
@@ -0,0 +1,23 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Users, roles and authentication
+//!
+//! Access control is off unless the server enables it. When on, clients
+//! authenticate with SCRAM-SHA-256 and every engine operation is checked
+//! against the roles of the session's user.
+
+pub mod audit;
+pub mod authentication;
+pub mod authorization;
+pub mod certificates;
+pub mod encryption;
+pub mod rbac;
+pub mod ssl_tls;
+
+pub use authentication::{ScramClient, ScramCredentials, User, UserInfo, UserStore};
+pub use authorization::{current_principal, with_principal, AccessControl, AuthLayer, ScramFinish, ScramStart};
+pub use rbac::{Action, Principal, Role, RoleGrant};
@@ -5,3 +5,204 @@
 // ===========================================
 
 //! Role-based access control
+//!
+//! Users hold role grants, each scoped to one database or to all of them.
+//! Every engine operation maps to an [`Action`], and a principal may perform
+//! it when one of its grants covers both the action and the database.
+
+use crate::{LargetableError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Built-in roles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Role {
+    /// Read documents, collections and indexes
+    Read,
+    /// Everything `read` allows, plus writing documents and creating collections
+    ReadWrite,
+    /// Manage collections, indexes and the database itself, without reading documents
+    DbAdmin,
+    /// Server-wide operations: listing databases, statistics, backups, users and encryption keys
+    ClusterAdmin,
+}
+
+/// Operations subject to access control
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    Find,
+    Insert,
+    Update,
+    Delete,
+    ListCollections,
+    CreateCollection,
+    ListIndexes,
+    CreateIndex,
+    CreateDatabase,
+    DropDatabase,
+    ListDatabases,
+    ServerStatus,
+    Backup,
+    ManageUsers,
+    ManageEncryption,
+}
+
+impl Action {
+    /// Cluster actions are not tied to a database, so only cluster-wide grants cover them
+    pub fn is_cluster(self) -> bool {
+        matches!(
+            self,
+            Action::ListDatabases | Action::ServerStatus | Action::Backup | Action::ManageUsers | Action::ManageEncryption
+        )
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Role {
+    pub fn allows(self, action: Action) -> bool {
+        use Action::*;
+        match self {
+            Role::Read => matches!(action, Find | ListCollections | ListIndexes),
+            Role::ReadWrite => {
+                matches!(action, Find | Insert | Update | Delete | ListCollections | CreateCollection | ListIndexes | CreateDatabase)
+            }
+            Role::DbAdmin => {
+                matches!(action, ListCollections | CreateCollection | ListIndexes | CreateIndex | CreateDatabase | DropDatabase)
+            }
+            Role::ClusterAdmin => action.is_cluster(),
+        }
+    }
+}
+
+/// A role on one database, or on every database when `database` is unset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleGrant {
+    pub role: Role,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+}
+
+impl RoleGrant {
+    pub fn on(role: Role, database: impl Into<String>) -> Self {
+        Self { role, database: Some(database.into()) }
+    }
+
+    pub fn any_database(role: Role) -> Self {
+        Self { role, database: None }
+    }
+
+    fn covers(&self, action: Action, database: Option<&str>) -> bool {
+        if !self.role.allows(action) {
+            return false;
+        }
+        match (&self.database, database) {
+            (None, _) => true,
+            (Some(_), _) if action.is_cluster() => false,
+            (Some(granted), Some(database)) => granted == database,
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Every role on every database, for the initial administrator
+pub fn all_roles() -> Vec<RoleGrant> {
+    [Role::ReadWrite, Role::DbAdmin, Role::ClusterAdmin]
+        .into_iter()
+        .map(RoleGrant::any_database)
+        .collect()
+}
+
+/// The identity an operation runs as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// Unset for requests that did not authenticate
+    pub username: Option<String>,
+    pub grants: Vec<RoleGrant>,
+}
+
+impl Principal {
+    pub fn user(username: impl Into<String>, grants: Vec<RoleGrant>) -> Self {
+        Self { username: Some(username.into()), grants }
+    }
+
+    /// A caller that did not authenticate and may do nothing
+    pub fn anonymous() -> Self {
+        Self { username: None, grants: Vec::new() }
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.username.is_some()
+    }
+
+    pub fn can(&self, action: Action, database: Option<&str>) -> bool {
+        self.grants.iter().any(|grant| grant.covers(action, database))
+    }
+
+    /// Whether any grant applies to `database`, for filtering database listings
+    pub fn has_access_to(&self, database: &str) -> bool {
+        self.grants.iter().any(|grant| match &grant.database {
+            Some(granted) => granted == database,
+            None => !matches!(grant.role, Role::ClusterAdmin),
+        })
+    }
+
+    /// `Auth` when the caller did not authenticate, `PermissionDenied` when no grant covers the action
+    pub fn check(&self, action: Action, database: Option<&str>) -> Result<()> {
+        if self.can(action, database) {
+            return Ok(());
+        }
+        let Some(username) = &self.username else {
+            return Err(LargetableError::Auth(format!("Authentication required for {}", action)));
+        };
+        Err(LargetableError::PermissionDenied(match database {
+            Some(database) if !action.is_cluster() => {
+                format!("User '{}' is not authorized to {} on database '{}'", username, action, database)
+            }
+            _ => format!("User '{}' is not authorized to {}", username, action),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_scopes() {
+        let principal = Principal::user(
+            "app",
+            vec![RoleGrant::on(Role::ReadWrite, "shop"), RoleGrant::on(Role::Read, "catalog")],
+        );
+        assert!(principal.check(Action::Insert, Some("shop")).is_ok());
+        assert!(principal.check(Action::Find, Some("catalog")).is_ok());
+        assert!(matches!(principal.check(Action::Insert, Some("catalog")), Err(LargetableError::PermissionDenied(_))));
+        assert!(principal.check(Action::CreateIndex, Some("shop")).is_err());
+        assert!(principal.check(Action::ListDatabases, None).is_err());
+        assert!(principal.has_access_to("catalog") && !principal.has_access_to("billing"));
+    }
+
+    #[test]
+    fn test_admin_roles() {
+        let dba = Principal::user("dba", vec![RoleGrant::any_database(Role::DbAdmin)]);
+        assert!(dba.check(Action::DropDatabase, Some("shop")).is_ok());
+        assert!(dba.check(Action::Find, Some("shop")).is_err());
+
+        let ops = Principal::user("ops", vec![RoleGrant::any_database(Role::ClusterAdmin)]);
+        assert!(ops.check(Action::ServerStatus, None).is_ok());
+        assert!(ops.check(Action::ManageUsers, None).is_ok());
+        assert!(ops.check(Action::Find, Some("shop")).is_err());
+
+        // A database-scoped grant never covers cluster actions
+        let scoped = Principal::user("scoped", vec![RoleGrant::on(Role::ClusterAdmin, "shop")]);
+        assert!(scoped.check(Action::ListDatabases, None).is_err());
+
+        assert!(matches!(Principal::anonymous().check(Action::Find, Some("shop")), Err(LargetableError::Auth(_))));
+    }
+}
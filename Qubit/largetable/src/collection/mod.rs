@@ -0,0 +1,17 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Collection management
+
+pub mod metadata;
+pub mod operations;
+pub mod partitioning;
+pub mod sharding;
+
+pub use partitioning::{
+    PartitionOptions, PartitionStats, PartitionWindow, PartitionedCollection, PartitionedCollectionStats,
+    RetentionReport,
+};
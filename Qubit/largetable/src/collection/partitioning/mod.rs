@@ -5,3 +5,718 @@
 // ===========================================
 
 //! Collection partitioning
+//!
+//! A partitioned collection splits its documents by a time field into daily
+//! or weekly windows, each held in a partition with its own storage engine
+//! and indexes. Queries bounded on the time field only read the partitions
+//! their bounds overlap, and a retention policy drops partitions once all of
+//! their window is too old, freeing them whole rather than deleting document
+//! by document. Suited to append-heavy event data such as analytics events.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tracing::{debug, info, warn};
+
+use crate::database::{Collection, StorageFactory};
+use crate::document::DocumentUtils;
+use crate::query::{DocumentStream, Query, QueryResult};
+use crate::replication::Oplog;
+use crate::storage::StorageEngine;
+use crate::{CollectionName, DatabaseName, Document, DocumentId, LargetableError, Result, Timestamp, Value};
+
+const SECOND: Timestamp = 1_000_000;
+const DAY: Timestamp = 86_400 * SECOND;
+const WEEK: Timestamp = 7 * DAY;
+/// 1970-01-05, the first Monday after the Unix epoch
+const FIRST_MONDAY: Timestamp = 4 * DAY;
+
+/// Time span of one partition; windows are aligned to UTC midnight, and
+/// weekly ones start on Monday
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionWindow {
+    Daily,
+    Weekly,
+}
+
+impl PartitionWindow {
+    /// Length of a window in microseconds
+    pub fn span(&self) -> Timestamp {
+        match self {
+            PartitionWindow::Daily => DAY,
+            PartitionWindow::Weekly => WEEK,
+        }
+    }
+
+    /// Start of the window holding `time`
+    pub fn start_of(&self, time: Timestamp) -> Timestamp {
+        match self {
+            PartitionWindow::Daily => time.div_euclid(DAY) * DAY,
+            PartitionWindow::Weekly => (time - FIRST_MONDAY).div_euclid(WEEK) * WEEK + FIRST_MONDAY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionOptions {
+    /// Field holding the document time, a timestamp or microseconds
+    pub time_field: String,
+    pub window: PartitionWindow,
+    /// Drop partitions whose whole window is older than this, in microseconds;
+    /// partitions are kept forever when unset
+    #[serde(default)]
+    pub retain_for: Option<Timestamp>,
+}
+
+impl PartitionOptions {
+    pub fn new(time_field: impl Into<String>, window: PartitionWindow) -> Self {
+        Self {
+            time_field: time_field.into(),
+            window,
+            retain_for: None,
+        }
+    }
+
+    pub fn retain_for(mut self, age: Timestamp) -> Self {
+        self.retain_for = Some(age);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.time_field.is_empty() || self.time_field.starts_with('$') {
+            return Err(LargetableError::Config(format!("Invalid time field '{}'", self.time_field)));
+        }
+        if self.retain_for.is_some_and(|age| age <= 0) {
+            return Err(LargetableError::Config("Retention must be a positive age".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// One window of a partitioned collection
+struct Partition {
+    start: Timestamp,
+    collection: Arc<Collection>,
+    /// The partition's own engine, destroyed when the partition is dropped
+    storage: Arc<dyn StorageEngine>,
+    documents: AtomicU64,
+    min_time: AtomicI64,
+    max_time: AtomicI64,
+}
+
+impl Partition {
+    fn observe(&self, time: Timestamp) {
+        self.min_time.fetch_min(time, Ordering::Relaxed);
+        self.max_time.fetch_max(time, Ordering::Relaxed);
+    }
+}
+
+/// Size and time range of one partition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionStats {
+    /// Window start, inclusive, in microseconds
+    pub start: Timestamp,
+    /// Window end, exclusive
+    pub end: Timestamp,
+    pub documents: u64,
+    /// Oldest and newest document times seen since the partition opened
+    pub min_time: Option<Timestamp>,
+    pub max_time: Option<Timestamp>,
+    /// When retention drops the partition
+    pub expires_at: Option<Timestamp>,
+}
+
+/// Partitions of a collection, oldest first, and how well queries prune them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionedCollectionStats {
+    pub database: DatabaseName,
+    pub collection: CollectionName,
+    pub options: PartitionOptions,
+    pub documents: u64,
+    pub partitions: Vec<PartitionStats>,
+    /// Counted since the collection was opened
+    pub dropped_partitions: u64,
+    pub dropped_documents: u64,
+    pub queries: u64,
+    pub partitions_scanned: u64,
+    pub partitions_pruned: u64,
+}
+
+/// What a retention pass dropped
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    /// Window starts of the dropped partitions
+    pub dropped_partitions: Vec<Timestamp>,
+    pub dropped_documents: u64,
+}
+
+#[derive(Debug, Default)]
+struct PartitionMetrics {
+    dropped_partitions: AtomicU64,
+    dropped_documents: AtomicU64,
+    queries: AtomicU64,
+    partitions_scanned: AtomicU64,
+    partitions_pruned: AtomicU64,
+}
+
+/// A collection split into time-window partitions
+///
+/// Documents are read and written through it rather than through the
+/// partitions. Document IDs do not say which window holds a document, so
+/// reads and writes by ID look through the partitions newest first.
+pub struct PartitionedCollection {
+    name: CollectionName,
+    database: DatabaseName,
+    options: PartitionOptions,
+    storage: StorageFactory,
+    oplog: Arc<Oplog>,
+    version: Arc<AtomicU64>,
+    /// Keyed by window start
+    partitions: parking_lot::RwLock<BTreeMap<Timestamp, Arc<Partition>>>,
+    metrics: PartitionMetrics,
+}
+
+impl PartitionedCollection {
+    pub fn new(
+        name: CollectionName,
+        database: DatabaseName,
+        options: PartitionOptions,
+        storage: StorageFactory,
+        oplog: Arc<Oplog>,
+        version: Arc<AtomicU64>,
+    ) -> Result<Self> {
+        options.validate()?;
+        Ok(Self {
+            name,
+            database,
+            options,
+            storage,
+            oplog,
+            version,
+            partitions: parking_lot::RwLock::new(BTreeMap::new()),
+            metrics: PartitionMetrics::default(),
+        })
+    }
+
+    pub fn options(&self) -> &PartitionOptions {
+        &self.options
+    }
+
+    pub fn name(&self) -> &CollectionName {
+        &self.name
+    }
+
+    /// Insert a document into the partition of its time
+    ///
+    /// Opening a new partition also applies retention, so old partitions go
+    /// as new ones arrive without a separate job. Documents already past
+    /// retention are rejected.
+    pub async fn insert(&self, document: Document) -> Result<DocumentId> {
+        let time = self.document_time(&document)?;
+        let now = chrono::Utc::now().timestamp_micros();
+        let start = self.options.window.start_of(time);
+        if self.is_expired(start, now) {
+            return Err(LargetableError::Validation(format!(
+                "Document at {} is older than the retention of collection '{}'",
+                time, self.name
+            )));
+        }
+
+        let (partition, opened) = self.partition_for(start)?;
+        let id = partition.collection.insert(document).await?;
+        partition.documents.fetch_add(1, Ordering::Relaxed);
+        partition.observe(time);
+
+        if opened {
+            self.maintain(now).await;
+        }
+        Ok(id)
+    }
+
+    /// Insert documents in order; stops at the first that fails, keeping the
+    /// ones before it
+    pub async fn insert_many(&self, documents: Vec<Document>) -> Result<Vec<DocumentId>> {
+        let mut ids = Vec::with_capacity(documents.len());
+        for document in documents {
+            ids.push(self.insert(document).await?);
+        }
+        Ok(ids)
+    }
+
+    pub async fn find_by_id(&self, id: &DocumentId) -> Result<Option<Document>> {
+        for partition in self.partitions_newest_first() {
+            if let Some(document) = partition.collection.find_by_id(id).await? {
+                return Ok(Some(document));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Replace a document; its time may change only within its window
+    pub async fn update_by_id(&self, id: &DocumentId, document: Document) -> Result<Option<Document>> {
+        let time = self.document_time(&document)?;
+        for partition in self.partitions_newest_first() {
+            if partition.collection.find_by_id(id).await?.is_none() {
+                continue;
+            }
+            if self.options.window.start_of(time) != partition.start {
+                return Err(LargetableError::Validation(format!(
+                    "Updating document {} would move it out of its partition",
+                    id
+                )));
+            }
+            let updated = partition.collection.update_by_id(id, document).await?;
+            partition.observe(time);
+            return Ok(updated);
+        }
+        Ok(None)
+    }
+
+    pub async fn delete_by_id(&self, id: &DocumentId) -> Result<bool> {
+        for partition in self.partitions_newest_first() {
+            if partition.collection.delete_by_id(id).await? {
+                partition.documents.fetch_sub(1, Ordering::Relaxed);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Run a query over the partitions its filter can match
+    ///
+    /// Each partition is searched with the filter through its own indexes;
+    /// sorting, paging and projection then apply to the combined matches.
+    pub async fn find(&self, query: &Query) -> Result<QueryResult> {
+        let partitions = self.prune(query.filter.as_ref());
+        let per_partition = Query {
+            filter: query.filter.clone(),
+            hint: query.hint.clone(),
+            ..Query::new()
+        };
+        let mut documents = Vec::new();
+        for partition in &partitions {
+            documents.extend(partition.collection.find(&per_partition).await?.documents);
+        }
+
+        debug!(
+            "Query on partitioned collection '{}' read {} partitions",
+            self.name,
+            partitions.len()
+        );
+        Query { filter: None, hint: None, ..query.clone() }.execute(documents).await
+    }
+
+    /// Stream the documents of the partitions `filter` can match, oldest
+    /// partition first, reading `batch_size` at a time
+    pub fn stream_documents(&self, filter: Option<&JsonValue>, batch_size: usize) -> DocumentStream<'static> {
+        let partitions = self.prune(filter);
+        stream::iter(partitions)
+            .flat_map(move |partition| partition.collection.stream_documents(batch_size))
+            .boxed()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.partitions.read().values().map(|partition| partition.documents.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Window starts of the partitions a query with `filter` reads
+    pub fn explain(&self, filter: Option<&JsonValue>) -> Vec<Timestamp> {
+        let (from, to) = filter.map_or((None, None), |filter| time_bounds(filter, &self.options.time_field));
+        self.overlapping(from, to).iter().map(|partition| partition.start).collect()
+    }
+
+    /// Drop partitions past retention as of `now`, destroying their storage
+    pub async fn maintain(&self, now: Timestamp) -> RetentionReport {
+        let mut report = RetentionReport::default();
+        if self.options.retain_for.is_none() {
+            return report;
+        }
+
+        let mut dropped = Vec::new();
+        {
+            let mut partitions = self.partitions.write();
+            let expired: Vec<Timestamp> =
+                partitions.keys().copied().filter(|start| self.is_expired(*start, now)).collect();
+            for start in expired {
+                if let Some(partition) = partitions.remove(&start) {
+                    report.dropped_documents += partition.documents.load(Ordering::Relaxed);
+                    report.dropped_partitions.push(start);
+                    dropped.push(partition);
+                }
+            }
+        }
+        // Already out of the map, so a failed destroy only leaves files behind
+        for partition in dropped {
+            if let Err(e) = partition.storage.destroy().await {
+                warn!("Failed to destroy partition {} of collection '{}': {}", partition.start, self.name, e);
+            }
+        }
+
+        if !report.dropped_partitions.is_empty() {
+            self.record_drop(&report);
+            info!(
+                "Retention dropped {} partitions ({} documents) of collection '{}'",
+                report.dropped_partitions.len(),
+                report.dropped_documents,
+                self.name
+            );
+        }
+        report
+    }
+
+    /// Drop the partition whose window starts at `start`, whatever its age,
+    /// destroying its storage
+    pub async fn drop_partition(&self, start: Timestamp) -> Result<Option<PartitionStats>> {
+        let Some(partition) = self.partitions.write().remove(&start) else {
+            return Ok(None);
+        };
+        let stats = self.partition_stats(&partition);
+        self.record_drop(&RetentionReport {
+            dropped_partitions: vec![start],
+            dropped_documents: stats.documents,
+        });
+        partition.storage.destroy().await?;
+        debug!("Dropped partition {} of collection '{}'", start, self.name);
+        Ok(Some(stats))
+    }
+
+    pub fn stats(&self) -> PartitionedCollectionStats {
+        let partitions: Vec<PartitionStats> =
+            self.partitions.read().values().map(|partition| self.partition_stats(partition)).collect();
+        PartitionedCollectionStats {
+            database: self.database.clone(),
+            collection: self.name.clone(),
+            options: self.options.clone(),
+            documents: partitions.iter().map(|partition| partition.documents).sum(),
+            partitions,
+            dropped_partitions: self.metrics.dropped_partitions.load(Ordering::Relaxed),
+            dropped_documents: self.metrics.dropped_documents.load(Ordering::Relaxed),
+            queries: self.metrics.queries.load(Ordering::Relaxed),
+            partitions_scanned: self.metrics.partitions_scanned.load(Ordering::Relaxed),
+            partitions_pruned: self.metrics.partitions_pruned.load(Ordering::Relaxed),
+        }
+    }
+
+    fn document_time(&self, document: &Document) -> Result<Timestamp> {
+        match document.fields.get(&self.options.time_field) {
+            Some(Value::Timestamp(t)) | Some(Value::Int64(t)) => Ok(*t),
+            Some(other) => Err(LargetableError::Validation(format!(
+                "Time field {} must be a timestamp, got {:?}",
+                self.options.time_field, other
+            ))),
+            None => Err(LargetableError::Validation(format!(
+                "Document has no time field {}",
+                self.options.time_field
+            ))),
+        }
+    }
+
+    fn is_expired(&self, start: Timestamp, now: Timestamp) -> bool {
+        self.options
+            .retain_for
+            .is_some_and(|age| start + self.options.window.span() <= now - age)
+    }
+
+    /// The partition starting at `start`, and whether it was just opened
+    fn partition_for(&self, start: Timestamp) -> Result<(Arc<Partition>, bool)> {
+        if let Some(partition) = self.partitions.read().get(&start) {
+            return Ok((partition.clone(), false));
+        }
+
+        let mut partitions = self.partitions.write();
+        if let Some(partition) = partitions.get(&start) {
+            return Ok((partition.clone(), false));
+        }
+        // Writes to the partition are logged and versioned as the collection's own
+        let storage = (self.storage)(&self.name, start)?;
+        let partition = Arc::new(Partition {
+            start,
+            collection: Arc::new(Collection::new(
+                self.name.clone(),
+                self.database.clone(),
+                storage.clone(),
+                self.oplog.clone(),
+                self.version.clone(),
+            )),
+            storage,
+            documents: AtomicU64::new(0),
+            min_time: AtomicI64::new(Timestamp::MAX),
+            max_time: AtomicI64::new(Timestamp::MIN),
+        });
+        partitions.insert(start, partition.clone());
+        debug!("Opened partition {} of collection '{}'", start, self.name);
+        Ok((partition, true))
+    }
+
+    fn partitions_newest_first(&self) -> Vec<Arc<Partition>> {
+        self.partitions.read().values().rev().cloned().collect()
+    }
+
+    fn overlapping(&self, from: Option<Timestamp>, to: Option<Timestamp>) -> Vec<Arc<Partition>> {
+        let span = self.options.window.span();
+        let (from, to) = (from.unwrap_or(Timestamp::MIN), to.unwrap_or(Timestamp::MAX));
+        self.partitions
+            .read()
+            .values()
+            .filter(|partition| partition.start <= to && partition.start.saturating_add(span) > from)
+            .cloned()
+            .collect()
+    }
+
+    /// Partitions a query with `filter` must read, counted in the metrics
+    fn prune(&self, filter: Option<&JsonValue>) -> Vec<Arc<Partition>> {
+        let (from, to) = filter.map_or((None, None), |filter| time_bounds(filter, &self.options.time_field));
+        let partitions = self.overlapping(from, to);
+        let total = self.partitions.read().len();
+        self.metrics.queries.fetch_add(1, Ordering::Relaxed);
+        self.metrics.partitions_scanned.fetch_add(partitions.len() as u64, Ordering::Relaxed);
+        self.metrics
+            .partitions_pruned
+            .fetch_add(total.saturating_sub(partitions.len()) as u64, Ordering::Relaxed);
+        partitions
+    }
+
+    fn partition_stats(&self, partition: &Partition) -> PartitionStats {
+        let documents = partition.documents.load(Ordering::Relaxed);
+        let (min_time, max_time) = (partition.min_time.load(Ordering::Relaxed), partition.max_time.load(Ordering::Relaxed));
+        let end = partition.start + self.options.window.span();
+        PartitionStats {
+            start: partition.start,
+            end,
+            documents,
+            min_time: (min_time <= max_time).then_some(min_time),
+            max_time: (min_time <= max_time).then_some(max_time),
+            expires_at: self.options.retain_for.map(|age| end + age),
+        }
+    }
+
+    fn record_drop(&self, report: &RetentionReport) {
+        self.metrics
+            .dropped_partitions
+            .fetch_add(report.dropped_partitions.len() as u64, Ordering::Relaxed);
+        self.metrics.dropped_documents.fetch_add(report.dropped_documents, Ordering::Relaxed);
+        self.version.store(crate::database::next_write_version(), Ordering::Release);
+    }
+}
+
+/// Inclusive bounds a filter puts on the integer times in `field`, from
+/// top-level equality, `$in` and range conditions
+pub fn time_bounds(filter: &JsonValue, field: &str) -> (Option<Timestamp>, Option<Timestamp>) {
+    let Some(condition) = filter.get(field) else {
+        return (None, None);
+    };
+    let (mut from, mut to) = (None, None);
+    let mut narrow = |lower: Option<Timestamp>, upper: Option<Timestamp>| {
+        if let Some(lower) = lower {
+            from = Some(from.map_or(lower, |from: Timestamp| from.max(lower)));
+        }
+        if let Some(upper) = upper {
+            to = Some(to.map_or(upper, |to: Timestamp| to.min(upper)));
+        }
+    };
+
+    match DocumentUtils::filter_operators(condition) {
+        Some(operators) => {
+            for (operator, operand) in operators {
+                match operator.as_str() {
+                    "$eq" => narrow(floor(operand), ceil(operand)),
+                    "$gt" => narrow(floor(operand).map(|t| t.saturating_add(1)), None),
+                    "$gte" => narrow(ceil(operand), None),
+                    "$lt" => narrow(None, ceil(operand).map(|t| t.saturating_sub(1))),
+                    "$lte" => narrow(None, floor(operand)),
+                    "$in" => {
+                        let Some(candidates) = operand.as_array() else {
+                            continue;
+                        };
+                        let lowest = candidates.iter().map(floor).collect::<Option<Vec<_>>>();
+                        let highest = candidates.iter().map(ceil).collect::<Option<Vec<_>>>();
+                        if let (Some(lowest), Some(highest)) = (lowest, highest) {
+                            narrow(lowest.into_iter().min(), highest.into_iter().max());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None => narrow(floor(condition), ceil(condition)),
+    }
+    (from, to)
+}
+
+fn floor(json: &JsonValue) -> Option<Timestamp> {
+    json.as_i64().or_else(|| json.as_f64().map(|f| f.floor() as Timestamp))
+}
+
+fn ceil(json: &JsonValue) -> Option<Timestamp> {
+    json.as_i64().or_else(|| json.as_f64().map(|f| f.ceil() as Timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::DocumentBuilder;
+    use crate::storage::engines::graph::GraphEngine;
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    fn events(options: PartitionOptions) -> PartitionedCollection {
+        // The graph engine keeps documents in memory
+        let storage: StorageFactory = Arc::new(|_: &str, _| Ok(Arc::new(GraphEngine::new()?) as _));
+        PartitionedCollection::new(
+            "events".to_string(),
+            "analytics".to_string(),
+            options,
+            storage,
+            Arc::new(Oplog::new()),
+            Arc::new(AtomicU64::new(0)),
+        )
+        .unwrap()
+    }
+
+    fn event(kind: &str, time: Timestamp) -> Document {
+        DocumentBuilder::new()
+            .string("event", kind)
+            .field("ts".to_string(), Value::Timestamp(time))
+            .build()
+    }
+
+    #[test]
+    fn test_windows() {
+        // 2026-10-16 is a Friday
+        let friday = 1_792_108_800 * SECOND;
+        assert_eq!(PartitionWindow::Daily.start_of(friday + 5 * SECOND), friday);
+        assert_eq!(PartitionWindow::Weekly.start_of(friday), friday - 4 * DAY);
+        assert_eq!(PartitionWindow::Weekly.start_of(friday - 4 * DAY), friday - 4 * DAY);
+        assert_eq!(PartitionWindow::Weekly.start_of(friday - 4 * DAY - 1), friday - 11 * DAY);
+        assert_eq!(PartitionWindow::Daily.start_of(-1), -DAY);
+    }
+
+    #[test]
+    fn test_time_bounds() {
+        assert_eq!(time_bounds(&json!({"ts": {"$gte": 10, "$lt": 20}}), "ts"), (Some(10), Some(19)));
+        assert_eq!(time_bounds(&json!({"ts": 15}), "ts"), (Some(15), Some(15)));
+        assert_eq!(time_bounds(&json!({"ts": {"$in": [30, 5]}}), "ts"), (Some(5), Some(30)));
+        assert_eq!(time_bounds(&json!({"ts": {"$gt": 1.5}, "event": "view"}), "ts"), (Some(2), None));
+        assert_eq!(time_bounds(&json!({"event": "view"}), "ts"), (None, None));
+        assert_eq!(time_bounds(&json!({"ts": {"$in": [1, "x"]}}), "ts"), (None, None));
+    }
+
+    #[tokio::test]
+    async fn test_pruning_and_retention() {
+        let today = PartitionWindow::Daily.start_of(chrono::Utc::now().timestamp_micros());
+        let events = events(PartitionOptions::new("ts", PartitionWindow::Daily).retain_for(7 * DAY));
+
+        for day in 0..3 {
+            for hour in 0..4 {
+                let time = today - day * DAY + hour * 3_600 * SECOND;
+                events.insert(event(if hour % 2 == 0 { "view" } else { "like" }, time)).await.unwrap();
+            }
+        }
+        assert_eq!(events.count(), 12);
+        assert!(events.insert(event("view", today - 9 * DAY)).await.is_err());
+        assert!(events.insert(DocumentBuilder::new().string("event", "view").build()).await.is_err());
+
+        // Yesterday only, newest first
+        let query = Query {
+            filter: Some(json!({"ts": {"$gte": today - DAY, "$lt": today}, "event": "view"})),
+            sort: vec![crate::query::SortField {
+                field: "ts".to_string(),
+                direction: crate::query::SortDirection::Descending,
+            }],
+            ..Query::new()
+        };
+        assert_eq!(events.explain(query.filter.as_ref()), vec![today - DAY]);
+        let result = events.find(&query).await.unwrap();
+        let times: Vec<Timestamp> = result
+            .documents
+            .iter()
+            .filter_map(|(_, doc)| match doc.fields.get("ts") {
+                Some(Value::Timestamp(t)) => Some(*t),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(times, vec![today - DAY + 2 * 3_600 * SECOND, today - DAY]);
+
+        let stats = events.stats();
+        assert_eq!((stats.queries, stats.partitions_scanned, stats.partitions_pruned), (1, 1, 2));
+        assert_eq!(stats.partitions.len(), 3);
+        assert_eq!(stats.partitions[0].start, today - 2 * DAY);
+        assert_eq!(stats.partitions[2].max_time, Some(today + 3 * 3_600 * SECOND));
+        assert_eq!(stats.partitions[2].expires_at, Some(today + 8 * DAY));
+
+        let all: Vec<(DocumentId, Document)> = events.stream_documents(None, 2).try_collect().await.unwrap();
+        assert_eq!(all.len(), 12);
+
+        // Updates and deletes find the document's partition
+        let (id, mut doc) = all[0].clone();
+        doc.fields.insert("event".to_string(), Value::String("share".to_string()));
+        assert!(events.update_by_id(&id, doc.clone()).await.unwrap().is_some());
+        doc.fields.insert("ts".to_string(), Value::Timestamp(today));
+        assert!(events.update_by_id(&id, doc).await.is_err());
+        assert!(events.delete_by_id(&id).await.unwrap());
+        assert_eq!(events.count(), 11);
+
+        // A week on, the two older days are dropped whole
+        let report = events.maintain(today + 7 * DAY).await;
+        assert_eq!(report.dropped_partitions, vec![today - 2 * DAY, today - DAY]);
+        assert_eq!(report.dropped_documents, 7);
+        assert_eq!(events.count(), 4);
+        assert!(events.find_by_id(&id).await.unwrap().is_none());
+
+        assert_eq!(events.drop_partition(today).await.unwrap().map(|stats| stats.documents), Some(4));
+        let stats = events.stats();
+        assert_eq!((stats.documents, stats.dropped_partitions, stats.dropped_documents), (0, 3, 11));
+        assert!(PartitionedCollection::new(
+            "events".to_string(),
+            "analytics".to_string(),
+            PartitionOptions::new("ts", PartitionWindow::Weekly).retain_for(0),
+            Arc::new(|_: &str, _| Ok(Arc::new(GraphEngine::new()?) as _)),
+            Arc::new(Oplog::new()),
+            Arc::new(AtomicU64::new(0)),
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_partitions_destroy_their_storage() {
+        let today = PartitionWindow::Daily.start_of(chrono::Utc::now().timestamp_micros());
+        // Keep a handle on every partition's engine to look into it after the drop
+        let engines: Arc<parking_lot::Mutex<Vec<Arc<dyn StorageEngine>>>> = Arc::default();
+        let opened = engines.clone();
+        let storage: StorageFactory = Arc::new(move |_: &str, _| {
+            let engine: Arc<dyn StorageEngine> = Arc::new(GraphEngine::new()?);
+            opened.lock().push(engine.clone());
+            Ok(engine)
+        });
+        let events = PartitionedCollection::new(
+            "events".to_string(),
+            "analytics".to_string(),
+            PartitionOptions::new("ts", PartitionWindow::Daily).retain_for(7 * DAY),
+            storage,
+            Arc::new(Oplog::new()),
+            Arc::new(AtomicU64::new(0)),
+        )
+        .unwrap();
+
+        for time in [today - DAY, today - DAY + SECOND, today] {
+            events.insert(event("view", time)).await.unwrap();
+        }
+        let engines: Vec<Arc<dyn StorageEngine>> = engines.lock().clone();
+        assert_eq!(engines.len(), 2);
+        assert_eq!(engines[0].scan(None, 10).await.unwrap().len(), 2);
+
+        let report = events.maintain(today + 7 * DAY).await;
+        assert_eq!(report.dropped_partitions, vec![today - DAY]);
+        assert!(engines[0].scan(None, 10).await.unwrap().is_empty());
+        assert_eq!(engines[1].scan(None, 10).await.unwrap().len(), 1);
+
+        events.drop_partition(today).await.unwrap();
+        assert!(engines[1].scan(None, 10).await.unwrap().is_empty());
+        assert!(events.drop_partition(today).await.unwrap().is_none());
+    }
+}
@@ -7,9 +7,16 @@
 //! Configuration management
 
 use crate::{Result, LargetableError, StorageEngine};
+use crate::query::QueryCacheConfig;
+use crate::storage::cache::DocumentCacheConfig;
+use crate::engine::memory_limits::MemoryLimitsConfig;
+use crate::replication::HistoryConfig;
+use crate::network::compression::CompressionOptions;
+use crate::network::{TlsVersion, WireCompression};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use tracing::{debug, info};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info, warn};
 
 /// Server configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +25,9 @@ pub struct ServerConfig {
     pub host: String,
     /// Server port
     pub port: u16,
+    /// Port of the gRPC API, served next to the HTTP API when set; query routers do not serve it
+    #[serde(default)]
+    pub grpc_port: Option<u16>,
     /// Default storage engine
     pub default_storage_engine: StorageEngine,
     /// Data directory
@@ -28,7 +38,7 @@ pub struct ServerConfig {
     pub max_connections: usize,
     /// Worker threads
     pub worker_threads: usize,
-    /// Memory limit in MB
+    /// Memory limit in MB, shared by the sorts, groups and index builds of every operation
     pub memory_limit_mb: usize,
     /// Enable compression
     pub enable_compression: bool,
@@ -36,6 +46,322 @@ pub struct ServerConfig {
     pub enable_replication: bool,
     /// Replication factor
     pub replication_factor: usize,
+    /// Query result caching; absent from older config files
+    #[serde(default)]
+    pub query_cache: QueryCacheSettings,
+    /// Caching of hot documents in front of the storage engines; absent from older config files
+    #[serde(default)]
+    pub document_cache: DocumentCacheSettings,
+    /// Query router role for sharded clusters; absent from older config files
+    #[serde(default)]
+    pub sharding: ShardingSettings,
+    /// Users, roles and SCRAM authentication; absent from older config files
+    #[serde(default)]
+    pub auth: AuthSettings,
+    /// TLS for the HTTP and gRPC listeners; absent from older config files
+    #[serde(default)]
+    pub tls: TlsSettings,
+    /// Compression of HTTP and gRPC traffic; absent from older config files
+    #[serde(default)]
+    pub wire_compression: WireCompressionSettings,
+    /// Encryption of stored documents; absent from older config files
+    #[serde(default)]
+    pub encryption: EncryptionSettings,
+    /// Deadlines of client operations; absent from older config files
+    #[serde(default)]
+    pub deadlines: DeadlineSettings,
+    /// Memory of each operation and spilling to disk; absent from older config files
+    #[serde(default)]
+    pub query_memory: QueryMemorySettings,
+    /// Oplog history kept for snapshot reads and incremental backups; absent from older config files
+    #[serde(default)]
+    pub history: HistorySettings,
+}
+
+/// Encryption at rest settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionSettings {
+    /// Seal documents written by the LSM and B-tree engines with AES-256-GCM
+    pub enabled: bool,
+    /// Base64 file with the 256-bit master key wrapping the data keys; keep it off the data volume
+    pub master_key_path: String,
+    /// Data keys, wrapped by the master key
+    pub keyring_path: String,
+    /// Seconds between automatic key rotations; 0 rotates only on request
+    pub rotation_interval_secs: u64,
+    /// Documents resealed per batch while a rotation runs
+    pub rotation_batch_size: usize,
+}
+
+impl Default for EncryptionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            master_key_path: "./certs/master.key".to_string(),
+            keyring_path: "./data/keyring.json".to_string(),
+            rotation_interval_secs: 0,
+            rotation_batch_size: 1000,
+        }
+    }
+}
+
+/// TLS settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    /// Serve HTTP and gRPC over TLS only
+    pub enabled: bool,
+    /// PEM certificate chain, leaf first
+    pub cert_path: String,
+    /// PEM private key of the leaf certificate
+    pub key_path: String,
+    /// Oldest protocol version accepted from clients
+    pub min_version: TlsVersion,
+}
+
+impl Default for TlsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_path: "./certs/server.pem".to_string(),
+            key_path: "./certs/server.key".to_string(),
+            min_version: TlsVersion::Tls12,
+        }
+    }
+}
+
+/// Wire compression settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WireCompressionSettings {
+    /// Compress responses for clients that accept it
+    pub enabled: bool,
+    /// HTTP algorithms in order of preference; gRPC always uses zstd
+    pub algorithms: Vec<WireCompression>,
+    /// Responses smaller than this are sent uncompressed
+    pub min_size_bytes: usize,
+    /// Zstd level for HTTP responses, 1 (fastest) to 22
+    pub zstd_level: i32,
+}
+
+impl Default for WireCompressionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            algorithms: vec![WireCompression::Zstd, WireCompression::Lz4],
+            min_size_bytes: 4096,
+            zstd_level: 3,
+        }
+    }
+}
+
+impl WireCompressionSettings {
+    /// Middleware options for these settings
+    pub fn to_options(&self) -> CompressionOptions {
+        CompressionOptions {
+            algorithms: self.algorithms.clone(),
+            min_size_bytes: self.min_size_bytes,
+            zstd_level: self.zstd_level,
+        }
+    }
+}
+
+/// Authentication settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthSettings {
+    /// Require SCRAM-SHA-256 authentication and check roles on every operation
+    pub enabled: bool,
+    /// Users and their roles and credentials
+    pub users_path: String,
+    /// Seconds a session token stays valid after authentication
+    pub session_ttl_secs: u64,
+    /// PBKDF2 iterations for passwords set on this server
+    pub scram_iterations: u32,
+    /// Password of the `admin` user created when the users file is empty; never written back
+    #[serde(skip_serializing)]
+    pub initial_admin_password: Option<String>,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            users_path: "./data/users.json".to_string(),
+            session_ttl_secs: 3600,
+            scram_iterations: crate::auth::authentication::DEFAULT_ITERATIONS,
+            initial_admin_password: None,
+        }
+    }
+}
+
+/// Sharding settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShardingSettings {
+    /// Run as a query router in front of shards instead of serving local data
+    pub router: bool,
+    /// Config catalog of shards and chunk ownership
+    pub catalog_path: String,
+    /// Seconds between automatic balancing rounds; 0 balances only on request
+    pub balancer_interval_secs: u64,
+}
+
+impl Default for ShardingSettings {
+    fn default() -> Self {
+        Self {
+            router: false,
+            catalog_path: "./data/sharding.json".to_string(),
+            balancer_interval_secs: 0,
+        }
+    }
+}
+
+/// Query cache settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryCacheSettings {
+    /// Cache query results
+    pub enabled: bool,
+    /// Maximum cached results
+    pub max_entries: usize,
+    /// Memory cap in MB
+    pub memory_limit_mb: usize,
+    /// Seconds a result may be served after it was cached
+    pub ttl_secs: u64,
+}
+
+impl Default for QueryCacheSettings {
+    fn default() -> Self {
+        let defaults = QueryCacheConfig::default();
+        Self {
+            enabled: defaults.enabled,
+            max_entries: defaults.max_entries,
+            memory_limit_mb: defaults.max_memory_bytes / (1024 * 1024),
+            ttl_secs: defaults.ttl.as_secs(),
+        }
+    }
+}
+
+impl QueryCacheSettings {
+    /// Engine-level cache configuration for these settings
+    pub fn to_config(&self) -> QueryCacheConfig {
+        QueryCacheConfig {
+            enabled: self.enabled,
+            max_entries: self.max_entries,
+            max_memory_bytes: self.memory_limit_mb * 1024 * 1024,
+            ttl: Duration::from_secs(self.ttl_secs),
+            ..QueryCacheConfig::default()
+        }
+    }
+}
+
+/// Document cache settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentCacheSettings {
+    /// Cache documents read from the storage engines
+    pub enabled: bool,
+    /// Maximum cached documents across all databases
+    pub max_documents: usize,
+    /// Memory cap in MB
+    pub memory_limit_mb: usize,
+}
+
+impl Default for DocumentCacheSettings {
+    fn default() -> Self {
+        let defaults = DocumentCacheConfig::default();
+        Self {
+            enabled: defaults.enabled,
+            max_documents: defaults.max_documents,
+            memory_limit_mb: defaults.max_memory_bytes / (1024 * 1024),
+        }
+    }
+}
+
+impl DocumentCacheSettings {
+    /// Engine-level cache configuration for these settings
+    pub fn to_config(&self) -> DocumentCacheConfig {
+        DocumentCacheConfig {
+            enabled: self.enabled,
+            max_documents: self.max_documents,
+            max_memory_bytes: self.memory_limit_mb * 1024 * 1024,
+        }
+    }
+}
+
+/// Operation deadline settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadlineSettings {
+    /// Milliseconds an operation may run when the client sends no timeout; 0 lets it run to completion
+    pub default_timeout_ms: u64,
+    /// Longest timeout a client may ask for in milliseconds; 0 leaves it uncapped
+    pub max_timeout_ms: u64,
+}
+
+impl DeadlineSettings {
+    pub fn default_timeout(&self) -> Option<Duration> {
+        (self.default_timeout_ms > 0).then(|| Duration::from_millis(self.default_timeout_ms))
+    }
+
+    pub fn max_timeout(&self) -> Option<Duration> {
+        (self.max_timeout_ms > 0).then(|| Duration::from_millis(self.max_timeout_ms))
+    }
+}
+
+/// Per-operation memory settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryMemorySettings {
+    /// Most one operation may hold in MB
+    pub query_limit_mb: usize,
+    /// Write sorts and groups over a limit to temporary files rather than failing them
+    pub spill_to_disk: bool,
+    /// Directory of spill files; empty for `_tmp` in the data directory
+    pub spill_dir: String,
+}
+
+impl Default for QueryMemorySettings {
+    fn default() -> Self {
+        let defaults = MemoryLimitsConfig::default();
+        Self {
+            query_limit_mb: defaults.query_limit_bytes / (1024 * 1024),
+            spill_to_disk: defaults.spill_to_disk,
+            spill_dir: String::new(),
+        }
+    }
+}
+
+/// Oplog history settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    /// Most oplog entries kept when no backup or snapshot read needs older ones
+    pub max_entries: usize,
+    /// Seconds of history kept for snapshot reads; 0 keeps entries until `max_entries` is reached
+    pub retention_secs: u64,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        let defaults = HistoryConfig::default();
+        Self {
+            max_entries: defaults.max_entries,
+            retention_secs: defaults.retention.map_or(0, |retention| retention.as_secs()),
+        }
+    }
+}
+
+impl HistorySettings {
+    /// Oplog history configuration for these settings
+    pub fn to_config(&self) -> HistoryConfig {
+        HistoryConfig {
+            max_entries: self.max_entries,
+            retention: (self.retention_secs > 0).then(|| Duration::from_secs(self.retention_secs)),
+        }
+    }
 }
 
 impl Default for ServerConfig {
@@ -43,6 +369,7 @@ impl Default for ServerConfig {
         Self {
             host: "127.0.0.1".to_string(),
             port: 27017, // MongoDB compatible port
+            grpc_port: None,
             default_storage_engine: StorageEngine::Lsm,
             data_dir: "./data".to_string(),
             log_level: "info".to_string(),
@@ -52,6 +379,16 @@ impl Default for ServerConfig {
             enable_compression: true,
             enable_replication: false,
             replication_factor: 1,
+            query_cache: QueryCacheSettings::default(),
+            document_cache: DocumentCacheSettings::default(),
+            sharding: ShardingSettings::default(),
+            auth: AuthSettings::default(),
+            tls: TlsSettings::default(),
+            wire_compression: WireCompressionSettings::default(),
+            encryption: EncryptionSettings::default(),
+            deadlines: DeadlineSettings::default(),
+            query_memory: QueryMemorySettings::default(),
+            history: HistorySettings::default(),
         }
     }
 }
@@ -109,14 +446,17 @@ impl ServerConfig {
             }
         }
         
+        if let Ok(grpc_port) = std::env::var("LARGETABLE_GRPC_PORT") {
+            if let Ok(port_num) = grpc_port.parse() {
+                self.grpc_port = Some(port_num);
+            }
+        }
+        
         if let Ok(engine) = std::env::var("LARGETABLE_STORAGE_ENGINE") {
-            self.default_storage_engine = match engine.to_lowercase().as_str() {
-                "lsm" => StorageEngine::Lsm,
-                "btree" => StorageEngine::BTree,
-                "columnar" => StorageEngine::Columnar,
-                "graph" => StorageEngine::Graph,
-                _ => StorageEngine::Lsm,
-            };
+            self.default_storage_engine = engine.parse().unwrap_or_else(|e| {
+                warn!("{}, falling back to lsm", e);
+                StorageEngine::Lsm
+            });
         }
         
         if let Ok(data_dir) = std::env::var("LARGETABLE_DATA_DIR") {
@@ -145,6 +485,20 @@ impl ServerConfig {
             }
         }
         
+        if let Ok(memory) = std::env::var("LARGETABLE_QUERY_MEMORY_LIMIT_MB") {
+            if let Ok(memory_num) = memory.parse() {
+                self.query_memory.query_limit_mb = memory_num;
+            }
+        }
+        
+        if let Ok(spill) = std::env::var("LARGETABLE_SPILL_TO_DISK") {
+            self.query_memory.spill_to_disk = spill.to_lowercase() == "true";
+        }
+        
+        if let Ok(spill_dir) = std::env::var("LARGETABLE_SPILL_DIR") {
+            self.query_memory.spill_dir = spill_dir;
+        }
+        
         if let Ok(compression) = std::env::var("LARGETABLE_ENABLE_COMPRESSION") {
             self.enable_compression = compression.to_lowercase() == "true";
         }
@@ -158,14 +512,198 @@ impl ServerConfig {
                 self.replication_factor = factor_num;
             }
         }
+        
+        if let Ok(query_cache) = std::env::var("LARGETABLE_QUERY_CACHE") {
+            self.query_cache.enabled = query_cache.to_lowercase() == "true";
+        }
+        
+        if let Ok(entries) = std::env::var("LARGETABLE_QUERY_CACHE_MAX_ENTRIES") {
+            if let Ok(entries_num) = entries.parse() {
+                self.query_cache.max_entries = entries_num;
+            }
+        }
+        
+        if let Ok(memory) = std::env::var("LARGETABLE_QUERY_CACHE_MEMORY_MB") {
+            if let Ok(memory_num) = memory.parse() {
+                self.query_cache.memory_limit_mb = memory_num;
+            }
+        }
+        
+        if let Ok(ttl) = std::env::var("LARGETABLE_QUERY_CACHE_TTL_SECS") {
+            if let Ok(ttl_secs) = ttl.parse() {
+                self.query_cache.ttl_secs = ttl_secs;
+            }
+        }
+        
+        if let Ok(document_cache) = std::env::var("LARGETABLE_DOCUMENT_CACHE") {
+            self.document_cache.enabled = document_cache.to_lowercase() == "true";
+        }
+        
+        if let Ok(documents) = std::env::var("LARGETABLE_DOCUMENT_CACHE_MAX_DOCUMENTS") {
+            if let Ok(documents_num) = documents.parse() {
+                self.document_cache.max_documents = documents_num;
+            }
+        }
+        
+        if let Ok(memory) = std::env::var("LARGETABLE_DOCUMENT_CACHE_MEMORY_MB") {
+            if let Ok(memory_num) = memory.parse() {
+                self.document_cache.memory_limit_mb = memory_num;
+            }
+        }
+        
+        if let Ok(entries) = std::env::var("LARGETABLE_HISTORY_MAX_ENTRIES") {
+            if let Ok(entries_num) = entries.parse() {
+                self.history.max_entries = entries_num;
+            }
+        }
+        
+        if let Ok(retention) = std::env::var("LARGETABLE_HISTORY_RETENTION_SECS") {
+            if let Ok(retention_secs) = retention.parse() {
+                self.history.retention_secs = retention_secs;
+            }
+        }
+        
+        if let Ok(timeout) = std::env::var("LARGETABLE_DEFAULT_TIMEOUT_MS") {
+            if let Ok(timeout_ms) = timeout.parse() {
+                self.deadlines.default_timeout_ms = timeout_ms;
+            }
+        }
+        
+        if let Ok(timeout) = std::env::var("LARGETABLE_MAX_TIMEOUT_MS") {
+            if let Ok(timeout_ms) = timeout.parse() {
+                self.deadlines.max_timeout_ms = timeout_ms;
+            }
+        }
+        
+        if let Ok(router) = std::env::var("LARGETABLE_SHARDING_ROUTER") {
+            self.sharding.router = router.to_lowercase() == "true";
+        }
+        
+        if let Ok(catalog_path) = std::env::var("LARGETABLE_SHARDING_CATALOG") {
+            self.sharding.catalog_path = catalog_path;
+        }
+        
+        if let Ok(interval) = std::env::var("LARGETABLE_BALANCER_INTERVAL_SECS") {
+            if let Ok(interval_secs) = interval.parse() {
+                self.sharding.balancer_interval_secs = interval_secs;
+            }
+        }
+        
+        if let Ok(auth) = std::env::var("LARGETABLE_AUTH") {
+            self.auth.enabled = auth.to_lowercase() == "true";
+        }
+        
+        if let Ok(users_path) = std::env::var("LARGETABLE_AUTH_USERS_PATH") {
+            self.auth.users_path = users_path;
+        }
+        
+        if let Ok(ttl) = std::env::var("LARGETABLE_AUTH_SESSION_TTL_SECS") {
+            if let Ok(ttl_secs) = ttl.parse() {
+                self.auth.session_ttl_secs = ttl_secs;
+            }
+        }
+        
+        if let Ok(iterations) = std::env::var("LARGETABLE_AUTH_SCRAM_ITERATIONS") {
+            if let Ok(iterations_num) = iterations.parse() {
+                self.auth.scram_iterations = iterations_num;
+            }
+        }
+        
+        if let Ok(password) = std::env::var("LARGETABLE_AUTH_ADMIN_PASSWORD") {
+            self.auth.initial_admin_password = Some(password);
+        }
+        
+        if let Ok(tls) = std::env::var("LARGETABLE_TLS") {
+            self.tls.enabled = tls.to_lowercase() == "true";
+        }
+        
+        if let Ok(cert_path) = std::env::var("LARGETABLE_TLS_CERT") {
+            self.tls.cert_path = cert_path;
+        }
+        
+        if let Ok(key_path) = std::env::var("LARGETABLE_TLS_KEY") {
+            self.tls.key_path = key_path;
+        }
+        
+        if let Ok(min_version) = std::env::var("LARGETABLE_TLS_MIN_VERSION") {
+            match min_version.parse() {
+                Ok(version) => self.tls.min_version = version,
+                Err(e) => warn!("{}, keeping TLS {:?}", e, self.tls.min_version),
+            }
+        }
+        
+        if let Ok(compression) = std::env::var("LARGETABLE_WIRE_COMPRESSION") {
+            self.wire_compression.enabled = compression.to_lowercase() == "true";
+        }
+        
+        if let Ok(algorithms) = std::env::var("LARGETABLE_WIRE_COMPRESSION_ALGORITHMS") {
+            match algorithms.split(',').map(str::parse).collect::<Result<Vec<_>>>() {
+                Ok(algorithms) => self.wire_compression.algorithms = algorithms,
+                Err(e) => warn!("{}, keeping {:?}", e, self.wire_compression.algorithms),
+            }
+        }
+        
+        if let Ok(min_size) = std::env::var("LARGETABLE_WIRE_COMPRESSION_MIN_BYTES") {
+            if let Ok(min_size_bytes) = min_size.parse() {
+                self.wire_compression.min_size_bytes = min_size_bytes;
+            }
+        }
+        
+        if let Ok(level) = std::env::var("LARGETABLE_WIRE_COMPRESSION_ZSTD_LEVEL") {
+            if let Ok(zstd_level) = level.parse() {
+                self.wire_compression.zstd_level = zstd_level;
+            }
+        }
+        
+        if let Ok(encryption) = std::env::var("LARGETABLE_ENCRYPTION") {
+            self.encryption.enabled = encryption.to_lowercase() == "true";
+        }
+        
+        if let Ok(master_key_path) = std::env::var("LARGETABLE_ENCRYPTION_MASTER_KEY") {
+            self.encryption.master_key_path = master_key_path;
+        }
+        
+        if let Ok(keyring_path) = std::env::var("LARGETABLE_ENCRYPTION_KEYRING") {
+            self.encryption.keyring_path = keyring_path;
+        }
+        
+        if let Ok(interval) = std::env::var("LARGETABLE_ENCRYPTION_ROTATION_INTERVAL_SECS") {
+            if let Ok(interval_secs) = interval.parse() {
+                self.encryption.rotation_interval_secs = interval_secs;
+            }
+        }
+        
+        if let Ok(batch_size) = std::env::var("LARGETABLE_ENCRYPTION_ROTATION_BATCH_SIZE") {
+            if let Ok(batch_size_num) = batch_size.parse() {
+                self.encryption.rotation_batch_size = batch_size_num;
+            }
+        }
     }
 
     /// Validate the configuration
+    /// Engine-level memory limits: `memory_limit_mb` for all operations and the per-query limit for each
+    pub fn memory_limits(&self) -> MemoryLimitsConfig {
+        let spill_dir = match self.query_memory.spill_dir.as_str() {
+            "" => Path::new(&self.data_dir).join("_tmp"),
+            dir => PathBuf::from(dir),
+        };
+        MemoryLimitsConfig {
+            query_limit_bytes: self.query_memory.query_limit_mb * 1024 * 1024,
+            global_limit_bytes: self.memory_limit_mb * 1024 * 1024,
+            spill_to_disk: self.query_memory.spill_to_disk,
+            spill_dir,
+        }
+    }
+
     pub fn validate(&self) -> Result<()> {
         if self.port == 0 {
             return Err(LargetableError::Config("Port cannot be 0".to_string()));
         }
         
+        if self.grpc_port == Some(0) || self.grpc_port == Some(self.port) {
+            return Err(LargetableError::Config("gRPC port cannot be 0 or the HTTP port".to_string()));
+        }
+        
         if self.max_connections == 0 {
             return Err(LargetableError::Config("Max connections cannot be 0".to_string()));
         }
@@ -178,10 +716,62 @@ impl ServerConfig {
             return Err(LargetableError::Config("Memory limit cannot be 0".to_string()));
         }
         
+        if self.query_memory.query_limit_mb == 0 || self.query_memory.query_limit_mb > self.memory_limit_mb {
+            return Err(LargetableError::Config("Per-query memory limit must be between 1 MB and the memory limit".to_string()));
+        }
+        
         if self.enable_replication && self.replication_factor < 2 {
             return Err(LargetableError::Config("Replication factor must be at least 2 when replication is enabled".to_string()));
         }
         
+        if self.query_cache.enabled && (self.query_cache.max_entries == 0 || self.query_cache.memory_limit_mb == 0) {
+            return Err(LargetableError::Config("Query cache entry and memory limits cannot be 0 when the cache is enabled".to_string()));
+        }
+        
+        if self.document_cache.enabled && (self.document_cache.max_documents == 0 || self.document_cache.memory_limit_mb == 0) {
+            return Err(LargetableError::Config("Document cache document and memory limits cannot be 0 when the cache is enabled".to_string()));
+        }
+        
+        if self.history.max_entries == 0 {
+            return Err(LargetableError::Config("Oplog history must keep at least one entry".to_string()));
+        }
+        
+        if self.sharding.router && self.sharding.catalog_path.is_empty() {
+            return Err(LargetableError::Config("Sharding catalog path cannot be empty when running as a router".to_string()));
+        }
+        
+        if self.auth.enabled && (self.auth.users_path.is_empty() || self.auth.session_ttl_secs == 0) {
+            return Err(LargetableError::Config("Users path and session TTL cannot be empty when authentication is enabled".to_string()));
+        }
+        
+        if self.auth.enabled && self.auth.scram_iterations < 4096 {
+            return Err(LargetableError::Config("SCRAM iterations must be at least 4096".to_string()));
+        }
+        
+        if self.tls.enabled && (self.tls.cert_path.is_empty() || self.tls.key_path.is_empty()) {
+            return Err(LargetableError::Config("TLS certificate and key paths cannot be empty when TLS is enabled".to_string()));
+        }
+        
+        if self.wire_compression.enabled && self.wire_compression.algorithms.is_empty() {
+            return Err(LargetableError::Config("Wire compression needs at least one algorithm when enabled".to_string()));
+        }
+        
+        if !(1..=22).contains(&self.wire_compression.zstd_level) {
+            return Err(LargetableError::Config("Wire compression zstd level must be between 1 and 22".to_string()));
+        }
+        
+        if self.encryption.enabled && (self.encryption.master_key_path.is_empty() || self.encryption.keyring_path.is_empty()) {
+            return Err(LargetableError::Config("Master key and keyring paths cannot be empty when encryption is enabled".to_string()));
+        }
+        
+        if self.encryption.enabled && self.encryption.rotation_batch_size == 0 {
+            return Err(LargetableError::Config("Key rotation batch size cannot be 0".to_string()));
+        }
+        
+        if self.encryption.enabled && self.default_storage_engine == StorageEngine::Columnar {
+            return Err(LargetableError::Config("The columnar engine does not support encryption at rest".to_string()));
+        }
+        
         Ok(())
     }
 }
\ No newline at end of file
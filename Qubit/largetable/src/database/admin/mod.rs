@@ -5,3 +5,199 @@
 // ===========================================
 
 //! Administrative operations
+
+use super::Collection;
+use crate::document::schema::inference::{InferredSchema, SchemaInference, DEFAULT_MAX_DEPTH};
+use crate::document::StoredDocument;
+use crate::query::aggregation::DEFAULT_SCAN_BATCH_SIZE;
+use crate::{CollectionName, DatabaseName, Document, IndexType, LargetableError, Result};
+use futures::TryStreamExt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Documents sampled for stats when the caller does not say
+pub const DEFAULT_SAMPLE_SIZE: usize = 1_000;
+
+/// The sample is held in memory, so it is capped
+pub const MAX_SAMPLE_SIZE: usize = 100_000;
+
+/// How much of a collection to sample for its stats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleOptions {
+    /// Documents to infer the schema and sizes from; 0 only counts
+    pub sample_size: usize,
+    /// Nesting below this depth is not looked inside
+    pub max_depth: usize,
+}
+
+impl Default for SampleOptions {
+    fn default() -> Self {
+        Self {
+            sample_size: DEFAULT_SAMPLE_SIZE,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+/// Size, indexes and inferred schema of a collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionStats {
+    pub database: DatabaseName,
+    pub collection: CollectionName,
+    pub document_count: u64,
+    /// Documents chosen at random for the schema and sizes; all of them when
+    /// the collection is smaller than the sample
+    pub sampled: u64,
+    /// Average stored size of the sampled documents, in bytes
+    pub avg_document_size: u64,
+    /// `avg_document_size` times `document_count`
+    pub estimated_size: u64,
+    /// Ready indexes by field
+    pub indexes: HashMap<String, IndexType>,
+    pub schema: InferredSchema,
+}
+
+impl Collection {
+    /// Count the collection and infer its schema from a uniform random sample.
+    ///
+    /// Reads every document once, keeping at most `sample_size` of them.
+    pub async fn stats(&self, options: SampleOptions) -> Result<CollectionStats> {
+        if options.sample_size > MAX_SAMPLE_SIZE {
+            return Err(LargetableError::Validation(format!(
+                "Sample size {} is over the limit of {}",
+                options.sample_size, MAX_SAMPLE_SIZE
+            )));
+        }
+
+        let sample = reservoir_sample(
+            self.stream_documents(DEFAULT_SCAN_BATCH_SIZE).map_ok(|(_, doc)| doc),
+            options.sample_size,
+        )
+        .await?;
+
+        let mut inference = SchemaInference::new(options.max_depth);
+        let mut sampled_bytes = 0;
+        for doc in &sample.documents {
+            inference.observe(doc);
+            sampled_bytes += StoredDocument::from_document(doc)?.as_bytes().len() as u64;
+        }
+        let sampled = sample.documents.len() as u64;
+        let avg_document_size = sampled_bytes.checked_div(sampled).unwrap_or(0);
+
+        debug!("Sampled {} of {} documents of collection '{}'", sampled, sample.seen, self.name);
+        Ok(CollectionStats {
+            database: self.database.clone(),
+            collection: self.name.clone(),
+            document_count: sample.seen,
+            sampled,
+            avg_document_size,
+            estimated_size: avg_document_size * sample.seen,
+            indexes: self.list_indexes().await?,
+            schema: inference.finish(),
+        })
+    }
+}
+
+struct Sample {
+    documents: Vec<Document>,
+    /// Documents read, sampled or not
+    seen: u64,
+}
+
+/// Keep a uniform random sample of `size` documents from a stream of unknown length
+async fn reservoir_sample<S>(mut documents: S, size: usize) -> Result<Sample>
+where
+    S: futures::TryStream<Ok = Document, Error = LargetableError> + Unpin,
+{
+    // `ThreadRng` is not `Send`, and this runs inside request futures
+    let mut rng = StdRng::from_entropy();
+    let mut sample = Sample { documents: Vec::with_capacity(size.min(DEFAULT_SAMPLE_SIZE)), seen: 0 };
+
+    while let Some(doc) = documents.try_next().await? {
+        if sample.documents.len() < size {
+            sample.documents.push(doc);
+        } else {
+            let slot = rng.gen_range(0..=sample.seen);
+            if slot < size as u64 {
+                sample.documents[slot as usize] = doc;
+            }
+        }
+        sample.seen += 1;
+    }
+    Ok(sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::document::DocumentUtils;
+    use crate::replication::Oplog;
+    use crate::storage::engines::graph::GraphEngine;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    async fn collection() -> Arc<Collection> {
+        // The graph engine keeps documents in memory
+        let database = Database::with_storage_engine(
+            "app".to_string(),
+            Arc::new(GraphEngine::new().unwrap()),
+            Arc::new(Oplog::new()),
+        );
+        database.collection("users".to_string()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_and_describe_the_collection() {
+        let users = collection().await;
+        for n in 0..30 {
+            let mut user = json!({"username": format!("user{}", n), "profile": {"age": n}});
+            if n % 3 == 0 {
+                user["verified"] = json!(true);
+            }
+            users.insert(DocumentUtils::from_json(user).unwrap()).await.unwrap();
+        }
+
+        let stats = users.stats(SampleOptions::default()).await.unwrap();
+        assert_eq!((stats.document_count, stats.sampled), (30, 30));
+        assert!(stats.avg_document_size > 0);
+        assert_eq!(stats.estimated_size, stats.avg_document_size * 30);
+        assert!(stats.schema.field("username").unwrap().required);
+        assert!(stats.schema.path("profile.age").unwrap().required);
+        assert_eq!(stats.schema.field("verified").unwrap().count, 10);
+
+        // A smaller sample still counts every document
+        let stats = users.stats(SampleOptions { sample_size: 5, ..SampleOptions::default() }).await.unwrap();
+        assert_eq!((stats.document_count, stats.sampled, stats.schema.documents), (30, 5, 5));
+        assert!(stats.schema.field("username").unwrap().required);
+
+        let stats = users.stats(SampleOptions { sample_size: 0, ..SampleOptions::default() }).await.unwrap();
+        assert_eq!((stats.document_count, stats.sampled, stats.avg_document_size), (30, 0, 0));
+        assert!(stats.schema.fields.is_empty());
+
+        let too_many = SampleOptions { sample_size: MAX_SAMPLE_SIZE + 1, ..SampleOptions::default() };
+        assert!(matches!(users.stats(too_many).await, Err(LargetableError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reservoir_sample_is_uniform() {
+        // Each of 10 documents should land in a sample of 5 about half the time
+        let documents: Vec<Document> = (0..10)
+            .map(|n| DocumentUtils::from_json(json!({"n": n})).unwrap())
+            .collect();
+        let mut picked = HashMap::new();
+        for _ in 0..2_000 {
+            let stream = futures::stream::iter(documents.clone().into_iter().map(Ok));
+            let sample = reservoir_sample(stream, 5).await.unwrap();
+            assert_eq!((sample.documents.len(), sample.seen), (5, 10));
+            for doc in sample.documents {
+                *picked.entry(doc.id).or_insert(0) += 1;
+            }
+        }
+        assert_eq!(picked.len(), 10);
+        assert!(picked.values().all(|count| (800..1_200).contains(count)), "{:?}", picked);
+    }
+}
@@ -11,19 +11,57 @@ pub mod catalog;
 pub mod migrations;
 pub mod namespace;
 
-use crate::{Result, DocumentId, Document, StorageEngine, CollectionName, DatabaseName};
-use crate::storage::engines::create_storage_engine;
+use crate::{Result, DocumentId, Document, StorageEngine, CollectionName, DatabaseName, LargetableError, Timestamp};
+use crate::collection::partitioning::{PartitionOptions, PartitionedCollection};
+use crate::document::rules::WriteRules;
+use crate::document::StoredDocument;
+use crate::engine::deadline::{checkpoint, CHECKPOINT_INTERVAL};
+use crate::storage::cache::DocumentCache;
+use crate::storage::encryption::PageCipher;
+use crate::storage::engines::{create_storage_engine, directory_name};
+use crate::storage::engines::graph::GraphEngine;
 use crate::storage::StorageEngine as StorageEngineTrait;
-use std::collections::HashMap;
+use crate::index::IndexManager;
+use crate::index::build::IndexBuildStatus;
+use crate::index::IndexQuery;
+use crate::query::aggregation::{self, DocumentStream, LookupSource};
+use crate::query::{AccessPath, Query, QueryPlan, QueryPlanner, QueryResult};
+use crate::replication::{Oplog, OplogOperation};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info};
 
+/// Source of collection versions, global so a recreated database never
+/// reuses a version its predecessor handed out
+static WRITE_VERSIONS: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn next_write_version() -> u64 {
+    WRITE_VERSIONS.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Opens the storage engine of one partition of a partitioned collection,
+/// given the collection's name and the start of the partition's window;
+/// every partition gets storage of its own
+pub type StorageFactory = Arc<dyn Fn(&str, Timestamp) -> Result<Arc<dyn StorageEngineTrait>> + Send + Sync>;
+
 /// Main database instance
 pub struct Database {
     name: DatabaseName,
     storage_engine: Arc<dyn StorageEngineTrait>,
     collections: Arc<RwLock<HashMap<CollectionName, Arc<Collection>>>>,
+    /// Time-partitioned collections, each partition with its own storage engine
+    partitioned: Arc<RwLock<HashMap<CollectionName, Arc<PartitionedCollection>>>>,
+    partition_storage: StorageFactory,
+    oplog: Arc<Oplog>,
+    /// Shared by every collection, since they all read the same storage engine
+    version: Arc<AtomicU64>,
 }
 
 /// Collection within a database
@@ -31,25 +69,67 @@ pub struct Collection {
     name: CollectionName,
     database: DatabaseName,
     storage_engine: Arc<dyn StorageEngineTrait>,
-    indexes: Arc<RwLock<HashMap<String, crate::IndexType>>>,
+    index_manager: Arc<IndexManager>,
+    oplog: Arc<Oplog>,
+    version: Arc<AtomicU64>,
+    /// Swapped whole under the write permit, so each write sees one rule set
+    rules: parking_lot::RwLock<Arc<WriteRules>>,
 }
 
 impl Database {
-    /// Create a new database with specified storage engine
-    pub fn new(name: DatabaseName, storage_engine: crate::StorageEngine) -> Result<Self> {
-        let engine = create_storage_engine(storage_engine)?;
+    /// Create a new database with specified storage engine, keeping its files
+    /// in a directory named after it under `data_dir`, logging writes to
+    /// `oplog`, sealing pages with `cipher` when encryption at rest is on and
+    /// reading through `cache` when it is enabled
+    pub fn new(
+        name: DatabaseName,
+        storage_engine: crate::StorageEngine,
+        data_dir: &Path,
+        oplog: Arc<Oplog>,
+        cipher: Option<Arc<PageCipher>>,
+        cache: &Arc<DocumentCache>,
+    ) -> Result<Self> {
+        let dir = data_dir.join(directory_name(&name)?);
+        let engine = cache.wrap(Arc::from(create_storage_engine(storage_engine, &dir.join("documents"), cipher.clone())?));
+        let partition_cache = cache.clone();
+        let partition_storage: StorageFactory = Arc::new(move |collection: &str, start: Timestamp| {
+            let partition_dir = dir.join("partitions").join(directory_name(collection)?).join(start.to_string());
+            Ok(partition_cache.wrap(Arc::from(create_storage_engine(storage_engine, &partition_dir, cipher.clone())?)))
+        });
         
         info!("Created database '{}' with {:?} storage engine", name, storage_engine);
         
-        Ok(Self {
+        Ok(Self::with_storage_engine(name, engine, oplog).with_partition_storage(partition_storage))
+    }
+
+    /// Create a database over an already opened storage engine; partitions
+    /// are kept in memory unless `with_partition_storage` says otherwise
+    pub fn with_storage_engine(name: DatabaseName, storage_engine: Arc<dyn StorageEngineTrait>, oplog: Arc<Oplog>) -> Self {
+        Self {
             name,
-            storage_engine: Arc::new(engine),
+            storage_engine,
             collections: Arc::new(RwLock::new(HashMap::new())),
-        })
+            partitioned: Arc::new(RwLock::new(HashMap::new())),
+            partition_storage: Arc::new(|_: &str, _| Ok(Arc::new(GraphEngine::new()?) as Arc<dyn StorageEngineTrait>)),
+            oplog,
+            version: Arc::new(AtomicU64::new(next_write_version())),
+        }
+    }
+
+    /// Open the partitions of partitioned collections with `storage`
+    pub fn with_partition_storage(mut self, storage: StorageFactory) -> Self {
+        self.partition_storage = storage;
+        self
     }
 
     /// Get or create a collection
     pub async fn collection(&self, name: CollectionName) -> Result<Arc<Collection>> {
+        if self.partitioned.read().await.contains_key(&name) {
+            return Err(LargetableError::Validation(format!(
+                "Collection '{}' is partitioned and has no single storage",
+                name
+            )));
+        }
         let mut collections = self.collections.write().await;
         
         if let Some(collection) = collections.get(&name) {
@@ -60,6 +140,8 @@ impl Database {
             name.clone(),
             self.name.clone(),
             self.storage_engine.clone(),
+            self.oplog.clone(),
+            self.version.clone(),
         ));
         
         collections.insert(name, collection.clone());
@@ -68,7 +150,54 @@ impl Database {
         Ok(collection)
     }
 
-    /// List all collections in the database
+    /// Create a collection partitioned by time, or get it if it exists with the same options
+    pub async fn create_partitioned_collection(
+        &self,
+        name: CollectionName,
+        options: PartitionOptions,
+    ) -> Result<Arc<PartitionedCollection>> {
+        let mut partitioned = self.partitioned.write().await;
+        if let Some(collection) = partitioned.get(&name) {
+            if *collection.options() != options {
+                return Err(LargetableError::Validation(format!(
+                    "Collection '{}' is already partitioned with other options",
+                    name
+                )));
+            }
+            return Ok(collection.clone());
+        }
+        if self.collections.read().await.contains_key(&name) {
+            return Err(LargetableError::Validation(format!(
+                "Collection '{}' already exists unpartitioned",
+                name
+            )));
+        }
+
+        let collection = Arc::new(PartitionedCollection::new(
+            name.clone(),
+            self.name.clone(),
+            options,
+            self.partition_storage.clone(),
+            self.oplog.clone(),
+            self.version.clone(),
+        )?);
+        partitioned.insert(name, collection.clone());
+        debug!("Created partitioned collection '{}' in database '{}'", collection.name(), self.name);
+        Ok(collection)
+    }
+
+    /// The partitioned collection `name`, if it is one
+    pub async fn partitioned_collection(&self, name: &str) -> Option<Arc<PartitionedCollection>> {
+        self.partitioned.read().await.get(name).cloned()
+    }
+
+    /// Every partitioned collection in the database
+    pub async fn partitioned_collections(&self) -> Vec<Arc<PartitionedCollection>> {
+        self.partitioned.read().await.values().cloned().collect()
+    }
+
+    /// List all collections in the database; partitioned collections are
+    /// listed by `partitioned_collections`
     pub async fn list_collections(&self) -> Result<Vec<CollectionName>> {
         let collections = self.collections.read().await;
         Ok(collections.keys().cloned().collect())
@@ -76,39 +205,127 @@ impl Database {
 
     /// Drop a collection
     pub async fn drop_collection(&self, name: &CollectionName) -> Result<bool> {
+        let partitioned = self.partitioned.write().await.remove(name).is_some();
         let mut collections = self.collections.write().await;
-        let removed = collections.remove(name).is_some();
+        let removed = collections.remove(name).is_some() || partitioned;
         
         if removed {
+            self.version.store(next_write_version(), Ordering::Release);
             debug!("Dropped collection '{}' from database '{}'", name, self.name);
         }
         
         Ok(removed)
     }
 
+    /// Storage engine shared by the collections of this database
+    pub fn storage_engine(&self) -> &Arc<dyn StorageEngineTrait> {
+        &self.storage_engine
+    }
+
+    /// Operation log this database writes to
+    pub fn oplog(&self) -> &Arc<Oplog> {
+        &self.oplog
+    }
+
     /// Get database name
     pub fn name(&self) -> &DatabaseName {
         &self.name
     }
 }
 
+/// `$lookup` against collections of this database, using an index on the
+/// foreign field when one is ready and a streaming scan otherwise
+#[async_trait]
+impl LookupSource for Database {
+    async fn lookup(&self, from: &str, foreign_field: &str, value: &crate::Value) -> Result<Vec<Document>> {
+        let Some(collection) = self.collections.read().await.get(from).cloned() else {
+            return Ok(Vec::new());
+        };
+
+        let indexed = collection.index_manager.list_indexes().await?
+            .iter()
+            .any(|(field, index_type)| {
+                field == foreign_field && matches!(index_type, crate::IndexType::BTree | crate::IndexType::Hash)
+            });
+        if indexed {
+            let query = IndexQuery::Exact { field: foreign_field.to_string(), value: value.clone() };
+            let mut documents = Vec::new();
+            for id in collection.index_manager.search(&query).await? {
+                if let Some(doc) = collection.find_by_id(&id).await? {
+                    documents.push(doc);
+                }
+            }
+            return Ok(documents);
+        }
+
+        collection
+            .stream_documents(aggregation::DEFAULT_SCAN_BATCH_SIZE)
+            .try_filter_map(|(_, doc)| {
+                let matched = crate::document::DocumentUtils::get_field(&doc, foreign_field)
+                    .is_some_and(|field| aggregation::values_equal(field, value));
+                futures::future::ready(Ok(matched.then_some(doc)))
+            })
+            .try_collect()
+            .await
+    }
+}
+
 impl Collection {
     /// Create a new collection
     pub fn new(
         name: CollectionName,
         database: DatabaseName,
         storage_engine: Arc<dyn StorageEngineTrait>,
+        oplog: Arc<Oplog>,
+        version: Arc<AtomicU64>,
     ) -> Self {
         Self {
+            index_manager: Arc::new(IndexManager::new(name.clone())),
             name,
             database,
             storage_engine,
-            indexes: Arc::new(RwLock::new(HashMap::new())),
+            oplog,
+            version,
+            rules: parking_lot::RwLock::new(Arc::new(WriteRules::default())),
         }
     }
 
+    /// Changes whenever a write may have changed what a query returns.
+    ///
+    /// Read it before running a query: a write that lands while the query
+    /// runs bumps it afterwards, so the result is never tagged as newer
+    /// than the data it saw.
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    fn bump_version(&self) {
+        self.version.store(next_write_version(), Ordering::Release);
+    }
+
     /// Insert a document into the collection
-    pub async fn insert(&self, mut document: Document) -> Result<DocumentId> {
+    pub async fn insert(&self, document: Document) -> Result<DocumentId> {
+        let _permit = self.oplog.write_permit().await;
+        self.insert_unlocked(document).await
+    }
+
+    /// Insert documents in order under a single write permit.
+    ///
+    /// Stops at the first document that fails; the ones before it stay inserted.
+    pub async fn insert_many(&self, documents: Vec<Document>) -> Result<Vec<DocumentId>> {
+        let _permit = self.oplog.write_permit().await;
+        let mut ids = Vec::with_capacity(documents.len());
+        for (inserted, document) in documents.into_iter().enumerate() {
+            if inserted % CHECKPOINT_INTERVAL == 0 {
+                checkpoint()?;
+            }
+            ids.push(self.insert_unlocked(document).await?);
+        }
+        Ok(ids)
+    }
+
+    /// Caller holds the oplog write permit
+    async fn insert_unlocked(&self, mut document: Document) -> Result<DocumentId> {
         let id = if document.id == uuid::Uuid::nil() {
             uuid::Uuid::now_v7() // Generate timestamp-ordered UUID
         } else {
@@ -121,7 +338,11 @@ impl Collection {
         document.updated_at = now;
         document.version = 1;
         
-        self.storage_engine.put(id, document).await?;
+        self.write_rules().apply(&mut document, None)?;
+        self.storage_engine.put(id, document.clone()).await?;
+        self.index_manager.insert_document(id, &document).await?;
+        self.oplog.append(&self.database, &self.name, OplogOperation::Insert { document });
+        self.bump_version();
         
         debug!("Inserted document with ID: {} into collection '{}'", id, self.name);
         Ok(id)
@@ -132,10 +353,31 @@ impl Collection {
         self.storage_engine.get(id).await
     }
 
+    /// Find a document by ID in its stored form, for reading a few fields
+    /// or borrowing strings without decoding the whole document
+    pub async fn find_stored_by_id(&self, id: &DocumentId) -> Result<Option<StoredDocument>> {
+        self.storage_engine.get_stored(id).await
+    }
+
+    /// Find a document by ID and deserialize it straight into `T`
+    pub async fn find_by_id_as<T: DeserializeOwned>(&self, id: &DocumentId) -> Result<Option<T>> {
+        match self.find_stored_by_id(id).await? {
+            Some(stored) => stored.deserialize().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Serialize `value` into a document and insert it; a `_id` field sets the document ID
+    pub async fn insert_as<T: Serialize + ?Sized>(&self, value: &T) -> Result<DocumentId> {
+        self.insert(crate::document::to_document(value)?).await
+    }
+
     /// Update a document by ID
     pub async fn update_by_id(&self, id: &DocumentId, mut document: Document) -> Result<Option<Document>> {
+        let _permit = self.oplog.write_permit().await;
+        
         // Get existing document to preserve metadata
-        if let Some(mut existing) = self.storage_engine.get(id).await? {
+        if let Some(existing) = self.storage_engine.get(id).await? {
             let now = chrono::Utc::now().timestamp_micros();
             
             // Preserve creation time and increment version
@@ -143,8 +385,16 @@ impl Collection {
             document.created_at = existing.created_at;
             document.updated_at = now;
             document.version = existing.version + 1;
+            self.write_rules().apply(&mut document, Some(&existing))?;
             
             self.storage_engine.put(*id, document.clone()).await?;
+            self.index_manager.update_document(*id, &existing, &document).await?;
+            self.oplog.append(
+                &self.database,
+                &self.name,
+                OplogOperation::Update { before: existing, after: document.clone() },
+            );
+            self.bump_version();
             
             debug!("Updated document with ID: {} in collection '{}'", id, self.name);
             Ok(Some(document))
@@ -155,15 +405,38 @@ impl Collection {
 
     /// Delete a document by ID
     pub async fn delete_by_id(&self, id: &DocumentId) -> Result<bool> {
+        let _permit = self.oplog.write_permit().await;
+        
+        // The oplog keeps the deleted version so older snapshots can still read it
+        let Some(before) = self.storage_engine.get(id).await? else {
+            return Ok(false);
+        };
         let result = self.storage_engine.delete(id).await?;
         
         if result {
+            self.index_manager.remove_document(id).await?;
+            self.oplog.append(&self.database, &self.name, OplogOperation::Delete { before });
+            self.bump_version();
             debug!("Deleted document with ID: {} from collection '{}'", id, self.name);
         }
         
         Ok(result)
     }
 
+    /// Defaults, computed fields and validators applied to every write
+    pub fn write_rules(&self) -> Arc<WriteRules> {
+        self.rules.read().clone()
+    }
+
+    /// Replace the write rules; documents already stored are not rewritten
+    pub async fn set_write_rules(&self, rules: WriteRules) -> Result<()> {
+        rules.validate()?;
+        let _permit = self.oplog.write_permit().await;
+        *self.rules.write() = Arc::new(rules);
+        debug!("Replaced write rules of collection '{}'", self.name);
+        Ok(())
+    }
+
     /// Find multiple documents with pagination
     pub async fn find_many(
         &self,
@@ -173,25 +446,107 @@ impl Collection {
         self.storage_engine.scan(start, limit).await
     }
 
+    /// Run a query, reading candidates through the best usable index
+    pub async fn find(&self, query: &Query) -> Result<QueryResult> {
+        let plan = self.explain(query).await?;
+        let documents = match &plan.access {
+            AccessPath::CollectionScan => self.find_many(None, usize::MAX).await?,
+            AccessPath::IndexScan { index, query: index_query } => {
+                match self.index_manager.search(index_query).await {
+                    Ok(ids) => {
+                        let mut seen = HashSet::new();
+                        let mut documents = Vec::with_capacity(ids.len());
+                        for (scanned, id) in ids.into_iter().enumerate() {
+                            if scanned % CHECKPOINT_INTERVAL == 0 {
+                                checkpoint()?;
+                            }
+                            if !seen.insert(id) {
+                                continue;
+                            }
+                            if let Some(doc) = self.find_by_id(&id).await? {
+                                documents.push((id, doc));
+                            }
+                        }
+                        documents
+                    }
+                    // The index may have been dropped since planning; only a hint makes that fatal
+                    Err(e) if !plan.hinted => {
+                        error!("Index '{}' failed on collection '{}', scanning instead: {}", index, self.name, e);
+                        self.find_many(None, usize::MAX).await?
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        };
+        
+        debug!("Query on collection '{}' planned as {}", self.name, plan);
+        query.execute(documents).await
+    }
+
+    /// The plan `find` would use for a query
+    pub async fn explain(&self, query: &Query) -> Result<QueryPlan> {
+        let indexes = self.index_manager.list_indexes().await?;
+        QueryPlanner::plan(query, &indexes)
+    }
+
+    /// Stream every document in the collection, reading `batch_size` at a time
+    pub fn stream_documents(&self, batch_size: usize) -> DocumentStream<'static> {
+        aggregation::scan_stream(self.storage_engine.clone(), batch_size)
+    }
+
     /// Count documents in the collection
     pub async fn count(&self) -> Result<usize> {
         let documents = self.storage_engine.scan(None, usize::MAX).await?;
         Ok(documents.len())
     }
 
-    /// Create an index on the collection
-    pub async fn create_index(&self, field: String, index_type: crate::IndexType) -> Result<()> {
-        let mut indexes = self.indexes.write().await;
-        indexes.insert(field, index_type);
+    /// Create an index on the collection.
+    ///
+    /// The index is built in the background without blocking writes; poll
+    /// `index_build_status` to see when it is ready.
+    pub async fn create_index(&self, field: String, index_type: crate::IndexType) -> Result<IndexBuildStatus> {
+        let status = self.index_manager
+            .start_index_build(field, index_type, self.storage_engine.clone())
+            .await?;
         
-        debug!("Created index on field '{}' for collection '{}'", field, self.name);
-        Ok(())
+        debug!("Started index build on field '{}' for collection '{}'", status.field, self.name);
+        Ok(status)
+    }
+
+    /// Create a compound index over `fields`, named by joining them with commas
+    pub async fn create_compound_index(&self, fields: Vec<String>) -> Result<IndexBuildStatus> {
+        if fields.len() < 2 {
+            return Err(crate::LargetableError::Index(
+                "A compound index needs at least two fields".to_string(),
+            ));
+        }
+        let name = crate::index::compound::index_name(&fields);
+        self.create_index(name, crate::IndexType::Compound { fields }).await
     }
 
-    /// List all indexes on the collection
+    /// List all ready indexes on the collection
     pub async fn list_indexes(&self) -> Result<HashMap<String, crate::IndexType>> {
-        let indexes = self.indexes.read().await;
-        Ok(indexes.clone())
+        Ok(self.index_manager.list_indexes().await?.into_iter().collect())
+    }
+
+    /// Progress of the latest build for a field
+    pub fn index_build_status(&self, field: &str) -> Option<IndexBuildStatus> {
+        self.index_manager.index_build_status(field)
+    }
+
+    /// Progress of all index builds, running and finished
+    pub fn list_index_builds(&self) -> Vec<IndexBuildStatus> {
+        self.index_manager.list_index_builds()
+    }
+
+    /// Abort a running index build
+    pub fn abort_index_build(&self, field: &str) -> Result<bool> {
+        self.index_manager.abort_index_build(field)
+    }
+
+    /// Indexes maintained for this collection
+    pub fn index_manager(&self) -> &Arc<IndexManager> {
+        &self.index_manager
     }
 
     /// Get collection name
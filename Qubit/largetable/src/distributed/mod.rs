@@ -14,6 +14,7 @@ pub mod fault_tolerance;
 pub mod cluster_management;
 
 use crate::{Result, Document, DocumentId, DatabaseName, CollectionName};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -91,7 +92,7 @@ pub struct Shard {
 }
 
 /// Key range for sharding
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct KeyRange {
     pub start: ShardKey,
     pub end: ShardKey,
@@ -99,12 +100,16 @@ pub struct KeyRange {
 }
 
 /// Shard key for partitioning
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ShardKey {
     String(String),
     Integer(i64),
     ObjectId(DocumentId),
     Hash(u64),
+    /// Sorts before every other key; lower bound of a collection's first chunk
+    MinKey,
+    /// Sorts after every other key; upper bound of a collection's last chunk
+    MaxKey,
 }
 
 /// Shard status
@@ -535,6 +540,63 @@ impl KeyRange {
             _ => false,
         }
     }
+
+    /// Whether `other` lies entirely within this range
+    pub fn covers(&self, other: &KeyRange) -> bool {
+        let start_ok = match other.start.compare(&self.start) {
+            Some(Ordering::Greater) => true,
+            Some(Ordering::Equal) => self.inclusive || !other.inclusive,
+            _ => false,
+        };
+        let end_ok = match other.end.compare(&self.end) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => self.inclusive || !other.inclusive,
+            _ => false,
+        };
+        start_ok && end_ok
+    }
+
+    /// Whether the two ranges share at least one key
+    pub fn overlaps(&self, other: &KeyRange) -> bool {
+        let both_inclusive = self.inclusive && other.inclusive;
+        let before = match other.end.compare(&self.start) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => !both_inclusive,
+            Some(Ordering::Greater) => false,
+            None => return false,
+        };
+        let after = match other.start.compare(&self.end) {
+            Some(Ordering::Greater) => true,
+            Some(Ordering::Equal) => !both_inclusive,
+            Some(Ordering::Less) => false,
+            None => return false,
+        };
+        !before && !after
+    }
+
+    /// Chunk membership: the start bound is included and the end bound is not,
+    /// so adjacent chunks never share a key
+    pub fn owns(&self, key: &ShardKey) -> bool {
+        matches!(key.compare(&self.start), Some(Ordering::Greater | Ordering::Equal))
+            && matches!(key.compare(&self.end), Some(Ordering::Less))
+    }
+}
+
+impl ShardKey {
+    /// Order two keys of the same kind; keys of different kinds are unordered,
+    /// except `MinKey` and `MaxKey` which bound every kind
+    pub fn compare(&self, other: &ShardKey) -> Option<Ordering> {
+        match (self, other) {
+            (ShardKey::MinKey, ShardKey::MinKey) | (ShardKey::MaxKey, ShardKey::MaxKey) => Some(Ordering::Equal),
+            (ShardKey::MinKey, _) | (_, ShardKey::MaxKey) => Some(Ordering::Less),
+            (_, ShardKey::MinKey) | (ShardKey::MaxKey, _) => Some(Ordering::Greater),
+            (ShardKey::String(a), ShardKey::String(b)) => Some(a.cmp(b)),
+            (ShardKey::Integer(a), ShardKey::Integer(b)) => Some(a.cmp(b)),
+            (ShardKey::ObjectId(a), ShardKey::ObjectId(b)) => Some(a.cmp(b)),
+            (ShardKey::Hash(a), ShardKey::Hash(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
 }
 
 impl ConsensusProtocol {
@@ -0,0 +1,306 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Deserializer from document views to Rust values
+
+use crate::document::view::{ArrayView, DocumentView, ValueView};
+use crate::{LargetableError, Result};
+use serde::de::value::{BorrowedStrDeserializer, F32Deserializer};
+use serde::de::{self, DeserializeSeed, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// Metadata fields a struct can read alongside the document's own fields
+const METADATA_FIELDS: [&str; 4] = ["_id", "_version", "_created_at", "_updated_at"];
+
+fn metadata<'de>(doc: DocumentView<'de>, field: &str) -> ValueView<'de> {
+    match field {
+        "_id" => ValueView::ObjectId(doc.id()),
+        "_version" => ValueView::UInt64(doc.version()),
+        "_created_at" => ValueView::Timestamp(doc.created_at()),
+        _ => ValueView::Timestamp(doc.updated_at()),
+    }
+}
+
+/// Deserializes a whole document as a map or struct
+pub(super) struct DocumentDeserializer<'de> {
+    doc: DocumentView<'de>,
+}
+
+impl<'de> DocumentDeserializer<'de> {
+    pub(super) fn new(doc: DocumentView<'de>) -> Self {
+        Self { doc }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for DocumentDeserializer<'de> {
+    type Error = LargetableError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        ValueDeserializer(ValueView::Document(self.doc)).deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        ValueDeserializer(ValueView::Document(self.doc)).deserialize_struct(name, fields, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Deserializes one value; strings and bytes are borrowed from the view
+pub(super) struct ValueDeserializer<'de>(pub(super) ValueView<'de>);
+
+impl<'de> ValueDeserializer<'de> {
+    /// Visit a document, exposing the metadata fields `wanted` allows
+    fn visit_document<V: Visitor<'de>>(doc: DocumentView<'de>, wanted: impl Fn(&str) -> bool, visitor: V) -> Result<V::Value> {
+        let entries = METADATA_FIELDS
+            .into_iter()
+            .filter(|field| wanted(field))
+            .map(move |field| -> (&'de str, ValueView<'de>) { (field, metadata(doc, field)) })
+            .chain(doc.fields());
+        visitor.visit_map(MapAccess { entries, value: None })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = LargetableError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            ValueView::Null => visitor.visit_unit(),
+            ValueView::Bool(b) => visitor.visit_bool(b),
+            ValueView::Int32(i) => visitor.visit_i32(i),
+            ValueView::Int64(i) | ValueView::Timestamp(i) => visitor.visit_i64(i),
+            ValueView::UInt64(u) => visitor.visit_u64(u),
+            ValueView::Float32(f) => visitor.visit_f32(f),
+            ValueView::Float64(f) => visitor.visit_f64(f),
+            ValueView::String(s) => visitor.visit_borrowed_str(s),
+            ValueView::Binary(b) => visitor.visit_borrowed_bytes(b),
+            ValueView::Decimal128(d) => visitor.visit_borrowed_bytes(d),
+            ValueView::ObjectId(id) => visitor.visit_string(id.to_string()),
+            ValueView::Document(doc) => Self::visit_document(doc, |_| false, visitor),
+            ValueView::Array(array) => visitor.visit_seq(SeqAccess { values: ArrayValues::Array(array, 0) }),
+            ValueView::Vector(v) => visitor.visit_seq(SeqAccess { values: ArrayValues::Vector(v.iter()) }),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            ValueView::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.0 {
+            // Metadata only when the struct has a field for it, so maps and
+            // `deny_unknown_fields` structs see just the stored fields
+            ValueView::Document(doc) => Self::visit_document(doc, |field| fields.contains(&field), visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.0 {
+            ValueView::String(variant) => visitor.visit_enum(EnumAccess { variant, value: None }),
+            ValueView::Document(doc) if doc.len() == 1 => {
+                let (variant, value) = doc.fields().next().expect("document has one field");
+                visitor.visit_enum(EnumAccess { variant, value: Some(value) })
+            }
+            _ => Err(LargetableError::Serialization(format!(
+                "Enum {} must be stored as a variant name or a single-field document",
+                name
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier ignored_any
+    }
+}
+
+struct MapAccess<'de, I: Iterator<Item = (&'de str, ValueView<'de>)>> {
+    entries: I,
+    value: Option<ValueView<'de>>,
+}
+
+impl<'de, I: Iterator<Item = (&'de str, ValueView<'de>)>> de::MapAccess<'de> for MapAccess<'de, I> {
+    type Error = LargetableError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| LargetableError::Serialization("Map value read before its key".to_string()))?;
+        seed.deserialize(ValueDeserializer(value))
+    }
+}
+
+/// Field names; integer keys written as text by the serializer parse back
+struct KeyDeserializer<'de>(&'de str);
+
+macro_rules! deserialize_integer_key {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                match self.0.parse() {
+                    Ok(key) => visitor.$visit(key),
+                    Err(_) => visitor.visit_borrowed_str(self.0),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = LargetableError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    deserialize_integer_key! {
+        deserialize_i8 => visit_i8, deserialize_i16 => visit_i16, deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64, deserialize_u8 => visit_u8, deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32, deserialize_u64 => visit_u64
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(EnumAccess { variant: self.0, value: None })
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+enum ArrayValues<'de> {
+    Array(ArrayView<'de>, usize),
+    Vector(std::slice::Iter<'de, f32>),
+}
+
+struct SeqAccess<'de> {
+    values: ArrayValues<'de>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = LargetableError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match &mut self.values {
+            ArrayValues::Array(array, index) => match array.get(*index) {
+                Some(value) => {
+                    *index += 1;
+                    seed.deserialize(ValueDeserializer(value)).map(Some)
+                }
+                None => Ok(None),
+            },
+            ArrayValues::Vector(values) => match values.next() {
+                Some(&value) => seed.deserialize(F32Deserializer::new(value)).map(Some),
+                None => Ok(None),
+            },
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match &self.values {
+            ArrayValues::Array(array, index) => Some(array.len() - index),
+            ArrayValues::Vector(values) => Some(values.len()),
+        }
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: &'de str,
+    value: Option<ValueView<'de>>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = LargetableError;
+    type Variant = VariantAccess<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, VariantAccess<'de>)> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<LargetableError>::new(self.variant))?;
+        Ok((variant, VariantAccess { value: self.value }))
+    }
+}
+
+struct VariantAccess<'de> {
+    value: Option<ValueView<'de>>,
+}
+
+impl<'de> VariantAccess<'de> {
+    fn value(self) -> Result<ValueView<'de>> {
+        self.value
+            .ok_or_else(|| LargetableError::Serialization("Enum variant is missing its value".to_string()))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = LargetableError;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None | Some(ValueView::Null) => Ok(()),
+            Some(_) => Err(LargetableError::Serialization("Unit enum variant has a value".to_string())),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(ValueDeserializer(self.value()?))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(ValueDeserializer(self.value()?), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_struct(ValueDeserializer(self.value()?), "", fields, visitor)
+    }
+}
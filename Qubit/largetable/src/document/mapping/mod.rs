@@ -0,0 +1,165 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Serde mapping between Rust types and documents
+//!
+//! Any `Serialize` struct is written straight into a `Document`, and any
+//! `Deserialize` struct is read straight from a document view, without going
+//! through `serde_json::Value` or another intermediate map. Read from a
+//! `StoredDocument`, `&str` and `&[u8]` fields borrow from the stored archive.
+//!
+//! Metadata follows the JSON conventions of `DocumentUtils`: a `_id` field
+//! holding a UUID string sets the document ID on write, and structs with
+//! `_id`, `_version`, `_created_at` or `_updated_at` fields read them back.
+//! Other underscore fields are managed by the database and ignored on write.
+//!
+//! Integers that fit in an `i32` by type become `Int32`, other integers
+//! `Int64`, and `u64` values past `i64::MAX` become `UInt64`. Unit enum
+//! variants are stored as strings and other variants as single-field
+//! documents, the same externally tagged layout as JSON.
+
+mod de;
+mod ser;
+
+use super::view::DocumentView;
+use crate::{Document, LargetableError, Result, Value};
+use serde::de::{Deserialize, DeserializeOwned};
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Serialize a struct or map into a new document
+pub fn to_document<T: Serialize + ?Sized>(value: &T) -> Result<Document> {
+    match value.serialize(ser::ValueSerializer)? {
+        Value::Document(doc) => Ok(doc),
+        other => Err(LargetableError::Serialization(format!(
+            "Only structs and maps can be stored as documents, got {}",
+            ser::kind(&other)
+        ))),
+    }
+}
+
+/// Serialize any value into a document value
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    value.serialize(ser::ValueSerializer)
+}
+
+/// Deserialize from a document view, borrowing from it where `T` allows
+pub fn from_view<'a, T: Deserialize<'a>>(view: DocumentView<'a>) -> Result<T> {
+    T::deserialize(de::DocumentDeserializer::new(view))
+}
+
+/// Deserialize from an owned document
+pub fn from_document<T: DeserializeOwned>(doc: &Document) -> Result<T> {
+    from_view(DocumentView::Owned(doc))
+}
+
+/// Deserialize from a single value
+pub fn from_value<T: DeserializeOwned>(value: &Value) -> Result<T> {
+    T::deserialize(de::ValueDeserializer(value.into()))
+}
+
+impl serde::ser::Error for LargetableError {
+    fn custom<T: Display>(msg: T) -> Self {
+        LargetableError::Serialization(msg.to_string())
+    }
+}
+
+impl serde::de::Error for LargetableError {
+    fn custom<T: Display>(msg: T) -> Self {
+        LargetableError::Serialization(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::view::{StoredDocument, ValueView};
+    use crate::document::DocumentBuilder;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Plan {
+        Free,
+        Paid { seats: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Account {
+        #[serde(rename = "_id")]
+        id: uuid::Uuid,
+        name: String,
+        tags: Vec<String>,
+        plan: Plan,
+        limits: BTreeMap<String, f64>,
+        nickname: Option<String>,
+        owner: Owner,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Owner {
+        email: String,
+        age: u8,
+    }
+
+    /// Reads only what it needs, borrowing the strings
+    #[derive(Deserialize)]
+    struct AccountSummary<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        tags: Vec<&'a str>,
+    }
+
+    fn account() -> Account {
+        Account {
+            id: uuid::Uuid::now_v7(),
+            name: "pixelle".to_string(),
+            tags: vec!["beta".to_string(), "eu".to_string()],
+            plan: Plan::Paid { seats: 5 },
+            limits: BTreeMap::from([("storage_gb".to_string(), 50.0)]),
+            nickname: None,
+            owner: Owner { email: "ops@pixelle.dev".to_string(), age: 31 },
+        }
+    }
+
+    #[test]
+    fn test_structs_round_trip_through_documents() {
+        let account = account();
+        let doc = to_document(&account).unwrap();
+        assert_eq!(doc.id, account.id);
+        assert!(!doc.fields.contains_key("_id"));
+        assert!(matches!(doc.fields.get("name"), Some(Value::String(s)) if s == "pixelle"));
+        assert!(matches!(doc.fields.get("nickname"), Some(Value::Null)));
+        assert!(matches!(
+            crate::document::DocumentUtils::get_field(&doc, "owner.age"),
+            Some(Value::Int32(31))
+        ));
+
+        assert_eq!(from_document::<Account>(&doc).unwrap(), account);
+        assert_eq!(from_value::<Plan>(&to_value(&Plan::Free).unwrap()).unwrap(), Plan::Free);
+        assert!(to_document(&42).is_err());
+    }
+
+    #[test]
+    fn test_stored_documents_deserialize_without_copying() {
+        let account = account();
+        let stored = StoredDocument::from_document(&to_document(&account).unwrap()).unwrap();
+
+        let summary: AccountSummary = stored.deserialize().unwrap();
+        assert_eq!(summary.name, "pixelle");
+        assert_eq!(summary.tags, vec!["beta", "eu"]);
+        let range = stored.as_bytes().as_ptr_range();
+        assert!(range.contains(&summary.name.as_ptr()));
+
+        assert_eq!(stored.deserialize::<Account>().unwrap(), account);
+        assert!(matches!(stored.view().get_path("owner.email"), Some(ValueView::String("ops@pixelle.dev"))));
+
+        // Type mismatches are reported, not coerced
+        let doc = DocumentBuilder::new().string("name", "x").int("tags", 3).build();
+        let stored = StoredDocument::from_document(&doc).unwrap();
+        assert!(stored.deserialize::<AccountSummary>().is_err());
+    }
+}
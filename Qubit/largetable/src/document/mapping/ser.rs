@@ -0,0 +1,499 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Serializer from Rust values to document values
+
+use crate::document::DocumentBuilder;
+use crate::{Document, LargetableError, Result, Value};
+use serde::ser::{self, Impossible, Serialize};
+
+pub(super) struct ValueSerializer;
+
+/// Name of a value's type, for error messages
+pub(super) fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Int32(_) | Value::Int64(_) | Value::UInt64(_) => "an integer",
+        Value::Float32(_) | Value::Float64(_) => "a float",
+        Value::String(_) => "a string",
+        Value::Binary(_) => "binary data",
+        Value::Document(_) => "a document",
+        Value::Array(_) => "an array",
+        Value::Timestamp(_) => "a timestamp",
+        Value::ObjectId(_) => "an object ID",
+        Value::Vector(_) => "a vector",
+        Value::Decimal128(_) => "a decimal",
+    }
+}
+
+/// `{variant: value}`, the externally tagged enum layout
+fn tagged(variant: &str, value: Value) -> Value {
+    Value::Document(DocumentBuilder::new().field(variant.to_string(), value).build())
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = LargetableError;
+    type SerializeSeq = ArraySerializer;
+    type SerializeTuple = ArraySerializer;
+    type SerializeTupleStruct = ArraySerializer;
+    type SerializeTupleVariant = ArraySerializer;
+    type SerializeMap = DocumentSerializer;
+    type SerializeStruct = DocumentSerializer;
+    type SerializeStructVariant = DocumentSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        Ok(Value::Int32(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        Ok(Value::Int32(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        Ok(Value::Int32(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        Ok(Value::Int64(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(Value::Int64(v)),
+            Err(_) => u64::try_from(v)
+                .map(Value::UInt64)
+                .map_err(|_| LargetableError::Serialization(format!("Integer {} is out of range", v))),
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        Ok(Value::Int32(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        Ok(Value::Int32(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        Ok(Value::Int64(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        Ok(i64::try_from(v).map(Value::Int64).unwrap_or(Value::UInt64(v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> {
+        Ok(Value::Float32(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value> {
+        Ok(Value::Float64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Ok(Value::Binary(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Value> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        Ok(tagged(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ArraySerializer> {
+        Ok(ArraySerializer { values: Vec::with_capacity(len.unwrap_or(0)), variant: None })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ArraySerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ArraySerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ArraySerializer> {
+        Ok(ArraySerializer { values: Vec::with_capacity(len), variant: Some(variant) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<DocumentSerializer> {
+        Ok(DocumentSerializer::new(None))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<DocumentSerializer> {
+        Ok(DocumentSerializer::new(None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<DocumentSerializer> {
+        Ok(DocumentSerializer::new(Some(variant)))
+    }
+}
+
+pub(super) struct ArraySerializer {
+    values: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+impl ArraySerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Value {
+        let array = Value::Array(self.values);
+        match self.variant {
+            Some(variant) => tagged(variant, array),
+            None => array,
+        }
+    }
+}
+
+impl ser::SerializeSeq for ArraySerializer {
+    type Ok = Value;
+    type Error = LargetableError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for ArraySerializer {
+    type Ok = Value;
+    type Error = LargetableError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for ArraySerializer {
+    type Ok = Value;
+    type Error = LargetableError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for ArraySerializer {
+    type Ok = Value;
+    type Error = LargetableError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(self.finish())
+    }
+}
+
+pub(super) struct DocumentSerializer {
+    builder: DocumentBuilder,
+    next_key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl DocumentSerializer {
+    fn new(variant: Option<&'static str>) -> Self {
+        Self { builder: DocumentBuilder::new(), next_key: None, variant }
+    }
+
+    fn insert<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        let value = value.serialize(ValueSerializer)?;
+        if key == "_id" {
+            let id = match value {
+                Value::ObjectId(id) => id,
+                Value::String(s) => uuid::Uuid::parse_str(&s)
+                    .map_err(|e| LargetableError::Serialization(format!("Invalid document ID: {}", e)))?,
+                other => {
+                    return Err(LargetableError::Serialization(format!(
+                        "Document ID must be a UUID, got {}",
+                        kind(&other)
+                    )))
+                }
+            };
+            self.builder = std::mem::take(&mut self.builder).id(id);
+        } else if !key.starts_with('_') {
+            self.builder = std::mem::take(&mut self.builder).field(key.to_string(), value);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Value {
+        let doc: Document = self.builder.build();
+        match self.variant {
+            Some(variant) => tagged(variant, Value::Document(doc)),
+            None => Value::Document(doc),
+        }
+    }
+}
+
+impl ser::SerializeMap for DocumentSerializer {
+    type Ok = Value;
+    type Error = LargetableError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| LargetableError::Serialization("Map value serialized before its key".to_string()))?;
+        self.insert(&key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for DocumentSerializer {
+    type Ok = Value;
+    type Error = LargetableError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for DocumentSerializer {
+    type Ok = Value;
+    type Error = LargetableError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(self.finish())
+    }
+}
+
+/// Field names must be strings; integer and char keys are written as text, as in JSON
+struct KeySerializer;
+
+impl KeySerializer {
+    fn unsupported(kind: &str) -> LargetableError {
+        LargetableError::Serialization(format!("Document field names must be strings, got {}", kind))
+    }
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = LargetableError;
+    type SerializeSeq = Impossible<String, LargetableError>;
+    type SerializeTuple = Impossible<String, LargetableError>;
+    type SerializeTupleStruct = Impossible<String, LargetableError>;
+    type SerializeTupleVariant = Impossible<String, LargetableError>;
+    type SerializeMap = Impossible<String, LargetableError>;
+    type SerializeStruct = Impossible<String, LargetableError>;
+    type SerializeStructVariant = Impossible<String, LargetableError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<String> {
+        Err(Self::unsupported("a boolean"))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String> {
+        Err(Self::unsupported("a float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String> {
+        Err(Self::unsupported("a float"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        Err(Self::unsupported("binary data"))
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(Self::unsupported("null"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(Self::unsupported("null"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(Self::unsupported("null"))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(Self::unsupported("an enum"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Self::unsupported("an array"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Self::unsupported("an array"))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct> {
+        Err(Self::unsupported("an array"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Self::unsupported("an enum"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Self::unsupported("a document"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Self::unsupported("a document"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Self::unsupported("an enum"))
+    }
+}
@@ -7,12 +7,19 @@
 //! Document operations and utilities
 
 pub mod bson;
+pub mod mapping;
+pub mod rules;
 pub mod schema;
 pub mod validation;
 pub mod versioning;
+pub mod view;
 pub mod zero_copy_serde;
 
+pub use mapping::{from_document, from_view, to_document};
+pub use view::{DocumentView, StoredDocument, ValueView};
+
 use crate::{Result, DocumentId, Document, Value, LargetableError};
+use crate::index::geospatial::{geometry::Geometry, GeoQuery};
 use serde_json::{Value as JsonValue, Map as JsonMap};
 use std::collections::HashMap;
 use tracing::{debug, error};
@@ -197,7 +204,7 @@ impl DocumentUtils {
     }
 
     /// Convert JSON to a Value
-    fn json_to_value(json: JsonValue) -> Result<Value> {
+    pub(crate) fn json_to_value(json: JsonValue) -> Result<Value> {
         match json {
             JsonValue::Null => Ok(Value::Null),
             JsonValue::Bool(b) => Ok(Value::Bool(b)),
@@ -232,7 +239,7 @@ impl DocumentUtils {
     }
 
     /// Get a field value from a document
-    pub fn get_field(doc: &Document, field_path: &str) -> Option<&Value> {
+    pub fn get_field<'a>(doc: &'a Document, field_path: &str) -> Option<&'a Value> {
         let parts: Vec<&str> = field_path.split('.').collect();
         let mut current = &doc.fields;
         
@@ -253,47 +260,58 @@ impl DocumentUtils {
 
     /// Set a field value in a document
     pub fn set_field(doc: &mut Document, field_path: &str, value: Value) -> Result<()> {
-        let parts: Vec<&str> = field_path.split('.').collect();
+        let mut parts = field_path.split('.').peekable();
         let mut current = &mut doc.fields;
         
-        for (i, part) in parts.iter().enumerate() {
-            if i == parts.len() - 1 {
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
                 current.insert(part.to_string(), value);
                 return Ok(());
             }
             
-            if let Some(Value::Document(nested_doc)) = current.get_mut(*part) {
-                current = &mut nested_doc.fields;
-            } else {
-                // Create nested document if it doesn't exist
-                let nested_doc = Document {
+            // Create nested document if it doesn't exist
+            let entry = current.entry(part.to_string()).or_insert(Value::Null);
+            if !matches!(entry, Value::Document(_)) {
+                *entry = Value::Document(Document {
                     id: uuid::Uuid::now_v7(),
                     fields: HashMap::new(),
                     version: 1,
                     created_at: chrono::Utc::now().timestamp_micros(),
                     updated_at: chrono::Utc::now().timestamp_micros(),
-                };
-                current.insert(part.to_string(), Value::Document(nested_doc));
-                
-                if let Some(Value::Document(nested_doc)) = current.get_mut(*part) {
-                    current = &mut nested_doc.fields;
-                }
+                });
             }
+            let Value::Document(nested_doc) = entry else {
+                unreachable!("nested field was just made a document");
+            };
+            current = &mut nested_doc.fields;
         }
         
         Ok(())
     }
 
-    /// Check if a document matches a filter
+    /// Check if a document matches a filter.
+    ///
+    /// Each condition is either a literal value, matched by equality, or an
+    /// object of operators: `$eq`, `$ne`, `$gt`, `$gte`, `$lt`, `$lte`, `$in`,
+    /// `$nin`, `$exists` and the geospatial `$near`, `$geoWithin` and
+    /// `$geoIntersects`. `$near` only checks distance here; results come
+    /// nearest first when a 2dsphere index answers the query.
     pub fn matches_filter(doc: &Document, filter: &JsonValue) -> Result<bool> {
         match filter {
             JsonValue::Object(filter_map) => {
                 for (key, expected_value) in filter_map {
-                    if let Some(actual_value) = Self::get_field(doc, key) {
-                        if !Self::value_matches(actual_value, expected_value)? {
-                            return Ok(false);
-                        }
-                    } else {
+                    if key == "$text" {
+                        return Err(LargetableError::Query("$text is only supported in collection queries with a full-text index".to_string()));
+                    }
+                    let actual_value = Self::get_field(doc, key);
+                    let matched = match Self::filter_operators(expected_value) {
+                        Some(operators) => Self::matches_operators(actual_value, operators)?,
+                        None => match actual_value {
+                            Some(actual_value) => Self::value_matches(actual_value, expected_value)?,
+                            None => false,
+                        },
+                    };
+                    if !matched {
                         return Ok(false);
                     }
                 }
@@ -303,13 +321,101 @@ impl DocumentUtils {
         }
     }
 
+    /// The operators of a filter condition, if it is an operator object
+    pub fn filter_operators(condition: &JsonValue) -> Option<&JsonMap<String, JsonValue>> {
+        match condition {
+            JsonValue::Object(map) if !map.is_empty() && map.keys().all(|key| key.starts_with('$')) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Check a field value against every operator of a condition
+    fn matches_operators(actual: Option<&Value>, operators: &JsonMap<String, JsonValue>) -> Result<bool> {
+        for (operator, operand) in operators {
+            let matched = match operator.as_str() {
+                "$exists" => actual.is_some() == operand.as_bool().unwrap_or(true),
+                "$eq" => match actual {
+                    Some(actual) => Self::value_matches(actual, operand)?,
+                    None => false,
+                },
+                "$ne" => match actual {
+                    Some(actual) => !Self::value_matches(actual, operand)?,
+                    None => true,
+                },
+                "$in" | "$nin" => {
+                    let JsonValue::Array(candidates) = operand else {
+                        return Err(LargetableError::Query(format!("{} expects an array", operator)));
+                    };
+                    let mut found = false;
+                    if let Some(actual) = actual {
+                        for candidate in candidates {
+                            if Self::value_matches(actual, candidate)? {
+                                found = true;
+                                break;
+                            }
+                        }
+                    }
+                    found == (operator == "$in")
+                }
+                geo if GeoQuery::is_operator(geo) => {
+                    let query = GeoQuery::from_operator(geo, operand)?;
+                    actual.and_then(Geometry::from_value).is_some_and(|geometry| query.matches(&geometry))
+                }
+                "$gt" | "$gte" | "$lt" | "$lte" => {
+                    match actual.and_then(|actual| Self::compare_to_json(actual, operand)) {
+                        Some(ordering) => match operator.as_str() {
+                            "$gt" => ordering.is_gt(),
+                            "$gte" => ordering.is_ge(),
+                            "$lt" => ordering.is_lt(),
+                            _ => ordering.is_le(),
+                        },
+                        None => false,
+                    }
+                }
+                other => {
+                    return Err(LargetableError::Query(format!("Unsupported filter operator '{}'", other)));
+                }
+            };
+            if !matched {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Order a value against a JSON operand; None when the types are not comparable
+    fn compare_to_json(value: &Value, json: &JsonValue) -> Option<std::cmp::Ordering> {
+        match (value, json) {
+            (Value::String(s), JsonValue::String(js)) => Some(s.as_str().cmp(js.as_str())),
+            (Value::Bool(b), JsonValue::Bool(jb)) => Some(b.cmp(jb)),
+            (Value::Int64(i), JsonValue::Number(jn)) | (Value::Timestamp(i), JsonValue::Number(jn)) if jn.is_i64() => {
+                jn.as_i64().map(|j| i.cmp(&j))
+            }
+            (_, JsonValue::Number(jn)) => Self::as_f64(value)?.partial_cmp(&jn.as_f64()?),
+            _ => None,
+        }
+    }
+
+    fn as_f64(value: &Value) -> Option<f64> {
+        match value {
+            Value::Int32(i) => Some(*i as f64),
+            Value::Int64(i) => Some(*i as f64),
+            Value::UInt64(u) => Some(*u as f64),
+            Value::Float32(f) => Some(*f as f64),
+            Value::Float64(f) => Some(*f),
+            _ => None,
+        }
+    }
+
     /// Check if a value matches a JSON value
     fn value_matches(value: &Value, json: &JsonValue) -> Result<bool> {
         match (value, json) {
             (Value::Null, JsonValue::Null) => Ok(true),
             (Value::Bool(b), JsonValue::Bool(jb)) => Ok(b == jb),
-            (Value::Int64(i), JsonValue::Number(jn)) => Ok(Some(*i) == jn.as_i64()),
-            (Value::Float64(f), JsonValue::Number(jn)) => Ok(Some(*f) == jn.as_f64()),
+            (Value::Int64(i), JsonValue::Number(jn)) | (Value::Timestamp(i), JsonValue::Number(jn)) if jn.is_i64() => {
+                Ok(Some(*i) == jn.as_i64())
+            }
+            (value, JsonValue::Number(jn)) => Ok(Self::as_f64(value).is_some_and(|v| Some(v) == jn.as_f64())),
             (Value::String(s), JsonValue::String(js)) => Ok(s == js),
             (Value::Array(arr), JsonValue::Array(jarr)) => {
                 if arr.len() != jarr.len() {
@@ -0,0 +1,592 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Expressions for defaults, computed fields and validators
+//!
+//! A small language evaluated against the document being written:
+//!
+//! - literals: `"text"`, `'text'`, `42`, `1.5`, `true`, `false`, `null`
+//! - field paths: `username`, `profile.display_name`; a missing field is `null`
+//! - operators: `+ - * /`, `== != < <= > >=`, `&& || !` and parentheses
+//! - functions: `lower`, `upper`, `trim`, `concat`, `coalesce`, `len` and `now`
+//!
+//! String functions and arithmetic return `null` for a `null` operand, so
+//! `lower(username)` on a document without a username yields `null` instead
+//! of failing the write. `now()` is the write's timestamp, the same value
+//! stored as the document's `updated_at`, and adding a number of
+//! microseconds to it gives another timestamp.
+
+use crate::query::aggregation::{as_f64, compare_values, values_equal};
+use crate::document::DocumentUtils;
+use crate::{Document, LargetableError, Result, Timestamp, Value};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A parsed expression; serialized as its source text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expr {
+    source: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(Value),
+    Field(String),
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Lower,
+    Upper,
+    Trim,
+    Concat,
+    Coalesce,
+    Len,
+    Now,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lower" => Some(Function::Lower),
+            "upper" => Some(Function::Upper),
+            "trim" => Some(Function::Trim),
+            "concat" => Some(Function::Concat),
+            "coalesce" => Some(Function::Coalesce),
+            "len" => Some(Function::Len),
+            "now" => Some(Function::Now),
+            _ => None,
+        }
+    }
+
+    /// Allowed argument counts, `None` for variadic
+    fn arity(self) -> Option<usize> {
+        match self {
+            Function::Lower | Function::Upper | Function::Trim | Function::Len => Some(1),
+            Function::Now => Some(0),
+            Function::Concat | Function::Coalesce => None,
+        }
+    }
+}
+
+impl Expr {
+    /// Parse an expression, rejecting unknown functions and wrong argument counts
+    pub fn parse(source: &str) -> Result<Self> {
+        let parse = || {
+            let mut parser = Parser { tokens: tokenize(source)?, position: 0 };
+            let node = parser.expression()?;
+            match parser.peek() {
+                Some(token) => Err(format!("unexpected {:?}", token)),
+                None => Ok(node),
+            }
+        };
+        let node = parse().map_err(|e| LargetableError::Query(format!("Invalid expression `{}`: {}", source, e)))?;
+        Ok(Self { source: source.to_string(), node })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluate against `document`, with `now` as the write's timestamp
+    pub fn evaluate(&self, document: &Document, now: Timestamp) -> Result<Value> {
+        evaluate(&self.node, document, now)
+            .map_err(|e| LargetableError::Validation(format!("`{}`: {}", self.source, e)))
+    }
+}
+
+impl FromStr for Expr {
+    type Err = LargetableError;
+
+    fn from_str(source: &str) -> Result<Self> {
+        Self::parse(source)
+    }
+}
+
+impl TryFrom<String> for Expr {
+    type Error = LargetableError;
+
+    fn try_from(source: String) -> Result<Self> {
+        Self::parse(&source)
+    }
+}
+
+impl From<Expr> for String {
+    fn from(expr: Expr) -> Self {
+        expr.source
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+type ParseResult<T> = std::result::Result<T, String>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64, bool),
+    String(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(source: &str) -> ParseResult<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '"' | '\'' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unterminated string".to_string()),
+                        Some(&ch) if ch == c => break,
+                        Some('\\') => {
+                            match chars.get(i + 1) {
+                                Some('n') => text.push('\n'),
+                                Some('t') => text.push('\t'),
+                                Some(&escaped) => text.push(escaped),
+                                None => return Err("unterminated string".to_string()),
+                            }
+                            i += 1;
+                        }
+                        Some(&ch) => text.push(ch),
+                    }
+                    i += 1;
+                }
+                tokens.push(Token::String(text));
+                i += 1;
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = text.parse::<f64>().map_err(|_| format!("bad number {}", text))?;
+                tokens.push(Token::Number(number, !text.contains('.')));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                    i += 1;
+                }
+                let ident: String = chars[start..i].iter().collect();
+                if ident.ends_with('.') || ident.contains("..") {
+                    return Err(format!("bad field path {}", ident));
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            _ => {
+                let two: String = chars[i..chars.len().min(i + 2)].iter().collect();
+                let op = ["==", "!=", "<=", ">=", "&&", "||"]
+                    .into_iter()
+                    .find(|op| *op == two)
+                    .or_else(|| ["+", "-", "*", "/", "<", ">", "!"].into_iter().find(|op| op.starts_with(c)))
+                    .ok_or_else(|| format!("unexpected character '{}'", c))?;
+                tokens.push(Token::Op(op));
+                i += op.len();
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the precedence levels `||`, `&&`, comparison,
+/// `+ -`, `* /` and unary operators
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self) -> ParseResult<Node> {
+        let mut node = self.and()?;
+        while self.eat_op(&["||"]).is_some() {
+            node = Node::Binary(BinaryOp::Or, Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> ParseResult<Node> {
+        let mut node = self.comparison()?;
+        while self.eat_op(&["&&"]).is_some() {
+            node = Node::Binary(BinaryOp::And, Box::new(node), Box::new(self.comparison()?));
+        }
+        Ok(node)
+    }
+
+    fn comparison(&mut self) -> ParseResult<Node> {
+        let node = self.additive()?;
+        let op = match self.eat_op(&["==", "!=", "<", "<=", ">", ">="]) {
+            Some("==") => BinaryOp::Eq,
+            Some("!=") => BinaryOp::Ne,
+            Some("<") => BinaryOp::Lt,
+            Some("<=") => BinaryOp::Le,
+            Some(">") => BinaryOp::Gt,
+            Some(">=") => BinaryOp::Ge,
+            _ => return Ok(node),
+        };
+        Ok(Node::Binary(op, Box::new(node), Box::new(self.additive()?)))
+    }
+
+    fn additive(&mut self) -> ParseResult<Node> {
+        let mut node = self.multiplicative()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            let op = if op == "+" { BinaryOp::Add } else { BinaryOp::Sub };
+            node = Node::Binary(op, Box::new(node), Box::new(self.multiplicative()?));
+        }
+        Ok(node)
+    }
+
+    fn multiplicative(&mut self) -> ParseResult<Node> {
+        let mut node = self.unary()?;
+        while let Some(op) = self.eat_op(&["*", "/"]) {
+            let op = if op == "*" { BinaryOp::Mul } else { BinaryOp::Div };
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> ParseResult<Node> {
+        match self.eat_op(&["!", "-"]) {
+            Some("!") => Ok(Node::Not(Box::new(self.unary()?))),
+            Some(_) => Ok(Node::Neg(Box::new(self.unary()?))),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> ParseResult<Node> {
+        match self.next() {
+            Some(Token::Number(number, true)) if number <= i64::MAX as f64 => Ok(Node::Literal(Value::Int64(number as i64))),
+            Some(Token::Number(number, _)) => Ok(Node::Literal(Value::Float64(number))),
+            Some(Token::String(text)) => Ok(Node::Literal(Value::String(text))),
+            Some(Token::LParen) => {
+                let node = self.expression()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(node),
+                    _ => Err("expected ')'".to_string()),
+                }
+            }
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" => Ok(Node::Literal(Value::Bool(true))),
+                "false" => Ok(Node::Literal(Value::Bool(false))),
+                "null" => Ok(Node::Literal(Value::Null)),
+                _ if self.peek() == Some(&Token::LParen) => self.call(ident),
+                _ => Ok(Node::Field(ident)),
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: String) -> ParseResult<Node> {
+        let function = Function::from_name(&name).ok_or_else(|| format!("unknown function {}", name))?;
+        self.position += 1; // '('
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.position += 1;
+        } else {
+            loop {
+                args.push(self.expression()?);
+                match self.next() {
+                    Some(Token::Comma) => continue,
+                    Some(Token::RParen) => break,
+                    _ => return Err(format!("expected ',' or ')' in call to {}", name)),
+                }
+            }
+        }
+        match function.arity() {
+            Some(arity) if arity != args.len() => {
+                Err(format!("{} takes {} argument(s), got {}", name, arity, args.len()))
+            }
+            None if args.is_empty() => Err(format!("{} needs at least one argument", name)),
+            _ => Ok(Node::Call(function, args)),
+        }
+    }
+}
+
+/// Values other than `null` and `false` count as true
+pub(crate) fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Int32(_) | Value::Int64(_) | Value::UInt64(_) => "integer",
+        Value::Float32(_) | Value::Float64(_) => "float",
+        Value::String(_) => "string",
+        Value::Binary(_) => "binary",
+        Value::Document(_) => "document",
+        Value::Array(_) => "array",
+        Value::Timestamp(_) => "timestamp",
+        Value::ObjectId(_) => "object id",
+        Value::Vector(_) => "vector",
+        Value::Decimal128(_) => "decimal",
+    }
+}
+
+fn as_i64(value: &Value) -> Option<i64> {
+    match value {
+        Value::Int32(i) => Some(*i as i64),
+        Value::Int64(i) => Some(*i),
+        Value::UInt64(u) => i64::try_from(*u).ok(),
+        _ => None,
+    }
+}
+
+fn evaluate(node: &Node, document: &Document, now: Timestamp) -> std::result::Result<Value, String> {
+    match node {
+        Node::Literal(value) => Ok(value.clone()),
+        Node::Field(path) => Ok(DocumentUtils::get_field(document, path).cloned().unwrap_or(Value::Null)),
+        Node::Not(inner) => Ok(Value::Bool(!is_truthy(&evaluate(inner, document, now)?))),
+        Node::Neg(inner) => match evaluate(inner, document, now)? {
+            Value::Null => Ok(Value::Null),
+            value => match (as_i64(&value), as_f64(&value)) {
+                (Some(i), _) => i.checked_neg().map(Value::Int64).ok_or_else(|| "integer overflow".to_string()),
+                (None, Some(f)) => Ok(Value::Float64(-f)),
+                _ => Err(format!("cannot negate a {}", type_name(&value))),
+            },
+        },
+        Node::Binary(BinaryOp::And, left, right) => {
+            let left = is_truthy(&evaluate(left, document, now)?);
+            Ok(Value::Bool(left && is_truthy(&evaluate(right, document, now)?)))
+        }
+        Node::Binary(BinaryOp::Or, left, right) => {
+            let left = is_truthy(&evaluate(left, document, now)?);
+            Ok(Value::Bool(left || is_truthy(&evaluate(right, document, now)?)))
+        }
+        Node::Binary(op, left, right) => {
+            let left = evaluate(left, document, now)?;
+            let right = evaluate(right, document, now)?;
+            binary(*op, &left, &right)
+        }
+        Node::Call(function, args) => {
+            let args = args
+                .iter()
+                .map(|arg| evaluate(arg, document, now))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            call(*function, args, now)
+        }
+    }
+}
+
+fn binary(op: BinaryOp, left: &Value, right: &Value) -> std::result::Result<Value, String> {
+    match op {
+        BinaryOp::Eq => return Ok(Value::Bool(values_equal(left, right))),
+        BinaryOp::Ne => return Ok(Value::Bool(!values_equal(left, right))),
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            if matches!(left, Value::Null) || matches!(right, Value::Null) {
+                return Ok(Value::Bool(false));
+            }
+            let comparable = (as_f64(left).is_some() && as_f64(right).is_some())
+                || matches!(
+                    (left, right),
+                    (Value::String(_), Value::String(_)) | (Value::Bool(_), Value::Bool(_)) | (Value::Timestamp(_), Value::Timestamp(_))
+                );
+            if !comparable {
+                return Err(format!("cannot compare a {} with a {}", type_name(left), type_name(right)));
+            }
+            let ordering = compare_values(left, right);
+            return Ok(Value::Bool(match op {
+                BinaryOp::Lt => ordering.is_lt(),
+                BinaryOp::Le => ordering.is_le(),
+                BinaryOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }));
+        }
+        _ => {}
+    }
+
+    if matches!(left, Value::Null) || matches!(right, Value::Null) {
+        return Ok(Value::Null);
+    }
+    match (op, left, right) {
+        (BinaryOp::Add, Value::Timestamp(t), offset) | (BinaryOp::Add, offset, Value::Timestamp(t)) if as_i64(offset).is_some() => {
+            return t.checked_add(as_i64(offset).unwrap()).map(Value::Timestamp).ok_or_else(|| "timestamp overflow".to_string());
+        }
+        (BinaryOp::Sub, Value::Timestamp(t), offset) if as_i64(offset).is_some() => {
+            return t.checked_sub(as_i64(offset).unwrap()).map(Value::Timestamp).ok_or_else(|| "timestamp overflow".to_string());
+        }
+        (BinaryOp::Sub, Value::Timestamp(a), Value::Timestamp(b)) => return Ok(Value::Int64(a - b)),
+        _ => {}
+    }
+
+    if let (Some(a), Some(b)) = (as_i64(left), as_i64(right)) {
+        let result = match op {
+            BinaryOp::Add => a.checked_add(b),
+            BinaryOp::Sub => a.checked_sub(b),
+            BinaryOp::Mul => a.checked_mul(b),
+            _ if b == 0 => return Err("division by zero".to_string()),
+            _ => return Ok(Value::Float64(a as f64 / b as f64)),
+        };
+        return result.map(Value::Int64).ok_or_else(|| "integer overflow".to_string());
+    }
+    match (as_f64(left), as_f64(right)) {
+        (Some(a), Some(b)) => Ok(Value::Float64(match op {
+            BinaryOp::Add => a + b,
+            BinaryOp::Sub => a - b,
+            BinaryOp::Mul => a * b,
+            _ if b == 0.0 => return Err("division by zero".to_string()),
+            _ => a / b,
+        })),
+        _ => Err(format!("cannot apply {:?} to a {} and a {}", op, type_name(left), type_name(right))),
+    }
+}
+
+fn call(function: Function, args: Vec<Value>, now: Timestamp) -> std::result::Result<Value, String> {
+    let string_fn = |map: fn(&str) -> String, name: &str| match &args[0] {
+        Value::Null => Ok(Value::Null),
+        Value::String(s) => Ok(Value::String(map(s))),
+        other => Err(format!("{} expects a string, got a {}", name, type_name(other))),
+    };
+    match function {
+        Function::Lower => string_fn(str::to_lowercase, "lower"),
+        Function::Upper => string_fn(str::to_uppercase, "upper"),
+        Function::Trim => string_fn(|s| s.trim().to_string(), "trim"),
+        Function::Now => Ok(Value::Timestamp(now)),
+        Function::Coalesce => Ok(args.into_iter().find(|value| !matches!(value, Value::Null)).unwrap_or(Value::Null)),
+        Function::Len => match &args[0] {
+            Value::Null => Ok(Value::Null),
+            Value::String(s) => Ok(Value::Int64(s.chars().count() as i64)),
+            Value::Array(values) => Ok(Value::Int64(values.len() as i64)),
+            Value::Binary(bytes) => Ok(Value::Int64(bytes.len() as i64)),
+            Value::Vector(vector) => Ok(Value::Int64(vector.len() as i64)),
+            other => Err(format!("len expects a string or array, got a {}", type_name(other))),
+        },
+        // Null arguments are skipped, so optional parts drop out
+        Function::Concat => {
+            let mut text = String::new();
+            for value in &args {
+                match value {
+                    Value::Null => {}
+                    Value::String(s) => text.push_str(s),
+                    Value::Bool(b) => text.push_str(&b.to_string()),
+                    value => match (as_i64(value), as_f64(value)) {
+                        (Some(i), _) => text.push_str(&i.to_string()),
+                        (None, Some(f)) => text.push_str(&f.to_string()),
+                        _ => return Err(format!("concat cannot include a {}", type_name(value))),
+                    },
+                }
+            }
+            Ok(Value::String(text))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::DocumentBuilder;
+
+    fn eval(source: &str, document: &Document) -> Value {
+        Expr::parse(source).unwrap().evaluate(document, 1_000).unwrap()
+    }
+
+    #[test]
+    fn test_expressions_evaluate_against_the_document() {
+        let profile = DocumentBuilder::new().string("city", "Lagos").build();
+        let doc = DocumentBuilder::new()
+            .string("username", "  Ada_L ")
+            .int("age", 36)
+            .float("score", 1.5)
+            .document("profile", profile)
+            .build();
+
+        assert!(matches!(eval("lower(trim(username))", &doc), Value::String(s) if s == "ada_l"));
+        assert!(matches!(eval("concat(upper(profile.city), '-', age)", &doc), Value::String(s) if s == "LAGOS-36"));
+        assert!(matches!(eval("age * 2 + 1", &doc), Value::Int64(73)));
+        assert!(matches!(eval("score + 1", &doc), Value::Float64(f) if f == 2.5));
+        assert!(matches!(eval("now() + 500", &doc), Value::Timestamp(1_500)));
+        assert!(matches!(eval("age >= 18 && len(username) < 10", &doc), Value::Bool(true)));
+        assert!(matches!(eval("!(age == 36) || missing != null", &doc), Value::Bool(false)));
+        assert!(matches!(eval("coalesce(nickname, 'anon')", &doc), Value::String(s) if s == "anon"));
+        assert!(matches!(eval("lower(nickname)", &doc), Value::Null));
+    }
+
+    #[test]
+    fn test_invalid_expressions_are_rejected() {
+        assert!(Expr::parse("lower(").is_err());
+        assert!(Expr::parse("shout(name)").is_err());
+        assert!(Expr::parse("lower(a, b)").is_err());
+        assert!(Expr::parse("a +").is_err());
+        assert!(Expr::parse("'open").is_err());
+        assert!(Expr::parse("a b").is_err());
+
+        let doc = DocumentBuilder::new().int("age", 3).build();
+        let err = Expr::parse("lower(age)").unwrap().evaluate(&doc, 0).unwrap_err();
+        assert!(matches!(err, LargetableError::Validation(_)));
+        assert!(Expr::parse("age / 0").unwrap().evaluate(&doc, 0).is_err());
+    }
+}
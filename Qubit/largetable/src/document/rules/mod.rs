@@ -0,0 +1,199 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Per-collection write rules: defaults, computed fields and validators
+//!
+//! Rules run inside the collection's write path, after the document's
+//! metadata is stamped and before it reaches storage, indexes and the oplog.
+//! A stored document therefore always carries its derived fields, and a
+//! write that fails a validator leaves nothing behind.
+
+pub mod expr;
+
+pub use expr::Expr;
+
+use crate::document::DocumentUtils;
+use crate::{Document, LargetableError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// When a computed field is evaluated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComputeOn {
+    /// On every insert and update, e.g. `updated_at = now()`
+    #[default]
+    Write,
+    /// Once, when the document is inserted; updates keep the stored value
+    Insert,
+}
+
+/// A field whose value is derived from the rest of the document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComputedField {
+    /// Field path, dotted for nested documents
+    pub field: String,
+    pub expr: Expr,
+    #[serde(default)]
+    pub on: ComputeOn,
+}
+
+/// A condition every written document must satisfy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Validator {
+    pub expr: Expr,
+    /// Returned to the client when the condition is false or null
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Defaults, computed fields and validators of one collection
+///
+/// On every write, defaults fill the fields the document is missing, then
+/// computed fields are evaluated in order, each seeing the ones before it,
+/// then every validator must hold. Updates replace whole documents, so
+/// defaults apply to them as well.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WriteRules {
+    /// Expressions for fields absent from a written document, by field path
+    #[serde(default)]
+    pub defaults: BTreeMap<String, Expr>,
+    #[serde(default)]
+    pub computed: Vec<ComputedField>,
+    #[serde(default)]
+    pub validators: Vec<Validator>,
+}
+
+impl WriteRules {
+    pub fn is_empty(&self) -> bool {
+        self.defaults.is_empty() && self.computed.is_empty() && self.validators.is_empty()
+    }
+
+    /// Reject field paths a document cannot hold and fields defined twice
+    pub fn validate(&self) -> Result<()> {
+        let mut computed = HashSet::new();
+        for field in self.defaults.keys().chain(self.computed.iter().map(|computed| &computed.field)) {
+            if field.is_empty() || field.starts_with('_') || field.split('.').any(str::is_empty) {
+                return Err(LargetableError::Query(format!("Invalid rule field path: '{}'", field)));
+            }
+        }
+        for field in &self.computed {
+            if !computed.insert(field.field.as_str()) {
+                return Err(LargetableError::Query(format!("Field '{}' is computed more than once", field.field)));
+            }
+            if self.defaults.contains_key(&field.field) {
+                return Err(LargetableError::Query(format!("Field '{}' has both a default and a computed value", field.field)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the rules to a document about to be written
+    ///
+    /// `previous` is the stored version on update. Expressions see the
+    /// document's `updated_at` as `now()`, so every field computed by one
+    /// write carries the same timestamp.
+    pub fn apply(&self, document: &mut Document, previous: Option<&Document>) -> Result<()> {
+        let now = document.updated_at;
+
+        for (field, expr) in &self.defaults {
+            if DocumentUtils::get_field(document, field).is_none() {
+                let value = expr.evaluate(document, now)?;
+                DocumentUtils::set_field(document, field, value)?;
+            }
+        }
+
+        for computed in &self.computed {
+            let kept = match (computed.on, previous) {
+                (ComputeOn::Insert, Some(previous)) => DocumentUtils::get_field(previous, &computed.field).cloned(),
+                _ => None,
+            };
+            let value = match kept {
+                Some(value) => value,
+                None => computed.expr.evaluate(document, now)?,
+            };
+            DocumentUtils::set_field(document, &computed.field, value)?;
+        }
+
+        for validator in &self.validators {
+            if !expr::is_truthy(&validator.expr.evaluate(document, now)?) {
+                let message = validator.message.clone().unwrap_or_else(|| format!("rule `{}` failed", validator.expr));
+                return Err(LargetableError::Validation(message));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::DocumentBuilder;
+    use crate::Value;
+
+    fn rules() -> WriteRules {
+        serde_json::from_value(serde_json::json!({
+            "defaults": {"role": "'member'", "profile.visibility": "'public'"},
+            "computed": [
+                {"field": "lowercase_username", "expr": "lower(trim(username))"},
+                {"field": "signup_at", "expr": "now()", "on": "insert"},
+                {"field": "last_modified", "expr": "now()"}
+            ],
+            "validators": [
+                {"expr": "len(lowercase_username) >= 3", "message": "username is too short"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn text<'a>(doc: &'a Document, field: &str) -> Option<&'a str> {
+        match DocumentUtils::get_field(doc, field) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_rules_fill_defaults_and_computed_fields() {
+        let rules = rules();
+        rules.validate().unwrap();
+
+        let mut inserted = DocumentBuilder::new().string("username", " Ada ").string("role", "admin").build();
+        inserted.updated_at = 100;
+        rules.apply(&mut inserted, None).unwrap();
+        assert_eq!(text(&inserted, "role"), Some("admin"));
+        assert_eq!(text(&inserted, "lowercase_username"), Some("ada"));
+        assert!(matches!(inserted.fields.get("signup_at"), Some(Value::Timestamp(100))));
+
+        let mut updated = DocumentBuilder::new().string("username", "AdaL").build();
+        updated.updated_at = 200;
+        rules.apply(&mut updated, Some(&inserted)).unwrap();
+        assert_eq!(text(&updated, "role"), Some("member"));
+        assert_eq!(text(&updated, "profile.visibility"), Some("public"));
+        assert_eq!(text(&updated, "lowercase_username"), Some("adal"));
+        assert!(matches!(updated.fields.get("signup_at"), Some(Value::Timestamp(100))));
+        assert!(matches!(updated.fields.get("last_modified"), Some(Value::Timestamp(200))));
+    }
+
+    #[test]
+    fn test_rules_reject_invalid_documents_and_definitions() {
+        let rules = rules();
+        let mut short = DocumentBuilder::new().string("username", "al").build();
+        match rules.apply(&mut short, None) {
+            Err(LargetableError::Validation(message)) => assert_eq!(message, "username is too short"),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+
+        let mut duplicate = rules.clone();
+        duplicate.defaults.insert("last_modified".to_string(), "0".parse().unwrap());
+        assert!(duplicate.validate().is_err());
+
+        let mut reserved = WriteRules::default();
+        reserved.defaults.insert("_id".to_string(), "null".parse().unwrap());
+        assert!(reserved.validate().is_err());
+        assert!(serde_json::from_value::<WriteRules>(serde_json::json!({"defaults": {"a": "lower("}})).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Schema inference from sampled documents
+//!
+//! Describes the fields documents actually have: the types seen for each,
+//! how often each appears and the structure nested inside documents and
+//! arrays, in a form code generators can read.
+
+use crate::{Document, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Values nested deeper than this are counted but not looked inside
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// Type of a stored value, one per `Value` variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueType {
+    Null,
+    Bool,
+    Int32,
+    Int64,
+    UInt64,
+    Float32,
+    Float64,
+    String,
+    Binary,
+    Document,
+    Array,
+    Timestamp,
+    ObjectId,
+    Vector,
+    Decimal128,
+}
+
+impl ValueType {
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => ValueType::Null,
+            Value::Bool(_) => ValueType::Bool,
+            Value::Int32(_) => ValueType::Int32,
+            Value::Int64(_) => ValueType::Int64,
+            Value::UInt64(_) => ValueType::UInt64,
+            Value::Float32(_) => ValueType::Float32,
+            Value::Float64(_) => ValueType::Float64,
+            Value::String(_) => ValueType::String,
+            Value::Binary(_) => ValueType::Binary,
+            Value::Document(_) => ValueType::Document,
+            Value::Array(_) => ValueType::Array,
+            Value::Timestamp(_) => ValueType::Timestamp,
+            Value::ObjectId(_) => ValueType::ObjectId,
+            Value::Vector(_) => ValueType::Vector,
+            Value::Decimal128(_) => ValueType::Decimal128,
+        }
+    }
+}
+
+/// How many of the values seen had a type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeCount {
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+    pub count: u64,
+}
+
+/// Shortest and longest of the arrays or vectors seen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LengthRange {
+    pub min: usize,
+    pub max: usize,
+}
+
+/// The values seen at one place in the documents
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ValueSchema {
+    /// Most common first
+    pub types: Vec<TypeCount>,
+    /// Fields of the values that were documents
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldSchema>,
+    /// Elements of the values that were arrays, all arrays together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<ValueSchema>>,
+    /// Lengths of the values that were arrays or vectors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lengths: Option<LengthRange>,
+}
+
+impl ValueSchema {
+    /// The only type seen, ignoring nulls
+    pub fn single_type(&self) -> Option<ValueType> {
+        let mut types = self.types.iter().map(|count| count.value_type).filter(|t| *t != ValueType::Null);
+        let first = types.next()?;
+        types.next().is_none().then_some(first)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// A field of the documents at one place
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldSchema {
+    pub name: String,
+    /// Dotted path from the top of the document; array elements add no segment
+    pub path: String,
+    /// Documents at this place that have the field
+    pub count: u64,
+    /// `count` as a share of the documents at this place
+    pub frequency: f64,
+    /// In every document at this place and never null
+    pub required: bool,
+    #[serde(flatten)]
+    pub schema: ValueSchema,
+}
+
+/// The shape of a set of documents
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct InferredSchema {
+    /// Documents the schema was inferred from
+    pub documents: u64,
+    /// Top-level fields, by name; `_id` and the other metadata fields every
+    /// document has are not listed
+    pub fields: Vec<FieldSchema>,
+}
+
+impl InferredSchema {
+    pub fn field(&self, name: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Look a field up by its dotted path, stepping into array elements
+    pub fn path(&self, path: &str) -> Option<&FieldSchema> {
+        let mut segments = path.split('.');
+        let mut field = self.field(segments.next()?)?;
+        for segment in segments {
+            let mut schema = &field.schema;
+            while schema.field(segment).is_none() {
+                schema = schema.items.as_deref()?;
+            }
+            field = schema.field(segment)?;
+        }
+        Some(field)
+    }
+}
+
+/// Builds an `InferredSchema` from documents seen one at a time
+#[derive(Debug)]
+pub struct SchemaInference {
+    max_depth: usize,
+    documents: u64,
+    root: Shape,
+}
+
+/// Running counts for the values seen at one place
+#[derive(Debug, Default)]
+struct Shape {
+    types: HashMap<ValueType, u64>,
+    fields: BTreeMap<String, Shape>,
+    items: Option<Box<Shape>>,
+    lengths: Option<LengthRange>,
+}
+
+impl Default for SchemaInference {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DEPTH)
+    }
+}
+
+impl SchemaInference {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            documents: 0,
+            root: Shape::default(),
+        }
+    }
+
+    pub fn observe(&mut self, document: &Document) {
+        self.documents += 1;
+        self.root.observe_fields(document, 1, self.max_depth);
+    }
+
+    pub fn documents(&self) -> u64 {
+        self.documents
+    }
+
+    pub fn finish(self) -> InferredSchema {
+        InferredSchema {
+            documents: self.documents,
+            fields: self.root.field_schemas("", self.documents),
+        }
+    }
+}
+
+impl Shape {
+    fn observe(&mut self, value: &Value, depth: usize, max_depth: usize) {
+        *self.types.entry(ValueType::of(value)).or_default() += 1;
+        match value {
+            Value::Document(document) if depth < max_depth => self.observe_fields(document, depth + 1, max_depth),
+            Value::Array(elements) => {
+                self.observe_length(elements.len());
+                if depth < max_depth {
+                    let items = self.items.get_or_insert_with(Default::default);
+                    for element in elements {
+                        items.observe(element, depth + 1, max_depth);
+                    }
+                }
+            }
+            Value::Vector(vector) => self.observe_length(vector.len()),
+            _ => {}
+        }
+    }
+
+    fn observe_fields(&mut self, document: &Document, depth: usize, max_depth: usize) {
+        for (name, value) in &document.fields {
+            self.fields.entry(name.clone()).or_default().observe(value, depth, max_depth);
+        }
+    }
+
+    fn observe_length(&mut self, length: usize) {
+        let lengths = self.lengths.get_or_insert(LengthRange { min: length, max: length });
+        lengths.min = lengths.min.min(length);
+        lengths.max = lengths.max.max(length);
+    }
+
+    /// Fields seen in the `documents` documents at `prefix`
+    fn field_schemas(&self, prefix: &str, documents: u64) -> Vec<FieldSchema> {
+        self.fields
+            .iter()
+            .map(|(name, shape)| {
+                let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+                let count: u64 = shape.types.values().sum();
+                FieldSchema {
+                    name: name.clone(),
+                    count,
+                    frequency: if documents == 0 { 0.0 } else { count as f64 / documents as f64 },
+                    required: count == documents && !shape.types.contains_key(&ValueType::Null),
+                    schema: shape.value_schema(&path),
+                    path,
+                }
+            })
+            .collect()
+    }
+
+    fn value_schema(&self, path: &str) -> ValueSchema {
+        let mut types: Vec<TypeCount> = self
+            .types
+            .iter()
+            .map(|(value_type, count)| TypeCount { value_type: *value_type, count: *count })
+            .collect();
+        types.sort_by(|a, b| b.count.cmp(&a.count).then(a.value_type.cmp(&b.value_type)));
+
+        let documents = self.types.get(&ValueType::Document).copied().unwrap_or(0);
+        ValueSchema {
+            types,
+            fields: self.field_schemas(path, documents),
+            items: self.items.as_ref().map(|items| Box::new(items.value_schema(path))),
+            lengths: self.lengths,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::DocumentUtils;
+    use serde_json::json;
+
+    fn infer(documents: Vec<serde_json::Value>, max_depth: usize) -> InferredSchema {
+        let mut inference = SchemaInference::new(max_depth);
+        for document in documents {
+            inference.observe(&DocumentUtils::from_json(document).unwrap());
+        }
+        inference.finish()
+    }
+
+    #[test]
+    fn test_infers_types_and_frequencies() {
+        let schema = infer(
+            vec![
+                json!({"username": "ada", "age": 36, "bio": null}),
+                json!({"username": "grace", "age": 45.5}),
+                json!({"username": "linus", "age": 28}),
+                json!({"username": "margaret"}),
+            ],
+            DEFAULT_MAX_DEPTH,
+        );
+        assert_eq!(schema.documents, 4);
+        assert_eq!(schema.fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["age", "bio", "username"]);
+
+        let username = schema.field("username").unwrap();
+        assert_eq!((username.count, username.frequency, username.required), (4, 1.0, true));
+        assert_eq!(username.schema.single_type(), Some(ValueType::String));
+
+        // Most common type first
+        let age = schema.field("age").unwrap();
+        assert_eq!((age.count, age.frequency, age.required), (3, 0.75, false));
+        assert_eq!(age.schema.types[0].count, 2);
+        assert_eq!(age.schema.types.len(), 2);
+        assert_eq!(age.schema.single_type(), None);
+
+        // Null is counted as a type but does not make the field mixed
+        let bio = schema.field("bio").unwrap();
+        assert_eq!(bio.schema.types, vec![TypeCount { value_type: ValueType::Null, count: 1 }]);
+        assert!(!bio.required);
+    }
+
+    #[test]
+    fn test_infers_nested_documents_and_arrays() {
+        let schema = infer(
+            vec![
+                json!({"profile": {"city": "London", "links": {"site": "a.dev"}}, "tags": ["rust", "db"],
+                       "comments": [{"author": "ada", "likes": 3}, {"author": "alan"}]}),
+                json!({"profile": {"city": "Paris"}, "tags": [],
+                       "comments": [{"author": "grace", "likes": 1}]}),
+            ],
+            DEFAULT_MAX_DEPTH,
+        );
+
+        // Nested frequencies are shares of the documents at that place
+        let city = schema.path("profile.city").unwrap();
+        assert_eq!((city.path.as_str(), city.frequency, city.required), ("profile.city", 1.0, true));
+        let site = schema.path("profile.links.site").unwrap();
+        assert_eq!((site.count, site.frequency), (1, 1.0));
+        assert_eq!(schema.path("profile.links").unwrap().frequency, 0.5);
+
+        let tags = schema.field("tags").unwrap();
+        assert_eq!(tags.schema.lengths, Some(LengthRange { min: 0, max: 2 }));
+        assert_eq!(tags.schema.items.as_ref().unwrap().single_type(), Some(ValueType::String));
+
+        // Array elements are pooled, so fields of element documents count elements
+        let likes = schema.path("comments.likes").unwrap();
+        assert_eq!((likes.path.as_str(), likes.count, likes.required), ("comments.likes", 2, false));
+        assert!((likes.frequency - 2.0 / 3.0).abs() < 1e-9);
+        assert!(schema.path("comments.author").unwrap().required);
+        assert!(schema.path("comments.missing").is_none());
+    }
+
+    #[test]
+    fn test_stops_descending_at_max_depth() {
+        let schema = infer(vec![json!({"a": {"b": {"c": 1}}, "list": [[1, 2]]})], 2);
+
+        let b = schema.path("a.b").unwrap();
+        assert_eq!(b.schema.single_type(), Some(ValueType::Document));
+        assert!(b.schema.fields.is_empty());
+
+        let list = schema.field("list").unwrap();
+        let inner = list.schema.items.as_ref().unwrap();
+        assert_eq!(inner.single_type(), Some(ValueType::Array));
+        assert_eq!(inner.lengths, Some(LengthRange { min: 2, max: 2 }));
+        assert!(inner.items.is_none());
+    }
+
+    #[test]
+    fn test_schema_is_machine_readable() {
+        let schema = infer(vec![json!({"score": 1, "profile": {"city": "Oslo"}})], DEFAULT_MAX_DEPTH);
+        let json = serde_json::to_value(&schema).unwrap();
+
+        assert_eq!(json["documents"], 1);
+        assert_eq!(json["fields"][0]["name"], "profile");
+        assert_eq!(json["fields"][0]["types"][0]["type"], "document");
+        assert_eq!(json["fields"][0]["fields"][0]["path"], "profile.city");
+        assert!(json["fields"][1].get("items").is_none());
+
+        let parsed: InferredSchema = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, schema);
+    }
+}
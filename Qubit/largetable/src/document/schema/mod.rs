@@ -14,9 +14,11 @@ use thiserror::Error;
 
 pub mod field;
 pub mod document_type;
+pub mod inference;
 
 pub use field::{Field, FieldType, FieldConstraints};
 pub use document_type::{DocumentSchema, SchemaVersion};
+pub use inference::{FieldSchema, InferredSchema, SchemaInference, ValueSchema, ValueType};
 
 /// Errors encountered during schema validation.
 #[derive(Error, Debug)]
@@ -0,0 +1,304 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Borrowed document views over owned and archived documents
+//!
+//! Storage engines keep documents as rkyv archives. Decoding one into a
+//! `Document` allocates every string, array and nested map; a view reads the
+//! archive in place instead, so a hot read path can inspect a few fields or
+//! deserialize straight into a Rust struct without building the map first.
+//! The same view type wraps an owned `Document`, so code written against
+//! views works on both.
+
+use crate::types::{ArchivedDocument, ArchivedValue};
+use crate::{Document, DocumentId, LargetableError, Result, Timestamp, Value};
+use rkyv::AlignedVec;
+
+/// Read-only view of a document, borrowed from an owned document or an archive
+#[derive(Debug, Clone, Copy)]
+pub enum DocumentView<'a> {
+    Owned(&'a Document),
+    Archived(&'a ArchivedDocument),
+}
+
+impl<'a> DocumentView<'a> {
+    pub fn id(&self) -> DocumentId {
+        match self {
+            Self::Owned(doc) => doc.id,
+            Self::Archived(doc) => doc.id,
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        match self {
+            Self::Owned(doc) => doc.version,
+            Self::Archived(doc) => doc.version,
+        }
+    }
+
+    pub fn created_at(&self) -> Timestamp {
+        match self {
+            Self::Owned(doc) => doc.created_at,
+            Self::Archived(doc) => doc.created_at,
+        }
+    }
+
+    pub fn updated_at(&self) -> Timestamp {
+        match self {
+            Self::Owned(doc) => doc.updated_at,
+            Self::Archived(doc) => doc.updated_at,
+        }
+    }
+
+    /// Number of user fields, not counting metadata
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Owned(doc) => doc.fields.len(),
+            Self::Archived(doc) => doc.fields.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Top-level field by name
+    pub fn get(&self, field: &str) -> Option<ValueView<'a>> {
+        match *self {
+            Self::Owned(doc) => doc.fields.get(field).map(ValueView::from),
+            Self::Archived(doc) => doc.fields.get(field).map(ValueView::from),
+        }
+    }
+
+    /// Field by dotted path, descending through nested documents
+    pub fn get_path(&self, path: &str) -> Option<ValueView<'a>> {
+        let mut parts = path.split('.');
+        let mut value = self.get(parts.next()?)?;
+        for part in parts {
+            match value {
+                ValueView::Document(doc) => value = doc.get(part)?,
+                _ => return None,
+            }
+        }
+        Some(value)
+    }
+
+    /// User fields in storage order
+    pub fn fields(&self) -> Box<dyn Iterator<Item = (&'a str, ValueView<'a>)> + 'a> {
+        match *self {
+            Self::Owned(doc) => Box::new(doc.fields.iter().map(|(key, value)| (key.as_str(), ValueView::from(value)))),
+            Self::Archived(doc) => Box::new(doc.fields.iter().map(|(key, value)| (key.as_str(), ValueView::from(value)))),
+        }
+    }
+
+    /// Decode into an owned document
+    pub fn to_document(&self) -> Document {
+        match self {
+            Self::Owned(doc) => (*doc).clone(),
+            Self::Archived(doc) => Document {
+                id: doc.id,
+                fields: doc.fields.iter().map(|(key, value)| (key.to_string(), ValueView::from(value).to_value())).collect(),
+                version: doc.version,
+                created_at: doc.created_at,
+                updated_at: doc.updated_at,
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a Document> for DocumentView<'a> {
+    fn from(doc: &'a Document) -> Self {
+        Self::Owned(doc)
+    }
+}
+
+impl<'a> From<&'a ArchivedDocument> for DocumentView<'a> {
+    fn from(doc: &'a ArchivedDocument) -> Self {
+        Self::Archived(doc)
+    }
+}
+
+/// Read-only view of a value; strings, binary and vectors borrow their bytes
+#[derive(Debug, Clone, Copy)]
+pub enum ValueView<'a> {
+    Null,
+    Bool(bool),
+    Int32(i32),
+    Int64(i64),
+    UInt64(u64),
+    Float32(f32),
+    Float64(f64),
+    String(&'a str),
+    Binary(&'a [u8]),
+    Document(DocumentView<'a>),
+    Array(ArrayView<'a>),
+    Timestamp(Timestamp),
+    ObjectId(DocumentId),
+    Vector(&'a [f32]),
+    Decimal128(&'a [u8; 16]),
+}
+
+impl ValueView<'_> {
+    /// Decode into an owned value
+    pub fn to_value(&self) -> Value {
+        match *self {
+            Self::Null => Value::Null,
+            Self::Bool(b) => Value::Bool(b),
+            Self::Int32(i) => Value::Int32(i),
+            Self::Int64(i) => Value::Int64(i),
+            Self::UInt64(u) => Value::UInt64(u),
+            Self::Float32(f) => Value::Float32(f),
+            Self::Float64(f) => Value::Float64(f),
+            Self::String(s) => Value::String(s.to_string()),
+            Self::Binary(b) => Value::Binary(b.to_vec()),
+            Self::Document(doc) => Value::Document(doc.to_document()),
+            Self::Array(array) => Value::Array(array.iter().map(|value| value.to_value()).collect()),
+            Self::Timestamp(t) => Value::Timestamp(t),
+            Self::ObjectId(id) => Value::ObjectId(id),
+            Self::Vector(v) => Value::Vector(v.to_vec()),
+            Self::Decimal128(d) => Value::Decimal128(*d),
+        }
+    }
+}
+
+impl<'a> From<&'a Value> for ValueView<'a> {
+    fn from(value: &'a Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(b) => Self::Bool(*b),
+            Value::Int32(i) => Self::Int32(*i),
+            Value::Int64(i) => Self::Int64(*i),
+            Value::UInt64(u) => Self::UInt64(*u),
+            Value::Float32(f) => Self::Float32(*f),
+            Value::Float64(f) => Self::Float64(*f),
+            Value::String(s) => Self::String(s),
+            Value::Binary(b) => Self::Binary(b),
+            Value::Document(doc) => Self::Document(DocumentView::Owned(doc)),
+            Value::Array(values) => Self::Array(ArrayView::Owned(values)),
+            Value::Timestamp(t) => Self::Timestamp(*t),
+            Value::ObjectId(id) => Self::ObjectId(*id),
+            Value::Vector(v) => Self::Vector(v),
+            Value::Decimal128(d) => Self::Decimal128(d),
+        }
+    }
+}
+
+impl<'a> From<&'a ArchivedValue> for ValueView<'a> {
+    fn from(value: &'a ArchivedValue) -> Self {
+        match value {
+            ArchivedValue::Null => Self::Null,
+            ArchivedValue::Bool(b) => Self::Bool(*b),
+            ArchivedValue::Int32(i) => Self::Int32(*i),
+            ArchivedValue::Int64(i) => Self::Int64(*i),
+            ArchivedValue::UInt64(u) => Self::UInt64(*u),
+            ArchivedValue::Float32(f) => Self::Float32(*f),
+            ArchivedValue::Float64(f) => Self::Float64(*f),
+            ArchivedValue::String(s) => Self::String(s.as_str()),
+            ArchivedValue::Binary(b) => Self::Binary(b.as_slice()),
+            ArchivedValue::Document(doc) => Self::Document(DocumentView::Archived(doc)),
+            ArchivedValue::Array(values) => Self::Array(ArrayView::Archived(values.as_slice())),
+            ArchivedValue::Timestamp(t) => Self::Timestamp(*t),
+            ArchivedValue::ObjectId(id) => Self::ObjectId(*id),
+            ArchivedValue::Vector(v) => Self::Vector(v.as_slice()),
+            ArchivedValue::Decimal128(d) => Self::Decimal128(d),
+        }
+    }
+}
+
+/// Read-only view of an array value
+#[derive(Debug, Clone, Copy)]
+pub enum ArrayView<'a> {
+    Owned(&'a [Value]),
+    Archived(&'a [ArchivedValue]),
+}
+
+impl<'a> ArrayView<'a> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Owned(values) => values.len(),
+            Self::Archived(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<ValueView<'a>> {
+        match *self {
+            Self::Owned(values) => values.get(index).map(ValueView::from),
+            Self::Archived(values) => values.get(index).map(ValueView::from),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ValueView<'a>> + 'a {
+        let array = *self;
+        (0..array.len()).filter_map(move |index| array.get(index))
+    }
+}
+
+/// A document in its stored form, validated once and read in place
+///
+/// This is what storage engines hand back on the zero-copy read path. The
+/// archive is checked when the `StoredDocument` is built; after that, views
+/// and typed reads borrow from it without decoding fields.
+#[derive(Clone)]
+pub struct StoredDocument {
+    bytes: AlignedVec,
+}
+
+impl StoredDocument {
+    /// Validate a page read from storage
+    ///
+    /// Pages are copied once into an aligned buffer, since storage backends
+    /// make no alignment promises; no field is decoded.
+    pub fn from_page(page: &[u8]) -> Result<Self> {
+        let mut bytes = AlignedVec::with_capacity(page.len());
+        bytes.extend_from_slice(page);
+        Self::from_aligned(bytes)
+    }
+
+    /// Archive an owned document, for engines that do not keep archives
+    pub fn from_document(doc: &Document) -> Result<Self> {
+        let bytes = rkyv::to_bytes::<_, 1024>(doc)
+            .map_err(|e| LargetableError::Serialization(format!("Failed to serialize document: {}", e)))?;
+        Ok(Self { bytes })
+    }
+
+    fn from_aligned(bytes: AlignedVec) -> Result<Self> {
+        rkyv::check_archived_root::<Document>(&bytes)
+            .map_err(|e| LargetableError::Serialization(format!("Invalid stored document: {}", e)))?;
+        Ok(Self { bytes })
+    }
+
+    pub fn view(&self) -> DocumentView<'_> {
+        // SAFETY: the archive was validated when `self` was built and the
+        // buffer is never mutated afterwards.
+        DocumentView::Archived(unsafe { rkyv::archived_root::<Document>(&self.bytes) })
+    }
+
+    /// Deserialize into `T`, which may borrow strings and bytes from the archive
+    pub fn deserialize<'a, T: serde::Deserialize<'a>>(&'a self) -> Result<T> {
+        super::mapping::from_view(self.view())
+    }
+
+    pub fn to_document(&self) -> Document {
+        self.view().to_document()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl std::fmt::Debug for StoredDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoredDocument")
+            .field("id", &self.view().id())
+            .field("bytes", &self.bytes.len())
+            .finish()
+    }
+}
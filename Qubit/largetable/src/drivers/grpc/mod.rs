@@ -0,0 +1,322 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Async gRPC client for a remote Largetable server
+//!
+//! Mirrors the document API of the native [`Client`](crate::drivers::native::Client),
+//! so code can move between an embedded engine and a remote server without
+//! changing types.
+
+use crate::{Result, LargetableError, DatabaseName, CollectionName, DocumentId, Document, IndexType};
+use crate::auth::ScramClient;
+use crate::document::DocumentUtils;
+use crate::index::build::IndexBuildStatus;
+use crate::query::{Query, QueryResult, SortDirection};
+use futures::StreamExt;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::codec::CompressionEncoding;
+use tonic::service::interceptor::{InterceptedService, Interceptor};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint};
+use tonic::{Code, Request, Status};
+use tracing::info;
+
+pub use crate::network::grpc::proto;
+use proto::largetable_client::LargetableClient;
+
+/// gRPC client for Largetable
+///
+/// Cloning is cheap and clones share the underlying connection and session.
+#[derive(Clone)]
+pub struct GrpcClient {
+    inner: LargetableClient<InterceptedService<Channel, CallMetadata>>,
+    channel: Channel,
+    metadata: CallMetadata,
+}
+
+/// Sends the session token, once authenticated, and the client's timeout with every call
+#[derive(Clone, Default)]
+struct CallMetadata {
+    token: Arc<RwLock<Option<MetadataValue<Ascii>>>>,
+    timeout: Option<Duration>,
+}
+
+impl Interceptor for CallMetadata {
+    fn call(&mut self, mut request: Request<()>) -> std::result::Result<Request<()>, Status> {
+        if let Some(token) = self.token.read().clone() {
+            request.metadata_mut().insert("authorization", token);
+        }
+        if let Some(timeout) = self.timeout {
+            // Sent as `grpc-timeout`; the server stops the operation when it runs out
+            request.set_timeout(timeout);
+        }
+        Ok(request)
+    }
+}
+
+/// Engine error for a failed call, the inverse of the server's status mapping
+fn from_status(status: Status) -> LargetableError {
+    let message = status.message().to_string();
+    match status.code() {
+        Code::InvalidArgument => LargetableError::Query(message),
+        Code::FailedPrecondition => LargetableError::Index(message),
+        Code::ResourceExhausted => LargetableError::ResourceExhausted(message),
+        Code::Unauthenticated => LargetableError::Auth(message),
+        Code::PermissionDenied => LargetableError::PermissionDenied(message),
+        Code::DeadlineExceeded => LargetableError::DeadlineExceeded(message),
+        Code::Cancelled => LargetableError::Cancelled(message),
+        Code::OutOfRange => LargetableError::SnapshotUnavailable(message),
+        code => LargetableError::Network(format!("{}: {}", code, message)),
+    }
+}
+
+fn path(database: DatabaseName, collection: CollectionName) -> Option<proto::CollectionPath> {
+    Some(proto::CollectionPath { database, collection })
+}
+
+fn document_from_json(json: &str) -> Result<Document> {
+    DocumentUtils::from_json(serde_json::from_str(json)?)
+}
+
+fn parse_id(id: &str) -> Result<DocumentId> {
+    uuid::Uuid::parse_str(id).map_err(|e| LargetableError::Serialization(format!("Invalid document ID from server: {}", e)))
+}
+
+impl GrpcClient {
+    /// Connect to a server's gRPC endpoint, e.g. `http://largetable:50051`
+    pub async fn connect(endpoint: impl Into<String>) -> Result<Self> {
+        let endpoint = endpoint.into();
+        Self::connect_endpoint(Self::endpoint(&endpoint)?, &endpoint).await
+    }
+
+    /// Connect over TLS, e.g. to `https://largetable:50051`, trusting servers signed by `ca_pem`
+    pub async fn connect_tls(endpoint: impl Into<String>, ca_pem: impl AsRef<[u8]>) -> Result<Self> {
+        let endpoint = endpoint.into();
+        let tls = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(ca_pem));
+        let channel = Self::endpoint(&endpoint)?
+            .tls_config(tls)
+            .map_err(|e| LargetableError::Config(format!("Invalid TLS configuration for {}: {}", endpoint, e)))?;
+        Self::connect_endpoint(channel, &endpoint).await
+    }
+
+    fn endpoint(endpoint: &str) -> Result<Endpoint> {
+        Channel::from_shared(endpoint.to_string())
+            .map_err(|e| LargetableError::Config(format!("Invalid gRPC endpoint {}: {}", endpoint, e)))
+    }
+
+    async fn connect_endpoint(channel: Endpoint, endpoint: &str) -> Result<Self> {
+        let channel = channel
+            .connect()
+            .await
+            .map_err(|e| LargetableError::Network(format!("Failed to connect to {}: {}", endpoint, e)))?;
+        info!("Connected Largetable gRPC client to {}", endpoint);
+        Ok(Self::with_metadata(channel, CallMetadata::default()))
+    }
+
+    fn with_metadata(channel: Channel, metadata: CallMetadata) -> Self {
+        // Servers only compress responses when wire compression is enabled on their side
+        let inner = LargetableClient::with_interceptor(channel.clone(), metadata.clone())
+            .accept_compressed(CompressionEncoding::Zstd);
+        Self { inner, channel, metadata }
+    }
+
+    /// A client on the same connection and session whose calls fail with
+    /// `DeadlineExceeded` once `timeout` passes; the server stops the operation too
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let metadata = CallMetadata { timeout: Some(timeout), ..self.metadata.clone() };
+        Self::with_metadata(self.channel.clone(), metadata)
+    }
+
+    /// Log in with SCRAM-SHA-256; later calls from this client and its clones run as the user
+    pub async fn authenticate(&self, username: &str, password: &str) -> Result<()> {
+        let mut scram = ScramClient::new(username, password);
+        let start = self
+            .inner
+            .clone()
+            .scram_start(proto::ScramStartRequest { payload: scram.client_first() })
+            .await
+            .map_err(from_status)?
+            .into_inner();
+        let finish = self
+            .inner
+            .clone()
+            .scram_finish(proto::ScramFinishRequest {
+                conversation_id: start.conversation_id,
+                payload: scram.client_final(&start.payload)?,
+            })
+            .await
+            .map_err(from_status)?
+            .into_inner();
+        scram.verify_server_final(&finish.payload)?;
+
+        let token = format!("Bearer {}", finish.token)
+            .parse()
+            .map_err(|_| LargetableError::Auth("Server returned an invalid session token".to_string()))?;
+        *self.metadata.token.write() = Some(token);
+        info!("Authenticated as '{}'", username);
+        Ok(())
+    }
+
+    /// Server version, failing when the server is unhealthy or unreachable
+    pub async fn health(&self) -> Result<String> {
+        let response = self.inner.clone().health(proto::HealthRequest {}).await.map_err(from_status)?;
+        Ok(response.into_inner().version)
+    }
+
+    /// Current cluster time of the server, for reading at it later with `find_by_id_at` or `QueryBuilder::read_at`
+    pub async fn cluster_time(&self) -> Result<i64> {
+        let response = self.inner.clone().health(proto::HealthRequest {}).await.map_err(from_status)?;
+        Ok(response.into_inner().cluster_time)
+    }
+
+    /// Get database statistics
+    pub async fn stats(&self) -> Result<crate::engine::DatabaseStats> {
+        let response = self.inner.clone().stats(proto::StatsRequest {}).await.map_err(from_status)?;
+        Ok(serde_json::from_str(&response.into_inner().stats_json)?)
+    }
+
+    /// List all databases
+    pub async fn list_databases(&self) -> Result<Vec<DatabaseName>> {
+        let response = self.inner.clone().list_databases(proto::ListDatabasesRequest {}).await.map_err(from_status)?;
+        Ok(response.into_inner().databases)
+    }
+
+    /// Create a database if it does not exist
+    pub async fn create_database(&self, name: DatabaseName) -> Result<()> {
+        self.inner
+            .clone()
+            .create_database(proto::CreateDatabaseRequest { database: name })
+            .await
+            .map_err(from_status)?;
+        Ok(())
+    }
+
+    /// Drop a database
+    pub async fn drop_database(&self, name: &DatabaseName) -> Result<bool> {
+        let response = self
+            .inner
+            .clone()
+            .drop_database(proto::DropDatabaseRequest { database: name.clone() })
+            .await
+            .map_err(from_status)?;
+        Ok(response.into_inner().dropped)
+    }
+
+    /// List the collections of a database
+    pub async fn list_collections(&self, database: DatabaseName) -> Result<Vec<CollectionName>> {
+        let response = self
+            .inner
+            .clone()
+            .list_collections(proto::ListCollectionsRequest { database })
+            .await
+            .map_err(from_status)?;
+        Ok(response.into_inner().collections)
+    }
+
+    /// Create a collection if it does not exist
+    pub async fn create_collection(&self, database: DatabaseName, collection: CollectionName) -> Result<()> {
+        self.inner
+            .clone()
+            .create_collection(proto::CreateCollectionRequest { path: path(database, collection) })
+            .await
+            .map_err(from_status)?;
+        Ok(())
+    }
+
+    /// Insert a document
+    pub async fn insert(&self, database: DatabaseName, collection: CollectionName, document: Document) -> Result<DocumentId> {
+        let request = proto::InsertDocumentRequest {
+            path: path(database, collection),
+            document_json: DocumentUtils::to_json(&document)?.to_string(),
+        };
+        let response = self.inner.clone().insert_document(request).await.map_err(from_status)?;
+        parse_id(&response.into_inner().id)
+    }
+
+    /// Find a document by ID
+    pub async fn find_by_id(&self, database: DatabaseName, collection: CollectionName, id: DocumentId) -> Result<Option<Document>> {
+        let request = proto::FindDocumentRequest { path: path(database, collection), id: id.to_string(), read_at: None };
+        let response = self.inner.clone().find_document(request).await.map_err(from_status)?;
+        response.into_inner().document_json.as_deref().map(document_from_json).transpose()
+    }
+
+    /// Find a document by ID as it was at a cluster timestamp
+    pub async fn find_by_id_at(&self, database: DatabaseName, collection: CollectionName, id: DocumentId, timestamp: i64) -> Result<Option<Document>> {
+        let request = proto::FindDocumentRequest { path: path(database, collection), id: id.to_string(), read_at: Some(timestamp) };
+        let response = self.inner.clone().find_document(request).await.map_err(from_status)?;
+        response.into_inner().document_json.as_deref().map(document_from_json).transpose()
+    }
+
+    /// Update a document by ID, returning the previous version
+    pub async fn update_by_id(&self, database: DatabaseName, collection: CollectionName, id: DocumentId, document: Document) -> Result<Option<Document>> {
+        let request = proto::UpdateDocumentRequest {
+            path: path(database, collection),
+            id: id.to_string(),
+            document_json: DocumentUtils::to_json(&document)?.to_string(),
+        };
+        let response = self.inner.clone().update_document(request).await.map_err(from_status)?;
+        response.into_inner().previous_json.as_deref().map(document_from_json).transpose()
+    }
+
+    /// Delete a document by ID
+    pub async fn delete_by_id(&self, database: DatabaseName, collection: CollectionName, id: DocumentId) -> Result<bool> {
+        let request = proto::DeleteDocumentRequest { path: path(database, collection), id: id.to_string() };
+        let response = self.inner.clone().delete_document(request).await.map_err(from_status)?;
+        Ok(response.into_inner().deleted)
+    }
+
+    /// Find multiple documents; index hints are chosen by the server
+    pub async fn find_many(&self, database: DatabaseName, collection: CollectionName, query: Query) -> Result<QueryResult> {
+        let request = proto::QueryRequest {
+            path: path(database, collection),
+            filter_json: query.filter.map(|filter| filter.to_string()),
+            sort: query
+                .sort
+                .into_iter()
+                .map(|sort| proto::SortField {
+                    field: sort.field,
+                    descending: matches!(sort.direction, SortDirection::Descending),
+                })
+                .collect(),
+            limit: query.limit.map(|limit| limit as u64),
+            skip: query.skip.map(|skip| skip as u64),
+            projection: query.projection.unwrap_or_default(),
+            bypass_cache: query.bypass_cache,
+            read_at: query.read_at,
+        };
+        let mut stream = self.inner.clone().query(request).await.map_err(from_status)?.into_inner();
+
+        let mut result = QueryResult { documents: Vec::new(), total_count: 0, has_more: false };
+        while let Some(message) = stream.next().await {
+            let message = message.map_err(from_status)?;
+            result.total_count = message.total_count as usize;
+            result.has_more = message.has_more;
+            result.documents.push((parse_id(&message.id)?, document_from_json(&message.document_json)?));
+        }
+        Ok(result)
+    }
+
+    /// Indexed fields of a collection and their types
+    pub async fn list_indexes(&self, database: DatabaseName, collection: CollectionName) -> Result<HashMap<String, IndexType>> {
+        let request = proto::ListIndexesRequest { path: path(database, collection) };
+        let response = self.inner.clone().list_indexes(request).await.map_err(from_status)?;
+        Ok(serde_json::from_str(&response.into_inner().indexes_json)?)
+    }
+
+    /// Start a background index build
+    pub async fn create_index(&self, database: DatabaseName, collection: CollectionName, field: String, index_type: IndexType) -> Result<IndexBuildStatus> {
+        let request = proto::CreateIndexRequest {
+            path: path(database, collection),
+            field,
+            index_type_json: serde_json::to_string(&index_type)?,
+        };
+        let response = self.inner.clone().create_index(request).await.map_err(from_status)?;
+        Ok(serde_json::from_str(&response.into_inner().build_status_json)?)
+    }
+}
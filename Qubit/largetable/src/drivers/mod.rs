@@ -6,6 +6,8 @@
 
 //! Client drivers and APIs
 
+pub mod grpc;
 pub mod native;
 
+pub use grpc::GrpcClient;
 pub use native::Client;
\ No newline at end of file
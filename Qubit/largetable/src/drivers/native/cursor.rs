@@ -0,0 +1,84 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Query results as an async stream of typed documents
+
+use crate::database::Collection;
+use crate::document::from_document;
+use crate::query::aggregation::{execute_stream, DocumentStream};
+use crate::query::{AccessPath, AggregationStage, Query};
+use crate::Result;
+use futures::stream::{self, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Documents matching a query, deserialized into `T` as they are read.
+///
+/// Queries answered by a collection scan read the collection in batches and
+/// stop reading once the consumer stops polling or the limit is reached, so
+/// memory stays bounded unless the query sorts without a limit. Queries the
+/// planner answers from an index load the indexed candidates up front.
+pub struct Cursor<T> {
+    documents: DocumentStream<'static>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Cursor<T> {
+    pub(crate) async fn open(collection: Arc<Collection>, query: Query, batch_size: usize) -> Result<Self> {
+        let plan = collection.explain(&query).await?;
+        let documents = match plan.access {
+            AccessPath::CollectionScan => {
+                let stages = stages(&query);
+                execute_stream(&stages, None, collection.stream_documents(batch_size))
+            }
+            AccessPath::IndexScan { .. } => {
+                let result = collection.find(&query).await?;
+                stream::iter(result.documents.into_iter().map(Ok)).boxed()
+            }
+        };
+        Ok(Self { documents, _marker: PhantomData })
+    }
+
+    /// The underlying documents with their IDs, without deserializing them
+    pub fn into_documents(self) -> DocumentStream<'static> {
+        self.documents
+    }
+}
+
+impl<T: DeserializeOwned> Stream for Cursor<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.documents
+            .poll_next_unpin(cx)
+            .map(|next| next.map(|document| document.and_then(|(_, doc)| from_document(&doc))))
+    }
+}
+
+/// The query's filter, sort, skip, limit and projection as streaming stages,
+/// applied in the same order as `Query::execute`
+fn stages(query: &Query) -> Vec<AggregationStage> {
+    let mut stages = Vec::new();
+    if let Some(filter) = &query.filter {
+        stages.push(AggregationStage::Match(filter.clone()));
+    }
+    if !query.sort.is_empty() {
+        stages.push(AggregationStage::Sort(query.sort.clone()));
+    }
+    if let Some(skip) = query.skip.filter(|skip| *skip > 0) {
+        stages.push(AggregationStage::Skip(skip));
+    }
+    if let Some(limit) = query.limit {
+        stages.push(AggregationStage::Limit(limit));
+    }
+    if let Some(projection) = &query.projection {
+        stages.push(AggregationStage::Project(projection.clone()));
+    }
+    stages
+}
@@ -6,9 +6,21 @@
 
 //! Native Rust client for Largetable
 
-use crate::{Result, DatabaseName, CollectionName, DocumentId, Document, StorageEngine};
+mod cursor;
+mod sink;
+
+pub use cursor::Cursor;
+pub use sink::{CollectionSink, SinkOptions};
+
+use crate::{Result, LargetableError, DatabaseName, CollectionName, DocumentId, Document, StorageEngine};
+use crate::auth::Action;
+use crate::collection::partitioning::{PartitionOptions, PartitionedCollectionStats};
+use crate::database::admin::{CollectionStats, SampleOptions};
 use crate::engine::DatabaseEngine;
+use crate::query::aggregation::DEFAULT_SCAN_BATCH_SIZE;
 use crate::query::{Query, QueryBuilder, AggregationPipeline, QueryResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::sync::Arc;
 use tracing::{debug, info};
 
@@ -32,6 +44,11 @@ impl Client {
         Ok(Self { engine })
     }
 
+    /// Create a client on an engine that is already set up, e.g. with caches or access control
+    pub fn with_engine(engine: Arc<DatabaseEngine>) -> Self {
+        Self { engine }
+    }
+
     /// Get a database
     pub async fn database(&self, name: DatabaseName) -> Result<Arc<crate::database::Database>> {
         self.engine.database(name).await
@@ -62,6 +79,29 @@ impl Client {
         self.engine.find_document_by_id(database, collection, id).await
     }
 
+    /// Find a document by ID as it was at a cluster timestamp
+    pub async fn find_by_id_at(&self, database: DatabaseName, collection: CollectionName, id: DocumentId, timestamp: i64) -> Result<Option<Document>> {
+        self.engine.find_document_by_id_at(database, collection, id, timestamp).await
+    }
+
+    /// Current cluster time, for reading at it later with `find_by_id_at` or `QueryBuilder::read_at`
+    pub fn cluster_time(&self) -> i64 {
+        self.engine.cluster_time()
+    }
+
+    /// Find a document by ID and deserialize it into `T`
+    pub async fn find_by_id_as<T: DeserializeOwned>(&self, database: DatabaseName, collection: CollectionName, id: DocumentId) -> Result<Option<T>> {
+        match self.engine.find_stored_document_by_id(database, collection, id).await? {
+            Some(stored) => stored.deserialize().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Serialize `value` into a document and insert it
+    pub async fn insert_as<T: Serialize + ?Sized>(&self, database: DatabaseName, collection: CollectionName, value: &T) -> Result<DocumentId> {
+        self.insert(database, collection, crate::document::to_document(value)?).await
+    }
+
     /// Update a document by ID
     pub async fn update_by_id(&self, database: DatabaseName, collection: CollectionName, id: DocumentId, document: Document) -> Result<Option<Document>> {
         self.engine.update_document_by_id(database, collection, id, document).await
@@ -77,6 +117,24 @@ impl Client {
         self.engine.query(database, collection, query).await
     }
 
+    /// Stream the documents matching `query`, deserialized into `T`
+    pub async fn find_stream<T: DeserializeOwned>(&self, database: DatabaseName, collection: CollectionName, query: Query) -> Result<Cursor<T>> {
+        self.engine.authorize(Action::Find, Some(&database))?;
+        if query.read_at.is_some() {
+            return Err(LargetableError::Query("Cursors read the latest state; use find_many to read at a timestamp".to_string()));
+        }
+        let collection = self.engine.collection(database, collection).await?;
+        Cursor::open(collection, query, DEFAULT_SCAN_BATCH_SIZE).await
+    }
+
+    /// A sink inserting the values sent to it in batches, with backpressure
+    /// from unacknowledged batches
+    pub async fn sink<T: Serialize>(&self, database: DatabaseName, collection: CollectionName, options: SinkOptions) -> Result<CollectionSink<T>> {
+        self.engine.authorize(Action::Insert, Some(&database))?;
+        let collection = self.engine.collection(database, collection).await?;
+        Ok(CollectionSink::new(collection, options))
+    }
+
     /// Execute aggregation pipeline
     pub async fn aggregate(&self, database: DatabaseName, collection: CollectionName, pipeline: AggregationPipeline) -> Result<Vec<serde_json::Value>> {
         self.engine.aggregate(database, collection, pipeline).await
@@ -87,6 +145,21 @@ impl Client {
         self.engine.get_stats().await
     }
 
+    /// Count, size, indexes and inferred schema of a collection
+    pub async fn collection_stats(&self, database: DatabaseName, collection: CollectionName, options: SampleOptions) -> Result<CollectionStats> {
+        self.engine.collection_stats(database, collection, options).await
+    }
+
+    /// Create a collection partitioned into time windows
+    pub async fn create_partitioned_collection(&self, database: DatabaseName, collection: CollectionName, options: PartitionOptions) -> Result<()> {
+        self.engine.create_partitioned_collection(database, collection, options).await
+    }
+
+    /// Partitions of a partitioned collection
+    pub async fn partition_stats(&self, database: DatabaseName, collection: CollectionName) -> Result<PartitionedCollectionStats> {
+        self.engine.partition_stats(database, collection).await
+    }
+
     /// Create a query builder
     pub fn query() -> QueryBuilder {
         QueryBuilder::new()
@@ -131,6 +204,21 @@ impl CollectionRef {
         self.client.find_by_id(self.database.clone(), self.collection.clone(), id).await
     }
 
+    /// Find a document by ID as it was at a cluster timestamp
+    pub async fn find_by_id_at(&self, id: DocumentId, timestamp: i64) -> Result<Option<Document>> {
+        self.client.find_by_id_at(self.database.clone(), self.collection.clone(), id, timestamp).await
+    }
+
+    /// Find a document by ID and deserialize it into `T`
+    pub async fn find_by_id_as<T: DeserializeOwned>(&self, id: DocumentId) -> Result<Option<T>> {
+        self.client.find_by_id_as(self.database.clone(), self.collection.clone(), id).await
+    }
+
+    /// Serialize `value` into a document and insert it
+    pub async fn insert_as<T: Serialize + ?Sized>(&self, value: &T) -> Result<DocumentId> {
+        self.client.insert_as(self.database.clone(), self.collection.clone(), value).await
+    }
+
     /// Update a document by ID
     pub async fn update_by_id(&self, id: DocumentId, document: Document) -> Result<Option<Document>> {
         self.client.update_by_id(self.database.clone(), self.collection.clone(), id, document).await
@@ -146,11 +234,31 @@ impl CollectionRef {
         self.client.find_many(self.database.clone(), self.collection.clone(), query).await
     }
 
+    /// Stream the documents matching `query`, deserialized into `T`
+    pub async fn find_stream<T: DeserializeOwned>(&self, query: Query) -> Result<Cursor<T>> {
+        self.client.find_stream(self.database.clone(), self.collection.clone(), query).await
+    }
+
+    /// A sink inserting the values sent to it in batches
+    pub async fn sink<T: Serialize>(&self, options: SinkOptions) -> Result<CollectionSink<T>> {
+        self.client.sink(self.database.clone(), self.collection.clone(), options).await
+    }
+
     /// Execute aggregation pipeline
     pub async fn aggregate(&self, pipeline: AggregationPipeline) -> Result<Vec<serde_json::Value>> {
         self.client.aggregate(self.database.clone(), self.collection.clone(), pipeline).await
     }
 
+    /// Count, size, indexes and inferred schema of the collection
+    pub async fn stats(&self, options: SampleOptions) -> Result<CollectionStats> {
+        self.client.collection_stats(self.database.clone(), self.collection.clone(), options).await
+    }
+
+    /// Partitions of the collection, if it is partitioned
+    pub async fn partitions(&self) -> Result<PartitionedCollectionStats> {
+        self.client.partition_stats(self.database.clone(), self.collection.clone()).await
+    }
+
     /// Get collection name
     pub fn name(&self) -> &CollectionName {
         &self.collection
@@ -169,4 +277,103 @@ impl Client {
             engine: self.engine.clone(),
         }), database, collection)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Collection, Database};
+    use crate::replication::Oplog;
+    use crate::storage::StorageEngine as StorageEngineTrait;
+    use async_trait::async_trait;
+    use futures::{stream, SinkExt, TryStreamExt};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+    use tokio::sync::RwLock;
+
+    #[derive(Default)]
+    struct MemoryEngine {
+        documents: RwLock<BTreeMap<DocumentId, Document>>,
+    }
+
+    #[async_trait]
+    impl StorageEngineTrait for MemoryEngine {
+        async fn get(&self, id: &DocumentId) -> Result<Option<Document>> {
+            Ok(self.documents.read().await.get(id).cloned())
+        }
+
+        async fn put(&self, id: DocumentId, doc: Document) -> Result<()> {
+            self.documents.write().await.insert(id, doc);
+            Ok(())
+        }
+
+        async fn delete(&self, id: &DocumentId) -> Result<bool> {
+            Ok(self.documents.write().await.remove(id).is_some())
+        }
+
+        async fn scan(&self, start: Option<DocumentId>, limit: usize) -> Result<Vec<(DocumentId, Document)>> {
+            let documents = self.documents.read().await;
+            Ok(documents
+                .range(start.unwrap_or(uuid::Uuid::nil())..)
+                .take(limit)
+                .map(|(id, doc)| (*id, doc.clone()))
+                .collect())
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Event {
+        kind: String,
+        seq: i64,
+    }
+
+    async fn collection() -> Arc<Collection> {
+        let database = Database::with_storage_engine("app".to_string(), Arc::new(MemoryEngine::default()), Arc::new(Oplog::new()));
+        database.collection("events".to_string()).await.unwrap()
+    }
+
+    fn events(count: i64) -> Vec<Event> {
+        (0..count)
+            .map(|seq| Event { kind: if seq % 2 == 0 { "click" } else { "view" }.to_string(), seq })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_sink_inserts_in_batches_with_bounded_in_flight() {
+        let collection = collection().await;
+        let mut sink = CollectionSink::new(collection.clone(), SinkOptions { batch_size: 10, max_in_flight: 2 });
+
+        sink.send_all(&mut stream::iter(events(95).into_iter().map(Ok))).await.unwrap();
+        assert!(sink.in_flight() <= 2);
+        sink.close().await.unwrap();
+
+        assert_eq!(sink.inserted(), 95);
+        assert_eq!(sink.in_flight(), 0);
+        assert_eq!(collection.count().await.unwrap(), 95);
+    }
+
+    #[tokio::test]
+    async fn test_sink_rejects_values_that_are_not_documents() {
+        let mut sink = CollectionSink::new(collection().await, SinkOptions::default());
+        assert!(sink.send(42).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_streams_filtered_sorted_typed_documents() {
+        let collection = collection().await;
+        collection.insert_many(events(50).iter().map(|event| crate::document::to_document(event).unwrap()).collect()).await.unwrap();
+
+        let query = Client::query()
+            .filter(serde_json::json!({ "kind": "view" }))
+            .sort("seq".to_string(), crate::query::SortDirection::Descending)
+            .skip(1)
+            .limit(3)
+            .build();
+        let cursor: Cursor<Event> = Cursor::open(collection.clone(), query, 7).await.unwrap();
+        let found: Vec<Event> = cursor.try_collect().await.unwrap();
+        assert_eq!(found.iter().map(|event| event.seq).collect::<Vec<_>>(), vec![47, 45, 43]);
+
+        let all: Vec<Event> = Cursor::open(collection, Query::new(), 7).await.unwrap().try_collect().await.unwrap();
+        assert_eq!(all.len(), 50);
+    }
+}
@@ -0,0 +1,140 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Batched bulk ingestion through `futures::Sink`
+
+use crate::database::Collection;
+use crate::document::to_document;
+use crate::{Document, DocumentId, LargetableError, Result};
+use futures::sink::Sink;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::task::JoinHandle;
+
+/// Batching and backpressure settings for a [`CollectionSink`]
+#[derive(Debug, Clone, Copy)]
+pub struct SinkOptions {
+    /// Documents written per batch
+    pub batch_size: usize,
+    /// Batches written concurrently; sending waits until one is acknowledged
+    pub max_in_flight: usize,
+}
+
+impl Default for SinkOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 1_000,
+            max_in_flight: 4,
+        }
+    }
+}
+
+/// Inserts values sent to it into a collection in batches.
+///
+/// Full batches are written in the background. Once `max_in_flight` batches
+/// are waiting to be acknowledged, `poll_ready` returns `Pending`, so a
+/// producer driving the sink with `send_all` slows to the rate the engine
+/// accepts writes. `flush` and `close` write the partial batch and wait for
+/// every acknowledgement. A failed batch is reported by the next `send`,
+/// `flush` or `close`; its documents before the failing one stay inserted.
+pub struct CollectionSink<T> {
+    collection: Arc<Collection>,
+    options: SinkOptions,
+    buffer: Vec<Document>,
+    in_flight: FuturesUnordered<JoinHandle<Result<Vec<DocumentId>>>>,
+    inserted: u64,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> CollectionSink<T> {
+    pub(crate) fn new(collection: Arc<Collection>, options: SinkOptions) -> Self {
+        let options = SinkOptions {
+            batch_size: options.batch_size.max(1),
+            max_in_flight: options.max_in_flight.max(1),
+        };
+        Self {
+            collection,
+            options,
+            buffer: Vec::with_capacity(options.batch_size),
+            in_flight: FuturesUnordered::new(),
+            inserted: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Documents the engine has acknowledged so far
+    pub fn inserted(&self) -> u64 {
+        self.inserted
+    }
+
+    /// Batches written but not yet acknowledged
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    fn start_batch(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let batch = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.options.batch_size));
+        let collection = self.collection.clone();
+        self.in_flight.push(tokio::spawn(async move { collection.insert_many(batch).await }));
+    }
+
+    /// Collect acknowledgements until at most `pending` batches remain
+    fn poll_acks(&mut self, cx: &mut Context<'_>, pending: usize) -> Poll<Result<()>> {
+        loop {
+            match self.in_flight.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(Ok(ids)))) => self.inserted += ids.len() as u64,
+                Poll::Ready(Some(Ok(Err(e)))) => return Poll::Ready(Err(e)),
+                Poll::Ready(Some(Err(e))) => {
+                    return Poll::Ready(Err(LargetableError::Storage(format!("Insert batch failed: {}", e))))
+                }
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending if self.in_flight.len() <= pending => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T: Serialize> Sink<T> for CollectionSink<T> {
+    type Error = LargetableError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        // Only the item completing a batch has to wait for room
+        let pending = if this.buffer.len() + 1 >= this.options.batch_size {
+            this.options.max_in_flight - 1
+        } else {
+            usize::MAX
+        };
+        this.poll_acks(cx, pending)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<()> {
+        let this = self.get_mut();
+        this.buffer.push(to_document(&item)?);
+        if this.buffer.len() >= this.options.batch_size {
+            this.start_batch();
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        this.start_batch();
+        this.poll_acks(cx, 0)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_flush(cx)
+    }
+}
@@ -0,0 +1,487 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Consistent online backups
+//!
+//! A backup session pins an oplog position and serves every collection of a
+//! database as it was at that position, in chunks a remote agent can fetch
+//! at its own pace and resume after a failure. Writes keep going while a
+//! session is open: documents changed since the snapshot are served from the
+//! versions the oplog retains. Incremental backups read the oplog after the
+//! position of the previous backup.
+
+use crate::database::Database;
+use crate::replication::OplogEntry;
+use crate::{CollectionName, DatabaseName, Document, DocumentId, IndexType, LargetableError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Backup session limits
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    /// Sessions idle longer than this are closed and their snapshot released
+    pub session_timeout: Duration,
+    pub max_sessions: usize,
+    pub default_chunk_size: usize,
+    pub max_chunk_size: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            session_timeout: Duration::from_secs(30 * 60),
+            max_sessions: 8,
+            default_chunk_size: 1_000,
+            max_chunk_size: 10_000,
+        }
+    }
+}
+
+/// What a backup agent asks for when opening a session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupOptions {
+    /// Collections to back up; all collections when unset
+    #[serde(default)]
+    pub collections: Option<Vec<CollectionName>>,
+    /// Documents read per chunk
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
+    /// Server-side pacing of chunk data for this session
+    #[serde(default)]
+    pub max_bytes_per_second: Option<u64>,
+}
+
+/// Index definitions of a collection at the time the snapshot was taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionManifest {
+    pub name: CollectionName,
+    pub indexes: HashMap<String, IndexType>,
+}
+
+/// An open backup session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSession {
+    pub session_id: String,
+    pub database: DatabaseName,
+    /// Oplog position the snapshot reflects; start the next incremental backup here
+    pub snapshot_position: u64,
+    pub collections: Vec<CollectionManifest>,
+    pub chunk_size: usize,
+    pub max_bytes_per_second: Option<u64>,
+    /// Microseconds since the Unix epoch
+    pub opened_at: i64,
+}
+
+/// Documents of one collection, in ID order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupChunk {
+    pub collection: CollectionName,
+    pub documents: Vec<Document>,
+    /// Pass as `after` to fetch the next chunk; `None` once the collection is done
+    pub resume_after: Option<DocumentId>,
+    pub done: bool,
+}
+
+/// Oplog entries for an incremental backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OplogChunk {
+    pub entries: Vec<OplogEntry>,
+    /// Pass as `after` to fetch the next chunk
+    pub resume_after: u64,
+    pub latest_position: u64,
+    pub done: bool,
+}
+
+struct SessionState {
+    info: BackupSession,
+    database: Arc<Database>,
+    last_used: Instant,
+    /// Earliest time the next chunk may be served under the session's rate limit
+    next_chunk_at: Instant,
+}
+
+/// Open backup sessions of an engine
+pub struct BackupManager {
+    config: BackupConfig,
+    sessions: Mutex<HashMap<String, Arc<tokio::sync::Mutex<SessionState>>>>,
+}
+
+impl BackupManager {
+    pub fn new(config: BackupConfig) -> Self {
+        Self {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a snapshot of `database` and open a session serving it
+    pub async fn open_session(&self, database: Arc<Database>, options: BackupOptions) -> Result<BackupSession> {
+        self.close_expired();
+        if self.sessions.lock().unwrap().len() >= self.config.max_sessions {
+            return Err(LargetableError::ResourceExhausted(format!(
+                "At most {} backup sessions can be open at once",
+                self.config.max_sessions
+            )));
+        }
+
+        let existing = database.list_collections().await?;
+        let names = match options.collections {
+            Some(requested) => {
+                if let Some(missing) = requested.iter().find(|name| !existing.contains(name)) {
+                    return Err(LargetableError::Query(format!(
+                        "Collection '{}' does not exist in database '{}'",
+                        missing,
+                        database.name()
+                    )));
+                }
+                requested
+            }
+            None => existing,
+        };
+
+        let mut collections = Vec::with_capacity(names.len());
+        for name in names {
+            let collection = database.collection(name.clone()).await?;
+            collections.push(CollectionManifest {
+                name,
+                indexes: collection.list_indexes().await?,
+            });
+        }
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // No write may be half applied when the position is taken
+        let oplog = database.oplog().clone();
+        let snapshot_position = {
+            let _paused = oplog.pause_writes().await;
+            let position = oplog.latest_position();
+            oplog.pin(position);
+            position
+        };
+
+        let chunk_size = options
+            .chunk_size
+            .unwrap_or(self.config.default_chunk_size)
+            .clamp(1, self.config.max_chunk_size);
+        let info = BackupSession {
+            session_id: uuid::Uuid::new_v4().to_string(),
+            database: database.name().clone(),
+            snapshot_position,
+            collections,
+            chunk_size,
+            max_bytes_per_second: options.max_bytes_per_second.filter(|rate| *rate > 0),
+            opened_at: chrono::Utc::now().timestamp_micros(),
+        };
+
+        let now = Instant::now();
+        self.sessions.lock().unwrap().insert(
+            info.session_id.clone(),
+            Arc::new(tokio::sync::Mutex::new(SessionState {
+                info: info.clone(),
+                database,
+                last_used: now,
+                next_chunk_at: now,
+            })),
+        );
+
+        info!(
+            "Opened backup session {} of database '{}' at oplog position {}",
+            info.session_id, info.database, snapshot_position
+        );
+        Ok(info)
+    }
+
+    pub async fn session(&self, session_id: &str) -> Option<BackupSession> {
+        self.close_expired();
+        let session = self.sessions.lock().unwrap().get(session_id).cloned()?;
+        let info = session.lock().await.info.clone();
+        Some(info)
+    }
+
+    /// Documents of `collection` after `after` as of the session's snapshot.
+    ///
+    /// Fetching the same `after` again returns the same chunk, so an agent
+    /// resumes from the last chunk it stored.
+    pub async fn next_chunk(
+        &self,
+        session_id: &str,
+        collection: &str,
+        after: Option<DocumentId>,
+    ) -> Result<BackupChunk> {
+        self.close_expired();
+        let session = self.sessions.lock().unwrap().get(session_id).cloned().ok_or_else(|| {
+            LargetableError::Query(format!("Backup session '{}' is not open", session_id))
+        })?;
+        let mut session = session.lock().await;
+
+        if !session.info.collections.iter().any(|manifest| manifest.name == collection) {
+            return Err(LargetableError::Query(format!(
+                "Collection '{}' is not part of backup session '{}'",
+                collection, session_id
+            )));
+        }
+
+        tokio::time::sleep_until(session.next_chunk_at.into()).await;
+
+        let chunk = snapshot_chunk(&session, collection, after).await?;
+        let now = Instant::now();
+        session.last_used = now;
+        session.next_chunk_at = match session.info.max_bytes_per_second {
+            Some(rate) => {
+                let bytes = serde_json::to_vec(&chunk.documents)?.len() as f64;
+                now + Duration::from_secs_f64(bytes / rate as f64)
+            }
+            None => now,
+        };
+
+        debug!(
+            "Backup session {} served {} documents of '{}'",
+            session_id,
+            chunk.documents.len(),
+            collection
+        );
+        Ok(chunk)
+    }
+
+    /// Close a session and release its snapshot; false if it was not open
+    pub async fn close_session(&self, session_id: &str) -> bool {
+        let Some(session) = self.sessions.lock().unwrap().remove(session_id) else {
+            return false;
+        };
+        // Waits for a chunk that is being served
+        let state = session.lock().await;
+        state.database.oplog().unpin(state.info.snapshot_position);
+        info!("Closed backup session {}", session_id);
+        true
+    }
+
+    fn close_expired(&self) {
+        let timeout = self.config.session_timeout;
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|session_id, session| {
+            // A session that is serving a chunk right now is in use
+            let Ok(state) = session.try_lock() else {
+                return true;
+            };
+            if state.last_used.elapsed() < timeout {
+                return true;
+            }
+            state.database.oplog().unpin(state.info.snapshot_position);
+            info!("Backup session {} timed out", session_id);
+            false
+        });
+    }
+}
+
+impl Default for BackupManager {
+    fn default() -> Self {
+        Self::new(BackupConfig::default())
+    }
+}
+
+/// Read one chunk and roll documents changed since the snapshot back to it
+async fn snapshot_chunk(session: &SessionState, collection_name: &str, after: Option<DocumentId>) -> Result<BackupChunk> {
+    let (documents, upper) = chunk_at(
+        &session.database,
+        collection_name,
+        session.info.snapshot_position,
+        after,
+        session.info.chunk_size,
+    )
+    .await?;
+
+    Ok(BackupChunk {
+        collection: collection_name.to_string(),
+        documents,
+        resume_after: upper,
+        done: upper.is_none(),
+    })
+}
+
+/// Up to `chunk_size` documents with IDs after `after`, as they were at `position`.
+///
+/// Also returns the last ID the chunk covers, or `None` when it reached the
+/// end of the collection. `position` must stay pinned between chunks.
+pub(crate) async fn chunk_at(
+    database: &Database,
+    collection_name: &str,
+    position: u64,
+    after: Option<DocumentId>,
+    chunk_size: usize,
+) -> Result<(Vec<Document>, Option<DocumentId>)> {
+    let collection = database.collection(collection_name.to_string()).await?;
+    let oplog = database.oplog();
+
+    // Writes store the document before logging it, so keep them out while
+    // reading both or a fresh write could be mistaken for snapshot data
+    let (scanned, exhausted, images) = {
+        let _paused = oplog.pause_writes().await;
+        // `find_many` includes its start key
+        let limit = if after.is_some() { chunk_size + 1 } else { chunk_size };
+        let scanned = collection.find_many(after, limit).await?;
+        let exhausted = scanned.len() < limit;
+        let images = oplog.images_at(database.name(), collection_name, position)?;
+        (scanned, exhausted, images)
+    };
+    let scanned: Vec<_> = scanned.into_iter().filter(|(id, _)| Some(*id) != after).collect();
+
+    // The chunk covers IDs after `after` up to the last scanned one, or to the end
+    let upper = if exhausted { None } else { scanned.last().map(|(id, _)| *id) };
+    let in_range = |id: &DocumentId| after.is_none_or(|after| *id > after) && upper.is_none_or(|upper| *id <= upper);
+
+    let mut documents = BTreeMap::new();
+    for (id, document) in scanned {
+        match images.get(&id) {
+            Some(Some(before)) => {
+                documents.insert(id, before.clone());
+            }
+            // Inserted after the snapshot
+            Some(None) => {}
+            None => {
+                documents.insert(id, document);
+            }
+        }
+    }
+    // Deleted since the snapshot, so the scan no longer sees them
+    for (id, image) in &images {
+        if let Some(before) = image {
+            if in_range(id) && !documents.contains_key(id) {
+                documents.insert(*id, before.clone());
+            }
+        }
+    }
+
+    Ok((documents.into_values().collect(), upper))
+}
+
+/// Up to `limit` oplog entries of `database` after `position`
+pub fn oplog_chunk(database: &Database, position: u64, limit: usize) -> Result<OplogChunk> {
+    let oplog = database.oplog();
+    let latest_position = oplog.latest_position();
+    let entries = oplog.read_after(database.name(), position, limit)?;
+    let resume_after = if entries.len() < limit {
+        latest_position
+    } else {
+        entries.last().map_or(position, |entry| entry.position)
+    };
+
+    Ok(OplogChunk {
+        done: resume_after >= latest_position,
+        entries,
+        resume_after,
+        latest_position,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replication::Oplog;
+    use crate::storage::StorageEngine;
+    use crate::Value;
+    use async_trait::async_trait;
+    use tokio::sync::RwLock;
+
+    #[derive(Default)]
+    struct MemoryEngine {
+        documents: RwLock<BTreeMap<DocumentId, Document>>,
+    }
+
+    #[async_trait]
+    impl StorageEngine for MemoryEngine {
+        async fn get(&self, id: &DocumentId) -> Result<Option<Document>> {
+            Ok(self.documents.read().await.get(id).cloned())
+        }
+
+        async fn put(&self, id: DocumentId, doc: Document) -> Result<()> {
+            self.documents.write().await.insert(id, doc);
+            Ok(())
+        }
+
+        async fn delete(&self, id: &DocumentId) -> Result<bool> {
+            Ok(self.documents.write().await.remove(id).is_some())
+        }
+
+        async fn scan(&self, start: Option<DocumentId>, limit: usize) -> Result<Vec<(DocumentId, Document)>> {
+            let documents = self.documents.read().await;
+            Ok(documents
+                .range(start.unwrap_or(uuid::Uuid::nil())..)
+                .take(limit)
+                .map(|(id, doc)| (*id, doc.clone()))
+                .collect())
+        }
+    }
+
+    fn document(name: &str) -> Document {
+        let mut fields = HashMap::new();
+        fields.insert("name".to_string(), Value::String(name.to_string()));
+        Document {
+            id: uuid::Uuid::nil(),
+            fields,
+            version: 0,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn names(documents: &[Document]) -> Vec<String> {
+        let mut names: Vec<String> = documents
+            .iter()
+            .map(|doc| match doc.fields.get("name") {
+                Some(Value::String(name)) => name.clone(),
+                other => panic!("unexpected name {:?}", other),
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_backup_reads_snapshot_while_writes_continue() {
+        let database = Arc::new(Database::with_storage_engine(
+            "app".to_string(),
+            Arc::new(MemoryEngine::default()),
+            Arc::new(Oplog::new()),
+        ));
+        let users = database.collection("users".to_string()).await.unwrap();
+        let mut ids = Vec::new();
+        for name in ["ada", "bob", "cy", "dee", "eve"] {
+            ids.push(users.insert(document(name)).await.unwrap());
+        }
+
+        let manager = BackupManager::default();
+        let options = BackupOptions { chunk_size: Some(2), ..Default::default() };
+        let session = manager.open_session(database.clone(), options).await.unwrap();
+
+        users.update_by_id(&ids[0], document("ada2")).await.unwrap();
+        users.delete_by_id(&ids[2]).await.unwrap();
+        users.insert(document("fay")).await.unwrap();
+
+        let mut documents = Vec::new();
+        let mut after = None;
+        loop {
+            let chunk = manager.next_chunk(&session.session_id, "users", after).await.unwrap();
+            // Fetching a chunk again returns the same documents
+            let again = manager.next_chunk(&session.session_id, "users", after).await.unwrap();
+            assert_eq!(names(&chunk.documents), names(&again.documents));
+            documents.extend(chunk.documents);
+            if chunk.done {
+                break;
+            }
+            after = chunk.resume_after;
+        }
+        assert_eq!(names(&documents), vec!["ada", "bob", "cy", "dee", "eve"]);
+
+        let incremental = oplog_chunk(&database, session.snapshot_position, 100).unwrap();
+        assert_eq!(incremental.entries.len(), 3);
+        assert!(incremental.done);
+
+        assert!(manager.close_session(&session.session_id).await);
+        assert!(manager.next_chunk(&session.session_id, "users", None).await.is_err());
+    }
+}
@@ -0,0 +1,373 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Per-operation deadlines and cooperative cancellation
+//!
+//! Clients send a timeout with each request: `grpc-timeout` on gRPC calls and
+//! `x-largetable-timeout-ms` on HTTP requests. [`DeadlineLayer`] runs the
+//! request with an [`OperationContext`] in scope. The engine stops reads once
+//! the deadline passes, and long scans call [`checkpoint`] between batches so
+//! they give up soon after the caller has. When the client disconnects first,
+//! the request future is dropped, which releases what the operation held.
+
+use crate::observability::cancellation::CancellationMetrics;
+use crate::{LargetableError, Result};
+use axum::http::{HeaderMap, Request};
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Timeout of an HTTP request in milliseconds; also sent on requests forwarded to shards
+pub const TIMEOUT_HEADER: &str = "x-largetable-timeout-ms";
+
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Documents a loop processes between checkpoints
+pub const CHECKPOINT_INTERVAL: usize = 1024;
+
+tokio::task_local! {
+    static OPERATION: OperationContext;
+}
+
+/// Deadline and cancellation flag of one client operation
+#[derive(Debug, Clone)]
+pub struct OperationContext {
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl OperationContext {
+    /// An operation that must finish within `timeout` from now, or may run until cancelled
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Time left before the deadline, zero once it has passed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Stop the operation at its next checkpoint
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn expired_error(&self) -> LargetableError {
+        LargetableError::DeadlineExceeded(format!(
+            "operation did not finish within its {} ms timeout",
+            self.timeout.unwrap_or_default().as_millis()
+        ))
+    }
+
+    /// Fail if the operation was cancelled or its deadline has passed
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(LargetableError::Cancelled("the client cancelled the operation".to_string()));
+        }
+        if self.is_expired() {
+            return Err(self.expired_error());
+        }
+        Ok(())
+    }
+}
+
+/// Run `future` as the operation `context`; checkpoints inside it observe its deadline
+pub async fn with_operation<F: Future>(context: OperationContext, future: F) -> F::Output {
+    OPERATION.scope(context, future).await
+}
+
+/// The operation the current request runs as, if a front end set one
+pub fn current_operation() -> Option<OperationContext> {
+    OPERATION.try_with(OperationContext::clone).ok()
+}
+
+/// Fail if the current operation was cancelled or ran past its deadline
+///
+/// Called between batches of long scans and aggregations. Always passes
+/// outside a request, e.g. for embedded use of the engine.
+pub fn checkpoint() -> Result<()> {
+    OPERATION.try_with(OperationContext::check).unwrap_or(Ok(()))
+}
+
+/// Time left for the current operation, to pass on with requests it makes to other servers
+pub fn remaining_time() -> Option<Duration> {
+    OPERATION.try_with(OperationContext::remaining).ok().flatten()
+}
+
+/// Run a read bounded by the current operation's deadline
+///
+/// The future is dropped as soon as the deadline passes, releasing the locks
+/// and buffers it holds. Writes do not go through here: they check the
+/// deadline before they start and then run to completion, so a timeout never
+/// leaves one half applied.
+pub async fn bounded<T>(metrics: &CancellationMetrics, future: impl Future<Output = Result<T>>) -> Result<T> {
+    let Some(context) = current_operation() else {
+        return future.await;
+    };
+    let result = match context.check() {
+        Err(e) => Err(e),
+        Ok(()) => match context.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, future)
+                .await
+                .unwrap_or_else(|_| Err(context.expired_error())),
+            None => future.await,
+        },
+    };
+    if let Err(e) = &result {
+        metrics.record_error(e);
+    }
+    result
+}
+
+/// Timeout a request was sent with, from `grpc-timeout` or [`TIMEOUT_HEADER`]
+pub fn request_timeout(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(value) = headers.get(GRPC_TIMEOUT_HEADER).and_then(|value| value.to_str().ok()) {
+        return parse_grpc_timeout(value);
+    }
+    headers
+        .get(TIMEOUT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_millis)
+}
+
+/// Parse a `grpc-timeout` value: up to 8 digits followed by a unit
+fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let (digits, unit) = value.split_at_checked(value.len().checked_sub(1)?)?;
+    if digits.is_empty() || digits.len() > 8 {
+        return None;
+    }
+    let amount: u64 = digits.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(amount * 60 * 60),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    })
+}
+
+/// Tower layer that runs each HTTP request or gRPC call within its client's deadline
+#[derive(Clone)]
+pub struct DeadlineLayer {
+    metrics: Arc<CancellationMetrics>,
+    default_timeout: Option<Duration>,
+    max_timeout: Option<Duration>,
+}
+
+impl DeadlineLayer {
+    pub fn new(metrics: Arc<CancellationMetrics>) -> Self {
+        Self { metrics, default_timeout: None, max_timeout: None }
+    }
+
+    /// Deadline for requests that do not send a timeout
+    pub fn with_default_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    /// Cap on the timeout a client may ask for
+    pub fn with_max_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.max_timeout = timeout;
+        self
+    }
+
+    fn timeout(&self, headers: &HeaderMap) -> Option<Duration> {
+        let timeout = request_timeout(headers).or(self.default_timeout);
+        match (timeout, self.max_timeout) {
+            (Some(timeout), Some(max)) => Some(timeout.min(max)),
+            (timeout, max) => timeout.or(max),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for DeadlineLayer {
+    type Service = DeadlineService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeadlineService { inner, layer: self.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct DeadlineService<S> {
+    inner: S,
+    layer: DeadlineLayer,
+}
+
+impl<S, B> tower::Service<Request<B>> for DeadlineService<S>
+where
+    S: tower::Service<Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, std::result::Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let context = OperationContext::new(self.layer.timeout(request.headers()));
+        if context.deadline.is_some() {
+            self.layer.metrics.record_deadline();
+        }
+        let guard = CancelOnDrop {
+            context: context.clone(),
+            metrics: self.layer.metrics.clone(),
+            finished: false,
+        };
+        let response = with_operation(context, self.inner.call(request));
+        Box::pin(async move {
+            let response = response.await;
+            guard.finish();
+            response
+        })
+    }
+}
+
+/// Cancels an operation whose request future is dropped before it finished
+///
+/// That happens when the client disconnects, or when tonic gives up on a call
+/// at its `grpc-timeout` before the engine noticed the deadline itself.
+struct CancelOnDrop {
+    context: OperationContext,
+    metrics: Arc<CancellationMetrics>,
+    finished: bool,
+}
+
+impl CancelOnDrop {
+    fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.context.cancel();
+        if self.context.is_expired() {
+            self.metrics.record_deadline_exceeded();
+        } else {
+            self.metrics.record_client_cancelled();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grpc_timeout_parsing() {
+        assert_eq!(parse_grpc_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_grpc_timeout("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_grpc_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_grpc_timeout("m"), None);
+        assert_eq!(parse_grpc_timeout("123456789m"), None);
+        assert_eq!(parse_grpc_timeout("10x"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMEOUT_HEADER, "1500".parse().unwrap());
+        assert_eq!(request_timeout(&headers), Some(Duration::from_millis(1500)));
+        headers.insert(GRPC_TIMEOUT_HEADER, "20m".parse().unwrap());
+        assert_eq!(request_timeout(&headers), Some(Duration::from_millis(20)));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_observes_deadline_and_cancellation() {
+        assert!(checkpoint().is_ok());
+
+        let expired = OperationContext::new(Some(Duration::ZERO));
+        let result = with_operation(expired, async { checkpoint() }).await;
+        assert!(matches!(result, Err(LargetableError::DeadlineExceeded(_))));
+
+        let context = OperationContext::new(None);
+        context.cancel();
+        let result = with_operation(context, async { checkpoint() }).await;
+        assert!(matches!(result, Err(LargetableError::Cancelled(_))));
+    }
+
+    #[tokio::test]
+    async fn test_bounded_stops_at_deadline() {
+        let metrics = CancellationMetrics::default();
+        let context = OperationContext::new(Some(Duration::from_millis(20)));
+        let result = with_operation(context, bounded(&metrics, async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        }))
+        .await;
+        assert!(matches!(result, Err(LargetableError::DeadlineExceeded(_))));
+        assert_eq!(metrics.stats().deadline_exceeded, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_request_cancels_operation() {
+        use tower::{Layer, Service};
+
+        let metrics = Arc::new(CancellationMetrics::default());
+        let (started, ready) = tokio::sync::oneshot::channel();
+        let mut started = Some(started);
+        let mut service = DeadlineLayer::new(metrics.clone()).layer(tower::service_fn(move |_: Request<()>| {
+            let started = started.take().unwrap();
+            async move {
+                started.send(current_operation().unwrap()).unwrap();
+                futures::future::pending::<std::result::Result<(), std::convert::Infallible>>().await
+            }
+        }));
+
+        // The handler starts, then the client goes away
+        let mut call = service.call(Request::new(()));
+        assert!(futures::poll!(&mut call).is_pending());
+        drop(call);
+        let context = ready.await.unwrap();
+        assert!(context.is_cancelled());
+        assert_eq!(metrics.stats().client_cancelled, 1);
+
+        let mut service = DeadlineLayer::new(metrics.clone())
+            .layer(tower::service_fn(|_: Request<()>| async { Ok::<_, std::convert::Infallible>(()) }));
+        service.call(Request::new(())).await.unwrap();
+        assert_eq!(metrics.stats().client_cancelled, 1);
+    }
+
+    #[test]
+    fn test_layer_caps_timeouts() {
+        let layer = DeadlineLayer::new(Arc::default())
+            .with_default_timeout(Some(Duration::from_secs(30)))
+            .with_max_timeout(Some(Duration::from_secs(60)));
+        assert_eq!(layer.timeout(&HeaderMap::new()), Some(Duration::from_secs(30)));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMEOUT_HEADER, "600000".parse().unwrap());
+        assert_eq!(layer.timeout(&headers), Some(Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,455 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Memory accounting and per-query memory limits
+//!
+//! Sorts, groups, window stages and index builds hold documents in memory,
+//! so they reserve what they hold from the engine's [`MemoryGovernor`] before
+//! growing. Each operation runs with a [`QueryMemory`] in scope, set with
+//! [`with_query_memory`] the way deadlines are, which caps it at the
+//! per-query limit while the governor caps all operations together at the
+//! global limit. A sort or group that reaches either limit writes what it
+//! holds to a temporary file and carries on, so a large aggregation gets
+//! slower instead of taking the server down; window stages cannot spill and
+//! fail with `ResourceExhausted`. Outside an operation nothing is tracked,
+//! e.g. for embedded use of the engine.
+//!
+//! Sizes are estimates of what documents take in memory, not exact
+//! allocations. Spill files are local and written with blocking I/O, once
+//! per limit's worth of documents.
+
+use crate::observability::memory::{MemoryMetrics, MemoryStats};
+use crate::{Document, LargetableError, Result, Value};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+tokio::task_local! {
+    static QUERY_MEMORY: QueryMemory;
+}
+
+/// What a reservation holds memory for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryConsumer {
+    Sort,
+    Group,
+    Window,
+    IndexBuild,
+}
+
+impl MemoryConsumer {
+    fn name(&self) -> &'static str {
+        match self {
+            MemoryConsumer::Sort => "sort",
+            MemoryConsumer::Group => "group",
+            MemoryConsumer::Window => "window",
+            MemoryConsumer::IndexBuild => "index-build",
+        }
+    }
+}
+
+/// Memory limits of the engine
+#[derive(Debug, Clone)]
+pub struct MemoryLimitsConfig {
+    /// Most one operation may hold
+    pub query_limit_bytes: usize,
+    /// Most all operations together may hold
+    pub global_limit_bytes: usize,
+    /// Write sorts and groups over a limit to temporary files rather than failing them
+    pub spill_to_disk: bool,
+    /// Where spill files are written; they are removed once read
+    pub spill_dir: PathBuf,
+}
+
+impl Default for MemoryLimitsConfig {
+    fn default() -> Self {
+        Self {
+            query_limit_bytes: 100 * 1024 * 1024, // 100MB
+            global_limit_bytes: 1024 * 1024 * 1024, // 1GB
+            spill_to_disk: true,
+            spill_dir: std::env::temp_dir().join("largetable-spill"),
+        }
+    }
+}
+
+/// Memory held by every operation of the engine
+pub struct MemoryGovernor {
+    config: MemoryLimitsConfig,
+    used: AtomicUsize,
+    metrics: MemoryMetrics,
+}
+
+impl Default for MemoryGovernor {
+    fn default() -> Self {
+        Self::new(MemoryLimitsConfig::default())
+    }
+}
+
+impl MemoryGovernor {
+    pub fn new(config: MemoryLimitsConfig) -> Self {
+        Self {
+            config,
+            used: AtomicUsize::new(0),
+            metrics: MemoryMetrics::default(),
+        }
+    }
+
+    pub fn config(&self) -> &MemoryLimitsConfig {
+        &self.config
+    }
+
+    /// Bytes reserved by every running operation
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+
+    /// Budget of a new operation
+    pub fn query(self: &Arc<Self>) -> QueryMemory {
+        QueryMemory {
+            governor: self.clone(),
+            used: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        let used = self.used();
+        MemoryStats {
+            used_bytes: used,
+            global_limit_bytes: self.config.global_limit_bytes,
+            query_limit_bytes: self.config.query_limit_bytes,
+            pressure: used as f64 / self.config.global_limit_bytes.max(1) as f64,
+            ..self.metrics.stats()
+        }
+    }
+
+    /// Add `bytes` to the usage unless that goes over the global limit; returns the new usage
+    fn try_acquire(&self, bytes: usize) -> Option<usize> {
+        let limit = self.config.global_limit_bytes;
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(bytes).filter(|total| *total <= limit)
+            })
+            .ok()
+            .map(|previous| previous + bytes)
+    }
+
+    fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::AcqRel);
+    }
+}
+
+/// Memory budget of one operation, shared by its stages
+#[derive(Clone)]
+pub struct QueryMemory {
+    governor: Arc<MemoryGovernor>,
+    used: Arc<AtomicUsize>,
+}
+
+impl QueryMemory {
+    /// Bytes reserved by this operation
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+
+    pub fn reserve(&self, consumer: MemoryConsumer) -> MemoryReservation {
+        MemoryReservation {
+            query: Some(self.clone()),
+            consumer,
+            bytes: 0,
+        }
+    }
+}
+
+/// Run `future` with the memory budget `memory`; reservations inside it count against its limits
+pub async fn with_query_memory<F: Future>(memory: QueryMemory, future: F) -> F::Output {
+    QUERY_MEMORY.scope(memory, future).await
+}
+
+/// The memory budget of the current operation, if the engine set one
+pub fn current_query_memory() -> Option<QueryMemory> {
+    QUERY_MEMORY.try_with(QueryMemory::clone).ok()
+}
+
+/// Memory held by one sort, group, window stage or index build, released when dropped
+pub struct MemoryReservation {
+    query: Option<QueryMemory>,
+    consumer: MemoryConsumer,
+    bytes: usize,
+}
+
+impl MemoryReservation {
+    /// Reservation within the current operation; tracks nothing outside one
+    pub fn current(consumer: MemoryConsumer) -> Self {
+        match current_query_memory() {
+            Some(memory) => memory.reserve(consumer),
+            None => Self { query: None, consumer, bytes: 0 },
+        }
+    }
+
+    /// Bytes held
+    pub fn size(&self) -> usize {
+        self.bytes
+    }
+
+    /// Whether `bytes` more could ever be reserved, once other reservations are released
+    pub fn fits(&self, bytes: usize) -> bool {
+        self.query.as_ref().is_none_or(|query| {
+            let config = &query.governor.config;
+            bytes <= config.query_limit_bytes.min(config.global_limit_bytes)
+        })
+    }
+
+    /// Reserve `bytes` more, or reserve nothing and return false when the
+    /// operation or the engine would go over its limit
+    pub fn try_grow(&mut self, bytes: usize) -> bool {
+        let Some(query) = &self.query else {
+            self.bytes += bytes;
+            return true;
+        };
+        let governor = &query.governor;
+        let limit = governor.config.query_limit_bytes;
+        let within_query = query
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(bytes).filter(|total| *total <= limit)
+            })
+            .is_ok();
+        if !within_query {
+            governor.metrics.record_query_limit_hit();
+            return false;
+        }
+        let Some(used) = governor.try_acquire(bytes) else {
+            query.used.fetch_sub(bytes, Ordering::AcqRel);
+            governor.metrics.record_global_limit_hit();
+            return false;
+        };
+
+        governor.metrics.record_reserved(self.consumer, bytes, used);
+        self.bytes += bytes;
+        true
+    }
+
+    /// Reserve `bytes` more, or fail the operation
+    pub fn grow(&mut self, bytes: usize) -> Result<()> {
+        if self.try_grow(bytes) {
+            return Ok(());
+        }
+        Err(self.exhausted())
+    }
+
+    /// Give back `bytes` no longer held
+    pub fn shrink(&mut self, bytes: usize) {
+        let bytes = bytes.min(self.bytes);
+        self.bytes -= bytes;
+        if let Some(query) = &self.query {
+            query.used.fetch_sub(bytes, Ordering::AcqRel);
+            query.governor.release(bytes);
+            query.governor.metrics.record_released(self.consumer, bytes);
+        }
+    }
+
+    /// Error of an operation that needs more memory than its limits allow
+    pub fn exhausted(&self) -> LargetableError {
+        let Some(query) = &self.query else {
+            return LargetableError::ResourceExhausted(format!("{} ran out of memory", self.consumer.name()));
+        };
+        let config = &query.governor.config;
+        query.governor.metrics.record_exhausted();
+        LargetableError::ResourceExhausted(format!(
+            "{} needs more memory than the {} byte per-query limit or the {} bytes left of the global limit",
+            self.consumer.name(),
+            config.query_limit_bytes,
+            config.global_limit_bytes.saturating_sub(query.governor.used()),
+        ))
+    }
+
+    /// Write `records` to a new spill file and release everything reserved,
+    /// for a sort or group that reached its limit
+    pub fn spill<T: Serialize>(&mut self, records: impl IntoIterator<Item = T>) -> Result<SpillFile<T>> {
+        let Some(governor) = self.query.as_ref().map(|query| query.governor.clone()) else {
+            return Err(self.exhausted());
+        };
+        if !governor.config.spill_to_disk {
+            return Err(self.exhausted());
+        }
+
+        std::fs::create_dir_all(&governor.config.spill_dir)?;
+        let mut spill = SpillFile {
+            path: governor
+                .config
+                .spill_dir
+                .join(format!("{}-{}.spill", self.consumer.name(), uuid::Uuid::new_v4())),
+            records: 0,
+            _records: PhantomData,
+        };
+        let mut writer = BufWriter::new(File::create(&spill.path)?);
+        for record in records {
+            bincode::serialize_into(&mut writer, &record).map_err(|e| LargetableError::Serialization(e.to_string()))?;
+            spill.records += 1;
+        }
+        writer.flush()?;
+        let written = writer.get_ref().metadata()?.len();
+
+        governor.metrics.record_spill(written);
+        tracing::debug!("Spilled {} {} records ({} bytes) to {}", spill.records, self.consumer.name(), written, spill.path.display());
+        self.shrink(self.bytes);
+        Ok(spill)
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.shrink(self.bytes);
+    }
+}
+
+/// Records a sort or group wrote to disk, read back in the order written;
+/// the file is removed when this is dropped
+pub struct SpillFile<T> {
+    path: PathBuf,
+    records: usize,
+    _records: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> SpillFile<T> {
+    pub fn len(&self) -> usize {
+        self.records
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    pub fn read(self) -> Result<SpillReader<T>> {
+        let reader = BufReader::new(File::open(&self.path)?);
+        Ok(SpillReader { reader, remaining: self.records, _file: self })
+    }
+}
+
+impl<T> Drop for SpillFile<T> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Records of a spill file, in the order they were written
+pub struct SpillReader<T> {
+    reader: BufReader<File>,
+    remaining: usize,
+    _file: SpillFile<T>,
+}
+
+impl<T: DeserializeOwned> Iterator for SpillReader<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(bincode::deserialize_from(&mut self.reader).map_err(|e| LargetableError::Serialization(e.to_string())))
+    }
+}
+
+/// Approximate bytes a document takes in memory
+pub fn document_size(doc: &Document) -> usize {
+    std::mem::size_of::<Document>() + fields_size(doc)
+}
+
+/// Approximate bytes a value takes in memory
+pub fn value_size(value: &Value) -> usize {
+    std::mem::size_of::<Value>()
+        + match value {
+            Value::String(s) => s.len(),
+            Value::Binary(bytes) => bytes.len(),
+            Value::Document(doc) => fields_size(doc),
+            Value::Array(items) => items.iter().map(value_size).sum(),
+            Value::Vector(vector) => vector.len() * std::mem::size_of::<f32>(),
+            _ => 0,
+        }
+}
+
+fn fields_size(doc: &Document) -> usize {
+    doc.fields
+        .iter()
+        .map(|(name, value)| std::mem::size_of::<String>() + name.len() + value_size(value))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn governor(query_limit_bytes: usize, global_limit_bytes: usize) -> Arc<MemoryGovernor> {
+        Arc::new(MemoryGovernor::new(MemoryLimitsConfig {
+            query_limit_bytes,
+            global_limit_bytes,
+            ..MemoryLimitsConfig::default()
+        }))
+    }
+
+    #[test]
+    fn test_query_and_global_limits() {
+        let governor = governor(100, 150);
+        let (first, second) = (governor.query(), governor.query());
+
+        let mut sort = first.reserve(MemoryConsumer::Sort);
+        assert!(sort.try_grow(80));
+        assert!(!sort.try_grow(30), "over the per-query limit");
+
+        let mut group = second.reserve(MemoryConsumer::Group);
+        assert!(!group.try_grow(80), "over the global limit");
+        assert!(group.try_grow(70));
+        assert_eq!(governor.used(), 150);
+
+        drop(sort);
+        assert_eq!((first.used(), governor.used()), (0, 70));
+        let stats = governor.stats();
+        assert_eq!((stats.sort_bytes, stats.group_bytes, stats.peak_bytes), (0, 70, 150));
+        assert_eq!((stats.query_limit_hits, stats.global_limit_hits), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_reservations_follow_the_operation_in_scope() {
+        assert!(MemoryReservation::current(MemoryConsumer::Sort).try_grow(usize::MAX / 2));
+
+        let governor = governor(100, 1000);
+        let memory = governor.query();
+        with_query_memory(memory.clone(), async {
+            let mut reservation = MemoryReservation::current(MemoryConsumer::Window);
+            reservation.grow(60).unwrap();
+            assert_eq!(memory.used(), 60);
+            assert!(matches!(reservation.grow(60), Err(LargetableError::ResourceExhausted(_))));
+        })
+        .await;
+        assert_eq!(governor.stats().exhausted, 1);
+    }
+
+    #[test]
+    fn test_spill_releases_and_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let governor = Arc::new(MemoryGovernor::new(MemoryLimitsConfig {
+            spill_dir: dir.path().to_path_buf(),
+            ..MemoryLimitsConfig::default()
+        }));
+        let mut reservation = governor.query().reserve(MemoryConsumer::Sort);
+        reservation.grow(1000).unwrap();
+
+        let spill = reservation.spill(vec![("a".to_string(), 1i64), ("b".to_string(), 2)]).unwrap();
+        assert_eq!((reservation.size(), governor.used(), spill.len()), (0, 0, 2));
+        assert_eq!(governor.stats().spills, 1);
+
+        let records: Vec<(String, i64)> = spill.read().unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(records, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0, "the spill file is removed once read");
+    }
+}
@@ -16,10 +16,29 @@ pub mod connection_pool;
 pub mod cache;
 pub mod memory_manager;
 pub mod auto_scaling;
+pub mod backup;
+pub mod deadline;
+pub mod memory_limits;
+pub mod snapshot;
 
-use crate::{Result, DatabaseName, CollectionName, StorageEngine, DocumentId, Document};
+use crate::{Result, LargetableError, DatabaseName, CollectionName, StorageEngine, DocumentId, Document};
+use crate::auth::{AccessControl, Action, RoleGrant, UserInfo};
+use crate::collection::partitioning::{
+    PartitionOptions, PartitionStats, PartitionedCollection, PartitionedCollectionStats, RetentionReport,
+};
 use crate::database::Database;
+use crate::database::admin::{CollectionStats, SampleOptions};
+use crate::document::rules::WriteRules;
+use crate::document::StoredDocument;
+use crate::observability::cancellation::{CancellationMetrics, CancellationStats};
+use crate::observability::document_cache::DocumentCacheStats;
+use crate::observability::memory::MemoryStats;
+use crate::query::{QueryCache, QueryCacheConfig, QueryCacheStats};
+use crate::replication::{HistoryConfig, Oplog};
+use crate::storage::cache::{DocumentCache, DocumentCacheConfig};
+use crate::storage::encryption::{self, KeyRotationStatus, PageCipher};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info};
@@ -29,11 +48,32 @@ use connection_pool::{ConnectionPool, ConnectionPoolConfig};
 use cache::{MultiLevelCache, CacheConfig};
 use memory_manager::{MemoryManager, MemoryConfig};
 use auto_scaling::{AutoScalingManager, AutoScalingConfig};
+use backup::{BackupManager, BackupOptions, BackupSession, OplogChunk};
+use memory_limits::{MemoryGovernor, MemoryLimitsConfig};
+
+/// Data directory of engines not given one, as in the default server config
+pub const DEFAULT_DATA_DIR: &str = "./data";
 
 /// Main database engine that manages multiple databases
 pub struct DatabaseEngine {
     databases: Arc<RwLock<HashMap<DatabaseName, Arc<Database>>>>,
     default_storage_engine: StorageEngine,
+    /// Each database keeps its files in a directory of its own under it
+    data_dir: PathBuf,
+    oplog: Arc<Oplog>,
+    backups: Arc<BackupManager>,
+    query_cache: Arc<QueryCache>,
+    /// Hot documents of every database, in front of their storage engines
+    document_cache: Arc<DocumentCache>,
+    access: Arc<AccessControl>,
+    /// Seals pages of newly opened databases when encryption at rest is on
+    encryption: Option<Arc<PageCipher>>,
+    rotation_batch_size: usize,
+    key_rotation: Arc<parking_lot::Mutex<KeyRotationStatus>>,
+    /// Operations stopped by their deadline or their client
+    cancellations: Arc<CancellationMetrics>,
+    /// Memory held by the sorts, groups and index builds of every operation
+    memory: Arc<MemoryGovernor>,
     // Enterprise-grade features
     connection_pool: Arc<ConnectionPool>,
     cache: Arc<MultiLevelCache>,
@@ -60,6 +100,17 @@ impl DatabaseEngine {
         Ok(Self {
             databases: Arc::new(RwLock::new(HashMap::new())),
             default_storage_engine,
+            data_dir: PathBuf::from(DEFAULT_DATA_DIR),
+            oplog: Arc::new(Oplog::new()),
+            backups: Arc::new(BackupManager::default()),
+            query_cache: Arc::new(QueryCache::default()),
+            document_cache: Arc::new(DocumentCache::default()),
+            access: Arc::new(AccessControl::disabled()),
+            encryption: None,
+            rotation_batch_size: 1000,
+            key_rotation: Arc::new(parking_lot::Mutex::new(KeyRotationStatus::default())),
+            cancellations: Arc::new(CancellationMetrics::default()),
+            memory: Arc::new(MemoryGovernor::default()),
             connection_pool,
             cache,
             memory_manager,
@@ -67,7 +118,98 @@ impl DatabaseEngine {
         })
     }
 
+    /// Keep the files of databases opened from now on under `data_dir`
+    pub fn with_data_dir(mut self, data_dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = data_dir.into();
+        info!("Data directory: {}", self.data_dir.display());
+        self
+    }
+
+    /// Cache query results; the cache is off unless `config.enabled` is set
+    pub fn with_query_cache(mut self, config: QueryCacheConfig) -> Self {
+        if config.enabled {
+            info!(
+                "Query cache enabled: {} entries, {} bytes, {:?} TTL",
+                config.max_entries, config.max_memory_bytes, config.ttl
+            );
+        }
+        self.query_cache = Arc::new(QueryCache::new(config));
+        self
+    }
+
+    /// Cache hot documents in front of the storage engines of databases opened from now on
+    pub fn with_document_cache(mut self, config: DocumentCacheConfig) -> Self {
+        if config.enabled {
+            info!(
+                "Document cache enabled: {} documents, {} bytes",
+                config.max_documents, config.max_memory_bytes
+            );
+        }
+        self.document_cache = Arc::new(DocumentCache::new(config));
+        self
+    }
+
+    /// Limit the memory of each operation and of all of them together
+    pub fn with_memory_limits(mut self, config: MemoryLimitsConfig) -> Self {
+        info!(
+            "Memory limits: {} bytes per query, {} bytes in total, spilling to {}",
+            config.query_limit_bytes,
+            config.global_limit_bytes,
+            if config.spill_to_disk { config.spill_dir.display().to_string() } else { "nowhere".to_string() }
+        );
+        self.memory = Arc::new(MemoryGovernor::new(config));
+        self
+    }
+
+    /// Retain oplog history for snapshot reads and incremental backups of databases opened from now on
+    pub fn with_history(mut self, config: HistoryConfig) -> Self {
+        info!(
+            "Oplog history: {} entries, {}",
+            config.max_entries,
+            config.retention.map_or("no age limit".to_string(), |retention| format!("{:?} retention", retention))
+        );
+        self.oplog = Arc::new(Oplog::with_history(config));
+        self
+    }
+
+    /// Check every operation against the roles of the principal it runs as
+    pub fn with_access_control(mut self, access: Arc<AccessControl>) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Encrypt pages at rest with `cipher`, resealing `rotation_batch_size` pages at a time when keys rotate
+    pub fn with_encryption(mut self, cipher: Arc<PageCipher>, rotation_batch_size: usize) -> Self {
+        self.key_rotation.lock().active_key_id = cipher.active_key_id();
+        self.encryption = Some(cipher);
+        self.rotation_batch_size = rotation_batch_size;
+        self
+    }
+
+    /// Users, SCRAM exchanges and sessions
+    pub fn access_control(&self) -> &Arc<AccessControl> {
+        &self.access
+    }
+
+    /// Counters shared with the deadline layers of the network front ends
+    pub fn cancellation_metrics(&self) -> &Arc<CancellationMetrics> {
+        &self.cancellations
+    }
+
+    /// Memory accounting of sorts, groups and index builds
+    pub fn memory_governor(&self) -> &Arc<MemoryGovernor> {
+        &self.memory
+    }
+
+    /// Check the current principal may perform `action`; always passes with access control off
+    pub fn authorize(&self, action: Action, database: Option<&str>) -> Result<()> {
+        self.access.authorize(action, database)
+    }
+
     /// Get or create a database
+    ///
+    /// Database and collection handles are not access checked; network front
+    /// ends go through the operations below, which are.
     pub async fn database(&self, name: DatabaseName) -> Result<Arc<Database>> {
         let mut databases = self.databases.write().await;
         
@@ -75,25 +217,40 @@ impl DatabaseEngine {
             return Ok(database.clone());
         }
         
-        let database = Arc::new(Database::new(name.clone(), self.default_storage_engine)?);
+        let database = Arc::new(Database::new(
+            name.clone(),
+            self.default_storage_engine,
+            &self.data_dir,
+            self.oplog.clone(),
+            self.encryption.clone(),
+            &self.document_cache,
+        )?);
         databases.insert(name, database.clone());
         
         debug!("Created database: {}", name);
         Ok(database)
     }
 
-    /// List all databases
+    /// Create a database if it does not exist
+    pub async fn create_database(&self, name: DatabaseName) -> Result<()> {
+        self.authorize(Action::CreateDatabase, Some(&name))?;
+        self.database(name).await.map(|_| ())
+    }
+
+    /// List the databases the current principal can access
     pub async fn list_databases(&self) -> Result<Vec<DatabaseName>> {
         let databases = self.databases.read().await;
-        Ok(databases.keys().cloned().collect())
+        Ok(databases.keys().filter(|name| self.access.can_see_database(name)).cloned().collect())
     }
 
     /// Drop a database
     pub async fn drop_database(&self, name: &DatabaseName) -> Result<bool> {
+        self.authorize(Action::DropDatabase, Some(name))?;
         let mut databases = self.databases.write().await;
         let removed = databases.remove(name).is_some();
         
         if removed {
+            self.query_cache.invalidate_database(name);
             debug!("Dropped database: {}", name);
         }
         
@@ -106,6 +263,146 @@ impl DatabaseEngine {
         database.collection(collection_name).await
     }
 
+    /// The partitioned collection `collection_name`, if it is one
+    async fn partitioned(&self, database_name: &DatabaseName, collection_name: &str) -> Result<Option<Arc<PartitionedCollection>>> {
+        Ok(self.database(database_name.clone()).await?.partitioned_collection(collection_name).await)
+    }
+
+    /// List the collections of a database, partitioned ones included
+    pub async fn list_collections(&self, database_name: DatabaseName) -> Result<Vec<CollectionName>> {
+        self.authorize(Action::ListCollections, Some(&database_name))?;
+        let database = self.database(database_name).await?;
+        let mut collections = database.list_collections().await?;
+        collections.extend(database.partitioned_collections().await.iter().map(|collection| collection.name().clone()));
+        Ok(collections)
+    }
+
+    /// Create a collection partitioned into time windows, or keep it if it
+    /// exists with the same options
+    pub async fn create_partitioned_collection(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+        options: PartitionOptions,
+    ) -> Result<()> {
+        self.authorize(Action::CreateCollection, Some(&database_name))?;
+        let database = self.database(database_name).await?;
+        database.create_partitioned_collection(collection_name, options).await.map(|_| ())
+    }
+
+    /// Partitions of a partitioned collection and how often queries skip them
+    pub async fn partition_stats(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+    ) -> Result<PartitionedCollectionStats> {
+        self.authorize(Action::ListCollections, Some(&database_name))?;
+        match self.partitioned(&database_name, &collection_name).await? {
+            Some(collection) => Ok(collection.stats()),
+            None => Err(LargetableError::Query(format!("Collection '{}' is not partitioned", collection_name))),
+        }
+    }
+
+    /// Drop one partition of a collection, whatever its age
+    pub async fn drop_partition(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+        start: crate::Timestamp,
+    ) -> Result<Option<PartitionStats>> {
+        self.authorize(Action::Delete, Some(&database_name))?;
+        match self.partitioned(&database_name, &collection_name).await? {
+            Some(collection) => {
+                let dropped = collection.drop_partition(start).await?;
+                if dropped.is_some() {
+                    self.query_cache.invalidate_database(&database_name);
+                }
+                Ok(dropped)
+            }
+            None => Err(LargetableError::Query(format!("Collection '{}' is not partitioned", collection_name))),
+        }
+    }
+
+    /// Drop the partitions past retention in every partitioned collection
+    pub(crate) async fn enforce_partition_retention(&self) -> RetentionReport {
+        let now = chrono::Utc::now().timestamp_micros();
+        let databases: Vec<Arc<Database>> = self.databases.read().await.values().cloned().collect();
+        let mut report = RetentionReport::default();
+        for database in databases {
+            for collection in database.partitioned_collections().await {
+                let dropped = collection.maintain(now).await;
+                if !dropped.dropped_partitions.is_empty() {
+                    self.query_cache.invalidate_database(database.name());
+                }
+                report.dropped_partitions.extend(dropped.dropped_partitions);
+                report.dropped_documents += dropped.dropped_documents;
+            }
+        }
+        report
+    }
+
+    /// Create a collection if it does not exist
+    pub async fn create_collection(&self, database_name: DatabaseName, collection_name: CollectionName) -> Result<()> {
+        self.authorize(Action::CreateCollection, Some(&database_name))?;
+        self.collection(database_name, collection_name).await.map(|_| ())
+    }
+
+    /// Defaults, computed fields and validators of a collection
+    pub async fn write_rules(&self, database_name: DatabaseName, collection_name: CollectionName) -> Result<WriteRules> {
+        self.authorize(Action::ListCollections, Some(&database_name))?;
+        let collection = self.collection(database_name, collection_name).await?;
+        Ok(collection.write_rules().as_ref().clone())
+    }
+
+    /// Replace the write rules of a collection, applied from the next write on
+    pub async fn set_write_rules(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+        rules: WriteRules,
+    ) -> Result<()> {
+        self.authorize(Action::CreateCollection, Some(&database_name))?;
+        self.collection(database_name, collection_name).await?.set_write_rules(rules).await
+    }
+
+    /// Indexed fields of a collection and their index types
+    pub async fn list_indexes(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+    ) -> Result<HashMap<String, crate::IndexType>> {
+        self.authorize(Action::ListIndexes, Some(&database_name))?;
+        self.collection(database_name, collection_name).await?.list_indexes().await
+    }
+
+    /// Document count, size, indexes and inferred schema of a collection
+    pub async fn collection_stats(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+        options: SampleOptions,
+    ) -> Result<CollectionStats> {
+        // Reads documents, so it needs the same access as a query
+        self.authorize(Action::Find, Some(&database_name))?;
+        deadline::bounded(&self.cancellations, async {
+            self.collection(database_name, collection_name).await?.stats(options).await
+        })
+        .await
+    }
+
+    /// Start a background index build
+    pub async fn create_index(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+        field: String,
+        index_type: crate::IndexType,
+    ) -> Result<crate::index::build::IndexBuildStatus> {
+        self.authorize(Action::CreateIndex, Some(&database_name))?;
+        let collection = self.collection(database_name, collection_name).await?;
+        memory_limits::with_query_memory(self.memory.query(), collection.create_index(field, index_type)).await
+    }
+
     /// Execute a query on a collection
     pub async fn query(
         &self,
@@ -113,13 +410,51 @@ impl DatabaseEngine {
         collection_name: CollectionName,
         query: crate::query::Query,
     ) -> Result<crate::query::QueryResult> {
-        let collection = self.collection(database_name, collection_name).await?;
-        
-        // Get all documents from the collection
-        let documents = collection.find_many(None, usize::MAX).await?;
-        
-        // Execute the query
-        query.execute(documents).await
+        self.authorize(Action::Find, Some(&database_name))?;
+        deadline::bounded(&self.cancellations, async {
+            // Partitioned collections skip the query cache, since pruning
+            // already keeps their reads small
+            if let Some(partitioned) = self.partitioned(&database_name, &collection_name).await? {
+                if query.read_at.is_some() {
+                    return Err(LargetableError::Query(
+                        "Snapshot reads are not supported on partitioned collections".to_string(),
+                    ));
+                }
+                return partitioned.find(&query).await;
+            }
+            if let Some(timestamp) = query.read_at {
+                let database = self.database(database_name).await?;
+                return snapshot::query_at(&database, &collection_name, &query, timestamp).await;
+            }
+            let collection = self.collection(database_name.clone(), collection_name.clone()).await?;
+            if query.bypass_cache || !self.query_cache.is_enabled() {
+                return collection.find(&query).await;
+            }
+            
+            let key = QueryCache::key(&database_name, &collection_name, &query);
+            let version = collection.version();
+            if let Some(result) = self.query_cache.get(&key, version) {
+                debug!("Query cache hit on collection '{}'", collection_name);
+                return Ok(result);
+            }
+            
+            // Candidates come from the planner's chosen index, or a full scan;
+            // a scan stopped by its deadline returns early and is never cached
+            let result = collection.find(&query).await?;
+            self.query_cache.insert(key, version, &result);
+            Ok(result)
+        })
+        .await
+    }
+
+    /// Hit rate and size of the query cache
+    pub fn query_cache_stats(&self) -> QueryCacheStats {
+        self.query_cache.stats()
+    }
+
+    /// Drop every cached query result
+    pub fn clear_query_cache(&self) {
+        self.query_cache.clear();
     }
 
     /// Execute an aggregation pipeline on a collection
@@ -129,13 +464,29 @@ impl DatabaseEngine {
         collection_name: CollectionName,
         pipeline: crate::query::AggregationPipeline,
     ) -> Result<Vec<serde_json::Value>> {
-        let collection = self.collection(database_name, collection_name).await?;
-        
-        // Get all documents from the collection
-        let documents = collection.find_many(None, usize::MAX).await?;
-        
-        // Execute the aggregation pipeline
-        pipeline.execute(documents).await
+        self.authorize(Action::Find, Some(&database_name))?;
+        let aggregation = deadline::bounded(&self.cancellations, async {
+            let database = self.database(database_name).await?;
+            let batch_size = crate::query::aggregation::DEFAULT_SCAN_BATCH_SIZE;
+            
+            // Stream the collection through the pipeline; $lookup resolves against the same database.
+            // A leading $match prunes the partitions of a partitioned collection.
+            let source = match database.partitioned_collection(&collection_name).await {
+                Some(partitioned) => {
+                    let filter = match pipeline.stages().first() {
+                        Some(crate::query::AggregationStage::Match(filter)) => Some(filter),
+                        _ => None,
+                    };
+                    partitioned.stream_documents(filter, batch_size)
+                }
+                None => database.collection(collection_name).await?.stream_documents(batch_size),
+            };
+            pipeline
+                .with_lookup_source(database)
+                .execute_stream(source)
+                .await
+        });
+        memory_limits::with_query_memory(self.memory.query(), aggregation).await
     }
 
     /// Insert a document into a collection
@@ -145,6 +496,11 @@ impl DatabaseEngine {
         collection_name: CollectionName,
         document: Document,
     ) -> Result<DocumentId> {
+        self.authorize(Action::Insert, Some(&database_name))?;
+        deadline::checkpoint()?;
+        if let Some(partitioned) = self.partitioned(&database_name, &collection_name).await? {
+            return partitioned.insert(document).await;
+        }
         let collection = self.collection(database_name, collection_name).await?;
         collection.insert(document).await
     }
@@ -156,10 +512,47 @@ impl DatabaseEngine {
         collection_name: CollectionName,
         id: DocumentId,
     ) -> Result<Option<Document>> {
+        self.authorize(Action::Find, Some(&database_name))?;
+        if let Some(partitioned) = self.partitioned(&database_name, &collection_name).await? {
+            return partitioned.find_by_id(&id).await;
+        }
         let collection = self.collection(database_name, collection_name).await?;
         collection.find_by_id(&id).await
     }
 
+    /// Find a document by ID as it was at a cluster timestamp, in microseconds since the Unix epoch
+    pub async fn find_document_by_id_at(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+        id: DocumentId,
+        timestamp: i64,
+    ) -> Result<Option<Document>> {
+        self.authorize(Action::Find, Some(&database_name))?;
+        let database = self.database(database_name).await?;
+        snapshot::find_by_id_at(&database, &collection_name, &id, timestamp).await
+    }
+
+    /// Current cluster time, for reading at it later; writes from now on come after it
+    pub fn cluster_time(&self) -> i64 {
+        self.oplog.cluster_time()
+    }
+
+    /// Find a document by ID in its stored form, without decoding its fields
+    pub async fn find_stored_document_by_id(
+        &self,
+        database_name: DatabaseName,
+        collection_name: CollectionName,
+        id: DocumentId,
+    ) -> Result<Option<StoredDocument>> {
+        self.authorize(Action::Find, Some(&database_name))?;
+        if let Some(partitioned) = self.partitioned(&database_name, &collection_name).await? {
+            return partitioned.find_by_id(&id).await?.as_ref().map(StoredDocument::from_document).transpose();
+        }
+        let collection = self.collection(database_name, collection_name).await?;
+        collection.find_stored_by_id(&id).await
+    }
+
     /// Update a document by ID
     pub async fn update_document_by_id(
         &self,
@@ -168,6 +561,11 @@ impl DatabaseEngine {
         id: DocumentId,
         document: Document,
     ) -> Result<Option<Document>> {
+        self.authorize(Action::Update, Some(&database_name))?;
+        deadline::checkpoint()?;
+        if let Some(partitioned) = self.partitioned(&database_name, &collection_name).await? {
+            return partitioned.update_by_id(&id, document).await;
+        }
         let collection = self.collection(database_name, collection_name).await?;
         collection.update_by_id(&id, document).await
     }
@@ -179,31 +577,157 @@ impl DatabaseEngine {
         collection_name: CollectionName,
         id: DocumentId,
     ) -> Result<bool> {
+        self.authorize(Action::Delete, Some(&database_name))?;
+        deadline::checkpoint()?;
+        if let Some(partitioned) = self.partitioned(&database_name, &collection_name).await? {
+            return partitioned.delete_by_id(&id).await;
+        }
         let collection = self.collection(database_name, collection_name).await?;
         collection.delete_by_id(&id).await
     }
 
+    /// Open a backup session over a consistent snapshot of a database
+    pub async fn open_backup(&self, database_name: DatabaseName, options: BackupOptions) -> Result<BackupSession> {
+        self.authorize(Action::Backup, None)?;
+        let database = self.database(database_name).await?;
+        self.backups.open_session(database, options).await
+    }
+
+    /// Open backup sessions, for reading chunks and closing them
+    pub fn backups(&self) -> &Arc<BackupManager> {
+        &self.backups
+    }
+
+    /// Oplog entries of a database after `position`, for incremental backups
+    pub async fn oplog_after(&self, database_name: DatabaseName, position: u64, limit: usize) -> Result<OplogChunk> {
+        self.authorize(Action::Backup, None)?;
+        let database = self.database(database_name).await?;
+        backup::oplog_chunk(&database, position, limit)
+    }
+
+    /// Active data key and progress of the latest rotation; `None` when encryption at rest is off
+    pub fn encryption_status(&self) -> Result<Option<KeyRotationStatus>> {
+        self.authorize(Action::ServerStatus, None)?;
+        Ok(self.encryption.as_ref().map(|_| self.key_rotation.lock().clone()))
+    }
+
+    /// Activate a new data key and reseal existing pages under it in the background
+    pub async fn rotate_encryption_key(&self) -> Result<u32> {
+        self.authorize(Action::ManageEncryption, None)?;
+        self.start_key_rotation().await
+    }
+
+    /// Key rotation without an access check, for the rotation schedule
+    ///
+    /// Only databases open in this process are resealed; pages of the others
+    /// stay readable under their old key until a later rotation reaches them.
+    pub(crate) async fn start_key_rotation(&self) -> Result<u32> {
+        let Some(cipher) = self.encryption.clone() else {
+            return Err(LargetableError::Config("Encryption at rest is not enabled".to_string()));
+        };
+        let key_id = {
+            let mut status = self.key_rotation.lock();
+            if status.in_progress {
+                return Err(LargetableError::Query("A key rotation is already in progress".to_string()));
+            }
+            let key_id = cipher.rotate()?;
+            *status = KeyRotationStatus {
+                active_key_id: key_id,
+                in_progress: true,
+                last_completed_at: status.last_completed_at,
+                ..KeyRotationStatus::default()
+            };
+            key_id
+        };
+
+        let engines: Vec<_> = self.databases.read().await.values().map(|database| database.storage_engine().clone()).collect();
+        let (status, batch_size) = (self.key_rotation.clone(), self.rotation_batch_size);
+        tokio::spawn(async move {
+            let mut outcome = Ok(());
+            for engine in engines {
+                match encryption::rotate_storage(engine.as_ref(), batch_size).await {
+                    Ok(resealed) => status.lock().pages_resealed += resealed,
+                    Err(e) => {
+                        outcome = Err(e);
+                        break;
+                    }
+                }
+            }
+
+            let mut status = status.lock();
+            status.in_progress = false;
+            match outcome {
+                Ok(()) => {
+                    info!("Resealed {} pages under data key {}", status.pages_resealed, key_id);
+                    status.last_completed_at = Some(chrono::Utc::now());
+                }
+                Err(e) => {
+                    error!("Key rotation to data key {} failed: {}", key_id, e);
+                    status.last_error = Some(e.to_string());
+                }
+            }
+        });
+        Ok(key_id)
+    }
+
     /// Get database statistics
     pub async fn get_stats(&self) -> Result<DatabaseStats> {
-        let databases = self.databases.read().await;
-        let mut total_collections = 0;
-        let mut total_documents = 0;
-        
-        for database in databases.values() {
-            let collections = database.list_collections().await?;
-            total_collections += collections.len();
+        self.authorize(Action::ServerStatus, None)?;
+        deadline::bounded(&self.cancellations, async {
+            let databases = self.databases.read().await;
+            let mut total_collections = 0;
+            let mut total_documents = 0;
             
-            for collection_name in collections {
-                let collection = database.collection(collection_name).await?;
-                total_documents += collection.count().await?;
+            for database in databases.values() {
+                let collections = database.list_collections().await?;
+                total_collections += collections.len();
+                
+                for collection_name in collections {
+                    deadline::checkpoint()?;
+                    let collection = database.collection(collection_name).await?;
+                    total_documents += collection.count().await?;
+                }
+                for collection in database.partitioned_collections().await {
+                    total_collections += 1;
+                    total_documents += collection.count() as usize;
+                }
             }
-        }
-        
-        Ok(DatabaseStats {
-            total_databases: databases.len(),
-            total_collections,
-            total_documents,
+            
+            Ok(DatabaseStats {
+                total_databases: databases.len(),
+                total_collections,
+                total_documents,
+                query_cache: self.query_cache.stats(),
+                cancellations: self.cancellations.stats(),
+                document_cache: self.document_cache.stats(),
+                memory: self.memory.stats(),
+            })
         })
+        .await
+    }
+
+    /// Users and their roles, without credentials
+    pub fn list_users(&self) -> Result<Vec<UserInfo>> {
+        self.authorize(Action::ManageUsers, None)?;
+        Ok(self.access.users().list_users())
+    }
+
+    /// Create a user with a SCRAM-SHA-256 password
+    pub fn create_user(&self, name: &str, password: &str, roles: Vec<RoleGrant>) -> Result<()> {
+        self.authorize(Action::ManageUsers, None)?;
+        self.access.users().create_user(name, password, roles)
+    }
+
+    /// Replace a user's roles
+    pub fn set_user_roles(&self, name: &str, roles: Vec<RoleGrant>) -> Result<bool> {
+        self.authorize(Action::ManageUsers, None)?;
+        self.access.users().set_roles(name, roles)
+    }
+
+    /// Drop a user; its open sessions lose every role
+    pub fn drop_user(&self, name: &str) -> Result<bool> {
+        self.authorize(Action::ManageUsers, None)?;
+        self.access.users().drop_user(name)
     }
 
     // Enterprise-grade features
@@ -261,9 +785,19 @@ impl DatabaseEngine {
 }
 
 /// Database statistics
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct DatabaseStats {
     pub total_databases: usize,
     pub total_collections: usize,
     pub total_documents: usize,
+    pub query_cache: QueryCacheStats,
+    /// Absent from servers that predate deadlines
+    #[serde(default)]
+    pub cancellations: CancellationStats,
+    /// Absent from servers that predate the document cache
+    #[serde(default)]
+    pub document_cache: DocumentCacheStats,
+    /// Absent from servers that predate memory limits
+    #[serde(default)]
+    pub memory: MemoryStats,
 }
@@ -27,12 +27,21 @@ pub enum LargetableError {
     #[error("Authentication error: {0}")]
     Auth(String),
     
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    
     #[error("Replication error: {0}")]
     Replication(String),
     
+    #[error("Snapshot unavailable: {0}")]
+    SnapshotUnavailable(String),
+    
     #[error("Sharding error: {0}")]
     Sharding(String),
     
+    #[error("Document validation failed: {0}")]
+    Validation(String),
+    
     #[error("Configuration error: {0}")]
     Config(String),
     
@@ -42,6 +51,12 @@ pub enum LargetableError {
     #[error("Concurrent access violation: {0}")]
     ConcurrencyViolation(String),
     
+    #[error("Deadline exceeded: {0}")]
+    DeadlineExceeded(String),
+    
+    #[error("Operation cancelled: {0}")]
+    Cancelled(String),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
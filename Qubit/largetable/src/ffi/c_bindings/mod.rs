@@ -5,3 +5,551 @@
 // ===========================================
 
 //! C bindings
+//!
+//! A C ABI for embedding Largetable in-process. `include/largetable.h` is
+//! generated from this module by cbindgen when the crate is built.
+//!
+//! - Every call that can fail returns an `LtStatus`, `LT_STATUS_OK` being 0.
+//!   The message of the last failure on the calling thread is available from
+//!   `largetable_last_error` until the next failure on that thread.
+//! - Documents and queries are UTF-8 JSON in the same shape as the HTTP API:
+//!   documents carry `_id`, `_version`, `_created_at` and `_updated_at`, and
+//!   queries are `{"filter", "limit", "skip", "projection", "bypass_cache", "read_at"}`.
+//! - Strings passed in are borrowed for the duration of the call. Strings
+//!   handed out through out-parameters belong to the caller, who releases
+//!   them with `largetable_string_free`; out-parameters are left untouched
+//!   when a call fails.
+//! - A database handle may be used from several threads at once and is
+//!   released with `largetable_close`. A cursor holds its results, so it
+//!   stays valid after the database is closed, but it must not be used from
+//!   two threads at once; it is released with `largetable_cursor_close`.
+//! - Panics are caught at the boundary and reported as
+//!   `LT_STATUS_INTERNAL`. Builds with `panic = "abort"`, like the release
+//!   profile, abort instead.
+
+use crate::document::DocumentUtils;
+use crate::engine::DatabaseEngine;
+use crate::query::Query;
+use crate::{Document, DocumentId, LargetableError, StorageEngine};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+/// Result of a call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LtStatus {
+    Ok = 0,
+    /// No document has the given id
+    NotFound = 1,
+    /// The cursor has no more documents
+    Done = 2,
+    /// A null pointer, invalid UTF-8, malformed JSON or an unknown option
+    InvalidArgument = 3,
+    /// A document could not be converted to or from JSON
+    Serialization = 4,
+    /// A document was rejected by its collection's validators
+    Validation = 5,
+    /// A query or index failed
+    Query = 6,
+    PermissionDenied = 7,
+    /// The operation ran past its deadline or was cancelled
+    DeadlineExceeded = 8,
+    ResourceExhausted = 9,
+    /// The storage engine, or a replica or shard it depends on, failed
+    Storage = 10,
+    /// A bug in Largetable; the message says where
+    Internal = 11,
+}
+
+/// An embedded database engine
+pub struct LtDatabase {
+    // Dropped before the runtime its background tasks run on
+    engine: Arc<DatabaseEngine>,
+    runtime: tokio::runtime::Runtime,
+}
+
+/// Results of a query, read one document at a time
+pub struct LtCursor {
+    documents: std::vec::IntoIter<(DocumentId, Document)>,
+    total_count: usize,
+}
+
+/// Query body, as accepted by the HTTP API
+#[derive(Debug, Default, Deserialize)]
+struct QueryRequest {
+    filter: Option<serde_json::Value>,
+    limit: Option<usize>,
+    skip: Option<usize>,
+    projection: Option<Vec<String>>,
+    #[serde(default)]
+    bypass_cache: bool,
+    /// Cluster timestamp to read at, in microseconds since the Unix epoch
+    read_at: Option<i64>,
+}
+
+enum FfiError {
+    InvalidArgument(String),
+    Engine(LargetableError),
+}
+
+impl From<LargetableError> for FfiError {
+    fn from(error: LargetableError) -> Self {
+        FfiError::Engine(error)
+    }
+}
+
+type FfiResult<T> = std::result::Result<T, FfiError>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn status_for(error: &LargetableError) -> LtStatus {
+    match error {
+        LargetableError::Serialization(_) | LargetableError::Json(_) | LargetableError::Bson(_) => LtStatus::Serialization,
+        LargetableError::Validation(_) => LtStatus::Validation,
+        LargetableError::Query(_) | LargetableError::Index(_) | LargetableError::SnapshotUnavailable(_) => LtStatus::Query,
+        LargetableError::Auth(_) | LargetableError::PermissionDenied(_) => LtStatus::PermissionDenied,
+        LargetableError::DeadlineExceeded(_) | LargetableError::Cancelled(_) => LtStatus::DeadlineExceeded,
+        LargetableError::ResourceExhausted(_) => LtStatus::ResourceExhausted,
+        LargetableError::Config(_) => LtStatus::InvalidArgument,
+        LargetableError::Storage(_)
+        | LargetableError::Io(_)
+        | LargetableError::Network(_)
+        | LargetableError::Replication(_)
+        | LargetableError::Sharding(_)
+        | LargetableError::ConcurrencyViolation(_) => LtStatus::Storage,
+    }
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run the body of an exported function, recording why it failed
+fn call(body: impl FnOnce() -> FfiResult<LtStatus>) -> LtStatus {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(status)) => status,
+        Ok(Err(FfiError::InvalidArgument(message))) => {
+            set_last_error(message);
+            LtStatus::InvalidArgument
+        }
+        Ok(Err(FfiError::Engine(error))) => {
+            let status = status_for(&error);
+            set_last_error(error.to_string());
+            status
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("Panic in Largetable: {}", message));
+            LtStatus::Internal
+        }
+    }
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> FfiResult<&'a str> {
+    if ptr.is_null() {
+        return Err(FfiError::InvalidArgument(format!("{} is null", name)));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| FfiError::InvalidArgument(format!("{} is not valid UTF-8", name)))
+}
+
+unsafe fn json_arg(ptr: *const c_char, name: &str) -> FfiResult<serde_json::Value> {
+    serde_json::from_str(str_arg(ptr, name)?)
+        .map_err(|e| FfiError::InvalidArgument(format!("{} is not valid JSON: {}", name, e)))
+}
+
+unsafe fn id_arg(ptr: *const c_char) -> FfiResult<DocumentId> {
+    uuid::Uuid::parse_str(str_arg(ptr, "id")?)
+        .map_err(|e| FfiError::InvalidArgument(format!("Invalid document ID: {}", e)))
+}
+
+unsafe fn handle_arg<'a, T>(ptr: *const T, name: &str) -> FfiResult<&'a T> {
+    ptr.as_ref().ok_or_else(|| FfiError::InvalidArgument(format!("{} is null", name)))
+}
+
+fn out_arg<T>(out: *mut T, name: &str) -> FfiResult<*mut T> {
+    if out.is_null() {
+        return Err(FfiError::InvalidArgument(format!("{} is null", name)));
+    }
+    Ok(out)
+}
+
+fn document_json(doc: &Document) -> FfiResult<*mut c_char> {
+    let json = DocumentUtils::to_json(doc)?.to_string();
+    // JSON escapes NUL, so the conversion cannot fail
+    Ok(CString::new(json).unwrap_or_default().into_raw())
+}
+
+/// Version of the library, e.g. "1.0.0"; a static string the caller must not free
+#[no_mangle]
+pub extern "C" fn largetable_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Message of the last failed call on this thread, or NULL if none failed
+///
+/// The string belongs to the library and is valid until the next failure on
+/// this thread.
+#[no_mangle]
+pub extern "C" fn largetable_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
+
+/// Release a string returned by the library; NULL is ignored
+///
+/// # Safety
+/// `string` must be NULL or a string handed out through an out-parameter of
+/// this library, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Open an embedded engine on `storage_engine` ("lsm", "btree", "columnar" or
+/// "graph"; NULL for "lsm")
+///
+/// # Safety
+/// `storage_engine` must be NULL or a NUL-terminated string, and `out` a
+/// valid pointer to write the handle to.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_open(storage_engine: *const c_char, out: *mut *mut LtDatabase) -> LtStatus {
+    call(|| {
+        let out = out_arg(out, "out")?;
+        let storage_engine = if storage_engine.is_null() {
+            StorageEngine::Lsm
+        } else {
+            let name = str_arg(storage_engine, "storage_engine")?;
+            serde_json::from_value(serde_json::Value::String(name.to_string()))
+                .map_err(|_| FfiError::InvalidArgument(format!("Unknown storage engine '{}'", name)))?
+        };
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .thread_name("largetable-ffi")
+            .enable_all()
+            .build()
+            .map_err(LargetableError::Io)?;
+        let engine = runtime.block_on(DatabaseEngine::with_default_storage_engine(storage_engine))?;
+        *out = Box::into_raw(Box::new(LtDatabase {
+            engine: Arc::new(engine),
+            runtime,
+        }));
+        Ok(LtStatus::Ok)
+    })
+}
+
+/// Close an engine opened by `largetable_open`; NULL is ignored
+///
+/// # Safety
+/// `db` must be NULL or a handle from `largetable_open` that is not used
+/// afterwards, by this or any other thread.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_close(db: *mut LtDatabase) {
+    if !db.is_null() {
+        drop(Box::from_raw(db));
+    }
+}
+
+/// Insert a JSON document, writing its id to `id_out` unless it is NULL
+///
+/// # Safety
+/// `db` must be a live handle, the strings NUL-terminated, and `id_out` NULL
+/// or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_insert(
+    db: *const LtDatabase,
+    database: *const c_char,
+    collection: *const c_char,
+    document: *const c_char,
+    id_out: *mut *mut c_char,
+) -> LtStatus {
+    call(|| {
+        let db = handle_arg(db, "db")?;
+        let database = str_arg(database, "database")?.to_string();
+        let collection = str_arg(collection, "collection")?.to_string();
+        let document = DocumentUtils::from_json(json_arg(document, "document")?)?;
+
+        let id = db.runtime.block_on(db.engine.insert_document(database, collection, document))?;
+        if !id_out.is_null() {
+            *id_out = CString::new(id.to_string()).unwrap_or_default().into_raw();
+        }
+        Ok(LtStatus::Ok)
+    })
+}
+
+/// Find a document by id, writing it as JSON to `document_out`
+///
+/// Returns `LT_STATUS_NOT_FOUND` if there is no such document.
+///
+/// # Safety
+/// `db` must be a live handle, the strings NUL-terminated, and
+/// `document_out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_find_by_id(
+    db: *const LtDatabase,
+    database: *const c_char,
+    collection: *const c_char,
+    id: *const c_char,
+    document_out: *mut *mut c_char,
+) -> LtStatus {
+    call(|| {
+        let db = handle_arg(db, "db")?;
+        let database = str_arg(database, "database")?.to_string();
+        let collection = str_arg(collection, "collection")?.to_string();
+        let id = id_arg(id)?;
+        let document_out = out_arg(document_out, "document_out")?;
+
+        match db.runtime.block_on(db.engine.find_document_by_id(database, collection, id))? {
+            Some(doc) => {
+                *document_out = document_json(&doc)?;
+                Ok(LtStatus::Ok)
+            }
+            None => Ok(LtStatus::NotFound),
+        }
+    })
+}
+
+/// Replace the document with `id` by a JSON document
+///
+/// Returns `LT_STATUS_NOT_FOUND` if there is no such document.
+///
+/// # Safety
+/// `db` must be a live handle and the strings NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_update_by_id(
+    db: *const LtDatabase,
+    database: *const c_char,
+    collection: *const c_char,
+    id: *const c_char,
+    document: *const c_char,
+) -> LtStatus {
+    call(|| {
+        let db = handle_arg(db, "db")?;
+        let database = str_arg(database, "database")?.to_string();
+        let collection = str_arg(collection, "collection")?.to_string();
+        let id = id_arg(id)?;
+        let document = DocumentUtils::from_json(json_arg(document, "document")?)?;
+
+        match db.runtime.block_on(db.engine.update_document_by_id(database, collection, id, document))? {
+            Some(_) => Ok(LtStatus::Ok),
+            None => Ok(LtStatus::NotFound),
+        }
+    })
+}
+
+/// Delete the document with `id`
+///
+/// Returns `LT_STATUS_NOT_FOUND` if there is no such document.
+///
+/// # Safety
+/// `db` must be a live handle and the strings NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_delete_by_id(
+    db: *const LtDatabase,
+    database: *const c_char,
+    collection: *const c_char,
+    id: *const c_char,
+) -> LtStatus {
+    call(|| {
+        let db = handle_arg(db, "db")?;
+        let database = str_arg(database, "database")?.to_string();
+        let collection = str_arg(collection, "collection")?.to_string();
+        let id = id_arg(id)?;
+
+        if db.runtime.block_on(db.engine.delete_document_by_id(database, collection, id))? {
+            Ok(LtStatus::Ok)
+        } else {
+            Ok(LtStatus::NotFound)
+        }
+    })
+}
+
+/// Run a JSON query, writing a cursor over its results to `cursor_out`
+///
+/// A NULL query matches every document. The query runs to completion before
+/// this returns, so iterating the cursor does not touch the database.
+///
+/// # Safety
+/// `db` must be a live handle, the strings NUL-terminated or, for `query`,
+/// NULL, and `cursor_out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_query(
+    db: *const LtDatabase,
+    database: *const c_char,
+    collection: *const c_char,
+    query: *const c_char,
+    cursor_out: *mut *mut LtCursor,
+) -> LtStatus {
+    call(|| {
+        let db = handle_arg(db, "db")?;
+        let database = str_arg(database, "database")?.to_string();
+        let collection = str_arg(collection, "collection")?.to_string();
+        let request: QueryRequest = if query.is_null() {
+            QueryRequest::default()
+        } else {
+            serde_json::from_value(json_arg(query, "query")?)
+                .map_err(|e| FfiError::InvalidArgument(format!("Invalid query: {}", e)))?
+        };
+        let cursor_out = out_arg(cursor_out, "cursor_out")?;
+
+        let query = Query {
+            filter: request.filter,
+            limit: request.limit,
+            skip: request.skip,
+            projection: request.projection,
+            bypass_cache: request.bypass_cache,
+            read_at: request.read_at,
+            ..Query::new()
+        };
+        let result = db.runtime.block_on(db.engine.query(database, collection, query))?;
+        *cursor_out = Box::into_raw(Box::new(LtCursor {
+            total_count: result.total_count,
+            documents: result.documents.into_iter(),
+        }));
+        Ok(LtStatus::Ok)
+    })
+}
+
+/// Write the next document of a cursor as JSON to `document_out`
+///
+/// Returns `LT_STATUS_DONE`, leaving `document_out` untouched, once every
+/// document has been read.
+///
+/// # Safety
+/// `cursor` must be a live cursor not used by another thread, and
+/// `document_out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_cursor_next(cursor: *mut LtCursor, document_out: *mut *mut c_char) -> LtStatus {
+    call(|| {
+        let cursor = cursor.as_mut().ok_or_else(|| FfiError::InvalidArgument("cursor is null".to_string()))?;
+        let document_out = out_arg(document_out, "document_out")?;
+        match cursor.documents.next() {
+            Some((_, doc)) => {
+                *document_out = document_json(&doc)?;
+                Ok(LtStatus::Ok)
+            }
+            None => Ok(LtStatus::Done),
+        }
+    })
+}
+
+/// Number of documents matching the query, before its skip and limit
+///
+/// # Safety
+/// `cursor` must be NULL, which counts 0, or a live cursor.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_cursor_total_count(cursor: *const LtCursor) -> usize {
+    cursor.as_ref().map_or(0, |cursor| cursor.total_count)
+}
+
+/// Release a cursor; NULL is ignored
+///
+/// # Safety
+/// `cursor` must be NULL or a cursor from `largetable_query` that is not
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn largetable_cursor_close(cursor: *mut LtCursor) {
+    if !cursor.is_null() {
+        drop(Box::from_raw(cursor));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    unsafe fn take(string: *mut c_char) -> String {
+        let owned = CStr::from_ptr(string).to_str().unwrap().to_string();
+        largetable_string_free(string);
+        owned
+    }
+
+    #[test]
+    fn test_crud_and_cursor_round_trip() {
+        unsafe {
+            let mut db = ptr::null_mut();
+            assert_eq!(largetable_open(c("graph").as_ptr(), &mut db), LtStatus::Ok);
+            let (database, collection) = (c("app"), c("users"));
+
+            let mut id = ptr::null_mut();
+            let status = largetable_insert(db, database.as_ptr(), collection.as_ptr(), c(r#"{"name":"ada","age":36}"#).as_ptr(), &mut id);
+            assert_eq!(status, LtStatus::Ok);
+            let id = c(&take(id));
+            largetable_insert(db, database.as_ptr(), collection.as_ptr(), c(r#"{"name":"alan","age":41}"#).as_ptr(), ptr::null_mut());
+
+            let mut found = ptr::null_mut();
+            assert_eq!(largetable_find_by_id(db, database.as_ptr(), collection.as_ptr(), id.as_ptr(), &mut found), LtStatus::Ok);
+            let found: serde_json::Value = serde_json::from_str(&take(found)).unwrap();
+            assert_eq!(found["name"], "ada");
+
+            let status = largetable_update_by_id(db, database.as_ptr(), collection.as_ptr(), id.as_ptr(), c(r#"{"name":"ada","age":37}"#).as_ptr());
+            assert_eq!(status, LtStatus::Ok);
+
+            let mut cursor = ptr::null_mut();
+            let query = c(r#"{"filter":{"age":{"$gt":36}}}"#);
+            assert_eq!(largetable_query(db, database.as_ptr(), collection.as_ptr(), query.as_ptr(), &mut cursor), LtStatus::Ok);
+            assert_eq!(largetable_cursor_total_count(cursor), 2);
+            let mut names = Vec::new();
+            let mut document = ptr::null_mut();
+            while largetable_cursor_next(cursor, &mut document) == LtStatus::Ok {
+                let json: serde_json::Value = serde_json::from_str(&take(document)).unwrap();
+                names.push(json["name"].as_str().unwrap().to_string());
+            }
+            names.sort();
+            assert_eq!(names, ["ada", "alan"]);
+            assert_eq!(largetable_cursor_next(cursor, &mut document), LtStatus::Done);
+
+            // The cursor holds its results past the engine
+            largetable_close(db);
+            assert_eq!(largetable_cursor_next(cursor, &mut document), LtStatus::Done);
+            largetable_cursor_close(cursor);
+        }
+    }
+
+    #[test]
+    fn test_failures_report_a_status_and_message() {
+        unsafe {
+            let mut db = ptr::null_mut();
+            assert_eq!(largetable_open(c("paper").as_ptr(), &mut db), LtStatus::InvalidArgument);
+            assert!(db.is_null());
+            let message = CStr::from_ptr(largetable_last_error()).to_str().unwrap();
+            assert!(message.contains("paper"), "{}", message);
+
+            assert_eq!(largetable_open(c("graph").as_ptr(), &mut db), LtStatus::Ok);
+            let (database, collection) = (c("app"), c("users"));
+            let mut found = ptr::null_mut();
+
+            let status = largetable_find_by_id(db, database.as_ptr(), collection.as_ptr(), c("not-an-id").as_ptr(), &mut found);
+            assert_eq!(status, LtStatus::InvalidArgument);
+            let missing = c(&uuid::Uuid::now_v7().to_string());
+            let status = largetable_find_by_id(db, database.as_ptr(), collection.as_ptr(), missing.as_ptr(), &mut found);
+            assert_eq!(status, LtStatus::NotFound);
+            assert!(found.is_null());
+            assert_eq!(largetable_delete_by_id(db, database.as_ptr(), collection.as_ptr(), missing.as_ptr()), LtStatus::NotFound);
+
+            let status = largetable_insert(db, database.as_ptr(), ptr::null(), c("{}").as_ptr(), ptr::null_mut());
+            assert_eq!(status, LtStatus::InvalidArgument);
+            let status = largetable_insert(db, database.as_ptr(), collection.as_ptr(), c("{oops").as_ptr(), ptr::null_mut());
+            assert_eq!(status, LtStatus::InvalidArgument);
+            largetable_close(db);
+        }
+    }
+}
@@ -0,0 +1,12 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Foreign function interfaces for embedding Largetable
+
+pub mod c_bindings;
+pub mod java;
+pub mod nodejs;
+pub mod python;
@@ -0,0 +1,436 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Background index builds
+//!
+//! A build scans the collection in batches while writes keep flowing. Writes
+//! that land during the build are captured as side-writes and replayed onto
+//! the new index once the scan is done; the last few are drained under the
+//! index map's write lock, so the finished index is swapped in atomically
+//! with no write missed.
+//!
+//! Each scan batch is reserved from the memory budget of the operation that
+//! started the build. While other operations hold the global limit, the
+//! build waits for memory instead of failing.
+
+use crate::{Result, DocumentId, Document, IndexType};
+use crate::engine::memory_limits::{document_size, MemoryConsumer, MemoryReservation};
+use crate::index::Index;
+use crate::storage::StorageEngine;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Documents indexed per scan batch before yielding to other tasks
+pub const DEFAULT_BUILD_BATCH_SIZE: usize = 1_000;
+
+/// Side-writes left over after which the build stops replaying concurrently
+/// and finishes under the write lock
+const COMMIT_THRESHOLD: usize = 64;
+
+/// How long a build waits for memory when the global limit is reached
+const MEMORY_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Phase of a background index build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexBuildPhase {
+    Scanning,
+    ApplyingSideWrites,
+    Committing,
+    Ready,
+    Aborted,
+    Failed,
+}
+
+impl IndexBuildPhase {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, IndexBuildPhase::Ready | IndexBuildPhase::Aborted | IndexBuildPhase::Failed)
+    }
+}
+
+/// Progress of a background index build, as reported by the admin API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexBuildStatus {
+    pub field: String,
+    pub index_type: IndexType,
+    pub phase: IndexBuildPhase,
+    pub documents_scanned: u64,
+    pub side_writes_captured: u64,
+    pub side_writes_applied: u64,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub error: Option<String>,
+}
+
+/// Write that happened while an index was being built
+#[derive(Debug, Clone)]
+pub enum SideWrite {
+    Upsert(DocumentId, Document),
+    Remove(DocumentId),
+}
+
+/// Shared state of one build, visible to writers and the admin API
+pub struct IndexBuild {
+    status: Mutex<IndexBuildStatus>,
+    side_writes: Mutex<VecDeque<SideWrite>>,
+    aborted: AtomicBool,
+}
+
+impl IndexBuild {
+    pub(crate) fn new(field: String, index_type: IndexType) -> Self {
+        Self {
+            status: Mutex::new(IndexBuildStatus {
+                field,
+                index_type,
+                phase: IndexBuildPhase::Scanning,
+                documents_scanned: 0,
+                side_writes_captured: 0,
+                side_writes_applied: 0,
+                started_at: chrono::Utc::now(),
+                finished_at: None,
+                error: None,
+            }),
+            side_writes: Mutex::new(VecDeque::new()),
+            aborted: AtomicBool::new(false),
+        }
+    }
+
+    pub fn status(&self) -> IndexBuildStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.status.lock().unwrap().phase.is_finished()
+    }
+
+    /// Ask the build to stop; it is dropped at its next checkpoint
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+    }
+
+    /// Record a write made while the build is running
+    pub(crate) fn capture(&self, write: SideWrite) {
+        if self.is_finished() {
+            return;
+        }
+        self.side_writes.lock().unwrap().push_back(write);
+        self.status.lock().unwrap().side_writes_captured += 1;
+    }
+
+    fn set_phase(&self, phase: IndexBuildPhase) {
+        let mut status = self.status.lock().unwrap();
+        status.phase = phase;
+        if phase.is_finished() {
+            status.finished_at = Some(chrono::Utc::now());
+        }
+    }
+
+    fn fail(&self, error: String) {
+        self.status.lock().unwrap().error = Some(error);
+        self.set_phase(IndexBuildPhase::Failed);
+        self.side_writes.lock().unwrap().clear();
+    }
+
+    fn take_side_writes(&self, limit: usize) -> Vec<SideWrite> {
+        let mut side_writes = self.side_writes.lock().unwrap();
+        let count = limit.min(side_writes.len());
+        side_writes.drain(..count).collect()
+    }
+
+    fn pending_side_writes(&self) -> usize {
+        self.side_writes.lock().unwrap().len()
+    }
+
+    /// Replay side-writes as remove-then-insert so they are idempotent no
+    /// matter which version of the document the scan saw
+    async fn apply(&self, index: &(dyn Index + Send + Sync), writes: Vec<SideWrite>) -> Result<()> {
+        let applied = writes.len() as u64;
+        for write in writes {
+            match write {
+                SideWrite::Upsert(id, doc) => {
+                    index.remove(&id).await?;
+                    index.insert(id, &doc).await?;
+                }
+                SideWrite::Remove(id) => index.remove(&id).await?,
+            }
+        }
+        self.status.lock().unwrap().side_writes_applied += applied;
+        Ok(())
+    }
+}
+
+/// Index map shared between the index manager and build tasks
+pub(crate) type IndexMap = Arc<RwLock<HashMap<String, Box<dyn Index + Send + Sync>>>>;
+
+/// Run a build to completion and swap the finished index into `indexes`
+pub(crate) async fn run_build(
+    build: Arc<IndexBuild>,
+    index: Box<dyn Index + Send + Sync>,
+    indexes: IndexMap,
+    source: Arc<dyn StorageEngine>,
+    batch_size: usize,
+) {
+    let field = build.status().field;
+    match build_index(&build, index, &indexes, source.as_ref(), batch_size).await {
+        Ok(true) => {
+            let status = build.status();
+            info!(
+                "Index build on '{}' finished: {} documents scanned, {} side-writes applied",
+                field, status.documents_scanned, status.side_writes_applied
+            );
+        }
+        Ok(false) => {
+            build.set_phase(IndexBuildPhase::Aborted);
+            build.side_writes.lock().unwrap().clear();
+            info!("Index build on '{}' aborted", field);
+        }
+        Err(e) => {
+            warn!("Index build on '{}' failed: {}", field, e);
+            build.fail(e.to_string());
+        }
+    }
+}
+
+/// Returns `Ok(false)` when the build was aborted
+async fn build_index(
+    build: &IndexBuild,
+    index: Box<dyn Index + Send + Sync>,
+    indexes: &IndexMap,
+    source: &dyn StorageEngine,
+    batch_size: usize,
+) -> Result<bool> {
+    let batch_size = batch_size.max(1);
+    let mut memory = MemoryReservation::current(MemoryConsumer::IndexBuild);
+
+    // Scan in batches; `scan` starts at the given ID inclusively, so every
+    // batch after the first repeats the previous batch's last document
+    let mut last: Option<DocumentId> = None;
+    loop {
+        if build.aborted.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+
+        let limit = if last.is_some() { batch_size + 1 } else { batch_size };
+        let batch = source.scan(last, limit).await?;
+        let fetched = batch.len();
+        let batch_bytes = batch.iter().map(|(_, doc)| document_size(doc)).sum();
+        if !reserve_batch(build, &mut memory, batch_bytes).await? {
+            return Ok(false);
+        }
+
+        let mut scanned = 0;
+        for (id, doc) in batch {
+            if Some(id) == last {
+                continue;
+            }
+            index.insert(id, &doc).await?;
+            last = Some(id);
+            scanned += 1;
+        }
+        build.status.lock().unwrap().documents_scanned += scanned;
+        memory.shrink(batch_bytes);
+
+        if fetched < limit {
+            break;
+        }
+        tokio::task::yield_now().await;
+    }
+
+    // Catch up on side-writes while writers keep going
+    build.set_phase(IndexBuildPhase::ApplyingSideWrites);
+    while build.pending_side_writes() > COMMIT_THRESHOLD {
+        if build.aborted.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        let writes = build.take_side_writes(batch_size);
+        build.apply(index.as_ref(), writes).await?;
+        tokio::task::yield_now().await;
+    }
+
+    // Writers capture side-writes while holding the read lock, so once the
+    // write lock is held the queue is final
+    build.set_phase(IndexBuildPhase::Committing);
+    let mut indexes = indexes.write().await;
+    if build.aborted.load(Ordering::SeqCst) {
+        return Ok(false);
+    }
+    let writes = build.take_side_writes(usize::MAX);
+    build.apply(index.as_ref(), writes).await?;
+
+    indexes.insert(build.status().field, index);
+    build.set_phase(IndexBuildPhase::Ready);
+    Ok(true)
+}
+
+/// Reserve memory for a scan batch, waiting while other operations hold the
+/// global limit; returns false when the build was aborted meanwhile
+async fn reserve_batch(build: &IndexBuild, memory: &mut MemoryReservation, bytes: usize) -> Result<bool> {
+    while !memory.try_grow(bytes) {
+        if !memory.fits(bytes) {
+            return Err(memory.exhausted());
+        }
+        if build.aborted.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        tokio::time::sleep(MEMORY_RETRY_INTERVAL).await;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{IndexManager, IndexQuery, IndexStats};
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// Storage engine over a sorted map, matching the inclusive `scan` of the real engines
+    #[derive(Default)]
+    struct MemoryEngine {
+        docs: RwLock<BTreeMap<DocumentId, Document>>,
+    }
+
+    #[async_trait::async_trait]
+    impl StorageEngine for MemoryEngine {
+        async fn get(&self, id: &DocumentId) -> Result<Option<Document>> {
+            Ok(self.docs.read().await.get(id).cloned())
+        }
+
+        async fn put(&self, id: DocumentId, doc: Document) -> Result<()> {
+            self.docs.write().await.insert(id, doc);
+            Ok(())
+        }
+
+        async fn delete(&self, id: &DocumentId) -> Result<bool> {
+            Ok(self.docs.write().await.remove(id).is_some())
+        }
+
+        async fn scan(&self, start: Option<DocumentId>, limit: usize) -> Result<Vec<(DocumentId, Document)>> {
+            let docs = self.docs.read().await;
+            let iter: Box<dyn Iterator<Item = (&DocumentId, &Document)>> = match start {
+                Some(start) => Box::new(docs.range(start..)),
+                None => Box::new(docs.iter()),
+            };
+            Ok(iter.take(limit).map(|(id, doc)| (*id, doc.clone())).collect())
+        }
+    }
+
+    /// Index that only tracks which documents it holds
+    #[derive(Default)]
+    struct IdSetIndex {
+        ids: RwLock<BTreeSet<DocumentId>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Index for IdSetIndex {
+        async fn insert(&self, id: DocumentId, _doc: &Document) -> Result<()> {
+            self.ids.write().await.insert(id);
+            Ok(())
+        }
+
+        async fn remove(&self, id: &DocumentId) -> Result<()> {
+            self.ids.write().await.remove(id);
+            Ok(())
+        }
+
+        async fn update(&self, id: DocumentId, _old_doc: &Document, new_doc: &Document) -> Result<()> {
+            self.insert(id, new_doc).await
+        }
+
+        async fn search(&self, _query: &IndexQuery) -> Result<Vec<DocumentId>> {
+            Ok(self.ids.read().await.iter().copied().collect())
+        }
+
+        async fn stats(&self) -> Result<IndexStats> {
+            Ok(IndexStats {
+                total_entries: self.ids.read().await.len(),
+                memory_usage: 0,
+                index_type: IndexType::Hash,
+            })
+        }
+
+        fn index_type(&self) -> IndexType {
+            IndexType::Hash
+        }
+    }
+
+    fn document(id: DocumentId) -> Document {
+        Document {
+            id,
+            fields: HashMap::new(),
+            version: 1,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    async fn seeded_engine(count: usize) -> (Arc<MemoryEngine>, Vec<DocumentId>) {
+        let engine = Arc::new(MemoryEngine::default());
+        let mut ids = Vec::new();
+        for _ in 0..count {
+            let id = uuid::Uuid::new_v4();
+            engine.put(id, document(id)).await.unwrap();
+            ids.push(id);
+        }
+        (engine, ids)
+    }
+
+    async fn wait_for(manager: &IndexManager, field: &str) -> IndexBuildStatus {
+        loop {
+            let status = manager.index_build_status(field).unwrap();
+            if status.phase.is_finished() {
+                return status;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_captures_concurrent_writes() {
+        let (engine, ids) = seeded_engine(500).await;
+        let manager = IndexManager::new("users".to_string());
+
+        manager
+            .start_build_with("email".to_string(), Box::new(IdSetIndex::default()), engine.clone(), 16)
+            .await
+            .unwrap();
+
+        // Keep writing while the build scans
+        let added = uuid::Uuid::new_v4();
+        engine.put(added, document(added)).await.unwrap();
+        manager.insert_document(added, &document(added)).await.unwrap();
+        engine.delete(&ids[0]).await.unwrap();
+        manager.remove_document(&ids[0]).await.unwrap();
+
+        let status = wait_for(&manager, "email").await;
+        assert_eq!(status.phase, IndexBuildPhase::Ready);
+        assert_eq!(status.side_writes_captured, 2);
+
+        let indexed = manager.search(&IndexQuery::Exact { field: "email".to_string(), value: crate::Value::Null }).await.unwrap();
+        assert_eq!(indexed.len(), 500);
+        assert!(indexed.contains(&added));
+        assert!(!indexed.contains(&ids[0]));
+    }
+
+    #[tokio::test]
+    async fn test_abort_drops_build() {
+        let (engine, _) = seeded_engine(2_000).await;
+        let manager = IndexManager::new("users".to_string());
+
+        manager
+            .start_build_with("email".to_string(), Box::new(IdSetIndex::default()), engine, 1)
+            .await
+            .unwrap();
+        assert!(manager.abort_index_build("email").unwrap());
+
+        let status = wait_for(&manager, "email").await;
+        assert_eq!(status.phase, IndexBuildPhase::Aborted);
+        assert!(manager.list_indexes().await.unwrap().is_empty());
+    }
+}
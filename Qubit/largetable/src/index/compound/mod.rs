@@ -5,3 +5,231 @@
 // ===========================================
 
 //! Compound indexes
+//!
+//! Keys are the tuple of the indexed fields' values in declaration order, so
+//! one index answers equality on any leading run of fields plus a range on
+//! the field after it. Missing fields are indexed as null.
+
+use crate::{Result, DocumentId, Document, LargetableError, IndexType};
+use crate::index::{Index, IndexKey, IndexQuery, IndexStats};
+use crate::document::DocumentUtils;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::debug;
+
+/// Name a compound index is registered under when the caller does not pick one
+pub fn index_name(fields: &[String]) -> String {
+    fields.join(",")
+}
+
+/// Ordered index over several fields
+pub struct CompoundIndex {
+    name: String,
+    fields: Vec<String>,
+    index: Arc<RwLock<CompoundEntries>>,
+}
+
+#[derive(Default)]
+struct CompoundEntries {
+    keys: BTreeMap<Vec<IndexKey>, Vec<DocumentId>>,
+    by_document: HashMap<DocumentId, Vec<IndexKey>>,
+}
+
+impl CompoundEntries {
+    fn remove(&mut self, id: &DocumentId) {
+        let Some(key) = self.by_document.remove(id) else {
+            return;
+        };
+        if let Some(ids) = self.keys.get_mut(&key) {
+            ids.retain(|existing| existing != id);
+            if ids.is_empty() {
+                self.keys.remove(&key);
+            }
+        }
+    }
+}
+
+impl CompoundIndex {
+    /// Create a new compound index registered as `name`
+    pub fn new(name: String, fields: Vec<String>) -> Self {
+        Self {
+            name,
+            fields,
+            index: Arc::new(RwLock::new(CompoundEntries::default())),
+        }
+    }
+
+    /// Indexed fields in key order
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    fn extract_key(&self, doc: &Document) -> Vec<IndexKey> {
+        self.fields
+            .iter()
+            .map(|field| DocumentUtils::get_field(doc, field).map_or(IndexKey::Null, IndexKey::from_value))
+            .collect()
+    }
+
+    fn search_prefix(
+        &self,
+        entries: &CompoundEntries,
+        equals: &[crate::Value],
+        min: Option<&crate::Value>,
+        max: Option<&crate::Value>,
+    ) -> Result<Vec<DocumentId>> {
+        let ranged = min.is_some() || max.is_some();
+        if equals.len() + usize::from(ranged) > self.fields.len() {
+            return Err(LargetableError::Index(format!(
+                "Compound index '{}' covers {} fields, query uses {}",
+                self.name,
+                self.fields.len(),
+                equals.len() + usize::from(ranged)
+            )));
+        }
+
+        let prefix: Vec<IndexKey> = equals.iter().map(IndexKey::from_value).collect();
+        let min = min.map(IndexKey::from_value);
+        let max = max.map(IndexKey::from_value);
+
+        // A shorter key sorts before every key it prefixes, so seeking to the
+        // prefix (plus the range start) lands on the first candidate
+        let mut start = prefix.clone();
+        start.extend(min.clone());
+
+        let mut results = Vec::new();
+        for (key, ids) in entries.keys.range((Bound::Included(start), Bound::Unbounded)) {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            if let Some(max) = &max {
+                if key[prefix.len()] > *max {
+                    break;
+                }
+            }
+            results.extend(ids.iter().cloned());
+        }
+        Ok(results)
+    }
+}
+
+#[async_trait::async_trait]
+impl Index for CompoundIndex {
+    async fn insert(&self, id: DocumentId, doc: &Document) -> Result<()> {
+        let key = self.extract_key(doc);
+        let mut index = self.index.write().await;
+        index.remove(&id);
+        index.keys.entry(key.clone()).or_default().push(id);
+        index.by_document.insert(id, key);
+        debug!("Inserted document {} into compound index '{}'", id, self.name);
+        Ok(())
+    }
+
+    async fn remove(&self, id: &DocumentId) -> Result<()> {
+        self.index.write().await.remove(id);
+        debug!("Removed document {} from compound index '{}'", id, self.name);
+        Ok(())
+    }
+
+    async fn update(&self, id: DocumentId, _old_doc: &Document, new_doc: &Document) -> Result<()> {
+        self.insert(id, new_doc).await
+    }
+
+    async fn search(&self, query: &IndexQuery) -> Result<Vec<DocumentId>> {
+        let index = self.index.read().await;
+        let results = match query {
+            IndexQuery::Prefix { index: name, equals, min, max } if name == &self.name => {
+                self.search_prefix(&index, equals, min.as_ref(), max.as_ref())?
+            }
+            // Single-field queries are served when they target the leading field
+            IndexQuery::Exact { field, value } if *field == self.fields[0] => {
+                self.search_prefix(&index, std::slice::from_ref(value), None, None)?
+            }
+            IndexQuery::Range { field, min, max } if *field == self.fields[0] => {
+                self.search_prefix(&index, &[], min.as_ref(), max.as_ref())?
+            }
+            _ => {
+                return Err(LargetableError::Index(format!(
+                    "Compound index '{}' does not support query type: {:?}",
+                    self.name, query
+                )));
+            }
+        };
+
+        debug!("Compound search on index '{}' returned {} results", self.name, results.len());
+        Ok(results)
+    }
+
+    async fn stats(&self) -> Result<IndexStats> {
+        let index = self.index.read().await;
+        let memory_usage = std::mem::size_of_val(&*index)
+            + index.keys.iter().map(|(k, v)| std::mem::size_of_val(k.as_slice()) + std::mem::size_of_val(v)).sum::<usize>();
+
+        Ok(IndexStats {
+            total_entries: index.by_document.len(),
+            memory_usage,
+            index_type: self.index_type(),
+        })
+    }
+
+    fn index_type(&self) -> IndexType {
+        IndexType::Compound {
+            fields: self.fields.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    fn document(country: &str, age: i64) -> (DocumentId, Document) {
+        let id = uuid::Uuid::new_v4();
+        let mut fields = HashMap::new();
+        fields.insert("country".to_string(), Value::String(country.to_string()));
+        fields.insert("age".to_string(), Value::Int64(age));
+        (id, Document { id, fields, version: 1, created_at: 0, updated_at: 0 })
+    }
+
+    #[tokio::test]
+    async fn test_prefix_and_range_search() {
+        let fields = vec!["country".to_string(), "age".to_string()];
+        let index = CompoundIndex::new(index_name(&fields), fields);
+        let docs = vec![document("NG", 20), document("NG", 35), document("NG", 50), document("US", 30)];
+        for (id, doc) in &docs {
+            index.insert(*id, doc).await.unwrap();
+        }
+
+        let query = |equals: Vec<Value>, min: Option<Value>, max: Option<Value>| IndexQuery::Prefix {
+            index: "country,age".to_string(),
+            equals,
+            min,
+            max,
+        };
+
+        let ng = index.search(&query(vec![Value::String("NG".into())], None, None)).await.unwrap();
+        assert_eq!(ng, vec![docs[0].0, docs[1].0, docs[2].0]);
+
+        let ranged = index
+            .search(&query(vec![Value::String("NG".into())], Some(Value::Int64(30)), Some(Value::Float64(50.0))))
+            .await
+            .unwrap();
+        assert_eq!(ranged, vec![docs[1].0, docs[2].0]);
+
+        let exact = index
+            .search(&query(vec![Value::String("US".into()), Value::Int32(30)], None, None))
+            .await
+            .unwrap();
+        assert_eq!(exact, vec![docs[3].0]);
+
+        // Moving a document re-keys it
+        let (id, mut doc) = docs[0].clone();
+        doc.fields.insert("country".to_string(), Value::String("US".into()));
+        index.update(id, &docs[0].1, &doc).await.unwrap();
+        let us = index.search(&IndexQuery::Exact { field: "country".to_string(), value: Value::String("US".into()) }).await.unwrap();
+        assert_eq!(us, vec![id, docs[3].0]);
+    }
+}
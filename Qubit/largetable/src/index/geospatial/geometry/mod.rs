@@ -0,0 +1,499 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! GeoJSON geometries on the sphere
+//!
+//! Coordinates are `(longitude, latitude)` in degrees and distances are in
+//! meters. Edges are treated as straight lines in longitude/latitude, which
+//! is close to the great-circle edge for the city-scale shapes location
+//! features use; shapes crossing the antimeridian are not supported.
+
+use crate::{LargetableError, Result, Value};
+use serde_json::Value as JsonValue;
+
+/// Mean Earth radius used for every distance
+pub const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// A `(longitude, latitude)` pair in degrees
+pub type Point = (f64, f64);
+
+/// Polygon as an exterior ring followed by its holes; rings are closed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub rings: Vec<Vec<Point>>,
+}
+
+impl Polygon {
+    fn from_rings(rings: Vec<Vec<Point>>) -> Result<Self> {
+        if rings.is_empty() {
+            return Err(LargetableError::Query("Polygon needs an exterior ring".to_string()));
+        }
+        for ring in &rings {
+            if ring.len() < 4 || ring.first() != ring.last() {
+                return Err(LargetableError::Query(
+                    "Polygon rings need at least four positions and must be closed".to_string(),
+                ));
+            }
+        }
+        Ok(Self { rings })
+    }
+
+    /// Inside the exterior ring and outside every hole; boundary points count as inside
+    pub fn contains(&self, point: Point) -> bool {
+        let mut rings = self.rings.iter();
+        let Some(exterior) = rings.next() else {
+            return false;
+        };
+        if on_ring(exterior, point) {
+            return true;
+        }
+        ring_contains(exterior, point) && rings.all(|hole| on_ring(hole, point) || !ring_contains(hole, point))
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.rings.iter().flat_map(|ring| ring.windows(2).map(|edge| (edge[0], edge[1])))
+    }
+}
+
+/// GeoJSON geometry
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    Point(Point),
+    MultiPoint(Vec<Point>),
+    LineString(Vec<Point>),
+    MultiLineString(Vec<Vec<Point>>),
+    Polygon(Polygon),
+    MultiPolygon(Vec<Polygon>),
+}
+
+impl Geometry {
+    /// Parse a GeoJSON geometry object
+    pub fn from_json(json: &JsonValue) -> Result<Self> {
+        let kind = json
+            .get("type")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| LargetableError::Query("GeoJSON geometry needs a type".to_string()))?;
+        let coordinates = json
+            .get("coordinates")
+            .ok_or_else(|| LargetableError::Query("GeoJSON geometry needs coordinates".to_string()))?;
+        Self::from_parts(kind, &Coordinates::Json(coordinates))
+    }
+
+    /// Geometry stored in a document field
+    ///
+    /// Accepts GeoJSON documents, legacy `[longitude, latitude]` pairs and
+    /// `{longitude, latitude}` documents.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Document(doc) => {
+                if let (Some(Value::String(kind)), Some(coordinates)) = (doc.fields.get("type"), doc.fields.get("coordinates")) {
+                    return Self::from_parts(kind, &Coordinates::Value(coordinates)).ok();
+                }
+                let lon = doc.fields.get("longitude").and_then(number)?;
+                let lat = doc.fields.get("latitude").and_then(number)?;
+                valid_point((lon, lat)).ok().map(Geometry::Point)
+            }
+            Value::Array(_) => Coordinates::Value(value).point().ok().map(Geometry::Point),
+            _ => None,
+        }
+    }
+
+    fn from_parts(kind: &str, coordinates: &Coordinates<'_>) -> Result<Self> {
+        Ok(match kind {
+            "Point" => Geometry::Point(coordinates.point()?),
+            "MultiPoint" => Geometry::MultiPoint(coordinates.points()?),
+            "LineString" => Geometry::LineString(line(coordinates.points()?)?),
+            "MultiLineString" => Geometry::MultiLineString(
+                coordinates.items()?.iter().map(|item| item.points().and_then(line)).collect::<Result<_>>()?,
+            ),
+            "Polygon" => Geometry::Polygon(coordinates.polygon()?),
+            "MultiPolygon" => Geometry::MultiPolygon(
+                coordinates.items()?.iter().map(Coordinates::polygon).collect::<Result<_>>()?,
+            ),
+            other => return Err(LargetableError::Query(format!("Unsupported GeoJSON type '{}'", other))),
+        })
+    }
+
+    /// Every position of the geometry
+    pub fn points(&self) -> Vec<Point> {
+        match self {
+            Geometry::Point(point) => vec![*point],
+            Geometry::MultiPoint(points) | Geometry::LineString(points) => points.clone(),
+            Geometry::MultiLineString(lines) => lines.concat(),
+            Geometry::Polygon(polygon) => polygon.rings.concat(),
+            Geometry::MultiPolygon(polygons) => polygons.iter().flat_map(|polygon| polygon.rings.concat()).collect(),
+        }
+    }
+
+    fn edges(&self) -> Vec<(Point, Point)> {
+        match self {
+            Geometry::Point(_) | Geometry::MultiPoint(_) => Vec::new(),
+            Geometry::LineString(points) => points.windows(2).map(|edge| (edge[0], edge[1])).collect(),
+            Geometry::MultiLineString(lines) => lines
+                .iter()
+                .flat_map(|points| points.windows(2).map(|edge| (edge[0], edge[1])))
+                .collect(),
+            Geometry::Polygon(polygon) => polygon.edges().collect(),
+            Geometry::MultiPolygon(polygons) => polygons.iter().flat_map(Polygon::edges).collect(),
+        }
+    }
+
+    fn polygons(&self) -> &[Polygon] {
+        match self {
+            Geometry::Polygon(polygon) => std::slice::from_ref(polygon),
+            Geometry::MultiPolygon(polygons) => polygons,
+            _ => &[],
+        }
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        Bounds::around(self.points())
+    }
+
+    /// Shortest distance in meters from a point; zero when the point is covered
+    pub fn distance_to(&self, point: Point) -> f64 {
+        if self.polygons().iter().any(|polygon| polygon.contains(point)) {
+            return 0.0;
+        }
+        let to_vertices = self.points().into_iter().map(|vertex| distance(vertex, point));
+        let to_edges = self.edges().into_iter().map(|(a, b)| segment_distance(a, b, point));
+        to_vertices.chain(to_edges).fold(f64::INFINITY, f64::min)
+    }
+
+    /// Whether the geometries share any point
+    pub fn intersects(&self, other: &Geometry) -> bool {
+        if !self.bounds().overlaps(&other.bounds()) {
+            return false;
+        }
+        let covers = |geometry: &Geometry, points: Vec<Point>| {
+            geometry.polygons().iter().any(|polygon| points.iter().any(|point| polygon.contains(*point)))
+        };
+        if covers(self, other.points()) || covers(other, self.points()) {
+            return true;
+        }
+
+        let (edges, other_edges) = (self.edges(), other.edges());
+        let touches = |points: Vec<Point>, edges: &[(Point, Point)]| {
+            points.iter().any(|point| edges.iter().any(|(a, b)| on_segment(*a, *b, *point)))
+        };
+        if touches(self.points(), &other_edges) || touches(other.points(), &edges) {
+            return true;
+        }
+        if edges.iter().any(|(a, b)| other_edges.iter().any(|(c, d)| segments_cross(*a, *b, *c, *d))) {
+            return true;
+        }
+        // Point sets with no edges can only meet at a shared position
+        self.points().iter().any(|point| other.points().contains(point))
+    }
+
+    /// Whether the geometry lies entirely inside a region
+    pub fn within(&self, region: &GeoRegion) -> bool {
+        match region {
+            GeoRegion::Shape(shape) => {
+                let polygons = shape.polygons();
+                let points = self.points();
+                // Each connected piece must sit in one polygon; checking every vertex
+                // and that no edge leaves through a boundary covers all geometry types
+                points.iter().all(|point| polygons.iter().any(|polygon| polygon.contains(*point)))
+                    && !self.edges().iter().any(|(a, b)| {
+                        shape.edges().iter().any(|(c, d)| segments_cross_properly(*a, *b, *c, *d))
+                    })
+                    // A region hole inside the geometry would not be crossed by any edge
+                    && !self.polygons().iter().any(|own| {
+                        polygons
+                            .iter()
+                            .flat_map(|polygon| polygon.rings.iter().skip(1))
+                            .any(|hole| hole.iter().any(|vertex| own.contains(*vertex) && !on_ring(&own.rings[0], *vertex)))
+                    })
+            }
+            GeoRegion::Cap { center, radius } => {
+                self.points().iter().all(|point| distance(*center, *point) <= *radius)
+            }
+            GeoRegion::Box(bounds) => self.points().iter().all(|point| bounds.contains(*point)),
+        }
+    }
+}
+
+/// Area a `$geoWithin` query selects
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoRegion {
+    /// `$geometry` with a Polygon or MultiPolygon
+    Shape(Geometry),
+    /// `$centerSphere`: points within `radius` meters of `center`
+    Cap { center: Point, radius: f64 },
+    /// `$box`: a longitude/latitude rectangle
+    Box(Bounds),
+}
+
+impl GeoRegion {
+    /// Parse a `$geoWithin` operand
+    pub fn from_json(json: &JsonValue) -> Result<Self> {
+        if let Some(geometry) = json.get("$geometry") {
+            let geometry = Geometry::from_json(geometry)?;
+            if geometry.polygons().is_empty() {
+                return Err(LargetableError::Query("$geoWithin needs a Polygon or MultiPolygon".to_string()));
+            }
+            return Ok(GeoRegion::Shape(geometry));
+        }
+        if let Some(JsonValue::Array(sphere)) = json.get("$centerSphere") {
+            let [center, radians] = sphere.as_slice() else {
+                return Err(LargetableError::Query("$centerSphere expects [[longitude, latitude], radians]".to_string()));
+            };
+            let radians = radians
+                .as_f64()
+                .filter(|radians| *radians >= 0.0)
+                .ok_or_else(|| LargetableError::Query("$centerSphere radius must be a non-negative number".to_string()))?;
+            return Ok(GeoRegion::Cap {
+                center: Coordinates::Json(center).point()?,
+                radius: radians * EARTH_RADIUS_METERS,
+            });
+        }
+        if let Some(JsonValue::Array(corners)) = json.get("$box") {
+            let [low, high] = corners.as_slice() else {
+                return Err(LargetableError::Query("$box expects two corners".to_string()));
+            };
+            return Ok(GeoRegion::Box(Bounds::around(vec![
+                Coordinates::Json(low).point()?,
+                Coordinates::Json(high).point()?,
+            ])));
+        }
+        Err(LargetableError::Query("$geoWithin needs $geometry, $centerSphere or $box".to_string()))
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        match self {
+            GeoRegion::Shape(shape) => shape.bounds(),
+            GeoRegion::Cap { center, radius } => Bounds::around_cap(*center, *radius),
+            GeoRegion::Box(bounds) => *bounds,
+        }
+    }
+}
+
+/// Longitude/latitude rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    fn around(points: Vec<Point>) -> Self {
+        points.into_iter().fold(
+            Bounds { min: (f64::INFINITY, f64::INFINITY), max: (f64::NEG_INFINITY, f64::NEG_INFINITY) },
+            |bounds, (lon, lat)| Bounds {
+                min: (bounds.min.0.min(lon), bounds.min.1.min(lat)),
+                max: (bounds.max.0.max(lon), bounds.max.1.max(lat)),
+            },
+        )
+    }
+
+    /// Rectangle enclosing every point within `radius` meters of `center`
+    pub fn around_cap((lon, lat): Point, radius: f64) -> Self {
+        let angular = (radius / EARTH_RADIUS_METERS).to_degrees();
+        let (min_lat, max_lat) = (lat - angular, lat + angular);
+        if min_lat <= -90.0 || max_lat >= 90.0 || angular >= 90.0 {
+            // The cap reaches a pole, so it spans every longitude
+            return Bounds { min: (-180.0, min_lat.max(-90.0)), max: (180.0, max_lat.min(90.0)) };
+        }
+        let spread = (angular.to_radians().sin() / lat.to_radians().cos()).min(1.0).asin().to_degrees();
+        Bounds { min: (lon - spread, min_lat), max: (lon + spread, max_lat) }
+    }
+
+    pub fn contains(&self, (lon, lat): Point) -> bool {
+        lon >= self.min.0 && lon <= self.max.0 && lat >= self.min.1 && lat <= self.max.1
+    }
+
+    pub fn overlaps(&self, other: &Bounds) -> bool {
+        self.min.0 <= other.max.0 && other.min.0 <= self.max.0 && self.min.1 <= other.max.1 && other.min.1 <= self.max.1
+    }
+}
+
+/// Great-circle distance in meters
+pub fn distance((lon1, lat1): Point, (lon2, lat2): Point) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let delta_lat = lat2 - lat1;
+    let delta_lon = (lon2 - lon1).to_radians();
+    let a = (delta_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (delta_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+}
+
+/// Distance from a point to a segment, projected onto the plane tangent at the point
+fn segment_distance(a: Point, b: Point, point: Point) -> f64 {
+    let scale = point.1.to_radians().cos();
+    let project = |(lon, lat): Point| ((lon - point.0) * scale, lat - point.1);
+    let ((ax, ay), (bx, by)) = (project(a), project(b));
+    let (dx, dy) = (bx - ax, by - ay);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 { 0.0 } else { (-(ax * dx + ay * dy) / length).clamp(0.0, 1.0) };
+    let nearest = (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1));
+    distance(nearest, point)
+}
+
+fn cross(origin: Point, a: Point, b: Point) -> f64 {
+    (a.0 - origin.0) * (b.1 - origin.1) - (a.1 - origin.1) * (b.0 - origin.0)
+}
+
+fn on_segment(a: Point, b: Point, point: Point) -> bool {
+    cross(a, b, point).abs() <= 1e-12
+        && point.0 >= a.0.min(b.0)
+        && point.0 <= a.0.max(b.0)
+        && point.1 >= a.1.min(b.1)
+        && point.1 <= a.1.max(b.1)
+}
+
+/// Segments share a point, including touching at an end
+fn segments_cross(a: Point, b: Point, c: Point, d: Point) -> bool {
+    segments_cross_properly(a, b, c, d)
+        || on_segment(a, b, c)
+        || on_segment(a, b, d)
+        || on_segment(c, d, a)
+        || on_segment(c, d, b)
+}
+
+/// Segments cross at a single point interior to both
+fn segments_cross_properly(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let (d1, d2) = (cross(c, d, a), cross(c, d, b));
+    let (d3, d4) = (cross(a, b, c), cross(a, b, d));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+fn on_ring(ring: &[Point], point: Point) -> bool {
+    ring.windows(2).any(|edge| on_segment(edge[0], edge[1], point))
+}
+
+/// Even-odd rule
+fn ring_contains(ring: &[Point], (x, y): Point) -> bool {
+    let mut inside = false;
+    for edge in ring.windows(2) {
+        let ((x1, y1), (x2, y2)) = (edge[0], edge[1]);
+        if (y1 > y) != (y2 > y) && x < (x2 - x1) * (y - y1) / (y2 - y1) + x1 {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn line(points: Vec<Point>) -> Result<Vec<Point>> {
+    if points.len() < 2 {
+        return Err(LargetableError::Query("LineString needs at least two positions".to_string()));
+    }
+    Ok(points)
+}
+
+fn valid_point((lon, lat): Point) -> Result<Point> {
+    if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+        return Err(LargetableError::Query(format!("Position ({}, {}) is out of range", lon, lat)));
+    }
+    Ok((lon, lat))
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float64(f) => Some(*f),
+        Value::Float32(f) => Some(*f as f64),
+        Value::Int64(i) => Some(*i as f64),
+        Value::Int32(i) => Some(*i as f64),
+        _ => None,
+    }
+}
+
+/// Coordinates from a query (JSON) or a stored document (`Value`)
+enum Coordinates<'a> {
+    Json(&'a JsonValue),
+    Value(&'a Value),
+}
+
+impl Coordinates<'_> {
+    fn items(&self) -> Result<Vec<Coordinates<'_>>> {
+        match self {
+            Coordinates::Json(JsonValue::Array(items)) => Ok(items.iter().map(Coordinates::Json).collect()),
+            Coordinates::Value(Value::Array(items)) => Ok(items.iter().map(Coordinates::Value).collect()),
+            _ => Err(LargetableError::Query("GeoJSON coordinates must be arrays".to_string())),
+        }
+    }
+
+    fn point(&self) -> Result<Point> {
+        let position: Vec<f64> = self
+            .items()?
+            .iter()
+            .map(|item| match item {
+                Coordinates::Json(json) => json.as_f64(),
+                Coordinates::Value(value) => number(value),
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| LargetableError::Query("GeoJSON positions must be numbers".to_string()))?;
+        match position.as_slice() {
+            [lon, lat] | [lon, lat, _] => valid_point((*lon, *lat)),
+            _ => Err(LargetableError::Query("GeoJSON positions are [longitude, latitude]".to_string())),
+        }
+    }
+
+    fn points(&self) -> Result<Vec<Point>> {
+        self.items()?.iter().map(Coordinates::point).collect()
+    }
+
+    fn polygon(&self) -> Result<Polygon> {
+        Polygon::from_rings(self.items()?.iter().map(Coordinates::points).collect::<Result<_>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn square(min: f64, max: f64) -> Geometry {
+        Geometry::from_json(&json!({
+            "type": "Polygon",
+            "coordinates": [[[min, min], [max, min], [max, max], [min, max], [min, min]]]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_polygon_with_hole() {
+        let polygon = Geometry::from_json(&json!({
+            "type": "Polygon",
+            "coordinates": [
+                [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]],
+                [[4.0, 4.0], [6.0, 4.0], [6.0, 6.0], [4.0, 6.0], [4.0, 4.0]]
+            ]
+        }))
+        .unwrap();
+        let Geometry::Polygon(polygon) = polygon else { unreachable!() };
+        assert!(polygon.contains((1.0, 1.0)));
+        assert!(polygon.contains((0.0, 5.0)));
+        assert!(!polygon.contains((5.0, 5.0)));
+        assert!(!polygon.contains((11.0, 5.0)));
+
+        assert!(Geometry::from_json(&json!({"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [0, 0]]]})).is_err());
+        assert!(Geometry::from_json(&json!({"type": "Point", "coordinates": [200, 0]})).is_err());
+    }
+
+    #[test]
+    fn test_intersects_and_within() {
+        let line = Geometry::LineString(vec![(-1.0, 5.0), (11.0, 5.0)]);
+        assert!(line.intersects(&square(0.0, 10.0)));
+        assert!(!line.within(&GeoRegion::Shape(square(0.0, 10.0))));
+        assert!(square(2.0, 3.0).within(&GeoRegion::Shape(square(0.0, 10.0))));
+        assert!(!Geometry::Point((20.0, 20.0)).intersects(&square(0.0, 10.0)));
+        assert!(Geometry::Point((10.0, 10.0)).intersects(&square(0.0, 10.0)));
+
+        let region = GeoRegion::from_json(&json!({"$centerSphere": [[0.0, 0.0], 1000.0 / EARTH_RADIUS_METERS]})).unwrap();
+        assert!(Geometry::Point((0.005, 0.0)).within(&region));
+        assert!(!Geometry::Point((0.01, 0.0)).within(&region));
+        assert!(region.bounds().contains((0.0089, 0.0)));
+    }
+
+    #[test]
+    fn test_distances() {
+        // One degree of latitude is about 111 km
+        assert!((distance((0.0, 0.0), (0.0, 1.0)) - 111_195.0).abs() < 10.0);
+        let line = Geometry::LineString(vec![(-1.0, 1.0), (1.0, 1.0)]);
+        assert!((line.distance_to((0.0, 0.0)) - 111_195.0).abs() < 50.0);
+        assert_eq!(square(-1.0, 1.0).distance_to((0.0, 0.0)), 0.0);
+    }
+}
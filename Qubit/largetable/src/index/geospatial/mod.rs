@@ -4,21 +4,191 @@
 // Built to outperform MongoDB with Rust's power.
 // ===========================================
 
-//! Geospatial index implementation
+//! 2dsphere geospatial index
+//!
+//! Stores GeoJSON geometries and answers `$near`, `$geoWithin` and
+//! `$geoIntersects`. Geometries are bucketed into a fixed longitude/latitude
+//! grid so a query only tests the shapes whose cells it overlaps.
 
-use crate::{Result, DocumentId, Document, LargetableError, IndexType, IndexQuery, IndexStats};
-use crate::index::Index;
+pub mod geometry;
+
+use crate::{Result, DocumentId, Document, LargetableError, IndexType};
+use crate::index::{Index, IndexQuery, IndexStats};
 use crate::document::DocumentUtils;
-use std::collections::HashMap;
-use std::sync::Arc;
+use geometry::{Bounds, GeoRegion, Geometry, Point};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
 use tokio::sync::RwLock;
-use tracing::{debug, error};
+use tracing::debug;
+
+/// Coordinate system of GeoJSON indexes measured on the sphere
+pub const SPHERE_2D: &str = "2dsphere";
+
+/// Side of a grid cell in degrees, about 11 km at the equator
+const CELL_DEGREES: f64 = 0.1;
+
+/// Geometries spanning more cells than this are kept in one list tested by every query
+const MAX_CELLS_PER_GEOMETRY: usize = 256;
+
+/// Geospatial predicate answered by a 2dsphere index
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoQuery {
+    /// Documents ordered nearest first, optionally within a distance band in meters
+    Near {
+        point: Point,
+        min_distance: Option<f64>,
+        max_distance: Option<f64>,
+    },
+    /// Geometries entirely inside a region
+    Within(GeoRegion),
+    /// Geometries sharing any point with a geometry
+    Intersects(Geometry),
+}
+
+impl GeoQuery {
+    /// Parse a `$near`, `$nearSphere`, `$geoWithin` or `$geoIntersects` operand
+    pub fn from_operator(operator: &str, operand: &JsonValue) -> Result<Self> {
+        match operator {
+            "$near" | "$nearSphere" => {
+                let point = match operand.get("$geometry").map(Geometry::from_json).transpose()? {
+                    Some(Geometry::Point(point)) => point,
+                    _ => {
+                        return Err(LargetableError::Query(format!("{} needs a GeoJSON Point $geometry", operator)));
+                    }
+                };
+                let distance = |key: &str| -> Result<Option<f64>> {
+                    operand
+                        .get(key)
+                        .map(|value| {
+                            value
+                                .as_f64()
+                                .filter(|meters| *meters >= 0.0)
+                                .ok_or_else(|| LargetableError::Query(format!("{} must be a non-negative number", key)))
+                        })
+                        .transpose()
+                };
+                Ok(GeoQuery::Near {
+                    point,
+                    min_distance: distance("$minDistance")?,
+                    max_distance: distance("$maxDistance")?,
+                })
+            }
+            "$geoWithin" => Ok(GeoQuery::Within(GeoRegion::from_json(operand)?)),
+            "$geoIntersects" => {
+                let geometry = operand
+                    .get("$geometry")
+                    .ok_or_else(|| LargetableError::Query("$geoIntersects needs a $geometry".to_string()))?;
+                Ok(GeoQuery::Intersects(Geometry::from_json(geometry)?))
+            }
+            other => Err(LargetableError::Query(format!("'{}' is not a geospatial operator", other))),
+        }
+    }
+
+    /// Whether `operator` is one of the geospatial operators
+    pub fn is_operator(operator: &str) -> bool {
+        matches!(operator, "$near" | "$nearSphere" | "$geoWithin" | "$geoIntersects")
+    }
+
+    /// Check a stored geometry against the predicate
+    pub fn matches(&self, geometry: &Geometry) -> bool {
+        match self {
+            GeoQuery::Near { point, min_distance, max_distance } => {
+                let distance = geometry.distance_to(*point);
+                min_distance.is_none_or(|min| distance >= min) && max_distance.is_none_or(|max| distance <= max)
+            }
+            GeoQuery::Within(region) => geometry.within(region),
+            GeoQuery::Intersects(other) => geometry.intersects(other),
+        }
+    }
+
+    /// Rectangle every match lies in, if the predicate is bounded
+    fn bounds(&self) -> Option<Bounds> {
+        match self {
+            GeoQuery::Near { point, max_distance, .. } => max_distance.map(|max| Bounds::around_cap(*point, max)),
+            GeoQuery::Within(region) => Some(region.bounds()),
+            GeoQuery::Intersects(geometry) => Some(geometry.bounds()),
+        }
+    }
+}
+
+type Cell = (i32, i32);
+
+#[derive(Default)]
+struct GeoEntries {
+    geometries: HashMap<DocumentId, Geometry>,
+    cells: HashMap<Cell, HashSet<DocumentId>>,
+    /// Geometries too large to bucket
+    large: HashSet<DocumentId>,
+}
+
+impl GeoEntries {
+    /// Grid cells a rectangle overlaps, or None if there are too many to list
+    fn cells(bounds: &Bounds) -> Option<Vec<Cell>> {
+        let cell = |degrees: f64| (degrees / CELL_DEGREES).floor() as i64;
+        let (min_x, min_y) = (cell(bounds.min.0), cell(bounds.min.1));
+        let (max_x, max_y) = (cell(bounds.max.0), cell(bounds.max.1));
+        let count = (max_x - min_x + 1).saturating_mul(max_y - min_y + 1);
+        if count > MAX_CELLS_PER_GEOMETRY as i64 {
+            return None;
+        }
+        Some(
+            (min_x..=max_x)
+                .flat_map(|x| (min_y..=max_y).map(move |y| (x as i32, y as i32)))
+                .collect(),
+        )
+    }
+
+    fn insert(&mut self, id: DocumentId, geometry: Geometry) {
+        self.remove(&id);
+        match Self::cells(&geometry.bounds()) {
+            Some(cells) => {
+                for cell in cells {
+                    self.cells.entry(cell).or_default().insert(id);
+                }
+            }
+            None => {
+                self.large.insert(id);
+            }
+        }
+        self.geometries.insert(id, geometry);
+    }
+
+    fn remove(&mut self, id: &DocumentId) {
+        let Some(geometry) = self.geometries.remove(id) else {
+            return;
+        };
+        if !self.large.remove(id) {
+            for cell in Self::cells(&geometry.bounds()).unwrap_or_default() {
+                if let Some(ids) = self.cells.get_mut(&cell) {
+                    ids.remove(id);
+                    if ids.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Geometries that may lie in a rectangle; every geometry when unbounded
+    fn candidates(&self, bounds: Option<Bounds>) -> Vec<(&DocumentId, &Geometry)> {
+        let Some(cells) = bounds.as_ref().and_then(Self::cells) else {
+            return self.geometries.iter().collect();
+        };
+        let mut ids: HashSet<&DocumentId> = self.large.iter().collect();
+        for cell in cells {
+            if let Some(cell_ids) = self.cells.get(&cell) {
+                ids.extend(cell_ids);
+            }
+        }
+        ids.into_iter().filter_map(|id| self.geometries.get_key_value(id)).collect()
+    }
+}
 
 /// Geospatial index for location-based queries
 pub struct GeospatialIndex {
     field: String,
     coordinate_system: String,
-    points: Arc<RwLock<HashMap<DocumentId, (f64, f64)>>>,
+    entries: RwLock<GeoEntries>,
 }
 
 impl GeospatialIndex {
@@ -27,111 +197,68 @@ impl GeospatialIndex {
         Self {
             field,
             coordinate_system,
-            points: Arc::new(RwLock::new(HashMap::new())),
+            entries: RwLock::new(GeoEntries::default()),
         }
     }
 
-    /// Extract coordinates from a document field
-    fn extract_coordinates(&self, doc: &Document) -> Option<(f64, f64)> {
-        DocumentUtils::get_field(doc, &self.field).and_then(|value| match value {
-            crate::Value::Document(geo_doc) => {
-                let lon = geo_doc.fields.get("longitude")
-                    .and_then(|v| match v {
-                        crate::Value::Float64(f) => Some(*f),
-                        crate::Value::Int64(i) => Some(*i as f64),
-                        _ => None,
-                    })?;
-                let lat = geo_doc.fields.get("latitude")
-                    .and_then(|v| match v {
-                        crate::Value::Float64(f) => Some(*f),
-                        crate::Value::Int64(i) => Some(*i as f64),
-                        _ => None,
-                    })?;
-                Some((lon, lat))
-            }
-            crate::Value::Array(coords) if coords.len() == 2 => {
-                let lon = match &coords[0] {
-                    crate::Value::Float64(f) => *f,
-                    crate::Value::Int64(i) => *i as f64,
-                    _ => return None,
-                };
-                let lat = match &coords[1] {
-                    crate::Value::Float64(f) => *f,
-                    crate::Value::Int64(i) => *i as f64,
-                    _ => return None,
-                };
-                Some((lon, lat))
-            }
-            _ => None,
-        })
+    /// Extract the geometry of a document field
+    fn extract_geometry(&self, doc: &Document) -> Option<Geometry> {
+        DocumentUtils::get_field(doc, &self.field).and_then(Geometry::from_value)
     }
 
-    /// Calculate distance between two points using Haversine formula
-    fn haversine_distance(&self, (lon1, lat1): (f64, f64), (lon2, lat2): (f64, f64)) -> f64 {
-        const EARTH_RADIUS_KM: f64 = 6371.0;
-        
-        let lat1_rad = lat1.to_radians();
-        let lat2_rad = lat2.to_radians();
-        let delta_lat = (lat2 - lat1).to_radians();
-        let delta_lon = (lon2 - lon1).to_radians();
-        
-        let a = (delta_lat / 2.0).sin().powi(2) +
-            lat1_rad.cos() * lat2_rad.cos() * (delta_lon / 2.0).sin().powi(2);
-        let c = 2.0 * a.sqrt().asin();
-        
-        EARTH_RADIUS_KM * c
-    }
-
-    /// Search for points within a radius
-    async fn search_within_radius(&self, center: (f64, f64), radius: f64) -> Result<Vec<DocumentId>> {
-        let points = self.points.read().await;
-        let mut results = Vec::new();
-        
-        for (doc_id, point) in points.iter() {
-            let distance = self.haversine_distance(center, *point);
-            if distance <= radius {
-                results.push(*doc_id);
+    /// Matching documents; `$near` results come nearest first
+    async fn search_geo(&self, query: &GeoQuery) -> Vec<DocumentId> {
+        let entries = self.entries.read().await;
+        let candidates = entries.candidates(query.bounds());
+
+        match query {
+            GeoQuery::Near { point, .. } => {
+                let mut matches: Vec<(f64, DocumentId)> = candidates
+                    .into_iter()
+                    .filter(|(_, geometry)| query.matches(geometry))
+                    .map(|(id, geometry)| (geometry.distance_to(*point), *id))
+                    .collect();
+                matches.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+                matches.into_iter().map(|(_, id)| id).collect()
             }
+            _ => candidates
+                .into_iter()
+                .filter(|(_, geometry)| query.matches(geometry))
+                .map(|(id, _)| *id)
+                .collect(),
         }
-        
-        Ok(results)
     }
 }
 
 #[async_trait::async_trait]
 impl Index for GeospatialIndex {
     async fn insert(&self, id: DocumentId, doc: &Document) -> Result<()> {
-        if let Some(coordinates) = self.extract_coordinates(doc) {
-            let mut points = self.points.write().await;
-            points.insert(id, coordinates);
+        if let Some(geometry) = self.extract_geometry(doc) {
+            self.entries.write().await.insert(id, geometry);
             debug!("Inserted document {} into geospatial index on field '{}'", id, self.field);
         }
         Ok(())
     }
 
     async fn remove(&self, id: &DocumentId) -> Result<()> {
-        let mut points = self.points.write().await;
-        points.remove(id);
+        self.entries.write().await.remove(id);
         debug!("Removed document {} from geospatial index on field '{}'", id, self.field);
         Ok(())
     }
 
-    async fn update(&self, id: DocumentId, old_doc: &Document, new_doc: &Document) -> Result<()> {
-        // Remove old coordinates
-        self.remove(&id).await?;
-        
-        // Insert new coordinates
-        self.insert(id, new_doc).await?;
-        
+    async fn update(&self, id: DocumentId, _old_doc: &Document, new_doc: &Document) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        match self.extract_geometry(new_doc) {
+            Some(geometry) => entries.insert(id, geometry),
+            None => entries.remove(&id),
+        }
         debug!("Updated document {} in geospatial index on field '{}'", id, self.field);
         Ok(())
     }
 
     async fn search(&self, query: &IndexQuery) -> Result<Vec<DocumentId>> {
         match query {
-            IndexQuery::Geospatial { field, center, radius } if field == &self.field => {
-                self.search_within_radius(*center, *radius).await
-            }
+            IndexQuery::Geospatial { field, query } if field == &self.field => Ok(self.search_geo(query).await),
             _ => {
                 Err(LargetableError::Index(format!(
                     "Geospatial index on field '{}' only supports geospatial queries, got: {:?}",
@@ -142,17 +269,19 @@ impl Index for GeospatialIndex {
     }
 
     async fn stats(&self) -> Result<IndexStats> {
-        let points = self.points.read().await;
-        let total_entries = points.len();
-        let memory_usage = std::mem::size_of_val(&*points) + 
-            points.iter().map(|(k, v)| std::mem::size_of_val(k) + std::mem::size_of_val(v)).sum::<usize>();
-        
+        let entries = self.entries.read().await;
+        let total_entries = entries.geometries.len();
+        let memory_usage = entries
+            .geometries
+            .values()
+            .map(|geometry| std::mem::size_of::<DocumentId>() + std::mem::size_of_val(geometry) + geometry.points().len() * std::mem::size_of::<Point>())
+            .sum::<usize>()
+            + entries.cells.values().map(|ids| std::mem::size_of::<Cell>() + ids.len() * std::mem::size_of::<DocumentId>()).sum::<usize>();
+
         Ok(IndexStats {
             total_entries,
             memory_usage,
-            index_type: IndexType::Geospatial {
-                coordinate_system: self.coordinate_system.clone(),
-            },
+            index_type: self.index_type(),
         })
     }
 
@@ -161,4 +290,76 @@ impl Index for GeospatialIndex {
             coordinate_system: self.coordinate_system.clone(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn place(location: JsonValue) -> Document {
+        DocumentUtils::from_json(json!({ "location": location })).unwrap()
+    }
+
+    fn point(lon: f64, lat: f64) -> Document {
+        place(json!({"type": "Point", "coordinates": [lon, lat]}))
+    }
+
+    #[tokio::test]
+    async fn test_near_orders_by_distance() {
+        let index = GeospatialIndex::new("location".to_string(), SPHERE_2D.to_string());
+        let (near, far, farther) = (DocumentId::new_v4(), DocumentId::new_v4(), DocumentId::new_v4());
+        index.insert(far, &point(3.40, 6.50)).await.unwrap();
+        index.insert(near, &point(3.38, 6.52)).await.unwrap();
+        index.insert(farther, &point(4.00, 7.00)).await.unwrap();
+
+        let query = |operand| IndexQuery::Geospatial {
+            field: "location".to_string(),
+            query: GeoQuery::from_operator("$near", &operand).unwrap(),
+        };
+        let origin = json!({"type": "Point", "coordinates": [3.37, 6.52]});
+        let all = index.search(&query(json!({"$geometry": origin}))).await.unwrap();
+        assert_eq!(all, vec![near, far, farther]);
+
+        let within_5km = index.search(&query(json!({"$geometry": origin, "$maxDistance": 5000}))).await.unwrap();
+        assert_eq!(within_5km, vec![near, far]);
+
+        // Moving a document updates its cell
+        index.update(near, &point(3.38, 6.52), &point(10.0, 10.0)).await.unwrap();
+        let within_5km = index.search(&query(json!({"$geometry": origin, "$maxDistance": 5000}))).await.unwrap();
+        assert_eq!(within_5km, vec![far]);
+    }
+
+    #[tokio::test]
+    async fn test_within_and_intersects() {
+        let index = GeospatialIndex::new("location".to_string(), SPHERE_2D.to_string());
+        let (inside, outside, road) = (DocumentId::new_v4(), DocumentId::new_v4(), DocumentId::new_v4());
+        index.insert(inside, &point(0.5, 0.5)).await.unwrap();
+        index.insert(outside, &point(5.0, 5.0)).await.unwrap();
+        let line = json!({"type": "LineString", "coordinates": [[-1.0, 0.5], [2.0, 0.5]]});
+        index.insert(road, &place(line)).await.unwrap();
+        // Not a geometry, so not indexed
+        index.insert(DocumentId::new_v4(), &place(json!("Lagos"))).await.unwrap();
+
+        let square = json!({"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]]]});
+        let search = |operator: &'static str, operand: JsonValue| {
+            let index = &index;
+            async move {
+                let query = GeoQuery::from_operator(operator, &operand).unwrap();
+                let mut ids = index
+                    .search(&IndexQuery::Geospatial { field: "location".to_string(), query })
+                    .await
+                    .unwrap();
+                ids.sort();
+                ids
+            }
+        };
+
+        assert_eq!(search("$geoWithin", json!({"$geometry": square})).await, vec![inside]);
+        let mut expected = vec![inside, road];
+        expected.sort();
+        assert_eq!(search("$geoIntersects", json!({"$geometry": square})).await, expected);
+        assert_eq!(search("$geoWithin", json!({"$box": [[4, 4], [6, 6]]})).await, vec![outside]);
+        assert_eq!(index.stats().await.unwrap().total_entries, 3);
+    }
+}
@@ -6,29 +6,25 @@
 
 //! Hash index implementation for exact matches
 
-use crate::{Result, DocumentId, Document, LargetableError, IndexType, IndexQuery, IndexStats};
-use crate::index::Index;
+use crate::{Result, DocumentId, Document, LargetableError, IndexType};
+use crate::index::{Index, IndexKey, IndexQuery, IndexStats};
 use crate::document::DocumentUtils;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{debug, error};
+use tracing::debug;
 
 /// Hash index for exact matches
 pub struct HashIndex {
     field: String,
-    index: Arc<RwLock<HashMap<IndexKey, Vec<DocumentId>>>>,
+    index: Arc<RwLock<HashEntries>>,
 }
 
-/// Key for the hash index
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum IndexKey {
-    Null,
-    Bool(bool),
-    Int(i64),
-    Float(f64),
-    String(String),
-    Timestamp(i64),
+/// Keyed entries plus the key each document was indexed under
+#[derive(Default)]
+struct HashEntries {
+    keys: HashMap<IndexKey, Vec<DocumentId>>,
+    by_document: HashMap<DocumentId, IndexKey>,
 }
 
 impl HashIndex {
@@ -36,39 +32,26 @@ impl HashIndex {
     pub fn new(field: String) -> Self {
         Self {
             field,
-            index: Arc::new(RwLock::new(HashMap::new())),
+            index: Arc::new(RwLock::new(HashEntries::default())),
         }
     }
 
     /// Extract the index key from a document
     fn extract_key(&self, doc: &Document) -> Option<IndexKey> {
-        DocumentUtils::get_field(doc, &self.field).map(|value| match value {
-            crate::Value::Null => IndexKey::Null,
-            crate::Value::Bool(b) => IndexKey::Bool(*b),
-            crate::Value::Int32(i) => IndexKey::Int(*i as i64),
-            crate::Value::Int64(i) => IndexKey::Int(*i),
-            crate::Value::UInt64(u) => IndexKey::Int(*u as i64),
-            crate::Value::Float32(f) => IndexKey::Float(*f as f64),
-            crate::Value::Float64(f) => IndexKey::Float(*f),
-            crate::Value::String(s) => IndexKey::String(s.clone()),
-            crate::Value::Timestamp(t) => IndexKey::Timestamp(*t),
-            _ => IndexKey::String(value.to_string()),
-        })
+        DocumentUtils::get_field(doc, &self.field).map(IndexKey::from_value)
     }
+}
 
-    /// Convert a Value to an IndexKey
-    fn value_to_key(&self, value: &crate::Value) -> IndexKey {
-        match value {
-            crate::Value::Null => IndexKey::Null,
-            crate::Value::Bool(b) => IndexKey::Bool(*b),
-            crate::Value::Int32(i) => IndexKey::Int(*i as i64),
-            crate::Value::Int64(i) => IndexKey::Int(*i),
-            crate::Value::UInt64(u) => IndexKey::Int(*u as i64),
-            crate::Value::Float32(f) => IndexKey::Float(*f as f64),
-            crate::Value::Float64(f) => IndexKey::Float(*f),
-            crate::Value::String(s) => IndexKey::String(s.clone()),
-            crate::Value::Timestamp(t) => IndexKey::Timestamp(*t),
-            _ => IndexKey::String(value.to_string()),
+impl HashEntries {
+    fn remove(&mut self, id: &DocumentId) {
+        let Some(key) = self.by_document.remove(id) else {
+            return;
+        };
+        if let Some(ids) = self.keys.get_mut(&key) {
+            ids.retain(|existing| existing != id);
+            if ids.is_empty() {
+                self.keys.remove(&key);
+            }
         }
     }
 }
@@ -76,44 +59,24 @@ impl HashIndex {
 #[async_trait::async_trait]
 impl Index for HashIndex {
     async fn insert(&self, id: DocumentId, doc: &Document) -> Result<()> {
+        let mut index = self.index.write().await;
+        index.remove(&id);
         if let Some(key) = self.extract_key(doc) {
-            let mut index = self.index.write().await;
-            index.entry(key).or_default().push(id);
+            index.keys.entry(key.clone()).or_default().push(id);
+            index.by_document.insert(id, key);
             debug!("Inserted document {} into hash index on field '{}'", id, self.field);
         }
         Ok(())
     }
 
     async fn remove(&self, id: &DocumentId) -> Result<()> {
-        let mut index = self.index.write().await;
-        
-        // Find and remove the document ID from all keys
-        let mut keys_to_remove = Vec::new();
-        
-        for (key, ids) in index.iter_mut() {
-            if let Some(pos) = ids.iter().position(|&x| x == *id) {
-                ids.remove(pos);
-                if ids.is_empty() {
-                    keys_to_remove.push(key.clone());
-                }
-                break;
-            }
-        }
-        
-        // Remove empty keys
-        for key in keys_to_remove {
-            index.remove(&key);
-        }
-        
+        self.index.write().await.remove(id);
         debug!("Removed document {} from hash index on field '{}'", id, self.field);
         Ok(())
     }
 
-    async fn update(&self, id: DocumentId, old_doc: &Document, new_doc: &Document) -> Result<()> {
-        // Remove from old key
-        self.remove(&id).await?;
-        
-        // Insert with new key
+    async fn update(&self, id: DocumentId, _old_doc: &Document, new_doc: &Document) -> Result<()> {
+        // Insert replaces whatever key the document was indexed under
         self.insert(id, new_doc).await?;
         
         debug!("Updated document {} in hash index on field '{}'", id, self.field);
@@ -126,8 +89,7 @@ impl Index for HashIndex {
         
         match query {
             IndexQuery::Exact { field, value } if field == &self.field => {
-                let key = self.value_to_key(value);
-                if let Some(ids) = index.get(&key) {
+                if let Some(ids) = index.keys.get(&IndexKey::from_value(value)) {
                     results.extend(ids.iter().cloned());
                 }
             }
@@ -145,9 +107,9 @@ impl Index for HashIndex {
 
     async fn stats(&self) -> Result<IndexStats> {
         let index = self.index.read().await;
-        let total_entries = index.values().map(|ids| ids.len()).sum();
+        let total_entries = index.by_document.len();
         let memory_usage = std::mem::size_of_val(&*index) + 
-            index.iter().map(|(k, v)| std::mem::size_of_val(k) + std::mem::size_of_val(v)).sum::<usize>();
+            index.keys.iter().map(|(k, v)| std::mem::size_of_val(k) + std::mem::size_of_val(v)).sum::<usize>();
         
         Ok(IndexStats {
             total_entries,
@@ -159,4 +121,4 @@ impl Index for HashIndex {
     fn index_type(&self) -> IndexType {
         IndexType::Hash
     }
-}
\ No newline at end of file
+}
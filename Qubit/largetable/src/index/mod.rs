@@ -8,6 +8,7 @@
 
 pub mod adaptive;
 pub mod btree;
+pub mod build;
 pub mod compound;
 pub mod fulltext;
 pub mod geospatial;
@@ -17,15 +18,22 @@ pub mod sparse;
 pub mod timeseries;
 pub mod vector;
 
-use crate::{Result, DocumentId, Document, LargetableError, IndexType, VectorMetric};
+use crate::{Result, DocumentId, Document, LargetableError, IndexType, Value, VectorMetric};
+use crate::engine::memory_limits;
+use crate::storage::StorageEngine;
+use build::{IndexBuild, IndexBuildStatus, SideWrite};
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info};
 
 /// Index manager for a collection
 pub struct IndexManager {
-    indexes: Arc<RwLock<HashMap<String, Box<dyn Index + Send + Sync>>>>,
+    indexes: build::IndexMap,
+    /// Background builds by field, kept after they finish so their outcome can be queried
+    builds: Arc<Mutex<HashMap<String, Arc<IndexBuild>>>>,
     collection_name: String,
 }
 
@@ -59,7 +67,7 @@ pub enum IndexQuery {
         field: String,
         value: crate::Value,
     },
-    /// Range query
+    /// Range query, inclusive of both bounds
     Range {
         field: String,
         min: Option<crate::Value>,
@@ -77,11 +85,18 @@ pub enum IndexQuery {
         limit: usize,
         threshold: Option<f32>,
     },
-    /// Geospatial query
+    /// `$near`, `$geoWithin` or `$geoIntersects` against a 2dsphere index
     Geospatial {
         field: String,
-        center: (f64, f64),
-        radius: f64,
+        query: geospatial::GeoQuery,
+    },
+    /// Compound index query: equality on the leading fields of `index`,
+    /// optionally followed by an inclusive range on the next field
+    Prefix {
+        index: String,
+        equals: Vec<crate::Value>,
+        min: Option<crate::Value>,
+        max: Option<crate::Value>,
     },
     /// Compound query (AND of multiple conditions)
     Compound {
@@ -89,6 +104,101 @@ pub enum IndexQuery {
     },
 }
 
+/// Ordered, hashable form of a field value shared by the B-Tree, hash and
+/// compound indexes.
+///
+/// Integral floats are stored as integers so `30` and `30.0` land on the same
+/// key, and floats order by `total_cmp` so the key is a proper total order.
+#[derive(Debug, Clone)]
+pub enum IndexKey {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Timestamp(i64),
+}
+
+impl IndexKey {
+    /// Key for a field value
+    pub fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Null => IndexKey::Null,
+            Value::Bool(b) => IndexKey::Bool(*b),
+            Value::Int32(i) => IndexKey::Int(*i as i64),
+            Value::Int64(i) => IndexKey::Int(*i),
+            Value::UInt64(u) => IndexKey::Int(*u as i64),
+            Value::Float32(f) => Self::from_float(*f as f64),
+            Value::Float64(f) => Self::from_float(*f),
+            Value::String(s) => IndexKey::String(s.clone()),
+            Value::Timestamp(t) => IndexKey::Timestamp(*t),
+            _ => IndexKey::String(format!("{:?}", value)),
+        }
+    }
+
+    fn from_float(f: f64) -> Self {
+        if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+            IndexKey::Int(f as i64)
+        } else {
+            IndexKey::Float(f)
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            IndexKey::Null => 0,
+            IndexKey::Bool(_) => 1,
+            IndexKey::Int(_) | IndexKey::Float(_) => 2,
+            IndexKey::String(_) => 3,
+            IndexKey::Timestamp(_) => 4,
+        }
+    }
+}
+
+impl Ord for IndexKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (IndexKey::Bool(a), IndexKey::Bool(b)) => a.cmp(b),
+            (IndexKey::Int(a), IndexKey::Int(b)) => a.cmp(b),
+            (IndexKey::Float(a), IndexKey::Float(b)) => a.total_cmp(b),
+            // Never equal: integral floats are stored as Int, so keep Eq consistent with Hash
+            (IndexKey::Int(a), IndexKey::Float(b)) => (*a as f64).total_cmp(b).then(Ordering::Less),
+            (IndexKey::Float(a), IndexKey::Int(b)) => a.total_cmp(&(*b as f64)).then(Ordering::Greater),
+            (IndexKey::String(a), IndexKey::String(b)) => a.cmp(b),
+            (IndexKey::Timestamp(a), IndexKey::Timestamp(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for IndexKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for IndexKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IndexKey {}
+
+impl Hash for IndexKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            IndexKey::Null => {}
+            IndexKey::Bool(b) => b.hash(state),
+            IndexKey::Int(i) => i.hash(state),
+            IndexKey::Float(f) => f.to_bits().hash(state),
+            IndexKey::String(s) => s.hash(state),
+            IndexKey::Timestamp(t) => t.hash(state),
+        }
+    }
+}
+
 /// Index statistics
 #[derive(Debug)]
 pub struct IndexStats {
@@ -102,19 +212,15 @@ impl IndexManager {
     pub fn new(collection_name: String) -> Self {
         Self {
             indexes: Arc::new(RwLock::new(HashMap::new())),
+            builds: Arc::new(Mutex::new(HashMap::new())),
             collection_name,
         }
     }
 
-    /// Create an index on a field
-    pub async fn create_index(&self, field: String, index_type: IndexType) -> Result<()> {
-        let mut indexes = self.indexes.write().await;
-        
-        if indexes.contains_key(&field) {
-            return Err(LargetableError::Index(format!("Index on field '{}' already exists", field)));
-        }
-        
-        let index: Box<dyn Index + Send + Sync> = match index_type {
+    /// Build a new, empty index for a field
+    fn new_index(field: &str, index_type: &IndexType) -> Box<dyn Index + Send + Sync> {
+        let field = field.to_string();
+        match index_type.clone() {
             IndexType::BTree => Box::new(btree::BTreeIndex::new(field.clone())),
             IndexType::Hash => Box::new(hash::HashIndex::new(field.clone())),
             IndexType::FullText { language, stop_words } => {
@@ -129,14 +235,119 @@ impl IndexManager {
             IndexType::TimeSeries { granularity } => {
                 Box::new(timeseries::TimeSeriesIndex::new(field.clone(), granularity))
             }
-        };
+            IndexType::Compound { fields } => {
+                Box::new(compound::CompoundIndex::new(field.clone(), fields))
+            }
+        }
+    }
+
+    /// Create an empty index on a field.
+    ///
+    /// Existing documents are not indexed; use `start_index_build` on a
+    /// populated collection.
+    pub async fn create_index(&self, field: String, index_type: IndexType) -> Result<()> {
+        let mut indexes = self.indexes.write().await;
+        
+        if indexes.contains_key(&field) || self.active_build(&field).is_some() {
+            return Err(LargetableError::Index(format!("Index on field '{}' already exists", field)));
+        }
         
+        let index = Self::new_index(&field, &index_type);
         indexes.insert(field.clone(), index);
         
         info!("Created {:?} index on field '{}' for collection '{}'", index_type, field, self.collection_name);
         Ok(())
     }
 
+    /// Build an index over the documents in `source` in the background.
+    ///
+    /// Writes keep flowing while the build runs; the index becomes visible to
+    /// queries once it is complete.
+    pub async fn start_index_build(
+        &self,
+        field: String,
+        index_type: IndexType,
+        source: Arc<dyn StorageEngine>,
+    ) -> Result<IndexBuildStatus> {
+        let index = Self::new_index(&field, &index_type);
+        self.start_build_with(field, index, source, build::DEFAULT_BUILD_BATCH_SIZE).await
+    }
+
+    pub(crate) async fn start_build_with(
+        &self,
+        field: String,
+        index: Box<dyn Index + Send + Sync>,
+        source: Arc<dyn StorageEngine>,
+        batch_size: usize,
+    ) -> Result<IndexBuildStatus> {
+        // Registering under the write lock means every write after this point is captured
+        let indexes = self.indexes.write().await;
+        if indexes.contains_key(&field) {
+            return Err(LargetableError::Index(format!("Index on field '{}' already exists", field)));
+        }
+
+        let build = {
+            let mut builds = self.builds.lock().unwrap();
+            if builds.get(&field).is_some_and(|build| !build.is_finished()) {
+                return Err(LargetableError::Index(format!("Index on field '{}' is already being built", field)));
+            }
+            let build = Arc::new(IndexBuild::new(field.clone(), index.index_type()));
+            builds.insert(field.clone(), build.clone());
+            build
+        };
+        drop(indexes);
+
+        info!("Started background index build on field '{}' for collection '{}'", field, self.collection_name);
+        let status = build.status();
+        // The build keeps running after the request that started it, against the same memory budget
+        let run = build::run_build(build, index, self.indexes.clone(), source, batch_size);
+        match memory_limits::current_query_memory() {
+            Some(memory) => tokio::spawn(memory_limits::with_query_memory(memory, run)),
+            None => tokio::spawn(run),
+        };
+        Ok(status)
+    }
+
+    /// Progress of the most recent build for a field
+    pub fn index_build_status(&self, field: &str) -> Option<IndexBuildStatus> {
+        self.builds.lock().unwrap().get(field).map(|build| build.status())
+    }
+
+    /// Progress of all builds, running and finished
+    pub fn list_index_builds(&self) -> Vec<IndexBuildStatus> {
+        self.builds.lock().unwrap().values().map(|build| build.status()).collect()
+    }
+
+    /// Abort a running build; returns false if there was none
+    pub fn abort_index_build(&self, field: &str) -> Result<bool> {
+        match self.active_build(field) {
+            Some(build) => {
+                build.abort();
+                info!("Aborting index build on field '{}' for collection '{}'", field, self.collection_name);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn active_build(&self, field: &str) -> Option<Arc<IndexBuild>> {
+        self.builds
+            .lock()
+            .unwrap()
+            .get(field)
+            .filter(|build| !build.is_finished())
+            .cloned()
+    }
+
+    /// Hand a write to every running build; callers hold the index map read lock
+    fn capture(&self, write: impl Fn() -> SideWrite) {
+        for build in self.builds.lock().unwrap().values() {
+            if !build.is_finished() {
+                build.capture(write());
+            }
+        }
+    }
+
     /// Drop an index
     pub async fn drop_index(&self, field: &str) -> Result<bool> {
         let mut indexes = self.indexes.write().await;
@@ -167,6 +378,7 @@ impl IndexManager {
                 return Err(e);
             }
         }
+        self.capture(|| SideWrite::Upsert(id, doc.clone()));
         
         debug!("Inserted document {} into all indexes", id);
         Ok(())
@@ -182,6 +394,7 @@ impl IndexManager {
                 return Err(e);
             }
         }
+        self.capture(|| SideWrite::Remove(*id));
         
         debug!("Removed document {} from all indexes", id);
         Ok(())
@@ -197,6 +410,7 @@ impl IndexManager {
                 return Err(e);
             }
         }
+        self.capture(|| SideWrite::Upsert(id, new_doc.clone()));
         
         debug!("Updated document {} in all indexes", id);
         Ok(())
@@ -211,11 +425,11 @@ impl IndexManager {
                 }
                 
                 // Start with the first query result
-                let mut result = self.search(&queries[0]).await?;
+                let mut result = Box::pin(self.search(&queries[0])).await?;
                 
                 // Intersect with results from other queries
                 for query in &queries[1..] {
-                    let query_result = self.search(query).await?;
+                    let query_result = Box::pin(self.search(query)).await?;
                     result = result.into_iter()
                         .filter(|id| query_result.contains(id))
                         .collect();
@@ -231,6 +445,7 @@ impl IndexManager {
                     IndexQuery::FullText { field, .. } => field,
                     IndexQuery::Vector { field, .. } => field,
                     IndexQuery::Geospatial { field, .. } => field,
+                    IndexQuery::Prefix { index, .. } => index,
                     _ => return Err(LargetableError::Index("Unsupported query type".to_string())),
                 };
                 
@@ -77,7 +77,4 @@ pub use document::Document;
 pub use query::Query;
 
 // === FFI EXPORTS FOR C BINDINGS ===
-#[no_mangle]
-pub extern "C" fn largetable_version() -> *const std::os::raw::c_char {
-    std::ffi::CString::new(env!("CARGO_PKG_VERSION")).unwrap().into_raw()
-}
+pub use ffi::c_bindings::largetable_version;
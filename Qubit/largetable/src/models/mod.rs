@@ -0,0 +1,15 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Data models beyond plain documents
+
+pub mod search;
+pub mod timeseries;
+
+pub use search::{Analyzer, Bm25, SearchHit, SearchIndex, TextQuery};
+pub use timeseries::{
+    Bucket, DownsamplePolicy, Granularity, MaintenanceReport, TimeSeriesCollection, TimeSeriesOptions, TimeSeriesStats,
+};
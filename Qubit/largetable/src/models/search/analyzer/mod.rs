@@ -0,0 +1,395 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Text analysis: tokenization, stop words and stemming
+//!
+//! Documents and queries go through the same analyzer, so "Running shoes"
+//! in a query matches "run" and "shoe" in the index.
+
+use std::collections::HashSet;
+
+/// Common English words dropped from the index
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "am", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "before", "being", "below", "between", "both", "but", "by", "can", "did", "do", "does",
+    "doing", "down", "during", "each", "few", "for", "from", "further", "had", "has", "have", "having", "he", "her",
+    "here", "hers", "herself", "him", "himself", "his", "how", "i", "if", "in", "into", "is", "it", "its", "itself",
+    "just", "me", "more", "most", "my", "myself", "no", "nor", "not", "now", "of", "off", "on", "once", "only", "or",
+    "other", "our", "ours", "ourselves", "out", "over", "own", "same", "she", "should", "so", "some", "such", "than",
+    "that", "the", "their", "theirs", "them", "themselves", "then", "there", "these", "they", "this", "those",
+    "through", "to", "too", "under", "until", "up", "very", "was", "we", "were", "what", "when", "where", "which",
+    "while", "who", "whom", "why", "will", "with", "you", "your", "yours", "yourself", "yourselves",
+];
+
+/// Term produced by the analyzer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub term: String,
+    /// Word offset in the source text, counting dropped stop words
+    pub position: u32,
+}
+
+/// Turns text into index terms
+#[derive(Debug, Clone)]
+pub struct Analyzer {
+    language: String,
+    stop_words: HashSet<String>,
+    stemming: bool,
+}
+
+impl Analyzer {
+    /// Analyzer for a language; `stop_words` are added to the language's own list
+    ///
+    /// English gets the built-in stop word list and Porter stemming. `none`
+    /// disables both; other languages only drop the given stop words.
+    pub fn new(language: &str, stop_words: impl IntoIterator<Item = String>) -> Self {
+        let language = language.to_lowercase();
+        let english = matches!(language.as_str(), "english" | "en");
+
+        let mut words: HashSet<String> = stop_words.into_iter().map(|word| word.to_lowercase()).collect();
+        if english {
+            words.extend(ENGLISH_STOP_WORDS.iter().map(|word| word.to_string()));
+        }
+
+        Self { language, stop_words: words, stemming: english }
+    }
+
+    pub fn english() -> Self {
+        Self::new("english", Vec::new())
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Terms of a text with their positions
+    pub fn analyze(&self, text: &str) -> Vec<Token> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .enumerate()
+            .filter_map(|(position, word)| {
+                let word = word.to_lowercase();
+                if word.chars().count() < 2 || self.stop_words.contains(&word) {
+                    return None;
+                }
+                let term = if self.stemming { stem(&word) } else { word };
+                Some(Token { term, position: position as u32 })
+            })
+            .collect()
+    }
+
+    /// Terms of a text without positions
+    pub fn terms(&self, text: &str) -> Vec<String> {
+        self.analyze(text).into_iter().map(|token| token.term).collect()
+    }
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+/// Reduce an English word to its stem with the Porter algorithm
+///
+/// Words that are not plain lowercase ASCII are returned unchanged.
+pub fn stem(word: &str) -> String {
+    if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+
+    let mut stemmer = PorterStemmer {
+        b: word.as_bytes().to_vec(),
+        k: word.len() as isize - 1,
+        j: 0,
+    };
+    stemmer.step1ab();
+    if stemmer.k > 0 {
+        stemmer.step1c();
+        stemmer.step2();
+        stemmer.step3();
+        stemmer.step4();
+        stemmer.step5();
+    }
+
+    String::from_utf8_lossy(&stemmer.b[..=stemmer.k as usize]).into_owned()
+}
+
+/// Martin Porter's reference algorithm; `b[..=k]` is the word being stemmed
+/// and `j` marks the end of the stem once a suffix has matched
+struct PorterStemmer {
+    b: Vec<u8>,
+    k: isize,
+    j: isize,
+}
+
+impl PorterStemmer {
+    fn at(&self, i: isize) -> u8 {
+        self.b[i as usize]
+    }
+
+    fn is_consonant(&self, i: isize) -> bool {
+        match self.at(i) {
+            b'a' | b'e' | b'i' | b'o' | b'u' => false,
+            b'y' => i == 0 || !self.is_consonant(i - 1),
+            _ => true,
+        }
+    }
+
+    /// Number of vowel-consonant sequences in `b[..=j]`
+    fn measure(&self) -> usize {
+        let mut n = 0;
+        let mut i = 0;
+        loop {
+            if i > self.j {
+                return n;
+            }
+            if !self.is_consonant(i) {
+                break;
+            }
+            i += 1;
+        }
+        i += 1;
+        loop {
+            loop {
+                if i > self.j {
+                    return n;
+                }
+                if self.is_consonant(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+            n += 1;
+            loop {
+                if i > self.j {
+                    return n;
+                }
+                if !self.is_consonant(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+
+    fn vowel_in_stem(&self) -> bool {
+        (0..=self.j).any(|i| !self.is_consonant(i))
+    }
+
+    fn double_consonant(&self, i: isize) -> bool {
+        i >= 1 && self.at(i) == self.at(i - 1) && self.is_consonant(i)
+    }
+
+    /// Consonant-vowel-consonant ending at `i`, where the last consonant is not w, x or y
+    fn cvc(&self, i: isize) -> bool {
+        if i < 2 || !self.is_consonant(i) || self.is_consonant(i - 1) || !self.is_consonant(i - 2) {
+            return false;
+        }
+        !matches!(self.at(i), b'w' | b'x' | b'y')
+    }
+
+    fn ends(&mut self, suffix: &str) -> bool {
+        let suffix = suffix.as_bytes();
+        let len = suffix.len() as isize;
+        if len > self.k + 1 || &self.b[(self.k + 1 - len) as usize..=self.k as usize] != suffix {
+            return false;
+        }
+        self.j = self.k - len;
+        true
+    }
+
+    fn set_to(&mut self, replacement: &str) {
+        let start = (self.j + 1) as usize;
+        self.b.truncate(start);
+        self.b.extend_from_slice(replacement.as_bytes());
+        self.k = self.j + replacement.len() as isize;
+    }
+
+    /// Replace the matched suffix if the stem has at least one vowel-consonant sequence
+    fn replace_if_measured(&mut self, replacement: &str) {
+        if self.measure() > 0 {
+            self.set_to(replacement);
+        }
+    }
+
+    /// Plurals and -ed/-ing
+    fn step1ab(&mut self) {
+        if self.at(self.k) == b's' {
+            if self.ends("sses") {
+                self.k -= 2;
+            } else if self.ends("ies") {
+                self.set_to("i");
+            } else if self.at(self.k - 1) != b's' {
+                self.k -= 1;
+            }
+        }
+
+        if self.ends("eed") {
+            if self.measure() > 0 {
+                self.k -= 1;
+            }
+        } else if (self.ends("ed") || self.ends("ing")) && self.vowel_in_stem() {
+            self.k = self.j;
+            if self.ends("at") {
+                self.set_to("ate");
+            } else if self.ends("bl") {
+                self.set_to("ble");
+            } else if self.ends("iz") {
+                self.set_to("ize");
+            } else if self.double_consonant(self.k) {
+                if !matches!(self.at(self.k), b'l' | b's' | b'z') {
+                    self.k -= 1;
+                }
+            } else {
+                self.j = self.k;
+                if self.measure() == 1 && self.cvc(self.k) {
+                    self.set_to("e");
+                }
+            }
+        }
+        self.b.truncate((self.k + 1) as usize);
+    }
+
+    /// Terminal y to i when the stem has a vowel
+    fn step1c(&mut self) {
+        if self.ends("y") && self.vowel_in_stem() {
+            let k = self.k as usize;
+            self.b[k] = b'i';
+        }
+    }
+
+    /// Double suffixes to single ones, e.g. -ization to -ize
+    fn step2(&mut self) {
+        const RULES: &[(&str, &str)] = &[
+            ("ational", "ate"),
+            ("tional", "tion"),
+            ("enci", "ence"),
+            ("anci", "ance"),
+            ("izer", "ize"),
+            ("bli", "ble"),
+            ("alli", "al"),
+            ("entli", "ent"),
+            ("eli", "e"),
+            ("ousli", "ous"),
+            ("ization", "ize"),
+            ("ation", "ate"),
+            ("ator", "ate"),
+            ("alism", "al"),
+            ("iveness", "ive"),
+            ("fulness", "ful"),
+            ("ousness", "ous"),
+            ("aliti", "al"),
+            ("iviti", "ive"),
+            ("biliti", "ble"),
+            ("logi", "log"),
+        ];
+        self.apply_first(RULES);
+    }
+
+    /// -ic-, -full, -ness and similar
+    fn step3(&mut self) {
+        const RULES: &[(&str, &str)] = &[
+            ("icate", "ic"),
+            ("ative", ""),
+            ("alize", "al"),
+            ("iciti", "ic"),
+            ("ical", "ic"),
+            ("ful", ""),
+            ("ness", ""),
+        ];
+        self.apply_first(RULES);
+    }
+
+    /// Drop -ant, -ence and other suffixes from long enough stems
+    fn step4(&mut self) {
+        const SUFFIXES: &[&str] = &[
+            "al", "ance", "ence", "er", "ic", "able", "ible", "ant", "ement", "ment", "ent", "ion", "ou", "ism", "ate",
+            "iti", "ous", "ive", "ize",
+        ];
+        for suffix in SUFFIXES {
+            if !self.ends(suffix) {
+                continue;
+            }
+            // -ion only goes after s or t
+            if *suffix == "ion" && (self.j < 0 || !matches!(self.at(self.j), b's' | b't')) {
+                return;
+            }
+            if self.measure() > 1 {
+                self.k = self.j;
+            }
+            return;
+        }
+    }
+
+    /// Final -e and double l
+    fn step5(&mut self) {
+        self.j = self.k;
+        if self.at(self.k) == b'e' {
+            let measure = self.measure();
+            if measure > 1 || (measure == 1 && !self.cvc(self.k - 1)) {
+                self.k -= 1;
+            }
+        }
+        if self.at(self.k) == b'l' && self.double_consonant(self.k) && self.measure() > 1 {
+            self.k -= 1;
+        }
+    }
+
+    fn apply_first(&mut self, rules: &[(&str, &str)]) {
+        if self.k < 1 {
+            return;
+        }
+        if let Some((_, replacement)) = rules.iter().find(|(suffix, _)| self.ends(suffix)) {
+            self.replace_if_measured(replacement);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_porter_stems() {
+        let cases = [
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("cats", "cat"),
+            ("running", "run"),
+            ("hopping", "hop"),
+            ("filing", "file"),
+            ("agreed", "agre"),
+            ("happy", "happi"),
+            ("relational", "relat"),
+            ("conditional", "condit"),
+            ("generalization", "gener"),
+            ("hopeful", "hope"),
+            ("goodness", "good"),
+            ("connection", "connect"),
+            ("connected", "connect"),
+            ("adjustment", "adjust"),
+            ("controlling", "control"),
+            ("rate", "rate"),
+        ];
+        for (word, expected) in cases {
+            assert_eq!(stem(word), expected, "stem of {}", word);
+        }
+        assert_eq!(stem("café"), "café");
+    }
+
+    #[test]
+    fn test_analyze_drops_stop_words_and_keeps_positions() {
+        let tokens = Analyzer::english().analyze("The Quick foxes, jumping over the lazy dog!");
+        let terms: Vec<(&str, u32)> = tokens.iter().map(|t| (t.term.as_str(), t.position)).collect();
+        assert_eq!(terms, vec![("quick", 1), ("fox", 2), ("jump", 3), ("lazi", 6), ("dog", 7)]);
+
+        let plain = Analyzer::new("none", vec!["dog".to_string()]);
+        assert_eq!(plain.terms("The lazy dog"), vec!["the", "lazy"]);
+    }
+}
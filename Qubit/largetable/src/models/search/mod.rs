@@ -0,0 +1,327 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Full-text search model
+//!
+//! An inverted index of analyzed terms with their positions, ranked with
+//! BM25. Backs `FullText` indexes and the `$text` query operator, so search
+//! features do not need a separate search cluster.
+
+pub mod analyzer;
+
+pub use analyzer::{stem, Analyzer, Token};
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::DocumentId;
+
+/// BM25 ranking parameters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Bm25 {
+    /// Term frequency saturation
+    pub k1: f64,
+    /// Document length normalization, 0 to 1
+    pub b: f64,
+}
+
+impl Default for Bm25 {
+    fn default() -> Self {
+        Self { k1: 1.2, b: 0.75 }
+    }
+}
+
+/// Parsed `$search` string
+///
+/// Follows MongoDB's syntax: words match any document containing one of
+/// them, `"quoted phrases"` must all appear verbatim, and `-word` excludes
+/// documents containing the word.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextQuery {
+    pub terms: Vec<String>,
+    pub phrases: Vec<Vec<Token>>,
+    pub excluded: Vec<String>,
+}
+
+impl TextQuery {
+    pub fn parse(search: &str, analyzer: &Analyzer) -> Self {
+        let mut query = TextQuery::default();
+
+        for (i, part) in search.split('"').enumerate() {
+            // Odd parts sit between quotes
+            if i % 2 == 1 {
+                let phrase = analyzer.analyze(part);
+                if !phrase.is_empty() {
+                    query.phrases.push(phrase);
+                }
+                continue;
+            }
+            for word in part.split_whitespace() {
+                match word.strip_prefix('-') {
+                    Some(negated) => query.excluded.extend(analyzer.terms(negated)),
+                    None => query.terms.extend(analyzer.terms(word)),
+                }
+            }
+        }
+
+        query
+    }
+
+    fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.phrases.is_empty()
+    }
+
+    /// Terms that contribute to the score
+    fn scored_terms(&self) -> HashSet<&str> {
+        self.terms
+            .iter()
+            .map(String::as_str)
+            .chain(self.phrases.iter().flatten().map(|token| token.term.as_str()))
+            .collect()
+    }
+}
+
+/// Matching document and its relevance
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub id: DocumentId,
+    pub score: f64,
+}
+
+/// Inverted index over one text field
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    analyzer: Analyzer,
+    bm25: Bm25,
+    /// Term to the positions it occurs at in each document
+    postings: HashMap<String, HashMap<DocumentId, Vec<u32>>>,
+    /// Distinct terms of each document, for removal
+    document_terms: HashMap<DocumentId, Vec<String>>,
+    /// Term count of each document, for length normalization
+    document_lengths: HashMap<DocumentId, u32>,
+    total_length: u64,
+}
+
+impl SearchIndex {
+    pub fn new(analyzer: Analyzer) -> Self {
+        Self { analyzer, ..Self::default() }
+    }
+
+    pub fn with_bm25(mut self, bm25: Bm25) -> Self {
+        self.bm25 = bm25;
+        self
+    }
+
+    pub fn analyzer(&self) -> &Analyzer {
+        &self.analyzer
+    }
+
+    /// Number of indexed documents
+    pub fn len(&self) -> usize {
+        self.document_lengths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.document_lengths.is_empty()
+    }
+
+    /// Number of distinct terms
+    pub fn term_count(&self) -> usize {
+        self.postings.len()
+    }
+
+    /// Index a document's text, replacing what was indexed for it before
+    pub fn index(&mut self, id: DocumentId, text: &str) {
+        self.remove(&id);
+
+        let tokens = self.analyzer.analyze(text);
+        if tokens.is_empty() {
+            return;
+        }
+
+        let mut terms = Vec::new();
+        for token in &tokens {
+            let positions = self.postings.entry(token.term.clone()).or_default().entry(id).or_default();
+            if positions.is_empty() {
+                terms.push(token.term.clone());
+            }
+            positions.push(token.position);
+        }
+
+        self.document_terms.insert(id, terms);
+        self.document_lengths.insert(id, tokens.len() as u32);
+        self.total_length += tokens.len() as u64;
+    }
+
+    pub fn remove(&mut self, id: &DocumentId) {
+        let Some(terms) = self.document_terms.remove(id) else {
+            return;
+        };
+        for term in terms {
+            if let Some(documents) = self.postings.get_mut(&term) {
+                documents.remove(id);
+                if documents.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+        if let Some(length) = self.document_lengths.remove(id) {
+            self.total_length -= u64::from(length);
+        }
+    }
+
+    /// Search with MongoDB `$search` syntax, best matches first
+    pub fn search(&self, search: &str) -> Vec<SearchHit> {
+        self.search_query(&TextQuery::parse(search, &self.analyzer))
+    }
+
+    pub fn search_query(&self, query: &TextQuery) -> Vec<SearchHit> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        // Phrases are required; without them any term is enough
+        let mut candidates: HashSet<DocumentId> = if query.phrases.is_empty() {
+            query
+                .terms
+                .iter()
+                .filter_map(|term| self.postings.get(term))
+                .flat_map(|documents| documents.keys().copied())
+                .collect()
+        } else {
+            let mut phrases = query.phrases.iter();
+            let first = phrases.next().map(|phrase| self.phrase_matches(phrase)).unwrap_or_default();
+            phrases.fold(first, |matched, phrase| {
+                let next = self.phrase_matches(phrase);
+                matched.intersection(&next).copied().collect()
+            })
+        };
+
+        for term in &query.excluded {
+            if let Some(documents) = self.postings.get(term) {
+                candidates.retain(|id| !documents.contains_key(id));
+            }
+        }
+
+        let scored = query.scored_terms();
+        let mut hits: Vec<SearchHit> = candidates
+            .into_iter()
+            .map(|id| SearchHit { id, score: scored.iter().map(|term| self.term_score(term, &id)).sum() })
+            .collect();
+        // Ties broken by ID so results are stable
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+        hits
+    }
+
+    /// BM25 contribution of one term to one document
+    fn term_score(&self, term: &str, id: &DocumentId) -> f64 {
+        let Some(documents) = self.postings.get(term) else {
+            return 0.0;
+        };
+        let Some(positions) = documents.get(id) else {
+            return 0.0;
+        };
+
+        let n = self.len() as f64;
+        let df = documents.len() as f64;
+        let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+
+        let tf = positions.len() as f64;
+        let length = f64::from(self.document_lengths.get(id).copied().unwrap_or(0));
+        let average = self.total_length as f64 / n.max(1.0);
+        let norm = self.bm25.k1 * (1.0 - self.bm25.b + self.bm25.b * length / average.max(1.0));
+        idf * tf * (self.bm25.k1 + 1.0) / (tf + norm)
+    }
+
+    /// Documents containing the phrase's terms at the same relative positions
+    fn phrase_matches(&self, phrase: &[Token]) -> HashSet<DocumentId> {
+        let Some((first, rest)) = phrase.split_first() else {
+            return HashSet::new();
+        };
+        let Some(starts) = self.postings.get(&first.term) else {
+            return HashSet::new();
+        };
+
+        starts
+            .iter()
+            .filter(|(id, positions)| {
+                positions.iter().any(|&start| {
+                    rest.iter().all(|token| {
+                        let offset = token.position - first.position;
+                        self.postings
+                            .get(&token.term)
+                            .and_then(|documents| documents.get(*id))
+                            .is_some_and(|positions| positions.contains(&(start + offset)))
+                    })
+                })
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Rough heap footprint in bytes
+    pub fn memory_usage(&self) -> usize {
+        let id = std::mem::size_of::<DocumentId>();
+        self.postings
+            .iter()
+            .map(|(term, documents)| {
+                term.len() + documents.values().map(|positions| id + positions.len() * 4).sum::<usize>()
+            })
+            .sum::<usize>()
+            + self.document_terms.values().map(|terms| id + terms.iter().map(String::len).sum::<usize>()).sum::<usize>()
+            + self.document_lengths.len() * (id + 4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(texts: &[&str]) -> (SearchIndex, Vec<DocumentId>) {
+        let mut index = SearchIndex::new(Analyzer::english());
+        let ids: Vec<DocumentId> = texts.iter().map(|_| uuid::Uuid::new_v4()).collect();
+        for (id, text) in ids.iter().zip(texts) {
+            index.index(*id, text);
+        }
+        (index, ids)
+    }
+
+    #[test]
+    fn test_bm25_ranks_focused_documents_first() {
+        let (index, ids) = index(&[
+            "Sunset over the ocean, shot on film",
+            "Ocean waves, ocean spray and more ocean",
+            "Mountain cabin in the snow",
+        ]);
+
+        let hits = index.search("oceans");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].id, ids[1]);
+        assert!(hits[0].score > hits[1].score);
+        assert!(index.search("desert").is_empty());
+    }
+
+    #[test]
+    fn test_phrases_and_exclusions() {
+        let (mut index, ids) = index(&[
+            "black and white street photography",
+            "white black cat on a street",
+            "street food photography in colour",
+        ]);
+
+        let hits = index.search("\"black and white\" street");
+        assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), vec![ids[0]]);
+
+        let hits = index.search("street -cat");
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| hit.id != ids[1]));
+
+        index.remove(&ids[0]);
+        assert!(index.search("\"black and white\"").is_empty());
+        assert_eq!(index.len(), 2);
+    }
+}
@@ -0,0 +1,189 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Columnar measurement buckets
+//!
+//! A bucket holds the measurements of one series over a fixed time span. Each
+//! field is stored as its own column aligned with the timestamps, so a scan
+//! over one metric touches only that metric and repeated field names are
+//! stored once per bucket instead of once per measurement.
+
+use std::collections::HashMap;
+
+use crate::document::DocumentBuilder;
+use crate::query::aggregation::AccumulatorState;
+use crate::query::Accumulator;
+use crate::{Document, Timestamp, Value};
+
+/// Measurements of one series over `[start, start + span)`
+#[derive(Debug, Clone)]
+pub struct Bucket {
+    pub meta: Value,
+    pub start: Timestamp,
+    pub span: Timestamp,
+    /// Interval each row summarizes; 0 while the bucket holds raw measurements
+    pub resolution: Timestamp,
+    /// Sorted measurement times
+    timestamps: Vec<Timestamp>,
+    /// Field columns aligned with `timestamps`, `Null` where a row lacks the field
+    columns: HashMap<String, Vec<Value>>,
+}
+
+impl Bucket {
+    pub fn new(meta: Value, start: Timestamp, span: Timestamp) -> Self {
+        Self {
+            meta,
+            start,
+            span,
+            resolution: 0,
+            timestamps: Vec::new(),
+            columns: HashMap::new(),
+        }
+    }
+
+    pub fn end(&self) -> Timestamp {
+        self.start + self.span
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    pub fn is_downsampled(&self) -> bool {
+        self.resolution > 0
+    }
+
+    pub fn timestamps(&self) -> &[Timestamp] {
+        &self.timestamps
+    }
+
+    pub fn column(&self, field: &str) -> Option<&[Value]> {
+        self.columns.get(field).map(Vec::as_slice)
+    }
+
+    /// Newest measurement time
+    pub fn max_time(&self) -> Option<Timestamp> {
+        self.timestamps.last().copied()
+    }
+
+    /// Add a measurement, keeping rows in time order
+    pub fn insert(&mut self, time: Timestamp, fields: HashMap<String, Value>) {
+        // Equal timestamps keep arrival order
+        let row = self.timestamps.partition_point(|t| *t <= time);
+        self.timestamps.insert(row, time);
+
+        let rows = self.timestamps.len();
+        for column in self.columns.values_mut() {
+            column.insert(row, Value::Null);
+        }
+        for (field, value) in fields {
+            let column = self.columns.entry(field).or_insert_with(|| vec![Value::Null; rows]);
+            column[row] = value;
+        }
+    }
+
+    /// Rows with `from <= time < to`
+    pub fn rows_between(&self, from: Timestamp, to: Timestamp) -> std::ops::Range<usize> {
+        let start = self.timestamps.partition_point(|t| *t < from);
+        let end = self.timestamps.partition_point(|t| *t < to);
+        start..end.max(start)
+    }
+
+    /// Rebuild a row as a document with the time and meta fields restored
+    pub fn document(&self, row: usize, time_field: &str, meta_field: Option<&str>) -> Document {
+        let mut doc = DocumentBuilder::new().field(time_field.to_string(), Value::Timestamp(self.timestamps[row]));
+        if let Some(meta_field) = meta_field {
+            doc = doc.field(meta_field.to_string(), self.meta.clone());
+        }
+        for (field, column) in &self.columns {
+            if !matches!(column[row], Value::Null) {
+                doc = doc.field(field.clone(), column[row].clone());
+            }
+        }
+        doc.build()
+    }
+
+    /// Roll rows up into one row per `interval`, keyed by output field like `$group`
+    pub fn downsample(&self, interval: Timestamp, accumulators: &HashMap<String, Accumulator>) -> Bucket {
+        let mut rolled = Bucket::new(self.meta.clone(), self.start, self.span);
+        rolled.resolution = interval;
+
+        let mut row = 0;
+        while row < self.len() {
+            let slot = self.start + (self.timestamps[row] - self.start).div_euclid(interval) * interval;
+            let end = row + self.timestamps[row..].partition_point(|t| *t < slot + interval);
+
+            let mut states: Vec<(&String, &Accumulator, AccumulatorState)> = accumulators
+                .iter()
+                .map(|(name, accumulator)| (name, accumulator, AccumulatorState::new(accumulator)))
+                .collect();
+            for source in row..end {
+                let doc = self.row_fields(source);
+                for (_, accumulator, state) in states.iter_mut() {
+                    state.add(accumulator, &doc);
+                }
+            }
+            rolled.insert(slot, states.into_iter().map(|(name, _, state)| (name.clone(), state.finish())).collect());
+            row = end;
+        }
+        rolled
+    }
+
+    /// Row fields without the time and meta fields, for accumulators
+    fn row_fields(&self, row: usize) -> Document {
+        let mut doc = DocumentBuilder::new().build();
+        for (field, column) in &self.columns {
+            doc.fields.insert(field.clone(), column[row].clone());
+        }
+        doc
+    }
+
+    /// Rough heap footprint in bytes
+    pub fn memory_usage(&self) -> usize {
+        let value = std::mem::size_of::<Value>();
+        self.timestamps.len() * std::mem::size_of::<Timestamp>()
+            + self
+                .columns
+                .iter()
+                .map(|(field, column)| field.len() + column.len() * value)
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, Value)]) -> HashMap<String, Value> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.clone())).collect()
+    }
+
+    #[test]
+    fn test_columns_stay_aligned_and_downsample() {
+        let mut bucket = Bucket::new(Value::String("cpu".to_string()), 0, 3_600_000_000);
+        bucket.insert(120_000_000, fields(&[("load", Value::Int64(30))]));
+        bucket.insert(0, fields(&[("load", Value::Int64(10))]));
+        bucket.insert(60_000_000, fields(&[("load", Value::Int64(20)), ("temp", Value::Float64(55.5))]));
+
+        assert_eq!(bucket.timestamps(), &[0, 60_000_000, 120_000_000]);
+        assert!(matches!(bucket.column("temp").unwrap(), [Value::Null, Value::Float64(_), Value::Null]));
+        assert_eq!(bucket.rows_between(60_000_000, 120_000_000), 1..2);
+
+        let mut accumulators = HashMap::new();
+        accumulators.insert("load".to_string(), Accumulator::Avg("load".to_string()));
+        accumulators.insert("samples".to_string(), Accumulator::Count);
+        let rolled = bucket.downsample(100_000_000, &accumulators);
+
+        assert!(rolled.is_downsampled());
+        assert_eq!(rolled.timestamps(), &[0, 100_000_000]);
+        assert!(matches!(rolled.column("load").unwrap(), [Value::Float64(a), Value::Float64(b)] if *a == 15.0 && *b == 30.0));
+        assert!(matches!(rolled.column("samples").unwrap(), [Value::Int64(2), Value::Int64(1)]));
+    }
+}
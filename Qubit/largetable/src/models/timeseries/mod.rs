@@ -0,0 +1,412 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Time-series collections
+//!
+//! Measurements are grouped by their meta field (the series, e.g. an event
+//! type or a device) and by time into columnar buckets, MongoDB style. Old
+//! buckets are rolled up by downsampling policies and eventually expired, so
+//! high-volume analytics events stay cheap to keep. Measurements are not
+//! addressable by ID; they are read back by time range and series.
+
+pub mod bucket;
+
+pub use bucket::Bucket;
+
+use std::collections::{BTreeMap, HashMap};
+
+use futures::stream::{self, StreamExt};
+
+use crate::query::{Accumulator, DocumentStream};
+use crate::{Document, DocumentId, LargetableError, Result, Timestamp, Value};
+
+const SECOND: Timestamp = 1_000_000;
+const MINUTE: Timestamp = 60 * SECOND;
+const HOUR: Timestamp = 60 * MINUTE;
+const DAY: Timestamp = 24 * HOUR;
+
+/// Expected spacing of measurements within a series, which sizes the buckets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Seconds,
+    Minutes,
+    Hours,
+}
+
+impl Granularity {
+    /// Time span of one bucket in microseconds
+    pub fn bucket_span(&self) -> Timestamp {
+        match self {
+            Granularity::Seconds => HOUR,
+            Granularity::Minutes => DAY,
+            Granularity::Hours => 30 * DAY,
+        }
+    }
+}
+
+/// Roll up buckets older than `after` into one row per `interval`
+///
+/// Output fields are computed like `$group` accumulators. Later policies roll
+/// up the output of earlier ones, so they should read the earlier policy's
+/// output fields (e.g. sum a `count` rather than count rows again).
+#[derive(Debug, Clone)]
+pub struct DownsamplePolicy {
+    /// Age in microseconds
+    pub after: Timestamp,
+    /// Row interval in microseconds
+    pub interval: Timestamp,
+    pub accumulators: HashMap<String, Accumulator>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeSeriesOptions {
+    /// Field holding the measurement time, a timestamp or microseconds
+    pub time_field: String,
+    /// Field identifying the series; measurements without it share one series
+    pub meta_field: Option<String>,
+    pub granularity: Granularity,
+    /// Drop buckets whose newest measurement is older than this, in microseconds
+    pub expire_after: Option<Timestamp>,
+    /// Ordered by increasing age
+    pub downsampling: Vec<DownsamplePolicy>,
+}
+
+impl TimeSeriesOptions {
+    pub fn new(time_field: impl Into<String>) -> Self {
+        Self {
+            time_field: time_field.into(),
+            meta_field: None,
+            granularity: Granularity::Seconds,
+            expire_after: None,
+            downsampling: Vec::new(),
+        }
+    }
+
+    pub fn meta_field(mut self, field: impl Into<String>) -> Self {
+        self.meta_field = Some(field.into());
+        self
+    }
+
+    pub fn granularity(mut self, granularity: Granularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    pub fn expire_after(mut self, age: Timestamp) -> Self {
+        self.expire_after = Some(age);
+        self
+    }
+
+    pub fn downsample(mut self, policy: DownsamplePolicy) -> Self {
+        self.downsampling.push(policy);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        let span = self.granularity.bucket_span();
+        let mut previous: Option<&DownsamplePolicy> = None;
+        for policy in &self.downsampling {
+            if policy.interval <= 0 || policy.interval > span {
+                return Err(LargetableError::Config(format!(
+                    "Downsampling interval must be between 1 and {} microseconds",
+                    span
+                )));
+            }
+            if policy.accumulators.is_empty() {
+                return Err(LargetableError::Config("Downsampling policy has no output fields".to_string()));
+            }
+            if let Some(previous) = previous {
+                if policy.after <= previous.after || policy.interval <= previous.interval {
+                    return Err(LargetableError::Config(
+                        "Downsampling policies must increase in age and interval".to_string(),
+                    ));
+                }
+            }
+            previous = Some(policy);
+        }
+        Ok(())
+    }
+}
+
+/// What a maintenance pass changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub downsampled_buckets: usize,
+    pub expired_buckets: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimeSeriesStats {
+    pub series: usize,
+    pub buckets: usize,
+    pub downsampled_buckets: usize,
+    /// Stored rows, raw or rolled up
+    pub measurements: usize,
+    pub memory_usage: usize,
+}
+
+/// A collection of measurements stored in time buckets
+#[derive(Debug, Clone)]
+pub struct TimeSeriesCollection {
+    options: TimeSeriesOptions,
+    /// Keyed by series, then bucket start
+    buckets: BTreeMap<(String, Timestamp), Bucket>,
+}
+
+impl TimeSeriesCollection {
+    pub fn new(options: TimeSeriesOptions) -> Result<Self> {
+        options.validate()?;
+        Ok(Self { options, buckets: BTreeMap::new() })
+    }
+
+    pub fn options(&self) -> &TimeSeriesOptions {
+        &self.options
+    }
+
+    /// Store a measurement
+    ///
+    /// Opening a new bucket also runs maintenance, so downsampling and expiry
+    /// keep up with ingestion without a separate job. Measurements old enough
+    /// to be downsampled already are rejected, since they can no longer be
+    /// stored raw.
+    pub fn insert(&mut self, doc: &Document) -> Result<()> {
+        let time = match doc.fields.get(&self.options.time_field) {
+            Some(Value::Timestamp(t)) | Some(Value::Int64(t)) => *t,
+            Some(other) => {
+                return Err(LargetableError::Storage(format!(
+                    "Time field {} must be a timestamp, got {:?}",
+                    self.options.time_field, other
+                )))
+            }
+            None => {
+                return Err(LargetableError::Storage(format!(
+                    "Measurement has no time field {}",
+                    self.options.time_field
+                )))
+            }
+        };
+        let now = chrono::Utc::now().timestamp_micros();
+        if let Some(policy) = self.options.downsampling.first() {
+            if time < now - policy.after {
+                return Err(LargetableError::Storage(format!(
+                    "Measurement at {} is older than the downsampling age",
+                    time
+                )));
+            }
+        }
+
+        let meta = self
+            .options
+            .meta_field
+            .as_ref()
+            .and_then(|field| doc.fields.get(field))
+            .cloned()
+            .unwrap_or(Value::Null);
+
+        let span = self.options.granularity.bucket_span();
+        let start = time.div_euclid(span) * span;
+        let key = (series_key(&meta), start);
+        let opened = !self.buckets.contains_key(&key);
+
+        let bucket = self.buckets.entry(key).or_insert_with(|| Bucket::new(meta, start, span));
+        if bucket.is_downsampled() {
+            return Err(LargetableError::Storage(format!(
+                "Measurement at {} falls in a bucket that was already downsampled",
+                time
+            )));
+        }
+
+        let fields = doc
+            .fields
+            .iter()
+            .filter(|(name, _)| **name != self.options.time_field && Some(*name) != self.options.meta_field.as_ref())
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        bucket.insert(time, fields);
+
+        if opened {
+            self.maintain(now);
+        }
+        Ok(())
+    }
+
+    pub fn insert_many(&mut self, docs: &[Document]) -> Result<usize> {
+        for doc in docs {
+            self.insert(doc)?;
+        }
+        Ok(docs.len())
+    }
+
+    /// Apply downsampling policies and expiry as of `now`
+    pub fn maintain(&mut self, now: Timestamp) -> MaintenanceReport {
+        let mut report = MaintenanceReport::default();
+
+        if let Some(expire_after) = self.options.expire_after {
+            let before = self.buckets.len();
+            self.buckets.retain(|_, bucket| bucket.max_time().is_some_and(|t| t >= now - expire_after));
+            report.expired_buckets = before - self.buckets.len();
+        }
+
+        // A bucket is rolled up once all of it is older than the policy's age;
+        // the oldest matching policy wins
+        for bucket in self.buckets.values_mut() {
+            let policy = self
+                .options
+                .downsampling
+                .iter()
+                .rev()
+                .find(|policy| bucket.end() <= now - policy.after && bucket.resolution < policy.interval);
+            if let Some(policy) = policy {
+                *bucket = bucket.downsample(policy.interval, &policy.accumulators);
+                report.downsampled_buckets += 1;
+            }
+        }
+
+        report
+    }
+
+    /// Measurements with `from <= time < to`, optionally of one series, in series then time order
+    pub fn find(&self, from: Option<Timestamp>, to: Option<Timestamp>, meta: Option<&Value>) -> Vec<(DocumentId, Document)> {
+        let (from, to) = (from.unwrap_or(Timestamp::MIN), to.unwrap_or(Timestamp::MAX));
+        let series = meta.map(series_key);
+        let time_field = self.options.time_field.as_str();
+        let meta_field = self.options.meta_field.as_deref();
+
+        self.buckets
+            .iter()
+            .filter(|((key, _), bucket)| {
+                series.as_ref().is_none_or(|series| series == key) && bucket.start < to && bucket.end() > from
+            })
+            .flat_map(|(_, bucket)| {
+                bucket
+                    .rows_between(from, to)
+                    .map(move |row| (uuid::Uuid::now_v7(), bucket.document(row, time_field, meta_field)))
+            })
+            .collect()
+    }
+
+    /// Matching measurements as an aggregation pipeline source
+    pub fn stream(&self, from: Option<Timestamp>, to: Option<Timestamp>, meta: Option<&Value>) -> DocumentStream<'static> {
+        stream::iter(self.find(from, to, meta).into_iter().map(Ok)).boxed()
+    }
+
+    pub fn buckets(&self) -> impl Iterator<Item = &Bucket> {
+        self.buckets.values()
+    }
+
+    pub fn stats(&self) -> TimeSeriesStats {
+        let mut series: Vec<&String> = self.buckets.keys().map(|(series, _)| series).collect();
+        series.dedup();
+        TimeSeriesStats {
+            series: series.len(),
+            buckets: self.buckets.len(),
+            downsampled_buckets: self.buckets.values().filter(|bucket| bucket.is_downsampled()).count(),
+            measurements: self.buckets.values().map(Bucket::len).sum(),
+            memory_usage: self.buckets.values().map(Bucket::memory_usage).sum(),
+        }
+    }
+}
+
+/// Stable key of a series' meta value
+///
+/// Embedded documents get fresh IDs whenever they are parsed, so they are keyed
+/// by their fields in name order rather than compared as a whole.
+fn series_key(meta: &Value) -> String {
+    match meta {
+        Value::Document(doc) => {
+            let mut fields: Vec<(&String, &Value)> = doc.fields.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = fields.into_iter().map(|(name, value)| format!("{}:{}", name, series_key(value))).collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => format!("[{}]", items.iter().map(series_key).collect::<Vec<_>>().join(",")),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{DocumentBuilder, DocumentUtils};
+    use crate::query::{AggregationPipeline, SortDirection, SortField, WindowBounds, WindowField, WindowFunction};
+
+    fn event(kind: &str, time: Timestamp, duration: i64) -> Document {
+        DocumentBuilder::new()
+            .string("event", kind)
+            .field("ts".to_string(), Value::Timestamp(time))
+            .int("duration_ms", duration)
+            .build()
+    }
+
+    fn per_minute() -> DownsamplePolicy {
+        let mut accumulators = HashMap::new();
+        accumulators.insert("duration_ms".to_string(), Accumulator::Avg("duration_ms".to_string()));
+        accumulators.insert("count".to_string(), Accumulator::Count);
+        DownsamplePolicy { after: DAY, interval: MINUTE, accumulators }
+    }
+
+    #[test]
+    fn test_bucketing_downsampling_and_expiry() {
+        let options = TimeSeriesOptions::new("ts")
+            .meta_field("event")
+            .downsample(per_minute())
+            .expire_after(30 * DAY);
+        let mut events = TimeSeriesCollection::new(options).unwrap();
+
+        let base = chrono::Utc::now().timestamp_micros().div_euclid(HOUR) * HOUR;
+        for (i, kind) in ["view", "view", "like", "view"].iter().enumerate() {
+            events.insert(&event(kind, base + i as i64 * SECOND, 10 * (i as i64 + 1))).unwrap();
+        }
+        events.insert(&event("view", base + 36 * HOUR, 5)).unwrap();
+        assert!(events.insert(&event("view", base - 2 * DAY, 5)).is_err());
+
+        // Two days on, the first hour of both series is rolled up
+        let report = events.maintain(base + 2 * DAY);
+        assert_eq!(report.downsampled_buckets, 2);
+        let stats = events.stats();
+        assert_eq!((stats.series, stats.buckets, stats.downsampled_buckets), (2, 3, 2));
+        assert!(events.insert(&event("view", base + 30 * SECOND, 1)).is_err());
+
+        let views = events.find(Some(base), Some(base + HOUR), Some(&Value::String("view".to_string())));
+        assert_eq!(views.len(), 1);
+        let row = &views[0].1;
+        assert!(matches!(DocumentUtils::get_field(row, "count"), Some(Value::Int64(3))));
+        assert!(matches!(DocumentUtils::get_field(row, "duration_ms"), Some(Value::Float64(avg)) if (*avg - 70.0 / 3.0).abs() < 1e-9));
+        assert!(matches!(DocumentUtils::get_field(row, "event"), Some(Value::String(kind)) if kind == "view"));
+
+        let report = events.maintain(base + 40 * DAY);
+        assert_eq!(report.expired_buckets, 3);
+        assert_eq!(events.stats().buckets, 0);
+        assert!(TimeSeriesCollection::new(TimeSeriesOptions::new("ts").downsample(per_minute()).downsample(per_minute())).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_window_functions_over_a_series() {
+        let mut events = TimeSeriesCollection::new(TimeSeriesOptions::new("ts").meta_field("event")).unwrap();
+        let base = chrono::Utc::now().timestamp_micros().div_euclid(HOUR) * HOUR;
+        for (i, duration) in [100, 200, 300, 400].iter().enumerate() {
+            events.insert(&event("upload", base + i as i64 * MINUTE, *duration)).unwrap();
+        }
+
+        let mut output = HashMap::new();
+        output.insert(
+            "running_total".to_string(),
+            WindowField {
+                function: WindowFunction::Accumulate(Accumulator::Sum("duration_ms".to_string())),
+                window: WindowBounds::Documents { lower: None, upper: Some(0) },
+            },
+        );
+        let pipeline = AggregationPipeline::new().set_window_fields(
+            Some("event".to_string()),
+            SortField { field: "ts".to_string(), direction: SortDirection::Ascending },
+            output,
+        );
+
+        let results = pipeline.execute_stream(events.stream(None, None, None)).await.unwrap();
+        let totals: Vec<f64> = results.iter().filter_map(|doc| doc["running_total"].as_f64()).collect();
+        assert_eq!(totals, vec![100.0, 300.0, 600.0, 1000.0]);
+    }
+}
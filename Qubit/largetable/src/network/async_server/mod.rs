@@ -7,24 +7,39 @@
 //! Async HTTP server implementation
 
 use crate::{Result, LargetableError};
+use crate::auth::authorization::bearer_token;
+use crate::auth::{AccessControl, Action, AuthLayer, RoleGrant, ScramFinish, ScramStart, UserInfo, UserStore};
+use crate::collection::partitioning::{PartitionOptions, PartitionStats, PartitionedCollectionStats};
 use crate::config::ServerConfig;
+use crate::database::admin::{CollectionStats, SampleOptions};
+use crate::document::rules::WriteRules;
 use crate::engine::DatabaseEngine;
+use crate::engine::backup::{BackupChunk, BackupOptions, BackupSession, OplogChunk};
+use crate::engine::deadline::DeadlineLayer;
+use crate::network::compression;
+use crate::network::grpc::GrpcService;
+use crate::network::tls;
+use crate::network::router::{QueryRouter, RoutedQueryResult, ShardQuery};
+use crate::sharding::{ChunkMigration, ShardKeyPattern, ShardingMetadata};
+use crate::storage::encryption::{self, KeyProvider, KeyRotationStatus, LocalKeyProvider, PageCipher};
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 /// Largetable HTTP server
 pub struct LargetableServer {
     config: ServerConfig,
     engine: Arc<DatabaseEngine>,
+    /// Set when the server runs as a query router for a sharded cluster
+    router: Option<Arc<QueryRouter>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +49,97 @@ struct HealthResponse {
     uptime: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct CreateIndexRequest {
+    field: String,
+    index_type: crate::IndexType,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryRequest {
+    filter: Option<serde_json::Value>,
+    limit: Option<usize>,
+    skip: Option<usize>,
+    projection: Option<Vec<String>>,
+    /// Run the query even if a cached result is available
+    #[serde(default)]
+    bypass_cache: bool,
+    /// Cluster timestamp to read at, in microseconds since the Unix epoch
+    read_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReadAtParams {
+    read_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CollectionStatsParams {
+    sample_size: Option<usize>,
+    max_depth: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BackupChunkParams {
+    after: Option<crate::DocumentId>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OplogParams {
+    #[serde(default)]
+    after: u64,
+    limit: Option<usize>,
+}
+
+/// Oplog entries returned per request when the client does not ask for fewer
+const DEFAULT_OPLOG_LIMIT: usize = 1_000;
+
+/// How often partitions past retention are dropped from collections that
+/// see no new partitions
+const PARTITION_RETENTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3_600);
+
+#[derive(Debug, Deserialize)]
+struct AddShardRequest {
+    id: String,
+    /// Base URL of the shard's HTTP API
+    host: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShardCollectionRequest {
+    key: ShardKeyPattern,
+    /// Chunks to pre-split a hashed collection into
+    #[serde(default)]
+    initial_chunks: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct SplitChunkRequest {
+    /// Shard key value that becomes the start of the new chunk
+    at: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScramStartRequest {
+    /// Client-first message
+    payload: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScramFinishRequest {
+    conversation_id: String,
+    /// Client-final message
+    payload: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateUserRequest {
+    name: String,
+    password: String,
+    #[serde(default)]
+    roles: Vec<RoleGrant>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ErrorResponse {
     error: String,
@@ -45,33 +151,223 @@ impl LargetableServer {
     pub async fn new(config: ServerConfig) -> Result<Self> {
         config.validate()?;
         
-        let engine = Arc::new(DatabaseEngine::with_default_storage_engine(
-            config.default_storage_engine.clone(),
-        )?);
+        let mut engine = DatabaseEngine::with_default_storage_engine(config.default_storage_engine.clone())
+            .await?
+            .with_data_dir(&config.data_dir)
+            .with_query_cache(config.query_cache.to_config())
+            .with_document_cache(config.document_cache.to_config())
+            .with_memory_limits(config.memory_limits())
+            .with_history(config.history.to_config())
+            .with_access_control(Arc::new(Self::access_control(&config)?));
+        if let Some(cipher) = Self::page_cipher(&config)? {
+            engine = engine.with_encryption(cipher, config.encryption.rotation_batch_size);
+        }
+        let engine = Arc::new(engine);
+        
+        let router = if config.sharding.router {
+            info!("Running as query router with catalog {}", config.sharding.catalog_path);
+            Some(Arc::new(QueryRouter::open(&config.sharding.catalog_path)?))
+        } else {
+            None
+        };
         
         info!("Created Largetable server on {}:{}", config.host, config.port);
         
-        Ok(Self { config, engine })
+        Ok(Self { config, engine, router })
+    }
+
+    /// Users and roles from the configured users file, seeding the admin user into an empty one
+    fn access_control(config: &ServerConfig) -> Result<AccessControl> {
+        if !config.auth.enabled {
+            return Ok(AccessControl::disabled());
+        }
+        let users = UserStore::open(&config.auth.users_path)?.with_iterations(config.auth.scram_iterations);
+        if users.is_empty() {
+            let Some(password) = &config.auth.initial_admin_password else {
+                return Err(LargetableError::Config(
+                    "Authentication is enabled but there are no users; set an initial admin password".to_string(),
+                ));
+            };
+            users.create_user("admin", password, crate::auth::rbac::all_roles())?;
+            info!("Created initial user 'admin'");
+        }
+        Ok(AccessControl::new(users, std::time::Duration::from_secs(config.auth.session_ttl_secs)))
+    }
+
+    /// Cipher over the configured keyring, when encryption at rest is on
+    fn page_cipher(config: &ServerConfig) -> Result<Option<Arc<PageCipher>>> {
+        if !config.encryption.enabled {
+            return Ok(None);
+        }
+        let master = encryption::load_master_key(&config.encryption.master_key_path)?;
+        let provider = LocalKeyProvider::open(&config.encryption.keyring_path, &master)?;
+        info!("Encryption at rest enabled with data key {}", provider.active_key_id());
+        Ok(Some(Arc::new(PageCipher::new(Arc::new(provider)))))
     }
 
     /// Run the server
     pub async fn run(self) -> Result<()> {
+        if let Some(router) = self.router.clone() {
+            return self.run_router(router).await;
+        }
+
+        if let Some(grpc_port) = self.config.grpc_port {
+            let addr = format!("{}:{}", self.config.host, grpc_port)
+                .parse()
+                .map_err(|e| LargetableError::Config(format!("Invalid gRPC address: {}", e)))?;
+            let mut service = GrpcService::new(self.engine.clone())
+                .with_compression(self.config.wire_compression.enabled)
+                .with_deadlines(self.deadline_layer());
+            if self.config.tls.enabled {
+                service = service.with_tls(self.tls_acceptor()?);
+            }
+            tokio::spawn(async move {
+                if let Err(e) = service.serve(addr).await {
+                    error!("{}", e);
+                }
+            });
+        }
+
+        {
+            let engine = self.engine.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(PARTITION_RETENTION_INTERVAL);
+                loop {
+                    ticker.tick().await;
+                    let report = engine.enforce_partition_retention().await;
+                    if !report.dropped_partitions.is_empty() {
+                        debug!("Retention sweep dropped {} partitions", report.dropped_partitions.len());
+                    }
+                }
+            });
+        }
+
+        if self.config.encryption.enabled && self.config.encryption.rotation_interval_secs > 0 {
+            let engine = self.engine.clone();
+            let interval = std::time::Duration::from_secs(self.config.encryption.rotation_interval_secs);
+            tokio::spawn(async move {
+                // The first rotation waits a full interval rather than running at every startup
+                let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = engine.start_key_rotation().await {
+                        error!("Scheduled key rotation failed: {}", e);
+                    }
+                }
+            });
+        }
+
         let app = Router::new()
             .route("/health", get(health_handler))
+            .route("/auth/scram/start", post(scram_start_handler))
+            .route("/auth/scram/finish", post(scram_finish_handler))
+            .route("/auth/logout", post(logout_handler))
+            .route("/users", get(list_users_handler).post(create_user_handler))
+            .route("/users/:name", delete(drop_user_handler))
+            .route("/users/:name/roles", put(set_user_roles_handler))
             .route("/stats", get(stats_handler))
+            .route("/query-cache", get(query_cache_stats_handler).delete(clear_query_cache_handler))
+            .route("/encryption", get(encryption_status_handler))
+            .route("/encryption/rotate", post(rotate_key_handler))
             .route("/databases", get(list_databases_handler))
             .route("/databases/:db", post(create_database_handler))
             .route("/databases/:db/collections", get(list_collections_handler))
             .route("/databases/:db/collections/:collection", post(create_collection_handler))
+            .route("/databases/:db/collections/:collection/rules", get(write_rules_handler).put(set_write_rules_handler))
+            .route("/databases/:db/collections/:collection/stats", get(collection_stats_handler))
+            .route("/databases/:db/collections/:collection/partitioning", put(create_partitioned_collection_handler))
+            .route("/databases/:db/collections/:collection/partitions", get(partition_stats_handler))
+            .route("/databases/:db/collections/:collection/partitions/:start", delete(drop_partition_handler))
             .route("/databases/:db/collections/:collection/documents", post(insert_document_handler))
-            .route("/databases/:db/collections/:collection/documents/:id", get(find_document_handler))
+            .route(
+                "/databases/:db/collections/:collection/documents/:id",
+                get(find_document_handler).delete(delete_document_handler),
+            )
             .route("/databases/:db/collections/:collection/query", post(query_handler))
+            .route("/databases/:db/collections/:collection/indexes", get(list_indexes_handler).post(create_index_handler))
+            .route("/databases/:db/collections/:collection/indexes/builds", get(list_index_builds_handler))
+            .route(
+                "/databases/:db/collections/:collection/indexes/builds/:field",
+                get(index_build_status_handler).delete(abort_index_build_handler),
+            )
+            .route("/databases/:db/backups", post(open_backup_handler))
+            .route("/databases/:db/oplog", get(oplog_handler))
+            .route("/backups/:session", get(backup_session_handler).delete(close_backup_handler))
+            .route("/backups/:session/collections/:collection", get(backup_chunk_handler))
+            .layer(AuthLayer::new(self.engine.access_control().clone()))
             .with_state(self.engine);
 
+        self.serve(app).await
+    }
+
+    /// Serve the document API by forwarding to shards, plus the catalog admin API
+    async fn run_router(self, router: Arc<QueryRouter>) -> Result<()> {
+        if self.config.sharding.balancer_interval_secs > 0 {
+            let balancer_router = router.clone();
+            let interval = std::time::Duration::from_secs(self.config.sharding.balancer_interval_secs);
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(interval);
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = balancer_router.balance().await {
+                        error!("Balancing round failed: {}", e);
+                    }
+                }
+            });
+        }
+
+        let app = Router::new()
+            .route("/health", get(health_handler))
+            .route("/sharding", get(sharding_status_handler))
+            .route("/sharding/shards", post(add_shard_handler))
+            .route("/sharding/collections/:db/:collection", post(shard_collection_handler))
+            .route("/sharding/chunks/:chunk/split", post(split_chunk_handler))
+            .route("/sharding/balance", post(balance_handler))
+            .route("/databases/:db/collections/:collection/documents", post(routed_insert_handler))
+            .route(
+                "/databases/:db/collections/:collection/documents/:id",
+                get(routed_find_handler).delete(routed_delete_handler),
+            )
+            .route("/databases/:db/collections/:collection/query", post(routed_query_handler))
+            .with_state(router);
+
+        self.serve(app).await
+    }
+
+    fn tls_acceptor(&self) -> Result<tokio_rustls::TlsAcceptor> {
+        tls::acceptor(&self.config.tls.cert_path, &self.config.tls.key_path, self.config.tls.min_version)
+    }
+
+    /// Deadlines for client operations, counted in the engine's cancellation stats
+    fn deadline_layer(&self) -> DeadlineLayer {
+        DeadlineLayer::new(self.engine.cancellation_metrics().clone())
+            .with_default_timeout(self.config.deadlines.default_timeout())
+            .with_max_timeout(self.config.deadlines.max_timeout())
+    }
+
+    async fn serve(self, app: Router) -> Result<()> {
+        // Every request, including those a query router forwards to shards with their remaining time
+        let app = app.layer(self.deadline_layer());
+        let app = if self.config.wire_compression.enabled {
+            let options = Arc::new(self.config.wire_compression.to_options());
+            app.layer(axum::middleware::from_fn_with_state(options, compression::compress))
+        } else {
+            app
+        };
+
         let listener = tokio::net::TcpListener::bind(format!("{}:{}", self.config.host, self.config.port))
             .await
             .map_err(|e| LargetableError::Network(format!("Failed to bind to address: {}", e)))?;
 
+        if self.config.tls.enabled {
+            let acceptor = self.tls_acceptor()?;
+            info!(
+                "🚀 Largetable server running on {}:{} (TLS {:?} and later)",
+                self.config.host, self.config.port, self.config.tls.min_version
+            );
+            return tls::serve(listener, acceptor, app).await;
+        }
+
         info!("🚀 Largetable server running on {}:{}", self.config.host, self.config.port);
         
         axum::serve(listener, app)
@@ -90,23 +386,130 @@ async fn health_handler() -> Json<HealthResponse> {
     })
 }
 
+/// Status code for a failed engine operation
+fn engine_error(action: &str, e: LargetableError) -> StatusCode {
+    match e {
+        LargetableError::Auth(e) => {
+            debug!("Rejected {}: {}", action, e);
+            StatusCode::UNAUTHORIZED
+        }
+        LargetableError::PermissionDenied(e) => {
+            debug!("Rejected {}: {}", action, e);
+            StatusCode::FORBIDDEN
+        }
+        LargetableError::Query(e) | LargetableError::Validation(e) => {
+            debug!("Rejected {}: {}", action, e);
+            StatusCode::BAD_REQUEST
+        }
+        // Read at a timestamp whose history has been trimmed
+        LargetableError::SnapshotUnavailable(e) => {
+            debug!("Rejected {}: {}", action, e);
+            StatusCode::GONE
+        }
+        LargetableError::DeadlineExceeded(e) => {
+            debug!("Stopped {}: {}", action, e);
+            StatusCode::GATEWAY_TIMEOUT
+        }
+        // Over the memory limits with spilling off, or a stage that cannot spill
+        LargetableError::ResourceExhausted(e) => {
+            warn!("Stopped {}: {}", action, e);
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        // Nobody is left to read the reply; the status only shows in access logs
+        LargetableError::Cancelled(e) => {
+            debug!("Stopped {}: {}", action, e);
+            StatusCode::REQUEST_TIMEOUT
+        }
+        e => {
+            error!("Failed to {}: {}", action, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Answer a client-first message with the server-first message
+async fn scram_start_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Json(request): Json<ScramStartRequest>,
+) -> Result<Json<ScramStart>, StatusCode> {
+    match engine.access_control().start_scram(&request.payload) {
+        Ok(start) => Ok(Json(start)),
+        Err(LargetableError::ResourceExhausted(e)) => {
+            debug!("Rejected authentication: {}", e);
+            Err(StatusCode::TOO_MANY_REQUESTS)
+        }
+        Err(e) => Err(engine_error("start authentication", e)),
+    }
+}
+
+/// Verify the client-final message and open a session
+async fn scram_finish_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Json(request): Json<ScramFinishRequest>,
+) -> Result<Json<ScramFinish>, StatusCode> {
+    engine
+        .access_control()
+        .finish_scram(&request.conversation_id, &request.payload)
+        .map(Json)
+        .map_err(|e| engine_error("finish authentication", e))
+}
+
+async fn logout_handler(State(engine): State<Arc<DatabaseEngine>>, headers: HeaderMap) -> StatusCode {
+    match bearer_token(&headers) {
+        Some(token) if engine.access_control().end_session(token) => StatusCode::NO_CONTENT,
+        _ => StatusCode::UNAUTHORIZED,
+    }
+}
+
+async fn list_users_handler(State(engine): State<Arc<DatabaseEngine>>) -> Result<Json<Vec<UserInfo>>, StatusCode> {
+    engine.list_users().map(Json).map_err(|e| engine_error("list users", e))
+}
+
+async fn create_user_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Json(request): Json<CreateUserRequest>,
+) -> StatusCode {
+    match engine.create_user(&request.name, &request.password, request.roles) {
+        Ok(()) => StatusCode::CREATED,
+        Err(LargetableError::Query(e)) => {
+            debug!("Rejected user: {}", e);
+            StatusCode::CONFLICT
+        }
+        Err(e) => engine_error("create user", e),
+    }
+}
+
+async fn set_user_roles_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path(name): Path<String>,
+    Json(roles): Json<Vec<RoleGrant>>,
+) -> StatusCode {
+    match engine.set_user_roles(&name, roles) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => engine_error("set user roles", e),
+    }
+}
+
+async fn drop_user_handler(State(engine): State<Arc<DatabaseEngine>>, Path(name): Path<String>) -> StatusCode {
+    match engine.drop_user(&name) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => engine_error("drop user", e),
+    }
+}
+
 async fn stats_handler(State(engine): State<Arc<DatabaseEngine>>) -> Result<Json<serde_json::Value>, StatusCode> {
     match engine.get_stats().await {
         Ok(stats) => Ok(Json(serde_json::to_value(stats).unwrap())),
-        Err(e) => {
-            error!("Failed to get stats: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Err(e) => Err(engine_error("get stats", e)),
     }
 }
 
 async fn list_databases_handler(State(engine): State<Arc<DatabaseEngine>>) -> Result<Json<Vec<String>>, StatusCode> {
     match engine.list_databases().await {
         Ok(databases) => Ok(Json(databases)),
-        Err(e) => {
-            error!("Failed to list databases: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Err(e) => Err(engine_error("list databases", e)),
     }
 }
 
@@ -114,12 +517,9 @@ async fn create_database_handler(
     State(engine): State<Arc<DatabaseEngine>>,
     Path(db): Path<String>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    match engine.database(db.clone()).await {
-        Ok(_) => Ok(Json(serde_json::json!({"status": "created", "database": db}))),
-        Err(e) => {
-            error!("Failed to create database {}: {}", db, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+    match engine.create_database(db.clone()).await {
+        Ok(()) => Ok(Json(serde_json::json!({"status": "created", "database": db}))),
+        Err(e) => Err(engine_error(&format!("create database {}", db), e)),
     }
 }
 
@@ -127,18 +527,9 @@ async fn list_collections_handler(
     State(engine): State<Arc<DatabaseEngine>>,
     Path(db): Path<String>,
 ) -> Result<Json<Vec<String>>, StatusCode> {
-    match engine.database(db).await {
-        Ok(database) => match database.list_collections().await {
-            Ok(collections) => Ok(Json(collections)),
-            Err(e) => {
-                error!("Failed to list collections: {}", e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        },
-        Err(e) => {
-            error!("Failed to get database: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+    match engine.list_collections(db).await {
+        Ok(collections) => Ok(Json(collections)),
+        Err(e) => Err(engine_error("list collections", e)),
     }
 }
 
@@ -146,12 +537,32 @@ async fn create_collection_handler(
     State(engine): State<Arc<DatabaseEngine>>,
     Path((db, collection)): Path<(String, String)>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    match engine.collection(db, collection.clone()).await {
-        Ok(_) => Ok(Json(serde_json::json!({"status": "created", "collection": collection}))),
-        Err(e) => {
-            error!("Failed to create collection {}: {}", collection, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+    match engine.create_collection(db, collection.clone()).await {
+        Ok(()) => Ok(Json(serde_json::json!({"status": "created", "collection": collection}))),
+        Err(e) => Err(engine_error(&format!("create collection {}", collection), e)),
+    }
+}
+
+async fn write_rules_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection)): Path<(String, String)>,
+) -> Result<Json<WriteRules>, StatusCode> {
+    engine.write_rules(db, collection).await.map(Json).map_err(|e| engine_error("get write rules", e))
+}
+
+/// Replace a collection's defaults, computed fields and validators
+async fn set_write_rules_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection)): Path<(String, String)>,
+    Json(rules): Json<WriteRules>,
+) -> StatusCode {
+    match engine.set_write_rules(db, collection, rules).await {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(LargetableError::Query(e)) => {
+            debug!("Rejected write rules: {}", e);
+            StatusCode::BAD_REQUEST
         }
+        Err(e) => engine_error("set write rules", e),
     }
 }
 
@@ -163,10 +574,7 @@ async fn insert_document_handler(
     match crate::document::DocumentUtils::from_json(document) {
         Ok(doc) => match engine.insert_document(db, collection, doc).await {
             Ok(id) => Ok(Json(serde_json::json!({"id": id.to_string()}))),
-            Err(e) => {
-                error!("Failed to insert document: {}", e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            Err(e) => Err(engine_error("insert document", e)),
         },
         Err(e) => {
             error!("Failed to parse document: {}", e);
@@ -178,21 +586,16 @@ async fn insert_document_handler(
 async fn find_document_handler(
     State(engine): State<Arc<DatabaseEngine>>,
     Path((db, collection, id)): Path<(String, String, String)>,
+    Query(params): Query<ReadAtParams>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     match uuid::Uuid::parse_str(&id) {
-        Ok(doc_id) => match engine.find_document_by_id(db, collection, doc_id).await {
+        Ok(doc_id) => match find_document(&engine, db, collection, doc_id, params.read_at).await {
             Ok(Some(doc)) => match crate::document::DocumentUtils::to_json(&doc) {
                 Ok(json) => Ok(Json(json)),
-                Err(e) => {
-                    error!("Failed to serialize document: {}", e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
+                Err(e) => Err(engine_error("serialize document", e)),
             },
             Ok(None) => Err(StatusCode::NOT_FOUND),
-            Err(e) => {
-                error!("Failed to find document: {}", e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            Err(e) => Err(engine_error("find document", e)),
         },
         Err(e) => {
             error!("Invalid document ID: {}", e);
@@ -201,14 +604,47 @@ async fn find_document_handler(
     }
 }
 
+async fn find_document(
+    engine: &DatabaseEngine,
+    db: String,
+    collection: String,
+    id: crate::DocumentId,
+    read_at: Option<i64>,
+) -> Result<Option<crate::Document>> {
+    match read_at {
+        Some(timestamp) => engine.find_document_by_id_at(db, collection, id, timestamp).await,
+        None => engine.find_document_by_id(db, collection, id).await,
+    }
+}
+
+async fn delete_document_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection, id)): Path<(String, String, String)>,
+) -> StatusCode {
+    let Ok(doc_id) = uuid::Uuid::parse_str(&id) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    match engine.delete_document_by_id(db, collection, doc_id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => engine_error("delete document", e),
+    }
+}
+
 async fn query_handler(
     State(engine): State<Arc<DatabaseEngine>>,
     Path((db, collection)): Path<(String, String)>,
-    Json(query): Json<serde_json::Value>,
+    Json(request): Json<QueryRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    // Simple query implementation - in a real system, this would parse the query
-    // and convert it to the appropriate Query type
-    let largetable_query = crate::query::Query::new();
+    let largetable_query = crate::query::Query {
+        filter: request.filter,
+        limit: request.limit,
+        skip: request.skip,
+        projection: request.projection,
+        bypass_cache: request.bypass_cache,
+        read_at: request.read_at,
+        ..crate::query::Query::new()
+    };
     
     match engine.query(db, collection, largetable_query).await {
         Ok(result) => {
@@ -223,9 +659,356 @@ async fn query_handler(
                 "has_more": result.has_more
             })))
         }
+        Err(e) => Err(engine_error("execute query", e)),
+    }
+}
+
+async fn query_cache_stats_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+) -> Result<Json<crate::query::QueryCacheStats>, StatusCode> {
+    engine.authorize(Action::ServerStatus, None).map_err(|e| engine_error("get query cache stats", e))?;
+    Ok(Json(engine.query_cache_stats()))
+}
+
+async fn clear_query_cache_handler(State(engine): State<Arc<DatabaseEngine>>) -> StatusCode {
+    if let Err(e) = engine.authorize(Action::ServerStatus, None) {
+        return engine_error("clear query cache", e);
+    }
+    engine.clear_query_cache();
+    debug!("Cleared query cache");
+    StatusCode::NO_CONTENT
+}
+
+/// Active data key and rotation progress; 404 when encryption at rest is off
+async fn encryption_status_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+) -> Result<Json<KeyRotationStatus>, StatusCode> {
+    match engine.encryption_status() {
+        Ok(Some(status)) => Ok(Json(status)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(engine_error("get encryption status", e)),
+    }
+}
+
+/// Activate a new data key; existing documents are resealed in the background
+async fn rotate_key_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match engine.rotate_encryption_key().await {
+        Ok(key_id) => Ok((StatusCode::ACCEPTED, Json(serde_json::json!({"status": "rotating", "active_key_id": key_id})))),
+        Err(LargetableError::Config(e)) => {
+            debug!("Rejected key rotation: {}", e);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(LargetableError::Query(e)) => {
+            debug!("Rejected key rotation: {}", e);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => Err(engine_error("rotate encryption key", e)),
+    }
+}
+
+async fn list_indexes_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match engine.list_indexes(db, collection).await {
+        Ok(indexes) => Ok(Json(serde_json::json!({"indexes": indexes}))),
+        Err(e) => Err(engine_error("list indexes", e)),
+    }
+}
+
+/// Count, size, indexes and schema of a collection, inferred from a random sample
+async fn collection_stats_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection)): Path<(String, String)>,
+    Query(params): Query<CollectionStatsParams>,
+) -> Result<Json<CollectionStats>, StatusCode> {
+    let defaults = SampleOptions::default();
+    let options = SampleOptions {
+        sample_size: params.sample_size.unwrap_or(defaults.sample_size),
+        max_depth: params.max_depth.unwrap_or(defaults.max_depth),
+    };
+    match engine.collection_stats(db, collection, options).await {
+        Ok(stats) => Ok(Json(stats)),
+        Err(e) => Err(engine_error("get collection stats", e)),
+    }
+}
+
+/// Create a collection partitioned by time windows
+async fn create_partitioned_collection_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection)): Path<(String, String)>,
+    Json(options): Json<PartitionOptions>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match engine.create_partitioned_collection(db, collection.clone(), options).await {
+        Ok(()) => Ok(Json(serde_json::json!({"status": "created", "collection": collection}))),
+        Err(e) => Err(engine_error(&format!("partition collection {}", collection), e)),
+    }
+}
+
+/// Partitions of a collection with their sizes and time ranges
+async fn partition_stats_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection)): Path<(String, String)>,
+) -> Result<Json<PartitionedCollectionStats>, StatusCode> {
+    engine.partition_stats(db, collection).await.map(Json).map_err(|e| engine_error("get partition stats", e))
+}
+
+/// Drop a partition by its window start, in microseconds
+async fn drop_partition_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection, start)): Path<(String, String, i64)>,
+) -> Result<Json<PartitionStats>, StatusCode> {
+    match engine.drop_partition(db, collection, start).await {
+        Ok(Some(dropped)) => Ok(Json(dropped)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(engine_error("drop partition", e)),
+    }
+}
+
+/// Start a background index build; responds as soon as the build is registered
+async fn create_index_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection)): Path<(String, String)>,
+    Json(request): Json<CreateIndexRequest>,
+) -> Result<(StatusCode, Json<crate::index::build::IndexBuildStatus>), StatusCode> {
+    match engine.create_index(db, collection, request.field, request.index_type).await {
+        Ok(status) => Ok((StatusCode::ACCEPTED, Json(status))),
+        Err(LargetableError::Index(e)) => {
+            debug!("Rejected index build: {}", e);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => Err(engine_error("start index build", e)),
+    }
+}
+
+async fn list_index_builds_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection)): Path<(String, String)>,
+) -> Result<Json<Vec<crate::index::build::IndexBuildStatus>>, StatusCode> {
+    engine.authorize(Action::ListIndexes, Some(&db)).map_err(|e| engine_error("list index builds", e))?;
+    match engine.collection(db, collection).await {
+        Ok(collection) => Ok(Json(collection.list_index_builds())),
+        Err(e) => Err(engine_error("get collection", e)),
+    }
+}
+
+async fn index_build_status_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection, field)): Path<(String, String, String)>,
+) -> Result<Json<crate::index::build::IndexBuildStatus>, StatusCode> {
+    engine.authorize(Action::ListIndexes, Some(&db)).map_err(|e| engine_error("get index build", e))?;
+    match engine.collection(db, collection).await {
+        Ok(collection) => collection.index_build_status(&field).map(Json).ok_or(StatusCode::NOT_FOUND),
+        Err(e) => Err(engine_error("get collection", e)),
+    }
+}
+
+async fn abort_index_build_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((db, collection, field)): Path<(String, String, String)>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    engine.authorize(Action::CreateIndex, Some(&db)).map_err(|e| engine_error("abort index build", e))?;
+    let collection = engine.collection(db, collection).await.map_err(|e| engine_error("get collection", e))?;
+
+    match collection.abort_index_build(&field) {
+        Ok(true) => Ok(Json(serde_json::json!({"status": "aborting", "field": field}))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
         Err(e) => {
-            error!("Failed to execute query: {}", e);
+            error!("Failed to abort index build on {}: {}", field, e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
-}
\ No newline at end of file
+}
+
+/// Take a snapshot of a database for a remote backup agent
+async fn open_backup_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path(db): Path<String>,
+    Json(options): Json<BackupOptions>,
+) -> Result<(StatusCode, Json<BackupSession>), StatusCode> {
+    match engine.open_backup(db, options).await {
+        Ok(session) => Ok((StatusCode::CREATED, Json(session))),
+        Err(LargetableError::ResourceExhausted(e)) => {
+            debug!("Rejected backup session: {}", e);
+            Err(StatusCode::TOO_MANY_REQUESTS)
+        }
+        Err(LargetableError::Query(e)) => {
+            debug!("Rejected backup session: {}", e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+        Err(e) => Err(engine_error("open backup session", e)),
+    }
+}
+
+async fn backup_session_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path(session_id): Path<String>,
+) -> Result<Json<BackupSession>, StatusCode> {
+    engine.authorize(Action::Backup, None).map_err(|e| engine_error("get backup session", e))?;
+    engine.backups().session(&session_id).await.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Next chunk of a collection as of the session's snapshot; resume with `?after=<resume_after>`
+async fn backup_chunk_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path((session_id, collection)): Path<(String, String)>,
+    Query(params): Query<BackupChunkParams>,
+) -> Result<Json<BackupChunk>, StatusCode> {
+    engine.authorize(Action::Backup, None).map_err(|e| engine_error("read backup chunk", e))?;
+    if engine.backups().session(&session_id).await.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    match engine.backups().next_chunk(&session_id, &collection, params.after).await {
+        Ok(chunk) => Ok(Json(chunk)),
+        Err(LargetableError::Query(e)) => {
+            debug!("Rejected backup chunk: {}", e);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            error!("Failed to read backup chunk of {}: {}", collection, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn close_backup_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path(session_id): Path<String>,
+) -> StatusCode {
+    if let Err(e) = engine.authorize(Action::Backup, None) {
+        return engine_error("close backup session", e);
+    }
+    if engine.backups().close_session(&session_id).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// Oplog entries after a position, for incremental backups
+async fn oplog_handler(
+    State(engine): State<Arc<DatabaseEngine>>,
+    Path(db): Path<String>,
+    Query(params): Query<OplogParams>,
+) -> Result<Json<OplogChunk>, StatusCode> {
+    let limit = params.limit.unwrap_or(DEFAULT_OPLOG_LIMIT).max(1);
+    match engine.oplog_after(db, params.after, limit).await {
+        Ok(chunk) => Ok(Json(chunk)),
+        // Entries were trimmed; the agent needs a new full backup
+        Err(LargetableError::Replication(e)) => {
+            debug!("Rejected oplog read: {}", e);
+            Err(StatusCode::GONE)
+        }
+        Err(e) => Err(engine_error("read oplog", e)),
+    }
+}
+
+/// Status code for a failed routed request
+fn routing_error(action: &str, e: LargetableError) -> StatusCode {
+    match e {
+        // Missing shard key, unknown chunk and the like are the client's to fix
+        LargetableError::Sharding(e) | LargetableError::Serialization(e) => {
+            debug!("Rejected {}: {}", action, e);
+            StatusCode::BAD_REQUEST
+        }
+        LargetableError::Network(e) => {
+            error!("Shard unreachable during {}: {}", action, e);
+            StatusCode::BAD_GATEWAY
+        }
+        LargetableError::DeadlineExceeded(e) => {
+            debug!("Stopped {}: {}", action, e);
+            StatusCode::GATEWAY_TIMEOUT
+        }
+        e => {
+            error!("Failed to {}: {}", action, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn sharding_status_handler(State(router): State<Arc<QueryRouter>>) -> Json<ShardingMetadata> {
+    Json(router.catalog().await)
+}
+
+async fn add_shard_handler(
+    State(router): State<Arc<QueryRouter>>,
+    Json(request): Json<AddShardRequest>,
+) -> Result<StatusCode, StatusCode> {
+    router
+        .add_shard(&request.id, &request.host)
+        .await
+        .map(|_| StatusCode::CREATED)
+        .map_err(|e| routing_error("add shard", e))
+}
+
+async fn shard_collection_handler(
+    State(router): State<Arc<QueryRouter>>,
+    Path((db, collection)): Path<(String, String)>,
+    Json(request): Json<ShardCollectionRequest>,
+) -> Result<StatusCode, StatusCode> {
+    router
+        .shard_collection(&db, &collection, request.key, request.initial_chunks)
+        .await
+        .map(|_| StatusCode::CREATED)
+        .map_err(|e| routing_error("shard collection", e))
+}
+
+async fn split_chunk_handler(
+    State(router): State<Arc<QueryRouter>>,
+    Path(chunk): Path<String>,
+    Json(request): Json<SplitChunkRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match router.split_chunk(&chunk, &request.at).await {
+        Ok(id) => Ok(Json(serde_json::json!({"chunk": chunk, "new_chunk": id}))),
+        Err(e) => Err(routing_error("split chunk", e)),
+    }
+}
+
+/// Run a balancing round now and report the chunks it moved
+async fn balance_handler(State(router): State<Arc<QueryRouter>>) -> Result<Json<Vec<ChunkMigration>>, StatusCode> {
+    router.balance().await.map(Json).map_err(|e| routing_error("balance", e))
+}
+
+async fn routed_insert_handler(
+    State(router): State<Arc<QueryRouter>>,
+    Path((db, collection)): Path<(String, String)>,
+    Json(document): Json<serde_json::Value>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match router.insert(&db, &collection, document).await {
+        Ok(id) => Ok(Json(serde_json::json!({"id": id}))),
+        Err(e) => Err(routing_error("insert document", e)),
+    }
+}
+
+async fn routed_find_handler(
+    State(router): State<Arc<QueryRouter>>,
+    Path((db, collection, id)): Path<(String, String, String)>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match router.find_by_id(&db, &collection, &id).await {
+        Ok(Some(document)) => Ok(Json(document)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(routing_error("find document", e)),
+    }
+}
+
+async fn routed_delete_handler(
+    State(router): State<Arc<QueryRouter>>,
+    Path((db, collection, id)): Path<(String, String, String)>,
+) -> StatusCode {
+    match router.delete(&db, &collection, &id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => routing_error("delete document", e),
+    }
+}
+
+async fn routed_query_handler(
+    State(router): State<Arc<QueryRouter>>,
+    Path((db, collection)): Path<(String, String)>,
+    Json(query): Json<ShardQuery>,
+) -> Result<Json<RoutedQueryResult>, StatusCode> {
+    router.query(&db, &collection, query).await.map(Json).map_err(|e| routing_error("execute query", e))
+}
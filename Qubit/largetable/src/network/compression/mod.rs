@@ -0,0 +1,267 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Wire compression for the HTTP API
+//!
+//! Each request negotiates its own algorithm: the client lists what it can
+//! decode in `Accept-Encoding` and the server picks the first configured
+//! algorithm it shares, so responses large enough to benefit (query results,
+//! backup chunks) are compressed while small ones go out as they are.
+//! Request bodies sent with a `Content-Encoding` are decompressed before
+//! they reach the handlers.
+
+use crate::{LargetableError, Result};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// Largest request body accepted after decompression, so small payloads cannot expand without bound
+pub const MAX_DECOMPRESSED_REQUEST_BYTES: usize = 64 * 1024 * 1024;
+
+/// Supported wire compression algorithms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireCompression {
+    /// Better ratio, for bulk results over slower links
+    Zstd,
+    /// Cheaper to compress, for fast local networks (LZ4 frame format)
+    Lz4,
+}
+
+impl WireCompression {
+    /// Content coding name used in `Accept-Encoding` and `Content-Encoding`
+    pub fn name(self) -> &'static str {
+        match self {
+            WireCompression::Zstd => "zstd",
+            WireCompression::Lz4 => "lz4",
+        }
+    }
+
+    pub fn compress(self, data: &[u8], zstd_level: i32) -> Result<Vec<u8>> {
+        match self {
+            WireCompression::Zstd => Ok(zstd::stream::encode_all(data, zstd_level)?),
+            WireCompression::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(data)?;
+                encoder
+                    .finish()
+                    .map_err(|e| LargetableError::Serialization(format!("LZ4 compression failed: {}", e)))
+            }
+        }
+    }
+
+    /// Decompress `data`, failing once the output would exceed `limit` bytes
+    pub fn decompress(self, data: &[u8], limit: usize) -> Result<Vec<u8>> {
+        let reader: Box<dyn Read + '_> = match self {
+            WireCompression::Zstd => Box::new(zstd::stream::Decoder::new(data)?),
+            WireCompression::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(data)),
+        };
+        let mut decompressed = Vec::new();
+        reader.take((limit as u64).saturating_add(1)).read_to_end(&mut decompressed).map_err(|e| {
+            LargetableError::Serialization(format!("Invalid {} data: {}", self.name(), e))
+        })?;
+        if decompressed.len() > limit {
+            return Err(LargetableError::ResourceExhausted(format!(
+                "Decompressed body exceeds {} bytes",
+                limit
+            )));
+        }
+        Ok(decompressed)
+    }
+}
+
+impl std::str::FromStr for WireCompression {
+    type Err = LargetableError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "zstd" => Ok(WireCompression::Zstd),
+            "lz4" => Ok(WireCompression::Lz4),
+            other => Err(LargetableError::Config(format!("Unknown wire compression '{}', expected zstd or lz4", other))),
+        }
+    }
+}
+
+/// How the server compresses responses
+#[derive(Debug, Clone)]
+pub struct CompressionOptions {
+    /// Algorithms offered, in order of preference
+    pub algorithms: Vec<WireCompression>,
+    /// Responses smaller than this are sent uncompressed
+    pub min_size_bytes: usize,
+    pub zstd_level: i32,
+}
+
+/// The first of `preferred` that the client accepts
+pub fn negotiate(accept_encoding: &str, preferred: &[WireCompression]) -> Option<WireCompression> {
+    let accepted: Vec<&str> = accept_encoding
+        .split(',')
+        .filter_map(|coding| {
+            let mut params = coding.split(';').map(str::trim);
+            let name = params.next()?;
+            let refused = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            (!refused).then_some(name)
+        })
+        .collect();
+    preferred
+        .iter()
+        .copied()
+        .find(|algorithm| accepted.iter().any(|name| name.eq_ignore_ascii_case(algorithm.name())))
+}
+
+/// Middleware decompressing request bodies and compressing responses the client can decode
+pub async fn compress(State(options): State<Arc<CompressionOptions>>, request: Request, next: Next) -> Response {
+    let request = match decompress_request(request).await {
+        Ok(request) => request,
+        Err(status) => return status.into_response(),
+    };
+    let algorithm = request
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| negotiate(value, &options.algorithms));
+
+    let response = next.run(request).await;
+    match algorithm {
+        Some(algorithm) if !response.headers().contains_key(header::CONTENT_ENCODING) => {
+            compress_response(response, algorithm, &options).await
+        }
+        _ => response,
+    }
+}
+
+fn content_encoding(headers: &HeaderMap) -> std::result::Result<Option<WireCompression>, StatusCode> {
+    match headers.get(header::CONTENT_ENCODING).map(HeaderValue::to_str) {
+        None => Ok(None),
+        Some(Ok(coding)) if coding.trim().eq_ignore_ascii_case("identity") => Ok(None),
+        Some(Ok(coding)) => coding.parse().map(Some).map_err(|_| StatusCode::UNSUPPORTED_MEDIA_TYPE),
+        Some(Err(_)) => Err(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+    }
+}
+
+async fn decompress_request(request: Request) -> std::result::Result<Request, StatusCode> {
+    let Some(algorithm) = content_encoding(request.headers())? else {
+        return Ok(request);
+    };
+    let (mut parts, body) = request.into_parts();
+    let compressed = to_bytes(body, MAX_DECOMPRESSED_REQUEST_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+    let body = algorithm
+        .decompress(&compressed, MAX_DECOMPRESSED_REQUEST_BYTES)
+        .map_err(|e| {
+            debug!("Rejected {} request body: {}", algorithm.name(), e);
+            match e {
+                LargetableError::ResourceExhausted(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                _ => StatusCode::BAD_REQUEST,
+            }
+        })?;
+
+    parts.headers.remove(header::CONTENT_ENCODING);
+    parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+    Ok(Request::from_parts(parts, Body::from(body)))
+}
+
+async fn compress_response(response: Response, algorithm: WireCompression, options: &CompressionOptions) -> Response {
+    let (mut parts, body) = response.into_parts();
+    parts.headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Failed to read response body: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if body.len() < options.min_size_bytes {
+        return Response::from_parts(parts, Body::from(body));
+    }
+
+    match algorithm.compress(&body, options.zstd_level) {
+        Ok(compressed) if compressed.len() < body.len() => {
+            parts.headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(algorithm.name()));
+            parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(compressed.len()));
+            Response::from_parts(parts, Body::from(compressed))
+        }
+        Ok(_) => Response::from_parts(parts, Body::from(body)),
+        Err(e) => {
+            error!("Failed to compress response: {}", e);
+            Response::from_parts(parts, Body::from(body))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_negotiate_prefers_server_order() {
+        let preferred = [WireCompression::Zstd, WireCompression::Lz4];
+        assert_eq!(negotiate("gzip, lz4, zstd", &preferred), Some(WireCompression::Zstd));
+        assert_eq!(negotiate("zstd;q=0, LZ4", &preferred), Some(WireCompression::Lz4));
+        assert_eq!(negotiate("gzip, br", &preferred), None);
+
+        for algorithm in [WireCompression::Zstd, WireCompression::Lz4] {
+            let data = b"largetable ".repeat(1000);
+            let compressed = algorithm.compress(&data, 3).unwrap();
+            assert!(compressed.len() < data.len());
+            assert_eq!(algorithm.decompress(&compressed, data.len()).unwrap(), data);
+            assert!(matches!(
+                algorithm.decompress(&compressed, data.len() - 1),
+                Err(LargetableError::ResourceExhausted(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_round_trip() {
+        let options = Arc::new(CompressionOptions {
+            algorithms: vec![WireCompression::Zstd, WireCompression::Lz4],
+            min_size_bytes: 1024,
+            zstd_level: 3,
+        });
+        let app = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .layer(axum::middleware::from_fn_with_state(options, compress));
+
+        let payload = "document ".repeat(1000);
+        let request = Request::post("/echo")
+            .header(header::CONTENT_ENCODING, "lz4")
+            .header(header::ACCEPT_ENCODING, "lz4")
+            .body(Body::from(WireCompression::Lz4.compress(payload.as_bytes(), 3).unwrap()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "lz4");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(WireCompression::Lz4.decompress(&body, usize::MAX).unwrap(), payload.as_bytes());
+
+        // Below the threshold, or without a shared algorithm, responses are left alone
+        let request = Request::post("/echo").header(header::ACCEPT_ENCODING, "zstd").body(Body::from("small")).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        let request = Request::post("/echo").header(header::ACCEPT_ENCODING, "gzip").body(Body::from(payload)).unwrap();
+        assert!(!app.clone().oneshot(request).await.unwrap().headers().contains_key(header::CONTENT_ENCODING));
+
+        let request = Request::post("/echo").header(header::CONTENT_ENCODING, "br").body(Body::from("x")).unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
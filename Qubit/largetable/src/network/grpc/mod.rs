@@ -0,0 +1,448 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! gRPC server exposing the document API to services in other languages
+//!
+//! The service is defined in `proto/largetable.proto`. Documents and filters
+//! travel as JSON text so clients need no Largetable-specific types; the Rust
+//! client lives in [`crate::drivers::grpc`].
+
+use crate::{Result, LargetableError, DocumentId};
+use crate::auth::AuthLayer;
+use crate::document::DocumentUtils;
+use crate::engine::deadline::DeadlineLayer;
+use crate::engine::DatabaseEngine;
+use crate::network::tls;
+use crate::query::{Query, SortDirection, SortField};
+use futures::Stream;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio_rustls::TlsAcceptor;
+use tonic::codec::CompressionEncoding;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info};
+
+/// Types and client generated from `proto/largetable.proto`
+pub mod proto {
+    tonic::include_proto!("largetable.v1");
+}
+
+use proto::largetable_server::{Largetable, LargetableServer};
+
+/// gRPC front end over the same engine the HTTP server uses
+pub struct GrpcService {
+    engine: Arc<DatabaseEngine>,
+    tls: Option<TlsAcceptor>,
+    compression: bool,
+    deadlines: DeadlineLayer,
+}
+
+impl GrpcService {
+    pub fn new(engine: Arc<DatabaseEngine>) -> Self {
+        let deadlines = DeadlineLayer::new(engine.cancellation_metrics().clone());
+        Self { engine, tls: None, compression: false, deadlines }
+    }
+
+    /// Accept only TLS connections
+    pub fn with_tls(mut self, acceptor: TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
+    /// Compress responses with zstd for clients that accept it, and accept zstd requests
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Bound calls by these deadlines instead of only the `grpc-timeout` clients send
+    pub fn with_deadlines(mut self, deadlines: DeadlineLayer) -> Self {
+        self.deadlines = deadlines;
+        self
+    }
+
+    /// Serve the service on `addr` until the server fails
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| LargetableError::Network(format!("Failed to bind gRPC address {}: {}", addr, e)))?;
+        self.serve_with_listener(listener).await
+    }
+
+    /// Serve the service on an already bound listener until the server fails
+    pub async fn serve_with_listener(mut self, listener: tokio::net::TcpListener) -> Result<()> {
+        let tls = self.tls.take();
+        info!(
+            "🚀 Largetable gRPC server running on {}{}",
+            listener.local_addr()?,
+            if tls.is_some() { " (TLS)" } else { "" }
+        );
+
+        let access = self.engine.access_control().clone();
+        let deadlines = self.deadlines.clone();
+        let compression = self.compression;
+        let mut service = LargetableServer::new(self);
+        if compression {
+            service = service
+                .accept_compressed(CompressionEncoding::Zstd)
+                .send_compressed(CompressionEncoding::Zstd);
+        }
+        let layers = tower::ServiceBuilder::new().layer(deadlines).layer(AuthLayer::new(access));
+        let router = tonic::transport::Server::builder().layer(layers).add_service(service);
+
+        match tls {
+            Some(acceptor) => router.serve_with_incoming(tls::incoming(listener, acceptor)).await,
+            None => router.serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)).await,
+        }
+        .map_err(|e| LargetableError::Network(format!("gRPC server error: {}", e)))
+    }
+}
+
+/// gRPC status for an engine error, mirroring the HTTP server's status codes
+fn to_status(action: &str, e: LargetableError) -> Status {
+    match e {
+        LargetableError::Query(e) | LargetableError::Serialization(e) | LargetableError::Validation(e) => {
+            debug!("Rejected {}: {}", action, e);
+            Status::invalid_argument(e)
+        }
+        LargetableError::Json(e) => {
+            debug!("Rejected {}: {}", action, e);
+            Status::invalid_argument(e.to_string())
+        }
+        LargetableError::Index(e) => {
+            debug!("Rejected {}: {}", action, e);
+            Status::failed_precondition(e)
+        }
+        LargetableError::ResourceExhausted(e) => Status::resource_exhausted(e),
+        LargetableError::SnapshotUnavailable(e) => {
+            debug!("Rejected {}: {}", action, e);
+            Status::out_of_range(e)
+        }
+        LargetableError::Auth(e) => {
+            debug!("Rejected {}: {}", action, e);
+            Status::unauthenticated(e)
+        }
+        LargetableError::PermissionDenied(e) => {
+            debug!("Rejected {}: {}", action, e);
+            Status::permission_denied(e)
+        }
+        LargetableError::DeadlineExceeded(e) => {
+            debug!("Stopped {}: {}", action, e);
+            Status::deadline_exceeded(e)
+        }
+        LargetableError::Cancelled(e) => {
+            debug!("Stopped {}: {}", action, e);
+            Status::cancelled(e)
+        }
+        e => {
+            error!("Failed to {}: {}", action, e);
+            Status::internal(e.to_string())
+        }
+    }
+}
+
+fn parse_json(field: &str, json: &str) -> Result<serde_json::Value> {
+    serde_json::from_str(json).map_err(|e| LargetableError::Query(format!("Invalid {}: {}", field, e)))
+}
+
+fn parse_document(json: &str) -> Result<crate::Document> {
+    DocumentUtils::from_json(parse_json("document", json)?)
+}
+
+fn parse_id(id: &str) -> Result<DocumentId> {
+    uuid::Uuid::parse_str(id).map_err(|e| LargetableError::Query(format!("Invalid document ID: {}", e)))
+}
+
+fn document_to_json(document: &crate::Document) -> Result<String> {
+    DocumentUtils::to_json(document).map(|json| json.to_string())
+}
+
+fn collection_path(path: Option<proto::CollectionPath>) -> Result<(String, String)> {
+    match path {
+        Some(path) if !path.database.is_empty() && !path.collection.is_empty() => Ok((path.database, path.collection)),
+        _ => Err(LargetableError::Query("Database and collection are required".to_string())),
+    }
+}
+
+/// Engine query for a gRPC query request
+fn to_query(request: &proto::QueryRequest) -> Result<Query> {
+    Ok(Query {
+        filter: request.filter_json.as_deref().map(|filter| parse_json("filter", filter)).transpose()?,
+        sort: request
+            .sort
+            .iter()
+            .map(|sort| SortField {
+                field: sort.field.clone(),
+                direction: if sort.descending { SortDirection::Descending } else { SortDirection::Ascending },
+            })
+            .collect(),
+        limit: request.limit.map(|limit| limit as usize),
+        skip: request.skip.map(|skip| skip as usize),
+        projection: (!request.projection.is_empty()).then(|| request.projection.clone()),
+        bypass_cache: request.bypass_cache,
+        read_at: request.read_at,
+        ..Query::new()
+    })
+}
+
+#[tonic::async_trait]
+impl Largetable for GrpcService {
+    type QueryStream = Pin<Box<dyn Stream<Item = std::result::Result<proto::QueryResponse, Status>> + Send>>;
+
+    async fn health(&self, _request: Request<proto::HealthRequest>) -> std::result::Result<Response<proto::HealthResponse>, Status> {
+        Ok(Response::new(proto::HealthResponse {
+            status: "healthy".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            cluster_time: self.engine.cluster_time(),
+        }))
+    }
+
+    async fn stats(&self, _request: Request<proto::StatsRequest>) -> std::result::Result<Response<proto::StatsResponse>, Status> {
+        let stats = self.engine.get_stats().await.map_err(|e| to_status("get stats", e))?;
+        let stats_json = serde_json::to_string(&stats).map_err(|e| to_status("get stats", e.into()))?;
+        Ok(Response::new(proto::StatsResponse { stats_json }))
+    }
+
+    async fn scram_start(
+        &self,
+        request: Request<proto::ScramStartRequest>,
+    ) -> std::result::Result<Response<proto::ScramStartResponse>, Status> {
+        let start = self
+            .engine
+            .access_control()
+            .start_scram(&request.into_inner().payload)
+            .map_err(|e| to_status("start authentication", e))?;
+        Ok(Response::new(proto::ScramStartResponse { conversation_id: start.conversation_id, payload: start.payload }))
+    }
+
+    async fn scram_finish(
+        &self,
+        request: Request<proto::ScramFinishRequest>,
+    ) -> std::result::Result<Response<proto::ScramFinishResponse>, Status> {
+        let request = request.into_inner();
+        let finish = self
+            .engine
+            .access_control()
+            .finish_scram(&request.conversation_id, &request.payload)
+            .map_err(|e| to_status("finish authentication", e))?;
+        Ok(Response::new(proto::ScramFinishResponse {
+            payload: finish.payload,
+            token: finish.token,
+            expires_in_secs: finish.expires_in_secs,
+        }))
+    }
+
+    async fn list_databases(
+        &self,
+        _request: Request<proto::ListDatabasesRequest>,
+    ) -> std::result::Result<Response<proto::ListDatabasesResponse>, Status> {
+        let databases = self.engine.list_databases().await.map_err(|e| to_status("list databases", e))?;
+        Ok(Response::new(proto::ListDatabasesResponse { databases }))
+    }
+
+    async fn create_database(
+        &self,
+        request: Request<proto::CreateDatabaseRequest>,
+    ) -> std::result::Result<Response<proto::CreateDatabaseResponse>, Status> {
+        let database = request.into_inner().database;
+        if database.is_empty() {
+            return Err(Status::invalid_argument("Database is required"));
+        }
+        self.engine.create_database(database).await.map_err(|e| to_status("create database", e))?;
+        Ok(Response::new(proto::CreateDatabaseResponse {}))
+    }
+
+    async fn drop_database(
+        &self,
+        request: Request<proto::DropDatabaseRequest>,
+    ) -> std::result::Result<Response<proto::DropDatabaseResponse>, Status> {
+        let database = request.into_inner().database;
+        let dropped = self.engine.drop_database(&database).await.map_err(|e| to_status("drop database", e))?;
+        Ok(Response::new(proto::DropDatabaseResponse { dropped }))
+    }
+
+    async fn list_collections(
+        &self,
+        request: Request<proto::ListCollectionsRequest>,
+    ) -> std::result::Result<Response<proto::ListCollectionsResponse>, Status> {
+        let collections = self
+            .engine
+            .list_collections(request.into_inner().database)
+            .await
+            .map_err(|e| to_status("list collections", e))?;
+        Ok(Response::new(proto::ListCollectionsResponse { collections }))
+    }
+
+    async fn create_collection(
+        &self,
+        request: Request<proto::CreateCollectionRequest>,
+    ) -> std::result::Result<Response<proto::CreateCollectionResponse>, Status> {
+        let (database, collection) = collection_path(request.into_inner().path).map_err(|e| to_status("resolve collection", e))?;
+        self.engine.create_collection(database, collection).await.map_err(|e| to_status("create collection", e))?;
+        Ok(Response::new(proto::CreateCollectionResponse {}))
+    }
+
+    async fn insert_document(
+        &self,
+        request: Request<proto::InsertDocumentRequest>,
+    ) -> std::result::Result<Response<proto::InsertDocumentResponse>, Status> {
+        let request = request.into_inner();
+        let (database, collection) = collection_path(request.path).map_err(|e| to_status("resolve collection", e))?;
+        let document = parse_document(&request.document_json).map_err(|e| to_status("parse document", e))?;
+        let id = self
+            .engine
+            .insert_document(database, collection, document)
+            .await
+            .map_err(|e| to_status("insert document", e))?;
+        Ok(Response::new(proto::InsertDocumentResponse { id: id.to_string() }))
+    }
+
+    async fn find_document(
+        &self,
+        request: Request<proto::FindDocumentRequest>,
+    ) -> std::result::Result<Response<proto::FindDocumentResponse>, Status> {
+        let request = request.into_inner();
+        let (database, collection) = collection_path(request.path).map_err(|e| to_status("resolve collection", e))?;
+        let id = parse_id(&request.id).map_err(|e| to_status("find document", e))?;
+        let document = match request.read_at {
+            Some(timestamp) => self.engine.find_document_by_id_at(database, collection, id, timestamp).await,
+            None => self.engine.find_document_by_id(database, collection, id).await,
+        };
+        let document = document
+            .and_then(|document| document.as_ref().map(document_to_json).transpose())
+            .map_err(|e| to_status("find document", e))?;
+        Ok(Response::new(proto::FindDocumentResponse { document_json: document }))
+    }
+
+    async fn update_document(
+        &self,
+        request: Request<proto::UpdateDocumentRequest>,
+    ) -> std::result::Result<Response<proto::UpdateDocumentResponse>, Status> {
+        let request = request.into_inner();
+        let (database, collection) = collection_path(request.path).map_err(|e| to_status("resolve collection", e))?;
+        let document = parse_document(&request.document_json).map_err(|e| to_status("parse document", e))?;
+        let id = parse_id(&request.id).map_err(|e| to_status("update document", e))?;
+        let previous = self
+            .engine
+            .update_document_by_id(database, collection, id, document)
+            .await
+            .and_then(|previous| previous.as_ref().map(document_to_json).transpose())
+            .map_err(|e| to_status("update document", e))?;
+        Ok(Response::new(proto::UpdateDocumentResponse { previous_json: previous }))
+    }
+
+    async fn delete_document(
+        &self,
+        request: Request<proto::DeleteDocumentRequest>,
+    ) -> std::result::Result<Response<proto::DeleteDocumentResponse>, Status> {
+        let request = request.into_inner();
+        let (database, collection) = collection_path(request.path).map_err(|e| to_status("resolve collection", e))?;
+        let id = parse_id(&request.id).map_err(|e| to_status("delete document", e))?;
+        let deleted = self
+            .engine
+            .delete_document_by_id(database, collection, id)
+            .await
+            .map_err(|e| to_status("delete document", e))?;
+        Ok(Response::new(proto::DeleteDocumentResponse { deleted }))
+    }
+
+    async fn query(&self, request: Request<proto::QueryRequest>) -> std::result::Result<Response<Self::QueryStream>, Status> {
+        let request = request.into_inner();
+        let query = to_query(&request).map_err(|e| to_status("parse query", e))?;
+        let (database, collection) = collection_path(request.path).map_err(|e| to_status("resolve collection", e))?;
+        let result = self
+            .engine
+            .query(database, collection, query)
+            .await
+            .map_err(|e| to_status("execute query", e))?;
+
+        let total_count = result.total_count as u64;
+        let has_more = result.has_more;
+        let responses = result
+            .documents
+            .iter()
+            .map(|(id, document)| {
+                Ok(proto::QueryResponse {
+                    id: id.to_string(),
+                    document_json: document_to_json(document)?,
+                    total_count,
+                    has_more,
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| to_status("serialize documents", e))?;
+        Ok(Response::new(Box::pin(futures::stream::iter(responses.into_iter().map(Ok)))))
+    }
+
+    async fn list_indexes(
+        &self,
+        request: Request<proto::ListIndexesRequest>,
+    ) -> std::result::Result<Response<proto::ListIndexesResponse>, Status> {
+        let (database, collection) = collection_path(request.into_inner().path).map_err(|e| to_status("resolve collection", e))?;
+        let indexes = self.engine.list_indexes(database, collection).await.map_err(|e| to_status("list indexes", e))?;
+        let indexes_json = serde_json::to_string(&indexes).map_err(|e| to_status("list indexes", e.into()))?;
+        Ok(Response::new(proto::ListIndexesResponse { indexes_json }))
+    }
+
+    async fn create_index(
+        &self,
+        request: Request<proto::CreateIndexRequest>,
+    ) -> std::result::Result<Response<proto::CreateIndexResponse>, Status> {
+        let request = request.into_inner();
+        let (database, collection) = collection_path(request.path).map_err(|e| to_status("resolve collection", e))?;
+        let index_type: crate::IndexType = serde_json::from_str(&request.index_type_json)
+            .map_err(|e| to_status("parse index type", LargetableError::Query(format!("Invalid index type: {}", e))))?;
+        let status = self
+            .engine
+            .create_index(database, collection, request.field, index_type)
+            .await
+            .map_err(|e| to_status("start index build", e))?;
+        let build_status_json = serde_json::to_string(&status).map_err(|e| to_status("start index build", e.into()))?;
+        Ok(Response::new(proto::CreateIndexResponse { build_status_json }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_conversion() {
+        let request = proto::QueryRequest {
+            path: Some(proto::CollectionPath { database: "shop".to_string(), collection: "orders".to_string() }),
+            filter_json: Some(r#"{"status": "paid"}"#.to_string()),
+            sort: vec![proto::SortField { field: "total".to_string(), descending: true }],
+            limit: Some(10),
+            skip: None,
+            projection: Vec::new(),
+            bypass_cache: false,
+            read_at: Some(1_700_000_000_000_000),
+        };
+        let query = to_query(&request).unwrap();
+        assert_eq!(query.filter, Some(serde_json::json!({"status": "paid"})));
+        assert!(matches!(query.sort[0].direction, SortDirection::Descending));
+        assert_eq!(query.limit, Some(10));
+        assert!(query.projection.is_none());
+        assert_eq!(query.read_at, Some(1_700_000_000_000_000));
+
+        let invalid = proto::QueryRequest { filter_json: Some("{status".to_string()), ..request };
+        assert_eq!(to_status("query", to_query(&invalid).unwrap_err()).code(), tonic::Code::InvalidArgument);
+        assert!(collection_path(None).is_err());
+    }
+
+    #[test]
+    fn test_error_status_codes() {
+        assert_eq!(to_status("query", LargetableError::Query("bad".into())).code(), tonic::Code::InvalidArgument);
+        assert_eq!(to_status("index", LargetableError::Index("exists".into())).code(), tonic::Code::FailedPrecondition);
+        assert_eq!(to_status("write", LargetableError::Storage("disk".into())).code(), tonic::Code::Internal);
+        assert_eq!(to_status("find", LargetableError::Auth("login".into())).code(), tonic::Code::Unauthenticated);
+        assert_eq!(to_status("drop", LargetableError::PermissionDenied("role".into())).code(), tonic::Code::PermissionDenied);
+        assert_eq!(to_status("query", LargetableError::DeadlineExceeded("late".into())).code(), tonic::Code::DeadlineExceeded);
+        assert_eq!(to_status("query", LargetableError::Cancelled("gone".into())).code(), tonic::Code::Cancelled);
+        assert_eq!(to_status("query", LargetableError::SnapshotUnavailable("trimmed".into())).code(), tonic::Code::OutOfRange);
+    }
+}
@@ -7,5 +7,13 @@
 //! Network layer and server
 
 pub mod async_server;
+pub mod compression;
+pub mod grpc;
+pub mod router;
+pub mod tls;
 
-pub use async_server::LargetableServer;
\ No newline at end of file
+pub use async_server::LargetableServer;
+pub use compression::WireCompression;
+pub use grpc::GrpcService;
+pub use router::{QueryRouter, ShardConnection};
+pub use tls::TlsVersion;
\ No newline at end of file
@@ -0,0 +1,547 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Query router for sharded clusters
+//!
+//! Plays the part of `mongos`: clients talk to the router with the same
+//! document API a single server exposes, and the router forwards each request
+//! to the shards that own the keys involved, using the config catalog.
+//! Writes go to exactly one shard. Queries that pin the shard key go to the
+//! shards owning those keys; everything else is scattered to every shard
+//! holding the collection and gathered back.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+use crate::distributed::ShardId;
+use crate::engine::deadline;
+use crate::document::DocumentUtils;
+use crate::sharding::{Balancer, Chunk, ChunkMigration, ChunkMover, KeyTarget, ShardKeyPattern, ShardingMetadata};
+use crate::{LargetableError, Result};
+
+/// Query forwarded to a shard
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShardQuery {
+    pub filter: Option<JsonValue>,
+    pub limit: Option<usize>,
+    pub skip: Option<usize>,
+    pub projection: Option<Vec<String>>,
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+/// A shard's answer to a query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShardQueryResult {
+    pub documents: Vec<JsonValue>,
+    pub total_count: usize,
+    pub has_more: bool,
+}
+
+/// Merged result of a routed query
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutedQueryResult {
+    pub documents: Vec<JsonValue>,
+    pub total_count: usize,
+    pub has_more: bool,
+    /// Shards the query was sent to
+    pub shards: Vec<ShardId>,
+}
+
+/// Document API of a single shard
+#[async_trait]
+pub trait ShardConnection: Send + Sync {
+    /// Insert a document, keeping its `_id` if it has one; returns the ID
+    async fn insert(&self, db: &str, collection: &str, document: &JsonValue) -> Result<String>;
+
+    async fn find_by_id(&self, db: &str, collection: &str, id: &str) -> Result<Option<JsonValue>>;
+
+    async fn query(&self, db: &str, collection: &str, query: &ShardQuery) -> Result<ShardQueryResult>;
+
+    /// Delete a document; returns whether it existed
+    async fn delete(&self, db: &str, collection: &str, id: &str) -> Result<bool>;
+}
+
+/// Shard reached through its HTTP API
+pub struct HttpShard {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl HttpShard {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn documents_url(&self, db: &str, collection: &str) -> String {
+        format!("{}/databases/{}/collections/{}/documents", self.base_url, db, collection)
+    }
+
+    /// Start a request that gives the shard only the time left of the client's deadline
+    fn request(&self, method: reqwest::Method, url: String) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match deadline::remaining_time() {
+            Some(remaining) => request
+                .header(deadline::TIMEOUT_HEADER, remaining.as_millis().to_string())
+                .timeout(remaining),
+            None => request,
+        }
+    }
+}
+
+fn network_error(e: reqwest::Error) -> LargetableError {
+    if e.is_timeout() || e.status() == Some(reqwest::StatusCode::GATEWAY_TIMEOUT) {
+        return LargetableError::DeadlineExceeded(format!("Shard request ran out of time: {}", e));
+    }
+    LargetableError::Network(format!("Shard request failed: {}", e))
+}
+
+#[async_trait]
+impl ShardConnection for HttpShard {
+    async fn insert(&self, db: &str, collection: &str, document: &JsonValue) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Inserted {
+            id: String,
+        }
+
+        let response = self
+            .request(reqwest::Method::POST, self.documents_url(db, collection))
+            .json(document)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(network_error)?;
+        Ok(response.json::<Inserted>().await.map_err(network_error)?.id)
+    }
+
+    async fn find_by_id(&self, db: &str, collection: &str, id: &str) -> Result<Option<JsonValue>> {
+        let response = self
+            .request(reqwest::Method::GET, format!("{}/{}", self.documents_url(db, collection), id))
+            .send()
+            .await
+            .map_err(network_error)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status().map_err(network_error)?;
+        Ok(Some(response.json().await.map_err(network_error)?))
+    }
+
+    async fn query(&self, db: &str, collection: &str, query: &ShardQuery) -> Result<ShardQueryResult> {
+        let response = self
+            .request(
+                reqwest::Method::POST,
+                format!("{}/databases/{}/collections/{}/query", self.base_url, db, collection),
+            )
+            .json(query)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(network_error)?;
+        response.json().await.map_err(network_error)
+    }
+
+    async fn delete(&self, db: &str, collection: &str, id: &str) -> Result<bool> {
+        let response = self
+            .request(reqwest::Method::DELETE, format!("{}/{}", self.documents_url(db, collection), id))
+            .send()
+            .await
+            .map_err(network_error)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status().map_err(network_error)?;
+        Ok(true)
+    }
+}
+
+/// Routes document requests across shards using the config catalog
+pub struct QueryRouter {
+    catalog: Arc<RwLock<ShardingMetadata>>,
+    /// Where the catalog is persisted after every change
+    catalog_path: Option<PathBuf>,
+    connections: RwLock<HashMap<ShardId, Arc<dyn ShardConnection>>>,
+    /// Writes hold this shared; a chunk migration holds it exclusively so no
+    /// write lands on a chunk's old shard after its documents were copied
+    migration_gate: RwLock<()>,
+    balancer: Balancer,
+}
+
+impl QueryRouter {
+    pub fn new(catalog: ShardingMetadata) -> Self {
+        let connections = catalog
+            .hosts
+            .iter()
+            .map(|(shard, host)| (shard.clone(), Arc::new(HttpShard::new(host.clone())) as Arc<dyn ShardConnection>))
+            .collect();
+
+        Self {
+            catalog: Arc::new(RwLock::new(catalog)),
+            catalog_path: None,
+            connections: RwLock::new(connections),
+            migration_gate: RwLock::new(()),
+            balancer: Balancer::default(),
+        }
+    }
+
+    /// Router backed by a catalog file, created empty if it does not exist yet
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let catalog = if path.exists() {
+            ShardingMetadata::load(&path)?
+        } else {
+            ShardingMetadata::default()
+        };
+        info!("Loaded sharding catalog version {} from {}", catalog.version, path.display());
+
+        Ok(Self {
+            catalog_path: Some(path),
+            ..Self::new(catalog)
+        })
+    }
+
+    pub fn with_balancer(mut self, balancer: Balancer) -> Self {
+        self.balancer = balancer;
+        self
+    }
+
+    /// Use a specific connection for a shard instead of its catalog host
+    pub async fn set_connection(&self, shard: &str, connection: Arc<dyn ShardConnection>) {
+        self.connections.write().await.insert(shard.to_string(), connection);
+    }
+
+    pub async fn catalog(&self) -> ShardingMetadata {
+        self.catalog.read().await.clone()
+    }
+
+    /// Register a shard reachable at `host`
+    pub async fn add_shard(&self, shard: &str, host: &str) -> Result<()> {
+        self.update_catalog(|catalog| {
+            catalog.add_shard(shard, host);
+            Ok(())
+        })
+        .await?;
+        let connection = Arc::new(HttpShard::new(host.trim_end_matches('/').to_string()));
+        self.set_connection(shard, connection).await;
+        Ok(())
+    }
+
+    pub async fn shard_collection(&self, db: &str, collection: &str, key: ShardKeyPattern, initial_chunks: usize) -> Result<()> {
+        let namespace = format!("{}.{}", db, collection);
+        self.update_catalog(|catalog| catalog.shard_collection(&namespace, key, initial_chunks)).await
+    }
+
+    pub async fn split_chunk(&self, chunk_id: &str, at: &JsonValue) -> Result<String> {
+        let at = at.clone();
+        self.update_catalog(|catalog| {
+            let chunk = catalog
+                .chunks
+                .iter()
+                .find(|chunk| chunk.id == chunk_id)
+                .ok_or_else(|| LargetableError::Sharding(format!("Unknown chunk {}", chunk_id)))?;
+            let collection = catalog
+                .collection(&chunk.namespace)
+                .ok_or_else(|| LargetableError::Sharding(format!("{} is not sharded", chunk.namespace)))?;
+            let key = collection.key.key_for_value(&DocumentUtils::json_to_value(at)?)?;
+            catalog.split_chunk(chunk_id, key)
+        })
+        .await
+    }
+
+    /// Insert a document on the shard owning its shard key
+    pub async fn insert(&self, db: &str, collection: &str, document: JsonValue) -> Result<String> {
+        let _gate = self.migration_gate.read().await;
+        let shard = self.shard_for_document(db, collection, &document).await?;
+        debug!("Routing insert into {}.{} to shard {}", db, collection, shard);
+        self.connection(&shard).await?.insert(db, collection, &document).await
+    }
+
+    /// Look a document up by ID; IDs are not shard keys, so every shard holding the collection is asked
+    pub async fn find_by_id(&self, db: &str, collection: &str, id: &str) -> Result<Option<JsonValue>> {
+        let shards = self.target_shards(db, collection, &KeyTarget::All).await;
+        for shard in shards {
+            if let Some(document) = self.connection(&shard).await?.find_by_id(db, collection, id).await? {
+                return Ok(Some(document));
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn delete(&self, db: &str, collection: &str, id: &str) -> Result<bool> {
+        let _gate = self.migration_gate.read().await;
+        let shards = self.target_shards(db, collection, &KeyTarget::All).await;
+        for shard in shards {
+            if self.connection(&shard).await?.delete(db, collection, id).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Run a query on the shards it can match and merge the results
+    ///
+    /// Each shard returns up to `skip + limit` documents; skip and limit are
+    /// applied again to the merged list.
+    pub async fn query(&self, db: &str, collection: &str, query: ShardQuery) -> Result<RoutedQueryResult> {
+        let target = self.key_pattern(db, collection).await.map_or(KeyTarget::All, |key| key.target(query.filter.as_ref()));
+        let shards = self.target_shards(db, collection, &target).await;
+        debug!("Routing query on {}.{} to shards {:?}", db, collection, shards);
+
+        let skip = query.skip.unwrap_or(0);
+        let per_shard = ShardQuery {
+            skip: None,
+            limit: query.limit.map(|limit| limit + skip),
+            ..query.clone()
+        };
+
+        let mut connections = Vec::with_capacity(shards.len());
+        for shard in &shards {
+            connections.push(self.connection(shard).await?);
+        }
+        let results = try_join_all(connections.iter().map(|connection| connection.query(db, collection, &per_shard))).await?;
+
+        let total_count = results.iter().map(|result| result.total_count).sum();
+        let mut has_more = results.iter().any(|result| result.has_more);
+        let mut documents: Vec<JsonValue> = results.into_iter().flat_map(|result| result.documents).skip(skip).collect();
+        if let Some(limit) = query.limit {
+            has_more |= documents.len() > limit;
+            documents.truncate(limit);
+        }
+
+        Ok(RoutedQueryResult { documents, total_count, has_more, shards })
+    }
+
+    /// Run one balancing round, moving chunk documents through the shard connections
+    pub async fn balance(&self) -> Result<Vec<ChunkMigration>> {
+        let _gate = self.migration_gate.write().await;
+        let result = self.balancer.run_round(&self.catalog, self).await;
+        // Commits made before a failure still have to reach the catalog file
+        self.persist().await?;
+        result
+    }
+
+    async fn update_catalog<T>(&self, change: impl FnOnce(&mut ShardingMetadata) -> Result<T>) -> Result<T> {
+        let value = change(&mut *self.catalog.write().await)?;
+        self.persist().await?;
+        Ok(value)
+    }
+
+    async fn persist(&self) -> Result<()> {
+        if let Some(path) = &self.catalog_path {
+            self.catalog.read().await.save(path)?;
+        }
+        Ok(())
+    }
+
+    async fn key_pattern(&self, db: &str, collection: &str) -> Option<ShardKeyPattern> {
+        let namespace = format!("{}.{}", db, collection);
+        self.catalog.read().await.collection(&namespace).map(|collection| collection.key.clone())
+    }
+
+    async fn target_shards(&self, db: &str, collection: &str, target: &KeyTarget) -> Vec<ShardId> {
+        self.catalog.read().await.shards_for(&format!("{}.{}", db, collection), target)
+    }
+
+    async fn shard_for_document(&self, db: &str, collection: &str, document: &JsonValue) -> Result<ShardId> {
+        let namespace = format!("{}.{}", db, collection);
+        let catalog = self.catalog.read().await;
+
+        let Some(sharded) = catalog.collection(&namespace) else {
+            return catalog
+                .primary_shard()
+                .cloned()
+                .ok_or_else(|| LargetableError::Sharding("No shards registered".to_string()));
+        };
+
+        let key = sharded.key.extract(&DocumentUtils::from_json(document.clone())?)?;
+        catalog
+            .chunk_for_key(&namespace, &key)
+            .map(|chunk| chunk.shard.clone())
+            .ok_or_else(|| LargetableError::Sharding(format!("No chunk of {} owns key {:?}", namespace, key)))
+    }
+
+    async fn connection(&self, shard: &str) -> Result<Arc<dyn ShardConnection>> {
+        self.connections
+            .read()
+            .await
+            .get(shard)
+            .cloned()
+            .ok_or_else(|| LargetableError::Sharding(format!("No connection to shard {}", shard)))
+    }
+}
+
+#[async_trait]
+impl ChunkMover for QueryRouter {
+    async fn move_chunk(&self, migration: &ChunkMigration, chunk: &Chunk, key: &ShardKeyPattern) -> Result<u64> {
+        let (db, collection) = migration
+            .namespace
+            .split_once('.')
+            .ok_or_else(|| LargetableError::Sharding(format!("Invalid namespace {}", migration.namespace)))?;
+        let source = self.connection(&migration.from).await?;
+        let target = self.connection(&migration.to).await?;
+
+        // Hashed chunks have no filter form, so the source is scanned and matched here
+        let documents = source.query(db, collection, &ShardQuery { bypass_cache: true, ..ShardQuery::default() }).await?.documents;
+        let mut moved = 0;
+        for document in documents {
+            let parsed = DocumentUtils::from_json(document.clone())?;
+            if !key.extract(&parsed).map(|value| chunk.owns(&value)).unwrap_or(false) {
+                continue;
+            }
+            target.insert(db, collection, &document).await?;
+            source.delete(db, collection, &parsed.id.to_string()).await?;
+            moved += 1;
+        }
+
+        Ok(moved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// In-memory shard keyed by `_id`
+    #[derive(Default)]
+    struct MemoryShard {
+        documents: Mutex<Vec<JsonValue>>,
+    }
+
+    impl MemoryShard {
+        fn len(&self) -> usize {
+            self.documents.lock().unwrap().len()
+        }
+    }
+
+    #[async_trait]
+    impl ShardConnection for MemoryShard {
+        async fn insert(&self, _db: &str, _collection: &str, document: &JsonValue) -> Result<String> {
+            let mut document = document.clone();
+            let id = match document.get("_id").and_then(JsonValue::as_str) {
+                Some(id) => id.to_string(),
+                None => uuid::Uuid::new_v4().to_string(),
+            };
+            document["_id"] = json!(id);
+            self.documents.lock().unwrap().push(document);
+            Ok(id)
+        }
+
+        async fn find_by_id(&self, _db: &str, _collection: &str, id: &str) -> Result<Option<JsonValue>> {
+            Ok(self.documents.lock().unwrap().iter().find(|doc| doc["_id"] == id).cloned())
+        }
+
+        async fn query(&self, _db: &str, _collection: &str, query: &ShardQuery) -> Result<ShardQueryResult> {
+            let mut matched = Vec::new();
+            for document in self.documents.lock().unwrap().iter() {
+                let parsed = DocumentUtils::from_json(document.clone())?;
+                let matches = match &query.filter {
+                    Some(filter) => DocumentUtils::matches_filter(&parsed, filter)?,
+                    None => true,
+                };
+                if matches {
+                    matched.push(document.clone());
+                }
+            }
+            let total_count = matched.len();
+            let documents: Vec<JsonValue> =
+                matched.into_iter().skip(query.skip.unwrap_or(0)).take(query.limit.unwrap_or(usize::MAX)).collect();
+            Ok(ShardQueryResult { has_more: documents.len() < total_count, documents, total_count })
+        }
+
+        async fn delete(&self, _db: &str, _collection: &str, id: &str) -> Result<bool> {
+            let mut documents = self.documents.lock().unwrap();
+            let before = documents.len();
+            documents.retain(|doc| doc["_id"] != id);
+            Ok(documents.len() < before)
+        }
+    }
+
+    async fn cluster(key: ShardKeyPattern) -> (QueryRouter, Arc<MemoryShard>, Arc<MemoryShard>) {
+        let mut catalog = ShardingMetadata::default();
+        catalog.add_shard("shard-a", "http://shard-a:27017");
+        catalog.add_shard("shard-b", "http://shard-b:27017");
+        catalog.shard_collection("pixelle.users", key, 2).unwrap();
+
+        let router = QueryRouter::new(catalog);
+        let (a, b) = (Arc::new(MemoryShard::default()), Arc::new(MemoryShard::default()));
+        router.set_connection("shard-a", a.clone()).await;
+        router.set_connection("shard-b", b.clone()).await;
+        (router, a, b)
+    }
+
+    #[tokio::test]
+    async fn test_hashed_writes_spread_and_targeted_reads() {
+        let (router, a, b) = cluster(ShardKeyPattern::hashed("user_id")).await;
+        for user_id in 0..40 {
+            router.insert("pixelle", "users", json!({"user_id": user_id})).await.unwrap();
+        }
+        assert_eq!(a.len() + b.len(), 40);
+        assert!(a.len() > 0 && b.len() > 0);
+
+        let one = router
+            .query("pixelle", "users", ShardQuery { filter: Some(json!({"user_id": 7})), ..ShardQuery::default() })
+            .await
+            .unwrap();
+        assert_eq!(one.shards.len(), 1);
+        assert_eq!(one.documents.len(), 1);
+
+        let page = router
+            .query("pixelle", "users", ShardQuery { limit: Some(5), skip: Some(10), ..ShardQuery::default() })
+            .await
+            .unwrap();
+        assert_eq!(page.shards.len(), 2);
+        assert_eq!(page.documents.len(), 5);
+        assert!(page.has_more);
+
+        assert!(router.insert("pixelle", "users", json!({"name": "no key"})).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_balance_moves_documents_with_their_chunk() {
+        let (router, a, b) = cluster(ShardKeyPattern::range("age")).await;
+        for age in 0..30 {
+            router.insert("pixelle", "users", json!({"age": age})).await.unwrap();
+        }
+        // A range collection starts as a single chunk on one shard
+        let (full, empty) = if a.len() == 30 { (a, b) } else { (b, a) };
+        assert_eq!(empty.len(), 0);
+
+        for age in [10, 20, 25] {
+            let catalog = router.catalog().await;
+            let chunk = catalog.chunk_for_key("pixelle.users", &crate::distributed::ShardKey::Integer(age)).unwrap();
+            router.split_chunk(&chunk.id, &json!(age)).await.unwrap();
+        }
+        assert_eq!(router.catalog().await.chunks_for("pixelle.users").len(), 4);
+
+        let migrations = router.balance().await.unwrap();
+        assert!(!migrations.is_empty());
+        assert_eq!(full.len() + empty.len(), 30);
+        assert!(empty.len() > 0);
+
+        // Every document is still found through the router after the move
+        let all = router.query("pixelle", "users", ShardQuery::default()).await.unwrap();
+        assert_eq!(all.documents.len(), 30);
+        let older = router
+            .query("pixelle", "users", ShardQuery { filter: Some(json!({"age": {"$gte": 25}})), ..ShardQuery::default() })
+            .await
+            .unwrap();
+        assert_eq!(older.documents.len(), 5);
+        assert_eq!(older.shards.len(), 1);
+    }
+}
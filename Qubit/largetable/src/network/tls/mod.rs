@@ -0,0 +1,213 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! TLS for the HTTP and gRPC listeners
+//!
+//! Both listeners accept TCP connections themselves and hand them to rustls,
+//! so the same certificate and minimum protocol version apply to each.
+
+use crate::{LargetableError, Result};
+use axum::Router;
+use futures::Stream;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tracing::debug;
+
+/// How long a client has to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Oldest TLS version a listener accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl std::str::FromStr for TlsVersion {
+    type Err = LargetableError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "1.2" => Ok(TlsVersion::Tls12),
+            "1.3" => Ok(TlsVersion::Tls13),
+            other => Err(LargetableError::Config(format!("Unsupported TLS version '{}', expected 1.2 or 1.3", other))),
+        }
+    }
+}
+
+static TLS13_ONLY: &[&rustls::SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+impl TlsVersion {
+    fn protocol_versions(self) -> &'static [&'static rustls::SupportedProtocolVersion] {
+        match self {
+            TlsVersion::Tls12 => rustls::ALL_VERSIONS,
+            TlsVersion::Tls13 => TLS13_ONLY,
+        }
+    }
+}
+
+fn load_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = std::fs::read(path)
+        .map_err(|e| LargetableError::Config(format!("Failed to read TLS certificate {}: {}", path.display(), e)))?;
+    let certificates = rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| LargetableError::Config(format!("Invalid TLS certificate {}: {}", path.display(), e)))?;
+    if certificates.is_empty() {
+        return Err(LargetableError::Config(format!("No certificates in {}", path.display())));
+    }
+    Ok(certificates)
+}
+
+fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let pem = std::fs::read(path)
+        .map_err(|e| LargetableError::Config(format!("Failed to read TLS key {}: {}", path.display(), e)))?;
+    rustls_pemfile::private_key(&mut pem.as_slice())
+        .map_err(|e| LargetableError::Config(format!("Invalid TLS key {}: {}", path.display(), e)))?
+        .ok_or_else(|| LargetableError::Config(format!("No private key in {}", path.display())))
+}
+
+/// Acceptor for a PEM certificate chain and key, offering HTTP/2 and HTTP/1.1
+pub fn acceptor(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>, min_version: TlsVersion) -> Result<TlsAcceptor> {
+    let certificates = load_certificates(cert_path.as_ref())?;
+    let key = load_private_key(key_path.as_ref())?;
+
+    let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_protocol_versions(min_version.protocol_versions())
+        .map_err(|e| LargetableError::Config(format!("Invalid TLS versions: {}", e)))?
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)
+        .map_err(|e| LargetableError::Config(format!("Invalid TLS certificate or key: {}", e)))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// TLS connections accepted on `listener`, for servers that take a connection stream
+///
+/// Handshakes run on their own tasks so a slow client cannot hold up others;
+/// failed ones are logged and skipped.
+pub fn incoming(listener: TcpListener, acceptor: TlsAcceptor) -> impl Stream<Item = std::io::Result<TlsStream<TcpStream>>> {
+    let (sender, receiver) = mpsc::channel(128);
+    tokio::spawn(async move {
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = sender.closed() => return,
+            };
+            let (stream, peer) = match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    let _ = sender.send(Err(e)).await;
+                    continue;
+                }
+            };
+            let (acceptor, sender) = (acceptor.clone(), sender.clone());
+            tokio::spawn(async move {
+                if let Some(stream) = handshake(&acceptor, stream, peer).await {
+                    let _ = sender.send(Ok(stream)).await;
+                }
+            });
+        }
+    });
+    ReceiverStream::new(receiver)
+}
+
+/// Serve `app` over TLS until accepting connections fails
+pub async fn serve(listener: TcpListener, acceptor: TlsAcceptor, app: Router) -> Result<()> {
+    loop {
+        let (stream, peer) = listener
+            .accept()
+            .await
+            .map_err(|e| LargetableError::Network(format!("Failed to accept connection: {}", e)))?;
+        let acceptor = acceptor.clone();
+        let service = TowerToHyperService::new(app.clone());
+
+        tokio::spawn(async move {
+            let Some(stream) = handshake(&acceptor, stream, peer).await else {
+                return;
+            };
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("Connection from {} closed with error: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handshake(acceptor: &TlsAcceptor, stream: TcpStream, peer: SocketAddr) -> Option<TlsStream<TcpStream>> {
+    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+        Ok(Ok(stream)) => Some(stream),
+        Ok(Err(e)) => {
+            debug!("TLS handshake with {} failed: {}", peer, e);
+            None
+        }
+        Err(_) => {
+            debug!("TLS handshake with {} timed out", peer);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_min_version() {
+        assert_eq!("1.3".parse::<TlsVersion>().unwrap(), TlsVersion::Tls13);
+        assert!("1.1".parse::<TlsVersion>().is_err());
+        assert_eq!(serde_json::to_string(&TlsVersion::Tls12).unwrap(), "\"1.2\"");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_clients_below_min_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let (cert_path, key_path) = (dir.path().join("cert.pem"), dir.path().join("key.pem"));
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/health", axum::routing::get(|| async { "ok" }));
+        tokio::spawn(serve(listener, acceptor(&cert_path, &key_path, TlsVersion::Tls13).unwrap(), app));
+
+        let connect = |versions: &[&'static rustls::SupportedProtocolVersion]| {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.add(certified.cert.der().clone()).unwrap();
+            let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_protocol_versions(versions)
+                .unwrap()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            async move {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                tokio_rustls::TlsConnector::from(Arc::new(config))
+                    .connect("localhost".try_into().unwrap(), stream)
+                    .await
+            }
+        };
+        assert!(connect(TLS13_ONLY).await.is_ok());
+        assert!(connect(&[&rustls::version::TLS12]).await.is_err());
+        assert!(acceptor(dir.path().join("missing.pem"), &key_path, TlsVersion::Tls12).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Counters for operations stopped by their deadline or by the client
+
+use crate::LargetableError;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Cancellation counters shared by the engine and the deadline layer
+#[derive(Debug, Default)]
+pub struct CancellationMetrics {
+    with_deadline: AtomicU64,
+    deadline_exceeded: AtomicU64,
+    client_cancelled: AtomicU64,
+}
+
+/// Snapshot of the cancellation counters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CancellationStats {
+    /// Requests that arrived with a timeout, or were given the server default
+    pub with_deadline: u64,
+    /// Operations stopped because their deadline passed
+    pub deadline_exceeded: u64,
+    /// Operations stopped because the client went away before the reply
+    pub client_cancelled: u64,
+}
+
+impl CancellationMetrics {
+    pub fn record_deadline(&self) {
+        self.with_deadline.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_deadline_exceeded(&self) {
+        self.deadline_exceeded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_client_cancelled(&self) {
+        self.client_cancelled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count `error` if it is what stopped an operation
+    pub fn record_error(&self, error: &LargetableError) {
+        match error {
+            LargetableError::DeadlineExceeded(_) => self.record_deadline_exceeded(),
+            LargetableError::Cancelled(_) => self.record_client_cancelled(),
+            _ => {}
+        }
+    }
+
+    pub fn stats(&self) -> CancellationStats {
+        CancellationStats {
+            with_deadline: self.with_deadline.load(Ordering::Relaxed),
+            deadline_exceeded: self.deadline_exceeded.load(Ordering::Relaxed),
+            client_cancelled: self.client_cancelled.load(Ordering::Relaxed),
+        }
+    }
+}
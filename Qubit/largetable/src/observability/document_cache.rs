@@ -0,0 +1,83 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Counters of the document cache in front of the storage engines
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Document cache counters, updated without the cache lock
+#[derive(Debug, Default)]
+pub struct DocumentCacheMetrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    ghost_hits: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+/// Snapshot of the document cache
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentCacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub memory_bytes: usize,
+    /// Most documents held at once
+    pub capacity: usize,
+    /// Cached documents read once since they were loaded
+    pub recent_entries: usize,
+    /// Cached documents read again since they were loaded
+    pub frequent_entries: usize,
+    /// Share of the capacity ARC currently gives to recently read documents
+    pub recency_target: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Documents loaded again shortly after being evicted, which shift the recency target
+    pub ghost_hits: u64,
+    /// Documents dropped to stay within the document and memory caps
+    pub evictions: u64,
+    /// Documents dropped because they were written or deleted
+    pub invalidations: u64,
+    pub hit_rate: f64,
+}
+
+impl DocumentCacheMetrics {
+    pub fn record_hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_ghost_hit(&self) {
+        self.ghost_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_eviction(&self) {
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_invalidation(&self) {
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counters only; the cache fills in what it holds
+    pub fn stats(&self) -> DocumentCacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        DocumentCacheStats {
+            hits,
+            misses,
+            ghost_hits: self.ghost_hits.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            hit_rate: if lookups > 0 { hits as f64 / lookups as f64 } else { 0.0 },
+            ..DocumentCacheStats::default()
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Counters of the memory held by sorts, groups, window stages and index builds
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::engine::memory_limits::MemoryConsumer;
+
+/// Memory counters, updated as reservations grow and shrink
+#[derive(Debug, Default)]
+pub struct MemoryMetrics {
+    sort_bytes: AtomicUsize,
+    group_bytes: AtomicUsize,
+    window_bytes: AtomicUsize,
+    index_build_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+    query_limit_hits: AtomicU64,
+    global_limit_hits: AtomicU64,
+    spills: AtomicU64,
+    spilled_bytes: AtomicU64,
+    exhausted: AtomicU64,
+}
+
+/// Snapshot of the memory accounting
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryStats {
+    /// Bytes reserved by every running operation
+    pub used_bytes: usize,
+    /// Most bytes reserved at once since the server started
+    pub peak_bytes: usize,
+    pub global_limit_bytes: usize,
+    pub query_limit_bytes: usize,
+    /// Share of the global limit in use, from 0 to 1
+    pub pressure: f64,
+    pub sort_bytes: usize,
+    pub group_bytes: usize,
+    pub window_bytes: usize,
+    pub index_build_bytes: usize,
+    /// Reservations refused because their operation reached the per-query limit
+    pub query_limit_hits: u64,
+    /// Reservations refused because every operation together reached the global limit
+    pub global_limit_hits: u64,
+    /// Sorted runs and groups written to temporary files
+    pub spills: u64,
+    pub spilled_bytes: u64,
+    /// Operations failed because they ran out of memory and could not spill
+    pub exhausted: u64,
+}
+
+impl MemoryMetrics {
+    fn consumer_bytes(&self, consumer: MemoryConsumer) -> &AtomicUsize {
+        match consumer {
+            MemoryConsumer::Sort => &self.sort_bytes,
+            MemoryConsumer::Group => &self.group_bytes,
+            MemoryConsumer::Window => &self.window_bytes,
+            MemoryConsumer::IndexBuild => &self.index_build_bytes,
+        }
+    }
+
+    /// Record `bytes` more held by `consumer`, with `used` the new total of all operations
+    pub fn record_reserved(&self, consumer: MemoryConsumer, bytes: usize, used: usize) {
+        self.consumer_bytes(consumer).fetch_add(bytes, Ordering::Relaxed);
+        self.peak_bytes.fetch_max(used, Ordering::Relaxed);
+    }
+
+    pub fn record_released(&self, consumer: MemoryConsumer, bytes: usize) {
+        self.consumer_bytes(consumer).fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_query_limit_hit(&self) {
+        self.query_limit_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_global_limit_hit(&self) {
+        self.global_limit_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_spill(&self, bytes: u64) {
+        self.spills.fetch_add(1, Ordering::Relaxed);
+        self.spilled_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_exhausted(&self) {
+        self.exhausted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counters only; the governor fills in its usage and limits
+    pub fn stats(&self) -> MemoryStats {
+        MemoryStats {
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+            sort_bytes: self.sort_bytes.load(Ordering::Relaxed),
+            group_bytes: self.group_bytes.load(Ordering::Relaxed),
+            window_bytes: self.window_bytes.load(Ordering::Relaxed),
+            index_build_bytes: self.index_build_bytes.load(Ordering::Relaxed),
+            query_limit_hits: self.query_limit_hits.load(Ordering::Relaxed),
+            global_limit_hits: self.global_limit_hits.load(Ordering::Relaxed),
+            spills: self.spills.load(Ordering::Relaxed),
+            spilled_bytes: self.spilled_bytes.load(Ordering::Relaxed),
+            exhausted: self.exhausted.load(Ordering::Relaxed),
+            ..MemoryStats::default()
+        }
+    }
+}
@@ -8,5 +8,8 @@
 
 pub mod tracing;
 pub mod metrics;
+pub mod cancellation;
+pub mod document_cache;
+pub mod memory;
 
 pub use tracing::init_tracing;
\ No newline at end of file
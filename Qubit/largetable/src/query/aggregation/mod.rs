@@ -5,3 +5,713 @@
 // ===========================================
 
 //! Aggregation engine
+//!
+//! Pipelines run as a chain of document streams. `$match`, `$project`,
+//! `$unwind`, `$lookup`, `$skip` and `$limit` pass documents through one at a
+//! time, and `$limit` stops pulling from the source once it is satisfied.
+//! `$group` keeps one accumulator state per group rather than the grouped
+//! documents, and a `$sort` followed by `$limit` keeps only the top documents.
+//! A `$sort` without a limit and `$setWindowFields` have to read their whole
+//! input before emitting anything.
+//!
+//! Sorts, groups and window stages count what they hold against the
+//! operation's memory limits (see [`crate::engine::memory_limits`]). Over a
+//! limit, sorts and groups write sorted runs to temporary files and merge
+//! them as they are read; window stages fail.
+
+pub mod window;
+
+use crate::document::DocumentUtils;
+use crate::engine::memory_limits::{document_size, value_size, MemoryConsumer, MemoryReservation};
+use crate::query::{Accumulator, AggregationStage, SortDirection, SortField};
+use crate::storage::StorageEngine;
+use crate::{Document, DocumentId, LargetableError, Result, Value};
+use async_trait::async_trait;
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Documents read per storage scan when streaming a collection
+pub const DEFAULT_SCAN_BATCH_SIZE: usize = 1_000;
+
+/// Stream of documents flowing between pipeline stages
+pub type DocumentStream<'a> = BoxStream<'a, Result<(DocumentId, Document)>>;
+
+/// Resolves the foreign side of a `$lookup` stage
+#[async_trait]
+pub trait LookupSource: Send + Sync {
+    /// Documents in collection `from` whose `foreign_field` equals `value`
+    async fn lookup(&self, from: &str, foreign_field: &str, value: &Value) -> Result<Vec<Document>>;
+}
+
+/// Stream every document of a storage engine, one scan batch at a time
+pub fn scan_stream(engine: Arc<dyn StorageEngine>, batch_size: usize) -> DocumentStream<'static> {
+    let batch_size = batch_size.max(1);
+
+    // `scan` includes its start key, so every batch after the first repeats
+    // the previous batch's last document
+    stream::try_unfold(Some(None), move |state: Option<Option<DocumentId>>| {
+        let engine = engine.clone();
+        async move {
+            let Some(last) = state else {
+                return Ok(None);
+            };
+            crate::engine::deadline::checkpoint()?;
+
+            let limit = if last.is_some() { batch_size + 1 } else { batch_size };
+            let batch = engine.scan(last, limit).await?;
+            let exhausted = batch.len() < limit;
+
+            let batch: Vec<_> = batch.into_iter().filter(|(id, _)| Some(*id) != last).collect();
+            let next = match batch.last() {
+                Some((id, _)) if !exhausted => Some(Some(*id)),
+                _ => None,
+            };
+
+            Ok::<_, LargetableError>(Some((stream::iter(batch.into_iter().map(Ok)), next)))
+        }
+    })
+    .try_flatten()
+    .boxed()
+}
+
+/// Chain `stages` onto `source`
+pub fn execute_stream<'a>(
+    stages: &[AggregationStage],
+    lookup: Option<Arc<dyn LookupSource>>,
+    source: DocumentStream<'a>,
+) -> DocumentStream<'a> {
+    let mut stream = source;
+    for (position, stage) in stages.iter().enumerate() {
+        stream = apply_stage(stage, &stages[position + 1..], lookup.clone(), stream);
+    }
+    stream
+}
+
+fn apply_stage<'a>(
+    stage: &AggregationStage,
+    rest: &[AggregationStage],
+    lookup: Option<Arc<dyn LookupSource>>,
+    input: DocumentStream<'a>,
+) -> DocumentStream<'a> {
+    match stage {
+        AggregationStage::Match(filter) => {
+            let filter = filter.clone();
+            input
+                .try_filter_map(move |(id, doc)| {
+                    future::ready(DocumentUtils::matches_filter(&doc, &filter).map(|matched| matched.then_some((id, doc))))
+                })
+                .boxed()
+        }
+        AggregationStage::Project(fields) => {
+            let fields = fields.clone();
+            input.map_ok(move |(id, doc)| (id, project_document(id, doc, &fields))).boxed()
+        }
+        AggregationStage::Skip(skip) => {
+            let mut remaining = *skip;
+            input
+                .try_filter(move |_| {
+                    let keep = remaining == 0;
+                    remaining = remaining.saturating_sub(1);
+                    future::ready(keep)
+                })
+                .boxed()
+        }
+        AggregationStage::Limit(limit) => input.take(*limit).boxed(),
+        AggregationStage::Unwind(field) => {
+            let field = field.clone();
+            input
+                .map(move |item| item.and_then(|(id, doc)| unwind_document(id, doc, &field)))
+                .map_ok(|docs| stream::iter(docs.into_iter().map(Ok)))
+                .try_flatten()
+                .boxed()
+        }
+        AggregationStage::Lookup { from, local_field, foreign_field, as_field } => {
+            let Some(lookup) = lookup else {
+                return stream::once(future::ready(Err(LargetableError::Query(
+                    "$lookup requires a lookup source".to_string(),
+                ))))
+                .boxed();
+            };
+            let (from, local_field, foreign_field, as_field) =
+                (from.clone(), local_field.clone(), foreign_field.clone(), as_field.clone());
+
+            input
+                .and_then(move |(id, mut doc)| {
+                    let lookup = lookup.clone();
+                    let local = DocumentUtils::get_field(&doc, &local_field).cloned();
+                    let (from, foreign_field, as_field) = (from.clone(), foreign_field.clone(), as_field.clone());
+                    async move {
+                        // Documents without the local field join to nothing
+                        let matches = match local {
+                            Some(value) => lookup.lookup(&from, &foreign_field, &value).await?,
+                            None => Vec::new(),
+                        };
+                        DocumentUtils::set_field(&mut doc, &as_field, Value::Array(matches.into_iter().map(Value::Document).collect()))?;
+                        Ok((id, doc))
+                    }
+                })
+                .boxed()
+        }
+        AggregationStage::Group { by, accumulators } => {
+            stream::once(group_documents(input, by.clone(), accumulators.clone())).try_flatten().boxed()
+        }
+        AggregationStage::SetWindowFields { partition_by, sort_by, output } => {
+            stream::once(window::window_documents(input, partition_by.clone(), sort_by.clone(), output.clone()))
+                .map_ok(|docs| stream::iter(docs.into_iter().map(Ok)))
+                .try_flatten()
+                .boxed()
+        }
+        AggregationStage::Sort(fields) => {
+            stream::once(sort_documents(input, fields.clone(), sort_bound(rest))).try_flatten().boxed()
+        }
+    }
+}
+
+/// Number of sorted documents the following `$skip`/`$limit` stages can consume
+fn sort_bound(rest: &[AggregationStage]) -> Option<usize> {
+    let mut skipped = 0usize;
+    for stage in rest {
+        match stage {
+            AggregationStage::Skip(skip) => skipped = skipped.saturating_add(*skip),
+            AggregationStage::Limit(limit) => return Some(skipped.saturating_add(*limit)),
+            _ => return None,
+        }
+    }
+    None
+}
+
+async fn sort_documents<'a>(
+    mut input: DocumentStream<'a>,
+    fields: Vec<SortField>,
+    bound: Option<usize>,
+) -> Result<DocumentStream<'a>> {
+    let order = move |a: &(DocumentId, Document), b: &(DocumentId, Document)| compare_documents(&a.1, &b.1, &fields);
+    let mut memory = MemoryReservation::current(MemoryConsumer::Sort);
+    let mut runs = Vec::new();
+
+    // With a bound, the buffer is trimmed back to the current top documents
+    // whenever it doubles. Over the memory limit, the buffer is written out
+    // as a sorted run and the runs are merged at the end. The sort is
+    // stable, so ties keep arrival order.
+    let mut buffer = Vec::new();
+    while let Some(item) = input.try_next().await? {
+        let size = document_size(&item.1);
+        if !memory.try_grow(size) {
+            buffer.sort_by(&order);
+            buffer.truncate(bound.unwrap_or(usize::MAX));
+            runs.push(memory.spill(buffer.drain(..))?);
+            memory.grow(size)?;
+        }
+        buffer.push(item);
+
+        if let Some(bound) = bound {
+            if buffer.len() >= bound.max(1) * 2 {
+                buffer.sort_by(&order);
+                buffer.truncate(bound);
+                let held: usize = buffer.iter().map(|(_, doc)| document_size(doc)).sum();
+                memory.shrink(memory.size() - held);
+            }
+        }
+    }
+
+    buffer.sort_by(&order);
+    buffer.truncate(bound.unwrap_or(usize::MAX));
+    let mut sorted = Vec::with_capacity(runs.len() + 1);
+    for run in runs {
+        sorted.push(Box::new(run.read()?) as Run<_>);
+    }
+    sorted.push(Box::new(buffer.into_iter().map(Ok)));
+
+    Ok(stream::iter(MergeRuns::new(sorted, order, memory)?).boxed())
+}
+
+/// Sorted runs of a spilled sort or group, read back one record at a time
+type Run<T> = Box<dyn Iterator<Item = Result<T>> + Send>;
+
+/// Merge of sorted runs into one sorted sequence
+///
+/// Ties go to the earlier run, and runs are written in arrival order, so the
+/// merge is as stable as the sorts that produced them. Holds the memory of
+/// the run kept in memory until the merge is dropped.
+struct MergeRuns<T, F> {
+    runs: Vec<Run<T>>,
+    heads: Vec<Option<T>>,
+    order: F,
+    _memory: MemoryReservation,
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> MergeRuns<T, F> {
+    fn new(mut runs: Vec<Run<T>>, order: F, memory: MemoryReservation) -> Result<Self> {
+        let heads = runs.iter_mut().map(|run| run.next().transpose()).collect::<Result<_>>()?;
+        Ok(Self { runs, heads, order, _memory: memory })
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Iterator for MergeRuns<T, F> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        let mut first: Option<(usize, &T)> = None;
+        for (position, head) in self.heads.iter().enumerate() {
+            let Some(head) = head else { continue };
+            let earlier = match first {
+                Some((_, current)) => (self.order)(head, current) == Ordering::Less,
+                None => true,
+            };
+            if earlier {
+                first = Some((position, head));
+            }
+        }
+
+        let (position, _) = first?;
+        let head = self.heads[position].take();
+        match self.runs[position].next().transpose() {
+            Ok(next) => self.heads[position] = next,
+            Err(e) => return Some(Err(e)),
+        }
+        head.map(Ok)
+    }
+}
+
+/// Running state of one accumulator within a group
+#[derive(Serialize, Deserialize)]
+pub(crate) enum AccumulatorState {
+    Sum(f64),
+    Count(i64),
+    Avg { sum: f64, count: usize },
+    Min(Option<f64>),
+    Max(Option<f64>),
+    First(Option<Value>),
+    Last(Value),
+}
+
+impl AccumulatorState {
+    pub(crate) fn new(accumulator: &Accumulator) -> Self {
+        match accumulator {
+            Accumulator::Sum(_) => AccumulatorState::Sum(0.0),
+            Accumulator::Count => AccumulatorState::Count(0),
+            Accumulator::Avg(_) => AccumulatorState::Avg { sum: 0.0, count: 0 },
+            Accumulator::Min(_) => AccumulatorState::Min(None),
+            Accumulator::Max(_) => AccumulatorState::Max(None),
+            Accumulator::First(_) => AccumulatorState::First(None),
+            Accumulator::Last(_) => AccumulatorState::Last(Value::Null),
+        }
+    }
+
+    pub(crate) fn add(&mut self, accumulator: &Accumulator, doc: &Document) {
+        let number = |field: &str| DocumentUtils::get_field(doc, field).and_then(as_f64);
+        let value = |field: &str| DocumentUtils::get_field(doc, field).cloned().unwrap_or(Value::Null);
+
+        match (self, accumulator) {
+            (AccumulatorState::Sum(sum), Accumulator::Sum(field)) => *sum += number(field).unwrap_or(0.0),
+            (AccumulatorState::Count(count), Accumulator::Count) => *count += 1,
+            (AccumulatorState::Avg { sum, count }, Accumulator::Avg(field)) => {
+                if let Some(n) = number(field) {
+                    *sum += n;
+                    *count += 1;
+                }
+            }
+            (AccumulatorState::Min(min), Accumulator::Min(field)) => {
+                if let Some(n) = number(field) {
+                    *min = Some(min.map_or(n, |m| m.min(n)));
+                }
+            }
+            (AccumulatorState::Max(max), Accumulator::Max(field)) => {
+                if let Some(n) = number(field) {
+                    *max = Some(max.map_or(n, |m| m.max(n)));
+                }
+            }
+            (AccumulatorState::First(first), Accumulator::First(field)) if first.is_none() => *first = Some(value(field)),
+            (AccumulatorState::Last(last), Accumulator::Last(field)) => *last = value(field),
+            _ => {}
+        }
+    }
+
+    /// Fold in the state of the same group over later documents
+    fn merge(&mut self, later: AccumulatorState) {
+        match (self, later) {
+            (AccumulatorState::Sum(sum), AccumulatorState::Sum(more)) => *sum += more,
+            (AccumulatorState::Count(count), AccumulatorState::Count(more)) => *count += more,
+            (AccumulatorState::Avg { sum, count }, AccumulatorState::Avg { sum: more_sum, count: more_count }) => {
+                *sum += more_sum;
+                *count += more_count;
+            }
+            (AccumulatorState::Min(min), AccumulatorState::Min(Some(n))) => *min = Some(min.map_or(n, |m| m.min(n))),
+            (AccumulatorState::Max(max), AccumulatorState::Max(Some(n))) => *max = Some(max.map_or(n, |m| m.max(n))),
+            (AccumulatorState::First(first), AccumulatorState::First(later)) if first.is_none() => *first = later,
+            (AccumulatorState::Last(last), AccumulatorState::Last(later)) => *last = later,
+            _ => {}
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + match self {
+                AccumulatorState::First(Some(value)) | AccumulatorState::Last(value) => value_size(value),
+                _ => 0,
+            }
+    }
+
+    pub(crate) fn finish(self) -> Value {
+        match self {
+            AccumulatorState::Sum(sum) => Value::Float64(sum),
+            AccumulatorState::Count(count) => Value::Int64(count),
+            AccumulatorState::Avg { count: 0, .. } => Value::Null,
+            AccumulatorState::Avg { sum, count } => Value::Float64(sum / count as f64),
+            AccumulatorState::Min(min) => min.map_or(Value::Null, Value::Float64),
+            AccumulatorState::Max(max) => max.map_or(Value::Null, Value::Float64),
+            AccumulatorState::First(first) => first.unwrap_or(Value::Null),
+            AccumulatorState::Last(last) => last,
+        }
+    }
+}
+
+/// Group key and the state of each accumulator, in the order of the stage's accumulators
+type GroupState = (String, Vec<AccumulatorState>);
+
+async fn group_documents<'a>(
+    mut input: DocumentStream<'a>,
+    by: String,
+    accumulators: HashMap<String, Accumulator>,
+) -> Result<DocumentStream<'a>> {
+    let accumulators: Vec<(String, Accumulator)> = accumulators.into_iter().collect();
+    let mut memory = MemoryReservation::current(MemoryConsumer::Group);
+    let mut runs = Vec::new();
+
+    // Groups are emitted in the order their first document arrived. Over the
+    // memory limit, the groups so far are written out sorted by key, and the
+    // runs are merged by key at the end, so spilled groups come out in key
+    // order instead. A group is charged for its key and its states after
+    // its first document.
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<GroupState> = Vec::new();
+
+    while let Some((_, doc)) = input.try_next().await? {
+        let key = group_key(DocumentUtils::get_field(&doc, &by));
+        if let Some(position) = positions.get(&key) {
+            for ((_, accumulator), state) in accumulators.iter().zip(groups[*position].1.iter_mut()) {
+                state.add(accumulator, &doc);
+            }
+            continue;
+        }
+
+        let mut states: Vec<_> = accumulators.iter().map(|(_, accumulator)| AccumulatorState::new(accumulator)).collect();
+        for ((_, accumulator), state) in accumulators.iter().zip(states.iter_mut()) {
+            state.add(accumulator, &doc);
+        }
+        let size = key.len() * 2 + states.iter().map(AccumulatorState::size).sum::<usize>();
+        if !memory.try_grow(size) {
+            positions.clear();
+            groups.sort_by(|a, b| a.0.cmp(&b.0));
+            runs.push(memory.spill(groups.drain(..))?);
+            memory.grow(size)?;
+        }
+        positions.insert(key.clone(), groups.len());
+        groups.push((key, states));
+    }
+    drop(positions);
+
+    let finish = move |(key, states): GroupState| {
+        let mut doc = crate::document::DocumentBuilder::new().string("_id", key).build();
+        for ((name, _), state) in accumulators.iter().zip(states) {
+            doc.fields.insert(name.clone(), state.finish());
+        }
+        (uuid::Uuid::now_v7(), doc)
+    };
+    // A single run is merged as it is, so groups that never spilled keep arrival order
+    if !runs.is_empty() {
+        groups.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+    let mut sorted = Vec::with_capacity(runs.len() + 1);
+    for run in runs {
+        sorted.push(Box::new(run.read()?) as Run<_>);
+    }
+    sorted.push(Box::new(groups.into_iter().map(Ok)));
+    let mut merged = MergeRuns::new(sorted, |a: &GroupState, b: &GroupState| a.0.cmp(&b.0), memory)?.peekable();
+
+    // Runs of the same key come out together and in the order they were written
+    let groups = std::iter::from_fn(move || {
+        let (key, mut states) = match merged.next()? {
+            Ok(group) => group,
+            Err(e) => return Some(Err(e)),
+        };
+        while matches!(merged.peek(), Some(Ok((next, _))) if *next == key) {
+            if let Some(Ok((_, later))) = merged.next() {
+                for (state, later) in states.iter_mut().zip(later) {
+                    state.merge(later);
+                }
+            }
+        }
+        Some(Ok(finish((key, states))))
+    });
+    Ok(stream::iter(groups).boxed())
+}
+
+pub(crate) fn group_key(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Int64(i)) => i.to_string(),
+        Some(Value::Float64(f)) => f.to_string(),
+        Some(Value::Bool(b)) => b.to_string(),
+        _ => "null".to_string(),
+    }
+}
+
+fn unwind_document(id: DocumentId, doc: Document, field: &str) -> Result<Vec<(DocumentId, Document)>> {
+    let Some(Value::Array(items)) = DocumentUtils::get_field(&doc, field) else {
+        return Ok(vec![(id, doc)]);
+    };
+
+    let mut unwound = Vec::with_capacity(items.len());
+    for item in items.clone() {
+        let mut new_doc = doc.clone();
+        DocumentUtils::set_field(&mut new_doc, field, item)?;
+        unwound.push((uuid::Uuid::now_v7(), new_doc));
+    }
+    Ok(unwound)
+}
+
+/// Keep the projected fields plus the document metadata
+pub fn project_document(id: DocumentId, mut doc: Document, fields: &[String]) -> Document {
+    let mut projected = HashMap::new();
+    projected.insert("_id".to_string(), Value::String(id.to_string()));
+    projected.insert("_version".to_string(), Value::UInt64(doc.version));
+    projected.insert("_created_at".to_string(), Value::Timestamp(doc.created_at));
+    projected.insert("_updated_at".to_string(), Value::Timestamp(doc.updated_at));
+
+    for field in fields {
+        if let Some(value) = doc.fields.remove(field) {
+            projected.insert(field.clone(), value);
+        }
+    }
+
+    doc.fields = projected;
+    doc
+}
+
+/// Order two documents by a list of sort fields; missing fields sort first
+pub fn compare_documents(a: &Document, b: &Document, fields: &[SortField]) -> Ordering {
+    for field in fields {
+        let ordering = match (DocumentUtils::get_field(a, &field.field), DocumentUtils::get_field(b, &field.field)) {
+            (Some(a), Some(b)) => compare_values(a, b),
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        };
+        let ordering = match field.direction {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse(),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Compare two values of comparable types; numbers compare across widths
+pub fn compare_values(a: &Value, b: &Value) -> Ordering {
+    if let (Some(a), Some(b)) = (as_f64(a), as_f64(b)) {
+        return a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    }
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Timestamp(a), Value::Timestamp(b)) => a.cmp(b),
+        (Value::ObjectId(a), Value::ObjectId(b)) => a.cmp(b),
+        _ => Ordering::Equal,
+    }
+}
+
+/// Equality used to join `$lookup` fields
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    if let (Some(a), Some(b)) = (as_f64(a), as_f64(b)) {
+        return a == b;
+    }
+    match (a, b) {
+        (Value::Null, Value::Null) => true,
+        (Value::String(a), Value::String(b)) => a == b,
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Timestamp(a), Value::Timestamp(b)) => a == b,
+        (Value::ObjectId(a), Value::ObjectId(b)) => a == b,
+        _ => false,
+    }
+}
+
+pub(crate) fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int32(i) => Some(*i as f64),
+        Value::Int64(i) => Some(*i as f64),
+        Value::UInt64(u) => Some(*u as f64),
+        Value::Float32(f) => Some(*f as f64),
+        Value::Float64(f) => Some(*f),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::DocumentBuilder;
+    use crate::engine::memory_limits::{with_query_memory, MemoryGovernor, MemoryLimitsConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    fn order(customer: &str, amount: i64) -> (DocumentId, Document) {
+        let doc = DocumentBuilder::new()
+            .string("customer", customer)
+            .int("amount", amount)
+            .bool("paid", amount > 5)
+            .build();
+        (uuid::Uuid::now_v7(), doc)
+    }
+
+    fn source(docs: Vec<(DocumentId, Document)>) -> DocumentStream<'static> {
+        stream::iter(docs.into_iter().map(Ok)).boxed()
+    }
+
+    struct Customers(Vec<Document>);
+
+    #[async_trait]
+    impl LookupSource for Customers {
+        async fn lookup(&self, from: &str, foreign_field: &str, value: &Value) -> Result<Vec<Document>> {
+            assert_eq!(from, "customers");
+            Ok(self
+                .0
+                .iter()
+                .filter(|doc| DocumentUtils::get_field(doc, foreign_field).is_some_and(|v| values_equal(v, value)))
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_group_sort_limit() {
+        let mut totals = HashMap::new();
+        totals.insert("total".to_string(), Accumulator::Sum("amount".to_string()));
+        totals.insert("orders".to_string(), Accumulator::Count);
+
+        let stages = vec![
+            AggregationStage::Match(serde_json::json!({"paid": true})),
+            AggregationStage::Group { by: "customer".to_string(), accumulators: totals },
+            AggregationStage::Sort(vec![SortField { field: "total".to_string(), direction: SortDirection::Descending }]),
+            AggregationStage::Limit(2),
+        ];
+        let docs = vec![order("ada", 10), order("bob", 40), order("ada", 20), order("cy", 1), order("cy", 25), order("bob", 3)];
+
+        let results: Vec<_> = execute_stream(&stages, None, source(docs)).try_collect().await.unwrap();
+        let summary: Vec<(String, f64)> = results
+            .iter()
+            .map(|(_, doc)| match (DocumentUtils::get_field(doc, "_id"), DocumentUtils::get_field(doc, "total")) {
+                (Some(Value::String(customer)), Some(Value::Float64(total))) => (customer.clone(), *total),
+                other => panic!("unexpected group {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(summary, vec![("bob".to_string(), 40.0), ("ada".to_string(), 30.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_limit_stops_pulling_from_source() {
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let counter = pulled.clone();
+        let docs = stream::iter((0..1_000).map(|i| order("ada", i)))
+            .inspect(move |_| {
+                counter.fetch_add(1, AtomicOrdering::SeqCst);
+            })
+            .map(Ok)
+            .boxed();
+
+        let stages = vec![AggregationStage::Skip(3), AggregationStage::Limit(5)];
+        let results: Vec<_> = execute_stream(&stages, None, docs).try_collect().await.unwrap();
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(pulled.load(AtomicOrdering::SeqCst), 8);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_joins_foreign_documents() {
+        let customers = Customers(vec![
+            DocumentBuilder::new().string("name", "ada").string("tier", "gold").build(),
+            DocumentBuilder::new().string("name", "bob").string("tier", "basic").build(),
+        ]);
+        let stages = vec![AggregationStage::Lookup {
+            from: "customers".to_string(),
+            local_field: "customer".to_string(),
+            foreign_field: "name".to_string(),
+            as_field: "profile".to_string(),
+        }];
+
+        let results: Vec<_> = execute_stream(&stages, Some(Arc::new(customers)), source(vec![order("ada", 10), order("zed", 5)]))
+            .try_collect()
+            .await
+            .unwrap();
+
+        let joined: Vec<usize> = results
+            .iter()
+            .map(|(_, doc)| match DocumentUtils::get_field(doc, "profile") {
+                Some(Value::Array(items)) => items.len(),
+                _ => usize::MAX,
+            })
+            .collect();
+        assert_eq!(joined, vec![1, 0]);
+
+        let missing_source = execute_stream(&stages, None, source(vec![order("ada", 10)])).try_collect::<Vec<_>>().await;
+        assert!(missing_source.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sort_and_group_spill_over_the_memory_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let governor = Arc::new(MemoryGovernor::new(MemoryLimitsConfig {
+            query_limit_bytes: 4096,
+            spill_dir: dir.path().to_path_buf(),
+            ..MemoryLimitsConfig::default()
+        }));
+        let docs: Vec<_> = (0..200).map(|i| order(&format!("c{:03}", i % 50), (i * 7919) % 1000)).collect();
+        let amount = |doc: &Document| match DocumentUtils::get_field(doc, "amount") {
+            Some(Value::Int64(amount)) => *amount,
+            other => panic!("unexpected amount {:?}", other),
+        };
+
+        let sort = vec![AggregationStage::Sort(vec![SortField { field: "amount".to_string(), direction: SortDirection::Ascending }])];
+        let sorted: Vec<_> = with_query_memory(governor.query(), execute_stream(&sort, None, source(docs.clone())).try_collect())
+            .await
+            .unwrap();
+        let mut expected: Vec<i64> = docs.iter().map(|(_, doc)| amount(doc)).collect();
+        expected.sort();
+        assert_eq!(sorted.iter().map(|(_, doc)| amount(doc)).collect::<Vec<_>>(), expected);
+
+        let mut accumulators = HashMap::new();
+        accumulators.insert("total".to_string(), Accumulator::Sum("amount".to_string()));
+        accumulators.insert("orders".to_string(), Accumulator::Count);
+        accumulators.insert("first".to_string(), Accumulator::First("amount".to_string()));
+        let group = vec![AggregationStage::Group { by: "customer".to_string(), accumulators }];
+        let grouped: Vec<_> = with_query_memory(governor.query(), execute_stream(&group, None, source(docs.clone())).try_collect())
+            .await
+            .unwrap();
+
+        assert_eq!(grouped.len(), 50);
+        for (_, doc) in &grouped {
+            let Some(Value::String(customer)) = DocumentUtils::get_field(doc, "_id") else {
+                panic!("group without a key");
+            };
+            let amounts: Vec<i64> = docs
+                .iter()
+                .filter(|(_, order)| matches!(DocumentUtils::get_field(order, "customer"), Some(Value::String(c)) if c == customer))
+                .map(|(_, order)| amount(order))
+                .collect();
+            assert!(matches!(DocumentUtils::get_field(doc, "total"), Some(Value::Float64(total)) if *total == amounts.iter().sum::<i64>() as f64));
+            assert!(matches!(DocumentUtils::get_field(doc, "orders"), Some(Value::Int64(4))));
+            assert!(matches!(DocumentUtils::get_field(doc, "first"), Some(Value::Int64(first)) if *first == amounts[0]));
+        }
+
+        let stats = governor.stats();
+        assert!(stats.spills >= 2, "expected both stages to spill, got {:?}", stats);
+        assert_eq!(stats.used_bytes, 0);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
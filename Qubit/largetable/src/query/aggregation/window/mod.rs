@@ -0,0 +1,294 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Window functions for `$setWindowFields`
+//!
+//! Documents are split into partitions, each partition is sorted, and every
+//! output field is evaluated over a window of neighbouring documents. Outputs
+//! are computed from the input documents only, so one output never sees
+//! another. Windows are recomputed per document unless they cover the same
+//! documents as the previous one, which makes whole-partition windows cheap and
+//! sliding windows proportional to their width.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use futures::stream::TryStreamExt;
+
+use super::{as_f64, compare_documents, group_key, AccumulatorState, DocumentStream};
+use crate::document::DocumentUtils;
+use crate::engine::memory_limits::{document_size, MemoryConsumer, MemoryReservation};
+use crate::query::{SortDirection, SortField, WindowBounds, WindowField, WindowFunction};
+use crate::{Document, DocumentId, Result, Value};
+
+pub(crate) async fn window_documents(
+    mut input: DocumentStream<'_>,
+    partition_by: Option<String>,
+    sort_by: SortField,
+    output: HashMap<String, WindowField>,
+) -> Result<Vec<(DocumentId, Document)>> {
+    // Partitions are emitted in the order their first document arrived
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut partitions: Vec<Vec<(DocumentId, Document)>> = Vec::new();
+    let mut memory = MemoryReservation::current(MemoryConsumer::Window);
+    while let Some((id, doc)) = input.try_next().await? {
+        memory.grow(document_size(&doc))?;
+        let key = partition_by
+            .as_deref()
+            .map(|field| group_key(DocumentUtils::get_field(&doc, field)))
+            .unwrap_or_default();
+        let position = *positions.entry(key).or_insert_with(|| {
+            partitions.push(Vec::new());
+            partitions.len() - 1
+        });
+        partitions[position].push((id, doc));
+    }
+
+    let mut results = Vec::new();
+    for mut partition in partitions {
+        partition.sort_by(|a, b| compare_documents(&a.1, &b.1, std::slice::from_ref(&sort_by)));
+        let docs: Vec<&Document> = partition.iter().map(|(_, doc)| doc).collect();
+        let evaluator = Evaluator::new(&docs, &sort_by);
+
+        let mut computed: Vec<Vec<(&String, Value)>> = vec![Vec::with_capacity(output.len()); docs.len()];
+        for (name, field) in &output {
+            for (row, value) in evaluator.evaluate(field).into_iter().enumerate() {
+                computed[row].push((name, value));
+            }
+        }
+
+        for ((id, mut doc), values) in partition.into_iter().zip(computed) {
+            for (name, value) in values {
+                DocumentUtils::set_field(&mut doc, name, value)?;
+            }
+            results.push((id, doc));
+        }
+    }
+    Ok(results)
+}
+
+/// Numeric position of a document along the sort field
+fn sort_key(value: &Value) -> Option<f64> {
+    match value {
+        Value::Timestamp(t) => Some(*t as f64),
+        other => as_f64(other),
+    }
+}
+
+/// One sorted partition
+struct Evaluator<'a> {
+    docs: &'a [&'a Document],
+    sort_by: &'a SortField,
+    keys: Vec<Option<f64>>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(docs: &'a [&'a Document], sort_by: &'a SortField) -> Self {
+        let keys = docs
+            .iter()
+            .map(|doc| DocumentUtils::get_field(doc, &sort_by.field).and_then(sort_key))
+            .collect();
+        Self { docs, sort_by, keys }
+    }
+
+    fn evaluate(&self, field: &WindowField) -> Vec<Value> {
+        let mut previous: Option<(Range<usize>, Value)> = None;
+        (0..self.docs.len())
+            .map(|row| {
+                let window = self.window(row, &field.window);
+                match &field.function {
+                    WindowFunction::RowNumber => Value::Int64(row as i64 + 1),
+                    WindowFunction::Rank => Value::Int64(self.rank(row) as i64 + 1),
+                    WindowFunction::Shift { field, by } => self.shift(row, field, *by),
+                    WindowFunction::Derivative { field, unit } => self.derivative(window, field, *unit),
+                    WindowFunction::Accumulate(accumulator) => {
+                        if let Some((range, value)) = &previous {
+                            if *range == window {
+                                return value.clone();
+                            }
+                        }
+                        let mut state = AccumulatorState::new(accumulator);
+                        for doc in &self.docs[window.clone()] {
+                            state.add(accumulator, doc);
+                        }
+                        let value = state.finish();
+                        previous = Some((window, value.clone()));
+                        value
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Rows covered by the window of `row`
+    fn window(&self, row: usize, bounds: &WindowBounds) -> Range<usize> {
+        let len = self.docs.len();
+        match bounds {
+            WindowBounds::Partition => 0..len,
+            WindowBounds::Documents { lower, upper } => {
+                let offset = |delta: i64| (row as i64).saturating_add(delta).clamp(0, len as i64) as usize;
+                let start = lower.map_or(0, offset);
+                let end = upper.map_or(len, |upper| offset(upper.saturating_add(1)));
+                start..end.max(start)
+            }
+            WindowBounds::Range { lower, upper } => {
+                let Some(current) = self.keys[row] else {
+                    return row..row;
+                };
+                let lower = lower.map_or(f64::NEG_INFINITY, |lower| current + lower);
+                let upper = upper.map_or(f64::INFINITY, |upper| current + upper);
+                // Documents without a numeric key sort first ascending and last
+                // descending, which is where -inf keeps them
+                let key = |row: &Option<f64>| row.unwrap_or(f64::NEG_INFINITY);
+                let (start, end) = match self.sort_by.direction {
+                    SortDirection::Ascending => (
+                        self.keys.partition_point(|k| key(k) < lower),
+                        self.keys.partition_point(|k| key(k) <= upper),
+                    ),
+                    SortDirection::Descending => (
+                        self.keys.partition_point(|k| key(k) > upper),
+                        self.keys.partition_point(|k| key(k) >= lower),
+                    ),
+                };
+                start..end.max(start)
+            }
+        }
+    }
+
+    fn rank(&self, row: usize) -> usize {
+        let sort = std::slice::from_ref(self.sort_by);
+        self.docs[..row]
+            .iter()
+            .rposition(|doc| compare_documents(doc, self.docs[row], sort) != std::cmp::Ordering::Equal)
+            .map_or(0, |before| before + 1)
+    }
+
+    fn shift(&self, row: usize, field: &str, by: i64) -> Value {
+        let target = row as i64 + by;
+        if target < 0 || target >= self.docs.len() as i64 {
+            return Value::Null;
+        }
+        DocumentUtils::get_field(self.docs[target as usize], field).cloned().unwrap_or(Value::Null)
+    }
+
+    fn derivative(&self, window: Range<usize>, field: &str, unit: f64) -> Value {
+        let points: Vec<(f64, f64)> = window
+            .filter_map(|row| {
+                let value = DocumentUtils::get_field(self.docs[row], field).and_then(as_f64)?;
+                Some((self.keys[row]?, value))
+            })
+            .collect();
+        match (points.first(), points.last()) {
+            (Some((k0, v0)), Some((k1, v1))) if k1 != k0 => Value::Float64((v1 - v0) / (k1 - k0) * unit),
+            _ => Value::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::DocumentBuilder;
+    use crate::query::Accumulator;
+    use futures::stream::{self, StreamExt};
+
+    fn reading(sensor: &str, minute: i64, value: i64) -> (DocumentId, Document) {
+        let doc = DocumentBuilder::new()
+            .string("sensor", sensor)
+            .field("ts".to_string(), Value::Timestamp(minute * 60_000_000))
+            .int("value", value)
+            .build();
+        (uuid::Uuid::now_v7(), doc)
+    }
+
+    fn field(doc: &Document, name: &str) -> Value {
+        DocumentUtils::get_field(doc, name).cloned().unwrap_or(Value::Null)
+    }
+
+    #[tokio::test]
+    async fn test_moving_average_and_derivative_per_partition() {
+        let docs = vec![
+            reading("a", 2, 30),
+            reading("b", 0, 100),
+            reading("a", 0, 10),
+            reading("a", 1, 20),
+            reading("a", 10, 40),
+        ];
+        let mut output = HashMap::new();
+        output.insert(
+            "moving".to_string(),
+            WindowField {
+                function: WindowFunction::Accumulate(Accumulator::Avg("value".to_string())),
+                window: WindowBounds::Documents { lower: Some(-1), upper: Some(0) },
+            },
+        );
+        output.insert(
+            "recent".to_string(),
+            WindowField {
+                function: WindowFunction::Accumulate(Accumulator::Count),
+                // The last five minutes, in microseconds
+                window: WindowBounds::Range { lower: Some(-300_000_000.0), upper: Some(0.0) },
+            },
+        );
+        output.insert(
+            "per_minute".to_string(),
+            WindowField {
+                function: WindowFunction::Derivative { field: "value".to_string(), unit: 60_000_000.0 },
+                window: WindowBounds::Partition,
+            },
+        );
+        let sort_by = SortField { field: "ts".to_string(), direction: SortDirection::Ascending };
+
+        let results = window_documents(stream::iter(docs.into_iter().map(Ok)).boxed(), Some("sensor".to_string()), sort_by, output)
+            .await
+            .unwrap();
+
+        let sensor_a: Vec<(f64, i64)> = results
+            .iter()
+            .filter(|(_, doc)| matches!(field(doc, "sensor"), Value::String(s) if s == "a"))
+            .map(|(_, doc)| match (field(doc, "moving"), field(doc, "recent")) {
+                (Value::Float64(moving), Value::Int64(recent)) => (moving, recent),
+                other => panic!("unexpected outputs {:?}", other),
+            })
+            .collect();
+        assert_eq!(sensor_a, vec![(10.0, 1), (15.0, 2), (25.0, 3), (35.0, 1)]);
+        assert!(matches!(field(&results[0].1, "per_minute"), Value::Float64(rate) if rate == 3.0));
+        // A single-document partition has no rate of change
+        assert!(matches!(field(&results[4].1, "per_minute"), Value::Null));
+    }
+
+    #[tokio::test]
+    async fn test_rank_row_number_and_shift() {
+        let docs = vec![reading("a", 0, 5), reading("a", 1, 9), reading("a", 2, 9), reading("a", 3, 7)];
+        let mut output = HashMap::new();
+        for (name, function) in [
+            ("row", WindowFunction::RowNumber),
+            ("rank", WindowFunction::Rank),
+            ("previous", WindowFunction::Shift { field: "value".to_string(), by: -1 }),
+        ] {
+            output.insert(name.to_string(), WindowField { function, window: WindowBounds::Partition });
+        }
+        let sort_by = SortField { field: "value".to_string(), direction: SortDirection::Descending };
+
+        let results = window_documents(stream::iter(docs.into_iter().map(Ok)).boxed(), None, sort_by, output)
+            .await
+            .unwrap();
+
+        let rows: Vec<(Value, Value, Value)> = results
+            .iter()
+            .map(|(_, doc)| (field(doc, "row"), field(doc, "rank"), field(doc, "previous")))
+            .collect();
+        let ints: Vec<(i64, i64, Option<i64>)> = rows
+            .into_iter()
+            .map(|row| match row {
+                (Value::Int64(r), Value::Int64(k), Value::Int64(p)) => (r, k, Some(p)),
+                (Value::Int64(r), Value::Int64(k), Value::Null) => (r, k, None),
+                other => panic!("unexpected outputs {:?}", other),
+            })
+            .collect();
+        assert_eq!(ints, vec![(1, 1, None), (2, 1, Some(9)), (3, 3, Some(9)), (4, 4, Some(7))]);
+    }
+}
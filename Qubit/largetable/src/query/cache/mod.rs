@@ -0,0 +1,401 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Server-side query result cache
+//!
+//! Results are keyed by the normalized query and tagged with the version of
+//! the collection they were read at. Every write bumps that version, so an
+//! entry is only served while nothing has been written since it was cached.
+
+use super::{Query, QueryResult, SortDirection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Query cache configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryCacheConfig {
+    /// Cache query results at all
+    pub enabled: bool,
+    /// Maximum number of cached results
+    pub max_entries: usize,
+    /// Maximum estimated size of all cached results
+    pub max_memory_bytes: usize,
+    /// Results larger than this are never cached
+    pub max_result_bytes: usize,
+    /// How long a result may be served after it was cached
+    pub ttl: Duration,
+}
+
+impl Default for QueryCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_entries: 10_000,
+            max_memory_bytes: 256 * 1024 * 1024, // 256MB
+            max_result_bytes: 4 * 1024 * 1024,   // 4MB
+            ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Query cache statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryCacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub memory_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped because their collection was written to
+    pub invalidations: u64,
+    /// Entries dropped because their TTL passed
+    pub expirations: u64,
+    /// Entries dropped to stay within the entry and memory caps
+    pub evictions: u64,
+    pub hit_rate: f64,
+}
+
+/// Normalized form of a query on a collection
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryCacheKey {
+    database: String,
+    collection: String,
+    query: String,
+}
+
+struct CacheEntry {
+    version: u64,
+    result: Arc<QueryResult>,
+    size_bytes: usize,
+    inserted_at: Instant,
+    /// Position in the LRU order
+    tick: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<QueryCacheKey, CacheEntry>,
+    /// Entries by last use, oldest first
+    lru: BTreeMap<u64, QueryCacheKey>,
+    memory_bytes: usize,
+    next_tick: u64,
+}
+
+impl CacheState {
+    fn touch(&mut self, key: &QueryCacheKey) {
+        self.next_tick += 1;
+        let tick = self.next_tick;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.lru.remove(&entry.tick);
+            entry.tick = tick;
+            self.lru.insert(tick, key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &QueryCacheKey) -> Option<CacheEntry> {
+        let entry = self.entries.remove(key)?;
+        self.lru.remove(&entry.tick);
+        self.memory_bytes -= entry.size_bytes;
+        Some(entry)
+    }
+}
+
+/// LRU cache of query results, invalidated by collection versions
+pub struct QueryCache {
+    config: QueryCacheConfig,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+    expirations: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl QueryCache {
+    pub fn new(config: QueryCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+            expirations: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn config(&self) -> &QueryCacheConfig {
+        &self.config
+    }
+
+    /// Key for a query, equal for queries that differ only in the order of
+    /// object keys in their filter or of their projected fields
+    pub fn key(database: &str, collection: &str, query: &Query) -> QueryCacheKey {
+        let mut projection = query.projection.clone();
+        if let Some(fields) = projection.as_mut() {
+            fields.sort();
+            fields.dedup();
+        }
+        let sort: Vec<JsonValue> = query
+            .sort
+            .iter()
+            .map(|field| {
+                let direction = match field.direction {
+                    SortDirection::Ascending => 1,
+                    SortDirection::Descending => -1,
+                };
+                serde_json::json!([field.field, direction])
+            })
+            .collect();
+        let shape = serde_json::json!({
+            "filter": query.filter,
+            "sort": sort,
+            "skip": query.skip,
+            "limit": query.limit,
+            "projection": projection,
+            // A hint does not change the documents returned, but a bad one must still fail
+            "hint": query.hint.as_ref().map(|hint| format!("{:?}", hint)),
+        });
+
+        let mut normalized = String::new();
+        write_canonical(&shape, &mut normalized);
+        QueryCacheKey {
+            database: database.to_string(),
+            collection: collection.to_string(),
+            query: normalized,
+        }
+    }
+
+    /// The cached result for `key` if it was read at `version` and has not expired
+    pub fn get(&self, key: &QueryCacheKey, version: u64) -> Option<QueryResult> {
+        let mut state = self.state.lock().unwrap();
+        let Some(entry) = state.entries.get(key) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+
+        if entry.version != version {
+            state.remove(key);
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        if entry.inserted_at.elapsed() > self.config.ttl {
+            state.remove(key);
+            self.expirations.fetch_add(1, Ordering::Relaxed);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        let result = entry.result.clone();
+        state.touch(key);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(QueryResult::clone(&result))
+    }
+
+    /// Cache a result read at `version`, evicting the least recently used
+    /// entries to stay within the caps
+    pub fn insert(&self, key: QueryCacheKey, version: u64, result: &QueryResult) {
+        if !self.config.enabled || self.config.max_entries == 0 {
+            return;
+        }
+        let size_bytes = estimate_size(&key, result);
+        if size_bytes > self.config.max_result_bytes || size_bytes > self.config.max_memory_bytes {
+            debug!("Not caching {} byte result for collection '{}'", size_bytes, key.collection);
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.remove(&key);
+        while state.entries.len() >= self.config.max_entries
+            || state.memory_bytes + size_bytes > self.config.max_memory_bytes
+        {
+            let Some((_, oldest)) = state.lru.pop_first() else {
+                break;
+            };
+            if let Some(entry) = state.entries.remove(&oldest) {
+                state.memory_bytes -= entry.size_bytes;
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        state.next_tick += 1;
+        let tick = state.next_tick;
+        state.lru.insert(tick, key.clone());
+        state.memory_bytes += size_bytes;
+        state.entries.insert(key, CacheEntry {
+            version,
+            result: Arc::new(result.clone()),
+            size_bytes,
+            inserted_at: Instant::now(),
+            tick,
+        });
+    }
+
+    /// Drop every entry for a database, e.g. when it is dropped
+    pub fn invalidate_database(&self, database: &str) {
+        let mut state = self.state.lock().unwrap();
+        let keys: Vec<QueryCacheKey> = state
+            .entries
+            .keys()
+            .filter(|key| key.database == database)
+            .cloned()
+            .collect();
+        for key in &keys {
+            state.remove(key);
+        }
+        self.invalidations.fetch_add(keys.len() as u64, Ordering::Relaxed);
+    }
+
+    /// Drop every entry
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        *state = CacheState { next_tick: state.next_tick, ..CacheState::default() };
+    }
+
+    pub fn stats(&self) -> QueryCacheStats {
+        let (entries, memory_bytes) = {
+            let state = self.state.lock().unwrap();
+            (state.entries.len(), state.memory_bytes)
+        };
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        QueryCacheStats {
+            enabled: self.config.enabled,
+            entries,
+            memory_bytes,
+            hits,
+            misses,
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            expirations: self.expirations.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            hit_rate: if lookups > 0 { hits as f64 / lookups as f64 } else { 0.0 },
+        }
+    }
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        Self::new(QueryCacheConfig::default())
+    }
+}
+
+/// Write JSON with object keys in sorted order
+fn write_canonical(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&JsonValue::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Approximate memory held by a cached result
+fn estimate_size(key: &QueryCacheKey, result: &QueryResult) -> usize {
+    let documents = serde_json::to_vec(&result.documents).map(|bytes| bytes.len()).unwrap_or(usize::MAX);
+    documents.saturating_add(key.database.len() + key.collection.len() + key.query.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryBuilder;
+    use crate::Document;
+
+    fn result(count: usize) -> QueryResult {
+        let documents = (0..count)
+            .map(|i| {
+                let id = uuid::Uuid::from_u128(i as u128);
+                let doc = Document { id, fields: HashMap::new(), version: 1, created_at: 0, updated_at: 0 };
+                (id, doc)
+            })
+            .collect();
+        QueryResult { documents, total_count: count, has_more: false }
+    }
+
+    fn cache(max_entries: usize) -> QueryCache {
+        QueryCache::new(QueryCacheConfig { enabled: true, max_entries, ..QueryCacheConfig::default() })
+    }
+
+    #[test]
+    fn test_key_ignores_key_and_projection_order() {
+        let a = QueryBuilder::new()
+            .filter(serde_json::json!({"age": 30, "name": "ada"}))
+            .projection(vec!["name".to_string(), "age".to_string()])
+            .build();
+        let b = QueryBuilder::new()
+            .filter(serde_json::json!({"name": "ada", "age": 30}))
+            .projection(vec!["age".to_string(), "name".to_string()])
+            .build();
+        let c = QueryBuilder::new().filter(serde_json::json!({"name": "ada", "age": 31})).build();
+
+        assert_eq!(QueryCache::key("db", "users", &a), QueryCache::key("db", "users", &b));
+        assert_ne!(QueryCache::key("db", "users", &a), QueryCache::key("db", "users", &c));
+        assert_ne!(QueryCache::key("db", "users", &a), QueryCache::key("db", "posts", &a));
+    }
+
+    #[test]
+    fn test_version_change_invalidates() {
+        let cache = cache(10);
+        let key = QueryCache::key("db", "users", &Query::new());
+        cache.insert(key.clone(), 1, &result(2));
+
+        assert_eq!(cache.get(&key, 1).unwrap().documents.len(), 2);
+        assert!(cache.get(&key, 2).is_none());
+        assert!(cache.get(&key, 1).is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.invalidations, stats.entries), (1, 2, 1, 0));
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = cache(2);
+        let keys: Vec<QueryCacheKey> = (0..3)
+            .map(|i| QueryCache::key("db", "users", &QueryBuilder::new().limit(i).build()))
+            .collect();
+        cache.insert(keys[0].clone(), 1, &result(1));
+        cache.insert(keys[1].clone(), 1, &result(1));
+        assert!(cache.get(&keys[0], 1).is_some());
+        cache.insert(keys[2].clone(), 1, &result(1));
+
+        assert!(cache.get(&keys[0], 1).is_some());
+        assert!(cache.get(&keys[1], 1).is_none());
+        assert!(cache.get(&keys[2], 1).is_some());
+        assert_eq!(cache.stats().evictions, 1);
+    }
+}
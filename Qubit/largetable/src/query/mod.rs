@@ -6,6 +6,7 @@
 
 //! Advanced query engine with multiple query types
 
+pub mod cache;
 pub mod document;
 pub mod executor;
 pub mod graph;
@@ -17,9 +18,16 @@ pub mod timeseries;
 pub mod vector;
 pub mod aggregation;
 
+pub use aggregation::{DocumentStream, LookupSource};
+pub use cache::{QueryCache, QueryCacheConfig, QueryCacheStats};
+pub use optimizer::{AccessPath, IndexHint, QueryPlan, QueryPlanner};
+
 use crate::{Result, DocumentId, Document, LargetableError};
+use crate::engine::deadline::{checkpoint, CHECKPOINT_INTERVAL};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::{debug, error};
 
 /// Query builder for creating complex queries
@@ -29,6 +37,9 @@ pub struct QueryBuilder {
     limit: Option<usize>,
     skip: Option<usize>,
     projection: Option<Vec<String>>,
+    hint: Option<IndexHint>,
+    bypass_cache: bool,
+    read_at: Option<i64>,
 }
 
 /// Sort field specification
@@ -46,7 +57,7 @@ pub enum SortDirection {
 }
 
 /// Query result with metadata
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct QueryResult {
     pub documents: Vec<(DocumentId, Document)>,
     pub total_count: usize,
@@ -62,6 +73,9 @@ impl QueryBuilder {
             limit: None,
             skip: None,
             projection: None,
+            hint: None,
+            bypass_cache: false,
+            read_at: None,
         }
     }
 
@@ -95,6 +109,24 @@ impl QueryBuilder {
         self
     }
 
+    /// Force the planner to use an index, or to scan the collection
+    pub fn hint(mut self, hint: IndexHint) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    /// Always execute the query, neither reading nor filling the query cache
+    pub fn bypass_cache(mut self) -> Self {
+        self.bypass_cache = true;
+        self
+    }
+
+    /// Read the collection as it was at a cluster timestamp, in microseconds since the Unix epoch
+    pub fn read_at(mut self, timestamp: i64) -> Self {
+        self.read_at = Some(timestamp);
+        self
+    }
+
     /// Build the query
     pub fn build(self) -> Query {
         Query {
@@ -103,6 +135,9 @@ impl QueryBuilder {
             limit: self.limit,
             skip: self.skip,
             projection: self.projection,
+            hint: self.hint,
+            bypass_cache: self.bypass_cache,
+            read_at: self.read_at,
         }
     }
 }
@@ -121,6 +156,12 @@ pub struct Query {
     pub limit: Option<usize>,
     pub skip: Option<usize>,
     pub projection: Option<Vec<String>>,
+    /// Overrides automatic index selection
+    pub hint: Option<IndexHint>,
+    /// Skip the query cache for this query
+    pub bypass_cache: bool,
+    /// Cluster timestamp to read at, in microseconds since the Unix epoch; `None` reads the latest state
+    pub read_at: Option<i64>,
 }
 
 impl Query {
@@ -132,6 +173,9 @@ impl Query {
             limit: None,
             skip: None,
             projection: None,
+            hint: None,
+            bypass_cache: false,
+            read_at: None,
         }
     }
 
@@ -187,9 +231,18 @@ impl Query {
     async fn apply_filter(&self, mut documents: Vec<(DocumentId, Document)>, filter: &JsonValue) -> Result<Vec<(DocumentId, Document)>> {
         use crate::document::DocumentUtils;
         
+        // `$text` was answered by the full-text index these documents came from
+        let mut filter = filter.clone();
+        if let JsonValue::Object(conditions) = &mut filter {
+            conditions.remove("$text");
+        }
+        
         let mut filtered = Vec::new();
-        for (id, doc) in documents {
-            if DocumentUtils::matches_filter(&doc, filter)? {
+        for (scanned, (id, doc)) in documents.into_iter().enumerate() {
+            if scanned % CHECKPOINT_INTERVAL == 0 {
+                checkpoint()?;
+            }
+            if DocumentUtils::matches_filter(&doc, &filter)? {
                 filtered.push((id, doc));
             }
         }
@@ -199,65 +252,16 @@ impl Query {
 
     /// Apply sorting to documents
     async fn apply_sorting(&self, mut documents: Vec<(DocumentId, Document)>) -> Result<Vec<(DocumentId, Document)>> {
-        use crate::document::DocumentUtils;
-        
-        documents.sort_by(|a, b| {
-            for sort_field in &self.sort {
-                let a_value = DocumentUtils::get_field(&a.1, &sort_field.field);
-                let b_value = DocumentUtils::get_field(&b.1, &sort_field.field);
-                
-                let comparison = match (a_value, b_value) {
-                    (Some(a_val), Some(b_val)) => {
-                        match (a_val, b_val) {
-                            (crate::Value::String(a_str), crate::Value::String(b_str)) => a_str.cmp(b_str),
-                            (crate::Value::Int64(a_int), crate::Value::Int64(b_int)) => a_int.cmp(b_int),
-                            (crate::Value::Float64(a_float), crate::Value::Float64(b_float)) => a_float.partial_cmp(b_float).unwrap_or(std::cmp::Ordering::Equal),
-                            (crate::Value::Bool(a_bool), crate::Value::Bool(b_bool)) => a_bool.cmp(b_bool),
-                            (crate::Value::Timestamp(a_ts), crate::Value::Timestamp(b_ts)) => a_ts.cmp(b_ts),
-                            _ => std::cmp::Ordering::Equal,
-                        }
-                    }
-                    (Some(_), None) => std::cmp::Ordering::Greater,
-                    (None, Some(_)) => std::cmp::Ordering::Less,
-                    (None, None) => std::cmp::Ordering::Equal,
-                };
-                
-                match sort_field.direction {
-                    SortDirection::Ascending => comparison,
-                    SortDirection::Descending => comparison.reverse(),
-                }
-            }
-            std::cmp::Ordering::Equal
-        });
-        
+        documents.sort_by(|a, b| aggregation::compare_documents(&a.1, &b.1, &self.sort));
         Ok(documents)
     }
 
     /// Apply projection to documents
     async fn apply_projection(&self, documents: Vec<(DocumentId, Document)>, projection: &[String]) -> Result<Vec<(DocumentId, Document)>> {
-        let mut projected = Vec::new();
-        
-        for (id, mut doc) in documents {
-            let mut new_fields = HashMap::new();
-            
-            // Always include _id, _version, _created_at, _updated_at
-            new_fields.insert("_id".to_string(), crate::Value::String(id.to_string()));
-            new_fields.insert("_version".to_string(), crate::Value::UInt64(doc.version));
-            new_fields.insert("_created_at".to_string(), crate::Value::Timestamp(doc.created_at));
-            new_fields.insert("_updated_at".to_string(), crate::Value::Timestamp(doc.updated_at));
-            
-            // Add projected fields
-            for field in projection {
-                if let Some(value) = doc.fields.get(field) {
-                    new_fields.insert(field.clone(), value.clone());
-                }
-            }
-            
-            doc.fields = new_fields;
-            projected.push((id, doc));
-        }
-        
-        Ok(projected)
+        Ok(documents
+            .into_iter()
+            .map(|(id, doc)| (id, aggregation::project_document(id, doc, projection)))
+            .collect())
     }
 }
 
@@ -270,6 +274,7 @@ impl Default for Query {
 /// Aggregation pipeline for complex data processing
 pub struct AggregationPipeline {
     stages: Vec<AggregationStage>,
+    lookup_source: Option<Arc<dyn LookupSource>>,
 }
 
 /// Aggregation stage types
@@ -291,6 +296,12 @@ pub enum AggregationStage {
         foreign_field: String,
         as_field: String,
     },
+    /// Compute fields from neighbouring documents, like `$setWindowFields`
+    SetWindowFields {
+        partition_by: Option<String>,
+        sort_by: SortField,
+        output: HashMap<String, WindowField>,
+    },
 }
 
 /// Aggregation accumulator functions
@@ -305,14 +316,61 @@ pub enum Accumulator {
     Last(String),
 }
 
+/// Output field of a `$setWindowFields` stage
+#[derive(Debug, Clone)]
+pub struct WindowField {
+    pub function: WindowFunction,
+    pub window: WindowBounds,
+}
+
+/// Functions evaluated for each document of a partition
+#[derive(Debug, Clone)]
+pub enum WindowFunction {
+    /// A group accumulator applied to the documents in the window
+    Accumulate(Accumulator),
+    /// 1-based position in the partition
+    RowNumber,
+    /// Position of the first document with the same sort value, so ties share a rank
+    Rank,
+    /// `field` of the document `by` positions away; ignores the window
+    Shift { field: String, by: i64 },
+    /// Change of `field` per `unit` of the sort field between the window's
+    /// first and last documents
+    Derivative { field: String, unit: f64 },
+}
+
+/// Documents a window covers, relative to the current one
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowBounds {
+    /// The whole partition
+    Partition,
+    /// Positions relative to the current document; `None` is unbounded
+    Documents { lower: Option<i64>, upper: Option<i64> },
+    /// Sort field values relative to the current document's; `None` is unbounded.
+    /// Timestamps are in microseconds.
+    Range { lower: Option<f64>, upper: Option<f64> },
+}
+
 impl AggregationPipeline {
     /// Create a new aggregation pipeline
     pub fn new() -> Self {
         Self {
             stages: Vec::new(),
+            lookup_source: None,
         }
     }
 
+    /// Set where `$lookup` stages find foreign documents
+    pub fn with_lookup_source(mut self, source: Arc<dyn LookupSource>) -> Self {
+        self.lookup_source = Some(source);
+        self
+    }
+
+    /// Stages in execution order
+    pub fn stages(&self) -> &[AggregationStage] {
+        &self.stages
+    }
+
     /// Add a match stage
     pub fn match_stage(mut self, filter: JsonValue) -> Self {
         self.stages.push(AggregationStage::Match(filter));
@@ -366,222 +424,39 @@ impl AggregationPipeline {
         self
     }
 
-    /// Execute the aggregation pipeline
-    pub async fn execute(&self, documents: Vec<(DocumentId, Document)>) -> Result<Vec<JsonValue>> {
-        let mut current_docs = documents;
-        
-        for stage in &self.stages {
-            current_docs = self.execute_stage(stage, current_docs).await?;
-        }
-        
-        // Convert documents to JSON
-        let mut results = Vec::new();
-        for (_, doc) in current_docs {
-            results.push(crate::document::DocumentUtils::to_json(&doc)?);
-        }
-        
-        Ok(results)
+    /// Add a window stage
+    pub fn set_window_fields(
+        mut self,
+        partition_by: Option<String>,
+        sort_by: SortField,
+        output: HashMap<String, WindowField>,
+    ) -> Self {
+        self.stages.push(AggregationStage::SetWindowFields {
+            partition_by,
+            sort_by,
+            output,
+        });
+        self
     }
 
-    /// Execute a single aggregation stage
-    async fn execute_stage(&self, stage: &AggregationStage, documents: Vec<(DocumentId, Document)>) -> Result<Vec<(DocumentId, Document)>> {
-        match stage {
-            AggregationStage::Match(filter) => {
-                let query = Query::new().filter(filter.clone());
-                let result = query.execute(documents).await?;
-                Ok(result.documents)
-            }
-            AggregationStage::Group { by, accumulators } => {
-                self.execute_group_stage(by, accumulators, documents).await
-            }
-            AggregationStage::Sort(sort_fields) => {
-                let query = Query::new().sort(sort_fields.clone());
-                let result = query.execute(documents).await?;
-                Ok(result.documents)
-            }
-            AggregationStage::Limit(limit) => {
-                let query = Query::new().limit(*limit);
-                let result = query.execute(documents).await?;
-                Ok(result.documents)
-            }
-            AggregationStage::Skip(skip) => {
-                let query = Query::new().skip(*skip);
-                let result = query.execute(documents).await?;
-                Ok(result.documents)
-            }
-            AggregationStage::Project(fields) => {
-                let query = Query::new().projection(fields.clone());
-                let result = query.execute(documents).await?;
-                Ok(result.documents)
-            }
-            AggregationStage::Unwind(field) => {
-                self.execute_unwind_stage(field, documents).await
-            }
-            AggregationStage::Lookup { .. } => {
-                // Lookup stage would require access to other collections
-                // For now, just return the documents as-is
-                Ok(documents)
-            }
-        }
+    /// Run the pipeline over a document stream, yielding results as they are produced
+    pub fn stream<'a>(&self, source: DocumentStream<'a>) -> DocumentStream<'a> {
+        aggregation::execute_stream(&self.stages, self.lookup_source.clone(), source)
     }
 
-    /// Execute group stage
-    async fn execute_group_stage(
-        &self,
-        by: &str,
-        accumulators: &HashMap<String, Accumulator>,
-        documents: Vec<(DocumentId, Document)>,
-    ) -> Result<Vec<(DocumentId, Document)>> {
-        use crate::document::DocumentUtils;
-        
-        let mut groups: HashMap<String, Vec<(DocumentId, Document)>> = HashMap::new();
-        
-        // Group documents by the specified field
-        for (id, doc) in documents {
-            let group_key = if let Some(value) = DocumentUtils::get_field(&doc, by) {
-                match value {
-                    crate::Value::String(s) => s.clone(),
-                    crate::Value::Int64(i) => i.to_string(),
-                    crate::Value::Float64(f) => f.to_string(),
-                    crate::Value::Bool(b) => b.to_string(),
-                    _ => "null".to_string(),
-                }
-            } else {
-                "null".to_string()
-            };
-            
-            groups.entry(group_key).or_default().push((id, doc));
-        }
-        
-        // Apply accumulators to each group
-        let mut result = Vec::new();
-        for (group_key, group_docs) in groups {
-            let mut aggregated_doc = DocumentBuilder::new()
-                .string("_id", group_key.clone())
-                .build();
-            
-            for (field, accumulator) in accumulators {
-                let value = match accumulator {
-                    Accumulator::Sum(field_name) => {
-                        let sum: f64 = group_docs.iter()
-                            .filter_map(|(_, doc)| {
-                                DocumentUtils::get_field(doc, field_name)
-                                    .and_then(|v| match v {
-                                        crate::Value::Int64(i) => Some(*i as f64),
-                                        crate::Value::Float64(f) => Some(*f),
-                                        _ => None,
-                                    })
-                            })
-                            .sum();
-                        crate::Value::Float64(sum)
-                    }
-                    Accumulator::Count => {
-                        crate::Value::Int64(group_docs.len() as i64)
-                    }
-                    Accumulator::Avg(field_name) => {
-                        let values: Vec<f64> = group_docs.iter()
-                            .filter_map(|(_, doc)| {
-                                DocumentUtils::get_field(doc, field_name)
-                                    .and_then(|v| match v {
-                                        crate::Value::Int64(i) => Some(*i as f64),
-                                        crate::Value::Float64(f) => Some(*f),
-                                        _ => None,
-                                    })
-                            })
-                            .collect();
-                        
-                        if values.is_empty() {
-                            crate::Value::Null
-                        } else {
-                            let avg = values.iter().sum::<f64>() / values.len() as f64;
-                            crate::Value::Float64(avg)
-                        }
-                    }
-                    Accumulator::Min(field_name) => {
-                        let min = group_docs.iter()
-                            .filter_map(|(_, doc)| {
-                                DocumentUtils::get_field(doc, field_name)
-                                    .and_then(|v| match v {
-                                        crate::Value::Int64(i) => Some(*i as f64),
-                                        crate::Value::Float64(f) => Some(*f),
-                                        _ => None,
-                                    })
-                            })
-                            .fold(f64::INFINITY, f64::min);
-                        
-                        if min == f64::INFINITY {
-                            crate::Value::Null
-                        } else {
-                            crate::Value::Float64(min)
-                        }
-                    }
-                    Accumulator::Max(field_name) => {
-                        let max = group_docs.iter()
-                            .filter_map(|(_, doc)| {
-                                DocumentUtils::get_field(doc, field_name)
-                                    .and_then(|v| match v {
-                                        crate::Value::Int64(i) => Some(*i as f64),
-                                        crate::Value::Float64(f) => Some(*f),
-                                        _ => None,
-                                    })
-                            })
-                            .fold(f64::NEG_INFINITY, f64::max);
-                        
-                        if max == f64::NEG_INFINITY {
-                            crate::Value::Null
-                        } else {
-                            crate::Value::Float64(max)
-                        }
-                    }
-                    Accumulator::First(field_name) => {
-                        group_docs.first()
-                            .and_then(|(_, doc)| DocumentUtils::get_field(doc, field_name))
-                            .cloned()
-                            .unwrap_or(crate::Value::Null)
-                    }
-                    Accumulator::Last(field_name) => {
-                        group_docs.last()
-                            .and_then(|(_, doc)| DocumentUtils::get_field(doc, field_name))
-                            .cloned()
-                            .unwrap_or(crate::Value::Null)
-                    }
-                };
-                
-                aggregated_doc.fields.insert(field.clone(), value);
-            }
-            
-            result.push((uuid::Uuid::now_v7(), aggregated_doc));
+    /// Run the pipeline over a document stream and collect the results as JSON
+    pub async fn execute_stream(&self, source: DocumentStream<'_>) -> Result<Vec<JsonValue>> {
+        let mut results = Vec::new();
+        let mut output = self.stream(source);
+        while let Some((_, doc)) = output.try_next().await? {
+            results.push(crate::document::DocumentUtils::to_json(&doc)?);
         }
-        
-        Ok(result)
+        Ok(results)
     }
 
-    /// Execute unwind stage
-    async fn execute_unwind_stage(&self, field: &str, documents: Vec<(DocumentId, Document)>) -> Result<Vec<(DocumentId, Document)>> {
-        use crate::document::DocumentUtils;
-        
-        let mut result = Vec::new();
-        
-        for (id, doc) in documents {
-            if let Some(value) = DocumentUtils::get_field(&doc, field) {
-                match value {
-                    crate::Value::Array(arr) => {
-                        for item in arr {
-                            let mut new_doc = doc.clone();
-                            DocumentUtils::set_field(&mut new_doc, field, item.clone())?;
-                            result.push((uuid::Uuid::now_v7(), new_doc));
-                        }
-                    }
-                    _ => {
-                        result.push((id, doc));
-                    }
-                }
-            } else {
-                result.push((id, doc));
-            }
-        }
-        
-        Ok(result)
+    /// Execute the aggregation pipeline over documents already in memory
+    pub async fn execute(&self, documents: Vec<(DocumentId, Document)>) -> Result<Vec<JsonValue>> {
+        self.execute_stream(stream::iter(documents.into_iter().map(Ok)).boxed()).await
     }
 }
 
@@ -5,3 +5,458 @@
 // ===========================================
 
 //! Query optimizer
+//!
+//! Picks the access path for a query: a scan of one secondary index or a
+//! full collection scan. Index scans only narrow the candidate set; the
+//! query's filter is always re-applied to the fetched documents. The
+//! exceptions are `$text`, which only a full-text index can answer, and
+//! `$near`, whose nearest-first order only a 2dsphere index provides.
+
+use crate::document::DocumentUtils;
+use crate::index::geospatial::GeoQuery;
+use crate::index::IndexQuery;
+use crate::query::Query;
+use crate::{IndexType, LargetableError, Result, Value};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Steers index selection for a single query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexHint {
+    /// Use the named index; the query fails if it is missing or unusable
+    Index(String),
+    /// Skip indexes and scan the collection
+    Natural,
+}
+
+/// How a query reads its candidate documents
+#[derive(Debug, Clone)]
+pub enum AccessPath {
+    CollectionScan,
+    IndexScan {
+        index: String,
+        query: Box<IndexQuery>,
+    },
+}
+
+/// Chosen access path and the indexes that were considered
+#[derive(Debug, Clone)]
+pub struct QueryPlan {
+    pub access: AccessPath,
+    /// Usable indexes, best first
+    pub candidates: Vec<String>,
+    pub hinted: bool,
+}
+
+impl QueryPlan {
+    fn collection_scan(candidates: Vec<String>, hinted: bool) -> Self {
+        Self {
+            access: AccessPath::CollectionScan,
+            candidates,
+            hinted,
+        }
+    }
+
+    /// Name of the index the plan reads, if any
+    pub fn index(&self) -> Option<&str> {
+        match &self.access {
+            AccessPath::IndexScan { index, .. } => Some(index),
+            AccessPath::CollectionScan => None,
+        }
+    }
+}
+
+impl fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.access {
+            AccessPath::CollectionScan => write!(f, "COLLSCAN")?,
+            AccessPath::IndexScan { index, .. } => write!(f, "IXSCAN {}", index)?,
+        }
+        if self.hinted {
+            write!(f, " (hinted)")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a filter asks of one field, as far as an index can help
+#[derive(Debug, Clone, Default)]
+struct FieldPredicate {
+    eq: Option<Value>,
+    min: Option<Value>,
+    max: Option<Value>,
+    geo: Option<GeoQuery>,
+}
+
+impl FieldPredicate {
+    fn is_range(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+}
+
+/// How well an index serves a filter; compared field by field, higher is better
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct IndexScore {
+    equality_fields: usize,
+    ranged: bool,
+    /// Hash beats B-Tree beats compound for the same coverage
+    kind: u8,
+}
+
+/// Selects indexes for queries
+pub struct QueryPlanner;
+
+impl QueryPlanner {
+    /// Plan `query` against the ready indexes of a collection
+    pub fn plan(query: &Query, indexes: &[(String, IndexType)]) -> Result<QueryPlan> {
+        if let Some(text) = query.filter.as_ref().and_then(|filter| filter.get("$text")) {
+            return Self::plan_text(query, text, indexes);
+        }
+        if let Some((field, near)) = query.filter.as_ref().map(Self::near).transpose()?.flatten() {
+            return Self::plan_near(query, field, near, indexes);
+        }
+
+        let predicates = query.filter.as_ref().map(Self::predicates).unwrap_or_default();
+
+        let mut usable: Vec<(IndexScore, &String, IndexQuery)> = indexes
+            .iter()
+            .filter_map(|(name, index_type)| {
+                Self::score(name, index_type, &predicates).map(|(score, index_query)| (score, name, index_query))
+            })
+            .collect();
+        // Best score first, ties broken by name so plans are stable
+        usable.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        let candidates: Vec<String> = usable.iter().map(|(_, name, _)| (*name).clone()).collect();
+
+        match &query.hint {
+            Some(IndexHint::Natural) => Ok(QueryPlan::collection_scan(candidates, true)),
+            Some(IndexHint::Index(hinted)) => {
+                if !indexes.iter().any(|(name, _)| name == hinted) {
+                    return Err(LargetableError::Query(format!("Hinted index '{}' does not exist", hinted)));
+                }
+                let Some((_, _, index_query)) = usable.into_iter().find(|(_, name, _)| *name == hinted) else {
+                    return Err(LargetableError::Query(format!(
+                        "Hinted index '{}' cannot be used for this filter",
+                        hinted
+                    )));
+                };
+                Ok(QueryPlan {
+                    access: AccessPath::IndexScan { index: hinted.clone(), query: Box::new(index_query) },
+                    candidates,
+                    hinted: true,
+                })
+            }
+            None => Ok(match usable.into_iter().next() {
+                Some((_, name, index_query)) => QueryPlan {
+                    access: AccessPath::IndexScan { index: name.clone(), query: Box::new(index_query) },
+                    candidates,
+                    hinted: false,
+                },
+                None => QueryPlan::collection_scan(candidates, false),
+            }),
+        }
+    }
+
+    /// `$text` queries must read the collection's full-text index
+    ///
+    /// A collection with several text indexes needs a hint naming the one to search.
+    fn plan_text(query: &Query, text: &JsonValue, indexes: &[(String, IndexType)]) -> Result<QueryPlan> {
+        let search = text
+            .get("$search")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| LargetableError::Query("$text requires a $search string".to_string()))?;
+
+        let text_indexes: Vec<&String> = indexes
+            .iter()
+            .filter(|(_, index_type)| matches!(index_type, IndexType::FullText { .. }))
+            .map(|(name, _)| name)
+            .collect();
+
+        let index = match &query.hint {
+            Some(IndexHint::Natural) => {
+                return Err(LargetableError::Query("$text queries cannot scan the collection".to_string()));
+            }
+            Some(IndexHint::Index(hinted)) => text_indexes
+                .iter()
+                .find(|name| *name == &hinted)
+                .ok_or_else(|| LargetableError::Query(format!("Hinted index '{}' is not a full-text index", hinted)))?,
+            None => match text_indexes.as_slice() {
+                [index] => index,
+                [] => return Err(LargetableError::Query("$text requires a full-text index".to_string())),
+                _ => {
+                    return Err(LargetableError::Query(
+                        "Collection has several full-text indexes; hint the one $text should use".to_string(),
+                    ))
+                }
+            },
+        };
+
+        Ok(QueryPlan {
+            access: AccessPath::IndexScan {
+                index: (*index).clone(),
+                query: Box::new(IndexQuery::FullText { field: (*index).clone(), query: search.to_string() }),
+            },
+            candidates: text_indexes.into_iter().cloned().collect(),
+            hinted: query.hint.is_some(),
+        })
+    }
+
+    /// The field and predicate of a filter's `$near` condition, if it has one
+    fn near(filter: &JsonValue) -> Result<Option<(&String, GeoQuery)>> {
+        let JsonValue::Object(conditions) = filter else {
+            return Ok(None);
+        };
+        let mut near = None;
+        for (field, condition) in conditions {
+            for (operator, operand) in DocumentUtils::filter_operators(condition).into_iter().flatten() {
+                if operator != "$near" && operator != "$nearSphere" {
+                    continue;
+                }
+                if near.is_some() {
+                    return Err(LargetableError::Query("A query can have only one $near condition".to_string()));
+                }
+                near = Some((field, GeoQuery::from_operator(operator, operand)?));
+            }
+        }
+        Ok(near)
+    }
+
+    /// `$near` queries must read a geospatial index on their field
+    fn plan_near(query: &Query, field: &String, near: GeoQuery, indexes: &[(String, IndexType)]) -> Result<QueryPlan> {
+        if !indexes.iter().any(|(name, index_type)| name == field && matches!(index_type, IndexType::Geospatial { .. })) {
+            return Err(LargetableError::Query(format!("$near requires a geospatial index on '{}'", field)));
+        }
+        match &query.hint {
+            Some(IndexHint::Natural) => {
+                return Err(LargetableError::Query("$near queries cannot scan the collection".to_string()));
+            }
+            Some(IndexHint::Index(hinted)) if hinted != field => {
+                return Err(LargetableError::Query(format!("Hinted index '{}' cannot answer $near on '{}'", hinted, field)));
+            }
+            _ => {}
+        }
+
+        Ok(QueryPlan {
+            access: AccessPath::IndexScan {
+                index: field.clone(),
+                query: Box::new(IndexQuery::Geospatial { field: field.clone(), query: near }),
+            },
+            candidates: vec![field.clone()],
+            hinted: query.hint.is_some(),
+        })
+    }
+
+    /// Per-field equality, range and geospatial bounds an index can serve
+    fn predicates(filter: &JsonValue) -> BTreeMap<String, FieldPredicate> {
+        let mut predicates = BTreeMap::new();
+        let JsonValue::Object(conditions) = filter else {
+            return predicates;
+        };
+
+        for (field, condition) in conditions {
+            if field.starts_with('$') {
+                continue;
+            }
+            let mut predicate = FieldPredicate::default();
+            match DocumentUtils::filter_operators(condition) {
+                Some(operators) => {
+                    for (operator, operand) in operators {
+                        if GeoQuery::is_operator(operator) {
+                            predicate.geo = GeoQuery::from_operator(operator, operand).ok();
+                            continue;
+                        }
+                        let Some(value) = Self::scalar(operand) else {
+                            continue;
+                        };
+                        match operator.as_str() {
+                            "$eq" => predicate.eq = Some(value),
+                            "$gt" | "$gte" => predicate.min = Some(value),
+                            "$lt" | "$lte" => predicate.max = Some(value),
+                            _ => {}
+                        }
+                    }
+                }
+                None => predicate.eq = Self::scalar(condition),
+            }
+            if predicate.eq.is_some() || predicate.is_range() || predicate.geo.is_some() {
+                predicates.insert(field.clone(), predicate);
+            }
+        }
+        predicates
+    }
+
+    /// Scalars are the only operands index keys compare like the filter does
+    fn scalar(json: &JsonValue) -> Option<Value> {
+        match json {
+            JsonValue::Null | JsonValue::Bool(_) | JsonValue::Number(_) | JsonValue::String(_) => {
+                DocumentUtils::json_to_value(json.clone()).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn score(
+        name: &str,
+        index_type: &IndexType,
+        predicates: &BTreeMap<String, FieldPredicate>,
+    ) -> Option<(IndexScore, IndexQuery)> {
+        match index_type {
+            IndexType::Hash => {
+                let eq = predicates.get(name)?.eq.clone()?;
+                Some((
+                    IndexScore { equality_fields: 1, ranged: false, kind: 2 },
+                    IndexQuery::Exact { field: name.to_string(), value: eq },
+                ))
+            }
+            IndexType::BTree => {
+                let predicate = predicates.get(name)?;
+                match &predicate.eq {
+                    Some(eq) => Some((
+                        IndexScore { equality_fields: 1, ranged: false, kind: 1 },
+                        IndexQuery::Exact { field: name.to_string(), value: eq.clone() },
+                    )),
+                    None if predicate.is_range() => Some((
+                        IndexScore { equality_fields: 0, ranged: true, kind: 1 },
+                        IndexQuery::Range {
+                            field: name.to_string(),
+                            min: predicate.min.clone(),
+                            max: predicate.max.clone(),
+                        },
+                    )),
+                    None => None,
+                }
+            }
+            IndexType::Compound { fields } => {
+                let equals: Vec<Value> = fields
+                    .iter()
+                    .map_while(|field| predicates.get(field).and_then(|predicate| predicate.eq.clone()))
+                    .collect();
+                let range = fields
+                    .get(equals.len())
+                    .and_then(|field| predicates.get(field))
+                    .filter(|predicate| predicate.is_range());
+                if equals.is_empty() && range.is_none() {
+                    return None;
+                }
+                Some((
+                    IndexScore { equality_fields: equals.len(), ranged: range.is_some(), kind: 0 },
+                    IndexQuery::Prefix {
+                        index: name.to_string(),
+                        equals,
+                        min: range.and_then(|predicate| predicate.min.clone()),
+                        max: range.and_then(|predicate| predicate.max.clone()),
+                    },
+                ))
+            }
+            // Ranks like a range scan: the region's selectivity is unknown
+            IndexType::Geospatial { .. } => {
+                let geo = predicates.get(name)?.geo.clone()?;
+                Some((
+                    IndexScore { equality_fields: 0, ranged: true, kind: 0 },
+                    IndexQuery::Geospatial { field: name.to_string(), query: geo },
+                ))
+            }
+            // Text, vector and time-series indexes answer their own query types
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryBuilder;
+    use serde_json::json;
+
+    fn indexes() -> Vec<(String, IndexType)> {
+        vec![
+            ("email".to_string(), IndexType::Hash),
+            ("age".to_string(), IndexType::BTree),
+            (
+                "country,age".to_string(),
+                IndexType::Compound { fields: vec!["country".to_string(), "age".to_string()] },
+            ),
+        ]
+    }
+
+    fn plan(filter: JsonValue) -> QueryPlan {
+        QueryPlanner::plan(&QueryBuilder::new().filter(filter).build(), &indexes()).unwrap()
+    }
+
+    #[test]
+    fn test_selects_most_selective_index() {
+        assert_eq!(plan(json!({"email": "a@b.c", "age": 30})).index(), Some("email"));
+        assert_eq!(plan(json!({"age": {"$gte": 18, "$lt": 65}})).index(), Some("age"));
+        assert_eq!(plan(json!({"country": "NG", "age": {"$gt": 18}})).index(), Some("country,age"));
+        assert_eq!(plan(json!({"name": "Ada"})).index(), None);
+
+        // Hash indexes cannot serve ranges
+        let ranged = plan(json!({"email": {"$gt": "a"}, "country": "NG"}));
+        assert_eq!(ranged.index(), Some("country,age"));
+        let AccessPath::IndexScan { query, .. } = ranged.access else {
+            panic!("expected an index scan");
+        };
+        assert!(matches!(*query, IndexQuery::Prefix { ref equals, min: None, max: None, .. } if equals.len() == 1));
+    }
+
+    #[test]
+    fn test_hints() {
+        let filter = json!({"email": "a@b.c", "age": 30});
+
+        let hinted = QueryBuilder::new().filter(filter.clone()).hint(IndexHint::Index("age".to_string())).build();
+        let plan = QueryPlanner::plan(&hinted, &indexes()).unwrap();
+        assert_eq!(plan.index(), Some("age"));
+        assert!(plan.hinted);
+
+        let natural = QueryBuilder::new().filter(filter.clone()).hint(IndexHint::Natural).build();
+        assert_eq!(QueryPlanner::plan(&natural, &indexes()).unwrap().index(), None);
+
+        let missing = QueryBuilder::new().filter(filter.clone()).hint(IndexHint::Index("name".to_string())).build();
+        assert!(QueryPlanner::plan(&missing, &indexes()).is_err());
+
+        let unusable = QueryBuilder::new().filter(json!({"age": 30})).hint(IndexHint::Index("email".to_string())).build();
+        assert!(QueryPlanner::plan(&unusable, &indexes()).is_err());
+    }
+
+    #[test]
+    fn test_text_queries_need_a_text_index() {
+        let filter = json!({"$text": {"$search": "sunset"}, "age": 30});
+        assert!(QueryPlanner::plan(&QueryBuilder::new().filter(filter.clone()).build(), &indexes()).is_err());
+
+        let mut with_text = indexes();
+        with_text.push((
+            "caption".to_string(),
+            IndexType::FullText { language: "english".to_string(), stop_words: Vec::new() },
+        ));
+        let plan = QueryPlanner::plan(&QueryBuilder::new().filter(filter.clone()).build(), &with_text).unwrap();
+        assert_eq!(plan.index(), Some("caption"));
+        let AccessPath::IndexScan { query, .. } = plan.access else {
+            panic!("expected an index scan");
+        };
+        assert!(matches!(*query, IndexQuery::FullText { ref query, .. } if query == "sunset"));
+
+        let natural = QueryBuilder::new().filter(filter).hint(IndexHint::Natural).build();
+        assert!(QueryPlanner::plan(&natural, &with_text).is_err());
+    }
+
+    #[test]
+    fn test_geo_queries() {
+        let mut with_geo = indexes();
+        with_geo.push(("location".to_string(), IndexType::Geospatial { coordinate_system: "2dsphere".to_string() }));
+        let point = json!({"type": "Point", "coordinates": [3.38, 6.52]});
+
+        let near = json!({"location": {"$near": {"$geometry": point, "$maxDistance": 5000}}, "age": 30});
+        assert!(QueryPlanner::plan(&QueryBuilder::new().filter(near.clone()).build(), &indexes()).is_err());
+        let plan = QueryPlanner::plan(&QueryBuilder::new().filter(near.clone()).build(), &with_geo).unwrap();
+        assert_eq!(plan.index(), Some("location"));
+        let natural = QueryBuilder::new().filter(near).hint(IndexHint::Natural).build();
+        assert!(QueryPlanner::plan(&natural, &with_geo).is_err());
+
+        // $geoWithin can use the index but does not need it
+        let within = json!({"location": {"$geoWithin": {"$centerSphere": [[3.38, 6.52], 0.001]}}});
+        let plan = QueryPlanner::plan(&QueryBuilder::new().filter(within.clone()).build(), &with_geo).unwrap();
+        assert!(matches!(plan.access, AccessPath::IndexScan { ref query, .. } if matches!(**query, IndexQuery::Geospatial { .. })));
+        assert_eq!(QueryPlanner::plan(&QueryBuilder::new().filter(within).build(), &indexes()).unwrap().index(), None);
+    }
+}
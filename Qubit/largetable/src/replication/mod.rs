@@ -0,0 +1,16 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Replication: operation log, consensus and replica sets
+
+pub mod conflict_resolution;
+pub mod consensus;
+pub mod heartbeat;
+pub mod oplog;
+pub mod raft;
+pub mod replica_set;
+
+pub use oplog::{HistoryConfig, Oplog, OplogEntry, OplogOperation};
@@ -5,3 +5,371 @@
 // ===========================================
 
 //! Operation log
+//!
+//! Every committed write is appended with a monotonically increasing
+//! position. Updates and deletes carry the document as it was before the
+//! write, which lets readers rebuild any collection as of an earlier
+//! position for as long as the entries after it are retained. Entry
+//! timestamps double as the cluster time that snapshot reads ask for.
+
+use crate::{CollectionName, DatabaseName, Document, DocumentId, LargetableError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Entries kept when no snapshot needs older ones
+pub const DEFAULT_OPLOG_CAPACITY: usize = 100_000;
+
+/// How much history the oplog keeps for snapshot reads and incremental backups
+#[derive(Debug, Clone)]
+pub struct HistoryConfig {
+    /// Entries kept when no snapshot needs older ones
+    pub max_entries: usize,
+    /// Trim entries older than this even below `max_entries`; `None` keeps them until the cap
+    pub retention: Option<Duration>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { max_entries: DEFAULT_OPLOG_CAPACITY, retention: None }
+    }
+}
+
+/// A write recorded in the oplog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum OplogOperation {
+    Insert { document: Document },
+    Update { before: Document, after: Document },
+    Delete { before: Document },
+}
+
+impl OplogOperation {
+    pub fn document_id(&self) -> DocumentId {
+        match self {
+            OplogOperation::Insert { document } => document.id,
+            OplogOperation::Update { after, .. } => after.id,
+            OplogOperation::Delete { before } => before.id,
+        }
+    }
+
+    /// The document as it was before this write, `None` for inserts
+    pub fn before(&self) -> Option<&Document> {
+        match self {
+            OplogOperation::Insert { .. } => None,
+            OplogOperation::Update { before, .. } | OplogOperation::Delete { before } => Some(before),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OplogEntry {
+    pub position: u64,
+    /// Microseconds since the Unix epoch
+    pub timestamp: i64,
+    pub database: DatabaseName,
+    pub collection: CollectionName,
+    pub operation: OplogOperation,
+}
+
+struct OplogState {
+    entries: VecDeque<OplogEntry>,
+    /// Position of the last appended entry; 0 before the first write
+    latest: u64,
+    /// Least timestamp the next entry may take; past any timestamp already read at
+    next_timestamp: i64,
+    /// Earliest timestamp a snapshot can still be read at
+    history_start: i64,
+}
+
+/// In-memory operation log shared by every database of an engine
+pub struct Oplog {
+    state: Mutex<OplogState>,
+    capacity: usize,
+    retention: Option<Duration>,
+    /// Pinned positions and how many holders each has
+    pins: Mutex<BTreeMap<u64, usize>>,
+    /// Held shared by writers and exclusively by readers that need no write in flight
+    commit: RwLock<()>,
+}
+
+impl Oplog {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_OPLOG_CAPACITY)
+    }
+
+    /// Keep at most `capacity` entries, plus any a pinned position still needs
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_history(HistoryConfig { max_entries: capacity, retention: None })
+    }
+
+    /// Keep the entries `config` asks for, plus any a pinned position still needs
+    pub fn with_history(config: HistoryConfig) -> Self {
+        // Writes made before the oplog existed are unknown, so history starts now
+        let now = chrono::Utc::now().timestamp_micros();
+        Self {
+            state: Mutex::new(OplogState { entries: VecDeque::new(), latest: 0, next_timestamp: now, history_start: now }),
+            capacity: config.max_entries,
+            retention: config.retention,
+            pins: Mutex::new(BTreeMap::new()),
+            commit: RwLock::new(()),
+        }
+    }
+
+    /// Held by a writer from its storage write until its entry is appended
+    pub async fn write_permit(&self) -> RwLockReadGuard<'_, ()> {
+        self.commit.read().await
+    }
+
+    /// Wait for in-flight writes and hold off new ones until the guard drops
+    pub async fn pause_writes(&self) -> RwLockWriteGuard<'_, ()> {
+        self.commit.write().await
+    }
+
+    /// Record a write and return its position
+    pub fn append(&self, database: &str, collection: &str, operation: OplogOperation) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.latest += 1;
+        let position = state.latest;
+        // Snapshot reads look entries up by timestamp, so a clock step back must not reorder them
+        let timestamp = chrono::Utc::now().timestamp_micros().max(state.next_timestamp);
+        state.next_timestamp = timestamp;
+        state.entries.push_back(OplogEntry {
+            position,
+            timestamp,
+            database: database.to_string(),
+            collection: collection.to_string(),
+            operation,
+        });
+
+        // Entries after the oldest pinned position are needed to rebuild its snapshot
+        let oldest_pin = self.pins.lock().unwrap().keys().next().copied();
+        let expired_before = self
+            .retention
+            .map_or(i64::MIN, |retention| timestamp.saturating_sub(retention.as_micros() as i64));
+        while let Some(front) = state.entries.front() {
+            let over = state.entries.len() > self.capacity || front.timestamp < expired_before;
+            if !over || oldest_pin.is_some_and(|pin| front.position > pin) {
+                break;
+            }
+            // Snapshots at or after a trimmed entry need none of the entries before it
+            let trimmed = front.timestamp;
+            state.entries.pop_front();
+            state.history_start = trimmed;
+        }
+        position
+    }
+
+    /// Current cluster time in microseconds since the Unix epoch.
+    ///
+    /// Every write logged after the call gets a later timestamp, so reading at
+    /// the returned time sees exactly the writes logged before it.
+    pub fn cluster_time(&self) -> i64 {
+        let mut state = self.state.lock().unwrap();
+        let now = chrono::Utc::now().timestamp_micros().max(state.next_timestamp);
+        state.next_timestamp = now + 1;
+        now
+    }
+
+    /// Earliest timestamp a snapshot can still be read at
+    pub fn history_start(&self) -> i64 {
+        self.state.lock().unwrap().history_start
+    }
+
+    /// Pin the position of the last write at or before `timestamp` and return it.
+    ///
+    /// Fails when `timestamp` is older than the retained history or ahead of
+    /// the cluster time, since later writes could still land before it.
+    pub fn pin_at(&self, timestamp: i64) -> Result<u64> {
+        let mut state = self.state.lock().unwrap();
+        let now = chrono::Utc::now().timestamp_micros().max(state.next_timestamp);
+        if timestamp > now {
+            return Err(LargetableError::Query(format!(
+                "Read timestamp {} is ahead of the cluster time {}",
+                timestamp, now
+            )));
+        }
+        if timestamp < state.history_start {
+            return Err(LargetableError::SnapshotUnavailable(format!(
+                "Read timestamp {} is older than the retained history, which starts at {}",
+                timestamp, state.history_start
+            )));
+        }
+
+        let newer = state.entries.partition_point(|entry| entry.timestamp <= timestamp);
+        let position = match newer.checked_sub(1) {
+            Some(index) => state.entries[index].position,
+            None => state.entries.front().map_or(state.latest, |oldest| oldest.position - 1),
+        };
+        // Later writes must not land at or before a timestamp that has been read at
+        state.next_timestamp = state.next_timestamp.max(timestamp + 1);
+        // Pinned under the state lock, so no append can trim past it in between
+        self.pin(position);
+        Ok(position)
+    }
+
+    /// Position of the last appended entry
+    pub fn latest_position(&self) -> u64 {
+        self.state.lock().unwrap().latest
+    }
+
+    /// Keep every entry after `position` until it is unpinned
+    pub fn pin(&self, position: u64) {
+        *self.pins.lock().unwrap().entry(position).or_insert(0) += 1;
+    }
+
+    pub fn unpin(&self, position: u64) {
+        let mut pins = self.pins.lock().unwrap();
+        if let Some(holders) = pins.get_mut(&position) {
+            *holders -= 1;
+            if *holders == 0 {
+                pins.remove(&position);
+            }
+        }
+    }
+
+    /// Up to `limit` entries for `database` after `position`.
+    ///
+    /// Fails when entries right after `position` have already been trimmed,
+    /// since the caller would silently miss writes.
+    pub fn read_after(&self, database: &str, position: u64, limit: usize) -> Result<Vec<OplogEntry>> {
+        let state = self.state.lock().unwrap();
+        self.check_retained(&state, position)?;
+        Ok(state
+            .entries
+            .iter()
+            .filter(|entry| entry.position > position && entry.database == database)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    /// Documents of a collection changed after `position`, as they were at it.
+    ///
+    /// `None` means the document did not exist yet at `position`.
+    pub fn images_at(&self, database: &str, collection: &str, position: u64) -> Result<HashMap<DocumentId, Option<Document>>> {
+        let state = self.state.lock().unwrap();
+        self.check_retained(&state, position)?;
+
+        let mut images = HashMap::new();
+        for entry in state.entries.iter().filter(|entry| {
+            entry.position > position && entry.database == database && entry.collection == collection
+        }) {
+            // The first write after the position holds the version the snapshot saw
+            images
+                .entry(entry.operation.document_id())
+                .or_insert_with(|| entry.operation.before().cloned());
+        }
+        Ok(images)
+    }
+
+    fn check_retained(&self, state: &OplogState, position: u64) -> Result<()> {
+        match state.entries.front() {
+            Some(oldest) if oldest.position > position + 1 => Err(LargetableError::Replication(format!(
+                "Oplog position {} is no longer retained; the oldest entry is {}",
+                position, oldest.position
+            ))),
+            None if position < state.latest => Err(LargetableError::Replication(format!(
+                "Oplog position {} is no longer retained; the oplog is empty",
+                position
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Oplog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(version: u64) -> Document {
+        Document {
+            id: uuid::Uuid::nil(),
+            fields: HashMap::new(),
+            version,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn update(version: u64) -> OplogOperation {
+        OplogOperation::Update { before: document(version), after: document(version + 1) }
+    }
+
+    #[test]
+    fn test_pins_hold_back_trimming() {
+        let oplog = Oplog::with_capacity(2);
+        oplog.append("db", "users", update(1));
+        oplog.pin(1);
+        for version in 2..=4 {
+            oplog.append("db", "users", update(version));
+        }
+
+        assert_eq!(oplog.read_after("db", 1, 10).unwrap().len(), 3);
+        assert_eq!(oplog.read_after("other", 1, 10).unwrap().len(), 0);
+
+        oplog.unpin(1);
+        oplog.append("db", "users", update(5));
+        assert!(oplog.read_after("db", 1, 10).is_err());
+        assert_eq!(oplog.read_after("db", 3, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_images_use_first_write_after_position() {
+        let oplog = Oplog::new();
+        oplog.append("db", "users", update(1));
+        let position = oplog.latest_position();
+        oplog.append("db", "users", update(2));
+        oplog.append("db", "users", OplogOperation::Delete { before: document(3) });
+
+        let images = oplog.images_at("db", "users", position).unwrap();
+        assert_eq!(images[&uuid::Uuid::nil()].as_ref().map(|doc| doc.version), Some(2));
+
+        let images = oplog.images_at("db", "posts", position).unwrap();
+        assert!(images.is_empty());
+    }
+
+    #[test]
+    fn test_pin_at_finds_last_write_before_timestamp() {
+        let oplog = Oplog::with_capacity(2);
+        let before_writes = oplog.cluster_time();
+        std::thread::sleep(Duration::from_millis(2));
+        oplog.append("db", "users", update(1));
+        let first = oplog.read_after("db", 0, 1).unwrap()[0].timestamp;
+        std::thread::sleep(Duration::from_millis(2));
+        oplog.append("db", "users", update(2));
+
+        assert_eq!(oplog.pin_at(before_writes).unwrap(), 0);
+        assert_eq!(oplog.pin_at(first).unwrap(), 1);
+        assert_eq!(oplog.pin_at(oplog.cluster_time()).unwrap(), 2);
+        assert!(matches!(oplog.pin_at(oplog.cluster_time() + 60_000_000), Err(LargetableError::Query(_))));
+        for position in 0..=2 {
+            oplog.unpin(position);
+        }
+
+        // Trimming the first write moves the start of history up to it
+        oplog.append("db", "users", update(3));
+        assert_eq!(oplog.history_start(), first);
+        assert!(matches!(oplog.pin_at(before_writes), Err(LargetableError::SnapshotUnavailable(_))));
+        assert_eq!(oplog.pin_at(first).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_retention_trims_old_entries_below_capacity() {
+        let oplog = Oplog::with_history(HistoryConfig { max_entries: 100, retention: Some(Duration::from_millis(5)) });
+        oplog.append("db", "users", update(1));
+        oplog.append("db", "users", update(2));
+        std::thread::sleep(Duration::from_millis(10));
+        oplog.append("db", "users", update(3));
+
+        assert_eq!(oplog.read_after("db", 2, 10).unwrap().len(), 1);
+        assert!(oplog.read_after("db", 0, 10).is_err());
+    }
+}
@@ -5,3 +5,261 @@
 // ===========================================
 
 //! Shard balancer
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::distributed::ShardId;
+use crate::sharding::chunk::Chunk;
+use crate::sharding::config_server::ShardingMetadata;
+use crate::sharding::migration::{ChunkMigration, ChunkMover, MigrationReason};
+use crate::sharding::zones::{ZoneMatch, ZoneRegistry};
+use crate::{LargetableError, Result};
+
+/// Balancer configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalancerConfig {
+    /// Chunk count difference between eligible shards that triggers a move
+    pub imbalance_threshold: usize,
+    /// Upper bound on migrations scheduled per round
+    pub max_migrations_per_round: usize,
+}
+
+impl Default for BalancerConfig {
+    fn default() -> Self {
+        Self {
+            imbalance_threshold: 2,
+            max_migrations_per_round: 8,
+        }
+    }
+}
+
+/// Plans chunk migrations that fix zone violations and even out load
+///
+/// Zone violations are fixed first. Balancing then only moves chunks between
+/// shards eligible for them, so a zoned chunk never leaves its zone.
+pub struct Balancer {
+    config: BalancerConfig,
+}
+
+impl Balancer {
+    pub fn new(config: BalancerConfig) -> Self {
+        Self { config }
+    }
+
+    /// Reject a migration that would break a zone constraint
+    pub fn validate_migration(&self, chunk: &Chunk, to: &str, zones: &ZoneRegistry) -> Result<()> {
+        match zones.zone_for_range(&chunk.namespace, &chunk.range) {
+            ZoneMatch::Unzoned => Ok(()),
+            ZoneMatch::Zone(zone) if zones.shard_zones(to).any(|z| *z == zone) => Ok(()),
+            ZoneMatch::Zone(zone) => Err(LargetableError::Sharding(format!(
+                "Cannot move chunk {} to shard {}: shard is not in zone {}",
+                chunk.id, to, zone
+            ))),
+            ZoneMatch::Spans(zones) => Err(LargetableError::Sharding(format!(
+                "Cannot move chunk {}: it spans zones {} and must be split first",
+                chunk.id,
+                zones.join(", ")
+            ))),
+        }
+    }
+
+    /// Plan one balancing round over `shards`
+    pub fn plan_round(&self, shards: &[ShardId], chunks: &[Chunk], zones: &ZoneRegistry) -> Vec<ChunkMigration> {
+        let mut migrations = Vec::new();
+        let mut moved: HashSet<&str> = HashSet::new();
+
+        // Chunk counts per namespace and shard, kept current as moves are planned
+        let mut load: HashMap<(&str, &str), usize> = HashMap::new();
+        for chunk in chunks {
+            *load.entry((chunk.namespace.as_str(), chunk.shard.as_str())).or_default() += 1;
+        }
+
+        // Zone violations first, since they are residency breaches rather than load issues
+        for chunk in chunks {
+            if migrations.len() >= self.config.max_migrations_per_round {
+                return migrations;
+            }
+
+            let ZoneMatch::Zone(zone) = zones.zone_for_range(&chunk.namespace, &chunk.range) else {
+                continue;
+            };
+            if zones.is_allowed(chunk, &chunk.shard) {
+                continue;
+            }
+
+            let eligible: Vec<&ShardId> = zones
+                .shards_in_zone(&zone)
+                .iter()
+                .filter_map(|shard| shards.iter().find(|s| *s == shard))
+                .collect();
+            let Some(target) = eligible
+                .into_iter()
+                .min_by_key(|shard| load.get(&(chunk.namespace.as_str(), shard.as_str())).copied().unwrap_or(0))
+            else {
+                tracing::warn!("Chunk {} belongs to zone {} which has no available shards", chunk.id, zone);
+                continue;
+            };
+
+            *load.entry((chunk.namespace.as_str(), chunk.shard.as_str())).or_default() -= 1;
+            *load.entry((chunk.namespace.as_str(), target.as_str())).or_default() += 1;
+            moved.insert(chunk.id.as_str());
+            migrations.push(ChunkMigration {
+                chunk_id: chunk.id.clone(),
+                namespace: chunk.namespace.clone(),
+                from: chunk.shard.clone(),
+                to: target.clone(),
+                reason: MigrationReason::ZoneViolation { zone },
+            });
+        }
+
+        // Balance each collection's chunks within the set of shards eligible for them
+        let mut groups: BTreeMap<(&str, Option<String>), Vec<&Chunk>> = BTreeMap::new();
+        for chunk in chunks {
+            if moved.contains(chunk.id.as_str()) {
+                continue;
+            }
+            let zone = match zones.zone_for_range(&chunk.namespace, &chunk.range) {
+                ZoneMatch::Unzoned => None,
+                ZoneMatch::Zone(zone) => Some(zone),
+                ZoneMatch::Spans(_) => continue,
+            };
+            groups.entry((chunk.namespace.as_str(), zone)).or_default().push(chunk);
+        }
+
+        for ((namespace, zone), group) in groups {
+            let eligible: Vec<&ShardId> = match &zone {
+                Some(zone) => shards.iter().filter(|shard| zones.shard_zones(shard).any(|z| z == zone)).collect(),
+                None => shards.iter().collect(),
+            };
+            if eligible.len() < 2 {
+                continue;
+            }
+
+            let mut per_shard: HashMap<&str, Vec<&Chunk>> =
+                eligible.iter().map(|shard| (shard.as_str(), Vec::new())).collect();
+            for chunk in group {
+                if let Some(owned) = per_shard.get_mut(chunk.shard.as_str()) {
+                    owned.push(chunk);
+                }
+            }
+
+            loop {
+                if migrations.len() >= self.config.max_migrations_per_round {
+                    return migrations;
+                }
+
+                let (Some((&from, _)), Some((&to, _))) = (
+                    per_shard.iter().max_by_key(|(shard, owned)| (owned.len(), std::cmp::Reverse(*shard))),
+                    per_shard.iter().min_by_key(|(shard, owned)| (owned.len(), *shard)),
+                ) else {
+                    break;
+                };
+                if per_shard[from].len() - per_shard[to].len() < self.config.imbalance_threshold {
+                    break;
+                }
+
+                let Some(chunk) = per_shard.get_mut(from).and_then(|owned| owned.pop()) else {
+                    break;
+                };
+                per_shard.get_mut(to).expect("eligible shard").push(chunk);
+                migrations.push(ChunkMigration {
+                    chunk_id: chunk.id.clone(),
+                    namespace: namespace.to_string(),
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    reason: MigrationReason::Balance,
+                });
+            }
+        }
+
+        migrations
+    }
+
+    /// Plan a round against the catalog and carry it out
+    ///
+    /// Each migration is committed to the catalog as soon as its documents have
+    /// moved, so a failure part way through leaves earlier moves in place and
+    /// the failed chunk on its source shard.
+    pub async fn run_round(&self, catalog: &RwLock<ShardingMetadata>, mover: &dyn ChunkMover) -> Result<Vec<ChunkMigration>> {
+        let snapshot = catalog.read().await.clone();
+        let planned = self.plan_round(&snapshot.shards, &snapshot.chunks, &snapshot.zones);
+
+        let mut completed = Vec::new();
+        for migration in planned {
+            let Some(chunk) = snapshot.chunks.iter().find(|chunk| chunk.id == migration.chunk_id) else {
+                continue;
+            };
+            let Some(collection) = snapshot.collection(&migration.namespace) else {
+                tracing::warn!("Skipping chunk {}: {} is not a sharded collection", chunk.id, migration.namespace);
+                continue;
+            };
+            self.validate_migration(chunk, &migration.to, &snapshot.zones)?;
+
+            let moved = mover.move_chunk(&migration, chunk, &collection.key).await?;
+            catalog.write().await.commit_migration(&migration)?;
+            tracing::info!(
+                "Moved chunk {} ({} documents) from {} to {}",
+                chunk.id, moved, migration.from, migration.to
+            );
+            completed.push(migration);
+        }
+
+        Ok(completed)
+    }
+}
+
+impl Default for Balancer {
+    fn default() -> Self {
+        Self::new(BalancerConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sharding::zones::tests::{chunk, range, registry};
+
+    fn shards() -> Vec<ShardId> {
+        vec!["eu-1".to_string(), "eu-2".to_string(), "us-1".to_string()]
+    }
+
+    #[test]
+    fn test_moves_chunks_into_their_zone() {
+        let zones = registry();
+        let chunks = vec![
+            chunk("c1", range("eu:a", "eu:m"), "us-1"),
+            chunk("c2", range("eu:n", "eu:z"), "eu-1"),
+        ];
+
+        let migrations = Balancer::default().plan_round(&shards(), &chunks, &zones);
+        assert_eq!(
+            migrations,
+            vec![ChunkMigration {
+                chunk_id: "c1".to_string(),
+                namespace: "pixelle.users".to_string(),
+                from: "us-1".to_string(),
+                to: "eu-2".to_string(),
+                reason: MigrationReason::ZoneViolation { zone: "EU".to_string() },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_balancing_keeps_zoned_chunks_in_zone() {
+        let zones = registry();
+        let chunks: Vec<Chunk> = (0..6)
+            .map(|i| chunk(&format!("c{}", i), range(&format!("eu:{}", i), &format!("eu:{}~", i)), "eu-1"))
+            .collect();
+
+        let migrations = Balancer::default().plan_round(&shards(), &chunks, &zones);
+        assert!(!migrations.is_empty());
+        assert!(migrations.iter().all(|m| m.to == "eu-2" && m.reason == MigrationReason::Balance));
+
+        let balancer = Balancer::default();
+        assert!(balancer.validate_migration(&chunks[0], "us-1", &zones).is_err());
+        assert!(balancer.validate_migration(&chunks[0], "eu-2", &zones).is_ok());
+    }
+}
@@ -5,3 +5,29 @@
 // ===========================================
 
 //! Shard chunk management
+
+use serde::{Deserialize, Serialize};
+
+use crate::distributed::{KeyRange, ShardId, ShardKey};
+
+/// Contiguous range of shard keys for one collection, owned by a single shard
+///
+/// Ranges start at their lower bound and stop just before their upper bound;
+/// a collection's chunks together cover `MinKey` to `MaxKey`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    pub id: String,
+    /// `database.collection`
+    pub namespace: String,
+    pub range: KeyRange,
+    pub shard: ShardId,
+    pub size_bytes: u64,
+    pub document_count: u64,
+}
+
+impl Chunk {
+    /// Whether a shard key falls into this chunk
+    pub fn owns(&self, key: &ShardKey) -> bool {
+        self.range.owns(key)
+    }
+}
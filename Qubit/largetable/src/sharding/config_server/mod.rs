@@ -5,3 +5,283 @@
 // ===========================================
 
 //! Configuration server
+//!
+//! The config catalog is the source of truth for which shard owns which chunk.
+//! Every change to the chunk map bumps `version`, so routers holding an older
+//! copy can tell that they need to reload it.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::distributed::{KeyRange, ShardId, ShardKey};
+use crate::sharding::chunk::Chunk;
+use crate::sharding::key::{KeyTarget, ShardKeyKind, ShardKeyPattern};
+use crate::sharding::migration::ChunkMigration;
+use crate::sharding::zones::{PlacementViolation, ZoneRegistry};
+use crate::{LargetableError, Result};
+
+/// Collection registered as sharded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShardedCollection {
+    /// `database.collection`
+    pub namespace: String,
+    pub key: ShardKeyPattern,
+}
+
+/// Sharding metadata persisted by the config server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShardingMetadata {
+    pub shards: Vec<ShardId>,
+    /// Base URL of each shard's HTTP API
+    #[serde(default)]
+    pub hosts: HashMap<ShardId, String>,
+    #[serde(default)]
+    pub zones: ZoneRegistry,
+    #[serde(default)]
+    pub collections: Vec<ShardedCollection>,
+    pub chunks: Vec<Chunk>,
+    /// Bumped on every chunk map change
+    #[serde(default)]
+    pub version: u64,
+    #[serde(default)]
+    next_chunk_id: u64,
+}
+
+impl ShardingMetadata {
+    /// Load a metadata snapshot written as JSON
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn placement_violations(&self) -> Vec<PlacementViolation> {
+        self.zones.check_placement(&self.chunks)
+    }
+
+    /// Number of chunks each shard holds
+    pub fn chunk_counts(&self) -> Vec<(ShardId, usize)> {
+        self.shards
+            .iter()
+            .map(|shard| (shard.clone(), self.chunks.iter().filter(|chunk| chunk.shard == *shard).count()))
+            .collect()
+    }
+
+    /// Register a shard, or move an existing one to a new host
+    pub fn add_shard(&mut self, shard: &str, host: &str) {
+        if !self.shards.iter().any(|s| s == shard) {
+            self.shards.push(shard.to_string());
+        }
+        self.hosts.insert(shard.to_string(), host.trim_end_matches('/').to_string());
+    }
+
+    pub fn shard_host(&self, shard: &str) -> Option<&str> {
+        self.hosts.get(shard).map(String::as_str)
+    }
+
+    /// Shard holding collections that are not sharded, like a MongoDB database primary
+    pub fn primary_shard(&self) -> Option<&ShardId> {
+        self.shards.first()
+    }
+
+    pub fn collection(&self, namespace: &str) -> Option<&ShardedCollection> {
+        self.collections.iter().find(|collection| collection.namespace == namespace)
+    }
+
+    /// Shard a collection and create its initial chunks
+    ///
+    /// A range-sharded collection starts as one chunk on the least loaded shard
+    /// and is split as it grows. A hashed collection is pre-split into
+    /// `initial_chunks` equal slices of the hash space, dealt round-robin.
+    pub fn shard_collection(&mut self, namespace: &str, key: ShardKeyPattern, initial_chunks: usize) -> Result<()> {
+        if self.collection(namespace).is_some() {
+            return Err(LargetableError::Sharding(format!("{} is already sharded", namespace)));
+        }
+        if self.shards.is_empty() {
+            return Err(LargetableError::Sharding("Cannot shard a collection without shards".to_string()));
+        }
+
+        let mut bounds = vec![ShardKey::MinKey];
+        if key.kind == ShardKeyKind::Hashed {
+            bounds.extend(ShardKeyPattern::hash_split_points(initial_chunks.max(self.shards.len())));
+        }
+        bounds.push(ShardKey::MaxKey);
+
+        let mut shards = self.chunk_counts();
+        shards.sort_by_key(|(_, chunks)| *chunks);
+        for (i, window) in bounds.windows(2).enumerate() {
+            let chunk = Chunk {
+                id: self.allocate_chunk_id(namespace),
+                namespace: namespace.to_string(),
+                range: KeyRange { start: window[0].clone(), end: window[1].clone(), inclusive: false },
+                shard: shards[i % shards.len()].0.clone(),
+                size_bytes: 0,
+                document_count: 0,
+            };
+            self.chunks.push(chunk);
+        }
+
+        self.collections.push(ShardedCollection { namespace: namespace.to_string(), key });
+        self.version += 1;
+        Ok(())
+    }
+
+    /// Chunks of a collection in key order
+    pub fn chunks_for(&self, namespace: &str) -> Vec<&Chunk> {
+        let mut chunks: Vec<&Chunk> = self.chunks.iter().filter(|chunk| chunk.namespace == namespace).collect();
+        chunks.sort_by(|a, b| a.range.start.compare(&b.range.start).unwrap_or(std::cmp::Ordering::Equal));
+        chunks
+    }
+
+    /// Chunk owning a shard key
+    pub fn chunk_for_key(&self, namespace: &str, key: &ShardKey) -> Option<&Chunk> {
+        self.chunks.iter().find(|chunk| chunk.namespace == namespace && chunk.owns(key))
+    }
+
+    /// Shards a query against a collection has to visit, in shard order
+    pub fn shards_for(&self, namespace: &str, target: &KeyTarget) -> Vec<ShardId> {
+        if self.collection(namespace).is_none() {
+            return self.primary_shard().cloned().into_iter().collect();
+        }
+
+        self.shards
+            .iter()
+            .filter(|shard| {
+                self.chunks
+                    .iter()
+                    .any(|chunk| chunk.namespace == namespace && chunk.shard == **shard && target.matches(&chunk.range))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Split a chunk at a key strictly inside it; returns the new upper chunk's ID
+    pub fn split_chunk(&mut self, chunk_id: &str, at: ShardKey) -> Result<String> {
+        let index = self
+            .chunks
+            .iter()
+            .position(|chunk| chunk.id == chunk_id)
+            .ok_or_else(|| LargetableError::Sharding(format!("Unknown chunk {}", chunk_id)))?;
+
+        let chunk = &self.chunks[index];
+        let inside = matches!(at.compare(&chunk.range.start), Some(std::cmp::Ordering::Greater))
+            && matches!(at.compare(&chunk.range.end), Some(std::cmp::Ordering::Less));
+        if !inside {
+            return Err(LargetableError::Sharding(format!("Split point {:?} is not inside chunk {}", at, chunk_id)));
+        }
+
+        let namespace = chunk.namespace.clone();
+        let id = self.allocate_chunk_id(&namespace);
+        let chunk = &mut self.chunks[index];
+        // Sizes are estimates until the shard reports real numbers
+        let upper = Chunk {
+            id: id.clone(),
+            namespace,
+            range: KeyRange { start: at.clone(), end: chunk.range.end.clone(), inclusive: false },
+            shard: chunk.shard.clone(),
+            size_bytes: chunk.size_bytes / 2,
+            document_count: chunk.document_count / 2,
+        };
+        chunk.range.end = at;
+        chunk.size_bytes -= upper.size_bytes;
+        chunk.document_count -= upper.document_count;
+        self.chunks.push(upper);
+        self.version += 1;
+        Ok(id)
+    }
+
+    /// Record a finished migration in the chunk map
+    pub fn commit_migration(&mut self, migration: &ChunkMigration) -> Result<()> {
+        if !self.shards.contains(&migration.to) {
+            return Err(LargetableError::Sharding(format!("Unknown shard {}", migration.to)));
+        }
+        let chunk = self
+            .chunks
+            .iter_mut()
+            .find(|chunk| chunk.id == migration.chunk_id)
+            .ok_or_else(|| LargetableError::Sharding(format!("Unknown chunk {}", migration.chunk_id)))?;
+        if chunk.shard != migration.from {
+            return Err(LargetableError::Sharding(format!(
+                "Chunk {} moved to {} while migrating from {}",
+                chunk.id, chunk.shard, migration.from
+            )));
+        }
+
+        chunk.shard = migration.to.clone();
+        self.version += 1;
+        Ok(())
+    }
+
+    fn allocate_chunk_id(&mut self, namespace: &str) -> String {
+        self.next_chunk_id += 1;
+        format!("{}-{}", namespace, self.next_chunk_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sharding::migration::MigrationReason;
+
+    fn catalog() -> ShardingMetadata {
+        let mut catalog = ShardingMetadata::default();
+        catalog.add_shard("shard-a", "http://10.0.0.1:27017/");
+        catalog.add_shard("shard-b", "http://10.0.0.2:27017");
+        catalog
+    }
+
+    #[test]
+    fn test_hashed_collection_is_presplit_across_shards() {
+        let mut catalog = catalog();
+        catalog.shard_collection("pixelle.events", ShardKeyPattern::hashed("user_id"), 4).unwrap();
+        assert!(catalog.shard_collection("pixelle.events", ShardKeyPattern::hashed("user_id"), 4).is_err());
+
+        let chunks = catalog.chunks_for("pixelle.events");
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0].range.start, ShardKey::MinKey);
+        assert_eq!(chunks[3].range.end, ShardKey::MaxKey);
+        assert_eq!(catalog.chunk_counts(), vec![("shard-a".to_string(), 2), ("shard-b".to_string(), 2)]);
+
+        // Every hash lands in exactly one chunk
+        for hash in [0, u64::MAX / 3, u64::MAX / 2, u64::MAX] {
+            let owners = chunks.iter().filter(|chunk| chunk.owns(&ShardKey::Hash(hash))).count();
+            assert_eq!(owners, 1);
+        }
+        assert_eq!(catalog.shard_host("shard-a"), Some("http://10.0.0.1:27017"));
+    }
+
+    #[test]
+    fn test_split_and_migrate_range_chunk() {
+        let mut catalog = catalog();
+        catalog.shard_collection("pixelle.users", ShardKeyPattern::range("age"), 1).unwrap();
+        let first = catalog.chunks_for("pixelle.users")[0].id.clone();
+
+        let upper = catalog.split_chunk(&first, ShardKey::Integer(40)).unwrap();
+        assert!(catalog.split_chunk(&first, ShardKey::Integer(40)).is_err());
+        assert_eq!(catalog.chunk_for_key("pixelle.users", &ShardKey::Integer(39)).unwrap().id, first);
+        assert_eq!(catalog.chunk_for_key("pixelle.users", &ShardKey::Integer(40)).unwrap().id, upper);
+
+        let version = catalog.version;
+        let migration = ChunkMigration {
+            chunk_id: upper.clone(),
+            namespace: "pixelle.users".to_string(),
+            from: "shard-a".to_string(),
+            to: "shard-b".to_string(),
+            reason: MigrationReason::Balance,
+        };
+        catalog.commit_migration(&migration).unwrap();
+        assert!(catalog.commit_migration(&migration).is_err());
+        assert_eq!(catalog.version, version + 1);
+
+        let adults = KeyTarget::Range { lower: ShardKey::Integer(50), upper: ShardKey::Integer(60) };
+        assert_eq!(catalog.shards_for("pixelle.users", &adults), vec!["shard-b".to_string()]);
+        assert_eq!(catalog.shards_for("pixelle.users", &KeyTarget::All).len(), 2);
+        assert_eq!(catalog.shards_for("pixelle.photos", &KeyTarget::All), vec!["shard-a".to_string()]);
+    }
+}
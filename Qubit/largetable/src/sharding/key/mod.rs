@@ -0,0 +1,218 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Shard keys: which field partitions a collection, and how
+//!
+//! Range keys keep adjacent values on the same chunk, which makes range
+//! queries cheap but concentrates monotonically increasing inserts on one
+//! shard. Hashed keys spread writes evenly at the cost of turning every range
+//! query into a scatter-gather.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::distributed::{KeyRange, ShardKey};
+use crate::document::DocumentUtils;
+use crate::{Document, LargetableError, Result, Value};
+
+/// How shard key values map onto chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardKeyKind {
+    /// Chunks hold contiguous ranges of the field's values
+    Range,
+    /// Chunks hold contiguous ranges of a stable 64-bit hash of the value
+    Hashed,
+}
+
+/// Shard key of a sharded collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShardKeyPattern {
+    /// Field holding the key; `_id` uses the document ID
+    pub field: String,
+    pub kind: ShardKeyKind,
+}
+
+/// Shards a query has to visit, as far as its filter pins the shard key
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyTarget {
+    /// The filter does not constrain the key; every shard is asked
+    All,
+    /// Equality or `$in` on the key
+    Keys(Vec<ShardKey>),
+    /// Bounds on a range key; both ends are treated as inclusive
+    Range { lower: ShardKey, upper: ShardKey },
+}
+
+impl ShardKeyPattern {
+    pub fn range(field: impl Into<String>) -> Self {
+        Self { field: field.into(), kind: ShardKeyKind::Range }
+    }
+
+    pub fn hashed(field: impl Into<String>) -> Self {
+        Self { field: field.into(), kind: ShardKeyKind::Hashed }
+    }
+
+    /// Shard key of a document; documents without the field cannot be routed
+    pub fn extract(&self, doc: &Document) -> Result<ShardKey> {
+        if self.field == "_id" {
+            return self.key_for_value(&Value::ObjectId(doc.id));
+        }
+        let value = DocumentUtils::get_field(doc, &self.field).ok_or_else(|| {
+            LargetableError::Sharding(format!("Document {} has no shard key field {}", doc.id, self.field))
+        })?;
+        self.key_for_value(value)
+    }
+
+    /// Shard key for a field value
+    pub fn key_for_value(&self, value: &Value) -> Result<ShardKey> {
+        let key = match value {
+            Value::String(s) => ShardKey::String(s.clone()),
+            Value::Int32(i) => ShardKey::Integer(i64::from(*i)),
+            Value::Int64(i) => ShardKey::Integer(*i),
+            Value::UInt64(u) => ShardKey::Integer(i64::try_from(*u).map_err(|_| {
+                LargetableError::Sharding(format!("Shard key value {} is out of range", u))
+            })?),
+            Value::ObjectId(id) => ShardKey::ObjectId(*id),
+            other => {
+                return Err(LargetableError::Sharding(format!(
+                    "Shard key field {} must be a string, integer or object id, got {:?}",
+                    self.field, other
+                )))
+            }
+        };
+
+        Ok(match self.kind {
+            ShardKeyKind::Range => key,
+            ShardKeyKind::Hashed => ShardKey::Hash(hash_key(&key)),
+        })
+    }
+
+    /// Work out which keys a query filter can match
+    pub fn target(&self, filter: Option<&JsonValue>) -> KeyTarget {
+        let Some(condition) = filter.and_then(|filter| filter.get(&self.field)) else {
+            return KeyTarget::All;
+        };
+
+        let Some(operators) = DocumentUtils::filter_operators(condition) else {
+            return self.json_keys(std::slice::from_ref(condition)).map_or(KeyTarget::All, KeyTarget::Keys);
+        };
+
+        if let Some(operand) = operators.get("$eq") {
+            return self.json_keys(std::slice::from_ref(operand)).map_or(KeyTarget::All, KeyTarget::Keys);
+        }
+        if let Some(JsonValue::Array(operands)) = operators.get("$in") {
+            return self.json_keys(operands).map_or(KeyTarget::All, KeyTarget::Keys);
+        }
+
+        // Hashing destroys ordering, so bounds only narrow range keys
+        if self.kind == ShardKeyKind::Hashed {
+            return KeyTarget::All;
+        }
+        let bound = |names: [&str; 2], default: ShardKey| {
+            names
+                .iter()
+                .find_map(|name| operators.get(*name))
+                .map(|operand| self.json_keys(std::slice::from_ref(operand)).and_then(|mut keys| keys.pop()))
+                .unwrap_or(Some(default))
+        };
+        match (bound(["$gte", "$gt"], ShardKey::MinKey), bound(["$lte", "$lt"], ShardKey::MaxKey)) {
+            (Some(ShardKey::MinKey), Some(ShardKey::MaxKey)) => KeyTarget::All,
+            (Some(lower), Some(upper)) => KeyTarget::Range { lower, upper },
+            _ => KeyTarget::All,
+        }
+    }
+
+    /// Keys of filter operands, or `None` if any operand cannot be a shard key
+    fn json_keys(&self, operands: &[JsonValue]) -> Option<Vec<ShardKey>> {
+        operands
+            .iter()
+            .map(|operand| {
+                let value = DocumentUtils::json_to_value(operand.clone()).ok()?;
+                self.key_for_value(&value).ok()
+            })
+            .collect()
+    }
+
+    /// Split points that divide the hash space into `chunks` equal ranges
+    pub fn hash_split_points(chunks: usize) -> Vec<ShardKey> {
+        let step = u64::MAX / chunks.max(1) as u64;
+        (1..chunks.max(1) as u64).map(|i| ShardKey::Hash(step * i)).collect()
+    }
+}
+
+impl KeyTarget {
+    /// Whether a chunk's key range may hold keys this target asks for
+    pub fn matches(&self, range: &KeyRange) -> bool {
+        match self {
+            KeyTarget::All => true,
+            KeyTarget::Keys(keys) => keys.iter().any(|key| range.owns(key)),
+            KeyTarget::Range { lower, upper } => {
+                use std::cmp::Ordering;
+                matches!(range.end.compare(lower), Some(Ordering::Greater))
+                    && matches!(range.start.compare(upper), Some(Ordering::Less | Ordering::Equal))
+            }
+        }
+    }
+}
+
+/// Stable hash of a key value
+///
+/// FNV-1a followed by a 64-bit finalizer. It must never change: chunk bounds of
+/// hashed collections are stored as hash values.
+fn hash_key(key: &ShardKey) -> u64 {
+    let (tag, bytes): (u8, Vec<u8>) = match key {
+        ShardKey::String(s) => (1, s.as_bytes().to_vec()),
+        ShardKey::Integer(i) => (2, i.to_le_bytes().to_vec()),
+        ShardKey::ObjectId(id) => (3, id.as_bytes().to_vec()),
+        ShardKey::Hash(h) => (4, h.to_le_bytes().to_vec()),
+        ShardKey::MinKey => (5, Vec::new()),
+        ShardKey::MaxKey => (6, Vec::new()),
+    };
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in std::iter::once(tag).chain(bytes) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_hashed_keys_are_stable_across_integer_widths() {
+        let pattern = ShardKeyPattern::hashed("user_id");
+        let narrow = pattern.key_for_value(&Value::Int32(42)).unwrap();
+        let wide = pattern.key_for_value(&Value::Int64(42)).unwrap();
+        assert_eq!(narrow, wide);
+        assert!(matches!(narrow, ShardKey::Hash(_)));
+        assert!(pattern.key_for_value(&Value::Float64(1.5)).is_err());
+    }
+
+    #[test]
+    fn test_target_from_filter() {
+        let range = ShardKeyPattern::range("age");
+        assert_eq!(range.target(Some(&json!({"age": 30}))), KeyTarget::Keys(vec![ShardKey::Integer(30)]));
+        assert_eq!(
+            range.target(Some(&json!({"age": {"$gte": 18, "$lt": 65}}))),
+            KeyTarget::Range { lower: ShardKey::Integer(18), upper: ShardKey::Integer(65) }
+        );
+        assert_eq!(range.target(Some(&json!({"name": "ada"}))), KeyTarget::All);
+
+        let hashed = ShardKeyPattern::hashed("age");
+        assert_eq!(hashed.target(Some(&json!({"age": {"$gt": 18}}))), KeyTarget::All);
+        assert!(matches!(hashed.target(Some(&json!({"age": {"$in": [1, 2]}}))), KeyTarget::Keys(keys) if keys.len() == 2));
+    }
+}
@@ -5,3 +5,39 @@
 // ===========================================
 
 //! Shard migration
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::distributed::ShardId;
+use crate::sharding::chunk::Chunk;
+use crate::sharding::key::ShardKeyPattern;
+use crate::Result;
+
+/// Move of a single chunk between shards
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkMigration {
+    pub chunk_id: String,
+    pub namespace: String,
+    pub from: ShardId,
+    pub to: ShardId,
+    pub reason: MigrationReason,
+}
+
+/// Why the balancer scheduled a migration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MigrationReason {
+    /// Chunk sits on a shard outside the zone its range belongs to
+    ZoneViolation { zone: String },
+    /// Evening out chunk counts between eligible shards
+    Balance,
+}
+
+/// Moves a chunk's documents between shards for the balancer
+#[async_trait]
+pub trait ChunkMover: Send + Sync {
+    /// Copy every document the chunk owns from `migration.from` to
+    /// `migration.to`, then delete them from the source. Returns the number of
+    /// documents moved. The chunk map is only updated once this succeeds.
+    async fn move_chunk(&self, migration: &ChunkMigration, chunk: &Chunk, key: &ShardKeyPattern) -> Result<u64>;
+}
@@ -0,0 +1,23 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Sharding: shard keys, the config catalog, zones, balancing and migration
+
+pub mod auto_scaling;
+pub mod balancer;
+pub mod chunk;
+pub mod config_server;
+pub mod key;
+pub mod migration;
+pub mod router;
+pub mod zones;
+
+pub use balancer::{Balancer, BalancerConfig};
+pub use chunk::Chunk;
+pub use config_server::{ShardedCollection, ShardingMetadata};
+pub use key::{KeyTarget, ShardKeyKind, ShardKeyPattern};
+pub use migration::{ChunkMigration, ChunkMover, MigrationReason};
+pub use zones::{PlacementViolation, Zone, ZoneRange, ZoneRegistry};
@@ -0,0 +1,355 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Zone-aware shard placement for data residency
+//!
+//! Shards are tagged with zones, and zone ranges pin a collection's shard key
+//! ranges to a zone, e.g. EU users to shards running in EU regions. Chunks in
+//! a zone range may only live on that zone's shards; chunks outside every
+//! range may live anywhere.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::distributed::{KeyRange, ShardId};
+use crate::sharding::chunk::Chunk;
+use crate::{LargetableError, Result};
+
+/// Named placement zone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub name: String,
+    /// Region the zone's shards run in, for reporting
+    pub region: Option<String>,
+}
+
+/// Shard key range of a collection pinned to a zone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneRange {
+    /// `database.collection`
+    pub namespace: String,
+    pub zone: String,
+    pub range: KeyRange,
+}
+
+/// Zone a key range falls into
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZoneMatch {
+    /// Not covered by any zone range; any shard may hold it
+    Unzoned,
+    Zone(String),
+    /// Straddles zone boundaries and must be split before it can be placed
+    Spans(Vec<String>),
+}
+
+/// Placement problem reported by `tools status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PlacementViolation {
+    /// Chunk lives on a shard outside its zone
+    ChunkOutsideZone {
+        namespace: String,
+        chunk_id: String,
+        shard: ShardId,
+        zone: String,
+    },
+    /// Chunk crosses a zone boundary
+    ChunkSpansZones {
+        namespace: String,
+        chunk_id: String,
+        zones: Vec<String>,
+    },
+    /// Zone has ranges but no shards to place them on
+    ZoneWithoutShards { zone: String },
+}
+
+impl fmt::Display for PlacementViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementViolation::ChunkOutsideZone { namespace, chunk_id, shard, zone } => {
+                write!(f, "{} chunk {} is on shard {} outside zone {}", namespace, chunk_id, shard, zone)
+            }
+            PlacementViolation::ChunkSpansZones { namespace, chunk_id, zones } => {
+                write!(f, "{} chunk {} spans zones {} and needs a split", namespace, chunk_id, zones.join(", "))
+            }
+            PlacementViolation::ZoneWithoutShards { zone } => {
+                write!(f, "zone {} has ranges but no shards", zone)
+            }
+        }
+    }
+}
+
+/// Zone definitions, shard tags and zone ranges, as held by the config server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ZoneRegistry {
+    zones: HashMap<String, Zone>,
+    shard_zones: HashMap<ShardId, BTreeSet<String>>,
+    ranges: Vec<ZoneRange>,
+}
+
+impl ZoneRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_zone(&mut self, zone: Zone) {
+        self.zones.insert(zone.name.clone(), zone);
+    }
+
+    /// Remove a zone that no range refers to anymore
+    pub fn remove_zone(&mut self, name: &str) -> Result<()> {
+        if self.ranges.iter().any(|range| range.zone == name) {
+            return Err(LargetableError::Sharding(format!("Zone {} still has ranges", name)));
+        }
+        self.zones.remove(name);
+        for zones in self.shard_zones.values_mut() {
+            zones.remove(name);
+        }
+        Ok(())
+    }
+
+    pub fn zone(&self, name: &str) -> Option<&Zone> {
+        self.zones.get(name)
+    }
+
+    pub fn zones(&self) -> impl Iterator<Item = &Zone> {
+        self.zones.values()
+    }
+
+    pub fn add_shard_to_zone(&mut self, shard: &str, zone: &str) -> Result<()> {
+        self.require_zone(zone)?;
+        self.shard_zones.entry(shard.to_string()).or_default().insert(zone.to_string());
+        Ok(())
+    }
+
+    pub fn remove_shard_from_zone(&mut self, shard: &str, zone: &str) {
+        if let Some(zones) = self.shard_zones.get_mut(shard) {
+            zones.remove(zone);
+        }
+    }
+
+    /// Zones a shard is tagged with
+    pub fn shard_zones(&self, shard: &str) -> impl Iterator<Item = &String> {
+        self.shard_zones.get(shard).into_iter().flatten()
+    }
+
+    /// Shards tagged with a zone, in sorted order
+    pub fn shards_in_zone(&self, zone: &str) -> Vec<ShardId> {
+        let mut shards: Vec<ShardId> = self
+            .shard_zones
+            .iter()
+            .filter(|(_, zones)| zones.contains(zone))
+            .map(|(shard, _)| shard.clone())
+            .collect();
+        shards.sort();
+        shards
+    }
+
+    /// Pin a key range to a zone; ranges of one collection may not overlap
+    pub fn add_zone_range(&mut self, range: ZoneRange) -> Result<()> {
+        self.require_zone(&range.zone)?;
+        if range.range.start.compare(&range.range.end).is_none() {
+            return Err(LargetableError::Sharding("Zone range bounds must be keys of the same type".to_string()));
+        }
+
+        if let Some(existing) = self
+            .ranges
+            .iter()
+            .find(|existing| existing.namespace == range.namespace && existing.range.overlaps(&range.range))
+        {
+            return Err(LargetableError::Sharding(format!(
+                "Range for zone {} overlaps existing range for zone {} on {}",
+                range.zone, existing.zone, range.namespace
+            )));
+        }
+
+        self.ranges.push(range);
+        Ok(())
+    }
+
+    pub fn remove_zone_range(&mut self, namespace: &str, range: &KeyRange) {
+        self.ranges.retain(|existing| !(existing.namespace == namespace && existing.range == *range));
+    }
+
+    /// Zone ranges defined for a collection
+    pub fn ranges_for(&self, namespace: &str) -> impl Iterator<Item = &ZoneRange> {
+        let namespace = namespace.to_string();
+        self.ranges.iter().filter(move |range| range.namespace == namespace)
+    }
+
+    /// Zone a collection's key range belongs to
+    pub fn zone_for_range(&self, namespace: &str, range: &KeyRange) -> ZoneMatch {
+        let mut covering = None;
+        let mut touching = Vec::new();
+
+        for zone_range in self.ranges_for(namespace) {
+            if zone_range.range.covers(range) {
+                covering = Some(zone_range.zone.clone());
+            } else if zone_range.range.overlaps(range) {
+                touching.push(zone_range.zone.clone());
+            }
+        }
+
+        match (covering, touching.is_empty()) {
+            (Some(zone), true) => ZoneMatch::Zone(zone),
+            (None, true) => ZoneMatch::Unzoned,
+            (covering, false) => {
+                // A range partly inside a zone also spans the unzoned gap next to it
+                let mut zones: Vec<String> = covering.into_iter().chain(touching).collect();
+                zones.sort();
+                zones.dedup();
+                ZoneMatch::Spans(zones)
+            }
+        }
+    }
+
+    /// Shards allowed to hold a chunk with this range, or `None` when any shard may
+    pub fn eligible_shards(&self, namespace: &str, range: &KeyRange) -> Option<Vec<ShardId>> {
+        match self.zone_for_range(namespace, range) {
+            ZoneMatch::Unzoned => None,
+            ZoneMatch::Zone(zone) => Some(self.shards_in_zone(&zone)),
+            ZoneMatch::Spans(_) => Some(Vec::new()),
+        }
+    }
+
+    /// Whether a chunk may be placed on a shard
+    pub fn is_allowed(&self, chunk: &Chunk, shard: &str) -> bool {
+        match self.zone_for_range(&chunk.namespace, &chunk.range) {
+            ZoneMatch::Unzoned => true,
+            ZoneMatch::Zone(zone) => self.shard_zones(shard).any(|z| *z == zone),
+            ZoneMatch::Spans(_) => false,
+        }
+    }
+
+    /// Every zone constraint the current chunk placement breaks
+    pub fn check_placement(&self, chunks: &[Chunk]) -> Vec<PlacementViolation> {
+        let mut violations = Vec::new();
+
+        let mut zoned: Vec<&String> = self.ranges.iter().map(|range| &range.zone).collect();
+        zoned.sort();
+        zoned.dedup();
+        for zone in zoned {
+            if self.shards_in_zone(zone).is_empty() {
+                violations.push(PlacementViolation::ZoneWithoutShards { zone: zone.clone() });
+            }
+        }
+
+        for chunk in chunks {
+            match self.zone_for_range(&chunk.namespace, &chunk.range) {
+                ZoneMatch::Unzoned => {}
+                ZoneMatch::Zone(zone) => {
+                    if !self.shard_zones(&chunk.shard).any(|z| *z == zone) {
+                        violations.push(PlacementViolation::ChunkOutsideZone {
+                            namespace: chunk.namespace.clone(),
+                            chunk_id: chunk.id.clone(),
+                            shard: chunk.shard.clone(),
+                            zone,
+                        });
+                    }
+                }
+                ZoneMatch::Spans(zones) => {
+                    violations.push(PlacementViolation::ChunkSpansZones {
+                        namespace: chunk.namespace.clone(),
+                        chunk_id: chunk.id.clone(),
+                        zones,
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+
+    fn require_zone(&self, zone: &str) -> Result<()> {
+        if self.zones.contains_key(zone) {
+            Ok(())
+        } else {
+            Err(LargetableError::Sharding(format!("Unknown zone {}", zone)))
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::distributed::ShardKey;
+
+    pub(crate) fn range(start: &str, end: &str) -> KeyRange {
+        KeyRange {
+            start: ShardKey::String(start.to_string()),
+            end: ShardKey::String(end.to_string()),
+            inclusive: true,
+        }
+    }
+
+    pub(crate) fn chunk(id: &str, range: KeyRange, shard: &str) -> Chunk {
+        Chunk {
+            id: id.to_string(),
+            namespace: "pixelle.users".to_string(),
+            range,
+            shard: shard.to_string(),
+            size_bytes: 0,
+            document_count: 0,
+        }
+    }
+
+    /// `eu-1`/`eu-2` in zone EU, `us-1` in zone US; EU owns keys `eu:*`
+    pub(crate) fn registry() -> ZoneRegistry {
+        let mut registry = ZoneRegistry::new();
+        registry.add_zone(Zone { name: "EU".to_string(), region: Some("eu-west-1".to_string()) });
+        registry.add_zone(Zone { name: "US".to_string(), region: Some("us-east-1".to_string()) });
+        registry.add_shard_to_zone("eu-1", "EU").unwrap();
+        registry.add_shard_to_zone("eu-2", "EU").unwrap();
+        registry.add_shard_to_zone("us-1", "US").unwrap();
+        registry
+            .add_zone_range(ZoneRange {
+                namespace: "pixelle.users".to_string(),
+                zone: "EU".to_string(),
+                range: range("eu:", "eu:~"),
+            })
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_rejects_overlapping_ranges_and_unknown_zones() {
+        let mut registry = registry();
+
+        let overlapping = ZoneRange {
+            namespace: "pixelle.users".to_string(),
+            zone: "US".to_string(),
+            range: range("eu:m", "us:~"),
+        };
+        assert!(registry.add_zone_range(overlapping).is_err());
+        assert!(registry.add_shard_to_zone("ap-1", "APAC").is_err());
+        assert!(registry.remove_zone("EU").is_err());
+    }
+
+    #[test]
+    fn test_check_placement_reports_violations() {
+        let registry = registry();
+        let chunks = vec![
+            chunk("c1", range("eu:a", "eu:m"), "eu-1"),
+            chunk("c2", range("eu:n", "eu:z"), "us-1"),
+            chunk("c3", range("ap:", "eu:c"), "us-1"),
+            chunk("c4", range("us:", "us:~"), "eu-2"),
+        ];
+
+        let violations = registry.check_placement(&chunks);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(
+            violations[0],
+            PlacementViolation::ChunkOutsideZone {
+                namespace: "pixelle.users".to_string(),
+                chunk_id: "c2".to_string(),
+                shard: "us-1".to_string(),
+                zone: "EU".to_string(),
+            }
+        );
+        assert!(matches!(&violations[1], PlacementViolation::ChunkSpansZones { chunk_id, .. } if chunk_id == "c3"));
+    }
+}
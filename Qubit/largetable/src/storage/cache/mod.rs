@@ -5,3 +5,564 @@
 // ===========================================
 
 //! In-memory caching layer
+//!
+//! Hot documents are kept in their stored form in front of the storage
+//! engines and evicted by ARC (adaptive replacement cache). ARC splits the
+//! cache between documents read once since they were loaded and documents
+//! read again, and remembers the keys it recently evicted from each side.
+//! Loading a remembered key means that side was too small, so the split
+//! moves towards it: a one-off scan cannot flush the frequently read
+//! documents, while a shift in the working set is still picked up quickly.
+
+use crate::document::StoredDocument;
+use crate::observability::document_cache::{DocumentCacheMetrics, DocumentCacheStats};
+use crate::storage::encryption::ResealBatch;
+use crate::storage::StorageEngine;
+use crate::{Document, DocumentId, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Writes bump the epoch of their stripe, so a read that raced one is not cached
+const EPOCH_STRIPES: usize = 32;
+/// Estimated bookkeeping per cached document on top of its page
+const ENTRY_OVERHEAD_BYTES: usize = 96;
+
+/// Document cache configuration
+#[derive(Debug, Clone)]
+pub struct DocumentCacheConfig {
+    /// Cache documents read from the storage engines at all
+    pub enabled: bool,
+    /// Maximum number of cached documents across all databases
+    pub max_documents: usize,
+    /// Maximum size of all cached pages
+    pub max_memory_bytes: usize,
+}
+
+impl Default for DocumentCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_documents: 100_000,
+            max_memory_bytes: 256 * 1024 * 1024, // 256MB
+        }
+    }
+}
+
+/// Databases share one cache, so keys carry the engine they were read from
+type CacheKey = (u64, DocumentId);
+
+struct Resident {
+    document: Arc<StoredDocument>,
+    size_bytes: usize,
+    tick: u64,
+    frequent: bool,
+}
+
+struct Ghost {
+    tick: u64,
+    frequent: bool,
+}
+
+#[derive(Default)]
+struct ArcState {
+    next_tick: u64,
+    resident: HashMap<CacheKey, Resident>,
+    /// Read once since loaded, by tick (ARC's T1)
+    recent: BTreeMap<u64, CacheKey>,
+    /// Read again since loaded, by tick (T2)
+    frequent: BTreeMap<u64, CacheKey>,
+    ghosts: HashMap<CacheKey, Ghost>,
+    /// Keys recently evicted from `recent` (B1) and `frequent` (B2)
+    recent_ghosts: BTreeMap<u64, CacheKey>,
+    frequent_ghosts: BTreeMap<u64, CacheKey>,
+    /// Number of documents `recent` may hold before it is evicted from first (p)
+    recency_target: usize,
+    memory_bytes: usize,
+    epochs: [u64; EPOCH_STRIPES],
+}
+
+impl ArcState {
+    fn tick(&mut self) -> u64 {
+        self.next_tick += 1;
+        self.next_tick
+    }
+
+    /// Move a resident document to the most recent end of `frequent`
+    fn touch(&mut self, key: &CacheKey) {
+        let tick = self.tick();
+        let Some(entry) = self.resident.get_mut(key) else {
+            return;
+        };
+        if entry.frequent {
+            self.frequent.remove(&entry.tick);
+        } else {
+            self.recent.remove(&entry.tick);
+        }
+        entry.tick = tick;
+        entry.frequent = true;
+        self.frequent.insert(tick, *key);
+    }
+
+    fn insert(&mut self, key: CacheKey, document: Arc<StoredDocument>, size_bytes: usize, frequent: bool) {
+        let tick = self.tick();
+        if frequent {
+            self.frequent.insert(tick, key);
+        } else {
+            self.recent.insert(tick, key);
+        }
+        self.memory_bytes += size_bytes;
+        self.resident.insert(key, Resident { document, size_bytes, tick, frequent });
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<Resident> {
+        let entry = self.resident.remove(key)?;
+        if entry.frequent {
+            self.frequent.remove(&entry.tick);
+        } else {
+            self.recent.remove(&entry.tick);
+        }
+        self.memory_bytes -= entry.size_bytes;
+        Some(entry)
+    }
+
+    fn remove_ghost(&mut self, key: &CacheKey) -> Option<Ghost> {
+        let ghost = self.ghosts.remove(key)?;
+        if ghost.frequent {
+            self.frequent_ghosts.remove(&ghost.tick);
+        } else {
+            self.recent_ghosts.remove(&ghost.tick);
+        }
+        Some(ghost)
+    }
+
+    fn pop_ghost(&mut self, frequent: bool) {
+        let ghosts = if frequent { &mut self.frequent_ghosts } else { &mut self.recent_ghosts };
+        if let Some((_, key)) = ghosts.pop_first() {
+            self.ghosts.remove(&key);
+        }
+    }
+
+    /// Evict the least recent document of the side over its share, remembering its key
+    ///
+    /// `loading_frequent_ghost` breaks the tie when `recent` is exactly at its
+    /// target, as ARC does for a key found among the `frequent` ghosts.
+    fn replace(&mut self, loading_frequent_ghost: bool, capacity: usize) -> bool {
+        let from_recent = !self.recent.is_empty()
+            && (self.recent.len() > self.recency_target
+                || (loading_frequent_ghost && self.recent.len() == self.recency_target)
+                || self.frequent.is_empty());
+        let list = if from_recent { &mut self.recent } else { &mut self.frequent };
+        let Some((_, key)) = list.pop_first() else {
+            return false;
+        };
+        if let Some(entry) = self.resident.remove(&key) {
+            self.memory_bytes -= entry.size_bytes;
+        }
+
+        let tick = self.tick();
+        if from_recent {
+            self.recent_ghosts.insert(tick, key);
+        } else {
+            self.frequent_ghosts.insert(tick, key);
+        }
+        self.ghosts.insert(key, Ghost { tick, frequent: !from_recent });
+        // Evictions forced by the memory cap can outrun ARC's own bound on remembered keys
+        while self.ghosts.len() > capacity {
+            let frequent = self.frequent_ghosts.len() > self.recent_ghosts.len();
+            self.pop_ghost(frequent);
+        }
+        true
+    }
+}
+
+/// ARC cache of stored documents, shared by every database of an engine
+pub struct DocumentCache {
+    config: DocumentCacheConfig,
+    state: Mutex<ArcState>,
+    metrics: DocumentCacheMetrics,
+    next_namespace: AtomicU64,
+}
+
+impl DocumentCache {
+    pub fn new(config: DocumentCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(ArcState::default()),
+            metrics: DocumentCacheMetrics::default(),
+            next_namespace: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && self.config.max_documents > 0
+    }
+
+    pub fn config(&self) -> &DocumentCacheConfig {
+        &self.config
+    }
+
+    /// Put the cache in front of `engine`, or return it as it is when the cache is off
+    pub fn wrap(self: &Arc<Self>, engine: Arc<dyn StorageEngine>) -> Arc<dyn StorageEngine> {
+        if !self.is_enabled() {
+            return engine;
+        }
+        Arc::new(CachedEngine {
+            inner: engine,
+            cache: self.clone(),
+            namespace: self.next_namespace.fetch_add(1, Ordering::Relaxed),
+        })
+    }
+
+    /// The cached document, counting a hit or a miss
+    pub fn get(&self, namespace: u64, id: &DocumentId) -> Option<Arc<StoredDocument>> {
+        let key = (namespace, *id);
+        let mut state = self.state.lock();
+        let Some(entry) = state.resident.get(&key) else {
+            self.metrics.record_miss();
+            return None;
+        };
+        let document = entry.document.clone();
+        state.touch(&key);
+        self.metrics.record_hit();
+        Some(document)
+    }
+
+    /// Write epoch of a document; read it before going to the storage engine
+    pub fn epoch(&self, namespace: u64, id: &DocumentId) -> u64 {
+        self.state.lock().epochs[stripe(namespace, id)]
+    }
+
+    /// Cache a document read at `epoch`, unless it was written since
+    pub fn insert(&self, namespace: u64, id: DocumentId, epoch: u64, document: Arc<StoredDocument>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let size_bytes = document.as_bytes().len() + ENTRY_OVERHEAD_BYTES;
+        if size_bytes > self.config.max_memory_bytes {
+            return;
+        }
+
+        let key = (namespace, id);
+        let capacity = self.config.max_documents;
+        let mut state = self.state.lock();
+        if state.epochs[stripe(namespace, &id)] != epoch {
+            return;
+        }
+        if state.remove(&key).is_some() {
+            // Loaded twice by concurrent misses; the later copy counts as a second read
+            state.insert(key, document, size_bytes, true);
+            return;
+        }
+
+        let frequent = match state.ghosts.get(&key).map(|ghost| ghost.frequent) {
+            // Evicted from `recent` too soon: give it more room
+            Some(false) => {
+                let delta = (state.frequent_ghosts.len() / state.recent_ghosts.len()).max(1);
+                state.recency_target = (state.recency_target + delta).min(capacity);
+                true
+            }
+            // Evicted from `frequent` too soon: give `recent` less room
+            Some(true) => {
+                let delta = (state.recent_ghosts.len() / state.frequent_ghosts.len()).max(1);
+                state.recency_target = state.recency_target.saturating_sub(delta);
+                true
+            }
+            None => {
+                if state.recent.len() + state.recent_ghosts.len() >= capacity {
+                    if state.recent.len() < capacity {
+                        state.pop_ghost(false);
+                    } else if let Some((_, oldest)) = state.recent.pop_first() {
+                        // `recent` alone fills the cache, so its oldest key is not worth remembering
+                        if let Some(entry) = state.resident.remove(&oldest) {
+                            state.memory_bytes -= entry.size_bytes;
+                            self.metrics.record_eviction();
+                        }
+                    }
+                } else if state.resident.len() + state.ghosts.len() >= 2 * capacity {
+                    state.pop_ghost(true);
+                }
+                false
+            }
+        };
+        let loading_frequent_ghost = match state.remove_ghost(&key) {
+            Some(ghost) => {
+                self.metrics.record_ghost_hit();
+                ghost.frequent
+            }
+            None => false,
+        };
+
+        while state.resident.len() >= capacity || state.memory_bytes + size_bytes > self.config.max_memory_bytes {
+            if !state.replace(loading_frequent_ghost, capacity) {
+                break;
+            }
+            self.metrics.record_eviction();
+        }
+        state.insert(key, document, size_bytes, frequent);
+    }
+
+    /// Drop a document that is being written or deleted
+    ///
+    /// Call it once the storage engine has the write, so reads that started
+    /// before it are not cached either.
+    pub fn invalidate(&self, namespace: u64, id: &DocumentId) {
+        let mut state = self.state.lock();
+        state.epochs[stripe(namespace, id)] += 1;
+        if state.remove(&(namespace, *id)).is_some() {
+            self.metrics.record_invalidation();
+        }
+    }
+
+    /// Drop every document of an engine, e.g. when its database is dropped
+    pub fn invalidate_namespace(&self, namespace: u64) {
+        let mut state = self.state.lock();
+        let keys: Vec<CacheKey> = state.resident.keys().filter(|key| key.0 == namespace).copied().collect();
+        for key in &keys {
+            state.remove(key);
+            self.metrics.record_invalidation();
+        }
+        let ghosts: Vec<CacheKey> = state.ghosts.keys().filter(|key| key.0 == namespace).copied().collect();
+        for key in &ghosts {
+            state.remove_ghost(key);
+        }
+    }
+
+    pub fn stats(&self) -> DocumentCacheStats {
+        let state = self.state.lock();
+        DocumentCacheStats {
+            enabled: self.is_enabled(),
+            entries: state.resident.len(),
+            memory_bytes: state.memory_bytes,
+            capacity: self.config.max_documents,
+            recent_entries: state.recent.len(),
+            frequent_entries: state.frequent.len(),
+            recency_target: state.recency_target,
+            ..self.metrics.stats()
+        }
+    }
+}
+
+impl Default for DocumentCache {
+    fn default() -> Self {
+        Self::new(DocumentCacheConfig::default())
+    }
+}
+
+fn stripe(namespace: u64, id: &DocumentId) -> usize {
+    // The low bits of v7 ids are random
+    ((id.as_u128() as u64) ^ namespace) as usize % EPOCH_STRIPES
+}
+
+/// Storage engine reading through the document cache
+///
+/// Scans go straight to the engine and leave the cache alone. Pages are
+/// cached after they are opened, so with encryption at rest the cache holds
+/// plaintext in memory, as the engines' own read paths do.
+struct CachedEngine {
+    inner: Arc<dyn StorageEngine>,
+    cache: Arc<DocumentCache>,
+    namespace: u64,
+}
+
+impl CachedEngine {
+    async fn read(&self, id: &DocumentId) -> Result<Option<Arc<StoredDocument>>> {
+        if let Some(document) = self.cache.get(self.namespace, id) {
+            return Ok(Some(document));
+        }
+        let epoch = self.cache.epoch(self.namespace, id);
+        let Some(document) = self.inner.get_stored(id).await? else {
+            return Ok(None);
+        };
+        let document = Arc::new(document);
+        self.cache.insert(self.namespace, *id, epoch, document.clone());
+        Ok(Some(document))
+    }
+}
+
+#[async_trait]
+impl StorageEngine for CachedEngine {
+    async fn get(&self, id: &DocumentId) -> Result<Option<Document>> {
+        Ok(self.read(id).await?.map(|document| document.to_document()))
+    }
+
+    async fn put(&self, id: DocumentId, doc: Document) -> Result<()> {
+        let result = self.inner.put(id, doc).await;
+        self.cache.invalidate(self.namespace, &id);
+        result
+    }
+
+    async fn delete(&self, id: &DocumentId) -> Result<bool> {
+        let result = self.inner.delete(id).await;
+        self.cache.invalidate(self.namespace, id);
+        result
+    }
+
+    async fn get_stored(&self, id: &DocumentId) -> Result<Option<StoredDocument>> {
+        Ok(self.read(id).await?.map(|document| StoredDocument::clone(&document)))
+    }
+
+    async fn scan(&self, start: Option<DocumentId>, limit: usize) -> Result<Vec<(DocumentId, Document)>> {
+        self.inner.scan(start, limit).await
+    }
+
+    async fn reseal(&self, start: Option<DocumentId>, limit: usize) -> Result<ResealBatch> {
+        self.inner.reseal(start, limit).await
+    }
+
+    async fn destroy(&self) -> Result<()> {
+        let result = self.inner.destroy().await;
+        self.cache.invalidate_namespace(self.namespace);
+        result
+    }
+}
+
+impl Drop for CachedEngine {
+    fn drop(&mut self) {
+        self.cache.invalidate_namespace(self.namespace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::RwLock;
+
+    #[derive(Default)]
+    struct CountingEngine {
+        documents: RwLock<BTreeMap<DocumentId, Document>>,
+        reads: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl StorageEngine for CountingEngine {
+        async fn get(&self, id: &DocumentId) -> Result<Option<Document>> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            Ok(self.documents.read().await.get(id).cloned())
+        }
+
+        async fn put(&self, id: DocumentId, doc: Document) -> Result<()> {
+            self.documents.write().await.insert(id, doc);
+            Ok(())
+        }
+
+        async fn delete(&self, id: &DocumentId) -> Result<bool> {
+            Ok(self.documents.write().await.remove(id).is_some())
+        }
+
+        async fn scan(&self, start: Option<DocumentId>, limit: usize) -> Result<Vec<(DocumentId, Document)>> {
+            let documents = self.documents.read().await;
+            Ok(documents
+                .range(start.unwrap_or(uuid::Uuid::nil())..)
+                .take(limit)
+                .map(|(id, doc)| (*id, doc.clone()))
+                .collect())
+        }
+    }
+
+    fn document(id: DocumentId, name: &str) -> Document {
+        let mut fields = HashMap::new();
+        fields.insert("name".to_string(), Value::String(name.to_string()));
+        Document {
+            id,
+            fields,
+            version: 0,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn cached(config: DocumentCacheConfig) -> (Arc<DocumentCache>, Arc<CountingEngine>, Arc<dyn StorageEngine>) {
+        let cache = Arc::new(DocumentCache::new(config));
+        let backend = Arc::new(CountingEngine::default());
+        let engine = cache.wrap(backend.clone());
+        (cache, backend, engine)
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_cached_until_written() {
+        let (cache, backend, engine) = cached(DocumentCacheConfig::default());
+        let id = DocumentId::now_v7();
+        engine.put(id, document(id, "first")).await.unwrap();
+
+        engine.get(&id).await.unwrap().unwrap();
+        let doc = engine.get(&id).await.unwrap().unwrap();
+        assert_eq!(doc.fields["name"], Value::String("first".to_string()));
+        assert_eq!(backend.reads.load(Ordering::Relaxed), 1);
+
+        engine.put(id, document(id, "second")).await.unwrap();
+        let doc = engine.get_stored(&id).await.unwrap().unwrap().to_document();
+        assert_eq!(doc.fields["name"], Value::String("second".to_string()));
+        assert_eq!(backend.reads.load(Ordering::Relaxed), 2);
+
+        engine.delete(&id).await.unwrap();
+        assert!(engine.get(&id).await.unwrap().is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.invalidations), (1, 3, 2));
+    }
+
+    #[tokio::test]
+    async fn test_frequent_documents_survive_a_scan() {
+        let (cache, backend, engine) = cached(DocumentCacheConfig {
+            max_documents: 4,
+            ..DocumentCacheConfig::default()
+        });
+        let ids: Vec<DocumentId> = (0..20).map(|_| DocumentId::now_v7()).collect();
+        for id in &ids {
+            engine.put(*id, document(*id, "doc")).await.unwrap();
+        }
+
+        // Two hot documents, then a one-off pass over everything else, twice
+        for _ in 0..2 {
+            for id in &ids[..2] {
+                engine.get(id).await.unwrap();
+                engine.get(id).await.unwrap();
+            }
+            for id in &ids[2..] {
+                engine.get(id).await.unwrap();
+            }
+        }
+        let reads = backend.reads.load(Ordering::Relaxed);
+        for id in &ids[..2] {
+            engine.get(id).await.unwrap();
+        }
+        assert_eq!(backend.reads.load(Ordering::Relaxed), reads);
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 4);
+        assert_eq!(stats.frequent_entries, 2);
+        assert!(stats.evictions > 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_racing_a_write_is_not_cached() {
+        let (cache, _, engine) = cached(DocumentCacheConfig::default());
+        let id = DocumentId::now_v7();
+        let stale = Arc::new(StoredDocument::from_document(&document(id, "stale")).unwrap());
+
+        let epoch = cache.epoch(0, &id);
+        engine.put(id, document(id, "fresh")).await.unwrap();
+        cache.insert(0, id, epoch, stale);
+        assert!(cache.get(0, &id).is_none());
+
+        let doc = engine.get(&id).await.unwrap().unwrap();
+        assert_eq!(doc.fields["name"], Value::String("fresh".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_engine_leaves_the_cache() {
+        let (cache, _, engine) = cached(DocumentCacheConfig::default());
+        let id = DocumentId::now_v7();
+        engine.put(id, document(id, "doc")).await.unwrap();
+        engine.get(&id).await.unwrap();
+        assert_eq!(cache.stats().entries, 1);
+
+        drop(engine);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -0,0 +1,407 @@
+// ===========================================
+// Largetable - Next-Generation NoSQL Database
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Built to outperform MongoDB with Rust's power.
+// ===========================================
+
+//! Encryption at rest
+//!
+//! Every page an engine writes to disk (one serialized document in the LSM
+//! and B-tree engines) is sealed with AES-256-GCM under the active data key,
+//! with the record key as associated data so a page cannot be moved under
+//! another record. Data keys come from a [`KeyProvider`]; the bundled
+//! [`LocalKeyProvider`] keeps them in a keyring file wrapped by a master key,
+//! the way a KMS wraps data keys with a key that never leaves it.
+//!
+//! Each page names the key that sealed it, so rotation is online: new writes
+//! switch to the new key at once while [`rotate_storage`] reseals older pages
+//! in batches, and reads keep working throughout. Pages written before
+//! encryption was enabled are read as they are and sealed by the next rotation.
+
+use crate::storage::StorageEngine;
+use crate::{DocumentId, LargetableError, Result};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Length of data and master keys
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+/// Marks a sealed page, followed by the key id and nonce
+const PAGE_MAGIC: &[u8; 4] = b"LTE1";
+const HEADER_LEN: usize = PAGE_MAGIC.len() + 4 + NONCE_LEN;
+
+/// A 256-bit AES key; its bytes are never printed
+#[derive(Clone, PartialEq, Eq)]
+pub struct DataKey([u8; KEY_LEN]);
+
+impl DataKey {
+    pub fn generate() -> Self {
+        let mut key = [0u8; KEY_LEN];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self(key)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        <[u8; KEY_LEN]>::try_from(bytes)
+            .map(Self)
+            .map_err(|_| LargetableError::Config(format!("Encryption keys must be {} bytes, got {}", KEY_LEN, bytes.len())))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0.into())
+    }
+}
+
+impl fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DataKey(..)")
+    }
+}
+
+/// Read a base64 encoded master key from `path`
+pub fn load_master_key(path: impl AsRef<Path>) -> Result<DataKey> {
+    let path = path.as_ref();
+    let encoded = std::fs::read_to_string(path)
+        .map_err(|e| LargetableError::Config(format!("Failed to read master key {}: {}", path.display(), e)))?;
+    let bytes = BASE64
+        .decode(encoded.trim())
+        .map_err(|e| LargetableError::Config(format!("Invalid base64 in master key {}: {}", path.display(), e)))?;
+    DataKey::from_bytes(&bytes)
+}
+
+/// Source of data keys, in the manner of a KMS
+///
+/// Keys are numbered from 1. The active key seals new pages; older keys stay
+/// available so the pages they sealed can be read until rotation has
+/// resealed them.
+pub trait KeyProvider: Send + Sync {
+    /// Id of the key new pages are sealed with
+    fn active_key_id(&self) -> u32;
+    /// Key material for `id`
+    fn data_key(&self, id: u32) -> Result<DataKey>;
+    /// Create a key and make it active, returning its id
+    fn rotate(&self) -> Result<u32>;
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Keyring {
+    active: u32,
+    /// Data keys wrapped by the master key, as base64 of nonce and ciphertext
+    keys: BTreeMap<u32, String>,
+}
+
+/// Key provider keeping data keys in a keyring file, wrapped by a master key
+/// that should live outside the data directory
+pub struct LocalKeyProvider {
+    path: Option<PathBuf>,
+    master: Aes256Gcm,
+    keyring: RwLock<Keyring>,
+}
+
+impl LocalKeyProvider {
+    /// Provider whose keyring lives only in memory, starting with one key
+    pub fn in_memory(master: &DataKey) -> Result<Self> {
+        let provider = Self {
+            path: None,
+            master: master.cipher(),
+            keyring: RwLock::new(Keyring::default()),
+        };
+        provider.rotate()?;
+        Ok(provider)
+    }
+
+    /// Provider backed by a keyring file, created with a first key if it does not exist yet
+    pub fn open(path: impl Into<PathBuf>, master: &DataKey) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            let provider = Self { path: Some(path), ..Self::in_memory(master)? };
+            provider.persist(&provider.keyring.read())?;
+            info!("Created keyring {:?}", provider.path);
+            return Ok(provider);
+        }
+
+        let keyring: Keyring = serde_json::from_slice(&std::fs::read(&path)?)?;
+        info!("Loaded {} data keys from {}", keyring.keys.len(), path.display());
+        let provider = Self {
+            path: Some(path),
+            master: master.cipher(),
+            keyring: RwLock::new(keyring),
+        };
+        // Unwrapping the active key now reports a wrong master key at startup rather than on first read
+        provider.data_key(provider.active_key_id())?;
+        Ok(provider)
+    }
+
+    fn wrap(&self, id: u32, key: &DataKey) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let wrapped = self
+            .master
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: key.as_bytes(), aad: &id.to_be_bytes() })
+            .map_err(|_| LargetableError::Storage(format!("Failed to wrap data key {}", id)))?;
+        Ok(BASE64.encode([nonce.as_slice(), &wrapped].concat()))
+    }
+
+    fn unwrap(&self, id: u32, wrapped: &str) -> Result<DataKey> {
+        let unwrap_error = || LargetableError::Config(format!("Cannot unwrap data key {}: wrong master key or corrupt keyring", id));
+        let wrapped = BASE64.decode(wrapped).map_err(|_| unwrap_error())?;
+        if wrapped.len() < NONCE_LEN {
+            return Err(unwrap_error());
+        }
+        let (nonce, ciphertext) = wrapped.split_at(NONCE_LEN);
+        let key = self
+            .master
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &id.to_be_bytes() })
+            .map_err(|_| unwrap_error())?;
+        DataKey::from_bytes(&key)
+    }
+
+    /// Replace the keyring file through a temporary one, since losing it loses the data
+    fn persist(&self, keyring: &Keyring) -> Result<()> {
+        if let Some(path) = &self.path {
+            let temporary = path.with_extension("tmp");
+            std::fs::write(&temporary, serde_json::to_vec_pretty(keyring)?)?;
+            std::fs::rename(&temporary, path)?;
+        }
+        Ok(())
+    }
+}
+
+impl KeyProvider for LocalKeyProvider {
+    fn active_key_id(&self) -> u32 {
+        self.keyring.read().active
+    }
+
+    fn data_key(&self, id: u32) -> Result<DataKey> {
+        let keyring = self.keyring.read();
+        let wrapped = keyring
+            .keys
+            .get(&id)
+            .ok_or_else(|| LargetableError::Storage(format!("Unknown data key {}", id)))?;
+        self.unwrap(id, wrapped)
+    }
+
+    fn rotate(&self) -> Result<u32> {
+        let mut keyring = self.keyring.write();
+        let previous = keyring.active;
+        let id = previous + 1;
+        let wrapped = self.wrap(id, &DataKey::generate())?;
+        keyring.keys.insert(id, wrapped);
+        keyring.active = id;
+
+        // A key that never reached the keyring file must not seal anything
+        if let Err(e) = self.persist(&keyring) {
+            keyring.keys.remove(&id);
+            keyring.active = previous;
+            return Err(e);
+        }
+        Ok(id)
+    }
+}
+
+/// Seals and opens pages with keys from a [`KeyProvider`]
+pub struct PageCipher {
+    provider: Arc<dyn KeyProvider>,
+    /// Unwrapped keys by id, so the provider is asked once per key
+    ciphers: RwLock<HashMap<u32, Arc<Aes256Gcm>>>,
+}
+
+impl PageCipher {
+    pub fn new(provider: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            provider,
+            ciphers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn active_key_id(&self) -> u32 {
+        self.provider.active_key_id()
+    }
+
+    fn cipher(&self, id: u32) -> Result<Arc<Aes256Gcm>> {
+        if let Some(cipher) = self.ciphers.read().get(&id) {
+            return Ok(cipher.clone());
+        }
+        let cipher = Arc::new(self.provider.data_key(id)?.cipher());
+        self.ciphers.write().insert(id, cipher.clone());
+        Ok(cipher)
+    }
+
+    /// Encrypt `page` under the active key, bound to the record `key` it is stored under
+    pub fn seal(&self, key: &[u8], page: &[u8]) -> Result<Vec<u8>> {
+        let id = self.active_key_id();
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher(id)?
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: page, aad: key })
+            .map_err(|_| LargetableError::Storage("Failed to encrypt page".to_string()))?;
+
+        let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        sealed.extend_from_slice(PAGE_MAGIC);
+        sealed.extend_from_slice(&id.to_be_bytes());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a page stored under the record `key`; plaintext pages are returned as they are
+    pub fn open(&self, key: &[u8], page: &[u8]) -> Result<Vec<u8>> {
+        let Some(id) = sealed_key_id(page) else {
+            return Ok(page.to_vec());
+        };
+        let nonce = &page[PAGE_MAGIC.len() + 4..HEADER_LEN];
+        self.cipher(id)?
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: &page[HEADER_LEN..], aad: key })
+            .map_err(|_| LargetableError::Storage(format!("Page sealed with data key {} failed authentication", id)))
+    }
+
+    /// `page` sealed under the active key, or `None` when it already is
+    pub fn reseal(&self, key: &[u8], page: &[u8]) -> Result<Option<Vec<u8>>> {
+        if sealed_key_id(page) == Some(self.active_key_id()) {
+            return Ok(None);
+        }
+        self.seal(key, &self.open(key, page)?).map(Some)
+    }
+
+    /// Activate a new data key for the pages written from now on
+    pub fn rotate(&self) -> Result<u32> {
+        let id = self.provider.rotate()?;
+        info!("Rotated to data key {}", id);
+        Ok(id)
+    }
+}
+
+/// Id of the key that sealed `page`, or `None` for a plaintext page
+pub fn sealed_key_id(page: &[u8]) -> Option<u32> {
+    if page.len() < HEADER_LEN + TAG_LEN || !page.starts_with(PAGE_MAGIC) {
+        return None;
+    }
+    let id = &page[PAGE_MAGIC.len()..PAGE_MAGIC.len() + 4];
+    Some(u32::from_be_bytes([id[0], id[1], id[2], id[3]]))
+}
+
+/// Prepare a serialized document for disk, sealing it when encryption is on
+pub fn seal_page(cipher: Option<&PageCipher>, key: &[u8], page: Vec<u8>) -> Result<Vec<u8>> {
+    match cipher {
+        Some(cipher) => cipher.seal(key, &page),
+        None => Ok(page),
+    }
+}
+
+/// The serialized document in a page read from disk
+pub fn open_page<'a>(cipher: Option<&PageCipher>, key: &[u8], page: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    match cipher {
+        Some(cipher) => cipher.open(key, page).map(Cow::Owned),
+        None if sealed_key_id(page).is_some() => Err(LargetableError::Storage(
+            "Page is encrypted but encryption at rest is disabled".to_string(),
+        )),
+        None => Ok(Cow::Borrowed(page)),
+    }
+}
+
+/// Outcome of resealing one batch of pages
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResealBatch {
+    /// Pages rewritten under the active key
+    pub resealed: usize,
+    /// Record the next batch starts at, `None` once the whole engine is covered
+    pub next: Option<DocumentId>,
+}
+
+/// Reseal every page of `engine` under the active key, a batch at a time so
+/// that writes interleave with the pass
+pub async fn rotate_storage(engine: &dyn StorageEngine, batch_size: usize) -> Result<u64> {
+    let mut start = None;
+    let mut resealed = 0u64;
+    loop {
+        let batch = engine.reseal(start, batch_size.max(1)).await?;
+        resealed += batch.resealed as u64;
+        match batch.next {
+            Some(next) => start = Some(next),
+            None => {
+                debug!("Resealed {} pages", resealed);
+                return Ok(resealed);
+            }
+        }
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Active key and progress of the latest rotation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyRotationStatus {
+    pub active_key_id: u32,
+    /// Pages sealed under older keys are being resealed
+    pub in_progress: bool,
+    /// Pages resealed by the current or latest pass
+    pub pages_resealed: u64,
+    pub last_completed_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_binds_page_to_record() {
+        let cipher = PageCipher::new(Arc::new(LocalKeyProvider::in_memory(&DataKey::generate()).unwrap()));
+        let page = b"serialized document".to_vec();
+
+        let sealed = cipher.seal(b"record-1", &page).unwrap();
+        assert_eq!(sealed_key_id(&sealed), Some(1));
+        assert!(!sealed.windows(page.len()).any(|window| window == page.as_slice()));
+        assert_eq!(cipher.open(b"record-1", &sealed).unwrap(), page);
+        assert!(cipher.open(b"record-2", &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher.open(b"record-1", &tampered).is_err());
+
+        // Plaintext pages pass through, but a sealed page never does without a cipher
+        assert_eq!(cipher.open(b"record-1", &page).unwrap(), page);
+        assert!(open_page(None, b"record-1", &sealed).is_err());
+        assert_eq!(open_page(None, b"record-1", &page).unwrap(), page.as_slice());
+    }
+
+    #[test]
+    fn test_rotation_keeps_old_pages_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keyring.json");
+        let master = DataKey::generate();
+
+        let cipher = PageCipher::new(Arc::new(LocalKeyProvider::open(&path, &master).unwrap()));
+        let old = cipher.seal(b"record", b"before rotation").unwrap();
+        assert_eq!(cipher.rotate().unwrap(), 2);
+        assert_eq!(cipher.open(b"record", &old).unwrap(), b"before rotation");
+
+        let resealed = cipher.reseal(b"record", &old).unwrap().unwrap();
+        assert_eq!(sealed_key_id(&resealed), Some(2));
+        assert_eq!(cipher.reseal(b"record", &resealed).unwrap(), None);
+        assert_eq!(sealed_key_id(&cipher.reseal(b"record", b"plaintext page").unwrap().unwrap()), Some(2));
+
+        // Both keys survive a restart, and only the right master key unwraps them
+        let reopened = PageCipher::new(Arc::new(LocalKeyProvider::open(&path, &master).unwrap()));
+        assert_eq!(reopened.active_key_id(), 2);
+        assert_eq!(reopened.open(b"record", &old).unwrap(), b"before rotation");
+        assert!(LocalKeyProvider::open(&path, &DataKey::generate()).is_err());
+    }
+}
@@ -6,6 +6,9 @@
 
 //! B-Tree storage engine - read-optimized
 
+use crate::document::StoredDocument;
+use crate::storage::encryption::{open_page, seal_page, PageCipher, ResealBatch};
+use crate::storage::engines::RemoveOnDrop;
 use crate::storage::StorageEngine;
 use crate::{Result, DocumentId, Document, LargetableError};
 use async_trait::async_trait;
@@ -21,6 +24,10 @@ const DOCUMENTS_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("doc
 /// B-Tree storage engine using Redb
 pub struct BTreeEngine {
     db: Arc<RwLock<Database>>,
+    /// Seals values on disk when encryption at rest is on
+    cipher: Option<Arc<PageCipher>>,
+    /// After `db`, so the database is closed before its file is removed
+    file: RemoveOnDrop,
 }
 
 impl BTreeEngine {
@@ -31,16 +38,24 @@ impl BTreeEngine {
 
     /// Create B-Tree engine with custom data path
     pub fn with_path<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let db = Database::create(path)
+        let db = Database::create(path.as_ref())
             .map_err(|e| LargetableError::Storage(format!("Failed to create Redb database: {}", e)))?;
         
         info!("B-Tree Engine initialized with Redb backend");
         
         Ok(Self {
             db: Arc::new(RwLock::new(db)),
+            cipher: None,
+            file: RemoveOnDrop::new(path.as_ref()),
         })
     }
 
+    /// Encrypt values at rest with `cipher`, or store them as they are when `None`
+    pub fn with_cipher(mut self, cipher: Option<Arc<PageCipher>>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
     /// Serialize document to bytes using zero-copy serialization
     fn serialize_document(&self, doc: &Document) -> Result<Vec<u8>> {
         to_bytes::<_, 1024>(doc)
@@ -85,7 +100,8 @@ impl StorageEngine for BTreeEngine {
         match table.get(&key) {
             Ok(Some(data)) => {
                 debug!("Retrieved document with ID: {}", id);
-                self.deserialize_document(&data.value()).map(Some)
+                let page = open_page(self.cipher.as_deref(), &key, data.value())?;
+                self.deserialize_document(&page).map(Some)
             }
             Ok(None) => {
                 debug!("Document not found with ID: {}", id);
@@ -98,10 +114,32 @@ impl StorageEngine for BTreeEngine {
         }
     }
     
+    async fn get_stored(&self, id: &DocumentId) -> Result<Option<StoredDocument>> {
+        let db = self.db.read().await;
+        let key = self.id_to_bytes(id);
+
+        let read_txn = db.begin_read()
+            .map_err(|e| LargetableError::Storage(format!("Failed to begin read transaction: {}", e)))?;
+        let table = read_txn.open_table(DOCUMENTS_TABLE)
+            .map_err(|e| LargetableError::Storage(format!("Failed to open table: {}", e)))?;
+
+        match table.get(&key) {
+            Ok(Some(data)) => {
+                let page = open_page(self.cipher.as_deref(), &key, data.value())?;
+                StoredDocument::from_page(&page).map(Some)
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                error!("Failed to get document {}: {}", id, e);
+                Err(LargetableError::Storage(format!("Get operation failed: {}", e)))
+            }
+        }
+    }
+
     async fn put(&self, id: DocumentId, doc: Document) -> Result<()> {
         let db = self.db.write().await;
         let key = self.id_to_bytes(&id);
-        let value = self.serialize_document(&doc)?;
+        let value = seal_page(self.cipher.as_deref(), &key, self.serialize_document(&doc)?)?;
         
         let write_txn = db.begin_write()
             .map_err(|e| LargetableError::Storage(format!("Failed to begin write transaction: {}", e)))?;
@@ -169,7 +207,7 @@ impl StorageEngine for BTreeEngine {
             match item {
                 Ok((key, value)) => {
                     let id = self.bytes_to_id(&key.value())?;
-                    let doc = self.deserialize_document(&value.value())?;
+                    let doc = self.deserialize_document(&open_page(self.cipher.as_deref(), key.value(), value.value())?)?;
                     results.push((id, doc));
                     count += 1;
                 }
@@ -183,4 +221,65 @@ impl StorageEngine for BTreeEngine {
         debug!("Scanned {} documents", results.len());
         Ok(results)
     }
-}
\ No newline at end of file
+    
+    async fn reseal(&self, start: Option<DocumentId>, limit: usize) -> Result<ResealBatch> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(ResealBatch::default());
+        };
+        // Holding the write lock keeps a concurrent put from being overwritten by its old value
+        let db = self.db.write().await;
+        let mut result = ResealBatch::default();
+        
+        let write_txn = db.begin_write()
+            .map_err(|e| LargetableError::Storage(format!("Failed to begin write transaction: {}", e)))?;
+        
+        {
+            let mut table = write_txn.open_table(DOCUMENTS_TABLE)
+                .map_err(|e| LargetableError::Storage(format!("Failed to open table: {}", e)))?;
+            
+            let start_key = start.map(|id| self.id_to_bytes(&id)).unwrap_or_default();
+            let mut pages = Vec::new();
+            
+            for (scanned, item) in table.range(start_key.as_slice()..)
+                .map_err(|e| LargetableError::Storage(format!("Reseal scan failed: {}", e)))?
+                .enumerate()
+            {
+                let (key, value) = item
+                    .map_err(|e| LargetableError::Storage(format!("Reseal scan failed: {}", e)))?;
+                if scanned == limit {
+                    result.next = Some(self.bytes_to_id(key.value())?);
+                    break;
+                }
+                if let Some(page) = cipher.reseal(key.value(), value.value())? {
+                    pages.push((key.value().to_vec(), page));
+                }
+            }
+            
+            for (key, page) in &pages {
+                table.insert(key.as_slice(), page.as_slice())
+                    .map_err(|e| LargetableError::Storage(format!("Failed to reseal document: {}", e)))?;
+            }
+            result.resealed = pages.len();
+        }
+        
+        write_txn.commit()
+            .map_err(|e| LargetableError::Storage(format!("Failed to commit transaction: {}", e)))?;
+        
+        debug!("Resealed {} documents", result.resealed);
+        Ok(result)
+    }
+
+    async fn destroy(&self) -> Result<()> {
+        let db = self.db.write().await;
+        let write_txn = db.begin_write()
+            .map_err(|e| LargetableError::Storage(format!("Failed to begin write transaction: {}", e)))?;
+        write_txn.delete_table(DOCUMENTS_TABLE)
+            .map_err(|e| LargetableError::Storage(format!("Failed to delete table: {}", e)))?;
+        write_txn.commit()
+            .map_err(|e| LargetableError::Storage(format!("Failed to commit transaction: {}", e)))?;
+        self.file.arm();
+        
+        info!("Destroyed B-Tree engine");
+        Ok(())
+    }
+}
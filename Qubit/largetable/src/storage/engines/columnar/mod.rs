@@ -383,4 +383,17 @@ impl StorageEngine for ColumnarEngine {
         debug!("Scanned {} documents", results.len());
         Ok(results)
     }
+    
+    async fn destroy(&self) -> Result<()> {
+        // Every write rewrites the file, so nothing else holds it open
+        match std::fs::remove_file(self.get_file_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(LargetableError::Storage(format!("Failed to remove parquet file: {}", e)))
+            }
+            _ => {
+                info!("Destroyed columnar engine at {}", self.data_path);
+                Ok(())
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -6,10 +6,13 @@
 
 //! LSM Tree storage engine - write-optimized
 
+use crate::document::StoredDocument;
+use crate::storage::encryption::{open_page, seal_page, PageCipher, ResealBatch};
+use crate::storage::engines::RemoveOnDrop;
 use crate::storage::StorageEngine;
 use crate::{Result, DocumentId, Document, LargetableError};
 use async_trait::async_trait;
-use rocksdb::{DB, Options, WriteOptions, ReadOptions, IteratorMode};
+use rocksdb::{DB, Options, WriteBatch, WriteOptions, ReadOptions, IteratorMode};
 use rkyv::{to_bytes, from_bytes};
 use std::path::Path;
 use std::sync::Arc;
@@ -21,19 +24,22 @@ pub struct LsmEngine {
     db: Arc<RwLock<DB>>,
     write_options: WriteOptions,
     read_options: ReadOptions,
+    /// Seals values on disk when encryption at rest is on
+    cipher: Option<Arc<PageCipher>>,
+    /// After `db`, so the database is closed before its directory is removed
+    directory: RemoveOnDrop,
 }
 
 impl LsmEngine {
-    /// Create a new LSM engine with RocksDB backend
-    pub fn new() -> Result<Self> {
-        Self::with_path("largetable_lsm")
-    }
-
-    /// Create LSM engine with custom data path
+    /// Open the RocksDB database in the directory `path`, creating it if missing
+    ///
+    /// Every write syncs the write-ahead log before it is acknowledged, so an
+    /// acknowledged write survives a crash or power loss.
     pub fn with_path<P: AsRef<Path>>(path: P) -> Result<Self> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+        // fdatasync is enough for the WAL; fsync would also flush file metadata
         opts.set_use_fsync(false);
         opts.set_max_background_jobs(4);
         opts.set_bytes_per_sync(1048576);
@@ -52,11 +58,11 @@ impl LsmEngine {
         // Bloom filter for point lookups
         opts.set_bloom_locality(1);
         
-        let db = DB::open(&opts, path)
+        let db = DB::open(&opts, path.as_ref())
             .map_err(|e| LargetableError::Storage(format!("Failed to open RocksDB: {}", e)))?;
         
         let mut write_opts = WriteOptions::default();
-        write_opts.set_sync(false);
+        write_opts.set_sync(true);
         write_opts.disable_wal(false);
         
         let mut read_opts = ReadOptions::default();
@@ -68,9 +74,17 @@ impl LsmEngine {
             db: Arc::new(RwLock::new(db)),
             write_options: write_opts,
             read_options: read_opts,
+            cipher: None,
+            directory: RemoveOnDrop::new(path.as_ref()),
         })
     }
 
+    /// Encrypt values at rest with `cipher`, or store them as they are when `None`
+    pub fn with_cipher(mut self, cipher: Option<Arc<PageCipher>>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
     /// Serialize document to bytes using zero-copy serialization
     fn serialize_document(&self, doc: &Document) -> Result<Vec<u8>> {
         to_bytes::<_, 1024>(doc)
@@ -109,7 +123,8 @@ impl StorageEngine for LsmEngine {
         match db.get_opt(&key, &self.read_options) {
             Ok(Some(data)) => {
                 debug!("Retrieved document with ID: {}", id);
-                self.deserialize_document(&data).map(Some)
+                let page = open_page(self.cipher.as_deref(), &key, &data)?;
+                self.deserialize_document(&page).map(Some)
             }
             Ok(None) => {
                 debug!("Document not found with ID: {}", id);
@@ -122,10 +137,27 @@ impl StorageEngine for LsmEngine {
         }
     }
     
+    async fn get_stored(&self, id: &DocumentId) -> Result<Option<StoredDocument>> {
+        let db = self.db.read().await;
+        let key = self.id_to_bytes(id);
+
+        match db.get_pinned_opt(&key, &self.read_options) {
+            Ok(Some(data)) => {
+                let page = open_page(self.cipher.as_deref(), &key, &data)?;
+                StoredDocument::from_page(&page).map(Some)
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                error!("Failed to get document {}: {}", id, e);
+                Err(LargetableError::Storage(format!("Get operation failed: {}", e)))
+            }
+        }
+    }
+
     async fn put(&self, id: DocumentId, doc: Document) -> Result<()> {
         let db = self.db.write().await;
         let key = self.id_to_bytes(&id);
-        let value = self.serialize_document(&doc)?;
+        let value = seal_page(self.cipher.as_deref(), &key, self.serialize_document(&doc)?)?;
         
         match db.put_opt(&key, &value, &self.write_options) {
             Ok(_) => {
@@ -176,7 +208,7 @@ impl StorageEngine for LsmEngine {
             match item {
                 Ok((key, value)) => {
                     let id = self.bytes_to_id(&key)?;
-                    let doc = self.deserialize_document(&value)?;
+                    let doc = self.deserialize_document(&open_page(self.cipher.as_deref(), &key, &value)?)?;
                     results.push((id, doc));
                     count += 1;
                 }
@@ -190,4 +222,52 @@ impl StorageEngine for LsmEngine {
         debug!("Scanned {} documents", results.len());
         Ok(results)
     }
+    
+    async fn reseal(&self, start: Option<DocumentId>, limit: usize) -> Result<ResealBatch> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(ResealBatch::default());
+        };
+        // Holding the write lock keeps a concurrent put from being overwritten by its old value
+        let db = self.db.write().await;
+        let mut result = ResealBatch::default();
+        let mut batch = WriteBatch::default();
+        
+        let start_key = start.map(|id| self.id_to_bytes(&id));
+        let iter_mode = match &start_key {
+            Some(start_key) => IteratorMode::From(start_key, rocksdb::Direction::Forward),
+            None => IteratorMode::Start,
+        };
+        
+        for (scanned, item) in db.iterator_opt(iter_mode, &self.read_options).enumerate() {
+            let (key, value) = item
+                .map_err(|e| LargetableError::Storage(format!("Reseal scan failed: {}", e)))?;
+            if scanned == limit {
+                result.next = Some(self.bytes_to_id(&key)?);
+                break;
+            }
+            if let Some(page) = cipher.reseal(&key, &value)? {
+                batch.put(&key, page);
+                result.resealed += 1;
+            }
+        }
+        
+        db.write_opt(batch, &self.write_options)
+            .map_err(|e| LargetableError::Storage(format!("Reseal write failed: {}", e)))?;
+        
+        debug!("Resealed {} documents", result.resealed);
+        Ok(result)
+    }
+
+    async fn destroy(&self) -> Result<()> {
+        let db = self.db.write().await;
+        // Keys are 16-byte document IDs, all below the 17-byte end key
+        let mut batch = WriteBatch::default();
+        batch.delete_range(&[0u8; 16][..], &[0xffu8; 17][..]);
+        db.write_opt(batch, &self.write_options)
+            .map_err(|e| LargetableError::Storage(format!("Destroy failed: {}", e)))?;
+        self.directory.arm();
+        
+        info!("Destroyed LSM engine at {}", db.path().display());
+        Ok(())
+    }
 }
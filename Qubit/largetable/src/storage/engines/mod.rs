@@ -11,14 +11,89 @@ pub mod btree;
 pub mod columnar;
 pub mod graph;
 
+use crate::storage::encryption::PageCipher;
 use crate::storage::StorageEngine;
-use crate::Result;
+use crate::{LargetableError, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::warn;
 
-pub fn create_storage_engine(engine_type: crate::StorageEngine) -> Result<Box<dyn StorageEngine>> {
+/// Open an engine of `engine_type` keeping its files under `dir`, sealing
+/// what it writes with `cipher` when encryption at rest is on
+pub fn create_storage_engine(
+    engine_type: crate::StorageEngine,
+    dir: &Path,
+    cipher: Option<Arc<PageCipher>>,
+) -> Result<Box<dyn StorageEngine>> {
+    if engine_type != crate::StorageEngine::Graph {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            LargetableError::Storage(format!("Failed to create data directory {}: {}", dir.display(), e))
+        })?;
+    }
     match engine_type {
-        crate::StorageEngine::Lsm => Ok(Box::new(lsm::LsmEngine::new()?)),
-        crate::StorageEngine::BTree => Ok(Box::new(btree::BTreeEngine::new()?)),
-        crate::StorageEngine::Columnar => Ok(Box::new(columnar::ColumnarEngine::new()?)),
+        crate::StorageEngine::Lsm => Ok(Box::new(lsm::LsmEngine::with_path(dir)?.with_cipher(cipher))),
+        crate::StorageEngine::BTree => Ok(Box::new(btree::BTreeEngine::with_path(dir.join("btree.redb"))?.with_cipher(cipher))),
+        crate::StorageEngine::Columnar if cipher.is_some() => Err(LargetableError::Config(
+            "The columnar engine does not support encryption at rest".to_string(),
+        )),
+        crate::StorageEngine::Columnar => Ok(Box::new(columnar::ColumnarEngine::with_path(dir.join("columnar"))?)),
+        // Graphs are held in memory, so there is nothing at rest to encrypt
         crate::StorageEngine::Graph => Ok(Box::new(graph::GraphEngine::new()?)),
     }
 }
+
+/// `name` as one directory under the data directory
+///
+/// Database and collection names become directory names, so a name that is
+/// empty, a dot segment or holds a path separator could reach outside the
+/// data directory or into another database's files.
+pub fn directory_name(name: &str) -> Result<&str> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(LargetableError::Validation(format!(
+            "'{}' cannot be used as a database or collection name",
+            name
+        )));
+    }
+    Ok(name)
+}
+
+/// Removes an engine's file or directory when dropped, once the engine has
+/// been destroyed
+///
+/// Declared after the engine's database handle, so the handle is closed
+/// before its files go.
+pub(crate) struct RemoveOnDrop {
+    path: PathBuf,
+    armed: AtomicBool,
+}
+
+impl RemoveOnDrop {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), armed: AtomicBool::new(false) }
+    }
+
+    /// Remove the path when dropped
+    pub(crate) fn arm(&self) {
+        self.armed.store(true, Ordering::Release);
+    }
+}
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        if !self.armed.load(Ordering::Acquire) {
+            return;
+        }
+        let removed = if self.path.is_dir() {
+            std::fs::remove_dir_all(&self.path)
+        } else {
+            std::fs::remove_file(&self.path)
+        };
+        match removed {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                warn!("Failed to remove {} of a destroyed engine: {}", self.path.display(), e)
+            }
+            _ => {}
+        }
+    }
+}
@@ -10,16 +10,51 @@ pub mod engines;
 pub mod wal;
 pub mod cache;
 pub mod compression;
+pub mod encryption;
 pub mod checksum;
 pub mod hotswap;
 
+use crate::document::StoredDocument;
 use crate::{Result, DocumentId, Document};
 use async_trait::async_trait;
+use encryption::ResealBatch;
+
+/// Documents deleted per batch by the default `StorageEngine::destroy`
+const DESTROY_BATCH_SIZE: usize = 1_000;
 
 #[async_trait]
 pub trait StorageEngine: Send + Sync {
     async fn get(&self, id: &DocumentId) -> Result<Option<Document>>;
     async fn put(&self, id: DocumentId, doc: Document) -> Result<()>;
     async fn delete(&self, id: &DocumentId) -> Result<bool>;
+    /// Read a document in its stored form, without decoding its fields;
+    /// engines that keep rkyv pages return them as they are
+    async fn get_stored(&self, id: &DocumentId) -> Result<Option<StoredDocument>> {
+        self.get(id).await?.as_ref().map(StoredDocument::from_document).transpose()
+    }
+
     async fn scan(&self, start: Option<DocumentId>, limit: usize) -> Result<Vec<(DocumentId, Document)>>;
+
+    /// Reseal up to `limit` pages from `start` under the active data key;
+    /// engines that keep nothing encrypted on disk have nothing to do
+    async fn reseal(&self, _start: Option<DocumentId>, _limit: usize) -> Result<ResealBatch> {
+        Ok(ResealBatch::default())
+    }
+
+    /// Delete everything the engine holds, when what it stores is dropped
+    /// whole; nothing is written to it afterwards
+    ///
+    /// Engines with files of their own remove them once they are closed;
+    /// the others delete their documents a batch at a time.
+    async fn destroy(&self) -> Result<()> {
+        loop {
+            let documents = self.scan(None, DESTROY_BATCH_SIZE).await?;
+            if documents.is_empty() {
+                return Ok(());
+            }
+            for (id, _) in documents {
+                self.delete(&id).await?;
+            }
+        }
+    }
 }
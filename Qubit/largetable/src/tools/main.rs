@@ -7,6 +7,7 @@
 //! Largetable command-line tools
 
 use clap::{Parser, Subcommand};
+use largetable::sharding::{Balancer, ShardingMetadata};
 
 #[derive(Parser)]
 #[command(name = "largetable-tools")]
@@ -34,6 +35,11 @@ enum Commands {
         #[arg(short, long)]
         data_dir: String,
     },
+    Status {
+        /// Sharding metadata snapshot exported from the config server
+        #[arg(short, long)]
+        metadata: String,
+    },
 }
 
 #[tokio::main]
@@ -54,7 +60,54 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Commands::Repair { data_dir } => {
             println!("Repairing database in: {}", data_dir);
         }
+        Commands::Status { metadata } => {
+            print_sharding_status(&ShardingMetadata::load(metadata)?);
+        }
     }
     
     Ok(())
 }
+
+fn print_sharding_status(metadata: &ShardingMetadata) {
+    println!("Catalog version: {}", metadata.version);
+    println!("Shards:");
+    for (shard, chunks) in metadata.chunk_counts() {
+        let zones: Vec<&str> = metadata.zones.shard_zones(&shard).map(String::as_str).collect();
+        let host = metadata.shard_host(&shard).unwrap_or("-");
+        println!("  {} at {} ({} chunks) zones: [{}]", shard, host, chunks, zones.join(", "));
+    }
+
+    println!("Sharded collections:");
+    for collection in &metadata.collections {
+        println!(
+            "  {} key: {} ({:?}) chunks: {}",
+            collection.namespace,
+            collection.key.field,
+            collection.key.kind,
+            metadata.chunks_for(&collection.namespace).len()
+        );
+    }
+
+    println!("Zones:");
+    for zone in metadata.zones.zones() {
+        println!(
+            "  {} region: {} shards: [{}]",
+            zone.name,
+            zone.region.as_deref().unwrap_or("-"),
+            metadata.zones.shards_in_zone(&zone.name).join(", ")
+        );
+    }
+
+    let violations = metadata.placement_violations();
+    if violations.is_empty() {
+        println!("Placement: OK");
+    } else {
+        println!("Placement violations ({}):", violations.len());
+        for violation in &violations {
+            println!("  {}", violation);
+        }
+    }
+
+    let pending = Balancer::default().plan_round(&metadata.shards, &metadata.chunks, &metadata.zones);
+    println!("Balancer: {} migrations pending", pending.len());
+}
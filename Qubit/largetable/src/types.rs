@@ -59,18 +59,40 @@ pub enum Value {
 }
 
 /// Storage engine selection
-#[derive(Debug, Clone, Copy, SerdeSerialize, SerdeDeserialize)]
+///
+/// Serialized in lowercase; the variant names written before are still read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SerdeSerialize, SerdeDeserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StorageEngine {
-    /// LSM Tree - optimized for writes
+    /// LSM Tree - optimized for writes, persisted through RocksDB
+    #[serde(alias = "Lsm")]
     Lsm,
     /// B-Tree - optimized for reads
+    #[serde(alias = "BTree")]
     BTree,
     /// Columnar - optimized for analytics
+    #[serde(alias = "Columnar")]
     Columnar,
     /// Graph - optimized for relationships
+    #[serde(alias = "Graph")]
     Graph,
 }
 
+impl std::str::FromStr for StorageEngine {
+    type Err = crate::LargetableError;
+
+    /// Parse the engine names used in config files and `LARGETABLE_STORAGE_ENGINE`
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "lsm" => Ok(StorageEngine::Lsm),
+            "btree" => Ok(StorageEngine::BTree),
+            "columnar" => Ok(StorageEngine::Columnar),
+            "graph" => Ok(StorageEngine::Graph),
+            other => Err(crate::LargetableError::Config(format!("Unknown storage engine: {}", other))),
+        }
+    }
+}
+
 /// Index type specification
 #[derive(Debug, Clone, SerdeSerialize, SerdeDeserialize)]
 pub enum IndexType {
@@ -96,6 +118,10 @@ pub enum IndexType {
     TimeSeries {
         granularity: String,
     },
+    /// Ordered index over several fields, usable for any prefix of them
+    Compound {
+        fields: Vec<String>,
+    },
 }
 
 /// Vector similarity metrics
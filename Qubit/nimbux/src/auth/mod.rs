@@ -7,10 +7,13 @@
 
 pub mod token;
 pub mod jwt_auth;
+pub mod presign;
 
 // Re-export commonly used types
 pub use token::{
     AuthManager, AuthContext, User, AccessKey, KeyStatus, 
-    PolicyDocument, PolicyStatement, SignatureV4
+    PolicyDocument, PolicyStatement, SignatureV4, TemporaryCredentials,
+    MIN_SESSION_DURATION, MAX_SESSION_DURATION
 };
+pub use presign::{Presigner, PresignMethod, PresignRequest, PresignedUrl, SigningCredentials};
 pub use jwt_auth::{JwtAuthManager, NimbuxUser, UserRole, Permission, JwtConfig, AuthResult, TokenValidationResult};
\ No newline at end of file
@@ -7,11 +7,12 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
 use hmac::{Hmac, Mac};
 use sha2::{Sha256, Digest};
 use base64::Engine;
+use rand::RngCore;
 use uuid::Uuid;
 use tracing::{info, warn, error, debug};
 
@@ -20,6 +21,10 @@ use crate::errors::{NimbuxError, Result};
 /// HMAC type for signature verification
 type HmacSha256 = Hmac<Sha256>;
 
+/// Shortest and longest lifetime of temporary credentials
+pub const MIN_SESSION_DURATION: Duration = Duration::from_secs(60);
+pub const MAX_SESSION_DURATION: Duration = Duration::from_secs(12 * 3600);
+
 /// Access key for authentication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessKey {
@@ -29,6 +34,39 @@ pub struct AccessKey {
     pub created_at: u64,
     pub last_used: Option<u64>,
     pub status: KeyStatus,
+    /// Set for temporary credentials, which stop working at this time
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Token requests signed with temporary credentials must present
+    #[serde(default)]
+    pub session_token: Option<String>,
+    /// Narrows the owner's policies for temporary credentials; never grants more
+    #[serde(default)]
+    pub session_policy: Option<PolicyDocument>,
+}
+
+impl AccessKey {
+    /// Active and, for temporary credentials, not yet expired
+    pub fn is_usable(&self, now: u64) -> bool {
+        self.status == KeyStatus::Active && self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+
+    /// Whether a request presenting `token` may use this key: temporary
+    /// credentials need their session token, long-lived keys take none
+    pub fn accepts_session_token(&self, token: Option<&str>) -> bool {
+        self.session_token.as_deref() == token
+    }
+}
+
+/// STS-style temporary credentials handed to a client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemporaryCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: String,
+    pub user_id: String,
+    /// Unix seconds after which the credentials are refused
+    pub expiration: u64,
 }
 
 /// Key status enumeration
@@ -274,6 +312,9 @@ impl AuthManager {
             created_at: now,
             last_used: None,
             status: KeyStatus::Active,
+            expires_at: None,
+            session_token: None,
+            session_policy: None,
         };
 
         // Add to access keys map
@@ -290,6 +331,64 @@ impl AuthManager {
         Ok(access_key)
     }
 
+    /// Issue temporary credentials for a user, valid for `duration`.
+    ///
+    /// They act with the user's policies, narrowed by `session_policy` when
+    /// one is given, and every request signed with them must carry the
+    /// session token. Expired credentials are dropped as new ones are issued.
+    pub async fn issue_temporary_credentials(
+        &self,
+        user_id: &str,
+        duration: Duration,
+        session_policy: Option<PolicyDocument>,
+    ) -> Result<TemporaryCredentials> {
+        if !(MIN_SESSION_DURATION..=MAX_SESSION_DURATION).contains(&duration) {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Session duration must be between {} and {} seconds",
+                MIN_SESSION_DURATION.as_secs(),
+                MAX_SESSION_DURATION.as_secs()
+            )));
+        }
+        if !self.users.read().await.contains_key(user_id) {
+            return Err(NimbuxError::Authentication("User not found".to_string()));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut secret = [0u8; 30];
+        let mut token = [0u8; 48];
+        rand::thread_rng().fill_bytes(&mut secret);
+        rand::thread_rng().fill_bytes(&mut token);
+
+        let credentials = TemporaryCredentials {
+            access_key_id: format!("NIMT{}", Uuid::new_v4().simple()).to_uppercase(),
+            secret_access_key: base64::engine::general_purpose::STANDARD.encode(secret),
+            session_token: base64::engine::general_purpose::STANDARD.encode(token),
+            user_id: user_id.to_string(),
+            expiration: now + duration.as_secs(),
+        };
+        let access_key = AccessKey {
+            access_key_id: credentials.access_key_id.clone(),
+            secret_access_key: credentials.secret_access_key.clone(),
+            user_id: user_id.to_string(),
+            created_at: now,
+            last_used: None,
+            status: KeyStatus::Active,
+            expires_at: Some(credentials.expiration),
+            session_token: Some(credentials.session_token.clone()),
+            session_policy,
+        };
+
+        let mut access_keys = self.access_keys.write().await;
+        access_keys.retain(|_, key| key.expires_at.is_none_or(|expires_at| now < expires_at));
+        access_keys.insert(access_key.access_key_id.clone(), access_key);
+
+        debug!("Issued temporary credentials {} for user {}", credentials.access_key_id, user_id);
+        Ok(credentials)
+    }
+
     /// Authenticate request using AWS Signature V4
     pub async fn authenticate_request(
         &self,
@@ -307,7 +406,11 @@ impl AuthManager {
         let access_key = access_keys.get(access_key_id)
             .ok_or_else(|| NimbuxError::Authentication("Invalid access key".to_string()))?;
 
-        if access_key.status != KeyStatus::Active {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        if !access_key.is_usable(now) {
             return Err(NimbuxError::Authentication("Access key is not active".to_string()));
         }
 
@@ -333,11 +436,6 @@ impl AuthManager {
         }
 
         // Update last used time
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
         drop(users);
         drop(access_keys);
 
@@ -359,44 +457,62 @@ impl AuthManager {
         })
     }
 
-    /// Check if user has permission for action on resource
+    /// Active access key and its user, for protocols that check signatures themselves.
+    /// Expired temporary credentials are refused
+    pub async fn active_access_key(&self, access_key_id: &str) -> Result<(AccessKey, User)> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let access_keys = self.access_keys.read().await;
+        let access_key = access_keys.get(access_key_id)
+            .filter(|key| key.is_usable(now))
+            .ok_or_else(|| NimbuxError::Authentication("Invalid access key".to_string()))?;
+
+        let users = self.users.read().await;
+        let user = users.get(&access_key.user_id)
+            .ok_or_else(|| NimbuxError::Authentication("User not found".to_string()))?;
+
+        Ok((access_key.clone(), user.clone()))
+    }
+
+    /// Record that a request signed with the access key was accepted
+    pub async fn record_key_use(&self, access_key_id: &str) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let mut access_keys = self.access_keys.write().await;
+        if let Some(key) = access_keys.get_mut(access_key_id) {
+            key.last_used = Some(now);
+        }
+    }
+
+    /// Check if user has permission for action on resource.
+    ///
+    /// Temporary credentials with a session policy need both the user's
+    /// policies and the session policy to allow the action.
     pub async fn check_permission(
         &self,
         auth_context: &AuthContext,
         action: &str,
         resource: &str,
     ) -> Result<bool> {
-        for policy in &auth_context.user.policies {
-            for statement in &policy.statement {
-                // Check if action matches
-                let action_matches = statement.action.iter().any(|a| {
-                    a == "*" || a == action || a.ends_with("*") && action.starts_with(&a[..a.len()-1])
-                });
-
-                if !action_matches {
-                    continue;
-                }
-
-                // Check if resource matches
-                let resource_matches = statement.resource.iter().any(|r| {
-                    r == "*" || r == resource || r.ends_with("*") && resource.starts_with(&r[..r.len()-1])
-                });
-
-                if !resource_matches {
-                    continue;
-                }
-
-                // Check effect
-                match statement.effect.as_str() {
-                    "Allow" => return Ok(true),
-                    "Deny" => return Ok(false),
-                    _ => continue,
-                }
-            }
-        }
+        let session_allows = auth_context
+            .access_key
+            .session_policy
+            .as_ref()
+            .is_none_or(|policy| policies_allow(std::slice::from_ref(policy), action, resource));
+        Ok(session_allows && policies_allow(&auth_context.user.policies, action, resource))
+    }
 
-        // Default deny
-        Ok(false)
+    /// Whether a user's policies allow an action, before any credentials are issued
+    pub async fn user_allows(&self, user_id: &str, action: &str, resource: &str) -> Result<bool> {
+        let users = self.users.read().await;
+        let user = users.get(user_id)
+            .ok_or_else(|| NimbuxError::Authentication("User not found".to_string()))?;
+        Ok(policies_allow(&user.policies, action, resource))
     }
 
     /// Add policy to user
@@ -424,6 +540,40 @@ impl AuthManager {
     }
 }
 
+/// First statement matching the action and resource decides; default deny
+fn policies_allow(policies: &[PolicyDocument], action: &str, resource: &str) -> bool {
+    for policy in policies {
+        for statement in &policy.statement {
+            // Check if action matches
+            let action_matches = statement.action.iter().any(|a| {
+                a == "*" || a == action || a.ends_with("*") && action.starts_with(&a[..a.len()-1])
+            });
+
+            if !action_matches {
+                continue;
+            }
+
+            // Check if resource matches
+            let resource_matches = statement.resource.iter().any(|r| {
+                r == "*" || r == resource || r.ends_with("*") && resource.starts_with(&r[..r.len()-1])
+            });
+
+            if !resource_matches {
+                continue;
+            }
+
+            // Check effect
+            match statement.effect.as_str() {
+                "Allow" => return true,
+                "Deny" => return false,
+                _ => continue,
+            }
+        }
+    }
+
+    false
+}
+
 impl Default for AuthManager {
     fn default() -> Self {
         Self::new()
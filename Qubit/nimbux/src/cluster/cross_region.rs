@@ -0,0 +1,1320 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Cross-region asynchronous replication of bucket objects
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify, RwLock};
+use tokio::task::JoinHandle;
+
+use crate::errors::{NimbuxError, Result};
+use crate::storage::{Object, ObjectMetadata, StorageBackend, StorageStats};
+
+/// Tag holding the version vector of a replicated object
+pub const VERSION_VECTOR_TAG: &str = "nimbux-version-vector";
+/// Tag holding when the object was written, in microseconds since the epoch
+pub const WRITTEN_AT_TAG: &str = "nimbux-written-at";
+/// Tag holding the region the object was written in
+pub const WRITTEN_IN_TAG: &str = "nimbux-written-in";
+/// Rules accepted in one bucket's configuration
+pub const MAX_RULES_PER_BUCKET: usize = 16;
+
+const CONFIG_PREFIX: &str = ".replication/buckets/";
+const TOMBSTONE_PREFIX: &str = ".replication/tombstones/";
+const LOCK_STRIPES: usize = 64;
+
+/// Cross-region replication configuration
+#[derive(Debug, Clone)]
+pub struct CrossRegionConfig {
+    /// Name of this region, counted in the version vectors of local writes
+    pub region: String,
+    /// Replications to remote endpoints running concurrently
+    pub workers: usize,
+    /// Objects waiting for a worker before new writes are not replicated
+    pub max_pending: usize,
+    /// Attempts per replication, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled after each further failure
+    pub retry_backoff: Duration,
+    /// Longest wait between retries
+    pub max_backoff: Duration,
+    /// Replications that failed on every attempt kept for inspection and retry
+    pub dead_letter_capacity: usize,
+    /// Bearer token other regions must present to write replicas here;
+    /// incoming replicas are refused when unset
+    pub incoming_token: Option<String>,
+}
+
+impl Default for CrossRegionConfig {
+    fn default() -> Self {
+        Self {
+            region: "default".to_string(),
+            workers: 4,
+            max_pending: 100_000,
+            max_attempts: 8,
+            retry_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+            dead_letter_capacity: 1000,
+            incoming_token: None,
+        }
+    }
+}
+
+/// How two version vectors relate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Causality {
+    /// Every write seen by this vector was seen by the other, and more
+    Before,
+    /// This vector has seen every write the other has, and more
+    After,
+    Equal,
+    /// Each vector has seen writes the other has not
+    Concurrent,
+}
+
+/// Writes per region that led to an object's current contents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionVector(BTreeMap<String, u64>);
+
+impl VersionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, region: &str) -> u64 {
+        self.0.get(region).copied().unwrap_or(0)
+    }
+
+    /// Count one more write in `region`
+    pub fn increment(&mut self, region: &str) {
+        *self.0.entry(region.to_string()).or_insert(0) += 1;
+    }
+
+    /// Take the highest count of every region
+    pub fn merge(&mut self, other: &VersionVector) {
+        for (region, &count) in &other.0 {
+            let entry = self.0.entry(region.clone()).or_insert(0);
+            *entry = (*entry).max(count);
+        }
+    }
+
+    pub fn compare(&self, other: &VersionVector) -> Causality {
+        let regions: HashSet<&String> = self.0.keys().chain(other.0.keys()).collect();
+        let (mut ahead, mut behind) = (false, false);
+        for region in regions {
+            match self.get(region).cmp(&other.get(region)) {
+                std::cmp::Ordering::Greater => ahead = true,
+                std::cmp::Ordering::Less => behind = true,
+                std::cmp::Ordering::Equal => {}
+            }
+        }
+        match (ahead, behind) {
+            (false, false) => Causality::Equal,
+            (true, false) => Causality::After,
+            (false, true) => Causality::Before,
+            (true, true) => Causality::Concurrent,
+        }
+    }
+
+    /// `region:count` pairs separated by commas, as stored in object tags
+    pub fn encode(&self) -> String {
+        self.0
+            .iter()
+            .map(|(region, count)| format!("{}:{}", region, count))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    pub fn parse(encoded: &str) -> Result<Self> {
+        let invalid = || NimbuxError::InvalidRequest(format!("Invalid version vector: {}", encoded));
+        let mut vector = BTreeMap::new();
+        for pair in encoded.split(',').filter(|pair| !pair.is_empty()) {
+            let (region, count) = pair.split_once(':').ok_or_else(invalid)?;
+            if !is_region(region) {
+                return Err(invalid());
+            }
+            let count = count.parse().map_err(|_| invalid())?;
+            if vector.insert(region.to_string(), count).is_some() {
+                return Err(invalid());
+            }
+        }
+        Ok(Self(vector))
+    }
+}
+
+/// Version of an object or delete, as replicated between regions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicaVersion {
+    pub vector: VersionVector,
+    /// Microseconds since the epoch when the write was made
+    pub written_at: i64,
+    /// Region the write was made in
+    pub region: String,
+}
+
+impl ReplicaVersion {
+    fn from_tags(tags: &HashMap<String, String>) -> Option<Self> {
+        Some(Self {
+            vector: VersionVector::parse(tags.get(VERSION_VECTOR_TAG)?).ok()?,
+            written_at: tags.get(WRITTEN_AT_TAG)?.parse().ok()?,
+            region: tags.get(WRITTEN_IN_TAG)?.clone(),
+        })
+    }
+
+    /// Version of a local object; objects stored before their bucket was
+    /// replicated count as unversioned writes made in `region`
+    fn of(metadata: &ObjectMetadata, region: &str) -> Self {
+        Self::from_tags(&metadata.tags).unwrap_or_else(|| Self {
+            vector: VersionVector::new(),
+            written_at: metadata.updated_at as i64 * 1_000_000,
+            region: region.to_string(),
+        })
+    }
+
+    fn stamp(&self, tags: &mut HashMap<String, String>) {
+        tags.insert(VERSION_VECTOR_TAG.to_string(), self.vector.encode());
+        tags.insert(WRITTEN_AT_TAG.to_string(), self.written_at.to_string());
+        tags.insert(WRITTEN_IN_TAG.to_string(), self.region.clone());
+    }
+
+    /// Last-writer-wins order used for concurrent writes, with the region
+    /// name breaking ties so every region picks the same winner
+    fn wins_over(&self, other: &ReplicaVersion) -> bool {
+        (self.written_at, &self.region) > (other.written_at, &other.region)
+    }
+}
+
+/// Remote bucket objects are copied to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationDestination {
+    /// Base URL of the remote Nimbux API
+    pub endpoint: String,
+    pub bucket: String,
+}
+
+/// Copies new objects with matching keys to a destination
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationRule {
+    pub id: String,
+    pub destination: ReplicationDestination,
+    /// Only keys starting with the prefix are replicated
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Whether deletes are replicated too
+    #[serde(default = "default_replicate_deletes")]
+    pub replicate_deletes: bool,
+}
+
+fn default_replicate_deletes() -> bool {
+    true
+}
+
+impl ReplicationRule {
+    fn applies_to(&self, key: &str) -> bool {
+        self.prefix.as_deref().is_none_or(|prefix| key.starts_with(prefix))
+    }
+}
+
+/// Replication rules of a bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketReplication {
+    pub bucket: String,
+    pub rules: Vec<ReplicationRule>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Change sent to a destination
+#[derive(Debug, Clone)]
+pub enum ReplicaChange {
+    Put { data: Vec<u8>, content_type: Option<String> },
+    Delete,
+}
+
+/// What a region did with an incoming replica
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplicaOutcome {
+    /// The replica was newer and was applied
+    Applied,
+    /// The region already had this write or a newer one
+    Stale,
+    /// The replica was concurrent with the local version and won
+    ConflictApplied,
+    /// The replica was concurrent with the local version and lost; the local
+    /// version is replicated back
+    ConflictRejected,
+}
+
+/// A replication that failed on every attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedReplication {
+    pub rule_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub endpoint: String,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Replication counts since startup
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrossRegionStats {
+    pub region: String,
+    /// Objects and deletes copied to a destination
+    pub replicated: u64,
+    /// Replications that failed on every attempt
+    pub failed: u64,
+    /// Attempts that failed and were retried
+    pub retries: u64,
+    /// Writes not replicated because too many were pending
+    pub dropped: u64,
+    /// Incoming replicas applied here
+    pub received: u64,
+    /// Incoming replicas already seen here
+    pub stale: u64,
+    /// Incoming replicas concurrent with the local version
+    pub conflicts: u64,
+    /// Objects waiting for a worker
+    pub pending: usize,
+    /// Replications waiting to be retried
+    pub retrying: usize,
+    pub dead_letters: usize,
+    /// Age of the oldest write waiting for a worker
+    pub oldest_pending_seconds: f64,
+    /// Time from a local write to its replication
+    pub last_lag_ms: u64,
+    pub max_lag_ms: u64,
+    pub average_lag_ms: f64,
+}
+
+/// Sends replicas to another region
+#[async_trait]
+pub trait ReplicationTarget: Send + Sync {
+    async fn replicate(
+        &self,
+        destination: &ReplicationDestination,
+        key: &str,
+        version: &ReplicaVersion,
+        change: &ReplicaChange,
+    ) -> Result<()>;
+}
+
+/// Sends replicas to the replication API of remote Nimbux servers
+pub struct HttpReplicationTarget {
+    client: reqwest::Client,
+    token: String,
+}
+
+impl HttpReplicationTarget {
+    pub fn new(timeout: Duration, token: impl Into<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| NimbuxError::Configuration(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self { client, token: token.into() })
+    }
+}
+
+#[async_trait]
+impl ReplicationTarget for HttpReplicationTarget {
+    async fn replicate(
+        &self,
+        destination: &ReplicationDestination,
+        key: &str,
+        version: &ReplicaVersion,
+        change: &ReplicaChange,
+    ) -> Result<()> {
+        // Each key segment is percent-encoded on its own so keys keep their slashes
+        let mut url = reqwest::Url::parse(&destination.endpoint)
+            .map_err(|e| NimbuxError::Configuration(format!("Invalid endpoint {}: {}", destination.endpoint, e)))?;
+        url.path_segments_mut()
+            .map_err(|_| NimbuxError::Configuration(format!("Invalid endpoint {}", destination.endpoint)))?
+            .pop_if_empty()
+            .extend(["api", "v1", "replication", destination.bucket.as_str()])
+            .extend(key.split('/'));
+        let request = match change {
+            ReplicaChange::Put { data, content_type } => {
+                let request = self.client.put(url.clone()).body(data.clone());
+                match content_type {
+                    Some(content_type) => request.header("content-type", content_type),
+                    None => request,
+                }
+            }
+            ReplicaChange::Delete => self.client.delete(url.clone()),
+        };
+        let response = request
+            .bearer_auth(&self.token)
+            .header("x-nimbux-version-vector", version.vector.encode())
+            .header("x-nimbux-written-at", version.written_at.to_string())
+            .header("x-nimbux-written-in", &version.region)
+            .send()
+            .await
+            .map_err(|e| NimbuxError::Network(format!("Failed to replicate to {}: {}", url, e)))?;
+        if !response.status().is_success() {
+            return Err(NimbuxError::Network(format!(
+                "Replication to {} was rejected with {}",
+                url,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+type TaskKey = (String, String);
+
+struct ReplicationTask {
+    rule: ReplicationRule,
+    bucket: String,
+    key: String,
+    /// Earliest local write not yet replicated
+    queued_at: DateTime<Utc>,
+    attempts: u32,
+}
+
+#[derive(Default)]
+struct PendingQueue {
+    order: VecDeque<TaskKey>,
+    tasks: HashMap<TaskKey, ReplicationTask>,
+}
+
+/// Replicates bucket objects to other regions and applies replicas they send.
+///
+/// Every write to a bucket with replication rules is stamped with a version
+/// vector counting writes per region. Background workers copy the object's
+/// current contents to each matching destination, retrying with backoff, so
+/// writes never wait on a remote region; several writes to an object waiting
+/// for a worker are sent once. Incoming replicas older than the local version
+/// are ignored and concurrent ones are resolved by last writer wins, so
+/// regions converge whichever order replicas arrive in. Give every region's
+/// bucket rules pointing at the others to detect conflicts both ways.
+///
+/// Pending replications are held in memory and are lost on restart. Rules are
+/// kept under `.replication/buckets/<bucket>` and deletes under
+/// `.replication/tombstones/<bucket>/<key>`.
+pub struct CrossRegionReplicator {
+    storage: Arc<dyn StorageBackend>,
+    target: Option<Arc<dyn ReplicationTarget>>,
+    config: CrossRegionConfig,
+    rules: RwLock<HashMap<String, Arc<Vec<ReplicationRule>>>>,
+    pending: Mutex<PendingQueue>,
+    wakeup: Notify,
+    dead_letters: Mutex<VecDeque<FailedReplication>>,
+    locks: Vec<Mutex<()>>,
+    replicated: AtomicU64,
+    failed: AtomicU64,
+    retries: AtomicU64,
+    dropped: AtomicU64,
+    received: AtomicU64,
+    stale: AtomicU64,
+    conflicts: AtomicU64,
+    retrying: AtomicU64,
+    last_lag_ms: AtomicU64,
+    max_lag_ms: AtomicU64,
+    total_lag_ms: AtomicU64,
+}
+
+impl CrossRegionReplicator {
+    /// `storage` is written without replicating, so replicas applied here are
+    /// not sent on to further regions
+    pub fn new(storage: Arc<dyn StorageBackend>, config: CrossRegionConfig) -> Result<Self> {
+        if !is_region(&config.region) {
+            return Err(NimbuxError::Configuration(format!("Invalid region name: {}", config.region)));
+        }
+        Ok(Self {
+            storage,
+            target: None,
+            config,
+            rules: RwLock::new(HashMap::new()),
+            pending: Mutex::new(PendingQueue::default()),
+            wakeup: Notify::new(),
+            dead_letters: Mutex::new(VecDeque::new()),
+            locks: (0..LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+            replicated: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            received: AtomicU64::new(0),
+            stale: AtomicU64::new(0),
+            conflicts: AtomicU64::new(0),
+            retrying: AtomicU64::new(0),
+            last_lag_ms: AtomicU64::new(0),
+            max_lag_ms: AtomicU64::new(0),
+            total_lag_ms: AtomicU64::new(0),
+        })
+    }
+
+    /// Send replicas through `target`
+    pub fn with_target(mut self, target: Arc<dyn ReplicationTarget>) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn region(&self) -> &str {
+        &self.config.region
+    }
+
+    pub async fn configuration(&self, bucket: &str) -> Result<Option<BucketReplication>> {
+        match self.storage.get(&config_id(bucket)).await {
+            Ok(object) => Ok(Some(serde_json::from_slice(&object.data)?)),
+            Err(NimbuxError::ObjectNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the replication rules of a bucket
+    pub async fn set_configuration(&self, bucket: &str, rules: Vec<ReplicationRule>) -> Result<BucketReplication> {
+        validate_bucket(bucket)?;
+        validate_rules(&rules)?;
+
+        let replication = BucketReplication {
+            bucket: bucket.to_string(),
+            rules,
+            updated_at: Utc::now(),
+        };
+        let id = config_id(bucket);
+        let object = Object::with_id(id.clone(), id, serde_json::to_vec(&replication)?, Some("application/json".to_string()));
+        self.storage.put(object).await?;
+
+        self.rules
+            .write()
+            .await
+            .insert(bucket.to_string(), Arc::new(replication.rules.clone()));
+        tracing::info!("Replication of bucket {} set with {} rules", bucket, replication.rules.len());
+        Ok(replication)
+    }
+
+    pub async fn delete_configuration(&self, bucket: &str) -> Result<()> {
+        match self.storage.delete(&config_id(bucket)).await {
+            Ok(()) => {}
+            Err(NimbuxError::ObjectNotFound { .. }) => {
+                return Err(NimbuxError::ObjectNotFound {
+                    object_id: format!("replication configuration of {}", bucket),
+                })
+            }
+            Err(e) => return Err(e),
+        }
+        self.rules.write().await.insert(bucket.to_string(), Arc::new(Vec::new()));
+        tracing::info!("Replication of bucket {} removed", bucket);
+        Ok(())
+    }
+
+    /// Check the bearer token presented with an incoming replica
+    pub fn authorize_incoming(&self, token: Option<&str>) -> Result<()> {
+        let Some(expected) = self.config.incoming_token.as_deref() else {
+            return Err(NimbuxError::Authorization("Incoming replication is not enabled".to_string()));
+        };
+        match token {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+            Some(_) => Err(NimbuxError::Authentication("Invalid replication token".to_string())),
+            None => Err(NimbuxError::Authentication("Missing replication token".to_string())),
+        }
+    }
+
+    /// Apply a replica sent by another region, unless this region already
+    /// has the write or a newer one
+    pub async fn apply_incoming(
+        &self,
+        bucket: &str,
+        key: &str,
+        version: ReplicaVersion,
+        change: ReplicaChange,
+    ) -> Result<ReplicaOutcome> {
+        validate_bucket(bucket)?;
+        if key.is_empty() {
+            return Err(NimbuxError::InvalidRequest("Replicas need an object key".to_string()));
+        }
+        if !is_region(&version.region) || version.region == self.config.region {
+            return Err(NimbuxError::InvalidRequest(format!("Invalid source region: {}", version.region)));
+        }
+        let object_id = format!("{}/{}", bucket, key);
+        let guard = self.lock_for(&object_id).lock().await;
+
+        let Some(current) = self.version_of(&object_id).await? else {
+            self.write_replica(&object_id, &version, change).await?;
+            self.received.fetch_add(1, Ordering::Relaxed);
+            return Ok(ReplicaOutcome::Applied);
+        };
+
+        match version.vector.compare(&current.vector) {
+            Causality::After => {
+                self.write_replica(&object_id, &version, change).await?;
+                self.received.fetch_add(1, Ordering::Relaxed);
+                Ok(ReplicaOutcome::Applied)
+            }
+            Causality::Before | Causality::Equal => {
+                self.stale.fetch_add(1, Ordering::Relaxed);
+                Ok(ReplicaOutcome::Stale)
+            }
+            Causality::Concurrent => {
+                self.conflicts.fetch_add(1, Ordering::Relaxed);
+                let mut vector = current.vector.clone();
+                vector.merge(&version.vector);
+
+                if version.wins_over(&current) {
+                    tracing::info!(
+                        "Concurrent writes to {} in {} and {}: keeping the replica",
+                        object_id,
+                        version.region,
+                        current.region
+                    );
+                    self.write_replica(&object_id, &ReplicaVersion { vector, ..version }, change).await?;
+                    self.received.fetch_add(1, Ordering::Relaxed);
+                    return Ok(ReplicaOutcome::ConflictApplied);
+                }
+
+                // The local version stays, and now supersedes both writes so
+                // the other region takes it when it is replicated back
+                tracing::info!(
+                    "Concurrent writes to {} in {} and {}: keeping the local version",
+                    object_id,
+                    version.region,
+                    current.region
+                );
+                let merged = ReplicaVersion { vector, ..current };
+                match self.storage.get(&object_id).await {
+                    Ok(mut object) => {
+                        merged.stamp(&mut object.metadata.tags);
+                        self.storage.put(object).await?;
+                    }
+                    Err(NimbuxError::ObjectNotFound { .. }) => self.write_tombstone(&object_id, &merged).await?,
+                    Err(e) => return Err(e),
+                }
+                drop(guard);
+                self.enqueue(bucket, key, false).await?;
+                Ok(ReplicaOutcome::ConflictRejected)
+            }
+        }
+    }
+
+    /// Replications that failed on every attempt, optionally for one bucket
+    pub async fn failed(&self, bucket: Option<&str>) -> Vec<FailedReplication> {
+        self.dead_letters
+            .lock()
+            .await
+            .iter()
+            .filter(|failure| bucket.is_none_or(|bucket| failure.bucket == bucket))
+            .cloned()
+            .collect()
+    }
+
+    /// Queue failed replications again, returning how many were queued
+    pub async fn retry_failed(&self, bucket: Option<&str>) -> Result<usize> {
+        let retried = {
+            let mut dead_letters = self.dead_letters.lock().await;
+            let (retried, kept): (Vec<_>, Vec<_>) = dead_letters
+                .drain(..)
+                .partition(|failure| bucket.is_none_or(|bucket| failure.bucket == bucket));
+            *dead_letters = kept.into();
+            retried
+        };
+
+        let mut queued = 0;
+        for failure in &retried {
+            let rules = self.rules_for(&failure.bucket).await?;
+            // Rules removed since the failure have nothing left to retry
+            let Some(rule) = rules.iter().find(|rule| rule.id == failure.rule_id) else {
+                continue;
+            };
+            let task = ReplicationTask {
+                rule: rule.clone(),
+                bucket: failure.bucket.clone(),
+                key: failure.key.clone(),
+                queued_at: Utc::now(),
+                attempts: 0,
+            };
+            if self.push(task).await {
+                queued += 1;
+            }
+        }
+        Ok(queued)
+    }
+
+    pub async fn stats(&self) -> CrossRegionStats {
+        let (pending, oldest) = {
+            let pending = self.pending.lock().await;
+            (pending.tasks.len(), pending.tasks.values().map(|task| task.queued_at).min())
+        };
+        let replicated = self.replicated.load(Ordering::Relaxed);
+        CrossRegionStats {
+            region: self.config.region.clone(),
+            replicated,
+            failed: self.failed.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            received: self.received.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+            conflicts: self.conflicts.load(Ordering::Relaxed),
+            pending,
+            retrying: self.retrying.load(Ordering::Relaxed) as usize,
+            dead_letters: self.dead_letters.lock().await.len(),
+            oldest_pending_seconds: oldest
+                .map(|queued_at| (Utc::now() - queued_at).num_milliseconds().max(0) as f64 / 1000.0)
+                .unwrap_or(0.0),
+            last_lag_ms: self.last_lag_ms.load(Ordering::Relaxed),
+            max_lag_ms: self.max_lag_ms.load(Ordering::Relaxed),
+            average_lag_ms: if replicated == 0 {
+                0.0
+            } else {
+                self.total_lag_ms.load(Ordering::Relaxed) as f64 / replicated as f64
+            },
+        }
+    }
+
+    /// Run the replication workers
+    pub fn start(self: Arc<Self>) -> Vec<JoinHandle<()>> {
+        (0..self.config.workers.max(1))
+            .map(|_| {
+                let replicator = Arc::clone(&self);
+                tokio::spawn(async move {
+                    loop {
+                        let next = replicator.pop().await;
+                        match next {
+                            Some(task) => replicator.process(task).await,
+                            None => replicator.wakeup.notified().await,
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Queue the object for every rule of the bucket matching its key,
+    /// returning whether any rule matched
+    async fn enqueue(&self, bucket: &str, key: &str, deleted: bool) -> Result<bool> {
+        let rules = self.rules_for(bucket).await?;
+        let mut matched = false;
+        for rule in rules.iter().filter(|rule| rule.applies_to(key)) {
+            if deleted && !rule.replicate_deletes {
+                continue;
+            }
+            matched = true;
+            let task = ReplicationTask {
+                rule: rule.clone(),
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                queued_at: Utc::now(),
+                attempts: 0,
+            };
+            self.push(task).await;
+        }
+        Ok(matched)
+    }
+
+    /// Add a task, merging it into one already waiting for the same object
+    /// and rule; returns false when it was dropped
+    async fn push(&self, task: ReplicationTask) -> bool {
+        let task_key = (task.rule.id.clone(), format!("{}/{}", task.bucket, task.key));
+        let mut pending = self.pending.lock().await;
+        if let Some(waiting) = pending.tasks.get_mut(&task_key) {
+            waiting.queued_at = waiting.queued_at.min(task.queued_at);
+            return true;
+        }
+        if pending.tasks.len() >= self.config.max_pending {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                "Not replicating {} with rule {}: {} replications pending",
+                task_key.1,
+                task_key.0,
+                pending.tasks.len()
+            );
+            return false;
+        }
+        pending.order.push_back(task_key.clone());
+        pending.tasks.insert(task_key, task);
+        drop(pending);
+        self.wakeup.notify_one();
+        true
+    }
+
+    async fn pop(&self) -> Option<ReplicationTask> {
+        let mut pending = self.pending.lock().await;
+        let task_key = pending.order.pop_front()?;
+        pending.tasks.remove(&task_key)
+    }
+
+    async fn process(self: &Arc<Self>, mut task: ReplicationTask) {
+        task.attempts += 1;
+        let error = match self.replicate(&task).await {
+            Ok(()) => {
+                let lag = (Utc::now() - task.queued_at).num_milliseconds().max(0) as u64;
+                self.replicated.fetch_add(1, Ordering::Relaxed);
+                self.last_lag_ms.store(lag, Ordering::Relaxed);
+                self.max_lag_ms.fetch_max(lag, Ordering::Relaxed);
+                self.total_lag_ms.fetch_add(lag, Ordering::Relaxed);
+                return;
+            }
+            Err(e) => e,
+        };
+
+        if task.attempts < self.config.max_attempts {
+            tracing::debug!(
+                "Replication of {}/{} with rule {} failed on attempt {}: {}",
+                task.bucket,
+                task.key,
+                task.rule.id,
+                task.attempts,
+                error
+            );
+            self.retries.fetch_add(1, Ordering::Relaxed);
+            self.retrying.fetch_add(1, Ordering::Relaxed);
+            let backoff = self
+                .config
+                .retry_backoff
+                .saturating_mul(1 << (task.attempts - 1).min(16))
+                .min(self.config.max_backoff);
+            let replicator = Arc::clone(self);
+            tokio::spawn(async move {
+                tokio::time::sleep(backoff).await;
+                replicator.retrying.fetch_sub(1, Ordering::Relaxed);
+                replicator.push(task).await;
+            });
+            return;
+        }
+
+        tracing::warn!(
+            "Giving up on replicating {}/{} to {} after {} attempts: {}",
+            task.bucket,
+            task.key,
+            task.rule.destination.endpoint,
+            task.attempts,
+            error
+        );
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        let mut dead_letters = self.dead_letters.lock().await;
+        if dead_letters.len() >= self.config.dead_letter_capacity.max(1) {
+            dead_letters.pop_front();
+        }
+        dead_letters.push_back(FailedReplication {
+            rule_id: task.rule.id,
+            bucket: task.bucket,
+            key: task.key,
+            endpoint: task.rule.destination.endpoint,
+            attempts: task.attempts,
+            error: error.to_string(),
+            failed_at: Utc::now(),
+        });
+    }
+
+    /// Send the object's current contents, or its delete, to the destination
+    async fn replicate(&self, task: &ReplicationTask) -> Result<()> {
+        let Some(target) = &self.target else {
+            return Err(NimbuxError::Configuration("No replication target is configured".to_string()));
+        };
+        let object_id = format!("{}/{}", task.bucket, task.key);
+        let (version, change) = match self.storage.get(&object_id).await {
+            Ok(object) => (
+                ReplicaVersion::of(&object.metadata, &self.config.region),
+                ReplicaChange::Put { data: object.data, content_type: object.metadata.content_type },
+            ),
+            Err(NimbuxError::ObjectNotFound { .. }) => match self.tombstone(&object_id).await? {
+                Some(version) if task.rule.replicate_deletes => (version, ReplicaChange::Delete),
+                // Deleted without replicating deletes, or never stamped
+                _ => return Ok(()),
+            },
+            Err(e) => return Err(e),
+        };
+        target.replicate(&task.rule.destination, &task.key, &version, &change).await
+    }
+
+    /// Version of a local write to the object about to be made
+    async fn next_version(&self, object_id: &str) -> Result<ReplicaVersion> {
+        let mut vector = self
+            .version_of(object_id)
+            .await?
+            .map(|version| version.vector)
+            .unwrap_or_default();
+        vector.increment(&self.config.region);
+        Ok(ReplicaVersion {
+            vector,
+            written_at: Utc::now().timestamp_micros(),
+            region: self.config.region.clone(),
+        })
+    }
+
+    /// Version of the stored object, or of its delete
+    async fn version_of(&self, object_id: &str) -> Result<Option<ReplicaVersion>> {
+        match self.storage.head(object_id).await {
+            Ok(metadata) => Ok(Some(ReplicaVersion::of(&metadata, &self.config.region))),
+            Err(NimbuxError::ObjectNotFound { .. }) => self.tombstone(object_id).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn write_replica(&self, object_id: &str, version: &ReplicaVersion, change: ReplicaChange) -> Result<()> {
+        match change {
+            ReplicaChange::Put { data, content_type } => {
+                let mut object = Object::with_id(object_id.to_string(), object_id.to_string(), data, content_type);
+                version.stamp(&mut object.metadata.tags);
+                self.storage.put(object).await?;
+                self.clear_tombstone(object_id).await
+            }
+            ReplicaChange::Delete => {
+                self.write_tombstone(object_id, version).await?;
+                match self.storage.delete(object_id).await {
+                    Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    }
+
+    async fn tombstone(&self, object_id: &str) -> Result<Option<ReplicaVersion>> {
+        match self.storage.get(&tombstone_id(object_id)).await {
+            Ok(object) => Ok(Some(serde_json::from_slice(&object.data)?)),
+            Err(NimbuxError::ObjectNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn write_tombstone(&self, object_id: &str, version: &ReplicaVersion) -> Result<()> {
+        let id = tombstone_id(object_id);
+        let object = Object::with_id(id.clone(), id, serde_json::to_vec(version)?, Some("application/json".to_string()));
+        self.storage.put(object).await
+    }
+
+    async fn clear_tombstone(&self, object_id: &str) -> Result<()> {
+        match self.storage.delete(&tombstone_id(object_id)).await {
+            Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn rules_for(&self, bucket: &str) -> Result<Arc<Vec<ReplicationRule>>> {
+        if let Some(rules) = self.rules.read().await.get(bucket) {
+            return Ok(Arc::clone(rules));
+        }
+
+        let rules = Arc::new(
+            self.configuration(bucket)
+                .await?
+                .map(|replication| replication.rules)
+                .unwrap_or_default(),
+        );
+        self.rules.write().await.insert(bucket.to_string(), Arc::clone(&rules));
+        Ok(rules)
+    }
+
+    fn lock_for(&self, object_id: &str) -> &Mutex<()> {
+        let mut hasher = DefaultHasher::new();
+        object_id.hash(&mut hasher);
+        &self.locks[hasher.finish() as usize % self.locks.len()]
+    }
+}
+
+/// Storage wrapper that stamps writes and deletes in replicated buckets with
+/// a version vector and queues them for replication.
+///
+/// Buckets without replication rules are passed straight through. Internal
+/// objects, whose bucket starts with `.`, are never replicated.
+pub struct ReplicatingStorage {
+    inner: Arc<dyn StorageBackend>,
+    replicator: Arc<CrossRegionReplicator>,
+}
+
+impl ReplicatingStorage {
+    pub fn new(inner: Arc<dyn StorageBackend>, replicator: Arc<CrossRegionReplicator>) -> Self {
+        Self { inner, replicator }
+    }
+
+    async fn replicated_bucket<'a>(&self, object_id: &'a str) -> Result<Option<(&'a str, &'a str)>> {
+        let Some((bucket, key)) = split_object_id(object_id) else {
+            return Ok(None);
+        };
+        let replicated = !self.replicator.rules_for(bucket).await?.is_empty();
+        Ok(replicated.then_some((bucket, key)))
+    }
+
+    async fn queue(&self, bucket: &str, key: &str, deleted: bool) {
+        if let Err(e) = self.replicator.enqueue(bucket, key, deleted).await {
+            tracing::warn!("Failed to queue replication of {}/{}: {}", bucket, key, e);
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ReplicatingStorage {
+    async fn put(&self, mut object: Object) -> Result<()> {
+        let object_id = object.metadata.id.clone();
+        let Some((bucket, key)) = self.replicated_bucket(&object_id).await? else {
+            return self.inner.put(object).await;
+        };
+
+        {
+            let _guard = self.replicator.lock_for(&object_id).lock().await;
+            let version = self.replicator.next_version(&object_id).await?;
+            version.stamp(&mut object.metadata.tags);
+            self.inner.put(object).await?;
+            self.replicator.clear_tombstone(&object_id).await?;
+        }
+        self.queue(bucket, key, false).await;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Object> {
+        self.inner.get(id).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let Some((bucket, key)) = self.replicated_bucket(id).await? else {
+            return self.inner.delete(id).await;
+        };
+
+        {
+            let _guard = self.replicator.lock_for(id).lock().await;
+            let version = self.replicator.next_version(id).await?;
+            self.inner.delete(id).await?;
+            self.replicator.write_tombstone(id, &version).await?;
+        }
+        self.queue(bucket, key, true).await;
+        Ok(())
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool> {
+        self.inner.exists(id).await
+    }
+
+    async fn list(&self, prefix: Option<&str>, limit: Option<usize>) -> Result<Vec<ObjectMetadata>> {
+        self.inner.list(prefix, limit).await
+    }
+
+    async fn head(&self, id: &str) -> Result<ObjectMetadata> {
+        self.inner.head(id).await
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        self.inner.stats().await
+    }
+}
+
+/// Bucket and key of a `<bucket>/<key>` object ID; internal objects have none
+fn split_object_id(object_id: &str) -> Option<(&str, &str)> {
+    object_id
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !bucket.starts_with('.') && !key.is_empty())
+}
+
+fn config_id(bucket: &str) -> String {
+    format!("{}{}", CONFIG_PREFIX, bucket)
+}
+
+fn tombstone_id(object_id: &str) -> String {
+    format!("{}{}", TOMBSTONE_PREFIX, object_id)
+}
+
+fn is_region(region: &str) -> bool {
+    !region.is_empty() && region.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn validate_bucket(bucket: &str) -> Result<()> {
+    if bucket.is_empty() || bucket.starts_with('.') || bucket.contains('/') {
+        return Err(NimbuxError::InvalidRequest(format!("Invalid bucket name: {}", bucket)));
+    }
+    Ok(())
+}
+
+fn validate_rules(rules: &[ReplicationRule]) -> Result<()> {
+    let invalid = |rule: &ReplicationRule, reason: &str| NimbuxError::InvalidRequest(format!("Rule {}: {}", rule.id, reason));
+
+    if rules.is_empty() || rules.len() > MAX_RULES_PER_BUCKET {
+        return Err(NimbuxError::InvalidRequest(format!(
+            "A replication configuration needs 1 to {} rules",
+            MAX_RULES_PER_BUCKET
+        )));
+    }
+    let mut ids = HashSet::new();
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err(NimbuxError::InvalidRequest("Rule IDs must not be empty".to_string()));
+        }
+        if !ids.insert(rule.id.as_str()) {
+            return Err(invalid(rule, "duplicate rule ID"));
+        }
+        let endpoint = &rule.destination.endpoint;
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            return Err(invalid(rule, "destination endpoint must be http or https"));
+        }
+        if validate_bucket(&rule.destination.bucket).is_err() {
+            return Err(invalid(rule, "invalid destination bucket"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use std::sync::atomic::AtomicU32;
+
+    fn rule(id: &str, endpoint: &str) -> ReplicationRule {
+        ReplicationRule {
+            id: id.to_string(),
+            destination: ReplicationDestination { endpoint: endpoint.to_string(), bucket: "media".to_string() },
+            prefix: None,
+            replicate_deletes: true,
+        }
+    }
+
+    fn config(region: &str) -> CrossRegionConfig {
+        CrossRegionConfig {
+            region: region.to_string(),
+            retry_backoff: Duration::from_millis(1),
+            incoming_token: Some("secret".to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn put(storage: &dyn StorageBackend, id: &str, data: &str) {
+        let object = Object::with_id(id.to_string(), id.to_string(), data.as_bytes().to_vec(), Some("text/plain".to_string()));
+        storage.put(object).await.unwrap();
+    }
+
+    /// Hands replicas to other in-process regions by endpoint, failing while
+    /// `failures_left` is above zero
+    #[derive(Default)]
+    struct LoopbackTarget {
+        regions: std::sync::Mutex<HashMap<String, Arc<CrossRegionReplicator>>>,
+        failures_left: AtomicU32,
+        outcomes: std::sync::Mutex<Vec<ReplicaOutcome>>,
+    }
+
+    #[async_trait]
+    impl ReplicationTarget for LoopbackTarget {
+        async fn replicate(
+            &self,
+            destination: &ReplicationDestination,
+            key: &str,
+            version: &ReplicaVersion,
+            change: &ReplicaChange,
+        ) -> Result<()> {
+            if self.failures_left.load(Ordering::SeqCst) > 0 {
+                self.failures_left.fetch_sub(1, Ordering::SeqCst);
+                return Err(NimbuxError::Network("unavailable".to_string()));
+            }
+            let region = self.regions.lock().unwrap().get(&destination.endpoint).cloned();
+            let region = region.ok_or_else(|| NimbuxError::Network(format!("unknown endpoint {}", destination.endpoint)))?;
+            let outcome = region
+                .apply_incoming(&destination.bucket, key, version.clone(), change.clone())
+                .await?;
+            self.outcomes.lock().unwrap().push(outcome);
+            Ok(())
+        }
+    }
+
+    struct Region {
+        inner: Arc<MemoryStorage>,
+        replicator: Arc<CrossRegionReplicator>,
+        storage: ReplicatingStorage,
+    }
+
+    fn region(name: &str, target: &Arc<LoopbackTarget>) -> Region {
+        let inner = Arc::new(MemoryStorage::new());
+        let replicator = Arc::new(
+            CrossRegionReplicator::new(inner.clone(), config(name))
+                .unwrap()
+                .with_target(target.clone()),
+        );
+        target
+            .regions
+            .lock()
+            .unwrap()
+            .insert(format!("http://{}", name), Arc::clone(&replicator));
+        let storage = ReplicatingStorage::new(inner.clone(), Arc::clone(&replicator));
+        Region { inner, replicator, storage }
+    }
+
+    async fn settled(replicator: &CrossRegionReplicator, replications: u64) -> CrossRegionStats {
+        for _ in 0..400 {
+            let stats = replicator.stats().await;
+            if stats.replicated + stats.failed >= replications && stats.pending == 0 && stats.retrying == 0 {
+                return stats;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("replications did not settle");
+    }
+
+    #[test]
+    fn test_version_vectors_order_writes() {
+        let mut a = VersionVector::new();
+        a.increment("us-east");
+        let mut b = a.clone();
+        b.increment("eu-west");
+        assert_eq!(a.compare(&b), Causality::Before);
+        assert_eq!(b.compare(&a), Causality::After);
+        assert_eq!(a.compare(&a.clone()), Causality::Equal);
+
+        a.increment("us-east");
+        assert_eq!(a.compare(&b), Causality::Concurrent);
+        a.merge(&b);
+        assert_eq!((a.get("us-east"), a.get("eu-west")), (2, 1));
+        assert_eq!(a.encode(), "eu-west:1,us-east:2");
+        assert_eq!(VersionVector::parse(&a.encode()).unwrap(), a);
+        assert!(VersionVector::parse("us-east:x").is_err());
+        assert!(VersionVector::parse("us-east:1,us-east:2").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_writes_and_deletes_reach_the_destination() {
+        let target = Arc::new(LoopbackTarget::default());
+        let east = region("us-east", &target);
+        let west = region("eu-west", &target);
+        let handles = Arc::clone(&east.replicator).start();
+
+        let mut clips = rule("to-west", "http://eu-west");
+        clips.prefix = Some("clips/".to_string());
+        east.replicator.set_configuration("media", vec![clips]).await.unwrap();
+
+        put(&east.storage, "media/clips/a.mp4", "one").await;
+        put(&east.storage, "media/clips/a.mp4", "two").await;
+        put(&east.storage, "media/thumbs/a.jpg", "thumb").await;
+        settled(&east.replicator, 1).await;
+
+        let replica = west.inner.get("media/clips/a.mp4").await.unwrap();
+        assert_eq!(replica.data, b"two");
+        assert_eq!(replica.metadata.content_type.as_deref(), Some("text/plain"));
+        let version = ReplicaVersion::from_tags(&replica.metadata.tags).unwrap();
+        assert_eq!((version.vector.get("us-east"), version.region.as_str()), (2, "us-east"));
+        assert!(!west.inner.exists("media/thumbs/a.jpg").await.unwrap());
+
+        east.storage.delete("media/clips/a.mp4").await.unwrap();
+        let stats = settled(&east.replicator, 2).await;
+        assert!(!west.inner.exists("media/clips/a.mp4").await.unwrap());
+        assert_eq!(west.replicator.tombstone("media/clips/a.mp4").await.unwrap().unwrap().vector.get("us-east"), 3);
+        assert_eq!((stats.failed, stats.pending, stats.dead_letters), (0, 0, 0));
+        assert!(stats.max_lag_ms >= stats.last_lag_ms);
+
+        // A late copy of the first write is older than the delete
+        let stale = ReplicaVersion::from_tags(&replica.metadata.tags).unwrap();
+        let outcome = west
+            .replicator
+            .apply_incoming("media", "clips/a.mp4", stale, ReplicaChange::Put { data: b"two".to_vec(), content_type: None })
+            .await
+            .unwrap();
+        assert_eq!(outcome, ReplicaOutcome::Stale);
+        assert!(!west.inner.exists("media/clips/a.mp4").await.unwrap());
+
+        for handle in handles {
+            handle.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writes_converge_on_the_last_writer() {
+        let target = Arc::new(LoopbackTarget::default());
+        let east = region("us-east", &target);
+        let west = region("eu-west", &target);
+        east.replicator.set_configuration("media", vec![rule("to-west", "http://eu-west")]).await.unwrap();
+        west.replicator.set_configuration("media", vec![rule("to-east", "http://us-east")]).await.unwrap();
+
+        // Both regions write before either replicates; the later write wins
+        put(&east.storage, "media/cover.jpg", "east").await;
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        put(&west.storage, "media/cover.jpg", "west").await;
+
+        let mut handles = Arc::clone(&east.replicator).start();
+        handles.extend(Arc::clone(&west.replicator).start());
+        let mut versions = None;
+        for _ in 0..400 {
+            let east_copy = east.inner.get("media/cover.jpg").await.unwrap();
+            let west_copy = west.inner.get("media/cover.jpg").await.unwrap();
+            let east_version = ReplicaVersion::from_tags(&east_copy.metadata.tags).unwrap();
+            let west_version = ReplicaVersion::from_tags(&west_copy.metadata.tags).unwrap();
+            if east_copy.data == west_copy.data && east_version == west_version {
+                versions = Some((east_copy.data, east_version));
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let (data, version) = versions.expect("regions did not converge");
+        assert_eq!(data, b"west");
+        assert_eq!((version.vector.get("us-east"), version.vector.get("eu-west")), (1, 1));
+        assert_eq!(version.region, "eu-west");
+
+        let conflicts = east.replicator.stats().await.conflicts + west.replicator.stats().await.conflicts;
+        assert!(conflicts >= 1);
+        let outcomes = target.outcomes.lock().unwrap().clone();
+        assert!(outcomes
+            .iter()
+            .any(|outcome| matches!(outcome, ReplicaOutcome::ConflictApplied | ReplicaOutcome::ConflictRejected)));
+
+        for handle in handles {
+            handle.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_replications_are_retried_then_dead_lettered() {
+        let target = Arc::new(LoopbackTarget::default());
+        let east = region("us-east", &target);
+        let west = region("eu-west", &target);
+        let handles = Arc::clone(&east.replicator).start();
+        east.replicator.set_configuration("media", vec![rule("to-west", "http://eu-west")]).await.unwrap();
+
+        target.failures_left.store(3, Ordering::SeqCst);
+        put(&east.storage, "media/a.mp4", "a").await;
+        let stats = settled(&east.replicator, 1).await;
+        assert_eq!((stats.replicated, stats.retries, stats.failed), (1, 3, 0));
+        assert!(west.inner.exists("media/a.mp4").await.unwrap());
+
+        target.failures_left.store(100, Ordering::SeqCst);
+        put(&east.storage, "media/b.mp4", "b").await;
+        let stats = settled(&east.replicator, 2).await;
+        assert_eq!((stats.failed, stats.dead_letters), (1, 1));
+        let failed = east.replicator.failed(Some("media")).await;
+        assert_eq!((failed[0].key.as_str(), failed[0].attempts), ("b.mp4", 8));
+        assert!(east.replicator.failed(Some("other")).await.is_empty());
+
+        target.failures_left.store(0, Ordering::SeqCst);
+        assert_eq!(east.replicator.retry_failed(Some("media")).await.unwrap(), 1);
+        let stats = settled(&east.replicator, 3).await;
+        assert_eq!((stats.replicated, stats.dead_letters), (2, 0));
+        assert_eq!(west.inner.get("media/b.mp4").await.unwrap().data, b"b");
+
+        for handle in handles {
+            handle.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_configurations_and_incoming_tokens() {
+        let replicator = CrossRegionReplicator::new(Arc::new(MemoryStorage::new()), config("us-east")).unwrap();
+        let valid = rule("to-west", "https://eu-west.nimbux.local");
+        assert!(replicator.set_configuration("media", vec![]).await.is_err());
+        assert!(replicator.set_configuration(".s3", vec![valid.clone()]).await.is_err());
+        assert!(replicator.set_configuration("media", vec![valid.clone(), valid.clone()]).await.is_err());
+        assert!(replicator.set_configuration("media", vec![rule("ftp", "ftp://eu-west")]).await.is_err());
+        assert!(replicator.delete_configuration("media").await.is_err());
+        replicator.set_configuration("media", vec![valid]).await.unwrap();
+        assert_eq!(replicator.configuration("media").await.unwrap().unwrap().rules.len(), 1);
+        assert!(CrossRegionReplicator::new(Arc::new(MemoryStorage::new()), config("us east")).is_err());
+
+        assert!(replicator.authorize_incoming(Some("secret")).is_ok());
+        assert!(replicator.authorize_incoming(Some("guess")).is_err());
+        assert!(replicator.authorize_incoming(None).is_err());
+        let closed = CrossRegionReplicator::new(Arc::new(MemoryStorage::new()), CrossRegionConfig::default()).unwrap();
+        assert!(closed.authorize_incoming(Some("secret")).is_err());
+
+        // Replicas claiming to come from this region are refused
+        let version = ReplicaVersion { vector: VersionVector::new(), written_at: 0, region: "us-east".to_string() };
+        assert!(replicator.apply_incoming("media", "a", version, ReplicaChange::Delete).await.is_err());
+    }
+}
@@ -333,6 +333,7 @@ impl DistributedStorage {
             replication_factor: self.strategy.factor,
             under_replicated_objects: under_replicated,
             pending_replications: queue.len(),
+            oldest_pending_at: queue.iter().map(|task| task.created_at).min(),
             consistency_level: self.strategy.consistency_level.clone(),
         })
     }
@@ -441,5 +442,7 @@ pub struct ReplicationStats {
     pub replication_factor: usize,
     pub under_replicated_objects: usize,
     pub pending_replications: usize,
+    /// When the oldest queued replication was created, in Unix seconds
+    pub oldest_pending_at: Option<u64>,
     pub consistency_level: ConsistencyLevel,
 }
\ No newline at end of file
@@ -21,14 +21,16 @@ pub mod auto_scaler;
 pub mod distributed_storage;
 pub mod consensus;
 pub mod sharding;
+pub mod cross_region;
 
 // Re-export commonly used types
 pub use node::{Node, NodeStatus, NodeRole, NodeMetrics};
 pub use load_balancer::{LoadBalancer, LoadBalancingStrategy, LoadBalancerConfig};
 pub use auto_scaler::{AutoScaler, ScalingPolicy, ScalingMetrics, ScalingDecision};
-pub use distributed_storage::{DistributedStorage, ReplicationStrategy, ConsistencyLevel};
+pub use distributed_storage::{DistributedStorage, ReplicationStrategy, ConsistencyLevel, ReplicationStats};
 pub use consensus::{ConsensusManager, ConsensusConfig, ConsensusState};
 pub use sharding::{ShardManager, ShardKey, ShardInfo, ShardDistribution};
+pub use cross_region::{CrossRegionReplicator, CrossRegionConfig, CrossRegionStats, ReplicatingStorage, ReplicationRule, ReplicationDestination, BucketReplication, ReplicaVersion, ReplicaChange, ReplicaOutcome, VersionVector, Causality, FailedReplication, ReplicationTarget, HttpReplicationTarget};
 
 /// Cluster configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -187,6 +189,11 @@ impl ClusterManager {
         }
     }
     
+    /// Snapshot of every node in the cluster
+    pub async fn get_nodes(&self) -> Vec<Node> {
+        self.nodes.read().await.values().cloned().collect()
+    }
+    
     /// Get cluster health status
     pub async fn get_cluster_health(&self) -> Result<ClusterHealth> {
         let nodes = self.nodes.read().await;
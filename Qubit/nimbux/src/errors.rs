@@ -34,6 +34,24 @@ pub enum NimbuxError {
     #[error("Checksum mismatch: expected {expected}, got {actual}")]
     ChecksumMismatch { expected: String, actual: String },
     
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+    
+    #[error("Write offset {offset} of {object_id} does not match its size {size}")]
+    OffsetMismatch { object_id: String, offset: u64, size: u64 },
+    
+    #[error("Range not satisfiable for an object of {size} bytes")]
+    RangeNotSatisfiable { size: u64 },
+    
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+    
+    #[error("Object {object_id} is locked: {reason}")]
+    ObjectLocked { object_id: String, reason: String },
+    
+    #[error("Slow down: {reason}")]
+    Throttled { reason: String, retry_after_ms: u64 },
+    
     #[error("Compression error: {0}")]
     Compression(String),
     
@@ -43,6 +61,9 @@ pub enum NimbuxError {
     #[error("Configuration error: {0}")]
     Configuration(String),
     
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
@@ -0,0 +1,733 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Bucket to filesystem mapping
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{GatewayConfig, GatewayError, GatewayResult};
+use crate::errors::NimbuxError;
+use crate::storage::{Object, ObjectMetadata, StorageBackend};
+
+/// Inode of the bucket root
+pub const ROOT_INO: u64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    File,
+    Directory,
+}
+
+/// Attributes reported for a file or directory
+#[derive(Debug, Clone)]
+pub struct NodeAttr {
+    pub ino: u64,
+    pub kind: NodeKind,
+    pub size: u64,
+    pub mtime: SystemTime,
+    pub crtime: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// Path relative to the bucket root, empty for the root itself
+    path: String,
+    kind: NodeKind,
+    size: u64,
+    mtime: SystemTime,
+    crtime: SystemTime,
+    /// Created through the gateway but not stored in the bucket yet
+    local: bool,
+}
+
+/// Open file with its write-back buffer
+struct OpenFile {
+    ino: u64,
+    writable: bool,
+    /// Object contents, loaded on first read or write
+    data: Option<Vec<u8>>,
+    dirty: bool,
+    /// Object version the buffer is based on, `None` for new files
+    base: Option<ObjectMetadata>,
+}
+
+/// Filesystem view of a bucket.
+///
+/// Files map to objects named `<bucket>/<path>` and directories are derived from
+/// key prefixes. Writes are buffered per open file and uploaded on flush or close,
+/// failing with [`GatewayError::ConcurrentModification`] when the object changed
+/// in the bucket since it was opened.
+pub struct BucketFs {
+    storage: Arc<dyn StorageBackend>,
+    config: GatewayConfig,
+    nodes: HashMap<u64, Node>,
+    paths: HashMap<String, u64>,
+    next_ino: u64,
+    handles: HashMap<u64, OpenFile>,
+    next_fh: u64,
+}
+
+impl BucketFs {
+    pub fn new(storage: Arc<dyn StorageBackend>, config: GatewayConfig) -> Self {
+        let now = SystemTime::now();
+        let root = Node {
+            path: String::new(),
+            kind: NodeKind::Directory,
+            size: 0,
+            mtime: now,
+            crtime: now,
+            local: false,
+        };
+
+        Self {
+            storage,
+            config,
+            nodes: HashMap::from([(ROOT_INO, root)]),
+            paths: HashMap::from([(String::new(), ROOT_INO)]),
+            next_ino: ROOT_INO + 1,
+            handles: HashMap::new(),
+            next_fh: 1,
+        }
+    }
+
+    pub fn config(&self) -> &GatewayConfig {
+        &self.config
+    }
+
+    /// Get cached attributes of a known inode
+    pub fn getattr(&self, ino: u64) -> GatewayResult<NodeAttr> {
+        let node = self.node(ino)?;
+        Ok(NodeAttr {
+            ino,
+            kind: node.kind,
+            size: node.size,
+            mtime: node.mtime,
+            crtime: node.crtime,
+        })
+    }
+
+    /// Resolve a name inside a directory
+    pub async fn lookup(&mut self, parent: u64, name: &str) -> GatewayResult<NodeAttr> {
+        let path = self.child_path(parent, name)?;
+
+        if let Some(&ino) = self.paths.get(&path) {
+            if self.nodes[&ino].local || self.is_open(ino) {
+                return self.getattr(ino);
+            }
+        }
+
+        if let Some(metadata) = self.head(&path).await? {
+            let ino = self.upsert_file(&path, &metadata);
+            return self.getattr(ino);
+        }
+
+        if self.has_remote_children(&path).await? {
+            let ino = self.upsert_dir(&path, false);
+            return self.getattr(ino);
+        }
+
+        self.remove_node(&path);
+        Err(GatewayError::NotFound(path))
+    }
+
+    /// List a directory: objects directly under its prefix become files and
+    /// deeper key prefixes become subdirectories
+    pub async fn readdir(&mut self, ino: u64) -> GatewayResult<Vec<(u64, NodeKind, String)>> {
+        let dir = self.node(ino)?.clone();
+        if dir.kind != NodeKind::Directory {
+            return Err(GatewayError::NotADirectory(dir.path));
+        }
+
+        let prefix = self.object_prefix(&dir.path);
+        let mut children: BTreeMap<String, Option<ObjectMetadata>> = BTreeMap::new();
+        for metadata in self.storage.list(Some(&prefix), None).await? {
+            let Some(rest) = metadata.name.strip_prefix(&prefix) else {
+                continue;
+            };
+            match rest.split_once('/') {
+                // A prefix shadows an object with the same name
+                Some((child, _)) => {
+                    children.insert(child.to_string(), None);
+                }
+                None if !rest.is_empty() => {
+                    let child = rest.to_string();
+                    children.entry(child).or_insert(Some(metadata));
+                }
+                None => {}
+            }
+        }
+
+        let mut entries = Vec::with_capacity(children.len());
+        let mut seen = HashSet::new();
+        for (name, metadata) in children {
+            let path = join_path(&dir.path, &name);
+            let (ino, kind) = match metadata {
+                Some(metadata) => (self.upsert_file(&path, &metadata), NodeKind::File),
+                None => (self.upsert_dir(&path, false), NodeKind::Directory),
+            };
+            seen.insert(name.clone());
+            entries.push((ino, kind, name));
+        }
+
+        // Entries created through the gateway that are not in the bucket yet
+        for (ino, node) in &self.nodes {
+            if !node.local || parent_path(&node.path) != Some(dir.path.as_str()) {
+                continue;
+            }
+            let name = file_name(&node.path).to_string();
+            if seen.insert(name.clone()) {
+                entries.push((*ino, node.kind, name));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Open a file, returning a handle for subsequent reads and writes
+    pub async fn open(&mut self, ino: u64, write: bool, truncate: bool) -> GatewayResult<u64> {
+        let node = self.node(ino)?.clone();
+        if node.kind == NodeKind::Directory {
+            return Err(GatewayError::IsADirectory(node.path));
+        }
+
+        if write {
+            self.ensure_writable()?;
+            if self.handles.values().any(|h| h.ino == ino && h.writable) {
+                return Err(GatewayError::Busy(node.path));
+            }
+        }
+
+        let base = if node.local {
+            None
+        } else {
+            match self.head(&node.path).await? {
+                Some(metadata) => Some(metadata),
+                None => {
+                    self.remove_node(&node.path);
+                    return Err(GatewayError::NotFound(node.path));
+                }
+            }
+        };
+
+        let mut file = OpenFile {
+            ino,
+            writable: write,
+            data: node.local.then(Vec::new),
+            dirty: false,
+            base,
+        };
+
+        if write && truncate {
+            file.data = Some(Vec::new());
+            file.dirty = true;
+            self.touch(ino, 0);
+        }
+
+        Ok(self.insert_handle(file))
+    }
+
+    /// Create and open a new empty file
+    pub async fn create(&mut self, parent: u64, name: &str) -> GatewayResult<(NodeAttr, u64)> {
+        self.ensure_writable()?;
+        let path = self.child_path(parent, name)?;
+
+        let known_locally = self
+            .paths
+            .get(&path)
+            .is_some_and(|ino| self.nodes[ino].local || self.is_open(*ino));
+        if known_locally || self.head(&path).await?.is_some() {
+            return Err(GatewayError::AlreadyExists(path));
+        }
+
+        let now = SystemTime::now();
+        let ino = self.upsert_node(path, NodeKind::File, 0, now, now, true);
+        let fh = self.insert_handle(OpenFile {
+            ino,
+            writable: true,
+            data: Some(Vec::new()),
+            dirty: true,
+            base: None,
+        });
+
+        Ok((self.getattr(ino)?, fh))
+    }
+
+    /// Read from an open file
+    pub async fn read(&mut self, fh: u64, offset: u64, size: usize) -> GatewayResult<Vec<u8>> {
+        self.load(fh).await?;
+
+        let data = self.handle(fh)?.data.as_deref().unwrap_or_default();
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(size).min(data.len());
+        Ok(data[start..end].to_vec())
+    }
+
+    /// Write into the buffer of an open file; the object is uploaded on flush
+    pub async fn write(&mut self, fh: u64, offset: u64, bytes: &[u8]) -> GatewayResult<u32> {
+        if !self.handle(fh)?.writable {
+            return Err(GatewayError::BadHandle(fh));
+        }
+
+        let end = offset + bytes.len() as u64;
+        if end > self.config.max_file_size {
+            return Err(GatewayError::FileTooLarge(end));
+        }
+
+        self.load(fh).await?;
+
+        let file = self.handle_mut(fh)?;
+        let buffer = file.data.get_or_insert_with(Vec::new);
+        if buffer.len() < end as usize {
+            buffer.resize(end as usize, 0);
+        }
+        buffer[offset as usize..end as usize].copy_from_slice(bytes);
+        file.dirty = true;
+
+        let (ino, size) = (file.ino, buffer.len() as u64);
+        self.touch(ino, size);
+        Ok(bytes.len() as u32)
+    }
+
+    /// Change the size of a file, through its handle when one is given
+    pub async fn set_size(&mut self, ino: u64, fh: Option<u64>, size: u64) -> GatewayResult<NodeAttr> {
+        if size > self.config.max_file_size {
+            return Err(GatewayError::FileTooLarge(size));
+        }
+
+        match fh {
+            Some(fh) => self.truncate(fh, size).await?,
+            None => {
+                let fh = self.open(ino, true, size == 0).await?;
+                let result = match self.truncate(fh, size).await {
+                    Ok(()) => self.flush(fh).await,
+                    Err(error) => Err(error),
+                };
+                self.handles.remove(&fh);
+                result?;
+            }
+        }
+
+        self.getattr(ino)
+    }
+
+    /// Upload the buffered contents of a file if they changed
+    pub async fn flush(&mut self, fh: u64) -> GatewayResult<()> {
+        let file = self.handle(fh)?;
+        if !file.dirty {
+            return Ok(());
+        }
+
+        let ino = file.ino;
+        let base = file.base.clone();
+        let data = file.data.clone().unwrap_or_default();
+        let path = self.node(ino)?.path.clone();
+
+        let current = self.head(&path).await?;
+        let conflict = match (&base, &current) {
+            (Some(base), Some(current)) => {
+                base.checksum != current.checksum || base.version != current.version
+            }
+            (Some(_), None) | (None, Some(_)) => true,
+            (None, None) => false,
+        };
+        if conflict {
+            tracing::warn!("Refusing to overwrite {} modified outside the gateway", path);
+            return Err(GatewayError::ConcurrentModification(path));
+        }
+
+        let object = self.build_object(&path, data, base.as_ref());
+        let metadata = object.metadata.clone();
+        self.storage.put(object).await?;
+        tracing::debug!("Flushed {} ({} bytes) to bucket {}", path, metadata.size, self.config.bucket);
+
+        let file = self.handle_mut(fh)?;
+        file.dirty = false;
+        file.base = Some(metadata.clone());
+        self.upsert_file(&path, &metadata);
+        Ok(())
+    }
+
+    /// Flush and close a file handle
+    pub async fn release(&mut self, fh: u64) -> GatewayResult<()> {
+        let result = self.flush(fh).await;
+        if let Some(file) = self.handles.remove(&fh) {
+            let unsaved = self.nodes.get(&file.ino).is_some_and(|node| node.local);
+            if unsaved && !self.is_open(file.ino) {
+                let path = self.nodes[&file.ino].path.clone();
+                self.remove_node(&path);
+            }
+        }
+        result
+    }
+
+    /// Create a directory.
+    ///
+    /// Buckets have no empty prefixes, so the directory only exists in the
+    /// gateway until a file is written into it.
+    pub async fn mkdir(&mut self, parent: u64, name: &str) -> GatewayResult<NodeAttr> {
+        self.ensure_writable()?;
+        if self.lookup(parent, name).await.is_ok() {
+            return Err(GatewayError::AlreadyExists(self.child_path(parent, name)?));
+        }
+
+        let path = self.child_path(parent, name)?;
+        let ino = self.upsert_dir(&path, true);
+        self.getattr(ino)
+    }
+
+    /// Delete the object behind a file
+    pub async fn unlink(&mut self, parent: u64, name: &str) -> GatewayResult<()> {
+        self.ensure_writable()?;
+        let attr = self.lookup(parent, name).await?;
+        let path = self.node(attr.ino)?.path.clone();
+
+        if attr.kind == NodeKind::Directory {
+            return Err(GatewayError::IsADirectory(path));
+        }
+        if self.handles.values().any(|h| h.ino == attr.ino && h.writable) {
+            return Err(GatewayError::Busy(path));
+        }
+
+        if !self.nodes[&attr.ino].local {
+            match self.storage.delete(&self.object_name(&path)).await {
+                Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        self.remove_node(&path);
+        Ok(())
+    }
+
+    /// Remove an empty directory
+    pub async fn rmdir(&mut self, parent: u64, name: &str) -> GatewayResult<()> {
+        self.ensure_writable()?;
+        let attr = self.lookup(parent, name).await?;
+        let path = self.node(attr.ino)?.path.clone();
+
+        if attr.kind != NodeKind::Directory {
+            return Err(GatewayError::NotADirectory(path));
+        }
+
+        let has_local_children = self
+            .nodes
+            .values()
+            .any(|node| node.local && parent_path(&node.path) == Some(path.as_str()));
+        if has_local_children || self.has_remote_children(&path).await? {
+            return Err(GatewayError::DirectoryNotEmpty(path));
+        }
+
+        self.remove_node(&path);
+        Ok(())
+    }
+
+    async fn truncate(&mut self, fh: u64, size: u64) -> GatewayResult<()> {
+        if !self.handle(fh)?.writable {
+            return Err(GatewayError::BadHandle(fh));
+        }
+
+        if size == 0 {
+            self.handle_mut(fh)?.data = Some(Vec::new());
+        } else {
+            self.load(fh).await?;
+        }
+
+        let file = self.handle_mut(fh)?;
+        let buffer = file.data.get_or_insert_with(Vec::new);
+        buffer.resize(size as usize, 0);
+        file.dirty = true;
+
+        let ino = file.ino;
+        self.touch(ino, size);
+        Ok(())
+    }
+
+    /// Fetch the object behind a handle into its buffer
+    async fn load(&mut self, fh: u64) -> GatewayResult<()> {
+        let file = self.handle(fh)?;
+        if file.data.is_some() {
+            return Ok(());
+        }
+
+        let path = self.node(file.ino)?.path.clone();
+        let object = match self.storage.get(&self.object_name(&path)).await {
+            Ok(object) => object,
+            Err(NimbuxError::ObjectNotFound { .. }) => return Err(GatewayError::NotFound(path)),
+            Err(error) => return Err(error.into()),
+        };
+
+        // Reads see the latest contents, so writes build on top of them
+        let file = self.handle_mut(fh)?;
+        file.base = Some(object.metadata);
+        file.data = Some(object.data);
+        Ok(())
+    }
+
+    async fn head(&self, path: &str) -> GatewayResult<Option<ObjectMetadata>> {
+        match self.storage.head(&self.object_name(path)).await {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(NimbuxError::ObjectNotFound { .. }) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn has_remote_children(&self, path: &str) -> GatewayResult<bool> {
+        let prefix = self.object_prefix(path);
+        Ok(!self.storage.list(Some(&prefix), Some(1)).await?.is_empty())
+    }
+
+    fn build_object(&self, path: &str, data: Vec<u8>, base: Option<&ObjectMetadata>) -> Object {
+        let name = self.object_name(path);
+        let content_type = base.and_then(|base| base.content_type.clone());
+        let mut object = Object::with_id(name.clone(), name, data, content_type);
+
+        if let Some(base) = base {
+            object.metadata.version = base.version + 1;
+            object.metadata.created_at = base.created_at;
+            object.metadata.tags = base.tags.clone();
+        }
+
+        object
+    }
+
+    fn object_name(&self, path: &str) -> String {
+        format!("{}/{}", self.config.bucket, path)
+    }
+
+    /// Key prefix shared by every object below a directory
+    fn object_prefix(&self, dir_path: &str) -> String {
+        if dir_path.is_empty() {
+            format!("{}/", self.config.bucket)
+        } else {
+            format!("{}/{}/", self.config.bucket, dir_path)
+        }
+    }
+
+    fn child_path(&self, parent: u64, name: &str) -> GatewayResult<String> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(GatewayError::InvalidName(name.to_string()));
+        }
+
+        let parent = self.node(parent)?;
+        if parent.kind != NodeKind::Directory {
+            return Err(GatewayError::NotADirectory(parent.path.clone()));
+        }
+
+        Ok(join_path(&parent.path, name))
+    }
+
+    fn ensure_writable(&self) -> GatewayResult<()> {
+        if self.config.read_only {
+            return Err(GatewayError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    fn node(&self, ino: u64) -> GatewayResult<&Node> {
+        self.nodes
+            .get(&ino)
+            .ok_or_else(|| GatewayError::NotFound(format!("inode {}", ino)))
+    }
+
+    fn handle(&self, fh: u64) -> GatewayResult<&OpenFile> {
+        self.handles.get(&fh).ok_or(GatewayError::BadHandle(fh))
+    }
+
+    fn handle_mut(&mut self, fh: u64) -> GatewayResult<&mut OpenFile> {
+        self.handles.get_mut(&fh).ok_or(GatewayError::BadHandle(fh))
+    }
+
+    fn insert_handle(&mut self, file: OpenFile) -> u64 {
+        let fh = self.next_fh;
+        self.next_fh += 1;
+        self.handles.insert(fh, file);
+        fh
+    }
+
+    fn is_open(&self, ino: u64) -> bool {
+        self.handles.values().any(|h| h.ino == ino)
+    }
+
+    fn is_dirty(&self, ino: u64) -> bool {
+        self.handles.values().any(|h| h.ino == ino && h.dirty)
+    }
+
+    /// Record a local size change
+    fn touch(&mut self, ino: u64, size: u64) {
+        if let Some(node) = self.nodes.get_mut(&ino) {
+            node.size = size;
+            node.mtime = SystemTime::now();
+        }
+    }
+
+    fn upsert_file(&mut self, path: &str, metadata: &ObjectMetadata) -> u64 {
+        // Unflushed writes take precedence over what the bucket reports
+        if let Some(&ino) = self.paths.get(path) {
+            if self.is_dirty(ino) {
+                return ino;
+            }
+        }
+
+        self.upsert_node(
+            path.to_string(),
+            NodeKind::File,
+            metadata.size,
+            UNIX_EPOCH + Duration::from_secs(metadata.updated_at),
+            UNIX_EPOCH + Duration::from_secs(metadata.created_at),
+            false,
+        )
+    }
+
+    fn upsert_dir(&mut self, path: &str, local: bool) -> u64 {
+        if let Some(&ino) = self.paths.get(path) {
+            if let Some(node) = self.nodes.get_mut(&ino) {
+                if node.kind == NodeKind::Directory {
+                    node.local &= local;
+                    return ino;
+                }
+            }
+        }
+
+        let now = SystemTime::now();
+        self.upsert_node(path.to_string(), NodeKind::Directory, 0, now, now, local)
+    }
+
+    fn upsert_node(
+        &mut self,
+        path: String,
+        kind: NodeKind,
+        size: u64,
+        mtime: SystemTime,
+        crtime: SystemTime,
+        local: bool,
+    ) -> u64 {
+        let node = Node {
+            path: path.clone(),
+            kind,
+            size,
+            mtime,
+            crtime,
+            local,
+        };
+
+        if let Some(&ino) = self.paths.get(&path) {
+            self.nodes.insert(ino, node);
+            return ino;
+        }
+
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.nodes.insert(ino, node);
+        self.paths.insert(path, ino);
+        ino
+    }
+
+    fn remove_node(&mut self, path: &str) {
+        if path.is_empty() {
+            return;
+        }
+        if let Some(ino) = self.paths.remove(path) {
+            self.nodes.remove(&ino);
+        }
+    }
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn parent_path(path: &str) -> Option<&str> {
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.rsplit_once('/').map_or("", |(parent, _)| parent))
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn bucket_fs() -> (Arc<MemoryStorage>, BucketFs) {
+        let storage = Arc::new(MemoryStorage::new());
+        let fs = BucketFs::new(storage.clone(), GatewayConfig::new("photos".to_string()));
+        (storage, fs)
+    }
+
+    async fn put(storage: &MemoryStorage, name: &str, data: &[u8]) {
+        let object = Object::with_id(name.to_string(), name.to_string(), data.to_vec(), None);
+        storage.put(object).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_readdir_maps_prefixes_to_directories() {
+        let (storage, mut fs) = bucket_fs();
+        put(&storage, "photos/2024/a.jpg", b"a").await;
+        put(&storage, "photos/2024/b.jpg", b"b").await;
+        put(&storage, "photos/readme.txt", b"hello").await;
+        put(&storage, "videos/c.mp4", b"c").await;
+
+        let mut entries = fs.readdir(ROOT_INO).await.unwrap();
+        entries.sort_by(|a, b| a.2.cmp(&b.2));
+        let names: Vec<_> = entries.iter().map(|(_, kind, name)| (name.as_str(), *kind)).collect();
+        assert_eq!(names, vec![("2024", NodeKind::Directory), ("readme.txt", NodeKind::File)]);
+
+        let readme = fs.lookup(ROOT_INO, "readme.txt").await.unwrap();
+        assert_eq!(readme.size, 5);
+    }
+
+    #[tokio::test]
+    async fn test_write_back_on_release() {
+        let (storage, mut fs) = bucket_fs();
+        let (_, fh) = fs.create(ROOT_INO, "notes.txt").await.unwrap();
+        fs.write(fh, 0, b"hello").await.unwrap();
+        fs.write(fh, 5, b" world").await.unwrap();
+        assert!(!storage.exists("photos/notes.txt").await.unwrap());
+
+        fs.release(fh).await.unwrap();
+        let object = storage.get("photos/notes.txt").await.unwrap();
+        assert_eq!(object.data, b"hello world");
+
+        let attr = fs.lookup(ROOT_INO, "notes.txt").await.unwrap();
+        let fh = fs.open(attr.ino, false, false).await.unwrap();
+        assert_eq!(fs.read(fh, 6, 100).await.unwrap(), b"world");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_concurrent_modification() {
+        let (storage, mut fs) = bucket_fs();
+        put(&storage, "photos/report.csv", b"v1").await;
+
+        let attr = fs.lookup(ROOT_INO, "report.csv").await.unwrap();
+        let fh = fs.open(attr.ino, true, false).await.unwrap();
+        fs.write(fh, 0, b"v2").await.unwrap();
+
+        put(&storage, "photos/report.csv", b"changed elsewhere").await;
+        let result = fs.flush(fh).await;
+        assert!(matches!(result, Err(GatewayError::ConcurrentModification(_))));
+        assert_eq!(storage.get("photos/report.csv").await.unwrap().data, b"changed elsewhere");
+    }
+
+    #[tokio::test]
+    async fn test_single_writer_per_file() {
+        let (storage, mut fs) = bucket_fs();
+        put(&storage, "photos/log.txt", b"entry").await;
+
+        let attr = fs.lookup(ROOT_INO, "log.txt").await.unwrap();
+        let _writer = fs.open(attr.ino, true, false).await.unwrap();
+        assert!(matches!(fs.open(attr.ino, true, false).await, Err(GatewayError::Busy(_))));
+        assert!(fs.open(attr.ino, false, false).await.is_ok());
+    }
+}
@@ -0,0 +1,345 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// FUSE adapter for the bucket filesystem
+
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use fuser::{
+    FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory,
+    ReplyEmpty, ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow,
+};
+use tokio::runtime::Handle;
+
+use super::{BucketFs, GatewayConfig, GatewayError, NodeAttr, NodeKind};
+use crate::errors::{NimbuxError, Result};
+use crate::storage::StorageBackend;
+
+const BLOCK_SIZE: u32 = 4096;
+
+/// FUSE filesystem serving a single bucket.
+///
+/// FUSE callbacks run on the session thread, so storage calls are driven to
+/// completion on the provided Tokio runtime.
+pub struct FuseGateway {
+    fs: BucketFs,
+    runtime: Handle,
+}
+
+impl FuseGateway {
+    pub fn new(storage: Arc<dyn StorageBackend>, config: GatewayConfig, runtime: Handle) -> Self {
+        Self {
+            fs: BucketFs::new(storage, config),
+            runtime,
+        }
+    }
+
+    fn file_attr(&self, attr: &NodeAttr) -> FileAttr {
+        let config = self.fs.config();
+        let (kind, perm, nlink) = match attr.kind {
+            NodeKind::File => (FileType::RegularFile, config.file_mode, 1),
+            NodeKind::Directory => (FileType::Directory, config.dir_mode, 2),
+        };
+
+        FileAttr {
+            ino: attr.ino,
+            size: attr.size,
+            blocks: attr.size.div_ceil(512),
+            atime: attr.mtime,
+            mtime: attr.mtime,
+            ctime: attr.mtime,
+            crtime: attr.crtime,
+            kind,
+            perm,
+            nlink,
+            uid: config.uid,
+            gid: config.gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE,
+            flags: 0,
+        }
+    }
+}
+
+/// Mount a bucket and serve it until the filesystem is unmounted.
+///
+/// Blocks the calling thread, so call it from `spawn_blocking` when running
+/// inside the runtime passed in.
+pub fn mount(
+    storage: Arc<dyn StorageBackend>,
+    config: GatewayConfig,
+    mountpoint: &Path,
+    runtime: Handle,
+) -> Result<()> {
+    let mut options = vec![
+        MountOption::FSName(format!("nimbux:{}", config.bucket)),
+        MountOption::DefaultPermissions,
+    ];
+    if config.read_only {
+        options.push(MountOption::RO);
+    }
+
+    tracing::info!("Mounting bucket {} at {}", config.bucket, mountpoint.display());
+    let gateway = FuseGateway::new(storage, config, runtime);
+    fuser::mount2(gateway, mountpoint, &options)?;
+    tracing::info!("Unmounted {}", mountpoint.display());
+    Ok(())
+}
+
+impl Filesystem for FuseGateway {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let Some(name) = name.to_str() else {
+            return reply.error(libc::EINVAL);
+        };
+
+        match self.runtime.block_on(self.fs.lookup(parent, name)) {
+            Ok(attr) => reply.entry(&self.fs.config().attr_ttl, &self.file_attr(&attr), 0),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        match self.fs.getattr(ino) {
+            Ok(attr) => reply.attr(&self.fs.config().attr_ttl, &self.file_attr(&attr)),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    /// Only size changes are applied; ownership, mode and times are derived
+    /// from the gateway config and object metadata
+    fn setattr(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        let result = match size {
+            Some(size) => self.runtime.block_on(self.fs.set_size(ino, fh, size)),
+            None => self.fs.getattr(ino),
+        };
+
+        match result {
+            Ok(attr) => reply.attr(&self.fs.config().attr_ttl, &self.file_attr(&attr)),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let children = match self.runtime.block_on(self.fs.readdir(ino)) {
+            Ok(children) => children,
+            Err(error) => return reply.error(errno(&error)),
+        };
+
+        let mut entries = vec![
+            (ino, NodeKind::Directory, ".".to_string()),
+            (ino, NodeKind::Directory, "..".to_string()),
+        ];
+        entries.extend(children);
+
+        for (index, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(ino, (index + 1) as i64, file_type(kind), name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        let write = flags & libc::O_ACCMODE != libc::O_RDONLY;
+        let truncate = flags & libc::O_TRUNC != 0;
+
+        match self.runtime.block_on(self.fs.open(ino, write, truncate)) {
+            Ok(fh) => reply.opened(fh, 0),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        if offset < 0 {
+            return reply.error(libc::EINVAL);
+        }
+
+        match self.runtime.block_on(self.fs.read(fh, offset as u64, size as usize)) {
+            Ok(data) => reply.data(&data),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        if offset < 0 {
+            return reply.error(libc::EINVAL);
+        }
+
+        match self.runtime.block_on(self.fs.write(fh, offset as u64, data)) {
+            Ok(written) => reply.written(written),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn flush(&mut self, _req: &Request<'_>, _ino: u64, fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
+        match self.runtime.block_on(self.fs.flush(fh)) {
+            Ok(()) => reply.ok(),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn fsync(&mut self, _req: &Request<'_>, _ino: u64, fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        match self.runtime.block_on(self.fs.flush(fh)) {
+            Ok(()) => reply.ok(),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn release(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        match self.runtime.block_on(self.fs.release(fh)) {
+            Ok(()) => reply.ok(),
+            Err(error) => {
+                tracing::warn!("Discarding unsaved changes on release: {}", error);
+                reply.error(errno(&error))
+            }
+        }
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let Some(name) = name.to_str() else {
+            return reply.error(libc::EINVAL);
+        };
+
+        match self.runtime.block_on(self.fs.create(parent, name)) {
+            Ok((attr, fh)) => {
+                reply.created(&self.fs.config().attr_ttl, &self.file_attr(&attr), 0, fh, 0)
+            }
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn mkdir(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        let Some(name) = name.to_str() else {
+            return reply.error(libc::EINVAL);
+        };
+
+        match self.runtime.block_on(self.fs.mkdir(parent, name)) {
+            Ok(attr) => reply.entry(&self.fs.config().attr_ttl, &self.file_attr(&attr), 0),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let Some(name) = name.to_str() else {
+            return reply.error(libc::EINVAL);
+        };
+
+        match self.runtime.block_on(self.fs.unlink(parent, name)) {
+            Ok(()) => reply.ok(),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let Some(name) = name.to_str() else {
+            return reply.error(libc::EINVAL);
+        };
+
+        match self.runtime.block_on(self.fs.rmdir(parent, name)) {
+            Ok(()) => reply.ok(),
+            Err(error) => reply.error(errno(&error)),
+        }
+    }
+}
+
+fn file_type(kind: NodeKind) -> FileType {
+    match kind {
+        NodeKind::File => FileType::RegularFile,
+        NodeKind::Directory => FileType::Directory,
+    }
+}
+
+fn errno(error: &GatewayError) -> i32 {
+    match error {
+        GatewayError::NotFound(_) => libc::ENOENT,
+        GatewayError::AlreadyExists(_) => libc::EEXIST,
+        GatewayError::NotADirectory(_) => libc::ENOTDIR,
+        GatewayError::IsADirectory(_) => libc::EISDIR,
+        GatewayError::DirectoryNotEmpty(_) => libc::ENOTEMPTY,
+        GatewayError::Busy(_) => libc::EBUSY,
+        GatewayError::ConcurrentModification(_) => libc::ESTALE,
+        GatewayError::ReadOnly => libc::EROFS,
+        GatewayError::InvalidName(_) => libc::EINVAL,
+        GatewayError::BadHandle(_) => libc::EBADF,
+        GatewayError::FileTooLarge(_) => libc::EFBIG,
+        GatewayError::Storage(NimbuxError::QuotaExceeded(_)) => libc::EDQUOT,
+        GatewayError::Storage(NimbuxError::ObjectLocked { .. }) => libc::EPERM,
+        GatewayError::Storage(_) => libc::EIO,
+    }
+}
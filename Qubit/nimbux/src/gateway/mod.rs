@@ -0,0 +1,106 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Filesystem gateway for mounting buckets
+
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::errors::NimbuxError;
+
+pub mod bucket_fs;
+#[cfg(feature = "fuse")]
+pub mod fuse;
+
+pub use bucket_fs::{BucketFs, NodeAttr, NodeKind, ROOT_INO};
+#[cfg(feature = "fuse")]
+pub use fuse::{mount, FuseGateway};
+
+/// Filesystem gateway configuration
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    /// Bucket exposed as the filesystem root
+    pub bucket: String,
+    /// Reject every operation that would modify the bucket
+    pub read_only: bool,
+    /// Largest file that can be buffered for write-back, in bytes
+    pub max_file_size: u64,
+    /// How long the kernel may cache attributes and lookups
+    pub attr_ttl: Duration,
+    /// Permission bits reported for files
+    pub file_mode: u16,
+    /// Permission bits reported for directories
+    pub dir_mode: u16,
+    /// Owner reported for every entry
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl GatewayConfig {
+    pub fn new(bucket: String) -> Self {
+        Self {
+            bucket,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            bucket: "default".to_string(),
+            read_only: false,
+            max_file_size: 1024 * 1024 * 1024, // 1GB
+            attr_ttl: Duration::from_secs(1),
+            file_mode: 0o644,
+            dir_mode: 0o755,
+            uid: 0,
+            gid: 0,
+        }
+    }
+}
+
+/// Errors surfaced by filesystem operations, mapped to errno values by the FUSE layer
+#[derive(Error, Debug)]
+pub enum GatewayError {
+    #[error("No such file or directory: {0}")]
+    NotFound(String),
+
+    #[error("File exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+
+    #[error("Is a directory: {0}")]
+    IsADirectory(String),
+
+    #[error("Directory not empty: {0}")]
+    DirectoryNotEmpty(String),
+
+    #[error("File is open for writing elsewhere: {0}")]
+    Busy(String),
+
+    #[error("Object was modified concurrently: {0}")]
+    ConcurrentModification(String),
+
+    #[error("Gateway is mounted read-only")]
+    ReadOnly,
+
+    #[error("Invalid file name: {0}")]
+    InvalidName(String),
+
+    #[error("Invalid file handle: {0}")]
+    BadHandle(u64),
+
+    #[error("File too large: {0} bytes")]
+    FileTooLarge(u64),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] NimbuxError),
+}
+
+/// Result type alias for gateway operations
+pub type GatewayResult<T> = std::result::Result<T, GatewayError>;
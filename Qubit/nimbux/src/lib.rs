@@ -18,3 +18,4 @@ pub mod performance;
 pub mod transfer;
 pub mod durability;
 pub mod security;
+pub mod gateway;
@@ -7,16 +7,17 @@
 use std::sync::Arc;
 use tracing_subscriber;
 
-use nimbux::errors::Result;
-use nimbux::storage::{MemoryStorage, ContentAddressableStorage, StorageEngine};
-use nimbux::network::{SimpleHttpServer, TcpServer, NimbuxApiServer};
-use nimbux::auth::AuthManager;
-use nimbux::observability::MetricsCollector;
-use nimbux::cluster::{ClusterManager, ClusterConfig};
-use nimbux::performance::{PerformanceManager, PerformanceConfig};
+use nimbux::errors::{NimbuxError, Result};
+use nimbux::storage::{StorageClass, MemoryStorage, ContentAddressableStorage, StorageEngine, IntegrityManager, IntegrityConfig, RestoreCoordinator, RestoreConfig, HttpRestoreNotifier, VersionedStorage, LifecycleEngine, InventoryExporter, ImageTransformer, TransformerConfig, EventNotifier, NotificationConfig, HttpEventSink, NotifyingStorage, StorageBackend};
+use nimbux::network::{SimpleHttpServer, TcpServer, NimbuxApiServer, S3Server, S3Config, DomainConfig, DomainRegistry};
+use nimbux::auth::{AuthManager, Presigner};
+use nimbux::observability::{MetricsCollector, OperatorDashboard, DashboardConfig};
+use nimbux::cluster::{ClusterManager, ClusterConfig, CrossRegionReplicator, CrossRegionConfig, HttpReplicationTarget, ReplicatingStorage};
+use nimbux::performance::{PerformanceManager, PerformanceConfig, AdmissionController, AdmissionConfig};
 use nimbux::transfer::{TransferManager, TransferConfig};
 use nimbux::durability::{DurabilityManager, DurabilityConfig};
-use nimbux::security::{SecurityManager, SecurityConfig};
+use nimbux::security::{SecurityManager, SecurityConfig, ComplianceManager, ComplianceConfig, ErasureCoordinator, ErasureConfig, NetworkPolicyEngine, NetworkPolicyConfig, CidrGeoIp};
+use nimbux::metadata::TagIndex;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -25,17 +26,49 @@ async fn main() -> Result<()> {
     
     tracing::info!("Starting Nimbux server...");
     
-    // Create storage backends
+    // Create metrics collector
+    let metrics = Arc::new(MetricsCollector::new());
+    
+    // Create storage backends; content is persisted when a data directory is set
     let memory_storage = Arc::new(MemoryStorage::new());
-    let content_storage = Arc::new(ContentAddressableStorage::new());
+    let content_storage = match std::env::var("NIMBUX_DATA_DIR") {
+        Ok(dir) => ContentAddressableStorage::open(dir)?,
+        Err(_) => ContentAddressableStorage::new(),
+    };
+    let content_storage = Arc::new(content_storage.with_metrics(Arc::clone(&metrics)));
+    let gc_interval = std::env::var("NIMBUX_GC_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(600);
+    Arc::clone(&content_storage).spawn_garbage_collector(std::time::Duration::from_secs(gc_interval));
+    
+    // Backends of the reduced-redundancy and cold storage classes. Cold
+    // buckets stay readable, unlike objects archived to the cold tier
+    let reduced_storage = match std::env::var("NIMBUX_REDUCED_REDUNDANCY_DIR") {
+        Ok(dir) => ContentAddressableStorage::open(dir)?,
+        Err(_) => ContentAddressableStorage::new(),
+    };
+    let cold_class_storage = match std::env::var("NIMBUX_COLD_CLASS_DIR") {
+        Ok(dir) => ContentAddressableStorage::open(dir)?,
+        Err(_) => ContentAddressableStorage::new(),
+    };
     
     // Create storage engine with content-addressable storage as default
     let mut storage_engine = StorageEngine::new("content".to_string());
     storage_engine.add_backend("memory".to_string(), Box::new(MemoryStorage::new()));
-    storage_engine.add_backend("content".to_string(), Box::new(ContentAddressableStorage::new()));
+    storage_engine.add_backend("content".to_string(), Box::new(Arc::clone(&content_storage)));
+    storage_engine.add_backend("reduced".to_string(), Box::new(reduced_storage));
+    storage_engine.add_backend("cold".to_string(), Box::new(cold_class_storage));
+    storage_engine.set_class_backend(StorageClass::ReducedRedundancy, "reduced".to_string());
+    storage_engine.set_class_backend(StorageClass::Cold, "cold".to_string());
+    storage_engine.load_placement().await?;
     
     let storage = Arc::new(storage_engine);
     
+    // Client-facing servers write through the versioning layer; internal jobs
+    // such as erasure and restores work on the underlying storage
+    let versioned_storage = Arc::new(VersionedStorage::new(storage.clone()));
+    
     // Create authentication manager
     let auth_manager = Arc::new(AuthManager::new());
     
@@ -65,9 +98,6 @@ async fn main() -> Result<()> {
     tracing::info!("Created admin user with access key: {}", admin_key.access_key_id);
     tracing::info!("Admin secret key: {}", admin_key.secret_access_key);
     
-    // Create metrics collector
-    let metrics = Arc::new(MetricsCollector::new());
-    
     // Create cluster manager for elastic scalability
     let cluster_config = ClusterConfig::default();
     let cluster_manager = Arc::new(ClusterManager::new(cluster_config)?);
@@ -86,7 +116,162 @@ async fn main() -> Result<()> {
     
     // Create security manager for security and data protection
     let security_config = SecurityConfig::default();
-    let security_manager = Arc::new(SecurityManager::new(security_config)?);
+    let compliance_manager = Arc::new(
+        ComplianceManager::new(ComplianceConfig {
+            enable_compliance: security_config.enable_compliance,
+            standards: security_config.compliance_standards.clone(),
+            enable_monitoring: true,
+            enable_reporting: true,
+        })?
+        .with_storage(storage.clone()),
+    );
+    let security_manager = Arc::new(
+        SecurityManager::new(security_config)?.with_compliance_manager(Arc::clone(&compliance_manager)),
+    );
+    
+    // Create erasure coordinator for GDPR deletion requests from Pixelle
+    let signing_key = std::env::var("NIMBUX_ERASURE_SIGNING_KEY").unwrap_or_else(|_| {
+        tracing::warn!("NIMBUX_ERASURE_SIGNING_KEY not set, erasure certificates will not verify after restart");
+        uuid::Uuid::new_v4().to_string()
+    });
+    let erasure_coordinator = Arc::new(ErasureCoordinator::new(
+        storage.clone(),
+        Arc::new(TagIndex::new()),
+        ErasureConfig::new(signing_key),
+    ));
+    
+    // Create restore coordinator bringing archived objects back from the cold tier
+    let cold_storage = match std::env::var("NIMBUX_COLD_DIR") {
+        Ok(dir) => ContentAddressableStorage::open(dir)?,
+        Err(_) => ContentAddressableStorage::new(),
+    };
+    let mut restore_config = RestoreConfig::default();
+    if let Some(workers) = std::env::var("NIMBUX_RESTORE_WORKERS").ok().and_then(|n| n.parse().ok()) {
+        restore_config.workers = workers;
+    }
+    if let Some(days) = std::env::var("NIMBUX_RESTORE_WINDOW_DAYS").ok().and_then(|d| d.parse::<u64>().ok()) {
+        restore_config.default_window = std::time::Duration::from_secs(days * 24 * 3600);
+    }
+    let mut restore_notifier = HttpRestoreNotifier::new(std::time::Duration::from_secs(10))?;
+    if let Ok(url) = std::env::var("NIMBUX_MESSENGER_URL") {
+        let token = std::env::var("NIMBUX_MESSENGER_TOKEN").unwrap_or_default();
+        restore_notifier = restore_notifier.with_messenger(url, token);
+    }
+    let restore_coordinator = Arc::new(
+        RestoreCoordinator::new(storage.clone(), Arc::new(cold_storage), restore_config)
+            .with_notifier(Arc::new(restore_notifier)),
+    );
+    Arc::clone(&restore_coordinator).start(std::time::Duration::from_secs(3600));
+    
+    // Create event notifier announcing object writes and deletes made through
+    // the client-facing servers to webhooks, Messenger streams and queues
+    let mut event_sink = HttpEventSink::new(std::time::Duration::from_secs(10))?;
+    if let Ok(url) = std::env::var("NIMBUX_MESSENGER_URL") {
+        let token = std::env::var("NIMBUX_MESSENGER_TOKEN").unwrap_or_default();
+        event_sink = event_sink.with_messenger(url, token);
+    }
+    let event_notifier = Arc::new(
+        EventNotifier::new(versioned_storage.clone(), NotificationConfig::default())
+            .with_sink(Arc::new(event_sink)),
+    );
+    Arc::clone(&event_notifier).start();
+    let notifying_storage: Arc<dyn StorageBackend> = Arc::new(NotifyingStorage::new(
+        versioned_storage.clone(),
+        Arc::clone(&event_notifier),
+    ));
+    
+    // Create cross-region replicator copying writes in replicated buckets to
+    // other regions; replicas received from them are announced but not sent on
+    let mut replication_config = CrossRegionConfig::default();
+    if let Ok(region) = std::env::var("NIMBUX_REGION") {
+        replication_config.region = region;
+    }
+    replication_config.incoming_token = std::env::var("NIMBUX_REPLICATION_TOKEN").ok();
+    let replication_target = HttpReplicationTarget::new(
+        std::time::Duration::from_secs(60),
+        replication_config.incoming_token.clone().unwrap_or_default(),
+    )?;
+    let replicator = Arc::new(
+        CrossRegionReplicator::new(notifying_storage.clone(), replication_config)?
+            .with_target(Arc::new(replication_target)),
+    );
+    Arc::clone(&replicator).start();
+    let client_storage: Arc<dyn StorageBackend> = Arc::new(ReplicatingStorage::new(
+        notifying_storage,
+        Arc::clone(&replicator),
+    ));
+    
+    // Create lifecycle engine applying bucket expiration, tiering and upload cleanup rules
+    let lifecycle_engine = Arc::new(
+        LifecycleEngine::new(versioned_storage.clone(), Arc::clone(&restore_coordinator))
+            .with_metrics(Arc::clone(&metrics)),
+    );
+    let lifecycle_interval = std::env::var("NIMBUX_LIFECYCLE_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(3600);
+    Arc::clone(&lifecycle_engine).spawn_scheduler(std::time::Duration::from_secs(lifecycle_interval));
+    
+    // Create inventory exporter writing scheduled object listings into analytics buckets
+    let inventory_exporter = Arc::new(
+        InventoryExporter::new(versioned_storage.clone()).with_restore(Arc::clone(&restore_coordinator)),
+    );
+    let inventory_interval = std::env::var("NIMBUX_INVENTORY_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(3600);
+    Arc::clone(&inventory_exporter).spawn_scheduler(std::time::Duration::from_secs(inventory_interval));
+    
+    // Create image transformer resizing and converting images of buckets that allow it as they are read
+    let mut transformer_config = TransformerConfig::default();
+    if let Some(workers) = std::env::var("NIMBUX_TRANSFORM_WORKERS").ok().and_then(|n| n.parse().ok()) {
+        transformer_config.workers = workers;
+    }
+    if let Some(mb) = std::env::var("NIMBUX_TRANSFORM_CACHE_MB").ok().and_then(|mb| mb.parse::<u64>().ok()) {
+        transformer_config.cache_size = mb * 1024 * 1024;
+    }
+    let image_transformer = Arc::new(ImageTransformer::new(versioned_storage.clone(), transformer_config));
+    
+    // Create operator dashboard aggregating cluster, capacity and integrity views
+    let integrity_manager = Arc::new(IntegrityManager::new(IntegrityConfig::default(), storage.clone()));
+    let dashboard = Arc::new(
+        OperatorDashboard::new(storage.clone(), Arc::clone(&metrics), DashboardConfig::default())
+            .with_cluster(Arc::clone(&cluster_manager))
+            .with_integrity(integrity_manager),
+    );
+    Arc::clone(&dashboard).spawn_sampler(std::time::Duration::from_secs(3600));
+    
+    // Create network policy engine for IP and country restrictions on buckets and access keys
+    let mut network_policy_config = NetworkPolicyConfig::default();
+    if let Ok(proxies) = std::env::var("NIMBUX_TRUSTED_PROXIES") {
+        network_policy_config.trusted_proxies = proxies
+            .split(',')
+            .filter(|proxy| !proxy.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_>>()?;
+    }
+    let mut network_policy = NetworkPolicyEngine::new(network_policy_config);
+    if let Ok(path) = std::env::var("NIMBUX_GEOIP_FILE") {
+        let geoip = CidrGeoIp::load(std::path::Path::new(&path))?;
+        tracing::info!("Loaded {} GeoIP networks from {}", geoip.len(), path);
+        network_policy = network_policy.with_geoip(Arc::new(geoip));
+    }
+    let network_policy = Arc::new(network_policy);
+    
+    // Create admission controller holding each access key to its request and bandwidth limits
+    fn env_limit<T: std::str::FromStr>(name: &str) -> Option<T> {
+        std::env::var(name).ok().and_then(|value| value.parse().ok())
+    }
+    let mut admission_config = AdmissionConfig::default();
+    let limits = &mut admission_config.default_limits;
+    limits.requests_per_second = env_limit("NIMBUX_TENANT_REQUESTS_PER_SEC");
+    limits.request_burst = env_limit("NIMBUX_TENANT_REQUEST_BURST").unwrap_or(limits.request_burst);
+    limits.bytes_per_second = env_limit("NIMBUX_TENANT_BYTES_PER_SEC");
+    limits.byte_burst = env_limit("NIMBUX_TENANT_BYTE_BURST").unwrap_or(limits.byte_burst);
+    limits.max_concurrent = env_limit("NIMBUX_TENANT_MAX_CONCURRENT").unwrap_or(limits.max_concurrent);
+    admission_config.max_in_flight = env_limit("NIMBUX_MAX_IN_FLIGHT").unwrap_or(admission_config.max_in_flight);
+    let admission = Arc::new(AdmissionController::new(admission_config));
+    Arc::clone(&admission).spawn_pruner(std::time::Duration::from_secs(60));
     
     // Start all managers
     cluster_manager.start_auto_scaling().await?;
@@ -94,16 +279,91 @@ async fn main() -> Result<()> {
     durability_manager.start().await?;
     security_manager.start().await?;
     
+    // Buckets are addressed by path, as <bucket>.NIMBUX_S3_DOMAIN and by
+    // custom domains registered through the Nimbux API. With a certificate
+    // the HTTP and S3 servers speak HTTPS, choosing certificates by SNI
+    let tls_files = (std::env::var("NIMBUX_TLS_CERT_FILE"), std::env::var("NIMBUX_TLS_KEY_FILE"));
+    let domain_config = DomainConfig {
+        base_domain: std::env::var("NIMBUX_S3_DOMAIN").ok(),
+        redirect_path_style: std::env::var("NIMBUX_REDIRECT_PATH_STYLE").is_ok_and(|value| value == "true"),
+        scheme: std::env::var("NIMBUX_PUBLIC_SCHEME")
+            .unwrap_or_else(|_| if tls_files.0.is_ok() { "https".to_string() } else { "http".to_string() }),
+    };
+    let mut domains = DomainRegistry::new(domain_config, storage.clone());
+    if let (Ok(cert_file), Ok(key_file)) = &tls_files {
+        domains = domains.with_default_certificate(&std::fs::read_to_string(cert_file)?, &std::fs::read_to_string(key_file)?)?;
+    }
+    let domains = Arc::new(domains);
+    domains.load().await?;
+    let tls = domains.tls_config();
+    
     // Create servers
-    let http_server = SimpleHttpServer::new(Arc::clone(&storage), 8080);
-    let tcp_server = TcpServer::new(Arc::clone(&storage), 8081)
+    let mut http_server = SimpleHttpServer::new(client_storage.clone(), 8080)
+        .with_transforms(Arc::clone(&image_transformer))
+        .with_domains(Arc::clone(&domains));
+    let tcp_server = TcpServer::new(client_storage.clone(), 8081)
         .with_max_connections(1000);
+    let mut s3_config = S3Config::default();
+    if let Ok(port) = std::env::var("NIMBUX_S3_PORT") {
+        s3_config.port = port
+            .parse()
+            .map_err(|_| NimbuxError::Configuration(format!("Invalid NIMBUX_S3_PORT: {}", port)))?;
+    }
+    if let Ok(region) = std::env::var("NIMBUX_S3_REGION") {
+        s3_config.region = region;
+    }
+    // Browsers follow presigned URLs to the S3 API directly
+    let s3_endpoint = std::env::var("NIMBUX_S3_PUBLIC_ENDPOINT")
+        .unwrap_or_else(|_| format!("http://localhost:{}", s3_config.port));
+    let presigner = Arc::new(Presigner::new(&s3_endpoint, s3_config.region.clone())?);
     let nimbux_api_server = NimbuxApiServer::new(
+        client_storage.clone(),
         Arc::clone(&storage),
         Arc::clone(&auth_manager),
         Arc::clone(&metrics),
+        Arc::clone(&erasure_coordinator),
+        Arc::clone(&restore_coordinator),
+        Arc::clone(&versioned_storage),
+        Arc::clone(&lifecycle_engine),
+        Arc::clone(&inventory_exporter),
+        Arc::clone(&image_transformer),
+        Arc::clone(&presigner),
+        Arc::clone(&event_notifier),
+        Arc::clone(&replicator),
+        Arc::clone(&dashboard),
+        Arc::clone(&network_policy),
+        Arc::clone(&admission),
+        Arc::clone(&domains),
+        Arc::clone(&compliance_manager),
         8082,
     );
+    let s3_port = s3_config.port;
+    let mut s3_server = S3Server::new(client_storage.clone(), Arc::clone(&auth_manager), s3_config)
+        .with_network_policy(Arc::clone(&network_policy))
+        .with_admission(Arc::clone(&admission))
+        .with_transforms(Arc::clone(&image_transformer))
+        .with_domains(Arc::clone(&domains));
+    if let Some(tls) = &tls {
+        http_server = http_server.with_tls(Arc::clone(tls));
+        s3_server = s3_server.with_tls(Arc::clone(tls));
+    }
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    
+    // Optionally mount a bucket through the FUSE gateway
+    #[cfg(feature = "fuse")]
+    if let (Ok(bucket), Ok(mountpoint)) = (
+        std::env::var("NIMBUX_FUSE_BUCKET"),
+        std::env::var("NIMBUX_FUSE_MOUNTPOINT"),
+    ) {
+        let config = nimbux::gateway::GatewayConfig::new(bucket);
+        let storage = client_storage.clone();
+        let runtime = tokio::runtime::Handle::current();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = nimbux::gateway::mount(storage, config, std::path::Path::new(&mountpoint), runtime) {
+                tracing::error!("FUSE gateway failed: {}", e);
+            }
+        });
+    }
     
     // Start all servers concurrently
     tracing::info!("Nimbux Enterprise server ready!");
@@ -117,9 +377,10 @@ async fn main() -> Result<()> {
     tracing::info!("  ✅ Security & Data Protection - Encryption and access control");
     tracing::info!("");
     tracing::info!("🌐 Servers:");
-    tracing::info!("  HTTP API: http://localhost:8080");
+    tracing::info!("  HTTP API: {}://localhost:8080", scheme);
     tracing::info!("  TCP Protocol: tcp://localhost:8081");
     tracing::info!("  Nimbux API: http://localhost:8082");
+    tracing::info!("  S3 API: {}://localhost:{}", scheme, s3_port);
     tracing::info!("");
     tracing::info!("📡 API endpoints:");
     tracing::info!("  GET  /health - Health check");
@@ -130,7 +391,7 @@ async fn main() -> Result<()> {
     tracing::info!("  GET  /durability/stats - Durability statistics");
     tracing::info!("  GET  /security/stats - Security statistics");
     tracing::info!("");
-    tracing::info!("🔧 Nimbux API endpoints (NO S3 COMPATIBILITY):");
+    tracing::info!("🔧 Nimbux API endpoints:");
     tracing::info!("  GET  /api/v1/buckets - List buckets");
     tracing::info!("  POST /api/v1/buckets - Create bucket");
     tracing::info!("  GET  /api/v1/buckets/:bucket - Get bucket details");
@@ -141,12 +402,26 @@ async fn main() -> Result<()> {
     tracing::info!("  GET  /api/v1/buckets/:bucket/objects/:key - Get object");
     tracing::info!("  PUT  /api/v1/buckets/:bucket/objects/:key - Update object");
     tracing::info!("  DEL  /api/v1/buckets/:bucket/objects/:key - Delete object");
+    tracing::info!("  PUT  /api/v1/buckets/:bucket/versioning - Enable or suspend versioning");
+    tracing::info!("  GET  /api/v1/buckets/:bucket/objects/:key/versions - List object versions");
+    tracing::info!("  POST /api/v1/buckets/:bucket/objects/:key/restore - Restore an object version");
+    tracing::info!("  PUT  /api/v1/buckets/:bucket/lifecycle - Set lifecycle rules");
+    tracing::info!("  GET  /api/v1/buckets/:bucket/lifecycle/metrics - Lifecycle rule metrics");
+    tracing::info!("  PUT  /api/v1/buckets/:bucket/inventory/:id - Schedule inventory reports");
+    tracing::info!("  POST /api/v1/buckets/:bucket/inventory/:id/run - Write an inventory report now");
+    tracing::info!("  PUT  /api/v1/buckets/:bucket/transforms - Allow image transformations on read");
+    tracing::info!("  PUT  /api/v1/buckets/:bucket/notifications - Set event notification rules");
+    tracing::info!("  PUT  /api/v1/buckets/:bucket/domains/:domain - Serve a bucket at a custom domain");
+    tracing::info!("  POST /api/v1/notifications/queues/:queue/receive - Receive queued events");
     tracing::info!("  POST /api/v1/search - Search objects");
     tracing::info!("  POST /api/v1/batch - Batch operations");
+    tracing::info!("  POST /api/v1/restores - Restore archived objects");
+    tracing::info!("  GET  /api/v1/restores/:job_id - Restore progress");
     tracing::info!("  GET  /api/v1/analytics - Analytics dashboard");
     tracing::info!("");
     tracing::info!("🔐 Authentication:");
     tracing::info!("  Use JWT tokens or custom Nimbux authentication");
+    tracing::info!("  S3 clients sign requests with SigV4 using the admin access key");
     tracing::info!("");
     tracing::info!("⚡ Performance Features:");
     tracing::info!("  - Connection pooling with 10,000+ concurrent connections");
@@ -173,7 +448,8 @@ async fn main() -> Result<()> {
     tokio::try_join!(
         http_server.start(),
         tcp_server.start(),
-        nimbux_api_server.start()
+        nimbux_api_server.start(),
+        s3_server.start()
     )?;
     
     Ok(())
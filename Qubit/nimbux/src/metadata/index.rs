@@ -4,3 +4,118 @@
 // Created by Neo Qiss - Unleash the power of Rust.
 // ===========================================
 // Indexing & lookup
+
+use std::collections::{BTreeSet, HashMap};
+use tokio::sync::RwLock;
+
+use crate::errors::Result;
+use crate::storage::{ObjectMetadata, StorageBackend};
+
+/// Reverse index from object tags to object IDs
+///
+/// Answers "which objects carry `key=value`" without scanning every bucket.
+/// Write paths keep it current through `index_object`/`remove_object`;
+/// `rebuild` recovers it from the backend after a restart.
+#[derive(Debug, Default)]
+pub struct TagIndex {
+    inner: RwLock<TagIndexInner>,
+}
+
+#[derive(Debug, Default)]
+struct TagIndexInner {
+    /// tag key -> tag value -> object IDs
+    by_tag: HashMap<String, HashMap<String, BTreeSet<String>>>,
+    /// object ID -> tags it was indexed with
+    by_object: HashMap<String, HashMap<String, String>>,
+}
+
+impl TagIndexInner {
+    fn remove(&mut self, object_id: &str) {
+        let Some(tags) = self.by_object.remove(object_id) else {
+            return;
+        };
+
+        for (key, value) in tags {
+            if let Some(values) = self.by_tag.get_mut(&key) {
+                if let Some(ids) = values.get_mut(&value) {
+                    ids.remove(object_id);
+                    if ids.is_empty() {
+                        values.remove(&value);
+                    }
+                }
+                if values.is_empty() {
+                    self.by_tag.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, metadata: &ObjectMetadata) {
+        self.remove(&metadata.id);
+        if metadata.tags.is_empty() {
+            return;
+        }
+
+        for (key, value) in &metadata.tags {
+            self.by_tag
+                .entry(key.clone())
+                .or_default()
+                .entry(value.clone())
+                .or_default()
+                .insert(metadata.id.clone());
+        }
+        self.by_object.insert(metadata.id.clone(), metadata.tags.clone());
+    }
+}
+
+impl TagIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index (or re-index) an object's current tags
+    pub async fn index_object(&self, metadata: &ObjectMetadata) {
+        self.inner.write().await.insert(metadata);
+    }
+
+    /// Drop an object from the index
+    pub async fn remove_object(&self, object_id: &str) {
+        self.inner.write().await.remove(object_id);
+    }
+
+    /// IDs of all objects tagged `key=value`, in sorted order
+    pub async fn objects_with_tag(&self, key: &str, value: &str) -> Vec<String> {
+        self.inner
+            .read()
+            .await
+            .by_tag
+            .get(key)
+            .and_then(|values| values.get(value))
+            .map(|ids| ids.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Number of tagged objects currently indexed
+    pub async fn len(&self) -> usize {
+        self.inner.read().await.by_object.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Replace the index contents with a full scan of the backend
+    pub async fn rebuild(&self, storage: &dyn StorageBackend) -> Result<usize> {
+        let objects = storage.list(None, None).await?;
+
+        let mut inner = TagIndexInner::default();
+        for metadata in &objects {
+            inner.insert(metadata);
+        }
+        let indexed = inner.by_object.len();
+
+        *self.inner.write().await = inner;
+        tracing::debug!("Rebuilt tag index: {} tagged objects out of {}", indexed, objects.len());
+        Ok(indexed)
+    }
+}
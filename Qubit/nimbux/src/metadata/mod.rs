@@ -5,7 +5,9 @@
 // ===========================================
 // Metadata management
 
+pub mod index;
 pub mod search_engine;
 
 // Re-export commonly used types
+pub use index::TagIndex;
 pub use search_engine::{SearchEngine, SearchQuery, SearchResponse, SearchResult, IndexedDocument, SearchIndex, IndexStats};
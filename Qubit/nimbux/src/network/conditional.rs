@@ -0,0 +1,183 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Range reads and conditional reads shared by the HTTP and TCP servers
+
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{NimbuxError, Result};
+use crate::storage::ObjectMetadata;
+
+/// Conditions and range of an object read, named after the HTTP headers
+/// they come from. Values use the header syntax in both servers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReadConditions {
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+    /// HTTP date, e.g. `Wed, 21 Oct 2015 07:28:00 GMT`
+    pub if_modified_since: Option<String>,
+    /// `bytes=<first>-<last>`, `bytes=<first>-` or `bytes=-<suffix length>`
+    pub range: Option<String>,
+}
+
+/// What a read that passed its conditions returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadOutcome {
+    /// The client's copy is current; send no body
+    NotModified,
+    Full,
+    /// First and last byte, inclusive
+    Partial(u64, u64),
+}
+
+impl ReadConditions {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let value = |name: header::HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            if_match: value(header::IF_MATCH),
+            if_none_match: value(header::IF_NONE_MATCH),
+            if_modified_since: value(header::IF_MODIFIED_SINCE),
+            range: value(header::RANGE),
+        }
+    }
+
+    /// Decide what to return for an object, in the order of RFC 9110:
+    /// `If-Match` failing is an error, then `If-None-Match` (or, without it,
+    /// `If-Modified-Since`) can make the read not modified, and only then is
+    /// the range applied.
+    pub fn evaluate(&self, metadata: &ObjectMetadata) -> Result<ReadOutcome> {
+        if let Some(if_match) = &self.if_match {
+            if !etag_matches(if_match, &metadata.checksum) {
+                return Err(NimbuxError::PreconditionFailed(format!(
+                    "ETag of {} is {}",
+                    metadata.id, metadata.checksum
+                )));
+            }
+        }
+
+        match (&self.if_none_match, &self.if_modified_since) {
+            (Some(if_none_match), _) if etag_matches(if_none_match, &metadata.checksum) => {
+                return Ok(ReadOutcome::NotModified);
+            }
+            // An unparseable date is ignored, as HTTP requires
+            (None, Some(since)) if parse_http_date(since).is_some_and(|since| metadata.updated_at <= since) => {
+                return Ok(ReadOutcome::NotModified);
+            }
+            _ => {}
+        }
+
+        match &self.range {
+            Some(range) => Ok(match parse_range(range, metadata.size)? {
+                Some((start, end)) => ReadOutcome::Partial(start, end),
+                None => ReadOutcome::Full,
+            }),
+            None => Ok(ReadOutcome::Full),
+        }
+    }
+}
+
+/// Whether a comma-separated list of entity tags, or `*`, includes `etag`.
+/// Weak tags compare by their value.
+pub fn etag_matches(list: &str, etag: &str) -> bool {
+    list.split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/").trim_matches('"') == etag)
+}
+
+/// First and last byte of a `Range: bytes=...` header; `None` serves the whole object.
+///
+/// Multiple ranges are not supported and get the whole object, as HTTP allows.
+pub fn parse_range(value: &str, size: u64) -> Result<Option<(u64, u64)>> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    let Some((start, end)) = spec.split_once('-').filter(|_| !spec.contains(',')) else {
+        return Ok(None);
+    };
+
+    let (start, end) = match (start.trim().parse::<u64>(), end.trim().parse::<u64>()) {
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(size.saturating_sub(1))),
+        (Ok(start), Err(_)) if end.trim().is_empty() => (start, size.saturating_sub(1)),
+        (Err(_), Ok(suffix)) if start.trim().is_empty() => {
+            if suffix == 0 || size == 0 {
+                return Err(NimbuxError::RangeNotSatisfiable { size });
+            }
+            (size.saturating_sub(suffix), size - 1)
+        }
+        _ => return Ok(None),
+    };
+    if start >= size {
+        return Err(NimbuxError::RangeNotSatisfiable { size });
+    }
+    Ok(Some((start, end)))
+}
+
+pub fn http_date(unix_secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(unix_secs as i64, 0)
+        .unwrap_or_default()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+fn parse_http_date(value: &str) -> Option<u64> {
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    u64::try_from(date.timestamp()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Object;
+
+    fn metadata() -> ObjectMetadata {
+        let mut object = Object::with_id("media/a.mp4".to_string(), "media/a.mp4".to_string(), vec![7; 100], None);
+        object.metadata.updated_at = 1_700_000_000;
+        object.metadata
+    }
+
+    #[test]
+    fn test_range_forms() {
+        assert_eq!(parse_range("bytes=0-9", 100).unwrap(), Some((0, 9)));
+        assert_eq!(parse_range("bytes=90-", 100).unwrap(), Some((90, 99)));
+        assert_eq!(parse_range("bytes=-10", 100).unwrap(), Some((90, 99)));
+        assert_eq!(parse_range("bytes=0-1,5-6", 100).unwrap(), None);
+        assert!(matches!(parse_range("bytes=100-", 100), Err(NimbuxError::RangeNotSatisfiable { size: 100 })));
+    }
+
+    #[test]
+    fn test_etag_conditions() {
+        let metadata = metadata();
+        let etag = format!("\"{}\"", metadata.checksum);
+
+        let conditions = ReadConditions { if_none_match: Some(format!("\"other\", W/{}", etag)), ..Default::default() };
+        assert_eq!(conditions.evaluate(&metadata).unwrap(), ReadOutcome::NotModified);
+
+        let conditions = ReadConditions { if_match: Some("\"other\"".to_string()), ..Default::default() };
+        assert!(matches!(conditions.evaluate(&metadata), Err(NimbuxError::PreconditionFailed(_))));
+
+        let conditions = ReadConditions { if_match: Some(etag), range: Some("bytes=10-19".to_string()), ..Default::default() };
+        assert_eq!(conditions.evaluate(&metadata).unwrap(), ReadOutcome::Partial(10, 19));
+    }
+
+    #[test]
+    fn test_modified_since() {
+        let metadata = metadata();
+        let at = |secs| ReadConditions { if_modified_since: Some(http_date(secs)), ..Default::default() };
+
+        assert_eq!(at(metadata.updated_at).evaluate(&metadata).unwrap(), ReadOutcome::NotModified);
+        assert_eq!(at(metadata.updated_at - 1).evaluate(&metadata).unwrap(), ReadOutcome::Full);
+
+        // If-None-Match takes precedence over the date
+        let conditions = ReadConditions { if_none_match: Some("\"other\"".to_string()), ..at(metadata.updated_at) };
+        assert_eq!(conditions.evaluate(&metadata).unwrap(), ReadOutcome::Full);
+    }
+}
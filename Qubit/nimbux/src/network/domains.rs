@@ -0,0 +1,678 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Bucket addressing: virtual hosts, custom domains and their certificates
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use openssl::asn1::Asn1Time;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::x509::X509;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::errors::{NimbuxError, Result};
+use crate::storage::{Object, StorageBackend};
+
+const DOMAIN_PREFIX: &str = ".domains/";
+
+/// How a request named its bucket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Addressing {
+    /// In the first path segment, `/<bucket>/<key>`
+    #[default]
+    Path,
+    /// In the host, `<bucket>.<base domain>`
+    VirtualHost,
+    /// By a domain registered to the bucket
+    CustomDomain,
+}
+
+/// Bucket a request's `Host` header names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostRoute {
+    pub bucket: String,
+    pub addressing: Addressing,
+}
+
+/// Where a request should have been sent instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// Host, with the port when the request's host had one
+    pub host: String,
+    pub addressing: Addressing,
+}
+
+impl Redirect {
+    /// Location of the same request at the new address; `key_path` is the
+    /// raw path after the bucket, starting with `/`
+    pub fn location(&self, scheme: &str, key_path: &str, raw_query: Option<&str>) -> String {
+        match raw_query.filter(|query| !query.is_empty()) {
+            Some(query) => format!("{}://{}{}?{}", scheme, self.host, key_path, query),
+            None => format!("{}://{}{}", scheme, self.host, key_path),
+        }
+    }
+}
+
+/// Addressing configuration shared by the HTTP servers
+#[derive(Debug, Clone)]
+pub struct DomainConfig {
+    /// Serve virtual-hosted-style requests for `<bucket>.<base_domain>`
+    pub base_domain: Option<String>,
+    /// Redirect path-style requests to the bucket's virtual host
+    pub redirect_path_style: bool,
+    /// Scheme of redirect locations, `https` when TLS ends in front of or at Nimbux
+    pub scheme: String,
+}
+
+impl Default for DomainConfig {
+    fn default() -> Self {
+        Self {
+            base_domain: None,
+            redirect_path_style: false,
+            scheme: "http".to_string(),
+        }
+    }
+}
+
+/// Certificate details shown for a domain; the key is never returned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    /// DNS names the certificate covers
+    pub names: Vec<String>,
+    pub not_after: DateTime<Utc>,
+    /// SHA-256 of the leaf certificate, hex
+    pub fingerprint: String,
+}
+
+/// A custom domain serving one bucket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketDomain {
+    pub domain: String,
+    pub bucket: String,
+    /// Redirect requests for the bucket made at its other addresses here
+    pub canonical: bool,
+    pub certificate: Option<CertificateInfo>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of `PUT /api/v1/buckets/:bucket/domains/:domain`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DomainRequest {
+    #[serde(default)]
+    pub canonical: bool,
+    /// PEM certificate chain, leaf first, for TLS on the domain
+    #[serde(default)]
+    pub certificate_pem: Option<String>,
+    /// PEM private key of the certificate
+    #[serde(default)]
+    pub private_key_pem: Option<String>,
+}
+
+/// How a domain is saved under `.domains/`
+#[derive(Serialize, Deserialize)]
+struct StoredDomain {
+    #[serde(flatten)]
+    domain: BucketDomain,
+    certificate_pem: Option<String>,
+    private_key_pem: Option<String>,
+}
+
+struct Entry {
+    domain: BucketDomain,
+    key: Option<Arc<CertifiedKey>>,
+}
+
+/// Maps request hosts to buckets and TLS server names to certificates.
+///
+/// Buckets are addressed path style, as `<bucket>.<base domain>`, or by a
+/// custom domain registered to them, usually a CDN origin. Custom domains
+/// and their certificates are saved under `.domains/` and take effect for
+/// new requests and handshakes as soon as they are registered. Lookups are
+/// synchronous because the TLS server name callback is.
+pub struct DomainRegistry {
+    config: DomainConfig,
+    store: Arc<dyn StorageBackend>,
+    domains: RwLock<HashMap<String, Entry>>,
+    /// Served when no custom domain matches the server name; it should cover
+    /// the base domain and `*.<base domain>`
+    default_certificate: Option<Arc<CertifiedKey>>,
+}
+
+impl DomainRegistry {
+    pub fn new(config: DomainConfig, store: Arc<dyn StorageBackend>) -> Self {
+        let base_domain = config
+            .base_domain
+            .as_deref()
+            .map(|domain| domain.trim_end_matches('.').to_ascii_lowercase());
+        Self {
+            config: DomainConfig { base_domain, ..config },
+            store,
+            domains: RwLock::new(HashMap::new()),
+            default_certificate: None,
+        }
+    }
+
+    /// Certificate for the base domain and any server name without its own
+    pub fn with_default_certificate(mut self, certificate_pem: &str, private_key_pem: &str) -> Result<Self> {
+        let (key, info) = load_certificate(certificate_pem, private_key_pem)?;
+        info!("Default TLS certificate covers {:?} until {}", info.names, info.not_after);
+        self.default_certificate = Some(key);
+        Ok(self)
+    }
+
+    pub fn config(&self) -> &DomainConfig {
+        &self.config
+    }
+
+    /// TLS server configuration choosing each connection's certificate by
+    /// server name; `None` without a default certificate
+    pub fn tls_config(self: &Arc<Self>) -> Option<Arc<rustls::ServerConfig>> {
+        self.default_certificate.as_ref()?;
+        let mut config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(SniResolver(Arc::clone(self))));
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Some(Arc::new(config))
+    }
+
+    /// Load the custom domains saved under `.domains/`
+    pub async fn load(&self) -> Result<()> {
+        let mut domains = HashMap::new();
+        for metadata in self.store.list(Some(DOMAIN_PREFIX), None).await? {
+            let object = match self.store.get(&metadata.id).await {
+                Ok(object) => object,
+                Err(NimbuxError::ObjectNotFound { .. }) => continue,
+                Err(e) => return Err(e),
+            };
+            let stored: StoredDomain = serde_json::from_slice(&object.data)?;
+            let key = match (&stored.certificate_pem, &stored.private_key_pem) {
+                (Some(certificate), Some(private_key)) => match load_certificate(certificate, private_key) {
+                    Ok((key, _)) => Some(key),
+                    Err(e) => {
+                        warn!("Not serving TLS for {}: {}", stored.domain.domain, e);
+                        None
+                    }
+                },
+                _ => None,
+            };
+            domains.insert(stored.domain.domain.clone(), Entry { domain: stored.domain, key });
+        }
+        info!("Loaded {} custom bucket domains", domains.len());
+        *self.domains.write().unwrap() = domains;
+        Ok(())
+    }
+
+    /// Serve a bucket at a domain, replacing the domain's settings when the
+    /// bucket already has it
+    pub async fn register(&self, bucket: &str, domain: &str, request: DomainRequest) -> Result<BucketDomain> {
+        if bucket.is_empty() || bucket.starts_with('.') || bucket.contains('/') {
+            return Err(NimbuxError::InvalidRequest(format!("Invalid bucket: {}", bucket)));
+        }
+        let domain = normalize_domain(domain)?;
+        if let Some(base) = &self.config.base_domain {
+            if domain == *base || domain.ends_with(&format!(".{}", base)) {
+                return Err(NimbuxError::InvalidRequest(format!(
+                    "{} is under the base domain {}, where buckets are addressed by name",
+                    domain, base
+                )));
+            }
+        }
+
+        let (key, certificate) = match (&request.certificate_pem, &request.private_key_pem) {
+            (Some(certificate_pem), Some(private_key_pem)) => {
+                let (key, info) = load_certificate(certificate_pem, private_key_pem)?;
+                if !info.names.iter().any(|name| covers(name, &domain)) {
+                    return Err(NimbuxError::InvalidRequest(format!(
+                        "The certificate covers {:?}, not {}",
+                        info.names, domain
+                    )));
+                }
+                if info.not_after <= Utc::now() {
+                    return Err(NimbuxError::InvalidRequest(format!("The certificate expired at {}", info.not_after)));
+                }
+                (Some(key), Some(info))
+            }
+            (None, None) => (None, None),
+            _ => {
+                return Err(NimbuxError::InvalidRequest(
+                    "certificate_pem and private_key_pem must be given together".to_string(),
+                ))
+            }
+        };
+
+        let now = Utc::now();
+        let created_at = match self.domains.read().unwrap().get(&domain) {
+            Some(entry) if entry.domain.bucket != bucket => {
+                return Err(NimbuxError::InvalidRequest(format!(
+                    "{} is registered to bucket {}",
+                    domain, entry.domain.bucket
+                )))
+            }
+            Some(entry) => entry.domain.created_at,
+            None => now,
+        };
+        let registered = BucketDomain {
+            domain: domain.clone(),
+            bucket: bucket.to_string(),
+            canonical: request.canonical,
+            certificate,
+            created_at,
+            updated_at: now,
+        };
+
+        let stored = StoredDomain {
+            domain: registered.clone(),
+            certificate_pem: request.certificate_pem,
+            private_key_pem: request.private_key_pem,
+        };
+        let id = format!("{}{}", DOMAIN_PREFIX, domain);
+        let object = Object::with_id(id.clone(), id, serde_json::to_vec(&stored)?, Some("application/json".to_string()));
+        self.store.put(object).await?;
+
+        let mut domains = self.domains.write().unwrap();
+        // Another bucket may have taken the domain while it was being saved
+        if let Some(entry) = domains.get(&domain).filter(|entry| entry.domain.bucket != bucket) {
+            return Err(NimbuxError::InvalidRequest(format!("{} is registered to bucket {}", domain, entry.domain.bucket)));
+        }
+        domains.insert(domain.clone(), Entry { domain: registered.clone(), key });
+        info!("Bucket {} is served at {} (canonical: {})", bucket, domain, registered.canonical);
+        Ok(registered)
+    }
+
+    /// Stop serving a bucket at a domain; false when the bucket does not have it
+    pub async fn unregister(&self, bucket: &str, domain: &str) -> Result<bool> {
+        let domain = normalize_domain(domain)?;
+        let registered = self
+            .domains
+            .read()
+            .unwrap()
+            .get(&domain)
+            .is_some_and(|entry| entry.domain.bucket == bucket);
+        if !registered {
+            return Ok(false);
+        }
+        match self.store.delete(&format!("{}{}", DOMAIN_PREFIX, domain)).await {
+            Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+        self.domains.write().unwrap().remove(&domain);
+        info!("Bucket {} is no longer served at {}", bucket, domain);
+        Ok(true)
+    }
+
+    /// Custom domains of a bucket
+    pub fn domains_of(&self, bucket: &str) -> Vec<BucketDomain> {
+        let mut domains: Vec<BucketDomain> = self
+            .domains
+            .read()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.domain.bucket == bucket)
+            .map(|entry| entry.domain.clone())
+            .collect();
+        domains.sort_by(|a, b| a.domain.cmp(&b.domain));
+        domains
+    }
+
+    /// Bucket named by a request's `Host` header; `None` for path style
+    pub fn resolve(&self, host: Option<&str>) -> Option<HostRoute> {
+        let (name, _) = split_host(host?);
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+
+        if let Some(entry) = self.domains.read().unwrap().get(&name) {
+            return Some(HostRoute {
+                bucket: entry.domain.bucket.clone(),
+                addressing: Addressing::CustomDomain,
+            });
+        }
+        let base = self.config.base_domain.as_deref()?;
+        let bucket = name
+            .strip_suffix(base)?
+            .strip_suffix('.')
+            .filter(|bucket| !bucket.is_empty() && !bucket.starts_with('.'))?;
+        Some(HostRoute {
+            bucket: bucket.to_string(),
+            addressing: Addressing::VirtualHost,
+        })
+    }
+
+    /// Address a request for a bucket should be redirected to, if any: the
+    /// bucket's canonical domain, or its virtual host for path-style
+    /// requests when those are redirected
+    pub fn redirect(&self, bucket: &str, addressing: Addressing, host: Option<&str>) -> Option<Redirect> {
+        if addressing != Addressing::CustomDomain {
+            let canonical = self
+                .domains
+                .read()
+                .unwrap()
+                .values()
+                .filter(|entry| entry.domain.bucket == bucket && entry.domain.canonical)
+                .map(|entry| entry.domain.domain.clone())
+                .min();
+            if let Some(domain) = canonical {
+                // Custom domains sit behind a CDN on the default ports
+                return Some(Redirect { host: domain, addressing: Addressing::CustomDomain });
+            }
+        }
+
+        let base = self.config.base_domain.as_deref()?;
+        if addressing != Addressing::Path || !self.config.redirect_path_style || !is_dns_label(bucket) {
+            return None;
+        }
+        let host = match host.map(split_host) {
+            Some((_, Some(port))) => format!("{}.{}:{}", bucket, base, port),
+            _ => format!("{}.{}", bucket, base),
+        };
+        Some(Redirect { host, addressing: Addressing::VirtualHost })
+    }
+
+    fn certificate_for(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        let custom = server_name.and_then(|name| {
+            let name = name.trim_end_matches('.').to_ascii_lowercase();
+            self.domains.read().unwrap().get(&name)?.key.clone()
+        });
+        custom.or_else(|| self.default_certificate.clone())
+    }
+}
+
+/// Picks the certificate of the domain a TLS client asks for
+struct SniResolver(Arc<DomainRegistry>);
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        self.0.certificate_for(client_hello.server_name())
+    }
+}
+
+/// Signing key and details of a PEM certificate chain, after checking the
+/// key belongs to the leaf certificate
+fn load_certificate(certificate_pem: &str, private_key_pem: &str) -> Result<(Arc<CertifiedKey>, CertificateInfo)> {
+    let invalid = |what: &str, e: &dyn std::fmt::Display| NimbuxError::InvalidRequest(format!("Invalid {}: {}", what, e));
+
+    let chain = X509::stack_from_pem(certificate_pem.as_bytes()).map_err(|e| invalid("certificate", &e))?;
+    let leaf = chain
+        .first()
+        .ok_or_else(|| NimbuxError::InvalidRequest("No certificate in certificate_pem".to_string()))?;
+    let private_key = PKey::private_key_from_pem(private_key_pem.as_bytes()).map_err(|e| invalid("private key", &e))?;
+    let public_key = leaf.public_key().map_err(|e| invalid("certificate", &e))?;
+    if !public_key.public_eq(&private_key) {
+        return Err(NimbuxError::InvalidRequest("The private key does not match the certificate".to_string()));
+    }
+
+    let mut names: Vec<String> = leaf
+        .subject_alt_names()
+        .map(|names| names.iter().filter_map(|name| name.dnsname().map(str::to_ascii_lowercase)).collect())
+        .unwrap_or_default();
+    if names.is_empty() {
+        names = leaf
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .filter_map(|entry| entry.data().to_string().ok().map(|name| name.to_ascii_lowercase()))
+            .collect();
+    }
+    let until_expiry = Asn1Time::from_unix(0)
+        .and_then(|epoch| epoch.diff(leaf.not_after()))
+        .map_err(|e| invalid("certificate expiry", &e))?;
+    let not_after = DateTime::<Utc>::from_timestamp(i64::from(until_expiry.days) * 86_400 + i64::from(until_expiry.secs), 0)
+        .unwrap_or_default();
+    let fingerprint = hex::encode(leaf.digest(MessageDigest::sha256()).map_err(|e| invalid("certificate", &e))?);
+
+    let der_chain = chain
+        .iter()
+        .map(|certificate| certificate.to_der().map(rustls::Certificate))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| invalid("certificate", &e))?;
+    let pkcs8 = private_key.private_key_to_pkcs8().map_err(|e| invalid("private key", &e))?;
+    let signing_key = rustls::sign::any_supported_type(&rustls::PrivateKey(pkcs8)).map_err(|e| invalid("private key", &e))?;
+
+    Ok((
+        Arc::new(CertifiedKey::new(der_chain, signing_key)),
+        CertificateInfo { names, not_after, fingerprint },
+    ))
+}
+
+/// Whether a certificate name covers a domain; wildcards match one label
+fn covers(name: &str, domain: &str) -> bool {
+    match name.strip_prefix("*.") {
+        Some(parent) => domain
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == parent),
+        None => name == domain,
+    }
+}
+
+/// Lowercase domain without a trailing dot, checked to be a DNS name
+fn normalize_domain(domain: &str) -> Result<String> {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    let labels: Vec<&str> = domain.split('.').collect();
+    let valid = domain.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|label| is_dns_label(label))
+        && !labels.last().is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit()));
+    if !valid {
+        return Err(NimbuxError::InvalidRequest(format!("Invalid domain: {}", domain)));
+    }
+    Ok(domain)
+}
+
+/// Whether a name can be one label of a host name, as buckets in virtual hosts must be
+fn is_dns_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Host name and port of a `Host` header
+fn split_host(host: &str) -> (&str, Option<&str>) {
+    if host.starts_with('[') {
+        // IPv6 literals never name a bucket
+        return match host.split_once("]:") {
+            Some((address, port)) => (&host[..=address.len()], Some(port)),
+            None => (host, None),
+        };
+    }
+    match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => (name, Some(port)),
+        _ => (host, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::pkey::Private;
+    use openssl::x509::extension::SubjectAlternativeName;
+    use openssl::x509::X509NameBuilder;
+
+    fn key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    /// Self-signed certificate for `names` and the PEM of its key
+    fn certificate(names: &[&str], key: &PKey<Private>, days: u32) -> (String, String) {
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_nid(Nid::COMMONNAME, names[0]).unwrap();
+        let subject = subject.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&subject).unwrap();
+        builder.set_issuer_name(&subject).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(days).unwrap()).unwrap();
+        let mut san = SubjectAlternativeName::new();
+        for name in names {
+            san.dns(name);
+        }
+        builder.append_extension(san.build(&builder.x509v3_context(None, None)).unwrap()).unwrap();
+        builder.sign(key, MessageDigest::sha256()).unwrap();
+
+        let certificate = String::from_utf8(builder.build().to_pem().unwrap()).unwrap();
+        let key = String::from_utf8(key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+        (certificate, key)
+    }
+
+    fn registry(config: DomainConfig) -> (DomainRegistry, Arc<MemoryStorage>) {
+        let store = Arc::new(MemoryStorage::new());
+        (DomainRegistry::new(config, store.clone()), store)
+    }
+
+    fn vhost_config(redirect_path_style: bool) -> DomainConfig {
+        DomainConfig {
+            base_domain: Some("s3.nimbux.local".to_string()),
+            redirect_path_style,
+            scheme: "https".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hosts_resolve_to_buckets() {
+        let (registry, _) = registry(vhost_config(false));
+        registry.register("media", "CDN.Example.com.", DomainRequest::default()).await.unwrap();
+
+        let route = |host| registry.resolve(Some(host)).map(|route| (route.bucket, route.addressing));
+        assert_eq!(route("media.s3.nimbux.local:8083"), Some(("media".to_string(), Addressing::VirtualHost)));
+        assert_eq!(route("cdn.example.com"), Some(("media".to_string(), Addressing::CustomDomain)));
+        assert_eq!(route("cdn.example.com:443"), Some(("media".to_string(), Addressing::CustomDomain)));
+        assert_eq!(route("s3.nimbux.local"), None);
+        assert_eq!(route("localhost:8083"), None);
+        assert_eq!(route("[::1]:8083"), None);
+        assert_eq!(registry.resolve(None), None);
+    }
+
+    #[tokio::test]
+    async fn test_domains_are_validated_and_owned_by_one_bucket() {
+        let (registry, _) = registry(vhost_config(false));
+        registry.register("media", "cdn.example.com", DomainRequest::default()).await.unwrap();
+
+        for domain in ["example", "-bad.example.com", "photos.s3.nimbux.local", "s3.nimbux.local", "10.0.0.1", "a..b.com"] {
+            assert!(registry.register("media", domain, DomainRequest::default()).await.is_err(), "{}", domain);
+        }
+        assert!(registry.register("avatars", "cdn.example.com", DomainRequest::default()).await.is_err());
+        assert!(!registry.unregister("avatars", "cdn.example.com").await.unwrap());
+
+        // Registering again updates the settings
+        let updated = registry
+            .register("media", "cdn.example.com", DomainRequest { canonical: true, ..Default::default() })
+            .await
+            .unwrap();
+        assert!(updated.canonical);
+        assert_eq!(registry.domains_of("media"), vec![updated]);
+
+        assert!(registry.unregister("media", "cdn.example.com").await.unwrap());
+        assert_eq!(registry.resolve(Some("cdn.example.com")), None);
+        registry.register("avatars", "cdn.example.com", DomainRequest::default()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_redirects_between_addressing_styles() {
+        let (registry, _) = registry(vhost_config(true));
+        let redirect = |bucket, addressing, host| {
+            registry.redirect(bucket, addressing, Some(host)).map(|redirect| (redirect.host, redirect.addressing))
+        };
+
+        // Path style goes to the bucket's virtual host, on the same port
+        assert_eq!(
+            redirect("media", Addressing::Path, "s3.nimbux.local:8083"),
+            Some(("media.s3.nimbux.local:8083".to_string(), Addressing::VirtualHost))
+        );
+        assert_eq!(redirect("media", Addressing::VirtualHost, "media.s3.nimbux.local"), None);
+        // Bucket names that are not a DNS label stay path style
+        assert_eq!(redirect("my.media", Addressing::Path, "s3.nimbux.local"), None);
+
+        // A canonical domain takes every other address
+        registry
+            .register("media", "cdn.example.com", DomainRequest { canonical: true, ..Default::default() })
+            .await
+            .unwrap();
+        for addressing in [Addressing::Path, Addressing::VirtualHost] {
+            assert_eq!(
+                redirect("media", addressing, "media.s3.nimbux.local:8083"),
+                Some(("cdn.example.com".to_string(), Addressing::CustomDomain))
+            );
+        }
+        assert_eq!(redirect("media", Addressing::CustomDomain, "cdn.example.com"), None);
+
+        let location = registry.redirect("media", Addressing::Path, None).unwrap().location("https", "/a%20b.jpg", Some("versionId=3"));
+        assert_eq!(location, "https://cdn.example.com/a%20b.jpg?versionId=3");
+
+        let (no_redirects, _) = self::registry(vhost_config(false));
+        assert_eq!(no_redirects.redirect("media", Addressing::Path, Some("s3.nimbux.local")), None);
+    }
+
+    #[tokio::test]
+    async fn test_certificates_are_checked_and_chosen_by_server_name() {
+        let (registry, store) = registry(vhost_config(false));
+        let (default_certificate, default_key) = certificate(&["s3.nimbux.local", "*.s3.nimbux.local"], &key(), 30);
+        let registry = Arc::new(registry.with_default_certificate(&default_certificate, &default_key).unwrap());
+
+        let cdn_key = key();
+        let (cdn_certificate, cdn_private_key) = certificate(&["cdn.example.com"], &cdn_key, 30);
+        let request = |certificate: &str, private_key: &str| DomainRequest {
+            canonical: false,
+            certificate_pem: Some(certificate.to_string()),
+            private_key_pem: Some(private_key.to_string()),
+        };
+
+        // The certificate must cover the domain and match the key
+        assert!(registry.register("media", "img.example.com", request(&cdn_certificate, &cdn_private_key)).await.is_err());
+        let (_, other_key) = certificate(&["cdn.example.com"], &key(), 30);
+        assert!(registry.register("media", "cdn.example.com", request(&cdn_certificate, &other_key)).await.is_err());
+        let (expired, expired_key) = certificate(&["cdn.example.com"], &key(), 0);
+        assert!(registry.register("media", "cdn.example.com", request(&expired, &expired_key)).await.is_err());
+        let missing_key = DomainRequest { certificate_pem: Some(cdn_certificate.clone()), ..Default::default() };
+        assert!(registry.register("media", "cdn.example.com", missing_key).await.is_err());
+
+        let registered = registry
+            .register("media", "cdn.example.com", request(&cdn_certificate, &cdn_private_key))
+            .await
+            .unwrap();
+        let info = registered.certificate.unwrap();
+        assert_eq!(info.names, vec!["cdn.example.com"]);
+        assert!(info.not_after > Utc::now() + chrono::Duration::days(29));
+
+        let leaf = |name| registry.certificate_for(name).unwrap().cert[0].0.clone();
+        let cdn_der = X509::from_pem(cdn_certificate.as_bytes()).unwrap().to_der().unwrap();
+        let default_der = X509::from_pem(default_certificate.as_bytes()).unwrap().to_der().unwrap();
+        assert_eq!(leaf(Some("CDN.example.com")), cdn_der);
+        assert_eq!(leaf(Some("media.s3.nimbux.local")), default_der);
+        assert_eq!(leaf(None), default_der);
+        assert!(registry.tls_config().is_some());
+
+        // The certificate is reloaded with the domain, and never shown
+        let reloaded = DomainRegistry::new(vhost_config(false), store.clone());
+        reloaded.load().await.unwrap();
+        assert_eq!(reloaded.certificate_for(Some("cdn.example.com")).unwrap().cert[0].0, cdn_der);
+        assert!(!serde_json::to_string(&reloaded.domains_of("media")).unwrap().contains("PRIVATE KEY"));
+    }
+
+    #[test]
+    fn test_certificate_names() {
+        assert!(covers("cdn.example.com", "cdn.example.com"));
+        assert!(covers("*.example.com", "cdn.example.com"));
+        assert!(!covers("*.example.com", "example.com"));
+        assert!(!covers("*.example.com", "a.cdn.example.com"));
+        assert!(!covers("cdn.example.com", "img.example.com"));
+    }
+}
@@ -251,6 +251,18 @@ impl From<NimbuxError> for (StatusCode, Json<ErrorResponse>) {
             NimbuxError::InvalidObjectId { object_id } => {
                 (StatusCode::BAD_REQUEST, format!("Invalid object ID: {}", object_id))
             }
+            NimbuxError::InvalidRequest(msg) => {
+                (StatusCode::BAD_REQUEST, format!("Invalid request: {}", msg))
+            }
+            NimbuxError::PreconditionFailed(msg) => {
+                (StatusCode::PRECONDITION_FAILED, format!("Precondition failed: {}", msg))
+            }
+            NimbuxError::OffsetMismatch { .. } => {
+                (StatusCode::CONFLICT, err.to_string())
+            }
+            NimbuxError::RangeNotSatisfiable { .. } => {
+                (StatusCode::RANGE_NOT_SATISFIABLE, err.to_string())
+            }
             NimbuxError::Authentication(msg) => {
                 (StatusCode::UNAUTHORIZED, format!("Authentication error: {}", msg))
             }
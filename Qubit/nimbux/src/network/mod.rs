@@ -3,19 +3,25 @@
 // (c) 2025 Neo Qiss. All Rights Reserved.
 // Created by Neo Qiss - Unleash the power of Rust.
 // ===========================================
-// Networking module - NO S3 COMPATIBILITY
+// Networking module
 
 // pub mod http;  // Commented out due to axum handler issues
 pub mod simple_http;
+pub mod conditional;  // Range and conditional reads
+pub mod domains;  // Virtual hosts, custom bucket domains and TLS certificates
 pub mod tcp;
-pub mod nimbux_api;  // Custom Nimbux API - NO S3 COMPATIBILITY
+pub mod nimbux_api;  // Custom Nimbux API
+pub mod s3;  // S3-compatible API for existing S3 clients
 pub mod binary_protocol;  // Custom binary protocol for high-performance operations
 pub mod connection_pool;
 
 // Re-export commonly used types
 pub use simple_http::SimpleHttpServer;
-pub use tcp::{TcpServer, ProtocolHeader, OpCode, TcpRequest, TcpResponse};
+pub use conditional::{ReadConditions, ReadOutcome};
+pub use domains::{Addressing, BucketDomain, DomainConfig, DomainRegistry, DomainRequest};
+pub use tcp::{TcpServer, TcpClient, TcpConfig, TcpOperation, TcpRequest, TcpResponse};
 pub use nimbux_api::{NimbuxApiServer, NimbuxApiState};
+pub use s3::{S3Server, S3State, S3Config, S3Error};
 pub use binary_protocol::{BinaryCodec, BinaryMessage, BinaryRequest, BinaryResponse, OpCode, CompressionType, EncryptionType, Priority};
 pub use connection_pool::{
     ConnectionPool, HttpConnectionPool, BufferPool, PerformanceMonitor,
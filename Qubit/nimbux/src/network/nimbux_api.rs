@@ -6,13 +6,16 @@
 // Custom Nimbux API - NO S3 COMPATIBILITY
 
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use axum::{
-    extract::{Path, Query, State, Multipart, Json},
-    http::{HeaderMap, StatusCode, HeaderValue},
+    body::Bytes,
+    extract::{ConnectInfo, DefaultBodyLimit, Path, Query, Request, State, Multipart, Json},
+    http::{header, HeaderMap, Method, StatusCode, HeaderValue, Uri},
+    middleware::{self, Next},
     response::{Response, IntoResponse},
-    routing::{get, post, put, delete, head},
+    routing::{get, post, put, patch, delete, head},
     Router,
 };
 use serde::{Deserialize, Serialize};
@@ -21,15 +24,38 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
 use crate::errors::{NimbuxError, Result};
-use crate::storage::{StorageBackend, Object, ObjectMetadata, StorageStats};
-use crate::auth::{AuthManager, AuthContext};
-use crate::observability::MetricsCollector;
+use crate::storage::{DefaultRetention, Quota, Retention, StorageClass, StorageEngine, EventNotifier, ImageTransformer, InventoryConfig, InventoryExporter, LifecycleEngine, NotificationRule, ObjectWriter, RestoreCoordinator, RestoreRequest, StorageBackend, Object, ObjectMetadata, StorageStats, TransformConfig, VersionedStorage, VersioningStatus, WriteConditions};
+use crate::storage::advanced::LifecycleRule as StorageLifecycleRule;
+use crate::cluster::{CrossRegionReplicator, ReplicaChange, ReplicaVersion, ReplicationRule, VersionVector};
+use crate::auth::{AuthManager, AuthContext, PolicyDocument, PresignMethod, PresignRequest, Presigner};
+use crate::observability::{BucketSort, MetricsCollector, OperatorDashboard};
+use crate::transfer::{CompletedPart, MultipartConfig, MultipartManager, MultipartUpload};
+use crate::security::{ComplianceManager, ErasureCertificate, ErasureCoordinator, NetworkPolicyEngine, NetworkRules, PolicyDecision, PolicyScope};
+use crate::network::domains::{DomainRegistry, DomainRequest};
+use crate::network::s3::sigv4::{self, PayloadSignature, SignedRequest};
+use crate::network::s3::S3Error;
+use crate::performance::{AdmissionController, TenantLimits};
 
 /// Custom Nimbux API server - NO S3 COMPATIBILITY
 pub struct NimbuxApiServer {
     storage: Arc<dyn StorageBackend>,
+    engine: Arc<StorageEngine>,
     auth_manager: Arc<AuthManager>,
     metrics: Arc<MetricsCollector>,
+    erasure: Arc<ErasureCoordinator>,
+    restore: Arc<RestoreCoordinator>,
+    versioning: Arc<VersionedStorage>,
+    lifecycle: Arc<LifecycleEngine>,
+    inventory: Arc<InventoryExporter>,
+    transforms: Arc<ImageTransformer>,
+    presigner: Arc<Presigner>,
+    notifications: Arc<EventNotifier>,
+    replication: Arc<CrossRegionReplicator>,
+    dashboard: Arc<OperatorDashboard>,
+    network_policy: Arc<NetworkPolicyEngine>,
+    admission: Arc<AdmissionController>,
+    domains: Arc<DomainRegistry>,
+    compliance: Arc<ComplianceManager>,
     port: u16,
 }
 
@@ -37,8 +63,26 @@ pub struct NimbuxApiServer {
 #[derive(Clone)]
 pub struct NimbuxApiState {
     pub storage: Arc<dyn StorageBackend>,
+    /// Engine under `storage`, for storage classes, quotas and usage
+    pub engine: Arc<StorageEngine>,
     pub auth_manager: Arc<AuthManager>,
     pub metrics: Arc<MetricsCollector>,
+    pub erasure: Arc<ErasureCoordinator>,
+    pub restore: Arc<RestoreCoordinator>,
+    pub versioning: Arc<VersionedStorage>,
+    pub lifecycle: Arc<LifecycleEngine>,
+    pub inventory: Arc<InventoryExporter>,
+    pub transforms: Arc<ImageTransformer>,
+    pub presigner: Arc<Presigner>,
+    pub notifications: Arc<EventNotifier>,
+    pub replication: Arc<CrossRegionReplicator>,
+    pub writer: Arc<ObjectWriter>,
+    pub multipart: Arc<MultipartManager>,
+    pub dashboard: Arc<OperatorDashboard>,
+    pub network_policy: Arc<NetworkPolicyEngine>,
+    pub admission: Arc<AdmissionController>,
+    pub domains: Arc<DomainRegistry>,
+    pub compliance: Arc<ComplianceManager>,
 }
 
 // ===========================================
@@ -261,24 +305,73 @@ pub struct BatchOperationResult {
 impl NimbuxApiServer {
     pub fn new(
         storage: Arc<dyn StorageBackend>,
+        engine: Arc<StorageEngine>,
         auth_manager: Arc<AuthManager>,
         metrics: Arc<MetricsCollector>,
+        erasure: Arc<ErasureCoordinator>,
+        restore: Arc<RestoreCoordinator>,
+        versioning: Arc<VersionedStorage>,
+        lifecycle: Arc<LifecycleEngine>,
+        inventory: Arc<InventoryExporter>,
+        transforms: Arc<ImageTransformer>,
+        presigner: Arc<Presigner>,
+        notifications: Arc<EventNotifier>,
+        replication: Arc<CrossRegionReplicator>,
+        dashboard: Arc<OperatorDashboard>,
+        network_policy: Arc<NetworkPolicyEngine>,
+        admission: Arc<AdmissionController>,
+        domains: Arc<DomainRegistry>,
+        compliance: Arc<ComplianceManager>,
         port: u16,
     ) -> Self {
         Self {
             storage,
+            engine,
             auth_manager,
             metrics,
+            erasure,
+            restore,
+            versioning,
+            lifecycle,
+            inventory,
+            transforms,
+            presigner,
+            notifications,
+            replication,
+            dashboard,
+            network_policy,
+            admission,
+            domains,
+            compliance,
             port,
         }
     }
 
     pub async fn start(self) -> Result<()> {
         let state = NimbuxApiState {
+            writer: Arc::new(ObjectWriter::new(Arc::clone(&self.storage))),
+            multipart: Arc::new(MultipartManager::new(Arc::clone(&self.storage), MultipartConfig::default())),
             storage: self.storage,
+            engine: self.engine,
             auth_manager: self.auth_manager,
             metrics: self.metrics,
+            erasure: self.erasure,
+            restore: self.restore,
+            versioning: self.versioning,
+            lifecycle: self.lifecycle,
+            inventory: self.inventory,
+            transforms: self.transforms,
+            presigner: self.presigner,
+            notifications: self.notifications,
+            replication: self.replication,
+            dashboard: self.dashboard,
+            network_policy: self.network_policy,
+            admission: self.admission,
+            domains: self.domains,
+            compliance: self.compliance,
         };
+        let max_part_size = state.multipart.config().max_part_size as usize;
+        let max_object_size = state.multipart.config().max_object_size as usize;
 
         let app = Router::new()
             // Health and system endpoints
@@ -291,16 +384,45 @@ impl NimbuxApiServer {
             .route("/api/v1/buckets", get(list_buckets).post(create_bucket))
             .route("/api/v1/buckets/:bucket", get(get_bucket).put(update_bucket).delete(delete_bucket))
             .route("/api/v1/buckets/:bucket/analytics", get(get_bucket_analytics))
-            .route("/api/v1/buckets/:bucket/lifecycle", get(get_lifecycle_policy).put(set_lifecycle_policy))
-            .route("/api/v1/buckets/:bucket/replication", get(get_replication_config).put(set_replication_config))
+            .route("/api/v1/buckets/:bucket/lifecycle", get(get_lifecycle_policy).put(set_lifecycle_policy).delete(delete_lifecycle_policy))
+            .route("/api/v1/buckets/:bucket/lifecycle/metrics", get(get_lifecycle_metrics))
+            .route("/api/v1/buckets/:bucket/inventory", get(list_bucket_inventories))
+            .route("/api/v1/buckets/:bucket/inventory/:id", get(get_bucket_inventory).put(set_bucket_inventory).delete(delete_bucket_inventory))
+            .route("/api/v1/buckets/:bucket/inventory/:id/run", post(run_bucket_inventory))
+            .route("/api/v1/buckets/:bucket/transforms", get(get_bucket_transforms).put(set_bucket_transforms).delete(delete_bucket_transforms))
+            .route("/api/v1/buckets/:bucket/notifications", get(get_bucket_notifications).put(set_bucket_notifications).delete(delete_bucket_notifications))
+            .route("/api/v1/buckets/:bucket/replication", get(get_bucket_replication).put(set_bucket_replication).delete(delete_bucket_replication))
+            .route("/api/v1/buckets/:bucket/replication/failures", get(get_replication_failures))
+            .route("/api/v1/buckets/:bucket/replication/failures/retry", post(retry_replication_failures))
+            .route("/api/v1/buckets/:bucket/storage-class", get(get_bucket_storage_class).put(set_bucket_storage_class))
+            .route("/api/v1/buckets/:bucket/quota", get(get_bucket_quota).put(set_bucket_quota).delete(delete_bucket_quota))
+            .route("/api/v1/buckets/:bucket/object-lock", get(get_bucket_object_lock).put(set_bucket_object_lock).delete(delete_bucket_object_lock))
             .route("/api/v1/buckets/:bucket/encryption", get(get_encryption_config).put(set_encryption_config))
+            .route("/api/v1/buckets/:bucket/versioning", get(get_bucket_versioning).put(set_bucket_versioning))
+            .route("/api/v1/buckets/:bucket/domains", get(list_bucket_domains))
+            .route("/api/v1/buckets/:bucket/domains/:domain", put(register_bucket_domain).delete(unregister_bucket_domain))
             
             // Object management
             .route("/api/v1/buckets/:bucket/objects", get(list_objects).post(upload_object))
-            .route("/api/v1/buckets/:bucket/objects/:key", get(get_object).put(update_object).delete(delete_object).head(head_object))
+            .route("/api/v1/buckets/:bucket/objects/:key", get(get_object).put(update_object).post(append_object).patch(patch_object).delete(delete_object).head(head_object))
             .route("/api/v1/buckets/:bucket/objects/:key/metadata", get(get_object_metadata).put(update_object_metadata))
             .route("/api/v1/buckets/:bucket/objects/:key/versions", get(list_object_versions))
             .route("/api/v1/buckets/:bucket/objects/:key/restore", post(restore_object))
+            .route("/api/v1/buckets/:bucket/objects/:key/presign", post(presign_object))
+            .route("/api/v1/buckets/:bucket/objects/:key/retention", get(get_object_lock).put(set_object_retention).delete(delete_object_retention))
+            .route("/api/v1/buckets/:bucket/objects/:key/legal-hold", get(get_object_lock).put(set_legal_hold))
+
+            // Multipart uploads
+            .route("/api/v1/buckets/:bucket/uploads", get(list_multipart_uploads).post(initiate_multipart_upload))
+            .route("/api/v1/buckets/:bucket/uploads/:upload_id", get(get_multipart_upload).delete(abort_multipart_upload))
+            .route("/api/v1/buckets/:bucket/uploads/:upload_id/parts/:part_number", put(upload_part).layer(DefaultBodyLimit::max(max_part_size)))
+            .route("/api/v1/buckets/:bucket/uploads/:upload_id/complete", post(complete_multipart_upload))
+
+            // Temporary credentials
+            .route("/api/v1/auth/credentials", post(issue_credentials))
+            
+            // User quotas
+            .route("/api/v1/users/:user_id/quota", get(get_user_quota).put(set_user_quota).delete(delete_user_quota))
             
             // Search and discovery
             .route("/api/v1/search", post(search_objects))
@@ -322,13 +444,55 @@ impl NimbuxApiServer {
             .route("/api/v1/events", get(get_events))
             .route("/api/v1/events/subscribe", post(subscribe_events))
             .route("/api/v1/notifications", get(get_notifications))
+            .route("/api/v1/notifications/queues/:queue/receive", post(receive_queued_events))
+            
+            // Cross-region replication
+            .route("/api/v1/replication", get(get_replication_stats))
+            .route("/api/v1/replication/:bucket/*key", put(receive_replica).delete(receive_replica_delete).layer(DefaultBodyLimit::max(max_object_size)))
+            
+            // Privacy
+            .route("/api/v1/erasure", post(start_erasure))
+            .route("/api/v1/erasure/verify", post(verify_erasure_certificate))
+            .route("/api/v1/erasure/:job_id", get(get_erasure_job))
+            
+            // Cold-tier restores
+            .route("/api/v1/restores", get(list_restore_jobs).post(start_restore))
+            .route("/api/v1/restores/stats", get(get_restore_stats))
+            .route("/api/v1/restores/:job_id", get(get_restore_job))
+            
+            // Operator dashboard (read-only)
+            .route("/api/v1/admin/transforms", get(get_transform_stats))
+            .route("/api/v1/admin/dashboard", get(get_dashboard_overview))
+            .route("/api/v1/admin/dashboard/topology", get(get_dashboard_topology))
+            .route("/api/v1/admin/dashboard/capacity", get(get_dashboard_capacity))
+            .route("/api/v1/admin/dashboard/buckets", get(get_dashboard_buckets))
+            .route("/api/v1/admin/dashboard/errors", get(get_dashboard_errors))
+            .route("/api/v1/admin/dashboard/integrity", get(get_dashboard_integrity))
+            .route("/api/v1/admin/dashboard/replication", get(get_dashboard_replication))
+            
+            // Compliance
+            .route("/api/v1/admin/compliance/report", get(get_compliance_report))
             
+            // Network policies
+            .route("/api/v1/admin/network-policies", get(list_network_policies))
+            .route("/api/v1/admin/network-policies/denials", get(get_network_policy_denials))
+            .route("/api/v1/admin/network-policies/buckets/:bucket", get(get_bucket_network_policy).put(set_bucket_network_policy).delete(delete_bucket_network_policy))
+            .route("/api/v1/admin/network-policies/access-keys/:key_id", get(get_key_network_policy).put(set_key_network_policy).delete(delete_key_network_policy))
+            
+            // Admission control
+            .route("/api/v1/admin/throttling", get(get_throttling_metrics))
+            .route("/api/v1/admin/throttling/limits", get(list_tenant_limits))
+            .route("/api/v1/admin/throttling/limits/:tenant", get(get_tenant_limits).put(set_tenant_limits).delete(delete_tenant_limits))
+            
+            .layer(middleware::from_fn_with_state(state.clone(), enforce_admission))
+            .layer(middleware::from_fn_with_state(state.clone(), enforce_network_policy))
+            .layer(middleware::from_fn_with_state(state.clone(), track_requests))
             .with_state(state);
 
         let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", self.port)).await?;
         tracing::info!("Nimbux API server listening on port {}", self.port);
         
-        axum::serve(listener, app).await?;
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
         Ok(())
     }
 }
@@ -417,6 +581,7 @@ async fn system_status(State(state): State<NimbuxApiState>) -> impl IntoResponse
 
 async fn get_metrics(State(state): State<NimbuxApiState>) -> impl IntoResponse {
     // TODO: Implement detailed metrics collection
+    let usage = state.engine.usage().await;
     let response = NimbuxResponse {
         success: true,
         data: Some(serde_json::json!({
@@ -428,11 +593,12 @@ async fn get_metrics(State(state): State<NimbuxApiState>) -> impl IntoResponse {
                     "rate_per_second": 0.0,
                 },
                 "storage": {
-                    "objects": 0,
-                    "size_bytes": 0,
+                    "objects": usage.total.objects,
+                    "size_bytes": usage.total.bytes,
                     "compression_ratio": 0.0,
                     "deduplication_ratio": 0.0,
                 },
+                "usage": usage,
                 "performance": {
                     "average_latency_ms": 0.0,
                     "p95_latency_ms": 0.0,
@@ -496,20 +662,518 @@ async fn get_bucket_analytics(State(_state): State<NimbuxApiState>) -> impl Into
     (StatusCode::NOT_IMPLEMENTED, "Bucket analytics not yet implemented")
 }
 
-async fn get_lifecycle_policy(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Lifecycle policies not yet implemented")
+/// Rules for `PUT /api/v1/buckets/:bucket/lifecycle`, replacing the current ones
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetLifecyclePolicyRequest {
+    pub rules: Vec<StorageLifecycleRule>,
+}
+
+async fn get_lifecycle_policy(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.lifecycle.policy(&bucket).await {
+        Ok(Some(policy)) => api_response(StatusCode::OK, Some(policy), None),
+        Ok(None) => api_response(StatusCode::NOT_FOUND, None, Some(format!("Bucket {} has no lifecycle policy", bucket))),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn set_lifecycle_policy(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(request): Json<SetLifecyclePolicyRequest>,
+) -> impl IntoResponse {
+    match state.lifecycle.set_policy(&bucket, request.rules).await {
+        Ok(policy) => api_response(StatusCode::OK, Some(policy), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_lifecycle_policy(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.lifecycle.delete_policy(&bucket).await {
+        Ok(()) => api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// What each rule of a bucket's lifecycle policy has done since startup
+async fn get_lifecycle_metrics(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    let rules = state.lifecycle.rule_metrics(&bucket).await;
+    api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket, "rules": rules })), None)
+}
+
+async fn list_bucket_inventories(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.inventory.inventories(&bucket).await {
+        Ok(inventories) => api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket, "inventories": inventories })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn get_bucket_inventory(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.inventory.inventory(&bucket, &id).await {
+        Ok(Some(inventory)) => api_response(StatusCode::OK, Some(inventory), None),
+        Ok(None) => api_response(StatusCode::NOT_FOUND, None, Some(format!("Bucket {} has no inventory {}", bucket, id))),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn set_bucket_inventory(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, id)): Path<(String, String)>,
+    Json(config): Json<InventoryConfig>,
+) -> impl IntoResponse {
+    match state.inventory.set_inventory(&bucket, &id, config).await {
+        Ok(inventory) => api_response(StatusCode::OK, Some(inventory), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_bucket_inventory(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.inventory.delete_inventory(&bucket, &id).await {
+        Ok(()) => api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket, "id": id })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Write an inventory report now instead of waiting for its schedule
+async fn run_bucket_inventory(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.inventory.export(&bucket, &id, Utc::now()).await {
+        Ok(export) => api_response(StatusCode::OK, Some(export), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn get_bucket_transforms(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.transforms.bucket_transforms(&bucket).await {
+        Ok(Some(transforms)) => api_response(StatusCode::OK, Some(transforms), None),
+        Ok(None) => api_response(StatusCode::NOT_FOUND, None, Some(format!("Bucket {} does not allow image transformations", bucket))),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn set_bucket_transforms(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(config): Json<TransformConfig>,
+) -> impl IntoResponse {
+    match state.transforms.set_bucket_transforms(&bucket, config).await {
+        Ok(transforms) => api_response(StatusCode::OK, Some(transforms), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_bucket_transforms(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.transforms.delete_bucket_transforms(&bucket).await {
+        Ok(()) => api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn get_transform_stats(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.transforms.stats()), None)
+}
+
+/// Rules for `PUT /api/v1/buckets/:bucket/notifications`, replacing the current ones
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetBucketNotificationsRequest {
+    pub rules: Vec<NotificationRule>,
+}
+
+async fn get_bucket_notifications(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.notifications.configuration(&bucket).await {
+        Ok(Some(notifications)) => api_response(StatusCode::OK, Some(notifications), None),
+        Ok(None) => api_response(StatusCode::NOT_FOUND, None, Some(format!("Bucket {} has no notification rules", bucket))),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn set_bucket_notifications(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(request): Json<SetBucketNotificationsRequest>,
+) -> impl IntoResponse {
+    match state.notifications.set_configuration(&bucket, request.rules).await {
+        Ok(notifications) => api_response(StatusCode::OK, Some(notifications), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_bucket_notifications(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.notifications.delete_configuration(&bucket).await {
+        Ok(()) => api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Class for `PUT /api/v1/buckets/:bucket/storage-class`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetStorageClassRequest {
+    pub storage_class: StorageClass,
+}
+
+async fn get_bucket_storage_class(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.engine.bucket_placement(&bucket).await), None)
+}
+
+/// Place new objects of the bucket in another class; stored objects keep theirs
+async fn set_bucket_storage_class(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(request): Json<SetStorageClassRequest>,
+) -> impl IntoResponse {
+    match state.engine.set_bucket_class(&bucket, request.storage_class).await {
+        Ok(placement) => api_response(StatusCode::OK, Some(placement), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn get_bucket_quota(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    let placement = state.engine.bucket_placement(&bucket).await;
+    let usage = state.engine.usage().await.buckets.into_iter().find(|usage| usage.bucket == bucket);
+    let data = serde_json::json!({
+        "bucket": bucket,
+        "quota": placement.quota,
+        "usage": usage.map(|usage| usage.usage).unwrap_or_default(),
+    });
+    api_response(StatusCode::OK, Some(data), None)
 }
 
-async fn set_lifecycle_policy(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Lifecycle policies not yet implemented")
+async fn set_bucket_quota(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(quota): Json<Quota>,
+) -> impl IntoResponse {
+    match state.engine.set_bucket_quota(&bucket, Some(quota)).await {
+        Ok(placement) => api_response(StatusCode::OK, Some(placement), None),
+        Err(e) => version_error_response(e),
+    }
 }
 
-async fn get_replication_config(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Replication not yet implemented")
+async fn delete_bucket_quota(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.engine.set_bucket_quota(&bucket, None).await {
+        Ok(placement) => api_response(StatusCode::OK, Some(placement), None),
+        Err(e) => version_error_response(e),
+    }
 }
 
-async fn set_replication_config(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Replication not yet implemented")
+async fn get_bucket_object_lock(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.engine.bucket_object_lock(&bucket).await), None)
+}
+
+/// Retain new objects of the bucket; stored objects keep their retention
+async fn set_bucket_object_lock(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(default_retention): Json<DefaultRetention>,
+) -> impl IntoResponse {
+    match state.engine.set_bucket_default_retention(&bucket, Some(default_retention)).await {
+        Ok(settings) => api_response(StatusCode::OK, Some(settings), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_bucket_object_lock(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.engine.set_bucket_default_retention(&bucket, None).await {
+        Ok(settings) => api_response(StatusCode::OK, Some(settings), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn list_bucket_domains(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.domains.domains_of(&bucket)), None)
+}
+
+/// Serve the bucket at a custom domain, optionally with a TLS certificate
+/// for it and redirecting the bucket's other addresses there
+async fn register_bucket_domain(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, domain)): Path<(String, String)>,
+    Json(request): Json<DomainRequest>,
+) -> impl IntoResponse {
+    match state.domains.register(&bucket, &domain, request).await {
+        Ok(registered) => api_response(StatusCode::OK, Some(registered), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn unregister_bucket_domain(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, domain)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.domains.unregister(&bucket, &domain).await {
+        Ok(true) => api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket, "domain": domain })), None),
+        Ok(false) => api_response(StatusCode::NOT_FOUND, None, Some(format!("{} is not a domain of bucket {}", domain, bucket))),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Quota and usage of the objects tagged `pixelle-user-id` with the user
+async fn get_user_quota(
+    State(state): State<NimbuxApiState>,
+    Path(user_id): Path<String>,
+) -> impl IntoResponse {
+    let quota = state.engine.user_quota(&user_id).await;
+    let usage = state.engine.usage().await.users.into_iter().find(|usage| usage.user_id == user_id);
+    let data = serde_json::json!({
+        "user_id": user_id,
+        "quota": quota.map(|quota| quota.quota),
+        "usage": usage.map(|usage| usage.usage).unwrap_or_default(),
+    });
+    api_response(StatusCode::OK, Some(data), None)
+}
+
+async fn set_user_quota(
+    State(state): State<NimbuxApiState>,
+    Path(user_id): Path<String>,
+    Json(quota): Json<Quota>,
+) -> impl IntoResponse {
+    match state.engine.set_user_quota(&user_id, Some(quota)).await {
+        Ok(quota) => api_response(StatusCode::OK, quota, None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_user_quota(
+    State(state): State<NimbuxApiState>,
+    Path(user_id): Path<String>,
+) -> impl IntoResponse {
+    match state.engine.set_user_quota(&user_id, None).await {
+        Ok(_) => api_response(StatusCode::OK, Some(serde_json::json!({ "user_id": user_id })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Rules for `PUT /api/v1/buckets/:bucket/replication`, replacing the current ones
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetBucketReplicationRequest {
+    pub rules: Vec<ReplicationRule>,
+}
+
+async fn get_bucket_replication(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.replication.configuration(&bucket).await {
+        Ok(Some(replication)) => api_response(StatusCode::OK, Some(replication), None),
+        Ok(None) => api_response(StatusCode::NOT_FOUND, None, Some(format!("Bucket {} has no replication rules", bucket))),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn set_bucket_replication(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(request): Json<SetBucketReplicationRequest>,
+) -> impl IntoResponse {
+    match state.replication.set_configuration(&bucket, request.rules).await {
+        Ok(replication) => api_response(StatusCode::OK, Some(replication), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_bucket_replication(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.replication.delete_configuration(&bucket).await {
+        Ok(()) => api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Replications of the bucket that failed on every attempt
+async fn get_replication_failures(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    let failures = state.replication.failed(Some(&bucket)).await;
+    api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket, "failures": failures })), None)
+}
+
+async fn retry_replication_failures(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.replication.retry_failed(Some(&bucket)).await {
+        Ok(queued) => api_response(StatusCode::OK, Some(serde_json::json!({ "bucket": bucket, "queued": queued })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Replication counts and lag of this region
+async fn get_replication_stats(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.replication.stats().await), None)
+}
+
+/// Replica sent by another region's `HttpReplicationTarget`
+async fn receive_replica(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    apply_replica(&state, &bucket, &key, &headers, ReplicaChange::Put { data: body.to_vec(), content_type }).await
+}
+
+async fn receive_replica_delete(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    apply_replica(&state, &bucket, &key, &headers, ReplicaChange::Delete).await
+}
+
+async fn apply_replica(
+    state: &NimbuxApiState,
+    bucket: &str,
+    key: &str,
+    headers: &HeaderMap,
+    change: ReplicaChange,
+) -> (StatusCode, Json<NimbuxResponse<serde_json::Value>>) {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Err(e) = state.replication.authorize_incoming(token) {
+        return auth_error_response(e);
+    }
+    let version = match replica_version(headers) {
+        Ok(version) => version,
+        Err(e) => return version_error_response(e),
+    };
+    match state.replication.apply_incoming(bucket, key, version, change).await {
+        Ok(outcome) => api_response(StatusCode::OK, Some(serde_json::json!({ "outcome": outcome })), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Version of an incoming replica from its `x-nimbux-*` headers
+fn replica_version(headers: &HeaderMap) -> Result<ReplicaVersion> {
+    let header_value = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| NimbuxError::InvalidRequest(format!("Missing {} header", name)))
+    };
+    Ok(ReplicaVersion {
+        vector: VersionVector::parse(header_value("x-nimbux-version-vector")?)?,
+        written_at: header_value("x-nimbux-written-at")?
+            .parse()
+            .map_err(|_| NimbuxError::InvalidRequest("Invalid x-nimbux-written-at header".to_string()))?,
+        region: header_value("x-nimbux-written-in")?.to_string(),
+    })
+}
+
+/// Presigned S3 URL for one object, for `POST /api/v1/buckets/:bucket/objects/:key/presign`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PresignObjectRequest {
+    /// User the URL acts for; their policies must allow the request
+    pub user_id: String,
+    pub method: PresignMethod,
+    #[serde(default = "default_presign_expiry_secs")]
+    pub expires_in_secs: u64,
+    /// For uploads, the `Content-Type` the client must send
+    pub content_type: Option<String>,
+}
+
+fn default_presign_expiry_secs() -> u64 {
+    900
+}
+
+/// Temporary credentials for `POST /api/v1/auth/credentials`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IssueCredentialsRequest {
+    pub user_id: String,
+    #[serde(default = "default_session_secs")]
+    pub duration_secs: u64,
+    /// Narrows what the credentials may do below the user's own policies
+    pub policy: Option<PolicyDocument>,
+}
+
+fn default_session_secs() -> u64 {
+    3600
+}
+
+async fn presign_object(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    Json(request): Json<PresignObjectRequest>,
+) -> impl IntoResponse {
+    let presign = PresignRequest {
+        method: request.method,
+        bucket,
+        key,
+        expires_in: std::time::Duration::from_secs(request.expires_in_secs),
+        content_type: request.content_type,
+    };
+    match state.presigner.presign_for_user(&state.auth_manager, &request.user_id, &presign).await {
+        Ok(url) => api_response(StatusCode::OK, Some(url), None),
+        Err(e) => auth_error_response(e),
+    }
+}
+
+async fn issue_credentials(
+    State(state): State<NimbuxApiState>,
+    Json(request): Json<IssueCredentialsRequest>,
+) -> impl IntoResponse {
+    let duration = std::time::Duration::from_secs(request.duration_secs);
+    match state.auth_manager.issue_temporary_credentials(&request.user_id, duration, request.policy).await {
+        Ok(credentials) => api_response(StatusCode::OK, Some(credentials), None),
+        Err(e) => auth_error_response(e),
+    }
 }
 
 async fn get_encryption_config(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
@@ -537,8 +1201,186 @@ async fn update_object(State(_state): State<NimbuxApiState>) -> impl IntoRespons
     (StatusCode::NOT_IMPLEMENTED, "Object operations not yet implemented")
 }
 
-async fn delete_object(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Object operations not yet implemented")
+#[derive(Debug, Deserialize)]
+pub struct ObjectWriteQuery {
+    /// Present (`?append`) to add the body to the end of the object
+    pub append: Option<String>,
+    /// Offset the body is written at; for appends, the size the object must have
+    pub offset: Option<u64>,
+}
+
+/// Append the body to an object, e.g. the next chunk of a log. With
+/// `offset` or `If-Match` the append only happens if nobody else wrote first.
+async fn append_object(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<ObjectWriteQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    if query.append.is_none() {
+        return api_response(StatusCode::BAD_REQUEST, None, Some("Use ?append to append to an object".to_string()));
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let conditions = write_conditions(&headers, query.offset);
+    match state.writer.append(&format!("{}/{}", bucket, key), body.to_vec(), content_type, &conditions).await {
+        Ok(result) => api_response(StatusCode::OK, Some(result), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Overwrite part of an object with the body, starting at `offset`
+async fn patch_object(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<ObjectWriteQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let conditions = write_conditions(&headers, query.offset);
+    match state.writer.patch(&format!("{}/{}", bucket, key), body.to_vec(), &conditions).await {
+        Ok(result) => api_response(StatusCode::OK, Some(result), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+fn write_conditions(headers: &HeaderMap, offset: Option<u64>) -> WriteConditions {
+    WriteConditions {
+        if_match: headers
+            .get(header::IF_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        offset,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InitiateMultipartRequest {
+    pub key: String,
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CompleteMultipartRequest {
+    /// Parts to assemble, in order; empty or missing for every uploaded part
+    #[serde(default)]
+    pub parts: Vec<CompletedPart>,
+}
+
+/// Start a multipart upload of `key`; parts are then uploaded under the
+/// returned `upload_id`
+async fn initiate_multipart_upload(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(request): Json<InitiateMultipartRequest>,
+) -> impl IntoResponse {
+    match state.multipart.initiate(&format!("{}/{}", bucket, request.key), request.content_type).await {
+        Ok(upload) => api_response(StatusCode::CREATED, Some(upload), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Uploads in the bucket that are still accepting parts
+async fn list_multipart_uploads(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.multipart.list(&format!("{}/", bucket)).await {
+        Ok(uploads) => api_response(StatusCode::OK, Some(uploads), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn get_multipart_upload(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, upload_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match upload_in_bucket(&state, &bucket, &upload_id).await {
+        Ok(upload) => api_response(StatusCode::OK, Some(upload), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Store one part of an upload. Parts can be sent in any order and in
+/// parallel; `x-nimbux-checksum-sha256` has the body checked on arrival.
+async fn upload_part(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, upload_id, part_number)): Path<(String, String, u32)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    if let Err(e) = upload_in_bucket(&state, &bucket, &upload_id).await {
+        return version_error_response(e);
+    }
+    let checksum = headers.get("x-nimbux-checksum-sha256").and_then(|value| value.to_str().ok());
+    match state.multipart.upload_part(&upload_id, part_number, body.to_vec(), checksum).await {
+        Ok(part) => api_response(StatusCode::OK, Some(part), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Assemble the parts into the object
+async fn complete_multipart_upload(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, upload_id)): Path<(String, String)>,
+    request: Option<Json<CompleteMultipartRequest>>,
+) -> impl IntoResponse {
+    if let Err(e) = upload_in_bucket(&state, &bucket, &upload_id).await {
+        return version_error_response(e);
+    }
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    match state.multipart.complete(&upload_id, &request.parts).await {
+        Ok(completed) => api_response(StatusCode::OK, Some(completed), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn abort_multipart_upload(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, upload_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if let Err(e) = upload_in_bucket(&state, &bucket, &upload_id).await {
+        return version_error_response(e);
+    }
+    match state.multipart.abort(&upload_id).await {
+        Ok(upload) => api_response(StatusCode::OK, Some(upload), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// An upload, if it writes to `bucket`
+async fn upload_in_bucket(state: &NimbuxApiState, bucket: &str, upload_id: &str) -> Result<MultipartUpload> {
+    let upload = state.multipart.get(upload_id).await?;
+    if !upload.object_id.starts_with(&format!("{}/", bucket)) {
+        return Err(NimbuxError::ObjectNotFound { object_id: format!("upload {}", upload_id) });
+    }
+    Ok(upload)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ObjectVersionQuery {
+    pub version_id: Option<String>,
+}
+
+/// Delete an object; in a versioned bucket this adds a delete marker unless a
+/// `version_id` is given, which removes that version permanently
+async fn delete_object(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<ObjectVersionQuery>,
+) -> impl IntoResponse {
+    let object_id = format!("{}/{}", bucket, key);
+    let result = match &query.version_id {
+        Some(version_id) => state.versioning.delete_version(&object_id, version_id).await,
+        None => state.versioning.delete(&object_id).await,
+    };
+    match result {
+        Ok(()) => api_response(StatusCode::OK, Some(serde_json::json!({ "deleted": object_id, "version_id": query.version_id })), None),
+        Err(e) => version_error_response(e),
+    }
 }
 
 async fn head_object(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
@@ -553,12 +1395,214 @@ async fn update_object_metadata(State(_state): State<NimbuxApiState>) -> impl In
     (StatusCode::NOT_IMPLEMENTED, "Object metadata not yet implemented")
 }
 
-async fn list_object_versions(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Object versioning not yet implemented")
+async fn list_object_versions(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.versioning.list_versions(&format!("{}/{}", bucket, key)).await {
+        Ok(versions) => api_response(StatusCode::OK, Some(versions), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RestoreVersionRequest {
+    /// Version to make current; the newest non-deleted version if unset
+    pub version_id: Option<String>,
+}
+
+async fn restore_object(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    request: Option<Json<RestoreVersionRequest>>,
+) -> impl IntoResponse {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let object_id = format!("{}/{}", bucket, key);
+    match state.versioning.restore_version(&object_id, request.version_id.as_deref()).await {
+        Ok(version) => api_response(StatusCode::OK, Some(version), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Retention and legal hold of the object
+async fn get_object_lock(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.engine.object_lock(&format!("{}/{}", bucket, key)).await {
+        Ok(lock) => api_response(StatusCode::OK, Some(lock), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+/// Action the caller's policies must allow on the object for `bypass_governance`
+const BYPASS_GOVERNANCE_ACTION: &str = "s3:BypassGovernanceRetention";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BypassGovernanceQuery {
+    /// Allow shortening or removing a governance retention; the request must
+    /// be signed by a caller allowed `s3:BypassGovernanceRetention`
+    #[serde(default)]
+    pub bypass_governance: bool,
+}
+
+async fn set_object_retention(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<BypassGovernanceQuery>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let object_id = format!("{}/{}", bucket, key);
+    let retention: Retention = match serde_json::from_slice(&body) {
+        Ok(retention) => retention,
+        Err(e) => return api_response(StatusCode::BAD_REQUEST, None, Some(format!("Invalid retention: {}", e))),
+    };
+    if query.bypass_governance {
+        let region = state.presigner.region();
+        if let Err(e) = authorize_governance_bypass(&state.auth_manager, region, &method, &uri, &headers, &body, &object_id).await {
+            return auth_error_response(e);
+        }
+    }
+    match state.engine.set_object_retention(&object_id, Some(retention), query.bypass_governance).await {
+        Ok(lock) => api_response(StatusCode::OK, Some(lock), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_object_retention(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<BypassGovernanceQuery>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let object_id = format!("{}/{}", bucket, key);
+    if query.bypass_governance {
+        let region = state.presigner.region();
+        if let Err(e) = authorize_governance_bypass(&state.auth_manager, region, &method, &uri, &headers, &[], &object_id).await {
+            return auth_error_response(e);
+        }
+    }
+    match state.engine.set_object_retention(&object_id, None, query.bypass_governance).await {
+        Ok(lock) => api_response(StatusCode::OK, Some(lock), None),
+        Err(e) => version_error_response(e),
+    }
 }
 
-async fn restore_object(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Object restore not yet implemented")
+/// Check a request that bypasses governance retention is SigV4-signed, body
+/// included, by a caller whose policies allow `s3:BypassGovernanceRetention`
+/// on the object
+async fn authorize_governance_bypass(
+    auth_manager: &AuthManager,
+    region: &str,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: &[u8],
+    object_id: &str,
+) -> Result<()> {
+    let unauthenticated = |e: S3Error| NimbuxError::Authentication(e.message);
+    let raw_query = uri.query().unwrap_or_default();
+    let query = sigv4::parse_query(raw_query).map_err(unauthenticated)?;
+    let signed = SignedRequest::parse(headers, &query, Utc::now())
+        .map_err(unauthenticated)?
+        .ok_or_else(|| NimbuxError::Authentication("Bypassing governance retention needs a signed request".to_string()))?;
+
+    let (access_key, user) = auth_manager.active_access_key(&signed.credential.access_key_id).await?;
+    if !access_key.accepts_session_token(signed.session_token.as_deref()) {
+        return Err(NimbuxError::Authentication("Invalid session token".to_string()));
+    }
+    signed
+        .verify(&access_key.secret_access_key, region, method, uri.path(), raw_query, headers)
+        .map_err(unauthenticated)?;
+    if signed.payload != PayloadSignature::Sha256(sigv4::sha256_hex(body)) {
+        return Err(NimbuxError::Authentication("The request body must be signed".to_string()));
+    }
+    auth_manager.record_key_use(&access_key.access_key_id).await;
+
+    let resource = format!("arn:aws:s3:::{}", object_id);
+    let context = AuthContext {
+        user,
+        access_key,
+        request_time: Utc::now().timestamp() as u64,
+        signature: signed.signature,
+    };
+    if !auth_manager.check_permission(&context, BYPASS_GOVERNANCE_ACTION, &resource).await? {
+        return Err(NimbuxError::Authorization(format!("Not allowed to {} on {}", BYPASS_GOVERNANCE_ACTION, resource)));
+    }
+    Ok(())
+}
+
+/// Hold for `PUT /api/v1/buckets/:bucket/objects/:key/legal-hold`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LegalHoldRequest {
+    pub legal_hold: bool,
+}
+
+async fn set_legal_hold(
+    State(state): State<NimbuxApiState>,
+    Path((bucket, key)): Path<(String, String)>,
+    Json(request): Json<LegalHoldRequest>,
+) -> impl IntoResponse {
+    match state.engine.set_legal_hold(&format!("{}/{}", bucket, key), request.legal_hold).await {
+        Ok(lock) => api_response(StatusCode::OK, Some(lock), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BucketVersioning {
+    pub status: VersioningStatus,
+}
+
+async fn get_bucket_versioning(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    match state.versioning.versioning(&bucket).await {
+        Ok(status) => api_response(StatusCode::OK, Some(BucketVersioning { status }), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn set_bucket_versioning(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(request): Json<BucketVersioning>,
+) -> impl IntoResponse {
+    match state.versioning.set_versioning(&bucket, request.status).await {
+        Ok(()) => api_response(StatusCode::OK, Some(request), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+fn version_error_response<T>(error: NimbuxError) -> (StatusCode, Json<NimbuxResponse<T>>) {
+    match error {
+        NimbuxError::ObjectNotFound { .. } => api_response(StatusCode::NOT_FOUND, None, Some(error.to_string())),
+        NimbuxError::InvalidRequest(msg) => api_response(StatusCode::BAD_REQUEST, None, Some(msg)),
+        NimbuxError::PreconditionFailed(msg) => api_response(StatusCode::PRECONDITION_FAILED, None, Some(msg)),
+        NimbuxError::OffsetMismatch { .. } => api_response(StatusCode::CONFLICT, None, Some(error.to_string())),
+        NimbuxError::QuotaExceeded(msg) => api_response(StatusCode::INSUFFICIENT_STORAGE, None, Some(msg)),
+        NimbuxError::Throttled { .. } => api_response(StatusCode::TOO_MANY_REQUESTS, None, Some(error.to_string())),
+        NimbuxError::ObjectLocked { .. } => api_response(StatusCode::FORBIDDEN, None, Some(error.to_string())),
+        NimbuxError::ChecksumMismatch { .. } | NimbuxError::InvalidObjectId { .. } => {
+            api_response(StatusCode::BAD_REQUEST, None, Some(error.to_string()))
+        }
+        e => api_response(StatusCode::INTERNAL_SERVER_ERROR, None, Some(e.to_string())),
+    }
+}
+
+fn auth_error_response<T>(error: NimbuxError) -> (StatusCode, Json<NimbuxResponse<T>>) {
+    match error {
+        NimbuxError::Authentication(msg) => api_response(StatusCode::UNAUTHORIZED, None, Some(msg)),
+        NimbuxError::Authorization(msg) => api_response(StatusCode::FORBIDDEN, None, Some(msg)),
+        NimbuxError::InvalidRequest(msg) => api_response(StatusCode::BAD_REQUEST, None, Some(msg)),
+        e => api_response(StatusCode::INTERNAL_SERVER_ERROR, None, Some(e.to_string())),
+    }
 }
 
 // Placeholder handlers for search and discovery
@@ -613,6 +1657,483 @@ async fn subscribe_events(State(_state): State<NimbuxApiState>) -> impl IntoResp
     (StatusCode::NOT_IMPLEMENTED, "Event subscription not yet implemented")
 }
 
-async fn get_notifications(State(_state): State<NimbuxApiState>) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Notifications not yet implemented")
-}
\ No newline at end of file
+/// Event notification counts since startup
+async fn get_notifications(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.notifications.stats().await), None)
+}
+
+#[derive(Debug, Deserialize)]
+struct ReceiveEventsQuery {
+    max: Option<usize>,
+}
+
+/// Take the oldest events from an internal queue; received events are removed
+async fn receive_queued_events(
+    State(state): State<NimbuxApiState>,
+    Path(queue): Path<String>,
+    Query(query): Query<ReceiveEventsQuery>,
+) -> impl IntoResponse {
+    let events = state.notifications.receive(&queue, query.max.unwrap_or(100).clamp(1, 1000)).await;
+    api_response(StatusCode::OK, Some(serde_json::json!({ "queue": queue, "events": events })), None)
+}
+
+// Privacy handlers
+
+#[derive(Debug, Deserialize)]
+pub struct ErasureRequest {
+    pub user_id: String,
+}
+
+fn api_response<T>(status: StatusCode, data: Option<T>, error: Option<String>) -> (StatusCode, Json<NimbuxResponse<T>>) {
+    (status, Json(NimbuxResponse {
+        success: error.is_none(),
+        data,
+        error,
+        request_id: Uuid::new_v4().to_string(),
+        timestamp: Utc::now(),
+        performance: None,
+    }))
+}
+
+/// Start erasing every object tagged to a user; poll the returned job for progress
+async fn start_erasure(
+    State(state): State<NimbuxApiState>,
+    Json(request): Json<ErasureRequest>,
+) -> impl IntoResponse {
+    match state.erasure.start_erasure(&request.user_id).await {
+        Ok(job) => api_response(StatusCode::ACCEPTED, Some(job), None),
+        Err(NimbuxError::InvalidRequest(msg)) => api_response(StatusCode::BAD_REQUEST, None, Some(msg)),
+        Err(e) => api_response(StatusCode::INTERNAL_SERVER_ERROR, None, Some(e.to_string())),
+    }
+}
+
+async fn get_erasure_job(
+    State(state): State<NimbuxApiState>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    match state.erasure.job(&job_id).await {
+        Some(job) => api_response(StatusCode::OK, Some(job), None),
+        None => api_response(StatusCode::NOT_FOUND, None, Some(format!("Erasure job not found: {}", job_id))),
+    }
+}
+
+async fn verify_erasure_certificate(
+    State(state): State<NimbuxApiState>,
+    Json(certificate): Json<ErasureCertificate>,
+) -> impl IntoResponse {
+    match state.erasure.verify_certificate(&certificate) {
+        Ok(valid) => api_response(StatusCode::OK, Some(serde_json::json!({ "valid": valid })), None),
+        Err(e) => api_response(StatusCode::INTERNAL_SERVER_ERROR, None, Some(e.to_string())),
+    }
+}
+
+/// Queue archived objects for restore; poll the returned job for progress
+async fn start_restore(
+    State(state): State<NimbuxApiState>,
+    Json(request): Json<RestoreRequest>,
+) -> impl IntoResponse {
+    match state.restore.submit(request).await {
+        Ok(job) => api_response(StatusCode::ACCEPTED, Some(job), None),
+        Err(NimbuxError::InvalidRequest(msg)) => api_response(StatusCode::BAD_REQUEST, None, Some(msg)),
+        Err(e) => api_response(StatusCode::INTERNAL_SERVER_ERROR, None, Some(e.to_string())),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RestoreJobsQuery {
+    tenant: Option<String>,
+}
+
+async fn list_restore_jobs(
+    State(state): State<NimbuxApiState>,
+    Query(query): Query<RestoreJobsQuery>,
+) -> impl IntoResponse {
+    let jobs = state.restore.jobs(query.tenant.as_deref()).await;
+    api_response(StatusCode::OK, Some(jobs), None)
+}
+
+async fn get_restore_job(
+    State(state): State<NimbuxApiState>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    match state.restore.job(&job_id).await {
+        Some(job) => api_response(StatusCode::OK, Some(job), None),
+        None => api_response(StatusCode::NOT_FOUND, None, Some(format!("Restore job not found: {}", job_id))),
+    }
+}
+
+async fn get_restore_stats(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.restore.stats().await), None)
+}
+
+// Operator dashboard handlers
+
+/// Feed request outcomes to the metrics collector and the operator dashboard
+async fn track_requests(
+    State(state): State<NimbuxApiState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let status = response.status();
+    if let Err(e) = state.metrics.record_request(!status.is_server_error(), started.elapsed()).await {
+        warn!("Failed to record request metrics: {}", e);
+    }
+    state.dashboard.record_request(&method, &path, status.as_u16()).await;
+    response
+}
+
+fn dashboard_result<T>(result: Result<T>) -> (StatusCode, Json<NimbuxResponse<T>>) {
+    match result {
+        Ok(data) => api_response(StatusCode::OK, Some(data), None),
+        Err(e) => api_response(StatusCode::INTERNAL_SERVER_ERROR, None, Some(e.to_string())),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DashboardBucketsQuery {
+    #[serde(default)]
+    pub sort: BucketSort,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DashboardErrorsQuery {
+    pub limit: Option<usize>,
+}
+
+/// Every dashboard view in one call, for the landing page
+async fn get_dashboard_overview(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    dashboard_result(state.dashboard.overview().await)
+}
+
+async fn get_dashboard_topology(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    dashboard_result(state.dashboard.topology().await)
+}
+
+async fn get_dashboard_capacity(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    dashboard_result(state.dashboard.capacity().await)
+}
+
+async fn get_dashboard_buckets(
+    State(state): State<NimbuxApiState>,
+    Query(query): Query<DashboardBucketsQuery>,
+) -> impl IntoResponse {
+    dashboard_result(state.dashboard.top_buckets(query.sort, query.limit).await)
+}
+
+async fn get_dashboard_errors(
+    State(state): State<NimbuxApiState>,
+    Query(query): Query<DashboardErrorsQuery>,
+) -> impl IntoResponse {
+    dashboard_result(Ok(state.dashboard.recent_errors(query.limit).await))
+}
+
+async fn get_dashboard_integrity(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    dashboard_result(state.dashboard.integrity().await)
+}
+
+async fn get_dashboard_replication(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    dashboard_result(state.dashboard.replication().await)
+}
+
+// Network policy handlers
+
+/// Bucket named by a `/api/v1/buckets/:bucket/...` path
+fn request_bucket(path: &str) -> Option<&str> {
+    path.strip_prefix("/api/v1/buckets/")
+        .and_then(|rest| rest.split('/').next())
+        .filter(|bucket| !bucket.is_empty())
+}
+
+/// Access key ID from the credential scope of a signed request
+fn request_access_key(headers: &HeaderMap) -> Option<&str> {
+    let authorization = headers.get("authorization")?.to_str().ok()?;
+    let credential = authorization.split_once("Credential=")?.1;
+    credential.split(['/', ',']).next().filter(|key_id| !key_id.is_empty())
+}
+
+/// Refuse requests from networks or countries a bucket or access key policy excludes,
+/// before they reach authentication and authorization
+async fn enforce_network_policy(
+    State(state): State<NimbuxApiState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let forwarded_for = request.headers().get("x-forwarded-for").and_then(|value| value.to_str().ok());
+    let client_ip = state.network_policy.client_ip(peer.ip(), forwarded_for);
+    let decision = state
+        .network_policy
+        .evaluate(client_ip, request_bucket(request.uri().path()), request_access_key(request.headers()))
+        .await;
+
+    match decision {
+        PolicyDecision::Allow => next.run(request).await,
+        PolicyDecision::Deny(denial) => {
+            api_response::<()>(StatusCode::FORBIDDEN, None, Some(denial.to_string())).into_response()
+        }
+    }
+}
+
+/// Locked objects per bucket and objects missing their bucket's retention
+async fn get_compliance_report(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    match state.compliance.report().await {
+        Ok(report) => api_response(StatusCode::OK, Some(report), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NetworkPolicyDenialsQuery {
+    pub limit: Option<usize>,
+}
+
+async fn list_network_policies(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.network_policy.list_policies().await), None)
+}
+
+async fn get_network_policy_denials(
+    State(state): State<NimbuxApiState>,
+    Query(query): Query<NetworkPolicyDenialsQuery>,
+) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.network_policy.denial_stats(query.limit).await), None)
+}
+
+async fn get_network_policy(state: &NimbuxApiState, scope: PolicyScope) -> impl IntoResponse {
+    match state.network_policy.policy(&scope).await {
+        Some(policy) => api_response(StatusCode::OK, Some(policy), None),
+        None => api_response(StatusCode::NOT_FOUND, None, Some(format!("No network policy for {}", scope))),
+    }
+}
+
+async fn set_network_policy(state: &NimbuxApiState, scope: PolicyScope, rules: NetworkRules) -> impl IntoResponse {
+    match state.network_policy.set_policy(scope, rules).await {
+        Ok(policy) => api_response(StatusCode::OK, Some(policy), None),
+        Err(NimbuxError::InvalidRequest(msg)) => api_response(StatusCode::BAD_REQUEST, None, Some(msg)),
+        Err(e) => api_response(StatusCode::INTERNAL_SERVER_ERROR, None, Some(e.to_string())),
+    }
+}
+
+async fn delete_network_policy(state: &NimbuxApiState, scope: PolicyScope) -> impl IntoResponse {
+    if state.network_policy.delete_policy(&scope).await {
+        api_response(StatusCode::OK, Some(serde_json::json!({ "deleted": scope })), None)
+    } else {
+        api_response(StatusCode::NOT_FOUND, None, Some(format!("No network policy for {}", scope)))
+    }
+}
+
+async fn get_bucket_network_policy(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    get_network_policy(&state, PolicyScope::Bucket(bucket)).await
+}
+
+async fn set_bucket_network_policy(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+    Json(rules): Json<NetworkRules>,
+) -> impl IntoResponse {
+    set_network_policy(&state, PolicyScope::Bucket(bucket), rules).await
+}
+
+async fn delete_bucket_network_policy(
+    State(state): State<NimbuxApiState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    delete_network_policy(&state, PolicyScope::Bucket(bucket)).await
+}
+
+async fn get_key_network_policy(
+    State(state): State<NimbuxApiState>,
+    Path(key_id): Path<String>,
+) -> impl IntoResponse {
+    get_network_policy(&state, PolicyScope::AccessKey(key_id)).await
+}
+
+async fn set_key_network_policy(
+    State(state): State<NimbuxApiState>,
+    Path(key_id): Path<String>,
+    Json(rules): Json<NetworkRules>,
+) -> impl IntoResponse {
+    set_network_policy(&state, PolicyScope::AccessKey(key_id), rules).await
+}
+
+async fn delete_key_network_policy(
+    State(state): State<NimbuxApiState>,
+    Path(key_id): Path<String>,
+) -> impl IntoResponse {
+    delete_network_policy(&state, PolicyScope::AccessKey(key_id)).await
+}
+
+// Admission control handlers
+
+/// Hold each tenant, the access key of a signed request or else the client
+/// IP, to its request and bandwidth limits; response bodies of a known
+/// length are charged on the way out
+async fn enforce_admission(
+    State(state): State<NimbuxApiState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let tenant = match request_access_key(request.headers()) {
+        Some(key_id) => key_id.to_string(),
+        None => {
+            let forwarded_for = request.headers().get("x-forwarded-for").and_then(|value| value.to_str().ok());
+            state.network_policy.client_ip(peer.ip(), forwarded_for).to_string()
+        }
+    };
+    let declared = content_length(request.headers());
+
+    let permit = match state.admission.admit(&tenant, declared.unwrap_or(0)) {
+        Ok(permit) => permit,
+        Err(error) => {
+            let retry_after = match &error {
+                NimbuxError::Throttled { retry_after_ms, .. } => retry_after_ms.div_ceil(1000),
+                _ => 1,
+            };
+            let mut response = version_error_response::<()>(error).into_response();
+            response.headers_mut().insert(header::RETRY_AFTER, retry_after.into());
+            return response;
+        }
+    };
+
+    let response = next.run(request).await;
+    if let Some(length) = content_length(response.headers()) {
+        permit.charge(length);
+    }
+    response
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers.get(header::CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+async fn get_throttling_metrics(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.admission.metrics()), None)
+}
+
+async fn list_tenant_limits(State(state): State<NimbuxApiState>) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.admission.overrides()), None)
+}
+
+async fn get_tenant_limits(
+    State(state): State<NimbuxApiState>,
+    Path(tenant): Path<String>,
+) -> impl IntoResponse {
+    api_response(StatusCode::OK, Some(state.admission.limits(&tenant)), None)
+}
+
+async fn set_tenant_limits(
+    State(state): State<NimbuxApiState>,
+    Path(tenant): Path<String>,
+    Json(limits): Json<TenantLimits>,
+) -> impl IntoResponse {
+    match state.admission.set_limits(&tenant, limits.clone()) {
+        Ok(()) => api_response(StatusCode::OK, Some(limits), None),
+        Err(e) => version_error_response(e),
+    }
+}
+
+async fn delete_tenant_limits(
+    State(state): State<NimbuxApiState>,
+    Path(tenant): Path<String>,
+) -> impl IntoResponse {
+    if state.admission.remove_limits(&tenant) {
+        api_response(StatusCode::OK, Some(state.admission.limits(&tenant)), None)
+    } else {
+        api_response(StatusCode::NOT_FOUND, None, Some(format!("No limits of their own for {}", tenant)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AccessKey, PolicyStatement};
+    use crate::network::s3::sigv4::{Credential, ALGORITHM, AMZ_DATE_FORMAT};
+
+    const REGION: &str = "us-east-1";
+    const PATH: &str = "/api/v1/buckets/records/objects/ledger.csv/retention";
+    const QUERY: &str = "bypass_governance=true";
+
+    /// Headers of a request to `PATH?QUERY` signed with `access_key`
+    fn signed_headers(access_key: &AccessKey, method: &Method, body: &[u8]) -> HeaderMap {
+        let now = Utc::now();
+        let amz_date = now.format(AMZ_DATE_FORMAT).to_string();
+        let credential = Credential {
+            access_key_id: access_key.access_key_id.clone(),
+            date: now.format("%Y%m%d").to_string(),
+            region: REGION.to_string(),
+            service: "s3".to_string(),
+        };
+        let payload_hash = sigv4::sha256_hex(body);
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("localhost:8080"));
+        headers.insert("x-amz-date", HeaderValue::from_str(&amz_date).unwrap());
+        headers.insert("x-amz-content-sha256", HeaderValue::from_str(&payload_hash).unwrap());
+        let signed = vec!["host".to_string(), "x-amz-content-sha256".to_string(), "x-amz-date".to_string()];
+
+        let canonical = sigv4::canonical_request(method.as_str(), PATH, QUERY, &headers, &signed, &payload_hash, false).unwrap();
+        let (_, signature) = sigv4::sign(&access_key.secret_access_key, &credential, &amz_date, &canonical);
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM,
+            credential.access_key_id,
+            credential.scope(),
+            signed.join(";"),
+            signature
+        );
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&authorization).unwrap());
+        headers
+    }
+
+    async fn caller(auth_manager: &AuthManager, name: &str, actions: &[&str]) -> AccessKey {
+        let user = auth_manager.create_user(name.to_string(), format!("{}@example.com", name)).await.unwrap();
+        auth_manager
+            .add_policy(&user.user_id, PolicyDocument {
+                version: "2012-10-17".to_string(),
+                statement: vec![PolicyStatement {
+                    effect: "Allow".to_string(),
+                    action: actions.iter().map(|action| action.to_string()).collect(),
+                    resource: vec!["arn:aws:s3:::records/*".to_string()],
+                    condition: None,
+                }],
+            })
+            .await
+            .unwrap();
+        auth_manager.create_access_key(&user.user_id).await.unwrap()
+    }
+
+    async fn bypass(auth_manager: &AuthManager, headers: HeaderMap, body: &[u8]) -> Result<()> {
+        let uri: Uri = format!("{}?{}", PATH, QUERY).parse().unwrap();
+        authorize_governance_bypass(auth_manager, REGION, &Method::PUT, &uri, &headers, body, "records/ledger.csv").await
+    }
+
+    #[tokio::test]
+    async fn test_only_privileged_callers_bypass_governance() {
+        let auth_manager = AuthManager::new();
+        let body = br#"{"mode":"GOVERNANCE","retain_until":"2020-01-01T00:00:00Z"}"#;
+
+        let err = bypass(&auth_manager, HeaderMap::new(), body).await.unwrap_err();
+        assert!(matches!(err, NimbuxError::Authentication(_)), "{:?}", err);
+
+        // Allowed to set retention, but not to bypass it
+        let ordinary = caller(&auth_manager, "writer", &["s3:PutObjectRetention"]).await;
+        let err = bypass(&auth_manager, signed_headers(&ordinary, &Method::PUT, body), body).await.unwrap_err();
+        assert!(matches!(err, NimbuxError::Authorization(_)), "{:?}", err);
+
+        let admin = caller(&auth_manager, "admin", &["s3:PutObjectRetention", BYPASS_GOVERNANCE_ACTION]).await;
+        bypass(&auth_manager, signed_headers(&admin, &Method::PUT, body), body).await.unwrap();
+
+        // The signature covers the body, so it cannot be swapped for another retention
+        let err = bypass(&auth_manager, signed_headers(&admin, &Method::PUT, body), b"{}").await.unwrap_err();
+        assert!(matches!(err, NimbuxError::Authentication(_)), "{:?}", err);
+    }
+}
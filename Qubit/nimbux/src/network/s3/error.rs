@@ -0,0 +1,219 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// S3 error codes
+
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use crate::errors::NimbuxError;
+
+/// Error returned to S3 clients as an `<Error>` document
+#[derive(Error, Debug, Clone)]
+#[error("{code}: {message}")]
+pub struct S3Error {
+    /// S3 error code clients switch on, e.g. `NoSuchKey`
+    pub code: &'static str,
+    pub status: StatusCode,
+    pub message: String,
+    /// Seconds a throttled client should wait, sent as `Retry-After`
+    pub retry_after: Option<u64>,
+}
+
+/// Result type alias for S3 operations
+pub type S3Result<T> = std::result::Result<T, S3Error>;
+
+impl S3Error {
+    pub fn new(code: &'static str, status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            status,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    pub fn access_denied(message: impl Into<String>) -> Self {
+        Self::new("AccessDenied", StatusCode::FORBIDDEN, message)
+    }
+
+    pub fn invalid_access_key_id() -> Self {
+        Self::new(
+            "InvalidAccessKeyId",
+            StatusCode::FORBIDDEN,
+            "The access key ID you provided does not exist in our records",
+        )
+    }
+
+    pub fn signature_does_not_match() -> Self {
+        Self::new(
+            "SignatureDoesNotMatch",
+            StatusCode::FORBIDDEN,
+            "The request signature we calculated does not match the signature you provided",
+        )
+    }
+
+    pub fn authorization_header_malformed(message: impl Into<String>) -> Self {
+        Self::new("AuthorizationHeaderMalformed", StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn invalid_token() -> Self {
+        Self::new(
+            "InvalidToken",
+            StatusCode::BAD_REQUEST,
+            "The provided token is malformed or otherwise invalid",
+        )
+    }
+
+    pub fn request_time_too_skewed() -> Self {
+        Self::new(
+            "RequestTimeTooSkewed",
+            StatusCode::FORBIDDEN,
+            "The difference between the request time and the server's time is too large",
+        )
+    }
+
+    pub fn content_sha256_mismatch() -> Self {
+        Self::new(
+            "XAmzContentSHA256Mismatch",
+            StatusCode::BAD_REQUEST,
+            "The provided x-amz-content-sha256 header does not match what was computed",
+        )
+    }
+
+    pub fn bad_digest() -> Self {
+        Self::new("BadDigest", StatusCode::BAD_REQUEST, "The Content-MD5 you specified did not match what we received")
+    }
+
+    pub fn incomplete_body(message: impl Into<String>) -> Self {
+        Self::new("IncompleteBody", StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn entity_too_large(max: usize) -> Self {
+        Self::new(
+            "EntityTooLarge",
+            StatusCode::BAD_REQUEST,
+            format!("Your proposed upload exceeds the maximum allowed size of {} bytes", max),
+        )
+    }
+
+    pub fn entity_too_small() -> Self {
+        Self::new(
+            "EntityTooSmall",
+            StatusCode::BAD_REQUEST,
+            "Your proposed upload is smaller than the minimum allowed object size",
+        )
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new("InvalidArgument", StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn invalid_bucket_name(bucket: &str) -> Self {
+        Self::new("InvalidBucketName", StatusCode::BAD_REQUEST, format!("The specified bucket is not valid: {}", bucket))
+    }
+
+    pub fn no_such_bucket(bucket: &str) -> Self {
+        Self::new("NoSuchBucket", StatusCode::NOT_FOUND, format!("The specified bucket does not exist: {}", bucket))
+    }
+
+    pub fn no_such_key(key: &str) -> Self {
+        Self::new("NoSuchKey", StatusCode::NOT_FOUND, format!("The specified key does not exist: {}", key))
+    }
+
+    pub fn no_such_upload(upload_id: &str) -> Self {
+        Self::new(
+            "NoSuchUpload",
+            StatusCode::NOT_FOUND,
+            format!("The specified multipart upload does not exist: {}", upload_id),
+        )
+    }
+
+    pub fn bucket_already_owned_by_you(bucket: &str) -> Self {
+        Self::new(
+            "BucketAlreadyOwnedByYou",
+            StatusCode::CONFLICT,
+            format!("Your previous request to create the named bucket succeeded: {}", bucket),
+        )
+    }
+
+    pub fn bucket_not_empty(bucket: &str) -> Self {
+        Self::new("BucketNotEmpty", StatusCode::CONFLICT, format!("The bucket you tried to delete is not empty: {}", bucket))
+    }
+
+    pub fn invalid_part(message: impl Into<String>) -> Self {
+        Self::new("InvalidPart", StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn invalid_part_order() -> Self {
+        Self::new("InvalidPartOrder", StatusCode::BAD_REQUEST, "The list of parts was not in ascending order")
+    }
+
+    pub fn invalid_range(size: u64) -> Self {
+        Self::new(
+            "InvalidRange",
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            format!("The requested range is not satisfiable for an object of {} bytes", size),
+        )
+    }
+
+    pub fn malformed_xml() -> Self {
+        Self::new(
+            "MalformedXML",
+            StatusCode::BAD_REQUEST,
+            "The XML you provided was not well-formed or did not validate against our published schema",
+        )
+    }
+
+    pub fn method_not_allowed() -> Self {
+        Self::new(
+            "MethodNotAllowed",
+            StatusCode::METHOD_NOT_ALLOWED,
+            "The specified method is not allowed against this resource",
+        )
+    }
+
+    pub fn not_implemented(what: &str) -> Self {
+        Self::new("NotImplemented", StatusCode::NOT_IMPLEMENTED, format!("{} is not implemented", what))
+    }
+
+    pub fn quota_exceeded(message: impl Into<String>) -> Self {
+        Self::new("QuotaExceeded", StatusCode::FORBIDDEN, message)
+    }
+
+    /// The tenant is over its request or bandwidth limits; SDKs retry
+    /// `SlowDown` with backoff
+    pub fn slow_down(message: impl Into<String>, retry_after_ms: u64) -> Self {
+        Self {
+            retry_after: Some(retry_after_ms.div_ceil(1000)),
+            ..Self::new("SlowDown", StatusCode::SERVICE_UNAVAILABLE, message)
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new("InternalError", StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl From<NimbuxError> for S3Error {
+    fn from(err: NimbuxError) -> Self {
+        match err {
+            NimbuxError::ObjectNotFound { object_id } => S3Error::no_such_key(&object_id),
+            NimbuxError::InvalidRequest(msg) => S3Error::invalid_argument(msg),
+            NimbuxError::Authentication(_) => S3Error::invalid_access_key_id(),
+            NimbuxError::Authorization(msg) => S3Error::access_denied(msg),
+            NimbuxError::QuotaExceeded(msg) => S3Error::quota_exceeded(msg),
+            err @ NimbuxError::ObjectLocked { .. } => S3Error::access_denied(err.to_string()),
+            NimbuxError::Throttled { reason, retry_after_ms } => S3Error::slow_down(reason, retry_after_ms),
+            other => S3Error::internal(other.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for S3Error {
+    fn from(err: serde_json::Error) -> Self {
+        S3Error::internal(format!("Corrupt S3 state: {}", err))
+    }
+}
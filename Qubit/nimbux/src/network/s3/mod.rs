@@ -0,0 +1,1245 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// S3-compatible REST API
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, DefaultBodyLimit, Request, State},
+    http::{header, request::Parts, HeaderMap, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use base64::Engine;
+use chrono::Utc;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::auth::{AuthContext, AuthManager};
+use crate::errors::Result;
+use crate::network::conditional::{self, http_date};
+use crate::network::domains::{Addressing, DomainConfig, DomainRegistry, Redirect};
+use crate::performance::AdmissionController;
+use crate::security::{NetworkPolicyEngine, PolicyDecision};
+use crate::storage::{ImageTransformer, StorageBackend, TransformParams};
+
+pub mod error;
+pub mod sigv4;
+pub mod store;
+pub mod xml;
+
+pub use error::{S3Error, S3Result};
+pub use store::{BucketInfo, ListRequest, Listing, MultipartUpload, ObjectInfo, PartInfo, S3Store};
+
+use sigv4::{ChunkVerifier, PayloadSignature, SignedRequest};
+use xml::{DeleteOutcome, ListParams, Owner};
+
+/// Most keys a listing or DeleteObjects request may cover
+const MAX_KEYS: usize = 1000;
+
+/// Query parameters naming S3 features this server does not offer
+const UNSUPPORTED_SUBRESOURCES: &[&str] = &[
+    "accelerate", "acl", "analytics", "attributes", "cors", "encryption", "intelligent-tiering", "inventory",
+    "legal-hold", "lifecycle", "logging", "metrics", "notification", "object-lock", "ownershipControls", "policy",
+    "policyStatus", "publicAccessBlock", "replication", "requestPayment", "restore", "retention", "select",
+    "tagging", "torrent", "versioning", "versions", "website",
+];
+
+/// S3 API configuration
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub port: u16,
+    /// Region clients must sign requests for
+    pub region: String,
+    /// Largest request body, which bounds single PUTs and multipart parts
+    pub max_body_size: usize,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            port: 8083,
+            region: "us-east-1".to_string(),
+            max_body_size: usize::try_from(5u64 * 1024 * 1024 * 1024).unwrap_or(usize::MAX), // 5GB, as S3
+        }
+    }
+}
+
+/// S3-compatible API server.
+///
+/// Speaks enough of the S3 REST protocol for awscli, rclone and the AWS and
+/// MinIO SDKs: SigV4 authentication (headers, presigned URLs and signed
+/// `aws-chunked` bodies), bucket and object CRUD, ListObjects and
+/// ListObjectsV2, copies, ranged reads and multipart uploads. Requests are
+/// authorized against the access key owner's policies with S3 action names
+/// such as `s3:GetObject` on `arn:aws:s3:::<bucket>/<key>`.
+///
+/// Buckets are addressed path style, by virtual host or by custom domain as
+/// the `DomainRegistry` resolves the `Host` header; requests at an address
+/// the bucket is redirected from get a `PermanentRedirect` before they are
+/// authenticated.
+///
+/// With admission control, authenticated requests are held to their access
+/// key's request and bandwidth limits and refused with `SlowDown` over them.
+///
+/// With image transformations, GetObject with `w`, `h`, `fit`, `fmt` or `q`
+/// query parameters returns a resized or converted rendition in buckets that
+/// allow it.
+pub struct S3Server {
+    state: S3State,
+    tls: Option<Arc<rustls::ServerConfig>>,
+}
+
+/// S3 API state shared across requests
+#[derive(Clone)]
+pub struct S3State {
+    pub store: Arc<S3Store>,
+    pub auth_manager: Arc<AuthManager>,
+    pub network_policy: Option<Arc<NetworkPolicyEngine>>,
+    pub admission: Option<Arc<AdmissionController>>,
+    pub transforms: Option<Arc<ImageTransformer>>,
+    pub domains: Arc<DomainRegistry>,
+    pub config: Arc<S3Config>,
+}
+
+impl S3Server {
+    pub fn new(storage: Arc<dyn StorageBackend>, auth_manager: Arc<AuthManager>, config: S3Config) -> Self {
+        Self {
+            state: S3State {
+                domains: Arc::new(DomainRegistry::new(DomainConfig::default(), Arc::clone(&storage))),
+                store: Arc::new(S3Store::new(storage)),
+                auth_manager,
+                network_policy: None,
+                admission: None,
+                transforms: None,
+                config: Arc::new(config),
+            },
+            tls: None,
+        }
+    }
+
+    /// Refuse requests that bucket or access key network policies exclude
+    pub fn with_network_policy(mut self, network_policy: Arc<NetworkPolicyEngine>) -> Self {
+        self.state.network_policy = Some(network_policy);
+        self
+    }
+
+    /// Limit the requests and bandwidth of each access key
+    pub fn with_admission(mut self, admission: Arc<AdmissionController>) -> Self {
+        self.state.admission = Some(admission);
+        self
+    }
+
+    /// Resize and convert images read with transformation parameters
+    pub fn with_transforms(mut self, transforms: Arc<ImageTransformer>) -> Self {
+        self.state.transforms = Some(transforms);
+        self
+    }
+
+    /// Address buckets by virtual host and custom domain as well as by path
+    pub fn with_domains(mut self, domains: Arc<DomainRegistry>) -> Self {
+        self.state.domains = domains;
+        self
+    }
+
+    /// Serve HTTPS, with certificates chosen by server name
+    pub fn with_tls(mut self, tls: Arc<rustls::ServerConfig>) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn router(&self) -> Router {
+        let mut router = Router::new()
+            .fallback(handle_request)
+            // Bodies are limited while reading, so the error is an S3 one
+            .layer(DefaultBodyLimit::disable());
+        if self.state.network_policy.is_some() {
+            router = router.layer(middleware::from_fn_with_state(self.state.clone(), enforce_network_policy));
+        }
+        router.with_state(self.state.clone())
+    }
+
+    pub async fn start(self) -> Result<()> {
+        let app = self.router();
+        let port = self.state.config.port;
+
+        if let Some(tls) = self.tls {
+            info!("S3 API server listening for HTTPS on port {} (region {})", port, self.state.config.region);
+            axum_server::bind_rustls(SocketAddr::from(([0, 0, 0, 0], port)), RustlsConfig::from_config(tls))
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+            return Ok(());
+        }
+
+        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+        info!("S3 API server listening on port {} (region {})", port, self.state.config.region);
+
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+        Ok(())
+    }
+}
+
+/// Bucket and key a request addresses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Target {
+    bucket: Option<String>,
+    key: Option<String>,
+    addressing: Addressing,
+}
+
+impl Target {
+    fn parse(domains: &DomainRegistry, host: Option<&str>, raw_path: &str) -> S3Result<Self> {
+        let path = sigv4::percent_decode(raw_path)?;
+        let path = path.strip_prefix('/').unwrap_or(&path);
+
+        let route = domains.resolve(host);
+        let (bucket, key) = match &route {
+            Some(route) => (route.bucket.as_str(), path),
+            None => path.split_once('/').unwrap_or((path, "")),
+        };
+
+        Ok(Self {
+            bucket: Some(bucket.to_string()).filter(|bucket| !bucket.is_empty()),
+            key: Some(key.to_string()).filter(|key| !key.is_empty()),
+            addressing: route.map(|route| route.addressing).unwrap_or_default(),
+        })
+    }
+
+    fn resource(&self) -> String {
+        match (&self.bucket, &self.key) {
+            (Some(bucket), Some(key)) => format!("arn:aws:s3:::{}/{}", bucket, key),
+            (Some(bucket), None) => format!("arn:aws:s3:::{}", bucket),
+            _ => "arn:aws:s3:::*".to_string(),
+        }
+    }
+}
+
+/// Decoded query parameters
+struct Query(Vec<(String, String)>);
+
+impl Query {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    fn parse<T: std::str::FromStr>(&self, name: &str) -> S3Result<Option<T>> {
+        self.get(name)
+            .map(|value| value.parse().map_err(|_| S3Error::invalid_argument(format!("Invalid {}: {}", name, value))))
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    ListBuckets,
+    CreateBucket,
+    HeadBucket,
+    DeleteBucket,
+    GetBucketLocation,
+    ListObjects,
+    ListObjectsV2,
+    ListMultipartUploads,
+    DeleteObjects,
+    PutObject,
+    CopyObject,
+    GetObject,
+    HeadObject,
+    DeleteObject,
+    CreateMultipartUpload,
+    UploadPart,
+    CompleteMultipartUpload,
+    AbortMultipartUpload,
+    ListParts,
+}
+
+impl Operation {
+    fn resolve(method: &Method, target: &Target, query: &Query, headers: &HeaderMap) -> S3Result<Self> {
+        if let Some(subresource) = UNSUPPORTED_SUBRESOURCES.iter().find(|name| query.has(name)) {
+            return Err(S3Error::not_implemented(&format!("The {} subresource", subresource)));
+        }
+        let copy = headers.contains_key("x-amz-copy-source");
+
+        let operation = match (&target.bucket, &target.key) {
+            (None, _) => match *method {
+                Method::GET => Self::ListBuckets,
+                _ => return Err(S3Error::method_not_allowed()),
+            },
+            (Some(_), None) => match *method {
+                Method::GET if query.has("location") => Self::GetBucketLocation,
+                Method::GET if query.has("uploads") => Self::ListMultipartUploads,
+                Method::GET => match query.get("list-type") {
+                    Some("2") => Self::ListObjectsV2,
+                    Some(other) => return Err(S3Error::invalid_argument(format!("Invalid list-type: {}", other))),
+                    None => Self::ListObjects,
+                },
+                Method::HEAD => Self::HeadBucket,
+                Method::PUT => Self::CreateBucket,
+                Method::DELETE => Self::DeleteBucket,
+                Method::POST if query.has("delete") => Self::DeleteObjects,
+                _ => return Err(S3Error::method_not_allowed()),
+            },
+            (Some(_), Some(_)) => match *method {
+                Method::GET if query.has("uploadId") => Self::ListParts,
+                Method::GET => Self::GetObject,
+                Method::HEAD => Self::HeadObject,
+                Method::PUT if query.has("uploadId") && copy => return Err(S3Error::not_implemented("UploadPartCopy")),
+                Method::PUT if query.has("uploadId") => Self::UploadPart,
+                Method::PUT if copy => Self::CopyObject,
+                Method::PUT => Self::PutObject,
+                Method::POST if query.has("uploads") => Self::CreateMultipartUpload,
+                Method::POST if query.has("uploadId") => Self::CompleteMultipartUpload,
+                Method::DELETE if query.has("uploadId") => Self::AbortMultipartUpload,
+                Method::DELETE => Self::DeleteObject,
+                _ => return Err(S3Error::method_not_allowed()),
+            },
+        };
+        Ok(operation)
+    }
+
+    /// Policy action checked before the request runs; DeleteObjects is checked per key
+    fn action(&self) -> Option<&'static str> {
+        let action = match self {
+            Self::ListBuckets => "s3:ListAllMyBuckets",
+            Self::CreateBucket => "s3:CreateBucket",
+            Self::DeleteBucket => "s3:DeleteBucket",
+            Self::HeadBucket | Self::ListObjects | Self::ListObjectsV2 => "s3:ListBucket",
+            Self::GetBucketLocation => "s3:GetBucketLocation",
+            Self::ListMultipartUploads => "s3:ListBucketMultipartUploads",
+            Self::DeleteObjects => return None,
+            Self::PutObject | Self::CopyObject | Self::CreateMultipartUpload | Self::UploadPart | Self::CompleteMultipartUpload => {
+                "s3:PutObject"
+            }
+            Self::GetObject | Self::HeadObject => "s3:GetObject",
+            Self::DeleteObject => "s3:DeleteObject",
+            Self::AbortMultipartUpload => "s3:AbortMultipartUpload",
+            Self::ListParts => "s3:ListMultipartUploadParts",
+        };
+        Some(action)
+    }
+}
+
+/// A request whose signature checked out
+struct Authenticated {
+    context: AuthContext,
+    payload: PayloadSignature,
+    chunks: Option<ChunkVerifier>,
+}
+
+async fn handle_request(State(state): State<S3State>, request: Request) -> Response {
+    let request_id = new_request_id();
+    let resource = request.uri().path().to_string();
+
+    let mut response = match serve(&state, request, &request_id).await {
+        Ok(response) => response,
+        Err(err) => {
+            debug!("S3 request {} for {} failed: {}", request_id, resource, err);
+            error_response(&err, &resource, &request_id)
+        }
+    };
+    if let Ok(value) = request_id.parse() {
+        response.headers_mut().insert("x-amz-request-id", value);
+    }
+    response
+}
+
+async fn serve(state: &S3State, request: Request, request_id: &str) -> S3Result<Response> {
+    let (parts, body) = request.into_parts();
+    let raw_path = parts.uri.path();
+    let raw_query = parts.uri.query().unwrap_or("");
+    let query = Query(sigv4::parse_query(raw_query)?);
+
+    let host = header_str(&parts.headers, "host");
+    let target = Target::parse(&state.domains, host, raw_path)?;
+    if let Some(bucket) = &target.bucket {
+        if let Some(redirect) = state.domains.redirect(bucket, target.addressing, host) {
+            return redirect_response(state, &parts.method, &redirect, bucket, key_path(raw_path, target.addressing), raw_query, request_id);
+        }
+    }
+    let operation = Operation::resolve(&parts.method, &target, &query, &parts.headers)?;
+
+    let auth = authenticate(state, &parts, raw_path, raw_query, &query).await?;
+    // Only signed requests are admitted against a key, so no one can spend another tenant's limits
+    let permit = match &state.admission {
+        Some(admission) => {
+            let declared = header_str(&parts.headers, "content-length").and_then(|len| len.parse().ok());
+            Some(admission.admit(&auth.context.access_key.access_key_id, declared.unwrap_or(0))?)
+        }
+        None => None,
+    };
+    if let Some(action) = operation.action() {
+        authorize(state, &auth.context, action, &target.resource()).await?;
+    }
+    let body = read_body(state, &parts.headers, body, auth.payload.clone(), auth.chunks).await?;
+
+    let bucket = target.bucket.as_deref().unwrap_or_default();
+    let key = target.key.as_deref().unwrap_or_default();
+    let store = &state.store;
+    let user = &auth.context.user;
+    let owner = Owner {
+        id: &user.user_id,
+        display_name: &user.username,
+    };
+
+    match operation {
+        Operation::ListBuckets => xml_response(StatusCode::OK, xml::list_buckets(&owner, &store.list_buckets().await?)),
+        Operation::CreateBucket => {
+            store.create_bucket(bucket).await?;
+            build(Response::builder().header(header::LOCATION, format!("/{}", bucket)), Body::empty())
+        }
+        Operation::HeadBucket => {
+            store.require_bucket(bucket).await?;
+            build(Response::builder().header("x-amz-bucket-region", &state.config.region), Body::empty())
+        }
+        Operation::DeleteBucket => {
+            store.delete_bucket(bucket).await?;
+            build(Response::builder().status(StatusCode::NO_CONTENT), Body::empty())
+        }
+        Operation::GetBucketLocation => {
+            store.require_bucket(bucket).await?;
+            xml_response(StatusCode::OK, xml::location(&state.config.region))
+        }
+        Operation::ListObjectsV2 => {
+            let max_keys = max_keys(&query)?;
+            let continuation_token = query.get("continuation-token");
+            let start_after = match continuation_token {
+                Some(token) => Some(decode_token(token)?),
+                None => query.get("start-after").map(str::to_string),
+            };
+            let request = list_request(&query, start_after, max_keys);
+            let listing = store.list_objects(bucket, &request).await?;
+            let next_token = listing
+                .next_marker
+                .as_deref()
+                .filter(|_| listing.is_truncated)
+                .map(encode_token);
+
+            let params = list_params(bucket, &query, &request, (query.get("fetch-owner") == Some("true")).then_some(owner));
+            xml_response(
+                StatusCode::OK,
+                xml::list_objects_v2(&params, &listing, continuation_token, next_token.as_deref(), query.get("start-after")),
+            )
+        }
+        Operation::ListObjects => {
+            let marker = query.get("marker").unwrap_or_default();
+            let request = list_request(&query, Some(marker.to_string()).filter(|marker| !marker.is_empty()), max_keys(&query)?);
+            let listing = store.list_objects(bucket, &request).await?;
+            let params = list_params(bucket, &query, &request, Some(owner));
+            xml_response(StatusCode::OK, xml::list_objects_v1(&params, &listing, marker))
+        }
+        Operation::ListMultipartUploads => {
+            let prefix = query.get("prefix").unwrap_or_default();
+            let uploads = store.list_multipart_uploads(bucket, prefix).await?;
+            xml_response(StatusCode::OK, xml::list_multipart_uploads(&owner, bucket, prefix, &uploads))
+        }
+        Operation::DeleteObjects => {
+            store.require_bucket(bucket).await?;
+            let (keys, quiet) = xml::parse_delete_objects(&body)?;
+            if keys.len() > MAX_KEYS {
+                return Err(S3Error::malformed_xml());
+            }
+
+            let mut outcomes = Vec::with_capacity(keys.len());
+            for key in keys {
+                let resource = format!("arn:aws:s3:::{}/{}", bucket, key);
+                let result = match authorize(state, &auth.context, "s3:DeleteObject", &resource).await {
+                    Ok(()) => store.delete_object(bucket, &key).await,
+                    Err(e) => Err(e),
+                };
+                outcomes.push(match result {
+                    Ok(()) => DeleteOutcome::Deleted(key),
+                    Err(error) => DeleteOutcome::Failed { key, error },
+                });
+            }
+            xml_response(StatusCode::OK, xml::delete_result(&outcomes, quiet))
+        }
+        Operation::PutObject => {
+            let info = store
+                .put_object(bucket, key, body.to_vec(), content_type(&parts.headers), user_metadata(&parts.headers))
+                .await?;
+            build(Response::builder().header(header::ETAG, format!("\"{}\"", info.etag)), Body::empty())
+        }
+        Operation::CopyObject => {
+            let (source_bucket, source_key) = copy_source(&parts.headers)?;
+            authorize(state, &auth.context, "s3:GetObject", &format!("arn:aws:s3:::{}/{}", source_bucket, source_key)).await?;
+
+            let replace = match header_str(&parts.headers, "x-amz-metadata-directive") {
+                Some("REPLACE") => Some((content_type(&parts.headers), user_metadata(&parts.headers))),
+                Some("COPY") | None => None,
+                Some(other) => return Err(S3Error::invalid_argument(format!("Unknown metadata directive: {}", other))),
+            };
+            let info = store.copy_object(&source_bucket, &source_key, bucket, key, replace).await?;
+            xml_response(StatusCode::OK, xml::copy_object_result(&info))
+        }
+        Operation::GetObject => {
+            let (mut info, mut data) = store.get_object(bucket, key).await?;
+            let mut vary = false;
+            if let Some(transforms) = &state.transforms {
+                if let Some(params) = TransformParams::parse(Some(raw_query), header_str(&parts.headers, "accept"))? {
+                    let rendition = transforms.transform(bucket, key, &info.etag, data, &params).await?;
+                    info.etag = rendition.etag;
+                    info.size = rendition.data.len() as u64;
+                    info.content_type = Some(rendition.format.content_type().to_string());
+                    data = rendition.data;
+                    vary = params.negotiated;
+                }
+            }
+            let size = data.len() as u64;
+            let range = match header_str(&parts.headers, "range") {
+                Some(range) => parse_range(range, size)?,
+                None => None,
+            };
+
+            if let Some(permit) = &permit {
+                permit.charge(range.map_or(size, |(start, end)| end - start + 1));
+            }
+
+            let mut builder = object_headers(Response::builder(), &info);
+            if vary {
+                builder = builder.header(header::VARY, "Accept");
+            }
+            match range {
+                Some((start, end)) => build(
+                    builder
+                        .status(StatusCode::PARTIAL_CONTENT)
+                        .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, size)),
+                    Body::from(data[start as usize..=end as usize].to_vec()),
+                ),
+                None => build(builder, Body::from(data)),
+            }
+        }
+        Operation::HeadObject => {
+            let info = store.head_object(bucket, key).await?;
+            build(
+                object_headers(Response::builder(), &info).header(header::CONTENT_LENGTH, info.size),
+                Body::empty(),
+            )
+        }
+        Operation::DeleteObject => {
+            store.delete_object(bucket, key).await?;
+            build(Response::builder().status(StatusCode::NO_CONTENT), Body::empty())
+        }
+        Operation::CreateMultipartUpload => {
+            let upload = store
+                .create_multipart_upload(bucket, key, content_type(&parts.headers), user_metadata(&parts.headers))
+                .await?;
+            xml_response(StatusCode::OK, xml::initiate_multipart_upload(&upload))
+        }
+        Operation::UploadPart => {
+            let upload_id = query.get("uploadId").unwrap_or_default();
+            let part_number = query
+                .parse("partNumber")?
+                .ok_or_else(|| S3Error::invalid_argument("Missing partNumber"))?;
+            let etag = store.upload_part(upload_id, bucket, key, part_number, body.to_vec()).await?;
+            build(Response::builder().header(header::ETAG, format!("\"{}\"", etag)), Body::empty())
+        }
+        Operation::CompleteMultipartUpload => {
+            let upload_id = query.get("uploadId").unwrap_or_default();
+            let parts = xml::parse_complete_multipart_upload(&body)?;
+            let info = store.complete_multipart_upload(upload_id, bucket, key, &parts).await?;
+            let location = format!("/{}/{}", bucket, sigv4::uri_encode(key, false));
+            xml_response(StatusCode::OK, xml::complete_multipart_upload(&location, bucket, &info))
+        }
+        Operation::AbortMultipartUpload => {
+            store.abort_multipart_upload(query.get("uploadId").unwrap_or_default(), bucket, key).await?;
+            build(Response::builder().status(StatusCode::NO_CONTENT), Body::empty())
+        }
+        Operation::ListParts => {
+            let upload_id = query.get("uploadId").unwrap_or_default();
+            let marker = query.parse("part-number-marker")?.unwrap_or(0);
+            let max_parts = query.parse("max-parts")?.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+            let (upload, parts) = store.list_parts(upload_id, bucket, key).await?;
+            xml_response(StatusCode::OK, xml::list_parts(&owner, &upload, &parts, marker, max_parts))
+        }
+    }
+}
+
+async fn authenticate(state: &S3State, parts: &Parts, raw_path: &str, raw_query: &str, query: &Query) -> S3Result<Authenticated> {
+    let signed = SignedRequest::parse(&parts.headers, &query.0, Utc::now())?
+        .ok_or_else(|| S3Error::access_denied("Anonymous access is not allowed"))?;
+
+    let (access_key, user) = state
+        .auth_manager
+        .active_access_key(&signed.credential.access_key_id)
+        .await
+        .map_err(|_| S3Error::invalid_access_key_id())?;
+    if !access_key.accepts_session_token(signed.session_token.as_deref()) {
+        return Err(S3Error::invalid_token());
+    }
+    let chunks = signed.verify(
+        &access_key.secret_access_key,
+        &state.config.region,
+        &parts.method,
+        raw_path,
+        raw_query,
+        &parts.headers,
+    )?;
+    state.auth_manager.record_key_use(&access_key.access_key_id).await;
+
+    let request_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    Ok(Authenticated {
+        chunks: matches!(signed.payload, PayloadSignature::StreamingSigned { .. }).then_some(chunks),
+        payload: signed.payload,
+        context: AuthContext {
+            user,
+            access_key,
+            request_time,
+            signature: signed.signature,
+        },
+    })
+}
+
+async fn authorize(state: &S3State, context: &AuthContext, action: &str, resource: &str) -> S3Result<()> {
+    if state.auth_manager.check_permission(context, action, resource).await? {
+        Ok(())
+    } else {
+        Err(S3Error::access_denied(format!("Not allowed to {} on {}", action, resource)))
+    }
+}
+
+/// Read the body, checking it against its signature, length and Content-MD5
+async fn read_body(
+    state: &S3State,
+    headers: &HeaderMap,
+    body: Body,
+    payload: PayloadSignature,
+    chunks: Option<ChunkVerifier>,
+) -> S3Result<Bytes> {
+    let max = state.config.max_body_size;
+    let declared: Option<usize> = header_str(headers, "content-length").and_then(|len| len.parse().ok());
+    if declared.is_some_and(|len| len > max) {
+        return Err(S3Error::entity_too_large(max));
+    }
+    let raw = axum::body::to_bytes(body, max)
+        .await
+        .map_err(|e| S3Error::incomplete_body(format!("Failed to read the request body: {}", e)))?;
+
+    let body = match payload {
+        PayloadSignature::Sha256(expected) => {
+            if sigv4::sha256_hex(&raw) != expected {
+                return Err(S3Error::content_sha256_mismatch());
+            }
+            raw
+        }
+        PayloadSignature::Unsigned => raw,
+        PayloadSignature::StreamingSigned { .. } | PayloadSignature::StreamingUnsigned => {
+            let decoded = sigv4::decode_aws_chunked(&raw, chunks)?;
+            let decoded_length: Option<usize> = header_str(headers, "x-amz-decoded-content-length").and_then(|len| len.parse().ok());
+            if decoded_length.is_some_and(|len| len != decoded.len()) {
+                return Err(S3Error::incomplete_body("Body length does not match x-amz-decoded-content-length"));
+            }
+            Bytes::from(decoded)
+        }
+    };
+
+    if let Some(content_md5) = header_str(headers, "content-md5") {
+        let expected = base64::engine::general_purpose::STANDARD
+            .decode(content_md5)
+            .map_err(|_| S3Error::new("InvalidDigest", StatusCode::BAD_REQUEST, "The Content-MD5 you specified was invalid"))?;
+        if store::md5(&body)? != expected {
+            return Err(S3Error::bad_digest());
+        }
+    }
+    Ok(body)
+}
+
+/// Refuse requests from networks or countries a bucket or access key policy excludes,
+/// before their signature is checked
+async fn enforce_network_policy(
+    State(state): State<S3State>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(network_policy) = &state.network_policy else {
+        return next.run(request).await;
+    };
+
+    let forwarded_for = header_str(request.headers(), "x-forwarded-for");
+    let client_ip = network_policy.client_ip(peer.ip(), forwarded_for);
+    let target = Target::parse(&state.domains, header_str(request.headers(), "host"), request.uri().path()).unwrap_or_default();
+    let access_key = request_access_key(request.headers(), request.uri().query().unwrap_or(""));
+
+    match network_policy.evaluate(client_ip, target.bucket.as_deref(), access_key.as_deref()).await {
+        PolicyDecision::Allow => next.run(request).await,
+        PolicyDecision::Deny(denial) => {
+            let request_id = new_request_id();
+            let mut response = error_response(&S3Error::access_denied(denial.to_string()), request.uri().path(), &request_id);
+            if let Ok(value) = request_id.parse() {
+                response.headers_mut().insert("x-amz-request-id", value);
+            }
+            response
+        }
+    }
+}
+
+/// Access key ID from the credential of a signed request or presigned URL
+fn request_access_key(headers: &HeaderMap, raw_query: &str) -> Option<String> {
+    let credential = match header_str(headers, "authorization") {
+        Some(authorization) => authorization.split_once("Credential=")?.1.to_string(),
+        None => sigv4::parse_query(raw_query)
+            .ok()?
+            .into_iter()
+            .find(|(name, _)| name == "X-Amz-Credential")?
+            .1,
+    };
+    credential.split(['/', ',']).next().filter(|key_id| !key_id.is_empty()).map(str::to_string)
+}
+
+/// Send the request to the address its bucket is served at; GET and HEAD
+/// are redirected permanently, other methods temporarily so their body is
+/// sent again
+fn redirect_response(
+    state: &S3State,
+    method: &Method,
+    redirect: &Redirect,
+    bucket: &str,
+    key_path: &str,
+    raw_query: &str,
+    request_id: &str,
+) -> S3Result<Response> {
+    let (status, code) = match *method {
+        Method::GET | Method::HEAD => (StatusCode::MOVED_PERMANENTLY, "PermanentRedirect"),
+        _ => (StatusCode::TEMPORARY_REDIRECT, "TemporaryRedirect"),
+    };
+    let location = redirect.location(&state.domains.config().scheme, key_path, Some(raw_query));
+    debug!("Redirecting {} request for bucket {} to {}", method, bucket, location);
+    build(
+        Response::builder()
+            .status(status)
+            .header(header::LOCATION, location)
+            .header(header::CONTENT_TYPE, "application/xml"),
+        Body::from(xml::redirect(code, bucket, &redirect.host, request_id)),
+    )
+}
+
+/// Raw path after the bucket, starting with `/`
+fn key_path(raw_path: &str, addressing: Addressing) -> &str {
+    match addressing {
+        Addressing::Path => {
+            let path = raw_path.strip_prefix('/').unwrap_or(raw_path);
+            path.find('/').map_or("/", |slash| &path[slash..])
+        }
+        Addressing::VirtualHost | Addressing::CustomDomain => raw_path,
+    }
+}
+
+fn error_response(err: &S3Error, resource: &str, request_id: &str) -> Response {
+    let body = xml::error(err.code, &err.message, resource, request_id);
+    let mut response = (err.status, [(header::CONTENT_TYPE, "application/xml")], body).into_response();
+    if let Some(seconds) = err.retry_after {
+        response.headers_mut().insert(header::RETRY_AFTER, seconds.into());
+    }
+    response
+}
+
+fn xml_response(status: StatusCode, body: String) -> S3Result<Response> {
+    build(
+        Response::builder().status(status).header(header::CONTENT_TYPE, "application/xml"),
+        Body::from(body),
+    )
+}
+
+fn build(builder: axum::http::response::Builder, body: Body) -> S3Result<Response> {
+    builder
+        .body(body)
+        .map_err(|e| S3Error::internal(format!("Failed to build response: {}", e)))
+}
+
+fn object_headers(builder: axum::http::response::Builder, info: &ObjectInfo) -> axum::http::response::Builder {
+    let mut builder = builder
+        .header(header::ETAG, format!("\"{}\"", info.etag))
+        .header(header::LAST_MODIFIED, http_date(info.last_modified))
+        .header(header::CONTENT_TYPE, info.content_type.as_deref().unwrap_or("binary/octet-stream"))
+        .header(header::ACCEPT_RANGES, "bytes");
+    for (name, value) in &info.metadata {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    builder
+}
+
+fn list_request(query: &Query, start_after: Option<String>, max_keys: usize) -> ListRequest {
+    ListRequest {
+        prefix: query.get("prefix").unwrap_or_default().to_string(),
+        delimiter: query.get("delimiter").map(str::to_string),
+        start_after,
+        max_keys,
+    }
+}
+
+fn list_params<'a>(bucket: &'a str, query: &'a Query, request: &'a ListRequest, owner: Option<Owner<'a>>) -> ListParams<'a> {
+    ListParams {
+        bucket,
+        prefix: &request.prefix,
+        delimiter: request.delimiter.as_deref(),
+        max_keys: request.max_keys,
+        url_encoded: query.get("encoding-type") == Some("url"),
+        owner,
+    }
+}
+
+fn max_keys(query: &Query) -> S3Result<usize> {
+    Ok(query.parse("max-keys")?.unwrap_or(MAX_KEYS).min(MAX_KEYS))
+}
+
+fn encode_token(marker: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(marker)
+}
+
+fn decode_token(token: &str) -> S3Result<String> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(token)
+        .ok()
+        .and_then(|marker| String::from_utf8(marker).ok())
+        .ok_or_else(|| S3Error::invalid_argument("The continuation token provided is incorrect"))
+}
+
+/// Source of a copy, from `x-amz-copy-source: [/]<bucket>/<key>[?versionId=...]`
+fn copy_source(headers: &HeaderMap) -> S3Result<(String, String)> {
+    let source = header_str(headers, "x-amz-copy-source").unwrap_or_default();
+    let source = source.split_once('?').map_or(source, |(path, _)| path);
+    let source = sigv4::percent_decode(source)?;
+    source
+        .trim_start_matches('/')
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .map(|(bucket, key)| (bucket.to_string(), key.to_string()))
+        .ok_or_else(|| S3Error::invalid_argument("Copy source must name a bucket and key"))
+}
+
+fn content_type(headers: &HeaderMap) -> Option<String> {
+    header_str(headers, "content-type").map(str::to_string)
+}
+
+fn user_metadata(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with(store::USER_METADATA_PREFIX))
+        .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn parse_range(value: &str, size: u64) -> S3Result<Option<(u64, u64)>> {
+    conditional::parse_range(value, size).map_err(|_| S3Error::invalid_range(size))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn new_request_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{PolicyDocument, PolicyStatement, PresignMethod, PresignRequest, Presigner};
+    use crate::network::domains::DomainRequest;
+    use crate::storage::MemoryStorage;
+    use axum::http::HeaderValue;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use tower::ServiceExt;
+
+    struct Client {
+        router: Router,
+        auth_manager: Arc<AuthManager>,
+        user_id: String,
+        access_key_id: String,
+        secret: String,
+    }
+
+    fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    impl Client {
+        async fn new(actions: &[&str], resources: &[&str]) -> Self {
+            let auth_manager = Arc::new(AuthManager::new());
+            let user = auth_manager.create_user("rclone".to_string(), "rclone@example.com".to_string()).await.unwrap();
+            let key = auth_manager.create_access_key(&user.user_id).await.unwrap();
+            let policy = PolicyDocument {
+                version: "2012-10-17".to_string(),
+                statement: vec![PolicyStatement {
+                    effect: "Allow".to_string(),
+                    action: actions.iter().map(|a| a.to_string()).collect(),
+                    resource: resources.iter().map(|r| r.to_string()).collect(),
+                    condition: None,
+                }],
+            };
+            auth_manager.add_policy(&user.user_id, policy).await.unwrap();
+
+            let server = S3Server::new(Arc::new(MemoryStorage::new()), auth_manager.clone(), S3Config::default());
+            Self {
+                router: server.router(),
+                auth_manager,
+                user_id: user.user_id,
+                access_key_id: key.access_key_id,
+                secret: key.secret_access_key,
+            }
+        }
+
+        /// Sign a request as an SDK would and send it
+        async fn send(&self, method: Method, path_and_query: &str, headers: &[(&str, &str)], body: Vec<u8>) -> (StatusCode, HeaderMap, String) {
+            let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+            let payload_hash = sigv4::sha256_hex(&body);
+            let mut header_map = HeaderMap::new();
+            header_map.insert("host", HeaderValue::from_static("localhost:8083"));
+            header_map.insert("x-amz-date", HeaderValue::from_str(&amz_date).unwrap());
+            header_map.insert("x-amz-content-sha256", HeaderValue::from_str(&payload_hash).unwrap());
+            for (name, value) in headers {
+                header_map.insert(axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap(), HeaderValue::from_str(value).unwrap());
+            }
+
+            let (path, query) = path_and_query.split_once('?').unwrap_or((path_and_query, ""));
+            let mut signed_headers: Vec<String> = header_map.keys().map(|name| name.as_str().to_string()).collect();
+            signed_headers.sort();
+            let canonical = sigv4::canonical_request(method.as_str(), path, query, &header_map, &signed_headers, &payload_hash, false).unwrap();
+
+            let date = &amz_date[..8];
+            let scope = format!("{}/us-east-1/s3/aws4_request", date);
+            let key = hmac(format!("AWS4{}", self.secret).as_bytes(), date);
+            let key = hmac(&hmac(&hmac(&key, "us-east-1"), "s3"), "aws4_request");
+            let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, sigv4::sha256_hex(canonical.as_bytes()));
+            let signature = hex::encode(hmac(&key, &string_to_sign));
+            let authorization = format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key_id,
+                scope,
+                signed_headers.join(";"),
+                signature
+            );
+            header_map.insert("authorization", HeaderValue::from_str(&authorization).unwrap());
+
+            self.send_as_is(method, path_and_query, header_map, body).await
+        }
+
+        /// Send a request without signing it, as a browser following a presigned URL would
+        async fn send_as_is(&self, method: Method, path_and_query: &str, headers: HeaderMap, body: Vec<u8>) -> (StatusCode, HeaderMap, String) {
+            let mut request = Request::builder().method(method).uri(path_and_query).body(Body::from(body)).unwrap();
+            *request.headers_mut() = headers;
+            let response = self.router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, headers, String::from_utf8_lossy(&body).to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signed_requests_round_trip_objects() {
+        let client = Client::new(&["s3:*"], &["*"]).await;
+
+        let (status, _, _) = client.send(Method::PUT, "/media", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, headers, _) = client
+            .send(Method::PUT, "/media/photos/caf%C3%A9%20night.jpg", &[("x-amz-meta-camera", "x100")], b"jpeg bytes".to_vec())
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let etag = format!("\"{}\"", hex::encode(store::md5(b"jpeg bytes").unwrap()));
+        assert_eq!(headers["etag"], etag.as_str());
+
+        let (status, headers, body) = client.send(Method::GET, "/media/photos/caf%C3%A9%20night.jpg", &[("range", "bytes=5-")], Vec::new()).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, "bytes");
+        assert_eq!(headers["content-range"], "bytes 5-9/10");
+        assert_eq!(headers["x-amz-meta-camera"], "x100");
+
+        let (status, _, body) = client.send(Method::GET, "/media?list-type=2&delimiter=%2F", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<CommonPrefixes><Prefix>photos/</Prefix></CommonPrefixes>"));
+        assert!(body.contains("<KeyCount>1</KeyCount>"));
+
+        let (status, _, body) = client.send(Method::GET, "/media?list-type=2&prefix=photos%2F", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<Key>photos/café night.jpg</Key>"));
+
+        let (status, _, body) = client.send(Method::GET, "/media/missing.jpg", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body.contains("<Code>NoSuchKey</Code>"));
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_over_http() {
+        let client = Client::new(&["s3:*"], &["*"]).await;
+        client.send(Method::PUT, "/backups", &[], Vec::new()).await;
+
+        let (status, _, body) = client.send(Method::POST, "/backups/db.tar?uploads", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        let upload_id = body.split("<UploadId>").nth(1).unwrap().split("</UploadId>").next().unwrap().to_string();
+
+        let first = vec![7u8; store::MIN_PART_SIZE as usize];
+        let (_, headers1, _) = client
+            .send(Method::PUT, &format!("/backups/db.tar?partNumber=1&uploadId={}", upload_id), &[], first)
+            .await;
+        let (_, headers2, _) = client
+            .send(Method::PUT, &format!("/backups/db.tar?partNumber=2&uploadId={}", upload_id), &[], b"end".to_vec())
+            .await;
+
+        let complete = format!(
+            "<CompleteMultipartUpload xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+             <Part><PartNumber>1</PartNumber><ETag>{}</ETag></Part>\
+             <Part><PartNumber>2</PartNumber><ETag>{}</ETag></Part></CompleteMultipartUpload>",
+            headers1["etag"].to_str().unwrap(),
+            headers2["etag"].to_str().unwrap()
+        );
+        let (status, _, body) = client
+            .send(Method::POST, &format!("/backups/db.tar?uploadId={}", upload_id), &[], complete.into_bytes())
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(body.contains("-2&quot;</ETag>"));
+
+        let (status, headers, _) = client.send(Method::HEAD, "/backups/db.tar", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["content-length"], (store::MIN_PART_SIZE + 3).to_string().as_str());
+    }
+
+    #[tokio::test]
+    async fn test_bad_signatures_and_policies_are_refused() {
+        let client = Client::new(&["s3:GetObject", "s3:ListBucket"], &["arn:aws:s3:::media*"]).await;
+
+        let (status, _, body) = client.send(Method::PUT, "/media", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("<Code>AccessDenied</Code>"));
+
+        let wrong_secret = Client {
+            secret: "not-the-secret".to_string(),
+            ..client
+        };
+        let (status, _, body) = wrong_secret.send(Method::GET, "/media", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("<Code>SignatureDoesNotMatch</Code>"));
+
+        let request = Request::builder().uri("/media").body(Body::empty()).unwrap();
+        let response = wrong_secret.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_presigned_urls_and_temporary_credentials() {
+        let client = Client::new(&["s3:*"], &["*"]).await;
+        let (status, _, _) = client.send(Method::PUT, "/uploads", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // A browser uploads straight to the URL the backend presigned
+        let presigner = Presigner::new("http://localhost:8083", "us-east-1").unwrap();
+        let mut request = PresignRequest {
+            method: PresignMethod::Put,
+            bucket: "uploads".to_string(),
+            key: "avatars/me.png".to_string(),
+            expires_in: std::time::Duration::from_secs(600),
+            content_type: Some("image/png".to_string()),
+        };
+        let upload = presigner.presign_for_user(&client.auth_manager, &client.user_id, &request).await.unwrap();
+        let upload_path = upload.url.strip_prefix("http://localhost:8083").unwrap();
+        let browser = |content_type: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("host", HeaderValue::from_static("localhost:8083"));
+            headers.insert("content-type", HeaderValue::from_static(content_type));
+            headers
+        };
+
+        let (status, _, body) = client.send_as_is(Method::PUT, upload_path, browser("text/html"), b"<html>".to_vec()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("<Code>SignatureDoesNotMatch</Code>"));
+        let (status, _, body) = client.send_as_is(Method::PUT, upload_path, browser("image/png"), b"png bytes".to_vec()).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        // Downloads work the same way
+        request.method = PresignMethod::Get;
+        let download = presigner.presign_for_user(&client.auth_manager, &client.user_id, &request).await.unwrap();
+        let download_path = download.url.strip_prefix("http://localhost:8083").unwrap();
+        let (status, _, body) = client.send_as_is(Method::GET, download_path, browser("image/png"), Vec::new()).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "png bytes"));
+
+        // Temporary credentials act within their session policy and need their token
+        let policy = PolicyDocument {
+            version: "2012-10-17".to_string(),
+            statement: vec![PolicyStatement {
+                effect: "Allow".to_string(),
+                action: vec!["s3:GetObject".to_string()],
+                resource: vec!["arn:aws:s3:::uploads/avatars/*".to_string()],
+                condition: None,
+            }],
+        };
+        let credentials = client
+            .auth_manager
+            .issue_temporary_credentials(&client.user_id, std::time::Duration::from_secs(900), Some(policy))
+            .await
+            .unwrap();
+        let session = Client {
+            access_key_id: credentials.access_key_id.clone(),
+            secret: credentials.secret_access_key.clone(),
+            ..client
+        };
+        let token = [("x-amz-security-token", credentials.session_token.as_str())];
+
+        let (status, _, body) = session.send(Method::GET, "/uploads/avatars/me.png", &token, Vec::new()).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "png bytes"));
+        let (status, _, body) = session.send(Method::PUT, "/uploads/avatars/me.png", &token, b"x".to_vec()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("<Code>AccessDenied</Code>"));
+        let (status, _, body) = session.send(Method::GET, "/uploads/avatars/me.png", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("<Code>InvalidToken</Code>"));
+    }
+
+    #[test]
+    fn test_range_parsing() {
+        assert_eq!(parse_range("bytes=0-9", 100).unwrap(), Some((0, 9)));
+        assert_eq!(parse_range("bytes=90-", 100).unwrap(), Some((90, 99)));
+        assert_eq!(parse_range("bytes=-10", 100).unwrap(), Some((90, 99)));
+        assert_eq!(parse_range("bytes=50-500", 100).unwrap(), Some((50, 99)));
+        assert_eq!(parse_range("bytes=0-1,5-6", 100).unwrap(), None);
+        assert_eq!(parse_range("bytes=100-", 100).unwrap_err().code, "InvalidRange");
+    }
+
+    fn domains(redirect_path_style: bool) -> Arc<DomainRegistry> {
+        let config = DomainConfig {
+            base_domain: Some("s3.nimbux.local".to_string()),
+            redirect_path_style,
+            scheme: "https".to_string(),
+        };
+        Arc::new(DomainRegistry::new(config, Arc::new(MemoryStorage::new())))
+    }
+
+    #[tokio::test]
+    async fn test_virtual_hosted_custom_domain_and_path_style_targets() {
+        let domains = domains(false);
+        domains.register("media", "cdn.example.com", DomainRequest::default()).await.unwrap();
+
+        let path_style = Target::parse(&domains, Some("localhost:8083"), "/media/a%20b.jpg").unwrap();
+        assert_eq!(path_style.bucket.as_deref(), Some("media"));
+        assert_eq!(path_style.key.as_deref(), Some("a b.jpg"));
+        assert_eq!(path_style.addressing, Addressing::Path);
+
+        let virtual_hosted = Target::parse(&domains, Some("media.s3.nimbux.local:8083"), "/a%20b.jpg").unwrap();
+        assert_eq!((virtual_hosted.bucket, virtual_hosted.key), (path_style.bucket.clone(), path_style.key.clone()));
+        assert_eq!(virtual_hosted.addressing, Addressing::VirtualHost);
+
+        let custom = Target::parse(&domains, Some("cdn.example.com"), "/a%20b.jpg").unwrap();
+        assert_eq!((custom.bucket, custom.key), (path_style.bucket, path_style.key));
+        assert_eq!(custom.addressing, Addressing::CustomDomain);
+
+        assert_eq!(Target::parse(&domains, Some("s3.nimbux.local"), "/").unwrap(), Target::default());
+        assert_eq!(key_path("/media/a%20b.jpg", Addressing::Path), "/a%20b.jpg");
+        assert_eq!(key_path("/media", Addressing::Path), "/");
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_redirected_to_the_canonical_address() {
+        let mut client = Client::new(&["s3:*"], &["arn:aws:s3:::*"]).await;
+        let domains = domains(true);
+        let storage: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::new());
+        client.router = S3Server::new(storage, Arc::clone(&client.auth_manager), S3Config::default())
+            .with_domains(Arc::clone(&domains))
+            .router();
+
+        // Path style goes to the virtual host before the signature is checked
+        let (status, headers, body) = client.send(Method::GET, "/media/a%20b.jpg?versionId=1", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(headers[header::LOCATION], "https://media.s3.nimbux.local:8083/a%20b.jpg?versionId=1");
+        assert!(body.contains("<Code>PermanentRedirect</Code>"));
+        assert!(body.contains("<Endpoint>media.s3.nimbux.local:8083</Endpoint>"));
+        assert!(body.contains(&format!("<RequestId>{}</RequestId>", headers["x-amz-request-id"].to_str().unwrap())));
+
+        let (status, headers, _) = client.send(Method::PUT, "/media/a.jpg", &[], b"data".to_vec()).await;
+        assert_eq!(status, StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(headers[header::LOCATION], "https://media.s3.nimbux.local:8083/a.jpg");
+
+        // Listing buckets names no bucket, so it stays where it is
+        let (status, _, _) = client.send(Method::GET, "/", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // A canonical custom domain takes the virtual host's requests too
+        domains
+            .register("media", "cdn.example.com", DomainRequest { canonical: true, ..Default::default() })
+            .await
+            .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("media.s3.nimbux.local:8083"));
+        let (status, headers, _) = client.send_as_is(Method::HEAD, "/a.jpg", headers, Vec::new()).await;
+        assert_eq!(status, StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(headers[header::LOCATION], "https://cdn.example.com/a.jpg");
+    }
+
+    #[tokio::test]
+    async fn test_access_keys_over_their_limits_slow_down() {
+        let mut client = Client::new(&["s3:*"], &["arn:aws:s3:::*"]).await;
+        let admission = Arc::new(AdmissionController::new(crate::performance::AdmissionConfig::default()));
+        let limits = crate::performance::TenantLimits {
+            requests_per_second: Some(0.5),
+            request_burst: 2,
+            ..Default::default()
+        };
+        admission.set_limits(&client.access_key_id, limits).unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::new());
+        client.router = S3Server::new(storage, Arc::clone(&client.auth_manager), S3Config::default())
+            .with_admission(Arc::clone(&admission))
+            .router();
+
+        let (status, _, _) = client.send(Method::PUT, "/media", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) = client.send(Method::PUT, "/media/a.jpg", &[("content-length", "4")], b"data".to_vec()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, headers, body) = client.send(Method::GET, "/media/a.jpg", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body.contains("<Code>SlowDown</Code>"));
+        assert_eq!(headers[header::RETRY_AFTER], "2");
+
+        let metrics = admission.metrics();
+        assert_eq!((metrics.admitted, metrics.throttled), (2, 1));
+        assert_eq!(metrics.tenants[0].bytes, 4);
+    }
+
+    #[tokio::test]
+    async fn test_get_object_transforms_images() {
+        let mut client = Client::new(&["s3:*"], &["arn:aws:s3:::*"]).await;
+        let storage: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::new());
+        let transforms = Arc::new(ImageTransformer::new(Arc::clone(&storage), Default::default()));
+        transforms.set_bucket_transforms("avatars", Default::default()).await.unwrap();
+        client.router = S3Server::new(storage, Arc::clone(&client.auth_manager), S3Config::default())
+            .with_transforms(transforms)
+            .router();
+
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(64, 64)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        client.send(Method::PUT, "/avatars", &[], Vec::new()).await;
+        let (status, headers, _) = client.send(Method::PUT, "/avatars/me.png", &[], png).await;
+        assert_eq!(status, StatusCode::OK);
+        let etag = headers[header::ETAG].clone();
+
+        let (status, headers, _) = client.send(Method::GET, "/avatars/me.png?fmt=jpeg&w=32", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "image/jpeg");
+        assert_ne!(headers[header::ETAG], etag);
+        let (status, headers, _) = client.send(Method::GET, "/avatars/me.png?fmt=auto", &[("accept", "image/webp")], Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "image/webp");
+        assert_eq!(headers[header::VARY], "Accept");
+
+        let (status, _, body) = client.send(Method::GET, "/avatars/me.png?w=0", &[], Vec::new()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("<Code>InvalidArgument</Code>"));
+    }
+}
@@ -0,0 +1,685 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// S3 buckets, objects and multipart uploads on a storage backend
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use openssl::hash::{hash, MessageDigest};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::error::{S3Error, S3Result};
+use crate::errors::NimbuxError;
+use crate::storage::{Object, ObjectMetadata, StorageBackend};
+
+// Objects under `.s3/` hold S3 state; no bucket name can start with a dot
+const BUCKET_PREFIX: &str = ".s3/buckets/";
+const UPLOAD_PREFIX: &str = ".s3/uploads/";
+
+/// Object tag holding the S3 ETag, without quotes
+pub const ETAG_TAG: &str = "s3-etag";
+/// User metadata is kept as tags named after its header
+pub const USER_METADATA_PREFIX: &str = "x-amz-meta-";
+
+/// Every part but the last must be at least this large
+pub const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+pub const MAX_PART_NUMBER: u32 = 10_000;
+const MAX_KEY_LENGTH: usize = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketInfo {
+    pub name: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+/// Metadata S3 clients see for an object
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub etag: String,
+    /// Seconds since the Unix epoch
+    pub last_modified: u64,
+    pub content_type: Option<String>,
+    /// `x-amz-meta-*` headers, by lowercase header name
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ObjectInfo {
+    fn from_metadata(key: &str, metadata: &ObjectMetadata) -> Self {
+        Self {
+            key: key.to_string(),
+            size: metadata.size,
+            // Objects written through other APIs have no MD5; their content hash stands in
+            etag: metadata
+                .tags
+                .get(ETAG_TAG)
+                .cloned()
+                .unwrap_or_else(|| metadata.checksum.chars().take(32).collect()),
+            last_modified: metadata.updated_at,
+            content_type: metadata.content_type.clone(),
+            metadata: metadata
+                .tags
+                .iter()
+                .filter(|(name, _)| name.starts_with(USER_METADATA_PREFIX))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// A multipart upload that has been started but not completed or aborted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultipartUpload {
+    pub upload_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub content_type: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+    /// Seconds since the Unix epoch
+    pub initiated: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartInfo {
+    pub part_number: u32,
+    pub etag: String,
+    pub size: u64,
+    pub last_modified: u64,
+}
+
+/// Which keys of a bucket to list
+#[derive(Debug, Clone, Default)]
+pub struct ListRequest {
+    pub prefix: String,
+    /// Keys sharing the prefix up to the next delimiter are rolled up into one common prefix
+    pub delimiter: Option<String>,
+    /// Only keys and common prefixes after this one are listed
+    pub start_after: Option<String>,
+    pub max_keys: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Listing {
+    pub objects: Vec<ObjectInfo>,
+    pub common_prefixes: Vec<String>,
+    pub is_truncated: bool,
+    /// Last key or common prefix listed, to continue from when truncated
+    pub next_marker: Option<String>,
+}
+
+/// S3 semantics on top of a storage backend.
+///
+/// Object `key` in `bucket` is stored as `<bucket>/<key>`, the naming the
+/// filesystem gateway and the Nimbux API use, so all of them see the same
+/// objects. Buckets and in-progress multipart uploads are kept as objects
+/// under `.s3/`, and so last as long as the backend does.
+pub struct S3Store {
+    storage: Arc<dyn StorageBackend>,
+}
+
+impl S3Store {
+    pub fn new(storage: Arc<dyn StorageBackend>) -> Self {
+        Self { storage }
+    }
+
+    pub async fn list_buckets(&self) -> S3Result<Vec<BucketInfo>> {
+        let mut buckets = Vec::new();
+        for metadata in self.storage.list(Some(BUCKET_PREFIX), None).await? {
+            let object = self.storage.get(&metadata.id).await?;
+            buckets.push(serde_json::from_slice::<BucketInfo>(&object.data)?);
+        }
+        buckets.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(buckets)
+    }
+
+    /// Create a bucket; objects already stored under its name become part of it
+    pub async fn create_bucket(&self, bucket: &str) -> S3Result<BucketInfo> {
+        validate_bucket_name(bucket)?;
+        let id = bucket_id(bucket);
+        if self.storage.exists(&id).await? {
+            return Err(S3Error::bucket_already_owned_by_you(bucket));
+        }
+
+        let info = BucketInfo {
+            name: bucket.to_string(),
+            created_at: unix_now(),
+        };
+        self.put_state(&id, &info).await?;
+        tracing::info!("Created S3 bucket {}", bucket);
+        Ok(info)
+    }
+
+    pub async fn require_bucket(&self, bucket: &str) -> S3Result<()> {
+        if validate_bucket_name(bucket).is_err() || !self.storage.exists(&bucket_id(bucket)).await? {
+            return Err(S3Error::no_such_bucket(bucket));
+        }
+        Ok(())
+    }
+
+    /// Delete an empty bucket, aborting its unfinished multipart uploads
+    pub async fn delete_bucket(&self, bucket: &str) -> S3Result<()> {
+        self.require_bucket(bucket).await?;
+        if !self.storage.list(Some(&object_id(bucket, "")), Some(1)).await?.is_empty() {
+            return Err(S3Error::bucket_not_empty(bucket));
+        }
+
+        for upload in self.list_multipart_uploads(bucket, "").await? {
+            self.abort_multipart_upload(&upload.upload_id, bucket, &upload.key).await?;
+        }
+        self.storage.delete(&bucket_id(bucket)).await?;
+        tracing::info!("Deleted S3 bucket {}", bucket);
+        Ok(())
+    }
+
+    pub async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Vec<u8>,
+        content_type: Option<String>,
+        metadata: BTreeMap<String, String>,
+    ) -> S3Result<ObjectInfo> {
+        self.require_bucket(bucket).await?;
+        validate_key(key)?;
+        let etag = hex::encode(md5(&data)?);
+        self.store_object(bucket, key, data, content_type, metadata, etag).await
+    }
+
+    pub async fn get_object(&self, bucket: &str, key: &str) -> S3Result<(ObjectInfo, Vec<u8>)> {
+        self.require_bucket(bucket).await?;
+        let object = self.storage.get(&object_id(bucket, key)).await.map_err(|e| not_found_as(e, key))?;
+        Ok((ObjectInfo::from_metadata(key, &object.metadata), object.data))
+    }
+
+    pub async fn head_object(&self, bucket: &str, key: &str) -> S3Result<ObjectInfo> {
+        self.require_bucket(bucket).await?;
+        let metadata = self.storage.head(&object_id(bucket, key)).await.map_err(|e| not_found_as(e, key))?;
+        Ok(ObjectInfo::from_metadata(key, &metadata))
+    }
+
+    /// Delete an object; deleting a missing key succeeds, as in S3
+    pub async fn delete_object(&self, bucket: &str, key: &str) -> S3Result<()> {
+        self.require_bucket(bucket).await?;
+        match self.storage.delete(&object_id(bucket, key)).await {
+            Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Copy an object, keeping its metadata unless `replace` gives new metadata
+    pub async fn copy_object(
+        &self,
+        source_bucket: &str,
+        source_key: &str,
+        bucket: &str,
+        key: &str,
+        replace: Option<(Option<String>, BTreeMap<String, String>)>,
+    ) -> S3Result<ObjectInfo> {
+        let (source, data) = self.get_object(source_bucket, source_key).await?;
+        self.require_bucket(bucket).await?;
+        validate_key(key)?;
+
+        let (content_type, metadata) = replace.unwrap_or((source.content_type, source.metadata));
+        self.store_object(bucket, key, data, content_type, metadata, source.etag).await
+    }
+
+    pub async fn list_objects(&self, bucket: &str, request: &ListRequest) -> S3Result<Listing> {
+        self.require_bucket(bucket).await?;
+
+        let bucket_prefix = object_id(bucket, "");
+        let mut entries: Vec<(String, ObjectMetadata)> = self
+            .storage
+            .list(Some(&object_id(bucket, &request.prefix)), None)
+            .await?
+            .into_iter()
+            .filter_map(|metadata| {
+                let key = metadata.name.strip_prefix(&bucket_prefix)?.to_string();
+                Some((key, metadata))
+            })
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let delimiter = request.delimiter.as_deref().filter(|delimiter| !delimiter.is_empty());
+        let start_after = request.start_after.as_deref().unwrap_or("");
+        let mut listing = Listing::default();
+        let mut count = 0;
+
+        for (key, metadata) in &entries {
+            if key.as_str() <= start_after {
+                continue;
+            }
+
+            let common_prefix = delimiter.and_then(|delimiter| {
+                let rest = &key[request.prefix.len()..];
+                rest.find(delimiter).map(|pos| &key[..request.prefix.len() + pos + delimiter.len()])
+            });
+            if let Some(common_prefix) = common_prefix {
+                // Keys under a prefix that was already listed, on this page or an earlier one
+                if common_prefix <= start_after || listing.common_prefixes.last().map(String::as_str) == Some(common_prefix) {
+                    continue;
+                }
+            }
+
+            if count == request.max_keys {
+                listing.is_truncated = true;
+                break;
+            }
+            count += 1;
+
+            match common_prefix {
+                Some(common_prefix) => {
+                    listing.common_prefixes.push(common_prefix.to_string());
+                    listing.next_marker = Some(common_prefix.to_string());
+                }
+                None => {
+                    listing.objects.push(ObjectInfo::from_metadata(key, metadata));
+                    listing.next_marker = Some(key.clone());
+                }
+            }
+        }
+
+        Ok(listing)
+    }
+
+    pub async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<String>,
+        metadata: BTreeMap<String, String>,
+    ) -> S3Result<MultipartUpload> {
+        self.require_bucket(bucket).await?;
+        validate_key(key)?;
+
+        let upload = MultipartUpload {
+            upload_id: Uuid::new_v4().simple().to_string(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            content_type,
+            metadata,
+            initiated: unix_now(),
+        };
+        self.put_state(&upload_id(&upload.upload_id), &upload).await?;
+        Ok(upload)
+    }
+
+    /// Store one part, replacing an earlier upload of the same part number
+    pub async fn upload_part(&self, upload_id: &str, bucket: &str, key: &str, part_number: u32, data: Vec<u8>) -> S3Result<String> {
+        if !(1..=MAX_PART_NUMBER).contains(&part_number) {
+            return Err(S3Error::invalid_argument(format!(
+                "Part number must be an integer between 1 and {}",
+                MAX_PART_NUMBER
+            )));
+        }
+        self.multipart_upload(upload_id, bucket, key).await?;
+
+        let etag = hex::encode(md5(&data)?);
+        let id = part_id(upload_id, part_number);
+        let mut object = Object::with_id(id.clone(), id, data, None);
+        object.add_tag(ETAG_TAG.to_string(), etag.clone());
+        self.storage.put(object).await?;
+        Ok(etag)
+    }
+
+    pub async fn list_parts(&self, upload_id: &str, bucket: &str, key: &str) -> S3Result<(MultipartUpload, Vec<PartInfo>)> {
+        let upload = self.multipart_upload(upload_id, bucket, key).await?;
+        Ok((upload, self.parts(upload_id).await?))
+    }
+
+    /// Join the listed parts into the object.
+    ///
+    /// The ETag is the MD5 of the parts' MD5s followed by the part count, as S3 computes it.
+    pub async fn complete_multipart_upload(
+        &self,
+        upload_id: &str,
+        bucket: &str,
+        key: &str,
+        requested: &[(u32, String)],
+    ) -> S3Result<ObjectInfo> {
+        let upload = self.multipart_upload(upload_id, bucket, key).await?;
+        if requested.is_empty() {
+            return Err(S3Error::malformed_xml());
+        }
+        if requested.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(S3Error::invalid_part_order());
+        }
+
+        let stored: BTreeMap<u32, PartInfo> = self
+            .parts(upload_id)
+            .await?
+            .into_iter()
+            .map(|part| (part.part_number, part))
+            .collect();
+
+        let mut data = Vec::new();
+        let mut digests = Vec::with_capacity(requested.len() * 16);
+        for (index, (part_number, etag)) in requested.iter().enumerate() {
+            let part = stored
+                .get(part_number)
+                .filter(|part| part.etag == etag.trim_matches('"'))
+                .ok_or_else(|| S3Error::invalid_part(format!("Part {} was not uploaded or its ETag does not match", part_number)))?;
+            if index + 1 < requested.len() && part.size < MIN_PART_SIZE {
+                return Err(S3Error::entity_too_small());
+            }
+
+            data.extend_from_slice(&self.storage.get(&part_id(upload_id, *part_number)).await?.data);
+            digests.extend_from_slice(&hex::decode(&part.etag).map_err(|_| S3Error::internal("Corrupt part ETag"))?);
+        }
+
+        let etag = format!("{}-{}", hex::encode(md5(&digests)?), requested.len());
+        let info = self
+            .store_object(bucket, key, data, upload.content_type.clone(), upload.metadata.clone(), etag)
+            .await?;
+
+        self.remove_upload(upload_id).await?;
+        tracing::debug!("Completed multipart upload {} of {}/{} with {} parts", upload_id, bucket, key, requested.len());
+        Ok(info)
+    }
+
+    pub async fn abort_multipart_upload(&self, upload_id: &str, bucket: &str, key: &str) -> S3Result<()> {
+        self.multipart_upload(upload_id, bucket, key).await?;
+        self.remove_upload(upload_id).await
+    }
+
+    /// Unfinished uploads in a bucket whose key starts with `prefix`, by key then start time
+    pub async fn list_multipart_uploads(&self, bucket: &str, prefix: &str) -> S3Result<Vec<MultipartUpload>> {
+        self.require_bucket(bucket).await?;
+        self.uploads(bucket, prefix).await
+    }
+
+    /// Abort unfinished uploads in a bucket whose key starts with `prefix` and
+    /// that were started before `initiated_before` (seconds since the Unix epoch)
+    pub async fn abort_multipart_uploads_before(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        initiated_before: u64,
+    ) -> S3Result<Vec<MultipartUpload>> {
+        let mut aborted = Vec::new();
+        for upload in self.uploads(bucket, prefix).await? {
+            if upload.initiated < initiated_before {
+                self.remove_upload(&upload.upload_id).await?;
+                aborted.push(upload);
+            }
+        }
+        Ok(aborted)
+    }
+
+    async fn uploads(&self, bucket: &str, prefix: &str) -> S3Result<Vec<MultipartUpload>> {
+        let mut uploads = Vec::new();
+        for metadata in self.storage.list(Some(UPLOAD_PREFIX), None).await? {
+            // Parts live below their upload's record
+            if metadata.name[UPLOAD_PREFIX.len()..].contains('/') {
+                continue;
+            }
+            let upload: MultipartUpload = serde_json::from_slice(&self.storage.get(&metadata.id).await?.data)?;
+            if upload.bucket == bucket && upload.key.starts_with(prefix) {
+                uploads.push(upload);
+            }
+        }
+        uploads.sort_by(|a, b| a.key.cmp(&b.key).then(a.initiated.cmp(&b.initiated)));
+        Ok(uploads)
+    }
+
+    async fn multipart_upload(&self, upload_id: &str, bucket: &str, key: &str) -> S3Result<MultipartUpload> {
+        self.require_bucket(bucket).await?;
+        let upload = match self.storage.get(&self::upload_id(upload_id)).await {
+            Ok(object) => serde_json::from_slice::<MultipartUpload>(&object.data)?,
+            Err(NimbuxError::ObjectNotFound { .. }) => return Err(S3Error::no_such_upload(upload_id)),
+            Err(e) => return Err(e.into()),
+        };
+        if upload.bucket != bucket || upload.key != key {
+            return Err(S3Error::no_such_upload(upload_id));
+        }
+        Ok(upload)
+    }
+
+    async fn parts(&self, upload_id: &str) -> S3Result<Vec<PartInfo>> {
+        let prefix = format!("{}/", self::upload_id(upload_id));
+        let mut parts: Vec<PartInfo> = self
+            .storage
+            .list(Some(&prefix), None)
+            .await?
+            .into_iter()
+            .filter_map(|metadata| {
+                Some(PartInfo {
+                    part_number: metadata.name[prefix.len()..].parse().ok()?,
+                    etag: metadata.tags.get(ETAG_TAG)?.clone(),
+                    size: metadata.size,
+                    last_modified: metadata.updated_at,
+                })
+            })
+            .collect();
+        parts.sort_by_key(|part| part.part_number);
+        Ok(parts)
+    }
+
+    async fn remove_upload(&self, upload_id: &str) -> S3Result<()> {
+        for part in self.parts(upload_id).await? {
+            self.storage.delete(&part_id(upload_id, part.part_number)).await?;
+        }
+        self.storage.delete(&self::upload_id(upload_id)).await?;
+        Ok(())
+    }
+
+    async fn store_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Vec<u8>,
+        content_type: Option<String>,
+        metadata: BTreeMap<String, String>,
+        etag: String,
+    ) -> S3Result<ObjectInfo> {
+        let id = object_id(bucket, key);
+        let previous = match self.storage.head(&id).await {
+            Ok(previous) => Some(previous),
+            Err(NimbuxError::ObjectNotFound { .. }) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut object = Object::with_id(id.clone(), id, data, content_type);
+        if let Some(previous) = previous {
+            object.metadata.version = previous.version + 1;
+            object.metadata.created_at = previous.created_at;
+        }
+        object.metadata.tags.extend(metadata);
+        object.add_tag(ETAG_TAG.to_string(), etag);
+
+        let info = ObjectInfo::from_metadata(key, &object.metadata);
+        self.storage.put(object).await?;
+        Ok(info)
+    }
+
+    async fn put_state<T: Serialize>(&self, id: &str, value: &T) -> S3Result<()> {
+        let data = serde_json::to_vec(value)?;
+        let object = Object::with_id(id.to_string(), id.to_string(), data, Some("application/json".to_string()));
+        self.storage.put(object).await?;
+        Ok(())
+    }
+}
+
+/// Bucket names follow the S3 rules, which also keeps them clear of `.s3/`
+pub fn validate_bucket_name(bucket: &str) -> S3Result<()> {
+    let valid_chars = bucket
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'.' || b == b'-');
+    let alnum_ends = bucket.starts_with(|c: char| c.is_ascii_alphanumeric()) && bucket.ends_with(|c: char| c.is_ascii_alphanumeric());
+
+    if !(3..=63).contains(&bucket.len()) || !valid_chars || !alnum_ends || bucket.contains("..") {
+        return Err(S3Error::invalid_bucket_name(bucket));
+    }
+    Ok(())
+}
+
+fn validate_key(key: &str) -> S3Result<()> {
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(S3Error::new(
+            "KeyTooLongError",
+            axum::http::StatusCode::BAD_REQUEST,
+            format!("Object keys must be 1 to {} bytes long", MAX_KEY_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+fn object_id(bucket: &str, key: &str) -> String {
+    format!("{}/{}", bucket, key)
+}
+
+fn bucket_id(bucket: &str) -> String {
+    format!("{}{}", BUCKET_PREFIX, bucket)
+}
+
+fn upload_id(upload_id: &str) -> String {
+    format!("{}{}", UPLOAD_PREFIX, upload_id)
+}
+
+fn part_id(upload_id: &str, part_number: u32) -> String {
+    format!("{}{}/{:05}", UPLOAD_PREFIX, upload_id, part_number)
+}
+
+fn not_found_as(err: NimbuxError, key: &str) -> S3Error {
+    match err {
+        NimbuxError::ObjectNotFound { .. } => S3Error::no_such_key(key),
+        other => other.into(),
+    }
+}
+
+pub(super) fn md5(data: &[u8]) -> S3Result<Vec<u8>> {
+    hash(MessageDigest::md5(), data)
+        .map(|digest| digest.to_vec())
+        .map_err(|e| S3Error::internal(format!("MD5 failed: {}", e)))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn store() -> S3Store {
+        S3Store::new(Arc::new(MemoryStorage::new()))
+    }
+
+    fn list(prefix: &str, delimiter: Option<&str>, start_after: Option<&str>, max_keys: usize) -> ListRequest {
+        ListRequest {
+            prefix: prefix.to_string(),
+            delimiter: delimiter.map(str::to_string),
+            start_after: start_after.map(str::to_string),
+            max_keys,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bucket_lifecycle() {
+        let store = store();
+        assert_eq!(store.create_bucket("Photos").await.unwrap_err().code, "InvalidBucketName");
+        store.create_bucket("photos").await.unwrap();
+        assert_eq!(store.create_bucket("photos").await.unwrap_err().code, "BucketAlreadyOwnedByYou");
+        assert_eq!(store.list_buckets().await.unwrap()[0].name, "photos");
+
+        store.put_object("photos", "a.jpg", b"jpeg".to_vec(), None, BTreeMap::new()).await.unwrap();
+        assert_eq!(store.delete_bucket("photos").await.unwrap_err().code, "BucketNotEmpty");
+        store.delete_object("photos", "a.jpg").await.unwrap();
+        store.delete_object("photos", "a.jpg").await.unwrap();
+        store.delete_bucket("photos").await.unwrap();
+        assert_eq!(store.head_object("photos", "a.jpg").await.unwrap_err().code, "NoSuchBucket");
+    }
+
+    #[tokio::test]
+    async fn test_listing_rolls_up_prefixes_and_pages() {
+        let store = store();
+        store.create_bucket("media").await.unwrap();
+        for key in ["a.txt", "photos/2024/1.jpg", "photos/2024/2.jpg", "photos/2025/1.jpg", "videos/1.mp4", "z.txt"] {
+            store.put_object("media", key, key.as_bytes().to_vec(), None, BTreeMap::new()).await.unwrap();
+        }
+
+        let root = store.list_objects("media", &list("", Some("/"), None, 1000)).await.unwrap();
+        let keys: Vec<_> = root.objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, ["a.txt", "z.txt"]);
+        assert_eq!(root.common_prefixes, ["photos/", "videos/"]);
+        assert!(!root.is_truncated);
+
+        let years = store.list_objects("media", &list("photos/", Some("/"), None, 1000)).await.unwrap();
+        assert_eq!(years.common_prefixes, ["photos/2024/", "photos/2025/"]);
+
+        // Pages of two, continuing after the last key or prefix listed
+        let mut seen = Vec::new();
+        let mut marker = None;
+        loop {
+            let page = store.list_objects("media", &list("", Some("/"), marker.as_deref(), 2)).await.unwrap();
+            seen.extend(page.objects.iter().map(|o| o.key.clone()));
+            seen.extend(page.common_prefixes.iter().cloned());
+            if !page.is_truncated {
+                break;
+            }
+            marker = page.next_marker;
+        }
+        seen.sort();
+        assert_eq!(seen, ["a.txt", "photos/", "videos/", "z.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_joins_parts() {
+        let store = store();
+        store.create_bucket("backups").await.unwrap();
+        let upload = store
+            .create_multipart_upload("backups", "db.tar", Some("application/x-tar".to_string()), BTreeMap::new())
+            .await
+            .unwrap();
+
+        let first = vec![1u8; MIN_PART_SIZE as usize];
+        let etag1 = store.upload_part(&upload.upload_id, "backups", "db.tar", 1, first.clone()).await.unwrap();
+        let etag2 = store.upload_part(&upload.upload_id, "backups", "db.tar", 2, b"tail".to_vec()).await.unwrap();
+        assert_eq!(store.list_parts(&upload.upload_id, "backups", "db.tar").await.unwrap().1.len(), 2);
+
+        let out_of_order = [(2, etag2.clone()), (1, etag1.clone())];
+        let err = store.complete_multipart_upload(&upload.upload_id, "backups", "db.tar", &out_of_order).await.unwrap_err();
+        assert_eq!(err.code, "InvalidPartOrder");
+
+        let parts = [(1, format!("\"{}\"", etag1)), (2, etag2)];
+        let info = store.complete_multipart_upload(&upload.upload_id, "backups", "db.tar", &parts).await.unwrap();
+        assert!(info.etag.ends_with("-2"));
+
+        let (info, data) = store.get_object("backups", "db.tar").await.unwrap();
+        assert_eq!(data.len(), first.len() + 4);
+        assert_eq!(info.content_type.as_deref(), Some("application/x-tar"));
+        assert!(store.list_multipart_uploads("backups", "").await.unwrap().is_empty());
+        assert_eq!(
+            store.list_parts(&upload.upload_id, "backups", "db.tar").await.unwrap_err().code,
+            "NoSuchUpload"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_small_middle_parts_are_rejected() {
+        let store = store();
+        store.create_bucket("backups").await.unwrap();
+        let upload = store.create_multipart_upload("backups", "k", None, BTreeMap::new()).await.unwrap();
+        let etag1 = store.upload_part(&upload.upload_id, "backups", "k", 1, b"small".to_vec()).await.unwrap();
+        let etag2 = store.upload_part(&upload.upload_id, "backups", "k", 2, b"tail".to_vec()).await.unwrap();
+
+        let err = store
+            .complete_multipart_upload(&upload.upload_id, "backups", "k", &[(1, etag1), (2, etag2)])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, "EntityTooSmall");
+
+        store.abort_multipart_upload(&upload.upload_id, "backups", "k").await.unwrap();
+        store.delete_bucket("backups").await.unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// S3 XML documents
+
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::error::{S3Error, S3Result};
+use super::sigv4::uri_encode;
+use super::store::{BucketInfo, Listing, MultipartUpload, ObjectInfo, PartInfo};
+
+const XML_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+const XMLNS: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+/// Owner reported for every bucket and object
+pub struct Owner<'a> {
+    pub id: &'a str,
+    pub display_name: &'a str,
+}
+
+/// Fields of a ListObjects or ListObjectsV2 response that echo the request
+pub struct ListParams<'a> {
+    pub bucket: &'a str,
+    pub prefix: &'a str,
+    pub delimiter: Option<&'a str>,
+    pub max_keys: usize,
+    /// Keys and prefixes are URL-encoded, for keys XML cannot carry
+    pub url_encoded: bool,
+    pub owner: Option<Owner<'a>>,
+}
+
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// ISO 8601 timestamp with milliseconds, as S3 writes them in XML
+pub fn timestamp(unix_secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(unix_secs as i64, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+fn quoted(etag: &str) -> String {
+    escape(&format!("\"{}\"", etag))
+}
+
+pub fn error(code: &str, message: &str, resource: &str, request_id: &str) -> String {
+    format!(
+        "{}<Error><Code>{}</Code><Message>{}</Message><Resource>{}</Resource><RequestId>{}</RequestId></Error>",
+        XML_HEADER,
+        code,
+        escape(message),
+        escape(resource),
+        request_id
+    )
+}
+
+/// Error telling the client which endpoint a bucket must be addressed at
+pub fn redirect(code: &str, bucket: &str, endpoint: &str, request_id: &str) -> String {
+    format!(
+        "{}<Error><Code>{}</Code><Message>The bucket you are attempting to access must be addressed using the specified endpoint. Please send all future requests to this endpoint.</Message><Bucket>{}</Bucket><Endpoint>{}</Endpoint><RequestId>{}</RequestId></Error>",
+        XML_HEADER,
+        code,
+        escape(bucket),
+        escape(endpoint),
+        request_id
+    )
+}
+
+pub fn list_buckets(owner: &Owner, buckets: &[BucketInfo]) -> String {
+    let mut xml = format!(
+        "{}<ListAllMyBucketsResult xmlns=\"{}\"><Owner><ID>{}</ID><DisplayName>{}</DisplayName></Owner><Buckets>",
+        XML_HEADER,
+        XMLNS,
+        escape(owner.id),
+        escape(owner.display_name)
+    );
+    for bucket in buckets {
+        let _ = write!(
+            xml,
+            "<Bucket><Name>{}</Name><CreationDate>{}</CreationDate></Bucket>",
+            escape(&bucket.name),
+            timestamp(bucket.created_at)
+        );
+    }
+    xml.push_str("</Buckets></ListAllMyBucketsResult>");
+    xml
+}
+
+/// `us-east-1` is reported as an empty constraint, as S3 does
+pub fn location(region: &str) -> String {
+    let region = if region == "us-east-1" { "" } else { region };
+    format!("{}<LocationConstraint xmlns=\"{}\">{}</LocationConstraint>", XML_HEADER, XMLNS, escape(region))
+}
+
+fn key_value(value: &str, url_encoded: bool) -> String {
+    if url_encoded {
+        uri_encode(value, false)
+    } else {
+        escape(value)
+    }
+}
+
+fn write_listing(xml: &mut String, params: &ListParams, listing: &Listing) {
+    for object in &listing.objects {
+        let _ = write!(
+            xml,
+            "<Contents><Key>{}</Key><LastModified>{}</LastModified><ETag>{}</ETag><Size>{}</Size>",
+            key_value(&object.key, params.url_encoded),
+            timestamp(object.last_modified),
+            quoted(&object.etag),
+            object.size
+        );
+        if let Some(owner) = &params.owner {
+            let _ = write!(
+                xml,
+                "<Owner><ID>{}</ID><DisplayName>{}</DisplayName></Owner>",
+                escape(owner.id),
+                escape(owner.display_name)
+            );
+        }
+        xml.push_str("<StorageClass>STANDARD</StorageClass></Contents>");
+    }
+    for prefix in &listing.common_prefixes {
+        let _ = write!(xml, "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>", key_value(prefix, params.url_encoded));
+    }
+}
+
+fn write_list_header(xml: &mut String, params: &ListParams, listing: &Listing) {
+    let _ = write!(
+        xml,
+        "<Name>{}</Name><Prefix>{}</Prefix><MaxKeys>{}</MaxKeys><IsTruncated>{}</IsTruncated>",
+        escape(params.bucket),
+        key_value(params.prefix, params.url_encoded),
+        params.max_keys,
+        listing.is_truncated
+    );
+    if let Some(delimiter) = params.delimiter {
+        let _ = write!(xml, "<Delimiter>{}</Delimiter>", key_value(delimiter, params.url_encoded));
+    }
+    if params.url_encoded {
+        xml.push_str("<EncodingType>url</EncodingType>");
+    }
+}
+
+pub fn list_objects_v2(
+    params: &ListParams,
+    listing: &Listing,
+    continuation_token: Option<&str>,
+    next_continuation_token: Option<&str>,
+    start_after: Option<&str>,
+) -> String {
+    let mut xml = format!("{}<ListBucketResult xmlns=\"{}\">", XML_HEADER, XMLNS);
+    write_list_header(&mut xml, params, listing);
+    let _ = write!(xml, "<KeyCount>{}</KeyCount>", listing.objects.len() + listing.common_prefixes.len());
+    if let Some(token) = continuation_token {
+        let _ = write!(xml, "<ContinuationToken>{}</ContinuationToken>", escape(token));
+    }
+    if let Some(token) = next_continuation_token {
+        let _ = write!(xml, "<NextContinuationToken>{}</NextContinuationToken>", escape(token));
+    }
+    if let Some(start_after) = start_after {
+        let _ = write!(xml, "<StartAfter>{}</StartAfter>", key_value(start_after, params.url_encoded));
+    }
+    write_listing(&mut xml, params, listing);
+    xml.push_str("</ListBucketResult>");
+    xml
+}
+
+pub fn list_objects_v1(params: &ListParams, listing: &Listing, marker: &str) -> String {
+    let mut xml = format!("{}<ListBucketResult xmlns=\"{}\">", XML_HEADER, XMLNS);
+    write_list_header(&mut xml, params, listing);
+    let _ = write!(xml, "<Marker>{}</Marker>", key_value(marker, params.url_encoded));
+    // Clients only get NextMarker with a delimiter; without one they continue from the last key
+    if let (true, Some(_), Some(next_marker)) = (listing.is_truncated, params.delimiter, &listing.next_marker) {
+        let _ = write!(xml, "<NextMarker>{}</NextMarker>", key_value(next_marker, params.url_encoded));
+    }
+    write_listing(&mut xml, params, listing);
+    xml.push_str("</ListBucketResult>");
+    xml
+}
+
+pub fn copy_object_result(object: &ObjectInfo) -> String {
+    format!(
+        "{}<CopyObjectResult xmlns=\"{}\"><LastModified>{}</LastModified><ETag>{}</ETag></CopyObjectResult>",
+        XML_HEADER,
+        XMLNS,
+        timestamp(object.last_modified),
+        quoted(&object.etag)
+    )
+}
+
+pub fn initiate_multipart_upload(upload: &MultipartUpload) -> String {
+    format!(
+        "{}<InitiateMultipartUploadResult xmlns=\"{}\"><Bucket>{}</Bucket><Key>{}</Key><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+        XML_HEADER,
+        XMLNS,
+        escape(&upload.bucket),
+        escape(&upload.key),
+        escape(&upload.upload_id)
+    )
+}
+
+pub fn complete_multipart_upload(location: &str, bucket: &str, object: &ObjectInfo) -> String {
+    format!(
+        "{}<CompleteMultipartUploadResult xmlns=\"{}\"><Location>{}</Location><Bucket>{}</Bucket><Key>{}</Key><ETag>{}</ETag></CompleteMultipartUploadResult>",
+        XML_HEADER,
+        XMLNS,
+        escape(location),
+        escape(bucket),
+        escape(&object.key),
+        quoted(&object.etag)
+    )
+}
+
+pub fn list_parts(owner: &Owner, upload: &MultipartUpload, parts: &[PartInfo], marker: u32, max_parts: usize) -> String {
+    let remaining: Vec<&PartInfo> = parts.iter().filter(|part| part.part_number > marker).collect();
+    let page = &remaining[..remaining.len().min(max_parts)];
+    let is_truncated = remaining.len() > page.len();
+
+    let mut xml = format!(
+        "{}<ListPartsResult xmlns=\"{}\"><Bucket>{}</Bucket><Key>{}</Key><UploadId>{}</UploadId>\
+         <Initiator><ID>{}</ID><DisplayName>{}</DisplayName></Initiator><Owner><ID>{}</ID><DisplayName>{}</DisplayName></Owner>\
+         <StorageClass>STANDARD</StorageClass><PartNumberMarker>{}</PartNumberMarker><MaxParts>{}</MaxParts><IsTruncated>{}</IsTruncated>",
+        XML_HEADER,
+        XMLNS,
+        escape(&upload.bucket),
+        escape(&upload.key),
+        escape(&upload.upload_id),
+        escape(owner.id),
+        escape(owner.display_name),
+        escape(owner.id),
+        escape(owner.display_name),
+        marker,
+        max_parts,
+        is_truncated
+    );
+    if let (true, Some(last)) = (is_truncated, page.last()) {
+        let _ = write!(xml, "<NextPartNumberMarker>{}</NextPartNumberMarker>", last.part_number);
+    }
+    for part in page {
+        let _ = write!(
+            xml,
+            "<Part><PartNumber>{}</PartNumber><LastModified>{}</LastModified><ETag>{}</ETag><Size>{}</Size></Part>",
+            part.part_number,
+            timestamp(part.last_modified),
+            quoted(&part.etag),
+            part.size
+        );
+    }
+    xml.push_str("</ListPartsResult>");
+    xml
+}
+
+pub fn list_multipart_uploads(owner: &Owner, bucket: &str, prefix: &str, uploads: &[MultipartUpload]) -> String {
+    let mut xml = format!(
+        "{}<ListMultipartUploadsResult xmlns=\"{}\"><Bucket>{}</Bucket><Prefix>{}</Prefix><MaxUploads>1000</MaxUploads><IsTruncated>false</IsTruncated>",
+        XML_HEADER,
+        XMLNS,
+        escape(bucket),
+        escape(prefix)
+    );
+    for upload in uploads {
+        let _ = write!(
+            xml,
+            "<Upload><Key>{}</Key><UploadId>{}</UploadId><Initiator><ID>{}</ID><DisplayName>{}</DisplayName></Initiator>\
+             <Owner><ID>{}</ID><DisplayName>{}</DisplayName></Owner><StorageClass>STANDARD</StorageClass><Initiated>{}</Initiated></Upload>",
+            escape(&upload.key),
+            escape(&upload.upload_id),
+            escape(owner.id),
+            escape(owner.display_name),
+            escape(owner.id),
+            escape(owner.display_name),
+            timestamp(upload.initiated)
+        );
+    }
+    xml.push_str("</ListMultipartUploadsResult>");
+    xml
+}
+
+/// Outcome of one key of a DeleteObjects request
+pub enum DeleteOutcome {
+    Deleted(String),
+    Failed { key: String, error: S3Error },
+}
+
+pub fn delete_result(outcomes: &[DeleteOutcome], quiet: bool) -> String {
+    let mut xml = format!("{}<DeleteResult xmlns=\"{}\">", XML_HEADER, XMLNS);
+    for outcome in outcomes {
+        match outcome {
+            DeleteOutcome::Deleted(key) if !quiet => {
+                let _ = write!(xml, "<Deleted><Key>{}</Key></Deleted>", escape(key));
+            }
+            DeleteOutcome::Deleted(_) => {}
+            DeleteOutcome::Failed { key, error } => {
+                let _ = write!(
+                    xml,
+                    "<Error><Key>{}</Key><Code>{}</Code><Message>{}</Message></Error>",
+                    escape(key),
+                    error.code,
+                    escape(&error.message)
+                );
+            }
+        }
+    }
+    xml.push_str("</DeleteResult>");
+    xml
+}
+
+#[derive(Debug, Deserialize)]
+struct CompleteMultipartUpload {
+    #[serde(rename = "Part", default)]
+    parts: Vec<CompletedPart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletedPart {
+    #[serde(rename = "PartNumber")]
+    part_number: u32,
+    #[serde(rename = "ETag")]
+    etag: String,
+}
+
+/// Part numbers and ETags of a CompleteMultipartUpload body, in request order
+pub fn parse_complete_multipart_upload(body: &[u8]) -> S3Result<Vec<(u32, String)>> {
+    let request: CompleteMultipartUpload = serde_xml_rs::from_reader(body).map_err(|_| S3Error::malformed_xml())?;
+    Ok(request.parts.into_iter().map(|part| (part.part_number, part.etag)).collect())
+}
+
+#[derive(Debug, Deserialize)]
+struct Delete {
+    #[serde(rename = "Quiet", default)]
+    quiet: bool,
+    #[serde(rename = "Object", default)]
+    objects: Vec<DeleteObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteObject {
+    #[serde(rename = "Key")]
+    key: String,
+}
+
+/// Keys of a DeleteObjects body and whether only errors should be reported
+pub fn parse_delete_objects(body: &[u8]) -> S3Result<(Vec<String>, bool)> {
+    let request: Delete = serde_xml_rs::from_reader(body).map_err(|_| S3Error::malformed_xml())?;
+    Ok((request.objects.into_iter().map(|object| object.key).collect(), request.quiet))
+}
@@ -6,34 +6,86 @@
 // Simple HTTP API server
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
+    body::Body,
+    extract::{Path, RawQuery, Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
     routing::get,
     Router,
 };
+use axum_server::tls_rustls::RustlsConfig;
 use serde_json::json;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
-use crate::errors::Result;
-use crate::storage::StorageBackend;
+use crate::errors::{NimbuxError, Result};
+use crate::network::conditional::{http_date, ReadConditions, ReadOutcome};
+use crate::network::domains::{Addressing, DomainRegistry};
+use crate::network::s3::sigv4::percent_decode;
+use crate::storage::{ImageTransformer, ObjectMetadata, StorageBackend, TransformParams};
 
 /// Simple HTTP server for Nimbux
 pub struct SimpleHttpServer {
     storage: Arc<dyn StorageBackend>,
+    transforms: Option<Arc<ImageTransformer>>,
+    domains: Option<Arc<DomainRegistry>>,
+    tls: Option<Arc<rustls::ServerConfig>>,
     port: u16,
 }
 
+/// State shared by the handlers
+#[derive(Clone)]
+struct HttpState {
+    storage: Arc<dyn StorageBackend>,
+    transforms: Option<Arc<ImageTransformer>>,
+}
+
+/// State of the host routing middleware
+#[derive(Clone)]
+struct HostRouting {
+    state: HttpState,
+    domains: Arc<DomainRegistry>,
+}
+
 impl SimpleHttpServer {
     /// Create a new simple HTTP server
     pub fn new(storage: Arc<dyn StorageBackend>, port: u16) -> Self {
-        Self { storage, port }
+        Self { storage, transforms: None, domains: None, tls: None, port }
+    }
+    
+    /// Resize and convert images read with `?w=&h=&fmt=` in buckets that allow it
+    pub fn with_transforms(mut self, transforms: Arc<ImageTransformer>) -> Self {
+        self.transforms = Some(transforms);
+        self
+    }
+    
+    /// Serve `GET /<key>` for buckets addressed by virtual host or custom
+    /// domain, such as CDN origins, next to `/objects/<bucket>/<key>`
+    pub fn with_domains(mut self, domains: Arc<DomainRegistry>) -> Self {
+        self.domains = Some(domains);
+        self
+    }
+    
+    /// Serve HTTPS, with certificates chosen by server name
+    pub fn with_tls(mut self, tls: Arc<rustls::ServerConfig>) -> Self {
+        self.tls = Some(tls);
+        self
     }
     
     /// Start the HTTP server
     pub async fn start(&self) -> Result<()> {
         let app = self.create_router();
         
+        if let Some(tls) = &self.tls {
+            tracing::info!("Simple HTTP server starting on port {} with TLS", self.port);
+            axum_server::bind_rustls(SocketAddr::from(([0, 0, 0, 0], self.port)), RustlsConfig::from_config(Arc::clone(tls)))
+                .serve(app.into_make_service())
+                .await
+                .map_err(|e| crate::errors::NimbuxError::Network(format!("HTTP server error: {}", e)))?;
+            return Ok(());
+        }
+        
         let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", self.port))
             .await
             .map_err(|e| crate::errors::NimbuxError::Network(format!("Failed to bind to port {}: {}", self.port, e)))?;
@@ -49,15 +101,57 @@ impl SimpleHttpServer {
     
     /// Create the API router
     fn create_router(&self) -> Router {
-        let storage = Arc::clone(&self.storage);
+        let state = HttpState {
+            storage: Arc::clone(&self.storage),
+            transforms: self.transforms.clone(),
+        };
         
-        Router::new()
+        let router = Router::new()
             .route("/health", get(health_check))
             .route("/stats", get(get_stats))
-            .with_state(storage)
+            .route("/objects/*id", get(get_object));
+        let router = match &self.domains {
+            Some(domains) => {
+                let routing = HostRouting { state: state.clone(), domains: Arc::clone(domains) };
+                router.layer(middleware::from_fn_with_state(routing, route_by_host))
+            }
+            None => router,
+        };
+        router.with_state(state)
     }
 }
 
+/// Serve requests whose `Host` names a bucket from that bucket, and redirect
+/// requests for a bucket made at an address it is redirected from
+async fn route_by_host(State(routing): State<HostRouting>, request: Request, next: Next) -> Response {
+    let host = request.headers().get(header::HOST).and_then(|value| value.to_str().ok());
+    let path = request.uri().path();
+    let (bucket, addressing, key_path) = match routing.domains.resolve(host) {
+        Some(route) => (route.bucket, route.addressing, path),
+        None => match path.strip_prefix("/objects/").and_then(|rest| rest.find('/').map(|slash| rest.split_at(slash))) {
+            Some((bucket, key_path)) => (bucket.to_string(), Addressing::Path, key_path),
+            None => return next.run(request).await,
+        },
+    };
+
+    if let Some(redirect) = routing.domains.redirect(&bucket, addressing, host) {
+        let location = redirect.location(&routing.domains.config().scheme, key_path, request.uri().query());
+        return (StatusCode::MOVED_PERMANENTLY, [(header::LOCATION, location)]).into_response();
+    }
+    if addressing == Addressing::Path {
+        return next.run(request).await;
+    }
+
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return (StatusCode::METHOD_NOT_ALLOWED, Json(json!({ "error": "Method not allowed" }))).into_response();
+    }
+    let key = match percent_decode(key_path.trim_start_matches('/')) {
+        Ok(key) if !key.is_empty() => key,
+        _ => return error_response(NimbuxError::ObjectNotFound { object_id: format!("{}{}", bucket, key_path) }),
+    };
+    read_object(&routing.state, format!("{}/{}", bucket, key), request.uri().query(), request.headers()).await
+}
+
 /// Health check endpoint
 async fn health_check() -> Json<serde_json::Value> {
     Json(json!({
@@ -68,8 +162,8 @@ async fn health_check() -> Json<serde_json::Value> {
 }
 
 /// Get storage statistics
-async fn get_stats(State(storage): State<Arc<dyn StorageBackend>>) -> std::result::Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    match storage.stats().await {
+async fn get_stats(State(state): State<HttpState>) -> std::result::Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    match state.storage.stats().await {
         Ok(stats) => Ok(Json(json!({
             "total_objects": stats.total_objects,
             "total_size": stats.total_size,
@@ -84,4 +178,223 @@ async fn get_stats(State(storage): State<Arc<dyn StorageBackend>>) -> std::resul
             Err((StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)))
         }
     }
-}
\ No newline at end of file
+}
+/// Object contents, honoring `Range`, `If-Match`, `If-None-Match` and
+/// `If-Modified-Since` so players can seek within stored media
+async fn get_object(
+    State(state): State<HttpState>,
+    Path(id): Path<String>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+) -> Response {
+    read_object(&state, id, query.as_deref(), &headers).await
+}
+
+async fn read_object(state: &HttpState, id: String, query: Option<&str>, headers: &HeaderMap) -> Response {
+    // Versions, bucket settings and domains are internal
+    if id.starts_with('.') {
+        return error_response(NimbuxError::ObjectNotFound { object_id: id });
+    }
+    let mut object = match state.storage.get(&id).await {
+        Ok(object) => object,
+        Err(e) => return error_response(e),
+    };
+    // Renditions stand in for the object, so conditions and ranges apply to them
+    let mut vary = false;
+    if let Some(transforms) = &state.transforms {
+        let accept = headers.get(header::ACCEPT).and_then(|value| value.to_str().ok());
+        let params = match TransformParams::parse(query, accept) {
+            Ok(params) => params,
+            Err(e) => return error_response(e),
+        };
+        if let Some(params) = params {
+            let (bucket, key) = id.split_once('/').unwrap_or((&id, ""));
+            let rendition = match transforms.transform(bucket, key, &object.metadata.checksum, object.data, &params).await {
+                Ok(rendition) => rendition,
+                Err(e) => return error_response(e),
+            };
+            object.metadata.checksum = rendition.etag;
+            object.metadata.size = rendition.data.len() as u64;
+            object.metadata.content_type = Some(rendition.format.content_type().to_string());
+            object.data = rendition.data;
+            vary = params.negotiated;
+        }
+    }
+    let outcome = match ReadConditions::from_headers(headers).evaluate(&object.metadata) {
+        Ok(outcome) => outcome,
+        Err(e) => return error_response(e),
+    };
+
+    let mut builder = object_headers(Response::builder(), &object.metadata);
+    if vary {
+        builder = builder.header(header::VARY, "Accept");
+    }
+    let size = object.metadata.size;
+    let response = match outcome {
+        ReadOutcome::NotModified => builder.status(StatusCode::NOT_MODIFIED).body(Body::empty()),
+        ReadOutcome::Full => builder.body(Body::from(object.data)),
+        ReadOutcome::Partial(start, end) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, size))
+            .body(Body::from(object.data[start as usize..=end as usize].to_vec())),
+    };
+    response.unwrap_or_else(|e| error_response(NimbuxError::Internal(e.to_string())))
+}
+
+fn object_headers(builder: axum::http::response::Builder, metadata: &ObjectMetadata) -> axum::http::response::Builder {
+    builder
+        .header(header::ETAG, format!("\"{}\"", metadata.checksum))
+        .header(header::LAST_MODIFIED, http_date(metadata.updated_at))
+        .header(header::CONTENT_TYPE, metadata.content_type.as_deref().unwrap_or("application/octet-stream"))
+        .header(header::ACCEPT_RANGES, "bytes")
+}
+
+fn error_response(error: NimbuxError) -> Response {
+    let status = match &error {
+        NimbuxError::ObjectNotFound { .. } => StatusCode::NOT_FOUND,
+        NimbuxError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        NimbuxError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+        NimbuxError::QuotaExceeded(_) => StatusCode::INSUFFICIENT_STORAGE,
+        NimbuxError::Throttled { .. } => StatusCode::TOO_MANY_REQUESTS,
+        NimbuxError::ObjectLocked { .. } => StatusCode::FORBIDDEN,
+        NimbuxError::RangeNotSatisfiable { size } => {
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+                Json(json!({ "error": "Range not satisfiable", "message": error.to_string() })),
+            )
+                .into_response();
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(json!({ "error": status.to_string(), "message": error.to_string() }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::domains::{DomainConfig, DomainRequest};
+    use crate::storage::{MemoryStorage, Object};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn server() -> (Router, String) {
+        let storage = Arc::new(MemoryStorage::new());
+        let data: Vec<u8> = (0..100).collect();
+        let object = Object::with_id("media/clip.mp4".to_string(), "media/clip.mp4".to_string(), data, Some("video/mp4".to_string()));
+        let etag = format!("\"{}\"", object.metadata.checksum);
+        storage.put(object).await.unwrap();
+        (SimpleHttpServer::new(storage, 8080).create_router(), etag)
+    }
+
+    async fn send(app: Router, headers: &[(&str, &str)]) -> Response {
+        let mut request = Request::builder().uri("/objects/media/clip.mp4");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_range_read() {
+        let (app, _) = server().await;
+        let response = send(app.clone(), &[("range", "bytes=10-19")]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 10-19/100");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), (10..20).collect::<Vec<u8>>());
+
+        let response = send(app, &[("range", "bytes=200-")]).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */100");
+    }
+
+    #[tokio::test]
+    async fn test_conditional_read() {
+        let (app, etag) = server().await;
+        let response = send(app.clone(), &[("if-none-match", etag.as_str())]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = send(app.clone(), &[("if-match", "\"stale\"")]).await;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+        let response = send(app, &[("if-match", etag.as_str())]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+    }
+    #[tokio::test]
+    async fn test_buckets_addressed_by_host() {
+        let storage = Arc::new(MemoryStorage::new());
+        let object = Object::with_id("media/a b.mp4".to_string(), "media/a b.mp4".to_string(), b"clip".to_vec(), None);
+        storage.put(object).await.unwrap();
+        let config = DomainConfig {
+            base_domain: Some("cdn.nimbux.local".to_string()),
+            ..DomainConfig::default()
+        };
+        let domains = Arc::new(DomainRegistry::new(config, storage.clone()));
+        domains.register("media", "videos.example.com", DomainRequest::default()).await.unwrap();
+        let app = SimpleHttpServer::new(storage, 8080).with_domains(Arc::clone(&domains)).create_router();
+
+        let get = |host: &str, uri: &str| {
+            let request = Request::builder().uri(uri).header(header::HOST, host).body(Body::empty()).unwrap();
+            app.clone().oneshot(request)
+        };
+        for host in ["media.cdn.nimbux.local:8080", "videos.example.com"] {
+            let response = get(host, "/a%20b.mp4").await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", host);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(body.as_ref(), b"clip");
+        }
+        assert_eq!(get("videos.example.com", "/health").await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(get("localhost:8080", "/health").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(get("localhost:8080", "/objects/media/a%20b.mp4").await.unwrap().status(), StatusCode::OK);
+        // Domain settings hold certificate keys
+        assert_eq!(get(".domains.cdn.nimbux.local", "/videos.example.com").await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        domains
+            .register("media", "videos.example.com", DomainRequest { canonical: true, ..Default::default() })
+            .await
+            .unwrap();
+        let response = get("localhost:8080", "/objects/media/a%20b.mp4?t=1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(response.headers()[header::LOCATION], "http://videos.example.com/a%20b.mp4?t=1");
+    }
+
+    #[tokio::test]
+    async fn test_images_transformed_on_read() {
+        let storage = Arc::new(MemoryStorage::new());
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(64, 32)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        for id in ["avatars/a.png", "media/a.png"] {
+            storage.put(Object::with_id(id.to_string(), id.to_string(), png.clone(), Some("image/png".to_string()))).await.unwrap();
+        }
+        let transforms = Arc::new(ImageTransformer::new(storage.clone(), Default::default()));
+        transforms.set_bucket_transforms("avatars", Default::default()).await.unwrap();
+        let app = SimpleHttpServer::new(storage, 8080).with_transforms(transforms).create_router();
+
+        let get = |uri: &str, headers: &[(&str, &str)]| {
+            let mut request = Request::builder().uri(uri);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+        let response = get("/objects/avatars/a.png?w=16&fmt=auto", &[("accept", "image/webp,*/*")]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/webp");
+        assert_eq!(response.headers()[header::VARY], "Accept");
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let image = image::load_from_memory(&body).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+
+        let response = get("/objects/avatars/a.png?w=16&fmt=auto", &[("accept", "image/webp"), ("if-none-match", &etag)]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let response = get("/objects/media/a.png?w=16", &[]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = get("/objects/media/a.png", &[]).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+    }
+}
@@ -0,0 +1,356 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Client of the streaming TCP protocol
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use super::{read_frame, Frame, FrameType, Outgoing, SendWindow, TcpConfig, TcpOperation, TcpRequest, TcpResponse, Trailer};
+use crate::errors::{NimbuxError, Result};
+use crate::network::conditional::ReadConditions;
+use crate::security::client_encryption::{ClientEncryption, ObjectEncryption};
+
+/// A stream waiting on the server
+struct PendingStream {
+    frames: mpsc::UnboundedSender<Frame>,
+    send_window: Arc<SendWindow>,
+}
+
+type PendingStreams = Arc<Mutex<HashMap<u64, PendingStream>>>;
+
+/// Client of a Nimbux TCP server.
+///
+/// Requests made from concurrent tasks are pipelined over the one
+/// connection, each on its own stream, and bodies move under the server's
+/// flow control in both directions.
+///
+/// With client-side encryption set, `put` encrypts bodies before they are
+/// sent and `get` decrypts client-side encrypted objects as they arrive.
+pub struct TcpClient {
+    outgoing: Outgoing,
+    streams: PendingStreams,
+    next_stream: AtomicU64,
+    config: TcpConfig,
+    encryption: Option<ClientEncryption>,
+}
+
+impl TcpClient {
+    pub async fn connect(addr: &str) -> Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| NimbuxError::Network(format!("Failed to connect to {}: {}", addr, e)))?;
+        let _ = stream.set_nodelay(true);
+        Ok(Self::new(stream))
+    }
+
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        Self::with_config(stream, TcpConfig::default())
+    }
+
+    /// Use the frame size and window of `config`; the frame size must not be
+    /// over the server's
+    pub fn with_config<S>(stream: S, config: TcpConfig) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, writer) = tokio::io::split(stream);
+        let (outgoing, _writer) = Outgoing::spawn(writer);
+        let streams = PendingStreams::default();
+        tokio::spawn(read_frames(reader, Arc::clone(&streams), config.max_frame_size));
+        Self { outgoing, streams, next_stream: AtomicU64::new(1), config, encryption: None }
+    }
+
+    /// Encrypt uploads with per-object data keys wrapped by the customer's
+    /// keys, and decrypt client-side encrypted downloads
+    pub fn with_encryption(mut self, encryption: ClientEncryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Send a request without a body and wait for its response
+    pub async fn request(&self, request: TcpRequest) -> Result<TcpResponse> {
+        if matches!(request.op, TcpOperation::Put | TcpOperation::Get) {
+            return Err(NimbuxError::InvalidRequest("Bodies are sent with put and read with get".to_string()));
+        }
+        let mut stream = self.open(&request).await?;
+        let response = stream.response().await?;
+        stream.finished = true;
+        Ok(response)
+    }
+
+    /// Upload `data`, encrypted first when client-side encryption is set
+    pub async fn put(&self, bucket: &str, key: &str, data: &[u8], content_type: Option<String>) -> Result<TcpResponse> {
+        let Some(encryption) = &self.encryption else {
+            return self.put_stream(bucket, key, data.len() as u64, content_type, data).await;
+        };
+        let body = encryption.encrypt(bucket, key, data).await?;
+        let mut request = TcpRequest::new(TcpOperation::Put, bucket, key);
+        request.size = Some(body.ciphertext.len() as u64);
+        request.content_type = content_type;
+        request.metadata = body.metadata;
+        self.upload(&request, body.ciphertext.as_slice()).await
+    }
+
+    /// Upload `size` bytes read from `body`, reading only as much as the
+    /// server has granted credit for. Bodies are sent as they are, since
+    /// client-side encryption needs the whole body.
+    pub async fn put_stream<R: AsyncRead + Unpin>(
+        &self,
+        bucket: &str,
+        key: &str,
+        size: u64,
+        content_type: Option<String>,
+        body: R,
+    ) -> Result<TcpResponse> {
+        let mut request = TcpRequest::new(TcpOperation::Put, bucket, key);
+        request.size = Some(size);
+        request.content_type = content_type;
+        self.upload(&request, body).await
+    }
+
+    async fn upload<R: AsyncRead + Unpin>(&self, request: &TcpRequest, mut body: R) -> Result<TcpResponse> {
+        let size = request.size.unwrap_or_default();
+        let mut stream = self.open(request).await?;
+        let window = Arc::clone(&stream.send_window);
+
+        let mut hasher = blake3::Hasher::new();
+        let mut buffer = vec![0u8; self.config.max_frame_size as usize];
+        let mut sent = 0u64;
+        while sent < size {
+            let wanted = (size - sent).min(buffer.len() as u64) as usize;
+            // The server answers early when it refuses the upload
+            let credit = tokio::select! {
+                credit = window.acquire(wanted) => credit?,
+                frame = stream.next() => {
+                    let response = frame?.parse()?;
+                    stream.finished = true;
+                    return Ok(response);
+                }
+            };
+            let read = body
+                .read(&mut buffer[..credit])
+                .await
+                .map_err(|e| NimbuxError::Network(format!("Failed to read upload body: {}", e)))?;
+            if read == 0 {
+                return Err(NimbuxError::InvalidRequest(format!("Body ended after {} of {} bytes", sent, size)));
+            }
+            if read < credit {
+                window.grant((credit - read) as u32);
+            }
+            hasher.update(&buffer[..read]);
+            self.outgoing
+                .send(Frame::new(FrameType::Data, stream.id, buffer[..read].to_vec()))
+                .await?;
+            sent += read as u64;
+        }
+
+        let trailer = Trailer { size, checksum: hasher.finalize().to_hex().to_string() };
+        self.outgoing.send(Frame::json(FrameType::Trailer, stream.id, &trailer)?).await?;
+        let response = stream.response().await?;
+        stream.finished = true;
+        Ok(response)
+    }
+
+    /// Download an object, decrypting it when client-side encryption is set;
+    /// without it the stored body is returned as it is
+    ///
+    /// With client-side encryption set, an object without client-side
+    /// encryption metadata is an error rather than plaintext, so a server
+    /// that drops the metadata cannot turn decryption off unnoticed. Use
+    /// `get_stream` to read such objects.
+    pub async fn get(&self, bucket: &str, key: &str, conditions: Option<ReadConditions>) -> Result<(TcpResponse, Vec<u8>)> {
+        let mut body = Vec::new();
+        let response = self.get_stream(bucket, key, conditions, &mut body).await?;
+        let Some(encryption) = &self.encryption else {
+            return Ok((response, body));
+        };
+        if !response.success || response.not_modified {
+            return Ok((response, body));
+        }
+        let Some(metadata) = response.metadata.as_ref().filter(|metadata| ObjectEncryption::of(&metadata.tags).is_client_side()) else {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "{}/{} has no client-side encryption metadata; refusing to return it as plaintext",
+                bucket, key
+            )));
+        };
+        if response.range.is_some() {
+            return Err(NimbuxError::InvalidRequest(
+                "Ranges of client-side encrypted objects cannot be decrypted".to_string(),
+            ));
+        }
+        let body = encryption.decrypt(bucket, key, &metadata.tags, &body).await?;
+        Ok((response, body))
+    }
+
+    /// Stream an object's body into `sink`, checked against its trailer.
+    /// Client-side encrypted bodies arrive encrypted.
+    /// Credit is granted as the sink takes the body, so a slow sink slows
+    /// the server down instead of buffering the object.
+    pub async fn get_stream<W: AsyncWrite + Unpin>(
+        &self,
+        bucket: &str,
+        key: &str,
+        conditions: Option<ReadConditions>,
+        mut sink: W,
+    ) -> Result<TcpResponse> {
+        let mut request = TcpRequest::new(TcpOperation::Get, bucket, key);
+        request.conditions = conditions;
+        let mut stream = self.open(&request).await?;
+        // Queued behind the request, so the server knows the stream by then
+        let window = self.config.window_size;
+        self.outgoing.send(Frame::window_update(stream.id, window)).await?;
+
+        let response = stream.response().await?;
+        let Some(size) = response.body_size else {
+            stream.finished = true;
+            return Ok(response);
+        };
+        let mut hasher = blake3::Hasher::new();
+        let mut received = 0u64;
+        let mut consumed = 0u32;
+        loop {
+            let frame = stream.next().await?;
+            match frame.frame_type {
+                FrameType::Data => {
+                    received += frame.payload.len() as u64;
+                    if received > size {
+                        return Err(NimbuxError::Network(format!("Body is longer than its announced {} bytes", size)));
+                    }
+                    hasher.update(&frame.payload);
+                    sink.write_all(&frame.payload)
+                        .await
+                        .map_err(|e| NimbuxError::Network(format!("Failed to write body: {}", e)))?;
+                    consumed += frame.payload.len() as u32;
+                    if consumed >= window / 2 {
+                        self.outgoing.control(Frame::window_update(stream.id, consumed))?;
+                        consumed = 0;
+                    }
+                }
+                FrameType::Trailer => {
+                    let trailer: Trailer = frame.parse()?;
+                    if trailer.size != received || received != size {
+                        return Err(NimbuxError::Network(format!(
+                            "Body of {} bytes does not match its announced size {} or its trailer's {}",
+                            received, size, trailer.size
+                        )));
+                    }
+                    let actual = hasher.finalize().to_hex().to_string();
+                    if actual != trailer.checksum {
+                        return Err(NimbuxError::ChecksumMismatch { expected: trailer.checksum, actual });
+                    }
+                    sink.flush()
+                        .await
+                        .map_err(|e| NimbuxError::Network(format!("Failed to write body: {}", e)))?;
+                    stream.finished = true;
+                    return Ok(response);
+                }
+                other => {
+                    return Err(NimbuxError::Network(format!("Unexpected {:?} frame in a body", other)));
+                }
+            }
+        }
+    }
+
+    async fn open(&self, request: &TcpRequest) -> Result<OpenStream<'_>> {
+        let id = self.next_stream.fetch_add(1, Ordering::Relaxed);
+        let (frames, received) = mpsc::unbounded_channel();
+        let send_window = Arc::new(SendWindow::new());
+        self.streams
+            .lock()
+            .unwrap()
+            .insert(id, PendingStream { frames, send_window: Arc::clone(&send_window) });
+        let stream = OpenStream { client: self, id, frames: received, send_window, finished: false };
+        self.outgoing.send(Frame::json(FrameType::Request, id, request)?).await?;
+        Ok(stream)
+    }
+}
+
+/// A stream of the client; one dropped before it finished is reset, so
+/// the server stops working on it
+struct OpenStream<'a> {
+    client: &'a TcpClient,
+    id: u64,
+    frames: mpsc::UnboundedReceiver<Frame>,
+    send_window: Arc<SendWindow>,
+    finished: bool,
+}
+
+impl OpenStream<'_> {
+    async fn next(&mut self) -> Result<Frame> {
+        let frame = self
+            .frames
+            .recv()
+            .await
+            .ok_or_else(|| NimbuxError::Network("Connection closed".to_string()))?;
+        if frame.frame_type == FrameType::Reset {
+            self.finished = true;
+            return Err(NimbuxError::Network(format!(
+                "Stream reset by server: {}",
+                String::from_utf8_lossy(&frame.payload)
+            )));
+        }
+        Ok(frame)
+    }
+
+    async fn response(&mut self) -> Result<TcpResponse> {
+        let frame = self.next().await?;
+        if frame.frame_type != FrameType::Response {
+            return Err(NimbuxError::Network(format!("Expected a response, got a {:?} frame", frame.frame_type)));
+        }
+        frame.parse()
+    }
+}
+
+impl Drop for OpenStream<'_> {
+    fn drop(&mut self) {
+        self.client.streams.lock().unwrap().remove(&self.id);
+        if !self.finished {
+            let _ = self.client.outgoing.control(Frame::reset(self.id, "Abandoned by client"));
+        }
+    }
+}
+
+/// Route frames from the server to their streams until the connection
+/// closes, then fail the streams still waiting
+async fn read_frames<R: AsyncRead + Unpin>(mut reader: R, streams: PendingStreams, max_frame_size: u32) {
+    loop {
+        let frame = match read_frame(&mut reader, max_frame_size).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("TCP client connection failed: {}", e);
+                break;
+            }
+        };
+        let open = streams.lock().unwrap();
+        // Frames of streams the client gave up on are dropped
+        let Some(stream) = open.get(&frame.stream_id) else { continue };
+        match frame.frame_type {
+            FrameType::WindowUpdate => match frame.credit() {
+                Ok(credit) => stream.send_window.grant(credit),
+                Err(e) => {
+                    warn!("TCP client connection failed: {}", e);
+                    break;
+                }
+            },
+            _ => {
+                let _ = stream.frames.send(frame);
+            }
+        }
+    }
+    for (_, stream) in streams.lock().unwrap().drain() {
+        stream.send_window.close();
+    }
+}
@@ -0,0 +1,147 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Credit-based flow control of stream bodies
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
+use crate::errors::{NimbuxError, Result};
+
+/// Body bytes a sender may still send on a stream.
+///
+/// Every stream starts with no credit; the receiver grants it with window
+/// updates as it consumes the body, so a slow reader stalls the sender
+/// instead of making anyone buffer.
+pub struct SendWindow {
+    state: Mutex<WindowState>,
+    granted: Notify,
+}
+
+struct WindowState {
+    credit: u64,
+    closed: bool,
+}
+
+impl SendWindow {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(WindowState { credit: 0, closed: false }),
+            granted: Notify::new(),
+        }
+    }
+
+    pub fn grant(&self, credit: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.credit = state.credit.saturating_add(u64::from(credit));
+        drop(state);
+        self.granted.notify_waiters();
+    }
+
+    /// No more credit will come, when the stream is reset or the connection
+    /// drops; sends fail once the remaining credit is spent
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.granted.notify_waiters();
+    }
+
+    /// Wait for credit and take up to `wanted` bytes of it
+    pub async fn acquire(&self, wanted: usize) -> Result<usize> {
+        loop {
+            let granted = self.granted.notified();
+            tokio::pin!(granted);
+            granted.as_mut().enable();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.credit > 0 {
+                    let taken = state.credit.min(wanted as u64);
+                    state.credit -= taken;
+                    return Ok(taken as usize);
+                }
+                if state.closed {
+                    return Err(NimbuxError::Network("Stream was reset".to_string()));
+                }
+            }
+            granted.await;
+        }
+    }
+}
+
+impl Default for SendWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Body bytes the peer may still send on a stream, checked as they arrive
+pub struct ReceiveWindow {
+    remaining: AtomicI64,
+}
+
+impl ReceiveWindow {
+    pub fn new() -> Self {
+        Self { remaining: AtomicI64::new(0) }
+    }
+
+    /// Record credit sent to the peer in a window update
+    pub fn grant(&self, credit: u32) {
+        self.remaining.fetch_add(i64::from(credit), Ordering::AcqRel);
+    }
+
+    /// Account for received bytes; false if the peer sent more than it was
+    /// granted
+    pub fn consume(&self, bytes: usize) -> bool {
+        self.remaining.fetch_sub(bytes as i64, Ordering::AcqRel) >= bytes as i64
+    }
+}
+
+impl Default for ReceiveWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_senders_wait_for_credit() {
+        let window = Arc::new(SendWindow::new());
+        let sender = tokio::spawn({
+            let window = Arc::clone(&window);
+            async move {
+                let mut sent = Vec::new();
+                while sent.iter().sum::<usize>() < 100 {
+                    sent.push(window.acquire(60).await.unwrap());
+                }
+                sent
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!sender.is_finished());
+        window.grant(40);
+        window.grant(30);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!sender.is_finished());
+        window.grant(30);
+        let sent = sender.await.unwrap();
+        assert_eq!(sent.iter().sum::<usize>(), 100);
+        assert!(sent.iter().all(|&bytes| bytes <= 60));
+
+        window.close();
+        assert!(window.acquire(1).await.is_err());
+
+        let receive = ReceiveWindow::new();
+        receive.grant(10);
+        assert!(receive.consume(6));
+        assert!(!receive.consume(6));
+    }
+}
@@ -0,0 +1,215 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Frames of the TCP protocol and their wire format
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::errors::{NimbuxError, Result};
+
+/// "NIMB"
+pub const MAGIC: u32 = 0x4E494D42;
+pub const PROTOCOL_VERSION: u8 = 2;
+/// Magic, version, type, stream ID, payload length and payload CRC32
+pub const HEADER_LEN: usize = 22;
+
+/// What a frame carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameType {
+    /// Client opens a stream with a JSON `TcpRequest`
+    Request = 0x01,
+    /// Server answers a stream with a JSON `TcpResponse`
+    Response = 0x02,
+    /// A chunk of an object body
+    Data = 0x03,
+    /// Ends a body with its size and BLAKE3 checksum
+    Trailer = 0x04,
+    /// Lets the other side send this many more body bytes on the stream
+    WindowUpdate = 0x05,
+    /// Abandons a stream, with the reason as UTF-8
+    Reset = 0x06,
+}
+
+impl TryFrom<u8> for FrameType {
+    type Error = NimbuxError;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0x01 => Ok(FrameType::Request),
+            0x02 => Ok(FrameType::Response),
+            0x03 => Ok(FrameType::Data),
+            0x04 => Ok(FrameType::Trailer),
+            0x05 => Ok(FrameType::WindowUpdate),
+            0x06 => Ok(FrameType::Reset),
+            _ => Err(NimbuxError::Network(format!("Unknown frame type 0x{:02x}", value))),
+        }
+    }
+}
+
+/// End of an object body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trailer {
+    pub size: u64,
+    /// BLAKE3 of the body, hex encoded
+    pub checksum: String,
+}
+
+impl Trailer {
+    pub fn of(body: &[u8]) -> Self {
+        Self { size: body.len() as u64, checksum: blake3::hash(body).to_hex().to_string() }
+    }
+}
+
+/// One frame of a stream.
+///
+/// Wire format, big endian:
+/// [4 bytes: Magic] [1 byte: Version] [1 byte: Type] [8 bytes: StreamID]
+/// [4 bytes: PayloadLength] [4 bytes: CRC32 of payload] [Payload]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub frame_type: FrameType,
+    pub stream_id: u64,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(frame_type: FrameType, stream_id: u64, payload: Vec<u8>) -> Self {
+        Self { frame_type, stream_id, payload }
+    }
+
+    pub fn json(frame_type: FrameType, stream_id: u64, value: &impl Serialize) -> Result<Self> {
+        Ok(Self::new(frame_type, stream_id, serde_json::to_vec(value)?))
+    }
+
+    pub fn window_update(stream_id: u64, credit: u32) -> Self {
+        Self::new(FrameType::WindowUpdate, stream_id, credit.to_be_bytes().to_vec())
+    }
+
+    pub fn reset(stream_id: u64, reason: &str) -> Self {
+        Self::new(FrameType::Reset, stream_id, reason.as_bytes().to_vec())
+    }
+
+    /// Payload of a request, response or trailer frame
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.payload)?)
+    }
+
+    /// Credit granted by a window update frame
+    pub fn credit(&self) -> Result<u32> {
+        let bytes: [u8; 4] = self
+            .payload
+            .as_slice()
+            .try_into()
+            .map_err(|_| NimbuxError::Network("Window update must carry 4 bytes".to_string()))?;
+        Ok(u32::from_be_bytes(bytes))
+    }
+}
+
+/// Read the next frame, or `None` if the connection closed between frames.
+/// Frames with payloads over `max_payload` are refused before the payload
+/// is read.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R, max_payload: u32) -> Result<Option<Frame>> {
+    let mut header = [0u8; HEADER_LEN];
+    match reader.read_exact(&mut header[..1]).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(NimbuxError::Network(format!("Failed to read frame: {}", e))),
+    }
+    reader
+        .read_exact(&mut header[1..])
+        .await
+        .map_err(|e| NimbuxError::Network(format!("Failed to read frame header: {}", e)))?;
+
+    let magic = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    if magic != MAGIC {
+        return Err(NimbuxError::Network("Invalid magic number".to_string()));
+    }
+    if header[4] != PROTOCOL_VERSION {
+        return Err(NimbuxError::Network(format!(
+            "Protocol version {} is not supported, only {}",
+            header[4], PROTOCOL_VERSION
+        )));
+    }
+    let frame_type = FrameType::try_from(header[5])?;
+    let stream_id = u64::from_be_bytes(header[6..14].try_into().unwrap());
+    let length = u32::from_be_bytes(header[14..18].try_into().unwrap());
+    let checksum = u32::from_be_bytes(header[18..22].try_into().unwrap());
+    if length > max_payload {
+        return Err(NimbuxError::Network(format!(
+            "Frame of {} bytes is over the limit of {}",
+            length, max_payload
+        )));
+    }
+
+    let mut payload = vec![0u8; length as usize];
+    reader
+        .read_exact(&mut payload)
+        .await
+        .map_err(|e| NimbuxError::Network(format!("Failed to read payload: {}", e)))?;
+    if crc32fast::hash(&payload) != checksum {
+        return Err(NimbuxError::Network(format!("Checksum mismatch in frame of stream {}", stream_id)));
+    }
+    Ok(Some(Frame { frame_type, stream_id, payload }))
+}
+
+/// Write a frame; the caller flushes
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &Frame) -> Result<()> {
+    let mut header = [0u8; HEADER_LEN];
+    header[0..4].copy_from_slice(&MAGIC.to_be_bytes());
+    header[4] = PROTOCOL_VERSION;
+    header[5] = frame.frame_type as u8;
+    header[6..14].copy_from_slice(&frame.stream_id.to_be_bytes());
+    header[14..18].copy_from_slice(&(frame.payload.len() as u32).to_be_bytes());
+    header[18..22].copy_from_slice(&crc32fast::hash(&frame.payload).to_be_bytes());
+
+    writer
+        .write_all(&header)
+        .await
+        .map_err(|e| NimbuxError::Network(format!("Failed to write frame header: {}", e)))?;
+    writer
+        .write_all(&frame.payload)
+        .await
+        .map_err(|e| NimbuxError::Network(format!("Failed to write payload: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_frames_round_trip_and_corruption_is_caught() {
+        let frames = vec![
+            Frame::new(FrameType::Data, 7, b"chunk".to_vec()),
+            Frame::window_update(7, 65536),
+            Frame::json(FrameType::Trailer, 7, &Trailer::of(b"chunk")).unwrap(),
+        ];
+        let mut wire = Vec::new();
+        for frame in &frames {
+            write_frame(&mut wire, frame).await.unwrap();
+        }
+
+        let mut reader = wire.as_slice();
+        for frame in &frames {
+            assert_eq!(read_frame(&mut reader, 1024).await.unwrap().as_ref(), Some(frame));
+        }
+        assert!(read_frame(&mut reader, 1024).await.unwrap().is_none());
+        assert_eq!(frames[1].credit().unwrap(), 65536);
+        assert_eq!(frames[2].parse::<Trailer>().unwrap().size, 5);
+
+        // A flipped payload byte, an oversized frame and a version 1 header are refused
+        let mut corrupt = wire.clone();
+        corrupt[HEADER_LEN] ^= 0xFF;
+        assert!(read_frame(&mut corrupt.as_slice(), 1024).await.is_err());
+        assert!(read_frame(&mut wire.as_slice(), 4).await.is_err());
+        let mut old = wire.clone();
+        old[4] = 1;
+        assert!(read_frame(&mut old.as_slice(), 1024).await.is_err());
+        // A header cut short is an error, not a clean close
+        assert!(read_frame(&mut &wire[..10], 1024).await.is_err());
+    }
+}
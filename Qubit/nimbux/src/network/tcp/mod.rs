@@ -0,0 +1,784 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Streaming TCP protocol (v2)
+
+mod client;
+mod flow;
+mod frame;
+
+pub use client::TcpClient;
+pub use flow::{ReceiveWindow, SendWindow};
+pub use frame::{read_frame, write_frame, Frame, FrameType, Trailer, HEADER_LEN, MAGIC, PROTOCOL_VERSION};
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument};
+
+use crate::errors::{NimbuxError, Result};
+use crate::network::conditional::{ReadConditions, ReadOutcome};
+use crate::network::s3::store::USER_METADATA_PREFIX;
+use crate::storage::{Object, ObjectMetadata, StorageBackend, StorageStats};
+
+/// Responses and body chunks a connection queues for its socket before
+/// the streams producing them wait
+const QUEUED_FRAMES: usize = 64;
+
+/// Limits of the protocol's streams
+#[derive(Debug, Clone)]
+pub struct TcpConfig {
+    /// Requests a connection may have in flight at once
+    pub max_concurrent_streams: usize,
+    /// Largest frame payload, and so the largest body chunk
+    pub max_frame_size: u32,
+    /// Body bytes a sender is granted ahead of what the receiver consumed
+    pub window_size: u32,
+    pub max_object_size: u64,
+    /// Upload bodies all connections may hold in memory together
+    pub max_buffered_bytes: u64,
+}
+
+impl Default for TcpConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_streams: 64,
+            max_frame_size: 256 * 1024,
+            window_size: 1024 * 1024,
+            max_object_size: 5 * 1024 * 1024 * 1024,
+            max_buffered_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Operation of a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TcpOperation {
+    Put,
+    Get,
+    Head,
+    Delete,
+    List,
+    Stats,
+    Health,
+}
+
+/// Opens a stream. A put's body follows in data frames and a trailer as
+/// the server grants credit; a get's body follows its response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TcpRequest {
+    pub op: TcpOperation,
+    #[serde(default)]
+    pub bucket: String,
+    /// Object key, or the key prefix of a list
+    #[serde(default)]
+    pub key: String,
+    /// Body size of a put, so memory can be reserved before it is sent
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Range and preconditions of a get
+    #[serde(default)]
+    pub conditions: Option<ReadConditions>,
+    /// Most objects a list returns
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// User metadata of a put by lowercase name, stored under `x-amz-meta-`
+    /// as S3 stores it
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl TcpRequest {
+    pub fn new(op: TcpOperation, bucket: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            op,
+            bucket: bucket.into(),
+            key: key.into(),
+            size: None,
+            content_type: None,
+            conditions: None,
+            limit: None,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    fn object_id(&self) -> Result<String> {
+        if self.bucket.is_empty() || self.key.is_empty() {
+            return Err(NimbuxError::InvalidRequest("Bucket and key are required".to_string()));
+        }
+        Ok(format!("{}/{}", self.bucket, self.key))
+    }
+}
+
+/// Answers a stream
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TcpResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    /// S3 style error code, such as `NoSuchKey` or `BadDigest`
+    pub code: Option<String>,
+    pub metadata: Option<ObjectMetadata>,
+    pub objects: Option<Vec<ObjectMetadata>>,
+    pub stats: Option<StorageStats>,
+    /// First and last byte of the body when a range was requested
+    #[serde(default)]
+    pub range: Option<(u64, u64)>,
+    /// Set instead of sending a body when the client's copy is current
+    #[serde(default)]
+    pub not_modified: bool,
+    /// Size of the body that follows in data frames and a trailer
+    #[serde(default)]
+    pub body_size: Option<u64>,
+}
+
+impl TcpResponse {
+    fn ok() -> Self {
+        Self { success: true, ..Default::default() }
+    }
+
+    fn failure(error: &NimbuxError) -> Self {
+        let code = match error {
+            NimbuxError::ObjectNotFound { .. } => "NoSuchKey",
+            NimbuxError::PreconditionFailed(_) => "PreconditionFailed",
+            NimbuxError::RangeNotSatisfiable { .. } => "InvalidRange",
+            NimbuxError::ChecksumMismatch { .. } => "BadDigest",
+            NimbuxError::QuotaExceeded(_) => "QuotaExceeded",
+            NimbuxError::Throttled { .. } => "SlowDown",
+            NimbuxError::ObjectLocked { .. } | NimbuxError::Authorization(_) => "AccessDenied",
+            NimbuxError::InvalidRequest(_) | NimbuxError::InvalidObjectId { .. } | NimbuxError::Serialization(_) => {
+                "InvalidRequest"
+            }
+            _ => "InternalError",
+        };
+        Self {
+            success: false,
+            error: Some(error.to_string()),
+            code: Some(code.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Queues of frames for a connection's writer task: responses and body
+/// chunks, and flow control frames that must not wait behind them
+#[derive(Clone)]
+struct Outgoing {
+    data: mpsc::Sender<Frame>,
+    control: mpsc::UnboundedSender<Frame>,
+}
+
+impl Outgoing {
+    /// Start writing frames to `writer`; the task ends once every queue
+    /// handle is dropped
+    fn spawn<W: AsyncWrite + Send + Unpin + 'static>(writer: W) -> (Self, JoinHandle<Result<()>>) {
+        let (data, data_frames) = mpsc::channel(QUEUED_FRAMES);
+        let (control, control_frames) = mpsc::unbounded_channel();
+        let writer = tokio::spawn(write_frames(BufWriter::new(writer), data_frames, control_frames));
+        (Self { data, control }, writer)
+    }
+
+    async fn send(&self, frame: Frame) -> Result<()> {
+        self.data.send(frame).await.map_err(|_| connection_closed())
+    }
+
+    fn control(&self, frame: Frame) -> Result<()> {
+        self.control.send(frame).map_err(|_| connection_closed())
+    }
+}
+
+fn connection_closed() -> NimbuxError {
+    NimbuxError::Network("Connection closed".to_string())
+}
+
+async fn write_frames<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut data: mpsc::Receiver<Frame>,
+    mut control: mpsc::UnboundedReceiver<Frame>,
+) -> Result<()> {
+    loop {
+        let frame = tokio::select! {
+            biased;
+            Some(frame) = control.recv() => frame,
+            Some(frame) = data.recv() => frame,
+            else => break,
+        };
+        write_frame(&mut writer, &frame).await?;
+        // Frames queued together go out in one write
+        if control.is_empty() && data.is_empty() {
+            writer
+                .flush()
+                .await
+                .map_err(|e| NimbuxError::Network(format!("Failed to flush frames: {}", e)))?;
+        }
+    }
+    writer
+        .shutdown()
+        .await
+        .map_err(|e| NimbuxError::Network(format!("Failed to close connection: {}", e)))
+}
+
+/// State shared by a server's connections
+struct Shared {
+    storage: Arc<dyn StorageBackend>,
+    config: TcpConfig,
+    /// Memory for upload bodies, one permit per KiB
+    buffered: Semaphore,
+}
+
+impl Shared {
+    fn new(storage: Arc<dyn StorageBackend>, config: TcpConfig) -> Self {
+        let buffered = Semaphore::new(kib(config.max_buffered_bytes) as usize);
+        Self { storage, config, buffered }
+    }
+}
+
+fn kib(bytes: u64) -> u32 {
+    u32::try_from(bytes.div_ceil(1024)).unwrap_or(u32::MAX)
+}
+
+/// High-performance TCP server for Nimbux.
+///
+/// Each request opens a stream on its connection, so clients pipeline
+/// requests instead of waiting for each response. Bodies move in chunks
+/// under credit-based flow control and end with a BLAKE3 trailer.
+pub struct TcpServer {
+    shared: Arc<Shared>,
+    port: u16,
+    max_connections: usize,
+}
+
+impl TcpServer {
+    /// Create a new TCP server
+    pub fn new(storage: Arc<dyn StorageBackend>, port: u16) -> Self {
+        Self {
+            shared: Arc::new(Shared::new(storage, TcpConfig::default())),
+            port,
+            max_connections: 1000,
+        }
+    }
+
+    /// Set maximum concurrent connections
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    pub fn with_config(mut self, config: TcpConfig) -> Self {
+        self.shared = Arc::new(Shared::new(Arc::clone(&self.shared.storage), config));
+        self
+    }
+
+    /// Start the TCP server
+    #[instrument(skip(self))]
+    pub async fn start(&self) -> Result<()> {
+        let listener = TcpListener::bind(format!("0.0.0.0:{}", self.port))
+            .await
+            .map_err(|e| NimbuxError::Network(format!("Failed to bind TCP port {}: {}", self.port, e)))?;
+
+        info!("TCP server listening on port {}", self.port);
+
+        let semaphore = Arc::new(Semaphore::new(self.max_connections));
+
+        loop {
+            let (stream, addr) = listener.accept().await
+                .map_err(|e| NimbuxError::Network(format!("Failed to accept connection: {}", e)))?;
+
+            debug!("New TCP connection from {}", addr);
+            let _ = stream.set_nodelay(true);
+
+            let shared = Arc::clone(&self.shared);
+            let permit = semaphore.clone().acquire_owned().await
+                .map_err(|e| NimbuxError::Network(format!("Failed to acquire semaphore: {}", e)))?;
+
+            tokio::spawn(async move {
+                if let Err(e) = serve(shared, stream).await {
+                    error!("Error handling TCP connection from {}: {}", addr, e);
+                }
+                drop(permit);
+            });
+        }
+    }
+
+    /// Serve one connection until the client closes it
+    pub async fn serve_connection<S>(&self, stream: S) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        serve(Arc::clone(&self.shared), stream).await
+    }
+}
+
+/// Handles of an open stream, kept by the connection's reader
+struct StreamHandle {
+    body: mpsc::UnboundedSender<Frame>,
+    send_window: Arc<SendWindow>,
+    receive_window: Arc<ReceiveWindow>,
+}
+
+type Streams = Arc<Mutex<HashMap<u64, StreamHandle>>>;
+
+async fn serve<S>(shared: Arc<Shared>, stream: S) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut reader, writer) = tokio::io::split(stream);
+    let (outgoing, writer) = Outgoing::spawn(writer);
+    let streams: Streams = Arc::default();
+
+    let result = loop {
+        match read_frame(&mut reader, shared.config.max_frame_size).await {
+            Ok(Some(frame)) => {
+                if let Err(e) = dispatch(&shared, &streams, &outgoing, frame) {
+                    break Err(e);
+                }
+            }
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+
+    // Nothing more will arrive for the open streams; their bodies end and
+    // their windows get no more credit
+    for (_, stream) in streams.lock().unwrap().drain() {
+        stream.send_window.close();
+    }
+    drop(outgoing);
+    // Requests still in flight finish before the connection closes
+    let written = writer
+        .await
+        .map_err(|e| NimbuxError::Internal(format!("TCP writer task failed: {}", e)))?;
+    result.and(written)
+}
+
+/// Route a frame read from the client to its stream
+fn dispatch(shared: &Arc<Shared>, streams: &Streams, outgoing: &Outgoing, frame: Frame) -> Result<()> {
+    let id = frame.stream_id;
+    let mut open = streams.lock().unwrap();
+    match frame.frame_type {
+        FrameType::Request => {
+            if open.contains_key(&id) {
+                return Err(NimbuxError::Network(format!("Stream {} is already open", id)));
+            }
+            if open.len() >= shared.config.max_concurrent_streams {
+                return outgoing.control(Frame::reset(id, "Too many concurrent streams"));
+            }
+            let request: TcpRequest = match frame.parse() {
+                Ok(request) => request,
+                Err(e) => return outgoing.control(Frame::reset(id, &format!("Invalid request: {}", e))),
+            };
+
+            let (body, body_frames) = mpsc::unbounded_channel();
+            let stream = Stream {
+                id,
+                shared: Arc::clone(shared),
+                outgoing: outgoing.clone(),
+                body: body_frames,
+                send_window: Arc::default(),
+                receive_window: Arc::default(),
+            };
+            open.insert(id, StreamHandle {
+                body,
+                send_window: Arc::clone(&stream.send_window),
+                receive_window: Arc::clone(&stream.receive_window),
+            });
+            let streams = Arc::clone(streams);
+            tokio::spawn(async move {
+                stream.run(request).await;
+                streams.lock().unwrap().remove(&id);
+            });
+        }
+        FrameType::Data | FrameType::Trailer => {
+            // Frames of a stream that already ended are dropped
+            let Some(stream) = open.get(&id) else { return Ok(()) };
+            if frame.frame_type == FrameType::Data && !stream.receive_window.consume(frame.payload.len()) {
+                if let Some(stream) = open.remove(&id) {
+                    stream.send_window.close();
+                }
+                return outgoing.control(Frame::reset(id, "Flow control window exceeded"));
+            }
+            let _ = stream.body.send(frame);
+        }
+        FrameType::WindowUpdate => {
+            if let Some(stream) = open.get(&id) {
+                stream.send_window.grant(frame.credit()?);
+            }
+        }
+        FrameType::Reset => {
+            if let Some(stream) = open.remove(&id) {
+                debug!("Stream {} reset by client: {}", id, String::from_utf8_lossy(&frame.payload));
+                stream.send_window.close();
+            }
+        }
+        FrameType::Response => {
+            return Err(NimbuxError::Network("Clients cannot send responses".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Part of an object sent as a response body
+struct Body {
+    data: Vec<u8>,
+    range: Range<usize>,
+}
+
+/// One request being served
+struct Stream {
+    id: u64,
+    shared: Arc<Shared>,
+    outgoing: Outgoing,
+    body: mpsc::UnboundedReceiver<Frame>,
+    send_window: Arc<SendWindow>,
+    receive_window: Arc<ReceiveWindow>,
+}
+
+impl Stream {
+    async fn run(mut self, request: TcpRequest) {
+        debug!("Stream {}: {:?} {}/{}", self.id, request.op, request.bucket, request.key);
+        let (response, body) = match self.handle(&request).await {
+            Ok(answer) => answer,
+            Err(e) => (TcpResponse::failure(&e), None),
+        };
+        let sent = match Frame::json(FrameType::Response, self.id, &response) {
+            Ok(frame) => self.outgoing.send(frame).await,
+            Err(e) => Err(e),
+        };
+        if let (Ok(()), Some(body)) = (sent, body) {
+            if let Err(e) = self.send_body(&body.data[body.range]).await {
+                debug!("Stream {} body abandoned: {}", self.id, e);
+                let _ = self.outgoing.control(Frame::reset(self.id, &e.to_string()));
+            }
+        }
+    }
+
+    async fn handle(&mut self, request: &TcpRequest) -> Result<(TcpResponse, Option<Body>)> {
+        let storage = &self.shared.storage;
+        let response = match request.op {
+            TcpOperation::Put => self.put(request).await?,
+            TcpOperation::Get => return self.get(request).await,
+            TcpOperation::Head => TcpResponse {
+                metadata: Some(storage.head(&request.object_id()?).await?),
+                ..TcpResponse::ok()
+            },
+            TcpOperation::Delete => {
+                storage.delete(&request.object_id()?).await?;
+                TcpResponse::ok()
+            }
+            TcpOperation::List => {
+                if request.bucket.is_empty() {
+                    return Err(NimbuxError::InvalidRequest("A list needs a bucket".to_string()));
+                }
+                let prefix = format!("{}/{}", request.bucket, request.key);
+                TcpResponse {
+                    objects: Some(storage.list(Some(&prefix), request.limit).await?),
+                    ..TcpResponse::ok()
+                }
+            }
+            TcpOperation::Stats => TcpResponse { stats: Some(storage.stats().await?), ..TcpResponse::ok() },
+            TcpOperation::Health => TcpResponse::ok(),
+        };
+        Ok((response, None))
+    }
+
+    /// Receive a body into memory reserved for its announced size, granting
+    /// credit as it arrives, and store it once its trailer checks out
+    async fn put(&mut self, request: &TcpRequest) -> Result<TcpResponse> {
+        let id = request.object_id()?;
+        let size = request
+            .size
+            .ok_or_else(|| NimbuxError::InvalidRequest("A put must announce its size".to_string()))?;
+        let config = &self.shared.config;
+        if size > config.max_object_size.min(config.max_buffered_bytes) {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Object of {} bytes is over the limit of {}",
+                size,
+                config.max_object_size.min(config.max_buffered_bytes)
+            )));
+        }
+        // Uploads over the memory budget wait here, before any credit is
+        // granted, so their clients hold their bodies instead of the server
+        let _reserved = self
+            .shared
+            .buffered
+            .acquire_many(kib(size))
+            .await
+            .map_err(|_| NimbuxError::Internal("Upload memory budget is closed".to_string()))?;
+
+        let window = u64::from(config.window_size);
+        let mut data = Vec::with_capacity(size as usize);
+        let mut granted = 0u64;
+        let trailer = loop {
+            // Keep the client a window ahead of what has arrived, topping up
+            // in half windows and never past the announced size
+            let target = size.min(data.len() as u64 + window);
+            if target - granted >= window / 2 || (target == size && target > granted) {
+                let credit = (target - granted) as u32;
+                self.receive_window.grant(credit);
+                self.outgoing.control(Frame::window_update(self.id, credit))?;
+                granted = target;
+            }
+            let frame = self
+                .body
+                .recv()
+                .await
+                .ok_or_else(|| NimbuxError::Network("Stream ended before its trailer".to_string()))?;
+            match frame.frame_type {
+                FrameType::Data => data.extend_from_slice(&frame.payload),
+                _ => break frame.parse::<Trailer>()?,
+            }
+        };
+
+        if data.len() as u64 != size || trailer.size != size {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Body of {} bytes does not match its announced size {} or its trailer's {}",
+                data.len(),
+                size,
+                trailer.size
+            )));
+        }
+        let mut object = Object::with_id(id.clone(), id, data, request.content_type.clone());
+        if object.metadata.checksum != trailer.checksum {
+            return Err(NimbuxError::ChecksumMismatch {
+                expected: trailer.checksum,
+                actual: object.metadata.checksum,
+            });
+        }
+        for (name, value) in &request.metadata {
+            let tag = format!("{}{}", USER_METADATA_PREFIX, name.to_ascii_lowercase());
+            object.metadata.tags.insert(tag, value.clone());
+        }
+        let metadata = object.metadata.clone();
+        self.shared.storage.put(object).await?;
+        Ok(TcpResponse { metadata: Some(metadata), ..TcpResponse::ok() })
+    }
+
+    async fn get(&self, request: &TcpRequest) -> Result<(TcpResponse, Option<Body>)> {
+        let object = self.shared.storage.get(&request.object_id()?).await?;
+        let conditions = request.conditions.clone().unwrap_or_default();
+        let mut response = TcpResponse::ok();
+        let range = match conditions.evaluate(&object.metadata)? {
+            ReadOutcome::NotModified => {
+                response.not_modified = true;
+                None
+            }
+            ReadOutcome::Full => Some(0..object.data.len()),
+            ReadOutcome::Partial(start, end) => {
+                response.range = Some((start, end));
+                Some(start as usize..end as usize + 1)
+            }
+        };
+        response.body_size = range.as_ref().map(|range| range.len() as u64);
+        response.metadata = Some(object.metadata);
+        Ok((response, range.map(|range| Body { data: object.data, range })))
+    }
+
+    /// Send a body as fast as the client's credit allows, then its trailer
+    async fn send_body(&self, body: &[u8]) -> Result<()> {
+        let chunk = self.shared.config.max_frame_size as usize;
+        let mut sent = 0;
+        while sent < body.len() {
+            let credit = self.send_window.acquire((body.len() - sent).min(chunk)).await?;
+            let data = body[sent..sent + credit].to_vec();
+            self.outgoing.send(Frame::new(FrameType::Data, self.id, data)).await?;
+            sent += credit;
+        }
+        self.outgoing
+            .send(Frame::json(FrameType::Trailer, self.id, &Trailer::of(body))?)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use std::time::Duration;
+    use tokio::io::DuplexStream;
+
+    fn config() -> TcpConfig {
+        TcpConfig {
+            max_frame_size: 8 * 1024,
+            window_size: 32 * 1024,
+            max_buffered_bytes: 4 * 1024 * 1024,
+            ..TcpConfig::default()
+        }
+    }
+
+    fn connect() -> (DuplexStream, Arc<MemoryStorage>, JoinHandle<Result<()>>) {
+        let storage = Arc::new(MemoryStorage::new());
+        let server = TcpServer::new(storage.clone(), 0).with_config(config());
+        let (client, connection) = tokio::io::duplex(16 * 1024);
+        let served = tokio::spawn(async move { server.serve_connection(connection).await });
+        (client, storage, served)
+    }
+
+    #[tokio::test]
+    async fn test_streams_large_bodies_and_pipelines_requests() {
+        let (connection, _storage, served) = connect();
+        let client = Arc::new(TcpClient::with_config(connection, config()));
+        let video: Vec<u8> = (0..3 * 1024 * 1024u32).map(|i| (i * 31 % 251) as u8).collect();
+
+        let put = client.put("media", "video.bin", &video, Some("video/mp4".to_string())).await.unwrap();
+        assert!(put.success);
+        assert_eq!(put.metadata.unwrap().size, video.len() as u64);
+        let (get, body) = client.get("media", "video.bin", None).await.unwrap();
+        assert_eq!(get.body_size, Some(video.len() as u64));
+        assert!(body == video);
+
+        let range = ReadConditions { range: Some("bytes=100-199".to_string()), ..Default::default() };
+        let (partial, body) = client.get("media", "video.bin", Some(range)).await.unwrap();
+        assert_eq!((partial.range, body.as_slice()), (Some((100, 199)), &video[100..200]));
+        let current = ReadConditions { if_none_match: get.metadata.map(|m| m.checksum), ..Default::default() };
+        let (unchanged, body) = client.get("media", "video.bin", Some(current)).await.unwrap();
+        assert!(unchanged.not_modified && body.is_empty());
+
+        // Requests from concurrent tasks share the connection
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                let client = Arc::clone(&client);
+                tokio::spawn(async move {
+                    let data = vec![i as u8; 20_000 + i * 1000];
+                    let key = format!("parts/{}", i);
+                    assert!(client.put("media", &key, &data, None).await.unwrap().success);
+                    let (_, body) = client.get("media", &key, None).await.unwrap();
+                    assert!(body == data);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let listed = client.request(TcpRequest::new(TcpOperation::List, "media", "parts/")).await.unwrap();
+        assert_eq!(listed.objects.unwrap().len(), 16);
+        let head = client.request(TcpRequest::new(TcpOperation::Head, "media", "parts/3")).await.unwrap();
+        assert_eq!(head.metadata.unwrap().size, 23_000);
+        assert!(client.request(TcpRequest::new(TcpOperation::Delete, "media", "parts/3")).await.unwrap().success);
+        let (missing, _) = client.get("media", "parts/3", None).await.unwrap();
+        assert_eq!(missing.code.as_deref(), Some("NoSuchKey"));
+
+        drop(client);
+        served.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_side_encryption_stores_ciphertext_and_decrypts_on_get() {
+        use crate::security::client_encryption::{ClientEncryption, LocalKeyWrapper, ObjectEncryption};
+
+        let (connection, storage, _served) = connect();
+        let wrapper = Arc::new(LocalKeyWrapper::new("customer-1", &[7; 32]));
+        let client = TcpClient::with_config(connection, config()).with_encryption(ClientEncryption::new(wrapper));
+        let photo = b"private photo".repeat(1000);
+
+        assert!(client.put("media", "photo.jpg", &photo, None).await.unwrap().success);
+        let stored = storage.get("media/photo.jpg").await.unwrap();
+        assert_ne!(stored.data, photo);
+        assert!(ObjectEncryption::of(&stored.metadata.tags).is_client_side());
+        assert_eq!(stored.metadata.tags["x-amz-meta-x-amz-unencrypted-content-length"], photo.len().to_string());
+
+        let (_, body) = client.get("media", "photo.jpg", None).await.unwrap();
+        assert!(body == photo);
+        let range = ReadConditions { range: Some("bytes=0-9".to_string()), ..Default::default() };
+        assert!(client.get("media", "photo.jpg", Some(range)).await.is_err());
+
+        // Clients without the key get the ciphertext
+        let server = TcpServer::new(storage.clone(), 0).with_config(config());
+        let (connection, served) = tokio::io::duplex(16 * 1024);
+        tokio::spawn(async move { server.serve_connection(served).await });
+        let plain = TcpClient::with_config(connection, config());
+        let (_, body) = plain.get("media", "photo.jpg", None).await.unwrap();
+        assert!(body == stored.data);
+
+        // Dropping the metadata does not get the ciphertext or a plaintext object past the key holder
+        let mut stripped = stored.clone();
+        stripped.metadata.tags.clear();
+        storage.put(stripped).await.unwrap();
+        assert!(matches!(client.get("media", "photo.jpg", None).await, Err(NimbuxError::InvalidRequest(_))));
+        let plaintext = Object::with_id("media/notes.txt".to_string(), "media/notes.txt".to_string(), b"hello".to_vec(), None);
+        storage.put(plaintext).await.unwrap();
+        assert!(client.get("media", "notes.txt", None).await.is_err());
+        let (missing, _) = client.get("media", "gone.jpg", None).await.unwrap();
+        assert_eq!(missing.code.as_deref(), Some("NoSuchKey"));
+
+        // A body moved under another key does not decrypt
+        let mut moved = Object::with_id("media/copy.jpg".to_string(), "media/copy.jpg".to_string(), stored.data.clone(), None);
+        moved.metadata.tags = stored.metadata.tags.clone();
+        storage.put(moved).await.unwrap();
+        assert!(client.get("media", "copy.jpg", None).await.is_err());
+    }
+
+    /// Next frame of `stream`, skipping frames of streams the test is done with
+    async fn next(connection: &mut DuplexStream, stream: u64) -> Frame {
+        loop {
+            let frame = read_frame(connection, 1024 * 1024).await.unwrap().unwrap();
+            if frame.stream_id == stream {
+                return frame;
+            }
+        }
+    }
+
+    async fn send(connection: &mut DuplexStream, frame: Frame) {
+        write_frame(connection, &frame).await.unwrap();
+        connection.flush().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flow_control_and_trailers_are_enforced() {
+        let (mut connection, storage, _served) = connect();
+        let object = Object::with_id("media/clip.bin".to_string(), "media/clip.bin".to_string(), vec![7; 100_000], None);
+        storage.put(object).await.unwrap();
+
+        // A get sends nothing past the credit the client granted
+        send(&mut connection, Frame::json(FrameType::Request, 1, &TcpRequest::new(TcpOperation::Get, "media", "clip.bin")).unwrap()).await;
+        let response: TcpResponse = next(&mut connection, 1).await.parse().unwrap();
+        assert_eq!(response.body_size, Some(100_000));
+        let idle = tokio::time::timeout(Duration::from_millis(50), read_frame(&mut connection, 1024 * 1024));
+        assert!(idle.await.is_err());
+        send(&mut connection, Frame::window_update(1, 10_000)).await;
+        let mut received = 0;
+        while received < 10_000 {
+            let frame = next(&mut connection, 1).await;
+            assert_eq!(frame.frame_type, FrameType::Data);
+            received += frame.payload.len();
+        }
+        assert_eq!(received, 10_000);
+        let idle = tokio::time::timeout(Duration::from_millis(50), read_frame(&mut connection, 1024 * 1024));
+        assert!(idle.await.is_err());
+        send(&mut connection, Frame::reset(1, "Enough")).await;
+
+        // A put is granted no more than its size, and its trailer is checked
+        let mut put = TcpRequest::new(TcpOperation::Put, "media", "notes.txt");
+        put.size = Some(10);
+        send(&mut connection, Frame::json(FrameType::Request, 3, &put).unwrap()).await;
+        assert_eq!(next(&mut connection, 3).await.credit().unwrap(), 10);
+        send(&mut connection, Frame::new(FrameType::Data, 3, b"0123456789".to_vec())).await;
+        let forged = Trailer { size: 10, checksum: Trailer::of(b"9876543210").checksum };
+        send(&mut connection, Frame::json(FrameType::Trailer, 3, &forged).unwrap()).await;
+        let response: TcpResponse = next(&mut connection, 3).await.parse().unwrap();
+        assert_eq!(response.code.as_deref(), Some("BadDigest"));
+        assert!(storage.head("media/notes.txt").await.is_err());
+
+        // Sending past the credit resets the stream
+        send(&mut connection, Frame::json(FrameType::Request, 5, &put).unwrap()).await;
+        assert_eq!(next(&mut connection, 5).await.credit().unwrap(), 10);
+        send(&mut connection, Frame::new(FrameType::Data, 5, vec![0; 20])).await;
+        assert_eq!(next(&mut connection, 5).await.frame_type, FrameType::Reset);
+
+        // Bodies over the memory budget are refused before any credit
+        put.size = Some(8 * 1024 * 1024);
+        send(&mut connection, Frame::json(FrameType::Request, 7, &put).unwrap()).await;
+        let response: TcpResponse = next(&mut connection, 7).await.parse().unwrap();
+        assert_eq!(response.code.as_deref(), Some("InvalidRequest"));
+    }
+}
@@ -0,0 +1,496 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Read-only operator dashboard aggregation
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::cluster::{ClusterManager, ClusterStatus, NodeRole, NodeStatus, ReplicationStats};
+use crate::errors::Result;
+use crate::observability::{MetricsCollector, MetricsSummary};
+use crate::storage::{IntegrityManager, IntegrityStats, StorageBackend};
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Operator dashboard configuration
+#[derive(Debug, Clone)]
+pub struct DashboardConfig {
+    /// Capacity samples kept for growth trends
+    pub capacity_history: usize,
+    /// Recent errors kept for the error feed
+    pub error_history: usize,
+    /// Buckets returned when a caller does not ask for a limit
+    pub default_bucket_limit: usize,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        Self {
+            capacity_history: 168, // a week of hourly samples
+            error_history: 100,
+            default_bucket_limit: 10,
+        }
+    }
+}
+
+/// One node as shown on the topology view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeView {
+    pub id: String,
+    pub endpoint: String,
+    pub role: NodeRole,
+    pub status: NodeStatus,
+    pub region: Option<String>,
+    pub zone: Option<String>,
+    pub cpu_utilization: f64,
+    pub memory_utilization: f64,
+    pub storage_used: u64,
+    pub storage_available: u64,
+    pub last_heartbeat: u64,
+}
+
+/// Cluster membership and node health
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopologyView {
+    /// False when this server runs without a cluster manager
+    pub clustered: bool,
+    pub status: Option<ClusterStatus>,
+    pub total_nodes: usize,
+    pub healthy_nodes: usize,
+    /// Node counts per region, with nodes that report no region under "default"
+    pub regions: HashMap<String, usize>,
+    pub nodes: Vec<NodeView>,
+}
+
+/// Used capacity at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacitySample {
+    pub timestamp: DateTime<Utc>,
+    pub total_objects: u64,
+    pub used_bytes: u64,
+}
+
+/// Current capacity and how fast it is being consumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityView {
+    pub total_objects: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    pub utilization_percent: f64,
+    /// Average growth across the sample history; `None` until two samples exist
+    pub growth_bytes_per_day: Option<f64>,
+    /// Days until the available space runs out at the current growth rate
+    pub days_until_full: Option<f64>,
+    pub history: Vec<CapacitySample>,
+}
+
+/// Order for the top buckets list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketSort {
+    #[default]
+    Size,
+    Requests,
+}
+
+/// Size and traffic of one bucket
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BucketUsage {
+    pub bucket: String,
+    pub object_count: u64,
+    pub total_size: u64,
+    pub requests: u64,
+    pub errors: u64,
+}
+
+/// A request that failed on the server side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorEvent {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub bucket: Option<String>,
+}
+
+/// Replication health and how far replicas trail their sources
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicationView {
+    pub stats: ReplicationStats,
+    /// Age of the oldest queued replication, zero when the queue is empty
+    pub lag_seconds: u64,
+}
+
+/// Everything on the dashboard landing page in one response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardOverview {
+    pub generated_at: DateTime<Utc>,
+    pub requests: MetricsSummary,
+    pub topology: TopologyView,
+    pub capacity: CapacityView,
+    pub top_buckets: Vec<BucketUsage>,
+    pub recent_errors: Vec<ErrorEvent>,
+    /// `None` when integrity checking is not configured
+    pub integrity: Option<IntegrityStats>,
+    /// `None` when this server runs without a cluster manager
+    pub replication: Option<ReplicationView>,
+}
+
+#[derive(Debug, Default)]
+struct BucketTraffic {
+    requests: u64,
+    errors: u64,
+}
+
+/// Aggregates storage, cluster, integrity and request data for operators.
+///
+/// Every view is computed server-side from the managers this server already
+/// runs, so a dashboard can render from a handful of calls. Nothing here
+/// changes state other than the request and capacity history it records.
+pub struct OperatorDashboard {
+    storage: Arc<dyn StorageBackend>,
+    metrics: Arc<MetricsCollector>,
+    cluster: Option<Arc<ClusterManager>>,
+    integrity: Option<Arc<IntegrityManager>>,
+    config: DashboardConfig,
+    capacity_history: RwLock<VecDeque<CapacitySample>>,
+    recent_errors: RwLock<VecDeque<ErrorEvent>>,
+    bucket_traffic: RwLock<HashMap<String, BucketTraffic>>,
+}
+
+impl OperatorDashboard {
+    pub fn new(storage: Arc<dyn StorageBackend>, metrics: Arc<MetricsCollector>, config: DashboardConfig) -> Self {
+        Self {
+            storage,
+            metrics,
+            cluster: None,
+            integrity: None,
+            config,
+            capacity_history: RwLock::new(VecDeque::new()),
+            recent_errors: RwLock::new(VecDeque::new()),
+            bucket_traffic: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Report topology and replication from a cluster manager
+    pub fn with_cluster(mut self, cluster: Arc<ClusterManager>) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// Report scrub and repair status from an integrity manager
+    pub fn with_integrity(mut self, integrity: Arc<IntegrityManager>) -> Self {
+        self.integrity = Some(integrity);
+        self
+    }
+
+    /// Count an API request against its bucket and keep it if the server failed it
+    pub async fn record_request(&self, method: &str, path: &str, status: u16) {
+        let bucket = bucket_from_path(path);
+        let failed = status >= 500;
+
+        if let Some(bucket) = &bucket {
+            let mut traffic = self.bucket_traffic.write().await;
+            let entry = traffic.entry(bucket.clone()).or_default();
+            entry.requests += 1;
+            if failed {
+                entry.errors += 1;
+            }
+        }
+
+        if failed {
+            let mut errors = self.recent_errors.write().await;
+            errors.push_back(ErrorEvent {
+                timestamp: Utc::now(),
+                method: method.to_string(),
+                path: path.to_string(),
+                status,
+                bucket,
+            });
+            while errors.len() > self.config.error_history {
+                errors.pop_front();
+            }
+        }
+    }
+
+    /// Record current usage for the capacity trend
+    pub async fn sample_capacity(&self) -> Result<CapacitySample> {
+        let stats = self.storage.stats().await?;
+        let sample = CapacitySample {
+            timestamp: Utc::now(),
+            total_objects: stats.total_objects,
+            used_bytes: stats.used_space,
+        };
+
+        let mut history = self.capacity_history.write().await;
+        history.push_back(sample.clone());
+        while history.len() > self.config.capacity_history {
+            history.pop_front();
+        }
+        Ok(sample)
+    }
+
+    /// Sample capacity every `interval` until the returned task is aborted
+    pub fn spawn_sampler(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.sample_capacity().await {
+                    tracing::warn!("Failed to sample storage capacity: {}", e);
+                }
+            }
+        })
+    }
+
+    pub async fn topology(&self) -> Result<TopologyView> {
+        let Some(cluster) = &self.cluster else {
+            return Ok(TopologyView {
+                clustered: false,
+                status: None,
+                total_nodes: 0,
+                healthy_nodes: 0,
+                regions: HashMap::new(),
+                nodes: Vec::new(),
+            });
+        };
+
+        let health = cluster.get_cluster_health().await?;
+        let mut nodes: Vec<NodeView> = cluster
+            .get_nodes()
+            .await
+            .into_iter()
+            .map(|node| NodeView {
+                endpoint: node.get_endpoint(),
+                id: node.id,
+                role: node.role,
+                status: node.status,
+                region: node.region,
+                zone: node.zone,
+                cpu_utilization: node.metrics.cpu_utilization,
+                memory_utilization: node.metrics.memory_utilization,
+                storage_used: node.metrics.storage_used,
+                storage_available: node.metrics.storage_available,
+                last_heartbeat: node.last_heartbeat,
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut regions = HashMap::new();
+        for node in &nodes {
+            let region = node.region.clone().unwrap_or_else(|| "default".to_string());
+            *regions.entry(region).or_insert(0) += 1;
+        }
+
+        Ok(TopologyView {
+            clustered: true,
+            status: Some(health.cluster_status),
+            total_nodes: health.total_nodes,
+            healthy_nodes: health.healthy_nodes,
+            regions,
+            nodes,
+        })
+    }
+
+    pub async fn capacity(&self) -> Result<CapacityView> {
+        let stats = self.storage.stats().await?;
+        let history: Vec<CapacitySample> = self.capacity_history.read().await.iter().cloned().collect();
+
+        let total_bytes = stats.used_space.saturating_add(stats.available_space);
+        let utilization_percent = if total_bytes > 0 {
+            stats.used_space as f64 / total_bytes as f64 * 100.0
+        } else {
+            0.0
+        };
+        let growth_bytes_per_day = growth_per_day(&history);
+        let days_until_full = growth_bytes_per_day
+            .filter(|growth| *growth > 0.0)
+            .map(|growth| stats.available_space as f64 / growth);
+
+        Ok(CapacityView {
+            total_objects: stats.total_objects,
+            used_bytes: stats.used_space,
+            available_bytes: stats.available_space,
+            utilization_percent,
+            growth_bytes_per_day,
+            days_until_full,
+            history,
+        })
+    }
+
+    /// Largest or busiest buckets, `limit` of them.
+    ///
+    /// Sizes come from listing storage, where objects are named
+    /// `<bucket>/<key>`; objects without a bucket prefix are not counted.
+    pub async fn top_buckets(&self, sort: BucketSort, limit: Option<usize>) -> Result<Vec<BucketUsage>> {
+        let mut buckets: HashMap<String, BucketUsage> = HashMap::new();
+        for metadata in self.storage.list(None, None).await? {
+            let Some((bucket, _)) = metadata.name.split_once('/') else {
+                continue;
+            };
+            let usage = buckets.entry(bucket.to_string()).or_insert_with(|| BucketUsage {
+                bucket: bucket.to_string(),
+                ..Default::default()
+            });
+            usage.object_count += 1;
+            usage.total_size += metadata.size;
+        }
+
+        for (bucket, traffic) in self.bucket_traffic.read().await.iter() {
+            let usage = buckets.entry(bucket.clone()).or_insert_with(|| BucketUsage {
+                bucket: bucket.clone(),
+                ..Default::default()
+            });
+            usage.requests = traffic.requests;
+            usage.errors = traffic.errors;
+        }
+
+        let mut buckets: Vec<BucketUsage> = buckets.into_values().collect();
+        buckets.sort_by(|a, b| {
+            let order = match sort {
+                BucketSort::Size => b.total_size.cmp(&a.total_size),
+                BucketSort::Requests => b.requests.cmp(&a.requests),
+            };
+            order.then_with(|| a.bucket.cmp(&b.bucket))
+        });
+        buckets.truncate(limit.unwrap_or(self.config.default_bucket_limit));
+        Ok(buckets)
+    }
+
+    /// Most recent server errors, newest first
+    pub async fn recent_errors(&self, limit: Option<usize>) -> Vec<ErrorEvent> {
+        let errors = self.recent_errors.read().await;
+        errors
+            .iter()
+            .rev()
+            .take(limit.unwrap_or(self.config.error_history))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn integrity(&self) -> Result<Option<IntegrityStats>> {
+        match &self.integrity {
+            Some(integrity) => Ok(Some(integrity.get_integrity_stats().await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn replication(&self) -> Result<Option<ReplicationView>> {
+        let Some(cluster) = &self.cluster else {
+            return Ok(None);
+        };
+
+        let stats = cluster.get_distributed_storage().get_replication_stats().await?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let lag_seconds = stats
+            .oldest_pending_at
+            .map(|queued_at| now.saturating_sub(queued_at))
+            .unwrap_or(0);
+        Ok(Some(ReplicationView { stats, lag_seconds }))
+    }
+
+    pub async fn overview(&self) -> Result<DashboardOverview> {
+        Ok(DashboardOverview {
+            generated_at: Utc::now(),
+            requests: self.metrics.get_metrics_summary().await?,
+            topology: self.topology().await?,
+            capacity: self.capacity().await?,
+            top_buckets: self.top_buckets(BucketSort::Size, None).await?,
+            recent_errors: self.recent_errors(Some(self.config.default_bucket_limit)).await,
+            integrity: self.integrity().await?,
+            replication: self.replication().await?,
+        })
+    }
+}
+
+/// Bucket named by a `/api/v1/buckets/<bucket>/...` request path
+fn bucket_from_path(path: &str) -> Option<String> {
+    let rest = path.strip_prefix("/api/v1/buckets/")?;
+    let bucket = rest.split('/').next().unwrap_or_default();
+    (!bucket.is_empty()).then(|| bucket.to_string())
+}
+
+/// Average daily growth between the oldest and newest samples
+fn growth_per_day(history: &[CapacitySample]) -> Option<f64> {
+    let (first, last) = (history.first()?, history.last()?);
+    let elapsed = (last.timestamp - first.timestamp).num_seconds();
+    if elapsed <= 0 {
+        return None;
+    }
+    let growth = last.used_bytes as f64 - first.used_bytes as f64;
+    Some(growth / elapsed as f64 * SECONDS_PER_DAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryStorage, Object};
+
+    async fn dashboard_with(objects: &[(&str, usize)]) -> OperatorDashboard {
+        let storage = Arc::new(MemoryStorage::new());
+        for (name, size) in objects {
+            let object = Object::with_id(name.to_string(), name.to_string(), vec![0; *size], None);
+            storage.put(object).await.unwrap();
+        }
+        OperatorDashboard::new(storage, Arc::new(MetricsCollector::new()), DashboardConfig::default())
+    }
+
+    #[tokio::test]
+    async fn test_top_buckets_by_size_and_requests() {
+        let dashboard = dashboard_with(&[("photos/a.jpg", 300), ("photos/b.jpg", 200), ("logs/1.txt", 100), ("loose.bin", 900)]).await;
+        for _ in 0..3 {
+            dashboard.record_request("GET", "/api/v1/buckets/logs/objects", 200).await;
+        }
+        dashboard.record_request("PUT", "/api/v1/buckets/photos/objects/c.jpg", 503).await;
+
+        let by_size = dashboard.top_buckets(BucketSort::Size, None).await.unwrap();
+        assert_eq!(by_size.iter().map(|b| b.bucket.as_str()).collect::<Vec<_>>(), vec!["photos", "logs"]);
+        assert_eq!(by_size[0].total_size, 500);
+        assert_eq!(by_size[0].object_count, 2);
+        assert_eq!(by_size[0].errors, 1);
+
+        let by_requests = dashboard.top_buckets(BucketSort::Requests, Some(1)).await.unwrap();
+        assert_eq!(by_requests.len(), 1);
+        assert_eq!(by_requests[0].bucket, "logs");
+        assert_eq!(by_requests[0].requests, 3);
+    }
+
+    #[tokio::test]
+    async fn test_recent_errors_are_bounded_and_newest_first() {
+        let mut dashboard = dashboard_with(&[]).await;
+        dashboard.config.error_history = 2;
+        dashboard.record_request("GET", "/health", 200).await;
+        dashboard.record_request("GET", "/api/v1/buckets/a/objects", 500).await;
+        dashboard.record_request("GET", "/api/v1/buckets/b/objects", 502).await;
+        dashboard.record_request("GET", "/status", 503).await;
+
+        let errors = dashboard.recent_errors(None).await;
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].path, "/status");
+        assert_eq!(errors[0].bucket, None);
+        assert_eq!(errors[1].bucket.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_growth_per_day() {
+        let start = Utc::now();
+        let sample = |hours: i64, used_bytes: u64| CapacitySample {
+            timestamp: start + chrono::Duration::hours(hours),
+            total_objects: 0,
+            used_bytes,
+        };
+
+        assert_eq!(growth_per_day(&[sample(0, 100)]), None);
+        assert_eq!(growth_per_day(&[sample(0, 1_000), sample(12, 1_500)]), Some(1_000.0));
+        assert_eq!(growth_per_day(&[sample(0, 1_000), sample(24, 400)]), Some(-600.0));
+    }
+}
@@ -251,8 +251,8 @@ impl MetricsCollector {
             metric_type,
         };
 
-        metrics.custom_metrics.insert(name, metric_point);
         debug!("Added custom metric: {}", name);
+        metrics.custom_metrics.insert(name, metric_point);
         Ok(())
     }
 
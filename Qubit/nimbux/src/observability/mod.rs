@@ -8,10 +8,14 @@
 pub mod metrics;
 pub mod analytics;
 pub mod logging;
+pub mod dashboard;
 
 // Re-export commonly used types
 pub use metrics::{
     MetricsCollector, NimbuxMetrics, MetricsSummary, MetricType, 
     MetricPoint, HistogramData
 };
-pub use analytics::{RealtimeAnalytics, AnalyticsConfig, IntegrityReport, IntegrityStats, Dashboard, Widget, AnalyticsInsight};
\ No newline at end of file
+pub use analytics::{RealtimeAnalytics, AnalyticsConfig, IntegrityReport, IntegrityStats, Dashboard, Widget, AnalyticsInsight};pub use dashboard::{
+    OperatorDashboard, DashboardConfig, DashboardOverview, TopologyView, NodeView,
+    CapacityView, CapacitySample, BucketUsage, BucketSort, ErrorEvent, ReplicationView
+};
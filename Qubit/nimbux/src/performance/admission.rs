@@ -0,0 +1,504 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Admission control and per-tenant rate limiting
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::errors::{NimbuxError, Result};
+
+/// Request and bandwidth limits of one tenant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantLimits {
+    /// Sustained requests per second; none is unlimited
+    pub requests_per_second: Option<f64>,
+    /// Requests a tenant may make at once on top of its rate
+    pub request_burst: u32,
+    /// Sustained request and response body bytes per second; none is unlimited
+    pub bytes_per_second: Option<u64>,
+    pub byte_burst: u64,
+    /// Requests of the tenant in progress at once; 0 is unlimited
+    pub max_concurrent: u32,
+}
+
+impl Default for TenantLimits {
+    fn default() -> Self {
+        Self {
+            requests_per_second: None,
+            request_burst: 100,
+            bytes_per_second: None,
+            byte_burst: 64 * 1024 * 1024, // 64MB
+            max_concurrent: 0,
+        }
+    }
+}
+
+/// Admission control configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmissionConfig {
+    /// Limits of tenants without limits of their own
+    pub default_limits: TenantLimits,
+    /// Requests in progress across all tenants before new ones are shed; 0 is unlimited
+    pub max_in_flight: usize,
+    /// Tenants idle this long are forgotten, coming back with full buckets
+    pub idle_timeout: u64, // seconds
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            default_limits: TenantLimits::default(),
+            max_in_flight: 0,
+            idle_timeout: 600, // 10 minutes
+        }
+    }
+}
+
+/// Tokens refilled at a steady rate up to a capacity
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, capacity: f64, now: Instant) -> Self {
+        let capacity = capacity.max(1.0);
+        Self { rate, capacity, tokens: capacity, refilled_at: now }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now > self.refilled_at {
+            let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+            self.refilled_at = now;
+        }
+    }
+
+    /// Take `amount` tokens, or say how long until there are enough. More
+    /// than the capacity only needs a full bucket and leaves it in debt,
+    /// which later takes wait off.
+    fn take(&mut self, amount: f64, now: Instant) -> std::result::Result<(), Duration> {
+        self.refill(now);
+        let needed = amount.min(self.capacity);
+        if self.tokens < needed {
+            return Err(Duration::from_secs_f64((needed - self.tokens) / self.rate));
+        }
+        self.tokens -= amount;
+        Ok(())
+    }
+
+    /// Take tokens for work already done, going into debt if need be
+    fn charge(&mut self, amount: f64, now: Instant) {
+        self.refill(now);
+        self.tokens -= amount;
+    }
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ThrottleReason {
+    RequestRate,
+    Bandwidth,
+    Concurrency,
+    Overload,
+}
+
+/// Throttling counters of one tenant
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TenantThrottleStats {
+    pub tenant: String,
+    pub admitted: u64,
+    pub throttled_requests: u64,
+    pub throttled_bandwidth: u64,
+    pub throttled_concurrency: u64,
+    /// Body bytes charged against the tenant's bandwidth
+    pub bytes: u64,
+    pub in_progress: u32,
+}
+
+/// Throttling counters across tenants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmissionMetrics {
+    pub in_flight: usize,
+    pub admitted: u64,
+    pub throttled: u64,
+    /// Requests refused because the server as a whole was at `max_in_flight`
+    pub shed: u64,
+    /// Tenants seen since they were last idle, most throttled first
+    pub tenants: Vec<TenantThrottleStats>,
+}
+
+struct TenantState {
+    limits: TenantLimits,
+    requests: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+    in_progress: u32,
+    last_seen: Instant,
+    stats: TenantThrottleStats,
+}
+
+impl TenantState {
+    fn new(tenant: &str, limits: TenantLimits, now: Instant) -> Self {
+        Self {
+            requests: limits
+                .requests_per_second
+                .map(|rate| TokenBucket::new(rate, f64::from(limits.request_burst), now)),
+            bytes: limits
+                .bytes_per_second
+                .map(|rate| TokenBucket::new(rate as f64, limits.byte_burst as f64, now)),
+            limits,
+            in_progress: 0,
+            last_seen: now,
+            stats: TenantThrottleStats { tenant: tenant.to_string(), ..Default::default() },
+        }
+    }
+
+    fn admit(&mut self, bytes: u64, now: Instant) -> std::result::Result<(), (ThrottleReason, Duration)> {
+        self.last_seen = now;
+        if self.limits.max_concurrent > 0 && self.in_progress >= self.limits.max_concurrent {
+            return Err((ThrottleReason::Concurrency, Duration::from_secs(1)));
+        }
+        // Check bandwidth before taking a request token, so a refusal costs nothing
+        if let Some(bucket) = &mut self.bytes {
+            bucket.refill(now);
+            let needed = (bytes as f64).min(bucket.capacity);
+            if bucket.tokens < needed {
+                let wait = (needed - bucket.tokens) / bucket.rate;
+                return Err((ThrottleReason::Bandwidth, Duration::from_secs_f64(wait)));
+            }
+        }
+        if let Some(bucket) = &mut self.requests {
+            bucket.take(1.0, now).map_err(|wait| (ThrottleReason::RequestRate, wait))?;
+        }
+        if let Some(bucket) = &mut self.bytes {
+            bucket.charge(bytes as f64, now);
+        }
+        self.stats.bytes += bytes;
+        self.stats.admitted += 1;
+        self.in_progress += 1;
+        Ok(())
+    }
+}
+
+/// Admission controller enforcing per-tenant request and bandwidth limits.
+///
+/// Tenants are access keys, or client IPs for unsigned requests. Each has a
+/// request token bucket and a bandwidth token bucket, refilled at its
+/// configured rates; bodies larger than the bandwidth burst are let through
+/// on a full bucket and paid off by waiting afterwards. Response bodies are
+/// charged once they are known, so a tenant downloading heavily is slowed
+/// down on its next requests. Refused requests get a retry delay clients
+/// should back off for.
+pub struct AdmissionController {
+    config: AdmissionConfig,
+    overrides: Mutex<HashMap<String, TenantLimits>>,
+    tenants: Mutex<HashMap<String, TenantState>>,
+    in_flight: AtomicUsize,
+    admitted: AtomicU64,
+    throttled: AtomicU64,
+    shed: AtomicU64,
+}
+
+impl AdmissionController {
+    pub fn new(config: AdmissionConfig) -> Self {
+        Self {
+            config,
+            overrides: Mutex::new(HashMap::new()),
+            tenants: Mutex::new(HashMap::new()),
+            in_flight: AtomicUsize::new(0),
+            admitted: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            shed: AtomicU64::new(0),
+        }
+    }
+
+    /// Admit a request of `tenant` with a body of `bytes`, or refuse it with
+    /// `NimbuxError::Throttled`. The tenant's request counts as in progress
+    /// until the permit is dropped.
+    pub fn admit(self: &Arc<Self>, tenant: &str, bytes: u64) -> Result<AdmissionPermit> {
+        self.admit_at(tenant, bytes, Instant::now())
+    }
+
+    fn admit_at(self: &Arc<Self>, tenant: &str, bytes: u64, now: Instant) -> Result<AdmissionPermit> {
+        let max_in_flight = self.config.max_in_flight;
+        if max_in_flight > 0 && self.in_flight.load(Ordering::Acquire) >= max_in_flight {
+            self.shed.fetch_add(1, Ordering::Relaxed);
+            return Err(self.refusal(tenant, ThrottleReason::Overload, Duration::from_secs(1)));
+        }
+
+        let limits = self.limits(tenant);
+        let mut tenants = self.tenants.lock().unwrap();
+        let state = tenants
+            .entry(tenant.to_string())
+            .or_insert_with(|| TenantState::new(tenant, limits, now));
+        if let Err((reason, wait)) = state.admit(bytes, now) {
+            match reason {
+                ThrottleReason::RequestRate => state.stats.throttled_requests += 1,
+                ThrottleReason::Bandwidth => state.stats.throttled_bandwidth += 1,
+                ThrottleReason::Concurrency => state.stats.throttled_concurrency += 1,
+                ThrottleReason::Overload => {}
+            }
+            drop(tenants);
+            return Err(self.refusal(tenant, reason, wait));
+        }
+        drop(tenants);
+
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        self.admitted.fetch_add(1, Ordering::Relaxed);
+        Ok(AdmissionPermit { controller: Arc::clone(self), tenant: tenant.to_string() })
+    }
+
+    fn refusal(&self, tenant: &str, reason: ThrottleReason, wait: Duration) -> NimbuxError {
+        self.throttled.fetch_add(1, Ordering::Relaxed);
+        let reason = match reason {
+            ThrottleReason::RequestRate => format!("{} is over its request rate", tenant),
+            ThrottleReason::Bandwidth => format!("{} is over its bandwidth", tenant),
+            ThrottleReason::Concurrency => format!("{} has too many requests in progress", tenant),
+            ThrottleReason::Overload => "The server has too many requests in progress".to_string(),
+        };
+        NimbuxError::Throttled { reason, retry_after_ms: (wait.as_millis() as u64).max(1) }
+    }
+
+    /// Charge body bytes known only after the request was admitted
+    pub fn charge(&self, tenant: &str, bytes: u64) {
+        let now = Instant::now();
+        let mut tenants = self.tenants.lock().unwrap();
+        if let Some(state) = tenants.get_mut(tenant) {
+            if let Some(bucket) = &mut state.bytes {
+                bucket.charge(bytes as f64, now);
+            }
+            state.stats.bytes += bytes;
+        }
+    }
+
+    pub fn limits(&self, tenant: &str) -> TenantLimits {
+        self.overrides
+            .lock()
+            .unwrap()
+            .get(tenant)
+            .cloned()
+            .unwrap_or_else(|| self.config.default_limits.clone())
+    }
+
+    /// Give a tenant limits of its own, starting it over with full buckets
+    pub fn set_limits(&self, tenant: &str, limits: TenantLimits) -> Result<()> {
+        if limits.requests_per_second.is_some_and(|rate| !(rate > 0.0 && rate.is_finite()))
+            || limits.bytes_per_second == Some(0)
+        {
+            return Err(NimbuxError::InvalidRequest("Rates must be positive".to_string()));
+        }
+        self.overrides.lock().unwrap().insert(tenant.to_string(), limits.clone());
+        self.reset(tenant, limits);
+        Ok(())
+    }
+
+    /// Put a tenant back on the default limits
+    pub fn remove_limits(&self, tenant: &str) -> bool {
+        let removed = self.overrides.lock().unwrap().remove(tenant).is_some();
+        if removed {
+            self.reset(tenant, self.config.default_limits.clone());
+        }
+        removed
+    }
+
+    fn reset(&self, tenant: &str, limits: TenantLimits) {
+        let mut tenants = self.tenants.lock().unwrap();
+        if let Some(state) = tenants.get_mut(tenant) {
+            let mut fresh = TenantState::new(tenant, limits, Instant::now());
+            fresh.in_progress = state.in_progress;
+            fresh.stats = std::mem::take(&mut state.stats);
+            *state = fresh;
+        }
+    }
+
+    pub fn overrides(&self) -> HashMap<String, TenantLimits> {
+        self.overrides.lock().unwrap().clone()
+    }
+
+    pub fn metrics(&self) -> AdmissionMetrics {
+        let mut tenants: Vec<_> = self
+            .tenants
+            .lock()
+            .unwrap()
+            .values()
+            .map(|state| TenantThrottleStats { in_progress: state.in_progress, ..state.stats.clone() })
+            .collect();
+        tenants.sort_by_key(|stats| {
+            std::cmp::Reverse(stats.throttled_requests + stats.throttled_bandwidth + stats.throttled_concurrency)
+        });
+        AdmissionMetrics {
+            in_flight: self.in_flight.load(Ordering::Acquire),
+            admitted: self.admitted.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            shed: self.shed.load(Ordering::Relaxed),
+            tenants,
+        }
+    }
+
+    /// Forget tenants idle longer than the idle timeout
+    pub fn prune(&self) -> usize {
+        let idle = Duration::from_secs(self.config.idle_timeout);
+        let now = Instant::now();
+        let mut tenants = self.tenants.lock().unwrap();
+        let before = tenants.len();
+        tenants.retain(|_, state| state.in_progress > 0 || now.saturating_duration_since(state.last_seen) < idle);
+        before - tenants.len()
+    }
+
+    /// Prune idle tenants every `interval` until the returned task is aborted
+    pub fn spawn_pruner(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let pruned = self.prune();
+                if pruned > 0 {
+                    tracing::debug!("Forgot {} idle tenants", pruned);
+                }
+            }
+        })
+    }
+
+    fn release(&self, tenant: &str) {
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+        if let Some(state) = self.tenants.lock().unwrap().get_mut(tenant) {
+            state.in_progress = state.in_progress.saturating_sub(1);
+        }
+    }
+}
+
+/// An admitted request, in progress until dropped
+pub struct AdmissionPermit {
+    controller: Arc<AdmissionController>,
+    tenant: String,
+}
+
+impl AdmissionPermit {
+    pub fn tenant(&self) -> &str {
+        &self.tenant
+    }
+
+    /// Charge the response body against the tenant's bandwidth
+    pub fn charge(&self, bytes: u64) {
+        self.controller.charge(&self.tenant, bytes);
+    }
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.controller.release(&self.tenant);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(limits: TenantLimits, max_in_flight: usize) -> Arc<AdmissionController> {
+        Arc::new(AdmissionController::new(AdmissionConfig {
+            default_limits: limits,
+            max_in_flight,
+            ..Default::default()
+        }))
+    }
+
+    fn retry_after(result: Result<AdmissionPermit>) -> u64 {
+        match result {
+            Err(NimbuxError::Throttled { retry_after_ms, .. }) => retry_after_ms,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("request was admitted"),
+        }
+    }
+
+    #[test]
+    fn test_request_rate_is_limited_per_tenant() {
+        let admission = controller(
+            TenantLimits { requests_per_second: Some(10.0), request_burst: 2, ..Default::default() },
+            0,
+        );
+        let now = Instant::now();
+
+        assert!(admission.admit_at("alice", 0, now).is_ok());
+        assert!(admission.admit_at("alice", 0, now).is_ok());
+        assert_eq!(retry_after(admission.admit_at("alice", 0, now)), 100);
+        // Tenants have buckets of their own
+        assert!(admission.admit_at("bob", 0, now).is_ok());
+        // And the bucket refills at the rate
+        assert!(admission.admit_at("alice", 0, now + Duration::from_millis(100)).is_ok());
+
+        let metrics = admission.metrics();
+        assert_eq!(metrics.admitted, 4);
+        assert_eq!(metrics.throttled, 1);
+        assert_eq!(metrics.tenants[0].tenant, "alice");
+        assert_eq!(metrics.tenants[0].throttled_requests, 1);
+    }
+
+    #[test]
+    fn test_large_bodies_put_the_tenant_in_debt() {
+        let admission = controller(
+            TenantLimits { bytes_per_second: Some(1000), byte_burst: 1000, ..Default::default() },
+            0,
+        );
+        let now = Instant::now();
+
+        // Larger than the burst, admitted on a full bucket
+        assert!(admission.admit_at("alice", 3000, now).is_ok());
+        assert_eq!(retry_after(admission.admit_at("alice", 0, now)), 2000);
+        assert!(admission.admit_at("alice", 0, now + Duration::from_secs(2)).is_ok());
+
+        admission.charge("alice", 500);
+        assert!(admission.admit_at("alice", 10, now + Duration::from_secs(2)).is_err());
+        assert_eq!(admission.metrics().tenants[0].bytes, 3500);
+    }
+
+    #[test]
+    fn test_permits_hold_concurrency_until_dropped() {
+        let admission = controller(TenantLimits { max_concurrent: 1, ..Default::default() }, 2);
+        let now = Instant::now();
+
+        let first = admission.admit_at("alice", 0, now).unwrap();
+        assert!(admission.admit_at("alice", 0, now).is_err());
+        let second = admission.admit_at("bob", 0, now).unwrap();
+        // The server is full whoever asks
+        assert!(admission.admit_at("carol", 0, now).is_err());
+        assert_eq!(admission.metrics().shed, 1);
+
+        drop(first);
+        drop(second);
+        assert!(admission.admit_at("alice", 0, now).is_ok());
+        assert_eq!(admission.metrics().in_flight, 0);
+    }
+
+    #[test]
+    fn test_tenant_limits_override_the_defaults() {
+        let admission = controller(
+            TenantLimits { requests_per_second: Some(1.0), request_burst: 1, ..Default::default() },
+            0,
+        );
+        let now = Instant::now();
+        admission
+            .set_limits("batch", TenantLimits { requests_per_second: Some(100.0), request_burst: 3, ..Default::default() })
+            .unwrap();
+
+        assert!(admission.admit_at("batch", 0, now).is_ok());
+        assert!(admission.admit_at("batch", 0, now).is_ok());
+        assert!(admission.admit_at("other", 0, now).is_ok());
+        assert!(admission.admit_at("other", 0, now).is_err());
+        assert!(admission
+            .set_limits("bad", TenantLimits { requests_per_second: Some(0.0), ..Default::default() })
+            .is_err());
+
+        assert!(admission.remove_limits("batch"));
+        assert_eq!(admission.limits("batch").request_burst, 1);
+    }
+}
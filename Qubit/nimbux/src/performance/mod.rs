@@ -20,6 +20,7 @@ pub mod caching;
 pub mod batching;
 pub mod compression;
 pub mod metrics;
+pub mod admission;
 
 // Re-export commonly used types
 pub use connection_pool::{ConnectionPool, PoolConfig, PoolStats};
@@ -28,6 +29,7 @@ pub use caching::{Cache, CacheConfig, CacheStats, CachePolicy};
 pub use batching::{BatchProcessor, BatchConfig, BatchStats, BatchOperation};
 pub use compression::{CompressionEngine, CompressionConfig, CompressionStats};
 pub use metrics::{PerformanceMetrics, MetricsCollector, LatencyTracker};
+pub use admission::{AdmissionController, AdmissionConfig, AdmissionMetrics, AdmissionPermit, TenantLimits, TenantThrottleStats};
 
 /// Performance configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
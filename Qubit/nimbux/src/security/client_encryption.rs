@@ -0,0 +1,323 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Client-side envelope encryption of object bodies
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{NimbuxError, Result};
+use crate::network::s3::store::USER_METADATA_PREFIX;
+use crate::storage::ENCRYPTION_TAG;
+
+/// User metadata carrying the envelope of a client-side encrypted object,
+/// named as the S3 encryption client names it. Objects with
+/// `CSE_CONTENT_ALGORITHM_METADATA` are client-side encrypted.
+pub const CSE_KEY_METADATA: &str = "x-amz-key-v2";
+pub const CSE_IV_METADATA: &str = "x-amz-iv";
+pub const CSE_CONTENT_ALGORITHM_METADATA: &str = "x-amz-cek-alg";
+pub const CSE_WRAP_ALGORITHM_METADATA: &str = "x-amz-wrap-alg";
+/// JSON naming the wrapping key, so the key wrapper knows which one unwraps
+pub const CSE_MATERIAL_METADATA: &str = "x-amz-matdesc";
+pub const CSE_PLAINTEXT_SIZE_METADATA: &str = "x-amz-unencrypted-content-length";
+
+/// The one content algorithm: AES-256-GCM with a 96-bit IV and the tag
+/// appended to the ciphertext
+pub const CSE_CONTENT_ALGORITHM: &str = "AES/GCM/NoPadding";
+
+const DATA_KEY_LEN: usize = 32;
+const IV_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// A data key wrapped by a customer key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrappedKey {
+    /// Customer key that wrapped it
+    pub key_id: String,
+    /// How it was wrapped, e.g. `kms` or `AES/GCM`
+    pub algorithm: String,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Wraps and unwraps per-object data keys with keys the customer holds,
+/// usually by calling their KMS. Nimbux never sees the customer keys or
+/// unwrapped data keys.
+#[async_trait]
+pub trait KeyWrapper: Send + Sync {
+    async fn wrap(&self, data_key: &[u8]) -> Result<WrappedKey>;
+
+    async fn unwrap(&self, wrapped: &WrappedKey) -> Result<Vec<u8>>;
+}
+
+/// Wraps data keys with an AES-256 key held by the client, for deployments
+/// without a KMS and for tests
+pub struct LocalKeyWrapper {
+    key_id: String,
+    cipher: Aes256Gcm,
+}
+
+impl LocalKeyWrapper {
+    pub const ALGORITHM: &'static str = "AES/GCM";
+
+    pub fn new(key_id: impl Into<String>, key: &[u8; DATA_KEY_LEN]) -> Self {
+        Self { key_id: key_id.into(), cipher: Aes256Gcm::new(key.into()) }
+    }
+}
+
+#[async_trait]
+impl KeyWrapper for LocalKeyWrapper {
+    async fn wrap(&self, data_key: &[u8]) -> Result<WrappedKey> {
+        let iv = random_bytes::<IV_LEN>();
+        let mut ciphertext = iv.to_vec();
+        ciphertext.extend(
+            self.cipher
+                .encrypt(Nonce::from_slice(&iv), data_key)
+                .map_err(|_| NimbuxError::Internal("Failed to wrap data key".to_string()))?,
+        );
+        Ok(WrappedKey { key_id: self.key_id.clone(), algorithm: Self::ALGORITHM.to_string(), ciphertext })
+    }
+
+    async fn unwrap(&self, wrapped: &WrappedKey) -> Result<Vec<u8>> {
+        if wrapped.key_id != self.key_id || wrapped.algorithm != Self::ALGORITHM {
+            return Err(NimbuxError::Authorization(format!(
+                "Data key was wrapped by {} with {}, not by {}",
+                wrapped.key_id, wrapped.algorithm, self.key_id
+            )));
+        }
+        if wrapped.ciphertext.len() < IV_LEN {
+            return Err(NimbuxError::InvalidRequest("Wrapped data key is truncated".to_string()));
+        }
+        let (iv, ciphertext) = wrapped.ciphertext.split_at(IV_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(iv), ciphertext)
+            .map_err(|_| NimbuxError::Authorization("Data key does not unwrap with this key".to_string()))
+    }
+}
+
+/// How an object's body is encrypted, read from its tags. A client-side
+/// encrypted object can also be encrypted at rest by the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectEncryption {
+    /// `SSE-S3`, `SSE-KMS` or `SSE-C`
+    pub server_side: Option<String>,
+    /// Content algorithm of a body the client encrypted before upload
+    pub client_side: Option<String>,
+}
+
+impl ObjectEncryption {
+    pub fn of(tags: &HashMap<String, String>) -> Self {
+        Self {
+            server_side: tags.get(ENCRYPTION_TAG).cloned(),
+            client_side: tags.get(&metadata_tag(CSE_CONTENT_ALGORITHM_METADATA)).cloned(),
+        }
+    }
+
+    pub fn is_client_side(&self) -> bool {
+        self.client_side.is_some()
+    }
+}
+
+/// Body encrypted for upload and the metadata to store with it, by user
+/// metadata name without `x-amz-meta-`
+#[derive(Debug, Clone)]
+pub struct EncryptedBody {
+    pub ciphertext: Vec<u8>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Names the wrapping key in `CSE_MATERIAL_METADATA`
+#[derive(Serialize, Deserialize)]
+struct MaterialDescription {
+    key_id: String,
+}
+
+/// Envelope encryption of object bodies before they leave the client.
+///
+/// Each object gets its own random data key. The body is sealed with it
+/// and the data key is wrapped by the `KeyWrapper`, then stored wrapped in
+/// the object's metadata, so reading the object back needs the customer's
+/// key as well as access to Nimbux.
+///
+/// The body is sealed for its bucket and key and the customer key that
+/// wrapped its data key, so a body copied or moved to another object, or
+/// paired with another object's metadata, no longer decrypts.
+#[derive(Clone)]
+pub struct ClientEncryption {
+    wrapper: Arc<dyn KeyWrapper>,
+}
+
+impl ClientEncryption {
+    pub fn new(wrapper: Arc<dyn KeyWrapper>) -> Self {
+        Self { wrapper }
+    }
+
+    /// Encrypt the body of the object `key` in `bucket`
+    pub async fn encrypt(&self, bucket: &str, key: &str, plaintext: &[u8]) -> Result<EncryptedBody> {
+        let data_key = random_bytes::<DATA_KEY_LEN>();
+        let iv = random_bytes::<IV_LEN>();
+        let wrapped = self.wrapper.wrap(&data_key).await?;
+        let aad = associated_data(bucket, key, &wrapped.key_id);
+        let ciphertext = Aes256Gcm::new((&data_key).into())
+            .encrypt(Nonce::from_slice(&iv), Payload { msg: plaintext, aad: &aad })
+            .map_err(|_| NimbuxError::Internal("Failed to encrypt object body".to_string()))?;
+
+        let material = MaterialDescription { key_id: wrapped.key_id };
+        let metadata = BTreeMap::from([
+            (CSE_KEY_METADATA.to_string(), STANDARD.encode(&wrapped.ciphertext)),
+            (CSE_IV_METADATA.to_string(), STANDARD.encode(iv)),
+            (CSE_CONTENT_ALGORITHM_METADATA.to_string(), CSE_CONTENT_ALGORITHM.to_string()),
+            (CSE_WRAP_ALGORITHM_METADATA.to_string(), wrapped.algorithm),
+            (CSE_MATERIAL_METADATA.to_string(), serde_json::to_string(&material)?),
+            (CSE_PLAINTEXT_SIZE_METADATA.to_string(), plaintext.len().to_string()),
+        ]);
+        Ok(EncryptedBody { ciphertext, metadata })
+    }
+
+    /// Decrypt the body of the object `key` in `bucket` with the given tags.
+    /// The whole body is needed, as the tag at its end authenticates all of it.
+    pub async fn decrypt(&self, bucket: &str, key: &str, tags: &HashMap<String, String>, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let field = |name: &str| {
+            tags.get(&metadata_tag(name))
+                .ok_or_else(|| NimbuxError::InvalidRequest(format!("Client-side encrypted object has no {} metadata", name)))
+        };
+        let algorithm = field(CSE_CONTENT_ALGORITHM_METADATA)?;
+        if algorithm != CSE_CONTENT_ALGORITHM {
+            return Err(NimbuxError::InvalidRequest(format!("Unsupported content algorithm {}", algorithm)));
+        }
+        let decode = |name: &str| {
+            STANDARD
+                .decode(field(name)?)
+                .map_err(|e| NimbuxError::InvalidRequest(format!("Invalid {} metadata: {}", name, e)))
+        };
+        let iv = decode(CSE_IV_METADATA)?;
+        if iv.len() != IV_LEN || ciphertext.len() < TAG_LEN {
+            return Err(NimbuxError::InvalidRequest("Client-side encrypted body or its IV is truncated".to_string()));
+        }
+        let material: MaterialDescription = serde_json::from_str(field(CSE_MATERIAL_METADATA)?)?;
+        let wrapped = WrappedKey {
+            key_id: material.key_id,
+            algorithm: field(CSE_WRAP_ALGORITHM_METADATA)?.clone(),
+            ciphertext: decode(CSE_KEY_METADATA)?,
+        };
+
+        let aad = associated_data(bucket, key, &wrapped.key_id);
+        let data_key = self.wrapper.unwrap(&wrapped).await?;
+        if data_key.len() != DATA_KEY_LEN {
+            return Err(NimbuxError::InvalidRequest("Unwrapped data key is not an AES-256 key".to_string()));
+        }
+        Aes256Gcm::new(data_key.as_slice().into())
+            .decrypt(Nonce::from_slice(&iv), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| NimbuxError::InvalidRequest("Client-side encrypted body was altered or does not match its metadata".to_string()))
+    }
+}
+
+/// Additional data a body is sealed with: its bucket, its key and the
+/// customer key that wrapped its data key, each prefixed with its length
+fn associated_data(bucket: &str, key: &str, key_id: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(24 + bucket.len() + key.len() + key_id.len());
+    for part in [bucket, key, key_id] {
+        aad.extend_from_slice(&(part.len() as u64).to_be_bytes());
+        aad.extend_from_slice(part.as_bytes());
+    }
+    aad
+}
+
+/// Tag a user metadata name is stored under
+fn metadata_tag(name: &str) -> String {
+    format!("{}{}", USER_METADATA_PREFIX, name)
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encryption(key_id: &str, key: u8) -> ClientEncryption {
+        ClientEncryption::new(Arc::new(LocalKeyWrapper::new(key_id, &[key; 32])))
+    }
+
+    /// Metadata as the server stores it, under `x-amz-meta-`
+    fn tags(body: &EncryptedBody) -> HashMap<String, String> {
+        body.metadata.iter().map(|(name, value)| (metadata_tag(name), value.clone())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_with_a_fresh_data_key_per_object() {
+        let encryption = encryption("customer-1", 7);
+        let first = encryption.encrypt("media", "photo.jpg", b"private photo").await.unwrap();
+        let second = encryption.encrypt("media", "photo.jpg", b"private photo").await.unwrap();
+        assert_ne!(first.ciphertext, second.ciphertext);
+        assert_ne!(first.metadata[CSE_KEY_METADATA], second.metadata[CSE_KEY_METADATA]);
+        assert_eq!(first.ciphertext.len(), b"private photo".len() + TAG_LEN);
+        assert_eq!(first.metadata[CSE_PLAINTEXT_SIZE_METADATA], "13");
+        assert_eq!(first.metadata[CSE_MATERIAL_METADATA], r#"{"key_id":"customer-1"}"#);
+
+        let plaintext = encryption.decrypt("media", "photo.jpg", &tags(&first), &first.ciphertext).await.unwrap();
+        assert_eq!(plaintext, b"private photo");
+    }
+
+    #[tokio::test]
+    async fn test_other_keys_and_tampered_bodies_are_refused() {
+        let body = encryption("customer-1", 7).encrypt("media", "photo.jpg", b"private photo").await.unwrap();
+        let tags = tags(&body);
+
+        let wrong_key = encryption("customer-1", 8).decrypt("media", "photo.jpg", &tags, &body.ciphertext).await;
+        assert!(matches!(wrong_key, Err(NimbuxError::Authorization(_))));
+        let wrong_id = encryption("customer-2", 7).decrypt("media", "photo.jpg", &tags, &body.ciphertext).await;
+        assert!(matches!(wrong_id, Err(NimbuxError::Authorization(_))));
+
+        let mut tampered = body.ciphertext.clone();
+        tampered[0] ^= 1;
+        let result = encryption("customer-1", 7).decrypt("media", "photo.jpg", &tags, &tampered).await;
+        assert!(matches!(result, Err(NimbuxError::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_bodies_are_bound_to_their_object_and_wrapping_key() {
+        let encryption = encryption("customer-1", 7);
+        let photo = encryption.encrypt("media", "photo.jpg", b"private photo").await.unwrap();
+        let other = encryption.encrypt("media", "other.jpg", b"other photo").await.unwrap();
+
+        // Moved to another key or bucket
+        for (bucket, key) in [("media", "other.jpg"), ("backup", "photo.jpg")] {
+            let moved = encryption.decrypt(bucket, key, &tags(&photo), &photo.ciphertext).await;
+            assert!(matches!(moved, Err(NimbuxError::InvalidRequest(_))), "{}/{}", bucket, key);
+        }
+        // Swapped with another object's body under the same customer key
+        let swapped = encryption.decrypt("media", "photo.jpg", &tags(&photo), &other.ciphertext).await;
+        assert!(matches!(swapped, Err(NimbuxError::InvalidRequest(_))));
+        // The same AAD parts split differently do not collide
+        assert_ne!(associated_data("ab", "c", "k"), associated_data("a", "bc", "k"));
+    }
+
+    #[tokio::test]
+    async fn test_encryption_is_read_from_tags() {
+        let body = encryption("customer-1", 7).encrypt("media", "x", b"x").await.unwrap();
+        let mut tags = tags(&body);
+        assert_eq!(
+            ObjectEncryption::of(&tags),
+            ObjectEncryption { server_side: None, client_side: Some(CSE_CONTENT_ALGORITHM.to_string()) }
+        );
+
+        tags.insert(ENCRYPTION_TAG.to_string(), "SSE-S3".to_string());
+        assert!(ObjectEncryption::of(&tags).is_client_side());
+        assert_eq!(ObjectEncryption::of(&tags).server_side.as_deref(), Some("SSE-S3"));
+        assert_eq!(ObjectEncryption::of(&HashMap::new()), ObjectEncryption::default());
+    }
+}
@@ -0,0 +1,196 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Compliance checks and reports over object lock retention and legal holds
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use super::{ComplianceStandard, ComplianceStatus};
+use crate::errors::Result;
+use crate::storage::{ObjectLock, ObjectLockReport, StorageBackend, StorageEngine};
+
+/// Compliance configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceConfig {
+    pub enable_compliance: bool,
+    pub standards: Vec<ComplianceStandard>,
+    pub enable_monitoring: bool,
+    pub enable_reporting: bool,
+}
+
+/// Compliance checks and reports since startup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComplianceStats {
+    pub checks: u64,
+    pub violations: u64,
+    pub reports: u64,
+    pub last_report_at: Option<DateTime<Utc>>,
+}
+
+/// Retention and legal hold state of the stored objects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    pub generated_at: DateTime<Utc>,
+    pub standards: Vec<ComplianceStandard>,
+    pub compliant: bool,
+    pub violations: Vec<String>,
+    pub object_lock: ObjectLockReport,
+}
+
+/// Checks objects against their buckets' retention settings.
+///
+/// Without a storage engine every object is reported compliant and reports
+/// are empty.
+pub struct ComplianceManager {
+    config: ComplianceConfig,
+    storage: Option<Arc<StorageEngine>>,
+    running: AtomicBool,
+    stats: RwLock<ComplianceStats>,
+}
+
+impl ComplianceManager {
+    pub fn new(config: ComplianceConfig) -> Result<Self> {
+        Ok(Self {
+            config,
+            storage: None,
+            running: AtomicBool::new(false),
+            stats: RwLock::new(ComplianceStats::default()),
+        })
+    }
+
+    /// Check objects and build reports from the engine's object lock state
+    pub fn with_storage(mut self, storage: Arc<StorageEngine>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn config(&self) -> &ComplianceConfig {
+        &self.config
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        self.running.store(true, Ordering::Relaxed);
+        tracing::info!("Compliance manager started for {:?}", self.config.standards);
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<()> {
+        self.running.store(false, Ordering::Relaxed);
+        tracing::info!("Compliance manager stopped");
+        Ok(())
+    }
+
+    /// An object is out of compliance when its bucket retains new objects
+    /// but it has never had a retention
+    pub async fn check_compliance(&self, object_id: &str) -> Result<ComplianceStatus> {
+        let mut violations = Vec::new();
+        if let Some(storage) = &self.storage {
+            let metadata = storage.head(object_id).await?;
+            let bucket = object_id.split_once('/').map(|(bucket, _)| bucket).unwrap_or_default();
+            let default_retention = storage.bucket_object_lock(bucket).await.default_retention;
+            if let (Some(default), None) = (default_retention, ObjectLock::of(&metadata).retention) {
+                violations.push(format!(
+                    "{} has no retention, but bucket {} retains new objects for {} days in {} mode",
+                    object_id, bucket, default.days, default.mode
+                ));
+            }
+        }
+
+        let mut stats = self.stats.write().await;
+        stats.checks += 1;
+        stats.violations += violations.len() as u64;
+        Ok(ComplianceStatus { compliant: violations.is_empty(), violations })
+    }
+
+    /// Report locked objects per bucket, and buckets holding objects written
+    /// before their default retention was set
+    pub async fn report(&self) -> Result<ComplianceReport> {
+        let object_lock = match (&self.storage, self.config.enable_reporting) {
+            (Some(storage), true) => storage.object_lock_report().await?,
+            _ => ObjectLockReport::default(),
+        };
+        let violations: Vec<String> = object_lock
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.unretained_objects > 0)
+            .map(|bucket| {
+                format!(
+                    "Bucket {} has {} objects without retention, written before its default retention was set",
+                    bucket.bucket, bucket.unretained_objects
+                )
+            })
+            .collect();
+
+        let generated_at = Utc::now();
+        let mut stats = self.stats.write().await;
+        stats.reports += 1;
+        stats.last_report_at = Some(generated_at);
+        Ok(ComplianceReport {
+            generated_at,
+            standards: self.config.standards.clone(),
+            compliant: violations.is_empty(),
+            violations,
+            object_lock,
+        })
+    }
+
+    pub async fn stats(&self) -> ComplianceStats {
+        self.stats.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{DefaultRetention, MemoryStorage, Object, RetentionMode};
+
+    fn manager() -> (ComplianceManager, Arc<StorageEngine>) {
+        let mut engine = StorageEngine::new("memory".to_string());
+        engine.add_backend("memory".to_string(), Box::new(MemoryStorage::new()));
+        let engine = Arc::new(engine);
+        let config = ComplianceConfig {
+            enable_compliance: true,
+            standards: vec![ComplianceStandard::SOX],
+            enable_monitoring: true,
+            enable_reporting: true,
+        };
+        (ComplianceManager::new(config).unwrap().with_storage(Arc::clone(&engine)), engine)
+    }
+
+    fn object(id: &str) -> Object {
+        Object::with_id(id.to_string(), id.to_string(), b"record".to_vec(), None)
+    }
+
+    #[tokio::test]
+    async fn test_reports_objects_written_before_default_retention() {
+        let (compliance, engine) = manager();
+        engine.put(object("ledger/2023.csv")).await.unwrap();
+        let default = DefaultRetention { mode: RetentionMode::Compliance, days: 2555 };
+        engine.set_bucket_default_retention("ledger", Some(default)).await.unwrap();
+        engine.put(object("ledger/2024.csv")).await.unwrap();
+        engine.put(object("scratch/tmp.csv")).await.unwrap();
+        engine.set_legal_hold("scratch/tmp.csv", true).await.unwrap();
+
+        assert!(compliance.check_compliance("ledger/2024.csv").await.unwrap().compliant);
+        assert!(compliance.check_compliance("scratch/tmp.csv").await.unwrap().compliant);
+        let status = compliance.check_compliance("ledger/2023.csv").await.unwrap();
+        assert_eq!(status.violations.len(), 1);
+
+        let report = compliance.report().await.unwrap();
+        assert!(!report.compliant);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!((report.object_lock.locked_objects, report.object_lock.compliance, report.object_lock.legal_holds), (2, 1, 1));
+        let ledger = report.object_lock.buckets.iter().find(|bucket| bucket.bucket == "ledger").unwrap();
+        assert_eq!((ledger.locked_objects, ledger.unretained_objects), (1, 1));
+
+        let stats = compliance.stats().await;
+        assert_eq!((stats.checks, stats.violations, stats.reports), (3, 1, 1));
+    }
+}
@@ -0,0 +1,514 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// GDPR erasure coordination for user-owned objects
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
+
+use crate::errors::{NimbuxError, Result};
+use crate::metadata::TagIndex;
+use crate::storage::StorageBackend;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signature algorithm recorded on issued certificates
+pub const CERTIFICATE_ALGORITHM: &str = "HMAC-SHA256";
+
+/// Erasure coordinator configuration
+#[derive(Debug, Clone)]
+pub struct ErasureConfig {
+    /// Object tag holding the owning Pixelle user ID
+    pub user_tag_key: String,
+    /// Key used to sign erasure certificates
+    pub signing_key: Vec<u8>,
+    /// Rebuild the tag index from storage before each erasure, so objects
+    /// written through paths that do not maintain the index are not missed
+    pub rebuild_index: bool,
+    /// Buffered events per subscriber before slow subscribers start lagging
+    pub event_capacity: usize,
+}
+
+impl ErasureConfig {
+    pub fn new(signing_key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            user_tag_key: "pixelle-user-id".to_string(),
+            signing_key: signing_key.into(),
+            rebuild_index: true,
+            event_capacity: 1024,
+        }
+    }
+}
+
+/// Erasure job status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErasureStatus {
+    Running,
+    Completed,
+    /// Finished, but some objects could not be deleted; re-run to retry them
+    Partial,
+    Failed,
+}
+
+/// Object removed as part of an erasure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErasedObject {
+    pub bucket: String,
+    pub key: String,
+    pub object_id: String,
+    pub size: u64,
+    pub checksum: String,
+    pub erased_at: DateTime<Utc>,
+}
+
+/// Object that could not be removed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedErasure {
+    pub bucket: String,
+    pub key: String,
+    pub object_id: String,
+    pub error: String,
+}
+
+/// Signed record of everything deleted for a user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErasureCertificate {
+    pub certificate_id: String,
+    pub job_id: String,
+    pub user_id: String,
+    pub requested_at: DateTime<Utc>,
+    pub issued_at: DateTime<Utc>,
+    pub objects: Vec<ErasedObject>,
+    pub failures: Vec<FailedErasure>,
+    pub algorithm: String,
+    /// Hex-encoded signature over every other field
+    pub signature: String,
+}
+
+impl ErasureCertificate {
+    fn signing_payload(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(
+            &self.certificate_id,
+            &self.job_id,
+            &self.user_id,
+            &self.requested_at,
+            &self.issued_at,
+            &self.objects,
+            &self.failures,
+            &self.algorithm,
+        ))?)
+    }
+}
+
+/// Erasure job state, as reported to the privacy pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErasureJob {
+    pub job_id: String,
+    pub user_id: String,
+    pub status: ErasureStatus,
+    pub requested_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub total_objects: usize,
+    pub erased_objects: usize,
+    pub failed_objects: usize,
+    pub certificate: Option<ErasureCertificate>,
+    pub error: Option<String>,
+}
+
+/// Progress events published while an erasure runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ErasureEvent {
+    Started {
+        job_id: String,
+        user_id: String,
+        total_objects: usize,
+        timestamp: DateTime<Utc>,
+    },
+    ObjectErased {
+        job_id: String,
+        user_id: String,
+        bucket: String,
+        key: String,
+        timestamp: DateTime<Utc>,
+    },
+    ObjectFailed {
+        job_id: String,
+        user_id: String,
+        bucket: String,
+        key: String,
+        error: String,
+    },
+    Completed {
+        job_id: String,
+        user_id: String,
+        certificate_id: String,
+        erased_objects: usize,
+        failed_objects: usize,
+        timestamp: DateTime<Utc>,
+    },
+    Failed {
+        job_id: String,
+        user_id: String,
+        error: String,
+    },
+}
+
+/// Deletes every object tagged to a user across all buckets and certifies it
+pub struct ErasureCoordinator {
+    storage: Arc<dyn StorageBackend>,
+    tag_index: Arc<TagIndex>,
+    config: ErasureConfig,
+    jobs: RwLock<HashMap<String, ErasureJob>>,
+    events: broadcast::Sender<ErasureEvent>,
+}
+
+impl ErasureCoordinator {
+    pub fn new(storage: Arc<dyn StorageBackend>, tag_index: Arc<TagIndex>, config: ErasureConfig) -> Self {
+        let (events, _) = broadcast::channel(config.event_capacity.max(1));
+        Self {
+            storage,
+            tag_index,
+            config,
+            jobs: RwLock::new(HashMap::new()),
+            events,
+        }
+    }
+
+    /// Receive progress events for all erasure jobs
+    pub fn subscribe(&self) -> broadcast::Receiver<ErasureEvent> {
+        self.events.subscribe()
+    }
+
+    /// Register an erasure job and run it in the background
+    pub async fn start_erasure(self: &Arc<Self>, user_id: &str) -> Result<ErasureJob> {
+        let job = self.register(user_id).await?;
+
+        let coordinator = Arc::clone(self);
+        let job_id = job.job_id.clone();
+        tokio::spawn(async move {
+            if let Err(e) = coordinator.run(&job_id).await {
+                tracing::error!("Erasure job {} failed: {}", job_id, e);
+            }
+        });
+
+        Ok(job)
+    }
+
+    /// Erase a user's objects and wait for the certificate
+    pub async fn erase_user(&self, user_id: &str) -> Result<ErasureCertificate> {
+        let job = self.register(user_id).await?;
+        self.run(&job.job_id).await
+    }
+
+    pub async fn job(&self, job_id: &str) -> Option<ErasureJob> {
+        self.jobs.read().await.get(job_id).cloned()
+    }
+
+    /// Check a certificate was issued with this coordinator's signing key
+    /// and has not been altered since
+    pub fn verify_certificate(&self, certificate: &ErasureCertificate) -> Result<bool> {
+        if certificate.algorithm != CERTIFICATE_ALGORITHM {
+            return Ok(false);
+        }
+        let Ok(signature) = hex::decode(&certificate.signature) else {
+            return Ok(false);
+        };
+
+        let mut mac = self.mac()?;
+        mac.update(&certificate.signing_payload()?);
+        Ok(mac.verify_slice(&signature).is_ok())
+    }
+
+    async fn register(&self, user_id: &str) -> Result<ErasureJob> {
+        if user_id.trim().is_empty() {
+            return Err(NimbuxError::InvalidRequest("User ID must not be empty".to_string()));
+        }
+
+        let job = ErasureJob {
+            job_id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            status: ErasureStatus::Running,
+            requested_at: Utc::now(),
+            completed_at: None,
+            total_objects: 0,
+            erased_objects: 0,
+            failed_objects: 0,
+            certificate: None,
+            error: None,
+        };
+        self.jobs.write().await.insert(job.job_id.clone(), job.clone());
+
+        tracing::info!("Registered erasure job {} for user {}", job.job_id, user_id);
+        Ok(job)
+    }
+
+    async fn run(&self, job_id: &str) -> Result<ErasureCertificate> {
+        let (user_id, requested_at) = {
+            let jobs = self.jobs.read().await;
+            let job = jobs
+                .get(job_id)
+                .ok_or_else(|| NimbuxError::Internal(format!("Unknown erasure job {}", job_id)))?;
+            (job.user_id.clone(), job.requested_at)
+        };
+
+        match self.erase(job_id, &user_id, requested_at).await {
+            Ok(certificate) => Ok(certificate),
+            Err(e) => {
+                if let Some(job) = self.jobs.write().await.get_mut(job_id) {
+                    job.status = ErasureStatus::Failed;
+                    job.completed_at = Some(Utc::now());
+                    job.error = Some(e.to_string());
+                }
+                self.publish(ErasureEvent::Failed {
+                    job_id: job_id.to_string(),
+                    user_id,
+                    error: e.to_string(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    async fn erase(&self, job_id: &str, user_id: &str, requested_at: DateTime<Utc>) -> Result<ErasureCertificate> {
+        if self.config.rebuild_index {
+            self.tag_index.rebuild(self.storage.as_ref()).await?;
+        }
+
+        let object_ids = self.tag_index.objects_with_tag(&self.config.user_tag_key, user_id).await;
+        self.update_job(job_id, |job| job.total_objects = object_ids.len()).await;
+        self.publish(ErasureEvent::Started {
+            job_id: job_id.to_string(),
+            user_id: user_id.to_string(),
+            total_objects: object_ids.len(),
+            timestamp: Utc::now(),
+        });
+
+        let mut objects = Vec::new();
+        let mut failures = Vec::new();
+
+        for object_id in object_ids {
+            let metadata = match self.storage.head(&object_id).await {
+                Ok(metadata) => metadata,
+                Err(NimbuxError::ObjectNotFound { .. }) => {
+                    // Already gone; nothing to certify
+                    self.tag_index.remove_object(&object_id).await;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            // The index can trail a re-tag; only erase what is still owned by the user
+            if metadata.tags.get(&self.config.user_tag_key).map(String::as_str) != Some(user_id) {
+                self.tag_index.index_object(&metadata).await;
+                continue;
+            }
+
+            let (bucket, key) = split_name(&metadata.name);
+            match self.storage.delete(&object_id).await {
+                Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => {
+                    self.tag_index.remove_object(&object_id).await;
+                    let erased_at = Utc::now();
+                    self.publish(ErasureEvent::ObjectErased {
+                        job_id: job_id.to_string(),
+                        user_id: user_id.to_string(),
+                        bucket: bucket.clone(),
+                        key: key.clone(),
+                        timestamp: erased_at,
+                    });
+                    objects.push(ErasedObject {
+                        bucket,
+                        key,
+                        object_id,
+                        size: metadata.size,
+                        checksum: metadata.checksum,
+                        erased_at,
+                    });
+                    self.update_job(job_id, |job| job.erased_objects += 1).await;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to erase {} for user {}: {}", metadata.name, user_id, e);
+                    self.publish(ErasureEvent::ObjectFailed {
+                        job_id: job_id.to_string(),
+                        user_id: user_id.to_string(),
+                        bucket: bucket.clone(),
+                        key: key.clone(),
+                        error: e.to_string(),
+                    });
+                    failures.push(FailedErasure {
+                        bucket,
+                        key,
+                        object_id,
+                        error: e.to_string(),
+                    });
+                    self.update_job(job_id, |job| job.failed_objects += 1).await;
+                }
+            }
+        }
+
+        let certificate = self.issue_certificate(job_id, user_id, requested_at, objects, failures)?;
+        let status = if certificate.failures.is_empty() {
+            ErasureStatus::Completed
+        } else {
+            ErasureStatus::Partial
+        };
+
+        let issued = certificate.clone();
+        self.update_job(job_id, move |job| {
+            job.status = status;
+            job.completed_at = Some(issued.issued_at);
+            job.certificate = Some(issued);
+        })
+        .await;
+
+        self.publish(ErasureEvent::Completed {
+            job_id: job_id.to_string(),
+            user_id: user_id.to_string(),
+            certificate_id: certificate.certificate_id.clone(),
+            erased_objects: certificate.objects.len(),
+            failed_objects: certificate.failures.len(),
+            timestamp: certificate.issued_at,
+        });
+        tracing::info!(
+            "Erasure job {} for user {} finished: {} erased, {} failed",
+            job_id,
+            user_id,
+            certificate.objects.len(),
+            certificate.failures.len()
+        );
+
+        Ok(certificate)
+    }
+
+    fn issue_certificate(
+        &self,
+        job_id: &str,
+        user_id: &str,
+        requested_at: DateTime<Utc>,
+        objects: Vec<ErasedObject>,
+        failures: Vec<FailedErasure>,
+    ) -> Result<ErasureCertificate> {
+        let mut certificate = ErasureCertificate {
+            certificate_id: Uuid::new_v4().to_string(),
+            job_id: job_id.to_string(),
+            user_id: user_id.to_string(),
+            requested_at,
+            issued_at: Utc::now(),
+            objects,
+            failures,
+            algorithm: CERTIFICATE_ALGORITHM.to_string(),
+            signature: String::new(),
+        };
+
+        let mut mac = self.mac()?;
+        mac.update(&certificate.signing_payload()?);
+        certificate.signature = hex::encode(mac.finalize().into_bytes());
+
+        Ok(certificate)
+    }
+
+    fn mac(&self) -> Result<HmacSha256> {
+        HmacSha256::new_from_slice(&self.config.signing_key)
+            .map_err(|e| NimbuxError::Internal(format!("Failed to create HMAC: {}", e)))
+    }
+
+    async fn update_job(&self, job_id: &str, update: impl FnOnce(&mut ErasureJob)) {
+        if let Some(job) = self.jobs.write().await.get_mut(job_id) {
+            update(job);
+        }
+    }
+
+    fn publish(&self, event: ErasureEvent) {
+        // No subscribers is fine; job state can still be polled
+        let _ = self.events.send(event);
+    }
+}
+
+/// Split an object name of the form `<bucket>/<key>`
+fn split_name(name: &str) -> (String, String) {
+    match name.split_once('/') {
+        Some((bucket, key)) => (bucket.to_string(), key.to_string()),
+        None => (String::new(), name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryStorage, Object};
+
+    async fn put(storage: &MemoryStorage, name: &str, user_id: Option<&str>) {
+        let mut object = Object::with_id(name.to_string(), name.to_string(), name.as_bytes().to_vec(), None);
+        if let Some(user_id) = user_id {
+            object.metadata.tags.insert("pixelle-user-id".to_string(), user_id.to_string());
+        }
+        storage.put(object).await.unwrap();
+    }
+
+    fn coordinator(storage: Arc<MemoryStorage>) -> ErasureCoordinator {
+        ErasureCoordinator::new(storage, Arc::new(TagIndex::new()), ErasureConfig::new(b"test-key".to_vec()))
+    }
+
+    #[tokio::test]
+    async fn test_erase_user_deletes_only_tagged_objects() {
+        let storage = Arc::new(MemoryStorage::new());
+        put(&storage, "photos/alice/1.jpg", Some("alice")).await;
+        put(&storage, "videos/alice/2.mp4", Some("alice")).await;
+        put(&storage, "photos/bob/1.jpg", Some("bob")).await;
+        put(&storage, "photos/shared.jpg", None).await;
+
+        let coordinator = coordinator(storage.clone());
+        let mut events = coordinator.subscribe();
+        let certificate = coordinator.erase_user("alice").await.unwrap();
+
+        let mut erased: Vec<_> = certificate.objects.iter().map(|o| (o.bucket.as_str(), o.key.as_str())).collect();
+        erased.sort();
+        assert_eq!(erased, vec![("photos", "alice/1.jpg"), ("videos", "alice/2.mp4")]);
+        assert!(certificate.failures.is_empty());
+
+        assert!(!storage.exists("photos/alice/1.jpg").await.unwrap());
+        assert!(storage.exists("photos/bob/1.jpg").await.unwrap());
+        assert!(storage.exists("photos/shared.jpg").await.unwrap());
+
+        let job = coordinator.job(&certificate.job_id).await.unwrap();
+        assert_eq!(job.status, ErasureStatus::Completed);
+        assert_eq!(job.erased_objects, 2);
+
+        assert!(matches!(events.recv().await.unwrap(), ErasureEvent::Started { total_objects: 2, .. }));
+        assert!(matches!(events.recv().await.unwrap(), ErasureEvent::ObjectErased { .. }));
+        assert!(matches!(events.recv().await.unwrap(), ErasureEvent::ObjectErased { .. }));
+        assert!(matches!(events.recv().await.unwrap(), ErasureEvent::Completed { erased_objects: 2, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_certificate_signature_detects_tampering() {
+        let storage = Arc::new(MemoryStorage::new());
+        put(&storage, "photos/alice/1.jpg", Some("alice")).await;
+        put(&storage, "photos/alice/2.jpg", Some("alice")).await;
+
+        let coordinator = coordinator(storage);
+        let certificate = coordinator.erase_user("alice").await.unwrap();
+        assert!(coordinator.verify_certificate(&certificate).unwrap());
+
+        let mut tampered = certificate.clone();
+        tampered.objects.pop();
+        assert!(!coordinator.verify_certificate(&tampered).unwrap());
+
+        let other = ErasureCoordinator::new(
+            Arc::new(MemoryStorage::new()),
+            Arc::new(TagIndex::new()),
+            ErasureConfig::new(b"other-key".to_vec()),
+        );
+        assert!(!other.verify_certificate(&certificate).unwrap());
+    }
+}
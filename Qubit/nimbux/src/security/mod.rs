@@ -20,6 +20,9 @@ pub mod audit;
 pub mod compliance;
 pub mod key_management;
 pub mod data_protection;
+pub mod erasure;
+pub mod network_policy;
+pub mod client_encryption;
 
 // Re-export commonly used types
 pub use encryption::{EncryptionManager, EncryptionConfig, EncryptionStats, EncryptionKey};
@@ -28,6 +31,14 @@ pub use audit::{AuditManager, AuditConfig, AuditStats, AuditEvent};
 pub use compliance::{ComplianceManager, ComplianceConfig, ComplianceStats, ComplianceReport};
 pub use key_management::{KeyManager, KeyConfig, KeyStats, KeyInfo};
 pub use data_protection::{DataProtectionManager, ProtectionConfig, ProtectionStats, ProtectionLevel};
+pub use erasure::{ErasureCoordinator, ErasureConfig, ErasureCertificate, ErasureEvent, ErasureJob, ErasureStatus};
+pub use network_policy::{
+    NetworkPolicyEngine, NetworkPolicyConfig, NetworkPolicy, NetworkRules, PolicyScope, PolicyDecision,
+    PolicyDenial, DenyReason, DenialStats, IpNetwork, GeoIpLookup, CidrGeoIp
+};
+pub use client_encryption::{
+    ClientEncryption, EncryptedBody, KeyWrapper, LocalKeyWrapper, ObjectEncryption, WrappedKey, CSE_CONTENT_ALGORITHM
+};
 
 /// Security configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -206,6 +217,12 @@ impl SecurityManager {
         })
     }
     
+    /// Use a compliance manager built elsewhere, such as one checking the storage engine
+    pub fn with_compliance_manager(mut self, compliance_manager: Arc<ComplianceManager>) -> Self {
+        self.compliance_manager = compliance_manager;
+        self
+    }
+    
     /// Start security monitoring and management
     pub async fn start(&self) -> Result<()> {
         // Start encryption manager
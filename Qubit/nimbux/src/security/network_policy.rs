@@ -0,0 +1,577 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Network access policies: IP allow/deny lists and geo restrictions
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::errors::{NimbuxError, Result};
+
+/// IPv4 or IPv6 network in CIDR notation; a bare address is a single-host network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self> {
+        let addr = addr.to_canonical();
+        if u32::from(prefix) > address_width(addr) {
+            return Err(NimbuxError::InvalidRequest(format!("Invalid prefix length /{} for {}", prefix, addr)));
+        }
+        Ok(Self { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        if ip.is_ipv4() != self.addr.is_ipv4() {
+            return false;
+        }
+        // Compare only the network bits; a /0 shifts everything out
+        let host_bits = address_width(ip) - u32::from(self.prefix);
+        let differing = address_bits(ip) ^ address_bits(self.addr);
+        differing.checked_shr(host_bits).unwrap_or(0) == 0
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+}
+
+fn address_width(ip: IpAddr) -> u32 {
+    if ip.is_ipv4() { 32 } else { 128 }
+}
+
+fn address_bits(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(v4) => u128::from(u32::from(v4)),
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = NimbuxError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| NimbuxError::InvalidRequest(format!("Invalid IP network: {}", s)))?;
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .map_err(|_| NimbuxError::InvalidRequest(format!("Invalid IP network: {}", s)))?,
+            None => address_width(addr.to_canonical()) as u8,
+        };
+        Self::new(addr, prefix)
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl Serialize for IpNetwork {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNetwork {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Resolves client addresses to ISO 3166-1 alpha-2 country codes
+pub trait GeoIpLookup: Send + Sync {
+    fn country(&self, ip: IpAddr) -> Option<String>;
+}
+
+/// GeoIP table of networks to countries, matched by longest prefix
+#[derive(Debug, Clone, Default)]
+pub struct CidrGeoIp {
+    networks: Vec<(IpNetwork, String)>,
+}
+
+impl CidrGeoIp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, network: IpNetwork, country: &str) {
+        self.networks.push((network, country.to_ascii_uppercase()));
+    }
+
+    /// Parse `network,country` lines; blank lines and `#` comments are skipped
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut table = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (network, country) = line.split_once(',').ok_or_else(|| {
+                NimbuxError::Configuration(format!("GeoIP line {}: expected `network,country`", number + 1))
+            })?;
+            let network = network
+                .parse()
+                .map_err(|e| NimbuxError::Configuration(format!("GeoIP line {}: {}", number + 1, e)))?;
+            table.insert(network, country.trim());
+        }
+        Ok(table)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.networks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+}
+
+impl GeoIpLookup for CidrGeoIp {
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        self.networks
+            .iter()
+            .filter(|(network, _)| network.contains(ip))
+            .max_by_key(|(network, _)| network.prefix())
+            .map(|(_, country)| country.clone())
+    }
+}
+
+/// What a network policy is attached to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "id", rename_all = "snake_case")]
+pub enum PolicyScope {
+    Bucket(String),
+    AccessKey(String),
+}
+
+impl fmt::Display for PolicyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyScope::Bucket(bucket) => write!(f, "bucket {}", bucket),
+            PolicyScope::AccessKey(key_id) => write!(f, "access key {}", key_id),
+        }
+    }
+}
+
+/// Address and country rules of a policy; empty lists impose nothing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkRules {
+    /// When non-empty, only these networks may connect
+    #[serde(default)]
+    pub allow: Vec<IpNetwork>,
+    /// Always refused, even when also in `allow`
+    #[serde(default)]
+    pub deny: Vec<IpNetwork>,
+    /// When non-empty, only clients located in these countries may connect
+    #[serde(default)]
+    pub allowed_countries: Vec<String>,
+    #[serde(default)]
+    pub denied_countries: Vec<String>,
+}
+
+impl NetworkRules {
+    fn has_geo_rules(&self) -> bool {
+        !self.allowed_countries.is_empty() || !self.denied_countries.is_empty()
+    }
+
+    fn normalize_countries(&mut self) -> Result<()> {
+        for country in self.allowed_countries.iter_mut().chain(self.denied_countries.iter_mut()) {
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(NimbuxError::InvalidRequest(format!(
+                    "Invalid country code {:?}, expected ISO 3166-1 alpha-2",
+                    country
+                )));
+            }
+            country.make_ascii_uppercase();
+        }
+        Ok(())
+    }
+}
+
+/// Network policy attached to a bucket or access key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkPolicy {
+    pub scope: PolicyScope,
+    #[serde(flatten)]
+    pub rules: NetworkRules,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DenyReason {
+    IpDenied,
+    IpNotAllowed,
+    CountryDenied,
+    CountryNotAllowed,
+    /// The policy allows only some countries and the client could not be located
+    CountryUnknown,
+}
+
+/// A refused request, kept for security review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyDenial {
+    pub scope: PolicyScope,
+    pub reason: DenyReason,
+    pub client_ip: IpAddr,
+    pub country: Option<String>,
+    pub denied_at: DateTime<Utc>,
+}
+
+impl fmt::Display for PolicyDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            DenyReason::IpDenied => "address is denied",
+            DenyReason::IpNotAllowed => "address is not allowed",
+            DenyReason::CountryDenied => "country is denied",
+            DenyReason::CountryNotAllowed => "country is not allowed",
+            DenyReason::CountryUnknown => "country could not be determined",
+        };
+        write!(f, "Request from {} refused by the network policy of {}: {}", self.client_ip, self.scope, reason)
+    }
+}
+
+/// Outcome of evaluating the policies that apply to a request
+#[derive(Debug, Clone)]
+pub enum PolicyDecision {
+    Allow,
+    Deny(PolicyDenial),
+}
+
+/// Denied-request counters and the most recent denials
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DenialStats {
+    pub total: u64,
+    pub by_reason: HashMap<DenyReason, u64>,
+    /// Keyed by the scope's display form, e.g. "bucket photos"
+    pub by_scope: HashMap<String, u64>,
+    /// Newest first
+    pub recent: Vec<PolicyDenial>,
+}
+
+/// Network policy engine configuration
+#[derive(Debug, Clone)]
+pub struct NetworkPolicyConfig {
+    /// Proxies whose `X-Forwarded-For` header is trusted for the client address
+    pub trusted_proxies: Vec<IpNetwork>,
+    /// Denials kept for review
+    pub denial_history: usize,
+}
+
+impl Default for NetworkPolicyConfig {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: Vec::new(),
+            denial_history: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct DenialLog {
+    total: u64,
+    by_reason: HashMap<DenyReason, u64>,
+    by_scope: HashMap<String, u64>,
+    recent: VecDeque<PolicyDenial>,
+}
+
+/// Evaluates bucket and access key network policies ahead of authorization
+pub struct NetworkPolicyEngine {
+    config: NetworkPolicyConfig,
+    geoip: Option<Arc<dyn GeoIpLookup>>,
+    policies: RwLock<HashMap<PolicyScope, NetworkPolicy>>,
+    denials: RwLock<DenialLog>,
+}
+
+impl NetworkPolicyEngine {
+    pub fn new(config: NetworkPolicyConfig) -> Self {
+        Self {
+            config,
+            geoip: None,
+            policies: RwLock::new(HashMap::new()),
+            denials: RwLock::new(DenialLog::default()),
+        }
+    }
+
+    /// Enable country rules; without a lookup, policies with country rules are rejected
+    pub fn with_geoip(mut self, geoip: Arc<dyn GeoIpLookup>) -> Self {
+        self.geoip = Some(geoip);
+        self
+    }
+
+    /// Create or replace the policy of a scope
+    pub async fn set_policy(&self, scope: PolicyScope, mut rules: NetworkRules) -> Result<NetworkPolicy> {
+        rules.normalize_countries()?;
+        if rules.has_geo_rules() && self.geoip.is_none() {
+            return Err(NimbuxError::InvalidRequest(
+                "Country rules need a GeoIP database, none is configured".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let mut policies = self.policies.write().await;
+        let created_at = policies.get(&scope).map_or(now, |existing| existing.created_at);
+        let policy = NetworkPolicy {
+            scope: scope.clone(),
+            rules,
+            created_at,
+            updated_at: now,
+        };
+        policies.insert(scope.clone(), policy.clone());
+        info!("Set network policy for {}", scope);
+        Ok(policy)
+    }
+
+    pub async fn policy(&self, scope: &PolicyScope) -> Option<NetworkPolicy> {
+        self.policies.read().await.get(scope).cloned()
+    }
+
+    pub async fn list_policies(&self) -> Vec<NetworkPolicy> {
+        let mut policies: Vec<_> = self.policies.read().await.values().cloned().collect();
+        policies.sort_by_key(|policy| policy.scope.to_string());
+        policies
+    }
+
+    /// Remove the policy of a scope, returning whether there was one
+    pub async fn delete_policy(&self, scope: &PolicyScope) -> bool {
+        let removed = self.policies.write().await.remove(scope).is_some();
+        if removed {
+            info!("Removed network policy for {}", scope);
+        }
+        removed
+    }
+
+    /// Client address for policy checks: the peer, or the forwarded client when the peer is a trusted proxy
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        if !self.config.trusted_proxies.iter().any(|proxy| proxy.contains(peer)) {
+            return peer;
+        }
+        // Walk back from the nearest hop, skipping our own proxies
+        forwarded_for
+            .into_iter()
+            .flat_map(|header| header.rsplit(','))
+            .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+            .find(|hop| !self.config.trusted_proxies.iter().any(|proxy| proxy.contains(*hop)))
+            .unwrap_or(peer)
+    }
+
+    /// Check the access key policy, then the bucket policy; a request must pass both
+    ///
+    /// The access key ID is the one the request claims, not yet verified. Omitting
+    /// it only skips the key policy, and the request then fails authentication.
+    pub async fn evaluate(&self, client_ip: IpAddr, bucket: Option<&str>, access_key_id: Option<&str>) -> PolicyDecision {
+        let scopes = access_key_id
+            .map(|key_id| PolicyScope::AccessKey(key_id.to_string()))
+            .into_iter()
+            .chain(bucket.map(|bucket| PolicyScope::Bucket(bucket.to_string())));
+
+        let denial = {
+            let policies = self.policies.read().await;
+            let mut country = None;
+            scopes
+                .filter_map(|scope| policies.get(&scope))
+                .find_map(|policy| self.check(policy, client_ip, &mut country))
+        };
+
+        match denial {
+            Some(denial) => {
+                warn!("{}", denial);
+                self.record_denial(&denial).await;
+                PolicyDecision::Deny(denial)
+            }
+            None => PolicyDecision::Allow,
+        }
+    }
+
+    /// `country` caches the GeoIP lookup across the policies of one request
+    fn check(&self, policy: &NetworkPolicy, client_ip: IpAddr, country: &mut Option<Option<String>>) -> Option<PolicyDenial> {
+        let rules = &policy.rules;
+        let reason = if rules.deny.iter().any(|network| network.contains(client_ip)) {
+            Some(DenyReason::IpDenied)
+        } else if !rules.allow.is_empty() && !rules.allow.iter().any(|network| network.contains(client_ip)) {
+            Some(DenyReason::IpNotAllowed)
+        } else if rules.has_geo_rules() {
+            let located = country
+                .get_or_insert_with(|| self.geoip.as_ref().and_then(|geoip| geoip.country(client_ip)))
+                .as_deref();
+            match located {
+                Some(code) if rules.denied_countries.iter().any(|c| c == code) => Some(DenyReason::CountryDenied),
+                Some(code) if !rules.allowed_countries.is_empty() && !rules.allowed_countries.iter().any(|c| c == code) => {
+                    Some(DenyReason::CountryNotAllowed)
+                }
+                None if !rules.allowed_countries.is_empty() => Some(DenyReason::CountryUnknown),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        reason.map(|reason| PolicyDenial {
+            scope: policy.scope.clone(),
+            reason,
+            client_ip,
+            country: country.clone().flatten(),
+            denied_at: Utc::now(),
+        })
+    }
+
+    async fn record_denial(&self, denial: &PolicyDenial) {
+        let mut log = self.denials.write().await;
+        log.total += 1;
+        *log.by_reason.entry(denial.reason).or_default() += 1;
+        *log.by_scope.entry(denial.scope.to_string()).or_default() += 1;
+        if self.config.denial_history > 0 {
+            if log.recent.len() == self.config.denial_history {
+                log.recent.pop_back();
+            }
+            log.recent.push_front(denial.clone());
+        }
+    }
+
+    pub async fn denial_stats(&self, limit: Option<usize>) -> DenialStats {
+        let log = self.denials.read().await;
+        DenialStats {
+            total: log.total,
+            by_reason: log.by_reason.clone(),
+            by_scope: log.by_scope.clone(),
+            recent: log.recent.iter().take(limit.unwrap_or(usize::MAX)).cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn networks(list: &[&str]) -> Vec<IpNetwork> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_network_contains() {
+        let net: IpNetwork = "10.1.0.0/16".parse().unwrap();
+        assert!(net.contains(ip("10.1.200.3")));
+        assert!(!net.contains(ip("10.2.0.1")));
+        assert!(net.contains(ip("::ffff:10.1.0.9")));
+        assert!(!net.contains(ip("2001:db8::1")));
+
+        let v6: IpNetwork = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8:ffff::1")));
+        assert!("0.0.0.0/0".parse::<IpNetwork>().unwrap().contains(ip("192.0.2.1")));
+        assert!("::/0".parse::<IpNetwork>().unwrap().contains(ip("2001:db8::1")));
+        assert_eq!("192.0.2.7".parse::<IpNetwork>().unwrap().to_string(), "192.0.2.7/32");
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_key_and_bucket_policies_both_apply() {
+        let engine = NetworkPolicyEngine::new(NetworkPolicyConfig::default());
+        engine
+            .set_policy(
+                PolicyScope::Bucket("photos".to_string()),
+                NetworkRules { allow: networks(&["10.0.0.0/8"]), deny: networks(&["10.9.0.0/16"]), ..Default::default() },
+            )
+            .await
+            .unwrap();
+        engine
+            .set_policy(
+                PolicyScope::AccessKey("NIMBKEY".to_string()),
+                NetworkRules { allow: networks(&["10.1.0.0/16"]), ..Default::default() },
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(engine.evaluate(ip("10.2.0.1"), Some("photos"), None).await, PolicyDecision::Allow));
+        assert!(matches!(engine.evaluate(ip("192.0.2.1"), Some("videos"), None).await, PolicyDecision::Allow));
+
+        let denied = |decision| match decision {
+            PolicyDecision::Deny(denial) => Some((denial.scope, denial.reason)),
+            PolicyDecision::Allow => None,
+        };
+        assert_eq!(
+            denied(engine.evaluate(ip("10.9.0.1"), Some("photos"), None).await),
+            Some((PolicyScope::Bucket("photos".to_string()), DenyReason::IpDenied))
+        );
+        assert_eq!(
+            denied(engine.evaluate(ip("192.0.2.1"), Some("photos"), None).await),
+            Some((PolicyScope::Bucket("photos".to_string()), DenyReason::IpNotAllowed))
+        );
+        assert_eq!(
+            denied(engine.evaluate(ip("10.2.0.1"), Some("photos"), Some("NIMBKEY")).await),
+            Some((PolicyScope::AccessKey("NIMBKEY".to_string()), DenyReason::IpNotAllowed))
+        );
+
+        let stats = engine.denial_stats(None).await;
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.by_scope["bucket photos"], 2);
+        assert_eq!(stats.recent[0].scope, PolicyScope::AccessKey("NIMBKEY".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_geo_restrictions() {
+        let geoip = CidrGeoIp::parse("# test table\n198.51.100.0/24,de\n198.51.100.128/25,FR\n").unwrap();
+        let engine = NetworkPolicyEngine::new(NetworkPolicyConfig::default());
+        let rules = NetworkRules { allowed_countries: vec!["de".to_string()], ..Default::default() };
+        assert!(engine.set_policy(PolicyScope::Bucket("eu".to_string()), rules.clone()).await.is_err());
+
+        let engine = engine.with_geoip(Arc::new(geoip));
+        engine.set_policy(PolicyScope::Bucket("eu".to_string()), rules).await.unwrap();
+
+        let reason = |decision| match decision {
+            PolicyDecision::Deny(denial) => Some(denial.reason),
+            PolicyDecision::Allow => None,
+        };
+        assert_eq!(reason(engine.evaluate(ip("198.51.100.7"), Some("eu"), None).await), None);
+        assert_eq!(
+            reason(engine.evaluate(ip("198.51.100.200"), Some("eu"), None).await),
+            Some(DenyReason::CountryNotAllowed)
+        );
+        assert_eq!(reason(engine.evaluate(ip("203.0.113.1"), Some("eu"), None).await), Some(DenyReason::CountryUnknown));
+    }
+
+    #[test]
+    fn test_client_ip_trusts_only_configured_proxies() {
+        let engine = NetworkPolicyEngine::new(NetworkPolicyConfig {
+            trusted_proxies: networks(&["10.0.0.0/8"]),
+            ..Default::default()
+        });
+        let forwarded = Some("203.0.113.9, 198.51.100.4, 10.0.0.2");
+        assert_eq!(engine.client_ip(ip("10.0.0.1"), forwarded), ip("198.51.100.4"));
+        assert_eq!(engine.client_ip(ip("192.0.2.1"), forwarded), ip("192.0.2.1"));
+        assert_eq!(engine.client_ip(ip("10.0.0.1"), None), ip("10.0.0.1"));
+    }
+}
@@ -44,7 +44,7 @@ pub struct LifecyclePolicy {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LifecycleStatus {
     Enabled,
     Disabled,
@@ -55,15 +55,18 @@ pub struct LifecycleRule {
     pub id: String,
     pub name: String,
     pub status: LifecycleStatus,
+    #[serde(default)]
     pub filter: LifecycleFilter,
+    #[serde(default)]
     pub transitions: Vec<Transition>,
     pub expiration: Option<Expiration>,
     pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LifecycleFilter {
     pub prefix: Option<String>,
+    #[serde(default)]
     pub tags: HashMap<String, String>,
     pub object_size_greater_than: Option<u64>,
     pub object_size_less_than: Option<u64>,
@@ -288,6 +291,8 @@ pub struct LifecycleProcessResult {
     pub processed_objects: u64,
     pub transitioned_objects: u64,
     pub expired_objects: u64,
+    #[serde(default)]
+    pub aborted_uploads: u64,
     pub errors: Vec<String>,
     pub processing_time_ms: u64,
 }
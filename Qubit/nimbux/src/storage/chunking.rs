@@ -0,0 +1,187 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Content-defined chunking (FastCDC)
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{NimbuxError, Result};
+
+/// Gear hash values for each byte, generated with splitmix64 so the table is
+/// fixed across builds; chunk boundaries, and therefore deduplication, depend on it
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x6e69_6d62_7578_6364;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Chunk size bounds for content-defined chunking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkerConfig {
+    /// No boundary is placed before this many bytes
+    pub min_size: usize,
+    /// Chunk size the boundaries are normalized around; must be a power of two
+    pub avg_size: usize,
+    /// A boundary is forced after this many bytes
+    pub max_size: usize,
+}
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 16 * 1024,
+            avg_size: 64 * 1024,
+            max_size: 256 * 1024,
+        }
+    }
+}
+
+impl ChunkerConfig {
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Result<Self> {
+        let config = Self { min_size, avg_size, max_size };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !self.avg_size.is_power_of_two() || self.avg_size < 64 {
+            return Err(NimbuxError::Configuration(format!(
+                "Average chunk size must be a power of two of at least 64 bytes, got {}",
+                self.avg_size
+            )));
+        }
+        if self.min_size == 0 || self.min_size >= self.avg_size || self.avg_size >= self.max_size {
+            return Err(NimbuxError::Configuration(format!(
+                "Chunk sizes must satisfy 0 < min < avg < max, got {} / {} / {}",
+                self.min_size, self.avg_size, self.max_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Masks for normalized chunking: a stricter one before the average size
+    /// and a looser one after, so chunk sizes cluster around the average
+    fn masks(&self) -> (u64, u64) {
+        let bits = self.avg_size.trailing_zeros();
+        let high_bits = |n: u32| ((1u64 << n) - 1) << (64 - n);
+        (high_bits(bits + 1), high_bits(bits - 1))
+    }
+
+    /// Length of the chunk at the start of `data`
+    fn cut_point(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+
+        let (mask_small, mask_large) = self.masks();
+        let end = data.len().min(self.max_size);
+        let normal = self.avg_size.min(end);
+        let mut hash = 0u64;
+        let mut i = self.min_size;
+        while i < normal {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & mask_small == 0 {
+                return i;
+            }
+            i += 1;
+        }
+        while i < end {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & mask_large == 0 {
+                return i;
+            }
+            i += 1;
+        }
+        end
+    }
+}
+
+/// Splits `data` into content-defined chunks, so an insertion or removal
+/// only changes the chunks around it
+pub fn chunks<'a>(data: &'a [u8], config: &ChunkerConfig) -> Chunks<'a> {
+    Chunks { data, config: *config }
+}
+
+/// Iterator over the chunks of a buffer
+pub struct Chunks<'a> {
+    data: &'a [u8],
+    config: ChunkerConfig,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let (chunk, rest) = self.data.split_at(self.config.cut_point(self.data));
+        self.data = rest;
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn random_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunks_respect_bounds_and_cover_data() {
+        let config = ChunkerConfig::new(1024, 4096, 16384).unwrap();
+        let data = random_bytes(500_000, 7);
+        let chunks: Vec<&[u8]> = chunks(&data, &config).collect();
+
+        assert_eq!(chunks.concat(), data);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.len() >= config.min_size && chunk.len() <= config.max_size);
+        }
+        let average = data.len() / chunks.len();
+        assert!(average > 2048 && average < 8192, "average chunk size {}", average);
+    }
+
+    #[test]
+    fn test_insertion_only_changes_nearby_chunks() {
+        let config = ChunkerConfig::new(1024, 4096, 16384).unwrap();
+        let original = random_bytes(400_000, 11);
+        let mut edited = original.clone();
+        edited.splice(200_000..200_000, b"an edit in the middle".iter().copied());
+
+        let before: HashSet<&[u8]> = chunks(&original, &config).collect();
+        let after: Vec<&[u8]> = chunks(&edited, &config).collect();
+        let shared = after.iter().filter(|chunk| before.contains(*chunk)).count();
+        assert!(after.len() - shared <= 2, "{} of {} chunks changed", after.len() - shared, after.len());
+    }
+
+    #[test]
+    fn test_rejects_invalid_sizes() {
+        assert!(ChunkerConfig::new(1024, 3000, 16384).is_err());
+        assert!(ChunkerConfig::new(8192, 4096, 16384).is_err());
+        assert!(ChunkerConfig::default().validate().is_ok());
+    }
+}
@@ -7,45 +7,222 @@
 
 use async_trait::async_trait;
 use blake3::Hasher;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tracing::{debug, error, info, warn};
 
+use super::chunking::{self, ChunkerConfig};
+use super::gc::{CompactionReport, GcPhase, GcReport, GcStatus};
+use super::pack::{ObjectJournal, PackStore, DEFAULT_SEGMENT_BYTES};
 use super::{Object, ObjectMetadata, StorageBackend, StorageStats};
 use crate::errors::{NimbuxError, Result};
+use crate::observability::{MetricType, MetricsCollector};
+
+/// Objects larger than this are split into content-defined chunks
+pub const DEFAULT_CHUNKING_THRESHOLD: u64 = 1024 * 1024; // 1MB
+
+/// Pack segments with at least this fraction of dead space are rewritten by compaction
+pub const DEFAULT_COMPACTION_DEAD_RATIO: f64 = 0.5;
+
+/// Object ID to its chunk hashes, in order, and metadata
+pub(crate) type ObjectIndex = HashMap<String, (Vec<String>, ObjectMetadata)>;
+
+/// Where chunk data is kept
+enum ChunkData {
+    Memory(HashMap<String, Vec<u8>>),
+    Packed(Arc<PackStore>),
+}
+
+/// Chunk data with the number of object references to each chunk
+struct ChunkStore {
+    data: ChunkData,
+    /// Chunks at zero stay stored until the next garbage collection
+    ref_counts: HashMap<String, u64>,
+    /// Bumped by every put and delete, so garbage collection can tell
+    /// whether the index changed while it was marking
+    write_epoch: u64,
+}
+
+impl ChunkStore {
+    fn new(data: ChunkData) -> Self {
+        Self { data, ref_counts: HashMap::new(), write_epoch: 0 }
+    }
+
+    fn size(&self) -> u64 {
+        match &self.data {
+            ChunkData::Memory(data) => data.values().map(|data| data.len() as u64).sum(),
+            ChunkData::Packed(pack) => pack.live_bytes(),
+        }
+    }
+
+    fn contains(&self, hash: &str) -> bool {
+        match &self.data {
+            ChunkData::Memory(data) => data.contains_key(hash),
+            ChunkData::Packed(pack) => pack.contains(hash),
+        }
+    }
+
+    fn chunk_size(&self, hash: &str) -> u64 {
+        match &self.data {
+            ChunkData::Memory(data) => data.get(hash).map_or(0, |data| data.len() as u64),
+            ChunkData::Packed(pack) => pack.chunk_len(hash).unwrap_or(0),
+        }
+    }
+
+    fn hashes(&self) -> Vec<String> {
+        match &self.data {
+            ChunkData::Memory(data) => data.keys().cloned().collect(),
+            ChunkData::Packed(pack) => pack.hashes(),
+        }
+    }
+
+    fn insert(&mut self, hash: &str, chunk: &[u8]) -> Result<()> {
+        match &mut self.data {
+            ChunkData::Memory(data) => {
+                data.entry(hash.to_string()).or_insert_with(|| chunk.to_vec());
+                Ok(())
+            }
+            ChunkData::Packed(pack) => pack.insert(hash, chunk),
+        }
+    }
+
+    /// Append a chunk's data to `out`, failing if it is not stored
+    fn read_into(&self, hash: &str, out: &mut Vec<u8>) -> Result<()> {
+        let missing = || NimbuxError::Storage("Content not found in store".to_string());
+        match &self.data {
+            ChunkData::Memory(data) => out.extend_from_slice(data.get(hash).ok_or_else(missing)?),
+            ChunkData::Packed(pack) => out.extend_from_slice(&pack.read(hash)?.ok_or_else(missing)?),
+        }
+        Ok(())
+    }
+
+    /// Drop a chunk's data, returning its size
+    fn remove(&mut self, hash: &str) -> Option<u64> {
+        match &mut self.data {
+            ChunkData::Memory(data) => data.remove(hash).map(|data| data.len() as u64),
+            ChunkData::Packed(pack) => pack.remove(hash),
+        }
+    }
+
+    fn is_referenced(&self, hash: &str) -> bool {
+        self.ref_counts.get(hash).is_some_and(|count| *count > 0)
+    }
+
+    fn release(&mut self, hashes: &[String]) {
+        for hash in hashes {
+            if let Some(count) = self.ref_counts.get_mut(hash) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+}
 
 /// Content-addressable storage that deduplicates based on content hash
+///
+/// Small objects are stored as a single content block. Objects above the
+/// chunking threshold are split with FastCDC, so partially similar objects
+/// (VM images, edited videos) share the chunks they have in common.
+///
+/// Storage opened with [`ContentAddressableStorage::open`] keeps chunks in
+/// pack files and logs index changes to a journal, so it survives restarts;
+/// otherwise everything is held in memory.
 pub struct ContentAddressableStorage {
-    /// Maps content hash to object data
-    content_store: Arc<RwLock<HashMap<String, Vec<u8>>>>,
-    /// Maps object ID to content hash and metadata
-    object_index: Arc<RwLock<HashMap<String, (String, ObjectMetadata)>>>,
-    /// Maps content hash to reference count for garbage collection
-    ref_counts: Arc<RwLock<HashMap<String, u64>>>,
+    /// Content blocks by hash, with their reference counts
+    chunks: Arc<RwLock<ChunkStore>>,
+    object_index: Arc<RwLock<ObjectIndex>>,
+    /// Present for storage opened on disk
+    journal: Option<ObjectJournal>,
+    chunker: ChunkerConfig,
+    chunking_threshold: u64,
     max_size: Option<u64>,
+    compaction_dead_ratio: f64,
+    gc_status: Arc<parking_lot::Mutex<GcStatus>>,
+    /// Serializes garbage collection and compaction passes
+    gc_lock: tokio::sync::Mutex<()>,
+    metrics: Option<Arc<MetricsCollector>>,
 }
 
 impl ContentAddressableStorage {
     /// Create a new content-addressable storage
     pub fn new() -> Self {
+        Self::with_chunk_data(ChunkData::Memory(HashMap::new()), HashMap::new(), None)
+    }
+
+    fn with_chunk_data(data: ChunkData, object_index: ObjectIndex, journal: Option<ObjectJournal>) -> Self {
+        let mut chunks = ChunkStore::new(data);
+        for (hashes, _) in object_index.values() {
+            for hash in hashes {
+                *chunks.ref_counts.entry(hash.clone()).or_insert(0) += 1;
+            }
+        }
         Self {
-            content_store: Arc::new(RwLock::new(HashMap::new())),
-            object_index: Arc::new(RwLock::new(HashMap::new())),
-            ref_counts: Arc::new(RwLock::new(HashMap::new())),
+            chunks: Arc::new(RwLock::new(chunks)),
+            object_index: Arc::new(RwLock::new(object_index)),
+            journal,
+            chunker: ChunkerConfig::default(),
+            chunking_threshold: DEFAULT_CHUNKING_THRESHOLD,
             max_size: None,
+            compaction_dead_ratio: DEFAULT_COMPACTION_DEAD_RATIO,
+            gc_status: Arc::new(parking_lot::Mutex::new(GcStatus::default())),
+            gc_lock: tokio::sync::Mutex::new(()),
+            metrics: None,
         }
     }
     
+    /// Open storage persisted in `dir`, creating it if needed
+    ///
+    /// Chunks are kept in pack files under `dir/packs` and object index
+    /// changes in `dir/objects.journal`. Reference counts are rebuilt from the
+    /// index, so chunks left unreferenced by a crash are collected by the next
+    /// garbage collection.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_segment_size(dir, DEFAULT_SEGMENT_BYTES)
+    }
+    
+    /// Open storage persisted in `dir`, rolling pack segments over at `segment_bytes`
+    pub fn open_with_segment_size(dir: impl AsRef<Path>, segment_bytes: u64) -> Result<Self> {
+        let dir = dir.as_ref();
+        let pack = PackStore::open(dir.join("packs"), segment_bytes)?;
+        let (journal, object_index) = ObjectJournal::open(dir.join("objects.journal"))?;
+        info!("Opened content-addressable storage in {} with {} objects", dir.display(), object_index.len());
+        Ok(Self::with_chunk_data(ChunkData::Packed(Arc::new(pack)), object_index, Some(journal)))
+    }
+    
     /// Create with size limit
     pub fn with_max_size(max_size: u64) -> Self {
         Self {
-            content_store: Arc::new(RwLock::new(HashMap::new())),
-            object_index: Arc::new(RwLock::new(HashMap::new())),
-            ref_counts: Arc::new(RwLock::new(HashMap::new())),
             max_size: Some(max_size),
+            ..Self::new()
         }
     }
     
+    /// Chunk objects larger than `threshold` bytes with the given chunk sizes
+    pub fn with_chunking(mut self, threshold: u64, chunker: ChunkerConfig) -> Result<Self> {
+        chunker.validate()?;
+        self.chunking_threshold = threshold;
+        self.chunker = chunker;
+        Ok(self)
+    }
+    
+    /// Rewrite pack segments once at least `dead_ratio` of them is dead space
+    pub fn with_compaction_threshold(mut self, dead_ratio: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&dead_ratio) {
+            return Err(NimbuxError::Configuration(format!("Compaction threshold {} is not between 0 and 1", dead_ratio)));
+        }
+        self.compaction_dead_ratio = dead_ratio;
+        Ok(self)
+    }
+    
+    /// Publish garbage collection metrics to `metrics` after each pass
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
     /// Calculate content hash for data
     fn calculate_hash(data: &[u8]) -> String {
         let mut hasher = Hasher::new();
@@ -53,31 +230,205 @@ impl ContentAddressableStorage {
         hasher.finalize().to_hex().to_string()
     }
     
-    /// Calculate current storage usage
-    async fn calculate_usage(&self) -> Result<u64> {
-        let content_store = self.content_store.read().await;
-        Ok(content_store.values().map(|data| data.len() as u64).sum())
+    /// Content blocks of an object, with their hashes
+    fn split<'a>(&self, data: &'a [u8]) -> Vec<(String, &'a [u8])> {
+        if (data.len() as u64) <= self.chunking_threshold {
+            return vec![(Self::calculate_hash(data), data)];
+        }
+        chunking::chunks(data, &self.chunker)
+            .map(|chunk| (Self::calculate_hash(chunk), chunk))
+            .collect()
+    }
+    
+    /// Progress of the running garbage collection and totals so far
+    pub fn gc_status(&self) -> GcStatus {
+        self.gc_status.lock().clone()
     }
     
-    /// Increment reference count for a content hash
-    async fn increment_ref_count(&self, content_hash: &str) {
-        let mut ref_counts = self.ref_counts.write().await;
-        *ref_counts.entry(content_hash.to_string()).or_insert(0) += 1;
+    /// Mark-and-sweep garbage collection, returning what was freed
+    ///
+    /// Marking walks the object index under a read lock, so reads carry on
+    /// and writes wait only for the walk. The sweep then drops chunks that
+    /// were unmarked and still have no references, which keeps chunks a
+    /// concurrent put started using. If no write happened in between, the
+    /// stored reference counts are also checked against the marked counts
+    /// and corrected.
+    pub async fn garbage_collect(&self) -> GcReport {
+        let _pass = self.gc_lock.lock().await;
+        let started = Instant::now();
+        let report = self.mark_and_sweep().await;
+        
+        let status = {
+            let mut status = self.gc_status.lock();
+            status.record_gc(report);
+            status.finish(started.elapsed().as_millis() as u64);
+            status.clone()
+        };
+        self.publish_metrics(&status).await;
+        
+        if report.chunks_removed > 0 {
+            debug!("Garbage collected {} chunks ({} bytes)", report.chunks_removed, report.bytes_freed);
+        }
+        report
     }
     
-    /// Decrement reference count for a content hash
-    async fn decrement_ref_count(&self, content_hash: &str) -> Result<()> {
-        let mut ref_counts = self.ref_counts.write().await;
-        if let Some(count) = ref_counts.get_mut(content_hash) {
-            *count -= 1;
-            if *count == 0 {
-                ref_counts.remove(content_hash);
-                // Remove from content store as well
-                let mut content_store = self.content_store.write().await;
-                content_store.remove(content_hash);
+    async fn mark_and_sweep(&self) -> GcReport {
+        let mut report = GcReport::default();
+        
+        // Mark
+        let (marked, epoch) = {
+            let object_index = self.object_index.read().await;
+            let epoch = self.chunks.read().await.write_epoch;
+            self.gc_status.lock().begin(object_index.len() as u64);
+            
+            let mut marked: HashMap<String, u64> = HashMap::new();
+            for (index, (hashes, _)) in object_index.values().enumerate() {
+                for hash in hashes {
+                    *marked.entry(hash.clone()).or_insert(0) += 1;
+                }
+                if index % 1024 == 1023 {
+                    self.gc_status.lock().objects_marked = index as u64 + 1;
+                }
+            }
+            report.objects_marked = object_index.len() as u64;
+            (marked, epoch)
+        };
+        {
+            let mut status = self.gc_status.lock();
+            status.objects_marked = report.objects_marked;
+            status.phase = GcPhase::Sweeping;
+        }
+        
+        // Sweep
+        let mut chunks = self.chunks.write().await;
+        report.validated = chunks.write_epoch == epoch;
+        if report.validated {
+            for (hash, count) in &marked {
+                if !chunks.contains(hash) {
+                    report.missing_chunks += 1;
+                    error!("Chunk {} is referenced by {} objects but missing from the store", hash, count);
+                }
+                let stored = chunks.ref_counts.get(hash).copied().unwrap_or(0);
+                if stored != *count {
+                    warn!("Correcting reference count of chunk {} from {} to {}", hash, stored, count);
+                    report.refcount_corrections += 1;
+                    chunks.ref_counts.insert(hash.clone(), *count);
+                }
+            }
+            let leaked: Vec<String> = chunks
+                .ref_counts
+                .iter()
+                .filter(|(hash, count)| **count > 0 && !marked.contains_key(*hash))
+                .map(|(hash, _)| hash.clone())
+                .collect();
+            for hash in leaked {
+                warn!("Correcting reference count of unreferenced chunk {} to 0", hash);
+                report.refcount_corrections += 1;
+                chunks.ref_counts.insert(hash, 0);
+            }
+        }
+        
+        for hash in chunks.hashes() {
+            if marked.contains_key(&hash) || chunks.is_referenced(&hash) {
+                continue;
+            }
+            chunks.ref_counts.remove(&hash);
+            if let Some(size) = chunks.remove(&hash) {
+                report.chunks_removed += 1;
+                report.bytes_freed += size;
+            }
+        }
+        // Counts for chunks whose data is already gone
+        chunks.ref_counts.retain(|hash, count| *count > 0 || marked.contains_key(hash));
+        
+        report
+    }
+    
+    /// Rewrite fragmented pack segments and an oversized journal
+    ///
+    /// Reads are served throughout. The journal is rewritten when it holds
+    /// more than twice as many entries as there are objects; writes wait
+    /// while it is. In-memory storage has nothing to compact.
+    pub async fn compact(&self) -> Result<CompactionReport> {
+        let pack = match &self.chunks.read().await.data {
+            ChunkData::Packed(pack) => Arc::clone(pack),
+            ChunkData::Memory(_) => return Ok(CompactionReport::default()),
+        };
+        
+        let _pass = self.gc_lock.lock().await;
+        let started = Instant::now();
+        self.gc_status.lock().phase = GcPhase::Compacting;
+        
+        let dead_ratio = self.compaction_dead_ratio;
+        let result = tokio::task::spawn_blocking(move || pack.compact(dead_ratio))
+            .await
+            .map_err(|e| NimbuxError::Internal(format!("Compaction task failed: {}", e)))
+            .and_then(|result| result);
+        let mut report = match result {
+            Ok(report) => report,
+            Err(e) => {
+                self.gc_status.lock().phase = GcPhase::Idle;
+                return Err(e);
+            }
+        };
+        
+        if let Some(journal) = &self.journal {
+            let object_index = self.object_index.read().await;
+            if journal.entries() > 2 * object_index.len() as u64 + 1024 {
+                journal.rewrite(&object_index)?;
+                report.journal_rewritten = true;
+            }
+        }
+        
+        let status = {
+            let mut status = self.gc_status.lock();
+            status.record_compaction(report);
+            status.finish(started.elapsed().as_millis() as u64);
+            status.clone()
+        };
+        self.publish_metrics(&status).await;
+        
+        if report.segments_rewritten > 0 {
+            info!("Compacted {} pack segments, reclaiming {} bytes", report.segments_rewritten, report.bytes_reclaimed);
+        }
+        Ok(report)
+    }
+    
+    async fn publish_metrics(&self, status: &GcStatus) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        let values = [
+            ("nimbux_gc_runs_total", status.runs_total as f64, MetricType::Counter),
+            ("nimbux_gc_chunks_removed_total", status.chunks_removed_total as f64, MetricType::Counter),
+            ("nimbux_gc_bytes_freed_total", status.bytes_freed_total as f64, MetricType::Counter),
+            ("nimbux_gc_refcount_corrections_total", status.refcount_corrections_total as f64, MetricType::Counter),
+            ("nimbux_compaction_segments_rewritten_total", status.segments_rewritten_total as f64, MetricType::Counter),
+            ("nimbux_compaction_bytes_reclaimed_total", status.file_bytes_reclaimed_total as f64, MetricType::Counter),
+            ("nimbux_gc_last_duration_ms", status.last_duration_ms.unwrap_or(0) as f64, MetricType::Gauge),
+        ];
+        for (name, value, metric_type) in values {
+            if let Err(e) = metrics.add_custom_metric(name.to_string(), value, HashMap::new(), metric_type).await {
+                debug!("Failed to record {}: {}", name, e);
             }
         }
-        Ok(())
+    }
+    
+    /// Run garbage collection and compaction on an interval for as long as the storage is alive
+    pub fn spawn_garbage_collector(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let report = self.garbage_collect().await;
+                if report.chunks_removed > 0 {
+                    info!("Reclaimed {} bytes from {} unreferenced chunks", report.bytes_freed, report.chunks_removed);
+                }
+                if let Err(e) = self.compact().await {
+                    error!("Compaction failed: {}", e);
+                }
+            }
+        })
     }
 }
 
@@ -90,18 +441,23 @@ impl Default for ContentAddressableStorage {
 #[async_trait]
 impl StorageBackend for ContentAddressableStorage {
     async fn put(&self, object: Object) -> Result<()> {
-        let content_hash = Self::calculate_hash(&object.data);
+        let blocks = self.split(&object.data);
+        let hashes: Vec<String> = blocks.iter().map(|(hash, _)| hash.clone()).collect();
         
-        // Check if this is an update to an existing object
-        let existing_hash = {
-            let object_index = self.object_index.read().await;
-            object_index.get(&object.metadata.id).map(|(hash, _)| hash.clone())
-        };
+        // The index lock is taken first everywhere, so a concurrent delete
+        // cannot release a chunk between storing it and referencing it
+        let mut object_index = self.object_index.write().await;
+        let mut chunks = self.chunks.write().await;
         
-        // Check size limits if configured
+        // Check size limits if configured; only chunks not stored yet take space
         if let Some(max_size) = self.max_size {
-            let current_usage = self.calculate_usage().await?;
-            let additional_size = if existing_hash.is_some() { 0 } else { object.data.len() as u64 };
+            let current_usage = chunks.size();
+            let mut new_hashes = HashSet::new();
+            let additional_size: u64 = blocks
+                .iter()
+                .filter(|(hash, _)| !chunks.contains(hash) && new_hashes.insert(hash))
+                .map(|(_, data)| data.len() as u64)
+                .sum();
             if current_usage + additional_size > max_size {
                 return Err(NimbuxError::Storage(
                     format!("Storage limit exceeded: {} + {} > {}", 
@@ -110,58 +466,58 @@ impl StorageBackend for ContentAddressableStorage {
             }
         }
         
-        // Store content if it doesn't exist
-        {
-            let mut content_store = self.content_store.write().await;
-            if !content_store.contains_key(&content_hash) {
-                content_store.insert(content_hash.clone(), object.data.clone());
-            }
+        // Store content that doesn't exist yet; chunks must be durable before
+        // the journal refers to them
+        for (hash, data) in &blocks {
+            chunks.insert(hash, data)?;
         }
-        
-        // Update object index
-        {
-            let mut object_index = self.object_index.write().await;
-            object_index.insert(object.metadata.id.clone(), (content_hash.clone(), object.metadata));
+        if let (Some(journal), ChunkData::Packed(pack)) = (&self.journal, &chunks.data) {
+            pack.sync()?;
+            journal.record_put(&hashes, &object.metadata)?;
         }
         
-        // Update reference counts
-        if let Some(old_hash) = existing_hash {
-            if old_hash != content_hash {
-                self.decrement_ref_count(&old_hash).await?;
-            }
+        // Reference every block, and release the previous version only after
+        // that, so blocks both versions share are never unreferenced
+        for hash in &hashes {
+            *chunks.ref_counts.entry(hash.clone()).or_insert(0) += 1;
         }
-        self.increment_ref_count(&content_hash).await;
+        if let Some((old_hashes, _)) = object_index.insert(object.metadata.id.clone(), (hashes, object.metadata)) {
+            chunks.release(&old_hashes);
+        }
+        chunks.write_epoch += 1;
         
         Ok(())
     }
     
     async fn get(&self, id: &str) -> Result<Object> {
-        let (content_hash, metadata) = {
-            let object_index = self.object_index.read().await;
-            let entry = object_index.get(id)
-                .ok_or_else(|| NimbuxError::ObjectNotFound { object_id: id.to_string() })?;
-            (entry.0.clone(), entry.1.clone())
-        };
+        let object_index = self.object_index.read().await;
+        let (hashes, metadata) = object_index.get(id)
+            .ok_or_else(|| NimbuxError::ObjectNotFound { object_id: id.to_string() })?;
         
-        let data = {
-            let content_store = self.content_store.read().await;
-            content_store.get(&content_hash)
-                .ok_or_else(|| NimbuxError::Storage("Content not found in store".to_string()))?
-                .clone()
-        };
+        let chunks = self.chunks.read().await;
+        let mut data = Vec::with_capacity(metadata.size as usize);
+        for hash in hashes {
+            chunks.read_into(hash, &mut data)?;
+        }
         
-        Ok(Object { metadata, data })
+        Ok(Object { metadata: metadata.clone(), data })
     }
     
     async fn delete(&self, id: &str) -> Result<()> {
-        let content_hash = {
-            let mut object_index = self.object_index.write().await;
-            let entry = object_index.remove(id)
-                .ok_or_else(|| NimbuxError::ObjectNotFound { object_id: id.to_string() })?;
-            entry.0
+        let mut object_index = self.object_index.write().await;
+        if !object_index.contains_key(id) {
+            return Err(NimbuxError::ObjectNotFound { object_id: id.to_string() });
+        }
+        if let Some(journal) = &self.journal {
+            journal.record_delete(id)?;
+        }
+        let Some((hashes, _)) = object_index.remove(id) else {
+            return Ok(());
         };
         
-        self.decrement_ref_count(&content_hash).await?;
+        let mut chunks = self.chunks.write().await;
+        chunks.release(&hashes);
+        chunks.write_epoch += 1;
         Ok(())
     }
     
@@ -204,15 +560,15 @@ impl StorageBackend for ContentAddressableStorage {
     
     async fn stats(&self) -> Result<StorageStats> {
         let object_index = self.object_index.read().await;
-        let content_store = self.content_store.read().await;
+        let chunks = self.chunks.read().await;
         
         let total_objects = object_index.len() as u64;
-        let total_size: u64 = content_store.values().map(|data| data.len() as u64).sum();
+        let total_size = chunks.size();
         
         Ok(StorageStats {
             total_objects,
             total_size,
-            available_space: self.max_size.unwrap_or(u64::MAX) - total_size,
+            available_space: self.max_size.unwrap_or(u64::MAX).saturating_sub(total_size),
             used_space: total_size,
         })
     }
@@ -221,38 +577,120 @@ impl StorageBackend for ContentAddressableStorage {
 impl ContentAddressableStorage {
     /// Get deduplication statistics
     pub async fn get_dedup_stats(&self) -> Result<DedupStats> {
-        let content_store = self.content_store.read().await;
         let object_index = self.object_index.read().await;
-        let ref_counts = self.ref_counts.read().await;
-        
-        let unique_content_blocks = content_store.len() as u64;
-        let total_objects = object_index.len() as u64;
-        let total_content_size: u64 = content_store.values().map(|data| data.len() as u64).sum();
+        let chunks = self.chunks.read().await;
         
-        let deduplication_ratio = if total_objects > 0 {
-            (total_objects as f64 - unique_content_blocks as f64) / total_objects as f64
-        } else {
-            0.0
+        let mut stats = DedupStats {
+            total_objects: object_index.len() as u64,
+            ..Default::default()
         };
+        let mut buckets: HashMap<&str, (BucketDedupStats, HashSet<&str>)> = HashMap::new();
+        for (hashes, metadata) in object_index.values() {
+            stats.logical_size += metadata.size;
+            if hashes.len() > 1 {
+                stats.chunked_objects += 1;
+            }
+            
+            // Objects are named `<bucket>/<key>`; others are only counted in the totals
+            let Some((bucket, _)) = metadata.name.split_once('/') else {
+                continue;
+            };
+            let (usage, bucket_chunks) = buckets.entry(bucket).or_insert_with(|| {
+                (BucketDedupStats { bucket: bucket.to_string(), ..Default::default() }, HashSet::new())
+            });
+            usage.object_count += 1;
+            usage.logical_size += metadata.size;
+            for hash in hashes {
+                if bucket_chunks.insert(hash) {
+                    usage.stored_size += chunks.chunk_size(hash);
+                }
+            }
+        }
         
-        Ok(DedupStats {
-            unique_content_blocks,
-            total_objects,
-            total_content_size,
-            deduplication_ratio,
-            reference_counts: ref_counts.clone(),
-        })
+        for hash in chunks.hashes() {
+            let size = chunks.chunk_size(&hash);
+            if chunks.is_referenced(&hash) {
+                stats.unique_content_blocks += 1;
+                stats.total_content_size += size;
+            } else {
+                stats.reclaimable_size += size;
+            }
+        }
+        stats.deduplication_ratio = savings_ratio(stats.logical_size, stats.total_content_size);
+        stats.reference_counts = chunks
+            .ref_counts
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(hash, count)| (hash.clone(), *count))
+            .collect();
+        
+        stats.buckets = buckets
+            .into_values()
+            .map(|(mut usage, _)| {
+                usage.deduplication_ratio = savings_ratio(usage.logical_size, usage.stored_size);
+                usage
+            })
+            .collect();
+        stats.buckets.sort_by(|a, b| a.bucket.cmp(&b.bucket));
+        
+        Ok(stats)
+    }
+    
+    /// Deduplication statistics of one bucket
+    pub async fn bucket_dedup_stats(&self, bucket: &str) -> Result<BucketDedupStats> {
+        Ok(self
+            .get_dedup_stats()
+            .await?
+            .buckets
+            .into_iter()
+            .find(|usage| usage.bucket == bucket)
+            .unwrap_or_else(|| BucketDedupStats { bucket: bucket.to_string(), ..Default::default() }))
     }
 }
 
+/// Fraction of `logical` bytes that deduplication avoided storing
+fn savings_ratio(logical: u64, stored: u64) -> f64 {
+    if logical == 0 {
+        return 0.0;
+    }
+    1.0 - stored.min(logical) as f64 / logical as f64
+}
+
 /// Deduplication statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DedupStats {
+    /// Content blocks referenced by at least one object
     pub unique_content_blocks: u64,
     pub total_objects: u64,
+    /// Objects stored as more than one chunk
+    pub chunked_objects: u64,
+    /// Bytes of referenced content blocks
     pub total_content_size: u64,
+    /// Sum of object sizes before deduplication
+    pub logical_size: u64,
+    /// Bytes held by unreferenced chunks until the next garbage collection
+    pub reclaimable_size: u64,
+    /// Fraction of the logical size saved by deduplication
     pub deduplication_ratio: f64,
     pub reference_counts: HashMap<String, u64>,
+    /// Per-bucket breakdown, sorted by bucket name
+    pub buckets: Vec<BucketDedupStats>,
+}
+
+/// Deduplication within one bucket
+///
+/// Chunks shared with other buckets count towards each bucket's stored size,
+/// so the ratio reflects only duplication inside the bucket.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BucketDedupStats {
+    pub bucket: String,
+    pub object_count: u64,
+    /// Sum of object sizes before deduplication
+    pub logical_size: u64,
+    /// Bytes of the distinct chunks the bucket's objects reference
+    pub stored_size: u64,
+    /// Fraction of the logical size saved by deduplication
+    pub deduplication_ratio: f64,
 }
 
 #[cfg(test)]
@@ -301,4 +739,107 @@ mod tests {
         assert_eq!(stats.unique_content_blocks, 0);
         assert_eq!(stats.total_objects, 0);
     }
+    
+    #[tokio::test]
+    async fn test_similar_large_objects_share_chunks() {
+        let storage = ContentAddressableStorage::new()
+            .with_chunking(64 * 1024, ChunkerConfig::new(2048, 8192, 32768).unwrap())
+            .unwrap();
+        
+        let mut state = 42u64;
+        let image: Vec<u8> = (0..1_000_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let mut edited = image.clone();
+        edited.splice(500_000..500_000, b"patched".iter().copied());
+        
+        storage.put(Object::with_id("vm1".to_string(), "images/base.img".to_string(), image.clone(), None)).await.unwrap();
+        storage.put(Object::with_id("vm2".to_string(), "images/patched.img".to_string(), edited.clone(), None)).await.unwrap();
+        storage.put(Object::with_id("note".to_string(), "docs/readme.txt".to_string(), b"hello".to_vec(), None)).await.unwrap();
+        
+        assert_eq!(storage.get("vm2").await.unwrap().data, edited);
+        
+        let stats = storage.get_dedup_stats().await.unwrap();
+        assert_eq!(stats.chunked_objects, 2);
+        assert!(stats.total_content_size < (image.len() + image.len() / 10) as u64);
+        
+        let images = storage.bucket_dedup_stats("images").await.unwrap();
+        assert_eq!(images.object_count, 2);
+        assert!(images.deduplication_ratio > 0.45, "ratio {}", images.deduplication_ratio);
+        assert_eq!(storage.bucket_dedup_stats("docs").await.unwrap().deduplication_ratio, 0.0);
+        
+        // Chunks only the deleted object used are reclaimed by garbage collection
+        storage.delete("vm2").await.unwrap();
+        let reclaimable = storage.get_dedup_stats().await.unwrap().reclaimable_size;
+        assert!(reclaimable > 0);
+        let report = storage.garbage_collect().await;
+        assert_eq!(report.bytes_freed, reclaimable);
+        assert_eq!(storage.get("vm1").await.unwrap().data, image);
+        assert_eq!(storage.get_dedup_stats().await.unwrap().reclaimable_size, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_gc_corrects_reference_counts() {
+        let storage = ContentAddressableStorage::new();
+        storage.put(Object::with_id("obj".to_string(), "a/obj".to_string(), b"kept".to_vec(), None)).await.unwrap();
+        let kept = ContentAddressableStorage::calculate_hash(b"kept");
+        
+        // A leaked count keeps an orphan alive; an inflated one would hide a later delete
+        {
+            let mut chunks = storage.chunks.write().await;
+            chunks.insert(&ContentAddressableStorage::calculate_hash(b"orphan"), b"orphan").unwrap();
+            chunks.ref_counts.insert(ContentAddressableStorage::calculate_hash(b"orphan"), 2);
+            chunks.ref_counts.insert(kept.clone(), 5);
+        }
+        
+        let report = storage.garbage_collect().await;
+        assert!(report.validated);
+        assert_eq!(report.refcount_corrections, 2);
+        assert_eq!(report.chunks_removed, 1);
+        assert_eq!(report.bytes_freed, 6);
+        assert_eq!(storage.get("obj").await.unwrap().data, b"kept");
+        
+        storage.delete("obj").await.unwrap();
+        assert_eq!(storage.garbage_collect().await.chunks_removed, 1);
+        
+        let status = storage.gc_status();
+        assert_eq!(status.phase, GcPhase::Idle);
+        assert_eq!(status.runs_total, 2);
+        assert_eq!(status.bytes_freed_total, 10);
+        assert_eq!(status.refcount_corrections_total, 2);
+    }
+    
+    #[tokio::test]
+    async fn test_packed_storage_compacts_and_reopens() {
+        let dir = std::env::temp_dir().join(format!("nimbux-cas-{}", uuid::Uuid::new_v4()));
+        let payload = |i: u8| vec![i; 1000];
+        {
+            let storage = ContentAddressableStorage::open_with_segment_size(&dir, 4096).unwrap();
+            for i in 0..12u8 {
+                storage.put(Object::with_id(format!("obj{}", i), format!("b/obj{}", i), payload(i), None)).await.unwrap();
+            }
+            for i in 0..10u8 {
+                storage.delete(&format!("obj{}", i)).await.unwrap();
+            }
+            
+            let report = storage.garbage_collect().await;
+            assert_eq!(report.chunks_removed, 10);
+            let compaction = storage.compact().await.unwrap();
+            assert!(compaction.segments_rewritten > 0);
+            assert!(compaction.bytes_reclaimed >= 3 * 1000);
+            assert_eq!(storage.get("obj11").await.unwrap().data, payload(11));
+        }
+        
+        let storage = ContentAddressableStorage::open_with_segment_size(&dir, 4096).unwrap();
+        assert!(!storage.exists("obj0").await.unwrap());
+        assert_eq!(storage.get("obj10").await.unwrap().data, payload(10));
+        assert_eq!(storage.get("obj11").await.unwrap().data, payload(11));
+        assert_eq!(storage.garbage_collect().await.refcount_corrections, 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,111 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Garbage collection progress and reports
+
+use serde::{Deserialize, Serialize};
+
+/// Phase of the garbage collector
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GcPhase {
+    #[default]
+    Idle,
+    /// Walking the object index to find referenced chunks
+    Marking,
+    /// Validating reference counts and dropping unreferenced chunks
+    Sweeping,
+    /// Rewriting pack files that are mostly dead space
+    Compacting,
+}
+
+/// Result of a garbage collection pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GcReport {
+    pub objects_marked: u64,
+    pub chunks_removed: u64,
+    /// Bytes of chunk data dropped; on disk they are returned by the next compaction
+    pub bytes_freed: u64,
+    /// Stored reference counts that disagreed with the object index and were corrected
+    pub refcount_corrections: u64,
+    /// Chunks objects reference that are missing from the store
+    pub missing_chunks: u64,
+    /// False when writes during marking meant reference counts could not be validated
+    pub validated: bool,
+}
+
+/// Result of a compaction pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionReport {
+    pub segments_rewritten: u64,
+    /// Live chunks copied out of the rewritten segments
+    pub chunks_moved: u64,
+    /// File bytes returned to the filesystem
+    pub bytes_reclaimed: u64,
+    /// Whether the object journal was rewritten as a snapshot
+    pub journal_rewritten: bool,
+}
+
+/// Progress of the running pass and totals since the store was opened
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GcStatus {
+    pub phase: GcPhase,
+    pub objects_marked: u64,
+    /// Objects in the index when the running pass started
+    pub objects_total: u64,
+    pub runs_total: u64,
+    pub chunks_removed_total: u64,
+    pub bytes_freed_total: u64,
+    pub refcount_corrections_total: u64,
+    pub segments_rewritten_total: u64,
+    pub file_bytes_reclaimed_total: u64,
+    /// Unix time the last pass finished
+    pub last_run_at: Option<u64>,
+    pub last_duration_ms: Option<u64>,
+    pub last_report: Option<GcReport>,
+    pub last_compaction: Option<CompactionReport>,
+}
+
+impl GcStatus {
+    /// Fraction of the mark phase done, 1.0 when idle
+    pub fn progress(&self) -> f64 {
+        match self.phase {
+            GcPhase::Marking if self.objects_total > 0 => self.objects_marked as f64 / self.objects_total as f64,
+            GcPhase::Marking => 0.0,
+            _ => 1.0,
+        }
+    }
+
+    pub(crate) fn begin(&mut self, objects_total: u64) {
+        self.phase = GcPhase::Marking;
+        self.objects_marked = 0;
+        self.objects_total = objects_total;
+    }
+
+    pub(crate) fn record_gc(&mut self, report: GcReport) {
+        self.runs_total += 1;
+        self.chunks_removed_total += report.chunks_removed;
+        self.bytes_freed_total += report.bytes_freed;
+        self.refcount_corrections_total += report.refcount_corrections;
+        self.last_report = Some(report);
+    }
+
+    pub(crate) fn record_compaction(&mut self, report: CompactionReport) {
+        self.segments_rewritten_total += report.segments_rewritten;
+        self.file_bytes_reclaimed_total += report.bytes_reclaimed;
+        self.last_compaction = Some(report);
+    }
+
+    pub(crate) fn finish(&mut self, duration_ms: u64) {
+        self.phase = GcPhase::Idle;
+        self.last_duration_ms = Some(duration_ms);
+        self.last_run_at = Some(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        );
+    }
+}
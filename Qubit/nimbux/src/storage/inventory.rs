@@ -0,0 +1,595 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Scheduled inventory reports listing every object of a bucket as CSV or Parquet
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use super::placement::{self, StorageClass, STORAGE_CLASS_TAG};
+use super::restore::{RestoreCoordinator, RESTORED_UNTIL_TAG};
+use super::{Object, ObjectMetadata, StorageBackend};
+use crate::errors::{NimbuxError, Result};
+
+/// S3 encryption status of an object encrypted at rest (SSE-S3, SSE-KMS or
+/// SSE-C), set by whatever encrypted it. Objects without it are `NOT-SSE`.
+pub const ENCRYPTION_TAG: &str = "nimbux-server-side-encryption";
+
+const CONFIG_PREFIX: &str = ".inventory/buckets/";
+const DEFAULT_DESTINATION_PREFIX: &str = "inventory";
+const NOT_ENCRYPTED: &str = "NOT-SSE";
+/// Rows per Parquet row group
+const ROW_GROUP_SIZE: usize = 100_000;
+/// Columns of every report, in order
+const COLUMNS: [&str; 7] = ["bucket", "key", "size", "last_modified", "checksum", "storage_class", "encryption_status"];
+const PARQUET_SCHEMA: &str = "
+    message inventory {
+        REQUIRED BYTE_ARRAY bucket (UTF8);
+        REQUIRED BYTE_ARRAY key (UTF8);
+        REQUIRED INT64 size;
+        REQUIRED INT64 last_modified (TIMESTAMP_MILLIS);
+        REQUIRED BYTE_ARRAY checksum (UTF8);
+        REQUIRED BYTE_ARRAY storage_class (UTF8);
+        REQUIRED BYTE_ARRAY encryption_status (UTF8);
+    }
+";
+
+/// File format of inventory reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InventoryFormat {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+impl InventoryFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            InventoryFormat::Csv => "data.csv",
+            InventoryFormat::Parquet => "data.parquet",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            InventoryFormat::Csv => "text/csv",
+            InventoryFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+/// How often a report is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InventoryFrequency {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+impl InventoryFrequency {
+    fn period(self) -> chrono::Duration {
+        match self {
+            InventoryFrequency::Daily => chrono::Duration::days(1),
+            InventoryFrequency::Weekly => chrono::Duration::weeks(1),
+        }
+    }
+}
+
+/// What to list and where to write it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryConfig {
+    /// Bucket the reports are written to; it cannot be the listed bucket
+    pub destination_bucket: String,
+    /// Key prefix of the reports, `inventory` by default
+    #[serde(default)]
+    pub destination_prefix: Option<String>,
+    #[serde(default)]
+    pub format: InventoryFormat,
+    #[serde(default)]
+    pub frequency: InventoryFrequency,
+    /// Only list keys starting with this
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// An inventory configuration of a bucket and its last report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketInventory {
+    pub bucket: String,
+    pub id: String,
+    pub config: InventoryConfig,
+    pub updated_at: DateTime<Utc>,
+    pub last_export: Option<InventoryExport>,
+}
+
+impl BucketInventory {
+    /// Enabled and never run, or last run a period or more before `now`
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.config.enabled
+            && self
+                .last_export
+                .as_ref()
+                .is_none_or(|export| now >= export.started_at + self.config.frequency.period())
+    }
+}
+
+/// A written report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryExport {
+    pub started_at: DateTime<Utc>,
+    pub objects: u64,
+    pub bytes: u64,
+    /// Object IDs of the report and its manifest
+    pub data_id: String,
+    pub manifest_id: String,
+}
+
+/// A report file and how to check it was read whole
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryFile {
+    pub key: String,
+    pub size: u64,
+    pub checksum: String,
+    pub rows: u64,
+}
+
+/// Written next to each report as `manifest.json`; ingesters read the
+/// manifest, then the files it lists
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryManifest {
+    pub source_bucket: String,
+    pub destination_bucket: String,
+    pub inventory_id: String,
+    pub format: InventoryFormat,
+    pub created_at: DateTime<Utc>,
+    pub file_schema: Vec<String>,
+    pub files: Vec<InventoryFile>,
+}
+
+/// One object in a report
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InventoryRow {
+    bucket: String,
+    key: String,
+    size: u64,
+    last_modified: DateTime<Utc>,
+    checksum: String,
+    storage_class: StorageClass,
+    encryption_status: String,
+}
+
+impl InventoryRow {
+    fn of(bucket: &str, metadata: &ObjectMetadata, storage_class: StorageClass) -> Option<Self> {
+        let key = metadata.id.strip_prefix(bucket)?.strip_prefix('/')?;
+        Some(Self {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            size: metadata.size,
+            last_modified: DateTime::from_timestamp(metadata.updated_at as i64, 0).unwrap_or_default(),
+            checksum: metadata.checksum.clone(),
+            storage_class,
+            encryption_status: metadata
+                .tags
+                .get(ENCRYPTION_TAG)
+                .cloned()
+                .unwrap_or_else(|| NOT_ENCRYPTED.to_string()),
+        })
+    }
+}
+
+/// Writes scheduled inventory reports of buckets into other buckets.
+///
+/// Objects are listed through `storage`, and archived objects in the cold
+/// tier through the restore coordinator, reported as `COLD`. Each report is
+/// written under `<destination>/<prefix>/<bucket>/<id>/<time>/` with a
+/// manifest. Configurations are kept under `.inventory/buckets/<bucket>/<id>`.
+pub struct InventoryExporter {
+    storage: Arc<dyn StorageBackend>,
+    restore: Option<Arc<RestoreCoordinator>>,
+}
+
+impl InventoryExporter {
+    pub fn new(storage: Arc<dyn StorageBackend>) -> Self {
+        Self { storage, restore: None }
+    }
+
+    /// List objects archived by lifecycle transitions too
+    pub fn with_restore(mut self, restore: Arc<RestoreCoordinator>) -> Self {
+        self.restore = Some(restore);
+        self
+    }
+
+    pub async fn inventory(&self, bucket: &str, id: &str) -> Result<Option<BucketInventory>> {
+        match self.storage.get(&config_id(bucket, id)).await {
+            Ok(object) => Ok(Some(serde_json::from_slice(&object.data)?)),
+            Err(NimbuxError::ObjectNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Inventory configurations of a bucket, by ID
+    pub async fn inventories(&self, bucket: &str) -> Result<Vec<BucketInventory>> {
+        let prefix = format!("{}{}/", CONFIG_PREFIX, bucket);
+        self.load(&prefix).await
+    }
+
+    async fn load(&self, prefix: &str) -> Result<Vec<BucketInventory>> {
+        let mut inventories = Vec::new();
+        for metadata in self.storage.list(Some(prefix), None).await? {
+            let object = self.storage.get(&metadata.id).await?;
+            inventories.push(serde_json::from_slice::<BucketInventory>(&object.data)?);
+        }
+        inventories.sort_by(|a, b| (&a.bucket, &a.id).cmp(&(&b.bucket, &b.id)));
+        Ok(inventories)
+    }
+
+    /// Create or replace an inventory configuration, keeping its last report
+    pub async fn set_inventory(&self, bucket: &str, id: &str, config: InventoryConfig) -> Result<BucketInventory> {
+        placement::validate_name(bucket, "bucket name")?;
+        placement::validate_name(id, "inventory ID")?;
+        placement::validate_name(&config.destination_bucket, "destination bucket")?;
+        if config.destination_bucket == bucket {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Inventory {} of {} cannot be written into the bucket it lists",
+                id, bucket
+            )));
+        }
+        if config.destination_prefix.as_deref().is_some_and(|prefix| prefix.trim_matches('/').is_empty()) {
+            return Err(NimbuxError::InvalidRequest("Destination prefix cannot be empty".to_string()));
+        }
+
+        let last_export = self.inventory(bucket, id).await?.and_then(|existing| existing.last_export);
+        let inventory = BucketInventory {
+            bucket: bucket.to_string(),
+            id: id.to_string(),
+            config,
+            updated_at: Utc::now(),
+            last_export,
+        };
+        placement::save(self.storage.as_ref(), config_id(bucket, id), &inventory).await?;
+        tracing::info!("Inventory {} of bucket {} set", id, bucket);
+        Ok(inventory)
+    }
+
+    /// Stop writing reports; reports already written are kept
+    pub async fn delete_inventory(&self, bucket: &str, id: &str) -> Result<()> {
+        match self.storage.delete(&config_id(bucket, id)).await {
+            Ok(()) => {
+                tracing::info!("Inventory {} of bucket {} removed", id, bucket);
+                Ok(())
+            }
+            Err(NimbuxError::ObjectNotFound { .. }) => Err(NimbuxError::ObjectNotFound {
+                object_id: format!("inventory {} of {}", id, bucket),
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write a report now, whether or not one is due
+    pub async fn export(&self, bucket: &str, id: &str, now: DateTime<Utc>) -> Result<InventoryExport> {
+        let mut inventory = self.inventory(bucket, id).await?.ok_or_else(|| NimbuxError::ObjectNotFound {
+            object_id: format!("inventory {} of {}", id, bucket),
+        })?;
+        let rows = self.rows(bucket, inventory.config.prefix.as_deref().unwrap_or_default()).await?;
+        let config = &inventory.config;
+        let data = match config.format {
+            InventoryFormat::Csv => write_csv(&rows)?,
+            InventoryFormat::Parquet => write_parquet(&rows)?,
+        };
+
+        let folder = format!(
+            "{}/{}/{}/{}",
+            config.destination_prefix.as_deref().unwrap_or(DEFAULT_DESTINATION_PREFIX).trim_matches('/'),
+            bucket,
+            id,
+            now.format("%Y-%m-%dT%H-%MZ")
+        );
+        let data_key = format!("{}/{}", folder, config.format.file_name());
+        let data_id = format!("{}/{}", config.destination_bucket, data_key);
+        let object = Object::with_id(data_id.clone(), data_id.clone(), data, Some(config.format.content_type().to_string()));
+        let file = InventoryFile {
+            key: data_key,
+            size: object.metadata.size,
+            checksum: object.metadata.checksum.clone(),
+            rows: rows.len() as u64,
+        };
+        self.storage.put(object).await?;
+
+        let manifest = InventoryManifest {
+            source_bucket: bucket.to_string(),
+            destination_bucket: config.destination_bucket.clone(),
+            inventory_id: id.to_string(),
+            format: config.format,
+            created_at: now,
+            file_schema: COLUMNS.iter().map(|column| column.to_string()).collect(),
+            files: vec![file],
+        };
+        let manifest_id = format!("{}/{}/manifest.json", config.destination_bucket, folder);
+        let object = Object::with_id(
+            manifest_id.clone(),
+            manifest_id.clone(),
+            serde_json::to_vec_pretty(&manifest)?,
+            Some("application/json".to_string()),
+        );
+        self.storage.put(object).await?;
+
+        let export = InventoryExport {
+            started_at: now,
+            objects: rows.len() as u64,
+            bytes: rows.iter().map(|row| row.size).sum(),
+            data_id,
+            manifest_id,
+        };
+        inventory.last_export = Some(export.clone());
+        placement::save(self.storage.as_ref(), config_id(bucket, id), &inventory).await?;
+        tracing::info!("Inventory {} of bucket {} listed {} objects ({} bytes)", id, bucket, export.objects, export.bytes);
+        Ok(export)
+    }
+
+    /// Write every report due by `now`; a failed report is retried on the next pass
+    pub async fn export_due(&self, now: DateTime<Utc>) -> Result<Vec<InventoryExport>> {
+        let mut exports = Vec::new();
+        for inventory in self.load(CONFIG_PREFIX).await?.into_iter().filter(|inventory| inventory.is_due(now)) {
+            match self.export(&inventory.bucket, &inventory.id, now).await {
+                Ok(export) => exports.push(export),
+                Err(e) => tracing::error!("Inventory {} of bucket {} failed: {}", inventory.id, inventory.bucket, e),
+            }
+        }
+        Ok(exports)
+    }
+
+    /// Objects of a bucket, hot and archived, ordered by key
+    async fn rows(&self, bucket: &str, prefix: &str) -> Result<Vec<InventoryRow>> {
+        let listing = format!("{}/{}", bucket, prefix);
+        let mut rows: Vec<InventoryRow> = self
+            .storage
+            .list(Some(&listing), None)
+            .await?
+            .iter()
+            // Restored copies are listed with the archived object
+            .filter(|metadata| !metadata.tags.contains_key(RESTORED_UNTIL_TAG))
+            .filter_map(|metadata| {
+                let class = metadata
+                    .tags
+                    .get(STORAGE_CLASS_TAG)
+                    .and_then(|class| class.parse().ok())
+                    .unwrap_or_default();
+                InventoryRow::of(bucket, metadata, class)
+            })
+            .collect();
+        if let Some(restore) = &self.restore {
+            let archived = restore.archived(&listing).await?;
+            rows.extend(archived.iter().filter_map(|metadata| InventoryRow::of(bucket, metadata, StorageClass::Cold)));
+        }
+        rows.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(rows)
+    }
+
+    /// Write due reports on an interval for as long as the exporter is alive
+    pub fn spawn_scheduler(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.export_due(Utc::now()).await {
+                    tracing::error!("Inventory pass failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+fn write_csv(rows: &[InventoryRow]) -> Result<Vec<u8>> {
+    let failed = |e: csv::Error| NimbuxError::Internal(format!("Failed to write CSV inventory: {}", e));
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(COLUMNS).map_err(failed)?;
+    for row in rows {
+        writer
+            .write_record([
+                row.bucket.as_str(),
+                row.key.as_str(),
+                &row.size.to_string(),
+                &row.last_modified.to_rfc3339(),
+                row.checksum.as_str(),
+                row.storage_class.name(),
+                row.encryption_status.as_str(),
+            ])
+            .map_err(failed)?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| NimbuxError::Internal(format!("Failed to write CSV inventory: {}", e)))
+}
+
+fn write_parquet(rows: &[InventoryRow]) -> Result<Vec<u8>> {
+    let failed = |e: parquet::errors::ParquetError| NimbuxError::Internal(format!("Failed to write Parquet inventory: {}", e));
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA).map_err(failed)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(Vec::new(), schema, properties).map_err(failed)?;
+
+    for group in rows.chunks(ROW_GROUP_SIZE) {
+        let text = |value: fn(&InventoryRow) -> &str| -> Vec<ByteArray> {
+            group.iter().map(|row| ByteArray::from(value(row))).collect()
+        };
+        let mut row_group = writer.next_row_group().map_err(failed)?;
+        let mut column = 0;
+        while let Some(mut column_writer) = row_group.next_column().map_err(failed)? {
+            match column {
+                2 => {
+                    let sizes: Vec<i64> = group.iter().map(|row| row.size as i64).collect();
+                    column_writer.typed::<Int64Type>().write_batch(&sizes, None, None).map_err(failed)?;
+                }
+                3 => {
+                    let times: Vec<i64> = group.iter().map(|row| row.last_modified.timestamp_millis()).collect();
+                    column_writer.typed::<Int64Type>().write_batch(&times, None, None).map_err(failed)?;
+                }
+                _ => {
+                    let values = match column {
+                        0 => text(|row| &row.bucket),
+                        1 => text(|row| &row.key),
+                        4 => text(|row| &row.checksum),
+                        5 => text(|row| row.storage_class.name()),
+                        _ => text(|row| &row.encryption_status),
+                    };
+                    column_writer.typed::<ByteArrayType>().write_batch(&values, None, None).map_err(failed)?;
+                }
+            }
+            column_writer.close().map_err(failed)?;
+            column += 1;
+        }
+        row_group.close().map_err(failed)?;
+    }
+    writer.into_inner().map_err(failed)
+}
+
+fn config_id(bucket: &str, id: &str) -> String {
+    format!("{}{}/{}", CONFIG_PREFIX, bucket, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::restore::RestoreConfig;
+    use crate::storage::MemoryStorage;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+
+    struct Fixture {
+        hot: Arc<MemoryStorage>,
+        exporter: InventoryExporter,
+        restore: Arc<RestoreCoordinator>,
+    }
+
+    fn fixture() -> Fixture {
+        let hot = Arc::new(MemoryStorage::new());
+        let cold = Arc::new(MemoryStorage::new());
+        let restore = Arc::new(RestoreCoordinator::new(hot.clone(), cold, RestoreConfig::default()));
+        let exporter = InventoryExporter::new(hot.clone()).with_restore(Arc::clone(&restore));
+        Fixture { hot, exporter, restore }
+    }
+
+    async fn put(storage: &MemoryStorage, id: &str, tags: &[(&str, &str)]) {
+        let mut object = Object::with_id(id.to_string(), id.to_string(), id.as_bytes().to_vec(), None);
+        for (name, value) in tags {
+            object.add_tag(name.to_string(), value.to_string());
+        }
+        storage.put(object).await.unwrap();
+    }
+
+    fn config(format: InventoryFormat) -> InventoryConfig {
+        InventoryConfig {
+            destination_bucket: "analytics".to_string(),
+            destination_prefix: None,
+            format,
+            frequency: InventoryFrequency::Daily,
+            prefix: None,
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_csv_report_lists_hot_and_archived_objects() {
+        let f = fixture();
+        put(&f.hot, "photos/a.jpg", &[(ENCRYPTION_TAG, "SSE-S3")]).await;
+        put(&f.hot, "photos/b.jpg", &[(STORAGE_CLASS_TAG, "REDUCED_REDUNDANCY")]).await;
+        put(&f.hot, "photos/c.jpg", &[]).await;
+        put(&f.hot, "photosets/x.jpg", &[]).await;
+        f.restore.archive("photos/c.jpg").await.unwrap();
+        f.exporter.set_inventory("photos", "daily", config(InventoryFormat::Csv)).await.unwrap();
+
+        let now = Utc::now();
+        let export = f.exporter.export("photos", "daily", now).await.unwrap();
+        assert_eq!(export.objects, 3);
+        assert_eq!(export.bytes, 36);
+
+        let data = String::from_utf8(f.hot.get(&export.data_id).await.unwrap().data).unwrap();
+        let lines: Vec<&str> = data.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].starts_with("photos,a.jpg,12,") && lines[1].ends_with(",STANDARD,SSE-S3"));
+        assert!(lines[2].ends_with(",REDUCED_REDUNDANCY,NOT-SSE"));
+        assert!(lines[3].ends_with(",COLD,NOT-SSE"));
+        assert_eq!(lines.len(), 4);
+
+        let manifest: InventoryManifest =
+            serde_json::from_slice(&f.hot.get(&export.manifest_id).await.unwrap().data).unwrap();
+        assert_eq!(manifest.files[0].rows, 3);
+        assert_eq!(manifest.files[0].checksum, blake3::hash(data.as_bytes()).to_hex().to_string());
+        assert_eq!(format!("analytics/{}", manifest.files[0].key), export.data_id);
+        assert!(export.data_id.starts_with("analytics/inventory/photos/daily/"));
+
+        let inventory = f.exporter.inventory("photos", "daily").await.unwrap().unwrap();
+        assert_eq!(inventory.last_export, Some(export));
+    }
+
+    #[tokio::test]
+    async fn test_parquet_report_is_readable() {
+        let f = fixture();
+        for n in 0..5 {
+            put(&f.hot, &format!("logs/app/{}.log", n), &[]).await;
+        }
+        put(&f.hot, "logs/web/0.log", &[]).await;
+        let mut parquet = config(InventoryFormat::Parquet);
+        parquet.prefix = Some("app/".to_string());
+        f.exporter.set_inventory("logs", "app", parquet).await.unwrap();
+
+        let export = f.exporter.export("logs", "app", Utc::now()).await.unwrap();
+        assert!(export.data_id.ends_with("/data.parquet"));
+        let data = f.hot.get(&export.data_id).await.unwrap().data;
+        let path = std::env::temp_dir().join(format!("nimbux-inventory-{}.parquet", uuid::Uuid::new_v4()));
+        std::fs::write(&path, &data).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let rows: Vec<_> = reader.get_row_iter(None).unwrap().map(|row| row.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0].get_string(1).unwrap(), "app/0.log");
+        assert_eq!(rows[0].get_long(2).unwrap(), 14);
+        assert_eq!(rows[4].get_string(5).unwrap(), "STANDARD");
+    }
+
+    #[tokio::test]
+    async fn test_reports_are_written_when_due() {
+        let f = fixture();
+        put(&f.hot, "media/a.mp4", &[]).await;
+        assert!(f.exporter.set_inventory("media", "self", InventoryConfig { destination_bucket: "media".to_string(), ..config(InventoryFormat::Csv) }).await.is_err());
+        assert!(f.exporter.set_inventory("media", ".hidden", config(InventoryFormat::Csv)).await.is_err());
+        f.exporter.set_inventory("media", "daily", config(InventoryFormat::Csv)).await.unwrap();
+        let weekly = InventoryConfig { frequency: InventoryFrequency::Weekly, ..config(InventoryFormat::Csv) };
+        f.exporter.set_inventory("media", "weekly", weekly).await.unwrap();
+        let paused = InventoryConfig { enabled: false, ..config(InventoryFormat::Csv) };
+        f.exporter.set_inventory("media", "paused", paused).await.unwrap();
+
+        let start = Utc::now();
+        assert_eq!(f.exporter.export_due(start).await.unwrap().len(), 2);
+        assert!(f.exporter.export_due(start + chrono::Duration::hours(1)).await.unwrap().is_empty());
+        let exports = f.exporter.export_due(start + chrono::Duration::days(1)).await.unwrap();
+        assert_eq!(exports.len(), 1);
+        assert!(exports[0].data_id.contains("/daily/"));
+        assert_eq!(f.exporter.export_due(start + chrono::Duration::days(7)).await.unwrap().len(), 2);
+
+        // Replacing a configuration keeps its schedule
+        f.exporter.set_inventory("media", "weekly", config(InventoryFormat::Parquet)).await.unwrap();
+        assert!(f.exporter.inventory("media", "weekly").await.unwrap().unwrap().last_export.is_some());
+        f.exporter.delete_inventory("media", "paused").await.unwrap();
+        assert_eq!(f.exporter.inventories("media").await.unwrap().len(), 2);
+        assert!(f.exporter.delete_inventory("media", "paused").await.is_err());
+    }
+}
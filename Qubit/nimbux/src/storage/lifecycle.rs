@@ -0,0 +1,597 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Bucket lifecycle policies: expiration, cold-tier transitions and multipart cleanup
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::advanced::{LifecyclePolicy, LifecycleProcessResult, LifecycleRule, LifecycleStatus};
+use super::object_lock::ObjectLock;
+use super::restore::{RestoreCoordinator, RESTORED_UNTIL_TAG};
+use super::{Object, ObjectMetadata, StorageBackend};
+use crate::errors::{NimbuxError, Result};
+use crate::network::s3::S3Store;
+use crate::observability::{MetricType, MetricsCollector};
+
+const POLICY_PREFIX: &str = ".lifecycle/buckets/";
+const DAY_SECS: i64 = 24 * 3600;
+/// Rules accepted in one policy, as in S3
+pub const MAX_RULES_PER_POLICY: usize = 1000;
+
+/// What a rule has done since the server started
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleRuleMetrics {
+    pub rule_id: String,
+    pub objects_expired: u64,
+    pub bytes_expired: u64,
+    pub objects_transitioned: u64,
+    pub bytes_transitioned: u64,
+    pub uploads_aborted: u64,
+    pub failures: u64,
+    pub last_applied_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// Action a rule takes on an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Expire,
+    Transition,
+}
+
+/// Applies bucket lifecycle policies.
+///
+/// Objects are read and expired through `storage`, so expiring an object in
+/// a versioned bucket leaves a delete marker. Transitions move objects to the
+/// cold tier through the restore coordinator, and expiration rules also
+/// remove archived objects from there. Policies are kept under
+/// `.lifecycle/buckets/<bucket>`.
+pub struct LifecycleEngine {
+    storage: Arc<dyn StorageBackend>,
+    restore: Arc<RestoreCoordinator>,
+    uploads: S3Store,
+    rule_metrics: RwLock<HashMap<String, HashMap<String, LifecycleRuleMetrics>>>,
+    metrics: Option<Arc<MetricsCollector>>,
+}
+
+impl LifecycleEngine {
+    pub fn new(storage: Arc<dyn StorageBackend>, restore: Arc<RestoreCoordinator>) -> Self {
+        Self {
+            uploads: S3Store::new(storage.clone()),
+            storage,
+            restore,
+            rule_metrics: RwLock::new(HashMap::new()),
+            metrics: None,
+        }
+    }
+
+    /// Publish lifecycle totals to `metrics` after each scheduled pass
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub async fn policy(&self, bucket: &str) -> Result<Option<LifecyclePolicy>> {
+        match self.storage.get(&policy_id(bucket)).await {
+            Ok(object) => Ok(Some(serde_json::from_slice(&object.data)?)),
+            Err(NimbuxError::ObjectNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the rules of a bucket's policy, creating the policy if needed
+    pub async fn set_policy(&self, bucket: &str, rules: Vec<LifecycleRule>) -> Result<LifecyclePolicy> {
+        validate_bucket(bucket)?;
+        validate_rules(&rules)?;
+
+        let now = Utc::now();
+        let policy = match self.policy(bucket).await? {
+            Some(existing) => LifecyclePolicy {
+                rules,
+                status: LifecycleStatus::Enabled,
+                updated_at: now,
+                ..existing
+            },
+            None => LifecyclePolicy {
+                id: Uuid::new_v4().to_string(),
+                name: bucket.to_string(),
+                bucket: bucket.to_string(),
+                status: LifecycleStatus::Enabled,
+                rules,
+                created_at: now,
+                updated_at: now,
+            },
+        };
+
+        let id = policy_id(bucket);
+        let object = Object::with_id(id.clone(), id, serde_json::to_vec(&policy)?, Some("application/json".to_string()));
+        self.storage.put(object).await?;
+
+        let rule_ids: HashSet<&str> = policy.rules.iter().map(|rule| rule.id.as_str()).collect();
+        if let Some(metrics) = self.rule_metrics.write().await.get_mut(bucket) {
+            metrics.retain(|rule_id, _| rule_ids.contains(rule_id.as_str()));
+        }
+        tracing::info!("Lifecycle policy of bucket {} set with {} rules", bucket, policy.rules.len());
+        Ok(policy)
+    }
+
+    pub async fn delete_policy(&self, bucket: &str) -> Result<()> {
+        match self.storage.delete(&policy_id(bucket)).await {
+            Ok(()) => {}
+            Err(NimbuxError::ObjectNotFound { .. }) => {
+                return Err(NimbuxError::ObjectNotFound {
+                    object_id: format!("lifecycle policy of {}", bucket),
+                })
+            }
+            Err(e) => return Err(e),
+        }
+        self.rule_metrics.write().await.remove(bucket);
+        tracing::info!("Lifecycle policy of bucket {} removed", bucket);
+        Ok(())
+    }
+
+    /// Metrics of each rule in a bucket's policy that has run, by rule ID
+    pub async fn rule_metrics(&self, bucket: &str) -> Vec<LifecycleRuleMetrics> {
+        let mut metrics: Vec<_> = self
+            .rule_metrics
+            .read()
+            .await
+            .get(bucket)
+            .map(|metrics| metrics.values().cloned().collect())
+            .unwrap_or_default();
+        metrics.sort_by(|a, b| a.rule_id.cmp(&b.rule_id));
+        metrics
+    }
+
+    /// Apply every bucket's policy as of `now`, by bucket
+    pub async fn apply_all(&self, now: DateTime<Utc>) -> Result<HashMap<String, LifecycleProcessResult>> {
+        let mut buckets: Vec<String> = self
+            .storage
+            .list(Some(POLICY_PREFIX), None)
+            .await?
+            .into_iter()
+            .filter_map(|metadata| metadata.id.strip_prefix(POLICY_PREFIX).map(str::to_string))
+            .collect();
+        buckets.sort();
+
+        let mut results = HashMap::with_capacity(buckets.len());
+        for bucket in buckets {
+            let result = self.apply(&bucket, now).await?;
+            if result.expired_objects + result.transitioned_objects + result.aborted_uploads > 0 || !result.errors.is_empty() {
+                tracing::info!(
+                    "Lifecycle of bucket {}: {} expired, {} transitioned, {} uploads aborted, {} errors",
+                    bucket,
+                    result.expired_objects,
+                    result.transitioned_objects,
+                    result.aborted_uploads,
+                    result.errors.len()
+                );
+            }
+            results.insert(bucket, result);
+        }
+        self.publish_metrics().await;
+        Ok(results)
+    }
+
+    /// Apply a bucket's policy as of `now`.
+    ///
+    /// Each object is handled by the first enabled rule that matches it and
+    /// has an action due, with expiration taking precedence over transition.
+    /// Failures on single objects are reported in the result and the rule's
+    /// metrics rather than stopping the pass.
+    pub async fn apply(&self, bucket: &str, now: DateTime<Utc>) -> Result<LifecycleProcessResult> {
+        let started = Instant::now();
+        let mut result = LifecycleProcessResult {
+            processed_objects: 0,
+            transitioned_objects: 0,
+            expired_objects: 0,
+            aborted_uploads: 0,
+            errors: Vec::new(),
+            processing_time_ms: 0,
+        };
+        let Some(policy) = self.policy(bucket).await? else {
+            return Ok(result);
+        };
+        if policy.status != LifecycleStatus::Enabled {
+            return Ok(result);
+        }
+        let rules: Vec<&LifecycleRule> = policy
+            .rules
+            .iter()
+            .filter(|rule| rule.status == LifecycleStatus::Enabled)
+            .collect();
+        let mut run: HashMap<String, LifecycleRuleMetrics> = rules
+            .iter()
+            .map(|rule| (rule.id.clone(), LifecycleRuleMetrics { rule_id: rule.id.clone(), ..Default::default() }))
+            .collect();
+
+        let prefix = format!("{}/", bucket);
+        for metadata in self.storage.list(Some(&prefix), None).await? {
+            let Some(key) = metadata.id.strip_prefix(&prefix) else {
+                continue;
+            };
+            result.processed_objects += 1;
+            let restored_copy = metadata.tags.contains_key(RESTORED_UNTIL_TAG);
+            let Some((rule, action)) = due_action(&rules, key, &metadata, now, !restored_copy) else {
+                continue;
+            };
+            // Expiring or archiving would delete it; it waits for its lock to end
+            if ObjectLock::of(&metadata).is_locked(now) {
+                continue;
+            }
+
+            let outcome = match action {
+                // The archived original goes along with a restored copy
+                Action::Expire if restored_copy => self.restore.delete_archived(&metadata.id).await,
+                Action::Expire => self.storage.delete(&metadata.id).await,
+                Action::Transition => self.restore.archive(&metadata.id).await,
+            };
+            let metrics = run.get_mut(&rule.id).expect("metrics for every enabled rule");
+            match outcome {
+                Ok(()) if action == Action::Expire => {
+                    metrics.objects_expired += 1;
+                    metrics.bytes_expired += metadata.size;
+                    result.expired_objects += 1;
+                }
+                Ok(()) => {
+                    metrics.objects_transitioned += 1;
+                    metrics.bytes_transitioned += metadata.size;
+                    result.transitioned_objects += 1;
+                }
+                Err(NimbuxError::ObjectNotFound { .. }) => {}
+                Err(e) => record_failure(metrics, &mut result, &metadata.id, e),
+            }
+        }
+
+        // Archived objects can still expire
+        for metadata in self.restore.archived(&prefix).await? {
+            let Some(key) = metadata.id.strip_prefix(&prefix) else {
+                continue;
+            };
+            result.processed_objects += 1;
+            let Some((rule, _)) = due_action(&rules, key, &metadata, now, false) else {
+                continue;
+            };
+            let metrics = run.get_mut(&rule.id).expect("metrics for every enabled rule");
+            match self.restore.delete_archived(&metadata.id).await {
+                Ok(()) => {
+                    metrics.objects_expired += 1;
+                    metrics.bytes_expired += metadata.size;
+                    result.expired_objects += 1;
+                }
+                Err(NimbuxError::ObjectNotFound { .. }) => {}
+                Err(e) => record_failure(metrics, &mut result, &metadata.id, e),
+            }
+        }
+
+        for rule in &rules {
+            let Some(abort) = &rule.abort_incomplete_multipart_upload else {
+                continue;
+            };
+            let cutoff = now.timestamp() - i64::from(abort.days_after_initiation) * DAY_SECS;
+            let key_prefix = rule.filter.prefix.as_deref().unwrap_or("");
+            let metrics = run.get_mut(&rule.id).expect("metrics for every enabled rule");
+            match self
+                .uploads
+                .abort_multipart_uploads_before(bucket, key_prefix, cutoff.max(0) as u64)
+                .await
+            {
+                Ok(aborted) => {
+                    metrics.uploads_aborted += aborted.len() as u64;
+                    result.aborted_uploads += aborted.len() as u64;
+                }
+                Err(e) => record_failure(metrics, &mut result, bucket, NimbuxError::Storage(e.to_string())),
+            }
+        }
+
+        let mut rule_metrics = self.rule_metrics.write().await;
+        let totals = rule_metrics.entry(bucket.to_string()).or_default();
+        totals.retain(|rule_id, _| policy.rules.iter().any(|rule| &rule.id == rule_id));
+        for (rule_id, delta) in run {
+            let total = totals
+                .entry(rule_id.clone())
+                .or_insert_with(|| LifecycleRuleMetrics { rule_id, ..Default::default() });
+            total.objects_expired += delta.objects_expired;
+            total.bytes_expired += delta.bytes_expired;
+            total.objects_transitioned += delta.objects_transitioned;
+            total.bytes_transitioned += delta.bytes_transitioned;
+            total.uploads_aborted += delta.uploads_aborted;
+            total.failures += delta.failures;
+            total.last_applied_at = Some(now);
+            if delta.last_error.is_some() {
+                total.last_error = delta.last_error;
+            }
+        }
+
+        result.processing_time_ms = started.elapsed().as_millis() as u64;
+        Ok(result)
+    }
+
+    async fn publish_metrics(&self) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        let mut totals = LifecycleRuleMetrics::default();
+        for rule in self.rule_metrics.read().await.values().flat_map(|rules| rules.values()) {
+            totals.objects_expired += rule.objects_expired;
+            totals.bytes_expired += rule.bytes_expired;
+            totals.objects_transitioned += rule.objects_transitioned;
+            totals.bytes_transitioned += rule.bytes_transitioned;
+            totals.uploads_aborted += rule.uploads_aborted;
+            totals.failures += rule.failures;
+        }
+        let values = [
+            ("nimbux_lifecycle_objects_expired_total", totals.objects_expired),
+            ("nimbux_lifecycle_bytes_expired_total", totals.bytes_expired),
+            ("nimbux_lifecycle_objects_transitioned_total", totals.objects_transitioned),
+            ("nimbux_lifecycle_bytes_transitioned_total", totals.bytes_transitioned),
+            ("nimbux_lifecycle_uploads_aborted_total", totals.uploads_aborted),
+            ("nimbux_lifecycle_failures_total", totals.failures),
+        ];
+        for (name, value) in values {
+            if let Err(e) = metrics
+                .add_custom_metric(name.to_string(), value as f64, HashMap::new(), MetricType::Counter)
+                .await
+            {
+                tracing::debug!("Failed to record {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Apply all policies on an interval for as long as the engine is alive
+    pub fn spawn_scheduler(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.apply_all(Utc::now()).await {
+                    tracing::error!("Lifecycle pass failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// First rule with an action due for an object, expirations before transitions
+fn due_action<'a>(
+    rules: &[&'a LifecycleRule],
+    key: &str,
+    metadata: &ObjectMetadata,
+    now: DateTime<Utc>,
+    can_transition: bool,
+) -> Option<(&'a LifecycleRule, Action)> {
+    let matching: Vec<&LifecycleRule> = rules.iter().copied().filter(|rule| matches(rule, key, metadata)).collect();
+    let age_secs = now.timestamp() - metadata.updated_at as i64;
+    let due = |days: Option<u32>, date: Option<DateTime<Utc>>| {
+        days.is_some_and(|days| age_secs >= i64::from(days) * DAY_SECS) || date.is_some_and(|date| now >= date)
+    };
+
+    if let Some(rule) = matching
+        .iter()
+        .find(|rule| rule.expiration.as_ref().is_some_and(|expiration| due(expiration.days, expiration.date)))
+    {
+        return Some((rule, Action::Expire));
+    }
+    if !can_transition {
+        return None;
+    }
+    matching
+        .into_iter()
+        .find(|rule| rule.transitions.iter().any(|transition| due(Some(transition.days), transition.date)))
+        .map(|rule| (rule, Action::Transition))
+}
+
+fn matches(rule: &LifecycleRule, key: &str, metadata: &ObjectMetadata) -> bool {
+    let filter = &rule.filter;
+    filter.prefix.as_deref().is_none_or(|prefix| key.starts_with(prefix))
+        && filter.tags.iter().all(|(name, value)| metadata.tags.get(name) == Some(value))
+        && filter.object_size_greater_than.is_none_or(|size| metadata.size > size)
+        && filter.object_size_less_than.is_none_or(|size| metadata.size < size)
+}
+
+fn record_failure(metrics: &mut LifecycleRuleMetrics, result: &mut LifecycleProcessResult, target: &str, error: NimbuxError) {
+    tracing::warn!("Lifecycle rule {} failed on {}: {}", metrics.rule_id, target, error);
+    metrics.failures += 1;
+    metrics.last_error = Some(error.to_string());
+    result.errors.push(format!("{}: {}", target, error));
+}
+
+fn validate_bucket(bucket: &str) -> Result<()> {
+    if bucket.is_empty() || bucket.starts_with('.') || bucket.contains('/') {
+        return Err(NimbuxError::InvalidRequest(format!("Invalid bucket name: {}", bucket)));
+    }
+    Ok(())
+}
+
+fn validate_rules(rules: &[LifecycleRule]) -> Result<()> {
+    let invalid = |rule: &LifecycleRule, reason: &str| NimbuxError::InvalidRequest(format!("Rule {}: {}", rule.id, reason));
+
+    if rules.is_empty() || rules.len() > MAX_RULES_PER_POLICY {
+        return Err(NimbuxError::InvalidRequest(format!(
+            "A lifecycle policy needs 1 to {} rules",
+            MAX_RULES_PER_POLICY
+        )));
+    }
+    let mut ids = HashSet::new();
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err(NimbuxError::InvalidRequest("Rule IDs must not be empty".to_string()));
+        }
+        if !ids.insert(rule.id.as_str()) {
+            return Err(invalid(rule, "duplicate rule ID"));
+        }
+        if rule.expiration.is_none() && rule.transitions.is_empty() && rule.abort_incomplete_multipart_upload.is_none() {
+            return Err(invalid(rule, "no expiration, transition or multipart upload action"));
+        }
+        if let Some(expiration) = &rule.expiration {
+            if expiration.days.is_none() && expiration.date.is_none() {
+                return Err(invalid(rule, "expiration needs days or a date"));
+            }
+            if expiration.days == Some(0) {
+                return Err(invalid(rule, "expiration days must be at least 1"));
+            }
+            if expiration.expired_object_delete_marker == Some(true) {
+                return Err(invalid(rule, "removing expired delete markers is not supported"));
+            }
+        }
+        // There is a single cold tier to move objects to
+        if rule.transitions.len() > 1 {
+            return Err(invalid(rule, "at most one transition is supported"));
+        }
+        if rule.abort_incomplete_multipart_upload.as_ref().is_some_and(|abort| abort.days_after_initiation == 0) {
+            return Err(invalid(rule, "days after initiation must be at least 1"));
+        }
+    }
+    Ok(())
+}
+
+fn policy_id(bucket: &str) -> String {
+    format!("{}{}", POLICY_PREFIX, bucket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::advanced::{AbortIncompleteMultipartUpload, Expiration, LifecycleFilter, Transition};
+    use crate::storage::restore::RestoreConfig;
+    use crate::storage::MemoryStorage;
+    use std::collections::BTreeMap;
+
+    struct Fixture {
+        hot: Arc<MemoryStorage>,
+        cold: Arc<MemoryStorage>,
+        engine: LifecycleEngine,
+    }
+
+    fn fixture() -> Fixture {
+        let hot = Arc::new(MemoryStorage::new());
+        let cold = Arc::new(MemoryStorage::new());
+        let restore = Arc::new(RestoreCoordinator::new(hot.clone(), cold.clone(), RestoreConfig::default()));
+        let engine = LifecycleEngine::new(hot.clone(), restore);
+        Fixture { hot, cold, engine }
+    }
+
+    fn rule(id: &str, prefix: &str) -> LifecycleRule {
+        LifecycleRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            status: LifecycleStatus::Enabled,
+            filter: LifecycleFilter {
+                prefix: Some(prefix.to_string()),
+                ..Default::default()
+            },
+            transitions: Vec::new(),
+            expiration: None,
+            abort_incomplete_multipart_upload: None,
+        }
+    }
+
+    fn expire_after(days: u32) -> Option<Expiration> {
+        Some(Expiration { days: Some(days), date: None, expired_object_delete_marker: None })
+    }
+
+    async fn put(storage: &MemoryStorage, id: &str) {
+        storage.put(Object::with_id(id.to_string(), id.to_string(), id.as_bytes().to_vec(), None)).await.unwrap();
+    }
+
+    fn days_from_now(days: i64) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::days(days)
+    }
+
+    #[tokio::test]
+    async fn test_expiration_only_touches_matching_old_objects() {
+        let f = fixture();
+        put(&f.hot, "logs/app/1.log").await;
+        put(&f.hot, "logs/keep/1.log").await;
+        put(&f.hot, "other/app/1.log").await;
+        let mut expire = rule("expire-app", "app/");
+        expire.expiration = expire_after(30);
+        f.engine.set_policy("logs", vec![expire]).await.unwrap();
+
+        let result = f.engine.apply("logs", days_from_now(29)).await.unwrap();
+        assert_eq!(result.expired_objects, 0);
+
+        let result = f.engine.apply("logs", days_from_now(30)).await.unwrap();
+        assert_eq!(result.expired_objects, 1);
+        assert!(!f.hot.exists("logs/app/1.log").await.unwrap());
+        assert!(f.hot.exists("logs/keep/1.log").await.unwrap());
+        assert!(f.hot.exists("other/app/1.log").await.unwrap());
+
+        let metrics = f.engine.rule_metrics("logs").await;
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].objects_expired, 1);
+        assert_eq!(metrics[0].bytes_expired, "logs/app/1.log".len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_transitioned_objects_expire_from_the_cold_tier() {
+        let f = fixture();
+        put(&f.hot, "media/raw/a.mov").await;
+        let mut tier = rule("tier", "raw/");
+        tier.transitions = vec![Transition { days: 7, storage_class: "GLACIER".to_string(), date: None }];
+        tier.expiration = expire_after(365);
+        f.engine.set_policy("media", vec![tier]).await.unwrap();
+
+        let result = f.engine.apply("media", days_from_now(7)).await.unwrap();
+        assert_eq!(result.transitioned_objects, 1);
+        assert!(!f.hot.exists("media/raw/a.mov").await.unwrap());
+        assert!(f.cold.exists("media/raw/a.mov").await.unwrap());
+
+        let result = f.engine.apply("media", days_from_now(365)).await.unwrap();
+        assert_eq!(result.expired_objects, 1);
+        assert!(!f.cold.exists("media/raw/a.mov").await.unwrap());
+
+        let metrics = &f.engine.rule_metrics("media").await[0];
+        assert_eq!((metrics.objects_transitioned, metrics.objects_expired), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_stale_multipart_uploads_are_aborted() {
+        let f = fixture();
+        let s3 = S3Store::new(f.hot.clone());
+        s3.create_bucket("backups").await.unwrap();
+        let upload = s3.create_multipart_upload("backups", "db/dump.tar", None, BTreeMap::new()).await.unwrap();
+        s3.upload_part(&upload.upload_id, "backups", "db/dump.tar", 1, b"part".to_vec()).await.unwrap();
+        let mut cleanup = rule("cleanup", "db/");
+        cleanup.abort_incomplete_multipart_upload = Some(AbortIncompleteMultipartUpload { days_after_initiation: 2 });
+        f.engine.set_policy("backups", vec![cleanup]).await.unwrap();
+
+        assert_eq!(f.engine.apply("backups", days_from_now(1)).await.unwrap().aborted_uploads, 0);
+        assert_eq!(f.engine.apply("backups", days_from_now(3)).await.unwrap().aborted_uploads, 1);
+        assert!(s3.list_multipart_uploads("backups", "").await.unwrap().is_empty());
+        assert!(f.hot.list(Some(".s3/uploads/"), None).await.unwrap().is_empty());
+        assert_eq!(f.engine.rule_metrics("backups").await[0].uploads_aborted, 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_and_disabled_rules() {
+        let f = fixture();
+        put(&f.hot, "docs/a.txt").await;
+        assert!(f.engine.set_policy("docs", vec![]).await.is_err());
+        assert!(f.engine.set_policy("docs", vec![rule("no-action", "")]).await.is_err());
+        let mut expire = rule("expire", "");
+        expire.expiration = expire_after(1);
+        assert!(f.engine.set_policy(".s3", vec![expire.clone()]).await.is_err());
+        assert!(f.engine.set_policy("docs", vec![expire.clone(), expire.clone()]).await.is_err());
+
+        expire.status = LifecycleStatus::Disabled;
+        f.engine.set_policy("docs", vec![expire]).await.unwrap();
+        let results = f.engine.apply_all(days_from_now(10)).await.unwrap();
+        assert_eq!(results["docs"].expired_objects, 0);
+        assert!(f.hot.exists("docs/a.txt").await.unwrap());
+
+        f.engine.delete_policy("docs").await.unwrap();
+        assert!(f.engine.policy("docs").await.unwrap().is_none());
+        assert!(f.engine.apply_all(days_from_now(10)).await.unwrap().is_empty());
+    }
+}
@@ -12,21 +12,45 @@ use uuid::Uuid;
 
 use crate::errors::{NimbuxError, Result};
 
+pub mod append;
 pub mod block;
+pub mod chunking;
 pub mod compression;
 pub mod content_addressable;
 pub mod disk;
+pub mod gc;
 pub mod memory;
+pub mod pack;
 pub mod advanced;
 pub mod ai_compression;
 pub mod integrity;
+pub mod inventory;
+pub mod lifecycle;
+pub mod notifications;
+pub mod object_lock;
+pub mod placement;
+pub mod restore;
+pub mod transform;
+pub mod versioning;
 
 // Re-export commonly used types
 pub use memory::MemoryStorage;
-pub use content_addressable::ContentAddressableStorage;
-pub use advanced::{AdvancedStorageBackend, AdvancedObject, AdvancedObjectMetadata, VersioningManager, LifecycleManager, ReplicationManager, EncryptionManager};
+pub use append::{ObjectWriter, WriteConditions, WriteResult};
+pub use chunking::ChunkerConfig;
+pub use content_addressable::{ContentAddressableStorage, DedupStats, BucketDedupStats};
+pub use gc::{GcPhase, GcReport, GcStatus, CompactionReport};
+pub use pack::PackStore;
+pub use advanced::{AdvancedStorageBackend, AdvancedObject, AdvancedObjectMetadata, ObjectVersion, VersioningManager, LifecycleManager, ReplicationManager, EncryptionManager};
 pub use ai_compression::{CompressionManager, AICompressionAnalyzer, CompressionAlgorithm, CompressionConfig, CompressionResult};
 pub use integrity::{IntegrityManager, IntegrityConfig, ChecksumAlgorithm, IntegrityReport, IntegrityStats};
+pub use restore::{RestoreCoordinator, RestoreConfig, RestoreRequest, RestoreJob, RestoreStatus, RestorePriority, RestoreNotification, RestoreEvent, RestoreNotifier, HttpRestoreNotifier};
+pub use versioning::{VersionedStorage, VersioningStatus};
+pub use lifecycle::{LifecycleEngine, LifecycleRuleMetrics};
+pub use inventory::{InventoryExporter, InventoryConfig, InventoryFormat, InventoryFrequency, BucketInventory, InventoryExport, InventoryManifest, InventoryFile, ENCRYPTION_TAG};
+pub use transform::{ImageTransformer, TransformerConfig, TransformConfig, TransformParams, TransformStats, BucketTransforms, Rendition, OutputFormat, Fit};
+pub use placement::{StorageClass, Quota, Usage, BucketPlacement, UserQuota, BucketUsage, UserUsage, UsageReport, STORAGE_CLASS_TAG, OWNER_TAG};
+pub use object_lock::{RetentionMode, Retention, ObjectLock, DefaultRetention, BucketObjectLock, BucketLockSummary, ObjectLockReport, RETENTION_MODE_TAG, RETAIN_UNTIL_TAG, LEGAL_HOLD_TAG};
+pub use notifications::{EventNotifier, NotifyingStorage, NotificationConfig, NotificationRule, NotificationTarget, NotificationFilter, BucketNotifications, ObjectEvent, ObjectEventType, NotificationStats, EventSink, HttpEventSink};
 
 /// Object metadata stored alongside the data
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,10 +108,18 @@ pub struct StorageStats {
     pub used_space: u64,
 }
 
-/// Storage engine that manages multiple backends
+/// Storage engine that manages multiple backends.
+///
+/// Bucket objects are written to the backend of their bucket's storage class
+/// and checked against the bucket's and owner's quotas first; see
+/// `placement`. Reads look in the class backend, then the others, so objects
+/// stay readable after their bucket's class changes. Objects under retention
+/// or legal hold cannot be deleted or overwritten; see `object_lock`.
 pub struct StorageEngine {
     backends: HashMap<String, Box<dyn StorageBackend>>,
     default_backend: String,
+    placement: placement::Placement,
+    object_lock: object_lock::ObjectLocks,
 }
 
 impl StorageEngine {
@@ -95,6 +127,8 @@ impl StorageEngine {
         Self {
             backends: HashMap::new(),
             default_backend,
+            placement: placement::Placement::new(),
+            object_lock: object_lock::ObjectLocks::new(),
         }
     }
     
@@ -103,6 +137,142 @@ impl StorageEngine {
         self.backends.insert(name, backend);
     }
     
+    /// Write objects of buckets in `class` to a backend. Standard buckets
+    /// use the default backend unless set; other classes need a backend
+    /// before buckets can be placed in them
+    pub fn set_class_backend(&mut self, class: StorageClass, backend: String) {
+        self.placement.set_class_backend(class, backend);
+    }
+    
+    /// Load bucket classes, quotas and default retentions, and count the
+    /// usage of stored objects
+    pub async fn load_placement(&self) -> Result<()> {
+        self.placement.load(self.get_default_backend()?).await?;
+        self.object_lock.load(self.get_default_backend()?).await?;
+        self.recalculate_usage().await
+    }
+    
+    pub async fn bucket_placement(&self, bucket: &str) -> BucketPlacement {
+        self.placement.bucket(bucket).await
+    }
+    
+    /// Place new objects of the bucket in `class`; existing objects stay where they are
+    pub async fn set_bucket_class(&self, bucket: &str, class: StorageClass) -> Result<BucketPlacement> {
+        self.placement
+            .update_bucket(self.get_default_backend()?, bucket, |placement| {
+                placement.storage_class = class;
+                Ok(())
+            })
+            .await
+    }
+    
+    /// Set or, with `None`, remove the bucket's quota
+    pub async fn set_bucket_quota(&self, bucket: &str, quota: Option<Quota>) -> Result<BucketPlacement> {
+        self.placement
+            .update_bucket(self.get_default_backend()?, bucket, |placement| {
+                placement.quota = quota;
+                Ok(())
+            })
+            .await
+    }
+    
+    pub async fn user_quota(&self, user_id: &str) -> Option<UserQuota> {
+        self.placement.user_quota(user_id).await
+    }
+    
+    /// Set or, with `None`, remove the quota of objects tagged to the user
+    pub async fn set_user_quota(&self, user_id: &str, quota: Option<Quota>) -> Result<Option<UserQuota>> {
+        self.placement.set_user_quota(self.get_default_backend()?, user_id, quota).await
+    }
+    
+    /// Bytes and objects stored per bucket, user and storage class
+    pub async fn usage(&self) -> UsageReport {
+        self.placement.report().await
+    }
+    
+    pub async fn object_lock(&self, id: &str) -> Result<ObjectLock> {
+        Ok(ObjectLock::of(&self.head(id).await?))
+    }
+    
+    /// Set or, with `None`, remove the object's retention; see `ObjectLock::set_retention`
+    pub async fn set_object_retention(
+        &self,
+        id: &str,
+        retention: Option<Retention>,
+        bypass_governance: bool,
+    ) -> Result<ObjectLock> {
+        self.update_lock(id, |lock, now| lock.set_retention(id, retention, bypass_governance, now)).await
+    }
+    
+    pub async fn set_legal_hold(&self, id: &str, legal_hold: bool) -> Result<ObjectLock> {
+        self.update_lock(id, |lock, _| {
+            lock.legal_hold = legal_hold;
+            Ok(())
+        })
+        .await
+    }
+    
+    pub async fn bucket_object_lock(&self, bucket: &str) -> BucketObjectLock {
+        self.object_lock.bucket(bucket).await
+    }
+    
+    /// Set or, with `None`, remove the retention new objects of the bucket
+    /// get; stored objects keep theirs
+    pub async fn set_bucket_default_retention(
+        &self,
+        bucket: &str,
+        default_retention: Option<DefaultRetention>,
+    ) -> Result<BucketObjectLock> {
+        self.object_lock
+            .set_default_retention(self.get_default_backend()?, bucket, default_retention)
+            .await
+    }
+    
+    /// Locked objects per bucket, read from every backend
+    pub async fn object_lock_report(&self) -> Result<ObjectLockReport> {
+        let mut objects = HashMap::new();
+        for backend in self.placement_backends()? {
+            for metadata in backend.list(None, None).await? {
+                objects.entry(metadata.id.clone()).or_insert(metadata);
+            }
+        }
+        Ok(self.object_lock.report(objects.into_values(), chrono::Utc::now()).await)
+    }
+    
+    /// Count usage again from the objects in every backend
+    pub async fn recalculate_usage(&self) -> Result<()> {
+        let mut charges = HashMap::new();
+        for backend in self.placement_backends()? {
+            for metadata in backend.list(None, None).await? {
+                if let Some(charge) = placement::Charge::of(&metadata) {
+                    charges.entry(metadata.id).or_insert(charge);
+                }
+            }
+        }
+        self.placement.replace_usage(charges.into_values()).await;
+        Ok(())
+    }
+    
+    /// Rewrite the object's lock tags in place
+    async fn update_lock(
+        &self,
+        id: &str,
+        update: impl FnOnce(&mut ObjectLock, chrono::DateTime<chrono::Utc>) -> Result<()>,
+    ) -> Result<ObjectLock> {
+        let _guard = self.placement.lock_for(id).lock().await;
+        let Some((backend, _)) = self.locate(id).await? else {
+            return Err(NimbuxError::ObjectNotFound { object_id: id.to_string() });
+        };
+        let backend = self.get_backend(backend)?;
+        let mut object = backend.get(id).await?;
+        let mut lock = ObjectLock::of(&object.metadata);
+        update(&mut lock, chrono::Utc::now())?;
+        lock.apply(&mut object.metadata.tags);
+        backend.put(object).await?;
+        tracing::info!("Object {} lock set to {:?}", id, lock);
+        Ok(lock)
+    }
+    
     /// Get the default backend
     fn get_default_backend(&self) -> Result<&dyn StorageBackend> {
         self.backends
@@ -118,36 +288,189 @@ impl StorageEngine {
             .map(|b| b.as_ref())
             .ok_or_else(|| NimbuxError::Storage(format!("Backend '{}' not found", name)))
     }
+    
+    /// Name of the backend new objects of the bucket are written to
+    async fn backend_name_for(&self, bucket: &str) -> &str {
+        let class = self.placement.class_of(bucket).await;
+        self.placement.class_backend(class).unwrap_or(&self.default_backend)
+    }
+    
+    /// Default backend followed by every other class backend, each once
+    fn placement_backend_names(&self) -> Vec<&str> {
+        let mut names = vec![self.default_backend.as_str()];
+        for name in self.placement.class_backends() {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+    
+    fn placement_backends(&self) -> Result<Vec<&dyn StorageBackend>> {
+        self.placement_backend_names().into_iter().map(|name| self.get_backend(name)).collect()
+    }
+    
+    /// Name of the backend holding the object, and its metadata. Bucket
+    /// objects are looked for in their bucket's class backend first
+    async fn locate(&self, id: &str) -> Result<Option<(&str, ObjectMetadata)>> {
+        let names = match placement::bucket_of(id) {
+            Some(bucket) => {
+                let mut names = vec![self.backend_name_for(bucket).await];
+                for name in self.placement_backend_names() {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                names
+            }
+            None => vec![self.default_backend.as_str()],
+        };
+        for name in names {
+            match self.get_backend(name)?.head(id).await {
+                Ok(metadata) => return Ok(Some((name, metadata))),
+                Err(NimbuxError::ObjectNotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
 }
 
 #[async_trait]
 impl StorageBackend for StorageEngine {
+    async fn put(&self, mut object: Object) -> Result<()> {
+        let id = object.metadata.id.clone();
+        let _guard = self.placement.lock_for(&id).lock().await;
+        let now = chrono::Utc::now();
+        let Some(bucket) = placement::bucket_of(&id).map(str::to_string) else {
+            if let Some((_, metadata)) = self.locate(&id).await? {
+                self.object_lock.check(&metadata, now)?;
+            }
+            return self.get_default_backend()?.put(object).await;
+        };
+        
+        let class = self.placement.class_of(&bucket).await;
+        object.metadata.tags.insert(STORAGE_CLASS_TAG.to_string(), class.name().to_string());
+        let backend_name = self.backend_name_for(&bucket).await;
+        let previous = self.locate(&id).await?;
+        if let Some((_, metadata)) = &previous {
+            self.object_lock.check(metadata, now)?;
+        }
+        self.object_lock.apply_default(&bucket, &mut object.metadata, now).await;
+        
+        let new_charge = placement::Charge::of(&object.metadata)
+            .ok_or_else(|| NimbuxError::InvalidObjectId { object_id: id.clone() })?;
+        let old_charge = previous.as_ref().and_then(|(_, metadata)| placement::Charge::of(metadata));
+        self.placement.reserve(&new_charge, old_charge.as_ref()).await?;
+        if let Err(e) = self.get_backend(backend_name)?.put(object).await {
+            self.placement.release(&new_charge, old_charge.as_ref()).await;
+            return Err(e);
+        }
+        
+        // The bucket changed class since the object was last written
+        if let Some((old_backend, _)) = previous.filter(|(name, _)| *name != backend_name) {
+            match self.get_backend(old_backend)?.delete(&id).await {
+                    Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => {}
+                Err(e) => tracing::warn!("Failed to remove {} from its previous storage class: {}", id, e),
+            }
+        }
+        Ok(())
+    }
+    
+    async fn get(&self, id: &str) -> Result<Object> {
+        match self.locate(id).await? {
+            Some((backend, _)) => self.get_backend(backend)?.get(id).await,
+            None => Err(NimbuxError::ObjectNotFound { object_id: id.to_string() }),
+        }
+    }
+    
+    async fn delete(&self, id: &str) -> Result<()> {
+        let _guard = self.placement.lock_for(id).lock().await;
+        let Some((backend, metadata)) = self.locate(id).await? else {
+            return Err(NimbuxError::ObjectNotFound { object_id: id.to_string() });
+        };
+        self.object_lock.check(&metadata, chrono::Utc::now())?;
+        self.get_backend(backend)?.delete(id).await?;
+        if let Some(charge) = placement::Charge::of(&metadata) {
+            self.placement.remove(&charge).await;
+        }
+        Ok(())
+    }
+    
+    async fn exists(&self, id: &str) -> Result<bool> {
+        Ok(self.locate(id).await?.is_some())
+    }
+    
+    async fn list(&self, prefix: Option<&str>, limit: Option<usize>) -> Result<Vec<ObjectMetadata>> {
+        let backends = self.placement_backends()?;
+        if backends.len() == 1 {
+            return backends[0].list(prefix, limit).await;
+        }
+        
+        let mut results = Vec::new();
+        for backend in backends {
+            results.extend(backend.list(prefix, limit).await?);
+        }
+        // Newest first, like each backend; an object caught moving between
+        // classes is listed once
+        results.sort_by_key(|metadata| std::cmp::Reverse(metadata.created_at));
+        let mut seen = std::collections::HashSet::new();
+        results.retain(|metadata| seen.insert(metadata.id.clone()));
+        if let Some(limit) = limit {
+            results.truncate(limit);
+        }
+        Ok(results)
+    }
+    
+    async fn head(&self, id: &str) -> Result<ObjectMetadata> {
+        match self.locate(id).await? {
+            Some((_, metadata)) => Ok(metadata),
+            None => Err(NimbuxError::ObjectNotFound { object_id: id.to_string() }),
+        }
+    }
+    
+    async fn stats(&self) -> Result<StorageStats> {
+        let mut total = StorageStats { total_objects: 0, total_size: 0, available_space: 0, used_space: 0 };
+        for backend in self.placement_backends()? {
+            let stats = backend.stats().await?;
+            total.total_objects += stats.total_objects;
+            total.total_size += stats.total_size;
+            total.available_space += stats.available_space;
+            total.used_space += stats.used_space;
+        }
+        Ok(total)
+    }
+}
+
+/// Shared backends, so a backend registered with the engine can also be driven directly
+#[async_trait]
+impl<T: StorageBackend + ?Sized> StorageBackend for std::sync::Arc<T> {
     async fn put(&self, object: Object) -> Result<()> {
-        self.get_default_backend()?.put(object).await
+        (**self).put(object).await
     }
     
     async fn get(&self, id: &str) -> Result<Object> {
-        self.get_default_backend()?.get(id).await
+        (**self).get(id).await
     }
     
     async fn delete(&self, id: &str) -> Result<()> {
-        self.get_default_backend()?.delete(id).await
+        (**self).delete(id).await
     }
     
     async fn exists(&self, id: &str) -> Result<bool> {
-        self.get_default_backend()?.exists(id).await
+        (**self).exists(id).await
     }
     
     async fn list(&self, prefix: Option<&str>, limit: Option<usize>) -> Result<Vec<ObjectMetadata>> {
-        self.get_default_backend()?.list(prefix, limit).await
+        (**self).list(prefix, limit).await
     }
     
     async fn head(&self, id: &str) -> Result<ObjectMetadata> {
-        self.get_default_backend()?.head(id).await
+        (**self).head(id).await
     }
     
     async fn stats(&self) -> Result<StorageStats> {
-        self.get_default_backend()?.stats().await
+        (**self).stats().await
     }
 }
 
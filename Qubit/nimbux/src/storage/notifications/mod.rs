@@ -0,0 +1,714 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Bucket event notifications for object writes and deletes
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify, RwLock};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::versioning::VERSION_ID_TAG;
+use super::{Object, ObjectMetadata, StorageBackend, StorageStats};
+use crate::errors::{NimbuxError, Result};
+
+mod sink;
+
+pub use sink::{EventSink, HttpEventSink};
+
+/// Event name of object writes, including overwrites and completed multipart uploads
+pub const OBJECT_CREATED_EVENT: &str = "object.created";
+/// Event name of object deletes, including those that leave a delete marker
+pub const OBJECT_DELETED_EVENT: &str = "object.deleted";
+/// Rules accepted in one bucket's configuration
+pub const MAX_RULES_PER_BUCKET: usize = 100;
+
+const CONFIG_PREFIX: &str = ".notifications/buckets/";
+
+/// Event notification configuration
+#[derive(Debug, Clone)]
+pub struct NotificationConfig {
+    /// Deliveries to webhooks and Messenger running concurrently
+    pub workers: usize,
+    /// Deliveries waiting for a worker before new events are dropped
+    pub max_pending: usize,
+    /// Attempts per delivery, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled after each further failure
+    pub retry_backoff: Duration,
+    /// Events kept in each internal queue before the oldest are dropped
+    pub queue_capacity: usize,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            max_pending: 10_000,
+            max_attempts: 5,
+            retry_backoff: Duration::from_millis(500),
+            queue_capacity: 10_000,
+        }
+    }
+}
+
+/// Kind of change to an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ObjectEventType {
+    #[serde(rename = "object.created")]
+    Created,
+    #[serde(rename = "object.deleted")]
+    Deleted,
+}
+
+impl ObjectEventType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ObjectEventType::Created => OBJECT_CREATED_EVENT,
+            ObjectEventType::Deleted => OBJECT_DELETED_EVENT,
+        }
+    }
+}
+
+/// Keys a rule applies to; an empty filter matches every key
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationFilter {
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub suffix: Option<String>,
+}
+
+impl NotificationFilter {
+    pub fn matches(&self, key: &str) -> bool {
+        self.prefix.as_deref().is_none_or(|prefix| key.starts_with(prefix))
+            && self.suffix.as_deref().is_none_or(|suffix| key.ends_with(suffix))
+    }
+}
+
+/// Where matching events are sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationTarget {
+    /// `POST` each event as JSON
+    Webhook { url: String },
+    /// Publish each event to a Messenger topic, keyed by object
+    Messenger { stream: String, topic: String },
+    /// Keep events in a named in-process queue until they are received
+    Queue { name: String },
+}
+
+/// Sends one kind of event for matching keys to a target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationRule {
+    pub id: String,
+    pub events: Vec<ObjectEventType>,
+    #[serde(default)]
+    pub filter: NotificationFilter,
+    pub target: NotificationTarget,
+}
+
+impl NotificationRule {
+    fn applies_to(&self, event_type: ObjectEventType, key: &str) -> bool {
+        self.events.contains(&event_type) && self.filter.matches(key)
+    }
+}
+
+/// Notification rules of a bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketNotifications {
+    pub bucket: String,
+    pub rules: Vec<NotificationRule>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A change to an object, as delivered to a target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectEvent {
+    pub event_id: String,
+    pub event_type: ObjectEventType,
+    /// Rule the event matched
+    pub rule_id: String,
+    pub bucket: String,
+    pub key: String,
+    /// Size, content type and checksum are only known for created objects
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    pub checksum: Option<String>,
+    /// Version written, in versioned buckets
+    pub version_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl ObjectEvent {
+    /// `<bucket>/<key>` ID of the object
+    pub fn object_id(&self) -> String {
+        format!("{}/{}", self.bucket, self.key)
+    }
+}
+
+/// Event counts since startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationStats {
+    /// Events that matched a rule
+    pub published: u64,
+    /// Events delivered to a webhook or Messenger, or added to a queue
+    pub delivered: u64,
+    /// Deliveries that failed on every attempt
+    pub failed: u64,
+    /// Events dropped because deliveries or a queue were full
+    pub dropped: u64,
+    /// Deliveries waiting for a worker
+    pub pending: usize,
+    /// Events waiting in internal queues
+    pub queued: usize,
+}
+
+struct PendingDelivery {
+    target: NotificationTarget,
+    event: ObjectEvent,
+}
+
+/// Matches object changes against bucket rules and delivers the events.
+///
+/// Webhook and Messenger deliveries are made by background workers through
+/// the sink, retrying with backoff, so writes never wait on a target.
+/// Pending deliveries and queued events are held in memory and are lost on
+/// restart. Rules are kept under `.notifications/buckets/<bucket>`.
+pub struct EventNotifier {
+    storage: Arc<dyn StorageBackend>,
+    sink: Option<Arc<dyn EventSink>>,
+    config: NotificationConfig,
+    rules: RwLock<HashMap<String, Arc<Vec<NotificationRule>>>>,
+    pending: Mutex<VecDeque<PendingDelivery>>,
+    wakeup: Notify,
+    queues: Mutex<HashMap<String, VecDeque<ObjectEvent>>>,
+    published: AtomicU64,
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl EventNotifier {
+    pub fn new(storage: Arc<dyn StorageBackend>, config: NotificationConfig) -> Self {
+        Self {
+            storage,
+            sink: None,
+            config,
+            rules: RwLock::new(HashMap::new()),
+            pending: Mutex::new(VecDeque::new()),
+            wakeup: Notify::new(),
+            queues: Mutex::new(HashMap::new()),
+            published: AtomicU64::new(0),
+            delivered: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Deliver webhook and Messenger events through `sink`
+    pub fn with_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    pub async fn configuration(&self, bucket: &str) -> Result<Option<BucketNotifications>> {
+        match self.storage.get(&config_id(bucket)).await {
+            Ok(object) => Ok(Some(serde_json::from_slice(&object.data)?)),
+            Err(NimbuxError::ObjectNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the notification rules of a bucket
+    pub async fn set_configuration(&self, bucket: &str, rules: Vec<NotificationRule>) -> Result<BucketNotifications> {
+        validate_bucket(bucket)?;
+        validate_rules(&rules)?;
+
+        let notifications = BucketNotifications {
+            bucket: bucket.to_string(),
+            rules,
+            updated_at: Utc::now(),
+        };
+        let id = config_id(bucket);
+        let object = Object::with_id(id.clone(), id, serde_json::to_vec(&notifications)?, Some("application/json".to_string()));
+        self.storage.put(object).await?;
+
+        self.rules
+            .write()
+            .await
+            .insert(bucket.to_string(), Arc::new(notifications.rules.clone()));
+        tracing::info!("Notifications of bucket {} set with {} rules", bucket, notifications.rules.len());
+        Ok(notifications)
+    }
+
+    pub async fn delete_configuration(&self, bucket: &str) -> Result<()> {
+        match self.storage.delete(&config_id(bucket)).await {
+            Ok(()) => {}
+            Err(NimbuxError::ObjectNotFound { .. }) => {
+                return Err(NimbuxError::ObjectNotFound {
+                    object_id: format!("notification configuration of {}", bucket),
+                })
+            }
+            Err(e) => return Err(e),
+        }
+        self.rules.write().await.insert(bucket.to_string(), Arc::new(Vec::new()));
+        tracing::info!("Notifications of bucket {} removed", bucket);
+        Ok(())
+    }
+
+    /// Whether any rule of the bucket wants this change to `key`
+    pub async fn wants(&self, bucket: &str, key: &str, event_type: ObjectEventType) -> Result<bool> {
+        Ok(self
+            .rules_for(bucket)
+            .await?
+            .iter()
+            .any(|rule| rule.applies_to(event_type, key)))
+    }
+
+    /// Send an event for every matching rule, returning how many matched.
+    ///
+    /// `metadata` is the object as written, for created objects.
+    pub async fn publish(
+        &self,
+        event_type: ObjectEventType,
+        bucket: &str,
+        key: &str,
+        metadata: Option<&ObjectMetadata>,
+    ) -> Result<usize> {
+        let rules = self.rules_for(bucket).await?;
+        let occurred_at = Utc::now();
+        let mut matched = 0;
+
+        for rule in rules.iter().filter(|rule| rule.applies_to(event_type, key)) {
+            matched += 1;
+            self.published.fetch_add(1, Ordering::Relaxed);
+            let event = ObjectEvent {
+                event_id: Uuid::new_v4().to_string(),
+                event_type,
+                rule_id: rule.id.clone(),
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                size: metadata.map(|metadata| metadata.size),
+                content_type: metadata.and_then(|metadata| metadata.content_type.clone()),
+                checksum: metadata.map(|metadata| metadata.checksum.clone()),
+                version_id: metadata.and_then(|metadata| metadata.tags.get(VERSION_ID_TAG).cloned()),
+                occurred_at,
+            };
+
+            match &rule.target {
+                NotificationTarget::Queue { name } => self.enqueue(name, event).await,
+                target => {
+                    let mut pending = self.pending.lock().await;
+                    if pending.len() >= self.config.max_pending {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!(
+                            "Dropped {} event for {}: {} deliveries pending",
+                            event_type.name(),
+                            event.object_id(),
+                            pending.len()
+                        );
+                        continue;
+                    }
+                    pending.push_back(PendingDelivery { target: target.clone(), event });
+                    drop(pending);
+                    self.wakeup.notify_one();
+                }
+            }
+        }
+        Ok(matched)
+    }
+
+    /// Take up to `max` events from an internal queue, oldest first
+    pub async fn receive(&self, queue: &str, max: usize) -> Vec<ObjectEvent> {
+        let mut queues = self.queues.lock().await;
+        let Some(events) = queues.get_mut(queue) else {
+            return Vec::new();
+        };
+        let count = max.min(events.len());
+        events.drain(..count).collect()
+    }
+
+    pub async fn stats(&self) -> NotificationStats {
+        NotificationStats {
+            published: self.published.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            pending: self.pending.lock().await.len(),
+            queued: self.queues.lock().await.values().map(VecDeque::len).sum(),
+        }
+    }
+
+    /// Run the delivery workers
+    pub fn start(self: Arc<Self>) -> Vec<JoinHandle<()>> {
+        (0..self.config.workers.max(1))
+            .map(|_| {
+                let notifier = Arc::clone(&self);
+                tokio::spawn(async move {
+                    loop {
+                        let next = notifier.pending.lock().await.pop_front();
+                        match next {
+                            Some(delivery) => notifier.deliver(delivery).await,
+                            None => notifier.wakeup.notified().await,
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+
+    async fn deliver(&self, delivery: PendingDelivery) {
+        let PendingDelivery { target, event } = delivery;
+        let Some(sink) = &self.sink else {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("Event {} matched rule {} but no sink is configured", event.event_id, event.rule_id);
+            return;
+        };
+
+        let mut backoff = self.config.retry_backoff;
+        for attempt in 1..=self.config.max_attempts.max(1) {
+            match sink.deliver(&target, &event).await {
+                Ok(()) => {
+                    self.delivered.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Err(e) if attempt < self.config.max_attempts => {
+                    tracing::debug!("Delivery of event {} failed on attempt {}: {}", event.event_id, attempt, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Giving up on {} event for {} after {} attempts: {}",
+                        event.event_type.name(),
+                        event.object_id(),
+                        attempt,
+                        e
+                    );
+                }
+            }
+        }
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    async fn enqueue(&self, queue: &str, event: ObjectEvent) {
+        let mut queues = self.queues.lock().await;
+        let events = queues.entry(queue.to_string()).or_default();
+        if events.len() >= self.config.queue_capacity.max(1) {
+            events.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        events.push_back(event);
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    async fn rules_for(&self, bucket: &str) -> Result<Arc<Vec<NotificationRule>>> {
+        if let Some(rules) = self.rules.read().await.get(bucket) {
+            return Ok(Arc::clone(rules));
+        }
+
+        let rules = Arc::new(
+            self.configuration(bucket)
+                .await?
+                .map(|notifications| notifications.rules)
+                .unwrap_or_default(),
+        );
+        self.rules.write().await.insert(bucket.to_string(), Arc::clone(&rules));
+        Ok(rules)
+    }
+}
+
+/// Storage wrapper that publishes an event after each successful write or
+/// delete of a bucket object.
+///
+/// Publishing failures are logged and never fail the write. Internal objects,
+/// whose bucket starts with `.`, are not announced.
+pub struct NotifyingStorage {
+    inner: Arc<dyn StorageBackend>,
+    notifier: Arc<EventNotifier>,
+}
+
+impl NotifyingStorage {
+    pub fn new(inner: Arc<dyn StorageBackend>, notifier: Arc<EventNotifier>) -> Self {
+        Self { inner, notifier }
+    }
+
+    async fn announce(&self, event_type: ObjectEventType, object_id: &str) {
+        let Some((bucket, key)) = split_object_id(object_id) else {
+            return;
+        };
+        let result = async {
+            if !self.notifier.wants(bucket, key, event_type).await? {
+                return Ok(());
+            }
+            let metadata = match event_type {
+                ObjectEventType::Created => Some(self.inner.head(object_id).await?),
+                ObjectEventType::Deleted => None,
+            };
+            self.notifier.publish(event_type, bucket, key, metadata.as_ref()).await.map(|_| ())
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to publish {} event for {}: {}", event_type.name(), object_id, e);
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for NotifyingStorage {
+    async fn put(&self, object: Object) -> Result<()> {
+        let object_id = object.metadata.id.clone();
+        self.inner.put(object).await?;
+        self.announce(ObjectEventType::Created, &object_id).await;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Object> {
+        self.inner.get(id).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        self.inner.delete(id).await?;
+        self.announce(ObjectEventType::Deleted, id).await;
+        Ok(())
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool> {
+        self.inner.exists(id).await
+    }
+
+    async fn list(&self, prefix: Option<&str>, limit: Option<usize>) -> Result<Vec<ObjectMetadata>> {
+        self.inner.list(prefix, limit).await
+    }
+
+    async fn head(&self, id: &str) -> Result<ObjectMetadata> {
+        self.inner.head(id).await
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        self.inner.stats().await
+    }
+}
+
+/// Bucket and key of a `<bucket>/<key>` object ID; internal objects have none
+fn split_object_id(object_id: &str) -> Option<(&str, &str)> {
+    object_id
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !bucket.starts_with('.') && !key.is_empty())
+}
+
+fn config_id(bucket: &str) -> String {
+    format!("{}{}", CONFIG_PREFIX, bucket)
+}
+
+fn validate_bucket(bucket: &str) -> Result<()> {
+    if bucket.is_empty() || bucket.starts_with('.') || bucket.contains('/') {
+        return Err(NimbuxError::InvalidRequest(format!("Invalid bucket name: {}", bucket)));
+    }
+    Ok(())
+}
+
+fn validate_rules(rules: &[NotificationRule]) -> Result<()> {
+    let invalid = |rule: &NotificationRule, reason: &str| NimbuxError::InvalidRequest(format!("Rule {}: {}", rule.id, reason));
+    let is_name = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+
+    if rules.is_empty() || rules.len() > MAX_RULES_PER_BUCKET {
+        return Err(NimbuxError::InvalidRequest(format!(
+            "A notification configuration needs 1 to {} rules",
+            MAX_RULES_PER_BUCKET
+        )));
+    }
+    let mut ids = HashSet::new();
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err(NimbuxError::InvalidRequest("Rule IDs must not be empty".to_string()));
+        }
+        if !ids.insert(rule.id.as_str()) {
+            return Err(invalid(rule, "duplicate rule ID"));
+        }
+        if rule.events.is_empty() {
+            return Err(invalid(rule, "no events"));
+        }
+        match &rule.target {
+            NotificationTarget::Webhook { url } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(invalid(rule, "webhook URL must be http or https"));
+                }
+            }
+            NotificationTarget::Messenger { stream, topic } => {
+                if !is_name(stream) || !is_name(topic) {
+                    return Err(invalid(rule, "invalid Messenger stream or topic"));
+                }
+            }
+            NotificationTarget::Queue { name } => {
+                if !is_name(name) {
+                    return Err(invalid(rule, "invalid queue name"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use std::sync::atomic::AtomicU32;
+
+    fn rule(id: &str, events: &[ObjectEventType], target: NotificationTarget) -> NotificationRule {
+        NotificationRule {
+            id: id.to_string(),
+            events: events.to_vec(),
+            filter: NotificationFilter::default(),
+            target,
+        }
+    }
+
+    fn queue(name: &str) -> NotificationTarget {
+        NotificationTarget::Queue { name: name.to_string() }
+    }
+
+    async fn put(storage: &dyn StorageBackend, id: &str) {
+        let object = Object::with_id(id.to_string(), id.to_string(), id.as_bytes().to_vec(), Some("video/quicktime".to_string()));
+        storage.put(object).await.unwrap();
+    }
+
+    #[derive(Default)]
+    struct FlakySink {
+        failures_left: AtomicU32,
+        delivered: std::sync::Mutex<Vec<(NotificationTarget, ObjectEvent)>>,
+    }
+
+    #[async_trait]
+    impl EventSink for FlakySink {
+        async fn deliver(&self, target: &NotificationTarget, event: &ObjectEvent) -> Result<()> {
+            if self.failures_left.load(Ordering::SeqCst) > 0 {
+                self.failures_left.fetch_sub(1, Ordering::SeqCst);
+                return Err(NimbuxError::Network("unavailable".to_string()));
+            }
+            self.delivered.lock().unwrap().push((target.clone(), event.clone()));
+            Ok(())
+        }
+    }
+
+    async fn settled(notifier: &EventNotifier, deliveries: u64) -> NotificationStats {
+        for _ in 0..200 {
+            let stats = notifier.stats().await;
+            if stats.delivered + stats.failed >= deliveries {
+                return stats;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("deliveries did not settle");
+    }
+
+    #[tokio::test]
+    async fn test_filtered_events_reach_internal_queues() {
+        let inner = Arc::new(MemoryStorage::new());
+        let notifier = Arc::new(EventNotifier::new(inner.clone(), NotificationConfig::default()));
+        let storage = NotifyingStorage::new(inner, notifier.clone());
+
+        let mut uploads = rule("uploads", &[ObjectEventType::Created], queue("media-processor"));
+        uploads.filter = NotificationFilter { prefix: Some("raw/".to_string()), suffix: Some(".mov".to_string()) };
+        let deletes = rule("deletes", &[ObjectEventType::Deleted], queue("cleanup"));
+        notifier.set_configuration("media", vec![uploads, deletes]).await.unwrap();
+
+        put(&storage, "media/raw/a.mov").await;
+        put(&storage, "media/raw/a.txt").await;
+        put(&storage, "media/thumbs/a.mov").await;
+        put(&storage, "other/raw/a.mov").await;
+        storage.delete("media/raw/a.mov").await.unwrap();
+
+        let created = notifier.receive("media-processor", 10).await;
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].event_type, ObjectEventType::Created);
+        assert_eq!(created[0].object_id(), "media/raw/a.mov");
+        assert_eq!(created[0].size, Some("media/raw/a.mov".len() as u64));
+        assert_eq!(created[0].content_type.as_deref(), Some("video/quicktime"));
+        assert!(notifier.receive("media-processor", 10).await.is_empty());
+
+        let deleted = notifier.receive("cleanup", 10).await;
+        assert_eq!(deleted.len(), 1);
+        assert_eq!((deleted[0].rule_id.as_str(), deleted[0].key.as_str()), ("deletes", "raw/a.mov"));
+
+        notifier.delete_configuration("media").await.unwrap();
+        put(&storage, "media/raw/b.mov").await;
+        assert!(notifier.receive("media-processor", 10).await.is_empty());
+        assert_eq!(notifier.stats().await.published, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_deliveries_are_retried_then_counted() {
+        let inner = Arc::new(MemoryStorage::new());
+        let sink = Arc::new(FlakySink { failures_left: AtomicU32::new(1), ..Default::default() });
+        let config = NotificationConfig { max_attempts: 2, retry_backoff: Duration::from_millis(1), ..Default::default() };
+        let notifier = Arc::new(EventNotifier::new(inner.clone(), config).with_sink(sink.clone()));
+        let handles = Arc::clone(&notifier).start();
+        let storage = NotifyingStorage::new(inner, notifier.clone());
+
+        let target = NotificationTarget::Messenger { stream: "nimbux".to_string(), topic: "uploads".to_string() };
+        notifier
+            .set_configuration("media", vec![rule("all", &[ObjectEventType::Created], target.clone())])
+            .await
+            .unwrap();
+
+        put(&storage, "media/a.mov").await;
+        let stats = settled(&notifier, 1).await;
+        assert_eq!((stats.delivered, stats.failed), (1, 0));
+        let delivered = sink.delivered.lock().unwrap().clone();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].0, target);
+        assert_eq!(delivered[0].1.object_id(), "media/a.mov");
+
+        sink.failures_left.store(2, Ordering::SeqCst);
+        put(&storage, "media/b.mov").await;
+        let stats = settled(&notifier, 2).await;
+        assert_eq!((stats.delivered, stats.failed), (1, 1));
+        assert_eq!(sink.delivered.lock().unwrap().len(), 1);
+
+        for handle in handles {
+            handle.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_configurations_and_full_queues() {
+        let inner = Arc::new(MemoryStorage::new());
+        let config = NotificationConfig { queue_capacity: 2, ..Default::default() };
+        let notifier = Arc::new(EventNotifier::new(inner.clone(), config));
+        let storage = NotifyingStorage::new(inner, notifier.clone());
+
+        let all = rule("all", &[ObjectEventType::Created], queue("events"));
+        assert!(notifier.set_configuration("media", vec![]).await.is_err());
+        assert!(notifier.set_configuration(".s3", vec![all.clone()]).await.is_err());
+        assert!(notifier.set_configuration("media", vec![all.clone(), all.clone()]).await.is_err());
+        assert!(notifier.set_configuration("media", vec![rule("none", &[], queue("events"))]).await.is_err());
+        let ftp = NotificationTarget::Webhook { url: "ftp://hooks.local".to_string() };
+        assert!(notifier.set_configuration("media", vec![rule("ftp", &[ObjectEventType::Created], ftp)]).await.is_err());
+        assert!(notifier.set_configuration("media", vec![rule("bad", &[ObjectEventType::Created], queue("a/b"))]).await.is_err());
+        assert!(notifier.delete_configuration("media").await.is_err());
+
+        notifier.set_configuration("media", vec![all]).await.unwrap();
+        assert_eq!(notifier.configuration("media").await.unwrap().unwrap().rules.len(), 1);
+        for name in ["media/1", "media/2", "media/3"] {
+            put(&storage, name).await;
+        }
+        let stats = notifier.stats().await;
+        assert_eq!((stats.published, stats.dropped, stats.queued), (3, 1, 2));
+        let keys: Vec<_> = notifier.receive("events", 10).await.into_iter().map(|event| event.key).collect();
+        assert_eq!(keys, vec!["2", "3"]);
+    }
+}
@@ -0,0 +1,101 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Delivery of bucket events to webhooks and Messenger streams
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde_json::json;
+
+use super::{NotificationTarget, ObjectEvent};
+use crate::errors::{NimbuxError, Result};
+
+/// Delivers an event to a target outside the process
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    async fn deliver(&self, target: &NotificationTarget, event: &ObjectEvent) -> Result<()>;
+}
+
+/// Posts events to webhooks, and to Messenger topics through its HTTP API
+pub struct HttpEventSink {
+    client: reqwest::Client,
+    messenger: Option<MessengerEndpoint>,
+}
+
+struct MessengerEndpoint {
+    url: String,
+    token: String,
+}
+
+impl HttpEventSink {
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| NimbuxError::Configuration(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self { client, messenger: None })
+    }
+
+    /// Messenger server and access token used for `messenger` targets
+    pub fn with_messenger(mut self, url: impl Into<String>, token: impl Into<String>) -> Self {
+        self.messenger = Some(MessengerEndpoint {
+            url: url.into().trim_end_matches('/').to_string(),
+            token: token.into(),
+        });
+        self
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder, target: &str) -> Result<()> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| NimbuxError::Network(format!("Failed to deliver event to {}: {}", target, e)))?;
+        if !response.status().is_success() {
+            return Err(NimbuxError::Network(format!(
+                "Event delivery to {} was rejected with {}",
+                target,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for HttpEventSink {
+    async fn deliver(&self, target: &NotificationTarget, event: &ObjectEvent) -> Result<()> {
+        match target {
+            NotificationTarget::Webhook { url } => {
+                let request = self
+                    .client
+                    .post(url)
+                    .header("x-nimbux-event", event.event_type.name())
+                    .json(event);
+                self.send(request, url).await
+            }
+            NotificationTarget::Messenger { stream, topic } => {
+                let Some(messenger) = &self.messenger else {
+                    return Err(NimbuxError::Configuration(
+                        "Messenger notifications are not configured".to_string(),
+                    ));
+                };
+                let url = format!("{}/streams/{}/topics/{}/messages", messenger.url, stream, topic);
+                // Keying by object keeps the events of one object in order
+                let body = json!({
+                    "partitioning": { "kind": "messages_key", "value": STANDARD.encode(event.object_id()) },
+                    "messages": [{ "id": 0, "payload": STANDARD.encode(serde_json::to_vec(event)?) }],
+                });
+                let request = self.client.post(&url).bearer_auth(&messenger.token).json(&body);
+                self.send(request, &url).await
+            }
+            NotificationTarget::Queue { name } => Err(NimbuxError::Internal(format!(
+                "Queue {} is delivered in process, not through a sink",
+                name
+            ))),
+        }
+    }
+}
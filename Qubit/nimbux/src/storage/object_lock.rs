@@ -0,0 +1,496 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Object lock: retention periods and legal holds that keep objects from
+// being deleted or overwritten
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use super::placement::{self, save, validate_name};
+use super::versioning::VERSIONS_PREFIX;
+use super::{ObjectMetadata, StorageBackend};
+use crate::errors::{NimbuxError, Result};
+
+/// Tag holding the retention mode of a retained object
+pub const RETENTION_MODE_TAG: &str = "nimbux-retention-mode";
+/// Tag holding when an object's retention ends, in RFC 3339
+pub const RETAIN_UNTIL_TAG: &str = "nimbux-retain-until";
+/// Tag set to `ON` on objects under legal hold
+pub const LEGAL_HOLD_TAG: &str = "nimbux-legal-hold";
+
+const BUCKET_PREFIX: &str = ".object-lock/buckets/";
+/// Longest default retention a bucket can have, about a hundred years
+const MAX_DEFAULT_RETENTION_DAYS: u32 = 36_500;
+
+/// Who can lift a retention before it ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RetentionMode {
+    /// Administrators can shorten or remove the retention by bypassing governance
+    Governance,
+    /// Nobody can shorten or remove the retention; it can only be extended
+    Compliance,
+}
+
+impl RetentionMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            RetentionMode::Governance => "GOVERNANCE",
+            RetentionMode::Compliance => "COMPLIANCE",
+        }
+    }
+}
+
+impl fmt::Display for RetentionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for RetentionMode {
+    type Err = NimbuxError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "GOVERNANCE" => Ok(RetentionMode::Governance),
+            "COMPLIANCE" => Ok(RetentionMode::Compliance),
+            _ => Err(NimbuxError::InvalidRequest(format!("Unknown retention mode: {}", name))),
+        }
+    }
+}
+
+/// Retention period of an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    pub mode: RetentionMode,
+    pub retain_until: DateTime<Utc>,
+}
+
+/// Lock state of an object, kept in its tags so it moves with the object
+/// between storage classes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectLock {
+    pub retention: Option<Retention>,
+    pub legal_hold: bool,
+}
+
+impl ObjectLock {
+    /// Lock state recorded in the object's tags; malformed tags are ignored
+    pub fn of(metadata: &ObjectMetadata) -> Self {
+        let mode = metadata.tags.get(RETENTION_MODE_TAG).and_then(|mode| mode.parse().ok());
+        let retain_until = metadata
+            .tags
+            .get(RETAIN_UNTIL_TAG)
+            .and_then(|until| DateTime::parse_from_rfc3339(until).ok())
+            .map(|until| until.with_timezone(&Utc));
+        Self {
+            retention: mode.zip(retain_until).map(|(mode, retain_until)| Retention { mode, retain_until }),
+            legal_hold: metadata.tags.get(LEGAL_HOLD_TAG).is_some_and(|hold| hold == "ON"),
+        }
+    }
+
+    /// Retention that has not ended yet
+    pub fn active_retention(&self, now: DateTime<Utc>) -> Option<&Retention> {
+        self.retention.as_ref().filter(|retention| retention.retain_until > now)
+    }
+
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.legal_hold || self.active_retention(now).is_some()
+    }
+
+    /// Fail if the object cannot be deleted or overwritten
+    pub fn check(&self, object_id: &str, now: DateTime<Utc>) -> Result<()> {
+        let reason = if self.legal_hold {
+            "it is under legal hold".to_string()
+        } else if let Some(retention) = self.active_retention(now) {
+            format!("it is retained in {} mode until {}", retention.mode, retention.retain_until.to_rfc3339())
+        } else {
+            return Ok(());
+        };
+        Err(NimbuxError::ObjectLocked { object_id: object_id.to_string(), reason })
+    }
+
+    /// Replace the retention. While one is active it can always be extended or
+    /// made compliance; shortening or removing a governance retention needs
+    /// `bypass_governance`, and a compliance retention never can be
+    pub fn set_retention(
+        &mut self,
+        object_id: &str,
+        retention: Option<Retention>,
+        bypass_governance: bool,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        if let Some(new) = &retention {
+            if new.retain_until <= now {
+                return Err(NimbuxError::InvalidRequest("Retention must end in the future".to_string()));
+            }
+        }
+        if let Some(current) = self.active_retention(now) {
+            let weakens = match &retention {
+                Some(new) => {
+                    new.retain_until < current.retain_until
+                        || (current.mode == RetentionMode::Compliance && new.mode == RetentionMode::Governance)
+                }
+                None => true,
+            };
+            let reason = match current.mode {
+                RetentionMode::Compliance if weakens => Some("a compliance retention can only be extended"),
+                RetentionMode::Governance if weakens && !bypass_governance => {
+                    Some("a governance retention can only be shortened or removed when bypassing governance")
+                }
+                _ => None,
+            };
+            if let Some(reason) = reason {
+                return Err(NimbuxError::ObjectLocked { object_id: object_id.to_string(), reason: reason.to_string() });
+            }
+        }
+        self.retention = retention;
+        Ok(())
+    }
+
+    /// Record the lock state in object tags
+    pub(super) fn apply(&self, tags: &mut HashMap<String, String>) {
+        match &self.retention {
+            Some(retention) => {
+                tags.insert(RETENTION_MODE_TAG.to_string(), retention.mode.name().to_string());
+                tags.insert(RETAIN_UNTIL_TAG.to_string(), retention.retain_until.to_rfc3339());
+            }
+            None => {
+                tags.remove(RETENTION_MODE_TAG);
+                tags.remove(RETAIN_UNTIL_TAG);
+            }
+        }
+        if self.legal_hold {
+            tags.insert(LEGAL_HOLD_TAG.to_string(), "ON".to_string());
+        } else {
+            tags.remove(LEGAL_HOLD_TAG);
+        }
+    }
+}
+
+/// Retention given to new objects of a bucket that do not set their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefaultRetention {
+    pub mode: RetentionMode,
+    pub days: u32,
+}
+
+/// Object lock settings of a bucket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketObjectLock {
+    pub bucket: String,
+    pub default_retention: Option<DefaultRetention>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Locked objects of a bucket, its archived versions included
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BucketLockSummary {
+    pub bucket: String,
+    pub default_retention: Option<DefaultRetention>,
+    /// Objects under retention or legal hold
+    pub locked_objects: u64,
+    pub legal_holds: u64,
+    pub governance: u64,
+    pub compliance: u64,
+    /// When the last retention in the bucket ends
+    pub retained_until: Option<DateTime<Utc>>,
+    /// Current objects that never had a retention, in a bucket with a
+    /// default retention; written before the default was set
+    pub unretained_objects: u64,
+}
+
+/// Locked objects across all buckets
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObjectLockReport {
+    pub locked_objects: u64,
+    pub legal_holds: u64,
+    pub governance: u64,
+    pub compliance: u64,
+    pub buckets: Vec<BucketLockSummary>,
+    /// Deletes and overwrites refused since startup because of a lock
+    pub denied_operations: u64,
+}
+
+/// Default retentions and refused writes of a `StorageEngine`.
+///
+/// Lock state lives in object tags; only bucket defaults are saved, under
+/// `.object-lock/buckets/`.
+pub(super) struct ObjectLocks {
+    buckets: RwLock<HashMap<String, BucketObjectLock>>,
+    denied: AtomicU64,
+}
+
+impl ObjectLocks {
+    pub(super) fn new() -> Self {
+        Self {
+            buckets: RwLock::new(HashMap::new()),
+            denied: AtomicU64::new(0),
+        }
+    }
+
+    pub(super) async fn load(&self, store: &dyn StorageBackend) -> Result<()> {
+        let mut buckets = HashMap::new();
+        for metadata in store.list(Some(BUCKET_PREFIX), None).await? {
+            let object = match store.get(&metadata.id).await {
+                Ok(object) => object,
+                Err(NimbuxError::ObjectNotFound { .. }) => continue,
+                Err(e) => return Err(e),
+            };
+            let settings: BucketObjectLock = serde_json::from_slice(&object.data)?;
+            buckets.insert(settings.bucket.clone(), settings);
+        }
+        tracing::info!("Loaded object lock settings of {} buckets", buckets.len());
+        *self.buckets.write().await = buckets;
+        Ok(())
+    }
+
+    pub(super) async fn bucket(&self, bucket: &str) -> BucketObjectLock {
+        self.buckets.read().await.get(bucket).cloned().unwrap_or_else(|| BucketObjectLock {
+            bucket: bucket.to_string(),
+            default_retention: None,
+            updated_at: None,
+        })
+    }
+
+    pub(super) async fn set_default_retention(
+        &self,
+        store: &dyn StorageBackend,
+        bucket: &str,
+        default_retention: Option<DefaultRetention>,
+    ) -> Result<BucketObjectLock> {
+        validate_name(bucket, "bucket")?;
+        if let Some(retention) = &default_retention {
+            if retention.days == 0 || retention.days > MAX_DEFAULT_RETENTION_DAYS {
+                return Err(NimbuxError::InvalidRequest(format!(
+                    "Default retention must be between 1 and {} days",
+                    MAX_DEFAULT_RETENTION_DAYS
+                )));
+            }
+        }
+
+        let mut buckets = self.buckets.write().await;
+        let settings = BucketObjectLock {
+            bucket: bucket.to_string(),
+            default_retention,
+            updated_at: Some(Utc::now()),
+        };
+        save(store, format!("{}{}", BUCKET_PREFIX, bucket), &settings).await?;
+        buckets.insert(bucket.to_string(), settings.clone());
+        tracing::info!("Bucket {} default retention set to {:?}", bucket, default_retention);
+        Ok(settings)
+    }
+
+    /// Give a new bucket object its bucket's default retention unless it
+    /// brings its own
+    pub(super) async fn apply_default(&self, bucket: &str, metadata: &mut ObjectMetadata, now: DateTime<Utc>) {
+        let mut lock = ObjectLock::of(metadata);
+        if lock.retention.is_some() {
+            return;
+        }
+        let Some(default) = self.buckets.read().await.get(bucket).and_then(|settings| settings.default_retention) else {
+            return;
+        };
+        lock.retention = Some(Retention { mode: default.mode, retain_until: now + Duration::days(default.days as i64) });
+        lock.apply(&mut metadata.tags);
+    }
+
+    /// Fail, and count the refusal, if the stored object cannot be deleted or overwritten
+    pub(super) fn check(&self, metadata: &ObjectMetadata, now: DateTime<Utc>) -> Result<()> {
+        ObjectLock::of(metadata).check(&metadata.id, now).inspect_err(|_| {
+            self.denied.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    pub(super) async fn report(&self, objects: impl IntoIterator<Item = ObjectMetadata>, now: DateTime<Utc>) -> ObjectLockReport {
+        let settings = self.buckets.read().await;
+        let mut buckets: BTreeMap<String, BucketLockSummary> = settings
+            .values()
+            .filter(|settings| settings.default_retention.is_some())
+            .map(|settings| {
+                let summary = BucketLockSummary {
+                    bucket: settings.bucket.clone(),
+                    default_retention: settings.default_retention,
+                    ..Default::default()
+                };
+                (settings.bucket.clone(), summary)
+            })
+            .collect();
+
+        let mut report = ObjectLockReport::default();
+        for metadata in objects {
+            let (bucket, current) = match placement::bucket_of(&metadata.id) {
+                Some(bucket) => (bucket, true),
+                None => match metadata.id.strip_prefix(VERSIONS_PREFIX).and_then(placement::bucket_of) {
+                    Some(bucket) => (bucket, false),
+                    None => continue,
+                },
+            };
+            let lock = ObjectLock::of(&metadata);
+            if current && lock.retention.is_none() {
+                if let Some(summary) = buckets.get_mut(bucket) {
+                    summary.unretained_objects += 1;
+                }
+            }
+            if !lock.is_locked(now) {
+                continue;
+            }
+
+            let summary = buckets.entry(bucket.to_string()).or_insert_with(|| BucketLockSummary {
+                bucket: bucket.to_string(),
+                default_retention: settings.get(bucket).and_then(|settings| settings.default_retention),
+                ..Default::default()
+            });
+            report.locked_objects += 1;
+            summary.locked_objects += 1;
+            if lock.legal_hold {
+                report.legal_holds += 1;
+                summary.legal_holds += 1;
+            }
+            if let Some(retention) = lock.active_retention(now) {
+                match retention.mode {
+                    RetentionMode::Governance => {
+                        report.governance += 1;
+                        summary.governance += 1;
+                    }
+                    RetentionMode::Compliance => {
+                        report.compliance += 1;
+                        summary.compliance += 1;
+                    }
+                }
+                summary.retained_until = summary.retained_until.max(Some(retention.retain_until));
+            }
+        }
+
+        report.buckets = buckets.into_values().collect();
+        report.denied_operations = self.denied.load(Ordering::Relaxed);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryStorage, Object, StorageEngine, VersionedStorage, VersioningStatus};
+    use std::sync::Arc;
+
+    fn engine() -> (StorageEngine, Arc<MemoryStorage>) {
+        let backend = Arc::new(MemoryStorage::new());
+        let mut engine = StorageEngine::new("memory".to_string());
+        engine.add_backend("memory".to_string(), Box::new(Arc::clone(&backend)));
+        (engine, backend)
+    }
+
+    fn object(id: &str, data: &[u8]) -> Object {
+        Object::with_id(id.to_string(), id.to_string(), data.to_vec(), None)
+    }
+
+    fn retention(mode: RetentionMode, days: i64) -> Option<Retention> {
+        Some(Retention { mode, retain_until: Utc::now() + Duration::days(days) })
+    }
+
+    fn is_locked<T: fmt::Debug>(result: Result<T>) -> bool {
+        matches!(result, Err(NimbuxError::ObjectLocked { .. }))
+    }
+
+    #[tokio::test]
+    async fn test_retained_objects_cannot_be_deleted_or_overwritten() {
+        let (engine, _) = engine();
+        engine.put(object("records/2024.pdf", b"ledger")).await.unwrap();
+        engine.set_object_retention("records/2024.pdf", retention(RetentionMode::Compliance, 30), false).await.unwrap();
+
+        assert!(is_locked(engine.delete("records/2024.pdf").await));
+        assert!(is_locked(engine.put(object("records/2024.pdf", b"forged")).await));
+        assert_eq!(engine.get("records/2024.pdf").await.unwrap().data, b"ledger");
+
+        // Compliance retention can be extended but not shortened, even when bypassing governance
+        engine.set_object_retention("records/2024.pdf", retention(RetentionMode::Compliance, 60), false).await.unwrap();
+        assert!(is_locked(engine.set_object_retention("records/2024.pdf", retention(RetentionMode::Compliance, 10), true).await));
+        assert!(is_locked(engine.set_object_retention("records/2024.pdf", None, true).await));
+        assert!(is_locked(engine.set_object_retention("records/2024.pdf", retention(RetentionMode::Governance, 90), true).await));
+
+        // Governance retention can be lifted by bypassing it
+        engine.put(object("records/draft.pdf", b"draft")).await.unwrap();
+        engine.set_object_retention("records/draft.pdf", retention(RetentionMode::Governance, 30), false).await.unwrap();
+        assert!(is_locked(engine.set_object_retention("records/draft.pdf", None, false).await));
+        engine.set_object_retention("records/draft.pdf", None, true).await.unwrap();
+        engine.delete("records/draft.pdf").await.unwrap();
+
+        assert_eq!(engine.object_lock_report().await.unwrap().denied_operations, 2);
+    }
+
+    #[tokio::test]
+    async fn test_legal_hold_blocks_deletes_until_released() {
+        let (engine, _) = engine();
+        engine.put(object("evidence/mail.eml", b"mail")).await.unwrap();
+        let lock = engine.set_legal_hold("evidence/mail.eml", true).await.unwrap();
+        assert_eq!(lock, ObjectLock { retention: None, legal_hold: true });
+        assert!(is_locked(engine.delete("evidence/mail.eml").await));
+
+        // The lock survives metadata reads and lives in the object's tags
+        assert_eq!(engine.head("evidence/mail.eml").await.unwrap().tags[LEGAL_HOLD_TAG], "ON");
+        assert!(engine.object_lock("evidence/mail.eml").await.unwrap().is_locked(Utc::now()));
+
+        engine.set_legal_hold("evidence/mail.eml", false).await.unwrap();
+        engine.delete("evidence/mail.eml").await.unwrap();
+        assert!(matches!(engine.set_legal_hold("evidence/mail.eml", true).await, Err(NimbuxError::ObjectNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_bucket_default_retention_applies_to_new_objects() {
+        let (engine, backend) = engine();
+        engine.put(object("audit/old.log", b"before")).await.unwrap();
+        let default = DefaultRetention { mode: RetentionMode::Governance, days: 7 };
+        engine.set_bucket_default_retention("audit", Some(default)).await.unwrap();
+        assert!(engine.set_bucket_default_retention("audit", Some(DefaultRetention { days: 0, ..default })).await.is_err());
+
+        engine.put(object("audit/new.log", b"after")).await.unwrap();
+        let lock = engine.object_lock("audit/new.log").await.unwrap();
+        assert_eq!(lock.retention.unwrap().mode, RetentionMode::Governance);
+        assert!(is_locked(engine.delete("audit/new.log").await));
+        engine.delete("audit/old.log").await.unwrap();
+        engine.put(object("audit/old.log", b"rewritten")).await.unwrap();
+
+        let report = engine.object_lock_report().await.unwrap();
+        assert_eq!((report.locked_objects, report.governance, report.compliance), (2, 2, 0));
+        assert_eq!(report.buckets[0].default_retention, Some(default));
+
+        // Defaults are reloaded with the rest of the engine's settings
+        let mut reloaded = StorageEngine::new("memory".to_string());
+        reloaded.add_backend("memory".to_string(), Box::new(backend));
+        reloaded.load_placement().await.unwrap();
+        assert_eq!(reloaded.bucket_object_lock("audit").await.default_retention, Some(default));
+        assert!(is_locked(reloaded.put(object("audit/new.log", b"again")).await));
+    }
+
+    #[tokio::test]
+    async fn test_versioned_buckets_keep_locked_versions() {
+        let (engine, _) = engine();
+        let engine = Arc::new(engine);
+        let versioned = VersionedStorage::new(engine.clone());
+        versioned.set_versioning("contracts", VersioningStatus::Enabled).await.unwrap();
+
+        versioned.put(object("contracts/acme.pdf", b"v1")).await.unwrap();
+        engine.set_legal_hold("contracts/acme.pdf", true).await.unwrap();
+
+        // Refused before the current version is archived, so no version is left behind
+        assert!(is_locked(versioned.delete("contracts/acme.pdf").await));
+        assert!(is_locked(versioned.put(object("contracts/acme.pdf", b"v2")).await));
+        assert_eq!(versioned.list_versions("contracts/acme.pdf").await.unwrap().len(), 1);
+
+        // Once released the object takes new versions again
+        engine.set_legal_hold("contracts/acme.pdf", false).await.unwrap();
+        versioned.put(object("contracts/acme.pdf", b"v2")).await.unwrap();
+        assert_eq!(versioned.list_versions("contracts/acme.pdf").await.unwrap().len(), 2);
+        assert_eq!(engine.object_lock_report().await.unwrap().locked_objects, 0);
+    }
+}
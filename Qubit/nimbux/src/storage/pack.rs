@@ -0,0 +1,460 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Pack files for the content-addressable store
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use super::content_addressable::ObjectIndex;
+use super::gc::CompactionReport;
+use super::ObjectMetadata;
+use crate::errors::{NimbuxError, Result};
+
+/// Pack segments roll over once they reach this size
+pub const DEFAULT_SEGMENT_BYTES: u64 = 64 * 1024 * 1024; // 64MB
+
+const SEGMENT_MAGIC: &[u8; 8] = b"NBXPACK1";
+const SEGMENT_HEADER_LEN: u64 = SEGMENT_MAGIC.len() as u64;
+/// Raw BLAKE3 hash, data length and CRC32 of the data, ahead of each chunk
+const RECORD_HEADER_LEN: u64 = 32 + 4 + 4;
+
+/// Where a chunk's data sits in the pack files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChunkLocation {
+    segment: u64,
+    /// Offset of the data, after the record header
+    offset: u64,
+    len: u32,
+}
+
+impl ChunkLocation {
+    fn record_len(&self) -> u64 {
+        RECORD_HEADER_LEN + self.len as u64
+    }
+}
+
+/// One append-only pack file
+struct Segment {
+    id: u64,
+    path: PathBuf,
+    file: File,
+    len: AtomicU64,
+    /// Bytes of records that were removed or superseded
+    dead_bytes: AtomicU64,
+}
+
+impl Segment {
+    fn create(dir: &Path, id: u64) -> Result<Self> {
+        let path = segment_path(dir, id);
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        file.write_all_at(SEGMENT_MAGIC, 0)?;
+        Ok(Self { id, path, file, len: AtomicU64::new(SEGMENT_HEADER_LEN), dead_bytes: AtomicU64::new(0) })
+    }
+
+    fn dead_ratio(&self) -> f64 {
+        let payload = self.len.load(Ordering::Acquire).saturating_sub(SEGMENT_HEADER_LEN);
+        if payload == 0 {
+            return 0.0;
+        }
+        self.dead_bytes.load(Ordering::Relaxed).min(payload) as f64 / payload as f64
+    }
+
+    fn mark_dead(&self, location: &ChunkLocation) {
+        self.dead_bytes.fetch_add(location.record_len(), Ordering::Relaxed);
+    }
+
+    fn read(&self, location: &ChunkLocation) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; location.len as usize];
+        self.file.read_exact_at(&mut data, location.offset)?;
+        Ok(data)
+    }
+}
+
+fn segment_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:016x}.pack", id))
+}
+
+/// Chunk data stored in append-only pack files
+///
+/// Chunks are appended to the active segment, which rolls over at the
+/// segment size. Removing a chunk only forgets its location; the bytes stay
+/// in the file as dead space until [`PackStore::compact`] copies the live
+/// chunks of a mostly dead segment forward and deletes it. Reads hold the
+/// segment they are reading from, so a segment deleted by compaction stays
+/// readable until they finish.
+///
+/// Lock order: writer, then locations, then segments.
+pub struct PackStore {
+    dir: PathBuf,
+    segment_bytes: u64,
+    segments: RwLock<BTreeMap<u64, Arc<Segment>>>,
+    locations: RwLock<HashMap<String, ChunkLocation>>,
+    /// Segment new chunks are appended to
+    writer: Mutex<Arc<Segment>>,
+}
+
+impl PackStore {
+    /// Open the pack files in `dir`, creating it if needed, and index their chunks
+    ///
+    /// A torn record at the end of the newest segment, left by a crash during
+    /// a write, is truncated away.
+    pub fn open(dir: impl AsRef<Path>, segment_bytes: u64) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut ids: Vec<u64> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                u64::from_str_radix(name.strip_suffix(".pack")?, 16).ok()
+            })
+            .collect();
+        ids.sort_unstable();
+
+        let mut segments = BTreeMap::new();
+        let mut locations = HashMap::new();
+        for (index, id) in ids.iter().enumerate() {
+            let path = segment_path(&dir, *id);
+            let file = OpenOptions::new().read(true).write(true).open(&path)?;
+            let segment = Arc::new(Segment {
+                id: *id,
+                path,
+                file,
+                len: AtomicU64::new(0),
+                dead_bytes: AtomicU64::new(0),
+            });
+            let newest = index + 1 == ids.len();
+            Self::scan(&segment, newest, &segments, &mut locations)?;
+            segments.insert(*id, segment);
+        }
+
+        let writer = match segments.values().next_back() {
+            Some(segment) if segment.len.load(Ordering::Acquire) < segment_bytes => Arc::clone(segment),
+            last => {
+                let id = last.map_or(0, |segment| segment.id + 1);
+                let segment = Arc::new(Segment::create(&dir, id)?);
+                segments.insert(id, Arc::clone(&segment));
+                segment
+            }
+        };
+
+        info!("Opened {} pack segments with {} chunks in {}", segments.len(), locations.len(), dir.display());
+        Ok(Self {
+            dir,
+            segment_bytes,
+            segments: RwLock::new(segments),
+            locations: RwLock::new(locations),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Index the records of one segment; a later copy of a chunk supersedes earlier ones
+    fn scan(
+        segment: &Segment,
+        newest: bool,
+        segments: &BTreeMap<u64, Arc<Segment>>,
+        locations: &mut HashMap<String, ChunkLocation>,
+    ) -> Result<()> {
+        let file_len = segment.file.metadata()?.len();
+        let mut magic = [0u8; SEGMENT_MAGIC.len()];
+        if file_len < SEGMENT_HEADER_LEN || segment.file.read_exact_at(&mut magic, 0).is_err() || &magic != SEGMENT_MAGIC {
+            return Err(NimbuxError::Storage(format!("{} is not a pack file", segment.path.display())));
+        }
+
+        let mut offset = SEGMENT_HEADER_LEN;
+        let mut header = [0u8; RECORD_HEADER_LEN as usize];
+        while offset < file_len {
+            let record = segment.file.read_exact_at(&mut header, offset).ok().and_then(|_| {
+                let len = u32::from_le_bytes(header[32..36].try_into().unwrap());
+                let crc = u32::from_le_bytes(header[36..40].try_into().unwrap());
+                let location = ChunkLocation { segment: segment.id, offset: offset + RECORD_HEADER_LEN, len };
+                let data = segment.read(&location).ok()?;
+                (crc32fast::hash(&data) == crc).then_some(location)
+            });
+            let Some(location) = record else {
+                break;
+            };
+
+            let hash = hex::encode(&header[..32]);
+            if let Some(previous) = locations.insert(hash, location) {
+                match segments.get(&previous.segment) {
+                    Some(earlier) => earlier.mark_dead(&previous),
+                    None => segment.mark_dead(&previous),
+                }
+            }
+            offset += location.record_len();
+        }
+
+        if offset < file_len {
+            if newest {
+                warn!("Truncating torn record at offset {} of {}", offset, segment.path.display());
+                segment.file.set_len(offset)?;
+            } else {
+                warn!("Corrupt record at offset {} of {}, ignoring the rest of the segment", offset, segment.path.display());
+                segment.dead_bytes.fetch_add(file_len - offset, Ordering::Relaxed);
+                offset = file_len;
+            }
+        }
+        segment.len.store(offset, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.locations.read().contains_key(hash)
+    }
+
+    pub fn chunk_len(&self, hash: &str) -> Option<u64> {
+        self.locations.read().get(hash).map(|location| location.len as u64)
+    }
+
+    /// Bytes of live chunk data
+    pub fn live_bytes(&self) -> u64 {
+        self.locations.read().values().map(|location| location.len as u64).sum()
+    }
+
+    /// Bytes the pack files take on disk, dead space included
+    pub fn file_bytes(&self) -> u64 {
+        self.segments.read().values().map(|segment| segment.len.load(Ordering::Acquire)).sum()
+    }
+
+    pub fn hashes(&self) -> Vec<String> {
+        self.locations.read().keys().cloned().collect()
+    }
+
+    pub fn read(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        // Compaction moves a location before it retires the segment, so the
+        // segment is still registered while the location lock is held
+        let (segment, location) = {
+            let locations = self.locations.read();
+            let Some(location) = locations.get(hash).copied() else {
+                return Ok(None);
+            };
+            let segment = self.segments.read().get(&location.segment).cloned();
+            match segment {
+                Some(segment) => (segment, location),
+                None => return Err(NimbuxError::Internal(format!("Pack segment {} missing for chunk {}", location.segment, hash))),
+            }
+        };
+        segment.read(&location).map(Some)
+    }
+
+    /// Store a chunk unless it is already stored
+    pub fn insert(&self, hash: &str, data: &[u8]) -> Result<()> {
+        let mut writer = self.writer.lock();
+        if self.contains(hash) {
+            return Ok(());
+        }
+        let location = self.append(&mut writer, hash, data)?;
+        self.locations.write().insert(hash.to_string(), location);
+        Ok(())
+    }
+
+    fn append(&self, writer: &mut Arc<Segment>, hash: &str, data: &[u8]) -> Result<ChunkLocation> {
+        let raw_hash = hex::decode(hash)
+            .ok()
+            .filter(|raw| raw.len() == 32)
+            .ok_or_else(|| NimbuxError::Internal(format!("Invalid chunk hash {}", hash)))?;
+        let len = u32::try_from(data.len())
+            .map_err(|_| NimbuxError::Storage(format!("Chunk of {} bytes is too large for a pack file", data.len())))?;
+
+        let record_len = RECORD_HEADER_LEN + data.len() as u64;
+        let end = writer.len.load(Ordering::Acquire);
+        if end > SEGMENT_HEADER_LEN && end + record_len > self.segment_bytes {
+            writer.file.sync_data()?;
+            let next = Arc::new(Segment::create(&self.dir, writer.id + 1)?);
+            self.segments.write().insert(next.id, Arc::clone(&next));
+            debug!("Rolled pack segment {} over to {}", writer.id, next.id);
+            *writer = next;
+        }
+
+        let offset = writer.len.load(Ordering::Acquire);
+        let mut record = Vec::with_capacity(record_len as usize);
+        record.extend_from_slice(&raw_hash);
+        record.extend_from_slice(&len.to_le_bytes());
+        record.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+        record.extend_from_slice(data);
+        writer.file.write_all_at(&record, offset)?;
+        writer.len.store(offset + record_len, Ordering::Release);
+
+        Ok(ChunkLocation { segment: writer.id, offset: offset + RECORD_HEADER_LEN, len })
+    }
+
+    /// Forget a chunk, returning its size; its bytes become dead space
+    pub fn remove(&self, hash: &str) -> Option<u64> {
+        let location = self.locations.write().remove(hash)?;
+        if let Some(segment) = self.segments.read().get(&location.segment) {
+            segment.mark_dead(&location);
+        }
+        Some(location.len as u64)
+    }
+
+    /// Make appended chunks durable
+    pub fn sync(&self) -> Result<()> {
+        self.writer.lock().file.sync_data()?;
+        Ok(())
+    }
+
+    /// Rewrite sealed segments whose dead fraction is at least `min_dead_ratio`
+    ///
+    /// Live chunks are copied to the active segment without blocking reads or
+    /// writes. Each chunk then moves to its new location only if nothing
+    /// removed or replaced it meanwhile; otherwise the copy is left as dead
+    /// space. The old segment is deleted once none of its chunks are live.
+    pub fn compact(&self, min_dead_ratio: f64) -> Result<CompactionReport> {
+        let active = self.writer.lock().id;
+        let candidates: Vec<Arc<Segment>> = self
+            .segments
+            .read()
+            .values()
+            .filter(|segment| segment.id != active && segment.dead_ratio() >= min_dead_ratio)
+            .cloned()
+            .collect();
+
+        let mut report = CompactionReport::default();
+        for segment in candidates {
+            let live: Vec<(String, ChunkLocation)> = self
+                .locations
+                .read()
+                .iter()
+                .filter(|(_, location)| location.segment == segment.id)
+                .map(|(hash, location)| (hash.clone(), *location))
+                .collect();
+
+            let mut moved = Vec::with_capacity(live.len());
+            let mut copied_bytes = 0;
+            for (hash, old) in live {
+                let data = segment.read(&old)?;
+                let new = self.append(&mut self.writer.lock(), &hash, &data)?;
+                copied_bytes += new.record_len();
+                moved.push((hash, old, new));
+            }
+            self.sync()?;
+
+            {
+                let mut locations = self.locations.write();
+                let segments = self.segments.read();
+                for (hash, old, new) in moved {
+                    if locations.get(&hash) == Some(&old) {
+                        locations.insert(hash, new);
+                        report.chunks_moved += 1;
+                    } else if let Some(target) = segments.get(&new.segment) {
+                        target.mark_dead(&new);
+                    }
+                }
+                // Sealed segments take no new chunks, so nothing points here any more
+                drop(segments);
+                self.segments.write().remove(&segment.id);
+            }
+
+            fs::remove_file(&segment.path)?;
+            report.segments_rewritten += 1;
+            report.bytes_reclaimed += segment.len.load(Ordering::Acquire).saturating_sub(copied_bytes);
+            debug!("Compacted pack segment {}", segment.id);
+        }
+        Ok(report)
+    }
+}
+
+/// One change to the object index
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    Put { hashes: Vec<String>, metadata: ObjectMetadata },
+    Delete { id: String },
+}
+
+/// Append-only log of object index changes, replayed on open
+pub(crate) struct ObjectJournal {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: AtomicU64,
+}
+
+impl ObjectJournal {
+    /// Open the journal and rebuild the object index from it
+    ///
+    /// A partial last line, left by a crash during an append, is ignored.
+    pub(crate) fn open(path: impl AsRef<Path>) -> Result<(Self, ObjectIndex)> {
+        let path = path.as_ref().to_path_buf();
+        let mut index = ObjectIndex::new();
+        let mut entries = 0;
+        let mut valid_len = 0;
+        if path.exists() {
+            let mut reader = BufReader::new(File::open(&path)?);
+            let mut line = String::new();
+            while reader.read_line(&mut line)? > 0 {
+                let Some(entry) = line.strip_suffix('\n').and_then(|line| serde_json::from_str::<JournalEntry>(line).ok()) else {
+                    warn!("Ignoring incomplete entry at the end of {}", path.display());
+                    break;
+                };
+                match entry {
+                    JournalEntry::Put { hashes, metadata } => {
+                        index.insert(metadata.id.clone(), (hashes, metadata));
+                    }
+                    JournalEntry::Delete { id } => {
+                        index.remove(&id);
+                    }
+                }
+                entries += 1;
+                valid_len += line.len() as u64;
+                line.clear();
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.set_len(valid_len)?;
+        Ok((Self { path, file: Mutex::new(file), entries: AtomicU64::new(entries) }, index))
+    }
+
+    fn append(&self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = self.file.lock();
+        file.write_all(&line)?;
+        file.sync_data()?;
+        self.entries.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub(crate) fn record_put(&self, hashes: &[String], metadata: &ObjectMetadata) -> Result<()> {
+        self.append(&JournalEntry::Put { hashes: hashes.to_vec(), metadata: metadata.clone() })
+    }
+
+    pub(crate) fn record_delete(&self, id: &str) -> Result<()> {
+        self.append(&JournalEntry::Delete { id: id.to_string() })
+    }
+
+    pub(crate) fn entries(&self) -> u64 {
+        self.entries.load(Ordering::Relaxed)
+    }
+
+    /// Replace the journal with one entry per object; callers hold off writers
+    pub(crate) fn rewrite(&self, index: &ObjectIndex) -> Result<()> {
+        let temp = self.path.with_extension("tmp");
+        {
+            let mut out = std::io::BufWriter::new(File::create(&temp)?);
+            for (hashes, metadata) in index.values() {
+                serde_json::to_writer(&mut out, &JournalEntry::Put { hashes: hashes.clone(), metadata: metadata.clone() })?;
+                out.write_all(b"\n")?;
+            }
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        }
+
+        let mut file = self.file.lock();
+        fs::rename(&temp, &self.path)?;
+        *file = OpenOptions::new().append(true).open(&self.path)?;
+        self.entries.store(index.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+}
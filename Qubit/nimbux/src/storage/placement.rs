@@ -0,0 +1,629 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Bucket storage classes and bucket and user quotas
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+
+use super::{Object, ObjectMetadata, StorageBackend};
+use crate::errors::{NimbuxError, Result};
+
+/// Tag recording the storage class an object was placed in
+pub const STORAGE_CLASS_TAG: &str = "nimbux-storage-class";
+/// Tag holding the Pixelle user an object counts against
+pub const OWNER_TAG: &str = "pixelle-user-id";
+
+const BUCKET_PREFIX: &str = ".placement/buckets/";
+const USER_PREFIX: &str = ".placement/users/";
+const LOCK_STRIPES: usize = 64;
+
+/// Durability and cost tier a bucket's new objects are written to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StorageClass {
+    #[default]
+    Standard,
+    /// Cheaper storage for data that can be regenerated, such as thumbnails
+    ReducedRedundancy,
+    /// Archive storage for data that is rarely read
+    Cold,
+}
+
+impl StorageClass {
+    pub fn name(self) -> &'static str {
+        match self {
+            StorageClass::Standard => "STANDARD",
+            StorageClass::ReducedRedundancy => "REDUCED_REDUNDANCY",
+            StorageClass::Cold => "COLD",
+        }
+    }
+}
+
+impl fmt::Display for StorageClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for StorageClass {
+    type Err = NimbuxError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_uppercase().replace('-', "_").as_str() {
+            "STANDARD" => Ok(StorageClass::Standard),
+            "REDUCED_REDUNDANCY" => Ok(StorageClass::ReducedRedundancy),
+            "COLD" => Ok(StorageClass::Cold),
+            _ => Err(NimbuxError::InvalidRequest(format!("Unknown storage class: {}", name))),
+        }
+    }
+}
+
+/// Limits on stored bytes and objects; unset limits are not enforced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quota {
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_objects: Option<u64>,
+}
+
+/// Bytes and objects stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub bytes: u64,
+    pub objects: u64,
+}
+
+impl Usage {
+    fn apply(&mut self, delta: Delta) {
+        self.bytes = self.bytes.saturating_add_signed(delta.bytes);
+        self.objects = self.objects.saturating_add_signed(delta.objects);
+    }
+}
+
+/// Storage class and quota of a bucket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketPlacement {
+    pub bucket: String,
+    pub storage_class: StorageClass,
+    pub quota: Option<Quota>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Quota of a user across all buckets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserQuota {
+    pub user_id: String,
+    pub quota: Quota,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketUsage {
+    pub bucket: String,
+    pub storage_class: StorageClass,
+    pub usage: Usage,
+    /// Objects keep the class they were written in, so a bucket whose class
+    /// changed holds several
+    pub by_class: BTreeMap<StorageClass, Usage>,
+    pub quota: Option<Quota>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserUsage {
+    pub user_id: String,
+    pub usage: Usage,
+    pub quota: Option<Quota>,
+}
+
+/// Usage of every bucket and user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub total: Usage,
+    pub by_class: BTreeMap<StorageClass, Usage>,
+    pub buckets: Vec<BucketUsage>,
+    pub users: Vec<UserUsage>,
+    /// Writes refused since startup because they would exceed a quota
+    pub quota_rejections: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Delta {
+    bytes: i64,
+    objects: i64,
+}
+
+/// What a stored object counts against
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Charge {
+    bucket: String,
+    owner: Option<String>,
+    class: StorageClass,
+    size: u64,
+}
+
+impl Charge {
+    /// Charge of a bucket object; internal objects are not charged
+    pub(super) fn of(metadata: &ObjectMetadata) -> Option<Self> {
+        Some(Self {
+            bucket: bucket_of(&metadata.id)?.to_string(),
+            owner: metadata.tags.get(OWNER_TAG).cloned(),
+            class: metadata
+                .tags
+                .get(STORAGE_CLASS_TAG)
+                .and_then(|class| class.parse().ok())
+                .unwrap_or_default(),
+            size: metadata.size,
+        })
+    }
+}
+
+#[derive(Default)]
+struct UsageTable {
+    buckets: HashMap<String, HashMap<StorageClass, Usage>>,
+    users: HashMap<String, Usage>,
+}
+
+impl UsageTable {
+    fn charge(&mut self, charge: &Charge, sign: i64) {
+        let delta = Delta { bytes: sign * charge.size as i64, objects: sign };
+        self.buckets
+            .entry(charge.bucket.clone())
+            .or_default()
+            .entry(charge.class)
+            .or_default()
+            .apply(delta);
+        if let Some(owner) = &charge.owner {
+            self.users.entry(owner.clone()).or_default().apply(delta);
+        }
+    }
+
+    fn bucket(&self, bucket: &str) -> Usage {
+        self.buckets
+            .get(bucket)
+            .map(|classes| total(classes.values()))
+            .unwrap_or_default()
+    }
+}
+
+/// Storage classes, quotas and usage accounting of a `StorageEngine`.
+///
+/// Placement applies to bucket objects, `<bucket>/<key>`; internal objects
+/// always go to the default backend and are not charged. Usage is kept in
+/// memory and rebuilt from the backends at startup.
+pub(super) struct Placement {
+    class_backends: HashMap<StorageClass, String>,
+    buckets: RwLock<HashMap<String, BucketPlacement>>,
+    users: RwLock<HashMap<String, UserQuota>>,
+    usage: Mutex<UsageTable>,
+    locks: Vec<Mutex<()>>,
+    rejections: AtomicU64,
+}
+
+impl Placement {
+    pub(super) fn new() -> Self {
+        Self {
+            class_backends: HashMap::new(),
+            buckets: RwLock::new(HashMap::new()),
+            users: RwLock::new(HashMap::new()),
+            usage: Mutex::new(UsageTable::default()),
+            locks: (0..LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+            rejections: AtomicU64::new(0),
+        }
+    }
+
+    pub(super) fn set_class_backend(&mut self, class: StorageClass, backend: String) {
+        self.class_backends.insert(class, backend);
+    }
+
+    /// Backend of a storage class, if one other than the default is set
+    pub(super) fn class_backend(&self, class: StorageClass) -> Option<&str> {
+        self.class_backends.get(&class).map(String::as_str)
+    }
+
+    pub(super) fn class_backends(&self) -> impl Iterator<Item = &str> {
+        self.class_backends.values().map(String::as_str)
+    }
+
+    pub(super) fn lock_for(&self, object_id: &str) -> &Mutex<()> {
+        let mut hasher = DefaultHasher::new();
+        object_id.hash(&mut hasher);
+        &self.locks[hasher.finish() as usize % self.locks.len()]
+    }
+
+    /// Load bucket and user settings saved under `.placement/`
+    pub(super) async fn load(&self, store: &dyn StorageBackend) -> Result<()> {
+        let mut buckets = HashMap::new();
+        let mut users = HashMap::new();
+        for metadata in store.list(Some(".placement/"), None).await? {
+            let object = match store.get(&metadata.id).await {
+                Ok(object) => object,
+                Err(NimbuxError::ObjectNotFound { .. }) => continue,
+                Err(e) => return Err(e),
+            };
+            if metadata.id.starts_with(BUCKET_PREFIX) {
+                let placement: BucketPlacement = serde_json::from_slice(&object.data)?;
+                buckets.insert(placement.bucket.clone(), placement);
+            } else if metadata.id.starts_with(USER_PREFIX) {
+                let quota: UserQuota = serde_json::from_slice(&object.data)?;
+                users.insert(quota.user_id.clone(), quota);
+            }
+        }
+        tracing::info!("Loaded placement of {} buckets and quotas of {} users", buckets.len(), users.len());
+        *self.buckets.write().await = buckets;
+        *self.users.write().await = users;
+        Ok(())
+    }
+
+    pub(super) async fn bucket(&self, bucket: &str) -> BucketPlacement {
+        self.buckets.read().await.get(bucket).cloned().unwrap_or_else(|| BucketPlacement {
+            bucket: bucket.to_string(),
+            storage_class: StorageClass::Standard,
+            quota: None,
+            updated_at: None,
+        })
+    }
+
+    pub(super) async fn class_of(&self, bucket: &str) -> StorageClass {
+        self.buckets
+            .read()
+            .await
+            .get(bucket)
+            .map(|placement| placement.storage_class)
+            .unwrap_or_default()
+    }
+
+    pub(super) async fn update_bucket(
+        &self,
+        store: &dyn StorageBackend,
+        bucket: &str,
+        update: impl FnOnce(&mut BucketPlacement) -> Result<()>,
+    ) -> Result<BucketPlacement> {
+        validate_name(bucket, "bucket")?;
+        let mut buckets = self.buckets.write().await;
+        let mut placement = buckets.get(bucket).cloned().unwrap_or_else(|| BucketPlacement {
+            bucket: bucket.to_string(),
+            storage_class: StorageClass::Standard,
+            quota: None,
+            updated_at: None,
+        });
+        update(&mut placement)?;
+        if placement.storage_class != StorageClass::Standard && self.class_backend(placement.storage_class).is_none() {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Storage class {} is not available",
+                placement.storage_class
+            )));
+        }
+        placement.updated_at = Some(Utc::now());
+
+        save(store, format!("{}{}", BUCKET_PREFIX, bucket), &placement).await?;
+        buckets.insert(bucket.to_string(), placement.clone());
+        tracing::info!("Bucket {} placed in {} with quota {:?}", bucket, placement.storage_class, placement.quota);
+        Ok(placement)
+    }
+
+    pub(super) async fn user_quota(&self, user_id: &str) -> Option<UserQuota> {
+        self.users.read().await.get(user_id).cloned()
+    }
+
+    pub(super) async fn set_user_quota(
+        &self,
+        store: &dyn StorageBackend,
+        user_id: &str,
+        quota: Option<Quota>,
+    ) -> Result<Option<UserQuota>> {
+        validate_name(user_id, "user ID")?;
+        let id = format!("{}{}", USER_PREFIX, user_id);
+        let mut users = self.users.write().await;
+        let Some(quota) = quota else {
+            match store.delete(&id).await {
+                Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+            users.remove(user_id);
+            return Ok(None);
+        };
+
+        let user_quota = UserQuota { user_id: user_id.to_string(), quota, updated_at: Utc::now() };
+        save(store, id, &user_quota).await?;
+        users.insert(user_id.to_string(), user_quota.clone());
+        Ok(Some(user_quota))
+    }
+
+    /// Charge a write of `new` replacing `old`, failing if it would take the
+    /// bucket or the owner past a quota. Writes that do not grow usage are
+    /// always allowed, so a full bucket can still be cleaned up.
+    pub(super) async fn reserve(&self, new: &Charge, old: Option<&Charge>) -> Result<()> {
+        let bucket_quota = self.buckets.read().await.get(&new.bucket).and_then(|placement| placement.quota);
+        let user_quota = match &new.owner {
+            Some(owner) => self.users.read().await.get(owner).map(|quota| quota.quota),
+            None => None,
+        };
+
+        let mut usage = self.usage.lock().await;
+        if let Some(quota) = bucket_quota {
+            let delta = delta(new, old, |charge| charge.bucket == new.bucket);
+            check(&quota, usage.bucket(&new.bucket), delta, || format!("bucket {}", new.bucket))
+                .inspect_err(|_| {
+                    self.rejections.fetch_add(1, Ordering::Relaxed);
+                })?;
+        }
+        if let (Some(quota), Some(owner)) = (user_quota, &new.owner) {
+            let delta = delta(new, old, |charge| charge.owner.as_ref() == Some(owner));
+            let current = usage.users.get(owner).copied().unwrap_or_default();
+            check(&quota, current, delta, || format!("user {}", owner)).inspect_err(|_| {
+                self.rejections.fetch_add(1, Ordering::Relaxed);
+            })?;
+        }
+
+        if let Some(old) = old {
+            usage.charge(old, -1);
+        }
+        usage.charge(new, 1);
+        Ok(())
+    }
+
+    /// Undo a reservation whose write failed
+    pub(super) async fn release(&self, new: &Charge, old: Option<&Charge>) {
+        let mut usage = self.usage.lock().await;
+        usage.charge(new, -1);
+        if let Some(old) = old {
+            usage.charge(old, 1);
+        }
+    }
+
+    pub(super) async fn remove(&self, charge: &Charge) {
+        self.usage.lock().await.charge(charge, -1);
+    }
+
+    pub(super) async fn replace_usage(&self, charges: impl IntoIterator<Item = Charge>) {
+        let mut table = UsageTable::default();
+        for charge in charges {
+            table.charge(&charge, 1);
+        }
+        *self.usage.lock().await = table;
+    }
+
+    pub(super) async fn report(&self) -> UsageReport {
+        let buckets = self.buckets.read().await;
+        let users = self.users.read().await;
+        let usage = self.usage.lock().await;
+
+        let mut by_class: BTreeMap<StorageClass, Usage> = BTreeMap::new();
+        let mut bucket_usage: Vec<BucketUsage> = usage
+            .buckets
+            .iter()
+            .map(|(bucket, classes)| {
+                for (class, class_usage) in classes {
+                    by_class.entry(*class).or_default().apply(Delta {
+                        bytes: class_usage.bytes as i64,
+                        objects: class_usage.objects as i64,
+                    });
+                }
+                let placement = buckets.get(bucket);
+                BucketUsage {
+                    bucket: bucket.clone(),
+                    storage_class: placement.map(|placement| placement.storage_class).unwrap_or_default(),
+                    usage: total(classes.values()),
+                    by_class: classes.iter().map(|(class, usage)| (*class, *usage)).collect(),
+                    quota: placement.and_then(|placement| placement.quota),
+                }
+            })
+            .collect();
+        // Configured buckets show up before their first write
+        for (bucket, placement) in buckets.iter().filter(|(bucket, _)| !usage.buckets.contains_key(*bucket)) {
+            bucket_usage.push(BucketUsage {
+                bucket: bucket.clone(),
+                storage_class: placement.storage_class,
+                usage: Usage::default(),
+                by_class: BTreeMap::new(),
+                quota: placement.quota,
+            });
+        }
+        bucket_usage.sort_by(|a, b| a.bucket.cmp(&b.bucket));
+
+        let mut user_usage: Vec<UserUsage> = usage
+            .users
+            .iter()
+            .map(|(user_id, user_usage)| UserUsage {
+                user_id: user_id.clone(),
+                usage: *user_usage,
+                quota: users.get(user_id).map(|quota| quota.quota),
+            })
+            .collect();
+        for (user_id, quota) in users.iter().filter(|(user_id, _)| !usage.users.contains_key(*user_id)) {
+            user_usage.push(UserUsage { user_id: user_id.clone(), usage: Usage::default(), quota: Some(quota.quota) });
+        }
+        user_usage.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+
+        UsageReport {
+            total: total(by_class.values()),
+            by_class,
+            buckets: bucket_usage,
+            users: user_usage,
+            quota_rejections: self.rejections.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Bucket of a `<bucket>/<key>` object ID; internal objects have none
+pub(super) fn bucket_of(object_id: &str) -> Option<&str> {
+    object_id
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !bucket.starts_with('.') && !key.is_empty())
+        .map(|(bucket, _)| bucket)
+}
+
+/// Change in usage of the scope matched by `counts` when `old` becomes `new`
+fn delta(new: &Charge, old: Option<&Charge>, counts: impl Fn(&Charge) -> bool) -> Delta {
+    let mut delta = Delta::default();
+    if counts(new) {
+        delta.bytes += new.size as i64;
+        delta.objects += 1;
+    }
+    if let Some(old) = old.filter(|old| counts(old)) {
+        delta.bytes -= old.size as i64;
+        delta.objects -= 1;
+    }
+    delta
+}
+
+fn check(quota: &Quota, current: Usage, delta: Delta, scope: impl Fn() -> String) -> Result<()> {
+    let exceeds = |limit: Option<u64>, current: u64, delta: i64| {
+        delta > 0 && limit.is_some_and(|limit| current.saturating_add_signed(delta) > limit)
+    };
+    if exceeds(quota.max_bytes, current.bytes, delta.bytes) {
+        return Err(NimbuxError::QuotaExceeded(format!(
+            "{} would store {} bytes, over its quota of {}",
+            scope(),
+            current.bytes.saturating_add_signed(delta.bytes),
+            quota.max_bytes.unwrap_or_default()
+        )));
+    }
+    if exceeds(quota.max_objects, current.objects, delta.objects) {
+        return Err(NimbuxError::QuotaExceeded(format!(
+            "{} would hold {} objects, over its quota of {}",
+            scope(),
+            current.objects.saturating_add_signed(delta.objects),
+            quota.max_objects.unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
+
+fn total<'a>(usages: impl Iterator<Item = &'a Usage>) -> Usage {
+    usages.fold(Usage::default(), |total, usage| Usage {
+        bytes: total.bytes + usage.bytes,
+        objects: total.objects + usage.objects,
+    })
+}
+
+pub(super) async fn save(store: &dyn StorageBackend, id: String, value: &impl Serialize) -> Result<()> {
+    let object = Object::with_id(id.clone(), id, serde_json::to_vec(value)?, Some("application/json".to_string()));
+    store.put(object).await
+}
+
+pub(super) fn validate_name(name: &str, what: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') {
+        return Err(NimbuxError::InvalidRequest(format!("Invalid {}: {}", what, name)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryStorage, StorageEngine};
+    use std::sync::Arc;
+
+    fn engine() -> (StorageEngine, Arc<MemoryStorage>, Arc<MemoryStorage>) {
+        let standard = Arc::new(MemoryStorage::new());
+        let cold = Arc::new(MemoryStorage::new());
+        let mut engine = StorageEngine::new("standard".to_string());
+        engine.add_backend("standard".to_string(), Box::new(Arc::clone(&standard)));
+        engine.add_backend("cold".to_string(), Box::new(Arc::clone(&cold)));
+        engine.set_class_backend(StorageClass::Cold, "cold".to_string());
+        (engine, standard, cold)
+    }
+
+    fn object(id: &str, size: usize, owner: Option<&str>) -> Object {
+        let mut object = Object::with_id(id.to_string(), id.to_string(), vec![0; size], None);
+        if let Some(owner) = owner {
+            object.add_tag(OWNER_TAG.to_string(), owner.to_string());
+        }
+        object
+    }
+
+    #[tokio::test]
+    async fn test_objects_are_placed_by_bucket_class() {
+        let (engine, standard, cold) = engine();
+        assert!(engine.set_bucket_class("thumbs", StorageClass::ReducedRedundancy).await.is_err());
+        engine.set_bucket_class("archive", StorageClass::Cold).await.unwrap();
+
+        engine.put(object("archive/2024.tar", 10, None)).await.unwrap();
+        engine.put(object("media/a.jpg", 5, None)).await.unwrap();
+        assert!(cold.exists("archive/2024.tar").await.unwrap());
+        assert!(standard.exists("media/a.jpg").await.unwrap());
+        assert_eq!(engine.head("archive/2024.tar").await.unwrap().tags[STORAGE_CLASS_TAG], "COLD");
+        assert_eq!(engine.list(None, None).await.unwrap().iter().filter(|m| !m.id.starts_with('.')).count(), 2);
+
+        // Objects stay readable after a class change and move when rewritten
+        engine.set_bucket_class("media", StorageClass::Cold).await.unwrap();
+        assert_eq!(engine.get("media/a.jpg").await.unwrap().data.len(), 5);
+        engine.put(object("media/a.jpg", 7, None)).await.unwrap();
+        assert!(!standard.exists("media/a.jpg").await.unwrap());
+        assert!(cold.exists("media/a.jpg").await.unwrap());
+
+        let usage = engine.usage().await;
+        assert_eq!(usage.total, Usage { bytes: 17, objects: 2 });
+        assert_eq!(usage.by_class[&StorageClass::Cold], Usage { bytes: 17, objects: 2 });
+        assert!(!usage.by_class.contains_key(&StorageClass::Standard) || usage.by_class[&StorageClass::Standard].objects == 0);
+
+        engine.delete("media/a.jpg").await.unwrap();
+        assert!(!engine.exists("media/a.jpg").await.unwrap());
+        assert_eq!(engine.usage().await.total, Usage { bytes: 10, objects: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_quotas_are_enforced_at_write_time() {
+        let (engine, _, _) = engine();
+        engine.set_bucket_quota("media", Some(Quota { max_bytes: Some(100), max_objects: Some(3) })).await.unwrap();
+        engine.set_user_quota("alice", Some(Quota { max_bytes: Some(60), max_objects: None })).await.unwrap();
+
+        engine.put(object("media/a", 40, Some("alice"))).await.unwrap();
+        let rejected = engine.put(object("media/b", 30, Some("alice"))).await;
+        assert!(matches!(rejected, Err(NimbuxError::QuotaExceeded(_))));
+        assert!(!engine.exists("media/b").await.unwrap());
+
+        // Overwrites are charged the difference, and shrinking is always allowed
+        engine.put(object("media/a", 60, Some("alice"))).await.unwrap();
+        engine.put(object("media/b", 40, Some("bob"))).await.unwrap();
+        assert!(engine.put(object("media/c", 1, None)).await.is_err());
+        engine.set_bucket_quota("media", Some(Quota { max_bytes: Some(50), max_objects: None })).await.unwrap();
+        engine.put(object("media/a", 5, Some("alice"))).await.unwrap();
+        engine.put(object("other/big", 500, None)).await.unwrap();
+
+        let usage = engine.usage().await;
+        let media = usage.buckets.iter().find(|bucket| bucket.bucket == "media").unwrap();
+        assert_eq!(media.usage, Usage { bytes: 45, objects: 2 });
+        let alice = usage.users.iter().find(|user| user.user_id == "alice").unwrap();
+        assert_eq!((alice.usage, alice.quota.and_then(|quota| quota.max_bytes)), (Usage { bytes: 5, objects: 1 }, Some(60)));
+        assert_eq!(usage.quota_rejections, 2);
+
+        engine.set_user_quota("alice", None).await.unwrap();
+        assert!(engine.user_quota("alice").await.is_none());
+        assert!(engine.set_user_quota(".admin", Some(Quota::default())).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_placement_and_usage_survive_reload() {
+        let (engine, standard, cold) = engine();
+        engine.set_bucket_class("archive", StorageClass::Cold).await.unwrap();
+        engine.set_bucket_quota("archive", Some(Quota { max_bytes: Some(20), max_objects: None })).await.unwrap();
+        engine.set_user_quota("alice", Some(Quota { max_bytes: None, max_objects: Some(5) })).await.unwrap();
+        engine.put(object("archive/a", 15, Some("alice"))).await.unwrap();
+
+        let mut reloaded = StorageEngine::new("standard".to_string());
+        reloaded.add_backend("standard".to_string(), Box::new(standard));
+        reloaded.add_backend("cold".to_string(), Box::new(cold));
+        reloaded.set_class_backend(StorageClass::Cold, "cold".to_string());
+        reloaded.load_placement().await.unwrap();
+
+        let placement = reloaded.bucket_placement("archive").await;
+        assert_eq!(placement.storage_class, StorageClass::Cold);
+        assert_eq!(reloaded.user_quota("alice").await.unwrap().quota.max_objects, Some(5));
+        assert_eq!(reloaded.usage().await.total, Usage { bytes: 15, objects: 1 });
+        assert!(reloaded.put(object("archive/b", 10, None)).await.is_err());
+    }
+}
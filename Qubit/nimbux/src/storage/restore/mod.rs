@@ -0,0 +1,780 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Bulk restores of archived objects from the cold tier
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Mutex, Notify, RwLock};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::errors::{NimbuxError, Result};
+use crate::storage::{ObjectMetadata, StorageBackend};
+
+mod notify;
+mod queue;
+
+pub use notify::{HttpRestoreNotifier, RestoreNotifier, RESTORE_COMPLETED_EVENT};
+use queue::{FairQueue, QueuedRestore};
+
+/// Tag set on a restored copy in the hot tier, holding when it expires (RFC 3339)
+pub const RESTORED_UNTIL_TAG: &str = "nimbux-restored-until";
+
+/// Restore coordinator configuration
+#[derive(Debug, Clone)]
+pub struct RestoreConfig {
+    /// Objects copied back concurrently
+    pub workers: usize,
+    /// How long restored copies stay in the hot tier when a request does not say
+    pub default_window: Duration,
+    /// Longest window a request may ask for
+    pub max_window: Duration,
+    /// Objects accepted in a single request
+    pub max_objects_per_request: usize,
+    /// Buffered events per subscriber before slow subscribers start lagging
+    pub event_capacity: usize,
+}
+
+impl Default for RestoreConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            default_window: Duration::from_secs(7 * 24 * 3600),
+            max_window: Duration::from_secs(30 * 24 * 3600),
+            max_objects_per_request: 100_000,
+            event_capacity: 1024,
+        }
+    }
+}
+
+/// How urgently a restore is needed; higher priorities are always served first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestorePriority {
+    Expedited,
+    #[default]
+    Standard,
+    Bulk,
+}
+
+impl RestorePriority {
+    fn rank(self) -> usize {
+        match self {
+            RestorePriority::Expedited => 0,
+            RestorePriority::Standard => 1,
+            RestorePriority::Bulk => 2,
+        }
+    }
+}
+
+/// Where to announce a finished job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RestoreNotification {
+    /// `POST` the job as JSON
+    Webhook { url: String },
+    /// Publish the job to a Messenger topic
+    Messenger { stream: String, topic: String },
+}
+
+/// Request to bring archived objects back to the hot tier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreRequest {
+    /// Tenant the restore is accounted to for fair scheduling
+    pub tenant: String,
+    /// Object IDs (`<bucket>/<key>`)
+    pub objects: Vec<String>,
+    #[serde(default)]
+    pub priority: RestorePriority,
+    /// Days the restored copies stay available, the configured window if unset
+    pub days: Option<u32>,
+    #[serde(default)]
+    pub notify: Vec<RestoreNotification>,
+}
+
+/// Restore job status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreStatus {
+    Queued,
+    Running,
+    Completed,
+    /// Finished, but some objects could not be restored
+    Partial,
+    Failed,
+}
+
+/// Object that could not be restored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedRestore {
+    pub object_id: String,
+    pub error: String,
+}
+
+/// Restore job state, as reported by the progress API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreJob {
+    pub job_id: String,
+    pub tenant: String,
+    pub priority: RestorePriority,
+    pub status: RestoreStatus,
+    pub requested_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Seconds each restored copy stays available after it is restored
+    pub window_secs: u64,
+    pub total_objects: usize,
+    pub restored_objects: usize,
+    pub failed_objects: usize,
+    pub failures: Vec<FailedRestore>,
+    pub notify: Vec<RestoreNotification>,
+}
+
+impl RestoreJob {
+    /// Share of objects handled so far, between 0 and 1
+    pub fn progress(&self) -> f64 {
+        if self.total_objects == 0 {
+            return 1.0;
+        }
+        (self.restored_objects + self.failed_objects) as f64 / self.total_objects as f64
+    }
+
+    fn is_done(&self) -> bool {
+        self.restored_objects + self.failed_objects >= self.total_objects
+    }
+}
+
+/// Temporary hot-tier copy of an archived object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoredCopy {
+    pub object_id: String,
+    pub job_id: String,
+    pub tenant: String,
+    pub restored_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Queue depth and restored copies, for operators
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreQueueStats {
+    pub queued_objects: usize,
+    pub queued_by_tenant: HashMap<String, usize>,
+    pub active_jobs: usize,
+    pub restored_copies: usize,
+}
+
+/// Progress events published while restores run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RestoreEvent {
+    Queued {
+        job_id: String,
+        tenant: String,
+        priority: RestorePriority,
+        total_objects: usize,
+    },
+    ObjectRestored {
+        job_id: String,
+        object_id: String,
+        expires_at: DateTime<Utc>,
+    },
+    ObjectFailed {
+        job_id: String,
+        object_id: String,
+        error: String,
+    },
+    Completed {
+        job_id: String,
+        tenant: String,
+        status: RestoreStatus,
+        restored_objects: usize,
+        failed_objects: usize,
+        timestamp: DateTime<Utc>,
+    },
+    Expired {
+        object_id: String,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+/// Copies archived objects from the cold tier back into the hot tier on request,
+/// and removes the copies again once their window has passed
+pub struct RestoreCoordinator {
+    hot: Arc<dyn StorageBackend>,
+    cold: Arc<dyn StorageBackend>,
+    config: RestoreConfig,
+    queue: Mutex<FairQueue>,
+    wakeup: Notify,
+    jobs: RwLock<HashMap<String, RestoreJob>>,
+    restored: RwLock<HashMap<String, RestoredCopy>>,
+    notifier: Option<Arc<dyn RestoreNotifier>>,
+    events: broadcast::Sender<RestoreEvent>,
+}
+
+impl RestoreCoordinator {
+    pub fn new(hot: Arc<dyn StorageBackend>, cold: Arc<dyn StorageBackend>, config: RestoreConfig) -> Self {
+        let (events, _) = broadcast::channel(config.event_capacity.max(1));
+        Self {
+            hot,
+            cold,
+            config,
+            queue: Mutex::new(FairQueue::new()),
+            wakeup: Notify::new(),
+            jobs: RwLock::new(HashMap::new()),
+            restored: RwLock::new(HashMap::new()),
+            notifier: None,
+            events,
+        }
+    }
+
+    /// Deliver completion notifications through `notifier`
+    pub fn with_notifier(mut self, notifier: Arc<dyn RestoreNotifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Receive progress events for all restore jobs
+    pub fn subscribe(&self) -> broadcast::Receiver<RestoreEvent> {
+        self.events.subscribe()
+    }
+
+    /// Run the restore workers and sweep expired copies every `expiry_interval`
+    pub fn start(self: Arc<Self>, expiry_interval: Duration) -> Vec<JoinHandle<()>> {
+        let mut handles: Vec<_> = (0..self.config.workers.max(1))
+            .map(|_| {
+                let coordinator = Arc::clone(&self);
+                tokio::spawn(async move {
+                    loop {
+                        if !coordinator.process_next().await {
+                            coordinator.wakeup.notified().await;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        handles.push(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(expiry_interval);
+            loop {
+                ticker.tick().await;
+                match self.expire_restored(Utc::now()).await {
+                    Ok(expired) if !expired.is_empty() => {
+                        tracing::info!("Expired {} restored copies", expired.len());
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Failed to expire restored copies: {}", e),
+                }
+            }
+        }));
+
+        handles
+    }
+
+    /// Move an object to the cold tier, removing it from the hot tier
+    pub async fn archive(&self, object_id: &str) -> Result<()> {
+        let object = self.hot.get(object_id).await?;
+        self.cold.put(object).await?;
+        self.hot.delete(object_id).await?;
+        self.restored.write().await.remove(object_id);
+        Ok(())
+    }
+
+    /// Archived objects whose name starts with `prefix`
+    pub async fn archived(&self, prefix: &str) -> Result<Vec<ObjectMetadata>> {
+        self.cold.list(Some(prefix), None).await
+    }
+
+    /// Remove an object from the cold tier along with any restored copy of it
+    pub async fn delete_archived(&self, object_id: &str) -> Result<()> {
+        if self.restored.write().await.remove(object_id).is_some() {
+            match self.hot.delete(object_id).await {
+                Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.cold.delete(object_id).await
+    }
+
+    /// Queue a restore; poll the returned job for progress
+    pub async fn submit(&self, request: RestoreRequest) -> Result<RestoreJob> {
+        let window = self.window(&request)?;
+        let mut seen = HashSet::new();
+        let objects: Vec<String> = request
+            .objects
+            .into_iter()
+            .filter(|object_id| seen.insert(object_id.clone()))
+            .collect();
+
+        let now = Utc::now();
+        let mut job = RestoreJob {
+            job_id: Uuid::new_v4().to_string(),
+            tenant: request.tenant,
+            priority: request.priority,
+            status: RestoreStatus::Queued,
+            requested_at: now,
+            started_at: None,
+            completed_at: None,
+            window_secs: window.as_secs(),
+            total_objects: objects.len(),
+            restored_objects: 0,
+            failed_objects: 0,
+            failures: Vec::new(),
+            notify: request.notify,
+        };
+
+        let mut queued = Vec::new();
+        for object_id in objects {
+            if self.extend_restored(&object_id, now + chrono_window(window)).await {
+                job.restored_objects += 1;
+            } else if self.cold.exists(&object_id).await? {
+                queued.push(QueuedRestore {
+                    job_id: job.job_id.clone(),
+                    tenant: job.tenant.clone(),
+                    object_id,
+                    priority: job.priority,
+                });
+            } else if self.hot.exists(&object_id).await? {
+                // Never archived, nothing to restore
+                job.restored_objects += 1;
+            } else {
+                job.failed_objects += 1;
+                job.failures.push(FailedRestore {
+                    error: format!("Object not found: {}", object_id),
+                    object_id,
+                });
+            }
+        }
+
+        self.jobs.write().await.insert(job.job_id.clone(), job.clone());
+        self.publish(RestoreEvent::Queued {
+            job_id: job.job_id.clone(),
+            tenant: job.tenant.clone(),
+            priority: job.priority,
+            total_objects: job.total_objects,
+        });
+
+        if queued.is_empty() {
+            return self.finish(&job.job_id).await;
+        }
+
+        let wakeups = queued.len().min(self.config.workers.max(1));
+        {
+            let mut queue = self.queue.lock().await;
+            for item in queued {
+                queue.push(item);
+            }
+        }
+        for _ in 0..wakeups {
+            self.wakeup.notify_one();
+        }
+
+        tracing::debug!("Queued restore job {} for tenant {}", job.job_id, job.tenant);
+        Ok(job)
+    }
+
+    pub async fn job(&self, job_id: &str) -> Option<RestoreJob> {
+        self.jobs.read().await.get(job_id).cloned()
+    }
+
+    /// Jobs of one tenant, or all jobs, oldest first
+    pub async fn jobs(&self, tenant: Option<&str>) -> Vec<RestoreJob> {
+        let mut jobs: Vec<_> = self
+            .jobs
+            .read()
+            .await
+            .values()
+            .filter(|job| tenant.is_none_or(|tenant| job.tenant == tenant))
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| job.requested_at);
+        jobs
+    }
+
+    pub async fn restored_copy(&self, object_id: &str) -> Option<RestoredCopy> {
+        self.restored.read().await.get(object_id).cloned()
+    }
+
+    pub async fn stats(&self) -> RestoreQueueStats {
+        let (queued_objects, queued_by_tenant) = {
+            let queue = self.queue.lock().await;
+            (queue.len(), queue.len_by_tenant())
+        };
+        let active_jobs = self
+            .jobs
+            .read()
+            .await
+            .values()
+            .filter(|job| matches!(job.status, RestoreStatus::Queued | RestoreStatus::Running))
+            .count();
+
+        RestoreQueueStats {
+            queued_objects,
+            queued_by_tenant,
+            active_jobs,
+            restored_copies: self.restored.read().await.len(),
+        }
+    }
+
+    /// Restore the next queued object; returns false when the queue is empty
+    pub async fn process_next(&self) -> bool {
+        let Some(item) = self.queue.lock().await.pop() else {
+            return false;
+        };
+
+        let window = self
+            .update_job(&item.job_id, |job| {
+                if job.status == RestoreStatus::Queued {
+                    job.status = RestoreStatus::Running;
+                    job.started_at = Some(Utc::now());
+                }
+                Duration::from_secs(job.window_secs)
+            })
+            .await
+            .unwrap_or(self.config.default_window);
+
+        match self.restore_object(&item, window).await {
+            Ok(expires_at) => {
+                self.update_job(&item.job_id, |job| job.restored_objects += 1).await;
+                self.publish(RestoreEvent::ObjectRestored {
+                    job_id: item.job_id.clone(),
+                    object_id: item.object_id.clone(),
+                    expires_at,
+                });
+            }
+            Err(e) => {
+                tracing::warn!("Failed to restore {} for job {}: {}", item.object_id, item.job_id, e);
+                let error = e.to_string();
+                self.update_job(&item.job_id, |job| {
+                    job.failed_objects += 1;
+                    job.failures.push(FailedRestore {
+                        object_id: item.object_id.clone(),
+                        error: error.clone(),
+                    });
+                })
+                .await;
+                self.publish(RestoreEvent::ObjectFailed {
+                    job_id: item.job_id.clone(),
+                    object_id: item.object_id.clone(),
+                    error,
+                });
+            }
+        }
+
+        let done = self.job(&item.job_id).await.is_some_and(|job| job.is_done());
+        if done {
+            if let Err(e) = self.finish(&item.job_id).await {
+                tracing::error!("Failed to finish restore job {}: {}", item.job_id, e);
+            }
+        }
+        true
+    }
+
+    /// Remove restored copies whose window ended by `now`; the archived
+    /// object stays in the cold tier
+    pub async fn expire_restored(&self, now: DateTime<Utc>) -> Result<Vec<String>> {
+        let due: Vec<String> = self
+            .restored
+            .read()
+            .await
+            .values()
+            .filter(|copy| copy.expires_at <= now)
+            .map(|copy| copy.object_id.clone())
+            .collect();
+
+        let mut expired = Vec::with_capacity(due.len());
+        for object_id in due {
+            // Never drop the only copy of an object
+            if self.cold.exists(&object_id).await? {
+                match self.hot.delete(&object_id).await {
+                    Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            self.restored.write().await.remove(&object_id);
+            self.publish(RestoreEvent::Expired {
+                object_id: object_id.clone(),
+                timestamp: now,
+            });
+            expired.push(object_id);
+        }
+        Ok(expired)
+    }
+
+    fn window(&self, request: &RestoreRequest) -> Result<Duration> {
+        if request.tenant.trim().is_empty() {
+            return Err(NimbuxError::InvalidRequest("Tenant must not be empty".to_string()));
+        }
+        if request.objects.is_empty() {
+            return Err(NimbuxError::InvalidRequest("No objects to restore".to_string()));
+        }
+        if request.objects.len() > self.config.max_objects_per_request {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "At most {} objects can be restored per request",
+                self.config.max_objects_per_request
+            )));
+        }
+
+        let window = match request.days {
+            Some(0) => return Err(NimbuxError::InvalidRequest("Days must be at least 1".to_string())),
+            Some(days) => Duration::from_secs(u64::from(days) * 24 * 3600),
+            None => self.config.default_window,
+        };
+        if window > self.config.max_window {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Restored copies can be kept for at most {} days",
+                self.config.max_window.as_secs() / (24 * 3600)
+            )));
+        }
+        Ok(window)
+    }
+
+    /// Keep an existing restored copy for at least the new window
+    async fn extend_restored(&self, object_id: &str, expires_at: DateTime<Utc>) -> bool {
+        let mut restored = self.restored.write().await;
+        let Some(copy) = restored.get_mut(object_id) else {
+            return false;
+        };
+        copy.expires_at = copy.expires_at.max(expires_at);
+        true
+    }
+
+    async fn restore_object(&self, item: &QueuedRestore, window: Duration) -> Result<DateTime<Utc>> {
+        let restored_at = Utc::now();
+        let expires_at = restored_at + chrono_window(window);
+        let expires_at = match self.restored.read().await.get(&item.object_id) {
+            Some(copy) => copy.expires_at.max(expires_at),
+            None => expires_at,
+        };
+
+        let mut object = self.cold.get(&item.object_id).await?;
+        object
+            .metadata
+            .tags
+            .insert(RESTORED_UNTIL_TAG.to_string(), expires_at.to_rfc3339());
+        self.hot.put(object).await?;
+
+        self.restored.write().await.insert(
+            item.object_id.clone(),
+            RestoredCopy {
+                object_id: item.object_id.clone(),
+                job_id: item.job_id.clone(),
+                tenant: item.tenant.clone(),
+                restored_at,
+                expires_at,
+            },
+        );
+        Ok(expires_at)
+    }
+
+    async fn finish(&self, job_id: &str) -> Result<RestoreJob> {
+        let job = self
+            .update_job(job_id, |job| {
+                job.status = if job.failed_objects == 0 {
+                    RestoreStatus::Completed
+                } else if job.restored_objects == 0 {
+                    RestoreStatus::Failed
+                } else {
+                    RestoreStatus::Partial
+                };
+                job.completed_at = Some(Utc::now());
+                job.clone()
+            })
+            .await
+            .ok_or_else(|| NimbuxError::Internal(format!("Restore job not found: {}", job_id)))?;
+
+        self.publish(RestoreEvent::Completed {
+            job_id: job.job_id.clone(),
+            tenant: job.tenant.clone(),
+            status: job.status,
+            restored_objects: job.restored_objects,
+            failed_objects: job.failed_objects,
+            timestamp: job.completed_at.unwrap_or_else(Utc::now),
+        });
+
+        if !job.notify.is_empty() {
+            match &self.notifier {
+                Some(notifier) => {
+                    for target in &job.notify {
+                        if let Err(e) = notifier.notify(target, &job).await {
+                            tracing::warn!("Failed to send restore notification for job {}: {}", job.job_id, e);
+                        }
+                    }
+                }
+                None => tracing::warn!("Restore job {} asked for notifications but no notifier is configured", job.job_id),
+            }
+        }
+
+        Ok(job)
+    }
+
+    async fn update_job<T>(&self, job_id: &str, update: impl FnOnce(&mut RestoreJob) -> T) -> Option<T> {
+        self.jobs.write().await.get_mut(job_id).map(update)
+    }
+
+    fn publish(&self, event: RestoreEvent) {
+        // No subscribers is fine; job state can still be polled
+        let _ = self.events.send(event);
+    }
+}
+
+fn chrono_window(window: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(window).unwrap_or_else(|_| chrono::Duration::days(365))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryStorage, Object};
+    use std::sync::Mutex as StdMutex;
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        sent: StdMutex<Vec<(RestoreNotification, String, RestoreStatus)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl RestoreNotifier for RecordingNotifier {
+        async fn notify(&self, target: &RestoreNotification, job: &RestoreJob) -> Result<()> {
+            self.sent.lock().unwrap().push((target.clone(), job.job_id.clone(), job.status));
+            Ok(())
+        }
+    }
+
+    struct Tiers {
+        hot: Arc<MemoryStorage>,
+        cold: Arc<MemoryStorage>,
+        coordinator: RestoreCoordinator,
+    }
+
+    async fn tiers(archived: &[&str]) -> Tiers {
+        let hot = Arc::new(MemoryStorage::new());
+        let cold = Arc::new(MemoryStorage::new());
+        let coordinator = RestoreCoordinator::new(hot.clone(), cold.clone(), RestoreConfig::default());
+        for name in archived {
+            let object = Object::with_id(name.to_string(), name.to_string(), name.as_bytes().to_vec(), None);
+            hot.put(object).await.unwrap();
+            coordinator.archive(name).await.unwrap();
+        }
+        Tiers { hot, cold, coordinator }
+    }
+
+    fn request(tenant: &str, objects: &[&str], priority: RestorePriority) -> RestoreRequest {
+        RestoreRequest {
+            tenant: tenant.to_string(),
+            objects: objects.iter().map(|o| o.to_string()).collect(),
+            priority,
+            days: None,
+            notify: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restore_copies_objects_back_and_reports_progress() {
+        let tiers = tiers(&["media/2024/a.jpg", "media/2024/b.jpg"]).await;
+        assert!(!tiers.hot.exists("media/2024/a.jpg").await.unwrap());
+
+        let notifier = Arc::new(RecordingNotifier::default());
+        let coordinator = tiers.coordinator.with_notifier(notifier.clone());
+        let mut events = coordinator.subscribe();
+
+        let mut restore = request("pixelle", &["media/2024/a.jpg", "media/2024/b.jpg", "media/2024/missing.jpg"], RestorePriority::Bulk);
+        restore.notify = vec![RestoreNotification::Messenger { stream: "media".to_string(), topic: "restores".to_string() }];
+        let job = coordinator.submit(restore).await.unwrap();
+        assert_eq!(job.status, RestoreStatus::Queued);
+        assert_eq!(job.total_objects, 3);
+        assert_eq!(job.failed_objects, 1);
+
+        assert!(coordinator.process_next().await);
+        let running = coordinator.job(&job.job_id).await.unwrap();
+        assert_eq!(running.status, RestoreStatus::Running);
+        assert!((running.progress() - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        assert!(coordinator.process_next().await);
+        assert!(!coordinator.process_next().await);
+
+        let finished = coordinator.job(&job.job_id).await.unwrap();
+        assert_eq!(finished.status, RestoreStatus::Partial);
+        assert_eq!(finished.restored_objects, 2);
+        assert_eq!(finished.failures[0].object_id, "media/2024/missing.jpg");
+
+        let restored = tiers.hot.get("media/2024/a.jpg").await.unwrap();
+        assert_eq!(restored.data, b"media/2024/a.jpg");
+        assert!(restored.metadata.tags.contains_key(RESTORED_UNTIL_TAG));
+        assert!(tiers.cold.exists("media/2024/a.jpg").await.unwrap());
+
+        let sent = notifier.sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1, job.job_id);
+        assert_eq!(sent[0].2, RestoreStatus::Partial);
+
+        assert!(matches!(events.recv().await.unwrap(), RestoreEvent::Queued { total_objects: 3, .. }));
+        assert!(matches!(events.recv().await.unwrap(), RestoreEvent::ObjectRestored { .. }));
+        assert!(matches!(events.recv().await.unwrap(), RestoreEvent::ObjectRestored { .. }));
+        assert!(matches!(events.recv().await.unwrap(), RestoreEvent::Completed { status: RestoreStatus::Partial, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_served_in_turn() {
+        let tiers = tiers(&["a/1", "a/2", "a/3", "b/1"]).await;
+        let coordinator = tiers.coordinator;
+        let mut events = coordinator.subscribe();
+
+        coordinator.submit(request("alpha", &["a/1", "a/2", "a/3"], RestorePriority::Standard)).await.unwrap();
+        coordinator.submit(request("beta", &["b/1"], RestorePriority::Standard)).await.unwrap();
+        let stats = coordinator.stats().await;
+        assert_eq!(stats.queued_objects, 4);
+        assert_eq!(stats.queued_by_tenant["alpha"], 3);
+        assert_eq!(stats.active_jobs, 2);
+
+        while coordinator.process_next().await {}
+
+        let mut order = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let RestoreEvent::ObjectRestored { object_id, .. } = event {
+                order.push(object_id);
+            }
+        }
+        assert_eq!(order, vec!["a/1", "b/1", "a/2", "a/3"]);
+        assert_eq!(coordinator.jobs(Some("beta")).await[0].status, RestoreStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_restored_copies_expire_after_their_window() {
+        let tiers = tiers(&["media/a.jpg"]).await;
+        let coordinator = tiers.coordinator;
+
+        let mut restore = request("pixelle", &["media/a.jpg"], RestorePriority::Expedited);
+        restore.days = Some(2);
+        coordinator.submit(restore).await.unwrap();
+        coordinator.process_next().await;
+
+        let copy = coordinator.restored_copy("media/a.jpg").await.unwrap();
+        assert!(coordinator.expire_restored(Utc::now()).await.unwrap().is_empty());
+
+        let expired = coordinator.expire_restored(copy.expires_at).await.unwrap();
+        assert_eq!(expired, vec!["media/a.jpg".to_string()]);
+        assert!(!tiers.hot.exists("media/a.jpg").await.unwrap());
+        assert!(tiers.cold.exists("media/a.jpg").await.unwrap());
+        assert!(coordinator.restored_copy("media/a.jpg").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_requests_are_rejected() {
+        let tiers = tiers(&[]).await;
+        let coordinator = tiers.coordinator;
+
+        assert!(coordinator.submit(request("", &["a/1"], RestorePriority::Bulk)).await.is_err());
+        assert!(coordinator.submit(request("alpha", &[], RestorePriority::Bulk)).await.is_err());
+
+        let mut restore = request("alpha", &["a/1"], RestorePriority::Bulk);
+        restore.days = Some(31);
+        assert!(matches!(coordinator.submit(restore).await, Err(NimbuxError::InvalidRequest(_))));
+    }
+}
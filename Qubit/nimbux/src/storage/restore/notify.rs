@@ -0,0 +1,96 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Completion notifications for restore jobs
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde_json::json;
+
+use super::{RestoreJob, RestoreNotification};
+use crate::errors::{NimbuxError, Result};
+
+/// Event name sent with every completion notification
+pub const RESTORE_COMPLETED_EVENT: &str = "restore.completed";
+
+/// Delivers a finished restore job to a notification target
+#[async_trait]
+pub trait RestoreNotifier: Send + Sync {
+    async fn notify(&self, target: &RestoreNotification, job: &RestoreJob) -> Result<()>;
+}
+
+/// Posts completions to webhooks, and to Messenger topics through its HTTP API
+pub struct HttpRestoreNotifier {
+    client: reqwest::Client,
+    messenger: Option<MessengerEndpoint>,
+}
+
+struct MessengerEndpoint {
+    url: String,
+    token: String,
+}
+
+impl HttpRestoreNotifier {
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| NimbuxError::Configuration(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self { client, messenger: None })
+    }
+
+    /// Messenger server and access token used for `messenger` targets
+    pub fn with_messenger(mut self, url: impl Into<String>, token: impl Into<String>) -> Self {
+        self.messenger = Some(MessengerEndpoint {
+            url: url.into().trim_end_matches('/').to_string(),
+            token: token.into(),
+        });
+        self
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder, target: &str) -> Result<()> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| NimbuxError::Network(format!("Failed to notify {}: {}", target, e)))?;
+        if !response.status().is_success() {
+            return Err(NimbuxError::Network(format!(
+                "Notification to {} was rejected with {}",
+                target,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RestoreNotifier for HttpRestoreNotifier {
+    async fn notify(&self, target: &RestoreNotification, job: &RestoreJob) -> Result<()> {
+        let payload = json!({ "event": RESTORE_COMPLETED_EVENT, "job": job });
+
+        match target {
+            RestoreNotification::Webhook { url } => {
+                self.send(self.client.post(url).json(&payload), url).await
+            }
+            RestoreNotification::Messenger { stream, topic } => {
+                let Some(messenger) = &self.messenger else {
+                    return Err(NimbuxError::Configuration(
+                        "Messenger notifications are not configured".to_string(),
+                    ));
+                };
+                let url = format!("{}/streams/{}/topics/{}/messages", messenger.url, stream, topic);
+                let body = json!({
+                    "partitioning": { "kind": "balanced", "value": "" },
+                    "messages": [{ "id": 0, "payload": STANDARD.encode(serde_json::to_vec(&payload)?) }],
+                });
+                let request = self.client.post(&url).bearer_auth(&messenger.token).json(&body);
+                self.send(request, &url).await
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Restore queue with strict priorities and per-tenant round robin
+
+use std::collections::{HashMap, VecDeque};
+
+use super::RestorePriority;
+
+/// One object waiting to be copied back from the cold tier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct QueuedRestore {
+    pub job_id: String,
+    pub tenant: String,
+    pub object_id: String,
+    pub priority: RestorePriority,
+}
+
+/// Tenants waiting at one priority, served in turn so a large request
+/// cannot hold back the other tenants' restores
+#[derive(Debug, Default)]
+struct PriorityLevel {
+    rotation: VecDeque<String>,
+    pending: HashMap<String, VecDeque<QueuedRestore>>,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct FairQueue {
+    levels: [PriorityLevel; 3],
+}
+
+impl FairQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, item: QueuedRestore) {
+        let level = &mut self.levels[item.priority.rank()];
+        let pending = level.pending.entry(item.tenant.clone()).or_default();
+        if pending.is_empty() {
+            level.rotation.push_back(item.tenant.clone());
+        }
+        pending.push_back(item);
+    }
+
+    /// Next object from the highest priority with work, taking one object
+    /// per tenant before returning to the first
+    pub fn pop(&mut self) -> Option<QueuedRestore> {
+        for level in &mut self.levels {
+            let Some(tenant) = level.rotation.pop_front() else {
+                continue;
+            };
+            let pending = level.pending.get_mut(&tenant)?;
+            let item = pending.pop_front();
+            if pending.is_empty() {
+                level.pending.remove(&tenant);
+            } else {
+                level.rotation.push_back(tenant);
+            }
+            return item;
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels
+            .iter()
+            .flat_map(|level| level.pending.values())
+            .map(VecDeque::len)
+            .sum()
+    }
+
+    /// Queued objects per tenant across all priorities
+    pub fn len_by_tenant(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for level in &self.levels {
+            for (tenant, pending) in &level.pending {
+                *counts.entry(tenant.clone()).or_insert(0) += pending.len();
+            }
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(tenant: &str, object_id: &str, priority: RestorePriority) -> QueuedRestore {
+        QueuedRestore {
+            job_id: format!("{}-job", tenant),
+            tenant: tenant.to_string(),
+            object_id: object_id.to_string(),
+            priority,
+        }
+    }
+
+    fn drain(queue: &mut FairQueue) -> Vec<String> {
+        std::iter::from_fn(|| queue.pop()).map(|item| item.object_id).collect()
+    }
+
+    #[test]
+    fn test_tenants_take_turns_within_a_priority() {
+        let mut queue = FairQueue::new();
+        for object_id in ["a1", "a2", "a3"] {
+            queue.push(item("alpha", object_id, RestorePriority::Bulk));
+        }
+        queue.push(item("beta", "b1", RestorePriority::Bulk));
+        queue.push(item("gamma", "g1", RestorePriority::Bulk));
+
+        assert_eq!(queue.len(), 5);
+        assert_eq!(queue.len_by_tenant()["alpha"], 3);
+        assert_eq!(drain(&mut queue), vec!["a1", "b1", "g1", "a2", "a3"]);
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn test_higher_priorities_are_served_first() {
+        let mut queue = FairQueue::new();
+        queue.push(item("alpha", "bulk", RestorePriority::Bulk));
+        queue.push(item("alpha", "standard", RestorePriority::Standard));
+        queue.push(item("beta", "expedited", RestorePriority::Expedited));
+
+        assert_eq!(drain(&mut queue), vec!["expedited", "standard", "bulk"]);
+    }
+}
@@ -0,0 +1,671 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Image resizing and format conversion applied to objects as they are read
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::io::{Limits, Reader};
+use image::{ColorType, DynamicImage, ImageFormat, ImageOutputFormat};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, Semaphore};
+
+use super::placement;
+use super::StorageBackend;
+use crate::errors::{NimbuxError, Result};
+
+const CONFIG_PREFIX: &str = ".transforms/buckets/";
+/// Query parameters that ask for a transformation
+const PARAMS: [&str; 5] = ["w", "h", "fmt", "fit", "q"];
+/// Widest or tallest source image decoded, whatever the bucket allows
+const MAX_SOURCE_DIMENSION: u32 = 16_384;
+const DEFAULT_QUALITY: u8 = 85;
+const FILTER: FilterType = FilterType::Lanczos3;
+
+/// Format of a transformed image.
+///
+/// WebP is written lossless, so it suits avatars and icons better than photos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => Some(OutputFormat::Jpeg),
+            "png" => Some(OutputFormat::Png),
+            "webp" => Some(OutputFormat::Webp),
+            _ => None,
+        }
+    }
+
+    /// Format a source image is written back in when none is asked for;
+    /// formats that cannot be written become PNG
+    fn of(source: ImageFormat) -> Self {
+        match source {
+            ImageFormat::Jpeg => OutputFormat::Jpeg,
+            ImageFormat::WebP => OutputFormat::Webp,
+            _ => OutputFormat::Png,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Png => "image/png",
+            OutputFormat::Webp => "image/webp",
+        }
+    }
+}
+
+/// How an image is fitted into the requested width and height
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+    /// Scale to fit inside the box, keeping the aspect ratio
+    #[default]
+    Contain,
+    /// Scale to cover the box, cropping what falls outside
+    Cover,
+    /// Stretch to the box
+    Fill,
+}
+
+impl Fit {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "contain" => Some(Fit::Contain),
+            "cover" => Some(Fit::Cover),
+            "fill" => Some(Fit::Fill),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Fit::Contain => "contain",
+            Fit::Cover => "cover",
+            Fit::Fill => "fill",
+        }
+    }
+}
+
+/// Transformation asked for in the query of an object read, such as
+/// `?w=256&h=256&fit=cover&fmt=webp`.
+///
+/// `fmt=auto` negotiates the format from the `Accept` header: WebP for
+/// clients that take it, the source format otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TransformParams {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// `None` keeps the source format
+    pub format: Option<OutputFormat>,
+    pub fit: Fit,
+    /// JPEG quality from 1 to 100
+    pub quality: u8,
+    /// The format came from `Accept`, so responses vary by it
+    pub negotiated: bool,
+}
+
+impl TransformParams {
+    /// Parameters of a raw query string; `None` when it asks for no transformation
+    pub fn parse(raw_query: Option<&str>, accept: Option<&str>) -> Result<Option<Self>> {
+        let pairs: Vec<(&str, &str)> = raw_query
+            .unwrap_or_default()
+            .split('&')
+            .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+            .filter(|(name, _)| PARAMS.contains(name))
+            .collect();
+        if pairs.is_empty() {
+            return Ok(None);
+        }
+
+        let invalid = |name: &str, value: &str| NimbuxError::InvalidRequest(format!("Invalid {}: {}", name, value));
+        let mut params = Self {
+            width: None,
+            height: None,
+            format: None,
+            fit: Fit::default(),
+            quality: DEFAULT_QUALITY,
+            negotiated: false,
+        };
+        for (name, value) in pairs {
+            match name {
+                "w" => params.width = Some(value.parse().ok().filter(|w| *w > 0).ok_or_else(|| invalid(name, value))?),
+                "h" => params.height = Some(value.parse().ok().filter(|h| *h > 0).ok_or_else(|| invalid(name, value))?),
+                "fit" => params.fit = Fit::parse(value).ok_or_else(|| invalid(name, value))?,
+                "q" => params.quality = value.parse().ok().filter(|q| (1..=100).contains(q)).ok_or_else(|| invalid(name, value))?,
+                "fmt" if value == "auto" => {
+                    params.negotiated = true;
+                    params.format = accept
+                        .is_some_and(|accept| accept.split(',').any(|media| media.trim().starts_with("image/webp")))
+                        .then_some(OutputFormat::Webp);
+                }
+                _ => params.format = Some(OutputFormat::parse(value).ok_or_else(|| invalid(name, value))?),
+            }
+        }
+        Ok(Some(params))
+    }
+
+    /// Stable description of the rendition, used in cache keys and ETags
+    fn canonical(&self) -> String {
+        let side = |side: Option<u32>| side.map_or_else(|| "auto".to_string(), |side| side.to_string());
+        format!(
+            "w={};h={};fit={};fmt={};q={}",
+            side(self.width),
+            side(self.height),
+            self.fit.name(),
+            self.format.map_or("source", OutputFormat::name),
+            self.quality
+        )
+    }
+
+    /// ETag of the rendition of an object with ETag `etag`, which changes
+    /// whenever the object or the transformation does
+    pub fn etag(&self, etag: &str) -> String {
+        let digest = blake3::hash(self.canonical().as_bytes()).to_hex();
+        format!("{}-{}", etag, &digest[..16])
+    }
+}
+
+/// Whether and how objects of a bucket may be transformed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransformConfig {
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// Largest width or height that may be asked for
+    #[serde(default = "default_max_dimension")]
+    pub max_dimension: u32,
+    /// Larger objects are not transformed
+    #[serde(default = "default_max_source_size")]
+    pub max_source_size: u64,
+    /// Formats that may be asked for; empty allows every format
+    #[serde(default)]
+    pub formats: Vec<OutputFormat>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_max_dimension() -> u32 {
+    4096
+}
+
+fn default_max_source_size() -> u64 {
+    32 * 1024 * 1024 // 32MB
+}
+
+impl Default for TransformConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_dimension: default_max_dimension(),
+            max_source_size: default_max_source_size(),
+            formats: Vec::new(),
+        }
+    }
+}
+
+/// Transformation settings of a bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketTransforms {
+    pub bucket: String,
+    pub config: TransformConfig,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Image transformer configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformerConfig {
+    /// Transformations running at once
+    pub workers: usize,
+    /// Transformations waiting for a worker before more are refused with `SlowDown`
+    pub max_queued: usize,
+    /// Bytes of renditions kept in memory
+    pub cache_size: u64,
+}
+
+impl Default for TransformerConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            max_queued: 64,
+            cache_size: 256 * 1024 * 1024, // 256MB
+        }
+    }
+}
+
+/// A transformed image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendition {
+    pub data: Vec<u8>,
+    pub format: OutputFormat,
+    pub etag: String,
+}
+
+/// Transformer counters since the server started
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransformStats {
+    pub transformed: u64,
+    pub cache_hits: u64,
+    /// Refused because every worker was busy and the queue full
+    pub throttled: u64,
+    pub failed: u64,
+    pub in_progress: usize,
+    pub cached_renditions: usize,
+    pub cached_bytes: u64,
+}
+
+/// Renditions by object, object ETag and transformation, least recently used evicted first
+#[derive(Default)]
+struct RenditionCache {
+    capacity: u64,
+    size: u64,
+    clock: u64,
+    entries: HashMap<String, (Rendition, u64)>,
+    by_use: BTreeMap<u64, String>,
+}
+
+impl RenditionCache {
+    fn get(&mut self, key: &str) -> Option<Rendition> {
+        self.clock += 1;
+        let (rendition, used) = self.entries.get_mut(key)?;
+        self.by_use.remove(used);
+        *used = self.clock;
+        self.by_use.insert(self.clock, key.to_string());
+        Some(rendition.clone())
+    }
+
+    fn insert(&mut self, key: String, rendition: Rendition) {
+        let size = rendition.data.len() as u64;
+        if size > self.capacity {
+            return;
+        }
+        if let Some((previous, used)) = self.entries.remove(&key) {
+            self.size -= previous.data.len() as u64;
+            self.by_use.remove(&used);
+        }
+        while self.size + size > self.capacity {
+            let Some((_, oldest)) = self.by_use.pop_first() else { break };
+            if let Some((evicted, _)) = self.entries.remove(&oldest) {
+                self.size -= evicted.data.len() as u64;
+            }
+        }
+        self.clock += 1;
+        self.size += size;
+        self.by_use.insert(self.clock, key.clone());
+        self.entries.insert(key, (rendition, self.clock));
+    }
+}
+
+/// Counts a transformation from when it queues until it finishes
+struct InProgress<'a>(&'a AtomicUsize);
+
+impl Drop for InProgress<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Resizes and converts images as they are read, for buckets that enable it.
+///
+/// Decoding and encoding run on blocking threads, at most `workers` at a
+/// time; past `max_queued` waiting transformations requests are throttled
+/// rather than piling up. Renditions are cached by object, ETag and
+/// transformation, so a changed object is never served stale and popular
+/// sizes are made once. Settings are kept under `.transforms/buckets/<bucket>`.
+pub struct ImageTransformer {
+    storage: Arc<dyn StorageBackend>,
+    config: TransformerConfig,
+    workers: Semaphore,
+    in_progress: AtomicUsize,
+    buckets: RwLock<HashMap<String, Option<BucketTransforms>>>,
+    cache: Mutex<RenditionCache>,
+    transformed: AtomicU64,
+    cache_hits: AtomicU64,
+    throttled: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl ImageTransformer {
+    pub fn new(storage: Arc<dyn StorageBackend>, config: TransformerConfig) -> Self {
+        Self {
+            storage,
+            workers: Semaphore::new(config.workers.max(1)),
+            cache: Mutex::new(RenditionCache {
+                capacity: config.cache_size,
+                ..RenditionCache::default()
+            }),
+            config,
+            in_progress: AtomicUsize::new(0),
+            buckets: RwLock::new(HashMap::new()),
+            transformed: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn bucket_transforms(&self, bucket: &str) -> Result<Option<BucketTransforms>> {
+        if let Some(transforms) = self.buckets.read().await.get(bucket) {
+            return Ok(transforms.clone());
+        }
+        let transforms = match self.storage.get(&config_id(bucket)).await {
+            Ok(object) => Some(serde_json::from_slice::<BucketTransforms>(&object.data)?),
+            Err(NimbuxError::ObjectNotFound { .. }) => None,
+            Err(e) => return Err(e),
+        };
+        self.buckets.write().await.insert(bucket.to_string(), transforms.clone());
+        Ok(transforms)
+    }
+
+    pub async fn set_bucket_transforms(&self, bucket: &str, config: TransformConfig) -> Result<BucketTransforms> {
+        placement::validate_name(bucket, "bucket name")?;
+        if config.max_dimension == 0 || config.max_dimension > MAX_SOURCE_DIMENSION {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Maximum dimension must be between 1 and {}",
+                MAX_SOURCE_DIMENSION
+            )));
+        }
+
+        let transforms = BucketTransforms {
+            bucket: bucket.to_string(),
+            config,
+            updated_at: Utc::now(),
+        };
+        placement::save(self.storage.as_ref(), config_id(bucket), &transforms).await?;
+        self.buckets.write().await.insert(bucket.to_string(), Some(transforms.clone()));
+        tracing::info!("Image transformations of bucket {} set", bucket);
+        Ok(transforms)
+    }
+
+    /// Stop transforming objects of a bucket; cached renditions age out
+    pub async fn delete_bucket_transforms(&self, bucket: &str) -> Result<()> {
+        let result = self.storage.delete(&config_id(bucket)).await;
+        self.buckets.write().await.insert(bucket.to_string(), None);
+        match result {
+            Ok(()) => {
+                tracing::info!("Image transformations of bucket {} removed", bucket);
+                Ok(())
+            }
+            Err(NimbuxError::ObjectNotFound { .. }) => Err(NimbuxError::ObjectNotFound {
+                object_id: format!("transformations of {}", bucket),
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Rendition of `key` in `bucket`, whose ETag is `etag`, from the cache
+    /// or made from `data`
+    pub async fn transform(&self, bucket: &str, key: &str, etag: &str, data: Vec<u8>, params: &TransformParams) -> Result<Rendition> {
+        let config = self
+            .bucket_transforms(bucket)
+            .await?
+            .map(|transforms| transforms.config)
+            .filter(|config| config.enabled)
+            .ok_or_else(|| NimbuxError::InvalidRequest(format!("Bucket {} does not allow image transformations", bucket)))?;
+        check(&config, params, data.len() as u64)?;
+
+        let cache_key = format!("{}/{}\n{}\n{}", bucket, key, etag, params.canonical());
+        if let Some(rendition) = self.cache.lock().unwrap().get(&cache_key) {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(rendition);
+        }
+
+        if self.in_progress.fetch_add(1, Ordering::SeqCst) >= self.config.workers.max(1) + self.config.max_queued {
+            self.in_progress.fetch_sub(1, Ordering::SeqCst);
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+            return Err(NimbuxError::Throttled {
+                reason: "Too many image transformations in progress".to_string(),
+                retry_after_ms: 1000,
+            });
+        }
+        let _in_progress = InProgress(&self.in_progress);
+        let _worker = self
+            .workers
+            .acquire()
+            .await
+            .map_err(|e| NimbuxError::Internal(format!("Transformation workers stopped: {}", e)))?;
+
+        let render_params = params.clone();
+        let rendered = tokio::task::spawn_blocking(move || render(&data, &render_params))
+            .await
+            .map_err(|e| NimbuxError::Internal(format!("Transformation worker failed: {}", e)))
+            .and_then(|rendered| rendered);
+        let (data, format) = match rendered {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+
+        let rendition = Rendition {
+            data,
+            format,
+            etag: params.etag(etag),
+        };
+        self.transformed.fetch_add(1, Ordering::Relaxed);
+        self.cache.lock().unwrap().insert(cache_key, rendition.clone());
+        Ok(rendition)
+    }
+
+    pub fn stats(&self) -> TransformStats {
+        let cache = self.cache.lock().unwrap();
+        TransformStats {
+            transformed: self.transformed.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            in_progress: self.in_progress.load(Ordering::SeqCst),
+            cached_renditions: cache.entries.len(),
+            cached_bytes: cache.size,
+        }
+    }
+}
+
+fn config_id(bucket: &str) -> String {
+    format!("{}{}", CONFIG_PREFIX, bucket)
+}
+
+/// Refuse transformations a bucket does not allow
+fn check(config: &TransformConfig, params: &TransformParams, source_size: u64) -> Result<()> {
+    if source_size > config.max_source_size {
+        return Err(NimbuxError::InvalidRequest(format!(
+            "Objects over {} bytes cannot be transformed",
+            config.max_source_size
+        )));
+    }
+    if params.width.max(params.height).is_some_and(|side| side > config.max_dimension) {
+        return Err(NimbuxError::InvalidRequest(format!(
+            "Width and height cannot exceed {}",
+            config.max_dimension
+        )));
+    }
+    if let Some(format) = params.format {
+        if !config.formats.is_empty() && !config.formats.contains(&format) {
+            return Err(NimbuxError::InvalidRequest(format!("Format {} is not allowed", format.name())));
+        }
+    }
+    Ok(())
+}
+
+/// Decode, resize and encode an image; runs on a blocking thread
+fn render(data: &[u8], params: &TransformParams) -> Result<(Vec<u8>, OutputFormat)> {
+    let not_an_image = |e: image::ImageError| NimbuxError::InvalidRequest(format!("Object cannot be transformed: {}", e));
+    let mut reader = Reader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| NimbuxError::Internal(e.to_string()))?;
+    let source = reader
+        .format()
+        .ok_or_else(|| NimbuxError::InvalidRequest("Object is not an image".to_string()))?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    reader.limits(limits);
+    let image = resize(reader.decode().map_err(not_an_image)?, params);
+
+    let format = params.format.unwrap_or(OutputFormat::of(source));
+    let mut out = Vec::new();
+    let encoded = match format {
+        OutputFormat::Jpeg => JpegEncoder::new_with_quality(&mut out, params.quality).encode_image(&image.to_rgb8()),
+        OutputFormat::Png => image.write_to(&mut Cursor::new(&mut out), ImageOutputFormat::Png),
+        OutputFormat::Webp => {
+            let rgba = image.to_rgba8();
+            WebPEncoder::new_lossless(&mut out).encode(rgba.as_raw(), rgba.width(), rgba.height(), ColorType::Rgba8)
+        }
+    };
+    encoded.map_err(|e| NimbuxError::Internal(format!("Failed to encode {}: {}", format.name(), e)))?;
+    Ok((out, format))
+}
+
+/// Fit an image into the requested box; images are never enlarged
+fn resize(image: DynamicImage, params: &TransformParams) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    let scaled = |side: u32, target: u32, reference: u32| {
+        ((side as u64 * target as u64 + reference as u64 / 2) / reference as u64).max(1) as u32
+    };
+    let (w, h) = match (params.width, params.height) {
+        (None, None) => return image,
+        (Some(w), None) => (w, scaled(height, w, width)),
+        (None, Some(h)) => (scaled(width, h, height), h),
+        (Some(w), Some(h)) => (w, h),
+    };
+    if w >= width && h >= height {
+        return image;
+    }
+    match params.fit {
+        Fit::Contain => image.resize(w, h, FILTER),
+        Fit::Cover => image.resize_to_fill(w, h, FILTER),
+        Fit::Fill => image.resize_exact(w, h, FILTER),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use image::{ImageBuffer, Rgb};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = ImageBuffer::from_fn(width, height, |x, y| Rgb([x as u8, y as u8, 128]));
+        let mut out = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut out), ImageOutputFormat::Png)
+            .unwrap();
+        out
+    }
+
+    fn params(query: &str) -> TransformParams {
+        TransformParams::parse(Some(query), None).unwrap().unwrap()
+    }
+
+    async fn transformer(config: TransformerConfig) -> ImageTransformer {
+        let transformer = ImageTransformer::new(Arc::new(MemoryStorage::new()), config);
+        transformer.set_bucket_transforms("avatars", TransformConfig::default()).await.unwrap();
+        transformer
+    }
+
+    #[test]
+    fn test_params_parse_and_negotiate() {
+        assert_eq!(TransformParams::parse(Some("t=1&X-Amz-Expires=60"), None).unwrap(), None);
+        let parsed = params("w=256&h=128&fit=cover&fmt=jpg&q=70");
+        assert_eq!((parsed.width, parsed.height, parsed.fit), (Some(256), Some(128), Fit::Cover));
+        assert_eq!((parsed.format, parsed.quality), (Some(OutputFormat::Jpeg), 70));
+        assert!(TransformParams::parse(Some("w=0"), None).is_err());
+        assert!(TransformParams::parse(Some("fmt=tiff"), None).is_err());
+
+        let webp = TransformParams::parse(Some("fmt=auto"), Some("image/avif,image/webp,*/*")).unwrap().unwrap();
+        assert_eq!(webp.format, Some(OutputFormat::Webp));
+        assert!(webp.negotiated);
+        let source = TransformParams::parse(Some("fmt=auto"), Some("image/png")).unwrap().unwrap();
+        assert_eq!(source.format, None);
+        assert_ne!(webp.etag("abc"), source.etag("abc"));
+    }
+
+    #[tokio::test]
+    async fn test_images_are_resized_converted_and_cached() {
+        let transformer = transformer(TransformerConfig::default()).await;
+        let source = png(400, 200);
+
+        let rendition = transformer.transform("avatars", "a.png", "v1", source.clone(), &params("w=100")).await.unwrap();
+        assert_eq!(rendition.format, OutputFormat::Png);
+        let image = image::load_from_memory(&rendition.data).unwrap();
+        assert_eq!((image.width(), image.height()), (100, 50));
+
+        let params = params("w=64&h=64&fit=cover&fmt=webp");
+        let rendition = transformer.transform("avatars", "a.png", "v1", source.clone(), &params).await.unwrap();
+        assert_eq!(image::guess_format(&rendition.data).unwrap(), ImageFormat::WebP);
+        let image = image::load_from_memory(&rendition.data).unwrap();
+        assert_eq!((image.width(), image.height()), (64, 64));
+        assert_eq!(rendition.etag, params.etag("v1"));
+
+        transformer.transform("avatars", "a.png", "v1", source.clone(), &params).await.unwrap();
+        // A new version of the object is never served from the cache
+        transformer.transform("avatars", "a.png", "v2", source, &params).await.unwrap();
+        let stats = transformer.stats();
+        assert_eq!((stats.transformed, stats.cache_hits, stats.cached_renditions), (3, 1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_buckets_must_enable_transformations() {
+        let transformer = transformer(TransformerConfig::default()).await;
+        let result = transformer.transform("photos", "a.png", "v1", png(10, 10), &params("w=5")).await;
+        assert!(matches!(result, Err(NimbuxError::InvalidRequest(_))));
+
+        let config = TransformConfig {
+            max_dimension: 512,
+            formats: vec![OutputFormat::Webp],
+            ..TransformConfig::default()
+        };
+        transformer.set_bucket_transforms("avatars", config).await.unwrap();
+        assert!(transformer.transform("avatars", "a.png", "v1", png(10, 10), &params("w=1024")).await.is_err());
+        assert!(transformer.transform("avatars", "a.png", "v1", png(10, 10), &params("fmt=png")).await.is_err());
+        assert!(transformer.transform("avatars", "a.png", "v1", png(10, 10), &params("fmt=webp")).await.is_ok());
+        assert!(transformer.transform("avatars", "a.txt", "v1", b"text".to_vec(), &params("w=5")).await.is_err());
+
+        transformer.delete_bucket_transforms("avatars").await.unwrap();
+        assert!(transformer.transform("avatars", "a.png", "v1", png(10, 10), &params("fmt=webp")).await.is_err());
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let rendition = |size: usize| Rendition {
+            data: vec![0; size],
+            format: OutputFormat::Png,
+            etag: String::new(),
+        };
+        let mut cache = RenditionCache { capacity: 100, ..RenditionCache::default() };
+        cache.insert("a".to_string(), rendition(40));
+        cache.insert("b".to_string(), rendition(40));
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_string(), rendition(40));
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some() && cache.get("c").is_some());
+        cache.insert("huge".to_string(), rendition(101));
+        assert_eq!((cache.entries.len(), cache.size), (2, 80));
+    }
+}
@@ -0,0 +1,564 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Bucket-level object versioning with delete markers
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use super::advanced::ObjectVersion;
+use super::object_lock::ObjectLock;
+use super::{Object, ObjectMetadata, StorageBackend, StorageStats};
+use crate::errors::{NimbuxError, Result};
+
+/// Tag holding the version ID of a stored version
+pub const VERSION_ID_TAG: &str = "nimbux-version-id";
+/// Tag holding when a version was written, in microseconds since the epoch
+pub const VERSIONED_AT_TAG: &str = "nimbux-versioned-at";
+/// Tag set on the empty objects that record a delete
+pub const DELETE_MARKER_TAG: &str = "nimbux-delete-marker";
+/// Version ID of objects written while versioning was off or suspended
+pub const NULL_VERSION_ID: &str = "null";
+
+const SETTINGS_PREFIX: &str = ".versions/buckets/";
+pub(super) const VERSIONS_PREFIX: &str = ".versions/objects/";
+const LOCK_STRIPES: usize = 64;
+
+/// Versioning state of a bucket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersioningStatus {
+    /// Versioning was never enabled; writes overwrite and deletes remove
+    #[default]
+    Unversioned,
+    /// Every write keeps the previous version and deletes leave a delete marker
+    Enabled,
+    /// Existing versions are kept, new writes replace the `null` version
+    Suspended,
+}
+
+/// Storage wrapper that keeps prior versions of objects in versioned buckets.
+///
+/// The current version stays at `<bucket>/<key>`, so readers of the inner
+/// backend are unaffected. Earlier versions and delete markers live under
+/// `.versions/objects/<bucket>/<key>/<version_id>` and the bucket settings
+/// under `.versions/buckets/<bucket>`; both are hidden from listings.
+pub struct VersionedStorage {
+    inner: Arc<dyn StorageBackend>,
+    settings: RwLock<HashMap<String, VersioningStatus>>,
+    locks: Vec<Mutex<()>>,
+}
+
+impl VersionedStorage {
+    pub fn new(inner: Arc<dyn StorageBackend>) -> Self {
+        Self {
+            inner,
+            settings: RwLock::new(HashMap::new()),
+            locks: (0..LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+
+    pub async fn versioning(&self, bucket: &str) -> Result<VersioningStatus> {
+        if let Some(status) = self.settings.read().await.get(bucket) {
+            return Ok(*status);
+        }
+
+        let status = match self.inner.get(&settings_id(bucket)).await {
+            Ok(object) => serde_json::from_slice(&object.data)?,
+            Err(NimbuxError::ObjectNotFound { .. }) => VersioningStatus::Unversioned,
+            Err(e) => return Err(e),
+        };
+        self.settings.write().await.insert(bucket.to_string(), status);
+        Ok(status)
+    }
+
+    /// Enable or suspend versioning; once enabled it can only be suspended
+    pub async fn set_versioning(&self, bucket: &str, status: VersioningStatus) -> Result<()> {
+        if bucket.is_empty() || bucket.starts_with('.') || bucket.contains('/') {
+            return Err(NimbuxError::InvalidRequest(format!("Invalid bucket name: {}", bucket)));
+        }
+        let current = self.versioning(bucket).await?;
+        if status == VersioningStatus::Unversioned && current != VersioningStatus::Unversioned {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Versioning of {} cannot be turned off once enabled, suspend it instead",
+                bucket
+            )));
+        }
+
+        let id = settings_id(bucket);
+        let object = Object::with_id(id.clone(), id, serde_json::to_vec(&status)?, Some("application/json".to_string()));
+        self.inner.put(object).await?;
+        self.settings.write().await.insert(bucket.to_string(), status);
+        tracing::info!("Versioning of bucket {} set to {:?}", bucket, status);
+        Ok(())
+    }
+
+    /// All versions of an object including delete markers, newest first
+    pub async fn list_versions(&self, object_id: &str) -> Result<Vec<ObjectVersion>> {
+        let mut entries = self.noncurrent(object_id).await?;
+        match self.inner.head(object_id).await {
+            Ok(metadata) => entries.push(metadata),
+            Err(NimbuxError::ObjectNotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+        if entries.is_empty() {
+            return Err(NimbuxError::ObjectNotFound { object_id: object_id.to_string() });
+        }
+
+        sort_newest_first(&mut entries);
+        Ok(entries
+            .iter()
+            .enumerate()
+            .map(|(i, metadata)| version_record(object_id, metadata, i == 0))
+            .collect())
+    }
+
+    pub async fn get_version(&self, object_id: &str, version_id: &str) -> Result<Object> {
+        match self.inner.get(object_id).await {
+            Ok(object) if version_id_of(&object.metadata) == version_id => return Ok(object),
+            Ok(_) | Err(NimbuxError::ObjectNotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        let object = match self.inner.get(&version_path(object_id, version_id)).await {
+            Ok(object) => object,
+            Err(NimbuxError::ObjectNotFound { .. }) => {
+                return Err(NimbuxError::ObjectNotFound {
+                    object_id: format!("{} (version {})", object_id, version_id),
+                })
+            }
+            Err(e) => return Err(e),
+        };
+        if is_delete_marker(&object.metadata) {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Version {} of {} is a delete marker",
+                version_id, object_id
+            )));
+        }
+        Ok(relocate(object, object_id))
+    }
+
+    /// Make an earlier version current again by writing it as a new version.
+    ///
+    /// Without `version_id` the newest version that is not a delete marker is
+    /// restored, which undoes the last delete.
+    pub async fn restore_version(&self, object_id: &str, version_id: Option<&str>) -> Result<ObjectVersion> {
+        if self.status_of(object_id).await? == VersioningStatus::Unversioned {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Versioning is not enabled for {}",
+                object_id
+            )));
+        }
+
+        let version_id = match version_id {
+            Some(version_id) => version_id.to_string(),
+            None => {
+                let versions = self.list_versions(object_id).await?;
+                let newest = versions
+                    .iter()
+                    .find(|version| !version.is_delete_marker)
+                    .ok_or_else(|| NimbuxError::ObjectNotFound { object_id: object_id.to_string() })?;
+                if newest.is_latest {
+                    return Err(NimbuxError::InvalidRequest(format!("{} is not deleted", object_id)));
+                }
+                newest.version_id.clone()
+            }
+        };
+
+        let mut object = self.get_version(object_id, &version_id).await?;
+        object.metadata.tags.remove(VERSION_ID_TAG);
+        object.metadata.tags.remove(VERSIONED_AT_TAG);
+        self.put(object).await?;
+
+        let metadata = self.inner.head(object_id).await?;
+        tracing::debug!("Restored version {} of {}", version_id, object_id);
+        Ok(version_record(object_id, &metadata, true))
+    }
+
+    /// Permanently remove one version. When the current version goes, the
+    /// next newest version takes its place unless that is a delete marker.
+    pub async fn delete_version(&self, object_id: &str, version_id: &str) -> Result<()> {
+        let _guard = self.lock_for(object_id).lock().await;
+
+        let current = match self.inner.head(object_id).await {
+            Ok(metadata) => Some(metadata),
+            Err(NimbuxError::ObjectNotFound { .. }) => None,
+            Err(e) => return Err(e),
+        };
+        match current {
+            Some(metadata) if version_id_of(&metadata) == version_id => self.inner.delete(object_id).await?,
+            _ => match self.inner.delete(&version_path(object_id, version_id)).await {
+                Ok(()) => {}
+                Err(NimbuxError::ObjectNotFound { .. }) => {
+                    return Err(NimbuxError::ObjectNotFound {
+                        object_id: format!("{} (version {})", object_id, version_id),
+                    })
+                }
+                Err(e) => return Err(e),
+            },
+        }
+
+        if !self.inner.exists(object_id).await? {
+            let mut remaining = self.noncurrent(object_id).await?;
+            sort_newest_first(&mut remaining);
+            if let Some(newest) = remaining.first().filter(|metadata| !is_delete_marker(metadata)) {
+                let path = newest.id.clone();
+                let object = self.inner.get(&path).await?;
+                self.inner.put(relocate(object, object_id)).await?;
+                self.inner.delete(&path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn status_of(&self, object_id: &str) -> Result<VersioningStatus> {
+        match bucket_of(object_id) {
+            Some(bucket) => self.versioning(bucket).await,
+            None => Ok(VersioningStatus::Unversioned),
+        }
+    }
+
+    async fn noncurrent(&self, object_id: &str) -> Result<Vec<ObjectMetadata>> {
+        let prefix = format!("{}{}/", VERSIONS_PREFIX, object_id);
+        let mut entries = self.inner.list(Some(&prefix), None).await?;
+        // Versions of `<key>/<more>` share the prefix
+        entries.retain(|metadata| !metadata.name[prefix.len()..].contains('/'));
+        Ok(entries)
+    }
+
+    /// Copy the current version aside before it is replaced
+    async fn archive(&self, current: Object, object_id: &str) -> Result<()> {
+        let path = version_path(object_id, &version_id_of(&current.metadata));
+        self.inner.put(relocate(current, &path)).await
+    }
+
+    fn lock_for(&self, object_id: &str) -> &Mutex<()> {
+        let mut hasher = DefaultHasher::new();
+        object_id.hash(&mut hasher);
+        &self.locks[hasher.finish() as usize % self.locks.len()]
+    }
+}
+
+#[async_trait]
+impl StorageBackend for VersionedStorage {
+    async fn put(&self, mut object: Object) -> Result<()> {
+        let object_id = object.metadata.id.clone();
+        let status = self.status_of(&object_id).await?;
+        if status == VersioningStatus::Unversioned {
+            return self.inner.put(object).await;
+        }
+
+        let _guard = self.lock_for(&object_id).lock().await;
+        let current = match self.inner.get(&object_id).await {
+            Ok(current) => Some(current),
+            Err(NimbuxError::ObjectNotFound { .. }) => None,
+            Err(e) => return Err(e),
+        };
+        // A locked object would be archived before the write is refused
+        if let Some(current) = &current {
+            ObjectLock::of(&current.metadata).check(&object_id, Utc::now())?;
+        }
+
+        let version_id = match status {
+            VersioningStatus::Enabled => {
+                if let Some(current) = current {
+                    self.archive(current, &object_id).await?;
+                }
+                new_version_id()
+            }
+            _ => {
+                // Suspended writes replace the null version wherever it is
+                if let Some(current) = current.filter(|current| version_id_of(&current.metadata) != NULL_VERSION_ID) {
+                    self.archive(current, &object_id).await?;
+                }
+                remove_if_present(self.inner.as_ref(), &version_path(&object_id, NULL_VERSION_ID)).await?;
+                NULL_VERSION_ID.to_string()
+            }
+        };
+
+        stamp(&mut object.metadata, &version_id);
+        self.inner.put(object).await
+    }
+
+    async fn get(&self, id: &str) -> Result<Object> {
+        self.inner.get(id).await
+    }
+
+    /// In a versioned bucket the current version is kept and a delete marker
+    /// becomes the latest version
+    async fn delete(&self, id: &str) -> Result<()> {
+        let status = self.status_of(id).await?;
+        if status == VersioningStatus::Unversioned {
+            return self.inner.delete(id).await;
+        }
+
+        let _guard = self.lock_for(id).lock().await;
+        let current = self.inner.get(id).await?;
+        ObjectLock::of(&current.metadata).check(id, Utc::now())?;
+        let marker_version = match status {
+            VersioningStatus::Enabled => {
+                self.archive(current, id).await?;
+                new_version_id()
+            }
+            _ => {
+                if version_id_of(&current.metadata) != NULL_VERSION_ID {
+                    self.archive(current, id).await?;
+                }
+                NULL_VERSION_ID.to_string()
+            }
+        };
+
+        let path = version_path(id, &marker_version);
+        let mut marker = Object::with_id(path.clone(), path, Vec::new(), None);
+        marker.add_tag(DELETE_MARKER_TAG.to_string(), "true".to_string());
+        stamp(&mut marker.metadata, &marker_version);
+        self.inner.put(marker).await?;
+        self.inner.delete(id).await
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool> {
+        self.inner.exists(id).await
+    }
+
+    async fn list(&self, prefix: Option<&str>, limit: Option<usize>) -> Result<Vec<ObjectMetadata>> {
+        match prefix {
+            Some(prefix) if !prefix.starts_with('.') || prefix.starts_with(".versions/") => {
+                self.inner.list(Some(prefix), limit).await
+            }
+            _ => {
+                let mut results = self.inner.list(prefix, None).await?;
+                results.retain(|metadata| !metadata.name.starts_with(".versions/"));
+                if let Some(limit) = limit {
+                    results.truncate(limit);
+                }
+                Ok(results)
+            }
+        }
+    }
+
+    async fn head(&self, id: &str) -> Result<ObjectMetadata> {
+        self.inner.head(id).await
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        self.inner.stats().await
+    }
+}
+
+fn settings_id(bucket: &str) -> String {
+    format!("{}{}", SETTINGS_PREFIX, bucket)
+}
+
+fn version_path(object_id: &str, version_id: &str) -> String {
+    format!("{}{}/{}", VERSIONS_PREFIX, object_id, version_id)
+}
+
+/// Bucket of a `<bucket>/<key>` object ID; internal objects have none
+fn bucket_of(object_id: &str) -> Option<&str> {
+    object_id
+        .split_once('/')
+        .map(|(bucket, _)| bucket)
+        .filter(|bucket| !bucket.is_empty() && !bucket.starts_with('.'))
+}
+
+/// Version IDs sort in the order they were created
+fn new_version_id() -> String {
+    let micros = Utc::now().timestamp_micros().max(0) as u64;
+    format!("{:016x}{}", micros, &Uuid::new_v4().simple().to_string()[..8])
+}
+
+fn stamp(metadata: &mut ObjectMetadata, version_id: &str) {
+    metadata.tags.insert(VERSION_ID_TAG.to_string(), version_id.to_string());
+    metadata
+        .tags
+        .insert(VERSIONED_AT_TAG.to_string(), Utc::now().timestamp_micros().to_string());
+}
+
+fn version_id_of(metadata: &ObjectMetadata) -> String {
+    metadata
+        .tags
+        .get(VERSION_ID_TAG)
+        .cloned()
+        .unwrap_or_else(|| NULL_VERSION_ID.to_string())
+}
+
+/// Objects written before versioning was enabled fall back to their update time
+fn versioned_at(metadata: &ObjectMetadata) -> i64 {
+    metadata
+        .tags
+        .get(VERSIONED_AT_TAG)
+        .and_then(|micros| micros.parse().ok())
+        .unwrap_or(metadata.updated_at as i64 * 1_000_000)
+}
+
+fn is_delete_marker(metadata: &ObjectMetadata) -> bool {
+    metadata.tags.contains_key(DELETE_MARKER_TAG)
+}
+
+fn sort_newest_first(entries: &mut [ObjectMetadata]) {
+    entries.sort_by(|a, b| {
+        versioned_at(b)
+            .cmp(&versioned_at(a))
+            .then_with(|| version_id_of(b).cmp(&version_id_of(a)))
+    });
+}
+
+fn relocate(mut object: Object, id: &str) -> Object {
+    object.metadata.id = id.to_string();
+    object.metadata.name = id.to_string();
+    object
+}
+
+async fn remove_if_present(storage: &dyn StorageBackend, id: &str) -> Result<()> {
+    match storage.delete(id).await {
+        Ok(()) | Err(NimbuxError::ObjectNotFound { .. }) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn version_record(object_id: &str, metadata: &ObjectMetadata, is_latest: bool) -> ObjectVersion {
+    let mut tags = metadata.tags.clone();
+    tags.remove(VERSION_ID_TAG);
+    tags.remove(VERSIONED_AT_TAG);
+    tags.remove(DELETE_MARKER_TAG);
+
+    let mut extra = HashMap::new();
+    if let Some(content_type) = &metadata.content_type {
+        extra.insert("content_type".to_string(), content_type.clone());
+    }
+
+    ObjectVersion {
+        version_id: version_id_of(metadata),
+        object_id: object_id.to_string(),
+        size: metadata.size,
+        content_hash: metadata.checksum.clone(),
+        created_at: DateTime::from_timestamp_micros(versioned_at(metadata)).unwrap_or_default(),
+        is_latest,
+        is_delete_marker: is_delete_marker(metadata),
+        storage_class: "STANDARD".to_string(),
+        compression_algorithm: metadata.compression.clone(),
+        compression_ratio: None,
+        encryption_key_id: None,
+        metadata: extra,
+        tags,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn object(id: &str, data: &[u8]) -> Object {
+        Object::with_id(id.to_string(), id.to_string(), data.to_vec(), None)
+    }
+
+    async fn versioned(status: VersioningStatus) -> (Arc<MemoryStorage>, VersionedStorage) {
+        let inner = Arc::new(MemoryStorage::new());
+        let storage = VersionedStorage::new(inner.clone());
+        if status != VersioningStatus::Unversioned {
+            storage.set_versioning("photos", status).await.unwrap();
+        }
+        (inner, storage)
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_buckets_overwrite_and_delete() {
+        let (inner, storage) = versioned(VersioningStatus::Unversioned).await;
+        storage.put(object("photos/a.jpg", b"one")).await.unwrap();
+        storage.put(object("photos/a.jpg", b"two")).await.unwrap();
+
+        assert_eq!(storage.list_versions("photos/a.jpg").await.unwrap().len(), 1);
+        storage.delete("photos/a.jpg").await.unwrap();
+        assert!(inner.list(None, None).await.unwrap().is_empty());
+        assert!(storage.restore_version("photos/a.jpg", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_leaves_marker_and_restore_undeletes() {
+        let (_, storage) = versioned(VersioningStatus::Enabled).await;
+        storage.put(object("photos/a.jpg", b"one")).await.unwrap();
+        storage.put(object("photos/a.jpg", b"two")).await.unwrap();
+
+        let versions = storage.list_versions("photos/a.jpg").await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert!(versions[0].is_latest);
+        assert!(!versions[1].is_latest);
+        let first = versions[1].version_id.clone();
+
+        storage.delete("photos/a.jpg").await.unwrap();
+        assert!(!storage.exists("photos/a.jpg").await.unwrap());
+        let versions = storage.list_versions("photos/a.jpg").await.unwrap();
+        assert_eq!(versions.len(), 3);
+        assert!(versions[0].is_delete_marker && versions[0].is_latest);
+
+        // Hidden from listings, still readable by version
+        assert!(storage.list(None, None).await.unwrap().iter().all(|m| !m.name.starts_with(".versions/")));
+        assert_eq!(storage.get_version("photos/a.jpg", &first).await.unwrap().data, b"one");
+
+        let restored = storage.restore_version("photos/a.jpg", None).await.unwrap();
+        assert!(restored.is_latest);
+        assert_eq!(storage.get("photos/a.jpg").await.unwrap().data, b"two");
+        assert_eq!(storage.list_versions("photos/a.jpg").await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_restoring_an_old_version_keeps_history() {
+        let (_, storage) = versioned(VersioningStatus::Enabled).await;
+        storage.put(object("photos/a.jpg", b"one")).await.unwrap();
+        storage.put(object("photos/a.jpg", b"two")).await.unwrap();
+        let first = storage.list_versions("photos/a.jpg").await.unwrap()[1].version_id.clone();
+
+        storage.restore_version("photos/a.jpg", Some(&first)).await.unwrap();
+        assert_eq!(storage.get("photos/a.jpg").await.unwrap().data, b"one");
+        assert_eq!(storage.list_versions("photos/a.jpg").await.unwrap().len(), 3);
+        assert!(matches!(
+            storage.restore_version("photos/a.jpg", None).await,
+            Err(NimbuxError::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_deleting_the_marker_brings_the_object_back() {
+        let (_, storage) = versioned(VersioningStatus::Enabled).await;
+        storage.put(object("photos/a.jpg", b"one")).await.unwrap();
+        storage.put(object("photos/a.jpg/b", b"nested")).await.unwrap();
+        storage.put(object("photos/a.jpg/b", b"nested again")).await.unwrap();
+        storage.delete("photos/a.jpg").await.unwrap();
+
+        let marker = storage.list_versions("photos/a.jpg").await.unwrap()[0].clone();
+        assert!(marker.is_delete_marker);
+        storage.delete_version("photos/a.jpg", &marker.version_id).await.unwrap();
+
+        assert_eq!(storage.get("photos/a.jpg").await.unwrap().data, b"one");
+        assert_eq!(storage.list_versions("photos/a.jpg").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_suspended_versioning_replaces_the_null_version() {
+        let (_, storage) = versioned(VersioningStatus::Enabled).await;
+        storage.put(object("photos/a.jpg", b"one")).await.unwrap();
+        storage.set_versioning("photos", VersioningStatus::Suspended).await.unwrap();
+        storage.put(object("photos/a.jpg", b"two")).await.unwrap();
+        storage.put(object("photos/a.jpg", b"three")).await.unwrap();
+
+        let versions = storage.list_versions("photos/a.jpg").await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].version_id, NULL_VERSION_ID);
+
+        assert!(matches!(
+            storage.set_versioning("photos", VersioningStatus::Unversioned).await,
+            Err(NimbuxError::InvalidRequest(_))
+        ));
+    }
+}
@@ -19,6 +19,7 @@ pub mod chunked_transfer;
 pub mod compression;
 pub mod acceleration;
 pub mod streaming;
+pub mod multipart;
 
 // Re-export commonly used types
 pub use parallel_upload::{ParallelUploader, UploadConfig, UploadStats, UploadChunk};
@@ -26,6 +27,7 @@ pub use chunked_transfer::{ChunkedTransfer, ChunkConfig, ChunkStats, ChunkInfo};
 pub use compression::{TransferCompression, CompressionConfig, CompressionStats};
 pub use acceleration::{TransferAccelerator, AccelerationConfig, AccelerationStats};
 pub use streaming::{StreamingTransfer, StreamConfig, StreamStats};
+pub use multipart::{CompletedPart, CompletedUpload, MultipartConfig, MultipartManager, MultipartState, MultipartUpload, PartRecord};
 
 /// Transfer configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
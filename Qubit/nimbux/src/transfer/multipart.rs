@@ -0,0 +1,543 @@
+// ===========================================
+// Nimbux - High-Performance Object Storage
+// (c) 2025 Neo Qiss. All Rights Reserved.
+// Created by Neo Qiss - Unleash the power of Rust.
+// ===========================================
+// Multipart uploads with out-of-order parts and parallel assembly
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::errors::{NimbuxError, Result};
+use crate::storage::{Object, StorageBackend};
+
+/// Upload records and their parts are kept under this prefix, which no
+/// bucket name can start with
+const UPLOAD_PREFIX: &str = ".multipart/";
+const LOCK_STRIPES: usize = 64;
+/// Tag on an assembled object holding the checksum of its parts
+pub const MULTIPART_CHECKSUM_TAG: &str = "multipart-checksum";
+
+/// Multipart upload limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultipartConfig {
+    /// Every part but the last must be at least this large
+    pub min_part_size: u64,
+    pub max_part_size: u64,
+    pub max_parts: u32,
+    /// Largest object an upload can produce, as it is assembled in memory
+    pub max_object_size: u64,
+    /// Parts read and verified at once while assembling
+    pub assembly_parallelism: usize,
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        Self {
+            min_part_size: 5 * 1024 * 1024, // 5MB
+            max_part_size: 512 * 1024 * 1024, // 512MB
+            max_parts: 10_000,
+            max_object_size: 5 * 1024 * 1024 * 1024, // 5GB
+            assembly_parallelism: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MultipartState {
+    /// Accepting parts
+    Uploading,
+    /// Parts are being assembled; no parts are accepted meanwhile
+    Completing,
+    Completed,
+    Aborted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartRecord {
+    pub part_number: u32,
+    pub size: u64,
+    /// SHA-256 of the part, hex encoded
+    pub checksum: String,
+    pub uploaded_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultipartUpload {
+    pub upload_id: String,
+    /// Object the parts are assembled into, `<bucket>/<key>`
+    pub object_id: String,
+    pub content_type: Option<String>,
+    pub state: MultipartState,
+    pub initiated_at: u64,
+    pub updated_at: u64,
+    /// Parts by number, whatever order they arrived in
+    pub parts: BTreeMap<u32, PartRecord>,
+    /// Kept so a retried completion gets the same answer
+    pub completed: Option<CompletedUpload>,
+}
+
+/// Part a client lists when completing, with the checksum it computed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedPart {
+    pub part_number: u32,
+    pub checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedUpload {
+    pub upload_id: String,
+    pub object_id: String,
+    pub size: u64,
+    pub parts: usize,
+    /// SHA-256 of the parts' SHA-256 digests, followed by `-<parts>`
+    pub checksum: String,
+    /// ETag of the assembled object
+    pub etag: String,
+}
+
+/// Tracks multipart uploads and assembles them into objects.
+///
+/// Parts are stored as objects of their own as they arrive, named after
+/// their content so that re-uploading a part never overwrites one that is
+/// being read. Completing reads the parts in parallel, checks each against
+/// the checksum it was uploaded with and writes them at their offsets.
+pub struct MultipartManager {
+    storage: Arc<dyn StorageBackend>,
+    config: MultipartConfig,
+    locks: Vec<Mutex<()>>,
+}
+
+impl MultipartManager {
+    pub fn new(storage: Arc<dyn StorageBackend>, config: MultipartConfig) -> Self {
+        Self {
+            storage,
+            config,
+            locks: (0..LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+
+    pub fn config(&self) -> &MultipartConfig {
+        &self.config
+    }
+
+    pub async fn initiate(&self, object_id: &str, content_type: Option<String>) -> Result<MultipartUpload> {
+        if object_id.is_empty() || object_id.starts_with('.') {
+            return Err(NimbuxError::InvalidObjectId { object_id: object_id.to_string() });
+        }
+        let now = now();
+        let upload = MultipartUpload {
+            upload_id: Uuid::new_v4().to_string(),
+            object_id: object_id.to_string(),
+            content_type,
+            state: MultipartState::Uploading,
+            initiated_at: now,
+            updated_at: now,
+            parts: BTreeMap::new(),
+            completed: None,
+        };
+        self.save(&upload).await?;
+        tracing::debug!("Initiated multipart upload {} of {}", upload.upload_id, object_id);
+        Ok(upload)
+    }
+
+    pub async fn get(&self, upload_id: &str) -> Result<MultipartUpload> {
+        let object = self.storage.get(&record_id(upload_id)).await.map_err(|e| match e {
+            NimbuxError::ObjectNotFound { .. } => NimbuxError::ObjectNotFound { object_id: format!("upload {}", upload_id) },
+            e => e,
+        })?;
+        Ok(serde_json::from_slice(&object.data)?)
+    }
+
+    /// Uploads still accepting parts whose object starts with `prefix`
+    pub async fn list(&self, prefix: &str) -> Result<Vec<MultipartUpload>> {
+        let mut uploads = Vec::new();
+        for metadata in self.storage.list(Some(UPLOAD_PREFIX), None).await? {
+            let Some(upload_id) = metadata.id.strip_prefix(UPLOAD_PREFIX).filter(|id| !id.contains('/')) else {
+                continue;
+            };
+            match self.get(upload_id).await {
+                Ok(upload) if upload.state == MultipartState::Uploading && upload.object_id.starts_with(prefix) => {
+                    uploads.push(upload)
+                }
+                Ok(_) | Err(NimbuxError::ObjectNotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        uploads.sort_by_key(|upload| upload.initiated_at);
+        Ok(uploads)
+    }
+
+    /// Store part `part_number` of an upload. Parts can arrive in any order,
+    /// and uploading a number again replaces the earlier part.
+    /// `expected_checksum` is the SHA-256 the client computed, if it sent one.
+    pub async fn upload_part(
+        &self,
+        upload_id: &str,
+        part_number: u32,
+        data: Vec<u8>,
+        expected_checksum: Option<&str>,
+    ) -> Result<PartRecord> {
+        if part_number == 0 || part_number > self.config.max_parts {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Part numbers run from 1 to {}, not {}",
+                self.config.max_parts, part_number
+            )));
+        }
+        if data.len() as u64 > self.config.max_part_size {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Part {} is {} bytes, more than the {} bytes allowed",
+                part_number,
+                data.len(),
+                self.config.max_part_size
+            )));
+        }
+
+        let checksum = sha256_hex(&data);
+        if let Some(expected) = expected_checksum.map(|expected| expected.trim().to_ascii_lowercase()) {
+            if expected != checksum {
+                return Err(NimbuxError::ChecksumMismatch { expected, actual: checksum });
+            }
+        }
+        // Fail early rather than store a part nobody will use
+        accepting_parts(&self.get(upload_id).await?)?;
+
+        let part = PartRecord { part_number, size: data.len() as u64, checksum, uploaded_at: now() };
+        let stored_as = part_id(upload_id, &part);
+        self.storage
+            .put(Object::with_id(stored_as.clone(), stored_as.clone(), data, None))
+            .await?;
+
+        let _guard = self.lock_for(upload_id).lock().await;
+        let mut upload = self.get(upload_id).await?;
+        // The upload may have been completed or aborted while the part was stored
+        if let Err(e) = accepting_parts(&upload) {
+            self.delete_quietly(&stored_as).await;
+            return Err(e);
+        }
+        let replaced = upload.parts.insert(part_number, part.clone());
+        upload.updated_at = part.uploaded_at;
+        self.save(&upload).await?;
+        if let Some(replaced) = replaced.filter(|replaced| part_id(upload_id, replaced) != stored_as) {
+            self.delete_quietly(&part_id(upload_id, &replaced)).await;
+        }
+        Ok(part)
+    }
+
+    /// Assemble the listed parts, in part number order, into the upload's
+    /// object; an empty list uses every uploaded part. Parts that were
+    /// uploaded but not listed are discarded.
+    ///
+    /// If assembly fails the upload goes back to accepting parts, so a bad
+    /// part can be uploaded again and the completion retried.
+    pub async fn complete(&self, upload_id: &str, requested: &[CompletedPart]) -> Result<CompletedUpload> {
+        let (upload, parts) = {
+            let _guard = self.lock_for(upload_id).lock().await;
+            let mut upload = self.get(upload_id).await?;
+            match (upload.state, &upload.completed) {
+                (MultipartState::Completed, Some(completed)) => return Ok(completed.clone()),
+                (MultipartState::Uploading, _) => {}
+                _ => accepting_parts(&upload)?,
+            }
+            let parts = self.select_parts(&upload, requested)?;
+            upload.state = MultipartState::Completing;
+            upload.updated_at = now();
+            self.save(&upload).await?;
+            (upload, parts)
+        };
+
+        let assembled = self.assemble(&upload, &parts).await;
+
+        let _guard = self.lock_for(upload_id).lock().await;
+        let mut upload = self.get(upload_id).await?;
+        upload.updated_at = now();
+        match assembled {
+            Ok(completed) => {
+                upload.state = MultipartState::Completed;
+                upload.completed = Some(completed.clone());
+                self.save(&upload).await?;
+                for part in upload.parts.values() {
+                    self.delete_quietly(&part_id(upload_id, part)).await;
+                }
+                tracing::info!(
+                    "Completed multipart upload {} of {} ({} parts, {} bytes)",
+                    upload_id, completed.object_id, completed.parts, completed.size
+                );
+                Ok(completed)
+            }
+            Err(e) => {
+                upload.state = MultipartState::Uploading;
+                self.save(&upload).await?;
+                tracing::warn!("Multipart upload {} failed to complete: {}", upload_id, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop an upload and discard its parts
+    pub async fn abort(&self, upload_id: &str) -> Result<MultipartUpload> {
+        let _guard = self.lock_for(upload_id).lock().await;
+        let mut upload = self.get(upload_id).await?;
+        match upload.state {
+            MultipartState::Aborted => return Ok(upload),
+            MultipartState::Uploading => {}
+            _ => accepting_parts(&upload)?,
+        }
+        upload.state = MultipartState::Aborted;
+        upload.updated_at = now();
+        self.save(&upload).await?;
+        for part in upload.parts.values() {
+            self.delete_quietly(&part_id(upload_id, part)).await;
+        }
+        tracing::debug!("Aborted multipart upload {} of {}", upload_id, upload.object_id);
+        Ok(upload)
+    }
+
+    fn select_parts(&self, upload: &MultipartUpload, requested: &[CompletedPart]) -> Result<Vec<PartRecord>> {
+        let parts: Vec<PartRecord> = if requested.is_empty() {
+            upload.parts.values().cloned().collect()
+        } else {
+            let mut previous = 0;
+            let mut parts = Vec::with_capacity(requested.len());
+            for listed in requested {
+                if listed.part_number <= previous {
+                    return Err(NimbuxError::InvalidRequest(
+                        "Parts must be listed in ascending part number order".to_string(),
+                    ));
+                }
+                previous = listed.part_number;
+                let part = upload.parts.get(&listed.part_number).ok_or_else(|| {
+                    NimbuxError::InvalidRequest(format!("Part {} was not uploaded", listed.part_number))
+                })?;
+                if let Some(checksum) = &listed.checksum {
+                    if !checksum.trim().eq_ignore_ascii_case(&part.checksum) {
+                        return Err(NimbuxError::ChecksumMismatch {
+                            expected: checksum.trim().to_ascii_lowercase(),
+                            actual: part.checksum.clone(),
+                        });
+                    }
+                }
+                parts.push(part.clone());
+            }
+            parts
+        };
+
+        if parts.is_empty() {
+            return Err(NimbuxError::InvalidRequest(format!("Upload {} has no parts", upload.upload_id)));
+        }
+        if let Some(small) = parts[..parts.len() - 1].iter().find(|part| part.size < self.config.min_part_size) {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "Part {} is {} bytes; every part but the last must be at least {} bytes",
+                small.part_number, small.size, self.config.min_part_size
+            )));
+        }
+        let size: u64 = parts.iter().map(|part| part.size).sum();
+        if size > self.config.max_object_size {
+            return Err(NimbuxError::InvalidRequest(format!(
+                "The parts add up to {} bytes, more than the {} bytes an object can have",
+                size, self.config.max_object_size
+            )));
+        }
+        Ok(parts)
+    }
+
+    /// Read and verify up to `assembly_parallelism` parts at a time, copying
+    /// each into place as it arrives
+    async fn assemble(&self, upload: &MultipartUpload, parts: &[PartRecord]) -> Result<CompletedUpload> {
+        let size: u64 = parts.iter().map(|part| part.size).sum();
+        let mut data = vec![0u8; size as usize];
+
+        let mut pending: VecDeque<(PartRecord, usize)> = VecDeque::with_capacity(parts.len());
+        let mut offset = 0;
+        for part in parts {
+            pending.push_back((part.clone(), offset));
+            offset += part.size as usize;
+        }
+
+        let mut tasks = JoinSet::new();
+        let spawn = |tasks: &mut JoinSet<Result<(usize, Vec<u8>)>>, (part, offset): (PartRecord, usize)| {
+            let storage = Arc::clone(&self.storage);
+            let stored_as = part_id(&upload.upload_id, &part);
+            tasks.spawn(async move {
+                let object = storage.get(&stored_as).await?;
+                let actual = sha256_hex(&object.data);
+                if object.data.len() as u64 != part.size || actual != part.checksum {
+                    return Err(NimbuxError::ChecksumMismatch { expected: part.checksum, actual });
+                }
+                Ok((offset, object.data))
+            });
+        };
+        for _ in 0..self.config.assembly_parallelism.max(1) {
+            if let Some(next) = pending.pop_front() {
+                spawn(&mut tasks, next);
+            }
+        }
+        // Returning early drops the set, which cancels the remaining reads
+        while let Some(joined) = tasks.join_next().await {
+            let (offset, bytes) = joined.map_err(|e| NimbuxError::Internal(format!("Part read failed: {}", e)))??;
+            data[offset..offset + bytes.len()].copy_from_slice(&bytes);
+            if let Some(next) = pending.pop_front() {
+                spawn(&mut tasks, next);
+            }
+        }
+
+        let mut digests = Sha256::new();
+        for part in parts {
+            let digest = hex::decode(&part.checksum).map_err(|e| NimbuxError::Internal(e.to_string()))?;
+            digests.update(digest);
+        }
+        let checksum = format!("{}-{}", hex::encode(digests.finalize()), parts.len());
+
+        let mut object = Object::with_id(upload.object_id.clone(), upload.object_id.clone(), data, upload.content_type.clone());
+        if let Ok(previous) = self.storage.head(&upload.object_id).await {
+            object.metadata.version = previous.version + 1;
+            object.metadata.created_at = previous.created_at;
+        }
+        object.add_tag(MULTIPART_CHECKSUM_TAG.to_string(), checksum.clone());
+        let etag = object.metadata.checksum.clone();
+        self.storage.put(object).await?;
+
+        Ok(CompletedUpload {
+            upload_id: upload.upload_id.clone(),
+            object_id: upload.object_id.clone(),
+            size,
+            parts: parts.len(),
+            checksum,
+            etag,
+        })
+    }
+
+    async fn save(&self, upload: &MultipartUpload) -> Result<()> {
+        let id = record_id(&upload.upload_id);
+        let data = serde_json::to_vec(upload)?;
+        self.storage
+            .put(Object::with_id(id.clone(), id, data, Some("application/json".to_string())))
+            .await
+    }
+
+    async fn delete_quietly(&self, id: &str) {
+        if let Err(e) = self.storage.delete(id).await {
+            tracing::warn!("Failed to delete multipart part {}: {}", id, e);
+        }
+    }
+
+    fn lock_for(&self, upload_id: &str) -> &Mutex<()> {
+        let mut hasher = DefaultHasher::new();
+        upload_id.hash(&mut hasher);
+        &self.locks[hasher.finish() as usize % self.locks.len()]
+    }
+}
+
+fn accepting_parts(upload: &MultipartUpload) -> Result<()> {
+    let problem = match upload.state {
+        MultipartState::Uploading => return Ok(()),
+        MultipartState::Completing => "is being completed",
+        MultipartState::Completed => "is already complete",
+        MultipartState::Aborted => "was aborted",
+    };
+    Err(NimbuxError::InvalidRequest(format!("Upload {} {}", upload.upload_id, problem)))
+}
+
+fn record_id(upload_id: &str) -> String {
+    format!("{}{}", UPLOAD_PREFIX, upload_id)
+}
+
+fn part_id(upload_id: &str, part: &PartRecord) -> String {
+    format!("{}{}/{:05}-{}", UPLOAD_PREFIX, upload_id, part.part_number, &part.checksum[..16])
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn manager() -> MultipartManager {
+        let config = MultipartConfig { min_part_size: 4, assembly_parallelism: 2, ..Default::default() };
+        MultipartManager::new(Arc::new(MemoryStorage::new()), config)
+    }
+
+    fn listed(part_number: u32) -> CompletedPart {
+        CompletedPart { part_number, checksum: None }
+    }
+
+    #[tokio::test]
+    async fn test_parts_out_of_order_are_assembled_in_order() {
+        let manager = manager();
+        let upload = manager.initiate("videos/a.mp4", Some("video/mp4".to_string())).await.unwrap();
+
+        for (number, data) in [(3, &b"cc"[..]), (1, b"aaaa"), (2, b"bbbb")] {
+            manager.upload_part(&upload.upload_id, number, data.to_vec(), None).await.unwrap();
+        }
+        let completed = manager.complete(&upload.upload_id, &[listed(1), listed(2), listed(3)]).await.unwrap();
+        assert_eq!((completed.size, completed.parts), (10, 3));
+        assert!(completed.checksum.ends_with("-3"));
+
+        let object = manager.storage.get("videos/a.mp4").await.unwrap();
+        assert_eq!(object.data, b"aaaabbbbcc");
+        assert_eq!(object.metadata.tags.get(MULTIPART_CHECKSUM_TAG), Some(&completed.checksum));
+
+        // Parts are gone, and a retried completion gets the same answer
+        assert_eq!(manager.storage.list(Some(&format!("{}{}/", UPLOAD_PREFIX, upload.upload_id)), None).await.unwrap().len(), 0);
+        assert_eq!(manager.complete(&upload.upload_id, &[]).await.unwrap().checksum, completed.checksum);
+    }
+
+    #[tokio::test]
+    async fn test_checksums_are_verified() {
+        let manager = manager();
+        let upload = manager.initiate("videos/b.mp4", None).await.unwrap();
+
+        assert!(matches!(
+            manager.upload_part(&upload.upload_id, 1, b"aaaa".to_vec(), Some(&sha256_hex(b"bbbb"))).await,
+            Err(NimbuxError::ChecksumMismatch { .. })
+        ));
+        let part = manager.upload_part(&upload.upload_id, 1, b"aaaa".to_vec(), Some(&sha256_hex(b"aaaa"))).await.unwrap();
+
+        let wrong = CompletedPart { part_number: 1, checksum: Some(sha256_hex(b"other")) };
+        assert!(matches!(manager.complete(&upload.upload_id, &[wrong]).await, Err(NimbuxError::ChecksumMismatch { .. })));
+
+        // A part corrupted in storage fails assembly and leaves the upload open
+        let stored_as = part_id(&upload.upload_id, &part);
+        manager.storage.put(Object::with_id(stored_as.clone(), stored_as, b"aaab".to_vec(), None)).await.unwrap();
+        assert!(matches!(manager.complete(&upload.upload_id, &[]).await, Err(NimbuxError::ChecksumMismatch { .. })));
+        assert_eq!(manager.get(&upload.upload_id).await.unwrap().state, MultipartState::Uploading);
+
+        manager.upload_part(&upload.upload_id, 1, b"aaaa".to_vec(), None).await.unwrap();
+        assert_eq!(manager.complete(&upload.upload_id, &[]).await.unwrap().size, 4);
+    }
+
+    #[tokio::test]
+    async fn test_small_parts_and_aborts() {
+        let manager = manager();
+        let upload = manager.initiate("videos/c.mp4", None).await.unwrap();
+        manager.upload_part(&upload.upload_id, 1, b"aa".to_vec(), None).await.unwrap();
+        manager.upload_part(&upload.upload_id, 2, b"bbbb".to_vec(), None).await.unwrap();
+        assert!(matches!(manager.complete(&upload.upload_id, &[]).await, Err(NimbuxError::InvalidRequest(_))));
+        assert_eq!(manager.list("videos/").await.unwrap().len(), 1);
+
+        manager.abort(&upload.upload_id).await.unwrap();
+        assert!(manager.list("videos/").await.unwrap().is_empty());
+        assert!(matches!(
+            manager.upload_part(&upload.upload_id, 3, b"cc".to_vec(), None).await,
+            Err(NimbuxError::InvalidRequest(_))
+        ));
+        assert!(!manager.storage.exists("videos/c.mp4").await.unwrap());
+    }
+}
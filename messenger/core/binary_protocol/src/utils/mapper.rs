@@ -906,6 +906,7 @@ fn map_to_client_info(
     let transport = match transport {
         1 => "TCP",
         2 => "QUIC",
+        3 => "WebSocket",
         _ => "Unknown",
     }
     .to_string();
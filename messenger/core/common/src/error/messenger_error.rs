@@ -112,8 +112,20 @@ pub enum MessengerError {
     PersonalAccessTokenExpired(String, u32) = 54,
     #[error("Users limit reached.")]
     UsersLimitReached = 55,
+    #[error("Tenant: {0} has reached the maximum number of streams: {1}")]
+    TenantStreamsLimitReached(String, u32) = 56,
+    #[error("Tenant: {0} has reached the maximum number of topics: {1}")]
+    TenantTopicsLimitReached(String, u32) = 57,
+    #[error("Tenant: {0} has reached the maximum size: {1}")]
+    TenantSizeLimitReached(String, MessengerByteSize) = 58,
+    #[error("Subscriptions limit: {0} has been reached")]
+    SubscriptionsLimitReached(u32) = 59,
+    #[error("Subscription: {0} already exists")]
+    SubscriptionAlreadyExists(String) = 60,
     #[error("Not connected")]
     NotConnected = 61,
+    #[error("Server is shutting down")]
+    ServerShuttingDown = 62,
     #[error("Client shutdown")]
     ClientShutdown = 63,
     #[error("Invalid TLS domain")]
@@ -124,6 +136,8 @@ pub enum MessengerError {
     InvalidTlsCertificate = 66,
     #[error("Failed to add certificate")]
     FailedToAddCertificate = 67,
+    #[error("Subscription: {0} was not found")]
+    SubscriptionNotFound(String) = 68,
     #[error("Invalid encryption key")]
     InvalidEncryptionKey = 70,
     #[error("Cannot encrypt data")]
@@ -152,6 +166,10 @@ pub enum MessengerError {
     InvalidBooleanValue = 83,
     #[error("Invalid number value")]
     InvalidNumberValue = 84,
+    #[error("Cannot compress data using {0} compression")]
+    CannotCompressData(String) = 85,
+    #[error("Cannot decompress data using {0} compression")]
+    CannotDecompressData(String) = 86,
     #[error("Client with ID: {0} was not found.")]
     ClientNotFound(u32) = 100,
     #[error("Invalid client ID")]
@@ -362,6 +380,10 @@ pub enum MessengerError {
     InvalidMessagesSize(u32, u32) = 4036,
     #[error("Too small message: {0}B, expected: {1}B")]
     TooSmallMessage(u32, u32) = 4037,
+    #[error("Header: {0} is not indexed for topic with ID: {1} and stream with ID: {2}")]
+    HeaderNotIndexed(String, u32, u32) = 4038,
+    #[error("Invalid message time to live, expected a positive number of milliseconds or a duration")]
+    InvalidMessageTtl = 4039,
     #[error("Cannot sed messages due to client disconnection")]
     CannotSendMessagesDueToClientDisconnection = 4050,
     #[error("Background send error")]
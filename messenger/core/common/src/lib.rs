@@ -49,6 +49,7 @@ pub use types::client::client_info::*;
 pub use types::client_state::ClientState;
 pub use types::command::*;
 pub use types::compression::compression_algorithm::*;
+pub use types::compression::message_compression::*;
 pub use types::configuration::auth_config::auto_login::*;
 pub use types::configuration::auth_config::connection_string::*;
 pub use types::configuration::auth_config::connection_string_options::*;
@@ -22,13 +22,13 @@ use serde::{
 };
 use std::{
     fmt::{Display, Formatter},
+    io::{Read, Write},
     str::FromStr,
 };
 
 use crate::error::MessengerError;
 
-// for now only those, in the future will add snappy, lz4, zstd (same as in confluent kafka) in addition to that
-// we should consider brotli as well.
+// snappy (same as in confluent kafka) and brotli are worth considering in the future.
 /// Supported compression algorithms
 #[derive(Debug, Default, PartialEq, Clone, Copy)]
 pub enum CompressionAlgorithm {
@@ -37,14 +37,23 @@ pub enum CompressionAlgorithm {
     None,
     // Gzip compression algorithm
     Gzip,
+    // LZ4 block compression, favours speed over ratio
+    Lz4,
+    // Zstandard compression, favours ratio over speed
+    Zstd,
 }
 
+/// Zstandard level used for payload compression, matches the zstd default.
+const ZSTD_COMPRESSION_LEVEL: i32 = 3;
+
 impl FromStr for CompressionAlgorithm {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "gzip" => Ok(CompressionAlgorithm::Gzip),
+            "lz4" => Ok(CompressionAlgorithm::Lz4),
+            "zstd" => Ok(CompressionAlgorithm::Zstd),
             "none" => Ok(CompressionAlgorithm::None),
             _ => Err(format!("Unknown compression type: {s}")),
         }
@@ -56,6 +65,8 @@ impl CompressionAlgorithm {
         match self {
             CompressionAlgorithm::None => 1,
             CompressionAlgorithm::Gzip => 2,
+            CompressionAlgorithm::Lz4 => 3,
+            CompressionAlgorithm::Zstd => 4,
         }
     }
 
@@ -63,9 +74,48 @@ impl CompressionAlgorithm {
         match code {
             1 => Ok(CompressionAlgorithm::None),
             2 => Ok(CompressionAlgorithm::Gzip),
+            3 => Ok(CompressionAlgorithm::Lz4),
+            4 => Ok(CompressionAlgorithm::Zstd),
             _ => Err(MessengerError::InvalidCommand),
         }
     }
+
+    /// Compresses the provided data, `None` returns it unchanged.
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, MessengerError> {
+        let error = |_| MessengerError::CannotCompressData(self.to_string());
+        match self {
+            CompressionAlgorithm::None => Ok(data.to_vec()),
+            CompressionAlgorithm::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).map_err(error)?;
+                encoder.finish().map_err(error)
+            }
+            CompressionAlgorithm::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            CompressionAlgorithm::Zstd => {
+                zstd::encode_all(data, ZSTD_COMPRESSION_LEVEL).map_err(error)
+            }
+        }
+    }
+
+    /// Decompresses data previously produced by [`CompressionAlgorithm::compress`].
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, MessengerError> {
+        let error = || MessengerError::CannotDecompressData(self.to_string());
+        match self {
+            CompressionAlgorithm::None => Ok(data.to_vec()),
+            CompressionAlgorithm::Gzip => {
+                let mut decompressed = Vec::new();
+                flate2::read::GzDecoder::new(data)
+                    .read_to_end(&mut decompressed)
+                    .map_err(|_| error())?;
+                Ok(decompressed)
+            }
+            CompressionAlgorithm::Lz4 => {
+                lz4_flex::decompress_size_prepended(data).map_err(|_| error())
+            }
+            CompressionAlgorithm::Zstd => zstd::decode_all(data).map_err(|_| error()),
+        }
+    }
 }
 
 impl Display for CompressionAlgorithm {
@@ -73,6 +123,8 @@ impl Display for CompressionAlgorithm {
         match self {
             CompressionAlgorithm::None => write!(f, "none"),
             CompressionAlgorithm::Gzip => write!(f, "gzip"),
+            CompressionAlgorithm::Lz4 => write!(f, "lz4"),
+            CompressionAlgorithm::Zstd => write!(f, "zstd"),
         }
     }
 }
@@ -85,6 +137,8 @@ impl Serialize for CompressionAlgorithm {
         match self {
             CompressionAlgorithm::None => serializer.serialize_str("none"),
             CompressionAlgorithm::Gzip => serializer.serialize_str("gzip"),
+            CompressionAlgorithm::Lz4 => serializer.serialize_str("lz4"),
+            CompressionAlgorithm::Zstd => serializer.serialize_str("zstd"),
         }
     }
 }
@@ -94,6 +148,8 @@ impl From<CompressionAlgorithm> for String {
         match value {
             CompressionAlgorithm::None => "none".to_string(),
             CompressionAlgorithm::Gzip => "gzip".to_string(),
+            CompressionAlgorithm::Lz4 => "lz4".to_string(),
+            CompressionAlgorithm::Zstd => "zstd".to_string(),
         }
     }
 }
@@ -186,6 +242,39 @@ mod tests {
         let gzip = CompressionAlgorithm::from_code(2);
         assert!(gzip.is_ok());
         assert_eq!(gzip.unwrap(), CompressionAlgorithm::Gzip);
+
+        let lz4 = CompressionAlgorithm::from_code(3);
+        assert!(lz4.is_ok());
+        assert_eq!(lz4.unwrap(), CompressionAlgorithm::Lz4);
+
+        let zstd = CompressionAlgorithm::from_code(4);
+        assert!(zstd.is_ok());
+        assert_eq!(zstd.unwrap(), CompressionAlgorithm::Zstd);
+    }
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let data = "messenger ".repeat(100).into_bytes();
+        for algorithm in [
+            CompressionAlgorithm::None,
+            CompressionAlgorithm::Gzip,
+            CompressionAlgorithm::Lz4,
+            CompressionAlgorithm::Zstd,
+        ] {
+            let compressed = algorithm.compress(&data).unwrap();
+            if algorithm != CompressionAlgorithm::None {
+                assert!(compressed.len() < data.len());
+            }
+            assert_eq!(algorithm.decompress(&compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_decompress_invalid_input() {
+        let garbage = [0xFFu8; 16];
+        assert!(CompressionAlgorithm::Gzip.decompress(&garbage).is_err());
+        assert!(CompressionAlgorithm::Lz4.decompress(&garbage).is_err());
+        assert!(CompressionAlgorithm::Zstd.decompress(&garbage).is_err());
     }
     #[test]
     fn test_from_code_invalid_input() {
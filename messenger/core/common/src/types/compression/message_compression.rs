@@ -0,0 +1,142 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::{
+    BytesSerializable, CompressionAlgorithm, HeaderKey, HeaderValue, MessengerError,
+    MessengerMessage,
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+
+/// User header carrying the code of the algorithm a message payload was compressed with.
+pub const COMPRESSION_HEADER: &str = "messenger-compression";
+
+/// Compresses the message payload in place and tags it with [`COMPRESSION_HEADER`].
+///
+/// Payloads that would not shrink are left untouched, so the returned flag tells
+/// whether the message was actually compressed.
+pub fn compress_message(
+    message: &mut MessengerMessage,
+    algorithm: CompressionAlgorithm,
+) -> Result<bool, MessengerError> {
+    if algorithm == CompressionAlgorithm::None {
+        return Ok(false);
+    }
+
+    let compressed = algorithm.compress(&message.payload)?;
+    if compressed.len() >= message.payload.len() {
+        return Ok(false);
+    }
+
+    let mut user_headers = message.user_headers_map()?.unwrap_or_default();
+    user_headers.insert(
+        HeaderKey::new(COMPRESSION_HEADER)?,
+        HeaderValue::from_uint8(algorithm.as_code())?,
+    );
+    set_user_headers(message, user_headers);
+    message.payload = Bytes::from(compressed);
+    message.header.payload_length = message.payload.len() as u32;
+    Ok(true)
+}
+
+/// Restores the original payload of a message tagged with [`COMPRESSION_HEADER`].
+///
+/// Messages without the header are returned as they are.
+pub fn decompress_message(message: &mut MessengerMessage) -> Result<(), MessengerError> {
+    let Some(mut user_headers) = message.user_headers_map()? else {
+        return Ok(());
+    };
+
+    let key = HeaderKey::new(COMPRESSION_HEADER)?;
+    let Some(value) = user_headers.remove(&key) else {
+        return Ok(());
+    };
+
+    let algorithm = CompressionAlgorithm::from_code(value.as_uint8()?)?;
+    message.payload = Bytes::from(algorithm.decompress(&message.payload)?);
+    message.header.payload_length = message.payload.len() as u32;
+    set_user_headers(message, user_headers);
+    Ok(())
+}
+
+fn set_user_headers(message: &mut MessengerMessage, user_headers: HashMap<HeaderKey, HeaderValue>) {
+    if user_headers.is_empty() {
+        message.user_headers = None;
+        message.header.user_headers_length = 0;
+        return;
+    }
+
+    let bytes = user_headers.to_bytes();
+    message.header.user_headers_length = bytes.len() as u32;
+    message.user_headers = Some(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn message(payload: &str) -> MessengerMessage {
+        MessengerMessage::builder()
+            .payload(Bytes::from(payload.to_owned()))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn should_compress_and_restore_payload() {
+        let payload = "payload ".repeat(64);
+        let mut message = message(&payload);
+
+        assert!(compress_message(&mut message, CompressionAlgorithm::Zstd).unwrap());
+        assert!(message.payload.len() < payload.len());
+        assert_eq!(message.header.payload_length as usize, message.payload.len());
+
+        decompress_message(&mut message).unwrap();
+        assert_eq!(message.payload, Bytes::from(payload));
+        assert!(message.user_headers.is_none());
+        assert_eq!(message.header.user_headers_length, 0);
+    }
+
+    #[test]
+    fn should_keep_payload_that_does_not_shrink() {
+        let mut message = message("abc");
+
+        assert!(!compress_message(&mut message, CompressionAlgorithm::Lz4).unwrap());
+        assert_eq!(message.payload, Bytes::from("abc"));
+        assert!(message.user_headers.is_none());
+    }
+
+    #[test]
+    fn should_preserve_existing_user_headers() {
+        let key = HeaderKey::from_str("content-type").unwrap();
+        let value = HeaderValue::from_str("text/plain").unwrap();
+        let mut message = MessengerMessage::builder()
+            .payload(Bytes::from("payload ".repeat(64)))
+            .user_headers(HashMap::from([(key.clone(), value.clone())]))
+            .build()
+            .unwrap();
+
+        assert!(compress_message(&mut message, CompressionAlgorithm::Lz4).unwrap());
+        decompress_message(&mut message).unwrap();
+
+        let user_headers = message.user_headers_map().unwrap().unwrap();
+        assert_eq!(user_headers.len(), 1);
+        assert_eq!(user_headers.get(&key), Some(&value));
+    }
+}
@@ -17,3 +17,4 @@
  */
 
 pub mod compression_algorithm;
+pub mod message_compression;
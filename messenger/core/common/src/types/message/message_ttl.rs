@@ -0,0 +1,153 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use super::user_headers::{HeaderKey, HeaderKind, HeaderValue};
+use crate::error::MessengerError;
+use crate::utils::duration::MessengerDuration;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// User header holding the time to live of a message.
+///
+/// A message expires once its time to live has passed since the server appended it.
+/// Expired messages are skipped when polling and are deleted by the messages cleaner
+/// once every message of their segment has expired.
+///
+/// The value is either a number of milliseconds (`uint32` or `uint64`),
+/// or a duration string such as `5s` or `1m 30s`.
+///
+/// # Examples
+///
+/// ```
+/// use messenger_common::*;
+/// use std::collections::HashMap;
+/// use std::time::Duration;
+///
+/// let message = MessengerMessage::builder()
+///     .payload("typing".into())
+///     .user_headers(HashMap::from([message_ttl_header(Duration::from_secs(5)).unwrap()]))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(message.ttl().unwrap(), Some(MessengerDuration::new_from_secs(5)));
+/// ```
+pub const MESSAGE_TTL_HEADER: &str = "messenger-ttl";
+
+/// Creates the user header setting the time to live of a message, rounded down to milliseconds.
+pub fn message_ttl_header(ttl: Duration) -> Result<(HeaderKey, HeaderValue), MessengerError> {
+    let milliseconds = ttl.as_millis();
+    if milliseconds == 0 || milliseconds > u64::MAX as u128 {
+        return Err(MessengerError::InvalidMessageTtl);
+    }
+
+    Ok((
+        HeaderKey::new(MESSAGE_TTL_HEADER)?,
+        HeaderValue::from_uint64(milliseconds as u64)?,
+    ))
+}
+
+/// Reads the time to live from the user headers of a message.
+///
+/// # Returns
+///
+/// * `Ok(Some(MessengerDuration))` - The message expires after the returned duration
+/// * `Ok(None)` - The message has no time to live header and never expires on its own
+/// * `Err(MessengerError::InvalidMessageTtl)` - The header is not a positive duration
+pub fn get_message_ttl(
+    user_headers: &HashMap<HeaderKey, HeaderValue>,
+) -> Result<Option<MessengerDuration>, MessengerError> {
+    let Some(value) = user_headers
+        .iter()
+        .find_map(|(key, value)| (key.as_str() == MESSAGE_TTL_HEADER).then_some(value))
+    else {
+        return Ok(None);
+    };
+
+    let ttl = match value.kind {
+        HeaderKind::Uint32 => Duration::from_millis(value.as_uint32()? as u64),
+        HeaderKind::Uint64 => Duration::from_millis(value.as_uint64()?),
+        HeaderKind::String => MessengerDuration::from_str(value.as_str()?)
+            .map_err(|_| MessengerError::InvalidMessageTtl)?
+            .get_duration(),
+        _ => return Err(MessengerError::InvalidMessageTtl),
+    };
+
+    if ttl.is_zero() {
+        return Err(MessengerError::InvalidMessageTtl);
+    }
+
+    Ok(Some(MessengerDuration::new(ttl)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(value: HeaderValue) -> HashMap<HeaderKey, HeaderValue> {
+        HashMap::from([(HeaderKey::new(MESSAGE_TTL_HEADER).unwrap(), value)])
+    }
+
+    #[test]
+    fn ttl_should_be_read_from_milliseconds() {
+        let ttl = get_message_ttl(&headers(HeaderValue::from_uint64(1500).unwrap())).unwrap();
+        assert_eq!(ttl, Some(MessengerDuration::new(Duration::from_millis(1500))));
+
+        let ttl = get_message_ttl(&headers(HeaderValue::from_uint32(200).unwrap())).unwrap();
+        assert_eq!(ttl, Some(MessengerDuration::new(Duration::from_millis(200))));
+    }
+
+    #[test]
+    fn ttl_should_be_read_from_duration_string() {
+        let ttl = get_message_ttl(&headers(HeaderValue::from_str("1m 30s").unwrap())).unwrap();
+        assert_eq!(ttl, Some(MessengerDuration::new_from_secs(90)));
+    }
+
+    #[test]
+    fn missing_ttl_header_should_not_expire() {
+        let user_headers = HashMap::from([(
+            HeaderKey::new("content-type").unwrap(),
+            HeaderValue::from_str("text/plain").unwrap(),
+        )]);
+        assert_eq!(get_message_ttl(&user_headers).unwrap(), None);
+    }
+
+    #[test]
+    fn zero_or_invalid_ttl_should_be_rejected() {
+        for value in [
+            HeaderValue::from_uint64(0).unwrap(),
+            HeaderValue::from_str("0").unwrap(),
+            HeaderValue::from_str("soon").unwrap(),
+            HeaderValue::from_int64(1000).unwrap(),
+        ] {
+            assert!(matches!(
+                get_message_ttl(&headers(value)),
+                Err(MessengerError::InvalidMessageTtl)
+            ));
+        }
+    }
+
+    #[test]
+    fn ttl_header_should_round_trip() {
+        let (key, value) = message_ttl_header(Duration::from_millis(2500)).unwrap();
+        let ttl = get_message_ttl(&HashMap::from([(key, value)])).unwrap();
+        assert_eq!(ttl, Some(MessengerDuration::new(Duration::from_millis(2500))));
+
+        assert!(message_ttl_header(Duration::from_micros(10)).is_err());
+    }
+}
@@ -18,11 +18,13 @@
 
 use super::HeaderValue;
 use super::message_header::*;
+use super::message_ttl::get_message_ttl;
 use crate::BytesSerializable;
 use crate::MessengerByteSize;
 use crate::Sizeable;
 use crate::error::MessengerError;
 use crate::utils::checksum;
+use crate::utils::duration::MessengerDuration;
 use crate::{HeaderKey, MessengerMessageHeaderView};
 use bytes::{Bytes, BytesMut};
 use std::{collections::HashMap, iter::Iterator};
@@ -98,6 +100,25 @@ impl<'a> MessengerMessageView<'a> {
         }
     }
 
+    /// Returns the time to live set with the `messenger-ttl` user header, if any.
+    pub fn ttl(&self) -> Result<Option<MessengerDuration>, MessengerError> {
+        match self.user_headers_map()? {
+            Some(user_headers) => get_message_ttl(&user_headers),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the timestamp (in microseconds) at which the message expires,
+    /// or `None` if it has no valid time to live.
+    pub fn expires_at(&self) -> Option<u64> {
+        if self.header().user_headers_length() == 0 {
+            return None;
+        }
+
+        let ttl = self.ttl().ok()??;
+        Some(self.header().timestamp().saturating_add(ttl.as_micros()))
+    }
+
     /// Returns the size of the entire message.
     pub fn size(&self) -> usize {
         let header_view = self.header();
@@ -17,11 +17,13 @@
  */
 
 use super::message_header::{MESSENGER_MESSAGE_HEADER_SIZE, MessengerMessageHeader};
+use super::message_ttl::get_message_ttl;
 use super::user_headers::get_user_headers_size;
 use crate::BytesSerializable;
 use crate::Sizeable;
 use crate::error::MessengerError;
 use crate::utils::byte_size::MessengerByteSize;
+use crate::utils::duration::MessengerDuration;
 use crate::utils::timestamp::MessengerTimestamp;
 use crate::{HeaderKey, HeaderValue};
 use bon::bon;
@@ -331,6 +333,37 @@ impl MessengerMessage {
             .is_some_and(|map| map.contains_key(key)))
     }
 
+    /// Gets the time to live set with the [`MESSAGE_TTL_HEADER`](crate::MESSAGE_TTL_HEADER) user header.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(MessengerDuration))` - The message expires after the returned duration
+    /// * `Ok(None)` - The message never expires on its own
+    /// * `Err(MessengerError::InvalidMessageTtl)` - The header is not a positive duration
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use messenger_common::*;
+    /// use std::collections::HashMap;
+    /// use std::time::Duration;
+    ///
+    /// let (key, value) = message_ttl_header(Duration::from_secs(3)).unwrap();
+    /// let message = MessengerMessage::builder()
+    ///     .payload("Hello".into())
+    ///     .user_headers(HashMap::from([(key, value)]))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(message.ttl().unwrap(), Some(MessengerDuration::new_from_secs(3)));
+    /// ```
+    pub fn ttl(&self) -> Result<Option<MessengerDuration>, MessengerError> {
+        match self.user_headers_map()? {
+            Some(user_headers) => get_message_ttl(&user_headers),
+            None => Ok(None),
+        }
+    }
+
     /// Gets the payload as a UTF-8 string, if valid.
     ///
     /// # Returns
@@ -22,6 +22,7 @@ mod index_view;
 mod indexes;
 mod message_header;
 mod message_header_view;
+mod message_ttl;
 mod message_view;
 mod messages_batch;
 pub mod partitioning;
@@ -48,6 +49,7 @@ pub use message_header::{
     MessengerMessageHeader,
 };
 pub use message_header_view::MessengerMessageHeaderView;
+pub use message_ttl::{MESSAGE_TTL_HEADER, get_message_ttl, message_ttl_header};
 pub use message_view::{MessengerMessageView, MessengerMessageViewIterator};
 pub use messages_batch::MessengerMessagesBatch;
 pub use partitioning::Partitioning;
@@ -0,0 +1,74 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::{configs::HttpApiConfig, lag::LagMonitor};
+use axum::{Json, Router, extract::State, routing::get};
+use std::{net::SocketAddr, sync::Arc};
+use tokio::spawn;
+use tracing::{error, info};
+
+const NAME: &str = env!("CARGO_PKG_NAME");
+
+pub async fn init(config: &HttpApiConfig, lag: Arc<LagMonitor>) {
+    if !config.enabled {
+        info!("{NAME} HTTP API is disabled");
+        return;
+    }
+
+    let app = Router::new()
+        .route("/", get(|| async { "Mirror API" }))
+        .route("/health", get(health))
+        .route("/lag", get(lag_report))
+        .with_state(lag);
+
+    let listener = tokio::net::TcpListener::bind(&config.address)
+        .await
+        .unwrap_or_else(|_| panic!("Failed to bind to HTTP address {}", config.address));
+    let address = listener
+        .local_addr()
+        .expect("Failed to get local address for HTTP server");
+    info!("Started {NAME} on: {address}");
+    spawn(async move {
+        if let Err(error) = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        {
+            error!("Failed to start {NAME} server, error: {error}");
+        }
+    });
+}
+
+async fn health(State(lag): State<Arc<LagMonitor>>) -> Json<serde_json::Value> {
+    let report = lag.report();
+    let status = if report.lagging_partitions > 0 {
+        "lagging"
+    } else {
+        "healthy"
+    };
+    Json(serde_json::json!({
+        "status": status,
+        "lagging_partitions": report.lagging_partitions,
+        "total_lag": report.total_lag,
+    }))
+}
+
+async fn lag_report(State(lag): State<Arc<LagMonitor>>) -> Json<crate::lag::LagReport> {
+    Json(lag.report())
+}
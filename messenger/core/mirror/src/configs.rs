@@ -0,0 +1,124 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use crate::error::MirrorError;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MirrorConfig {
+    pub http_api: HttpApiConfig,
+    pub source: ClusterConfig,
+    pub target: ClusterConfig,
+    pub mirror: MirroringConfig,
+    pub state: StateConfig,
+}
+
+impl MirrorConfig {
+    pub fn validate(&self) -> Result<(), MirrorError> {
+        for cluster in [&self.source, &self.target] {
+            if cluster.id.is_empty() || cluster.id.contains(',') {
+                return Err(MirrorError::InvalidConfiguration(format!(
+                    "cluster ID '{}' must be non-empty and cannot contain commas",
+                    cluster.id
+                )));
+            }
+        }
+        if self.source.id == self.target.id {
+            return Err(MirrorError::InvalidConfiguration(format!(
+                "source and target cannot share the cluster ID '{}'",
+                self.source.id
+            )));
+        }
+        if self.mirror.topics.is_empty() {
+            return Err(MirrorError::InvalidConfiguration(
+                "at least one topic pattern must be mirrored".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Connection to one cluster; the ID names it in the mirror path of the
+/// messages it replicates and must be unique across the clusters mirroring
+/// into each other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    pub id: String,
+    pub address: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirroringConfig {
+    /// `stream/topic` patterns of the source topics to mirror, `*` matching
+    /// any part of a stream or topic name
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub rename: Vec<RenameConfig>,
+    /// Consumer groups of the source whose offsets are translated to the target
+    #[serde(default)]
+    pub consumer_groups: Vec<String>,
+    #[serde(default)]
+    pub start_from: StartFrom,
+    pub batch_length: Option<u32>,
+    pub poll_interval: Option<String>,
+    /// How often new topics and partitions are discovered, consumer group
+    /// offsets translated and checkpoints saved
+    pub sync_interval: Option<String>,
+    /// Partition lag in messages above which a warning is logged and the
+    /// health check reports the mirror as lagging
+    pub lag_alert_threshold: Option<u64>,
+}
+
+/// Renames a mirrored topic, e.g. `prod-*/*` to `dr-*/*`; every `*` of
+/// `from` fills the `*` at the same position of `to`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameConfig {
+    pub from: String,
+    pub to: String,
+}
+
+/// Where partitions without a checkpoint start mirroring from
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Display,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum StartFrom {
+    #[default]
+    #[strum(to_string = "earliest")]
+    Earliest,
+    #[strum(to_string = "latest")]
+    Latest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpApiConfig {
+    pub enabled: bool,
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateConfig {
+    pub path: String,
+}
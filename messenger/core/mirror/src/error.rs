@@ -0,0 +1,50 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MirrorError {
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+    #[error("Invalid topic rule: {0}")]
+    InvalidTopicRule(String),
+    #[error("Messenger client error: {0}")]
+    MessengerClient(#[from] messenger::prelude::ClientError),
+    #[error("Messenger error: {0}")]
+    MessengerError(#[from] messenger::prelude::MessengerError),
+    #[error("Missing Messenger credentials for cluster: {0}")]
+    MissingMessengerCredentials(String),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Cannot read checkpoints file: {0}")]
+    CannotReadCheckpoints(String),
+    #[error("Cannot write checkpoints file: {0}")]
+    CannotWriteCheckpoints(String),
+}
+
+impl MirrorError {
+    pub fn as_code(&self) -> &'static str {
+        match self {
+            MirrorError::InvalidConfiguration(_) => "invalid_configuration",
+            MirrorError::InvalidTopicRule(_) => "invalid_configuration",
+            MirrorError::MissingMessengerCredentials(_) => "invalid_configuration",
+            _ => "error",
+        }
+    }
+}
@@ -0,0 +1,143 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use dashmap::DashMap;
+use messenger::prelude::MessengerTimestamp;
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// Progress of one mirrored partition
+#[derive(Debug, Clone, Serialize)]
+pub struct PartitionLag {
+    pub source: String,
+    pub target: String,
+    pub partition_id: u32,
+    /// The next source offset to mirror
+    pub next_offset: u64,
+    /// Messages on the source not mirrored yet
+    pub lag: u64,
+    /// Time between the source and the target receiving the latest mirrored message
+    pub latency_ms: Option<u64>,
+    pub mirrored_messages: u64,
+    /// Messages not mirrored because they came from the target
+    pub skipped_messages: u64,
+    pub updated_at: u64,
+    #[serde(skip)]
+    alerting: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LagReport {
+    pub total_lag: u64,
+    pub max_latency_ms: Option<u64>,
+    pub lagging_partitions: usize,
+    pub partitions: Vec<PartitionLag>,
+}
+
+/// One mirrored batch (or empty poll) of a partition
+#[derive(Debug)]
+pub struct BatchProgress {
+    pub next_offset: u64,
+    pub lag: u64,
+    pub latency_ms: Option<u64>,
+    pub mirrored: u64,
+    pub skipped: u64,
+}
+
+#[derive(Debug)]
+pub struct LagMonitor {
+    partitions: DashMap<String, PartitionLag>,
+    alert_threshold: u64,
+}
+
+impl LagMonitor {
+    pub fn new(alert_threshold: u64) -> Self {
+        Self {
+            partitions: DashMap::new(),
+            alert_threshold,
+        }
+    }
+
+    pub fn record(
+        &self,
+        key: &str,
+        source: &str,
+        target: &str,
+        partition_id: u32,
+        progress: BatchProgress,
+    ) {
+        let mut partition = self
+            .partitions
+            .entry(key.to_owned())
+            .or_insert_with(|| PartitionLag {
+                source: source.to_owned(),
+                target: target.to_owned(),
+                partition_id,
+                next_offset: 0,
+                lag: 0,
+                latency_ms: None,
+                mirrored_messages: 0,
+                skipped_messages: 0,
+                updated_at: 0,
+                alerting: false,
+            });
+        partition.next_offset = progress.next_offset;
+        partition.lag = progress.lag;
+        if progress.latency_ms.is_some() {
+            partition.latency_ms = progress.latency_ms;
+        }
+        partition.mirrored_messages += progress.mirrored;
+        partition.skipped_messages += progress.skipped;
+        partition.updated_at = MessengerTimestamp::now().as_micros();
+
+        let lagging = partition.lag > self.alert_threshold;
+        if lagging && !partition.alerting {
+            warn!(
+                "Mirror of partition: {partition_id} of: {source} to: {target} is lagging by {} messages (threshold: {})",
+                partition.lag, self.alert_threshold
+            );
+        } else if !lagging && partition.alerting {
+            info!(
+                "Mirror of partition: {partition_id} of: {source} to: {target} caught up, lag: {} messages",
+                partition.lag
+            );
+        }
+        partition.alerting = lagging;
+    }
+
+    pub fn report(&self) -> LagReport {
+        let mut partitions = self
+            .partitions
+            .iter()
+            .map(|partition| partition.value().clone())
+            .collect::<Vec<_>>();
+        partitions.sort_by(|a, b| (&a.source, a.partition_id).cmp(&(&b.source, b.partition_id)));
+        LagReport {
+            total_lag: partitions.iter().map(|partition| partition.lag).sum(),
+            max_latency_ms: partitions
+                .iter()
+                .filter_map(|partition| partition.latency_ms)
+                .max(),
+            lagging_partitions: partitions
+                .iter()
+                .filter(|partition| partition.alerting)
+                .count(),
+            partitions,
+        }
+    }
+}
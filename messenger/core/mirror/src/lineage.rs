@@ -0,0 +1,98 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::{collections::HashMap, str::FromStr};
+
+use messenger::prelude::{HeaderKey, HeaderValue, MessengerError, MessengerMessage};
+
+/// User header listing, comma separated, the clusters a message was
+/// mirrored from, oldest first.
+pub const MIRROR_PATH_HEADER: &str = "messenger-mirror-path";
+
+/// Whether the message was already mirrored out of `cluster`, in which case
+/// mirroring it into `cluster` again would start a loop between clusters
+/// that mirror each other.
+pub fn passed_through(message: &MessengerMessage, cluster: &str) -> Result<bool, MessengerError> {
+    let key = HeaderKey::new(MIRROR_PATH_HEADER)?;
+    let Some(path) = message.get_user_header(&key)? else {
+        return Ok(false);
+    };
+    Ok(path.as_str()?.split(',').any(|hop| hop == cluster))
+}
+
+/// The user headers of a message mirrored out of `cluster`, with the
+/// cluster appended to its mirror path.
+pub fn mirrored_headers(
+    message: &MessengerMessage,
+    cluster: &str,
+) -> Result<HashMap<HeaderKey, HeaderValue>, MessengerError> {
+    let key = HeaderKey::new(MIRROR_PATH_HEADER)?;
+    let mut headers = message.user_headers_map()?.unwrap_or_default();
+    let path = match headers.get(&key) {
+        Some(path) => format!("{},{cluster}", path.as_str()?),
+        None => cluster.to_owned(),
+    };
+    headers.insert(key, HeaderValue::from_str(&path)?);
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(path: Option<&str>) -> MessengerMessage {
+        let mut headers = HashMap::from([(
+            HeaderKey::new("content-type").unwrap(),
+            HeaderValue::from_str("text/plain").unwrap(),
+        )]);
+        if let Some(path) = path {
+            headers.insert(
+                HeaderKey::new(MIRROR_PATH_HEADER).unwrap(),
+                HeaderValue::from_str(path).unwrap(),
+            );
+        }
+        MessengerMessage::builder()
+            .payload("payload".into())
+            .user_headers(headers)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn should_detect_messages_mirrored_out_of_a_cluster() {
+        assert!(!passed_through(&message(None), "eu").unwrap());
+        assert!(passed_through(&message(Some("us,eu")), "eu").unwrap());
+        assert!(!passed_through(&message(Some("us,eu-west")), "eu").unwrap());
+    }
+
+    #[test]
+    fn should_append_the_source_cluster_to_the_path() {
+        let headers = mirrored_headers(&message(None), "eu").unwrap();
+        let key = HeaderKey::new(MIRROR_PATH_HEADER).unwrap();
+        assert_eq!(headers[&key].as_str().unwrap(), "eu");
+        assert_eq!(
+            headers[&HeaderKey::new("content-type").unwrap()]
+                .as_str()
+                .unwrap(),
+            "text/plain"
+        );
+
+        let headers = mirrored_headers(&message(Some("us")), "eu").unwrap();
+        assert_eq!(headers[&key].as_str().unwrap(), "us,eu");
+    }
+}
@@ -0,0 +1,131 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use config::{Config, Environment, File};
+use configs::MirrorConfig;
+use dotenvy::dotenv;
+use error::MirrorError;
+use figlet_rs::FIGfont;
+use lag::LagMonitor;
+use messenger::prelude::Client;
+use mimalloc::MiMalloc;
+use mirror::Mirror;
+use offsets::CheckpointStore;
+use std::{env, sync::Arc};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+use tracing_subscriber::{EnvFilter, Registry, layer::SubscriberExt, util::SubscriberInitExt};
+
+mod api;
+pub(crate) mod configs;
+pub(crate) mod error;
+mod lag;
+mod lineage;
+mod mirror;
+mod offsets;
+mod rules;
+mod stream;
+
+#[global_allocator]
+static GLOBAL: MiMalloc = MiMalloc;
+
+#[tokio::main]
+async fn main() -> Result<(), MirrorError> {
+    let standard_font = FIGfont::standard().unwrap();
+    let figure = standard_font.convert("Messenger Mirror");
+    println!("{}", figure.unwrap());
+
+    if let Ok(env_path) = std::env::var("MESSENGER_MIRROR_ENV_PATH") {
+        if dotenvy::from_path(&env_path).is_ok() {
+            println!("Loaded environment variables from path: {env_path}");
+        }
+    } else if let Ok(path) = dotenv() {
+        println!(
+            "Loaded environment variables from .env file at path: {}",
+            path.display()
+        );
+    }
+
+    Registry::default()
+        .with(tracing_subscriber::fmt::layer())
+        .with(EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new("INFO")))
+        .init();
+
+    let config_path =
+        env::var("MESSENGER_MIRROR_CONFIG_PATH").unwrap_or_else(|_| "config".to_string());
+    info!("Starting Messenger Mirror, loading configuration from: {config_path}...");
+    let builder = Config::builder()
+        .add_source(File::with_name(&config_path))
+        .add_source(Environment::with_prefix("MESSENGER_MIRROR").separator("_"));
+
+    let config: MirrorConfig = builder
+        .build()
+        .expect("Failed to build mirror config")
+        .try_deserialize()
+        .expect("Failed to deserialize mirror config");
+    config.validate()?;
+
+    std::fs::create_dir_all(&config.state.path).expect("Failed to create state directory");
+    info!("State will be stored in: {}", config.state.path);
+
+    let source = Arc::new(stream::create_client(&config.source).await?);
+    let target = Arc::new(stream::create_client(&config.target).await?);
+    let checkpoints = Arc::new(CheckpointStore::load(&config.state.path).await?);
+    let lag = Arc::new(LagMonitor::new(
+        config.mirror.lag_alert_threshold.unwrap_or(10_000),
+    ));
+    api::init(&config.http_api, lag.clone()).await;
+
+    let shutdown = CancellationToken::new();
+    let mirror = Mirror::new(
+        &config,
+        source.clone(),
+        target.clone(),
+        checkpoints,
+        lag,
+        shutdown.clone(),
+    )?;
+    let mirror = tokio::spawn(mirror.run());
+
+    #[cfg(unix)]
+    let (mut ctrl_c, mut sigterm) = {
+        use tokio::signal::unix::{SignalKind, signal};
+        (
+            signal(SignalKind::interrupt()).expect("Failed to create SIGINT signal"),
+            signal(SignalKind::terminate()).expect("Failed to create SIGTERM signal"),
+        )
+    };
+
+    #[cfg(unix)]
+    tokio::select! {
+        _ = ctrl_c.recv() => {
+            info!("Received SIGINT. Shutting down mirror...");
+        },
+        _ = sigterm.recv() => {
+            info!("Received SIGTERM. Shutting down mirror...");
+        }
+    }
+
+    shutdown.cancel();
+    let _ = mirror.await;
+    source.shutdown().await?;
+    target.shutdown().await?;
+
+    info!("Mirror shutdown complete.");
+    Ok(())
+}
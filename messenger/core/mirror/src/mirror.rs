@@ -0,0 +1,594 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+
+use messenger::prelude::{
+    Consumer, ConsumerGroupClient, ConsumerOffsetClient, Identifier, MessageClient,
+    MessengerClient, MessengerDuration, MessengerMessage, MessengerTimestamp, PartitionClient,
+    Partitioning, PollingStrategy, StreamClient, Topic, TopicClient,
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    configs::{MirrorConfig, StartFrom},
+    error::MirrorError,
+    lag::{BatchProgress, LagMonitor},
+    lineage,
+    offsets::{CheckpointStore, OffsetSync},
+    rules::{TopicName, TopicRules},
+};
+
+const MIRROR_CONSUMER: &str = "messenger-mirror";
+
+struct MirrorContext {
+    source: Arc<MessengerClient>,
+    target: Arc<MessengerClient>,
+    source_cluster: String,
+    target_cluster: String,
+    rules: TopicRules,
+    consumer_groups: Vec<String>,
+    start_from: StartFrom,
+    batch_length: u32,
+    poll_interval: Duration,
+    sync_interval: Duration,
+    checkpoints: Arc<CheckpointStore>,
+    lag: Arc<LagMonitor>,
+}
+
+/// A source topic and the target topic it is mirrored to
+#[derive(Debug, Clone)]
+struct Route {
+    source: TopicName,
+    target: TopicName,
+    partitions_count: u32,
+}
+
+/// Mirrors the selected topics of the source cluster into the target one,
+/// one task per partition, and periodically discovers new topics and
+/// partitions, translates consumer group offsets and saves checkpoints.
+///
+/// Delivery is at least once: after a restart, the messages mirrored since
+/// the last saved checkpoint are mirrored again.
+pub struct Mirror {
+    context: Arc<MirrorContext>,
+    routes: HashMap<TopicName, Route>,
+    tasks: Vec<JoinHandle<()>>,
+    shutdown: CancellationToken,
+}
+
+impl Mirror {
+    pub fn new(
+        config: &MirrorConfig,
+        source: Arc<MessengerClient>,
+        target: Arc<MessengerClient>,
+        checkpoints: Arc<CheckpointStore>,
+        lag: Arc<LagMonitor>,
+        shutdown: CancellationToken,
+    ) -> Result<Self, MirrorError> {
+        let mirror = &config.mirror;
+        let duration = |value: &Option<String>, default: &str| {
+            let value = value.as_deref().unwrap_or(default);
+            MessengerDuration::from_str(value)
+                .map(|duration| duration.get_duration())
+                .map_err(|error| {
+                    MirrorError::InvalidConfiguration(format!(
+                        "invalid duration '{value}': {error}"
+                    ))
+                })
+        };
+
+        let context = MirrorContext {
+            source,
+            target,
+            source_cluster: config.source.id.to_owned(),
+            target_cluster: config.target.id.to_owned(),
+            rules: TopicRules::new(mirror)?,
+            consumer_groups: mirror.consumer_groups.clone(),
+            start_from: mirror.start_from,
+            batch_length: mirror.batch_length.unwrap_or(1000),
+            poll_interval: duration(&mirror.poll_interval, "5ms")?,
+            sync_interval: duration(&mirror.sync_interval, "10s")?,
+            checkpoints,
+            lag,
+        };
+
+        Ok(Self {
+            context: Arc::new(context),
+            routes: HashMap::new(),
+            tasks: Vec::new(),
+            shutdown,
+        })
+    }
+
+    pub async fn run(mut self) {
+        info!(
+            "Mirroring cluster: {} to cluster: {}",
+            self.context.source_cluster, self.context.target_cluster
+        );
+        loop {
+            if let Err(error) = self.discover().await {
+                error!("Failed to discover the topics to mirror. {error}");
+            }
+            self.translate_consumer_groups().await;
+            if let Err(error) = self.context.checkpoints.save().await {
+                error!("Failed to save mirror checkpoints. {error}");
+            }
+            if pause(&self.shutdown, self.context.sync_interval).await {
+                break;
+            }
+        }
+
+        info!("Stopping {} partition mirrors...", self.tasks.len());
+        for task in self.tasks.drain(..) {
+            let _ = task.await;
+        }
+        self.translate_consumer_groups().await;
+        if let Err(error) = self.context.checkpoints.save().await {
+            error!("Failed to save mirror checkpoints. {error}");
+        }
+        info!("Mirror stopped.");
+    }
+
+    /// Start mirroring the selected source topics and partitions that are
+    /// not mirrored yet, creating their target streams and topics if needed.
+    async fn discover(&mut self) -> Result<(), MirrorError> {
+        let context = Arc::clone(&self.context);
+        for stream in context.source.get_streams().await? {
+            let stream_id = Identifier::numeric(stream.id)?;
+            for topic in context.source.get_topics(&stream_id).await? {
+                let source = TopicName::new(&stream.name, &topic.name);
+                if !context.rules.selects(&source) {
+                    continue;
+                }
+
+                let mirrored = self
+                    .routes
+                    .get(&source)
+                    .map(|route| route.partitions_count)
+                    .unwrap_or_default();
+                if mirrored >= topic.partitions_count {
+                    continue;
+                }
+
+                let target = context.rules.target_of(&source);
+                if let Err(error) = ensure_target_topic(&context, &target, &topic).await {
+                    error!(
+                        "Failed to prepare topic: {target} to mirror topic: {source} into. {error}"
+                    );
+                    continue;
+                }
+
+                if mirrored == 0 {
+                    info!("Mirroring topic: {source} to topic: {target}");
+                }
+                let route = Route {
+                    source: source.clone(),
+                    target,
+                    partitions_count: topic.partitions_count,
+                };
+                for partition_id in mirrored + 1..=topic.partitions_count {
+                    self.tasks.push(tokio::spawn(mirror_partition(
+                        Arc::clone(&context),
+                        route.clone(),
+                        partition_id,
+                        self.shutdown.clone(),
+                    )));
+                }
+                self.routes.insert(source, route);
+            }
+        }
+        Ok(())
+    }
+
+    async fn translate_consumer_groups(&self) {
+        for route in self.routes.values() {
+            for group in &self.context.consumer_groups {
+                if let Err(error) = translate_consumer_group(&self.context, route, group).await {
+                    warn!(
+                        "Failed to translate offsets of consumer group: {group} from topic: {} to topic: {}. {error}",
+                        route.source, route.target
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn identifiers(name: &TopicName) -> Result<(Identifier, Identifier), MirrorError> {
+    Ok((
+        Identifier::named(&name.stream)?,
+        Identifier::named(&name.topic)?,
+    ))
+}
+
+fn partition_key(source: &TopicName, partition_id: u32) -> String {
+    format!("{source}/{partition_id}")
+}
+
+/// Wait for the interval, true if the mirror was shut down meanwhile
+async fn pause(shutdown: &CancellationToken, interval: Duration) -> bool {
+    tokio::select! {
+        _ = shutdown.cancelled() => true,
+        _ = tokio::time::sleep(interval) => false,
+    }
+}
+
+/// Create the target stream and topic, with the settings and at least the
+/// partitions of the source topic, so every source partition is mirrored
+/// into the partition with the same ID.
+async fn ensure_target_topic(
+    context: &MirrorContext,
+    target: &TopicName,
+    source: &Topic,
+) -> Result<(), MirrorError> {
+    let (stream_id, topic_id) = identifiers(target)?;
+    if context.target.get_stream(&stream_id).await?.is_none() {
+        context.target.create_stream(&target.stream, None).await?;
+        info!("Created stream: {} on the target cluster", target.stream);
+    }
+
+    match context.target.get_topic(&stream_id, &topic_id).await? {
+        None => {
+            context
+                .target
+                .create_topic(
+                    &stream_id,
+                    &target.topic,
+                    source.partitions_count,
+                    source.compression_algorithm,
+                    None,
+                    None,
+                    source.message_expiry,
+                    source.max_topic_size,
+                )
+                .await?;
+            info!(
+                "Created topic: {target} with {} partitions on the target cluster",
+                source.partitions_count
+            );
+        }
+        Some(topic) if topic.partitions_count < source.partitions_count => {
+            let missing = source.partitions_count - topic.partitions_count;
+            context
+                .target
+                .create_partitions(&stream_id, &topic_id, missing)
+                .await?;
+            info!("Added {missing} partitions to topic: {target} on the target cluster");
+        }
+        Some(_) => {}
+    }
+    Ok(())
+}
+
+async fn mirror_partition(
+    context: Arc<MirrorContext>,
+    route: Route,
+    partition_id: u32,
+    shutdown: CancellationToken,
+) {
+    let key = partition_key(&route.source, partition_id);
+    let resolve = || -> Result<_, MirrorError> {
+        Ok((
+            identifiers(&route.source)?,
+            identifiers(&route.target)?,
+            Consumer::new(Identifier::named(MIRROR_CONSUMER)?),
+        ))
+    };
+    let ((source_stream, source_topic), (target_stream, target_topic), consumer) = match resolve() {
+        Ok(resolved) => resolved,
+        Err(error) => {
+            error!(
+                "Cannot mirror partition: {partition_id} of topic: {}. {error}",
+                route.source
+            );
+            return;
+        }
+    };
+
+    let mut next_offset = match context.checkpoints.get(&key) {
+        Some(checkpoint) => checkpoint.next_offset,
+        None if context.start_from == StartFrom::Latest => loop {
+            match latest_offset(&context, &source_stream, &source_topic, partition_id).await {
+                Ok(offset) => break offset,
+                Err(error) => {
+                    warn!(
+                        "Failed to get the latest offset of partition: {partition_id} of topic: {}. {error}",
+                        route.source
+                    );
+                    if pause(&shutdown, context.sync_interval).await {
+                        return;
+                    }
+                }
+            }
+        },
+        None => 0,
+    };
+    info!(
+        "Mirroring partition: {partition_id} of topic: {} from offset: {next_offset}",
+        route.source
+    );
+
+    let source_name = route.source.to_string();
+    let target_name = route.target.to_string();
+    loop {
+        let polled = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            polled = context.source.poll_messages(
+                &source_stream,
+                &source_topic,
+                Some(partition_id),
+                &consumer,
+                &PollingStrategy::offset(next_offset),
+                context.batch_length,
+                false,
+            ) => polled,
+        };
+        let polled = match polled {
+            Ok(polled) => polled,
+            Err(error) => {
+                warn!("Failed to poll partition: {partition_id} of topic: {source_name}. {error}");
+                if pause(&shutdown, context.poll_interval).await {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        let Some(last_offset) = polled.messages.last().map(|message| message.header.offset) else {
+            let progress = BatchProgress {
+                next_offset,
+                lag: 0,
+                latency_ms: None,
+                mirrored: 0,
+                skipped: 0,
+            };
+            context
+                .lag
+                .record(&key, &source_name, &target_name, partition_id, progress);
+            if pause(&shutdown, context.poll_interval).await {
+                break;
+            }
+            continue;
+        };
+
+        let lag = polled.current_offset.saturating_sub(last_offset);
+        let batch = mirror_batch(
+            &context,
+            &target_stream,
+            &target_topic,
+            partition_id,
+            polled.messages,
+        );
+        match batch.await {
+            Ok(batch) => {
+                next_offset = last_offset + 1;
+                context.checkpoints.advance(&key, next_offset, batch.sync);
+                let progress = BatchProgress {
+                    next_offset,
+                    lag,
+                    latency_ms: batch.latency_ms,
+                    mirrored: batch.mirrored,
+                    skipped: batch.skipped,
+                };
+                context
+                    .lag
+                    .record(&key, &source_name, &target_name, partition_id, progress);
+            }
+            Err(error) => {
+                error!(
+                    "Failed to mirror messages from offset: {next_offset} of partition: {partition_id} of topic: {source_name} to topic: {target_name}, retrying. {error}"
+                );
+                if pause(&shutdown, context.poll_interval).await {
+                    break;
+                }
+            }
+        }
+    }
+    debug!("Stopped mirroring partition: {partition_id} of topic: {source_name}");
+}
+
+async fn latest_offset(
+    context: &MirrorContext,
+    stream_id: &Identifier,
+    topic_id: &Identifier,
+    partition_id: u32,
+) -> Result<u64, MirrorError> {
+    let partition = context
+        .source
+        .get_topic(stream_id, topic_id)
+        .await?
+        .and_then(|topic| {
+            topic
+                .partitions
+                .into_iter()
+                .find(|partition| partition.id == partition_id)
+        });
+    Ok(match partition {
+        Some(partition) if partition.messages_count > 0 => partition.current_offset + 1,
+        _ => 0,
+    })
+}
+
+struct MirroredBatch {
+    sync: Option<OffsetSync>,
+    mirrored: u64,
+    skipped: u64,
+    latency_ms: Option<u64>,
+}
+
+/// Send the messages to the same partition of the target topic, keeping
+/// their IDs and origin timestamps and extending their mirror path, except
+/// for those that came from the target cluster in the first place.
+async fn mirror_batch(
+    context: &MirrorContext,
+    stream_id: &Identifier,
+    topic_id: &Identifier,
+    partition_id: u32,
+    messages: Vec<MessengerMessage>,
+) -> Result<MirroredBatch, MirrorError> {
+    let mut batch = Vec::with_capacity(messages.len());
+    let mut skipped = 0;
+    let mut last_mirrored = None;
+    for message in messages {
+        if lineage::passed_through(&message, &context.target_cluster)? {
+            skipped += 1;
+            continue;
+        }
+
+        let headers = lineage::mirrored_headers(&message, &context.source_cluster)?;
+        let mut mirrored = MessengerMessage::builder()
+            .id(message.header.id)
+            .payload(message.payload)
+            .user_headers(headers)
+            .build()?;
+        mirrored.header.origin_timestamp = message.header.origin_timestamp;
+        last_mirrored = Some((message.header.offset, message.header.timestamp));
+        batch.push(mirrored);
+    }
+
+    let Some((source_offset, timestamp)) = last_mirrored else {
+        return Ok(MirroredBatch {
+            sync: None,
+            mirrored: 0,
+            skipped,
+            latency_ms: None,
+        });
+    };
+
+    context
+        .target
+        .send_messages(
+            stream_id,
+            topic_id,
+            &Partitioning::partition_id(partition_id),
+            &mut batch,
+        )
+        .await?;
+
+    // The mirror is the only producer of the target partition, so its
+    // current offset is that of the last message just sent
+    let target_offset = context
+        .target
+        .get_topic(stream_id, topic_id)
+        .await?
+        .and_then(|topic| {
+            topic
+                .partitions
+                .into_iter()
+                .find(|partition| partition.id == partition_id)
+        })
+        .map(|partition| partition.current_offset);
+    let latency_ms = MessengerTimestamp::now()
+        .as_micros()
+        .saturating_sub(timestamp)
+        / 1000;
+
+    Ok(MirroredBatch {
+        sync: target_offset.map(|target| OffsetSync {
+            source: source_offset,
+            target,
+        }),
+        mirrored: batch.len() as u64,
+        skipped,
+        latency_ms: Some(latency_ms),
+    })
+}
+
+/// Store the translated offsets of a source consumer group on the target,
+/// so its consumers can fail over and resume there. Offsets only move
+/// forward, never rewinding a group that already consumes on the target.
+async fn translate_consumer_group(
+    context: &MirrorContext,
+    route: &Route,
+    group: &str,
+) -> Result<(), MirrorError> {
+    let (source_stream, source_topic) = identifiers(&route.source)?;
+    let (target_stream, target_topic) = identifiers(&route.target)?;
+    let group_id = Identifier::named(group)?;
+    if context
+        .source
+        .get_consumer_group(&source_stream, &source_topic, &group_id)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    if context
+        .target
+        .get_consumer_group(&target_stream, &target_topic, &group_id)
+        .await?
+        .is_none()
+    {
+        context
+            .target
+            .create_consumer_group(&target_stream, &target_topic, group, None)
+            .await?;
+        info!(
+            "Created consumer group: {group} for topic: {} on the target cluster",
+            route.target
+        );
+    }
+
+    let consumer = Consumer::group(group_id);
+    for partition_id in 1..=route.partitions_count {
+        let Some(source_offset) = context
+            .source
+            .get_consumer_offset(&consumer, &source_stream, &source_topic, Some(partition_id))
+            .await?
+        else {
+            continue;
+        };
+
+        let key = partition_key(&route.source, partition_id);
+        let Some(target_offset) = context
+            .checkpoints
+            .translate(&key, source_offset.stored_offset)
+        else {
+            continue;
+        };
+
+        let stored = context
+            .target
+            .get_consumer_offset(&consumer, &target_stream, &target_topic, Some(partition_id))
+            .await?
+            .map(|offset| offset.stored_offset);
+        if stored.is_some_and(|stored| stored >= target_offset) {
+            continue;
+        }
+
+        context
+            .target
+            .store_consumer_offset(
+                &consumer,
+                &target_stream,
+                &target_topic,
+                Some(partition_id),
+                target_offset,
+            )
+            .await?;
+        debug!(
+            "Translated offset: {} of consumer group: {group} for partition: {partition_id} of topic: {} to offset: {target_offset} of topic: {}",
+            source_offset.stored_offset, route.source, route.target
+        );
+    }
+    Ok(())
+}
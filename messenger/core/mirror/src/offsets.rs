@@ -0,0 +1,258 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::MirrorError;
+
+const CHECKPOINTS_FILE: &str = "mirror_checkpoints.json";
+const MAX_OFFSET_SYNCS: usize = 256;
+
+/// A source offset and the offset its message got on the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffsetSync {
+    pub source: u64,
+    pub target: u64,
+}
+
+/// How far a source partition has been mirrored and how its offsets map
+/// onto the target partition.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionCheckpoint {
+    /// The next source offset to mirror
+    pub next_offset: u64,
+    /// Ascending by both offsets; the recent ones are kept densely and the
+    /// older ones ever more sparsely.
+    pub syncs: Vec<OffsetSync>,
+}
+
+impl PartitionCheckpoint {
+    pub fn record(&mut self, sync: OffsetSync) {
+        if let Some(last) = self.syncs.last()
+            && (sync.source <= last.source || sync.target < last.target)
+        {
+            return;
+        }
+
+        self.syncs.push(sync);
+        if self.syncs.len() > MAX_OFFSET_SYNCS {
+            let older = self.syncs.len() / 2;
+            let mut index = 0;
+            self.syncs.retain(|_| {
+                let keep = index >= older || index % 2 == 0;
+                index += 1;
+                keep
+            });
+        }
+    }
+
+    /// The target offset a consumer that has processed `source_offset` can
+    /// resume from. Between two syncs the offsets no longer map one to one
+    /// (skipped or expired messages), so the nearest sync at or below is
+    /// used and the consumer reprocesses a few messages rather than skip
+    /// any. Offsets older than every sync cannot be translated.
+    pub fn translate(&self, source_offset: u64) -> Option<u64> {
+        let index = self
+            .syncs
+            .partition_point(|sync| sync.source <= source_offset);
+        index.checked_sub(1).map(|index| self.syncs[index].target)
+    }
+}
+
+/// Checkpoints of all the mirrored partitions, persisted as one JSON file
+/// in the state directory.
+#[derive(Debug)]
+pub struct CheckpointStore {
+    path: PathBuf,
+    checkpoints: Mutex<HashMap<String, PartitionCheckpoint>>,
+}
+
+impl CheckpointStore {
+    pub async fn load(directory: &str) -> Result<Self, MirrorError> {
+        let path = Path::new(directory).join(CHECKPOINTS_FILE);
+        let checkpoints = match tokio::fs::read(&path).await {
+            Ok(content) => {
+                let checkpoints: HashMap<String, PartitionCheckpoint> =
+                    serde_json::from_slice(&content)?;
+                info!(
+                    "Loaded checkpoints of {} partitions from: {}",
+                    checkpoints.len(),
+                    path.display()
+                );
+                checkpoints
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => {
+                return Err(MirrorError::CannotReadCheckpoints(format!(
+                    "{}: {error}",
+                    path.display()
+                )));
+            }
+        };
+
+        Ok(Self {
+            path,
+            checkpoints: Mutex::new(checkpoints),
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<PartitionCheckpoint> {
+        self.checkpoints.lock().unwrap().get(key).cloned()
+    }
+
+    /// Advance a partition past a mirrored batch
+    pub fn advance(&self, key: &str, next_offset: u64, sync: Option<OffsetSync>) {
+        let mut checkpoints = self.checkpoints.lock().unwrap();
+        let checkpoint = checkpoints.entry(key.to_owned()).or_default();
+        checkpoint.next_offset = next_offset;
+        if let Some(sync) = sync {
+            checkpoint.record(sync);
+        }
+    }
+
+    pub fn translate(&self, key: &str, source_offset: u64) -> Option<u64> {
+        self.checkpoints
+            .lock()
+            .unwrap()
+            .get(key)
+            .and_then(|checkpoint| checkpoint.translate(source_offset))
+    }
+
+    /// Write the checkpoints to a temporary file and move it in place, so a
+    /// crash never leaves a torn file behind.
+    pub async fn save(&self) -> Result<(), MirrorError> {
+        let content = {
+            let checkpoints = self.checkpoints.lock().unwrap();
+            serde_json::to_vec(&*checkpoints)?
+        };
+        let temporary = self.path.with_extension("json.tmp");
+        let write_error = |error: std::io::Error| {
+            MirrorError::CannotWriteCheckpoints(format!("{}: {error}", self.path.display()))
+        };
+        tokio::fs::write(&temporary, content)
+            .await
+            .map_err(write_error)?;
+        tokio::fs::rename(&temporary, &self.path)
+            .await
+            .map_err(write_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(syncs: &[(u64, u64)]) -> PartitionCheckpoint {
+        let mut checkpoint = PartitionCheckpoint::default();
+        for &(source, target) in syncs {
+            checkpoint.record(OffsetSync { source, target });
+        }
+        checkpoint
+    }
+
+    #[test]
+    fn should_translate_to_the_nearest_sync_at_or_below() {
+        let checkpoint = checkpoint(&[(9, 4), (19, 14), (29, 20)]);
+
+        assert_eq!(checkpoint.translate(5), None);
+        assert_eq!(checkpoint.translate(9), Some(4));
+        assert_eq!(checkpoint.translate(15), Some(4));
+        assert_eq!(checkpoint.translate(19), Some(14));
+        assert_eq!(checkpoint.translate(100), Some(20));
+    }
+
+    #[test]
+    fn should_ignore_syncs_that_go_backwards() {
+        let checkpoint = checkpoint(&[(10, 10), (5, 12), (20, 8), (20, 20), (20, 21)]);
+
+        assert_eq!(
+            checkpoint.syncs,
+            vec![
+                OffsetSync {
+                    source: 10,
+                    target: 10
+                },
+                OffsetSync {
+                    source: 20,
+                    target: 20
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn should_thin_out_old_syncs() {
+        let syncs = (0..1000).map(|offset| (offset, offset)).collect::<Vec<_>>();
+        let checkpoint = checkpoint(&syncs);
+
+        assert!(checkpoint.syncs.len() <= MAX_OFFSET_SYNCS);
+        assert_eq!(
+            checkpoint.syncs.last(),
+            Some(&OffsetSync {
+                source: 999,
+                target: 999
+            })
+        );
+        assert_eq!(
+            checkpoint.syncs.first(),
+            Some(&OffsetSync {
+                source: 0,
+                target: 0
+            })
+        );
+        assert!(
+            checkpoint
+                .syncs
+                .windows(2)
+                .all(|pair| pair[0].source < pair[1].source)
+        );
+        assert_eq!(checkpoint.translate(998), Some(998));
+    }
+
+    #[tokio::test]
+    async fn should_persist_checkpoints() {
+        let directory =
+            std::env::temp_dir().join(format!("messenger-mirror-{}", std::process::id()));
+        tokio::fs::create_dir_all(&directory).await.unwrap();
+        let directory = directory.to_str().unwrap().to_owned();
+
+        let store = CheckpointStore::load(&directory).await.unwrap();
+        store.advance(
+            "prod/orders/1",
+            42,
+            Some(OffsetSync {
+                source: 41,
+                target: 7,
+            }),
+        );
+        store.save().await.unwrap();
+
+        let store = CheckpointStore::load(&directory).await.unwrap();
+        assert_eq!(store.get("prod/orders/1").unwrap().next_offset, 42);
+        assert_eq!(store.translate("prod/orders/1", 50), Some(7));
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::fmt::{Display, Formatter};
+
+use crate::{configs::MirroringConfig, error::MirrorError};
+
+/// A topic by the names of its stream and itself
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TopicName {
+    pub stream: String,
+    pub topic: String,
+}
+
+impl TopicName {
+    pub fn new(stream: &str, topic: &str) -> Self {
+        Self {
+            stream: stream.to_owned(),
+            topic: topic.to_owned(),
+        }
+    }
+}
+
+impl Display for TopicName {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}/{}", self.stream, self.topic)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RenameRule {
+    from: String,
+    to: String,
+}
+
+/// Which source topics are mirrored and what they are called on the target
+#[derive(Debug, Clone)]
+pub struct TopicRules {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    rename: Vec<RenameRule>,
+}
+
+impl TopicRules {
+    pub fn new(config: &MirroringConfig) -> Result<Self, MirrorError> {
+        for pattern in config.topics.iter().chain(&config.exclude) {
+            validate_pattern(pattern)?;
+        }
+        let mut rename = Vec::with_capacity(config.rename.len());
+        for rule in &config.rename {
+            validate_pattern(&rule.from)?;
+            validate_pattern(&rule.to)?;
+            if rule.from.matches('*').count() != rule.to.matches('*').count() {
+                return Err(MirrorError::InvalidTopicRule(format!(
+                    "rename from '{}' to '{}' must use the same number of wildcards",
+                    rule.from, rule.to
+                )));
+            }
+            rename.push(RenameRule {
+                from: rule.from.to_owned(),
+                to: rule.to.to_owned(),
+            });
+        }
+
+        Ok(Self {
+            include: config.topics.clone(),
+            exclude: config.exclude.clone(),
+            rename,
+        })
+    }
+
+    pub fn selects(&self, name: &TopicName) -> bool {
+        let name = name.to_string();
+        self.include
+            .iter()
+            .any(|pattern| captures(pattern, &name).is_some())
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| captures(pattern, &name).is_some())
+    }
+
+    /// The name of the topic on the target, from the first rename rule that
+    /// matches or the source name when none does.
+    pub fn target_of(&self, name: &TopicName) -> TopicName {
+        let source = name.to_string();
+        for rule in &self.rename {
+            let Some(captures) = captures(&rule.from, &source) else {
+                continue;
+            };
+            let mut captures = captures.into_iter();
+            let renamed = rule.to.split('*').enumerate().fold(
+                String::new(),
+                |mut renamed, (index, literal)| {
+                    if index > 0 {
+                        renamed.push_str(&captures.next().unwrap_or_default());
+                    }
+                    renamed.push_str(literal);
+                    renamed
+                },
+            );
+            if let Some((stream, topic)) = renamed.split_once('/')
+                && !stream.is_empty()
+                && !topic.is_empty()
+            {
+                return TopicName::new(stream, topic);
+            }
+        }
+        name.clone()
+    }
+}
+
+fn validate_pattern(pattern: &str) -> Result<(), MirrorError> {
+    match pattern.split_once('/') {
+        Some((stream, topic))
+            if !stream.is_empty() && !topic.is_empty() && !topic.contains('/') =>
+        {
+            Ok(())
+        }
+        _ => Err(MirrorError::InvalidTopicRule(format!(
+            "'{pattern}' must have the form 'stream/topic'"
+        ))),
+    }
+}
+
+/// The parts of `name` matched by the wildcards of `pattern`, if it matches.
+/// A wildcard never spans the `/` between the stream and the topic.
+fn captures(pattern: &str, name: &str) -> Option<Vec<String>> {
+    fn matches(pattern: &[char], name: &[char], captures: &mut Vec<String>) -> bool {
+        match pattern.first() {
+            None => name.is_empty(),
+            Some('*') => {
+                for length in 0..=name.len() {
+                    if length > 0 && name[length - 1] == '/' {
+                        break;
+                    }
+                    captures.push(name[..length].iter().collect());
+                    if matches(&pattern[1..], &name[length..], captures) {
+                        return true;
+                    }
+                    captures.pop();
+                }
+                false
+            }
+            Some(char) => {
+                name.first() == Some(char) && matches(&pattern[1..], &name[1..], captures)
+            }
+        }
+    }
+
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let mut captures = Vec::new();
+    matches(&pattern, &name, &mut captures).then_some(captures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::RenameConfig;
+
+    fn config(topics: &[&str], exclude: &[&str], rename: &[(&str, &str)]) -> MirroringConfig {
+        MirroringConfig {
+            topics: topics.iter().map(|pattern| pattern.to_string()).collect(),
+            exclude: exclude.iter().map(|pattern| pattern.to_string()).collect(),
+            rename: rename
+                .iter()
+                .map(|(from, to)| RenameConfig {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                })
+                .collect(),
+            consumer_groups: vec![],
+            start_from: Default::default(),
+            batch_length: None,
+            poll_interval: None,
+            sync_interval: None,
+            lag_alert_threshold: None,
+        }
+    }
+
+    #[test]
+    fn should_select_included_topics_that_are_not_excluded() {
+        let rules = TopicRules::new(&config(&["prod-*/*"], &["*/internal-*"], &[])).unwrap();
+
+        assert!(rules.selects(&TopicName::new("prod-eu", "orders")));
+        assert!(!rules.selects(&TopicName::new("prod-eu", "internal-audit")));
+        assert!(!rules.selects(&TopicName::new("staging", "orders")));
+    }
+
+    #[test]
+    fn wildcards_should_not_span_stream_and_topic() {
+        let rules = TopicRules::new(&config(&["prod*"], &[], &[]));
+        assert!(rules.is_err());
+
+        let rules = TopicRules::new(&config(&["prod/*"], &[], &[])).unwrap();
+        assert!(rules.selects(&TopicName::new("prod", "orders")));
+        assert!(!rules.selects(&TopicName::new("prod/eu", "orders")));
+    }
+
+    #[test]
+    fn should_rename_with_the_first_matching_rule() {
+        let rules = TopicRules::new(&config(
+            &["*/*"],
+            &[],
+            &[("prod-*/*", "dr-*/*"), ("*/*", "mirror/*-*")],
+        ))
+        .unwrap();
+
+        assert_eq!(
+            rules.target_of(&TopicName::new("prod-eu", "orders")),
+            TopicName::new("dr-eu", "orders")
+        );
+        assert_eq!(
+            rules.target_of(&TopicName::new("analytics", "clicks")),
+            TopicName::new("mirror", "analytics-clicks")
+        );
+    }
+
+    #[test]
+    fn should_keep_the_source_name_without_a_matching_rule() {
+        let rules = TopicRules::new(&config(&["*/*"], &[], &[("prod/*", "dr/*")])).unwrap();
+
+        assert_eq!(
+            rules.target_of(&TopicName::new("staging", "orders")),
+            TopicName::new("staging", "orders")
+        );
+    }
+
+    #[test]
+    fn should_reject_rename_rules_with_different_wildcards() {
+        let rules = TopicRules::new(&config(&["*/*"], &[], &[("prod/*", "dr/orders")]));
+
+        assert!(rules.is_err());
+    }
+}
@@ -0,0 +1,59 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use messenger::prelude::{Client, MessengerClient, MessengerClientBuilder};
+use tracing::{error, info};
+
+use crate::{configs::ClusterConfig, error::MirrorError};
+
+pub async fn create_client(config: &ClusterConfig) -> Result<MessengerClient, MirrorError> {
+    let cluster = &config.id;
+    let address = config.address.to_owned();
+    let missing_credentials = || MirrorError::MissingMessengerCredentials(cluster.to_owned());
+
+    let connection_string = if let Some(token) = config.token.to_owned() {
+        if token.is_empty() {
+            error!(
+                "Messenger token for cluster: {cluster} cannot be empty (if username and password are not provided)"
+            );
+            return Err(missing_credentials());
+        }
+
+        let redacted_token = token.chars().take(3).collect::<String>();
+        info!("Using token: {redacted_token}*** for authentication to cluster: {cluster}");
+        format!("messenger://{token}@{address}")
+    } else {
+        let username = config.username.to_owned().ok_or_else(missing_credentials)?;
+        let password = config.password.to_owned().ok_or_else(missing_credentials)?;
+        if username.is_empty() || password.is_empty() {
+            error!(
+                "Messenger username and password for cluster: {cluster} cannot be empty (if token is not provided)"
+            );
+            return Err(missing_credentials());
+        }
+
+        let redacted_username = username.chars().take(3).collect::<String>();
+        info!("Using username: {redacted_username}*** for authentication to cluster: {cluster}");
+        format!("messenger://{username}:{password}@{address}")
+    };
+
+    let client = MessengerClientBuilder::from_connection_string(&connection_string)?.build()?;
+    client.connect().await?;
+    info!("Connected to cluster: {cluster} at: {address}");
+    Ok(client)
+}
@@ -17,6 +17,8 @@
  */
 
 use crate::client_wrappers::client_wrapper::ClientWrapper;
+use crate::clients::consumer_flow_control::{ConsumerFlowControl, FlowControlConfig};
+use crate::clients::consumer_metrics::ConsumerMetricsSnapshot;
 use bytes::Bytes;
 use dashmap::DashMap;
 use futures::Stream;
@@ -27,9 +29,10 @@ use messenger_binary_protocol::{
 use messenger_common::locking::{MessengerSharedMut, MessengerSharedMutFn};
 use messenger_common::{
     Consumer, ConsumerKind, DiagnosticEvent, EncryptorKind, IdKind, Identifier, MessengerDuration,
-    MessengerError, MessengerMessage, MessengerTimestamp, PolledMessages, PollingKind, PollingStrategy,
+    MessengerError, MessengerMessage, MessengerTimestamp, PolledMessages, PollingKind,
+    PollingStrategy, decompress_message,
 };
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -42,6 +45,8 @@ use tracing::{error, info, trace, warn};
 
 const ORDERING: std::sync::atomic::Ordering = std::sync::atomic::Ordering::SeqCst;
 type PollMessagesFuture = Pin<Box<dyn Future<Output = Result<PolledMessages, MessengerError>>>>;
+/// Maximum number of messages kept aside for paused partitions before polling stops.
+const MAX_PARKED_MESSAGES: usize = 10_000;
 
 /// The auto-commit configuration for storing the offset on the server.
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -116,6 +121,8 @@ pub struct MessengerConsumer {
     current_offsets: Arc<DashMap<u32, AtomicU64>>,
     poll_future: Option<PollMessagesFuture>,
     buffered_messages: VecDeque<MessengerMessage>,
+    parked_messages: HashMap<u32, VecDeque<MessengerMessage>>,
+    flow_control: ConsumerFlowControl,
     encryptor: Option<Arc<EncryptorKind>>,
     store_offset_sender: flume::Sender<(u32, u64)>,
     store_offset_after_each_message: bool,
@@ -149,6 +156,7 @@ impl MessengerConsumer {
         init_retries: Option<u32>,
         init_retry_interval: MessengerDuration,
         allow_replay: bool,
+        flow_control: FlowControlConfig,
     ) -> Self {
         let (store_offset_sender, _) = flume::unbounded();
         Self {
@@ -178,6 +186,8 @@ impl MessengerConsumer {
             auto_join_consumer_group,
             create_consumer_group_if_not_exists,
             buffered_messages: VecDeque::new(),
+            parked_messages: HashMap::new(),
+            flow_control: ConsumerFlowControl::new(flow_control),
             encryptor,
             store_offset_sender,
             store_offset_after_each_message: matches!(
@@ -230,6 +240,34 @@ impl MessengerConsumer {
         self.current_partition_id.load(ORDERING)
     }
 
+    /// Returns a handle to pause and resume partitions and acknowledge processed messages.
+    ///
+    /// The handle can be cloned and used from other tasks while the consumer is polled.
+    pub fn flow_control(&self) -> ConsumerFlowControl {
+        self.flow_control.clone()
+    }
+
+    /// Acknowledges every message of the partition up to and including the offset,
+    /// freeing their slots in the in-flight window.
+    pub fn ack(&self, partition_id: u32, offset: u64) {
+        self.flow_control.ack(partition_id, offset);
+    }
+
+    /// Stops yielding messages from the partition until `resume_partition()` is called.
+    pub fn pause_partition(&self, partition_id: u32) {
+        self.flow_control.pause(partition_id);
+    }
+
+    /// Resumes yielding messages from a partition paused with `pause_partition()`.
+    pub fn resume_partition(&self, partition_id: u32) {
+        self.flow_control.resume(partition_id);
+    }
+
+    /// Returns a snapshot of the flow control and lag metrics.
+    pub fn metrics(&self) -> ConsumerMetricsSnapshot {
+        self.flow_control.metrics()
+    }
+
     /// Stores the consumer offset on the server either for the current partition or the provided partition ID.
     pub async fn store_offset(
         &self,
@@ -485,6 +523,78 @@ impl MessengerConsumer {
         });
     }
 
+    /// Keeps the messages of a paused partition aside until it is resumed, skipping the ones already kept or consumed.
+    fn park_messages(
+        &mut self,
+        partition_id: u32,
+        messages: impl IntoIterator<Item = MessengerMessage>,
+    ) {
+        let last_consumed_offset = self
+            .last_consumed_offsets
+            .get(&partition_id)
+            .map(|offset| offset.load(ORDERING));
+        let parked = self.parked_messages.entry(partition_id).or_default();
+        let mut last_offset = parked
+            .back()
+            .map(|message| message.header.offset)
+            .max(last_consumed_offset);
+        for message in messages {
+            if last_offset.is_some_and(|last_offset| message.header.offset <= last_offset) {
+                continue;
+            }
+            last_offset = Some(message.header.offset);
+            parked.push_back(message);
+        }
+
+        if self.polling_strategy.kind == PollingKind::Offset
+            && let Some(last_offset) = last_offset
+        {
+            self.polling_strategy = PollingStrategy::offset(last_offset + 1);
+        }
+    }
+
+    fn parked_messages_count(&self) -> usize {
+        self.parked_messages.values().map(VecDeque::len).sum()
+    }
+
+    /// Takes the next message kept aside for a partition that is no longer paused.
+    fn next_parked_message(&mut self) -> Option<ReceivedMessage> {
+        let partition_id = *self
+            .parked_messages
+            .keys()
+            .find(|partition_id| !self.flow_control.is_paused(**partition_id))?;
+        let parked = self.parked_messages.get_mut(&partition_id)?;
+        let message = parked.pop_front()?;
+        let is_last = parked.is_empty();
+        if is_last {
+            self.parked_messages.remove(&partition_id);
+        }
+
+        self.current_partition_id.store(partition_id, ORDERING);
+        if let Some(last_consumed_offset_entry) = self.last_consumed_offsets.get(&partition_id) {
+            last_consumed_offset_entry.store(message.header.offset, ORDERING);
+        } else {
+            self.last_consumed_offsets
+                .insert(partition_id, AtomicU64::new(message.header.offset));
+        }
+
+        if (self.store_after_every_nth_message > 0
+            && message.header.offset % self.store_after_every_nth_message == 0)
+            || self.store_offset_after_each_message
+            || (self.store_offset_after_all_messages && is_last)
+        {
+            self.send_store_offset(partition_id, message.header.offset);
+        }
+
+        let current_offset = self
+            .current_offsets
+            .get(&partition_id)
+            .map_or(0, |offset| offset.load(ORDERING));
+        self.flow_control
+            .on_delivered(partition_id, message.header.offset, current_offset);
+        Some(ReceivedMessage::new(message, current_offset, partition_id))
+    }
+
     pub(crate) fn send_store_offset(&self, partition_id: u32, offset: u64) {
         if let Err(error) = self.store_offset_sender.send((partition_id, offset)) {
             error!(
@@ -755,7 +865,10 @@ impl MessengerConsumer {
             error!("Failed to poll messages: {error}");
             if matches!(
                 error,
-                MessengerError::Disconnected | MessengerError::Unauthenticated | MessengerError::StaleClient
+                MessengerError::Disconnected
+                    | MessengerError::Unauthenticated
+                    | MessengerError::StaleClient
+                    | MessengerError::ServerShuttingDown
             ) {
                 trace!("Retrying to poll messages in {retry_interval}...");
                 sleep(retry_interval.get_duration()).await;
@@ -887,7 +1000,21 @@ impl Stream for MessengerConsumer {
     type Item = Result<ReceivedMessage, MessengerError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.flow_control.register(cx.waker());
+        if !self.flow_control.has_capacity() {
+            return Poll::Pending;
+        }
+
         let partition_id = self.current_partition_id.load(ORDERING);
+        if !self.buffered_messages.is_empty() && self.flow_control.is_paused(partition_id) {
+            let messages = std::mem::take(&mut self.buffered_messages);
+            self.park_messages(partition_id, messages);
+        }
+
+        if let Some(message) = self.next_parked_message() {
+            return Poll::Ready(Some(Ok(message)));
+        }
+
         if let Some(message) = self.buffered_messages.pop_front() {
             {
                 if let Some(last_consumed_offset_entry) =
@@ -924,6 +1051,8 @@ impl Stream for MessengerConsumer {
                 current_offset = 0;
             }
 
+            self.flow_control
+                .on_delivered(partition_id, message.header.offset, current_offset);
             return Poll::Ready(Some(Ok(ReceivedMessage::new(
                 message,
                 current_offset,
@@ -931,6 +1060,15 @@ impl Stream for MessengerConsumer {
             ))));
         }
 
+        // Polling more is pointless while the only partition is paused or too many messages wait for resuming.
+        if self
+            .partition_id
+            .is_some_and(|partition_id| self.flow_control.is_paused(partition_id))
+            || self.parked_messages_count() >= MAX_PARKED_MESSAGES
+        {
+            return Poll::Pending;
+        }
+
         if self.poll_future.is_none() {
             let future = self.create_poll_messages_future();
             self.poll_future = Some(Box::pin(future));
@@ -942,6 +1080,15 @@ impl Stream for MessengerConsumer {
                     let partition_id = polled_messages.partition_id;
                     self.current_partition_id.store(partition_id, ORDERING);
                     if polled_messages.messages.is_empty() {
+                        let consumed_offset = self
+                            .last_consumed_offsets
+                            .get(&partition_id)
+                            .map_or(0, |offset| offset.load(ORDERING));
+                        self.flow_control.on_caught_up(
+                            partition_id,
+                            polled_messages.current_offset,
+                            consumed_offset,
+                        );
                         self.poll_future = Some(Box::pin(self.create_poll_messages_future()));
                     } else {
                         if let Some(ref encryptor) = self.encryptor {
@@ -962,6 +1109,17 @@ impl Stream for MessengerConsumer {
                             }
                         }
 
+                        for message in &mut polled_messages.messages {
+                            if let Err(error) = decompress_message(message) {
+                                self.poll_future = None;
+                                error!(
+                                    "Failed to decompress the message payload at offset: {}, partition ID: {}",
+                                    message.header.offset, partition_id
+                                );
+                                return Poll::Ready(Some(Err(error)));
+                            }
+                        }
+
                         if let Some(current_offset_entry) = self.current_offsets.get(&partition_id)
                         {
                             current_offset_entry.store(polled_messages.current_offset, ORDERING);
@@ -972,6 +1130,16 @@ impl Stream for MessengerConsumer {
                             );
                         }
 
+                        if self.flow_control.is_paused(partition_id) {
+                            self.park_messages(partition_id, polled_messages.messages);
+                            if self.parked_messages_count() >= MAX_PARKED_MESSAGES {
+                                self.poll_future = None;
+                                return Poll::Pending;
+                            }
+                            self.poll_future = Some(Box::pin(self.create_poll_messages_future()));
+                            continue;
+                        }
+
                         let message = polled_messages.messages.remove(0);
                         self.buffered_messages.extend(polled_messages.messages);
 
@@ -1001,6 +1169,11 @@ impl Stream for MessengerConsumer {
                             );
                         }
 
+                        self.flow_control.on_delivered(
+                            partition_id,
+                            message.header.offset,
+                            polled_messages.current_offset,
+                        );
                         self.poll_future = None;
                         return Poll::Ready(Some(Ok(ReceivedMessage::new(
                             message,
@@ -17,6 +17,7 @@
  */
 
 use crate::client_wrappers::client_wrapper::ClientWrapper;
+use crate::clients::consumer_flow_control::FlowControlConfig;
 use crate::prelude::{AutoCommit, AutoCommitWhen, MessengerConsumer};
 use messenger_common::locking::MessengerSharedMut;
 use messenger_common::{Consumer, EncryptorKind, Identifier, MessengerDuration, PollingStrategy};
@@ -41,6 +42,7 @@ pub struct MessengerConsumerBuilder {
     init_retries: Option<u32>,
     init_retry_interval: MessengerDuration,
     allow_replay: bool,
+    flow_control: FlowControlConfig,
 }
 
 impl MessengerConsumerBuilder {
@@ -76,6 +78,7 @@ impl MessengerConsumerBuilder {
             init_retries: None,
             init_retry_interval: MessengerDuration::ONE_SECOND,
             allow_replay: false,
+            flow_control: FlowControlConfig::default(),
         }
     }
 
@@ -216,6 +219,43 @@ impl MessengerConsumerBuilder {
         }
     }
 
+    /// Limits the number of messages handed out but not yet acknowledged, across all partitions.
+    /// The consumer stops yielding messages until some are acknowledged with `ack()`.
+    pub fn max_in_flight(self, max_in_flight: u32) -> Self {
+        Self {
+            flow_control: FlowControlConfig {
+                max_in_flight: Some(max_in_flight),
+                ..self.flow_control
+            },
+            ..self
+        }
+    }
+
+    /// Pauses a partition once it has `high` unacknowledged messages and resumes it when
+    /// acknowledgements bring the count down to `low`.
+    pub fn pause_at_high_water_mark(self, high: u32, low: u32) -> Self {
+        Self {
+            flow_control: FlowControlConfig {
+                high_water_mark: Some(high),
+                low_water_mark: low.min(high.saturating_sub(1)),
+                ..self.flow_control
+            },
+            ..self
+        }
+    }
+
+    /// Raises a lag alert, visible in the consumer metrics, when a partition falls more than
+    /// `threshold` messages behind its current offset.
+    pub fn lag_alert_threshold(self, threshold: u64) -> Self {
+        Self {
+            flow_control: FlowControlConfig {
+                lag_alert_threshold: Some(threshold),
+                ..self.flow_control
+            },
+            ..self
+        }
+    }
+
     /// Builds the consumer.
     ///
     /// Note: After building the consumer, `init()` must be invoked before producing messages.
@@ -238,6 +278,7 @@ impl MessengerConsumerBuilder {
             self.init_retries,
             self.init_retry_interval,
             self.allow_replay,
+            self.flow_control,
         )
     }
 }
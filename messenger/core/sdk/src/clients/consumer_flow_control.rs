@@ -0,0 +1,409 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::clients::consumer_metrics::ConsumerMetricsSnapshot;
+use futures::task::AtomicWaker;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+use tracing::{info, warn};
+
+/// Limits a consumer applies to itself so that a slow application is not flooded with messages.
+///
+/// Every limit is disabled by default. When `max_in_flight` or `high_water_mark` is set,
+/// the application must acknowledge processed messages with
+/// [`ConsumerFlowControl::ack`] (or use `consume_messages()`, which does it after each message).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FlowControlConfig {
+    /// Maximum number of messages handed out but not yet acknowledged, across all partitions.
+    /// The consumer stops yielding and polling once the window is full.
+    pub max_in_flight: Option<u32>,
+    /// Number of unacknowledged messages in one partition at which the partition is paused automatically.
+    pub high_water_mark: Option<u32>,
+    /// Number of unacknowledged messages at which an automatically paused partition resumes.
+    pub low_water_mark: u32,
+    /// Lag, in messages behind the partition's current offset, above which a lag alert is raised.
+    pub lag_alert_threshold: Option<u64>,
+}
+
+impl FlowControlConfig {
+    fn tracks_in_flight(&self) -> bool {
+        self.max_in_flight.is_some() || self.high_water_mark.is_some()
+    }
+}
+
+#[derive(Debug, Default)]
+struct PartitionFlow {
+    /// Offsets handed out and not yet acknowledged, oldest first.
+    in_flight: VecDeque<u64>,
+    paused: bool,
+    auto_paused: bool,
+    lag: u64,
+    lag_alert: bool,
+}
+
+impl PartitionFlow {
+    fn is_paused(&self) -> bool {
+        self.paused || self.auto_paused
+    }
+}
+
+#[derive(Debug, Default)]
+struct FlowState {
+    partitions: HashMap<u32, PartitionFlow>,
+    in_flight: usize,
+    messages_delivered: u64,
+    messages_acked: u64,
+    auto_pauses: u64,
+    lag_alerts: u64,
+}
+
+#[derive(Debug)]
+struct FlowShared {
+    config: FlowControlConfig,
+    state: Mutex<FlowState>,
+    waker: AtomicWaker,
+}
+
+/// Handle controlling the flow of messages of a [`MessengerConsumer`](super::consumer::MessengerConsumer).
+///
+/// Cloning is cheap and every clone controls the same consumer, so the handle can be
+/// moved to the tasks processing messages while the consumer itself is being polled.
+#[derive(Debug, Clone)]
+pub struct ConsumerFlowControl {
+    shared: Arc<FlowShared>,
+}
+
+impl ConsumerFlowControl {
+    pub(crate) fn new(config: FlowControlConfig) -> Self {
+        Self {
+            shared: Arc::new(FlowShared {
+                config,
+                state: Mutex::new(FlowState::default()),
+                waker: AtomicWaker::new(),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> FlowControlConfig {
+        self.shared.config
+    }
+
+    /// Stops handing out messages from the partition until [`resume`](Self::resume) is called.
+    ///
+    /// Messages already polled from the partition are kept and delivered after resuming.
+    pub fn pause(&self, partition_id: u32) {
+        self.state()
+            .partitions
+            .entry(partition_id)
+            .or_default()
+            .paused = true;
+        info!("Paused consuming partition ID: {partition_id}");
+    }
+
+    /// Resumes a partition paused with [`pause`](Self::pause).
+    ///
+    /// A partition paused automatically at the high-water mark stays paused until it drains.
+    pub fn resume(&self, partition_id: u32) {
+        if let Some(partition) = self.state().partitions.get_mut(&partition_id) {
+            partition.paused = false;
+        }
+        info!("Resumed consuming partition ID: {partition_id}");
+        self.shared.waker.wake();
+    }
+
+    /// Whether the partition is paused, manually or at the high-water mark.
+    pub fn is_paused(&self, partition_id: u32) -> bool {
+        self.state()
+            .partitions
+            .get(&partition_id)
+            .is_some_and(PartitionFlow::is_paused)
+    }
+
+    /// IDs of the paused partitions, in ascending order.
+    pub fn paused_partitions(&self) -> Vec<u32> {
+        let mut paused: Vec<u32> = self
+            .state()
+            .partitions
+            .iter()
+            .filter(|(_, partition)| partition.is_paused())
+            .map(|(partition_id, _)| *partition_id)
+            .collect();
+        paused.sort_unstable();
+        paused
+    }
+
+    /// Acknowledges every message of the partition up to and including `offset`,
+    /// freeing their slots in the in-flight window.
+    pub fn ack(&self, partition_id: u32, offset: u64) {
+        if !self.shared.config.tracks_in_flight() {
+            return;
+        }
+
+        let low_water_mark = self.shared.config.low_water_mark as usize;
+        let mut state = self.state();
+        let Some(partition) = state.partitions.get_mut(&partition_id) else {
+            return;
+        };
+        let mut acked = 0;
+        while partition
+            .in_flight
+            .front()
+            .is_some_and(|front| *front <= offset)
+        {
+            partition.in_flight.pop_front();
+            acked += 1;
+        }
+        if partition.auto_paused && partition.in_flight.len() <= low_water_mark {
+            partition.auto_paused = false;
+            info!("Partition ID: {partition_id} drained below the low-water mark, resuming");
+        }
+        state.in_flight -= acked;
+        state.messages_acked += acked as u64;
+        drop(state);
+
+        if acked > 0 {
+            self.shared.waker.wake();
+        }
+    }
+
+    /// Number of messages handed out and not yet acknowledged.
+    pub fn in_flight(&self) -> usize {
+        self.state().in_flight
+    }
+
+    /// Last known lag of the partition, in messages.
+    pub fn lag(&self, partition_id: u32) -> Option<u64> {
+        self.state()
+            .partitions
+            .get(&partition_id)
+            .map(|partition| partition.lag)
+    }
+
+    pub fn metrics(&self) -> ConsumerMetricsSnapshot {
+        let state = self.state();
+        let mut lagging_partitions: Vec<u32> = state
+            .partitions
+            .iter()
+            .filter(|(_, partition)| partition.lag_alert)
+            .map(|(partition_id, _)| *partition_id)
+            .collect();
+        lagging_partitions.sort_unstable();
+
+        ConsumerMetricsSnapshot {
+            messages_delivered: state.messages_delivered,
+            messages_acked: state.messages_acked,
+            in_flight: state.in_flight as u64,
+            paused_partitions: state
+                .partitions
+                .values()
+                .filter(|partition| partition.is_paused())
+                .count() as u32,
+            auto_pauses: state.auto_pauses,
+            total_lag: state
+                .partitions
+                .values()
+                .map(|partition| partition.lag)
+                .sum(),
+            max_lag: state
+                .partitions
+                .values()
+                .map(|partition| partition.lag)
+                .max()
+                .unwrap_or_default(),
+            lag_alerts: state.lag_alerts,
+            lagging_partitions,
+        }
+    }
+
+    /// Wakes `waker` when an ack or resume lets the consumer make progress again.
+    pub(crate) fn register(&self, waker: &Waker) {
+        self.shared.waker.register(waker);
+    }
+
+    /// Whether the in-flight window has room for another message.
+    pub(crate) fn has_capacity(&self) -> bool {
+        match self.shared.config.max_in_flight {
+            Some(max_in_flight) => self.state().in_flight < max_in_flight as usize,
+            None => true,
+        }
+    }
+
+    /// Records a message handed to the application, pausing its partition at the high-water mark.
+    pub(crate) fn on_delivered(&self, partition_id: u32, offset: u64, current_offset: u64) {
+        let config = self.shared.config;
+        let mut state = self.state();
+        state.messages_delivered += 1;
+        if config.tracks_in_flight() {
+            state.in_flight += 1;
+        }
+
+        let partition = state.partitions.entry(partition_id).or_default();
+        let mut auto_paused = false;
+        if config.tracks_in_flight() {
+            partition.in_flight.push_back(offset);
+            if let Some(high_water_mark) = config.high_water_mark
+                && !partition.auto_paused
+                && partition.in_flight.len() >= high_water_mark as usize
+            {
+                partition.auto_paused = true;
+                auto_paused = true;
+                warn!(
+                    "Partition ID: {partition_id} reached the high-water mark of {high_water_mark} unacknowledged messages, pausing"
+                );
+            }
+        }
+        let lag_alert = Self::update_lag(
+            &config,
+            partition_id,
+            partition,
+            current_offset.saturating_sub(offset),
+        );
+        if auto_paused {
+            state.auto_pauses += 1;
+        }
+        if lag_alert {
+            state.lag_alerts += 1;
+        }
+    }
+
+    /// Records the lag seen by a poll that returned nothing new for the partition.
+    pub(crate) fn on_caught_up(
+        &self,
+        partition_id: u32,
+        current_offset: u64,
+        consumed_offset: u64,
+    ) {
+        let config = self.shared.config;
+        let mut state = self.state();
+        let partition = state.partitions.entry(partition_id).or_default();
+        if Self::update_lag(
+            &config,
+            partition_id,
+            partition,
+            current_offset.saturating_sub(consumed_offset),
+        ) {
+            state.lag_alerts += 1;
+        }
+    }
+
+    /// Returns true when the lag crosses above the alert threshold.
+    fn update_lag(
+        config: &FlowControlConfig,
+        partition_id: u32,
+        partition: &mut PartitionFlow,
+        lag: u64,
+    ) -> bool {
+        partition.lag = lag;
+        let Some(threshold) = config.lag_alert_threshold else {
+            return false;
+        };
+        let lagging = lag > threshold;
+        let raised = lagging && !partition.lag_alert;
+        if raised {
+            warn!(
+                "Partition ID: {partition_id} is {lag} messages behind, above the alert threshold of {threshold}"
+            );
+        } else if partition.lag_alert && !lagging {
+            info!("Partition ID: {partition_id} lag is back to {lag} messages");
+        }
+        partition.lag_alert = lagging;
+        raised
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, FlowState> {
+        self.shared.state.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_bound_in_flight_messages() {
+        let flow = ConsumerFlowControl::new(FlowControlConfig {
+            max_in_flight: Some(2),
+            ..Default::default()
+        });
+        flow.on_delivered(1, 10, 20);
+        assert!(flow.has_capacity());
+        flow.on_delivered(1, 11, 20);
+        assert!(!flow.has_capacity());
+
+        flow.ack(1, 10);
+        assert!(flow.has_capacity());
+        assert_eq!(flow.in_flight(), 1);
+        assert_eq!(flow.metrics().messages_acked, 1);
+    }
+
+    #[test]
+    fn should_pause_at_high_water_mark_and_resume_at_low_water_mark() {
+        let flow = ConsumerFlowControl::new(FlowControlConfig {
+            high_water_mark: Some(3),
+            low_water_mark: 1,
+            ..Default::default()
+        });
+        for offset in 0..3 {
+            flow.on_delivered(2, offset, 10);
+        }
+        assert!(flow.is_paused(2));
+        assert_eq!(flow.metrics().auto_pauses, 1);
+
+        flow.ack(2, 0);
+        assert!(flow.is_paused(2));
+        flow.ack(2, 1);
+        assert!(!flow.is_paused(2));
+    }
+
+    #[test]
+    fn should_keep_manual_pause_until_resumed() {
+        let flow = ConsumerFlowControl::new(FlowControlConfig::default());
+        flow.pause(3);
+        flow.pause(1);
+        assert_eq!(flow.paused_partitions(), vec![1, 3]);
+        flow.resume(3);
+        assert_eq!(flow.paused_partitions(), vec![1]);
+        // Nothing is tracked without a window or high-water mark, so acks are ignored
+        flow.on_delivered(1, 5, 5);
+        flow.ack(1, 5);
+        assert_eq!(flow.in_flight(), 0);
+    }
+
+    #[test]
+    fn should_raise_lag_alert_once_per_crossing() {
+        let flow = ConsumerFlowControl::new(FlowControlConfig {
+            lag_alert_threshold: Some(100),
+            ..Default::default()
+        });
+        flow.on_delivered(1, 0, 500);
+        flow.on_delivered(1, 1, 500);
+        flow.on_delivered(2, 0, 50);
+
+        let metrics = flow.metrics();
+        assert_eq!(metrics.lag_alerts, 1);
+        assert_eq!(metrics.lagging_partitions, vec![1]);
+        assert_eq!(metrics.max_lag, 499);
+        assert_eq!(metrics.total_lag, 549);
+
+        flow.on_caught_up(1, 500, 500);
+        assert!(flow.metrics().lagging_partitions.is_empty());
+        flow.on_delivered(1, 501, 700);
+        assert_eq!(flow.metrics().lag_alerts, 2);
+    }
+}
@@ -0,0 +1,47 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+/// Point-in-time view of a consumer's flow control and lag.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsumerMetricsSnapshot {
+    /// Number of messages handed to the application.
+    pub messages_delivered: u64,
+    /// Number of messages acknowledged by the application.
+    pub messages_acked: u64,
+    /// Number of messages handed out and not yet acknowledged.
+    pub in_flight: u64,
+    /// Number of partitions currently paused, manually or at the high-water mark.
+    pub paused_partitions: u32,
+    /// Number of times a partition was paused at the high-water mark.
+    pub auto_pauses: u64,
+    /// Sum of the last known lag of every partition, in messages.
+    pub total_lag: u64,
+    /// Largest last known lag of a partition, in messages.
+    pub max_lag: u64,
+    /// Number of times a partition's lag crossed above the alert threshold.
+    pub lag_alerts: u64,
+    /// Partitions whose lag is currently above the alert threshold.
+    pub lagging_partitions: Vec<u32>,
+}
+
+impl ConsumerMetricsSnapshot {
+    /// Whether any partition is currently lagging above the alert threshold.
+    pub fn is_lagging(&self) -> bool {
+        !self.lagging_partitions.is_empty()
+    }
+}
@@ -30,11 +30,15 @@ pub mod client;
 pub mod client_builder;
 pub mod consumer;
 pub mod consumer_builder;
+pub mod consumer_flow_control;
+pub mod consumer_metrics;
 pub mod producer;
 pub mod producer_builder;
 pub mod producer_config;
+pub mod producer_delivery_callback;
 pub mod producer_dispatcher;
 pub mod producer_error_callback;
+pub mod producer_metrics;
 pub mod producer_sharding;
 
 const ORDERING: std::sync::atomic::Ordering = std::sync::atomic::Ordering::SeqCst;
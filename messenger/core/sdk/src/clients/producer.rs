@@ -21,6 +21,7 @@ use crate::clients::MAX_BATCH_LENGTH;
 use crate::clients::producer_builder::SendMode;
 use crate::clients::producer_config::DirectConfig;
 use crate::clients::producer_dispatcher::ProducerDispatcher;
+use crate::clients::producer_metrics::{ProducerMetrics, ProducerMetricsSnapshot};
 use bytes::Bytes;
 use futures_util::StreamExt;
 use messenger_binary_protocol::{Client, MessageClient, StreamClient, TopicClient};
@@ -28,10 +29,11 @@ use messenger_common::locking::{MessengerSharedMut, MessengerSharedMutFn};
 use messenger_common::{
     CompressionAlgorithm, DiagnosticEvent, EncryptorKind, IdKind, Identifier, MessengerDuration,
     MessengerError, MessengerExpiry, MessengerMessage, MessengerTimestamp, MaxTopicSize, Partitioner, Partitioning,
+    compress_message,
 };
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
-use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64};
 use std::time::Duration;
 use tokio::time::{Interval, sleep};
 use tracing::{error, info, trace, warn};
@@ -72,6 +74,9 @@ pub struct ProducerCore {
     send_retries_count: Option<u32>,
     send_retries_interval: Option<MessengerDuration>,
     direct_config: Option<DirectConfig>,
+    compression: CompressionAlgorithm,
+    negotiated_compression: AtomicU8,
+    metrics: Arc<ProducerMetrics>,
 }
 
 impl ProducerCore {
@@ -103,36 +108,51 @@ impl ProducerCore {
             client.create_stream(&name, id).await?;
         }
 
-        if client.get_topic(&stream_id, &topic_id).await?.is_none() {
-            if !self.create_topic_if_not_exists {
-                error!("Topic does not exist and auto-creation is disabled.");
-                return Err(MessengerError::TopicNameNotFound(
-                    self.topic_name.clone(),
-                    self.stream_name.clone(),
-                ));
+        let topic_compression = match client.get_topic(&stream_id, &topic_id).await? {
+            Some(topic) => topic.compression_algorithm,
+            None => {
+                if !self.create_topic_if_not_exists {
+                    error!("Topic does not exist and auto-creation is disabled.");
+                    return Err(MessengerError::TopicNameNotFound(
+                        self.topic_name.clone(),
+                        self.stream_name.clone(),
+                    ));
+                }
+
+                let (name, id) = match self.topic_id.kind {
+                    IdKind::Numeric => (
+                        self.topic_name.to_owned(),
+                        Some(self.topic_id.get_u32_value()?),
+                    ),
+                    IdKind::String => (self.topic_id.get_string_value()?, None),
+                };
+                info!("Creating topic: {name} for stream: {}", self.stream_name);
+                client
+                    .create_topic(
+                        &self.stream_id,
+                        &self.topic_name,
+                        self.topic_partitions_count,
+                        self.compression,
+                        self.topic_replication_factor,
+                        id,
+                        self.topic_message_expiry,
+                        self.topic_max_size,
+                    )
+                    .await?;
+                self.compression
             }
+        };
 
-            let (name, id) = match self.topic_id.kind {
-                IdKind::Numeric => (
-                    self.topic_name.to_owned(),
-                    Some(self.topic_id.get_u32_value()?),
-                ),
-                IdKind::String => (self.topic_id.get_string_value()?, None),
-            };
-            info!("Creating topic: {name} for stream: {}", self.stream_name);
-            client
-                .create_topic(
-                    &self.stream_id,
-                    &self.topic_name,
-                    self.topic_partitions_count,
-                    CompressionAlgorithm::None,
-                    self.topic_replication_factor,
-                    id,
-                    self.topic_message_expiry,
-                    self.topic_max_size,
-                )
-                .await?;
+        let compression = negotiate_compression(self.compression, topic_compression);
+        if compression != self.compression {
+            warn!(
+                "Topic: {topic_id} in stream: {stream_id} uses {compression} compression, \
+                 overriding the requested {} compression.",
+                self.compression
+            );
         }
+        self.negotiated_compression
+            .store(compression.as_code(), Ordering::SeqCst);
 
         let _ = self
             .initialized
@@ -297,6 +317,26 @@ impl ProducerCore {
         }
     }
 
+    /// Compresses the payloads with the negotiated algorithm and returns the original payload sizes.
+    fn compress_messages(&self, messages: &mut [MessengerMessage]) -> Result<Vec<u64>, MessengerError> {
+        let original_sizes = messages.iter().map(|m| m.payload.len() as u64).collect();
+        let compression = CompressionAlgorithm::from_code(self.negotiated_compression.load(ORDERING))?;
+        if compression != CompressionAlgorithm::None {
+            for message in messages {
+                compress_message(message, compression)?;
+            }
+        }
+        Ok(original_sizes)
+    }
+
+    fn record_batch(&self, original_sizes: &[u64], compressed_sizes: &[u64]) {
+        self.metrics.record_batch(
+            original_sizes.len() as u64,
+            original_sizes.iter().sum(),
+            compressed_sizes.iter().sum(),
+        );
+    }
+
     fn encrypt_messages(&self, messages: &mut [MessengerMessage]) -> Result<(), MessengerError> {
         if let Some(encryptor) = &self.encryptor {
             for message in messages {
@@ -369,6 +409,13 @@ impl ProducerCoreBackend for ProducerCore {
             return Ok(());
         }
 
+        let original_sizes = match self.compress_messages(&mut msgs) {
+            Ok(sizes) => sizes,
+            Err(err) => return Err(self.make_failed_error(err, msgs)),
+        };
+        // Compressed sizes are recorded before encryption, which does not shrink the payload.
+        let compressed_sizes: Vec<u64> = msgs.iter().map(|m| m.payload.len() as u64).collect();
+
         if let Err(err) = self.encrypt_messages(&mut msgs) {
             return Err(self.make_failed_error(err, msgs));
         }
@@ -399,21 +446,25 @@ impl ProducerCoreBackend for ProducerCore {
                     let chunk = &mut msgs[index..end];
 
                     if let Err(err) = self.try_send_messages(stream, topic, &part, chunk).await {
+                        self.metrics.record_failed_batch();
                         let failed_tail = msgs.split_off(index);
                         return Err(self.make_failed_error(err, failed_tail));
                     }
                     self.last_sent_at
                         .store(MessengerTimestamp::now().into(), ORDERING);
+                    self.record_batch(&original_sizes[index..end], &compressed_sizes[index..end]);
                     index = end;
                 }
             }
             // background send on
             _ => {
-                self.try_send_messages(stream, topic, &part, &mut msgs)
-                    .await
-                    .map_err(|err| self.make_failed_error(err, msgs))?;
+                if let Err(err) = self.try_send_messages(stream, topic, &part, &mut msgs).await {
+                    self.metrics.record_failed_batch();
+                    return Err(self.make_failed_error(err, msgs));
+                }
                 self.last_sent_at
                     .store(MessengerTimestamp::now().into(), ORDERING);
+                self.record_batch(&original_sizes, &compressed_sizes);
             }
         }
 
@@ -421,6 +472,18 @@ impl ProducerCoreBackend for ProducerCore {
     }
 }
 
+/// Picks the compression used for a topic: a topic created with compression
+/// enforces its algorithm, otherwise the producer's requested one is used.
+fn negotiate_compression(
+    requested: CompressionAlgorithm,
+    topic: CompressionAlgorithm,
+) -> CompressionAlgorithm {
+    match topic {
+        CompressionAlgorithm::None => requested,
+        topic => topic,
+    }
+}
+
 unsafe impl Send for MessengerProducer {}
 unsafe impl Sync for MessengerProducer {}
 
@@ -448,6 +511,7 @@ impl MessengerProducer {
         topic_max_size: MaxTopicSize,
         send_retries_count: Option<u32>,
         send_retries_interval: Option<MessengerDuration>,
+        compression: CompressionAlgorithm,
         mode: SendMode,
     ) -> Self {
         let core = Arc::new(ProducerCore {
@@ -475,6 +539,9 @@ impl MessengerProducer {
                 SendMode::Direct(ref cfg) => Some(cfg.clone()),
                 _ => None,
             },
+            compression,
+            negotiated_compression: AtomicU8::new(CompressionAlgorithm::None.as_code()),
+            metrics: Arc::new(ProducerMetrics::default()),
         });
         let dispatcher = match mode {
             SendMode::Background(cfg) => Some(ProducerDispatcher::new(core.clone(), cfg)),
@@ -570,9 +637,50 @@ impl MessengerProducer {
         }
     }
 
+    /// Sends everything buffered by the background producer and waits for the requests to complete.
+    ///
+    /// Delivery and failure of the flushed batches are reported through the configured callbacks.
+    /// Direct sends are never buffered, so this is a no-op in the direct mode.
+    pub async fn flush(&self) -> Result<(), MessengerError> {
+        match &self.dispatcher {
+            Some(disp) => disp.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the batch counters collected since the producer was built.
+    pub fn metrics(&self) -> ProducerMetricsSnapshot {
+        self.core.metrics.snapshot()
+    }
+
     pub async fn shutdown(self) {
         if let Some(disp) = self.dispatcher {
             disp.shutdown().await;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_use_requested_compression_for_uncompressed_topic() {
+        assert_eq!(
+            negotiate_compression(CompressionAlgorithm::Lz4, CompressionAlgorithm::None),
+            CompressionAlgorithm::Lz4
+        );
+    }
+
+    #[test]
+    fn should_enforce_topic_compression() {
+        assert_eq!(
+            negotiate_compression(CompressionAlgorithm::Lz4, CompressionAlgorithm::Zstd),
+            CompressionAlgorithm::Zstd
+        );
+        assert_eq!(
+            negotiate_compression(CompressionAlgorithm::None, CompressionAlgorithm::Gzip),
+            CompressionAlgorithm::Gzip
+        );
+    }
+}
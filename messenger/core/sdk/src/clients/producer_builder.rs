@@ -20,7 +20,7 @@ use crate::clients::producer_config::{BackgroundConfig, DirectConfig};
 use crate::prelude::MessengerProducer;
 use messenger_common::locking::MessengerSharedMut;
 use messenger_common::{
-    EncryptorKind, Identifier, MessengerDuration, MessengerExpiry, MaxTopicSize, Partitioner, Partitioning,
+    CompressionAlgorithm, EncryptorKind, Identifier, MessengerDuration, MessengerExpiry, MaxTopicSize, Partitioner, Partitioning,
 };
 use std::sync::Arc;
 
@@ -52,6 +52,7 @@ pub struct MessengerProducerBuilder {
     topic_message_expiry: MessengerExpiry,
     topic_max_size: MaxTopicSize,
     partitioning: Option<Partitioning>,
+    compression: CompressionAlgorithm,
     mode: SendMode,
 }
 
@@ -83,6 +84,7 @@ impl MessengerProducerBuilder {
             topic_max_size: MaxTopicSize::ServerDefault,
             send_retries_count: Some(3),
             send_retries_interval: Some(MessengerDuration::ONE_SECOND),
+            compression: CompressionAlgorithm::None,
             mode: SendMode::default(),
         }
     }
@@ -198,6 +200,15 @@ impl MessengerProducerBuilder {
         }
     }
 
+    /// Sets the algorithm used to compress the messages' payloads, also used when the producer creates the topic.
+    /// A compression algorithm already set on the topic takes precedence. Default is no compression.
+    pub fn compression(self, compression: CompressionAlgorithm) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+
     /// Sets the producer to use direct message sending.
     /// This mode ensures that messages are sent immediately to the server
     /// without being buffered or delayed.
@@ -231,6 +242,7 @@ impl MessengerProducerBuilder {
             self.topic_max_size,
             self.send_retries_count,
             self.send_retries_interval,
+            self.compression,
             self.mode,
         )
     }
@@ -16,6 +16,7 @@
  * under the License.
  */
 use crate::clients::MIB;
+use crate::clients::producer_delivery_callback::{DeliveryCallback, LogDeliveryCallback};
 use crate::clients::producer_error_callback::{ErrorCallback, LogErrorCallback};
 use crate::clients::producer_sharding::{BalancedSharding, Sharding};
 use bon::Builder;
@@ -83,6 +84,10 @@ pub struct BackgroundConfig {
     /// (e.g. network failure).
     #[builder(default = Arc::new(Box::new(LogErrorCallback)))]
     pub error_callback: Arc<Box<dyn ErrorCallback + Send + Sync>>,
+    /// User-supplied asynchronous callback that will be executed for every
+    /// batch accepted by the server.
+    #[builder(default = Arc::new(Box::new(LogDeliveryCallback)))]
+    pub delivery_callback: Arc<Box<dyn DeliveryCallback + Send + Sync>>,
     /// Strategy that maps a message to a shard.
     #[builder(default = Box::new(BalancedSharding::default()))]
     pub sharding: Box<dyn Sharding + Send + Sync>,
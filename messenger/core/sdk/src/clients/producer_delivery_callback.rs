@@ -0,0 +1,60 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use messenger_common::{Identifier, Partitioning};
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::Arc;
+use tracing::trace;
+
+#[derive(Debug)]
+pub struct DeliveryCtx {
+    pub stream: Arc<Identifier>,
+    pub topic: Arc<Identifier>,
+    pub partitioning: Option<Arc<Partitioning>>,
+    /// Number of messages delivered in the batch.
+    pub messages_count: usize,
+    /// Payload bytes of the batch before compression.
+    pub payload_bytes: u64,
+}
+
+/// A trait for observing batches delivered by the background producer.
+///
+/// Invoked once per batch accepted by the server, after the shard has flushed it.
+/// Failed batches are reported through [`ErrorCallback`](super::producer_error_callback::ErrorCallback) instead.
+pub trait DeliveryCallback: Send + Sync + Debug + 'static {
+    fn call(&self, ctx: DeliveryCtx) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+}
+
+/// Default implementation of [`DeliveryCallback`] that traces the delivered batch.
+#[derive(Debug, Default)]
+pub struct LogDeliveryCallback;
+
+impl DeliveryCallback for LogDeliveryCallback {
+    fn call(&self, ctx: DeliveryCtx) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {
+        Box::pin(async move {
+            trace!(
+                stream = %ctx.stream,
+                topic = %ctx.topic,
+                num_messages = ctx.messages_count,
+                payload_bytes = ctx.payload_bytes,
+                "Delivered messages in background task",
+            );
+        })
+    }
+}
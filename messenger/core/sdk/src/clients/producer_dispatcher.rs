@@ -17,6 +17,7 @@
  */
 use crate::clients::producer::ProducerCoreBackend;
 use crate::clients::producer_config::{BackgroundConfig, BackpressureMode};
+use crate::clients::producer_delivery_callback::DeliveryCtx;
 use crate::clients::producer_error_callback::ErrorCtx;
 use crate::clients::producer_sharding::{Shard, ShardMessage, ShardMessageWithPermits};
 use futures::FutureExt;
@@ -42,7 +43,9 @@ impl ProducerDispatcher {
         let config = Arc::new(config);
 
         let (err_tx, err_rx) = flume::unbounded::<ErrorCtx>();
+        let (delivery_tx, delivery_rx) = flume::unbounded::<DeliveryCtx>();
         let err_callback = config.error_callback.clone();
+        let delivery_callback = config.delivery_callback.clone();
         let (stop_tx, _) = broadcast::channel::<()>(1);
 
         let mut stop_rx = stop_tx.subscribe();
@@ -62,8 +65,21 @@ impl ProducerDispatcher {
                             Err(_) => break
                         }
                     }
+                    maybe_delivery = delivery_rx.recv_async() => {
+                        match maybe_delivery {
+                            Ok(ctx) => {
+                                if let Err(panic) = std::panic::AssertUnwindSafe(delivery_callback.call(ctx))
+                                    .catch_unwind()
+                                    .await
+                                {
+                                    tracing::error!("delivery_callback panicked: {:?}", panic);
+                                }
+                            }
+                            Err(_) => break
+                        }
+                    }
                     _ = stop_rx.recv() => {
-                        tracing::debug!("error-callback worker finished");
+                        tracing::debug!("callback worker finished");
                         break
                     }
                 }
@@ -76,6 +92,7 @@ impl ProducerDispatcher {
                 core.clone(),
                 config.clone(),
                 err_tx.clone(),
+                delivery_tx.clone(),
                 stop_rx,
             ));
         }
@@ -211,6 +228,18 @@ impl ProducerDispatcher {
             .await
     }
 
+    /// Flushes all shards and waits until the messages buffered so far have been sent.
+    ///
+    /// Outcomes are reported through the delivery and error callbacks.
+    pub async fn flush(&self) -> Result<(), MessengerError> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(MessengerError::ProducerClosed);
+        }
+
+        futures::future::try_join_all(self.shards.iter().map(|shard| shard.flush())).await?;
+        Ok(())
+    }
+
     pub async fn shutdown(mut self) {
         if self.closed.swap(true, Ordering::Relaxed) {
             return;
@@ -225,7 +254,7 @@ impl ProducerDispatcher {
         }
 
         if let Err(e) = self._join_handle.await {
-            tracing::error!("error-worker panicked: {e:?}");
+            tracing::error!("callback-worker panicked: {e:?}");
         }
     }
 }
@@ -240,6 +269,7 @@ mod tests {
     use tokio::time::sleep;
 
     use crate::clients::producer::MockProducerCoreBackend;
+    use crate::clients::producer_delivery_callback::DeliveryCallback;
     use crate::clients::producer_error_callback::ErrorCallback;
     use crate::clients::producer_sharding::Sharding;
 
@@ -463,4 +493,52 @@ mod tests {
         assert_eq!(sharding_called.load(Ordering::SeqCst), 1);
         assert_eq!(error_called.load(Ordering::SeqCst), 1);
     }
+
+    #[derive(Clone, Debug)]
+    struct TestDeliveryCallback {
+        delivered: Arc<AtomicUsize>,
+    }
+
+    impl DeliveryCallback for TestDeliveryCallback {
+        fn call(&self, ctx: DeliveryCtx) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {
+            self.delivered.fetch_add(ctx.messages_count, Ordering::SeqCst);
+            Box::pin(async {})
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush_delivers_buffered_messages() {
+        let mut mock = MockProducerCoreBackend::new();
+        mock.expect_send_internal()
+            .times(1)
+            .withf(|_, _, msgs, _| msgs.len() == 3)
+            .returning(|_, _, _, _| Box::pin(async { Ok(()) }));
+
+        let delivered = Arc::new(AtomicUsize::new(0));
+        let config = BackgroundConfig::builder()
+            .num_shards(1)
+            .linger_time(Duration::from_secs(60).into())
+            .delivery_callback(Arc::new(Box::new(TestDeliveryCallback {
+                delivered: delivered.clone(),
+            })))
+            .build();
+
+        let dispatcher = ProducerDispatcher::new(Arc::new(mock), config);
+        for _ in 0..3 {
+            dispatcher
+                .dispatch(
+                    vec![dummy_message(10)],
+                    dummy_identifier(),
+                    dummy_identifier(),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+
+        dispatcher.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(delivered.load(Ordering::SeqCst), 3);
+    }
 }
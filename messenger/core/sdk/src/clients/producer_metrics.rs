@@ -0,0 +1,127 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Batch counters collected by a producer across all of its sends.
+#[derive(Debug, Default)]
+pub struct ProducerMetrics {
+    batches_sent: AtomicU64,
+    batches_failed: AtomicU64,
+    messages_sent: AtomicU64,
+    payload_bytes: AtomicU64,
+    compressed_payload_bytes: AtomicU64,
+    max_batch_messages: AtomicU64,
+    max_batch_bytes: AtomicU64,
+}
+
+/// Point-in-time view of [`ProducerMetrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProducerMetricsSnapshot {
+    /// Number of batches accepted by the server.
+    pub batches_sent: u64,
+    /// Number of batches that failed after all retries.
+    pub batches_failed: u64,
+    /// Number of messages accepted by the server.
+    pub messages_sent: u64,
+    /// Payload bytes before compression.
+    pub payload_bytes: u64,
+    /// Payload bytes actually sent, after compression.
+    pub compressed_payload_bytes: u64,
+    /// Largest batch sent, in messages.
+    pub max_batch_messages: u64,
+    /// Largest batch sent, in payload bytes before compression.
+    pub max_batch_bytes: u64,
+}
+
+impl ProducerMetricsSnapshot {
+    /// Average number of messages per batch, `0.0` before the first batch.
+    pub fn avg_batch_messages(&self) -> f64 {
+        if self.batches_sent == 0 {
+            return 0.0;
+        }
+        self.messages_sent as f64 / self.batches_sent as f64
+    }
+
+    /// Average payload bytes per batch before compression, `0.0` before the first batch.
+    pub fn avg_batch_bytes(&self) -> f64 {
+        if self.batches_sent == 0 {
+            return 0.0;
+        }
+        self.payload_bytes as f64 / self.batches_sent as f64
+    }
+
+    /// Compressed to uncompressed payload size, `1.0` when nothing was compressed.
+    pub fn compression_ratio(&self) -> f64 {
+        if self.payload_bytes == 0 {
+            return 1.0;
+        }
+        self.compressed_payload_bytes as f64 / self.payload_bytes as f64
+    }
+}
+
+impl ProducerMetrics {
+    pub(crate) fn record_batch(&self, messages: u64, payload_bytes: u64, compressed_bytes: u64) {
+        self.batches_sent.fetch_add(1, Ordering::Relaxed);
+        self.messages_sent.fetch_add(messages, Ordering::Relaxed);
+        self.payload_bytes.fetch_add(payload_bytes, Ordering::Relaxed);
+        self.compressed_payload_bytes
+            .fetch_add(compressed_bytes, Ordering::Relaxed);
+        self.max_batch_messages.fetch_max(messages, Ordering::Relaxed);
+        self.max_batch_bytes.fetch_max(payload_bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_failed_batch(&self) {
+        self.batches_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ProducerMetricsSnapshot {
+        ProducerMetricsSnapshot {
+            batches_sent: self.batches_sent.load(Ordering::Relaxed),
+            batches_failed: self.batches_failed.load(Ordering::Relaxed),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            payload_bytes: self.payload_bytes.load(Ordering::Relaxed),
+            compressed_payload_bytes: self.compressed_payload_bytes.load(Ordering::Relaxed),
+            max_batch_messages: self.max_batch_messages.load(Ordering::Relaxed),
+            max_batch_bytes: self.max_batch_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_aggregate_batches() {
+        let metrics = ProducerMetrics::default();
+        metrics.record_batch(10, 1000, 400);
+        metrics.record_batch(30, 3000, 1200);
+        metrics.record_failed_batch();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.batches_sent, 2);
+        assert_eq!(snapshot.batches_failed, 1);
+        assert_eq!(snapshot.messages_sent, 40);
+        assert_eq!(snapshot.max_batch_messages, 30);
+        assert_eq!(snapshot.max_batch_bytes, 3000);
+        assert_eq!(snapshot.avg_batch_messages(), 20.0);
+        assert_eq!(snapshot.avg_batch_bytes(), 2000.0);
+        assert_eq!(snapshot.compression_ratio(), 0.4);
+    }
+}
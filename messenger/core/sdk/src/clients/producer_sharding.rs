@@ -15,13 +15,15 @@
  * specific language governing permissions and limitations
  * under the License.
  */
+use crate::clients::MAX_BATCH_LENGTH;
 use crate::clients::producer::ProducerCoreBackend;
 use crate::clients::producer_config::BackgroundConfig;
+use crate::clients::producer_delivery_callback::DeliveryCtx;
 use crate::clients::producer_error_callback::ErrorCtx;
 use messenger_common::{Identifier, MessengerByteSize, MessengerError, MessengerMessage, Partitioning, Sizeable};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use tokio::sync::{OwnedSemaphorePermit, broadcast};
+use tokio::sync::{OwnedSemaphorePermit, broadcast, oneshot};
 use tokio::task::JoinHandle;
 use tracing::{debug, error};
 
@@ -68,6 +70,16 @@ pub struct ShardMessage {
     pub partitioning: Option<Arc<Partitioning>>,
 }
 
+impl ShardMessage {
+    /// Whether both messages target the same stream, topic and partitioning,
+    /// in which case they can be sent to the server as a single batch.
+    fn has_same_destination(&self, other: &ShardMessage) -> bool {
+        self.stream == other.stream
+            && self.topic == other.topic
+            && self.partitioning == other.partitioning
+    }
+}
+
 impl Sizeable for ShardMessage {
     fn get_size_bytes(&self) -> MessengerByteSize {
         let mut total = MessengerByteSize::new(0);
@@ -100,8 +112,13 @@ impl ShardMessageWithPermits {
     }
 }
 
+enum ShardCommand {
+    Send(ShardMessageWithPermits),
+    Flush(oneshot::Sender<()>),
+}
+
 pub struct Shard {
-    tx: flume::Sender<ShardMessageWithPermits>,
+    tx: flume::Sender<ShardCommand>,
     closed: Arc<AtomicBool>,
     pub(crate) _handle: JoinHandle<()>,
 }
@@ -111,9 +128,10 @@ impl Shard {
         core: Arc<impl ProducerCoreBackend>,
         config: Arc<BackgroundConfig>,
         err_sender: flume::Sender<ErrorCtx>,
+        delivery_sender: flume::Sender<DeliveryCtx>,
         mut stop_rx: broadcast::Receiver<()>,
     ) -> Self {
-        let (tx, rx) = flume::bounded::<ShardMessageWithPermits>(256);
+        let (tx, rx) = flume::bounded::<ShardCommand>(256);
         let closed = Arc::new(AtomicBool::new(false));
 
         let closed_clone = closed.clone();
@@ -125,9 +143,9 @@ impl Shard {
             loop {
                 let deadline = last_flush + config.linger_time.get_duration();
                 tokio::select! {
-                    maybe_msg = rx.recv_async() => {
-                        match maybe_msg {
-                            Ok(msg) => {
+                    maybe_command = rx.recv_async() => {
+                        match maybe_command {
+                            Ok(ShardCommand::Send(msg)) => {
                                 buffer_bytes += msg.inner.get_size_bytes().as_bytes_usize();
                                 buffer.push(msg);
                                 debug!(
@@ -148,7 +166,7 @@ impl Shard {
                                         exceed_batch_size,
                                     );
 
-                                    Self::flush_buffer(&core, &mut buffer, &mut buffer_bytes, &err_sender).await;
+                                    Self::flush_buffer(&core, &mut buffer, &mut buffer_bytes, &err_sender, &delivery_sender).await;
                                     debug!(
                                         new_buffer_len = buffer.len(),
                                         new_buffer_bytes = buffer_bytes,
@@ -158,19 +176,27 @@ impl Shard {
                                     last_flush = tokio::time::Instant::now();
                                 }
                             }
+                            Ok(ShardCommand::Flush(ack)) => {
+                                if !buffer.is_empty() {
+                                    debug!(buffer_len = buffer.len(), buffer_bytes, "Flushing buffer on request");
+                                    Self::flush_buffer(&core, &mut buffer, &mut buffer_bytes, &err_sender, &delivery_sender).await;
+                                    last_flush = tokio::time::Instant::now();
+                                }
+                                let _ = ack.send(());
+                            }
                             Err(_) => break,
                         }
                     }
                     _ = tokio::time::sleep_until(deadline) => {
                         if !buffer.is_empty() {
-                            Self::flush_buffer(&core, &mut buffer, &mut buffer_bytes, &err_sender).await;
+                            Self::flush_buffer(&core, &mut buffer, &mut buffer_bytes, &err_sender, &delivery_sender).await;
                             last_flush = tokio::time::Instant::now();
                         }
                     }
                     _ = stop_rx.recv() => {
                         closed_clone.store(true, Ordering::Release);
                         if !buffer.is_empty() {
-                            Self::flush_buffer(&core, &mut buffer, &mut buffer_bytes, &err_sender).await;
+                            Self::flush_buffer(&core, &mut buffer, &mut buffer_bytes, &err_sender, &delivery_sender).await;
                         }
                         break;
                     }
@@ -185,23 +211,71 @@ impl Shard {
         }
     }
 
+    /// Sends the buffered messages, coalescing consecutive entries that share
+    /// a destination into a single request instead of one request per entry.
     async fn flush_buffer(
         core: &Arc<impl ProducerCoreBackend>,
         buffer: &mut Vec<ShardMessageWithPermits>,
         buffer_bytes: &mut usize,
         err_sender: &flume::Sender<ErrorCtx>,
+        delivery_sender: &flume::Sender<DeliveryCtx>,
     ) {
-        for msg in buffer.drain(..) {
-            let result = core
-                .send_internal(
-                    &msg.inner.stream,
-                    &msg.inner.topic,
-                    msg.inner.messages,
-                    msg.inner.partitioning.clone(),
-                )
-                .await;
-
-            if let Err(err) = result {
+        let mut pending = buffer.drain(..).peekable();
+        while let Some(first) = pending.next() {
+            let mut messages_count = first.inner.messages.len();
+            let mut batch = vec![first];
+            while let Some(next) = pending.next_if(|next| {
+                next.inner.has_same_destination(&batch[0].inner)
+                    && messages_count + next.inner.messages.len() <= MAX_BATCH_LENGTH
+            }) {
+                messages_count += next.inner.messages.len();
+                batch.push(next);
+            }
+
+            Self::send_batch(core, batch, messages_count, err_sender, delivery_sender).await;
+        }
+        *buffer_bytes = 0;
+    }
+
+    async fn send_batch(
+        core: &Arc<impl ProducerCoreBackend>,
+        mut batch: Vec<ShardMessageWithPermits>,
+        messages_count: usize,
+        err_sender: &flume::Sender<ErrorCtx>,
+        delivery_sender: &flume::Sender<DeliveryCtx>,
+    ) {
+        let stream = batch[0].inner.stream.clone();
+        let topic = batch[0].inner.topic.clone();
+        let partitioning = batch[0].inner.partitioning.clone();
+
+        // Permits stay with the batch until the request completes.
+        let mut messages = Vec::with_capacity(messages_count);
+        for msg in batch.iter_mut() {
+            messages.append(&mut msg.inner.messages);
+        }
+        let payload_bytes = messages.iter().map(|m| m.payload.len() as u64).sum::<u64>();
+
+        debug!(
+            entries = batch.len(),
+            messages_count, payload_bytes, "Sending coalesced batch"
+        );
+
+        let result = core
+            .send_internal(&stream, &topic, messages, partitioning.clone())
+            .await;
+
+        match result {
+            Ok(()) => {
+                let ctx = DeliveryCtx {
+                    stream,
+                    topic,
+                    partitioning,
+                    messages_count,
+                    payload_bytes,
+                };
+                let _ = delivery_sender.send_async(ctx).await;
+            }
+            Err(err) => {
                 if let MessengerError::ProducerSendFailed {
                     failed,
                     cause,
@@ -211,11 +285,11 @@ impl Shard {
                 {
                     let ctx = ErrorCtx {
                         cause: cause.to_owned(),
-                        stream: msg.inner.stream,
+                        stream,
                         stream_name: stream_name.clone(),
-                        topic: msg.inner.topic,
+                        topic,
                         topic_name: topic_name.clone(),
-                        partitioning: msg.inner.partitioning,
+                        partitioning,
                         messages: failed.clone(),
                     };
                     let _ = err_sender.send_async(ctx).await;
@@ -224,7 +298,6 @@ impl Shard {
                 }
             }
         }
-        *buffer_bytes = 0;
     }
 
     pub(crate) async fn send(&self, message: ShardMessageWithPermits) -> Result<(), MessengerError> {
@@ -232,10 +305,30 @@ impl Shard {
             return Err(MessengerError::ProducerClosed);
         }
 
-        self.tx.send_async(message).await.map_err(|e| {
-            error!("Failed to send_async: {e}");
-            MessengerError::BackgroundSendError
-        })
+        self.tx
+            .send_async(ShardCommand::Send(message))
+            .await
+            .map_err(|e| {
+                error!("Failed to send_async: {e}");
+                MessengerError::BackgroundSendError
+            })
+    }
+
+    /// Flushes everything buffered so far and waits until it has been sent.
+    pub(crate) async fn flush(&self) -> Result<(), MessengerError> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(MessengerError::ProducerClosed);
+        }
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.tx
+            .send_async(ShardCommand::Flush(ack_tx))
+            .await
+            .map_err(|e| {
+                error!("Failed to request flush: {e}");
+                MessengerError::BackgroundSendError
+            })?;
+        ack_rx.await.map_err(|_| MessengerError::BackgroundSendError)
     }
 }
 
@@ -263,7 +356,8 @@ mod tests {
     async fn test_shard_flushes_by_batch_length() {
         let mut mock = MockProducerCoreBackend::new();
         mock.expect_send_internal()
-            .times(10)
+            .times(1)
+            .withf(|_, _, msgs, _| msgs.len() == 10)
             .returning(|_, _, _, _| Box::pin(async { Ok(()) }));
 
         let bb = BackgroundConfig::builder()
@@ -278,7 +372,13 @@ mod tests {
         );
 
         let (_stop_tx, stop_rx) = broadcast::channel(1);
-        let shard = Shard::new(Arc::new(mock), config, flume::unbounded().0, stop_rx);
+        let shard = Shard::new(
+            Arc::new(mock),
+            config,
+            flume::unbounded().0,
+            flume::unbounded().0,
+            stop_rx,
+        );
 
         for _ in 0..10 {
             let message = ShardMessage {
@@ -317,7 +417,13 @@ mod tests {
         );
 
         let (_stop_tx, stop_rx) = broadcast::channel(1);
-        let shard = Shard::new(Arc::new(mock), config, flume::unbounded().0, stop_rx);
+        let shard = Shard::new(
+            Arc::new(mock),
+            config,
+            flume::unbounded().0,
+            flume::unbounded().0,
+            stop_rx,
+        );
 
         let message = ShardMessage {
             stream: dummy_identifier(),
@@ -358,7 +464,13 @@ mod tests {
         );
 
         let (_stop_tx, stop_rx) = broadcast::channel(1);
-        let shard = Shard::new(Arc::new(mock), config, flume::unbounded().0, stop_rx);
+        let shard = Shard::new(
+            Arc::new(mock),
+            config,
+            flume::unbounded().0,
+            flume::unbounded().0,
+            stop_rx,
+        );
 
         let message = ShardMessage {
             stream: dummy_identifier(),
@@ -401,7 +513,7 @@ mod tests {
         );
 
         let (_stop_tx, stop_rx) = broadcast::channel(1);
-        let shard = Shard::new(Arc::new(mock), config, err_tx, stop_rx);
+        let shard = Shard::new(Arc::new(mock), config, err_tx, flume::unbounded().0, stop_rx);
 
         let message = ShardMessage {
             stream: dummy_identifier(),
@@ -423,7 +535,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_shard_send_error_on_closed_channel() {
-        let (tx, rx) = flume::bounded::<ShardMessageWithPermits>(1);
+        let (tx, rx) = flume::bounded::<ShardCommand>(1);
         drop(rx);
 
         let shard = Shard {
@@ -452,4 +564,95 @@ mod tests {
         let result = shard.send(wrapped).await;
         assert!(matches!(result, Err(MessengerError::BackgroundSendError)));
     }
+
+    #[tokio::test]
+    async fn test_shard_coalesces_only_same_destination() {
+        let mut mock = MockProducerCoreBackend::new();
+        mock.expect_send_internal()
+            .times(2)
+            .withf(|_, _, msgs, _| msgs.len() == 2)
+            .returning(|_, _, _, _| Box::pin(async { Ok(()) }));
+
+        let config = Arc::new(BackgroundConfig::builder().batch_length(4).build());
+        let (permit_bytes, permit_slot) = (
+            Arc::new(Semaphore::new(10_000)),
+            Arc::new(Semaphore::new(100)),
+        );
+
+        let (delivery_tx, delivery_rx) = flume::unbounded();
+        let (_stop_tx, stop_rx) = broadcast::channel(1);
+        let shard = Shard::new(
+            Arc::new(mock),
+            config,
+            flume::unbounded().0,
+            delivery_tx,
+            stop_rx,
+        );
+
+        for topic_id in [1, 1, 2, 2] {
+            let message = ShardMessage {
+                stream: dummy_identifier(),
+                topic: Arc::new(Identifier::numeric(topic_id).unwrap()),
+                messages: vec![dummy_message(1)],
+                partitioning: None,
+            };
+            let wrapped = ShardMessageWithPermits::new(
+                message,
+                permit_bytes.clone().acquire_many_owned(1).await.unwrap(),
+                permit_slot.clone().acquire_owned().await.unwrap(),
+            );
+            shard.send(wrapped).await.unwrap();
+        }
+
+        for _ in 0..2 {
+            let delivery = delivery_rx.recv_async().await.unwrap();
+            assert_eq!(delivery.messages_count, 2);
+            assert_eq!(delivery.payload_bytes, 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shard_flush_sends_buffered_messages() {
+        let mut mock = MockProducerCoreBackend::new();
+        mock.expect_send_internal()
+            .times(1)
+            .returning(|_, _, _, _| Box::pin(async { Ok(()) }));
+
+        let config = Arc::new(
+            BackgroundConfig::builder()
+                .linger_time(MessengerDuration::new_from_secs(60))
+                .build(),
+        );
+        let (permit_bytes, permit_slot) = (
+            Arc::new(Semaphore::new(10_000)),
+            Arc::new(Semaphore::new(100)),
+        );
+
+        let (delivery_tx, delivery_rx) = flume::unbounded();
+        let (_stop_tx, stop_rx) = broadcast::channel(1);
+        let shard = Shard::new(
+            Arc::new(mock),
+            config,
+            flume::unbounded().0,
+            delivery_tx,
+            stop_rx,
+        );
+
+        let message = ShardMessage {
+            stream: dummy_identifier(),
+            topic: dummy_identifier(),
+            messages: vec![dummy_message(1)],
+            partitioning: None,
+        };
+        let wrapped = ShardMessageWithPermits::new(
+            message,
+            permit_bytes.clone().acquire_many_owned(1).await.unwrap(),
+            permit_slot.clone().acquire_owned().await.unwrap(),
+        );
+        shard.send(wrapped).await.unwrap();
+        shard.flush().await.unwrap();
+
+        let delivery = delivery_rx.try_recv().unwrap();
+        assert_eq!(delivery.messages_count, 1);
+    }
 }
@@ -96,6 +96,7 @@ impl<'a> MessengerConsumerMessageExt<'a> for MessengerConsumer {
                                 trace!("Message at offset: {message_offset}/{current_offset}, partition: {partition_id} has been handled by consumer: {name} on topic: {topic} and stream: {stream}",
                                     name = self.name(), topic = self.topic(), stream = self.stream());
                             }
+                            self.ack(partition_id, message_offset);
 
                             if store_offset_after_each_message {
                                 trace!("Storing offset: {message_offset}/{current_offset}, partition: {partition_id}, after each message for consumer: {name} on topic: {topic} and stream: {stream}",
@@ -36,9 +36,13 @@ pub use crate::clients::consumer::{
     AutoCommit, AutoCommitAfter, AutoCommitWhen, MessengerConsumer, ReceivedMessage,
 };
 pub use crate::clients::consumer_builder::MessengerConsumerBuilder;
+pub use crate::clients::consumer_flow_control::{ConsumerFlowControl, FlowControlConfig};
+pub use crate::clients::consumer_metrics::ConsumerMetricsSnapshot;
 pub use crate::clients::producer::MessengerProducer;
 pub use crate::clients::producer_builder::MessengerProducerBuilder;
 pub use crate::clients::producer_config::{BackgroundConfig, DirectConfig};
+pub use crate::clients::producer_delivery_callback::{DeliveryCallback, DeliveryCtx};
+pub use crate::clients::producer_metrics::ProducerMetricsSnapshot;
 pub use crate::consumer_ext::MessengerConsumerMessageExt;
 pub use crate::stream_builder::MessengerConsumerConfig;
 pub use crate::stream_builder::MessengerStreamConsumer;
@@ -116,7 +116,10 @@ impl BinaryTransport for QuicClient {
         let error = result.unwrap_err();
         if !matches!(
             error,
-            MessengerError::Disconnected | MessengerError::EmptyResponse | MessengerError::Unauthenticated
+            MessengerError::Disconnected
+                | MessengerError::EmptyResponse
+                | MessengerError::Unauthenticated
+                | MessengerError::ServerShuttingDown
         ) {
             return Err(error);
         }
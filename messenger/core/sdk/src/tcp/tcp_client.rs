@@ -117,6 +117,7 @@ impl BinaryTransport for TcpClient {
                 | MessengerError::EmptyResponse
                 | MessengerError::Unauthenticated
                 | MessengerError::StaleClient
+                | MessengerError::ServerShuttingDown
         ) {
             return Err(error);
         }
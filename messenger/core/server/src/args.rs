@@ -61,6 +61,7 @@ TRANSPORT PROTOCOLS:
     - TCP (binary protocol): High-performance, low-latency (default: 127.0.0.1:8090)
     - QUIC: Modern UDP-based protocol with built-in encryption (default: 127.0.0.1:8080)
     - HTTP: RESTful API for web integration (default: 127.0.0.1:3000, disabled by default)
+    - WebSocket: browser consumers on the HTTP server, with Pixelle access tokens (MESSENGER_HTTP_WEBSOCKET_ENABLED=true)
 
 GETTING STARTED:
     1. Start the server: messenger-server
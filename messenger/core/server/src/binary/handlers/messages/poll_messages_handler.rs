@@ -33,6 +33,10 @@ use tracing::{debug, trace};
 pub struct MessengerPollMetadata {
     pub partition_id: u32,
     pub current_offset: u64,
+    /// Offset of the last message read, including the expired messages left out of the response.
+    pub last_polled_offset: Option<u64>,
+    /// Count of messages left out of the response because their time to live has passed.
+    pub expired_messages_count: u32,
 }
 
 impl MessengerPollMetadata {
@@ -40,6 +44,8 @@ impl MessengerPollMetadata {
         Self {
             partition_id,
             current_offset,
+            last_polled_offset: None,
+            expired_messages_count: 0,
         }
     }
 }
@@ -137,11 +137,23 @@ impl BackgroundServerCommand<MaintainMessagesCommand> for MaintainMessagesExecut
 
                 let deleted_expired_segments = expired_segments.unwrap();
                 let deleted_oldest_segments = oldest_segments.unwrap();
+                for (partition_id, messages_count) in
+                    &deleted_expired_segments.messages_count_per_partition
+                {
+                    system.metrics.record_expired_deleted(
+                        &stream.name,
+                        topic,
+                        *partition_id,
+                        *messages_count,
+                    );
+                }
+
                 let deleted_segments = HandledSegments {
                     segments_count: deleted_expired_segments.segments_count
                         + deleted_oldest_segments.segments_count,
                     messages_count: deleted_expired_segments.messages_count
                         + deleted_oldest_segments.messages_count,
+                    messages_count_per_partition: Vec::new(),
                 };
 
                 if deleted_segments.segments_count == 0 {
@@ -429,6 +441,7 @@ struct SegmentsToHandle {
 struct HandledSegments {
     pub segments_count: u32,
     pub messages_count: u64,
+    pub messages_count_per_partition: Vec<(u32, u64)>,
 }
 
 impl HandledSegments {
@@ -436,6 +449,7 @@ impl HandledSegments {
         Self {
             segments_count: 0,
             messages_count: 0,
+            messages_count_per_partition: Vec::new(),
         }
     }
 }
@@ -517,19 +531,24 @@ async fn delete_segments(
 
     let mut segments_count = 0;
     let mut messages_count = 0;
+    let mut messages_count_per_partition = Vec::with_capacity(segments_to_delete.len());
     for segment_to_delete in segments_to_delete {
         match topic.get_partition(segment_to_delete.partition_id) {
             Ok(partition) => {
                 let mut partition = partition.write().await;
                 let mut last_end_offset = 0;
+                let mut partition_messages_count = 0;
                 for start_offset in &segment_to_delete.start_offsets {
                     let deleted_segment = partition.delete_segment(*start_offset).await.with_error_context(|error| {
                         format!("CHANNEL_COMMAND - failed to delete segment for stream with ID: {}, topic with ID: {}. {error}", topic.stream_id, topic.topic_id)
                     })?;
                     last_end_offset = deleted_segment.end_offset;
                     segments_count += 1;
-                    messages_count += deleted_segment.messages_count as u64;
+                    partition_messages_count += deleted_segment.messages_count as u64;
                 }
+                messages_count += partition_messages_count;
+                messages_count_per_partition
+                    .push((segment_to_delete.partition_id, partition_messages_count));
 
                 if partition.get_segments().is_empty() {
                     let start_offset = last_end_offset + 1;
@@ -551,5 +570,6 @@ async fn delete_segments(
     Ok(HandledSegments {
         segments_count,
         messages_count,
+        messages_count_per_partition,
     })
 }
@@ -20,18 +20,19 @@ use super::system::MemoryPoolConfig;
 use super::tcp::TcpSocketConfig;
 use crate::configs::http::{
     HttpConfig, HttpCorsConfig, HttpJwtConfig, HttpMetricsConfig, HttpTlsConfig,
+    HttpWebSocketConfig,
 };
 use crate::configs::quic::{QuicCertificateConfig, QuicConfig};
 use crate::configs::server::{
     ArchiverConfig, DataMaintenanceConfig, HeartbeatConfig, MessageSaverConfig,
     MessagesMaintenanceConfig, PersonalAccessTokenCleanerConfig, PersonalAccessTokenConfig,
-    ServerConfig, StateMaintenanceConfig, TelemetryConfig, TelemetryLogsConfig,
+    ServerConfig, ShutdownConfig, StateMaintenanceConfig, TelemetryConfig, TelemetryLogsConfig,
     TelemetryTracesConfig,
 };
 use crate::configs::system::{
-    BackupConfig, CompatibilityConfig, CompressionConfig, EncryptionConfig, LoggingConfig,
-    MessageDeduplicationConfig, PartitionConfig, RecoveryConfig, RuntimeConfig, SegmentConfig,
-    StateConfig, StreamConfig, SystemConfig, TopicConfig,
+    BackupConfig, CompatibilityConfig, CompressionConfig, EncryptionConfig, HeaderIndexingConfig,
+    LoggingConfig, MessageDeduplicationConfig, PartitionConfig, RecoveryConfig, RuntimeConfig, SegmentConfig,
+    StateConfig, StreamConfig, SystemConfig, TenancyConfig, TopicConfig, TopicMetricsConfig,
 };
 use crate::configs::tcp::{TcpConfig, TcpTlsConfig};
 use messenger_common::MessengerByteSize;
@@ -56,6 +57,7 @@ impl Default for ServerConfig {
             tcp: TcpConfig::default(),
             http: HttpConfig::default(),
             telemetry: TelemetryConfig::default(),
+            shutdown: ShutdownConfig::default(),
         }
     }
 }
@@ -183,6 +185,7 @@ impl Default for HttpConfig {
             jwt: HttpJwtConfig::default(),
             metrics: HttpMetricsConfig::default(),
             tls: HttpTlsConfig::default(),
+            websocket: HttpWebSocketConfig::default(),
         }
     }
 }
@@ -255,6 +258,22 @@ impl Default for HttpJwtConfig {
     }
 }
 
+impl Default for HttpWebSocketConfig {
+    fn default() -> HttpWebSocketConfig {
+        HttpWebSocketConfig {
+            enabled: false,
+            path: "/ws".to_string(),
+            username: "browser".to_string(),
+            jwks_url: "http://localhost:8084/.well-known/jwks.json".to_string(),
+            jwks_refresh_interval: MessengerDuration::new_from_secs(60),
+            auth_timeout: MessengerDuration::new_from_secs(10),
+            poll_interval: MessengerDuration::new(Duration::from_millis(100)),
+            max_subscriptions: 16,
+            max_batch_size: 1000,
+        }
+    }
+}
+
 impl Default for HttpMetricsConfig {
     fn default() -> HttpMetricsConfig {
         HttpMetricsConfig {
@@ -324,6 +343,9 @@ impl Default for SystemConfig {
             message_deduplication: MessageDeduplicationConfig::default(),
             recovery: RecoveryConfig::default(),
             memory_pool: MemoryPoolConfig::default(),
+            tenancy: TenancyConfig::default(),
+            header_indexing: HeaderIndexingConfig::default(),
+            topic_metrics: TopicMetricsConfig::default(),
         }
     }
 }
@@ -360,6 +382,15 @@ impl Default for HeartbeatConfig {
     }
 }
 
+impl Default for ShutdownConfig {
+    fn default() -> ShutdownConfig {
+        ShutdownConfig {
+            drain_period: MessengerDuration::new_from_secs(5),
+            timeout: MessengerDuration::new_from_secs(30),
+        }
+    }
+}
+
 impl Default for RuntimeConfig {
     fn default() -> RuntimeConfig {
         RuntimeConfig {
@@ -487,6 +518,27 @@ impl Default for MessageDeduplicationConfig {
     }
 }
 
+impl Default for HeaderIndexingConfig {
+    fn default() -> HeaderIndexingConfig {
+        HeaderIndexingConfig {
+            enabled: false,
+            max_entries: 100_000,
+            topics: Vec::new(),
+        }
+    }
+}
+
+impl Default for TopicMetricsConfig {
+    fn default() -> TopicMetricsConfig {
+        TopicMetricsConfig {
+            enabled: true,
+            per_partition: true,
+            max_topics: 1000,
+            max_consumer_series: 10_000,
+        }
+    }
+}
+
 impl Default for RecoveryConfig {
     fn default() -> RecoveryConfig {
         RecoveryConfig {
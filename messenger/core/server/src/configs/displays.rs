@@ -19,12 +19,15 @@
 use crate::configs::quic::{QuicCertificateConfig, QuicConfig};
 use crate::configs::server::{
     ArchiverConfig, DataMaintenanceConfig, DiskArchiverConfig, HeartbeatConfig,
-    MessagesMaintenanceConfig, S3ArchiverConfig, StateMaintenanceConfig, TelemetryConfig,
-    TelemetryLogsConfig, TelemetryTracesConfig,
+    MessagesMaintenanceConfig, S3ArchiverConfig, ShutdownConfig, StateMaintenanceConfig,
+    TelemetryConfig, TelemetryLogsConfig, TelemetryTracesConfig,
 };
 use crate::configs::system::MessageDeduplicationConfig;
 use crate::configs::{
-    http::{HttpConfig, HttpCorsConfig, HttpJwtConfig, HttpMetricsConfig, HttpTlsConfig},
+    http::{
+        HttpConfig, HttpCorsConfig, HttpJwtConfig, HttpMetricsConfig, HttpTlsConfig,
+        HttpWebSocketConfig,
+    },
     server::{MessageSaverConfig, ServerConfig},
     system::{
         CompressionConfig, EncryptionConfig, LoggingConfig, PartitionConfig, SegmentConfig,
@@ -38,14 +41,15 @@ impl Display for HttpConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{{ enabled: {}, address: {}, max_request_size: {}, cors: {}, jwt: {}, metrics: {}, tls: {} }}",
+            "{{ enabled: {}, address: {}, max_request_size: {}, cors: {}, jwt: {}, metrics: {}, tls: {}, websocket: {} }}",
             self.enabled,
             self.address,
             self.max_request_size,
             self.cors,
             self.jwt,
             self.metrics,
-            self.tls
+            self.tls,
+            self.websocket
         )
     }
 }
@@ -76,6 +80,24 @@ impl Display for HttpJwtConfig {
     }
 }
 
+impl Display for HttpWebSocketConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{ enabled: {}, path: {}, username: {}, jwks_url: {}, jwks_refresh_interval: {}, auth_timeout: {}, poll_interval: {}, max_subscriptions: {}, max_batch_size: {} }}",
+            self.enabled,
+            self.path,
+            self.username,
+            self.jwks_url,
+            self.jwks_refresh_interval,
+            self.auth_timeout,
+            self.poll_interval,
+            self.max_subscriptions,
+            self.max_batch_size
+        )
+    }
+}
+
 impl Display for HttpMetricsConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -206,7 +228,7 @@ impl Display for ServerConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{{ data_maintenance: {}, message_saver: {}, heartbeat: {}, system: {}, quic: {}, tcp: {}, http: {}, telemetry: {} }}",
+            "{{ data_maintenance: {}, message_saver: {}, heartbeat: {}, system: {}, quic: {}, tcp: {}, http: {}, telemetry: {}, shutdown: {} }}",
             self.data_maintenance,
             self.message_saver,
             self.heartbeat,
@@ -214,7 +236,8 @@ impl Display for ServerConfig {
             self.quic,
             self.tcp,
             self.http,
-            self.telemetry
+            self.telemetry,
+            self.shutdown
         )
     }
 }
@@ -239,6 +262,16 @@ impl Display for HeartbeatConfig {
     }
 }
 
+impl Display for ShutdownConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{ drain_period: {}, timeout: {} }}",
+            self.drain_period, self.timeout
+        )
+    }
+}
+
 impl Display for EncryptionConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{{ enabled: {} }}", self.enabled)
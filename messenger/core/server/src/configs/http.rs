@@ -34,6 +34,8 @@ pub struct HttpConfig {
     pub jwt: HttpJwtConfig,
     pub metrics: HttpMetricsConfig,
     pub tls: HttpTlsConfig,
+    #[serde(default)]
+    pub websocket: HttpWebSocketConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -72,6 +74,29 @@ pub struct HttpMetricsConfig {
     pub endpoint: String,
 }
 
+/// WebSocket transport for browser consumers, authenticated with Pixelle access tokens.
+#[serde_as]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HttpWebSocketConfig {
+    pub enabled: bool,
+    /// Path of the WebSocket endpoint; the JSON Schema of its frames is served at `{path}/schema`.
+    pub path: String,
+    /// Messenger user that WebSocket sessions act as, so its permissions bound what browsers may consume.
+    pub username: String,
+    /// JSON Web Key Set of pixelle-auth, used to verify the ES256 access tokens it issues.
+    pub jwks_url: String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub jwks_refresh_interval: MessengerDuration,
+    /// How long a new connection may take to send its access token before it is closed.
+    #[serde_as(as = "DisplayFromStr")]
+    pub auth_timeout: MessengerDuration,
+    /// Pause before polling a subscription again after it found no new messages.
+    #[serde_as(as = "DisplayFromStr")]
+    pub poll_interval: MessengerDuration,
+    pub max_subscriptions: u32,
+    pub max_batch_size: u32,
+}
+
 #[derive(Debug)]
 pub enum JwtSecret {
     Default(String),
@@ -45,6 +45,8 @@ pub struct ServerConfig {
     pub tcp: TcpConfig,
     pub http: HttpConfig,
     pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
 }
 
 #[serde_as]
@@ -127,6 +129,17 @@ pub struct HeartbeatConfig {
     pub interval: MessengerDuration,
 }
 
+#[serde_as]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ShutdownConfig {
+    /// How long clients may keep consuming and committing offsets after produces are rejected.
+    #[serde_as(as = "DisplayFromStr")]
+    pub drain_period: MessengerDuration,
+    /// Upper bound for the whole shutdown sequence; past it the server exits even if the flush has not finished.
+    #[serde_as(as = "DisplayFromStr")]
+    pub timeout: MessengerDuration,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TelemetryConfig {
     pub enabled: bool,
@@ -18,6 +18,7 @@
 
 use super::cache_indexes::CacheIndexesConfig;
 use messenger_common::Confirmation;
+use messenger_common::HeaderKey;
 use messenger_common::MessengerByteSize;
 use messenger_common::MessengerExpiry;
 use messenger_common::MaxTopicSize;
@@ -42,6 +43,12 @@ pub struct SystemConfig {
     pub message_deduplication: MessageDeduplicationConfig,
     pub recovery: RecoveryConfig,
     pub memory_pool: MemoryPoolConfig,
+    #[serde(default)]
+    pub tenancy: TenancyConfig,
+    #[serde(default)]
+    pub header_indexing: HeaderIndexingConfig,
+    #[serde(default)]
+    pub topic_metrics: TopicMetricsConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -154,6 +161,81 @@ pub struct StateConfig {
     pub retry_delay: MessengerDuration,
 }
 
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TenancyConfig {
+    pub enabled: bool,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TenantConfig {
+    pub name: String,
+    /// Usernames whose credentials (including their personal access tokens) are bound to this tenant.
+    #[serde(default)]
+    pub users: Vec<String>,
+    pub max_streams: Option<u32>,
+    pub max_topics: Option<u32>,
+    pub max_size: Option<MessengerByteSize>,
+    /// Streams of this tenant that other tenants are explicitly allowed to access.
+    #[serde(default)]
+    pub shares: Vec<TenantShareConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TenantShareConfig {
+    /// Stream name without the tenant namespace.
+    pub stream: String,
+    pub tenant: String,
+    #[serde(default)]
+    pub send_messages: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HeaderIndexingConfig {
+    pub enabled: bool,
+    /// Maximum number of indexed header values kept per partition, the oldest are evicted first.
+    pub max_entries: u64,
+    pub topics: Vec<HeaderIndexTopicConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HeaderIndexTopicConfig {
+    pub stream: String,
+    pub topic: String,
+    /// User header keys indexed for this topic, e.g. `correlation-id`.
+    pub headers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TopicMetricsConfig {
+    pub enabled: bool,
+    /// Adds a `partition` label to the per-topic series, multiplying their count by the partitions.
+    pub per_partition: bool,
+    /// Maximum number of topics with their own series, the rest are reported under `_other`.
+    pub max_topics: usize,
+    /// Maximum number of consumer lag series, the rest are dropped from the output.
+    pub max_consumer_series: usize,
+}
+
+impl HeaderIndexingConfig {
+    /// Returns the header keys indexed for the given stream and topic names.
+    pub fn get_indexed_headers(&self, stream: &str, topic: &str) -> Vec<HeaderKey> {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        self.topics
+            .iter()
+            .filter(|config| config.stream == stream && config.topic == topic)
+            .flat_map(|config| config.headers.iter())
+            .filter_map(|header| HeaderKey::new(header).ok())
+            .collect()
+    }
+}
+
 impl SystemConfig {
     pub fn get_system_path(&self) -> String {
         self.path.to_string()
@@ -20,17 +20,24 @@ extern crate sysinfo;
 
 use super::server::{
     ArchiverConfig, DataMaintenanceConfig, MessageSaverConfig, MessagesMaintenanceConfig,
-    StateMaintenanceConfig, TelemetryConfig,
+    ShutdownConfig, StateMaintenanceConfig, TelemetryConfig,
+};
+use super::system::{
+    CompressionConfig, HeaderIndexingConfig, MemoryPoolConfig, PartitionConfig, TenancyConfig,
+    TopicMetricsConfig,
 };
-use super::system::{CompressionConfig, MemoryPoolConfig, PartitionConfig};
 use crate::archiver::ArchiverKindType;
 use crate::configs::COMPONENT;
+use crate::configs::http::HttpWebSocketConfig;
 use crate::configs::server::{PersonalAccessTokenConfig, ServerConfig};
 use crate::configs::system::SegmentConfig;
 use crate::server_error::ConfigError;
 use crate::streaming::segments::*;
+use crate::streaming::tenants::TENANT_NAMESPACE_SEPARATOR;
+use ahash::AHashSet;
 use error_set::ErrContext;
 use messenger_common::CompressionAlgorithm;
+use messenger_common::HeaderKey;
 use messenger_common::MessengerExpiry;
 use messenger_common::MaxTopicSize;
 use messenger_common::Validatable;
@@ -68,6 +75,27 @@ impl Validatable<ConfigError> for ServerConfig {
         self.telemetry.validate().with_error_context(|error| {
             format!("{COMPONENT} (error: {error}) - failed to validate telemetry config")
         })?;
+        self.system.tenancy.validate().with_error_context(|error| {
+            format!("{COMPONENT} (error: {error}) - failed to validate tenancy config")
+        })?;
+        self.system
+            .header_indexing
+            .validate()
+            .with_error_context(|error| {
+                format!("{COMPONENT} (error: {error}) - failed to validate header indexing config")
+            })?;
+        self.system
+            .topic_metrics
+            .validate()
+            .with_error_context(|error| {
+                format!("{COMPONENT} (error: {error}) - failed to validate topic metrics config")
+            })?;
+        self.shutdown.validate().with_error_context(|error| {
+            format!("{COMPONENT} (error: {error}) - failed to validate shutdown config")
+        })?;
+        self.http.websocket.validate().with_error_context(|error| {
+            format!("{COMPONENT} (error: {error}) - failed to validate WebSocket config")
+        })?;
 
         let topic_size = match self.system.topic.max_size {
             MaxTopicSize::Custom(size) => Ok(size.as_bytes_u64()),
@@ -129,6 +157,165 @@ impl Validatable<ConfigError> for TelemetryConfig {
     }
 }
 
+impl Validatable<ConfigError> for ShutdownConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.timeout.is_zero() {
+            error!("Shutdown timeout must be greater than 0.");
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        if self.drain_period.get_duration() >= self.timeout.get_duration() {
+            error!(
+                "Shutdown drain period: {} must be shorter than the shutdown timeout: {}.",
+                self.drain_period, self.timeout
+            );
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        Ok(())
+    }
+}
+
+impl Validatable<ConfigError> for HttpWebSocketConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        if !self.path.starts_with('/') || self.path == "/" {
+            error!(
+                "WebSocket path: {} must start with '/' and must not be the root path.",
+                self.path
+            );
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        if self.username.is_empty() {
+            error!("WebSocket username cannot be empty.");
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        if !self.jwks_url.starts_with("http://") && !self.jwks_url.starts_with("https://") {
+            error!("WebSocket JWKS URL: {} must be an HTTP(S) URL.", self.jwks_url);
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        if self.jwks_refresh_interval.is_zero() || self.poll_interval.is_zero() {
+            error!("WebSocket JWKS refresh interval and poll interval must be greater than 0.");
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        if self.max_subscriptions == 0 || self.max_batch_size == 0 {
+            error!("WebSocket max subscriptions and max batch size must be greater than 0.");
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        Ok(())
+    }
+}
+
+impl Validatable<ConfigError> for TenancyConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let mut tenant_names = AHashSet::new();
+        let mut usernames = AHashSet::new();
+        for tenant in &self.tenants {
+            if tenant.name.trim().is_empty() || tenant.name.contains(TENANT_NAMESPACE_SEPARATOR) {
+                error!("Invalid tenant name: '{}'.", tenant.name);
+                return Err(ConfigError::InvalidConfiguration);
+            }
+
+            if !tenant_names.insert(tenant.name.as_str()) {
+                error!("Tenant: '{}' is defined more than once.", tenant.name);
+                return Err(ConfigError::InvalidConfiguration);
+            }
+
+            for username in &tenant.users {
+                if !usernames.insert(username.as_str()) {
+                    error!("User: '{username}' is bound to more than one tenant.");
+                    return Err(ConfigError::InvalidConfiguration);
+                }
+            }
+        }
+
+        for tenant in &self.tenants {
+            for share in &tenant.shares {
+                if share.tenant == tenant.name || !tenant_names.contains(share.tenant.as_str()) {
+                    error!(
+                        "Tenant: '{}' shares stream: '{}' with invalid tenant: '{}'.",
+                        tenant.name, share.stream, share.tenant
+                    );
+                    return Err(ConfigError::InvalidConfiguration);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Validatable<ConfigError> for TopicMetricsConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        if self.max_topics == 0 {
+            error!("Topic metrics max topics must be greater than 0.");
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        Ok(())
+    }
+}
+
+impl Validatable<ConfigError> for HeaderIndexingConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        if self.max_entries == 0 {
+            error!("Header indexing max entries must be greater than 0.");
+            return Err(ConfigError::InvalidConfiguration);
+        }
+
+        let mut topics = AHashSet::new();
+        for topic in &self.topics {
+            if !topics.insert((topic.stream.as_str(), topic.topic.as_str())) {
+                error!(
+                    "Header indexes for stream: '{}', topic: '{}' are defined more than once.",
+                    topic.stream, topic.topic
+                );
+                return Err(ConfigError::InvalidConfiguration);
+            }
+
+            if topic.headers.is_empty() {
+                error!(
+                    "No headers to index are defined for stream: '{}', topic: '{}'.",
+                    topic.stream, topic.topic
+                );
+                return Err(ConfigError::InvalidConfiguration);
+            }
+
+            for header in &topic.headers {
+                if HeaderKey::new(header).is_err() {
+                    error!(
+                        "Invalid header key: '{header}' to index for stream: '{}', topic: '{}'.",
+                        topic.stream, topic.topic
+                    );
+                    return Err(ConfigError::InvalidConfiguration);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl Validatable<ConfigError> for PartitionConfig {
     fn validate(&self) -> Result<(), ConfigError> {
         if self.messages_required_to_save < 32 {
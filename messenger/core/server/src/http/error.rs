@@ -61,6 +61,7 @@ impl IntoResponse for CustomError {
                     MessengerError::InvalidAccessToken => StatusCode::UNAUTHORIZED,
                     MessengerError::InvalidPersonalAccessToken => StatusCode::UNAUTHORIZED,
                     MessengerError::Unauthorized => StatusCode::FORBIDDEN,
+                    MessengerError::ServerShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
                     _ => StatusCode::BAD_REQUEST,
                 };
                 (status_code, Json(ErrorResponse::from_error(error)))
@@ -107,6 +108,8 @@ impl ErrorResponse {
                 MessengerError::ConsumerGroupNameAlreadyExists(_, _) => Some("name".to_string()),
                 MessengerError::UserAlreadyExists => Some("username".to_string()),
                 MessengerError::PersonalAccessTokenAlreadyExists(_, _) => Some("name".to_string()),
+                MessengerError::HeaderNotIndexed(_, _, _) => Some("key".to_string()),
+                MessengerError::InvalidMessageTtl => Some("headers".to_string()),
                 _ => None,
             },
         }
@@ -25,6 +25,7 @@ use crate::http::metrics::metrics;
 use crate::http::shared::AppState;
 use crate::http::*;
 use crate::streaming::systems::system::SharedSystem;
+use crate::websocket;
 use axum::extract::DefaultBodyLimit;
 use axum::http::Method;
 use axum::{Router, middleware};
@@ -60,6 +61,13 @@ pub async fn start(config: HttpConfig, system: SharedSystem) -> SocketAddr {
         ))
         .layer(middleware::from_fn_with_state(app_state.clone(), jwt_auth));
 
+    if config.websocket.enabled {
+        // Merged after the JWT layer: WebSocket clients authenticate with Pixelle tokens instead
+        let websocket_router = websocket::router(config.websocket.clone(), app_state.system.clone());
+        app = app.merge(websocket_router);
+        info!("WebSocket consumer transport is enabled on: {}", config.websocket.path);
+    }
+
     if config.cors.enabled {
         app = app.layer(configure_cors(config.cors));
     }
@@ -22,7 +22,7 @@ use crate::http::jwt::json_web_token::Identity;
 use crate::http::shared::AppState;
 use crate::streaming::segments::{MessengerIndexesMut, MessengerMessagesBatchMut};
 use crate::streaming::session::Session;
-use crate::streaming::systems::messages::PollingArgs;
+use crate::streaming::systems::messages::{HeaderLookupArgs, PollingArgs};
 use crate::streaming::utils::PooledBuffer;
 use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
@@ -30,9 +30,11 @@ use axum::routing::get;
 use axum::{Extension, Json, Router};
 use error_set::ErrContext;
 use messenger_common::Identifier;
+use messenger_common::{HeaderKey, MessengerTimestamp};
 use messenger_common::MessengerMessagesBatch;
 use messenger_common::Validatable;
 use messenger_common::{Consumer, PollMessages, PolledMessages, SendMessages};
+use serde::Deserialize;
 use std::sync::Arc;
 use tracing::instrument;
 
@@ -42,6 +44,10 @@ pub fn router(state: Arc<AppState>) -> Router {
             "/streams/{stream_id}/topics/{topic_id}/messages",
             get(poll_messages).post(send_messages),
         )
+        .route(
+            "/streams/{stream_id}/topics/{topic_id}/messages/by-header",
+            get(get_messages_by_header),
+        )
         .route(
             "/streams/{stream_id}/topics/{topic_id}/messages/flush/{partition_id}/{fsync}",
             get(flush_unsaved_buffer),
@@ -81,6 +87,41 @@ async fn poll_messages(
     Ok(Json(polled_messages))
 }
 
+async fn get_messages_by_header(
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    Path((stream_id, topic_id)): Path<(String, String)>,
+    Query(query): Query<HeaderLookupQuery>,
+) -> Result<Json<Vec<PolledMessages>>, CustomError> {
+    let stream_id = Identifier::from_str_value(&stream_id)?;
+    let topic_id = Identifier::from_str_value(&topic_id)?;
+    let key = HeaderKey::new(&query.key)?;
+    let to = query.to.map(MessengerTimestamp::from).unwrap_or_else(MessengerTimestamp::now);
+    let args = HeaderLookupArgs::new(key, query.value, query.from.into(), to, query.count);
+
+    let system = state.system.read().await;
+    let results = system
+        .get_messages_by_header(
+            &Session::stateless(identity.user_id, identity.ip_address),
+            &stream_id,
+            &topic_id,
+            query.partition_id,
+            args,
+        )
+        .await
+        .with_error_context(|error| {
+            format!(
+                "{COMPONENT} (error: {error}) - failed to get messages by header: {}, stream ID: {stream_id}, topic ID: {topic_id}",
+                query.key
+            )
+        })?;
+    let polled_messages = results
+        .into_iter()
+        .map(|(metadata, messages)| messages.into_polled_messages(metadata))
+        .collect();
+    Ok(Json(polled_messages))
+}
+
 async fn send_messages(
     State(state): State<Arc<AppState>>,
     Extension(identity): Extension<Identity>,
@@ -146,3 +187,20 @@ fn make_mutable(batch: MessengerMessagesBatch) -> MessengerMessagesBatchMut {
     let messages_buffer_mut = PooledBuffer::from_existing(messages.into());
     MessengerMessagesBatchMut::from_indexes_and_messages(count, indexes_mut, messages_buffer_mut)
 }
+
+#[derive(Debug, Deserialize)]
+struct HeaderLookupQuery {
+    key: String,
+    value: String,
+    partition_id: Option<u32>,
+    /// Inclusive range of message timestamps in microseconds, up to now by default.
+    #[serde(default)]
+    from: u64,
+    to: Option<u64>,
+    #[serde(default = "default_header_lookup_count")]
+    count: u32,
+}
+
+fn default_header_lookup_count() -> u32 {
+    100
+}
@@ -23,6 +23,7 @@ use crate::http::jwt::json_web_token::Identity;
 use crate::http::mapper;
 use crate::http::shared::AppState;
 use crate::streaming::session::Session;
+use crate::streaming::tenants::tenant::TenantStats;
 use axum::body::Body;
 use axum::extract::{Path, State};
 use axum::http::{HeaderMap, header};
@@ -47,6 +48,7 @@ pub fn router(state: Arc<AppState>, metrics_config: &HttpMetricsConfig) -> Route
         .route("/", get(|| async { NAME }))
         .route("/ping", get(|| async { PONG }))
         .route("/stats", get(get_stats))
+        .route("/tenants", get(get_tenants))
         .route("/clients", get(get_clients))
         .route("/clients/{client_id}", get(get_client))
         .route("/snapshot", post(get_snapshot));
@@ -59,7 +61,10 @@ pub fn router(state: Arc<AppState>, metrics_config: &HttpMetricsConfig) -> Route
 
 async fn get_metrics(State(state): State<Arc<AppState>>) -> Result<String, CustomError> {
     let system = state.system.read().await;
-    Ok(system.metrics.get_formatted_output())
+    Ok(system
+        .metrics
+        .get_formatted_output(&system.get_streams())
+        .await)
 }
 
 async fn get_stats(State(state): State<Arc<AppState>>) -> Result<Json<Stats>, CustomError> {
@@ -70,6 +75,22 @@ async fn get_stats(State(state): State<Arc<AppState>>) -> Result<Json<Stats>, Cu
     Ok(Json(stats))
 }
 
+async fn get_tenants(
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Json<Vec<TenantStats>>, CustomError> {
+    let system = state.system.read().await;
+    let tenants = system
+        .get_tenants_stats(&Session::stateless(identity.user_id, identity.ip_address))
+        .with_error_context(|error| {
+            format!(
+                "{COMPONENT} (error: {error}) - failed to get tenants stats, user ID: {}",
+                identity.user_id
+            )
+        })?;
+    Ok(Json(tenants))
+}
+
 async fn get_client(
     State(state): State<Arc<AppState>>,
     Extension(identity): Extension<Identity>,
@@ -40,6 +40,7 @@ pub mod state;
 pub mod streaming;
 pub mod tcp;
 pub mod versioning;
+pub mod websocket;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const MESSENGER_ROOT_USERNAME_ENV: &str = "MESSENGER_ROOT_USERNAME";
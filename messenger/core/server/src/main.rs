@@ -173,8 +173,7 @@ async fn main() -> Result<(), ServerError> {
     }
 
     let shutdown_timestamp = Instant::now();
-    let mut system = system.write().await;
-    system.shutdown().await?;
+    system.shutdown_gracefully(&config.shutdown).await?;
     let elapsed_time = shutdown_timestamp.elapsed();
 
     info!(
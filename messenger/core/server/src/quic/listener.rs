@@ -145,6 +145,12 @@ async fn handle_stream(
 
     trace!("Received a QUIC command: {command}, payload size: {length}");
 
+    if let Some(error) = system.shutdown_state().reject_command(code) {
+        trace!("Server is shutting down, rejecting the QUIC command: {command}.");
+        sender.send_error_response(error).await?;
+        return Ok(());
+    }
+
     match command
         .handle(&mut sender, length, session.as_ref(), &system)
         .await
@@ -53,6 +53,7 @@ pub struct ConsumerGroup {
 pub enum Transport {
     Tcp,
     Quic,
+    WebSocket,
 }
 
 impl Display for Transport {
@@ -60,6 +61,7 @@ impl Display for Transport {
         match self {
             Transport::Tcp => write!(f, "TCP"),
             Transport::Quic => write!(f, "QUIC"),
+            Transport::WebSocket => write!(f, "WebSocket"),
         }
     }
 }
@@ -16,12 +16,25 @@
  * under the License.
  */
 
+use crate::configs::system::TopicMetricsConfig;
+use crate::streaming::streams::stream::Stream;
+use crate::streaming::topics::topic::Topic;
+use ahash::{AHashMap, AHashSet};
+use messenger_common::Sizeable;
+use messenger_common::locking::MessengerSharedMutFn;
 use prometheus_client::encoding::text::encode;
 use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
 use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::registry::Registry;
+use std::sync::Mutex;
 use tracing::error;
 
+/// Stream and topic label value of the topics over the `max_topics` limit.
+const OTHER_LABEL: &str = "_other";
+
+type Labels = Vec<(String, String)>;
+
 #[derive(Debug)]
 pub(crate) struct Metrics {
     registry: Registry,
@@ -33,10 +46,45 @@ pub(crate) struct Metrics {
     messages: Gauge,
     users: Gauge,
     clients: Gauge,
+    topics_series: Option<TopicSeries>,
+    scrape: tokio::sync::Mutex<()>,
+}
+
+/// Series labeled by stream and topic, and optionally by partition.
+///
+/// Topics get their own series in the order they are first seen, up to `max_topics`,
+/// the traffic of any further topics is summed under `_other` so that the output stays bounded.
+#[derive(Debug)]
+struct TopicSeries {
+    per_partition: bool,
+    max_topics: usize,
+    max_consumer_series: usize,
+    /// Counter label sets of each admitted topic, removed when the topic is deleted.
+    admitted: Mutex<AHashMap<(u32, u32), AHashSet<Labels>>>,
+    messages_produced: Family<Labels, Counter>,
+    bytes_produced: Family<Labels, Counter>,
+    messages_consumed: Family<Labels, Counter>,
+    bytes_consumed: Family<Labels, Counter>,
+    messages_expired: Family<Labels, Counter>,
+    messages_expired_deleted: Family<Labels, Counter>,
+    topic_messages: Family<Labels, Gauge>,
+    topic_size_bytes: Family<Labels, Gauge>,
+    topic_segments: Family<Labels, Gauge>,
+    consumer_lag: Family<Labels, Gauge>,
+    dropped_series: Gauge,
+}
+
+#[derive(Default)]
+struct TopicGauges {
+    messages: AHashMap<Labels, i64>,
+    size_bytes: AHashMap<Labels, i64>,
+    segments: AHashMap<Labels, i64>,
+    consumer_lag: Vec<(Labels, i64)>,
+    dropped_series: i64,
 }
 
 impl Metrics {
-    pub fn init() -> Self {
+    pub fn init(config: &TopicMetricsConfig) -> Self {
         let mut metrics = Metrics {
             registry: <Registry>::default(),
             http_requests: Counter::default(),
@@ -47,6 +95,8 @@ impl Metrics {
             messages: Gauge::default(),
             users: Gauge::default(),
             clients: Gauge::default(),
+            topics_series: config.enabled.then(|| TopicSeries::new(config)),
+            scrape: tokio::sync::Mutex::new(()),
         };
 
         metrics.register_counter("http_requests", metrics.http_requests.clone());
@@ -57,6 +107,9 @@ impl Metrics {
         metrics.register_gauge("messages", metrics.messages.clone());
         metrics.register_gauge("users", metrics.users.clone());
         metrics.register_gauge("clients", metrics.clients.clone());
+        if let Some(series) = &metrics.topics_series {
+            series.register(&mut metrics.registry);
+        }
 
         metrics
     }
@@ -71,7 +124,14 @@ impl Metrics {
             .register(name, format!("total count of {name}"), gauge)
     }
 
-    pub fn get_formatted_output(&self) -> String {
+    /// Refreshes the per-topic gauges from the given streams and encodes all the metrics.
+    pub async fn get_formatted_output(&self, streams: &[&Stream]) -> String {
+        // Concurrent scrapes would otherwise interleave clearing and setting the gauges.
+        let _scrape = self.scrape.lock().await;
+        if let Some(series) = &self.topics_series {
+            series.refresh(streams).await;
+        }
+
         let mut buffer = String::new();
         if let Err(err) = encode(&mut buffer, &self.registry) {
             error!("Failed to encode metrics: {}", err);
@@ -79,6 +139,91 @@ impl Metrics {
         buffer
     }
 
+    pub fn record_produced(
+        &self,
+        stream_name: &str,
+        topic: &Topic,
+        partition_id: u32,
+        messages_count: u32,
+        size_bytes: u32,
+    ) {
+        if let Some(series) = &self.topics_series {
+            let labels = series.counter_labels(stream_name, topic, partition_id);
+            series.messages_produced.get_or_create(&labels).inc_by(messages_count as u64);
+            series.bytes_produced.get_or_create(&labels).inc_by(size_bytes as u64);
+        }
+    }
+
+    pub fn record_consumed(
+        &self,
+        stream_name: &str,
+        topic: &Topic,
+        partition_id: u32,
+        messages_count: u32,
+        size_bytes: u32,
+    ) {
+        if messages_count == 0 {
+            return;
+        }
+
+        if let Some(series) = &self.topics_series {
+            let labels = series.counter_labels(stream_name, topic, partition_id);
+            series.messages_consumed.get_or_create(&labels).inc_by(messages_count as u64);
+            series.bytes_consumed.get_or_create(&labels).inc_by(size_bytes as u64);
+        }
+    }
+
+    /// Records the messages left out of a poll because their time to live had passed.
+    pub fn record_expired_skipped(
+        &self,
+        stream_name: &str,
+        topic: &Topic,
+        partition_id: u32,
+        messages_count: u32,
+    ) {
+        if messages_count == 0 {
+            return;
+        }
+
+        if let Some(series) = &self.topics_series {
+            let labels = series.counter_labels(stream_name, topic, partition_id);
+            series.messages_expired.get_or_create(&labels).inc_by(messages_count as u64);
+        }
+    }
+
+    /// Records the messages deleted with expired segments, either by the topic message expiry
+    /// or because every message of the segment outlived its time to live.
+    pub fn record_expired_deleted(
+        &self,
+        stream_name: &str,
+        topic: &Topic,
+        partition_id: u32,
+        messages_count: u64,
+    ) {
+        if messages_count == 0 {
+            return;
+        }
+
+        if let Some(series) = &self.topics_series {
+            let labels = series.counter_labels(stream_name, topic, partition_id);
+            series.messages_expired_deleted.get_or_create(&labels).inc_by(messages_count);
+        }
+    }
+
+    /// Removes the series of a deleted topic, freeing its place under the `max_topics` limit.
+    pub fn remove_topic(&self, stream_id: u32, topic_id: u32) {
+        if let Some(series) = &self.topics_series {
+            series.remove(|id| id == (stream_id, topic_id));
+        }
+    }
+
+    /// Removes the series of all the topics of a deleted stream.
+    pub fn remove_stream(&self, stream_id: u32) {
+        if let Some(series) = &self.topics_series {
+            series.remove(|(id, _)| id == stream_id);
+        }
+    }
+
     pub fn increment_http_requests(&self) {
         self.http_requests.inc();
     }
@@ -139,3 +284,277 @@ impl Metrics {
         self.clients.dec_by(count as i64);
     }
 }
+
+impl TopicSeries {
+    fn new(config: &TopicMetricsConfig) -> Self {
+        TopicSeries {
+            per_partition: config.per_partition,
+            max_topics: config.max_topics,
+            max_consumer_series: config.max_consumer_series,
+            admitted: Mutex::new(AHashMap::new()),
+            messages_produced: Family::default(),
+            bytes_produced: Family::default(),
+            messages_consumed: Family::default(),
+            bytes_consumed: Family::default(),
+            messages_expired: Family::default(),
+            messages_expired_deleted: Family::default(),
+            topic_messages: Family::default(),
+            topic_size_bytes: Family::default(),
+            topic_segments: Family::default(),
+            consumer_lag: Family::default(),
+            dropped_series: Gauge::default(),
+        }
+    }
+
+    fn register(&self, registry: &mut Registry) {
+        registry.register(
+            "messages_produced",
+            "total count of messages appended per topic",
+            self.messages_produced.clone(),
+        );
+        registry.register(
+            "bytes_produced",
+            "total size of messages appended per topic",
+            self.bytes_produced.clone(),
+        );
+        registry.register(
+            "messages_consumed",
+            "total count of messages polled per topic",
+            self.messages_consumed.clone(),
+        );
+        registry.register(
+            "bytes_consumed",
+            "total size of messages polled per topic",
+            self.bytes_consumed.clone(),
+        );
+        registry.register(
+            "messages_expired",
+            "total count of messages skipped by polls after their time to live passed per topic",
+            self.messages_expired.clone(),
+        );
+        registry.register(
+            "messages_expired_deleted",
+            "total count of expired messages deleted per topic",
+            self.messages_expired_deleted.clone(),
+        );
+        registry.register(
+            "topic_messages",
+            "count of messages stored per topic",
+            self.topic_messages.clone(),
+        );
+        registry.register(
+            "topic_size_bytes",
+            "size of messages stored per topic",
+            self.topic_size_bytes.clone(),
+        );
+        registry.register(
+            "topic_segments",
+            "count of segments per topic",
+            self.topic_segments.clone(),
+        );
+        registry.register(
+            "consumer_lag",
+            "count of messages after the stored offset of each consumer and consumer group",
+            self.consumer_lag.clone(),
+        );
+        registry.register(
+            "topic_series_dropped",
+            "count of topics and consumers whose series exceed the cardinality limits",
+            self.dropped_series.clone(),
+        );
+    }
+
+    /// Admits the topic if there is room left, returns false if it is reported under `_other`.
+    fn admit(&self, stream_id: u32, topic_id: u32) -> bool {
+        let mut admitted = self.admitted.lock().unwrap();
+        if admitted.contains_key(&(stream_id, topic_id)) {
+            return true;
+        }
+
+        if admitted.len() >= self.max_topics {
+            return false;
+        }
+
+        admitted.insert((stream_id, topic_id), AHashSet::new());
+        true
+    }
+
+    /// Returns the labels of the topic, or of `_other` if it was not admitted.
+    fn labels(&self, stream_name: &str, topic: &Topic, partition_id: Option<u32>) -> Option<Labels> {
+        if !self.admit(topic.stream_id, topic.topic_id) {
+            return None;
+        }
+
+        let mut labels = vec![
+            ("stream".to_owned(), stream_name.to_owned()),
+            ("topic".to_owned(), topic.name.clone()),
+        ];
+        if self.per_partition
+            && let Some(partition_id) = partition_id
+        {
+            labels.push(("partition".to_owned(), partition_id.to_string()));
+        }
+        Some(labels)
+    }
+
+    fn other_labels() -> Labels {
+        vec![
+            ("stream".to_owned(), OTHER_LABEL.to_owned()),
+            ("topic".to_owned(), OTHER_LABEL.to_owned()),
+        ]
+    }
+
+    fn counter_labels(&self, stream_name: &str, topic: &Topic, partition_id: u32) -> Labels {
+        let Some(labels) = self.labels(stream_name, topic, Some(partition_id)) else {
+            return Self::other_labels();
+        };
+
+        // Streams and topics can be renamed, so every label set in use is kept for removal.
+        if let Some(label_sets) = self
+            .admitted
+            .lock()
+            .unwrap()
+            .get_mut(&(topic.stream_id, topic.topic_id))
+            && !label_sets.contains(&labels)
+        {
+            label_sets.insert(labels.clone());
+        }
+        labels
+    }
+
+    fn remove(&self, matches: impl Fn((u32, u32)) -> bool) {
+        let mut admitted = self.admitted.lock().unwrap();
+        admitted.retain(|id, label_sets| {
+            if !matches(*id) {
+                return true;
+            }
+
+            for labels in label_sets.iter() {
+                self.messages_produced.remove(labels);
+                self.bytes_produced.remove(labels);
+                self.messages_consumed.remove(labels);
+                self.bytes_consumed.remove(labels);
+                self.messages_expired.remove(labels);
+                self.messages_expired_deleted.remove(labels);
+            }
+            false
+        });
+    }
+
+    async fn refresh(&self, streams: &[&Stream]) {
+        let mut gauges = TopicGauges::default();
+        for stream in streams {
+            for topic in stream.get_topics() {
+                self.collect_topic(&stream.name, topic, &mut gauges).await;
+            }
+        }
+
+        for (family, values) in [
+            (&self.topic_messages, &gauges.messages),
+            (&self.topic_size_bytes, &gauges.size_bytes),
+            (&self.topic_segments, &gauges.segments),
+        ] {
+            family.clear();
+            for (labels, value) in values {
+                family.get_or_create(labels).set(*value);
+            }
+        }
+
+        self.consumer_lag.clear();
+        for (labels, lag) in &gauges.consumer_lag {
+            self.consumer_lag.get_or_create(labels).set(*lag);
+        }
+        self.dropped_series.set(gauges.dropped_series);
+    }
+
+    async fn collect_topic(&self, stream_name: &str, topic: &Topic, gauges: &mut TopicGauges) {
+        let admitted = self.admit(topic.stream_id, topic.topic_id);
+        if !admitted {
+            gauges.dropped_series += 1;
+        }
+
+        let mut group_names = AHashMap::new();
+        if admitted {
+            for consumer_group in topic.get_consumer_groups() {
+                let consumer_group = consumer_group.read().await;
+                group_names.insert(consumer_group.group_id, consumer_group.name.clone());
+            }
+        }
+
+        for partition in topic.get_partitions() {
+            let partition = partition.read().await;
+            let labels = self
+                .labels(stream_name, topic, Some(partition.partition_id))
+                .unwrap_or_else(Self::other_labels);
+            *gauges.messages.entry(labels.clone()).or_default() +=
+                partition.get_messages_count() as i64;
+            *gauges.size_bytes.entry(labels.clone()).or_default() +=
+                partition.get_size_bytes().as_bytes_u64() as i64;
+            *gauges.segments.entry(labels.clone()).or_default() +=
+                partition.get_segments_count() as i64;
+
+            // Lag of topics under `_other` is not summed, as it means nothing across topics.
+            if !admitted {
+                continue;
+            }
+
+            let consumers = partition
+                .consumer_offsets
+                .iter()
+                .map(|offset| ("consumer", offset.consumer_id.to_string(), offset.offset))
+                .chain(partition.consumer_group_offsets.iter().map(|offset| {
+                    let name = group_names
+                        .get(&offset.consumer_id)
+                        .cloned()
+                        .unwrap_or_else(|| offset.consumer_id.to_string());
+                    ("consumer_group", name, offset.offset)
+                }));
+            for (kind, consumer, offset) in consumers {
+                if gauges.consumer_lag.len() >= self.max_consumer_series {
+                    gauges.dropped_series += 1;
+                    continue;
+                }
+
+                let mut labels = labels.clone();
+                labels.push(("kind".to_owned(), kind.to_owned()));
+                labels.push(("consumer".to_owned(), consumer));
+                let lag = partition.current_offset.saturating_sub(offset);
+                gauges.consumer_lag.push((labels, lag as i64));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_topics: usize) -> TopicMetricsConfig {
+        TopicMetricsConfig {
+            enabled: true,
+            per_partition: true,
+            max_topics,
+            max_consumer_series: 10,
+        }
+    }
+
+    #[test]
+    fn topics_over_the_limit_should_be_reported_as_other() {
+        let series = TopicSeries::new(&config(1));
+
+        assert!(series.admit(1, 1));
+        assert!(series.admit(1, 1));
+        assert!(!series.admit(1, 2));
+    }
+
+    #[test]
+    fn removed_topics_should_free_their_place() {
+        let series = TopicSeries::new(&config(1));
+        assert!(series.admit(1, 1));
+
+        series.remove(|(stream_id, _)| stream_id == 1);
+
+        assert!(series.admit(2, 1));
+        assert!(!series.admit(1, 1));
+    }
+}
@@ -0,0 +1,196 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use ahash::{AHashMap, AHashSet};
+use messenger_common::{HeaderKey, HeaderValue};
+use std::collections::{HashMap, VecDeque};
+
+/// Offsets of the messages carrying a given value of one of the indexed headers.
+#[derive(Debug)]
+pub struct MessageHeaderIndex {
+    headers: AHashSet<HeaderKey>,
+    max_entries: usize,
+    entries: AHashMap<(HeaderKey, String), VecDeque<IndexedMessage>>,
+    insertion_order: VecDeque<(HeaderKey, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IndexedMessage {
+    offset: u64,
+    timestamp: u64,
+}
+
+impl MessageHeaderIndex {
+    /// Creates a new index over the given headers, keeping at most `max_entries` header values.
+    pub fn new(headers: impl IntoIterator<Item = HeaderKey>, max_entries: u64) -> Self {
+        Self {
+            headers: headers.into_iter().collect(),
+            max_entries: max_entries as usize,
+            entries: AHashMap::new(),
+            insertion_order: VecDeque::new(),
+        }
+    }
+
+    /// Checks if the given header is indexed.
+    pub fn is_indexed(&self, key: &HeaderKey) -> bool {
+        self.headers.contains(key)
+    }
+
+    /// Returns the number of indexed header values.
+    pub fn len(&self) -> usize {
+        self.insertion_order.len()
+    }
+
+    /// Checks if no header values are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.insertion_order.is_empty()
+    }
+
+    /// Indexes the user headers of the message with the given offset and timestamp.
+    /// Messages must be indexed in offset order, the oldest entries are evicted once the index is full.
+    pub fn insert(
+        &mut self,
+        offset: u64,
+        timestamp: u64,
+        user_headers: &HashMap<HeaderKey, HeaderValue>,
+    ) {
+        for (key, value) in user_headers {
+            if !self.is_indexed(key) {
+                continue;
+            }
+
+            let entry = (key.clone(), value.value_only_to_string());
+            self.entries
+                .entry(entry.clone())
+                .or_default()
+                .push_back(IndexedMessage { offset, timestamp });
+            self.insertion_order.push_back(entry);
+        }
+
+        while self.insertion_order.len() > self.max_entries {
+            self.evict_oldest();
+        }
+    }
+
+    /// Returns up to `count` offsets of the messages with the given header value,
+    /// whose timestamps are within the inclusive range, in ascending order.
+    pub fn find(&self, key: &HeaderKey, value: &str, from: u64, to: u64, count: u32) -> Vec<u64> {
+        let Some(messages) = self.entries.get(&(key.clone(), value.to_owned())) else {
+            return Vec::new();
+        };
+
+        messages
+            .iter()
+            .filter(|message| message.timestamp >= from && message.timestamp <= to)
+            .take(count as usize)
+            .map(|message| message.offset)
+            .collect()
+    }
+
+    fn evict_oldest(&mut self) {
+        let Some(entry) = self.insertion_order.pop_front() else {
+            return;
+        };
+
+        if let Some(messages) = self.entries.get_mut(&entry) {
+            messages.pop_front();
+            if messages.is_empty() {
+                self.entries.remove(&entry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn headers(entries: &[(&str, &str)]) -> HashMap<HeaderKey, HeaderValue> {
+        entries
+            .iter()
+            .map(|(key, value)| {
+                (
+                    HeaderKey::new(key).unwrap(),
+                    HeaderValue::from_str(value).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn key(key: &str) -> HeaderKey {
+        HeaderKey::new(key).unwrap()
+    }
+
+    #[test]
+    fn message_header_index_should_find_only_indexed_headers() {
+        let mut index = MessageHeaderIndex::new([key("correlation-id")], 100);
+        for offset in 0..4 {
+            let correlation_id = if offset % 2 == 0 { "order-1" } else { "order-2" };
+            index.insert(
+                offset,
+                offset * 10,
+                &headers(&[("correlation-id", correlation_id), ("tenant", "acme")]),
+            );
+        }
+
+        assert_eq!(
+            index.find(&key("correlation-id"), "order-1", 0, u64::MAX, 10),
+            vec![0, 2]
+        );
+        assert_eq!(
+            index.find(&key("Correlation-Id"), "order-2", 0, u64::MAX, 10),
+            vec![1, 3]
+        );
+        assert!(!index.is_indexed(&key("tenant")));
+        assert!(index.find(&key("tenant"), "acme", 0, u64::MAX, 10).is_empty());
+        assert_eq!(index.len(), 4);
+    }
+
+    #[test]
+    fn message_header_index_should_filter_by_time_range_and_count() {
+        let mut index = MessageHeaderIndex::new([key("entity-id")], 100);
+        for offset in 0..10 {
+            index.insert(offset, offset * 10, &headers(&[("entity-id", "42")]));
+        }
+
+        assert_eq!(index.find(&key("entity-id"), "42", 20, 50, 10), vec![2, 3, 4, 5]);
+        assert_eq!(index.find(&key("entity-id"), "42", 20, 50, 2), vec![2, 3]);
+        assert!(index.find(&key("entity-id"), "43", 0, u64::MAX, 10).is_empty());
+    }
+
+    #[test]
+    fn message_header_index_should_evict_oldest_entries_when_full() {
+        let max_entries = 3;
+        let mut index = MessageHeaderIndex::new([key("correlation-id")], max_entries);
+        for offset in 0..5 {
+            let correlation_id = format!("order-{}", offset % 2);
+            index.insert(offset, offset, &headers(&[("correlation-id", &correlation_id)]));
+        }
+
+        assert_eq!(index.len(), max_entries as usize);
+        assert_eq!(
+            index.find(&key("correlation-id"), "order-0", 0, u64::MAX, 10),
+            vec![2, 4]
+        );
+        assert_eq!(
+            index.find(&key("correlation-id"), "order-1", 0, u64::MAX, 10),
+            vec![3]
+        );
+    }
+}
@@ -19,6 +19,7 @@
 pub mod clients;
 mod deduplication;
 pub mod diagnostics;
+mod header_index;
 pub mod local_sizeable;
 pub mod partitions;
 pub mod persistence;
@@ -29,6 +30,7 @@ pub mod session;
 pub mod storage;
 pub mod streams;
 pub mod systems;
+pub mod tenants;
 pub mod topics;
 pub mod users;
 pub mod utils;
@@ -16,14 +16,15 @@
  * under the License.
  */
 
+use crate::streaming::header_index::message_header_index::MessageHeaderIndex;
 use crate::streaming::partitions::COMPONENT;
 use crate::streaming::partitions::partition::Partition;
 use crate::streaming::polling_consumer::PollingConsumer;
 use crate::streaming::segments::*;
 use error_set::ErrContext;
-use messenger_common::{Confirmation, MessengerError, MessengerTimestamp, Sizeable};
+use messenger_common::{Confirmation, HeaderKey, MessengerError, MessengerTimestamp, Sizeable};
 use std::sync::atomic::Ordering;
-use tracing::trace;
+use tracing::{trace, warn};
 
 impl Partition {
     /// Retrieves messages by timestamp (up to a specified count).
@@ -218,6 +219,95 @@ impl Partition {
         Ok(batches)
     }
 
+    /// Retrieves messages with the given value of an indexed header, whose timestamps are within the inclusive range (up to a specified count).
+    pub async fn get_messages_by_header(
+        &self,
+        key: &HeaderKey,
+        value: &str,
+        from: MessengerTimestamp,
+        to: MessengerTimestamp,
+        count: u32,
+    ) -> Result<MessengerMessagesBatchSet, MessengerError> {
+        trace!(
+            "Getting {count} messages by header: {key} for partition: {}...",
+            self.partition_id
+        );
+
+        let header_index = match &self.header_index {
+            Some(header_index) if header_index.is_indexed(key) => header_index,
+            _ => {
+                return Err(MessengerError::HeaderNotIndexed(
+                    key.to_string(),
+                    self.topic_id,
+                    self.stream_id,
+                ));
+            }
+        };
+
+        let offsets = header_index.find(key, value, from.as_micros(), to.as_micros(), count);
+        let mut batches = MessengerMessagesBatchSet::empty();
+        for offset in offsets {
+            // The segment holding the message might have already expired.
+            let messages = self.get_messages_by_offset(offset, 1).await.with_error_context(|error| {
+                format!(
+                    "{COMPONENT} (error: {error}) - failed to get indexed message, partition: {}, offset: {offset}",
+                    self.partition_id
+                )
+            })?;
+            batches.add_batch_set(messages);
+        }
+
+        Ok(batches)
+    }
+
+    /// Starts indexing the given headers, the most recent messages are indexed right away.
+    pub async fn enable_header_index(&mut self, headers: Vec<HeaderKey>) -> Result<(), MessengerError> {
+        if headers.is_empty() {
+            self.header_index = None;
+            return Ok(());
+        }
+
+        let max_entries = self.config.header_indexing.max_entries;
+        self.header_index = Some(MessageHeaderIndex::new(headers, max_entries));
+        if self.get_messages_count() == 0 {
+            return Ok(());
+        }
+
+        let count = max_entries.min(u32::MAX as u64) as u32;
+        let messages = self.get_last_messages(count).await.with_error_context(|error| {
+            format!(
+                "{COMPONENT} (error: {error}) - failed to get messages to index headers, partition: {}",
+                self.partition_id
+            )
+        })?;
+        self.index_headers(&messages);
+        Ok(())
+    }
+
+    fn index_headers(&mut self, messages: &MessengerMessagesBatchSet) {
+        let Some(header_index) = self.header_index.as_mut() else {
+            return;
+        };
+
+        for batch in messages.iter() {
+            for message in batch.iter() {
+                match message.user_headers_map() {
+                    Ok(Some(user_headers)) => header_index.insert(
+                        message.header().offset(),
+                        message.header().timestamp(),
+                        &user_headers,
+                    ),
+                    Ok(None) => {}
+                    Err(error) => warn!(
+                        "Cannot index headers of message with offset: {} for partition: {}. Error: {error}",
+                        message.header().offset(),
+                        self.partition_id
+                    ),
+                }
+            }
+        }
+    }
+
     pub async fn append_messages(
         &mut self,
         batch: MessengerMessagesBatchMut,
@@ -279,6 +369,19 @@ impl Partition {
             self.current_offset = last_offset;
         }
 
+        if self.header_index.is_some() {
+            let appended_messages = self
+                .get_messages_by_offset(current_offset, batch_messages_count)
+                .await
+                .with_error_context(|error| {
+                    format!(
+                        "{COMPONENT} (error: {error}) - failed to get appended messages to index headers, partition: {}, offset: {current_offset}",
+                        self.partition_id
+                    )
+                })?;
+            self.index_headers(&appended_messages);
+        }
+
         self.unsaved_messages_count += batch_messages_count;
         self.unsaved_messages_size += batch_messages_size;
 
@@ -287,6 +390,7 @@ impl Partition {
         let unsaved_messages_size_exceeded =
             self.unsaved_messages_size >= self.config.partition.size_of_messages_required_to_save;
 
+        let last_segment = self.segments.last_mut().ok_or(MessengerError::SegmentNotFound)?;
         if unsaved_messages_count_exceeded
             || unsaved_messages_size_exceeded
             || last_segment.is_full().await
@@ -367,7 +471,9 @@ mod tests {
     use crate::streaming::storage::SystemStorage;
     use crate::streaming::utils::MemoryPool;
     use bytes::Bytes;
-    use messenger_common::{MessengerExpiry, MessengerMessage};
+    use messenger_common::{HeaderValue, MessengerExpiry, MessengerMessage};
+    use std::collections::HashMap;
+    use std::str::FromStr;
     use std::sync::Arc;
     use std::sync::atomic::{AtomicU32, AtomicU64};
     use tempfile::TempDir;
@@ -704,6 +810,67 @@ mod tests {
         assert_eq!(loaded_messages.count(), 4);
     }
 
+    #[tokio::test]
+    async fn given_indexed_header_messages_should_be_retrieved_by_header_value() {
+        let (mut partition, _tempdir) = create_partition(false).await;
+        let correlation_id = HeaderKey::new("correlation-id").unwrap();
+        partition
+            .enable_header_index(vec![correlation_id.clone()])
+            .await
+            .unwrap();
+        let messages = (1..=6)
+            .map(|id| {
+                let order = if id % 2 == 0 { "order-2" } else { "order-1" };
+                MessengerMessage::builder()
+                    .id(id)
+                    .payload(Bytes::from(format!("message {id}")))
+                    .user_headers(HashMap::from([(
+                        correlation_id.clone(),
+                        HeaderValue::from_str(order).unwrap(),
+                    )]))
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let messages_size = messages
+            .iter()
+            .map(|m| m.get_size_bytes().as_bytes_u32())
+            .sum();
+        let batch = MessengerMessagesBatchMut::from_messages(&messages, messages_size);
+
+        partition.append_messages(batch, None).await.unwrap();
+
+        let loaded_messages = partition
+            .get_messages_by_header(
+                &correlation_id,
+                "order-2",
+                0.into(),
+                MessengerTimestamp::now(),
+                10,
+            )
+            .await
+            .unwrap();
+        let offsets = loaded_messages
+            .iter()
+            .flat_map(|batch| batch.iter().map(|message| message.header().offset()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![1, 3, 5]);
+
+        let not_indexed = partition
+            .get_messages_by_header(
+                &HeaderKey::new("entity-id").unwrap(),
+                "42",
+                0.into(),
+                MessengerTimestamp::now(),
+                10,
+            )
+            .await;
+        assert!(matches!(
+            not_indexed,
+            Err(MessengerError::HeaderNotIndexed(_, 2, 1))
+        ));
+    }
+
     async fn create_partition(deduplication_enabled: bool) -> (Partition, TempDir) {
         let stream_id = 1;
         let topic_id = 2;
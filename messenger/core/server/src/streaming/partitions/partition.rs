@@ -18,6 +18,7 @@
 
 use crate::configs::system::SystemConfig;
 use crate::streaming::deduplication::message_deduplicator::MessageDeduplicator;
+use crate::streaming::header_index::message_header_index::MessageHeaderIndex;
 use crate::streaming::segments::*;
 use crate::streaming::storage::SystemStorage;
 use dashmap::DashMap;
@@ -42,6 +43,7 @@ pub struct Partition {
     pub consumer_group_offsets_path: String,
     pub current_offset: u64,
     pub message_deduplicator: Option<MessageDeduplicator>,
+    pub header_index: Option<MessageHeaderIndex>,
     pub unsaved_messages_count: u32,
     pub unsaved_messages_size: MessengerByteSize,
     pub should_increment_offset: bool,
@@ -133,6 +135,7 @@ impl Partition {
             consumer_group_offsets_path,
             message_expiry,
             message_deduplicator,
+            header_index: None,
             segments: vec![],
             current_offset: 0,
             unsaved_messages_count: 0,
@@ -64,7 +64,17 @@ impl Partition {
             }
         }
 
+        // Segments whose messages outlived their time to live are only deleted from the oldest one,
+        // so that the remaining offsets stay contiguous.
+        for segment in &self.segments {
+            if !segment.is_ttl_expired(now).await {
+                break;
+            }
+            expired_segments.push(segment.start_offset());
+        }
+
         expired_segments.sort();
+        expired_segments.dedup();
         expired_segments
     }
 
@@ -26,7 +26,7 @@ mod writing_messages;
 
 pub use indexes::MessengerIndexesMut;
 pub use messages_accumulator::MessagesAccumulator;
-pub use segment::Segment;
+pub use segment::{Segment, TtlExpiry};
 pub use types::MessengerMessageHeaderViewMut;
 pub use types::MessengerMessageViewMut;
 pub use types::MessengerMessagesBatchMut;
@@ -27,12 +27,36 @@ use messenger_common::MessengerByteSize;
 use messenger_common::MessengerError;
 use messenger_common::MessengerExpiry;
 use messenger_common::MessengerTimestamp;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::fs::remove_file;
 use tracing::{info, warn};
 
 const SIZE_16MB: usize = 16 * 1024 * 1024;
+/// Count of messages read at once when looking for the time to live of a loaded segment.
+const TTL_SCAN_MESSAGES_COUNT: u32 = 1000;
+
+/// When the messages of a segment expire according to their time to live.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtlExpiry {
+    /// The segment was loaded from disk and its messages have not been read yet.
+    Unknown,
+    /// At least one message has no time to live.
+    Never,
+    /// Every message has a time to live, the last of them expires at the given timestamp.
+    At(u64),
+}
+
+impl TtlExpiry {
+    /// Combines the expiry with the one of the messages appended after.
+    pub fn then(self, next: TtlExpiry) -> TtlExpiry {
+        match (self, next) {
+            (TtlExpiry::Unknown, _) | (_, TtlExpiry::Unknown) => TtlExpiry::Unknown,
+            (TtlExpiry::Never, _) | (_, TtlExpiry::Never) => TtlExpiry::Never,
+            (TtlExpiry::At(current), TtlExpiry::At(next)) => TtlExpiry::At(current.max(next)),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Segment {
@@ -64,6 +88,7 @@ pub struct Segment {
     pub(super) indexes: MessengerIndexesMut,
     pub(super) messages_size: Arc<AtomicU64>,
     pub(super) indexes_size: Arc<AtomicU64>,
+    pub(super) ttl_expiry: Mutex<TtlExpiry>,
 }
 
 impl Segment {
@@ -124,6 +149,11 @@ impl Segment {
             config,
             messages_size: Arc::new(AtomicU64::new(0)),
             indexes_size: Arc::new(AtomicU64::new(0)),
+            ttl_expiry: Mutex::new(if fresh {
+                TtlExpiry::At(0)
+            } else {
+                TtlExpiry::Unknown
+            }),
         }
     }
 
@@ -266,6 +296,54 @@ impl Segment {
         self.is_expired(MessengerTimestamp::now()).await
     }
 
+    /// Returns true if the segment is closed and every message in it has outlived its time to live.
+    ///
+    /// The messages of a segment loaded from disk are read the first time it is checked.
+    pub async fn is_ttl_expired(&self, now: MessengerTimestamp) -> bool {
+        if !self.is_closed {
+            return false;
+        }
+
+        let mut ttl_expiry = *self.ttl_expiry.lock().unwrap();
+        if ttl_expiry == TtlExpiry::Unknown {
+            match self.load_ttl_expiry().await {
+                Ok(loaded) => {
+                    *self.ttl_expiry.lock().unwrap() = loaded;
+                    ttl_expiry = loaded;
+                }
+                Err(error) => {
+                    warn!("Failed to read the time to live of messages in {self}. {error}");
+                    return false;
+                }
+            }
+        }
+
+        match ttl_expiry {
+            TtlExpiry::At(expires_at) => expires_at <= now.as_micros(),
+            TtlExpiry::Unknown | TtlExpiry::Never => false,
+        }
+    }
+
+    async fn load_ttl_expiry(&self) -> Result<TtlExpiry, MessengerError> {
+        let mut ttl_expiry = TtlExpiry::At(0);
+        let mut offset = self.start_offset;
+        while offset <= self.end_offset {
+            let batches = self
+                .get_messages_by_offset(offset, TTL_SCAN_MESSAGES_COUNT)
+                .await?;
+            let Some(last_offset) = batches.last_offset() else {
+                break;
+            };
+
+            ttl_expiry = ttl_expiry.then(batches.ttl_expiry());
+            if ttl_expiry == TtlExpiry::Never {
+                break;
+            }
+            offset = last_offset + 1;
+        }
+        Ok(ttl_expiry)
+    }
+
     pub async fn is_expired(&self, now: MessengerTimestamp) -> bool {
         if !self.is_closed {
             return false;
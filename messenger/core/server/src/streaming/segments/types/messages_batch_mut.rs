@@ -19,6 +19,7 @@
 use super::message_view_mut::MessengerMessageViewMutIterator;
 use crate::streaming::deduplication::message_deduplicator::MessageDeduplicator;
 use crate::streaming::segments::indexes::MessengerIndexesMut;
+use crate::streaming::segments::TtlExpiry;
 use crate::streaming::utils::PooledBuffer;
 use crate::streaming::utils::random_id;
 use bytes::{BufMut, BytesMut};
@@ -606,6 +607,61 @@ impl MessengerMessagesBatchMut {
         self.indexes = new_indexes;
     }
 
+    /// Validates the time to live header of every message that has one.
+    pub fn validate_ttls(&self) -> Result<(), MessengerError> {
+        for message in self.iter() {
+            if message.header().user_headers_length() > 0 {
+                message.ttl()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the longest time to live (in microseconds) of the messages,
+    /// or `None` if any of them has no time to live.
+    pub fn longest_ttl(&self) -> Option<u64> {
+        let mut longest_ttl = 0;
+        for message in self.iter() {
+            let ttl = message.ttl().ok().flatten()?;
+            longest_ttl = longest_ttl.max(ttl.as_micros());
+        }
+        Some(longest_ttl)
+    }
+
+    /// Returns when the last of the messages expires according to their time to live.
+    pub fn ttl_expiry(&self) -> TtlExpiry {
+        let mut ttl_expiry = TtlExpiry::At(0);
+        for message in self.iter() {
+            match message.expires_at() {
+                Some(expires_at) => ttl_expiry = ttl_expiry.then(TtlExpiry::At(expires_at)),
+                None => return TtlExpiry::Never,
+            }
+        }
+        ttl_expiry
+    }
+
+    /// Removes the messages that expired by `now` according to their time to live,
+    /// returns the count of removed messages.
+    pub fn remove_expired(&mut self, now: u64) -> u32 {
+        let expired: Vec<u32> = self
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| message.expires_at().is_some_and(|expires_at| expires_at <= now))
+            .map(|(index, _)| index as u32)
+            .collect();
+        if expired.is_empty() {
+            return 0;
+        }
+
+        if expired.len() == self.count() as usize {
+            *self = Self::empty();
+        } else {
+            let base_position = self.indexes.base_position();
+            self.remove_messages(&expired, base_position);
+        }
+        expired.len() as u32
+    }
+
     /// Validates that all messages in batch have correct checksums.
     pub fn validate_checksums(&self) -> Result<(), MessengerError> {
         for message in self.iter() {
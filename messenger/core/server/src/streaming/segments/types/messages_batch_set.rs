@@ -18,6 +18,7 @@
 
 use crate::binary::handlers::messages::poll_messages_handler::MessengerPollMetadata;
 use crate::streaming::segments::MessengerIndexesMut;
+use crate::streaming::segments::TtlExpiry;
 use bytes::Bytes;
 use messenger_common::{MessengerByteSize, MessengerMessage, MessengerMessageView, PolledMessages, Sizeable};
 use std::ops::Index;
@@ -136,6 +137,28 @@ impl MessengerMessagesBatchSet {
         self.batches.last().map(|batch| batch.last_offset())?
     }
 
+    /// Returns when the last of the messages expires according to their time to live.
+    pub fn ttl_expiry(&self) -> TtlExpiry {
+        self.iter()
+            .fold(TtlExpiry::At(0), |ttl_expiry, batch| ttl_expiry.then(batch.ttl_expiry()))
+    }
+
+    /// Removes the messages that expired by `now` according to their time to live,
+    /// returns the count of removed messages.
+    pub fn remove_expired(&mut self, now: u64) -> u32 {
+        let mut removed = 0;
+        for batch in self.batches.iter_mut() {
+            removed += batch.remove_expired(now);
+        }
+        if removed == 0 {
+            return 0;
+        }
+
+        let batches = std::mem::take(&mut self.batches);
+        *self = Self::from_vec(batches.into_iter().filter(|batch| !batch.is_empty()).collect());
+        removed
+    }
+
     /// Get a reference to the underlying vector of message containers
     pub fn inner(&self) -> &Vec<MessengerMessagesBatchMut> {
         &self.batches
@@ -17,7 +17,7 @@
  */
 
 use super::MessengerMessagesBatchMut;
-use crate::streaming::segments::segment::Segment;
+use crate::streaming::segments::segment::{Segment, TtlExpiry};
 use crate::{
     configs::cache_indexes::CacheIndexesConfig,
     streaming::deduplication::message_deduplicator::MessageDeduplicator,
@@ -43,6 +43,7 @@ impl Segment {
         }
         let batch_messages_size = messages.size();
         let batch_messages_count = messages.count();
+        let longest_ttl = messages.longest_ttl();
 
         let messages_accumulator = &mut self.accumulator;
         messages_accumulator
@@ -61,6 +62,14 @@ impl Segment {
         self.end_timestamp = messages_accumulator.last_timestamp();
         self.end_offset = messages_accumulator.last_offset();
 
+        // Every message of the batch is timestamped when coalesced, so none of them expires later than this.
+        let batch_ttl_expiry = match longest_ttl {
+            Some(ttl) => TtlExpiry::At(self.end_timestamp.saturating_add(ttl)),
+            None => TtlExpiry::Never,
+        };
+        let ttl_expiry = self.ttl_expiry.get_mut().unwrap();
+        *ttl_expiry = ttl_expiry.then(batch_ttl_expiry);
+
         self.update_counters(batch_messages_size as u64, batch_messages_count as u64);
 
         Ok(())
@@ -190,6 +190,10 @@ impl StreamStorage for FileStreamStorage {
             stream.topics.insert(topic.topic_id, topic);
         }
 
+        stream.apply_header_indexing().await.with_error_context(|error| {
+            format!("{COMPONENT} (error: {error}) - failed to apply header indexing to stream: {}", stream.stream_id)
+        })?;
+
         info!(
             "Loaded stream: '{}' with ID: {} from disk.",
             &stream.name, &stream.stream_id
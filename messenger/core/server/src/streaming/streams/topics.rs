@@ -96,6 +96,7 @@ impl Stream {
         info!("Created topic {}", topic);
         self.topics_ids.insert(name.to_owned(), id);
         self.topics.insert(id, topic);
+        self.apply_header_indexing().await?;
         Ok(id)
     }
 
@@ -164,6 +165,25 @@ impl Stream {
             self.topics_ids.insert(name.to_owned(), topic_id);
         }
 
+        self.apply_header_indexing().await?;
+        Ok(())
+    }
+
+    /// Applies the header indexes configured for the topics of this stream, which are matched by stream and topic names.
+    pub async fn apply_header_indexing(&mut self) -> Result<(), MessengerError> {
+        for topic in self.topics.values_mut() {
+            let headers = self
+                .config
+                .header_indexing
+                .get_indexed_headers(&self.name, &topic.name);
+            topic
+                .enable_header_indexing(headers)
+                .await
+                .with_error_context(|error| {
+                    format!("{COMPONENT} (error: {error}) - failed to apply header indexing to topic: {topic}")
+                })?;
+        }
+
         Ok(())
     }
 
@@ -82,6 +82,7 @@ impl System {
         name: &str,
     ) -> Result<&RwLock<ConsumerGroup>, MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         {
             let topic = self.find_topic(session, stream_id, topic_id)
                 .with_error_context(|error| format!("{COMPONENT} (error: {error}) - topic not found for stream ID: {stream_id}, topic_id: {topic_id}"))?;
@@ -113,6 +114,7 @@ impl System {
         consumer_group_id: &Identifier,
     ) -> Result<(), MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         let stream_id_value;
         let topic_id_value;
         {
@@ -239,6 +241,7 @@ impl System {
         consumer_group_id: &Identifier,
     ) -> Result<(), MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         {
             let topic = self
                 .find_topic(session, stream_id, topic_id)
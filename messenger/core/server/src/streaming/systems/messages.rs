@@ -21,11 +21,12 @@ use crate::streaming::segments::{MessengerIndexesMut, MessengerMessagesBatchMut,
 use crate::streaming::session::Session;
 use crate::streaming::systems::COMPONENT;
 use crate::streaming::systems::system::System;
+use crate::streaming::tenants::tenant::TenantAccess;
 use crate::streaming::utils::PooledBuffer;
 use error_set::ErrContext;
 use messenger_common::{
-    BytesSerializable, Confirmation, Consumer, EncryptorKind, MESSENGER_MESSAGE_HEADER_SIZE, Identifier,
-    MessengerError, Partitioning, PollingStrategy,
+    BytesSerializable, Confirmation, Consumer, EncryptorKind, HeaderKey, MESSENGER_MESSAGE_HEADER_SIZE,
+    Identifier, MessengerError, MessengerTimestamp, Partitioning, PollingStrategy,
 };
 use tracing::{error, trace};
 
@@ -70,10 +71,10 @@ impl System {
             .get_messages(polling_consumer, partition_id, args.strategy, args.count)
             .await?;
 
-        if args.auto_commit && !batch_set.is_empty() {
-            let offset = batch_set
-                .last_offset()
-                .expect("Batch set should have at least one batch");
+        // Expired messages are committed too, so that the consumer does not read them again.
+        if args.auto_commit
+            && let Some(offset) = metadata.last_polled_offset
+        {
             trace!(
                 "Last offset: {} will be automatically stored for {}, stream: {}, topic: {}, partition: {}",
                 offset, consumer, stream_id, topic_id, partition_id
@@ -90,9 +91,82 @@ impl System {
             batch_set
         };
 
+        if let Some(stream) = self.streams.get(&topic.stream_id) {
+            self.metrics.record_consumed(
+                &stream.name,
+                topic,
+                metadata.partition_id,
+                batch_set.count(),
+                batch_set.size(),
+            );
+            self.metrics.record_expired_skipped(
+                &stream.name,
+                topic,
+                metadata.partition_id,
+                metadata.expired_messages_count,
+            );
+        }
+        self.tenants
+            .record_messages_polled(session.get_user_id(), batch_set.count() as u64);
         Ok((metadata, batch_set))
     }
 
+    pub async fn get_messages_by_header(
+        &self,
+        session: &Session,
+        stream_id: &Identifier,
+        topic_id: &Identifier,
+        partition_id: Option<u32>,
+        args: HeaderLookupArgs,
+    ) -> Result<Vec<(MessengerPollMetadata, MessengerMessagesBatchSet)>, MessengerError> {
+        self.ensure_authenticated(session)?;
+        if args.count == 0 {
+            return Err(MessengerError::InvalidMessagesCount);
+        }
+
+        let topic = self.find_topic(session, stream_id, topic_id).with_error_context(|error| format!("{COMPONENT} (error: {error}) - topic not found for stream ID: {stream_id}, topic_id: {topic_id}"))?;
+        self.permissioner
+            .poll_messages(session.get_user_id(), topic.stream_id, topic.topic_id)
+            .with_error_context(|error| format!(
+                "{COMPONENT} (error: {error}) - permission denied to get messages by header for user {} on stream ID: {}, topic ID: {}",
+                session.get_user_id(),
+                topic.stream_id,
+                topic.topic_id
+            ))?;
+
+        let results = topic
+            .get_messages_by_header(partition_id, &args.key, &args.value, args.from, args.to, args.count)
+            .await
+            .with_error_context(|error| format!("{COMPONENT} (error: {error}) - failed to get messages by header: {}, stream ID: {stream_id}, topic ID: {topic_id}", args.key))?;
+
+        let stream = self.streams.get(&topic.stream_id);
+        let mut decrypted_results = Vec::with_capacity(results.len());
+        for (metadata, batch_set) in results {
+            if let Some(stream) = stream {
+                self.metrics.record_expired_skipped(
+                    &stream.name,
+                    topic,
+                    metadata.partition_id,
+                    metadata.expired_messages_count,
+                );
+            }
+            if batch_set.is_empty() {
+                continue;
+            }
+
+            let batch_set = if let Some(encryptor) = &self.encryptor {
+                self.decrypt_messages(batch_set, encryptor.as_ref()).await?
+            } else {
+                batch_set
+            };
+            self.tenants
+                .record_messages_polled(session.get_user_id(), batch_set.count() as u64);
+            decrypted_results.push((metadata, batch_set));
+        }
+
+        Ok(decrypted_results)
+    }
+
     pub async fn append_messages(
         &self,
         session: &Session,
@@ -103,6 +177,7 @@ impl System {
         confirmation: Option<Confirmation>,
     ) -> Result<(), MessengerError> {
         self.ensure_authenticated(session)?;
+        self.shutdown.ensure_accepting_messages()?;
         let topic = self.find_topic(session, stream_id, topic_id).with_error_context(|error| format!("{COMPONENT} (error: {error}) - topic not found for stream_id: {stream_id}, topic_id: {topic_id}"))?;
         self.permissioner.append_messages(
             session.get_user_id(),
@@ -114,7 +189,11 @@ impl System {
             topic.stream_id,
             topic.topic_id
         ))?;
+        self.ensure_tenant_access(session, topic.stream_id, TenantAccess::Write)?;
+        self.tenants.ensure_within_size_quota(topic.stream_id)?;
+        messages.validate_ttls()?;
         let messages_count = messages.count();
+        let messages_size = messages.size();
 
         // Encrypt messages if encryptor is configured
         let messages = if let Some(encryptor) = &self.encryptor {
@@ -123,11 +202,24 @@ impl System {
             messages
         };
 
-        topic
+        let partition_id = topic
             .append_messages(partitioning, messages, confirmation)
             .await?;
 
         self.metrics.increment_messages(messages_count as u64);
+        if let (Some(partition_id), Some(stream)) =
+            (partition_id, self.streams.get(&topic.stream_id))
+        {
+            self.metrics.record_produced(
+                &stream.name,
+                topic,
+                partition_id,
+                messages_count,
+                messages_size,
+            );
+        }
+        self.tenants
+            .record_messages_sent(session.get_user_id(), messages_count as u64);
         Ok(())
     }
 
@@ -151,6 +243,7 @@ impl System {
             topic.stream_id,
             topic.topic_id
         ))?;
+        self.ensure_tenant_access(session, topic.stream_id, TenantAccess::Write)?;
         topic.flush_unsaved_buffer(partition_id, fsync).await?;
         Ok(())
     }
@@ -264,3 +357,30 @@ impl PollingArgs {
         }
     }
 }
+
+#[derive(Debug)]
+pub struct HeaderLookupArgs {
+    pub key: HeaderKey,
+    pub value: String,
+    pub from: MessengerTimestamp,
+    pub to: MessengerTimestamp,
+    pub count: u32,
+}
+
+impl HeaderLookupArgs {
+    pub fn new(
+        key: HeaderKey,
+        value: String,
+        from: MessengerTimestamp,
+        to: MessengerTimestamp,
+        count: u32,
+    ) -> Self {
+        Self {
+            key,
+            value,
+            from,
+            to,
+            count,
+        }
+    }
+}
@@ -24,11 +24,13 @@ pub mod messages;
 pub mod partitions;
 pub mod personal_access_tokens;
 pub mod segments;
+pub mod shutdown;
 pub mod snapshot;
 pub mod stats;
 pub mod storage;
 pub mod streams;
 pub mod system;
+pub mod tenants;
 pub mod topics;
 pub mod users;
 
@@ -19,6 +19,7 @@
 use crate::streaming::session::Session;
 use crate::streaming::systems::COMPONENT;
 use crate::streaming::systems::system::System;
+use crate::streaming::tenants::tenant::TenantAccess;
 use error_set::ErrContext;
 use messenger_common::Identifier;
 use messenger_common::MessengerError;
@@ -32,6 +33,7 @@ impl System {
         partitions_count: u32,
     ) -> Result<(), MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         {
             let topic = self.find_topic(session, stream_id, topic_id).with_error_context(|error| format!("{COMPONENT} (error: {error}) - topic not found for stream ID: {stream_id}, topic_id: {topic_id}"))?;
             self.permissioner.create_partitions(
@@ -44,6 +46,7 @@ impl System {
                 topic.stream_id,
                 topic.topic_id
             ))?;
+            self.ensure_tenant_access(session, topic.stream_id, TenantAccess::Manage)?;
         }
 
         let topic = self
@@ -74,6 +77,7 @@ impl System {
         partitions_count: u32,
     ) -> Result<(), MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         {
             let topic = self.find_topic(session, stream_id, topic_id).with_error_context(|error| format!("{COMPONENT} (error: {error}) - topic not found for stream ID: {stream_id}, topic_id: {topic_id}"))?;
             self.permissioner.delete_partitions(
@@ -86,6 +90,7 @@ impl System {
                 topic.stream_id,
                 topic.topic_id
             ))?;
+            self.ensure_tenant_access(session, topic.stream_id, TenantAccess::Manage)?;
         }
 
         let topic = self
@@ -18,6 +18,7 @@
 use crate::streaming::session::Session;
 use crate::streaming::systems::COMPONENT;
 use crate::streaming::systems::system::System;
+use crate::streaming::tenants::tenant::TenantAccess;
 use error_set::ErrContext;
 use messenger_common::Identifier;
 use messenger_common::MessengerError;
@@ -34,6 +35,7 @@ impl System {
     ) -> Result<(), MessengerError> {
         // Assert authentication.
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
 
         {
             let topic = self.find_topic(session, stream_id, topic_id).with_error_context(|error| format!("{COMPONENT} (error: {error}) - topic not found for stream_id: {stream_id}, topic_id: {topic_id}"))?;
@@ -48,6 +50,7 @@ impl System {
                 topic.stream_id,
                 topic.topic_id
             ))?;
+            self.ensure_tenant_access(session, topic.stream_id, TenantAccess::Manage)?;
         }
 
         let topic = self
@@ -0,0 +1,169 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::configs::server::ShutdownConfig;
+use crate::streaming::systems::COMPONENT;
+use crate::streaming::systems::system::SharedSystem;
+use error_set::ErrContext;
+use messenger_common::locking::MessengerSharedMutFn;
+use messenger_common::{MessengerError, SEND_MESSAGES_CODE};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+use tokio::time::{Instant, sleep, timeout};
+use tracing::{error, info};
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ShutdownPhase {
+    Running = 0,
+    /// New messages are rejected; consumers may keep polling and storing offsets.
+    Draining = 1,
+    /// Every command is rejected and connections are closed after the reply.
+    Closing = 2,
+}
+
+/// Shutdown phase shared by the system and the connection handlers, readable without the system lock.
+#[derive(Debug, Default)]
+pub struct ShutdownState {
+    phase: AtomicU8,
+}
+
+impl ShutdownState {
+    pub fn phase(&self) -> ShutdownPhase {
+        match self.phase.load(Ordering::Acquire) {
+            0 => ShutdownPhase::Running,
+            1 => ShutdownPhase::Draining,
+            _ => ShutdownPhase::Closing,
+        }
+    }
+
+    fn advance(&self, phase: ShutdownPhase) {
+        self.phase.fetch_max(phase as u8, Ordering::AcqRel);
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.phase() == ShutdownPhase::Closing
+    }
+
+    /// Returns the error to reply with if a command with the given code must not run in the current phase.
+    pub fn reject_command(&self, code: u32) -> Option<MessengerError> {
+        match self.phase() {
+            ShutdownPhase::Running => None,
+            ShutdownPhase::Draining if code != SEND_MESSAGES_CODE => None,
+            ShutdownPhase::Draining | ShutdownPhase::Closing => {
+                Some(MessengerError::ServerShuttingDown)
+            }
+        }
+    }
+
+    pub fn ensure_accepting_messages(&self) -> Result<(), MessengerError> {
+        match self.phase() {
+            ShutdownPhase::Running => Ok(()),
+            _ => Err(MessengerError::ServerShuttingDown),
+        }
+    }
+}
+
+impl SharedSystem {
+    /// Stops the server in phases, bounded by the configured timeout.
+    ///
+    /// 1. Draining: produces are rejected with the retriable `ServerShuttingDown` error, so
+    ///    producers back off and reconnect, while consumers finish polling and store offsets.
+    ///    The phase ends after the drain period or once every client has disconnected.
+    /// 2. Closing: every command is rejected with the same error and the connection is closed.
+    ///    Connected clients are removed, which releases their consumer group memberships.
+    /// 3. Flush: once in-flight commands have finished, unsaved messages are persisted.
+    ///
+    /// The server runs as a single node, so there are no peers to hand partition leadership to;
+    /// consumer offsets are stored on every commit, and the flush is the checkpoint the server
+    /// resumes from on restart.
+    pub async fn shutdown_gracefully(&self, config: &ShutdownConfig) -> Result<(), MessengerError> {
+        match timeout(
+            config.timeout.get_duration(),
+            self.run_shutdown(config.drain_period.get_duration()),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => {
+                error!(
+                    "{COMPONENT} - graceful shutdown did not finish within {} (phase: {:?}), unsaved messages may be lost.",
+                    config.timeout,
+                    self.shutdown_state().phase()
+                );
+                Ok(())
+            }
+        }
+    }
+
+    async fn run_shutdown(&self, drain_period: Duration) -> Result<(), MessengerError> {
+        let state = self.shutdown_state();
+        state.advance(ShutdownPhase::Draining);
+        info!(
+            "Draining: new messages are rejected for up to {} ms...",
+            drain_period.as_millis()
+        );
+        let drain_started = Instant::now();
+        while drain_started.elapsed() < drain_period {
+            if self.connected_clients().await == 0 {
+                info!("All clients have disconnected, ending the drain early.");
+                break;
+            }
+            sleep(DRAIN_POLL_INTERVAL).await;
+        }
+
+        state.advance(ShutdownPhase::Closing);
+        let (clients, memberships) = self.release_clients().await;
+        info!(
+            "Closing: released {clients} clients and {memberships} consumer group memberships, groups rebalance when clients reconnect."
+        );
+
+        // The write lock is granted once every in-flight command has released its read lock.
+        let system = self.write().await;
+        let saved_messages = system.persist_messages().await.with_error_context(|error| {
+            format!("{COMPONENT} (error: {error}) - failed to persist messages during shutdown")
+        })?;
+        info!("Flushed {saved_messages} unsaved messages to disk.");
+        Ok(())
+    }
+
+    async fn connected_clients(&self) -> usize {
+        let system = self.read().await;
+        let client_manager = system.client_manager.read().await;
+        client_manager.get_clients().len()
+    }
+
+    async fn release_clients(&self) -> (usize, usize) {
+        let system = self.read().await;
+        let clients = system.client_manager.read().await.get_clients();
+        let mut client_ids = Vec::with_capacity(clients.len());
+        let mut memberships = 0;
+        for client in clients {
+            let client = client.read().await;
+            memberships += client.consumer_groups.len();
+            client_ids.push(client.session.client_id);
+        }
+
+        for client_id in &client_ids {
+            system.delete_client(*client_id).await;
+        }
+        (client_ids.len(), memberships)
+    }
+}
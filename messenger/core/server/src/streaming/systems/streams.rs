@@ -21,6 +21,7 @@ use crate::streaming::session::Session;
 use crate::streaming::streams::stream::Stream;
 use crate::streaming::systems::COMPONENT;
 use crate::streaming::systems::system::System;
+use crate::streaming::tenants::tenant::TenantAccess;
 use ahash::{AHashMap, AHashSet};
 use error_set::ErrContext;
 use futures::future::try_join_all;
@@ -159,6 +160,12 @@ impl System {
 
             self.streams_ids
                 .insert(stream.name.clone(), stream.stream_id);
+            self.tenants.register_stream(
+                stream.stream_id,
+                &stream.name,
+                stream.size_bytes.clone(),
+                stream.messages_count.clone(),
+            );
             self.streams.insert(stream.stream_id, stream);
         }
 
@@ -180,7 +187,15 @@ impl System {
                     session.get_user_id(),
                 )
             })?;
-        Ok(self.get_streams())
+        let user_id = session.get_user_id();
+        Ok(self
+            .get_streams()
+            .into_iter()
+            .filter(|stream| {
+                self.tenants
+                    .can_access_stream(user_id, stream.stream_id, TenantAccess::Read)
+            })
+            .collect())
     }
 
     pub fn find_stream(
@@ -189,6 +204,7 @@ impl System {
         identifier: &Identifier,
     ) -> Result<&Stream, MessengerError> {
         self.ensure_authenticated(session)?;
+        let identifier = &self.scope_stream_id(session, identifier)?;
         let stream = self.get_stream(identifier);
         if let Ok(stream) = stream {
             self.permissioner
@@ -199,6 +215,7 @@ impl System {
                         session.get_user_id(),
                     )
                 })?;
+            self.ensure_tenant_access(session, stream.stream_id, TenantAccess::Read)?;
             return Ok(stream);
         }
 
@@ -211,6 +228,7 @@ impl System {
         identifier: &Identifier,
     ) -> Result<Option<&Stream>, MessengerError> {
         self.ensure_authenticated(session)?;
+        let identifier = &self.scope_stream_id(session, identifier)?;
         let Some(stream) = self.try_get_stream(identifier)? else {
             return Ok(None);
         };
@@ -223,6 +241,7 @@ impl System {
                     session.get_user_id(),
                 )
             })?;
+        self.ensure_tenant_access(session, stream.stream_id, TenantAccess::Read)?;
         Ok(Some(stream))
     }
 
@@ -302,6 +321,13 @@ impl System {
     ) -> Result<&Stream, MessengerError> {
         self.ensure_authenticated(session)?;
         self.permissioner.create_stream(session.get_user_id())?;
+        self.tenants
+            .ensure_can_create_stream(session.get_user_id())?;
+        let name = &self
+            .tenants
+            .scope_stream_name(session.get_user_id(), name);
+        self.tenants
+            .ensure_stream_name_in_namespace(session.get_user_id(), name)?;
         if self.streams_ids.contains_key(name) {
             return Err(MessengerError::StreamNameAlreadyExists(name.to_owned()));
         }
@@ -331,6 +357,12 @@ impl System {
         stream.persist().await?;
         info!("Created stream with ID: {id}, name: '{name}'.");
         self.streams_ids.insert(name.to_owned(), stream.stream_id);
+        self.tenants.register_stream(
+            stream.stream_id,
+            name,
+            stream.size_bytes.clone(),
+            stream.messages_count.clone(),
+        );
         self.streams.insert(stream.stream_id, stream);
         self.metrics.increment_streams(1);
         self.get_stream_by_id(id)
@@ -343,6 +375,10 @@ impl System {
         name: &str,
     ) -> Result<(), MessengerError> {
         self.ensure_authenticated(session)?;
+        let id = &self.scope_stream_id(session, id)?;
+        let name = &self
+            .tenants
+            .scope_stream_name(session.get_user_id(), name);
         let stream_id;
         {
             let stream = self.get_stream(id).with_error_context(|error| {
@@ -360,6 +396,9 @@ impl System {
                     stream_id
                 )
             })?;
+        self.ensure_tenant_access(session, stream_id, TenantAccess::Manage)?;
+        self.tenants
+            .ensure_stream_name_in_namespace(session.get_user_id(), name)?;
 
         {
             if let Some(stream_id_by_name) = self.streams_ids.get(name)
@@ -370,6 +409,8 @@ impl System {
         }
 
         let old_name;
+        let size_bytes;
+        let messages_count;
         {
             let stream = self.get_stream_mut(id).with_error_context(|error| {
                 format!("{COMPONENT} (error: {error}) - failed to get mutable reference to stream with id: {id}")
@@ -377,11 +418,16 @@ impl System {
             old_name = stream.name.clone();
             stream.name = name.to_owned();
             stream.persist().await?;
+            stream.apply_header_indexing().await?;
+            size_bytes = stream.size_bytes.clone();
+            messages_count = stream.messages_count.clone();
         }
 
         {
             self.streams_ids.remove(&old_name);
             self.streams_ids.insert(name.to_owned(), stream_id);
+            self.tenants
+                .register_stream(stream_id, name, size_bytes, messages_count);
         }
 
         info!("Stream with ID '{id}' updated. Old name: '{old_name}' changed to: '{name}'.");
@@ -394,6 +440,7 @@ impl System {
         id: &Identifier,
     ) -> Result<u32, MessengerError> {
         self.ensure_authenticated(session)?;
+        let id = &self.scope_stream_id(session, id)?;
         let stream = self.get_stream(id).with_error_context(|error| {
             format!("{COMPONENT} (error: {error}) - failed to get stream with ID: {id}")
         })?;
@@ -407,12 +454,14 @@ impl System {
                     stream.stream_id,
                 )
             })?;
+        self.ensure_tenant_access(session, stream_id, TenantAccess::Manage)?;
         let stream_name = stream.name.clone();
         if stream.delete().await.is_err() {
             return Err(MessengerError::CannotDeleteStream(stream_id));
         }
 
         self.metrics.decrement_streams(1);
+        self.metrics.remove_stream(stream_id);
         self.metrics.decrement_topics(stream.get_topics_count());
         self.metrics
             .decrement_partitions(stream.get_partitions_count());
@@ -420,6 +469,7 @@ impl System {
         self.metrics.decrement_segments(stream.get_segments_count());
         self.streams.remove(&stream_id);
         self.streams_ids.remove(&stream_name);
+        self.tenants.unregister_stream(stream_id);
         let current_stream_id = CURRENT_STREAM_ID.load(Ordering::SeqCst);
         if current_stream_id > stream_id {
             CURRENT_STREAM_ID.store(stream_id, Ordering::SeqCst);
@@ -437,6 +487,7 @@ impl System {
         session: &Session,
         stream_id: &Identifier,
     ) -> Result<(), MessengerError> {
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         let stream = self.get_stream(stream_id).with_error_context(|error| {
             format!("{COMPONENT} (error: {error}) - failed to get stream with ID: {stream_id}")
         })?;
@@ -449,6 +500,7 @@ impl System {
                     stream.stream_id,
                 )
             })?;
+        self.ensure_tenant_access(session, stream.stream_id, TenantAccess::Manage)?;
         stream.purge().await
     }
 }
@@ -457,11 +509,12 @@ impl System {
 mod tests {
     use super::*;
     use crate::configs::server::{DataMaintenanceConfig, PersonalAccessTokenConfig};
-    use crate::configs::system::SystemConfig;
+    use crate::configs::system::{SystemConfig, TenancyConfig, TenantConfig};
     use crate::state::{MockState, StateKind};
     use crate::streaming::persistence::persister::{FileWithSyncPersister, PersisterKind};
     use crate::streaming::storage::SystemStorage;
     use crate::streaming::users::user::User;
+    use messenger_common::Permissions;
     use messenger_common::defaults::{DEFAULT_ROOT_PASSWORD, DEFAULT_ROOT_USERNAME};
     use std::{
         net::{Ipv4Addr, SocketAddr},
@@ -517,4 +570,79 @@ mod tests {
         assert_eq!(stream.stream_id, stream_id);
         assert_eq!(stream.name, stream_name);
     }
+
+    #[tokio::test]
+    async fn should_isolate_streams_between_tenants() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let config = Arc::new(SystemConfig {
+            path: tempdir.path().to_str().unwrap().to_string(),
+            tenancy: TenancyConfig {
+                enabled: true,
+                tenants: vec![
+                    TenantConfig {
+                        name: "production".to_string(),
+                        users: vec!["prod".to_string()],
+                        max_streams: None,
+                        max_topics: None,
+                        max_size: None,
+                        shares: vec![],
+                    },
+                    TenantConfig {
+                        name: "staging".to_string(),
+                        users: vec!["staging".to_string()],
+                        max_streams: None,
+                        max_topics: None,
+                        max_size: None,
+                        shares: vec![],
+                    },
+                ],
+            },
+            ..Default::default()
+        });
+        let storage = SystemStorage::new(
+            config.clone(),
+            Arc::new(PersisterKind::FileWithSync(FileWithSyncPersister {})),
+        );
+        let mut system = System::create(
+            config,
+            storage,
+            Arc::new(StateKind::Mock(MockState::new())),
+            None,
+            DataMaintenanceConfig::default(),
+            PersonalAccessTokenConfig::default(),
+        );
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 1234);
+        let mut sessions = Vec::new();
+        for (user_id, username) in [(2, "prod"), (3, "staging")] {
+            system
+                .permissioner
+                .init_permissions_for_user(user_id, Some(Permissions::root()));
+            system.tenants.bind_user(user_id, username);
+            sessions.push(Session::new(user_id, user_id, address));
+        }
+        let (production, staging) = (&sessions[0], &sessions[1]);
+
+        for session in [production, staging] {
+            system
+                .create_stream(session, None, "events")
+                .await
+                .unwrap();
+        }
+
+        let events = Identifier::named("events").unwrap();
+        let production_stream = system.find_stream(production, &events).unwrap();
+        assert_eq!(production_stream.name, "production::events");
+        let staging_stream = system.find_stream(staging, &events).unwrap();
+        assert_eq!(staging_stream.name, "staging::events");
+
+        let production_events = Identifier::named("production::events").unwrap();
+        assert!(system.find_stream(staging, &production_events).is_err());
+        assert_eq!(system.find_streams(staging).unwrap().len(), 1);
+        assert!(
+            system
+                .create_stream(staging, None, "production::orders")
+                .await
+                .is_err()
+        );
+    }
 }
@@ -30,6 +30,8 @@ use crate::streaming::session::Session;
 use crate::streaming::storage::SystemStorage;
 use crate::streaming::streams::stream::Stream;
 use crate::streaming::systems::COMPONENT;
+use crate::streaming::systems::shutdown::ShutdownState;
+use crate::streaming::tenants::registry::TenantRegistry;
 use crate::streaming::users::permissioner::Permissioner;
 use crate::streaming::users::user::User;
 use crate::versioning::SemanticVersion;
@@ -48,15 +50,22 @@ use tracing::{error, info, instrument, trace};
 #[derive(Debug)]
 pub struct SharedSystem {
     system: Arc<RwLock<System>>,
+    shutdown: Arc<ShutdownState>,
 }
 
 impl SharedSystem {
     pub fn new(system: System) -> SharedSystem {
         SharedSystem {
+            shutdown: system.shutdown.clone(),
             system: Arc::new(RwLock::new(system)),
         }
     }
 
+    /// Available without the system lock, which is held exclusively while shutting down.
+    pub fn shutdown_state(&self) -> &ShutdownState {
+        &self.shutdown
+    }
+
     pub async fn read(&self) -> RwLockReadGuard<'_, System> {
         self.system.read().await
     }
@@ -70,6 +79,7 @@ impl Clone for SharedSystem {
     fn clone(&self) -> Self {
         SharedSystem {
             system: self.system.clone(),
+            shutdown: self.shutdown.clone(),
         }
     }
 }
@@ -77,6 +87,7 @@ impl Clone for SharedSystem {
 #[derive(Debug)]
 pub struct System {
     pub permissioner: Permissioner,
+    pub(crate) tenants: TenantRegistry,
     pub(crate) storage: Arc<SystemStorage>,
     pub(crate) streams: AHashMap<u32, Stream>,
     pub(crate) streams_ids: AHashMap<String, u32>,
@@ -87,6 +98,7 @@ pub struct System {
     pub(crate) metrics: Metrics,
     pub(crate) state: Arc<StateKind>,
     pub(crate) archiver: Option<Arc<ArchiverKind>>,
+    pub(crate) shutdown: Arc<ShutdownState>,
     pub personal_access_token: PersonalAccessTokenConfig,
 }
 
@@ -168,6 +180,13 @@ impl System {
             None
         };
 
+        info!(
+            "Multi-tenancy is {}.",
+            map_toggle_str(system_config.tenancy.enabled)
+        );
+        let tenants = TenantRegistry::from_config(&system_config.tenancy);
+        let metrics = Metrics::init(&system_config.topic_metrics);
+
         System {
             config: system_config,
             streams: AHashMap::new(),
@@ -176,11 +195,13 @@ impl System {
             encryptor,
             client_manager: MessengerSharedMut::new(ClientManager::default()),
             permissioner: Permissioner::default(),
-            metrics: Metrics::init(),
+            tenants,
+            metrics,
             users: AHashMap::new(),
             state,
             personal_access_token: pat_config,
             archiver,
+            shutdown: Arc::new(ShutdownState::default()),
         }
     }
 
@@ -0,0 +1,89 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::streaming::session::Session;
+use crate::streaming::systems::COMPONENT;
+use crate::streaming::systems::system::System;
+use crate::streaming::tenants::tenant::{TenantAccess, TenantStats};
+use error_set::ErrContext;
+use messenger_common::{Identifier, MessengerError};
+
+impl System {
+    pub fn get_tenants_stats(&self, session: &Session) -> Result<Vec<TenantStats>, MessengerError> {
+        self.ensure_authenticated(session)?;
+        self.permissioner
+            .get_stats(session.get_user_id())
+            .with_error_context(|error| {
+                format!(
+                    "{COMPONENT} (error: {error}) - permission denied to get tenants stats for user {}",
+                    session.get_user_id(),
+                )
+            })?;
+        Ok(self.tenants.get_stats())
+    }
+
+    /// Resolves a stream name within the session user's tenant namespace.
+    pub(crate) fn scope_stream_id(
+        &self,
+        session: &Session,
+        stream_id: &Identifier,
+    ) -> Result<Identifier, MessengerError> {
+        self.tenants
+            .scope_stream_identifier(session.get_user_id(), stream_id)
+    }
+
+    pub(crate) fn ensure_tenant_access(
+        &self,
+        session: &Session,
+        stream_id: u32,
+        access: TenantAccess,
+    ) -> Result<(), MessengerError> {
+        self.tenants
+            .ensure_stream_access(session.get_user_id(), stream_id, access)
+            .with_error_context(|error| {
+                format!(
+                    "{COMPONENT} (error: {error}) - tenant access ({access:?}) denied for user {} on stream ID: {stream_id}",
+                    session.get_user_id(),
+                )
+            })
+    }
+
+    pub(crate) fn ensure_tenant_can_create_topic(&self, stream_id: u32) -> Result<(), MessengerError> {
+        let Some(tenant) = self.tenants.get_stream_tenant(stream_id) else {
+            return Ok(());
+        };
+
+        let Some(max_topics) = tenant.max_topics else {
+            return Ok(());
+        };
+
+        let topics_count: u32 = tenant
+            .get_stream_ids()
+            .filter_map(|id| self.streams.get(&id))
+            .map(|stream| stream.get_topics_count())
+            .sum();
+        if topics_count >= max_topics {
+            return Err(MessengerError::TenantTopicsLimitReached(
+                tenant.name.clone(),
+                max_topics,
+            ));
+        }
+
+        Ok(())
+    }
+}
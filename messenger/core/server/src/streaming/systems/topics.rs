@@ -19,6 +19,7 @@
 use crate::streaming::session::Session;
 use crate::streaming::systems::COMPONENT;
 use crate::streaming::systems::system::System;
+use crate::streaming::tenants::tenant::TenantAccess;
 use crate::streaming::topics::topic::Topic;
 use error_set::ErrContext;
 use messenger_common::locking::MessengerSharedMutFn;
@@ -59,6 +60,7 @@ impl System {
         stream_id: &Identifier,
     ) -> Result<Vec<&Topic>, MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         let stream = self.get_stream(stream_id).with_error_context(|error| {
             format!("{COMPONENT} (error: {error}) - failed to get stream with ID: {stream_id}")
         })?;
@@ -70,6 +72,7 @@ impl System {
                     session.get_user_id(),
                 )
             })?;
+        self.ensure_tenant_access(session, stream.stream_id, TenantAccess::Read)?;
         Ok(stream.get_topics())
     }
 
@@ -118,6 +121,7 @@ impl System {
         replication_factor: Option<u8>,
     ) -> Result<&Topic, MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         {
             let stream = self.get_stream(stream_id).with_error_context(|error| {
                 format!("{COMPONENT} (error: {error}) - failed to get stream with ID: {stream_id}")
@@ -130,6 +134,8 @@ impl System {
                         session.get_user_id(),
                     )
                 })?;
+            self.ensure_tenant_access(session, stream.stream_id, TenantAccess::Manage)?;
+            self.ensure_tenant_can_create_topic(stream.stream_id)?;
         }
 
         let created_topic_id = self
@@ -177,6 +183,7 @@ impl System {
         replication_factor: Option<u8>,
     ) -> Result<&Topic, MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         let topic_numeric_id;
         {
             let topic = self
@@ -199,6 +206,7 @@ impl System {
                     topic.topic_id,
                 )
             })?;
+            self.ensure_tenant_access(session, topic.stream_id, TenantAccess::Manage)?;
         }
 
         self.get_stream_mut(stream_id)?
@@ -237,6 +245,7 @@ impl System {
         topic_id: &Identifier,
     ) -> Result<(), MessengerError> {
         self.ensure_authenticated(session)?;
+        let stream_id = &self.scope_stream_id(session, stream_id)?;
         let stream_id_value;
         {
             let topic = self
@@ -254,6 +263,7 @@ impl System {
                     session.get_user_id(),
                 )
             })?;
+            self.ensure_tenant_access(session, topic.stream_id, TenantAccess::Manage)?;
             stream_id_value = topic.stream_id;
         }
 
@@ -264,6 +274,7 @@ impl System {
             .with_error_context(|error| format!("{COMPONENT} (error: {error}) - failed to delete topic with ID: {topic_id} in stream with ID: {stream_id}"))?;
 
         self.metrics.decrement_topics(1);
+        self.metrics.remove_topic(stream_id_value, topic.topic_id);
         self.metrics
             .decrement_partitions(topic.get_partitions_count());
         self.metrics.decrement_messages(topic.get_messages_count());
@@ -295,6 +306,7 @@ impl System {
                     session.get_user_id(),
                 )
             })?;
+        self.ensure_tenant_access(session, topic.stream_id, TenantAccess::Manage)?;
         topic.purge().await.with_error_context(|error| {
             format!("{COMPONENT} (error: {error}) - failed to purge topic with ID: {topic_id} in stream with ID: {stream_id}")
         })
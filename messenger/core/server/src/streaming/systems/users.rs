@@ -104,6 +104,9 @@ impl System {
         USER_ID.store(current_user_id + 1, Ordering::SeqCst);
         self.permissioner
             .init(&self.users.values().collect::<Vec<&User>>());
+        for user in self.users.values() {
+            self.tenants.bind_user(user.id, &user.username);
+        }
         self.metrics.increment_users(users_count as u32);
         info!("Initialized {users_count} user(s).");
         Ok(())
@@ -248,6 +251,7 @@ impl System {
         let user = User::new(user_id, username, password, status, permissions.clone());
         self.permissioner
             .init_permissions_for_user(user_id, permissions);
+        self.tenants.bind_user(user_id, username);
         self.users.insert(user.id, user);
         info!("Created user: {username} with ID: {user_id}.");
         self.metrics.increment_users(1);
@@ -293,6 +297,7 @@ impl System {
             .ok_or(MessengerError::ResourceNotFound(user_id.to_string()))?;
         self.permissioner
             .delete_permissions_for_user(existing_user_id);
+        self.tenants.unbind_user(existing_user_id);
         let mut client_manager = self.client_manager.write().await;
         client_manager
             .delete_clients_for_user(existing_user_id)
@@ -345,7 +350,10 @@ impl System {
         }
 
         info!("Updated user: {} with ID: {}.", user.username, user.id);
-        Ok(user)
+        let updated_user_id = user.id;
+        let updated_username = user.username.clone();
+        self.tenants.bind_user(updated_user_id, &updated_username);
+        self.get_user(&updated_user_id.try_into()?)
     }
 
     pub async fn update_permissions(
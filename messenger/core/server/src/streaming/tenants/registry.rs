@@ -0,0 +1,465 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::configs::system::TenancyConfig;
+use crate::streaming::tenants::TENANT_NAMESPACE_SEPARATOR;
+use crate::streaming::tenants::tenant::{SharedAccess, Tenant, TenantAccess, TenantStats, TenantStream};
+use ahash::AHashMap;
+use messenger_common::defaults::DEFAULT_ROOT_USER_ID;
+use messenger_common::{IdKind, Identifier, MessengerError, UserId};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Tracks which tenant owns each user and stream.
+///
+/// Streams belong to a tenant when their name starts with the tenant namespace
+/// (`<tenant>::<stream>`), so ownership survives restarts without extra state.
+/// Cross-tenant access is denied unless the owning tenant shares the stream.
+#[derive(Debug, Default)]
+pub struct TenantRegistry {
+    enabled: bool,
+    tenants: AHashMap<String, Tenant>,
+    usernames_tenants: AHashMap<String, String>,
+    users_tenants: AHashMap<UserId, String>,
+    streams_tenants: AHashMap<u32, String>,
+    streams_names: AHashMap<u32, String>,
+    shares: AHashMap<(String, String), SharedAccess>,
+}
+
+impl TenantRegistry {
+    pub fn from_config(config: &TenancyConfig) -> Self {
+        let mut registry = TenantRegistry {
+            enabled: config.enabled,
+            ..Default::default()
+        };
+        if !config.enabled {
+            return registry;
+        }
+
+        for tenant in &config.tenants {
+            for username in &tenant.users {
+                registry
+                    .usernames_tenants
+                    .insert(username.clone(), tenant.name.clone());
+            }
+
+            for share in &tenant.shares {
+                let stream_name = Self::namespaced_name(&tenant.name, &share.stream);
+                registry.shares.insert(
+                    (stream_name, share.tenant.clone()),
+                    SharedAccess {
+                        send_messages: share.send_messages,
+                    },
+                );
+            }
+
+            registry.tenants.insert(
+                tenant.name.clone(),
+                Tenant::new(
+                    &tenant.name,
+                    tenant.max_streams,
+                    tenant.max_topics,
+                    tenant.max_size,
+                ),
+            );
+        }
+
+        registry
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn namespaced_name(tenant: &str, name: &str) -> String {
+        format!("{tenant}{TENANT_NAMESPACE_SEPARATOR}{name}")
+    }
+
+    pub fn bind_user(&mut self, user_id: UserId, username: &str) {
+        match self.usernames_tenants.get(username) {
+            Some(tenant) => {
+                self.users_tenants.insert(user_id, tenant.clone());
+            }
+            None => {
+                self.users_tenants.remove(&user_id);
+            }
+        }
+    }
+
+    pub fn unbind_user(&mut self, user_id: UserId) {
+        self.users_tenants.remove(&user_id);
+    }
+
+    pub fn get_user_tenant(&self, user_id: UserId) -> Option<&Tenant> {
+        self.users_tenants
+            .get(&user_id)
+            .and_then(|tenant| self.tenants.get(tenant))
+    }
+
+    pub fn get_stream_tenant(&self, stream_id: u32) -> Option<&Tenant> {
+        self.streams_tenants
+            .get(&stream_id)
+            .and_then(|tenant| self.tenants.get(tenant))
+    }
+
+    pub fn register_stream(
+        &mut self,
+        stream_id: u32,
+        name: &str,
+        size_bytes: Arc<AtomicU64>,
+        messages_count: Arc<AtomicU64>,
+    ) {
+        self.unregister_stream(stream_id);
+        self.streams_names.insert(stream_id, name.to_string());
+        let Some((namespace, _)) = name.split_once(TENANT_NAMESPACE_SEPARATOR) else {
+            return;
+        };
+
+        let Some(tenant) = self.tenants.get_mut(namespace) else {
+            return;
+        };
+
+        tenant.streams.insert(
+            stream_id,
+            TenantStream {
+                size_bytes,
+                messages_count,
+            },
+        );
+        self.streams_tenants.insert(stream_id, namespace.to_string());
+    }
+
+    pub fn unregister_stream(&mut self, stream_id: u32) {
+        self.streams_names.remove(&stream_id);
+        if let Some(tenant) = self.streams_tenants.remove(&stream_id)
+            && let Some(tenant) = self.tenants.get_mut(&tenant)
+        {
+            tenant.streams.remove(&stream_id);
+        }
+    }
+
+    /// Places a stream name into the user's tenant namespace.
+    ///
+    /// Names that already carry a namespace are left as they are, so a user
+    /// can address a stream shared by another tenant by its full name.
+    pub fn scope_stream_name(&self, user_id: UserId, name: &str) -> String {
+        if !self.enabled || name.contains(TENANT_NAMESPACE_SEPARATOR) {
+            return name.to_string();
+        }
+
+        match self.users_tenants.get(&user_id) {
+            Some(tenant) => Self::namespaced_name(tenant, name),
+            None => name.to_string(),
+        }
+    }
+
+    pub fn scope_stream_identifier(
+        &self,
+        user_id: UserId,
+        identifier: &Identifier,
+    ) -> Result<Identifier, MessengerError> {
+        if !self.enabled || identifier.kind != IdKind::String {
+            return Ok(identifier.clone());
+        }
+
+        let name = identifier.get_cow_str_value()?;
+        Identifier::named(&self.scope_stream_name(user_id, &name))
+    }
+
+    pub fn can_access_stream(&self, user_id: UserId, stream_id: u32, access: TenantAccess) -> bool {
+        if !self.enabled || user_id == DEFAULT_ROOT_USER_ID {
+            return true;
+        }
+
+        let user_tenant = self.users_tenants.get(&user_id);
+        let stream_tenant = self.streams_tenants.get(&stream_id);
+        if user_tenant == stream_tenant {
+            return true;
+        }
+
+        let (Some(user_tenant), Some(stream_name)) =
+            (user_tenant, self.streams_names.get(&stream_id))
+        else {
+            return false;
+        };
+
+        let Some(share) = self
+            .shares
+            .get(&(stream_name.clone(), user_tenant.clone()))
+        else {
+            return false;
+        };
+
+        match access {
+            TenantAccess::Read => true,
+            TenantAccess::Write => share.send_messages,
+            TenantAccess::Manage => false,
+        }
+    }
+
+    pub fn ensure_stream_access(
+        &self,
+        user_id: UserId,
+        stream_id: u32,
+        access: TenantAccess,
+    ) -> Result<(), MessengerError> {
+        if self.can_access_stream(user_id, stream_id, access) {
+            return Ok(());
+        }
+
+        if let Some(tenant) = self.get_user_tenant(user_id) {
+            tenant.denied_requests.fetch_add(1, Ordering::Relaxed);
+        }
+        Err(MessengerError::Unauthorized)
+    }
+
+    /// Ensures a (scoped) stream name lies in the user's own namespace, so that
+    /// streams cannot be created in or renamed into another tenant.
+    pub fn ensure_stream_name_in_namespace(
+        &self,
+        user_id: UserId,
+        name: &str,
+    ) -> Result<(), MessengerError> {
+        if !self.enabled || user_id == DEFAULT_ROOT_USER_ID {
+            return Ok(());
+        }
+
+        let namespace = name
+            .split_once(TENANT_NAMESPACE_SEPARATOR)
+            .map(|(namespace, _)| namespace)
+            .filter(|namespace| self.tenants.contains_key(*namespace));
+        if namespace == self.users_tenants.get(&user_id).map(|tenant| tenant.as_str()) {
+            return Ok(());
+        }
+
+        if let Some(tenant) = self.get_user_tenant(user_id) {
+            tenant.denied_requests.fetch_add(1, Ordering::Relaxed);
+        }
+        Err(MessengerError::Unauthorized)
+    }
+
+    pub fn ensure_can_create_stream(&self, user_id: UserId) -> Result<(), MessengerError> {
+        let Some(tenant) = self.get_user_tenant(user_id) else {
+            return Ok(());
+        };
+
+        if let Some(max_streams) = tenant.max_streams
+            && tenant.get_streams_count() >= max_streams
+        {
+            return Err(MessengerError::TenantStreamsLimitReached(
+                tenant.name.clone(),
+                max_streams,
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn ensure_within_size_quota(&self, stream_id: u32) -> Result<(), MessengerError> {
+        let Some(tenant) = self.get_stream_tenant(stream_id) else {
+            return Ok(());
+        };
+
+        if let Some(max_size) = tenant.max_size
+            && tenant.get_size_bytes() >= max_size.as_bytes_u64()
+        {
+            return Err(MessengerError::TenantSizeLimitReached(
+                tenant.name.clone(),
+                max_size,
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn record_messages_sent(&self, user_id: UserId, count: u64) {
+        if let Some(tenant) = self.get_user_tenant(user_id) {
+            tenant.messages_sent.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_messages_polled(&self, user_id: UserId, count: u64) {
+        if let Some(tenant) = self.get_user_tenant(user_id) {
+            tenant.messages_polled.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get_stats(&self) -> Vec<TenantStats> {
+        let mut stats = self
+            .tenants
+            .values()
+            .map(|tenant| tenant.get_stats())
+            .collect::<Vec<_>>();
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::system::{TenantConfig, TenantShareConfig};
+    use messenger_common::MessengerByteSize;
+
+    const PRODUCTION_USER: UserId = 2;
+    const STAGING_USER: UserId = 3;
+    const UNBOUND_USER: UserId = 4;
+
+    fn registry() -> TenantRegistry {
+        let config = TenancyConfig {
+            enabled: true,
+            tenants: vec![
+                TenantConfig {
+                    name: "production".to_string(),
+                    users: vec!["pixelle-prod".to_string()],
+                    max_streams: Some(2),
+                    max_topics: None,
+                    max_size: Some(MessengerByteSize::from(100_u64)),
+                    shares: vec![TenantShareConfig {
+                        stream: "events".to_string(),
+                        tenant: "staging".to_string(),
+                        send_messages: false,
+                    }],
+                },
+                TenantConfig {
+                    name: "staging".to_string(),
+                    users: vec!["pixelle-staging".to_string()],
+                    max_streams: None,
+                    max_topics: None,
+                    max_size: None,
+                    shares: vec![],
+                },
+            ],
+        };
+        let mut registry = TenantRegistry::from_config(&config);
+        registry.bind_user(PRODUCTION_USER, "pixelle-prod");
+        registry.bind_user(STAGING_USER, "pixelle-staging");
+        registry.bind_user(UNBOUND_USER, "someone");
+        registry
+    }
+
+    fn register(registry: &mut TenantRegistry, stream_id: u32, name: &str, size: u64) {
+        registry.register_stream(
+            stream_id,
+            name,
+            Arc::new(AtomicU64::new(size)),
+            Arc::new(AtomicU64::new(0)),
+        );
+    }
+
+    #[test]
+    fn should_scope_stream_names_to_user_tenant() {
+        let registry = registry();
+        assert_eq!(
+            registry.scope_stream_name(PRODUCTION_USER, "events"),
+            "production::events"
+        );
+        assert_eq!(
+            registry.scope_stream_name(STAGING_USER, "production::events"),
+            "production::events"
+        );
+        assert_eq!(registry.scope_stream_name(UNBOUND_USER, "events"), "events");
+    }
+
+    #[test]
+    fn should_reject_stream_names_outside_user_namespace() {
+        let registry = registry();
+        assert!(
+            registry
+                .ensure_stream_name_in_namespace(PRODUCTION_USER, "production::events")
+                .is_ok()
+        );
+        assert!(
+            registry
+                .ensure_stream_name_in_namespace(STAGING_USER, "production::events")
+                .is_err()
+        );
+        assert!(
+            registry
+                .ensure_stream_name_in_namespace(UNBOUND_USER, "production::events")
+                .is_err()
+        );
+        assert!(
+            registry
+                .ensure_stream_name_in_namespace(UNBOUND_USER, "events")
+                .is_ok()
+        );
+        assert!(
+            registry
+                .ensure_stream_name_in_namespace(DEFAULT_ROOT_USER_ID, "staging::events")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn should_deny_cross_tenant_access_by_default() {
+        let mut registry = registry();
+        register(&mut registry, 1, "production::orders", 0);
+        register(&mut registry, 2, "staging::orders", 0);
+
+        assert!(registry.can_access_stream(PRODUCTION_USER, 1, TenantAccess::Manage));
+        assert!(!registry.can_access_stream(PRODUCTION_USER, 2, TenantAccess::Read));
+        assert!(!registry.can_access_stream(STAGING_USER, 1, TenantAccess::Read));
+        assert!(!registry.can_access_stream(UNBOUND_USER, 1, TenantAccess::Read));
+        assert!(registry.can_access_stream(DEFAULT_ROOT_USER_ID, 2, TenantAccess::Manage));
+
+        assert!(registry.ensure_stream_access(STAGING_USER, 1, TenantAccess::Read).is_err());
+        let staging = registry
+            .get_stats()
+            .into_iter()
+            .find(|stats| stats.name == "staging")
+            .unwrap();
+        assert_eq!(staging.denied_requests, 1);
+    }
+
+    #[test]
+    fn should_allow_access_to_shared_streams_only_as_granted() {
+        let mut registry = registry();
+        register(&mut registry, 1, "production::events", 0);
+
+        assert!(registry.can_access_stream(STAGING_USER, 1, TenantAccess::Read));
+        assert!(!registry.can_access_stream(STAGING_USER, 1, TenantAccess::Write));
+        assert!(!registry.can_access_stream(STAGING_USER, 1, TenantAccess::Manage));
+    }
+
+    #[test]
+    fn should_enforce_stream_and_size_quotas() {
+        let mut registry = registry();
+        register(&mut registry, 1, "production::a", 60);
+        assert!(registry.ensure_can_create_stream(PRODUCTION_USER).is_ok());
+        assert!(registry.ensure_within_size_quota(1).is_ok());
+
+        register(&mut registry, 2, "production::b", 40);
+        assert!(registry.ensure_can_create_stream(PRODUCTION_USER).is_err());
+        assert!(registry.ensure_within_size_quota(1).is_err());
+
+        registry.unregister_stream(2);
+        assert!(registry.ensure_can_create_stream(PRODUCTION_USER).is_ok());
+        assert!(registry.ensure_can_create_stream(STAGING_USER).is_ok());
+    }
+
+    #[test]
+    fn should_allow_everything_when_disabled() {
+        let mut registry = TenantRegistry::from_config(&TenancyConfig::default());
+        registry.bind_user(PRODUCTION_USER, "pixelle-prod");
+        register(&mut registry, 1, "production::orders", 0);
+
+        assert!(registry.can_access_stream(STAGING_USER, 1, TenantAccess::Manage));
+        assert_eq!(registry.scope_stream_name(PRODUCTION_USER, "orders"), "orders");
+    }
+}
@@ -0,0 +1,124 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use ahash::AHashMap;
+use messenger_common::MessengerByteSize;
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenantAccess {
+    Read,
+    Write,
+    Manage,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharedAccess {
+    pub send_messages: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct TenantStream {
+    pub size_bytes: Arc<AtomicU64>,
+    pub messages_count: Arc<AtomicU64>,
+}
+
+#[derive(Debug)]
+pub struct Tenant {
+    pub name: String,
+    pub max_streams: Option<u32>,
+    pub max_topics: Option<u32>,
+    pub max_size: Option<MessengerByteSize>,
+    pub(crate) streams: AHashMap<u32, TenantStream>,
+    pub(crate) messages_sent: AtomicU64,
+    pub(crate) messages_polled: AtomicU64,
+    pub(crate) denied_requests: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TenantStats {
+    pub name: String,
+    pub streams_count: u32,
+    pub size_bytes: u64,
+    pub messages_count: u64,
+    pub messages_sent: u64,
+    pub messages_polled: u64,
+    pub denied_requests: u64,
+    pub max_streams: Option<u32>,
+    pub max_topics: Option<u32>,
+    pub max_size_bytes: Option<u64>,
+}
+
+impl Tenant {
+    pub fn new(
+        name: &str,
+        max_streams: Option<u32>,
+        max_topics: Option<u32>,
+        max_size: Option<MessengerByteSize>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            max_streams,
+            max_topics,
+            max_size,
+            streams: AHashMap::new(),
+            messages_sent: AtomicU64::new(0),
+            messages_polled: AtomicU64::new(0),
+            denied_requests: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get_stream_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.streams.keys().copied()
+    }
+
+    pub fn get_streams_count(&self) -> u32 {
+        self.streams.len() as u32
+    }
+
+    pub fn get_size_bytes(&self) -> u64 {
+        self.streams
+            .values()
+            .map(|stream| stream.size_bytes.load(Ordering::SeqCst))
+            .sum()
+    }
+
+    pub fn get_messages_count(&self) -> u64 {
+        self.streams
+            .values()
+            .map(|stream| stream.messages_count.load(Ordering::SeqCst))
+            .sum()
+    }
+
+    pub fn get_stats(&self) -> TenantStats {
+        TenantStats {
+            name: self.name.clone(),
+            streams_count: self.get_streams_count(),
+            size_bytes: self.get_size_bytes(),
+            messages_count: self.get_messages_count(),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            messages_polled: self.messages_polled.load(Ordering::Relaxed),
+            denied_requests: self.denied_requests.load(Ordering::Relaxed),
+            max_streams: self.max_streams,
+            max_topics: self.max_topics,
+            max_size_bytes: self.max_size.map(|size| size.as_bytes_u64()),
+        }
+    }
+}
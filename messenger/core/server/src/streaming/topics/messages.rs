@@ -25,10 +25,10 @@ use crate::streaming::utils::hash;
 use ahash::AHashMap;
 use error_set::ErrContext;
 use messenger_common::locking::MessengerSharedMutFn;
-use messenger_common::{Confirmation, MessengerTimestamp, PollingStrategy};
-use messenger_common::{MessengerError, MessengerExpiry, Partitioning, PartitioningKind, PollingKind};
+use messenger_common::{Confirmation, HeaderKey, MessengerTimestamp, PollingStrategy};
+use messenger_common::{MessengerError, Partitioning, PartitioningKind, PollingKind};
 use std::sync::atomic::Ordering;
-use tracing::trace;
+use tracing::{info, trace};
 
 impl Topic {
     pub fn get_messages_count(&self) -> u64 {
@@ -57,31 +57,126 @@ impl Topic {
 
         let partition = partition.unwrap();
         let partition = partition.read().await;
-        let value = strategy.value;
-        let messages = match strategy.kind {
-            PollingKind::Offset => partition.get_messages_by_offset(value, count).await,
-            PollingKind::Timestamp => {
-                partition
-                    .get_messages_by_timestamp(value.into(), count)
-                    .await
-                    .with_error_context(|error| format!("{COMPONENT} (error: {error}) - failed to get messages by timestamp: {value}, count: {count}"))
-                                }
-            PollingKind::First => partition.get_first_messages(count).await,
-            PollingKind::Last => partition.get_last_messages(count).await,
-            PollingKind::Next => partition.get_next_messages(consumer, count).await,
-        }?;
-
-        let metadata = MessengerPollMetadata::new(partition_id, partition.current_offset);
+        let now = MessengerTimestamp::now().as_micros();
+        let mut metadata = MessengerPollMetadata::new(partition_id, partition.current_offset);
+        let mut strategy = strategy;
+        let messages = loop {
+            let value = strategy.value;
+            let mut messages = match strategy.kind {
+                PollingKind::Offset => partition.get_messages_by_offset(value, count).await,
+                PollingKind::Timestamp => {
+                    partition
+                        .get_messages_by_timestamp(value.into(), count)
+                        .await
+                        .with_error_context(|error| format!("{COMPONENT} (error: {error}) - failed to get messages by timestamp: {value}, count: {count}"))
+                }
+                PollingKind::First => partition.get_first_messages(count).await,
+                PollingKind::Last => partition.get_last_messages(count).await,
+                PollingKind::Next => partition.get_next_messages(consumer, count).await,
+            }?;
+
+            let Some(last_offset) = messages.last_offset() else {
+                break messages;
+            };
+            metadata.last_polled_offset = Some(last_offset);
+            metadata.expired_messages_count += messages.remove_expired(now);
+
+            // When everything polled has expired, keep reading forward rather than
+            // returning nothing for the consumer to poll again.
+            if !messages.is_empty()
+                || strategy.kind == PollingKind::Last
+                || last_offset >= partition.current_offset
+            {
+                break messages;
+            }
+            strategy = PollingStrategy::offset(last_offset + 1);
+        };
 
         Ok((metadata, messages))
     }
 
+    /// Retrieves messages with the given value of an indexed header from the given partition,
+    /// or from all of them, in partition order (up to a specified count in total).
+    #[allow(clippy::too_many_arguments)]
+    pub async fn get_messages_by_header(
+        &self,
+        partition_id: Option<u32>,
+        key: &HeaderKey,
+        value: &str,
+        from: MessengerTimestamp,
+        to: MessengerTimestamp,
+        count: u32,
+    ) -> Result<Vec<(MessengerPollMetadata, MessengerMessagesBatchSet)>, MessengerError> {
+        if !self.indexed_headers.contains(key) {
+            return Err(MessengerError::HeaderNotIndexed(
+                key.to_string(),
+                self.topic_id,
+                self.stream_id,
+            ));
+        }
+
+        let mut partition_ids = match partition_id {
+            Some(partition_id) => vec![partition_id],
+            None => self.partitions.keys().copied().collect(),
+        };
+        partition_ids.sort_unstable();
+
+        let now = MessengerTimestamp::now().as_micros();
+        let mut remaining_count = count;
+        let mut results = Vec::new();
+        for partition_id in partition_ids {
+            if remaining_count == 0 {
+                break;
+            }
+
+            let partition = self.get_partition(partition_id)?;
+            let partition = partition.read().await;
+            let mut messages = partition
+                .get_messages_by_header(key, value, from, to, remaining_count)
+                .await
+                .with_error_context(|error| format!("{COMPONENT} (error: {error}) - failed to get messages by header: {key}, partition ID: {partition_id}"))?;
+            let mut metadata = MessengerPollMetadata::new(partition_id, partition.current_offset);
+            metadata.last_polled_offset = messages.last_offset();
+            metadata.expired_messages_count = messages.remove_expired(now);
+            if messages.is_empty() && metadata.expired_messages_count == 0 {
+                continue;
+            }
+
+            remaining_count = remaining_count.saturating_sub(messages.count());
+            results.push((metadata, messages));
+        }
+
+        Ok(results)
+    }
+
+    /// Replaces the set of indexed headers for all the partitions of this topic.
+    pub async fn enable_header_indexing(&mut self, headers: Vec<HeaderKey>) -> Result<(), MessengerError> {
+        if headers == self.indexed_headers {
+            return Ok(());
+        }
+
+        for partition in self.partitions.values() {
+            let mut partition = partition.write().await;
+            partition
+                .enable_header_index(headers.clone())
+                .await
+                .with_error_context(|error| format!("{COMPONENT} (error: {error}) - failed to enable header index, partition ID: {}", partition.partition_id))?;
+        }
+
+        if !headers.is_empty() {
+            info!("Indexing headers: {headers:?} for topic with ID: {} and stream with ID: {}.", self.topic_id, self.stream_id);
+        }
+        self.indexed_headers = headers;
+        Ok(())
+    }
+
+    /// Returns the ID of the partition the messages were appended to, or `None` if there were no messages.
     pub async fn append_messages(
         &self,
         partitioning: &Partitioning,
         messages: MessengerMessagesBatchMut,
         confirmation: Option<Confirmation>,
-    ) -> Result<(), MessengerError> {
+    ) -> Result<Option<u32>, MessengerError> {
         if !self.has_partitions() {
             return Err(MessengerError::NoPartitions(self.topic_id, self.stream_id));
         }
@@ -93,7 +188,7 @@ impl Topic {
         }
 
         if messages.is_empty() {
-            return Ok(());
+            return Ok(None);
         }
 
         let partition_id = match partitioning.kind {
@@ -109,7 +204,8 @@ impl Topic {
         };
 
         self.append_messages_to_partition(messages, partition_id, confirmation)
-            .await
+            .await?;
+        Ok(Some(partition_id))
     }
 
     pub async fn flush_unsaved_buffer(
@@ -185,13 +281,11 @@ impl Topic {
         now: MessengerTimestamp,
     ) -> AHashMap<u32, Vec<u64>> {
         let mut expired_segments = AHashMap::new();
-        if let MessengerExpiry::ExpireDuration(_) = self.message_expiry {
-            for (_, partition) in self.partitions.iter() {
-                let partition = partition.read().await;
-                let segments = partition.get_expired_segments_start_offsets(now).await;
-                if !segments.is_empty() {
-                    expired_segments.insert(partition.partition_id, segments);
-                }
+        for (_, partition) in self.partitions.iter() {
+            let partition = partition.read().await;
+            let segments = partition.get_expired_segments_start_offsets(now).await;
+            if !segments.is_empty() {
+                expired_segments.insert(partition.partition_id, segments);
             }
         }
         expired_segments
@@ -208,10 +302,14 @@ mod tests {
     use crate::streaming::utils::MemoryPool;
     use bytes::Bytes;
     use messenger_common::CompressionAlgorithm;
+    use messenger_common::MessengerExpiry;
+    use messenger_common::message_ttl_header;
     use messenger_common::{MessengerMessage, MaxTopicSize};
+    use std::collections::HashMap;
     use std::sync::Arc;
     use std::sync::atomic::AtomicU32;
     use std::sync::atomic::AtomicU64;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn given_partition_id_key_messages_should_be_appended_only_to_the_chosen_partition() {
@@ -319,6 +417,45 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn given_messages_with_passed_ttl_polling_should_skip_them() {
+        let partitioning = Partitioning::partition_id(1);
+        let topic = init_topic(1).await;
+        let ttl_headers = HashMap::from([message_ttl_header(Duration::from_millis(1)).unwrap()]);
+        for entity_id in 1..=3u32 {
+            let message = MessengerMessage::builder()
+                .id(entity_id as u128)
+                .payload(Bytes::from(entity_id.to_string()))
+                .maybe_user_headers((entity_id != 2).then(|| ttl_headers.clone()))
+                .build()
+                .unwrap();
+            let messages = MessengerMessagesBatchMut::from_messages(&[message], 1);
+            topic
+                .append_messages(&partitioning, messages, None)
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let consumer = PollingConsumer::Consumer(1, 1);
+        let (metadata, messages) = topic
+            .get_messages(consumer, 1, PollingStrategy::offset(0), 10)
+            .await
+            .unwrap();
+        assert_eq!(messages.count(), 1);
+        assert_eq!(metadata.expired_messages_count, 2);
+        assert_eq!(metadata.last_polled_offset, Some(2));
+
+        let (metadata, messages) = topic
+            .get_messages(consumer, 1, PollingStrategy::offset(0), 1)
+            .await
+            .unwrap();
+        assert_eq!(messages.count(), 1);
+        assert_eq!(messages.first_offset(), Some(1));
+        assert_eq!(metadata.expired_messages_count, 1);
+        assert_eq!(metadata.last_polled_offset, Some(1));
+    }
+
     async fn init_topic(partitions_count: u32) -> Topic {
         let tempdir = tempfile::TempDir::new().unwrap();
         let config = Arc::new(SystemConfig {
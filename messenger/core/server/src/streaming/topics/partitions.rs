@@ -46,7 +46,7 @@ impl Topic {
 
         let mut partition_ids = Vec::with_capacity(count as usize);
         for partition_id in current_partitions_count + 1..=current_partitions_count + count {
-            let partition = Partition::create(
+            let mut partition = Partition::create(
                 self.stream_id,
                 self.topic_id,
                 partition_id,
@@ -62,6 +62,9 @@ impl Topic {
                 MessengerTimestamp::now(),
             )
             .await;
+            partition
+                .enable_header_index(self.indexed_headers.clone())
+                .await?;
             self.partitions
                 .insert(partition_id, MessengerSharedMut::new(partition));
             partition_ids.push(partition_id)
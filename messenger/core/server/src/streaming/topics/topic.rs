@@ -25,8 +25,8 @@ use ahash::AHashMap;
 use core::fmt;
 use messenger_common::locking::MessengerSharedMut;
 use messenger_common::{
-    CompressionAlgorithm, Consumer, ConsumerKind, MessengerByteSize, MessengerError, MessengerExpiry,
-    MessengerTimestamp, MaxTopicSize, Sizeable,
+    CompressionAlgorithm, Consumer, ConsumerKind, HeaderKey, MessengerByteSize, MessengerError,
+    MessengerExpiry, MessengerTimestamp, MaxTopicSize, Sizeable,
 };
 
 use std::sync::Arc;
@@ -55,6 +55,7 @@ pub struct Topic {
     pub(crate) consumer_groups_ids: AHashMap<String, u32>,
     pub(crate) current_consumer_group_id: AtomicU32,
     pub(crate) current_partition_id: AtomicU32,
+    pub(crate) indexed_headers: Vec<HeaderKey>,
     pub message_expiry: MessengerExpiry,
     pub compression_algorithm: CompressionAlgorithm,
     pub max_topic_size: MaxTopicSize,
@@ -128,6 +129,7 @@ impl Topic {
             consumer_groups_ids: AHashMap::new(),
             current_consumer_group_id: AtomicU32::new(1),
             current_partition_id: AtomicU32::new(1),
+            indexed_headers: Vec::new(),
             message_expiry: Topic::get_message_expiry(message_expiry, &config),
             max_topic_size: Topic::get_max_topic_size(max_topic_size, &config)?,
             compression_algorithm,
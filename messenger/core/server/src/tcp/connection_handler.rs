@@ -62,6 +62,15 @@ pub(crate) async fn handle_connection(
         debug!("Received a TCP request, length: {length}, code: {code}");
         let command = ServerCommand::from_code_and_reader(code, sender, length - 4).await?;
         debug!("Received a TCP command: {command}, payload size: {length}");
+        let shutdown = system.shutdown_state();
+        if let Some(error) = shutdown.reject_command(code) {
+            sender.send_error_response(error).await?;
+            if shutdown.is_closing() {
+                debug!("Server is shutting down, closing the connection for: {session}.");
+                return Err(ConnectionError::from(MessengerError::ServerShuttingDown));
+            }
+            continue;
+        }
         match command.handle(sender, length, &session, &system).await {
             Ok(_) => {
                 debug!(
@@ -109,6 +118,9 @@ pub(crate) fn handle_error(error: ConnectionError) {
             MessengerError::ConnectionClosed => {
                 debug!("Client closed connection.");
             }
+            MessengerError::ServerShuttingDown => {
+                info!("Connection has been closed by the server shutdown.");
+            }
             _ => {
                 error!("Failure in internal SDK call: {sdk_error}");
             }
@@ -0,0 +1,136 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::websocket::COMPONENT;
+use error_set::ErrContext;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use messenger_common::MessengerError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+/// Claims of a Pixelle access token that the WebSocket transport relies on.
+#[derive(Debug, Deserialize)]
+pub struct PixelleClaims {
+    /// Pixelle user ID.
+    pub sub: String,
+    /// Expiry in seconds since the Unix epoch.
+    pub exp: u64,
+    /// Set on client-credentials tokens, which act for a service rather than a user.
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+/// Verifies the short-lived ES256 access tokens issued by pixelle-auth against its published keys.
+///
+/// Keys are fetched from the JWKS on start and on every refresh interval, so rotated keys are
+/// picked up before tokens signed with them arrive.
+pub struct PixelleTokenValidator {
+    jwks_url: String,
+    client: reqwest::Client,
+    keys: RwLock<HashMap<String, DecodingKey>>,
+}
+
+impl PixelleTokenValidator {
+    pub fn new(jwks_url: &str) -> Self {
+        Self {
+            jwks_url: jwks_url.to_string(),
+            client: reqwest::Client::new(),
+            keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Replaces the known keys with the current JWKS, returning how many were loaded.
+    pub async fn refresh(&self) -> Result<usize, MessengerError> {
+        let response = self
+            .client
+            .get(&self.jwks_url)
+            .send()
+            .await
+            .with_error_context(|error| {
+                format!("{COMPONENT} (error: {error}) - failed to fetch JWKS from {}", self.jwks_url)
+            })
+            .map_err(|_| MessengerError::InvalidHttpRequest)?;
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(MessengerError::HttpResponseError(status, body));
+        }
+
+        let jwks = response
+            .json::<JwkSet>()
+            .await
+            .with_error_context(|error| {
+                format!("{COMPONENT} (error: {error}) - invalid JWKS from {}", self.jwks_url)
+            })
+            .map_err(|_| MessengerError::InvalidJsonResponse)?;
+        let mut keys = HashMap::new();
+        for jwk in &jwks.keys {
+            let Some(kid) = jwk.common.key_id.clone() else {
+                continue;
+            };
+            match DecodingKey::from_jwk(jwk) {
+                Ok(key) => {
+                    keys.insert(kid, key);
+                }
+                Err(error) => warn!("{COMPONENT} - skipping JWK: {kid}, error: {error}"),
+            }
+        }
+
+        let count = keys.len();
+        *self.keys.write().unwrap() = keys;
+        debug!("{COMPONENT} - loaded {count} signing keys from {}", self.jwks_url);
+        Ok(count)
+    }
+
+    pub fn start_refresher(self: Arc<Self>, interval: Duration) {
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(interval);
+            loop {
+                interval_timer.tick().await;
+                if let Err(error) = self.refresh().await {
+                    error!("{COMPONENT} - failed to refresh Pixelle signing keys. Error: {error}");
+                }
+            }
+        });
+    }
+
+    /// Checks the signature and expiry of a user's access token.
+    pub fn validate(&self, token: &str) -> Result<PixelleClaims, MessengerError> {
+        let header = jsonwebtoken::decode_header(token).map_err(|_| MessengerError::InvalidAccessToken)?;
+        let kid = header.kid.ok_or(MessengerError::InvalidAccessToken)?;
+        let keys = self.keys.read().unwrap();
+        let key = keys.get(&kid).ok_or(MessengerError::InvalidAccessToken)?;
+
+        let mut validation = Validation::new(Algorithm::ES256);
+        validation.set_required_spec_claims(&["exp", "sub"]);
+        let claims = jsonwebtoken::decode::<PixelleClaims>(token, key, &validation)
+            .with_error_context(|error| {
+                format!("{COMPONENT} (error: {error}) - rejected access token with key: {kid}")
+            })
+            .map_err(|_| MessengerError::InvalidAccessToken)?
+            .claims;
+        if claims.client_id.is_some() {
+            return Err(MessengerError::InvalidAccessToken);
+        }
+        Ok(claims)
+    }
+}
@@ -0,0 +1,438 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::streaming::clients::client_manager::Transport;
+use crate::streaming::session::Session;
+use crate::streaming::systems::messages::PollingArgs;
+use crate::streaming::systems::system::SharedSystem;
+use crate::websocket::COMPONENT;
+use crate::websocket::WebSocketState;
+use crate::websocket::protocol::{
+    ClientFrame, ServerFrame, StartFrom, Subscribe, SubscriptionConsumer,
+};
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, StreamExt};
+use messenger_common::{
+    Consumer, Identifier, MessengerError, MessengerTimestamp, PollingStrategy,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, sleep, sleep_until, timeout};
+use tracing::{debug, error, info, warn};
+
+/// Frames queued for the socket; a slow browser makes its subscriptions wait here instead of
+/// buffering messages in the server.
+const OUTGOING_FRAMES_CAPACITY: usize = 32;
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+type Frames = mpsc::Sender<ServerFrame>;
+
+struct Subscription {
+    stream_id: Identifier,
+    topic_id: Identifier,
+    group_id: Option<Identifier>,
+    task: JoinHandle<()>,
+}
+
+/// What a subscription task polls, resolved from a `subscribe` frame.
+struct SubscriptionSpec {
+    name: String,
+    consumer: Consumer,
+    stream_id: Identifier,
+    topic_id: Identifier,
+    partition_id: Option<u32>,
+    start: StartFrom,
+    count: u32,
+}
+
+pub(crate) async fn handle_connection(
+    socket: WebSocket,
+    address: SocketAddr,
+    state: Arc<WebSocketState>,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let (frames, mut outgoing) = mpsc::channel::<ServerFrame>(OUTGOING_FRAMES_CAPACITY);
+    let writer = tokio::spawn(async move {
+        while let Some(frame) = outgoing.recv().await {
+            let text = match serde_json::to_string(&frame) {
+                Ok(text) => text,
+                Err(error) => {
+                    error!("{COMPONENT} - failed to serialize frame: {error}");
+                    continue;
+                }
+            };
+            if sink.send(Message::Text(text.into())).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let auth_timeout = state.config.auth_timeout.get_duration();
+    let authenticated = match timeout(auth_timeout, first_token(&mut stream)).await {
+        Ok(Some(token)) => authenticate(&state, &token, &frames).await,
+        Ok(None) => Err(MessengerError::Unauthenticated),
+        Err(_) => {
+            debug!("{COMPONENT} - connection from {address} sent no access token in time.");
+            Err(MessengerError::AccessTokenMissing)
+        }
+    };
+    let expires_at = match authenticated {
+        Ok(expires_at) => expires_at,
+        Err(error) => {
+            let _ = frames.send(ServerFrame::error(None, &error)).await;
+            drop(frames);
+            let _ = writer.await;
+            return;
+        }
+    };
+
+    let session = state
+        .system
+        .read()
+        .await
+        .add_client(&address, Transport::WebSocket)
+        .await;
+    let login = state
+        .system
+        .read()
+        .await
+        .login_user_with_credentials(&state.config.username, None, Some(&session))
+        .await
+        .map(|_| ());
+    if let Err(error) = login {
+        error!(
+            "{COMPONENT} - cannot act as user: {} for {session}. {error}",
+            state.config.username
+        );
+        let _ = frames.send(ServerFrame::error(None, &error)).await;
+    } else {
+        info!("{COMPONENT} - authenticated connection with session: {session}");
+        let mut connection = Connection {
+            state: state.clone(),
+            session: session.clone(),
+            frames: frames.clone(),
+            subscriptions: HashMap::new(),
+            expires_at,
+        };
+        connection.run(&mut stream).await;
+        connection.close();
+    }
+
+    state.system.read().await.delete_client(session.client_id).await;
+    drop(frames);
+    let _ = writer.await;
+    info!("{COMPONENT} - closed connection with session: {session}");
+}
+
+/// Waits for the `authenticate` frame; any other frame before it ends the connection.
+async fn first_token(stream: &mut futures::stream::SplitStream<WebSocket>) -> Option<String> {
+    while let Some(Ok(message)) = stream.next().await {
+        match message {
+            Message::Text(text) => match serde_json::from_str::<ClientFrame>(text.as_str()) {
+                Ok(ClientFrame::Authenticate { token }) => return Some(token),
+                _ => return None,
+            },
+            Message::Close(_) => return None,
+            _ => continue,
+        }
+    }
+    None
+}
+
+/// Validates a token and replies with `authenticated`, returning when the token expires.
+async fn authenticate(
+    state: &WebSocketState,
+    token: &str,
+    frames: &Frames,
+) -> Result<Instant, MessengerError> {
+    let claims = state.validator.validate(token)?;
+    let now = MessengerTimestamp::now().to_secs();
+    let remaining = Duration::from_secs(claims.exp.saturating_sub(now));
+    let _ = frames
+        .send(ServerFrame::Authenticated {
+            user_id: claims.sub,
+            expires_at: claims.exp,
+        })
+        .await;
+    Ok(Instant::now() + remaining)
+}
+
+struct Connection {
+    state: Arc<WebSocketState>,
+    session: Arc<Session>,
+    frames: Frames,
+    subscriptions: HashMap<String, Subscription>,
+    expires_at: Instant,
+}
+
+impl Connection {
+    async fn run(&mut self, stream: &mut futures::stream::SplitStream<WebSocket>) {
+        let mut shutdown_check = tokio::time::interval(SHUTDOWN_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                message = stream.next() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle_frame(text.as_str()).await,
+                    Some(Ok(Message::Binary(_))) => {
+                        self.send_error(None, &MessengerError::InvalidFormat).await;
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+                _ = sleep_until(self.expires_at) => {
+                    debug!("{COMPONENT} - access token expired for session: {}", self.session);
+                    self.send_error(None, &MessengerError::InvalidAccessToken).await;
+                    return;
+                }
+                _ = shutdown_check.tick() => {
+                    if self.state.system.shutdown_state().is_closing() {
+                        self.send_error(None, &MessengerError::ServerShuttingDown).await;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle_frame(&mut self, text: &str) {
+        let frame = match serde_json::from_str::<ClientFrame>(text) {
+            Ok(frame) => frame,
+            Err(error) => {
+                debug!("{COMPONENT} - invalid frame from session: {}. {error}", self.session);
+                self.send_error(None, &MessengerError::InvalidCommand).await;
+                return;
+            }
+        };
+
+        match frame {
+            ClientFrame::Authenticate { token } => {
+                match authenticate(&self.state, &token, &self.frames).await {
+                    Ok(expires_at) => self.expires_at = expires_at,
+                    Err(error) => self.send_error(None, &error).await,
+                }
+            }
+            ClientFrame::Subscribe(subscribe) => {
+                let name = subscribe.subscription.clone();
+                match self.subscribe(subscribe).await {
+                    Ok(()) => {
+                        let _ = self
+                            .frames
+                            .send(ServerFrame::Subscribed { subscription: name })
+                            .await;
+                    }
+                    Err(error) => self.send_error(Some(&name), &error).await,
+                }
+            }
+            ClientFrame::Unsubscribe { subscription } => {
+                match self.unsubscribe(&subscription).await {
+                    Ok(()) => {
+                        let _ = self
+                            .frames
+                            .send(ServerFrame::Unsubscribed { subscription })
+                            .await;
+                    }
+                    Err(error) => self.send_error(Some(&subscription), &error).await,
+                }
+            }
+            ClientFrame::Ping => {
+                let _ = self.frames.send(ServerFrame::Pong).await;
+            }
+        }
+    }
+
+    async fn subscribe(&mut self, subscribe: Subscribe) -> Result<(), MessengerError> {
+        // A subscription whose task stopped on an error has already been reported and can be reused.
+        self.subscriptions.retain(|_, subscription| !subscription.task.is_finished());
+        if self.subscriptions.contains_key(&subscribe.subscription) {
+            return Err(MessengerError::SubscriptionAlreadyExists(
+                subscribe.subscription,
+            ));
+        }
+        let max_subscriptions = self.state.config.max_subscriptions;
+        if self.subscriptions.len() >= max_subscriptions as usize {
+            return Err(MessengerError::SubscriptionsLimitReached(max_subscriptions));
+        }
+        if subscribe.batch_size == 0 {
+            return Err(MessengerError::InvalidMessagesCount);
+        }
+
+        let stream_id = Identifier::from_str_value(&subscribe.stream)?;
+        let topic_id = Identifier::from_str_value(&subscribe.topic)?;
+        let (consumer, group_id) = match &subscribe.consumer {
+            SubscriptionConsumer::Consumer { id } => {
+                (Consumer::new(Identifier::from_str_value(id)?), None)
+            }
+            SubscriptionConsumer::ConsumerGroup { id } => {
+                if subscribe.start != StartFrom::Next {
+                    return Err(MessengerError::InvalidCommand);
+                }
+                let group_id = Identifier::from_str_value(id)?;
+                (Consumer::group(group_id.clone()), Some(group_id))
+            }
+        };
+
+        if let Some(group_id) = &group_id {
+            self.state
+                .system
+                .read()
+                .await
+                .join_consumer_group(&self.session, &stream_id, &topic_id, group_id)
+                .await?;
+        }
+
+        let spec = SubscriptionSpec {
+            name: subscribe.subscription.clone(),
+            consumer,
+            stream_id: stream_id.clone(),
+            topic_id: topic_id.clone(),
+            partition_id: match group_id {
+                Some(_) => None,
+                None => subscribe.partition_id,
+            },
+            start: subscribe.start,
+            count: subscribe.batch_size.min(self.state.config.max_batch_size),
+        };
+        let task = tokio::spawn(push_messages(
+            self.state.system.clone(),
+            self.session.clone(),
+            self.frames.clone(),
+            spec,
+            self.state.config.poll_interval.get_duration(),
+        ));
+        self.subscriptions.insert(
+            subscribe.subscription,
+            Subscription {
+                stream_id,
+                topic_id,
+                group_id,
+                task,
+            },
+        );
+        Ok(())
+    }
+
+    async fn unsubscribe(&mut self, name: &str) -> Result<(), MessengerError> {
+        let subscription = self
+            .subscriptions
+            .remove(name)
+            .ok_or_else(|| MessengerError::SubscriptionNotFound(name.to_string()))?;
+        subscription.task.abort();
+
+        let Some(group_id) = &subscription.group_id else {
+            return Ok(());
+        };
+        let still_used = self.subscriptions.values().any(|other| {
+            other.group_id.as_ref() == Some(group_id)
+                && other.stream_id == subscription.stream_id
+                && other.topic_id == subscription.topic_id
+        });
+        if !still_used {
+            self.state
+                .system
+                .read()
+                .await
+                .leave_consumer_group(
+                    &self.session,
+                    &subscription.stream_id,
+                    &subscription.topic_id,
+                    group_id,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Stops every subscription; group memberships are released when the client is deleted.
+    fn close(&mut self) {
+        for (_, subscription) in self.subscriptions.drain() {
+            subscription.task.abort();
+        }
+    }
+
+    async fn send_error(&self, subscription: Option<&str>, error: &MessengerError) {
+        let _ = self
+            .frames
+            .send(ServerFrame::error(subscription, error))
+            .await;
+    }
+}
+
+/// Polls a topic for one subscription and pushes what it finds, until the subscription is removed,
+/// the connection closes or a poll fails.
+async fn push_messages(
+    system: SharedSystem,
+    session: Arc<Session>,
+    frames: Frames,
+    spec: SubscriptionSpec,
+    poll_interval: Duration,
+) {
+    let (mut strategy, auto_commit) = match spec.start {
+        StartFrom::Next => (PollingStrategy::next(), true),
+        StartFrom::First => (PollingStrategy::first(), false),
+        StartFrom::Last => (PollingStrategy::last(), false),
+        StartFrom::Offset(offset) => (PollingStrategy::offset(offset), false),
+        StartFrom::Timestamp(timestamp) => (PollingStrategy::timestamp(timestamp.into()), false),
+    };
+
+    loop {
+        let polled = {
+            let system = system.read().await;
+            system
+                .poll_messages(
+                    &session,
+                    &spec.consumer,
+                    &spec.stream_id,
+                    &spec.topic_id,
+                    spec.partition_id,
+                    PollingArgs::new(strategy, spec.count, auto_commit),
+                )
+                .await
+                .map(|(metadata, messages)| messages.into_polled_messages(metadata))
+        };
+
+        match polled {
+            Ok(polled) if polled.messages.is_empty() => sleep(poll_interval).await,
+            Ok(polled) => {
+                if !auto_commit && let Some(last) = polled.messages.last() {
+                    strategy = PollingStrategy::offset(last.header.offset + 1);
+                }
+                if frames
+                    .send(ServerFrame::messages(&spec.name, polled))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            Err(error) => {
+                warn!(
+                    "{COMPONENT} - subscription: {} for session: {session} failed. {error}",
+                    spec.name
+                );
+                let _ = frames
+                    .send(ServerFrame::error(Some(&spec.name), &error))
+                    .await;
+                return;
+            }
+        }
+    }
+}
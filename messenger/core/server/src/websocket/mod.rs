@@ -0,0 +1,73 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+pub mod auth;
+mod connection;
+pub mod protocol;
+
+use crate::configs::http::HttpWebSocketConfig;
+use crate::streaming::systems::system::SharedSystem;
+use crate::websocket::auth::PixelleTokenValidator;
+use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
+use axum::response::Response;
+use axum::routing::get;
+use axum::{Json, Router};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+pub const COMPONENT: &str = "WEBSOCKET";
+
+pub struct WebSocketState {
+    pub config: HttpWebSocketConfig,
+    pub system: SharedSystem,
+    pub validator: Arc<PixelleTokenValidator>,
+}
+
+/// Routes for the WebSocket consumer transport and the JSON Schema of its frames.
+///
+/// Both authenticate on their own rather than with the HTTP API's JWT: connections send a
+/// Pixelle access token as their first frame, and the schema is public.
+pub fn router(config: HttpWebSocketConfig, system: SharedSystem) -> Router {
+    let validator = Arc::new(PixelleTokenValidator::new(&config.jwks_url));
+    validator
+        .clone()
+        .start_refresher(config.jwks_refresh_interval.get_duration());
+
+    let path = config.path.clone();
+    let state = Arc::new(WebSocketState {
+        config,
+        system,
+        validator,
+    });
+    Router::new()
+        .route(&path, get(upgrade))
+        .route(&format!("{path}/schema"), get(schema))
+        .with_state(state)
+}
+
+async fn upgrade(
+    State(state): State<Arc<WebSocketState>>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| connection::handle_connection(socket, address, state))
+}
+
+async fn schema() -> Json<schemars::Schema> {
+    Json(protocol::json_schema())
+}
@@ -0,0 +1,292 @@
+/* Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use messenger_common::{HeaderKind, MessengerError, MessengerMessage, PolledMessages};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A frame sent by the browser, as one JSON text message.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientFrame {
+    /// Must be the first frame; send it again with a fresh token before the current one expires.
+    Authenticate { token: String },
+    Subscribe(Subscribe),
+    Unsubscribe { subscription: String },
+    Ping,
+}
+
+/// Starts pushing the messages of a topic to the connection.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct Subscribe {
+    /// Chosen by the client and echoed on every frame about this subscription.
+    pub subscription: String,
+    /// Stream ID or name.
+    pub stream: String,
+    /// Topic ID or name.
+    pub topic: String,
+    pub consumer: SubscriptionConsumer,
+    /// Partition to read, 1 by default; consumer groups are assigned partitions by the server.
+    #[serde(default)]
+    pub partition_id: Option<u32>,
+    #[serde(default)]
+    pub start: StartFrom,
+    /// Most messages pushed in one `messages` frame; capped by the server.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: u32,
+}
+
+/// Consumer whose offsets a subscription reads and commits.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SubscriptionConsumer {
+    /// A standalone consumer, ID or name.
+    Consumer { id: String },
+    /// An existing consumer group, ID or name; the connection joins it while subscribed.
+    ConsumerGroup { id: String },
+}
+
+/// Where a subscription starts reading.
+///
+/// `next` resumes from the consumer's stored offset and commits offsets as messages are pushed,
+/// so delivery is at most once. The other positions read one partition without committing,
+/// so reconnecting clients replay from where they choose; consumer groups only support `next`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StartFrom {
+    #[default]
+    Next,
+    First,
+    Last,
+    Offset(u64),
+    /// Microseconds since the Unix epoch.
+    Timestamp(u64),
+}
+
+/// A frame pushed by the server, as one JSON text message.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerFrame {
+    /// The token was accepted; the connection is closed once it expires unless a fresh one is sent.
+    Authenticated {
+        /// Pixelle user ID from the token.
+        user_id: String,
+        /// Seconds since the Unix epoch.
+        expires_at: u64,
+    },
+    Subscribed { subscription: String },
+    Unsubscribed { subscription: String },
+    Messages(PushedMessages),
+    /// A failed frame or subscription; subscriptions that fail are removed.
+    Error(ErrorFrame),
+    Pong,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PushedMessages {
+    pub subscription: String,
+    pub partition_id: u32,
+    /// Offset of the last message in the partition when the messages were read.
+    pub current_offset: u64,
+    pub messages: Vec<PushedMessage>,
+}
+
+#[derive(Debug, PartialEq, Serialize, JsonSchema)]
+pub struct PushedMessage {
+    pub offset: u64,
+    /// 128-bit message ID as a decimal string, since it does not fit a JavaScript number.
+    pub id: String,
+    /// Microseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub origin_timestamp: u64,
+    /// Base64 of the payload.
+    pub payload: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, PushedHeader>,
+}
+
+#[derive(Debug, PartialEq, Serialize, JsonSchema)]
+pub struct PushedHeader {
+    /// Header kind such as `string`, `bool` or `uint64`.
+    pub kind: String,
+    /// The value as text; base64 for `raw` headers.
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ErrorFrame {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription: Option<String>,
+    /// Numeric error code, the same as in the binary protocol.
+    pub id: u32,
+    pub code: String,
+    pub reason: String,
+}
+
+/// Both directions of the protocol, described for code generators such as
+/// `json-schema-to-typescript`.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct WebSocketProtocol {
+    client: ClientFrame,
+    server: ServerFrame,
+}
+
+/// JSON Schema of every frame, generated from the types above.
+pub fn json_schema() -> schemars::Schema {
+    schemars::schema_for!(WebSocketProtocol)
+}
+
+fn default_batch_size() -> u32 {
+    100
+}
+
+impl ServerFrame {
+    pub fn error(subscription: Option<&str>, error: &MessengerError) -> Self {
+        ServerFrame::Error(ErrorFrame {
+            subscription: subscription.map(ToOwned::to_owned),
+            id: error.as_code(),
+            code: error.as_string().to_string(),
+            reason: error.to_string(),
+        })
+    }
+
+    pub fn messages(subscription: &str, polled: PolledMessages) -> Self {
+        ServerFrame::Messages(PushedMessages {
+            subscription: subscription.to_string(),
+            partition_id: polled.partition_id,
+            current_offset: polled.current_offset,
+            messages: polled.messages.iter().map(PushedMessage::from).collect(),
+        })
+    }
+}
+
+impl From<&MessengerMessage> for PushedMessage {
+    fn from(message: &MessengerMessage) -> Self {
+        let headers = message
+            .user_headers_map()
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| {
+                let text = match value.kind {
+                    HeaderKind::Raw => STANDARD.encode(&value.value),
+                    _ => value.value_only_to_string(),
+                };
+                (
+                    key.to_string(),
+                    PushedHeader {
+                        kind: value.kind.to_string(),
+                        value: text,
+                    },
+                )
+            })
+            .collect();
+        PushedMessage {
+            offset: message.header.offset,
+            id: message.header.id.to_string(),
+            timestamp: message.header.timestamp,
+            origin_timestamp: message.header.origin_timestamp,
+            payload: STANDARD.encode(&message.payload),
+            headers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use messenger_common::{HeaderKey, HeaderValue};
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_parse_subscribe_with_defaults() {
+        let frame: ClientFrame = serde_json::from_str(
+            r#"{"type":"subscribe","subscription":"chat","stream":"app","topic":"rooms","consumer":{"kind":"consumer_group","id":"web"}}"#,
+        )
+        .unwrap();
+
+        let ClientFrame::Subscribe(subscribe) = frame else {
+            panic!("Expected a subscribe frame");
+        };
+        assert_eq!(subscribe.subscription, "chat");
+        assert!(matches!(
+            subscribe.consumer,
+            SubscriptionConsumer::ConsumerGroup { ref id } if id == "web"
+        ));
+        assert_eq!(subscribe.start, StartFrom::Next);
+        assert_eq!(subscribe.partition_id, None);
+        assert_eq!(subscribe.batch_size, 100);
+    }
+
+    #[test]
+    fn should_parse_start_positions() {
+        let start: StartFrom = serde_json::from_str(r#"{"offset":42}"#).unwrap();
+        assert_eq!(start, StartFrom::Offset(42));
+        let start: StartFrom = serde_json::from_str(r#""last""#).unwrap();
+        assert_eq!(start, StartFrom::Last);
+    }
+
+    #[test]
+    fn should_reject_unknown_frame_type() {
+        assert!(serde_json::from_str::<ClientFrame>(r#"{"type":"produce"}"#).is_err());
+    }
+
+    #[test]
+    fn should_map_message_with_string_id_and_base64_payload() {
+        let mut user_headers = HashMap::new();
+        user_headers.insert(
+            HeaderKey::new("room").unwrap(),
+            HeaderValue::from_str("lobby").unwrap(),
+        );
+        let mut message = MessengerMessage::builder()
+            .id(u128::MAX)
+            .payload(Bytes::from_static(b"hello"))
+            .user_headers(user_headers)
+            .build()
+            .unwrap();
+        message.header.offset = 7;
+
+        let pushed = PushedMessage::from(&message);
+
+        assert_eq!(pushed.offset, 7);
+        assert_eq!(pushed.id, u128::MAX.to_string());
+        assert_eq!(pushed.payload, "aGVsbG8=");
+        assert_eq!(
+            pushed.headers.get("room"),
+            Some(&PushedHeader {
+                kind: "string".to_string(),
+                value: "lobby".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_describe_both_directions_in_schema() {
+        let schema = serde_json::to_value(json_schema()).unwrap();
+        let definitions = schema["$defs"].as_object().unwrap();
+        assert!(definitions.contains_key("ClientFrame"));
+        assert!(definitions.contains_key("ServerFrame"));
+    }
+}